@@ -0,0 +1,148 @@
+#ifndef LUCENE_H
+#define LUCENE_H
+
+/* Generated by cbindgen; do not edit. Update with LUCENE_UPDATE_HEADER=1 cargo test. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * The outcome of a call.
+ */
+typedef enum LuceneStatus {
+  /**
+   * The call succeeded.
+   */
+  LUCENE_STATUS_OK = 0,
+  /**
+   * A required pointer argument was null.
+   */
+  LUCENE_STATUS_NULL_ARGUMENT = 1,
+  /**
+   * An argument was invalid: a string wasn't UTF-8, JSON was malformed, or a document or query was rejected.
+   */
+  LUCENE_STATUS_INVALID_ARGUMENT = 2,
+  /**
+   * The call failed for another reason.
+   */
+  LUCENE_STATUS_ERROR = 3,
+  /**
+   * The library panicked. The objects passed to the call should no longer be used.
+   */
+  LUCENE_STATUS_PANIC = 4,
+} LuceneStatus;
+
+/**
+ * An index of JSON documents, opened on a directory.
+ *
+ * Documents are JSON objects converted with lucene-core's `Document::from_json`: their values are strings (analyzed
+ * text), integers, or arrays of these. They're added to an [IndexWriter], which holds the directory's write lock until
+ * the index is closed, and become visible to searches once committed.
+ *
+ * lucene-core can't write segments to disk yet, so the writer's segments are held in memory. Changes are made durable
+ * by a [Translog] in the same directory instead: each addition and deletion is logged, a commit syncs the log, and
+ * opening the index replays it. Changes that aren't committed before the index is closed are discarded.
+ *
+ * An index may be searched from several threads at once, but must not be modified while it is in use by another
+ * thread.
+ */
+typedef struct LuceneIndex LuceneIndex;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Returns a description of the last error on the calling thread, or null if no call has failed yet.
+ *
+ * The string is owned by the library, and remains valid until the next failing call on the same thread.
+ */
+const char *lucene_last_error(void);
+
+/**
+ * Opens the index in the directory at `path`, creating the directory if it doesn't exist, and replays the changes
+ * committed before it was last closed. Returns null on failure, including when another writer holds the directory's
+ * write lock. Close the index with [lucene_index_close].
+ *
+ * # Safety
+ * `path` must be a nul-terminated string.
+ */
+struct LuceneIndex *lucene_index_open(const char *path);
+
+/**
+ * Closes an index, releasing its write lock and freeing its memory. Changes that weren't committed are discarded. The
+ * index is freed even if releasing the lock fails. Passing null does nothing.
+ *
+ * # Safety
+ * `index` must be null or have been returned by [lucene_index_open], and must not be used afterwards.
+ */
+enum LuceneStatus lucene_index_close(struct LuceneIndex *index);
+
+/**
+ * Adds the document in `json`, a JSON object. The document is visible once committed.
+ *
+ * # Safety
+ * `index` must be a valid index, and `json` a nul-terminated string.
+ */
+enum LuceneStatus lucene_index_add(struct LuceneIndex *index, const char *json);
+
+/**
+ * Deletes the documents whose `field` contains the exact term `value`, including documents added since the last
+ * commit. If `deleted` isn't null, it's set to the number of documents deleted. The deletion is visible once
+ * committed.
+ *
+ * # Safety
+ * `index` must be a valid index, `field` and `value` nul-terminated strings, and `deleted` null or valid for writes.
+ */
+enum LuceneStatus lucene_index_delete(struct LuceneIndex *index,
+                                      const char *field,
+                                      const char *value,
+                                      uint32_t *deleted);
+
+/**
+ * Makes the documents added and deleted since the last commit durable and visible to searches. If syncing them to
+ * disk fails, an error status is returned and they stay uncommitted.
+ *
+ * # Safety
+ * `index` must be a valid index.
+ */
+enum LuceneStatus lucene_index_commit(struct LuceneIndex *index);
+
+/**
+ * Returns the number of committed documents, or 0 if `index` is null.
+ *
+ * # Safety
+ * `index` must be null or a valid index.
+ */
+size_t lucene_index_num_docs(const struct LuceneIndex *index);
+
+/**
+ * Runs the search request in `request_json`, for example `{"query": {"match": {"field": "title", "text": "fox"}},
+ * "size": 10}`, against the committed documents. The query is in lucene-core's JSON query language (`QueryDsl`),
+ * and `size` defaults to 10.
+ *
+ * Returns the response as a JSON string, with the total hit count and the top hits with their scores and stored
+ * fields, or null on failure. Free the string with [lucene_string_free].
+ *
+ * # Safety
+ * `index` must be a valid index, and `request_json` a nul-terminated string.
+ */
+char *lucene_index_search(const struct LuceneIndex *index,
+                          const char *request_json);
+
+/**
+ * Frees a string returned by the library. Passing null does nothing.
+ *
+ * # Safety
+ * `s` must be null or have been returned by the library, and must not be used afterwards.
+ */
+void lucene_string_free(char *s);
+
+#ifdef __cplusplus
+} // extern "C"
+#endif // __cplusplus
+
+#endif /* LUCENE_H */
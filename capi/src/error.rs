@@ -0,0 +1,109 @@
+use {
+    lucene_core::{BoxError, LuceneError},
+    std::{
+        cell::RefCell,
+        ffi::{c_char, CString},
+        panic::{catch_unwind, AssertUnwindSafe},
+        ptr,
+    },
+};
+
+/// The outcome of a call.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum LuceneStatus {
+    /// The call succeeded.
+    Ok = 0,
+
+    /// A required pointer argument was null.
+    NullArgument = 1,
+
+    /// An argument was invalid: a string wasn't UTF-8, JSON was malformed, or a document or query was rejected.
+    InvalidArgument = 2,
+
+    /// The call failed for another reason.
+    Error = 3,
+
+    /// The library panicked. The objects passed to the call should no longer be used.
+    Panic = 4,
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// Returns a description of the last error on the calling thread, or null if no call has failed yet.
+///
+/// The string is owned by the library, and remains valid until the next failing call on the same thread.
+#[no_mangle]
+pub extern "C" fn lucene_last_error() -> *const c_char {
+    LAST_ERROR.with(|last_error| last_error.borrow().as_ref().map_or(ptr::null(), |message| message.as_ptr()))
+}
+
+/// Records the message of a failed call for [lucene_last_error].
+pub(crate) fn set_last_error(message: impl Into<String>) {
+    let message = message.into().replace('\0', " ");
+    let message = CString::new(message).expect("nul bytes were replaced");
+    LAST_ERROR.with(|last_error| *last_error.borrow_mut() = Some(message));
+}
+
+/// An error from a call, carrying the status to return.
+pub(crate) struct CallError {
+    pub(crate) status: LuceneStatus,
+    pub(crate) message: String,
+}
+
+impl CallError {
+    pub(crate) fn null_argument(name: &str) -> Self {
+        Self {
+            status: LuceneStatus::NullArgument,
+            message: format!("{name} is null"),
+        }
+    }
+
+    pub(crate) fn invalid_argument(message: impl Into<String>) -> Self {
+        Self {
+            status: LuceneStatus::InvalidArgument,
+            message: message.into(),
+        }
+    }
+}
+
+impl From<BoxError> for CallError {
+    fn from(e: BoxError) -> Self {
+        let status = match LuceneError::find(e.as_ref()) {
+            Some(LuceneError::InvalidArgument(_)) => LuceneStatus::InvalidArgument,
+            _ => LuceneStatus::Error,
+        };
+        Self {
+            status,
+            message: e.to_string(),
+        }
+    }
+}
+
+/// Runs the body of an exported function, recording any error or panic for [lucene_last_error] so that neither
+/// unwinds into the caller.
+pub(crate) fn call<T>(f: impl FnOnce() -> Result<T, CallError>) -> Result<T, LuceneStatus> {
+    match catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(value)) => Ok(value),
+        Ok(Err(e)) => {
+            set_last_error(e.message);
+            Err(e.status)
+        }
+        Err(panic) => {
+            let message = panic
+                .downcast_ref::<&str>()
+                .map(|message| message.to_string())
+                .or_else(|| panic.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "unknown panic".to_string());
+            set_last_error(format!("panic: {message}"));
+            Err(LuceneStatus::Panic)
+        }
+    }
+}
+
+/// Converts the result of [call] to the status returned by an exported function.
+pub(crate) fn status(result: Result<(), LuceneStatus>) -> LuceneStatus {
+    result.err().unwrap_or(LuceneStatus::Ok)
+}
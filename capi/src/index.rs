@@ -0,0 +1,320 @@
+use {
+    crate::error::{call, status, CallError, LuceneStatus},
+    lucene_core::{
+        document::Document,
+        fs::FilesystemDirectory,
+        index::{IndexWriter, IndexWriterConfig, Term, Translog, TranslogDurability},
+        io::BlockingExecutor,
+        search::{IndexSearcher, QueryDsl, TotalHitsRelation},
+        BoxError,
+    },
+    serde::Deserialize,
+    serde_json::{json, Map, Value},
+    std::{
+        ffi::{c_char, CStr, CString},
+        ptr,
+        sync::Arc,
+        time::Duration,
+    },
+};
+
+/// The number of hits returned when a search request doesn't say.
+const DEFAULT_SEARCH_SIZE: usize = 10;
+
+/// A search request: a query and the number of hits to return.
+#[derive(Deserialize)]
+struct SearchRequest {
+    query: QueryDsl,
+
+    #[serde(default = "default_search_size")]
+    size: usize,
+}
+
+fn default_search_size() -> usize {
+    DEFAULT_SEARCH_SIZE
+}
+
+/// An index of JSON documents, opened on a directory.
+///
+/// Documents are JSON objects converted with lucene-core's `Document::from_json`: their values are strings (analyzed
+/// text), integers, or arrays of these. They're added to an [IndexWriter], which holds the directory's write lock until
+/// the index is closed, and become visible to searches once committed.
+///
+/// lucene-core can't write segments to disk yet, so the writer's segments are held in memory. Changes are made durable
+/// by a [Translog] in the same directory instead: each addition and deletion is logged, a commit syncs the log, and
+/// opening the index replays it. Changes that aren't committed before the index is closed are discarded.
+///
+/// An index may be searched from several threads at once, but must not be modified while it is in use by another
+/// thread.
+pub struct LuceneIndex {
+    executor: BlockingExecutor,
+    writer: IndexWriter,
+    translog: Translog,
+    searcher: IndexSearcher,
+}
+
+/// Opens the index in the directory at `path`, creating the directory if it doesn't exist, and replays the changes
+/// committed before it was last closed. Returns null on failure, including when another writer holds the directory's
+/// write lock. Close the index with [lucene_index_close].
+///
+/// # Safety
+/// `path` must be a nul-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn lucene_index_open(path: *const c_char) -> *mut LuceneIndex {
+    call(|| {
+        let path = to_str(path, "path")?;
+        let executor = BlockingExecutor::new().map_err(BoxError::from)?;
+        let (writer, translog) = executor.block_on(async {
+            let directory = FilesystemDirectory::open_or_create(path).await?;
+            let writer = IndexWriter::new(Box::new(directory), IndexWriterConfig::new()).await?;
+
+            // Commits sync the translog, so operations are only written then.
+            let directory = FilesystemDirectory::open(path).await?;
+            let mut translog = Translog::open(Box::new(directory), TranslogDurability::Interval(Duration::MAX)).await?;
+            translog.recover(&writer).await?;
+            Ok::<_, BoxError>((writer, translog))
+        })?;
+        writer.flush()?;
+        let searcher = IndexSearcher::new(Arc::new(writer.reader()?));
+        Ok(LuceneIndex {
+            executor,
+            writer,
+            translog,
+            searcher,
+        })
+    })
+    .map_or(ptr::null_mut(), |index| Box::into_raw(Box::new(index)))
+}
+
+/// Closes an index, releasing its write lock and freeing its memory. Changes that weren't committed are discarded. The
+/// index is freed even if releasing the lock fails. Passing null does nothing.
+///
+/// # Safety
+/// `index` must be null or have been returned by [lucene_index_open], and must not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn lucene_index_close(index: *mut LuceneIndex) -> LuceneStatus {
+    if index.is_null() {
+        return LuceneStatus::Ok;
+    }
+
+    let mut index = Box::from_raw(index);
+    status(call(|| {
+        let LuceneIndex {
+            executor,
+            writer,
+            ..
+        } = index.as_mut();
+        Ok(executor.block_on(writer.close())?)
+    }))
+}
+
+/// Adds the document in `json`, a JSON object. The document is visible once committed.
+///
+/// # Safety
+/// `index` must be a valid index, and `json` a nul-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn lucene_index_add(index: *mut LuceneIndex, json: *const c_char) -> LuceneStatus {
+    status(call(|| {
+        let index = index_mut(index)?;
+        let source: Map<String, Value> = serde_json::from_str(to_str(json, "json")?)
+            .map_err(|e| CallError::invalid_argument(format!("invalid document: {e}")))?;
+        let document = Document::from_json(&source)?;
+        index.executor.block_on(index.translog.add_document(&index.writer, &document))?;
+        Ok(())
+    }))
+}
+
+/// Deletes the documents whose `field` contains the exact term `value`, including documents added since the last
+/// commit. If `deleted` isn't null, it's set to the number of documents deleted. The deletion is visible once
+/// committed.
+///
+/// # Safety
+/// `index` must be a valid index, `field` and `value` nul-terminated strings, and `deleted` null or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn lucene_index_delete(
+    index: *mut LuceneIndex,
+    field: *const c_char,
+    value: *const c_char,
+    deleted: *mut u32,
+) -> LuceneStatus {
+    status(call(|| {
+        let index = index_mut(index)?;
+        let term = Term::new(to_str(field, "field")?, to_str(value, "value")?);
+        let count = index.executor.block_on(index.translog.delete_documents(&index.writer, &[term]))?;
+        if !deleted.is_null() {
+            *deleted = count;
+        }
+        Ok(())
+    }))
+}
+
+/// Makes the documents added and deleted since the last commit durable and visible to searches. If syncing them to
+/// disk fails, an error status is returned and they stay uncommitted.
+///
+/// # Safety
+/// `index` must be a valid index.
+#[no_mangle]
+pub unsafe extern "C" fn lucene_index_commit(index: *mut LuceneIndex) -> LuceneStatus {
+    status(call(|| {
+        let index = index_mut(index)?;
+        index.executor.block_on(index.translog.sync())?;
+        index.writer.flush()?;
+        index.searcher = IndexSearcher::new(Arc::new(index.writer.reader()?));
+        Ok(())
+    }))
+}
+
+/// Returns the number of committed documents, or 0 if `index` is null.
+///
+/// # Safety
+/// `index` must be null or a valid index.
+#[no_mangle]
+pub unsafe extern "C" fn lucene_index_num_docs(index: *const LuceneIndex) -> usize {
+    index.as_ref().map_or(0, |index| index.searcher.reader().num_docs() as usize)
+}
+
+/// Runs the search request in `request_json`, for example `{"query": {"match": {"field": "title", "text": "fox"}},
+/// "size": 10}`, against the committed documents. The query is in lucene-core's JSON query language (`QueryDsl`),
+/// and `size` defaults to 10.
+///
+/// Returns the response as a JSON string, with the total hit count and the top hits with their scores and stored
+/// fields, or null on failure. Free the string with [lucene_string_free].
+///
+/// # Safety
+/// `index` must be a valid index, and `request_json` a nul-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn lucene_index_search(index: *const LuceneIndex, request_json: *const c_char) -> *mut c_char {
+    call(|| {
+        let index = index.as_ref().ok_or_else(|| CallError::null_argument("index"))?;
+        let request: SearchRequest = serde_json::from_str(to_str(request_json, "request_json")?)
+            .map_err(|e| CallError::invalid_argument(format!("invalid search request: {e}")))?;
+        let query = request.query.to_query(index.writer.config().analyzer().as_ref())?;
+        let top_docs = index.searcher.search(query.as_ref(), request.size)?;
+
+        let mut hits = Vec::with_capacity(top_docs.score_docs.len());
+        for score_doc in &top_docs.score_docs {
+            hits.push(json!({
+                "score": score_doc.score,
+                "fields": index.searcher.doc(score_doc.doc)?.to_json(),
+            }));
+        }
+        let response = json!({
+            "total_hits": top_docs.total_hits.value,
+            "total_hits_is_lower_bound": top_docs.total_hits.relation == TotalHitsRelation::GreaterThanOrEqualTo,
+            "hits": hits,
+        });
+        Ok(CString::new(response.to_string()).expect("JSON escapes nul characters"))
+    })
+    .map_or(ptr::null_mut(), CString::into_raw)
+}
+
+/// Frees a string returned by the library. Passing null does nothing.
+///
+/// # Safety
+/// `s` must be null or have been returned by the library, and must not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn lucene_string_free(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
+
+unsafe fn index_mut<'a>(index: *mut LuceneIndex) -> Result<&'a mut LuceneIndex, CallError> {
+    index.as_mut().ok_or_else(|| CallError::null_argument("index"))
+}
+
+unsafe fn to_str<'a>(s: *const c_char, name: &str) -> Result<&'a str, CallError> {
+    if s.is_null() {
+        return Err(CallError::null_argument(name));
+    }
+
+    CStr::from_ptr(s).to_str().map_err(|e| CallError::invalid_argument(format!("{name} isn't UTF-8: {e}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{
+            lucene_index_add, lucene_index_close, lucene_index_commit, lucene_index_delete, lucene_index_num_docs,
+            lucene_index_open, lucene_index_search, lucene_last_error, lucene_string_free, LuceneStatus,
+        },
+        pretty_assertions::assert_eq,
+        serde_json::{json, Value},
+        std::{
+            env,
+            ffi::{CStr, CString},
+            fs, process, ptr,
+        },
+    };
+
+    fn c(s: &str) -> CString {
+        CString::new(s).unwrap()
+    }
+
+    unsafe fn search(index: *const super::LuceneIndex, request: &str) -> Value {
+        let response = lucene_index_search(index, c(request).as_ptr());
+        assert!(!response.is_null());
+        let value = serde_json::from_str(CStr::from_ptr(response).to_str().unwrap()).unwrap();
+        lucene_string_free(response);
+        value
+    }
+
+    unsafe fn last_error() -> String {
+        CStr::from_ptr(lucene_last_error()).to_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn test_index() {
+        let dir = env::temp_dir().join(format!("lucene-capi-test-{}", process::id()));
+        let path = c(dir.to_str().unwrap());
+        unsafe {
+            let index = lucene_index_open(path.as_ptr());
+            assert!(!index.is_null());
+            assert!(dir.is_dir());
+
+            // The index holds the directory's write lock until closed.
+            assert!(lucene_index_open(path.as_ptr()).is_null());
+            assert!(last_error().contains("lock"), "unexpected error: {}", last_error());
+
+            let doc = c(r#"{"id": "1", "title": "The quick fox", "year": 2020}"#);
+            assert_eq!(lucene_index_add(index, doc.as_ptr()), LuceneStatus::Ok);
+            assert_eq!(lucene_index_add(index, c(r#"{"id": "2", "title": "A lazy dog"}"#).as_ptr()), LuceneStatus::Ok);
+            assert_eq!(lucene_index_num_docs(index), 0);
+            assert_eq!(lucene_index_commit(index), LuceneStatus::Ok);
+            assert_eq!(lucene_index_num_docs(index), 2);
+
+            let response = search(index, r#"{"query": {"match": {"field": "title", "text": "fox"}}}"#);
+            assert_eq!(response["total_hits"], json!(1));
+            assert_eq!(response["hits"][0]["fields"], json!({"id": "1", "title": "The quick fox", "year": 2020}));
+
+            let mut deleted = 0;
+            assert_eq!(lucene_index_delete(index, c("id").as_ptr(), c("1").as_ptr(), &mut deleted), LuceneStatus::Ok);
+            assert_eq!(deleted, 1);
+            assert_eq!(lucene_index_commit(index), LuceneStatus::Ok);
+            assert_eq!(search(index, r#"{"query": {"match_all": {}}}"#)["total_hits"], json!(1));
+
+            // Errors are reported through the status and lucene_last_error.
+            assert_eq!(lucene_index_add(index, c("{").as_ptr()), LuceneStatus::InvalidArgument);
+            assert!(last_error().starts_with("invalid document"));
+            assert_eq!(lucene_index_add(index, c(r#"{"price": 1.5}"#).as_ptr()), LuceneStatus::InvalidArgument);
+            assert_eq!(lucene_index_add(index, ptr::null()), LuceneStatus::NullArgument);
+            assert_eq!(last_error(), "json is null");
+            assert!(lucene_index_search(index, c(r#"{"query": {"nope": {}}}"#).as_ptr()).is_null());
+            assert_eq!(lucene_index_commit(ptr::null_mut()), LuceneStatus::NullArgument);
+
+            // Committed changes survive closing the index, while uncommitted ones are discarded.
+            assert_eq!(lucene_index_add(index, c(r#"{"id": "3", "title": "A lazy fox"}"#).as_ptr()), LuceneStatus::Ok);
+            assert_eq!(lucene_index_close(index), LuceneStatus::Ok);
+            assert_eq!(lucene_index_close(ptr::null_mut()), LuceneStatus::Ok);
+
+            // Closing the index releases the lock.
+            let index = lucene_index_open(path.as_ptr());
+            assert!(!index.is_null());
+            assert_eq!(lucene_index_num_docs(index), 1);
+            let response = search(index, r#"{"query": {"match_all": {}}}"#);
+            assert_eq!(response["hits"][0]["fields"], json!({"id": "2", "title": "A lazy dog"}));
+            assert_eq!(lucene_index_close(index), LuceneStatus::Ok);
+        }
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
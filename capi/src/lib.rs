@@ -0,0 +1,57 @@
+//! C bindings to Lucene, for embedding the engine in applications written in other languages such as Python, Go or
+//! Swift.
+//!
+//! The library is built as a shared and a static library. Its header, `include/lucene.h`, is generated by cbindgen
+//! and checked in; a test fails when it's out of date, and rewrites it when run with `LUCENE_UPDATE_HEADER=1`.
+//! Documents and search requests are passed as JSON strings, using lucene-core's JSON documents and query language:
+//!
+//! ```c
+//! LuceneIndex *index = lucene_index_open("/var/lib/myapp/index");
+//! lucene_index_add(index, "{\"title\": \"The quick fox\"}");
+//! lucene_index_commit(index);
+//! char *response = lucene_index_search(index, "{\"query\": {\"match\": {\"field\": \"title\", \"text\": \"fox\"}}}");
+//! printf("%s\n", response);
+//! lucene_string_free(response);
+//! lucene_index_close(index);
+//! ```
+//!
+//! Functions that can fail return a [LuceneStatus], or null for those returning a pointer; the reason is available
+//! from [lucene_last_error]. Panics are caught and never unwind into the caller.
+
+#![warn(clippy::all)]
+#![warn(rustdoc::missing_crate_level_docs)]
+#![warn(rustdoc::broken_intra_doc_links)]
+#![warn(missing_docs)]
+
+mod error;
+mod index;
+
+pub use {error::*, index::*};
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, path::PathBuf};
+
+    #[test]
+    fn test_header() {
+        let crate_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        let config = cbindgen::Config::from_file(crate_dir.join("cbindgen.toml")).unwrap();
+        let mut header = Vec::new();
+        cbindgen::Builder::new()
+            .with_config(config)
+            .with_src(crate_dir.join("src/lib.rs"))
+            .generate()
+            .unwrap()
+            .write(&mut header);
+
+        let path = crate_dir.join("include/lucene.h");
+        if env::var_os("LUCENE_UPDATE_HEADER").is_some() {
+            fs::write(&path, &header).unwrap();
+        }
+        let expected = String::from_utf8(header).unwrap();
+        assert!(
+            fs::read_to_string(&path).unwrap() == expected,
+            "include/lucene.h is out of date; regenerate it with LUCENE_UPDATE_HEADER=1 cargo test -p lucene-capi"
+        );
+    }
+}
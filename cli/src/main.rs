@@ -0,0 +1,371 @@
+//! A command-line tool for inspecting Lucene indexes.
+//!
+//! ```text
+//! lucene-cli segments <index-dir>        Lists the segments of the latest commit.
+//! lucene-cli usage <index-dir>           Prints disk usage by segment and data structure, and by field for norms
+//!                                        and term vectors.
+//! lucene-cli fields <index-dir>          Prints the statistics of each field.
+//! lucene-cli terms <index-dir> <field>   Prints the statistics of each term of a field with term vectors.
+//! lucene-cli doc <index-dir> <doc-id>    Prints the norms and term vectors of a document.
+//! ```
+//!
+//! Field, term and document data come from the norms and term vectors of the segments (see [IndexFieldStats]), as
+//! lucene-core can't read field infos, postings or stored fields from disk yet. Until it can, this tool doesn't:
+//!
+//! * run queries against an index, which needs the postings;
+//! * dump the stored fields of a document, which needs the stored fields reader;
+//! * list the terms of a field without term vectors: `terms` fails for such fields, even when they are indexed.
+
+#![warn(clippy::all)]
+#![warn(missing_docs)]
+
+use {
+    lucene_core::{
+        fs::FilesystemDirectory,
+        index::{IndexDiskUsage, IndexFieldStats, SegmentFieldData, SegmentIndex},
+        BoxResult, LuceneError,
+    },
+    std::{
+        collections::HashMap,
+        env,
+        io::{stdout, Write},
+        path::Path,
+        process::ExitCode,
+    },
+};
+
+const USAGE: &str = "\
+Usage: lucene-cli <command> <index-dir> [<args>]
+
+Commands:
+  segments          Lists the segments of the latest commit
+  usage             Prints disk usage by segment and data structure, and by field for norms and term vectors
+  fields            Prints the statistics of each field
+  terms <field>     Prints the statistics of each term of a field with term vectors
+  doc <doc-id>      Prints the norms and term vectors of a document
+
+Running queries and dumping stored fields aren't supported yet.";
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let [command, index_dir, rest @ ..] = args.as_slice() else {
+        eprintln!("{USAGE}");
+        return ExitCode::FAILURE;
+    };
+
+    let index_dir = Path::new(index_dir);
+    let result = match (command.as_str(), rest) {
+        ("segments", []) => print_segments(index_dir, &mut stdout()).await,
+        ("usage", []) => print_usage(index_dir, &mut stdout()).await,
+        ("fields", []) => print_fields(index_dir, &mut stdout()).await,
+        ("terms", [field]) => print_terms(index_dir, field, &mut stdout()).await,
+        ("doc", [doc]) => match doc.parse() {
+            Ok(doc) => print_doc(index_dir, doc, &mut stdout()).await,
+            Err(e) => Err(LuceneError::InvalidArgument(format!("invalid doc id {doc:?}: {e}")).into()),
+        },
+        _ => Err(LuceneError::InvalidArgument(format!("invalid command {:?}\n\n{USAGE}", args.join(" "))).into()),
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("lucene-cli: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Prints the latest commit of the index and its segments.
+async fn print_segments(index_dir: &Path, out: &mut impl Write) -> BoxResult<()> {
+    let mut directory = FilesystemDirectory::open(index_dir).await?;
+    let segment_index = SegmentIndex::open(&mut directory).await?;
+
+    writeln!(
+        out,
+        "Commit generation {}, version {}, id {}, Lucene {}, created by Lucene {}.x",
+        segment_index.get_generation(),
+        segment_index.get_version(),
+        segment_index.get_id(),
+        segment_index.get_lucene_version(),
+        segment_index.get_index_created_version_major()
+    )?;
+    print_map(out, "User data", segment_index.get_user_data())?;
+
+    let mut total_docs = 0;
+    let mut total_deleted = 0;
+    for commit_info in segment_index.get_segments() {
+        let info = commit_info.get_segment_info();
+        let deleted = commit_info.get_del_count() + commit_info.get_soft_del_count();
+        total_docs += info.get_max_doc() as u64;
+        total_deleted += deleted as u64;
+
+        writeln!(out)?;
+        writeln!(
+            out,
+            "Segment {}: {} docs, {} deleted, Lucene {}{}",
+            info.get_name(),
+            info.get_max_doc(),
+            deleted,
+            commit_info.get_version(),
+            if info.is_compound_file() {
+                ", compound"
+            } else {
+                ""
+            }
+        )?;
+        if let Some(id) = commit_info.get_id() {
+            writeln!(out, "  Id: {id}")?;
+        }
+        if let Some(sort) = info.get_index_sort() {
+            writeln!(out, "  Index sort: {sort:?}")?;
+        }
+
+        let mut files: Vec<&String> = info.get_files().iter().collect();
+        files.sort();
+        writeln!(out, "  Files: {}", files.iter().map(|file| file.as_str()).collect::<Vec<_>>().join(" "))?;
+        print_map(out, "  Attributes", info.get_attributes())?;
+        print_map(out, "  Diagnostics", info.get_diagnostics())?;
+    }
+
+    writeln!(out)?;
+    writeln!(out, "{} segments, {total_docs} docs, {total_deleted} deleted", segment_index.get_segments().len())?;
+    Ok(())
+}
+
+/// Prints the disk usage of the latest commit of the index.
+async fn print_usage(index_dir: &Path, out: &mut impl Write) -> BoxResult<()> {
+    let mut directory = FilesystemDirectory::open(index_dir).await?;
+    write!(out, "{}", IndexDiskUsage::analyze(&mut directory).await?)?;
+    Ok(())
+}
+
+/// Prints the statistics of each field of the latest commit of the index.
+async fn print_fields(index_dir: &Path, out: &mut impl Write) -> BoxResult<()> {
+    let mut directory = FilesystemDirectory::open(index_dir).await?;
+    write!(out, "{}", IndexFieldStats::analyze(&mut directory).await?)?;
+    Ok(())
+}
+
+/// Prints the document frequency and total term frequency of each term of a field, from its term vectors.
+async fn print_terms(index_dir: &Path, field: &str, out: &mut impl Write) -> BoxResult<()> {
+    let mut directory = FilesystemDirectory::open(index_dir).await?;
+    let stats = IndexFieldStats::analyze(&mut directory).await?;
+    let Some(field_stats) = stats.fields.get(field).filter(|field_stats| !field_stats.terms.is_empty()) else {
+        return Err(LuceneError::InvalidArgument(format!("field {field} has no term vectors")).into());
+    };
+
+    writeln!(out, "{:<32} {:>10} {:>14}", "Term", "Doc freq", "Term freq")?;
+    for (term, term_stats) in &field_stats.terms {
+        writeln!(
+            out,
+            "{:<32} {:>10} {:>14}",
+            String::from_utf8_lossy(term),
+            term_stats.doc_freq,
+            term_stats.total_term_freq
+        )?;
+    }
+    writeln!(out, "{} terms", field_stats.terms.len())?;
+    Ok(())
+}
+
+/// Prints the norms and term vectors of a document, identified by its doc id across the segments of the latest
+/// commit, in commit order.
+async fn print_doc(index_dir: &Path, doc: u32, out: &mut impl Write) -> BoxResult<()> {
+    let mut directory = FilesystemDirectory::open(index_dir).await?;
+    let segment_index = SegmentIndex::open(&mut directory).await?;
+
+    let mut doc_base = 0;
+    for commit_info in segment_index.get_segments() {
+        let info = commit_info.get_segment_info();
+        if doc >= doc_base + info.get_max_doc() {
+            doc_base += info.get_max_doc();
+            continue;
+        }
+
+        let segment_doc = doc - doc_base;
+        let data = SegmentFieldData::read(&mut directory, commit_info).await?;
+
+        let deleted = if data.is_live(segment_doc) {
+            ""
+        } else {
+            ", deleted"
+        };
+        writeln!(out, "Document {doc}: segment {}, doc {segment_doc}{deleted}", info.get_name())?;
+
+        let mut norms: Vec<_> = data.norms.iter().map(|(field, norms)| (field, norms[segment_doc as usize])).collect();
+        norms.sort();
+        for (field, norm) in norms.into_iter().filter(|(_, norm)| *norm != 0) {
+            writeln!(out, "  {field}: norm {norm}")?;
+        }
+
+        let Some(term_vectors) = data.term_vectors.get(segment_doc as usize) else {
+            return Ok(());
+        };
+        for (field, term_vector) in term_vectors.iter() {
+            writeln!(out, "  {field}: {} terms", term_vector.size())?;
+            for term in term_vector.terms() {
+                write!(out, "    {} freq {}", String::from_utf8_lossy(&term.term), term.freq)?;
+                if !term.positions.is_empty() {
+                    write!(out, ", positions {:?}", term.positions)?;
+                }
+                if !term.offsets.is_empty() {
+                    write!(out, ", offsets {:?}", term.offsets)?;
+                }
+                writeln!(out)?;
+            }
+        }
+        return Ok(());
+    }
+
+    Err(LuceneError::InvalidArgument(format!("doc id {doc} is out of range: the index has {doc_base} documents"))
+        .into())
+}
+
+/// Prints a map on a single line, with its keys sorted.
+fn print_map(out: &mut impl Write, label: &str, map: &HashMap<String, String>) -> BoxResult<()> {
+    if map.is_empty() {
+        return Ok(());
+    }
+
+    let mut entries: Vec<_> = map.iter().collect();
+    entries.sort();
+    let entries: Vec<String> = entries.iter().map(|(key, value)| format!("{key}={value}")).collect();
+    writeln!(out, "{label}: {}", entries.join(", "))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::{print_doc, print_fields, print_segments, print_terms, print_usage},
+        lucene_core::{
+            analysis::SimpleAnalyzer,
+            codec::get_codec,
+            document::{Document, Field, Store, TermVectorOptions},
+            fs::FilesystemDirectory,
+            index::{MemorySegmentBuilder, SegmentCommitInfo, SegmentIndex, SegmentInfo},
+            Id, IoContext, LATEST,
+        },
+        pretty_assertions::assert_eq,
+        std::{
+            env, fs,
+            path::{Path, PathBuf},
+            process,
+            sync::Arc,
+        },
+    };
+
+    /// Commits an index of one segment in a new directory named after `test`, holding the given bodies with norms
+    /// and term vectors, and untitled titles with norms only.
+    async fn create_index(test: &str, bodies: &[&str]) -> PathBuf {
+        let path = env::temp_dir().join(format!("lucene-cli-{test}-{}", process::id()));
+        let mut dir = FilesystemDirectory::create(&path).await.unwrap();
+
+        let mut builder = MemorySegmentBuilder::new(Arc::new(SimpleAnalyzer));
+        for body in bodies {
+            let mut doc = Document::new();
+            let field = Field::text("body", *body, Store::Yes);
+            doc.add(field.with_term_vectors(TermVectorOptions::default()).unwrap());
+            doc.add(Field::text("title", "untitled", Store::No));
+            builder.add_document(&doc).unwrap();
+        }
+        let segment = builder.build();
+
+        let codec = get_codec("Lucene95").unwrap();
+        let context = IoContext::Default;
+        let mut segment_index = SegmentIndex::new(LATEST.major()).unwrap();
+        let mut info = SegmentInfo::new(segment_index.new_segment_name(), Id::random_id(), bodies.len() as u32, false);
+        info.set_codec_name("Lucene95");
+        codec.norms_format().write_norms(&segment, &mut dir, &info, &context).await.unwrap();
+        codec.term_vectors_format().write_term_vectors(&segment, &mut dir, &info, &context).await.unwrap();
+        let mut files = codec.norms_format().files(&info);
+        files.extend(codec.term_vectors_format().files(&info));
+        files.push(format!("{}.si", info.get_name()));
+        for file_name in files {
+            info.add_file(file_name);
+        }
+        codec.segment_info_format().write_segment_info(&mut dir, &info, &context).await.unwrap();
+        segment_index.add_segment(SegmentCommitInfo::new(info, 0, 0, None, None, None, None)).unwrap();
+        segment_index.commit(&mut dir).await.unwrap();
+        path
+    }
+
+    /// Runs a command against a new index holding two documents, returning its output.
+    macro_rules! run {
+        ($test:literal, |$path:ident, $out:ident| $command:expr) => {{
+            let path = create_index($test, &["quick fox", "lazy lazy dog"]).await;
+            let $path: &Path = &path;
+            let mut $out = Vec::new();
+            let result = $command.await;
+            fs::remove_dir_all($path).unwrap();
+            result.map(|()| String::from_utf8($out).unwrap())
+        }};
+    }
+
+    #[tokio::test]
+    async fn test_segments() {
+        let segments = run!("segments", |path, out| print_segments(path, &mut out)).unwrap();
+        assert!(segments.starts_with("Commit generation 1, version "), "{segments}");
+        assert!(segments.contains("\nSegment _0: 2 docs, 0 deleted, Lucene 9.5.0\n"), "{segments}");
+        assert!(segments.contains("\n  Files: _0.nvd _0.nvm _0.si _0.tvd _0.tvm _0.tvx\n"), "{segments}");
+        assert!(segments.ends_with("\n1 segments, 2 docs, 0 deleted\n"), "{segments}");
+    }
+
+    #[tokio::test]
+    async fn test_usage() {
+        let usage = run!("usage", |path, out| print_usage(path, &mut out)).unwrap();
+        assert!(usage.starts_with("Commit generation 1\nBy segment:\n  _0 "), "{usage}");
+        assert!(usage.contains("\n  term vectors "), "{usage}");
+        assert!(usage.contains("\n  norms "), "{usage}");
+        assert!(usage.contains("\nBy field (norms and term vectors only):\n"), "{usage}");
+        assert!(usage.contains("%  (norms 2, term vectors 33)\n"), "{usage}");
+        assert!(usage.contains("%  (norms 0)\n"), "{usage}");
+    }
+
+    #[tokio::test]
+    async fn test_fields() {
+        let fields = run!("fields", |path, out| print_fields(path, &mut out)).unwrap();
+        assert_eq!(
+            fields,
+            "Commit generation 1, 2 docs\n  \
+             Field                         Norms Term vectors      Terms      Term freq\n  \
+             body                              2            2          4              5\n  \
+             title                             2            0          0              0\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_terms() {
+        let terms = run!("terms", |path, out| print_terms(path, "body", &mut out)).unwrap();
+        let rows: Vec<Vec<&str>> = terms.lines().map(|line| line.split_whitespace().collect()).collect();
+        assert_eq!(
+            rows,
+            [
+                vec!["Term", "Doc", "freq", "Term", "freq"],
+                vec!["dog", "1", "1"],
+                vec!["fox", "1", "1"],
+                vec!["lazy", "1", "2"],
+                vec!["quick", "1", "1"],
+                vec!["4", "terms"],
+            ]
+        );
+
+        // Titles are indexed without term vectors, so their terms can't be listed.
+        let error = run!("terms-title", |path, out| print_terms(path, "title", &mut out)).unwrap_err();
+        assert_eq!(error.to_string(), "Invalid argument: field title has no term vectors");
+    }
+
+    #[tokio::test]
+    async fn test_doc() {
+        let doc = run!("doc", |path, out| print_doc(path, 1, &mut out)).unwrap();
+        assert_eq!(
+            doc,
+            "Document 1: segment _0, doc 1\n  body: norm 3\n  title: norm 1\n  body: 2 terms\n    dog freq 1\n    \
+             lazy freq 2\n"
+        );
+
+        let error = run!("doc-out-of-range", |path, out| print_doc(path, 2, &mut out)).unwrap_err();
+        assert_eq!(error.to_string(), "Invalid argument: doc id 2 is out of range: the index has 2 documents");
+    }
+}
@@ -0,0 +1,88 @@
+//! Compares building and iterating the doc id set implementations at several densities.
+//!
+//! Run with `cargo bench -p lucene-core --bench doc_id_sets`.
+
+use {
+    lucene_core::{
+        search::{BitDocIdSet, DocIdSet, DocIdSetBuilder, IntArrayDocIdSet, RoaringDocIdSet, NO_MORE_DOCS},
+        util::{BitSet, FixedBitSet, SparseFixedBitSet},
+    },
+    std::{
+        hint::black_box,
+        sync::Arc,
+        time::{Duration, Instant},
+    },
+};
+
+const MAX_DOC: u32 = 10_000_000;
+const ITERATIONS: u32 = 5;
+
+fn time<T>(f: impl Fn() -> T) -> Duration {
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        black_box(f());
+    }
+    start.elapsed() / ITERATIONS
+}
+
+fn count(set: &dyn DocIdSet) -> u32 {
+    let mut iterator = set.iterator();
+    let mut count = 0;
+    while iterator.next_doc().unwrap() != NO_MORE_DOCS {
+        count += 1;
+    }
+    count
+}
+
+fn main() {
+    println!("{:>8} {:>14} {:>12} {:>12} {:>12}", "density", "set", "build", "iterate", "bytes");
+    for density in [0.0001, 0.001, 0.01, 0.1, 0.5] {
+        let step = (1.0 / density) as u32;
+        let docs: Vec<u32> = (0..MAX_DOC).step_by(step as usize).collect();
+
+        let array = IntArrayDocIdSet::new(docs.clone().into());
+        let mut fixed = FixedBitSet::new(MAX_DOC);
+        let mut sparse = SparseFixedBitSet::new(MAX_DOC);
+        for &doc in &docs {
+            fixed.set(doc);
+            sparse.set(doc);
+        }
+        let fixed = BitDocIdSet::with_cardinality(Arc::new(fixed));
+        let sparse = BitDocIdSet::with_cardinality(Arc::new(sparse));
+        let roaring = RoaringDocIdSet::from_iterator(array.iterator().as_mut(), MAX_DOC).unwrap();
+
+        let builds: [(&str, Duration, &dyn DocIdSet); 4] = [
+            ("int array", time(|| IntArrayDocIdSet::new(docs.clone().into())), &array),
+            (
+                "fixed bits",
+                time(|| {
+                    let mut bits = FixedBitSet::new(MAX_DOC);
+                    docs.iter().for_each(|&doc| bits.set(doc));
+                    bits
+                }),
+                &fixed,
+            ),
+            (
+                "sparse bits",
+                time(|| {
+                    let mut bits = SparseFixedBitSet::new(MAX_DOC);
+                    docs.iter().for_each(|&doc| bits.set(doc));
+                    bits
+                }),
+                &sparse,
+            ),
+            ("roaring", time(|| RoaringDocIdSet::from_iterator(array.iterator().as_mut(), MAX_DOC).unwrap()), &roaring),
+        ];
+        for (name, build, set) in builds {
+            let iterate = time(|| count(set));
+            println!("{density:>8} {name:>14} {build:>12.2?} {iterate:>12.2?} {:>12}", set.ram_bytes_used());
+        }
+
+        let builder = time(|| {
+            let mut builder = DocIdSetBuilder::new(MAX_DOC);
+            builder.add(array.iterator().as_mut()).unwrap();
+            builder.build()
+        });
+        println!("{density:>8} {:>14} {builder:>12.2?}", "builder");
+    }
+}
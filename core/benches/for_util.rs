@@ -0,0 +1,97 @@
+//! Compares decoding blocks of 128 integers with ForUtil against a scalar decoder that extracts one value at a time
+//! from a plain packed bit stream, as Lucene's `BulkOperationPacked` does.
+//!
+//! Run with `cargo bench -p lucene-core --bench for_util`. Building with `RUSTFLAGS="-C target-cpu=native"` lets the
+//! compiler use the widest vector instructions available.
+
+use {
+    lucene_core::codec::{ForUtil, BLOCK_SIZE},
+    std::{
+        hint::black_box,
+        time::{Duration, Instant},
+    },
+};
+
+const NUM_BLOCKS: usize = 10_000;
+const ITERATIONS: u32 = 5;
+
+fn time(mut f: impl FnMut()) -> Duration {
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        f();
+    }
+    start.elapsed() / ITERATIONS
+}
+
+/// Packs values as a little-endian bit stream, one after another.
+fn scalar_encode(values: &[u64; BLOCK_SIZE], bits_per_value: u32, output: &mut Vec<u8>) {
+    let mut pending = 0u128;
+    let mut pending_bits = 0;
+    for &value in values {
+        pending |= (value as u128) << pending_bits;
+        pending_bits += bits_per_value;
+        while pending_bits >= 8 {
+            output.push(pending as u8);
+            pending >>= 8;
+            pending_bits -= 8;
+        }
+    }
+}
+
+/// Extracts each value from the bit stream with its own shifts and masks.
+fn scalar_decode(input: &[u8], bits_per_value: u32, values: &mut [u64; BLOCK_SIZE]) {
+    let mask = u64::MAX >> (64 - bits_per_value);
+    for (i, value) in values.iter_mut().enumerate() {
+        let bit = i * bits_per_value as usize;
+        let mut word = [0; 8];
+        let available = (input.len() - bit / 8).min(8);
+        word[..available].copy_from_slice(&input[bit / 8..bit / 8 + available]);
+        *value = (u64::from_le_bytes(word) >> (bit % 8)) & mask;
+    }
+}
+
+fn main() {
+    println!("{:>4} {:>14} {:>14} {:>10}", "bits", "for_util", "scalar", "speedup");
+    let mut for_util = ForUtil::new();
+    for bits_per_value in [1, 2, 4, 7, 8, 12, 16, 20, 24, 27, 32] {
+        let mask = u64::MAX >> (64 - bits_per_value);
+        let blocks: Vec<[u64; BLOCK_SIZE]> = (0..NUM_BLOCKS)
+            .map(|block| {
+                let mut values = [0; BLOCK_SIZE];
+                for (i, value) in values.iter_mut().enumerate() {
+                    *value = ((block * BLOCK_SIZE + i) as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15) & mask;
+                }
+                values
+            })
+            .collect();
+
+        let num_bytes = ForUtil::num_bytes(bits_per_value);
+        let mut for_bytes = Vec::with_capacity(NUM_BLOCKS * num_bytes);
+        let mut scalar_bytes = Vec::with_capacity(NUM_BLOCKS * num_bytes);
+        for block in &blocks {
+            for_util.encode_block(block, bits_per_value, &mut for_bytes).unwrap();
+            scalar_encode(block, bits_per_value, &mut scalar_bytes);
+        }
+
+        let mut values = [0; BLOCK_SIZE];
+        let for_time = time(|| {
+            for bytes in for_bytes.chunks_exact(num_bytes) {
+                for_util.decode_block(bits_per_value, bytes, &mut values).unwrap();
+                black_box(&values);
+            }
+        });
+        assert_eq!(values, blocks[NUM_BLOCKS - 1]);
+        let scalar_time = time(|| {
+            for bytes in scalar_bytes.chunks_exact(num_bytes) {
+                scalar_decode(bytes, bits_per_value, &mut values);
+                black_box(&values);
+            }
+        });
+        assert_eq!(values, blocks[NUM_BLOCKS - 1]);
+
+        println!(
+            "{bits_per_value:>4} {for_time:>14.2?} {scalar_time:>14.2?} {:>9.2}x",
+            scalar_time.as_secs_f64() / for_time.as_secs_f64()
+        );
+    }
+}
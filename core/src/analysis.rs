@@ -0,0 +1,13 @@
+mod analyzer;
+mod cjk_bigram_analyzer;
+mod english_minimal_stem_filter;
+mod keyword_marker_filter;
+mod per_field_analyzer_wrapper;
+mod simple_analyzer;
+mod stemmer_override_filter;
+mod token_filter;
+
+pub use {
+    analyzer::*, cjk_bigram_analyzer::*, english_minimal_stem_filter::*, keyword_marker_filter::*,
+    per_field_analyzer_wrapper::*, simple_analyzer::*, stemmer_override_filter::*, token_filter::*,
+};
@@ -0,0 +1,63 @@
+use std::fmt::Debug;
+
+/// A single token produced by analyzing text.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Token {
+    /// The text of the token, as it will be indexed.
+    pub term: String,
+
+    /// The position of this token relative to the previous one. This is usually 1; 0 places the token at the same
+    /// position as the previous one (as with synonyms).
+    pub position_increment: u32,
+
+    /// The byte offset of the start of the token in the original text.
+    pub start_offset: u32,
+
+    /// The byte offset just past the end of the token in the original text.
+    pub end_offset: u32,
+
+    /// Arbitrary bytes indexed with this occurrence of the token, such as a weight for scoring; empty if there are
+    /// none.
+    pub payload: Vec<u8>,
+
+    /// Whether the token is a keyword that stemmers must leave as it is, as marked by a
+    /// [crate::analysis::KeywordMarkerFilter] or a [crate::analysis::StemmerOverrideFilter].
+    pub keyword: bool,
+}
+
+impl Token {
+    /// Creates a token with a position increment of 1.
+    pub fn new(term: impl Into<String>, start_offset: u32, end_offset: u32) -> Self {
+        Self {
+            term: term.into(),
+            position_increment: 1,
+            start_offset,
+            end_offset,
+            payload: Vec::new(),
+            keyword: false,
+        }
+    }
+
+    /// Sets the payload of the token.
+    pub fn with_payload(mut self, payload: impl Into<Vec<u8>>) -> Self {
+        self.payload = payload.into();
+        self
+    }
+}
+
+/// Converts the text of a field into the tokens that are indexed for it.
+pub trait Analyzer: Debug + Send + Sync {
+    /// Analyzes the given text for the given field.
+    fn analyze(&self, field: &str, text: &str) -> Vec<Token>;
+
+    /// Returns the position gap inserted between multiple values of the same field in a document.
+    fn position_increment_gap(&self, _field: &str) -> u32 {
+        0
+    }
+
+    /// Returns the offset gap inserted between multiple values of the same field in a document, so that the offsets of
+    /// a value's tokens follow those of the previous value.
+    fn offset_gap(&self, _field: &str) -> u32 {
+        1
+    }
+}
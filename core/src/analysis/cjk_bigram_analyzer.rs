@@ -0,0 +1,102 @@
+use crate::analysis::{Analyzer, Token};
+
+/// An [Analyzer] for Chinese, Japanese and Korean text, which isn't separated into words by spaces.
+///
+/// Runs of CJK characters are indexed as their overlapping bigrams at consecutive positions, so `東京都` becomes
+/// `東京 京都`; a lone CJK character is indexed as itself. Other runs of letters and digits are lowercased whole, as
+/// with [crate::analysis::SimpleAnalyzer].
+///
+/// Searching for a word takes a phrase of its bigrams, which [crate::search::QueryBuilder] creates with
+/// [crate::search::NGramPhraseQuery].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CJKBigramAnalyzer;
+
+impl CJKBigramAnalyzer {
+    /// Indicates whether `c` is written without spaces between words: Han ideographs, Hiragana, Katakana or Hangul.
+    pub fn is_cjk(c: char) -> bool {
+        matches!(
+            c,
+            '\u{1100}'..='\u{11ff}'
+                | '\u{3040}'..='\u{309f}'
+                | '\u{30a0}'..='\u{30ff}'
+                | '\u{3130}'..='\u{318f}'
+                | '\u{3400}'..='\u{4dbf}'
+                | '\u{4e00}'..='\u{9fff}'
+                | '\u{ac00}'..='\u{d7af}'
+                | '\u{f900}'..='\u{faff}'
+                | '\u{ff66}'..='\u{ff9f}'
+                | '\u{20000}'..='\u{2fa1f}'
+        )
+    }
+
+    /// Adds the bigrams of a run of CJK characters, given with their offsets.
+    fn add_bigrams(tokens: &mut Vec<Token>, text: &str, run: &[(usize, char)]) {
+        let end = |&(offset, c): &(usize, char)| offset + c.len_utf8();
+        match run {
+            [] => (),
+            [c] => tokens.push(Token::new(&text[c.0..end(c)], c.0 as u32, end(c) as u32)),
+            _ => {
+                for pair in run.windows(2) {
+                    let (start, end) = (pair[0].0, end(&pair[1]));
+                    tokens.push(Token::new(&text[start..end], start as u32, end as u32));
+                }
+            }
+        }
+    }
+}
+
+impl Analyzer for CJKBigramAnalyzer {
+    fn analyze(&self, _field: &str, text: &str) -> Vec<Token> {
+        let mut tokens = Vec::new();
+        let mut start = None;
+        let mut cjk_run = Vec::new();
+
+        for (offset, c) in text.char_indices().chain(std::iter::once((text.len(), ' '))) {
+            let cjk = Self::is_cjk(c);
+            if let (Some(s), false) = (start, c.is_alphanumeric() && !cjk) {
+                tokens.push(Token::new(text[s..offset].to_lowercase(), s as u32, offset as u32));
+                start = None;
+            }
+
+            if cjk {
+                cjk_run.push((offset, c));
+                continue;
+            }
+
+            Self::add_bigrams(&mut tokens, text, &cjk_run);
+            cjk_run.clear();
+            if start.is_none() && c.is_alphanumeric() {
+                start = Some(offset);
+            }
+        }
+
+        tokens
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::analysis::{Analyzer, CJKBigramAnalyzer, Token},
+        pretty_assertions::assert_eq,
+    };
+
+    #[test]
+    fn test_analyze() {
+        let tokens = CJKBigramAnalyzer.analyze("body", "東京都Tower 2 は、日");
+        assert_eq!(
+            tokens,
+            vec![
+                Token::new("東京", 0, 6),
+                Token::new("京都", 3, 9),
+                Token::new("tower", 9, 14),
+                Token::new("2", 15, 16),
+                Token::new("は", 17, 20),
+                Token::new("日", 23, 26),
+            ]
+        );
+
+        assert_eq!(CJKBigramAnalyzer.analyze("body", "서울시").len(), 2);
+        assert!(CJKBigramAnalyzer.analyze("body", " ,. ").is_empty());
+    }
+}
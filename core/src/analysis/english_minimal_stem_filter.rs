@@ -0,0 +1,86 @@
+use crate::analysis::{Token, TokenFilter};
+
+/// A [TokenFilter] that reduces English plurals to their singular forms, as Lucene's `EnglishMinimalStemFilter` does:
+/// `queries` becomes `query` and `foxes` becomes `foxe`, while `glass` and `status` are left alone. Tokens marked as
+/// keywords are not stemmed.
+///
+/// Tokens are expected to be lowercase.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct EnglishMinimalStemFilter;
+
+impl EnglishMinimalStemFilter {
+    /// Returns the stem of a lowercase term.
+    pub fn stem(term: &str) -> String {
+        let chars: Vec<char> = term.chars().collect();
+        let len = chars.len();
+        if len < 3 || chars[len - 1] != 's' {
+            return term.to_string();
+        }
+
+        match chars[len - 2] {
+            'u' | 's' => term.to_string(),
+            'e' if len > 3 && chars[len - 3] == 'i' && !matches!(chars[len - 4], 'a' | 'e') => {
+                chars[..len - 3].iter().chain(['y'].iter()).collect()
+            }
+            'e' if matches!(chars[len - 3], 'i' | 'a' | 'o' | 'e') => term.to_string(),
+            _ => chars[..len - 1].iter().collect(),
+        }
+    }
+}
+
+impl TokenFilter for EnglishMinimalStemFilter {
+    fn filter(&self, _field: &str, mut tokens: Vec<Token>) -> Vec<Token> {
+        for token in tokens.iter_mut().filter(|token| !token.keyword) {
+            token.term = Self::stem(&token.term);
+        }
+        tokens
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::analysis::{
+            Analyzer, EnglishMinimalStemFilter, FilteredAnalyzer, KeywordMarkerFilter, SimpleAnalyzer,
+            StemmerOverrideFilter,
+        },
+        pretty_assertions::assert_eq,
+        std::sync::Arc,
+    };
+
+    #[test]
+    fn test_stem() {
+        for (term, stem) in [
+            ("queries", "query"),
+            ("foxes", "foxe"),
+            ("dogs", "dog"),
+            ("glass", "glass"),
+            ("status", "status"),
+            ("toes", "toes"),
+            ("agencies", "agency"),
+            ("keys", "key"),
+            ("is", "is"),
+            ("café", "café"),
+        ] {
+            assert_eq!(EnglishMinimalStemFilter::stem(term), stem, "stem of {term}");
+        }
+    }
+
+    #[test]
+    fn test_filter_chain() {
+        let mut analyzer = FilteredAnalyzer::new(Arc::new(SimpleAnalyzer));
+        analyzer
+            .add_filter(Arc::new(KeywordMarkerFilter::new(["lucens"])))
+            .add_filter(Arc::new(KeywordMarkerFilter::from_pattern("x[0-9]+s").unwrap()))
+            .add_filter(Arc::new(StemmerOverrideFilter::new([("Mice", "mouse"), ("lucens", "lucen")], true).unwrap()))
+            .add_filter(Arc::new(EnglishMinimalStemFilter));
+
+        let tokens = analyzer.analyze("body", "Lucens mice chase X200s and cats");
+        let terms: Vec<_> = tokens.iter().map(|token| (token.term.as_str(), token.keyword)).collect();
+        assert_eq!(
+            terms,
+            vec![("lucens", true), ("mouse", true), ("chase", false), ("x200s", true), ("and", false), ("cat", false),]
+        );
+        assert_eq!((tokens[1].start_offset, tokens[1].end_offset), (7, 11));
+    }
+}
@@ -0,0 +1,77 @@
+use {
+    crate::{
+        analysis::{Token, TokenFilter},
+        util::automaton::{CharacterRunAutomaton, RegExp, DEFAULT_DETERMINIZE_WORK_LIMIT},
+        BoxResult,
+    },
+    std::collections::HashSet,
+};
+
+/// A [TokenFilter] that marks tokens as keywords, so that stemmers later in the chain leave them as they are. This
+/// protects terms such as product and brand names, which stemming would otherwise conflate with other words.
+///
+/// Terms are matched after earlier filters have run, so with a lowercasing analyzer they must be given in lowercase.
+/// This combines Lucene's `SetKeywordMarkerFilter` and `PatternKeywordMarkerFilter`.
+#[derive(Clone, Debug)]
+pub struct KeywordMarkerFilter {
+    keywords: HashSet<String>,
+    pattern: Option<CharacterRunAutomaton>,
+}
+
+impl KeywordMarkerFilter {
+    /// Creates a filter that marks the given terms.
+    pub fn new<S: Into<String>, I: IntoIterator<Item = S>>(keywords: I) -> Self {
+        Self {
+            keywords: keywords.into_iter().map(Into::into).collect(),
+            pattern: None,
+        }
+    }
+
+    /// Creates a filter that marks the terms matched in full by a regular expression in the syntax of [RegExp].
+    pub fn from_pattern(pattern: &str) -> BoxResult<Self> {
+        let automaton = RegExp::new(pattern)?.to_automaton()?;
+        Ok(Self {
+            keywords: HashSet::new(),
+            pattern: Some(CharacterRunAutomaton::new(&automaton, DEFAULT_DETERMINIZE_WORK_LIMIT)?),
+        })
+    }
+
+    /// Indicates whether `term` is marked as a keyword.
+    pub fn is_keyword(&self, term: &str) -> bool {
+        self.keywords.contains(term) || self.pattern.as_ref().is_some_and(|pattern| pattern.run(term))
+    }
+}
+
+impl TokenFilter for KeywordMarkerFilter {
+    fn filter(&self, _field: &str, mut tokens: Vec<Token>) -> Vec<Token> {
+        for token in tokens.iter_mut() {
+            if !token.keyword && self.is_keyword(&token.term) {
+                token.keyword = true;
+            }
+        }
+        tokens
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::analysis::{KeywordMarkerFilter, Token, TokenFilter},
+        pretty_assertions::assert_eq,
+    };
+
+    #[test]
+    fn test_keyword_marker() {
+        let filter = KeywordMarkerFilter::new(["ipad", "lucene"]);
+        assert!(filter.is_keyword("ipad"));
+        assert!(!filter.is_keyword("iPad"));
+
+        let filter = KeywordMarkerFilter::from_pattern("[a-z]+[0-9]+").unwrap();
+        assert!(filter.is_keyword("gpt4"));
+        assert!(!filter.is_keyword("4gpt"));
+        assert!(KeywordMarkerFilter::from_pattern("[a-").is_err());
+
+        let tokens = filter.filter("body", vec![Token::new("rtx4090", 0, 7), Token::new("cards", 8, 13)]);
+        assert_eq!(tokens.iter().map(|token| token.keyword).collect::<Vec<_>>(), vec![true, false]);
+    }
+}
@@ -0,0 +1,82 @@
+use {
+    crate::analysis::{Analyzer, SimpleAnalyzer, Token},
+    std::{collections::HashMap, sync::Arc},
+};
+
+/// An [Analyzer] that delegates to a different analyzer for each field, falling back to a default for fields without
+/// their own, as in Lucene's `PerFieldAnalyzerWrapper`. This lets, for example, a body field be split into words
+/// while a title field is also split into CJK bigrams.
+#[derive(Clone, Debug)]
+pub struct PerFieldAnalyzerWrapper {
+    default: Arc<dyn Analyzer>,
+    fields: HashMap<String, Arc<dyn Analyzer>>,
+}
+
+impl Default for PerFieldAnalyzerWrapper {
+    fn default() -> Self {
+        Self::new(Arc::new(SimpleAnalyzer))
+    }
+}
+
+impl PerFieldAnalyzerWrapper {
+    /// Creates a wrapper that uses `default` for every field.
+    pub fn new(default: Arc<dyn Analyzer>) -> Self {
+        Self {
+            default,
+            fields: HashMap::new(),
+        }
+    }
+
+    /// Sets the analyzer for a field.
+    pub fn set(&mut self, field: &str, analyzer: Arc<dyn Analyzer>) -> &mut Self {
+        self.fields.insert(field.to_string(), analyzer);
+        self
+    }
+
+    /// Returns the analyzer for a field.
+    #[inline]
+    pub fn get(&self, field: &str) -> &Arc<dyn Analyzer> {
+        self.fields.get(field).unwrap_or(&self.default)
+    }
+
+    /// Returns the analyzer for fields without their own.
+    #[inline]
+    pub fn default_analyzer(&self) -> &Arc<dyn Analyzer> {
+        &self.default
+    }
+}
+
+impl Analyzer for PerFieldAnalyzerWrapper {
+    fn analyze(&self, field: &str, text: &str) -> Vec<Token> {
+        self.get(field).analyze(field, text)
+    }
+
+    fn position_increment_gap(&self, field: &str) -> u32 {
+        self.get(field).position_increment_gap(field)
+    }
+
+    fn offset_gap(&self, field: &str) -> u32 {
+        self.get(field).offset_gap(field)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::analysis::{Analyzer, CJKBigramAnalyzer, PerFieldAnalyzerWrapper, Token},
+        pretty_assertions::assert_eq,
+        std::sync::Arc,
+    };
+
+    #[test]
+    fn test_per_field() {
+        let mut analyzer = PerFieldAnalyzerWrapper::default();
+        analyzer.set("title", Arc::new(CJKBigramAnalyzer));
+        assert_eq!(
+            analyzer.analyze("body", "Hello World"),
+            vec![Token::new("hello", 0, 5), Token::new("world", 6, 11)]
+        );
+        assert_eq!(analyzer.analyze("title", "東京都").len(), 2);
+        assert_eq!(analyzer.analyze("body", "東京都").len(), 1);
+    }
+}
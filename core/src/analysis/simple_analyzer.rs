@@ -0,0 +1,49 @@
+use crate::analysis::{Analyzer, Token};
+
+/// An [Analyzer] that splits text into runs of letters and digits and lowercases them.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SimpleAnalyzer;
+
+impl Analyzer for SimpleAnalyzer {
+    fn analyze(&self, _field: &str, text: &str) -> Vec<Token> {
+        let mut tokens = Vec::new();
+        let mut start = None;
+
+        for (offset, c) in text.char_indices().chain(std::iter::once((text.len(), ' '))) {
+            match (start, c.is_alphanumeric()) {
+                (None, true) => start = Some(offset),
+                (Some(s), false) => {
+                    tokens.push(Token::new(text[s..offset].to_lowercase(), s as u32, offset as u32));
+                    start = None;
+                }
+                _ => (),
+            }
+        }
+
+        tokens
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::analysis::{Analyzer, SimpleAnalyzer, Token},
+        pretty_assertions::assert_eq,
+    };
+
+    #[test]
+    fn test_analyze() {
+        let tokens = SimpleAnalyzer.analyze("body", "The quick-brown Fox, née 42!");
+        assert_eq!(
+            tokens,
+            vec![
+                Token::new("the", 0, 3),
+                Token::new("quick", 4, 9),
+                Token::new("brown", 10, 15),
+                Token::new("fox", 16, 19),
+                Token::new("née", 21, 25),
+                Token::new("42", 26, 28),
+            ]
+        );
+    }
+}
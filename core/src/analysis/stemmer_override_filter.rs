@@ -0,0 +1,113 @@
+use {
+    crate::{
+        analysis::{Token, TokenFilter},
+        util::{Fst, FstBuilder},
+        BoxResult,
+    },
+    std::collections::BTreeMap,
+};
+
+/// A [TokenFilter] that replaces terms with custom stems and marks them as keywords, so that stemmers later in the
+/// chain leave them as they are, as Lucene's `StemmerOverrideFilter` does. This fixes words a stemmer gets wrong,
+/// such as `mice` to `mouse`, and keeps brand names from being stemmed at all.
+///
+/// The terms are held in an [Fst] that maps each term to the index of its stem, so large dictionaries stay compact.
+/// Tokens already marked as keywords are left alone.
+#[derive(Clone, Debug)]
+pub struct StemmerOverrideFilter {
+    fst: Fst,
+    stems: Vec<String>,
+    ignore_case: bool,
+}
+
+impl StemmerOverrideFilter {
+    /// Creates a filter that replaces each term of `overrides` with its stem. If a term is given more than once, its
+    /// first stem is used. If `ignore_case` is true, terms match regardless of case.
+    pub fn new<S: Into<String>, I: IntoIterator<Item = (S, S)>>(overrides: I, ignore_case: bool) -> BoxResult<Self> {
+        let mut sorted = BTreeMap::new();
+        for (term, stem) in overrides {
+            let term = term.into();
+            let term = if ignore_case {
+                term.to_lowercase()
+            } else {
+                term
+            };
+            sorted.entry(term).or_insert_with(|| stem.into());
+        }
+
+        let mut builder = FstBuilder::new();
+        let mut stems = Vec::with_capacity(sorted.len());
+        for (term, stem) in sorted {
+            builder.add(term.as_bytes(), stems.len() as u64)?;
+            stems.push(stem);
+        }
+
+        Ok(Self {
+            fst: builder.finish(),
+            stems,
+            ignore_case,
+        })
+    }
+
+    /// Returns the number of terms with a custom stem.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.stems.len()
+    }
+
+    /// Indicates whether there are no custom stems.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.stems.is_empty()
+    }
+
+    /// Returns the custom stem of `term`, if it has one.
+    pub fn get(&self, term: &str) -> Option<&str> {
+        let ord = if self.ignore_case {
+            self.fst.get(term.to_lowercase().as_bytes())
+        } else {
+            self.fst.get(term.as_bytes())
+        };
+        ord.map(|ord| self.stems[ord as usize].as_str())
+    }
+}
+
+impl TokenFilter for StemmerOverrideFilter {
+    fn filter(&self, _field: &str, mut tokens: Vec<Token>) -> Vec<Token> {
+        for token in tokens.iter_mut().filter(|token| !token.keyword) {
+            if let Some(stem) = self.get(&token.term) {
+                token.term = stem.to_string();
+                token.keyword = true;
+            }
+        }
+        tokens
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::analysis::{StemmerOverrideFilter, Token, TokenFilter},
+        pretty_assertions::assert_eq,
+    };
+
+    #[test]
+    fn test_stemmer_override() {
+        let filter =
+            StemmerOverrideFilter::new([("mice", "mouse"), ("geese", "goose"), ("mice", "rat")], false).unwrap();
+        assert_eq!(filter.len(), 2);
+        assert_eq!(filter.get("mice"), Some("mouse"));
+        assert_eq!(filter.get("Mice"), None);
+        assert_eq!(filter.get("mic"), None);
+
+        let filter = StemmerOverrideFilter::new([("Mice", "mouse")], true).unwrap();
+        assert_eq!(filter.get("MICE"), Some("mouse"));
+
+        let mut protected = Token::new("mice", 10, 14);
+        protected.keyword = true;
+        let tokens = filter.filter("body", vec![Token::new("mice", 0, 4), Token::new("run", 5, 8), protected]);
+        let terms: Vec<_> = tokens.iter().map(|token| (token.term.as_str(), token.keyword)).collect();
+        assert_eq!(terms, vec![("mouse", true), ("run", false), ("mice", true)]);
+        assert!(StemmerOverrideFilter::new(Vec::<(&str, &str)>::new(), false).unwrap().is_empty());
+    }
+}
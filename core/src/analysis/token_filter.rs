@@ -0,0 +1,61 @@
+use {
+    crate::analysis::{Analyzer, Token},
+    std::{fmt::Debug, sync::Arc},
+};
+
+/// Transforms the tokens produced by an [Analyzer], such as by stemming them or marking some as keywords. Filters are
+/// chained with a [FilteredAnalyzer].
+pub trait TokenFilter: Debug + Send + Sync {
+    /// Filters the tokens of a field value.
+    fn filter(&self, field: &str, tokens: Vec<Token>) -> Vec<Token>;
+}
+
+/// An [Analyzer] that passes the tokens of another analyzer through a chain of [TokenFilter]s, in the order they
+/// were added, as Lucene's `CustomAnalyzer` does.
+#[derive(Clone, Debug)]
+pub struct FilteredAnalyzer {
+    analyzer: Arc<dyn Analyzer>,
+    filters: Vec<Arc<dyn TokenFilter>>,
+}
+
+impl FilteredAnalyzer {
+    /// Creates an analyzer that returns the tokens of `analyzer` until filters are added.
+    pub fn new(analyzer: Arc<dyn Analyzer>) -> Self {
+        Self {
+            analyzer,
+            filters: Vec::new(),
+        }
+    }
+
+    /// Adds a filter to the end of the chain.
+    pub fn add_filter(&mut self, filter: Arc<dyn TokenFilter>) -> &mut Self {
+        self.filters.push(filter);
+        self
+    }
+
+    /// Returns the analyzer whose tokens are filtered.
+    #[inline]
+    pub fn analyzer(&self) -> &Arc<dyn Analyzer> {
+        &self.analyzer
+    }
+
+    /// Returns the filters, in the order they are applied.
+    #[inline]
+    pub fn filters(&self) -> &[Arc<dyn TokenFilter>] {
+        &self.filters
+    }
+}
+
+impl Analyzer for FilteredAnalyzer {
+    fn analyze(&self, field: &str, text: &str) -> Vec<Token> {
+        self.filters.iter().fold(self.analyzer.analyze(field, text), |tokens, filter| filter.filter(field, tokens))
+    }
+
+    fn position_increment_gap(&self, field: &str) -> u32 {
+        self.analyzer.position_increment_gap(field)
+    }
+
+    fn offset_gap(&self, field: &str) -> u32 {
+        self.analyzer.offset_gap(field)
+    }
+}
@@ -0,0 +1,3 @@
+mod hit_exporter;
+
+pub use hit_exporter::*;
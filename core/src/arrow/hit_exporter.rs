@@ -0,0 +1,364 @@
+use {
+    crate::{
+        document::{Document, Field},
+        index::{sub_index, BinaryDocValues, LeafReaderContext, NumericDocValues},
+        search::{IndexSearcher, ScoreDoc},
+        BoxResult, LuceneError,
+    },
+    arrow_array::{
+        builder::{BinaryBuilder, Int64Builder, StringBuilder},
+        ArrayRef, Float32Array, Int64Array, RecordBatch, UInt32Array,
+    },
+    arrow_schema::{DataType, Field as ArrowField, Schema, SchemaRef},
+    std::sync::Arc,
+};
+
+/// The name of the column holding the global document id of each hit.
+pub const DOC_COLUMN: &str = "doc";
+
+/// The name of the column holding the score of each hit.
+pub const SCORE_COLUMN: &str = "score";
+
+/// The number of hits in each record batch by default.
+pub const DEFAULT_EXPORT_BATCH_SIZE: usize = 8192;
+
+/// A value materialized for each hit, in addition to its document id and score. Hits without the value get a null.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum HitColumn {
+    /// The numeric doc values of a field, as an `Int64` column.
+    NumericDocValues(String),
+
+    /// The binary doc values of a field, as a `Binary` column.
+    BinaryDocValues(String),
+
+    /// The first stored text value of a field, as a `Utf8` column.
+    StoredText(String),
+
+    /// The first stored integer value of a field, as an `Int64` column.
+    StoredLong(String),
+}
+
+impl HitColumn {
+    /// Returns the name of the field, which is also the name of the column.
+    pub fn field(&self) -> &str {
+        match self {
+            Self::NumericDocValues(field)
+            | Self::BinaryDocValues(field)
+            | Self::StoredText(field)
+            | Self::StoredLong(field) => field,
+        }
+    }
+
+    fn data_type(&self) -> DataType {
+        match self {
+            Self::NumericDocValues(_) | Self::StoredLong(_) => DataType::Int64,
+            Self::BinaryDocValues(_) => DataType::Binary,
+            Self::StoredText(_) => DataType::Utf8,
+        }
+    }
+
+    fn is_stored(&self) -> bool {
+        matches!(self, Self::StoredText(_) | Self::StoredLong(_))
+    }
+}
+
+/// Materializes search hits into Apache Arrow record batches, and optionally streams them to Parquet, for analytics
+/// over search results.
+///
+/// Each batch has a non-null `doc` (`UInt32`) and `score` (`Float32`) column, followed by one nullable column per
+/// [HitColumn], in order. Rows keep the order of the hits. Doc values are read segment by segment in document order,
+/// and stored fields are only loaded when a stored column is requested.
+#[derive(Clone, Debug)]
+pub struct HitExporter {
+    columns: Vec<HitColumn>,
+    schema: SchemaRef,
+    batch_size: usize,
+}
+
+impl HitExporter {
+    /// Creates an exporter for the given columns. Column names must be distinct and must not be `doc` or `score`.
+    pub fn new(columns: Vec<HitColumn>) -> BoxResult<Self> {
+        let mut fields = vec![
+            ArrowField::new(DOC_COLUMN, DataType::UInt32, false),
+            ArrowField::new(SCORE_COLUMN, DataType::Float32, false),
+        ];
+        for column in &columns {
+            if fields.iter().any(|field| field.name() == column.field()) {
+                return Err(LuceneError::InvalidArgument(format!("duplicate column {}", column.field())).into());
+            }
+            fields.push(ArrowField::new(column.field(), column.data_type(), true));
+        }
+
+        Ok(Self {
+            columns,
+            schema: Arc::new(Schema::new(fields)),
+            batch_size: DEFAULT_EXPORT_BATCH_SIZE,
+        })
+    }
+
+    /// Returns the columns exported in addition to the document id and score.
+    #[inline]
+    pub fn columns(&self) -> &[HitColumn] {
+        &self.columns
+    }
+
+    /// Returns the schema of the record batches.
+    #[inline]
+    pub fn schema(&self) -> &SchemaRef {
+        &self.schema
+    }
+
+    /// Returns the maximum number of hits in each record batch.
+    #[inline]
+    pub fn batch_size(&self) -> usize {
+        self.batch_size
+    }
+
+    /// Sets the maximum number of hits in each record batch.
+    pub fn set_batch_size(&mut self, batch_size: usize) -> BoxResult<&mut Self> {
+        if batch_size == 0 {
+            return Err(LuceneError::InvalidArgument("batch size must be positive".to_string()).into());
+        }
+
+        self.batch_size = batch_size;
+        Ok(self)
+    }
+
+    /// Materializes hits of the searcher's index into record batches of at most [HitExporter::batch_size] rows.
+    pub fn export(&self, searcher: &IndexSearcher, hits: &[ScoreDoc]) -> BoxResult<Vec<RecordBatch>> {
+        hits.chunks(self.batch_size).map(|hits| self.export_batch(searcher, hits)).collect()
+    }
+
+    /// Materializes hits of the searcher's index into a single record batch.
+    pub fn export_batch(&self, searcher: &IndexSearcher, hits: &[ScoreDoc]) -> BoxResult<RecordBatch> {
+        let mut arrays: Vec<ArrayRef> = Vec::with_capacity(self.columns.len() + 2);
+        arrays.push(Arc::new(UInt32Array::from_iter_values(hits.iter().map(|hit| hit.doc))));
+        arrays.push(Arc::new(Float32Array::from_iter_values(hits.iter().map(|hit| hit.score))));
+
+        let documents = if self.columns.iter().any(HitColumn::is_stored) {
+            hits.iter().map(|hit| searcher.doc(hit.doc)).collect::<BoxResult<Vec<_>>>()?
+        } else {
+            Vec::new()
+        };
+
+        // Doc values iterators only move forward, so they're visited in document order.
+        let mut order: Vec<usize> = (0..hits.len()).collect();
+        order.sort_by_key(|&i| hits[i].doc);
+
+        for column in &self.columns {
+            let array: ArrayRef = match column {
+                HitColumn::NumericDocValues(field) => {
+                    let values = read_doc_values(
+                        searcher.leaves(),
+                        hits,
+                        &order,
+                        |leaf| leaf.reader().numeric_doc_values(field),
+                        |doc_values: &mut Box<dyn NumericDocValues>, doc| {
+                            Ok(if doc_values.advance_exact(doc)? {
+                                Some(doc_values.long_value()?)
+                            } else {
+                                None
+                            })
+                        },
+                    )?;
+                    Arc::new(Int64Array::from(values))
+                }
+                HitColumn::BinaryDocValues(field) => {
+                    let values = read_doc_values(
+                        searcher.leaves(),
+                        hits,
+                        &order,
+                        |leaf| leaf.reader().binary_doc_values(field),
+                        |doc_values: &mut Box<dyn BinaryDocValues>, doc| {
+                            Ok(if doc_values.advance_exact(doc)? {
+                                Some(doc_values.binary_value()?.to_vec())
+                            } else {
+                                None
+                            })
+                        },
+                    )?;
+                    let mut builder = BinaryBuilder::new();
+                    values.iter().for_each(|value| builder.append_option(value.as_deref()));
+                    Arc::new(builder.finish())
+                }
+                HitColumn::StoredText(field) => {
+                    let mut builder = StringBuilder::new();
+                    for document in &documents {
+                        builder.append_option(stored_value(document, field, |f| f.string_value()));
+                    }
+                    Arc::new(builder.finish())
+                }
+                HitColumn::StoredLong(field) => {
+                    let mut builder = Int64Builder::new();
+                    for document in &documents {
+                        builder.append_option(stored_value(document, field, |f| f.numeric_value()));
+                    }
+                    Arc::new(builder.finish())
+                }
+            };
+            arrays.push(array);
+        }
+
+        Ok(RecordBatch::try_new(self.schema.clone(), arrays)?)
+    }
+
+    /// Streams hits of the searcher's index to a Parquet file, one row group per batch, and returns the writer.
+    /// `properties` configures the file, such as its compression; the defaults are used if it's `None`.
+    #[cfg(feature = "parquet")]
+    pub fn write_parquet<W: std::io::Write + Send>(
+        &self,
+        searcher: &IndexSearcher,
+        hits: &[ScoreDoc],
+        writer: W,
+        properties: Option<parquet::file::properties::WriterProperties>,
+    ) -> BoxResult<W> {
+        let mut writer = parquet::arrow::ArrowWriter::try_new(writer, self.schema.clone(), properties)?;
+        for hits in hits.chunks(self.batch_size) {
+            writer.write(&self.export_batch(searcher, hits)?)?;
+            writer.flush()?;
+        }
+        Ok(writer.into_inner()?)
+    }
+}
+
+/// Reads a doc values column for the hits, visiting them in the given document order, and returns the values in the
+/// order of the hits. A new iterator is opened for each segment, and repeated hits on a document share a value.
+fn read_doc_values<D, T: Clone>(
+    leaves: &[LeafReaderContext],
+    hits: &[ScoreDoc],
+    order: &[usize],
+    open: impl Fn(&LeafReaderContext) -> BoxResult<Option<D>>,
+    read: impl Fn(&mut D, u32) -> BoxResult<Option<T>>,
+) -> BoxResult<Vec<Option<T>>> {
+    let mut values = vec![None; hits.len()];
+    let mut current: Option<(usize, Option<D>)> = None;
+    let mut last: Option<(u32, Option<T>)> = None;
+
+    for &i in order {
+        let doc = hits[i].doc;
+        if let Some((last_doc, value)) = &last {
+            if *last_doc == doc {
+                values[i] = value.clone();
+                continue;
+            }
+        }
+
+        let leaf_index = sub_index(doc, leaves);
+        if current.as_ref().is_none_or(|(index, _)| *index != leaf_index) {
+            current = Some((leaf_index, open(&leaves[leaf_index])?));
+        }
+
+        let leaf = &leaves[leaf_index];
+        let value = match current.as_mut().and_then(|(_, doc_values)| doc_values.as_mut()) {
+            Some(doc_values) => read(doc_values, doc - leaf.doc_base())?,
+            None => None,
+        };
+        values[i] = value.clone();
+        last = Some((doc, value));
+    }
+
+    Ok(values)
+}
+
+/// Returns a value of the first stored field with the given name that has one.
+fn stored_value<'a, T>(document: &'a Document, field: &str, value: impl Fn(&'a Field) -> Option<T>) -> Option<T> {
+    document.fields().iter().filter(|f| f.name() == field).find_map(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{
+            arrow::{HitColumn, HitExporter},
+            document::{Document, Field, Store},
+            search::{test_util, IndexSearcher, ScoreDoc},
+        },
+        arrow_array::{Array, BinaryArray, Float32Array, Int64Array, StringArray, UInt32Array},
+        pretty_assertions::assert_eq,
+    };
+
+    fn searcher() -> IndexSearcher {
+        test_util::searcher([vec![("a", Some(10)), ("b", None)], vec![("c", Some(30))]].map(|docs| {
+            docs.into_iter().map(|(title, year)| {
+                let mut document = Document::new();
+                document.add(Field::text("title", title, Store::Yes));
+                document.add(Field::binary_doc_values("tag", title.as_bytes()));
+                if let Some(year) = year {
+                    document.add(Field::numeric_doc_values("year", year));
+                    document.add(Field::stored("year", year));
+                }
+                document
+            })
+        }))
+    }
+
+    #[test]
+    fn test_export() {
+        let searcher = searcher();
+        let mut exporter = HitExporter::new(vec![
+            HitColumn::NumericDocValues("year".to_string()),
+            HitColumn::BinaryDocValues("tag".to_string()),
+            HitColumn::StoredText("title".to_string()),
+            HitColumn::StoredText("missing".to_string()),
+        ])
+        .unwrap();
+
+        // Hits out of document order, spanning both segments, with a repeated document.
+        let hits: Vec<_> =
+            [(2, 3.0), (0, 2.0), (1, 1.5), (0, 1.0)].map(|(doc, score)| ScoreDoc::new(doc, score)).into();
+        let batches = exporter.export(&searcher, &hits).unwrap();
+        assert_eq!(batches.len(), 1);
+
+        let batch = &batches[0];
+        assert_eq!(batch.column(0).as_any().downcast_ref::<UInt32Array>().unwrap().values().to_vec(), vec![2, 0, 1, 0]);
+        assert_eq!(
+            batch.column(1).as_any().downcast_ref::<Float32Array>().unwrap().values().to_vec(),
+            vec![3.0, 2.0, 1.5, 1.0]
+        );
+        assert_eq!(
+            batch.column(2).as_any().downcast_ref::<Int64Array>().unwrap().iter().collect::<Vec<_>>(),
+            vec![Some(30), Some(10), None, Some(10)]
+        );
+        assert_eq!(
+            batch.column(3).as_any().downcast_ref::<BinaryArray>().unwrap().iter().collect::<Vec<_>>(),
+            vec![Some(&b"c"[..]), Some(b"a"), Some(b"b"), Some(b"a")]
+        );
+        assert_eq!(
+            batch.column(4).as_any().downcast_ref::<StringArray>().unwrap().iter().collect::<Vec<_>>(),
+            vec![Some("c"), Some("a"), Some("b"), Some("a")]
+        );
+        assert_eq!(batch.column(5).null_count(), 4);
+
+        exporter.set_batch_size(3).unwrap();
+        let batches = exporter.export(&searcher, &hits).unwrap();
+        assert_eq!(batches.iter().map(|batch| batch.num_rows()).collect::<Vec<_>>(), vec![3, 1]);
+
+        assert!(HitExporter::new(vec![HitColumn::StoredText("score".to_string())]).is_err());
+        assert!(exporter.set_batch_size(0).is_err());
+    }
+
+    #[cfg(feature = "parquet")]
+    #[test]
+    fn test_write_parquet() {
+        use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+        let searcher = searcher();
+        let mut exporter = HitExporter::new(vec![HitColumn::StoredLong("year".to_string())]).unwrap();
+        exporter.set_batch_size(2).unwrap();
+        let hits: Vec<_> = (0..3).map(|doc| ScoreDoc::new(doc, 1.0)).collect();
+        let file = exporter.write_parquet(&searcher, &hits, Vec::new(), None).unwrap();
+
+        let reader = ParquetRecordBatchReaderBuilder::try_new(bytes::Bytes::from(file)).unwrap();
+        assert_eq!(reader.metadata().num_row_groups(), 2);
+        assert_eq!(reader.schema(), exporter.schema());
+        let years: Vec<_> = reader
+            .build()
+            .unwrap()
+            .flat_map(|batch| {
+                let batch = batch.unwrap();
+                batch.column(2).as_any().downcast_ref::<Int64Array>().unwrap().iter().collect::<Vec<_>>()
+            })
+            .collect();
+        assert_eq!(years, vec![Some(10), None, Some(30)]);
+    }
+}
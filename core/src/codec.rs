@@ -1,11 +1,20 @@
+mod codec_util;
+mod compound;
+mod live_docs;
 mod lucene_90;
 mod lucene_95;
+mod norms;
+mod registry;
 mod segment_info;
-pub use {lucene_90::*, lucene_95::*, segment_info::*};
+mod term_vectors;
+pub use {
+    codec_util::*, compound::*, live_docs::*, lucene_90::*, lucene_95::*, norms::*, registry::*, segment_info::*,
+    term_vectors::*,
+};
 
 use {
     crate::{
-        codec::{Lucene95Codec, SegmentInfoFormat},
+        codec::{LiveDocsFormat, NormsFormat, SegmentInfoFormat, TermVectorsFormat},
         io::{EncodingReadExt, EncodingWriteExt},
         BoxResult, LuceneError,
     },
@@ -13,22 +22,6 @@ use {
     tokio::io::{AsyncRead, AsyncReadExt},
 };
 
-/// Create a new instance of a codec given its name. If the codec is not known, `None` is returned.
-///
-/// Unlike the Lucene Java implemention, the Rust implementation does not have the ability to dynamically
-/// load codecs. Codecs are hard-coded in the [get_codec] function.
-///
-/// FIXME: This function currently hard codes the available codecs. In the future, it should allow for dynamically
-/// loading codecs.
-///
-/// FIXME: This function currently only handles the `"Lucene95" codec.
-pub fn get_codec(name: &str) -> Result<Box<dyn Codec>, LuceneError> {
-    match name {
-        "Lucene95" => Ok(Box::new(Lucene95Codec::new())),
-        _ => Err(LuceneError::UnknownCodec(name.to_string())),
-    }
-}
-
 /// Encodes and decodes an inverted segment index.
 pub trait Codec: Debug {
     /// Returns the Codec's name.
@@ -36,6 +29,18 @@ pub trait Codec: Debug {
 
     /// Encodes/decodes segment info file.
     fn segment_info_format(&self) -> Box<dyn SegmentInfoFormat>;
+
+    /// Encodes/decodes live docs files.
+    fn live_docs_format(&self) -> Box<dyn LiveDocsFormat>;
+
+    /// Encodes/decodes norms files.
+    fn norms_format(&self) -> Box<dyn NormsFormat>;
+
+    /// Encodes/decodes term vectors files.
+    fn term_vectors_format(&self) -> Box<dyn TermVectorsFormat>;
+
+    /// Encodes/decodes compound files.
+    fn compound_format(&self) -> Box<dyn CompoundFormat>;
 }
 
 /// Constant to identify the start of a codec header.
@@ -103,10 +108,11 @@ impl CodecHeader {
         }
 
         let version = r.read_u32().await?;
-        if version < min_version || version > max_version {
-            return Err(
-                LuceneError::UnsupportedCodecVersion(codec.to_string(), version, min_version, max_version).into()
-            );
+        if version < min_version {
+            return Err(LuceneError::IndexFormatTooOld(codec.to_string(), version, min_version, max_version).into());
+        }
+        if version > max_version {
+            return Err(LuceneError::IndexFormatTooNew(codec.to_string(), version, min_version, max_version).into());
         }
 
         Ok(Self {
@@ -123,9 +129,10 @@ impl CodecHeader {
     ) -> BoxResult<()> {
         let suffix = r.read_short_string().await?;
         if suffix != expected {
-            return Err(LuceneError::CorruptIndex(format!(
-                "Codec header suffix contained invalid codec name: got {suffix:?}, expected {expected:?}"
-            ))
+            return Err(LuceneError::CorruptIndex(
+                format!("Codec header suffix contained invalid codec name: got {suffix:?}, expected {expected:?}")
+                    .into(),
+            )
             .into());
         }
 
@@ -0,0 +1,288 @@
+//! Reading and writing the headers and footers that frame every Lucene index file, as Lucene's `CodecUtil` does.
+//!
+//! Files start with a [CodecHeader](crate::codec::CodecHeader), or an [IndexHeader](crate::index::IndexHeader) that
+//! also records the id of the segment or commit and a suffix, and end with a footer holding a CRC-32 checksum of
+//! everything before it. Write files through a [Crc32Writer] so that [write_footer] can append the checksum, and read
+//! them through a [Crc32Reader] so that [check_footer] can verify it.
+
+use {
+    crate::{
+        codec::FOOTER_MAGIC,
+        io::{Crc32Reader, Crc32Writer, RandomAccessInput},
+        BoxError, BoxResult, CorruptIndexError, LuceneError, ID_LENGTH,
+    },
+    crc32fast::Hasher,
+    tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+};
+
+/// The length of a codec footer: [FOOTER_MAGIC], the checksum algorithm id (always 0, for CRC-32), and the checksum.
+pub const FOOTER_LENGTH: usize = 16;
+
+/// The number of bytes read at a time by [checksum_entire_file].
+const VERIFY_BUFFER_SIZE: usize = 8192;
+
+/// Returns the length of the codec header written for `codec`: the magic, the name and the version.
+pub fn header_length(codec: &str) -> usize {
+    // Codec names are at most 127 bytes, so their length is written in a single byte.
+    4 + 1 + codec.len() + 4
+}
+
+/// Returns the length of the index header written for `codec` and `suffix`: the codec header, the id and the suffix.
+pub fn index_header_length(codec: &str, suffix: &str) -> usize {
+    header_length(codec) + ID_LENGTH + 1 + suffix.len()
+}
+
+/// Writes a codec footer holding the checksum of everything written through `w` so far.
+///
+/// CodecFooter --> Magic + AlgorithmId + Checksum
+///
+/// * Magic (4 bytes): This identifies the start of the footer and is always [FOOTER_MAGIC].
+/// * AlgorithmId (BE i32): The checksum algorithm; always 0, for CRC-32.
+/// * Checksum (BE u64): The CRC-32 of the file up to, but not including, the checksum.
+pub async fn write_footer<W: AsyncWrite + Unpin>(w: &mut Crc32Writer<W>) -> BoxResult<()> {
+    w.write_all(&FOOTER_MAGIC).await?;
+    w.write_i32(0).await?;
+    let checksum = w.digest() as u64;
+    w.write_u64(checksum).await?;
+    Ok(())
+}
+
+/// Reads and validates the codec footer at the end of a file, returning its checksum. Every byte of the file before
+/// the footer must have been read through `r`.
+///
+/// This fails with [LuceneError::CorruptIndex] if the footer is malformed, if the checksum doesn't match the data,
+/// or if the file continues past the footer. The error reports the expected and actual values, but not the file;
+/// callers add that with [crate::locate_corruption].
+pub async fn check_footer<R: AsyncRead + Unpin>(r: &mut Crc32Reader<R>) -> BoxResult<u64> {
+    let mut footer = [0; FOOTER_LENGTH];
+    r.read_exact(&mut footer[..8]).await?;
+
+    // The checksum covers the magic and algorithm id, but not itself.
+    let digest = r.digest();
+    let start = r.position() - 8;
+    r.read_exact(&mut footer[8..]).await?;
+    let checksum = validate_footer(&footer, digest, start)?;
+
+    if r.read(&mut [0]).await? != 0 {
+        return Err(corrupt(format!("data after codec footer, which starts at {start}"), r.position()));
+    }
+
+    Ok(checksum)
+}
+
+/// Returns the checksum recorded in the footer of a file, without verifying it against the file's data. This is
+/// cheap, as only the footer is read, so it suits comparing files, such as when replicating an index.
+///
+/// This fails with [LuceneError::CorruptIndex] if the file is too short to hold a footer or the footer is malformed.
+pub fn retrieve_checksum<I: RandomAccessInput + ?Sized>(input: &I) -> BoxResult<u64> {
+    let length = input.length();
+    if length < FOOTER_LENGTH as u64 {
+        return Err(corrupt(format!("file is too short to hold a codec footer: {length} bytes"), length));
+    }
+
+    let start = length - FOOTER_LENGTH as u64;
+    let mut footer = [0; FOOTER_LENGTH];
+    input.read_bytes_at(start, &mut footer)?;
+    validate_footer_structure(&footer, start)
+}
+
+/// Reads a file to its end and validates its codec footer, returning its checksum. This detects any corruption of
+/// the file's data, such as bit rot, that has changed its checksum. Errors are reported as for [check_footer].
+pub async fn checksum_entire_file<R: AsyncRead + Unpin + ?Sized>(r: &mut R) -> BoxResult<u64> {
+    let mut digest = Hasher::new();
+    let mut buffer = vec![0; VERIFY_BUFFER_SIZE + FOOTER_LENGTH];
+
+    // The last FOOTER_LENGTH bytes read so far are kept back, as they may be the footer.
+    let mut len = 0;
+    let mut position = 0;
+    loop {
+        let n = r.read(&mut buffer[len..]).await?;
+        if n == 0 {
+            break;
+        }
+
+        len += n;
+        if len > FOOTER_LENGTH {
+            let hashed = len - FOOTER_LENGTH;
+            digest.update(&buffer[..hashed]);
+            buffer.copy_within(hashed..len, 0);
+            position += hashed as u64;
+            len = FOOTER_LENGTH;
+        }
+    }
+
+    if len < FOOTER_LENGTH {
+        return Err(corrupt(
+            format!("file is too short to hold a codec footer: {} bytes", position + len as u64),
+            position + len as u64,
+        ));
+    }
+
+    let footer: [u8; FOOTER_LENGTH] = buffer[..FOOTER_LENGTH].try_into().unwrap();
+    digest.update(&footer[..8]);
+    validate_footer(&footer, digest.finalize(), position)
+}
+
+/// Validates a footer that starts at `start`, given the CRC-32 of the file up to its checksum.
+fn validate_footer(footer: &[u8; FOOTER_LENGTH], digest: u32, start: u64) -> BoxResult<u64> {
+    let checksum = validate_footer_structure(footer, start)?;
+    if checksum != digest as u64 {
+        return Err(corrupt(
+            format!("checksum failed (hardware problem?): expected={checksum:#x} actual={digest:#x}"),
+            start + 8,
+        ));
+    }
+
+    Ok(checksum)
+}
+
+/// Validates the magic, algorithm id and checksum range of a footer that starts at `start`, returning its checksum.
+fn validate_footer_structure(footer: &[u8; FOOTER_LENGTH], start: u64) -> BoxResult<u64> {
+    let magic: [u8; 4] = footer[..4].try_into().unwrap();
+    if magic != FOOTER_MAGIC {
+        return Err(corrupt(
+            format!("codec footer mismatch: actual footer={magic:#x?} vs expected footer={FOOTER_MAGIC:#x?}"),
+            start,
+        ));
+    }
+
+    let algorithm_id = i32::from_be_bytes(footer[4..8].try_into().unwrap());
+    if algorithm_id != 0 {
+        return Err(corrupt(format!("codec footer mismatch: unknown algorithm id {algorithm_id}"), start + 4));
+    }
+
+    let checksum = u64::from_be_bytes(footer[8..].try_into().unwrap());
+    if checksum >> 32 != 0 {
+        return Err(corrupt(format!("illegal CRC-32 checksum: {checksum:#x}"), start + 8));
+    }
+
+    Ok(checksum)
+}
+
+fn corrupt(message: String, position: u64) -> BoxError {
+    LuceneError::CorruptIndex(CorruptIndexError::new(message).with_position(position)).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{
+            codec::{
+                check_footer, checksum_entire_file, header_length, index_header_length, retrieve_checksum,
+                write_footer, FOOTER_LENGTH, FOOTER_MAGIC,
+            },
+            index::IndexHeader,
+            io::{Crc32Reader, Crc32Writer},
+            locate_corruption, Id, LuceneError,
+        },
+        pretty_assertions::assert_eq,
+        std::path::PathBuf,
+        tokio::io::{AsyncReadExt, AsyncWriteExt},
+    };
+
+    /// Returns `data` followed by a valid footer.
+    fn with_footer(data: &[u8]) -> Vec<u8> {
+        let mut file = data.to_vec();
+        file.extend_from_slice(&FOOTER_MAGIC);
+        file.extend_from_slice(&0_i32.to_be_bytes());
+        let checksum = crc32fast::hash(&file);
+        file.extend_from_slice(&(checksum as u64).to_be_bytes());
+        file
+    }
+
+    async fn check(file: &[u8], data_len: usize) -> Result<u64, String> {
+        let mut r = Crc32Reader::new(file);
+        r.read_exact(&mut vec![0; data_len]).await.unwrap();
+        check_footer(&mut r).await.map_err(|e| locate_corruption(e, "_0.si", r.position()).to_string())
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_check_footer() {
+        let file = with_footer(b"segment data");
+        let checksum = crc32fast::hash(&file[..file.len() - 8]) as u64;
+        assert_eq!(check(&file, 12).await, Ok(checksum));
+        assert_eq!(checksum_entire_file(&mut &file[..]).await.unwrap(), checksum);
+
+        let mut flipped = file.clone();
+        flipped[3] ^= 1;
+        assert_eq!(
+            check(&flipped, 12).await.unwrap_err(),
+            format!(
+                "Corrupt index: checksum failed (hardware problem?): expected={checksum:#x} actual={:#x} \
+                 (resource=_0.si, position=20)",
+                crc32fast::hash(&flipped[..20])
+            )
+        );
+        assert!(checksum_entire_file(&mut &flipped[..]).await.is_err());
+
+        let mut bad_magic = file.clone();
+        bad_magic[12] = 0;
+        let error = check(&bad_magic, 12).await.unwrap_err();
+        assert!(error.contains("codec footer mismatch") && error.ends_with("(resource=_0.si, position=12)"), "{error}");
+
+        let mut trailing = file.clone();
+        trailing.push(0);
+        let error = check(&trailing, 12).await.unwrap_err();
+        assert!(error.starts_with("Corrupt index: data after codec footer"), "{error}");
+
+        assert_eq!(
+            check(&file[..file.len() - 1], 12).await.unwrap_err(),
+            "Corrupt index: unexpected end of file (resource=_0.si, position=27)"
+        );
+
+        let error = checksum_entire_file(&mut &file[..10]).await.unwrap_err();
+        assert!(matches!(LuceneError::find(error.as_ref()), Some(LuceneError::CorruptIndex(_))));
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_write_header_and_footer() {
+        let id = Id::from_bytes(*b"0123456789abcdef");
+        let header = IndexHeader::new("Lucene90PostingsWriterDoc", 1, id).unwrap();
+        let mut w = Crc32Writer::new(Vec::new());
+        header.write(&mut w, "Lucene90_0").await.unwrap();
+        assert_eq!(w.position() as usize, index_header_length("Lucene90PostingsWriterDoc", "Lucene90_0"));
+        w.write_all(b"postings").await.unwrap();
+        write_footer(&mut w).await.unwrap();
+        let file = w.into_inner();
+        assert_eq!(file.len(), index_header_length("Lucene90PostingsWriterDoc", "Lucene90_0") + 8 + FOOTER_LENGTH);
+        assert_eq!(header_length("Lucene90PostingsWriterDoc"), 34);
+
+        let checksum = crc32fast::hash(&file[..file.len() - 8]) as u64;
+        assert_eq!(retrieve_checksum(&file).unwrap(), checksum);
+        assert_eq!(checksum_entire_file(&mut &file[..]).await.unwrap(), checksum);
+
+        let mut r = Crc32Reader::new(&file[..]);
+        let read =
+            IndexHeader::read_from(&mut r, "Lucene90PostingsWriterDoc", 0, 1, Some(id), "Lucene90_0").await.unwrap();
+        assert_eq!(read.id(), id);
+        assert_eq!(read.version(), 1);
+        r.read_exact(&mut [0; 8]).await.unwrap();
+        assert_eq!(check_footer(&mut r).await.unwrap(), checksum);
+
+        let mut r = &file[..];
+        let error = IndexHeader::read_from(&mut r, "Lucene90PostingsWriterDoc", 0, 1, Some(id), "Lucene90_1")
+            .await
+            .unwrap_err();
+        assert!(matches!(LuceneError::find(error.as_ref()), Some(LuceneError::CorruptIndex(_))));
+        assert!(header.write(&mut Vec::new(), "é").await.is_err());
+
+        // Only the footer's structure is checked, not the data.
+        let mut flipped = file.clone();
+        flipped[40] ^= 1;
+        assert_eq!(retrieve_checksum(&flipped).unwrap(), checksum);
+        let error = retrieve_checksum(&file[..10]).unwrap_err();
+        assert!(error.to_string().contains("too short to hold a codec footer"), "{error}");
+        let mut bad_magic = file.clone();
+        bad_magic[file.len() - FOOTER_LENGTH] = 0;
+        assert!(retrieve_checksum(&bad_magic).is_err());
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_verify_checksum_of_lucene_file() {
+        // Written by Lucene 9.5.
+        let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        path.extend(["tests", "rfc-database", "segments_1"]);
+        let file = std::fs::read(path).unwrap();
+        assert_eq!(checksum_entire_file(&mut &file[..]).await.unwrap(), 0x5965dd4f);
+    }
+}
@@ -0,0 +1,157 @@
+use {
+    crate::{
+        index::SegmentInfo,
+        io::{Directory, IoContext, Lock},
+        BoxResult, LuceneError,
+    },
+    async_trait::async_trait,
+    std::{
+        collections::BTreeMap,
+        fmt::Debug,
+        io::{Error as IoError, ErrorKind as IoErrorKind, Result as IoResult},
+        pin::Pin,
+    },
+    tokio::io::{self as tokio_io, AsyncRead, AsyncReadExt, AsyncWrite},
+};
+
+/// Controls the format of compound files, which pack the files of a segment into a single data file, so that an
+/// index with many segments doesn't use as many file handles.
+#[async_trait(?Send)]
+pub trait CompoundFormat: Debug {
+    /// Reads the entries of the compound file of a segment: the files it holds, named without the segment name (see
+    /// [crate::index::strip_segment_name]), with their offsets and lengths in the data file.
+    async fn read_entries(
+        &self,
+        directory: &mut dyn Directory,
+        segment: &SegmentInfo,
+        context: &IoContext,
+    ) -> BoxResult<BTreeMap<String, CompoundEntry>>;
+
+    /// Packs the files of `segment` into its compound file. The caller then replaces the files of the segment with
+    /// [CompoundFormat::files], and removes the packed files.
+    async fn write_compound(
+        &self,
+        directory: &mut dyn Directory,
+        segment: &SegmentInfo,
+        context: &IoContext,
+    ) -> BoxResult<()>;
+
+    /// Returns the name of the data file of a segment's compound file.
+    fn data_file_name(&self, segment: &SegmentInfo) -> String;
+
+    /// Returns the names of the compound file of a segment, its data file first.
+    fn files(&self, segment: &SegmentInfo) -> Vec<String>;
+}
+
+/// The location of a file in the data file of a compound file.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct CompoundEntry {
+    /// The offset of the file in the data file.
+    pub offset: u64,
+
+    /// The length of the file.
+    pub length: u64,
+}
+
+/// A read-only [Directory] over the files a segment's compound file holds, like Lucene's `CompoundDirectory`.
+///
+/// Files are opened by their full names, such as `_0.nvd`. The [Directory] trait can't seek, so opening a file
+/// reads past the files before it in the data file.
+#[derive(Debug)]
+pub struct CompoundDirectory<'a> {
+    inner: &'a mut dyn Directory,
+    segment_name: String,
+    data_file_name: String,
+    entries: BTreeMap<String, CompoundEntry>,
+}
+
+impl<'a> CompoundDirectory<'a> {
+    /// Opens the compound file of `segment` in `directory` with `format`.
+    pub async fn open(
+        directory: &'a mut dyn Directory,
+        format: &dyn CompoundFormat,
+        segment: &SegmentInfo,
+        context: &IoContext,
+    ) -> BoxResult<Self> {
+        if !segment.is_compound_file() {
+            return Err(
+                LuceneError::InvalidArgument(format!("segment {} isn't a compound file", segment.get_name())).into()
+            );
+        }
+
+        let entries = format.read_entries(directory, segment, context).await?;
+        Ok(Self {
+            inner: directory,
+            segment_name: segment.get_name().to_string(),
+            data_file_name: format.data_file_name(segment),
+            entries,
+        })
+    }
+
+    /// Returns the files held by the compound file, by full name, with their locations in the data file.
+    pub fn entries(&self) -> impl Iterator<Item = (String, &CompoundEntry)> + '_ {
+        let segment_name = self.segment_name.as_str();
+        self.entries.iter().map(move |(name, entry)| (format!("{segment_name}{name}"), entry))
+    }
+
+    /// Returns the location of a file in the data file, or `None` if the compound file doesn't hold it.
+    pub fn entry(&self, file_name: &str) -> Option<&CompoundEntry> {
+        file_name.strip_prefix(self.segment_name.as_str()).and_then(|name| self.entries.get(name))
+    }
+}
+
+#[async_trait(?Send)]
+impl Directory for CompoundDirectory<'_> {
+    async fn read_dir(&self) -> IoResult<Vec<String>> {
+        Ok(self.entries().map(|(name, _)| name).collect())
+    }
+
+    async fn create(&mut self, file_name: &str, _context: &IoContext) -> IoResult<Pin<Box<dyn AsyncWrite + Send>>> {
+        Err(read_only(file_name))
+    }
+
+    async fn open(&mut self, file_name: &str, context: &IoContext) -> IoResult<Pin<Box<dyn AsyncRead>>> {
+        let Some(entry) = self.entry(file_name).copied() else {
+            return Err(IoError::new(
+                IoErrorKind::NotFound,
+                format!("{file_name} isn't in compound file {}", self.data_file_name),
+            ));
+        };
+
+        let mut r = self.inner.open(&self.data_file_name, context).await?;
+        let skipped = tokio_io::copy(&mut (&mut r).take(entry.offset), &mut tokio_io::sink()).await?;
+        if skipped != entry.offset {
+            return Err(IoError::new(
+                IoErrorKind::UnexpectedEof,
+                format!("{file_name} starts at {}, past the end of {}", entry.offset, self.data_file_name),
+            ));
+        }
+        Ok(Box::pin(r.take(entry.length)))
+    }
+
+    async fn remove(&mut self, file_name: &str) -> IoResult<()> {
+        Err(read_only(file_name))
+    }
+
+    async fn rename(&mut self, old_file_name: &str, _new_file_name: &str) -> IoResult<()> {
+        Err(read_only(old_file_name))
+    }
+
+    // Nothing is written through a compound directory, so there's nothing to make durable.
+    async fn sync(&mut self, _file_names: &[&str]) -> IoResult<()> {
+        Ok(())
+    }
+
+    async fn sync_meta_data(&mut self) -> IoResult<()> {
+        Ok(())
+    }
+
+    async fn obtain_lock(&mut self, lock_name: &str) -> BoxResult<Box<dyn Lock>> {
+        Err(LuceneError::LockObtainFailed(format!("Cannot obtain lock {lock_name}: compound files are read-only"))
+            .into())
+    }
+}
+
+fn read_only(file_name: &str) -> IoError {
+    IoError::new(IoErrorKind::Unsupported, format!("Cannot modify file {file_name}: compound files are read-only"))
+}
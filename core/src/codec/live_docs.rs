@@ -0,0 +1,40 @@
+use {
+    crate::{
+        index::SegmentCommitInfo,
+        io::{Directory, IoContext},
+        util::FixedBitSet,
+        BoxResult,
+    },
+    async_trait::async_trait,
+    std::fmt::Debug,
+};
+
+/// Controls the format of the live docs file, which records the documents of a segment that have not been deleted.
+///
+/// Deletes are written to a new generation of the file at each commit, numbered by
+/// [SegmentCommitInfo::get_next_write_del_gen], so that earlier commits remain readable.
+#[async_trait(?Send)]
+pub trait LiveDocsFormat: Debug {
+    /// Reads the live docs of the current deletion generation of a segment. A bit is set for each document that has
+    /// not been deleted.
+    async fn read_live_docs(
+        &self,
+        directory: &mut dyn Directory,
+        info: &SegmentCommitInfo,
+        context: &IoContext,
+    ) -> BoxResult<FixedBitSet>;
+
+    /// Writes the live docs of a segment to its next deletion generation. `new_del_count` is the number of
+    /// documents deleted since the current generation; the caller advances the generation once this succeeds.
+    async fn write_live_docs(
+        &self,
+        live_docs: &FixedBitSet,
+        directory: &mut dyn Directory,
+        info: &SegmentCommitInfo,
+        new_del_count: u32,
+        context: &IoContext,
+    ) -> BoxResult<()>;
+
+    /// Returns the names of the live docs files of the current deletion generation of a segment.
+    fn files(&self, info: &SegmentCommitInfo) -> Vec<String>;
+}
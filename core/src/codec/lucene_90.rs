@@ -1,2 +1,8 @@
+mod compound;
+mod for_util;
+mod live_docs;
+mod norms;
+mod pfor_util;
 mod segment_info;
-pub use segment_info::*;
+mod term_vectors;
+pub use {compound::*, for_util::*, live_docs::*, norms::*, pfor_util::*, segment_info::*, term_vectors::*};
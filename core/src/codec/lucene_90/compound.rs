@@ -0,0 +1,254 @@
+use {
+    crate::{
+        codec::{check_footer, write_footer, CompoundEntry, CompoundFormat},
+        index::{file_name_from_generation, strip_segment_name, IndexHeader, SegmentInfo},
+        io::{Crc32Reader, Crc32Writer, Directory, EncodingReadExt, EncodingWriteExt, IoContext},
+        locate_corruption, BoxResult, ErrorContext, LuceneError,
+    },
+    async_trait::async_trait,
+    std::collections::BTreeMap,
+    tokio::io::{self as tokio_io, AsyncRead, AsyncReadExt, AsyncWriteExt},
+};
+
+const DATA_CODEC_NAME: &str = "Lucene90CompoundData";
+const ENTRIES_CODEC_NAME: &str = "Lucene90CompoundEntries";
+const VERSION_START: u32 = 0;
+const VERSION_CURRENT: u32 = 0;
+
+/// The extension of compound data files.
+pub const COMPOUND_DATA_EXTENSION: &str = "cfs";
+
+/// The extension of compound entries files.
+pub const COMPOUND_ENTRIES_EXTENSION: &str = "cfe";
+
+/// The alignment of the files in a compound data file.
+const ALIGNMENT: u64 = 8;
+
+/// Lucene 9.0 compound (`.cfs` and `.cfe`) file format.
+///
+/// CompoundData (.cfs) --> IndexHeader + &lt;Padding, FileData&gt;<sup>FileCount</sup> + Footer
+///
+/// * Padding: Zero bytes that align each file to 8 bytes.
+/// * FileData: The contents of a file of the segment, including its own header and footer.
+///
+/// CompoundEntries (.cfe) --> IndexHeader + FileCount (vi32) + &lt;FileName (string), DataOffset (LE i64),
+///     DataLength (LE i64)&gt;<sup>FileCount</sup> + Footer
+///
+/// * FileName: The name of the file without the segment name (see [strip_segment_name]), such as `.nvd`.
+/// * DataOffset, DataLength: The location of the file in the data file.
+#[derive(Debug)]
+pub struct Lucene90CompoundFormat {}
+
+impl Lucene90CompoundFormat {
+    /// Create a new instance of [Lucene90CompoundFormat]
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    async fn read_entries_from<R: AsyncRead + Unpin>(
+        &self,
+        r: &mut Crc32Reader<R>,
+        segment: &SegmentInfo,
+    ) -> BoxResult<BTreeMap<String, CompoundEntry>> {
+        IndexHeader::read_from(r, ENTRIES_CODEC_NAME, VERSION_START, VERSION_CURRENT, Some(segment.get_id()), "")
+            .await?;
+        let num_files = r.read_vi32().await?;
+        if num_files < 0 {
+            return Err(LuceneError::CorruptIndex(format!("invalid file count {num_files}").into()).into());
+        }
+
+        let mut entries = BTreeMap::new();
+        for _ in 0..num_files {
+            let name = r.read_string().await?;
+            let offset = r.read_i64_le().await?;
+            let length = r.read_i64_le().await?;
+            let (Ok(offset), Ok(length)) = (u64::try_from(offset), u64::try_from(length)) else {
+                return Err(LuceneError::CorruptIndex(
+                    format!("invalid entry for {name:?}: {length} bytes at {offset}").into(),
+                )
+                .into());
+            };
+            if entries
+                .insert(
+                    name.clone(),
+                    CompoundEntry {
+                        offset,
+                        length,
+                    },
+                )
+                .is_some()
+            {
+                return Err(LuceneError::CorruptIndex(format!("duplicate entry for {name:?}").into()).into());
+            }
+        }
+
+        check_footer(r).await?;
+        Ok(entries)
+    }
+}
+
+impl Default for Lucene90CompoundFormat {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait(?Send)]
+impl CompoundFormat for Lucene90CompoundFormat {
+    async fn read_entries(
+        &self,
+        directory: &mut dyn Directory,
+        segment: &SegmentInfo,
+        context: &IoContext,
+    ) -> BoxResult<BTreeMap<String, CompoundEntry>> {
+        let file_name = file_name_from_generation(segment.get_name(), COMPOUND_ENTRIES_EXTENSION, 0);
+        let mut r = Crc32Reader::buffered(directory.open(&file_name, context).await?);
+        self.read_entries_from(&mut r, segment)
+            .await
+            .map_err(|e| locate_corruption(e, &file_name, r.position()))
+            .with_context(|| format!("reading compound entries {file_name}"))
+    }
+
+    async fn write_compound(
+        &self,
+        directory: &mut dyn Directory,
+        segment: &SegmentInfo,
+        context: &IoContext,
+    ) -> BoxResult<()> {
+        let mut files: Vec<&String> = segment.get_files().iter().collect();
+        files.sort_unstable();
+
+        let data_file_name = self.data_file_name(segment);
+        let mut data = Crc32Writer::new(directory.create(&data_file_name, context).await?);
+        IndexHeader::new(DATA_CODEC_NAME, VERSION_CURRENT, segment.get_id())?.write(&mut data, "").await?;
+
+        let mut entries = Vec::with_capacity(files.len());
+        for file_name in files {
+            let padding = data.position().next_multiple_of(ALIGNMENT) - data.position();
+            data.write_all(&[0; ALIGNMENT as usize][..padding as usize]).await?;
+
+            let offset = data.position();
+            let mut r = directory.open(file_name, context).await?;
+            let length = tokio_io::copy(&mut r, &mut data)
+                .await
+                .with_context(|| format!("copying {file_name} into {data_file_name}"))?;
+            entries.push((strip_segment_name(file_name), offset, length));
+        }
+        write_footer(&mut data).await?;
+        data.shutdown().await?;
+
+        let file_name = file_name_from_generation(segment.get_name(), COMPOUND_ENTRIES_EXTENSION, 0);
+        let mut w = Crc32Writer::new(directory.create(&file_name, context).await?);
+        IndexHeader::new(ENTRIES_CODEC_NAME, VERSION_CURRENT, segment.get_id())?.write(&mut w, "").await?;
+        w.write_vi32(entries.len() as i32).await?;
+        for (name, offset, length) in entries {
+            w.write_string(name).await?;
+            w.write_i64_le(offset as i64).await?;
+            w.write_i64_le(length as i64).await?;
+        }
+        write_footer(&mut w).await?;
+        w.shutdown().await?;
+        Ok(())
+    }
+
+    fn data_file_name(&self, segment: &SegmentInfo) -> String {
+        file_name_from_generation(segment.get_name(), COMPOUND_DATA_EXTENSION, 0)
+    }
+
+    fn files(&self, segment: &SegmentInfo) -> Vec<String> {
+        [COMPOUND_DATA_EXTENSION, COMPOUND_ENTRIES_EXTENSION]
+            .into_iter()
+            .map(|extension| file_name_from_generation(segment.get_name(), extension, 0))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{
+            codec::{get_codec, CompoundDirectory},
+            index::SegmentInfo,
+            io::{ByteBuffersDirectory, Directory, IoContext},
+            Id, LuceneError, LATEST,
+        },
+        pretty_assertions::assert_eq,
+        std::{collections::HashMap, io::ErrorKind as IoErrorKind},
+        tokio::io::{AsyncReadExt, AsyncWriteExt},
+    };
+
+    fn segment_info(files: &[&str]) -> SegmentInfo {
+        SegmentInfo {
+            name: "_5".to_string(),
+            id: Id::random_id(),
+            max_doc: 1,
+            attributes: HashMap::new(),
+            diagnostics: HashMap::new(),
+            files: files.iter().map(|file| file.to_string()).collect(),
+            version: LATEST,
+            min_version: Some(LATEST),
+            is_compound_file: true,
+            index_sort: None,
+            codec: None,
+        }
+    }
+
+    async fn read_file<D: Directory + ?Sized>(dir: &mut D, file_name: &str) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        dir.open(file_name, &IoContext::Read).await.unwrap().read_to_end(&mut bytes).await.unwrap();
+        bytes
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_compound_roundtrip() {
+        let files = [("_5.nvd", &b"norms"[..]), ("_5.nvm", &[7; 13][..]), ("_5_Lucene90_0.doc", &[][..])];
+        let mut dir = ByteBuffersDirectory::new();
+        for (file_name, bytes) in files {
+            let mut w = dir.create(file_name, &IoContext::Default).await.unwrap();
+            w.write_all(bytes).await.unwrap();
+            w.shutdown().await.unwrap();
+        }
+
+        let codec = get_codec("Lucene95").unwrap();
+        let format = codec.compound_format();
+        let info = segment_info(&files.map(|(file_name, _)| file_name));
+        format.write_compound(&mut dir, &info, &IoContext::Default).await.unwrap();
+        assert_eq!(format.files(&info), vec!["_5.cfs", "_5.cfe"]);
+
+        let entries = format.read_entries(&mut dir, &info, &IoContext::Read).await.unwrap();
+        assert_eq!(entries.keys().collect::<Vec<_>>(), vec![".nvd", ".nvm", "_Lucene90_0.doc"]);
+        assert!(entries.values().all(|entry| entry.offset % 8 == 0), "{entries:?}");
+        assert_eq!(entries[".nvm"].length, 13);
+
+        let mut compound = CompoundDirectory::open(&mut dir, format.as_ref(), &info, &IoContext::Read).await.unwrap();
+        let mut listed = compound.read_dir().await.unwrap();
+        listed.sort();
+        assert_eq!(listed, vec!["_5.nvd", "_5.nvm", "_5_Lucene90_0.doc"]);
+        for (file_name, bytes) in files {
+            assert_eq!(read_file(&mut compound, file_name).await, bytes, "{file_name}");
+        }
+        let error = compound.open("_5.tvd", &IoContext::Read).await.err().unwrap();
+        assert_eq!(error.kind(), IoErrorKind::NotFound);
+        let error = compound.create("_5.tvd", &IoContext::Default).await.err().unwrap();
+        assert_eq!(error.kind(), IoErrorKind::Unsupported);
+        assert_eq!(compound.remove("_5.nvd").await.unwrap_err().kind(), IoErrorKind::Unsupported);
+
+        // The entries must belong to the segment, and a flipped bit is caught by the checksum.
+        let other = segment_info(&[]);
+        let error = format.read_entries(&mut dir, &other, &IoContext::Read).await.unwrap_err();
+        assert!(matches!(LuceneError::find(error.as_ref()), Some(LuceneError::CorruptIndex(_))));
+        let mut file = read_file(&mut dir, "_5.cfe").await;
+        file[50] ^= 1;
+        let mut w = dir.create("_5.cfe", &IoContext::Default).await.unwrap();
+        w.write_all(&file).await.unwrap();
+        w.shutdown().await.unwrap();
+        let error = format.read_entries(&mut dir, &info, &IoContext::Read).await.unwrap_err();
+        assert!(matches!(LuceneError::find(error.as_ref()), Some(LuceneError::CorruptIndex(_))));
+        assert!(format!("{error:#}").contains("_5.cfe"), "{error:#}");
+
+        // Only compound segments can be opened as compound files.
+        let mut flat = segment_info(&[]);
+        flat.is_compound_file = false;
+        assert!(CompoundDirectory::open(&mut dir, format.as_ref(), &flat, &IoContext::Read).await.is_err());
+    }
+}
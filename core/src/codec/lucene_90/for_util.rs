@@ -0,0 +1,358 @@
+use {
+    crate::{BoxResult, LuceneError},
+    tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+};
+
+/// The number of values in a block encoded by [ForUtil].
+pub const BLOCK_SIZE: usize = 128;
+
+/// The largest number of bits per value [ForUtil] supports.
+pub const MAX_BITS_PER_VALUE: u32 = 32;
+
+/// Returns the width of the lanes values are packed into for `bits_per_value` bits: 8, 16 or 32.
+#[inline]
+const fn lane_bits(bits_per_value: u32) -> u32 {
+    if bits_per_value <= 8 {
+        8
+    } else if bits_per_value <= 16 {
+        16
+    } else {
+        32
+    }
+}
+
+/// Returns a mask of the low `bits` bits of every lane of `lane_bits` bits.
+#[inline]
+const fn lane_mask(bits: u32, lane_bits: u32) -> u64 {
+    if bits == 0 {
+        return 0;
+    }
+    let mask = u64::MAX >> (64 - bits);
+    let mut result = 0;
+    let mut shift = 0;
+    while shift < 64 {
+        result |= mask << shift;
+        shift += lane_bits;
+    }
+    result
+}
+
+/// Packs the 128 values into `128 * lane_bits / 64` longs, each holding one value per lane: long `i` holds values `i`,
+/// `i + n`, `i + 2n`... from its most significant lane down, where `n` is the number of longs.
+#[inline]
+fn collapse(lane_bits: u32, longs: &mut [u64; BLOCK_SIZE]) {
+    let num_longs = BLOCK_SIZE * lane_bits as usize / 64;
+    let lanes = 64 / lane_bits as usize;
+    for i in 0..num_longs {
+        let mut collapsed = 0;
+        for lane in 0..lanes {
+            collapsed |= longs[lane * num_longs + i] << (64 - lane_bits as usize * (lane + 1));
+        }
+        longs[i] = collapsed;
+    }
+}
+
+/// Reverses [collapse].
+#[inline]
+fn expand(lane_bits: u32, longs: &mut [u64; BLOCK_SIZE]) {
+    let num_longs = BLOCK_SIZE * lane_bits as usize / 64;
+    let lanes = 64 / lane_bits as usize;
+    let mask = u64::MAX >> (64 - lane_bits);
+    for i in 0..num_longs {
+        let collapsed = longs[i];
+        for lane in 0..lanes {
+            longs[lane * num_longs + i] = (collapsed >> (64 - lane_bits as usize * (lane + 1))) & mask;
+        }
+    }
+}
+
+/// Encodes 128 values of `BPV` bits into `2 * BPV` longs of `tmp`. Making the number of bits a constant lets the
+/// compiler unroll the loops and turn each of them into a few vector shifts and masks.
+fn encode_bits<const BPV: u32>(longs: &mut [u64; BLOCK_SIZE], tmp: &mut [u64]) {
+    let lane_bits = lane_bits(BPV);
+    collapse(lane_bits, longs);
+    let num_longs = BLOCK_SIZE * lane_bits as usize / 64;
+    let num_longs_per_shift = 2 * BPV as usize;
+    let tmp = &mut tmp[..num_longs_per_shift];
+
+    // Whole values first: each pass shifts one long per lane of values into place.
+    let mut idx = 0;
+    let mut shift = (lane_bits - BPV) as i32;
+    for (i, t) in tmp.iter_mut().enumerate() {
+        *t = longs[i] << shift;
+    }
+    idx += num_longs_per_shift;
+    shift -= BPV as i32;
+    while shift >= 0 {
+        for (i, t) in tmp.iter_mut().enumerate() {
+            *t |= longs[idx + i] << shift;
+        }
+        idx += num_longs_per_shift;
+        shift -= BPV as i32;
+    }
+
+    // Then the values left over, split across the bits remaining at the bottom of each lane.
+    let remaining_bits_per_long = (shift + BPV as i32) as u32;
+    let mask_remaining_bits_per_long = lane_mask(remaining_bits_per_long, lane_bits);
+    let mut tmp_idx = 0;
+    let mut remaining_bits_per_value = BPV;
+    while idx < num_longs {
+        if remaining_bits_per_value >= remaining_bits_per_long {
+            remaining_bits_per_value -= remaining_bits_per_long;
+            tmp[tmp_idx] |= (longs[idx] >> remaining_bits_per_value) & mask_remaining_bits_per_long;
+            tmp_idx += 1;
+            if remaining_bits_per_value == 0 {
+                idx += 1;
+                remaining_bits_per_value = BPV;
+            }
+        } else {
+            let mask1 = lane_mask(remaining_bits_per_value, lane_bits);
+            let mask2 = lane_mask(remaining_bits_per_long - remaining_bits_per_value, lane_bits);
+            tmp[tmp_idx] |= (longs[idx] & mask1) << (remaining_bits_per_long - remaining_bits_per_value);
+            idx += 1;
+            remaining_bits_per_value += BPV - remaining_bits_per_long;
+            tmp[tmp_idx] |= (longs[idx] >> remaining_bits_per_value) & mask2;
+            tmp_idx += 1;
+        }
+    }
+}
+
+/// Decodes 128 values of `BPV` bits from the `2 * BPV` longs of `tmp`; the reverse of [encode_bits].
+fn decode_bits<const BPV: u32>(tmp: &[u64], longs: &mut [u64; BLOCK_SIZE]) {
+    let lane_bits = lane_bits(BPV);
+    let num_longs = BLOCK_SIZE * lane_bits as usize / 64;
+    let num_longs_per_shift = 2 * BPV as usize;
+    let tmp = &tmp[..num_longs_per_shift];
+    let mask = lane_mask(BPV, lane_bits);
+
+    let mut idx = 0;
+    let mut shift = (lane_bits - BPV) as i32;
+    while shift >= 0 {
+        for (longs, &t) in longs[idx..idx + num_longs_per_shift].iter_mut().zip(tmp) {
+            *longs = (t >> shift) & mask;
+        }
+        idx += num_longs_per_shift;
+        shift -= BPV as i32;
+    }
+
+    let remaining_bits_per_long = (shift + BPV as i32) as u32;
+    let mask_remaining_bits_per_long = lane_mask(remaining_bits_per_long, lane_bits);
+    let mut tmp_idx = 0;
+    let mut remaining_bits = remaining_bits_per_long;
+    while idx < num_longs {
+        let mut b = BPV - remaining_bits;
+        let mut l = (tmp[tmp_idx] & lane_mask(remaining_bits, lane_bits)) << b;
+        tmp_idx += 1;
+        while b >= remaining_bits_per_long {
+            b -= remaining_bits_per_long;
+            l |= (tmp[tmp_idx] & mask_remaining_bits_per_long) << b;
+            tmp_idx += 1;
+        }
+        if b > 0 {
+            l |= (tmp[tmp_idx] >> (remaining_bits_per_long - b)) & lane_mask(b, lane_bits);
+            remaining_bits = remaining_bits_per_long - b;
+        } else {
+            remaining_bits = remaining_bits_per_long;
+        }
+        longs[idx] = l;
+        idx += 1;
+    }
+
+    expand(lane_bits, longs);
+}
+
+/// Calls `$f::<bits>($args)` for a number of bits between 1 and 32 known only at runtime.
+macro_rules! dispatch_bits {
+    ($bits_per_value:expr, $f:ident $args:tt, $($bits:literal)*) => {
+        match $bits_per_value {
+            $($bits => $f::<$bits> $args,)*
+            _ => unreachable!("bits per value must be between 1 and 32"),
+        }
+    };
+    ($bits_per_value:expr, $f:ident $args:tt) => {
+        dispatch_bits!(
+            $bits_per_value, $f $args,
+            1 2 3 4 5 6 7 8 9 10 11 12 13 14 15 16 17 18 19 20 21 22 23 24 25 26 27 28 29 30 31 32
+        )
+    };
+}
+
+/// Encodes and decodes blocks of 128 integers with frame of reference (FOR) encoding, as Lucene's postings format
+/// does: every value of a block is stored with the same number of bits, at most 32.
+///
+/// Values are packed into lanes of 8, 16 or 32 bits (the narrowest that fits) spread across 64-bit words, so that
+/// decoding is a fixed sequence of whole-word shifts and masks, one per lane row. The decoders are specialized for each
+/// number of bits, which lets the compiler vectorize them; the format matches Lucene's `ForUtil` byte for byte.
+#[derive(Clone, Debug)]
+pub struct ForUtil {
+    tmp: [u64; BLOCK_SIZE / 2],
+}
+
+impl Default for ForUtil {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ForUtil {
+    /// Creates a new encoder and decoder.
+    pub fn new() -> Self {
+        Self {
+            tmp: [0; BLOCK_SIZE / 2],
+        }
+    }
+
+    /// Returns the number of bytes a block of values of `bits_per_value` bits takes.
+    #[inline]
+    pub fn num_bytes(bits_per_value: u32) -> usize {
+        bits_per_value as usize * BLOCK_SIZE / 8
+    }
+
+    fn check_bits_per_value(bits_per_value: u32) -> BoxResult<()> {
+        if !(1..=MAX_BITS_PER_VALUE).contains(&bits_per_value) {
+            return Err(LuceneError::InvalidArgument(format!(
+                "bits per value must be between 1 and {MAX_BITS_PER_VALUE}, got {bits_per_value}"
+            ))
+            .into());
+        }
+        Ok(())
+    }
+
+    /// Encodes a block of values, which must all fit in `bits_per_value` bits, appending the bytes to `output`. This
+    /// fails with [LuceneError::InvalidArgument] if `bits_per_value` isn't between 1 and 32.
+    pub fn encode_block(
+        &mut self,
+        values: &[u64; BLOCK_SIZE],
+        bits_per_value: u32,
+        output: &mut Vec<u8>,
+    ) -> BoxResult<()> {
+        Self::check_bits_per_value(bits_per_value)?;
+        debug_assert!(
+            values.iter().all(|&value| value >> bits_per_value == 0),
+            "a value doesn't fit in {bits_per_value} bits"
+        );
+
+        let mut longs = *values;
+        dispatch_bits!(bits_per_value, encode_bits(&mut longs, &mut self.tmp));
+        for long in &self.tmp[..2 * bits_per_value as usize] {
+            output.extend_from_slice(&long.to_le_bytes());
+        }
+        Ok(())
+    }
+
+    /// Decodes a block of values of `bits_per_value` bits from the start of `input`. This fails with
+    /// [LuceneError::CorruptIndex] if `bits_per_value` isn't between 1 and 32 or `input` is too short.
+    pub fn decode_block(&mut self, bits_per_value: u32, input: &[u8], values: &mut [u64; BLOCK_SIZE]) -> BoxResult<()> {
+        let num_bytes = Self::num_bytes(bits_per_value);
+        if !(1..=MAX_BITS_PER_VALUE).contains(&bits_per_value) || input.len() < num_bytes {
+            return Err(LuceneError::CorruptIndex(
+                format!("Invalid FOR block: {bits_per_value} bits per value in {} bytes", input.len()).into(),
+            )
+            .into());
+        }
+
+        for (long, bytes) in self.tmp.iter_mut().zip(input[..num_bytes].chunks_exact(8)) {
+            *long = u64::from_le_bytes(bytes.try_into().unwrap());
+        }
+        dispatch_bits!(bits_per_value, decode_bits(&self.tmp, values));
+        Ok(())
+    }
+
+    /// Encodes a block of values to `output`; see [encode_block](Self::encode_block).
+    pub async fn encode<W: AsyncWrite + Unpin + ?Sized>(
+        &mut self,
+        values: &[u64; BLOCK_SIZE],
+        bits_per_value: u32,
+        output: &mut W,
+    ) -> BoxResult<()> {
+        let mut bytes = Vec::with_capacity(Self::num_bytes(bits_per_value));
+        self.encode_block(values, bits_per_value, &mut bytes)?;
+        output.write_all(&bytes).await?;
+        Ok(())
+    }
+
+    /// Reads and decodes a block of values from `input`; see [decode_block](Self::decode_block).
+    pub async fn decode<R: AsyncRead + Unpin + ?Sized>(
+        &mut self,
+        bits_per_value: u32,
+        input: &mut R,
+        values: &mut [u64; BLOCK_SIZE],
+    ) -> BoxResult<()> {
+        Self::check_bits_per_value(bits_per_value)
+            .map_err(|_| LuceneError::CorruptIndex(format!("Invalid FOR bits per value: {bits_per_value}").into()))?;
+        let num_bytes = Self::num_bytes(bits_per_value);
+        let mut bytes = [0; BLOCK_SIZE * 4];
+        input.read_exact(&mut bytes[..num_bytes]).await?;
+        self.decode_block(bits_per_value, &bytes[..num_bytes], values)
+    }
+
+    /// Reads and decodes a block of deltas from `input`, then replaces them by their running sum starting at `base`.
+    pub async fn decode_and_prefix_sum<R: AsyncRead + Unpin + ?Sized>(
+        &mut self,
+        bits_per_value: u32,
+        input: &mut R,
+        base: u64,
+        values: &mut [u64; BLOCK_SIZE],
+    ) -> BoxResult<()> {
+        self.decode(bits_per_value, input, values).await?;
+        let mut sum = base;
+        for value in values.iter_mut() {
+            sum += *value;
+            *value = sum;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{
+            codec::{ForUtil, BLOCK_SIZE},
+            io::block_on,
+        },
+        pretty_assertions::assert_eq,
+    };
+
+    #[test]
+    fn test_for_util() {
+        let mut for_util = ForUtil::new();
+        for bits_per_value in 1..=32 {
+            let mask = u64::MAX >> (64 - bits_per_value);
+            let mut values = [0u64; BLOCK_SIZE];
+            for (i, value) in values.iter_mut().enumerate() {
+                *value = (i as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15) & mask;
+            }
+            values[7] = mask;
+
+            let mut bytes = Vec::new();
+            for_util.encode_block(&values, bits_per_value, &mut bytes).unwrap();
+            assert_eq!(bytes.len(), ForUtil::num_bytes(bits_per_value));
+
+            let mut decoded = [0u64; BLOCK_SIZE];
+            for_util.decode_block(bits_per_value, &bytes, &mut decoded).unwrap();
+            assert_eq!(decoded, values, "{bits_per_value} bits");
+            assert!(for_util.decode_block(bits_per_value, &bytes[1..], &mut decoded).is_err());
+        }
+
+        // The first values of each lane row take the top bit of each byte, as in Lucene.
+        let mut values = [0u64; BLOCK_SIZE];
+        values[0] = 1;
+        values[17] = 1;
+        let mut bytes = Vec::new();
+        for_util.encode_block(&values, 1, &mut bytes).unwrap();
+        assert_eq!(&bytes[..16], &[0, 0, 0, 0, 0, 0, 0, 0x80, 0, 0, 0, 0, 0, 0, 0x80, 0]);
+
+        assert!(for_util.encode_block(&values, 0, &mut bytes).is_err());
+        assert!(for_util.encode_block(&values, 33, &mut bytes).is_err());
+
+        block_on(async {
+            let deltas = [3u64; BLOCK_SIZE];
+            let mut output = Vec::new();
+            for_util.encode(&deltas, 2, &mut output).await.unwrap();
+            let mut values = [0u64; BLOCK_SIZE];
+            for_util.decode_and_prefix_sum(2, &mut output.as_slice(), 10, &mut values).await.unwrap();
+            assert_eq!((values[0], values[127]), (13, 10 + 3 * 128));
+        });
+    }
+}
@@ -0,0 +1,233 @@
+use {
+    crate::{
+        codec::{check_footer, write_footer, LiveDocsFormat},
+        index::{file_name_from_generation, generation_to_string, IndexHeader, SegmentCommitInfo},
+        io::{Crc32Reader, Crc32Writer, Directory, IoContext},
+        locate_corruption,
+        util::{BitSet, FixedBitSet},
+        BoxResult, ErrorContext, LuceneError,
+    },
+    async_trait::async_trait,
+    tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt},
+};
+
+const CODEC_NAME: &str = "Lucene90LiveDocs";
+const VERSION_START: u32 = 0;
+const VERSION_CURRENT: u32 = 0;
+
+/// The extension of live docs files.
+pub const LIVE_DOCS_EXTENSION: &str = "liv";
+
+/// Lucene 9.0 live docs (`.liv`) file format.
+///
+/// LiveDocs --> IndexHeader + Bits + Footer
+///
+/// * IndexHeader: See [IndexHeader::write]. The id is that of the segment and the suffix is the deletion generation,
+///   in base 36.
+/// * Bits (LE u64 * ceil(max_doc / 64)): The words of a [FixedBitSet] with a bit set for each live document.
+/// * Footer: See [write_footer].
+#[derive(Debug)]
+pub struct Lucene90LiveDocsFormat {}
+
+impl Lucene90LiveDocsFormat {
+    /// Create a new instance of [Lucene90LiveDocsFormat]
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    async fn read_live_docs_from<R: AsyncRead + Unpin>(
+        &self,
+        r: &mut Crc32Reader<R>,
+        info: &SegmentCommitInfo,
+        generation: u64,
+    ) -> BoxResult<FixedBitSet> {
+        let segment = info.get_segment_info();
+        let suffix = generation_to_string(generation);
+        IndexHeader::read_from(r, CODEC_NAME, VERSION_START, VERSION_CURRENT, Some(segment.get_id()), &suffix).await?;
+
+        let max_doc = segment.get_max_doc();
+        let mut words = Vec::with_capacity(FixedBitSet::num_words(max_doc));
+        for _ in 0..FixedBitSet::num_words(max_doc) {
+            words.push(r.read_u64_le().await?);
+        }
+        let live_docs = FixedBitSet::from_words(words, max_doc)
+            .map_err(|e| LuceneError::CorruptIndex(format!("invalid live docs: {e}").into()))?;
+
+        let deleted = max_doc - live_docs.cardinality();
+        if deleted != info.get_del_count() {
+            return Err(LuceneError::CorruptIndex(
+                format!("bits.deleted={deleted} info.del_count={}", info.get_del_count()).into(),
+            )
+            .into());
+        }
+
+        check_footer(r).await?;
+        Ok(live_docs)
+    }
+}
+
+impl Default for Lucene90LiveDocsFormat {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait(?Send)]
+impl LiveDocsFormat for Lucene90LiveDocsFormat {
+    async fn read_live_docs(
+        &self,
+        directory: &mut dyn Directory,
+        info: &SegmentCommitInfo,
+        context: &IoContext,
+    ) -> BoxResult<FixedBitSet> {
+        let Some(generation) = info.get_del_gen() else {
+            return Err(LuceneError::InvalidArgument(format!(
+                "segment {} has no deletions",
+                info.get_segment_info().get_name()
+            ))
+            .into());
+        };
+
+        let file_name = file_name_from_generation(info.get_segment_info().get_name(), LIVE_DOCS_EXTENSION, generation);
+        let fd = directory.open(&file_name, context).await?;
+        let mut r = Crc32Reader::buffered(fd);
+        self.read_live_docs_from(&mut r, info, generation)
+            .await
+            .map_err(|e| locate_corruption(e, &file_name, r.position()))
+            .with_context(|| format!("reading live docs {file_name}"))
+    }
+
+    async fn write_live_docs(
+        &self,
+        live_docs: &FixedBitSet,
+        directory: &mut dyn Directory,
+        info: &SegmentCommitInfo,
+        new_del_count: u32,
+        context: &IoContext,
+    ) -> BoxResult<()> {
+        let segment = info.get_segment_info();
+        if live_docs.num_bits() != segment.get_max_doc() {
+            return Err(LuceneError::InvalidArgument(format!(
+                "live docs hold {} bits, but segment {} has {} documents",
+                live_docs.num_bits(),
+                segment.get_name(),
+                segment.get_max_doc()
+            ))
+            .into());
+        }
+
+        let deleted = live_docs.num_bits() - live_docs.cardinality();
+        if deleted as u64 != info.get_del_count() as u64 + new_del_count as u64 {
+            return Err(LuceneError::InvalidArgument(format!(
+                "bits.deleted={deleted} info.del_count={} new_del_count={new_del_count}",
+                info.get_del_count()
+            ))
+            .into());
+        }
+
+        let generation = info.get_next_write_del_gen();
+        let file_name = file_name_from_generation(segment.get_name(), LIVE_DOCS_EXTENSION, generation);
+        let mut w = Crc32Writer::new(directory.create(&file_name, context).await?);
+        IndexHeader::new(CODEC_NAME, VERSION_CURRENT, segment.get_id())?
+            .write(&mut w, &generation_to_string(generation))
+            .await?;
+        for &word in live_docs.words() {
+            w.write_u64_le(word).await?;
+        }
+        write_footer(&mut w).await?;
+        w.shutdown().await?;
+        Ok(())
+    }
+
+    fn files(&self, info: &SegmentCommitInfo) -> Vec<String> {
+        match info.get_del_gen() {
+            Some(generation) => {
+                vec![file_name_from_generation(info.get_segment_info().get_name(), LIVE_DOCS_EXTENSION, generation)]
+            }
+            None => Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{
+            codec::{LiveDocsFormat, Lucene90LiveDocsFormat},
+            index::{SegmentCommitInfo, SegmentInfo},
+            io::{ByteBuffersDirectory, Directory, IoContext},
+            util::{BitSet, FixedBitSet},
+            Id, LuceneError, LATEST,
+        },
+        pretty_assertions::assert_eq,
+        std::collections::{HashMap, HashSet},
+        tokio::io::{AsyncReadExt, AsyncWriteExt},
+    };
+
+    fn commit_info(max_doc: u32) -> SegmentCommitInfo {
+        let info = SegmentInfo {
+            name: "_3".to_string(),
+            id: Id::random_id(),
+            max_doc,
+            attributes: HashMap::new(),
+            diagnostics: HashMap::new(),
+            files: HashSet::new(),
+            version: LATEST,
+            min_version: Some(LATEST),
+            is_compound_file: false,
+            index_sort: None,
+            codec: None,
+        };
+        SegmentCommitInfo::new(info, 0, 0, None, None, None, Some(Id::random_id()))
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_live_docs_roundtrip() {
+        let format = Lucene90LiveDocsFormat::new();
+        let mut dir = ByteBuffersDirectory::new();
+        let mut info = commit_info(100);
+        assert!(format.files(&info).is_empty());
+
+        let mut live_docs = FixedBitSet::new(100);
+        live_docs.set_range(0, 100);
+        live_docs.clear(3);
+        live_docs.clear(64);
+        format.write_live_docs(&live_docs, &mut dir, &info, 2, &IoContext::Default).await.unwrap();
+        info.advance_del_gen();
+        info.set_del_count(2).unwrap();
+        assert_eq!(format.files(&info), vec!["_3_1.liv".to_string()]);
+        let read = format.read_live_docs(&mut dir, &info, &IoContext::Read).await.unwrap();
+        assert_eq!(read.words(), live_docs.words());
+
+        // A second generation is written alongside the first.
+        live_docs.clear(99);
+        format.write_live_docs(&live_docs, &mut dir, &info, 1, &IoContext::Default).await.unwrap();
+        info.advance_del_gen();
+        info.set_del_count(3).unwrap();
+        assert_eq!(format.files(&info), vec!["_3_2.liv".to_string()]);
+        assert_eq!(dir.read_dir().await.unwrap().len(), 2);
+        let read = format.read_live_docs(&mut dir, &info, &IoContext::Read).await.unwrap();
+        assert_eq!(read.cardinality(), 97);
+        assert!(!read.get(99));
+
+        // The number of deleted documents must match the commit.
+        let error = format.write_live_docs(&live_docs, &mut dir, &info, 2, &IoContext::Default).await.unwrap_err();
+        assert!(error.to_string().contains("bits.deleted=3 info.del_count=3 new_del_count=2"), "{error}");
+        info.set_del_count(4).unwrap();
+        let error = format.read_live_docs(&mut dir, &info, &IoContext::Read).await.unwrap_err();
+        assert!(matches!(LuceneError::find(error.as_ref()), Some(LuceneError::CorruptIndex(_))));
+        assert!(format!("{error:#}").contains("bits.deleted=3 info.del_count=4"), "{error:#}");
+        info.set_del_count(3).unwrap();
+        assert!(info.set_del_count(101).is_err());
+
+        // Flipping a bit is caught by the checksum.
+        let mut file = Vec::new();
+        dir.open("_3_2.liv", &IoContext::Read).await.unwrap().read_to_end(&mut file).await.unwrap();
+        file[50] ^= 1;
+        let mut w = dir.create("_3_2.liv", &IoContext::Default).await.unwrap();
+        w.write_all(&file).await.unwrap();
+        w.shutdown().await.unwrap();
+        let error = format.read_live_docs(&mut dir, &info, &IoContext::Read).await.unwrap_err();
+        assert!(matches!(LuceneError::find(error.as_ref()), Some(LuceneError::CorruptIndex(_))));
+    }
+}
@@ -0,0 +1,538 @@
+use {
+    crate::{
+        codec::{check_footer, write_footer, NormsFormat},
+        index::{file_name_from_generation, IndexHeader, LeafReader, SegmentInfo},
+        io::{CountingInput, Crc32Reader, Crc32Writer, Directory, EncodingReadExt, EncodingWriteExt, IoContext},
+        locate_corruption, BoxResult, ErrorContext, LuceneError,
+    },
+    async_trait::async_trait,
+    std::{collections::HashMap, sync::Arc},
+    tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt},
+};
+
+const META_CODEC_NAME: &str = "Lucene90NormsMetadata";
+const DATA_CODEC_NAME: &str = "Lucene90NormsData";
+const VERSION_START: u32 = 0;
+const VERSION_CURRENT: u32 = 0;
+
+/// The extension of norms metadata files.
+pub const NORMS_META_EXTENSION: &str = "nvm";
+
+/// The extension of norms data files.
+pub const NORMS_DATA_EXTENSION: &str = "nvd";
+
+/// The docs-with-field offset of a field that every document has.
+const ALL_DOCS: i64 = -1;
+
+/// The docs-with-field offset of a field that no document has.
+const NO_DOCS: i64 = -2;
+
+/// Lucene 9.0 norms (`.nvm` and `.nvd`) file format.
+///
+/// Each field is encoded according to its norms: only documents with a non-zero norm are recorded, as a missing norm
+/// reads as 0, and their ids are only written if some document lacks one (the sparse case). Norms are written with
+/// the fewest bytes that hold all of them, or not at all if they are all the same (the constant case).
+///
+/// NormsMeta (.nvm) --> IndexHeader + MaxDoc + NumFields + Entry<sup>NumFields</sup> + DataLength + Footer
+///
+/// * MaxDoc (vi32): The number of documents in the segment.
+/// * Entry --> FieldName (string) + NumDocsWithField (vi32) + DocsWithFieldOffset (LE i64) +
+///   DocsWithFieldLength (LE i64) + BytesPerNorm (u8) + NormsOffset (LE i64).
+/// * DocsWithFieldOffset: The offset in the data file of the ids of the documents with a norm, or -1 if every
+///   document has one and -2 if none has.
+/// * BytesPerNorm: 1, 2, 4 or 8, or 0 if every document with a norm has the same one.
+/// * NormsOffset: The offset in the data file of the norms, or the norm of every document if BytesPerNorm is 0.
+/// * DataLength (LE i64): The length of the data file, without its footer.
+///
+/// NormsData (.nvd) --> IndexHeader + &lt;DocDelta (vi32)<sup>NumDocsWithField</sup>,
+///     Norm<sup>NumDocsWithField</sup>&gt;<sup>NumFields</sup> + Footer
+///
+/// * DocDelta: The id of a document with a norm, less that of the previous one. Only written for sparse fields.
+/// * Norm (LE i8, i16, i32 or i64, by BytesPerNorm): The norm of each document with a norm, in document order. Only
+///   written if BytesPerNorm isn't 0.
+#[derive(Debug)]
+pub struct Lucene90NormsFormat {}
+
+impl Lucene90NormsFormat {
+    /// Create a new instance of [Lucene90NormsFormat]
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    async fn read_meta_from<R: AsyncRead + Unpin>(
+        &self,
+        r: &mut Crc32Reader<R>,
+        segment: &SegmentInfo,
+    ) -> BoxResult<(Vec<NormsEntry>, u64)> {
+        IndexHeader::read_from(r, META_CODEC_NAME, VERSION_START, VERSION_CURRENT, Some(segment.get_id()), "").await?;
+        let max_doc = read_count(r, "max_doc").await?;
+        if max_doc != segment.get_max_doc() {
+            return Err(LuceneError::CorruptIndex(
+                format!("norms hold {max_doc} documents, but the segment has {}", segment.get_max_doc()).into(),
+            )
+            .into());
+        }
+
+        let num_fields = read_count(r, "num_fields").await?;
+        let mut entries = Vec::with_capacity(num_fields.min(1024) as usize);
+        for _ in 0..num_fields {
+            let field = r.read_string().await?;
+            let num_docs_with_field = read_count(r, "num_docs_with_field").await?;
+            let docs_with_field_offset = r.read_i64_le().await?;
+            let docs_with_field_length = r.read_i64_le().await?;
+            let bytes_per_norm = r.read_u8().await?;
+            let norms_offset = r.read_i64_le().await?;
+
+            let expected_offset = match num_docs_with_field {
+                0 => Some(NO_DOCS),
+                n if n == max_doc => Some(ALL_DOCS),
+                _ => None,
+            };
+            if num_docs_with_field > max_doc
+                || expected_offset.is_some_and(|offset| offset != docs_with_field_offset)
+                || !matches!(bytes_per_norm, 0 | 1 | 2 | 4 | 8)
+            {
+                return Err(LuceneError::CorruptIndex(
+                    format!(
+                        "invalid norms entry for {field:?}: {num_docs_with_field} documents with docs offset \
+                         {docs_with_field_offset} and {bytes_per_norm} bytes per norm"
+                    )
+                    .into(),
+                )
+                .into());
+            }
+
+            entries.push(NormsEntry {
+                field,
+                num_docs_with_field,
+                docs_with_field_offset,
+                docs_with_field_length,
+                bytes_per_norm,
+                norms_offset,
+            });
+        }
+
+        let data_length = r.read_i64_le().await?;
+        check_footer(r).await?;
+        let data_length = u64::try_from(data_length)
+            .map_err(|_| LuceneError::CorruptIndex(format!("invalid data length {data_length}").into()))?;
+        Ok((entries, data_length))
+    }
+
+    async fn read_data_from<R: AsyncRead + Unpin>(
+        &self,
+        r: &mut Crc32Reader<R>,
+        segment: &SegmentInfo,
+        data_length: u64,
+    ) -> BoxResult<Vec<u8>> {
+        IndexHeader::read_from(r, DATA_CODEC_NAME, VERSION_START, VERSION_CURRENT, Some(segment.get_id()), "").await?;
+        let start = r.position();
+        let Some(len) = data_length.checked_sub(start) else {
+            return Err(LuceneError::CorruptIndex(
+                format!("data length {data_length} is shorter than the header").into(),
+            )
+            .into());
+        };
+
+        // The data is read with its header, so that offsets into it are offsets into the file.
+        let mut data = vec![0; start as usize];
+        (&mut *r).take(len).read_to_end(&mut data).await?;
+        if (data.len() as u64) < data_length {
+            return Err(LuceneError::CorruptIndex("unexpected end of file".to_string().into()).into());
+        }
+        check_footer(r).await?;
+        Ok(data)
+    }
+
+    /// Reads the norms of every field of a segment that has them, with the number of bytes each takes in the data
+    /// file.
+    async fn read_fields(
+        &self,
+        directory: &mut dyn Directory,
+        segment: &SegmentInfo,
+        context: &IoContext,
+    ) -> BoxResult<Vec<(String, Vec<i64>, u64)>> {
+        let name = segment.get_name();
+
+        let file_name = file_name_from_generation(name, NORMS_META_EXTENSION, 0);
+        let mut r = Crc32Reader::buffered(directory.open(&file_name, context).await?);
+        let (entries, data_length) = self
+            .read_meta_from(&mut r, segment)
+            .await
+            .map_err(|e| locate_corruption(e, &file_name, r.position()))
+            .with_context(|| format!("reading norms metadata {file_name}"))?;
+
+        let file_name = file_name_from_generation(name, NORMS_DATA_EXTENSION, 0);
+        let mut r = Crc32Reader::buffered(directory.open(&file_name, context).await?);
+        let data = self
+            .read_data_from(&mut r, segment, data_length)
+            .await
+            .map_err(|e| locate_corruption(e, &file_name, r.position()))
+            .with_context(|| format!("reading norms {file_name}"))?;
+
+        let mut fields = Vec::with_capacity(entries.len());
+        for entry in entries {
+            let (norms, bytes) = entry
+                .decode(&data, segment.get_max_doc())
+                .await
+                .map_err(|e| locate_corruption(e, &file_name, entry.data_offset()))
+                .with_context(|| format!("reading norms of {:?} from {file_name}", entry.field))?;
+            fields.push((entry.field, norms, bytes));
+        }
+        Ok(fields)
+    }
+}
+
+impl Default for Lucene90NormsFormat {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait(?Send)]
+impl NormsFormat for Lucene90NormsFormat {
+    async fn read_norms(
+        &self,
+        directory: &mut dyn Directory,
+        segment: &SegmentInfo,
+        context: &IoContext,
+    ) -> BoxResult<HashMap<String, Arc<[i64]>>> {
+        let fields = self.read_fields(directory, segment, context).await?;
+        Ok(fields.into_iter().map(|(field, norms, _)| (field, norms.into())).collect())
+    }
+
+    async fn field_disk_usage(
+        &self,
+        directory: &mut dyn Directory,
+        segment: &SegmentInfo,
+        context: &IoContext,
+    ) -> BoxResult<HashMap<String, u64>> {
+        let fields = self.read_fields(directory, segment, context).await?;
+        Ok(fields.into_iter().map(|(field, _, bytes)| (field, bytes)).collect())
+    }
+
+    async fn write_norms(
+        &self,
+        reader: &dyn LeafReader,
+        directory: &mut dyn Directory,
+        segment: &SegmentInfo,
+        context: &IoContext,
+    ) -> BoxResult<()> {
+        let max_doc = segment.get_max_doc();
+        if reader.max_doc() != max_doc {
+            return Err(LuceneError::InvalidArgument(format!(
+                "segment {} has {max_doc} documents, but its reader has {}",
+                segment.get_name(),
+                reader.max_doc()
+            ))
+            .into());
+        }
+
+        let mut fields = reader.indexed_fields();
+        fields.sort_unstable();
+
+        let name = segment.get_name();
+        let file_name = file_name_from_generation(name, NORMS_DATA_EXTENSION, 0);
+        let mut data = Crc32Writer::new(directory.create(&file_name, context).await?);
+        IndexHeader::new(DATA_CODEC_NAME, VERSION_CURRENT, segment.get_id())?.write(&mut data, "").await?;
+
+        let mut entries = Vec::new();
+        for field in fields {
+            let Some(norms) = reader.norms(field)? else {
+                continue;
+            };
+
+            let docs: Vec<u32> = (0..max_doc).filter(|&doc| norms[doc as usize] != 0).collect();
+            let values: Vec<i64> = docs.iter().map(|&doc| norms[doc as usize]).collect();
+
+            let docs_with_field_offset = match docs.len() {
+                0 => NO_DOCS,
+                n if n == max_doc as usize => ALL_DOCS,
+                _ => {
+                    let offset = data.position() as i64;
+                    let mut previous = 0;
+                    for &doc in &docs {
+                        data.write_vi32((doc - previous) as i32).await?;
+                        previous = doc;
+                    }
+                    offset
+                }
+            };
+            let docs_with_field_length = if docs_with_field_offset >= 0 {
+                data.position() as i64 - docs_with_field_offset
+            } else {
+                0
+            };
+
+            let (min, max) = (values.iter().min().copied().unwrap_or(0), values.iter().max().copied().unwrap_or(0));
+            let (bytes_per_norm, norms_offset) = if min == max {
+                (0, min)
+            } else {
+                let bytes_per_norm = bytes_per_norm(min, max);
+                let offset = data.position() as i64;
+                for &value in &values {
+                    match bytes_per_norm {
+                        1 => data.write_i8(value as i8).await?,
+                        2 => data.write_i16_le(value as i16).await?,
+                        4 => data.write_i32_le(value as i32).await?,
+                        _ => data.write_i64_le(value).await?,
+                    }
+                }
+                (bytes_per_norm, offset)
+            };
+
+            entries.push(NormsEntry {
+                field: field.to_string(),
+                num_docs_with_field: docs.len() as u32,
+                docs_with_field_offset,
+                docs_with_field_length,
+                bytes_per_norm,
+                norms_offset,
+            });
+        }
+
+        let data_length = data.position();
+        write_footer(&mut data).await?;
+        data.shutdown().await?;
+
+        let file_name = file_name_from_generation(name, NORMS_META_EXTENSION, 0);
+        let mut meta = Crc32Writer::new(directory.create(&file_name, context).await?);
+        IndexHeader::new(META_CODEC_NAME, VERSION_CURRENT, segment.get_id())?.write(&mut meta, "").await?;
+        meta.write_vi32(max_doc as i32).await?;
+        meta.write_vi32(entries.len() as i32).await?;
+        for entry in &entries {
+            meta.write_string(&entry.field).await?;
+            meta.write_vi32(entry.num_docs_with_field as i32).await?;
+            meta.write_i64_le(entry.docs_with_field_offset).await?;
+            meta.write_i64_le(entry.docs_with_field_length).await?;
+            meta.write_u8(entry.bytes_per_norm).await?;
+            meta.write_i64_le(entry.norms_offset).await?;
+        }
+        meta.write_i64_le(data_length as i64).await?;
+        write_footer(&mut meta).await?;
+        meta.shutdown().await?;
+        Ok(())
+    }
+
+    fn files(&self, segment: &SegmentInfo) -> Vec<String> {
+        [NORMS_META_EXTENSION, NORMS_DATA_EXTENSION]
+            .into_iter()
+            .map(|extension| file_name_from_generation(segment.get_name(), extension, 0))
+            .collect()
+    }
+}
+
+/// How the norms of a field are encoded, as recorded in the metadata file.
+#[derive(Debug)]
+struct NormsEntry {
+    field: String,
+    num_docs_with_field: u32,
+    docs_with_field_offset: i64,
+    docs_with_field_length: i64,
+    bytes_per_norm: u8,
+    norms_offset: i64,
+}
+
+impl NormsEntry {
+    /// Returns the offset in the data file of the field's first bytes, or 0 if it has none.
+    fn data_offset(&self) -> u64 {
+        if self.docs_with_field_offset >= 0 {
+            self.docs_with_field_offset as u64
+        } else if self.bytes_per_norm > 0 {
+            self.norms_offset.max(0) as u64
+        } else {
+            0
+        }
+    }
+
+    /// Decodes the norms of the field from the data file, returning one per document and the number of bytes they
+    /// were decoded from.
+    async fn decode(&self, data: &[u8], max_doc: u32) -> BoxResult<(Vec<i64>, u64)> {
+        let mut bytes_read = 0;
+        let docs: Vec<u32> = match self.docs_with_field_offset {
+            NO_DOCS => Vec::new(),
+            ALL_DOCS => (0..max_doc).collect(),
+            offset => {
+                let mut r = CountingInput::new(slice(data, offset, self.docs_with_field_length)?);
+                let mut docs = Vec::with_capacity(self.num_docs_with_field as usize);
+                let mut doc = 0_u32;
+                for i in 0..self.num_docs_with_field {
+                    let delta = read_count(&mut r, "doc_delta").await?;
+                    doc = match doc.checked_add(delta) {
+                        Some(next) if next < max_doc && (i == 0 || delta > 0) => next,
+                        _ => {
+                            return Err(
+                                LuceneError::CorruptIndex(format!("invalid document delta {delta}").into()).into()
+                            )
+                        }
+                    };
+                    docs.push(doc);
+                }
+                bytes_read += r.bytes_read();
+                if !r.into_inner().is_empty() {
+                    return Err(
+                        LuceneError::CorruptIndex("trailing bytes after document ids".to_string().into()).into()
+                    );
+                }
+                docs
+            }
+        };
+
+        let mut norms = vec![0; max_doc as usize];
+        if self.bytes_per_norm == 0 {
+            for &doc in &docs {
+                norms[doc as usize] = self.norms_offset;
+            }
+            return Ok((norms, bytes_read));
+        }
+
+        let length = docs.len() as i64 * self.bytes_per_norm as i64;
+        let mut r = CountingInput::new(slice(data, self.norms_offset, length)?);
+        for &doc in &docs {
+            norms[doc as usize] = match self.bytes_per_norm {
+                1 => r.read_i8().await? as i64,
+                2 => r.read_i16_le().await? as i64,
+                4 => r.read_i32_le().await? as i64,
+                _ => r.read_i64_le().await?,
+            };
+        }
+        Ok((norms, bytes_read + r.bytes_read()))
+    }
+}
+
+/// Returns the fewest bytes that hold every norm from `min` to `max`.
+fn bytes_per_norm(min: i64, max: i64) -> u8 {
+    if min >= i8::MIN as i64 && max <= i8::MAX as i64 {
+        1
+    } else if min >= i16::MIN as i64 && max <= i16::MAX as i64 {
+        2
+    } else if min >= i32::MIN as i64 && max <= i32::MAX as i64 {
+        4
+    } else {
+        8
+    }
+}
+
+/// Returns the `length` bytes of `data` from `offset`, failing if they aren't within it.
+fn slice(data: &[u8], offset: i64, length: i64) -> BoxResult<&[u8]> {
+    usize::try_from(offset)
+        .ok()
+        .zip(usize::try_from(length).ok())
+        .and_then(|(offset, length)| data.get(offset..offset.checked_add(length)?))
+        .ok_or_else(|| LuceneError::CorruptIndex(format!("{length} bytes at {offset} are out of bounds").into()).into())
+}
+
+/// Reads a vi32 that must not be negative.
+async fn read_count<R: AsyncRead + Unpin>(r: &mut R, name: &str) -> BoxResult<u32> {
+    let value = r.read_vi32().await?;
+    u32::try_from(value).map_err(|_| LuceneError::CorruptIndex(format!("invalid {name}: {value}").into()).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{
+            analysis::SimpleAnalyzer,
+            codec::get_codec,
+            document::{Document, Field, Store},
+            index::{LeafReader, MemorySegmentBuilder, SegmentInfo},
+            io::{ByteBuffersDirectory, Directory, IoContext},
+            search::{
+                BooleanSimilarity, CollectionStatistics, FieldInvertState, SimScorer, Similarity, TermStatistics,
+            },
+            Id, LuceneError, LATEST,
+        },
+        pretty_assertions::assert_eq,
+        std::{
+            collections::{HashMap, HashSet},
+            sync::Arc,
+        },
+        tokio::io::{AsyncReadExt, AsyncWriteExt},
+    };
+
+    /// Computes large norms, which take more than a byte each, and scores as [BooleanSimilarity] does.
+    #[derive(Debug)]
+    struct LargeNormSimilarity;
+
+    impl Similarity for LargeNormSimilarity {
+        fn compute_norm(&self, state: &FieldInvertState) -> i64 {
+            state.length as i64 * 100_000
+        }
+
+        fn scorer(&self, boost: f32, cs: &CollectionStatistics, ts: &[TermStatistics]) -> Box<dyn SimScorer> {
+            BooleanSimilarity.scorer(boost, cs, ts)
+        }
+    }
+
+    fn segment_info(max_doc: u32) -> SegmentInfo {
+        SegmentInfo {
+            name: "_4".to_string(),
+            id: Id::random_id(),
+            max_doc,
+            attributes: HashMap::new(),
+            diagnostics: HashMap::new(),
+            files: HashSet::new(),
+            version: LATEST,
+            min_version: Some(LATEST),
+            is_compound_file: false,
+            index_sort: None,
+            codec: None,
+        }
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_norms_roundtrip() {
+        let mut builder = MemorySegmentBuilder::new(Arc::new(SimpleAnalyzer));
+        builder.set_similarity(Arc::new(LargeNormSimilarity));
+        for i in 0..50 {
+            let mut doc = Document::new();
+            // Dense, with norms of 4 bytes.
+            doc.add(Field::text("body", "word ".repeat(i + 1), Store::No));
+            // Sparse and constant.
+            if i % 7 == 0 {
+                doc.add(Field::text("tag", "seven", Store::No));
+            }
+            // Sparse, with norms of 4 bytes.
+            if i % 2 == 0 {
+                doc.add(Field::text("title", "half ".repeat(i % 4 + 1), Store::No));
+            }
+            // Indexed without norms.
+            doc.add(Field::string("id", format!("{i}"), Store::No));
+            builder.add_document(&doc).unwrap();
+        }
+        let segment = builder.build();
+
+        let codec = get_codec("Lucene95").unwrap();
+        let format = codec.norms_format();
+        let info = segment_info(50);
+        let mut dir = ByteBuffersDirectory::new();
+        format.write_norms(&segment, &mut dir, &info, &IoContext::Default).await.unwrap();
+        assert_eq!(format.files(&info), vec!["_4.nvm", "_4.nvd"]);
+
+        let norms = format.read_norms(&mut dir, &info, &IoContext::Read).await.unwrap();
+        let mut fields: Vec<&str> = norms.keys().map(String::as_str).collect();
+        fields.sort_unstable();
+        assert_eq!(fields, vec!["body", "tag", "title"]);
+        for field in fields {
+            assert_eq!(norms[field], segment.norms(field).unwrap().unwrap(), "{field}");
+        }
+        assert_eq!(norms["tag"][7], 100_000);
+        assert_eq!(norms["tag"][8], 0);
+
+        // The metadata must match the segment.
+        let error = format.read_norms(&mut dir, &segment_info(50), &IoContext::Read).await.unwrap_err();
+        assert!(matches!(LuceneError::find(error.as_ref()), Some(LuceneError::CorruptIndex(_))));
+        assert!(format.write_norms(&segment, &mut dir, &segment_info(49), &IoContext::Default).await.is_err());
+
+        // Flipping a bit is caught by the checksum.
+        let mut file = Vec::new();
+        dir.open("_4.nvd", &IoContext::Read).await.unwrap().read_to_end(&mut file).await.unwrap();
+        file[60] ^= 1;
+        let mut w = dir.create("_4.nvd", &IoContext::Default).await.unwrap();
+        w.write_all(&file).await.unwrap();
+        w.shutdown().await.unwrap();
+        let error = format.read_norms(&mut dir, &info, &IoContext::Read).await.unwrap_err();
+        assert!(matches!(LuceneError::find(error.as_ref()), Some(LuceneError::CorruptIndex(_))));
+        assert!(format!("{error:#}").contains("_4.nvd"), "{error:#}");
+    }
+}
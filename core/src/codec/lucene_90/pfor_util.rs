@@ -0,0 +1,197 @@
+use {
+    crate::{
+        codec::{ForUtil, BLOCK_SIZE},
+        io::{EncodingReadExt, EncodingWriteExt},
+        util::packed::bits_required,
+        BoxResult, LuceneError,
+    },
+    tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+};
+
+/// The largest number of values of a block that [PForUtil] stores as exceptions.
+pub const MAX_EXCEPTIONS: usize = 7;
+
+/// Encodes and decodes blocks of 128 integers with patched frame of reference (PFOR) encoding, as Lucene's postings
+/// format does for frequencies and positions.
+///
+/// The block is encoded with [ForUtil] using the number of bits of all but its largest values; up to
+/// [MAX_EXCEPTIONS] larger values keep their low bits there and store their high byte separately as a patch. A block
+/// of equal small values is stored as a single variable-length integer. The format matches Lucene's `PForUtil`.
+#[derive(Clone, Debug, Default)]
+pub struct PForUtil {
+    for_util: ForUtil,
+}
+
+impl PForUtil {
+    /// Creates a new encoder and decoder.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Encodes a block of values, which must fit in 31 bits since the token of a block has 5 bits for the number of
+    /// bits per value, to `output`. This fails with [LuceneError::InvalidArgument] if a value doesn't.
+    pub async fn encode<W: AsyncWrite + Unpin + ?Sized>(
+        &mut self,
+        values: &[u64; BLOCK_SIZE],
+        output: &mut W,
+    ) -> BoxResult<()> {
+        let mut sorted = *values;
+        sorted.sort_unstable();
+        let max = sorted[BLOCK_SIZE - 1];
+        if max > i32::MAX as u64 {
+            return Err(LuceneError::InvalidArgument(format!("value {max} doesn't fit in 31 bits")).into());
+        }
+
+        // Every value but the MAX_EXCEPTIONS largest must fit without a patch, and a patch holds at most 8 bits.
+        let top_value = sorted[BLOCK_SIZE - 1 - MAX_EXCEPTIONS];
+        let max_bits_required = bits_required(max);
+        let patched_bits_required = bits_required(top_value).max(max_bits_required.saturating_sub(8));
+        let max_unpatched_value = (1u64 << patched_bits_required) - 1;
+
+        let mut longs = *values;
+        let mut exceptions = Vec::with_capacity(MAX_EXCEPTIONS * 2);
+        for (i, long) in longs.iter_mut().enumerate() {
+            if *long > max_unpatched_value {
+                exceptions.push(i as u8);
+                exceptions.push((*long >> patched_bits_required) as u8);
+                *long &= max_unpatched_value;
+            }
+        }
+        let num_exceptions = exceptions.len() / 2;
+
+        if max_bits_required <= 8 && longs.iter().all(|&long| long == longs[0]) {
+            // Patches are applied without a shift when every value is the same.
+            for patch in exceptions.iter_mut().skip(1).step_by(2) {
+                *patch <<= patched_bits_required;
+            }
+            output.write_u8((num_exceptions << 5) as u8).await?;
+            output.write_vi64(longs[0] as i64).await?;
+        } else {
+            output.write_u8((num_exceptions << 5) as u8 | patched_bits_required as u8).await?;
+            self.for_util.encode(&longs, patched_bits_required, output).await?;
+        }
+        output.write_all(&exceptions).await?;
+        Ok(())
+    }
+
+    /// Reads the token of a block, returning its number of bits per value and of exceptions.
+    async fn read_token<R: AsyncRead + Unpin + ?Sized>(input: &mut R) -> BoxResult<(u32, usize)> {
+        let token = input.read_u8().await?;
+        Ok(((token & 0x1f) as u32, (token >> 5) as usize))
+    }
+
+    /// Reads and decodes a block of values from `input`. This fails with [LuceneError::CorruptIndex] if the block is
+    /// invalid.
+    pub async fn decode<R: AsyncRead + Unpin + ?Sized>(
+        &mut self,
+        input: &mut R,
+        values: &mut [u64; BLOCK_SIZE],
+    ) -> BoxResult<()> {
+        let (bits_per_value, num_exceptions) = Self::read_token(input).await?;
+        if bits_per_value == 0 {
+            let value = input.read_vi64().await?;
+            if value < 0 {
+                return Err(LuceneError::CorruptIndex(format!("Invalid PFOR block value: {value}").into()).into());
+            }
+            values.fill(value as u64);
+        } else {
+            self.for_util.decode(bits_per_value, input, values).await?;
+        }
+
+        let mut exceptions = [0; MAX_EXCEPTIONS * 2];
+        let exceptions = &mut exceptions[..num_exceptions * 2];
+        input.read_exact(exceptions).await?;
+        for exception in exceptions.chunks_exact(2) {
+            let index = exception[0] as usize;
+            if index >= BLOCK_SIZE {
+                return Err(LuceneError::CorruptIndex(format!("Invalid PFOR exception index: {index}").into()).into());
+            }
+            values[index] |= (exception[1] as u64) << bits_per_value;
+        }
+        Ok(())
+    }
+
+    /// Skips over a block of `input` without decoding it.
+    pub async fn skip<R: AsyncRead + Unpin + ?Sized>(&mut self, input: &mut R) -> BoxResult<()> {
+        let (bits_per_value, num_exceptions) = Self::read_token(input).await?;
+        if bits_per_value == 0 {
+            input.read_vi64().await?;
+        } else {
+            let mut bytes = [0; BLOCK_SIZE * 4];
+            let num_bytes = ForUtil::num_bytes(bits_per_value);
+            if num_bytes > bytes.len() {
+                return Err(
+                    LuceneError::CorruptIndex(format!("Invalid FOR bits per value: {bits_per_value}").into()).into()
+                );
+            }
+            input.read_exact(&mut bytes[..num_bytes]).await?;
+        }
+        let mut exceptions = [0; MAX_EXCEPTIONS * 2];
+        input.read_exact(&mut exceptions[..num_exceptions * 2]).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{
+            codec::{ForUtil, PForUtil, BLOCK_SIZE},
+            io::block_on,
+        },
+        pretty_assertions::assert_eq,
+    };
+
+    fn round_trip(pfor_util: &mut PForUtil, values: &[u64; BLOCK_SIZE]) -> usize {
+        block_on(async {
+            let mut output = Vec::new();
+            pfor_util.encode(values, &mut output).await.unwrap();
+            output.push(0xaa);
+
+            let mut input = output.as_slice();
+            let mut decoded = [0; BLOCK_SIZE];
+            pfor_util.decode(&mut input, &mut decoded).await.unwrap();
+            assert_eq!(&decoded, values);
+            assert_eq!(input, &[0xaa]);
+
+            let mut input = output.as_slice();
+            pfor_util.skip(&mut input).await.unwrap();
+            assert_eq!(input, &[0xaa]);
+            output.len() - 1
+        })
+    }
+
+    #[test]
+    fn test_pfor_util() {
+        let mut pfor_util = PForUtil::new();
+
+        // Small values with a few outliers: the outliers are patched rather than widening every value.
+        let mut values = [0u64; BLOCK_SIZE];
+        for (i, value) in values.iter_mut().enumerate() {
+            *value = (i as u64 * 7) % 13;
+        }
+        values[3] = 1000;
+        values[90] = 2000;
+        let len = round_trip(&mut pfor_util, &values);
+        assert_eq!(len, 1 + ForUtil::num_bytes(4) + 2 * 2);
+
+        // Equal values, with and without exceptions.
+        assert_eq!(round_trip(&mut pfor_util, &[1; BLOCK_SIZE]), 2);
+        let mut values = [1u64; BLOCK_SIZE];
+        values[127] = 201;
+        assert_eq!(round_trip(&mut pfor_util, &values), 2 + 2);
+        assert_eq!(round_trip(&mut pfor_util, &[0; BLOCK_SIZE]), 2);
+
+        // Too many outliers to patch.
+        let mut values = [5u64; BLOCK_SIZE];
+        for value in values.iter_mut().step_by(10) {
+            *value = i32::MAX as u64;
+        }
+        round_trip(&mut pfor_util, &values);
+
+        block_on(async {
+            let values = [i32::MAX as u64 + 1; BLOCK_SIZE];
+            assert!(pfor_util.encode(&values, &mut Vec::new()).await.is_err());
+        });
+    }
+}
@@ -1,15 +1,19 @@
 use {
     crate::{
-        codec::SegmentInfoFormat,
+        codec::{check_footer, write_footer, SegmentInfoFormat},
         index::{IndexHeader, SegmentInfo},
-        io::{Crc32Reader, Directory, EncodingReadExt},
+        io::{Crc32Reader, Crc32Writer, Directory, EncodingReadExt, EncodingWriteExt, IoContext},
+        locate_corruption,
         search::{get_sort_field_provider, Sort},
-        BoxResult, Id, LuceneError, Version,
+        BoxResult, ErrorContext, Id, LuceneError, Version,
     },
     async_trait::async_trait,
-    tokio::io::{AsyncRead, AsyncReadExt},
+    tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
 };
 
+/// The name of the only sort field provider, which writes every sort field of an index sort.
+const SORT_FIELD_PROVIDER_NAME: &str = "SortField";
+
 const CODEC_NAME: &str = "Lucene90SegmentInfo";
 const VERSION_START: u32 = 0;
 const VERSION_CURRENT: u32 = 0;
@@ -37,18 +41,18 @@ impl Lucene90SegmentInfoFormat {
             0 => None,
             1 => Some(Version::read_from_i32_le(r).await?),
             _ => {
-                return Err(LuceneError::CorruptIndex(format!(
-                    "Invalid has_min_version value found in segment index: {has_min_version}"
-                ))
+                return Err(LuceneError::CorruptIndex(
+                    format!("Invalid has_min_version value found in segment index: {has_min_version}").into(),
+                )
                 .into())
             }
         };
 
         let doc_count = r.read_i32_le().await?;
         if doc_count < 0 {
-            return Err(LuceneError::CorruptIndex(format!(
-                "Invalid doc_count value found in segment index: {doc_count}"
-            ))
+            return Err(LuceneError::CorruptIndex(
+                format!("Invalid doc_count value found in segment index: {doc_count}").into(),
+            )
             .into());
         }
         let doc_count = doc_count as u32;
@@ -59,9 +63,9 @@ impl Lucene90SegmentInfoFormat {
 
         let num_sort_fields = r.read_vi32().await?;
         if num_sort_fields < 0 {
-            return Err(LuceneError::CorruptIndex(format!(
-                "Invalid num_sort_fields value found in segment index: {num_sort_fields}"
-            ))
+            return Err(LuceneError::CorruptIndex(
+                format!("Invalid num_sort_fields value found in segment index: {num_sort_fields}").into(),
+            )
             .into());
         }
 
@@ -75,6 +79,7 @@ impl Lucene90SegmentInfoFormat {
             }
             Some(Sort::from_fields(sort_fields)?)
         };
+        check_footer(r).await?;
 
         Ok(SegmentInfo {
             version,
@@ -87,7 +92,61 @@ impl Lucene90SegmentInfoFormat {
             attributes,
             index_sort,
             files,
+            codec: None,
+        })
+    }
+}
+
+impl Lucene90SegmentInfoFormat {
+    async fn write_segment_info_to<W: AsyncWrite + Unpin>(
+        &self,
+        w: &mut Crc32Writer<W>,
+        segment_info: &SegmentInfo,
+    ) -> BoxResult<()> {
+        IndexHeader::new(CODEC_NAME, VERSION_CURRENT, segment_info.get_id())?.write(w, "").await?;
+        segment_info.get_version().write_to_i32_le(w).await?;
+        match segment_info.get_min_version() {
+            None => w.write_u8(0).await?,
+            Some(min_version) => {
+                w.write_u8(1).await?;
+                min_version.write_to_i32_le(w).await?;
+            }
+        }
+
+        w.write_i32_le(segment_info.get_max_doc() as i32).await?;
+        w.write_u8(if segment_info.is_compound_file() {
+            1
+        } else {
+            0xff
         })
+        .await?;
+        w.write_string_map(segment_info.get_diagnostics()).await?;
+        w.write_string_set(segment_info.get_files()).await?;
+        w.write_string_map(segment_info.get_attributes()).await?;
+
+        match segment_info.get_index_sort() {
+            None => w.write_vi32(0).await?,
+            Some(sort) => {
+                let provider = get_sort_field_provider(SORT_FIELD_PROVIDER_NAME)?;
+                w.write_vi32(sort.get_fields().len() as i32).await?;
+                for field in sort.get_fields() {
+                    w.write_string(SORT_FIELD_PROVIDER_NAME).await?;
+                    provider.write_sort_field(w, field.as_ref()).await?;
+                }
+            }
+        }
+
+        write_footer(w).await
+    }
+}
+
+/// Returns the name of the segment a file belongs to: its name up to the extension or generation, such as `_3` for
+/// `_3_1.liv`.
+fn segment_name_of(file_name: &str) -> &str {
+    let stem = file_name.split_once('.').map_or(file_name, |(stem, _)| stem);
+    match stem.char_indices().skip(1).find(|&(_, c)| c == '_') {
+        Some((i, _)) => &stem[..i],
+        None => stem,
     }
 }
 
@@ -104,11 +163,39 @@ impl SegmentInfoFormat for Lucene90SegmentInfoFormat {
         directory: &mut dyn Directory,
         segment_name: &str,
         segment_id: Id,
+        context: &IoContext,
     ) -> BoxResult<SegmentInfo> {
         let mut segment_file_name = String::with_capacity(segment_name.len() + 3);
         segment_file_name.push_str(segment_name);
         segment_file_name.push_str(".si");
-        let fd = directory.open(&segment_file_name).await?;
-        self.read_segment_info_from(&mut Crc32Reader::new(fd), segment_name, segment_id).await
+        let fd = directory.open(&segment_file_name, context).await?;
+        let mut r = Crc32Reader::buffered(fd);
+        self.read_segment_info_from(&mut r, segment_name, segment_id)
+            .await
+            .map_err(|e| locate_corruption(e, &segment_file_name, r.position()))
+            .with_context(|| format!("reading segment info {segment_file_name}"))
+    }
+
+    async fn write_segment_info(
+        &self,
+        directory: &mut dyn Directory,
+        segment_info: &SegmentInfo,
+        context: &IoContext,
+    ) -> BoxResult<()> {
+        let segment_name = segment_info.get_name();
+        if let Some(file) = segment_info.get_files().iter().find(|file| segment_name_of(file) != segment_name) {
+            return Err(LuceneError::InvalidArgument(format!(
+                "file {file:?} doesn't belong to segment {segment_name}"
+            ))
+            .into());
+        }
+
+        let segment_file_name = format!("{segment_name}.si");
+        let mut w = Crc32Writer::new(directory.create(&segment_file_name, context).await?);
+        self.write_segment_info_to(&mut w, segment_info)
+            .await
+            .with_context(|| format!("writing segment info {segment_file_name}"))?;
+        w.shutdown().await?;
+        Ok(())
     }
 }
@@ -0,0 +1,664 @@
+use {
+    crate::{
+        codec::{check_footer, write_footer, TermVectorsFormat},
+        document::TermVectorOptions,
+        index::{
+            file_name_from_generation, IndexHeader, LeafReader, SegmentInfo, TermVector, TermVectorTerm, TermVectors,
+        },
+        io::{CountingInput, Crc32Reader, Crc32Writer, Directory, EncodingReadExt, EncodingWriteExt, IoContext},
+        locate_corruption, BoxResult, ErrorContext, LuceneError,
+    },
+    async_trait::async_trait,
+    std::collections::HashMap,
+    tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+};
+
+const META_CODEC_NAME: &str = "Lucene90TermVectorsMeta";
+const INDEX_CODEC_NAME: &str = "Lucene90TermVectorsIndex";
+const DATA_CODEC_NAME: &str = "Lucene90TermVectorsData";
+const VERSION_START: u32 = 0;
+const VERSION_CURRENT: u32 = 0;
+
+/// The extension of term vectors metadata files.
+pub const TERM_VECTORS_META_EXTENSION: &str = "tvm";
+
+/// The extension of term vectors index files.
+pub const TERM_VECTORS_INDEX_EXTENSION: &str = "tvx";
+
+/// The extension of term vectors data files.
+pub const TERM_VECTORS_DATA_EXTENSION: &str = "tvd";
+
+/// The amount of encoded term vectors buffered before a chunk is written.
+const CHUNK_SIZE: usize = 4 << 10;
+
+/// The largest number of documents written to a chunk.
+const MAX_DOCS_PER_CHUNK: u32 = 128;
+
+/// Marks a chunk stored as is.
+const CHUNK_RAW: u8 = 0;
+
+/// Marks a chunk compressed with LZ4.
+const CHUNK_LZ4: u8 = 1;
+
+const FLAG_POSITIONS: u8 = 0x1;
+const FLAG_OFFSETS: u8 = 0x2;
+const FLAG_PAYLOADS: u8 = 0x4;
+
+/// Lucene 9.0 term vectors (`.tvm`, `.tvx` and `.tvd`) file format.
+///
+/// The term vectors of consecutive documents are buffered and written in chunks of about [CHUNK_SIZE] bytes or at
+/// most [MAX_DOCS_PER_CHUNK] documents, which are compressed with LZ4 when the `lz4` feature is enabled. Chunks
+/// compressed with LZ4 can only be read with that feature.
+///
+/// TermVectorsMeta (.tvm) --> IndexHeader + MaxDoc + NumFields + FieldName<sup>NumFields</sup> + NumChunks +
+///     DataLength + Footer
+///
+/// * MaxDoc (vi32): The number of documents in the segment.
+/// * FieldName (string): The names of the fields with term vectors; fields are referred to by their index in this
+///   list.
+/// * NumChunks (vi32): The number of chunks.
+/// * DataLength (vi64): The length of the data file, without its footer.
+///
+/// TermVectorsIndex (.tvx) --> IndexHeader + &lt;ChunkDocs, StartPointerDelta&gt;<sup>NumChunks</sup> + Footer
+///
+/// * ChunkDocs (vi32): The number of documents in the chunk.
+/// * StartPointerDelta (vi64): The offset of the chunk in the data file, less that of the previous chunk.
+///
+/// TermVectorsData (.tvd) --> IndexHeader + Chunk<sup>NumChunks</sup> + Footer
+///
+/// * Chunk --> DocBase (vi32) + ChunkDocs (vi32) + Compression (u8) + RawLength (vi32) + [CompressedLength (vi32)] +
+///   Bytes. Compression is 0 for raw bytes and 1 for LZ4; CompressedLength is only written for LZ4.
+/// * Once decompressed, Bytes --> Document<sup>ChunkDocs</sup>.
+/// * Document --> NumFields (vi32) + &lt;FieldNumber (vi32), Flags (u8), NumTerms (vi32),
+///   Term<sup>NumTerms</sup>&gt;<sup>NumFields</sup>. Flags has bit 0 set if positions are recorded, bit 1 for
+///   offsets and bit 2 for payloads.
+/// * Term --> PrefixLength (vi32) + SuffixLength (vi32) + SuffixBytes + Freq (vi32) +
+///   [PositionDelta (vi32)<sup>Freq</sup>] + [&lt;StartOffset (vi32), Length (vi32)&gt;<sup>Freq</sup>] +
+///   [&lt;PayloadLength (vi32), PayloadBytes&gt;<sup>Freq</sup>]. Terms share PrefixLength bytes with the previous
+///   term of the field.
+#[derive(Debug)]
+pub struct Lucene90TermVectorsFormat {}
+
+impl Lucene90TermVectorsFormat {
+    /// Create a new instance of [Lucene90TermVectorsFormat]
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    async fn read_meta_from<R: AsyncRead + Unpin>(
+        &self,
+        r: &mut Crc32Reader<R>,
+        segment: &SegmentInfo,
+    ) -> BoxResult<TermVectorsMeta> {
+        IndexHeader::read_from(r, META_CODEC_NAME, VERSION_START, VERSION_CURRENT, Some(segment.get_id()), "").await?;
+        let max_doc = read_count(r, "max_doc").await?;
+        if max_doc != segment.get_max_doc() {
+            return Err(LuceneError::CorruptIndex(
+                format!("term vectors hold {max_doc} documents, but the segment has {}", segment.get_max_doc()).into(),
+            )
+            .into());
+        }
+
+        let num_fields = read_count(r, "num_fields").await?;
+        let mut fields = Vec::with_capacity(num_fields.min(1024) as usize);
+        for _ in 0..num_fields {
+            fields.push(r.read_string().await?);
+        }
+        let num_chunks = read_count(r, "num_chunks").await?;
+        let data_length = r.read_vi64().await?;
+        check_footer(r).await?;
+
+        Ok(TermVectorsMeta {
+            fields,
+            num_chunks,
+            data_length,
+        })
+    }
+
+    async fn read_index_from<R: AsyncRead + Unpin>(
+        &self,
+        r: &mut Crc32Reader<R>,
+        segment: &SegmentInfo,
+        meta: &TermVectorsMeta,
+    ) -> BoxResult<Vec<(u32, u64)>> {
+        IndexHeader::read_from(r, INDEX_CODEC_NAME, VERSION_START, VERSION_CURRENT, Some(segment.get_id()), "").await?;
+        let mut chunks = Vec::with_capacity(meta.num_chunks.min(1024) as usize);
+        let mut num_docs = 0;
+        let mut start = 0;
+        for _ in 0..meta.num_chunks {
+            let chunk_docs = read_count(r, "chunk_docs").await?;
+            start = r.read_vi64().await?.wrapping_add(start);
+            num_docs += chunk_docs as u64;
+            chunks.push((chunk_docs, start as u64));
+        }
+        check_footer(r).await?;
+
+        if num_docs != segment.get_max_doc() as u64 {
+            return Err(LuceneError::CorruptIndex(
+                format!("chunks hold {num_docs} documents, but the segment has {}", segment.get_max_doc()).into(),
+            )
+            .into());
+        }
+        Ok(chunks)
+    }
+
+    async fn read_data_from<R: AsyncRead + Unpin>(
+        &self,
+        r: &mut Crc32Reader<R>,
+        segment: &SegmentInfo,
+        meta: &TermVectorsMeta,
+        chunks: &[(u32, u64)],
+    ) -> BoxResult<(Vec<TermVectors>, Vec<u64>)> {
+        IndexHeader::read_from(r, DATA_CODEC_NAME, VERSION_START, VERSION_CURRENT, Some(segment.get_id()), "").await?;
+        let mut term_vectors = Vec::with_capacity(segment.get_max_doc() as usize);
+        let mut field_bytes = vec![0; meta.fields.len()];
+        for &(chunk_docs, start) in chunks {
+            if r.position() != start {
+                return Err(LuceneError::CorruptIndex(
+                    format!("chunk starts at {}, but the index points to {start}", r.position()).into(),
+                )
+                .into());
+            }
+
+            let doc_base = read_count(r, "doc_base").await?;
+            if doc_base as usize != term_vectors.len() || read_count(r, "chunk_docs").await? != chunk_docs {
+                return Err(LuceneError::CorruptIndex(
+                    format!("chunk at {start} doesn't hold the {chunk_docs} documents from {}", term_vectors.len())
+                        .into(),
+                )
+                .into());
+            }
+
+            let (bytes, stored_length) = read_chunk_bytes(r).await?;
+            let mut chunk = CountingInput::new(bytes.as_slice());
+            let mut chunk_field_bytes = vec![0; meta.fields.len()];
+            for _ in 0..chunk_docs {
+                term_vectors.push(read_document(&mut chunk, &meta.fields, &mut chunk_field_bytes).await?);
+            }
+            let rest = chunk.into_inner();
+            if !rest.is_empty() {
+                return Err(LuceneError::CorruptIndex(
+                    format!("chunk at {start} has {} bytes after its documents", rest.len()).into(),
+                )
+                .into());
+            }
+
+            // Compressed chunks are shared between their fields in proportion to their decompressed bytes.
+            for (total, bytes_read) in field_bytes.iter_mut().zip(chunk_field_bytes) {
+                *total += (bytes_read as u128 * stored_length as u128 / bytes.len().max(1) as u128) as u64;
+            }
+        }
+
+        if r.position() as i64 != meta.data_length {
+            return Err(LuceneError::CorruptIndex(
+                format!("data ends at {}, but the metadata records {}", r.position(), meta.data_length).into(),
+            )
+            .into());
+        }
+        check_footer(r).await?;
+        Ok((term_vectors, field_bytes))
+    }
+
+    /// Reads the term vectors of every document of a segment, with the number of bytes each field takes in the data
+    /// file.
+    async fn read(
+        &self,
+        directory: &mut dyn Directory,
+        segment: &SegmentInfo,
+        context: &IoContext,
+    ) -> BoxResult<(Vec<TermVectors>, HashMap<String, u64>)> {
+        let name = segment.get_name();
+
+        let file_name = file_name_from_generation(name, TERM_VECTORS_META_EXTENSION, 0);
+        let mut r = Crc32Reader::buffered(directory.open(&file_name, context).await?);
+        let meta = self
+            .read_meta_from(&mut r, segment)
+            .await
+            .map_err(|e| locate_corruption(e, &file_name, r.position()))
+            .with_context(|| format!("reading term vectors metadata {file_name}"))?;
+
+        let file_name = file_name_from_generation(name, TERM_VECTORS_INDEX_EXTENSION, 0);
+        let mut r = Crc32Reader::buffered(directory.open(&file_name, context).await?);
+        let chunks = self
+            .read_index_from(&mut r, segment, &meta)
+            .await
+            .map_err(|e| locate_corruption(e, &file_name, r.position()))
+            .with_context(|| format!("reading term vectors index {file_name}"))?;
+
+        let file_name = file_name_from_generation(name, TERM_VECTORS_DATA_EXTENSION, 0);
+        let mut r = Crc32Reader::buffered(directory.open(&file_name, context).await?);
+        let (term_vectors, field_bytes) = self
+            .read_data_from(&mut r, segment, &meta, &chunks)
+            .await
+            .map_err(|e| locate_corruption(e, &file_name, r.position()))
+            .with_context(|| format!("reading term vectors {file_name}"))?;
+        Ok((term_vectors, meta.fields.into_iter().zip(field_bytes).collect()))
+    }
+}
+
+impl Default for Lucene90TermVectorsFormat {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait(?Send)]
+impl TermVectorsFormat for Lucene90TermVectorsFormat {
+    async fn read_term_vectors(
+        &self,
+        directory: &mut dyn Directory,
+        segment: &SegmentInfo,
+        context: &IoContext,
+    ) -> BoxResult<Vec<TermVectors>> {
+        Ok(self.read(directory, segment, context).await?.0)
+    }
+
+    async fn field_disk_usage(
+        &self,
+        directory: &mut dyn Directory,
+        segment: &SegmentInfo,
+        context: &IoContext,
+    ) -> BoxResult<HashMap<String, u64>> {
+        Ok(self.read(directory, segment, context).await?.1)
+    }
+
+    async fn write_term_vectors(
+        &self,
+        reader: &dyn LeafReader,
+        directory: &mut dyn Directory,
+        segment: &SegmentInfo,
+        context: &IoContext,
+    ) -> BoxResult<()> {
+        if reader.max_doc() != segment.get_max_doc() {
+            return Err(LuceneError::InvalidArgument(format!(
+                "segment {} has {} documents, but its reader has {}",
+                segment.get_name(),
+                segment.get_max_doc(),
+                reader.max_doc()
+            ))
+            .into());
+        }
+
+        let name = segment.get_name();
+        let file_name = file_name_from_generation(name, TERM_VECTORS_DATA_EXTENSION, 0);
+        let mut data = Crc32Writer::new(directory.create(&file_name, context).await?);
+        IndexHeader::new(DATA_CODEC_NAME, VERSION_CURRENT, segment.get_id())?.write(&mut data, "").await?;
+
+        let mut writer = ChunkWriter::default();
+        for doc in 0..reader.max_doc() {
+            writer.add_document(&reader.term_vectors(doc)?.unwrap_or_default()).await?;
+            if writer.chunk.len() >= CHUNK_SIZE || writer.chunk_docs == MAX_DOCS_PER_CHUNK {
+                writer.flush(&mut data).await?;
+            }
+        }
+        writer.flush(&mut data).await?;
+        let data_length = data.position();
+        write_footer(&mut data).await?;
+        data.shutdown().await?;
+
+        let file_name = file_name_from_generation(name, TERM_VECTORS_INDEX_EXTENSION, 0);
+        let mut index = Crc32Writer::new(directory.create(&file_name, context).await?);
+        IndexHeader::new(INDEX_CODEC_NAME, VERSION_CURRENT, segment.get_id())?.write(&mut index, "").await?;
+        let mut previous_start = 0;
+        for &(chunk_docs, start) in &writer.chunks {
+            index.write_vi32(chunk_docs as i32).await?;
+            index.write_vi64((start - previous_start) as i64).await?;
+            previous_start = start;
+        }
+        write_footer(&mut index).await?;
+        index.shutdown().await?;
+
+        let file_name = file_name_from_generation(name, TERM_VECTORS_META_EXTENSION, 0);
+        let mut meta = Crc32Writer::new(directory.create(&file_name, context).await?);
+        IndexHeader::new(META_CODEC_NAME, VERSION_CURRENT, segment.get_id())?.write(&mut meta, "").await?;
+        meta.write_vi32(reader.max_doc() as i32).await?;
+        meta.write_vi32(writer.fields.len() as i32).await?;
+        for field in &writer.fields {
+            meta.write_string(field).await?;
+        }
+        meta.write_vi32(writer.chunks.len() as i32).await?;
+        meta.write_vi64(data_length as i64).await?;
+        write_footer(&mut meta).await?;
+        meta.shutdown().await?;
+        Ok(())
+    }
+
+    fn files(&self, segment: &SegmentInfo) -> Vec<String> {
+        [TERM_VECTORS_META_EXTENSION, TERM_VECTORS_INDEX_EXTENSION, TERM_VECTORS_DATA_EXTENSION]
+            .into_iter()
+            .map(|extension| file_name_from_generation(segment.get_name(), extension, 0))
+            .collect()
+    }
+}
+
+/// The contents of a term vectors metadata file.
+#[derive(Debug)]
+struct TermVectorsMeta {
+    fields: Vec<String>,
+    num_chunks: u32,
+    data_length: i64,
+}
+
+/// Buffers the encoded term vectors of consecutive documents until they are written as a chunk.
+#[derive(Debug, Default)]
+struct ChunkWriter {
+    /// The names of the fields seen so far, in the order they were numbered.
+    fields: Vec<String>,
+    field_numbers: HashMap<String, u32>,
+
+    /// The encoded documents of the current chunk.
+    chunk: Vec<u8>,
+    chunk_docs: u32,
+    doc_base: u32,
+
+    /// The number of documents and the start of each chunk written.
+    chunks: Vec<(u32, u64)>,
+}
+
+impl ChunkWriter {
+    /// Encodes the term vectors of the next document into the current chunk.
+    async fn add_document(&mut self, term_vectors: &TermVectors) -> BoxResult<()> {
+        self.chunk.write_vi32(term_vectors.len() as i32).await?;
+        for (field, term_vector) in term_vectors.iter() {
+            let next_number = self.fields.len() as u32;
+            let number = *self.field_numbers.entry(field.to_string()).or_insert_with(|| {
+                self.fields.push(field.to_string());
+                next_number
+            });
+
+            let options = term_vector.options();
+            let flags = if options.positions {
+                FLAG_POSITIONS
+            } else {
+                0
+            } | if options.offsets {
+                FLAG_OFFSETS
+            } else {
+                0
+            } | if options.payloads {
+                FLAG_PAYLOADS
+            } else {
+                0
+            };
+            self.chunk.write_vi32(number as i32).await?;
+            self.chunk.write_u8(flags).await?;
+            self.chunk.write_vi32(term_vector.size() as i32).await?;
+
+            let mut previous: &[u8] = &[];
+            for term in term_vector.terms() {
+                let prefix = previous.iter().zip(&term.term).take_while(|(a, b)| a == b).count();
+                self.chunk.write_vi32(prefix as i32).await?;
+                self.chunk.write_vi32((term.term.len() - prefix) as i32).await?;
+                self.chunk.write_all(&term.term[prefix..]).await?;
+                self.chunk.write_vi32(term.freq as i32).await?;
+
+                let mut previous_position = 0;
+                for &position in &term.positions {
+                    self.chunk.write_vi32((position - previous_position) as i32).await?;
+                    previous_position = position;
+                }
+                for &(start, end) in &term.offsets {
+                    self.chunk.write_vi32(start as i32).await?;
+                    self.chunk.write_vi32((end - start) as i32).await?;
+                }
+                for payload in &term.payloads {
+                    self.chunk.write_vi32(payload.len() as i32).await?;
+                    self.chunk.write_all(payload).await?;
+                }
+                previous = &term.term;
+            }
+        }
+
+        self.chunk_docs += 1;
+        Ok(())
+    }
+
+    /// Writes the buffered documents as a chunk, if there are any.
+    async fn flush<W: AsyncWrite + Unpin>(&mut self, w: &mut Crc32Writer<W>) -> BoxResult<()> {
+        if self.chunk_docs == 0 {
+            return Ok(());
+        }
+
+        self.chunks.push((self.chunk_docs, w.position()));
+        w.write_vi32(self.doc_base as i32).await?;
+        w.write_vi32(self.chunk_docs as i32).await?;
+        write_chunk_bytes(w, &self.chunk).await?;
+
+        self.doc_base += self.chunk_docs;
+        self.chunk_docs = 0;
+        self.chunk.clear();
+        Ok(())
+    }
+}
+
+#[cfg(feature = "lz4")]
+async fn write_chunk_bytes<W: AsyncWrite + Unpin>(w: &mut W, bytes: &[u8]) -> BoxResult<()> {
+    let compressed = lz4_flex::block::compress(bytes);
+    w.write_u8(CHUNK_LZ4).await?;
+    w.write_vi32(bytes.len() as i32).await?;
+    w.write_vi32(compressed.len() as i32).await?;
+    w.write_all(&compressed).await?;
+    Ok(())
+}
+
+#[cfg(not(feature = "lz4"))]
+async fn write_chunk_bytes<W: AsyncWrite + Unpin>(w: &mut W, bytes: &[u8]) -> BoxResult<()> {
+    w.write_u8(CHUNK_RAW).await?;
+    w.write_vi32(bytes.len() as i32).await?;
+    w.write_all(bytes).await?;
+    Ok(())
+}
+
+/// Reads the bytes of a chunk, decompressing them if needed, with the number of bytes they are stored in.
+async fn read_chunk_bytes<R: AsyncRead + Unpin>(r: &mut R) -> BoxResult<(Vec<u8>, u64)> {
+    let compression = r.read_u8().await?;
+    let raw_len = read_count(r, "raw_length").await? as usize;
+    match compression {
+        CHUNK_RAW => {
+            let mut bytes = vec![0; raw_len];
+            r.read_exact(&mut bytes).await?;
+            Ok((bytes, raw_len as u64))
+        }
+        CHUNK_LZ4 => {
+            let mut compressed = vec![0; read_count(r, "compressed_length").await? as usize];
+            r.read_exact(&mut compressed).await?;
+            Ok((decompress(&compressed, raw_len)?, compressed.len() as u64))
+        }
+        _ => Err(LuceneError::CorruptIndex(format!("unknown chunk compression {compression}").into()).into()),
+    }
+}
+
+#[cfg(feature = "lz4")]
+fn decompress(compressed: &[u8], raw_len: usize) -> BoxResult<Vec<u8>> {
+    lz4_flex::block::decompress(compressed, raw_len)
+        .map_err(|e| LuceneError::CorruptIndex(format!("invalid LZ4 chunk: {e}").into()).into())
+}
+
+#[cfg(not(feature = "lz4"))]
+fn decompress(_compressed: &[u8], _raw_len: usize) -> BoxResult<Vec<u8>> {
+    Err(LuceneError::IllegalState("term vectors compressed with LZ4 require the lz4 feature".to_string()).into())
+}
+
+/// Decodes the term vectors of a document from a chunk, adding the number of bytes each field was decoded from to
+/// `field_bytes`, which is indexed by field number.
+async fn read_document<R: AsyncRead + Unpin>(
+    r: &mut CountingInput<R>,
+    fields: &[String],
+    field_bytes: &mut [u64],
+) -> BoxResult<TermVectors> {
+    let mut term_vectors = TermVectors::new();
+    for _ in 0..read_count(r, "num_fields").await? {
+        r.take_bytes_read();
+        let number = read_count(r, "field_number").await?;
+        let Some(field) = fields.get(number as usize) else {
+            return Err(LuceneError::CorruptIndex(format!("invalid field number {number}").into()).into());
+        };
+
+        let flags = r.read_u8().await?;
+        if flags & !(FLAG_POSITIONS | FLAG_OFFSETS | FLAG_PAYLOADS) != 0 {
+            return Err(LuceneError::CorruptIndex(format!("invalid term vector flags {flags:#x}").into()).into());
+        }
+        let options = TermVectorOptions {
+            positions: flags & FLAG_POSITIONS != 0,
+            offsets: flags & FLAG_OFFSETS != 0,
+            payloads: flags & FLAG_PAYLOADS != 0,
+        };
+
+        let mut term_vector = TermVector::new(options);
+        let mut term = Vec::new();
+        for _ in 0..read_count(r, "num_terms").await? {
+            let prefix = read_count(r, "prefix_length").await? as usize;
+            if prefix > term.len() {
+                return Err(LuceneError::CorruptIndex(
+                    format!("term prefix of {prefix} bytes is longer than the previous term").into(),
+                )
+                .into());
+            }
+            term.truncate(prefix);
+            let suffix = read_count(r, "suffix_length").await? as usize;
+            term.resize(prefix + suffix, 0);
+            r.read_exact(&mut term[prefix..]).await?;
+
+            let freq = read_count(r, "freq").await?;
+            let mut entry = TermVectorTerm {
+                term: term.clone(),
+                freq,
+                ..Default::default()
+            };
+            if options.positions {
+                let mut position = 0_u32;
+                for _ in 0..freq {
+                    position = position.checked_add(read_count(r, "position_delta").await?).ok_or_else(|| {
+                        LuceneError::CorruptIndex("term vector position overflows".to_string().into())
+                    })?;
+                    entry.positions.push(position);
+                }
+            }
+            if options.offsets {
+                for _ in 0..freq {
+                    let start = read_count(r, "start_offset").await?;
+                    let length = read_count(r, "offset_length").await?;
+                    entry.offsets.push((start, start.saturating_add(length)));
+                }
+            }
+            if options.payloads {
+                for _ in 0..freq {
+                    let mut payload = vec![0; read_count(r, "payload_length").await? as usize];
+                    r.read_exact(&mut payload).await?;
+                    entry.payloads.push(payload);
+                }
+            }
+
+            term_vector
+                .add(entry)
+                .map_err(|e| LuceneError::CorruptIndex(format!("invalid term vector of {field:?}: {e}").into()))?;
+        }
+        term_vectors.insert(field.as_str(), term_vector);
+        field_bytes[number as usize] += r.take_bytes_read();
+    }
+    Ok(term_vectors)
+}
+
+/// Reads a vi32 that must not be negative.
+async fn read_count<R: AsyncRead + Unpin>(r: &mut R, name: &str) -> BoxResult<u32> {
+    let value = r.read_vi32().await?;
+    u32::try_from(value).map_err(|_| LuceneError::CorruptIndex(format!("invalid {name}: {value}").into()).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{
+            analysis::SimpleAnalyzer,
+            codec::get_codec,
+            document::{Document, Field, Store, TermVectorOptions},
+            index::{LeafReader, MemorySegmentBuilder, SegmentInfo, TermVectors},
+            io::{ByteBuffersDirectory, Directory, IoContext},
+            Id, LuceneError, LATEST,
+        },
+        pretty_assertions::assert_eq,
+        std::{
+            collections::{HashMap, HashSet},
+            sync::Arc,
+        },
+        tokio::io::{AsyncReadExt, AsyncWriteExt},
+    };
+
+    fn segment_info(max_doc: u32) -> SegmentInfo {
+        SegmentInfo {
+            name: "_2".to_string(),
+            id: Id::random_id(),
+            max_doc,
+            attributes: HashMap::new(),
+            diagnostics: HashMap::new(),
+            files: HashSet::new(),
+            version: LATEST,
+            min_version: Some(LATEST),
+            is_compound_file: false,
+            index_sort: None,
+            codec: None,
+        }
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_term_vectors_roundtrip() {
+        let mut builder = MemorySegmentBuilder::new(Arc::new(SimpleAnalyzer));
+        for i in 0..300 {
+            let mut doc = Document::new();
+            if i % 3 != 0 {
+                let body = format!("document {i} of many documents, number {i}");
+                let field = Field::text("body", body, Store::No);
+                doc.add(field.with_term_vectors(TermVectorOptions::POSITIONS_AND_OFFSETS).unwrap());
+            }
+            if i % 5 == 0 {
+                let options = TermVectorOptions {
+                    positions: true,
+                    offsets: false,
+                    payloads: true,
+                };
+                doc.add(Field::string("id", format!("{i}"), Store::No).with_term_vectors(options).unwrap());
+            }
+            doc.add(Field::text("title", "not stored in term vectors", Store::No));
+            builder.add_document(&doc).unwrap();
+        }
+        let segment = builder.build();
+
+        let codec = get_codec("Lucene95").unwrap();
+        let format = codec.term_vectors_format();
+        let info = segment_info(300);
+        let mut dir = ByteBuffersDirectory::new();
+        format.write_term_vectors(&segment, &mut dir, &info, &IoContext::Default).await.unwrap();
+        let mut files = dir.read_dir().await.unwrap();
+        files.sort();
+        assert_eq!(files, vec!["_2.tvd", "_2.tvm", "_2.tvx"]);
+        assert_eq!(format.files(&info), vec!["_2.tvm", "_2.tvx", "_2.tvd"]);
+
+        let read = format.read_term_vectors(&mut dir, &info, &IoContext::Read).await.unwrap();
+        assert_eq!(read.len(), 300);
+        for (doc, term_vectors) in read.iter().enumerate() {
+            assert_eq!(term_vectors, &segment.term_vectors(doc as u32).unwrap().unwrap_or_default());
+        }
+        assert_eq!(read[0].len(), 1);
+        let body = read[1].get("body").unwrap();
+        assert_eq!(body.get(b"1").unwrap().positions, vec![1, 6]);
+        assert_eq!(body.get(b"documents").unwrap().offsets, vec![(19, 28)]);
+        assert_eq!(read[5].get("id").unwrap().terms()[0].payloads, vec![Vec::<u8>::new()]);
+        assert_eq!(read[3], TermVectors::new());
+
+        // The segment must match the metadata.
+        let error = format.read_term_vectors(&mut dir, &segment_info(300), &IoContext::Read).await.unwrap_err();
+        assert!(matches!(LuceneError::find(error.as_ref()), Some(LuceneError::CorruptIndex(_))));
+        assert!(format.write_term_vectors(&segment, &mut dir, &segment_info(299), &IoContext::Default).await.is_err());
+
+        // Flipping a bit is caught by the checksum.
+        let mut file = Vec::new();
+        dir.open("_2.tvd", &IoContext::Read).await.unwrap().read_to_end(&mut file).await.unwrap();
+        file[100] ^= 1;
+        let mut w = dir.create("_2.tvd", &IoContext::Default).await.unwrap();
+        w.write_all(&file).await.unwrap();
+        w.shutdown().await.unwrap();
+        let error = format.read_term_vectors(&mut dir, &info, &IoContext::Read).await.unwrap_err();
+        assert!(matches!(LuceneError::find(error.as_ref()), Some(LuceneError::CorruptIndex(_))));
+        assert!(format!("{error:#}").contains("_2.tvd"), "{error:#}");
+    }
+}
@@ -1,5 +1,9 @@
-use crate::codec::{Codec, Lucene90SegmentInfoFormat, SegmentInfoFormat};
+use crate::codec::{
+    Codec, CompoundFormat, LiveDocsFormat, Lucene90CompoundFormat, Lucene90LiveDocsFormat, Lucene90NormsFormat,
+    Lucene90SegmentInfoFormat, Lucene90TermVectorsFormat, NormsFormat, SegmentInfoFormat, TermVectorsFormat,
+};
 
+/// The codec of Lucene 9.5 indexes, named `"Lucene95"`.
 #[derive(Debug)]
 pub struct Lucene95Codec {}
 
@@ -10,6 +14,7 @@ impl Default for Lucene95Codec {
 }
 
 impl Lucene95Codec {
+    /// Creates the codec.
     pub fn new() -> Self {
         Self {}
     }
@@ -23,4 +28,20 @@ impl Codec for Lucene95Codec {
     fn segment_info_format(&self) -> Box<dyn SegmentInfoFormat> {
         Box::new(Lucene90SegmentInfoFormat::new())
     }
+
+    fn live_docs_format(&self) -> Box<dyn LiveDocsFormat> {
+        Box::new(Lucene90LiveDocsFormat::new())
+    }
+
+    fn norms_format(&self) -> Box<dyn NormsFormat> {
+        Box::new(Lucene90NormsFormat::new())
+    }
+
+    fn term_vectors_format(&self) -> Box<dyn TermVectorsFormat> {
+        Box::new(Lucene90TermVectorsFormat::new())
+    }
+
+    fn compound_format(&self) -> Box<dyn CompoundFormat> {
+        Box::new(Lucene90CompoundFormat::new())
+    }
 }
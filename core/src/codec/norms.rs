@@ -0,0 +1,44 @@
+use {
+    crate::{
+        index::{LeafReader, SegmentInfo},
+        io::{Directory, IoContext},
+        BoxResult,
+    },
+    async_trait::async_trait,
+    std::{collections::HashMap, fmt::Debug, sync::Arc},
+};
+
+/// Controls the format of the norms files, which record the per-document length normalization value of each field
+/// (see [crate::search::Similarity::compute_norm]).
+#[async_trait(?Send)]
+pub trait NormsFormat: Debug {
+    /// Reads the norms of every field of a segment that has them, indexed by document id as
+    /// [LeafReader::norms] returns them.
+    async fn read_norms(
+        &self,
+        directory: &mut dyn Directory,
+        segment: &SegmentInfo,
+        context: &IoContext,
+    ) -> BoxResult<HashMap<String, Arc<[i64]>>>;
+
+    /// Writes the norms of every indexed field of `reader`, which holds the data of `segment`.
+    async fn write_norms(
+        &self,
+        reader: &dyn LeafReader,
+        directory: &mut dyn Directory,
+        segment: &SegmentInfo,
+        context: &IoContext,
+    ) -> BoxResult<()>;
+
+    /// Returns the number of bytes the norms of each field of a segment that has them take in its data file, counted
+    /// by decoding them through a [crate::io::CountingInput]. Fields whose norms are all the same take no bytes.
+    async fn field_disk_usage(
+        &self,
+        directory: &mut dyn Directory,
+        segment: &SegmentInfo,
+        context: &IoContext,
+    ) -> BoxResult<HashMap<String, u64>>;
+
+    /// Returns the names of the norms files of a segment.
+    fn files(&self, segment: &SegmentInfo) -> Vec<String>;
+}
@@ -0,0 +1,121 @@
+use {
+    crate::{
+        codec::{Codec, CodecHeader, Lucene95Codec},
+        LuceneError,
+    },
+    once_cell::sync::Lazy,
+    std::{collections::HashMap, sync::RwLock},
+};
+
+/// Creates a new instance of a codec.
+pub type CodecFactory = fn() -> Box<dyn Codec>;
+
+/// The codecs built into this crate, which are always available.
+const BUILT_IN_CODECS: &[CodecFactory] = &[|| Box::new(Lucene95Codec::new())];
+
+/// The codecs available by name: the built-in ones, followed by those added with [register_codec].
+static CODECS: Lazy<RwLock<HashMap<String, CodecFactory>>> =
+    Lazy::new(|| RwLock::new(BUILT_IN_CODECS.iter().map(|factory| (factory().get_name(), *factory)).collect()));
+
+impl dyn Codec {
+    /// Creates a new instance of the codec named `name`, as recorded in the segment infos of an index.
+    ///
+    /// This fails with [LuceneError::UnknownCodec] if no codec of that name is built in or has been registered with
+    /// [register_codec].
+    pub fn for_name(name: &str) -> Result<Box<dyn Codec>, LuceneError> {
+        let factory = CODECS.read().unwrap().get(name).copied();
+        match factory {
+            Some(factory) => Ok(factory()),
+            None => Err(LuceneError::UnknownCodec(name.to_string())),
+        }
+    }
+}
+
+/// Creates a new instance of a codec given its name. See [Codec::for_name](trait.Codec.html#method.for_name).
+pub fn get_codec(name: &str) -> Result<Box<dyn Codec>, LuceneError> {
+    <dyn Codec>::for_name(name)
+}
+
+/// Makes a custom codec available by name, so that segments written with it can be read. The name is that returned
+/// by the [Codec::get_name] of the codecs `factory` creates.
+///
+/// This fails with [LuceneError::InvalidCodecName] if the name can't be written to an index, and with
+/// [LuceneError::InvalidArgument] if a codec of that name is already available.
+pub fn register_codec(factory: CodecFactory) -> Result<(), LuceneError> {
+    let name = factory().get_name();
+    CodecHeader::new(&name, 0)?;
+
+    let mut codecs = CODECS.write().unwrap();
+    if codecs.contains_key(&name) {
+        return Err(LuceneError::InvalidArgument(format!("a codec named {name:?} is already registered")));
+    }
+    codecs.insert(name, factory);
+    Ok(())
+}
+
+/// Returns the names of the available codecs, in alphabetical order.
+pub fn available_codecs() -> Vec<String> {
+    let mut names: Vec<String> = CODECS.read().unwrap().keys().cloned().collect();
+    names.sort_unstable();
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{
+            codec::{
+                available_codecs, get_codec, register_codec, Codec, CompoundFormat, LiveDocsFormat, Lucene95Codec,
+                NormsFormat, SegmentInfoFormat, TermVectorsFormat,
+            },
+            LuceneError,
+        },
+        pretty_assertions::assert_eq,
+    };
+
+    /// A codec that writes the same files as [Lucene95Codec] under another name.
+    #[derive(Debug)]
+    struct RenamedCodec(&'static str);
+
+    impl Codec for RenamedCodec {
+        fn get_name(&self) -> String {
+            self.0.to_string()
+        }
+
+        fn segment_info_format(&self) -> Box<dyn SegmentInfoFormat> {
+            Lucene95Codec::new().segment_info_format()
+        }
+
+        fn live_docs_format(&self) -> Box<dyn LiveDocsFormat> {
+            Lucene95Codec::new().live_docs_format()
+        }
+
+        fn norms_format(&self) -> Box<dyn NormsFormat> {
+            Lucene95Codec::new().norms_format()
+        }
+
+        fn term_vectors_format(&self) -> Box<dyn TermVectorsFormat> {
+            Lucene95Codec::new().term_vectors_format()
+        }
+
+        fn compound_format(&self) -> Box<dyn CompoundFormat> {
+            Lucene95Codec::new().compound_format()
+        }
+    }
+
+    #[test]
+    fn test_register_codec() {
+        assert_eq!(<dyn Codec>::for_name("Lucene95").unwrap().get_name(), "Lucene95");
+        assert!(matches!(<dyn Codec>::for_name("Custom"), Err(LuceneError::UnknownCodec(_))));
+
+        register_codec(|| Box::new(RenamedCodec("Custom"))).unwrap();
+        assert_eq!(<dyn Codec>::for_name("Custom").unwrap().get_name(), "Custom");
+        assert_eq!(get_codec("Custom").unwrap().get_name(), "Custom");
+        assert!(available_codecs().iter().any(|name| name == "Custom"));
+        assert!(available_codecs().iter().any(|name| name == "Lucene95"));
+
+        assert!(matches!(register_codec(|| Box::new(RenamedCodec("Custom"))), Err(LuceneError::InvalidArgument(_))));
+        assert!(register_codec(|| Box::new(RenamedCodec("Lucene95"))).is_err());
+        assert!(matches!(register_codec(|| Box::new(RenamedCodec("Café"))), Err(LuceneError::InvalidCodecName(_))));
+    }
+}
@@ -1,5 +1,9 @@
 use {
-    crate::{index::SegmentInfo, io::Directory, BoxResult, Id},
+    crate::{
+        index::SegmentInfo,
+        io::{Directory, IoContext},
+        BoxResult, Id,
+    },
     async_trait::async_trait,
     std::fmt::Debug,
 };
@@ -13,5 +17,14 @@ pub trait SegmentInfoFormat: Debug {
         directory: &mut dyn Directory,
         segment_name: &str,
         segment_id: Id,
+        context: &IoContext,
     ) -> BoxResult<SegmentInfo>;
+
+    /// Write segment info to the given directory, in a file named after the segment.
+    async fn write_segment_info(
+        &self,
+        directory: &mut dyn Directory,
+        segment_info: &SegmentInfo,
+        context: &IoContext,
+    ) -> BoxResult<()>;
 }
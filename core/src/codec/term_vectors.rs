@@ -0,0 +1,46 @@
+use {
+    crate::{
+        index::{LeafReader, SegmentInfo, TermVectors},
+        io::{Directory, IoContext},
+        BoxResult,
+    },
+    async_trait::async_trait,
+    std::{collections::HashMap, fmt::Debug},
+};
+
+/// Controls the format of the term vectors files, which record the term vectors of each document of a segment (see
+/// [crate::document::Field::with_term_vectors]).
+#[async_trait(?Send)]
+pub trait TermVectorsFormat: Debug {
+    /// Reads the term vectors of every document of a segment, in document order. Documents without term vectors
+    /// have empty ones.
+    async fn read_term_vectors(
+        &self,
+        directory: &mut dyn Directory,
+        segment: &SegmentInfo,
+        context: &IoContext,
+    ) -> BoxResult<Vec<TermVectors>>;
+
+    /// Writes the term vectors of every document of `reader`, which holds the data of `segment`, including its
+    /// deleted documents.
+    async fn write_term_vectors(
+        &self,
+        reader: &dyn LeafReader,
+        directory: &mut dyn Directory,
+        segment: &SegmentInfo,
+        context: &IoContext,
+    ) -> BoxResult<()>;
+
+    /// Returns the number of bytes the term vectors of each field take in the data file of a segment, counted by
+    /// decoding them through a [crate::io::CountingInput]. The bytes of compressed chunks are shared between their
+    /// fields in proportion to their decompressed bytes.
+    async fn field_disk_usage(
+        &self,
+        directory: &mut dyn Directory,
+        segment: &SegmentInfo,
+        context: &IoContext,
+    ) -> BoxResult<HashMap<String, u64>>;
+
+    /// Returns the names of the term vectors files of a segment.
+    fn files(&self, segment: &SegmentInfo) -> Vec<String>;
+}
@@ -0,0 +1,17 @@
+mod date_field;
+#[allow(clippy::module_inception)]
+mod document;
+mod field;
+mod ip_address_point;
+#[cfg(feature = "serde")]
+mod json;
+mod lat_lon_point;
+mod lat_lon_shape;
+mod numeric_doc_values_field;
+mod object_value;
+mod range_field;
+
+pub use {
+    date_field::*, document::*, field::*, ip_address_point::*, lat_lon_point::*, lat_lon_shape::*,
+    numeric_doc_values_field::*, object_value::*, range_field::*,
+};
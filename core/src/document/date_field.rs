@@ -0,0 +1,326 @@
+use {
+    crate::{document::Field, search::NumericDocValuesRangeQuery, BoxError, BoxResult, LuceneError},
+    chrono::{
+        DateTime, Datelike, Duration, Months, NaiveDate, NaiveDateTime, NaiveTime, SecondsFormat, TimeZone, Timelike,
+        Utc,
+    },
+};
+
+/// Factories for dates and times, stored as milliseconds since the Unix epoch with [Field::numeric_doc_values], as in
+/// Lucene's `DateTools` and Elasticsearch's `date` field.
+///
+/// Range queries accept date-math expressions, such as `now-7d/d`, through [DateMathParser].
+#[derive(Clone, Copy, Debug)]
+pub struct DateField;
+
+impl DateField {
+    /// Creates a field recording `value` as milliseconds since the epoch.
+    pub fn new_field(name: &str, value: DateTime<Utc>) -> Field {
+        Field::numeric_doc_values(name, value.timestamp_millis())
+    }
+
+    /// Creates a query matching the documents whose date is from `lower` to `upper`, inclusive. See
+    /// [crate::document::NumericDocValuesField::new_slow_range_query] for how values are checked.
+    pub fn new_range_query(field: &str, lower: DateTime<Utc>, upper: DateTime<Utc>) -> NumericDocValuesRangeQuery {
+        NumericDocValuesRangeQuery::new(field, lower.timestamp_millis(), upper.timestamp_millis())
+    }
+
+    /// Converts milliseconds since the epoch, as held by a field's doc values, back to a date, or returns `None` if
+    /// it's out of range.
+    pub fn to_date(millis: i64) -> Option<DateTime<Utc>> {
+        DateTime::from_timestamp_millis(millis)
+    }
+}
+
+/// The formats that dates are parsed from and printed in: either [chrono] `strftime` patterns, or one of the named
+/// formats [DateFormat::RFC_3339] and [DateFormat::EPOCH_MILLIS]. Parsing tries each format in turn; printing uses
+/// the first.
+///
+/// A pattern without a time zone is read as UTC, and one without a time as midnight.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DateFormat {
+    patterns: Vec<String>,
+}
+
+impl DateFormat {
+    /// The named format for RFC 3339 timestamps, such as `2024-03-01T12:30:00Z` or `2024-03-01T12:30:00.5+01:00`.
+    pub const RFC_3339: &'static str = "rfc3339";
+
+    /// The named format for a number of milliseconds since the Unix epoch.
+    pub const EPOCH_MILLIS: &'static str = "epoch_millis";
+
+    /// Creates a format from its patterns, in the order they're tried. This fails with [LuceneError::InvalidArgument]
+    /// if there are none.
+    pub fn new<S: AsRef<str>>(patterns: &[S]) -> BoxResult<Self> {
+        if patterns.is_empty() {
+            return Err(LuceneError::InvalidArgument("a date format needs at least one pattern".to_string()).into());
+        }
+
+        Ok(Self {
+            patterns: patterns.iter().map(|pattern| pattern.as_ref().to_string()).collect(),
+        })
+    }
+
+    /// Returns the patterns, in the order they're tried.
+    #[inline]
+    pub fn patterns(&self) -> &[String] {
+        &self.patterns
+    }
+
+    /// Parses `text` with the first pattern that matches all of it. This fails with [LuceneError::InvalidArgument]
+    /// if none does.
+    pub fn parse(&self, text: &str) -> BoxResult<DateTime<Utc>> {
+        self.patterns.iter().find_map(|pattern| Self::parse_with(pattern, text)).ok_or_else(|| {
+            LuceneError::InvalidArgument(format!("{text:?} doesn't match the date formats {:?}", self.patterns)).into()
+        })
+    }
+
+    fn parse_with(pattern: &str, text: &str) -> Option<DateTime<Utc>> {
+        match pattern {
+            Self::RFC_3339 => DateTime::parse_from_rfc3339(text).ok().map(|date| date.to_utc()),
+            Self::EPOCH_MILLIS => text.parse().ok().and_then(DateTime::from_timestamp_millis),
+            _ => {
+                if let Ok(date) = DateTime::parse_from_str(text, pattern) {
+                    return Some(date.to_utc());
+                }
+                if let Ok(date) = NaiveDateTime::parse_from_str(text, pattern) {
+                    return Some(date.and_utc());
+                }
+                NaiveDate::parse_from_str(text, pattern).ok().map(|date| date.and_time(NaiveTime::MIN).and_utc())
+            }
+        }
+    }
+
+    /// Prints `date` in the first pattern.
+    pub fn format(&self, date: DateTime<Utc>) -> String {
+        match self.patterns[0].as_str() {
+            Self::RFC_3339 => date.to_rfc3339_opts(SecondsFormat::AutoSi, true),
+            Self::EPOCH_MILLIS => date.timestamp_millis().to_string(),
+            pattern => date.format(pattern).to_string(),
+        }
+    }
+}
+
+impl Default for DateFormat {
+    /// RFC 3339 timestamps, then the same without a time zone, then dates alone (`2024-03-01`), then milliseconds
+    /// since the epoch.
+    fn default() -> Self {
+        Self {
+            patterns: [Self::RFC_3339, "%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%d", Self::EPOCH_MILLIS]
+                .into_iter()
+                .map(str::to_string)
+                .collect(),
+        }
+    }
+}
+
+/// Evaluates date-math expressions, in the syntax of Elasticsearch: an anchor followed by any number of operations,
+/// applied in order.
+///
+/// The anchor is either `now` or a date in the parser's [DateFormat] followed by `||`. Each operation is one of:
+/// * `+1d` or `-7d`: adds or subtracts a number of units;
+/// * `/d`: rounds to the unit, down to its start for a lower bound, or up to its last millisecond for an upper bound.
+///
+/// The units are `y` (years), `M` (months), `w` (weeks, starting on Monday), `d` (days), `h` or `H` (hours), `m`
+/// (minutes) and `s` (seconds). For example, `now-7d/d` is the start of the day a week ago, and
+/// `2024-03-01||+1M/M` is the start of April 2024. All dates are in UTC.
+#[derive(Clone, Debug, Default)]
+pub struct DateMathParser {
+    format: DateFormat,
+}
+
+impl DateMathParser {
+    /// Creates a parser reading anchor dates in `format`.
+    pub fn new(format: DateFormat) -> Self {
+        Self {
+            format,
+        }
+    }
+
+    /// Returns the format of anchor dates.
+    #[inline]
+    pub fn format(&self) -> &DateFormat {
+        &self.format
+    }
+
+    /// Evaluates `expression` with `now` as the current time, rounding up if `round_up` is set, as for an inclusive
+    /// upper bound. This fails with [LuceneError::InvalidArgument] if the expression is malformed, or its result is
+    /// out of range.
+    pub fn parse(&self, expression: &str, now: DateTime<Utc>, round_up: bool) -> BoxResult<DateTime<Utc>> {
+        let (mut date, mut math) = match expression.strip_prefix("now") {
+            Some(math) => (now, math),
+            None => match expression.split_once("||") {
+                Some((anchor, math)) => (self.format.parse(anchor)?, math),
+                None => return self.format.parse(expression),
+            },
+        };
+
+        let invalid = |message: &str| -> BoxError {
+            LuceneError::InvalidArgument(format!("invalid date math {expression:?}: {message}")).into()
+        };
+        while let Some(op) = math.chars().next() {
+            math = &math[op.len_utf8()..];
+            let digits = math.find(|c: char| !c.is_ascii_digit()).unwrap_or(math.len());
+            let amount = match (op, digits) {
+                ('/', 0) => None,
+                ('/', _) => return Err(invalid("rounding takes no amount")),
+                ('+' | '-', 0) => Some(1),
+                ('+' | '-', _) => Some(math[..digits].parse::<u32>().map_err(|_| invalid("amount is too large"))?),
+                _ => return Err(invalid(&format!("unexpected {op:?}"))),
+            };
+            math = &math[digits..];
+
+            let Some(unit) = math.chars().next() else {
+                return Err(invalid("missing unit"));
+            };
+            math = &math[unit.len_utf8()..];
+            date = match amount {
+                Some(amount) => Self::add(
+                    date,
+                    unit,
+                    if op == '-' {
+                        -i64::from(amount)
+                    } else {
+                        i64::from(amount)
+                    },
+                ),
+                None => Self::round(date, unit, round_up),
+            }
+            .ok_or_else(|| invalid(&format!("unknown unit {unit:?}, or the date is out of range")))?;
+        }
+        Ok(date)
+    }
+
+    /// Creates a query matching the documents of `field` whose date is within the expressions `lower` and `upper`,
+    /// inclusive, where `lower` rounds down and `upper` rounds up. This fails as [DateMathParser::parse] does.
+    pub fn range_query(
+        &self,
+        field: &str,
+        lower: &str,
+        upper: &str,
+        now: DateTime<Utc>,
+    ) -> BoxResult<NumericDocValuesRangeQuery> {
+        Ok(DateField::new_range_query(field, self.parse(lower, now, false)?, self.parse(upper, now, true)?))
+    }
+
+    fn add(date: DateTime<Utc>, unit: char, amount: i64) -> Option<DateTime<Utc>> {
+        let months = |n: i64| u32::try_from(n.unsigned_abs()).ok().map(Months::new);
+        match unit {
+            'y' | 'M' => {
+                let n = if unit == 'y' {
+                    amount.checked_mul(12)?
+                } else {
+                    amount
+                };
+                match n >= 0 {
+                    true => date.checked_add_months(months(n)?),
+                    false => date.checked_sub_months(months(n)?),
+                }
+            }
+            'w' => date.checked_add_signed(Duration::try_weeks(amount)?),
+            'd' => date.checked_add_signed(Duration::try_days(amount)?),
+            'h' | 'H' => date.checked_add_signed(Duration::try_hours(amount)?),
+            'm' => date.checked_add_signed(Duration::try_minutes(amount)?),
+            's' => date.checked_add_signed(Duration::try_seconds(amount)?),
+            _ => None,
+        }
+    }
+
+    fn round(date: DateTime<Utc>, unit: char, round_up: bool) -> Option<DateTime<Utc>> {
+        let start = match unit {
+            'y' => Utc.with_ymd_and_hms(date.year(), 1, 1, 0, 0, 0).single()?,
+            'M' => Utc.with_ymd_and_hms(date.year(), date.month(), 1, 0, 0, 0).single()?,
+            'w' => {
+                let day = Utc.with_ymd_and_hms(date.year(), date.month(), date.day(), 0, 0, 0).single()?;
+                day.checked_sub_signed(Duration::try_days(date.weekday().num_days_from_monday().into())?)?
+            }
+            'd' => Utc.with_ymd_and_hms(date.year(), date.month(), date.day(), 0, 0, 0).single()?,
+            'h' | 'H' => date.with_nanosecond(0)?.with_second(0)?.with_minute(0)?,
+            'm' => date.with_nanosecond(0)?.with_second(0)?,
+            's' => date.with_nanosecond(0)?,
+            _ => return None,
+        };
+
+        match round_up {
+            true => Self::add(start, unit, 1)?.checked_sub_signed(Duration::try_milliseconds(1)?),
+            false => Some(start),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{
+            document::{DateField, DateFormat, DateMathParser, Document},
+            search::test_util::searcher,
+        },
+        chrono::{DateTime, TimeZone, Utc},
+        pretty_assertions::assert_eq,
+    };
+
+    fn date(text: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(text).unwrap().to_utc()
+    }
+
+    #[test]
+    fn test_formats() {
+        let format = DateFormat::default();
+        assert_eq!(format.parse("2024-03-01T12:30:00+01:00").unwrap(), date("2024-03-01T11:30:00Z"));
+        assert_eq!(format.parse("2024-03-01T12:30:00.250").unwrap(), date("2024-03-01T12:30:00.250Z"));
+        assert_eq!(format.parse("2024-03-01").unwrap(), date("2024-03-01T00:00:00Z"));
+        assert_eq!(format.parse("86400000").unwrap(), date("1970-01-02T00:00:00Z"));
+        assert!(format.parse("March 1st").is_err());
+        assert_eq!(format.format(date("2024-03-01T11:30:00.5Z")), "2024-03-01T11:30:00.500Z");
+
+        let format = DateFormat::new(&["%d/%m/%Y %H:%M", DateFormat::EPOCH_MILLIS]).unwrap();
+        assert_eq!(format.parse("01/03/2024 09:15").unwrap(), date("2024-03-01T09:15:00Z"));
+        assert_eq!(format.format(date("2024-03-01T09:15:00Z")), "01/03/2024 09:15");
+        assert!(DateFormat::new::<&str>(&[]).is_err());
+    }
+
+    #[test]
+    fn test_date_math() {
+        let parser = DateMathParser::default();
+        // A Wednesday.
+        let now = date("2024-03-13T15:42:10.123Z");
+        let parse = |expression: &str, round_up: bool| parser.parse(expression, now, round_up).unwrap();
+
+        assert_eq!(parse("now", false), now);
+        assert_eq!(parse("now-7d/d", false), date("2024-03-06T00:00:00Z"));
+        assert_eq!(parse("now-7d/d", true), date("2024-03-06T23:59:59.999Z"));
+        assert_eq!(parse("now+1h-30m", false), date("2024-03-13T16:12:10.123Z"));
+        assert_eq!(parse("now/w", false), date("2024-03-11T00:00:00Z"));
+        assert_eq!(parse("now/M", true), date("2024-03-31T23:59:59.999Z"));
+        assert_eq!(parse("now-1y/y", false), date("2023-01-01T00:00:00Z"));
+        assert_eq!(parse("now/s", false), date("2024-03-13T15:42:10Z"));
+        assert_eq!(parse("2024-01-31||+1M", false), date("2024-02-29T00:00:00Z"));
+        assert_eq!(parse("2024-03-01||+1M/M", false), date("2024-04-01T00:00:00Z"));
+        assert_eq!(parse("2024-03-01", false), date("2024-03-01T00:00:00Z"));
+
+        for invalid in ["now-7", "now*2d", "now→1d", "now/3d", "now+1x", "yesterday", "2024-13-01||+1d"] {
+            assert!(parser.parse(invalid, now, false).is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn test_range_query() {
+        let mut documents = Vec::new();
+        let start = Utc.with_ymd_and_hms(2024, 3, 1, 6, 0, 0).unwrap();
+        for day in 0..14 {
+            let mut doc = Document::new();
+            doc.add(DateField::new_field("published", start + chrono::Duration::days(day)));
+            documents.push(doc);
+        }
+        let searcher = searcher([documents]);
+
+        let parser = DateMathParser::default();
+        let now = date("2024-03-14T00:30:00Z");
+        let query = parser.range_query("published", "now-7d/d", "now-1d/d", now).unwrap();
+        assert_eq!(searcher.count(&query).unwrap(), 7);
+        let query = parser.range_query("published", "2024-03-01", "2024-03-01||/d", now).unwrap();
+        assert_eq!(searcher.count(&query).unwrap(), 1);
+        assert_eq!(DateField::to_date(query.lower()), Some(date("2024-03-01T00:00:00Z")));
+        assert!(parser.range_query("published", "now-1w", "later", now).is_err());
+    }
+}
@@ -0,0 +1,91 @@
+use crate::{
+    document::Field,
+    io::{EncodingReadExt, EncodingWriteExt},
+    util::Accountable,
+    BoxResult, LuceneError,
+};
+
+/// A document: the unit of indexing and search, made up of a list of fields.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Document {
+    fields: Vec<Field>,
+}
+
+impl Document {
+    /// Creates an empty document.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a field to the document. A document may contain multiple fields with the same name.
+    pub fn add(&mut self, field: Field) {
+        self.fields.push(field);
+    }
+
+    /// Returns the fields of the document, in the order they were added.
+    #[inline]
+    pub fn fields(&self) -> &[Field] {
+        &self.fields
+    }
+
+    /// Returns the first field with the given name.
+    pub fn get_field(&self, name: &str) -> Option<&Field> {
+        self.fields.iter().find(|f| f.name() == name)
+    }
+
+    /// Returns the string value of the first field with the given name, if it has one.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.fields.iter().filter(|f| f.name() == name).find_map(|f| f.string_value())
+    }
+
+    /// Returns the string values of all fields with the given name.
+    pub fn get_values(&self, name: &str) -> Vec<&str> {
+        self.fields.iter().filter(|f| f.name() == name).filter_map(|f| f.string_value()).collect()
+    }
+
+    /// Writes the document with everything needed to index it again, for [crate::index::Translog].
+    ///
+    /// Document --> NumFields + Field<sup>NumFields</sup>
+    ///
+    /// * NumFields ([EncodingWriteExt::write_vi32]): The number of fields.
+    /// * Field: See [Field::write_to].
+    pub(crate) async fn write_to<W: EncodingWriteExt + Unpin>(&self, w: &mut W) -> BoxResult<()> {
+        let num_fields = i32::try_from(self.fields.len())
+            .map_err(|_| LuceneError::InvalidArgument(format!("too many fields: {}", self.fields.len())))?;
+        w.write_vi32(num_fields).await?;
+        for field in &self.fields {
+            field.write_to(w).await?;
+        }
+        Ok(())
+    }
+
+    /// Reads a document written by [Document::write_to].
+    pub(crate) async fn read_from<R: EncodingReadExt + Unpin>(r: &mut R) -> BoxResult<Self> {
+        let num_fields = r.read_vi32().await?;
+        let num_fields = usize::try_from(num_fields)
+            .map_err(|_| LuceneError::CorruptIndex(format!("invalid number of fields {num_fields}").into()))?;
+        let mut fields = Vec::with_capacity(num_fields.min(1024));
+        for _ in 0..num_fields {
+            fields.push(Field::read_from(r).await?);
+        }
+        Ok(Self {
+            fields,
+        })
+    }
+}
+
+impl Accountable for Document {
+    fn ram_bytes_used(&self) -> usize {
+        size_of::<Self>()
+            + (self.fields.capacity() - self.fields.len()) * size_of::<Field>()
+            + self.fields.iter().map(Field::ram_bytes_used).sum::<usize>()
+    }
+}
+
+impl FromIterator<Field> for Document {
+    fn from_iter<I: IntoIterator<Item = Field>>(iter: I) -> Self {
+        Self {
+            fields: iter.into_iter().collect(),
+        }
+    }
+}
@@ -0,0 +1,634 @@
+use {
+    crate::{
+        geo::encode_lat_lon,
+        index::{DocValuesType, VectorSimilarityFunction, MAX_VECTOR_DIMENSIONS},
+        io::{EncodingReadExt, EncodingWriteExt},
+        util::Accountable,
+        BoxResult, LuceneError,
+    },
+    std::fmt::{Display, Formatter, Result as FmtResult},
+};
+
+/// Flags recording how a [Field] written by [Field::write_to] is indexed and stored.
+const FLAG_INDEXED: u8 = 1;
+const FLAG_TOKENIZED: u8 = 2;
+const FLAG_STORED: u8 = 4;
+const FLAG_TERM_FREQ: u8 = 8;
+const FLAG_TERM_VECTORS: u8 = 16;
+const FLAG_OFFSETS: u8 = 32;
+
+/// Flags recording what the term vectors of a [Field] written by [Field::write_to] record.
+const TERM_VECTOR_POSITIONS: u8 = 1;
+const TERM_VECTOR_OFFSETS: u8 = 2;
+const TERM_VECTOR_PAYLOADS: u8 = 4;
+
+/// The kinds of [FieldValue] written by [Field::write_to].
+const VALUE_TEXT: u8 = 0;
+const VALUE_BINARY: u8 = 1;
+const VALUE_LONG: u8 = 2;
+const VALUE_FLOAT_VECTOR: u8 = 3;
+
+/// Whether a field's value is stored so that it can be retrieved with search results.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Store {
+    /// Store the original value.
+    Yes,
+
+    /// Do not store the value.
+    No,
+}
+
+/// What a field's term vectors record beyond the frequency of each term. See [Field::with_term_vectors].
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct TermVectorOptions {
+    /// Record the positions of each occurrence of a term.
+    pub positions: bool,
+
+    /// Record the start and end offsets of each occurrence of a term in the original text.
+    pub offsets: bool,
+
+    /// Record the payload of each occurrence of a term. This requires positions.
+    pub payloads: bool,
+}
+
+impl TermVectorOptions {
+    /// Term vectors that record positions and offsets, as highlighters need.
+    pub const POSITIONS_AND_OFFSETS: Self = Self {
+        positions: true,
+        offsets: true,
+        payloads: false,
+    };
+}
+
+/// The value of a [Field].
+#[derive(Clone, Debug, PartialEq)]
+pub enum FieldValue {
+    /// A string value.
+    Text(String),
+
+    /// A binary value.
+    Binary(Vec<u8>),
+
+    /// A 64-bit integer value.
+    Long(i64),
+
+    /// A vector of floats, for nearest neighbor search. See [Field::knn_vector].
+    FloatVector(Vec<f32>),
+}
+
+impl From<&str> for FieldValue {
+    fn from(value: &str) -> Self {
+        Self::Text(value.to_string())
+    }
+}
+
+impl From<String> for FieldValue {
+    fn from(value: String) -> Self {
+        Self::Text(value)
+    }
+}
+
+impl From<Vec<u8>> for FieldValue {
+    fn from(value: Vec<u8>) -> Self {
+        Self::Binary(value)
+    }
+}
+
+impl From<&[u8]> for FieldValue {
+    fn from(value: &[u8]) -> Self {
+        Self::Binary(value.to_vec())
+    }
+}
+
+impl From<i64> for FieldValue {
+    fn from(value: i64) -> Self {
+        Self::Long(value)
+    }
+}
+
+/// A named value in a document, along with how it should be indexed and stored.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Field {
+    name: String,
+    value: FieldValue,
+    indexed: bool,
+    tokenized: bool,
+    stored: bool,
+    doc_values: Option<DocValuesType>,
+    term_freq: Option<u32>,
+    term_vectors: Option<TermVectorOptions>,
+    offsets: bool,
+    vector_similarity: Option<VectorSimilarityFunction>,
+}
+
+impl Field {
+    /// Creates a field whose text is analyzed into tokens and indexed, for full-text search.
+    pub fn text(name: &str, value: impl Into<String>, store: Store) -> Self {
+        Self {
+            name: name.to_string(),
+            value: FieldValue::Text(value.into()),
+            indexed: true,
+            tokenized: true,
+            stored: store == Store::Yes,
+            doc_values: None,
+            term_freq: None,
+            term_vectors: None,
+            offsets: false,
+            vector_similarity: None,
+        }
+    }
+
+    /// Creates a field whose entire value is indexed as a single term, for identifiers and other keywords.
+    pub fn string(name: &str, value: impl Into<FieldValue>, store: Store) -> Self {
+        Self {
+            name: name.to_string(),
+            value: value.into(),
+            indexed: true,
+            tokenized: false,
+            stored: store == Store::Yes,
+            doc_values: None,
+            term_freq: None,
+            term_vectors: None,
+            offsets: false,
+            vector_similarity: None,
+        }
+    }
+
+    /// Creates a field that is only stored, not indexed.
+    pub fn stored(name: &str, value: impl Into<FieldValue>) -> Self {
+        Self {
+            name: name.to_string(),
+            value: value.into(),
+            indexed: false,
+            tokenized: false,
+            stored: true,
+            doc_values: None,
+            term_freq: None,
+            term_vectors: None,
+            offsets: false,
+            vector_similarity: None,
+        }
+    }
+
+    /// Creates a field that records a single 64-bit integer per document as numeric doc values, for sorting,
+    /// faceting and scoring. The value is neither indexed nor stored.
+    pub fn numeric_doc_values(name: &str, value: i64) -> Self {
+        Self {
+            name: name.to_string(),
+            value: FieldValue::Long(value),
+            indexed: false,
+            tokenized: false,
+            stored: false,
+            doc_values: Some(DocValuesType::Numeric),
+            term_freq: None,
+            term_vectors: None,
+            offsets: false,
+            vector_similarity: None,
+        }
+    }
+
+    /// Creates a field that records a single byte string per document as binary doc values, for values that are read
+    /// back per matching document, such as encoded shapes. The value is neither indexed nor stored.
+    pub fn binary_doc_values(name: &str, value: impl Into<Vec<u8>>) -> Self {
+        Self {
+            name: name.to_string(),
+            value: FieldValue::Binary(value.into()),
+            indexed: false,
+            tokenized: false,
+            stored: false,
+            doc_values: Some(DocValuesType::Binary),
+            term_freq: None,
+            term_vectors: None,
+            offsets: false,
+            vector_similarity: None,
+        }
+    }
+
+    /// Creates a field that records a geographic point per document as numeric doc values, for distance sorting,
+    /// filtering and scoring (see [crate::search::LatLonDistanceSortField]). Both coordinates are in degrees and are
+    /// quantized to 32 bits each with [crate::geo::encode_lat_lon].
+    pub fn lat_lon_doc_values(name: &str, latitude: f64, longitude: f64) -> BoxResult<Self> {
+        Ok(Self::numeric_doc_values(name, encode_lat_lon(latitude, longitude)?))
+    }
+
+    /// Creates a field holding a static scoring signal, such as a page rank, for use with
+    /// [crate::search::FeatureQuery]. `feature` is indexed as a single term whose frequency encodes `value` with
+    /// about 9 significant bits of precision; each feature may appear at most once per document.
+    ///
+    /// `value` must be a finite, positive normal number.
+    pub fn feature(name: &str, feature: &str, value: f32) -> BoxResult<Self> {
+        if !value.is_finite() || value < f32::MIN_POSITIVE {
+            return Err(LuceneError::InvalidArgument(format!(
+                "Feature value must be a finite, positive normal number; got {value} for {name}:{feature}"
+            ))
+            .into());
+        }
+
+        Ok(Self {
+            name: name.to_string(),
+            value: FieldValue::Text(feature.to_string()),
+            indexed: true,
+            tokenized: false,
+            stored: false,
+            doc_values: None,
+            term_freq: Some(encode_feature_value(value)),
+            term_vectors: None,
+            offsets: false,
+            vector_similarity: None,
+        })
+    }
+
+    /// Creates a field that records a vector per document for approximate nearest neighbor search with
+    /// [crate::search::KnnFloatVectorQuery]. Each segment links the vectors of the field into an HNSW graph (see
+    /// [crate::util::hnsw]) whose edges are chosen by `similarity`, which every value of the field must share. The
+    /// value is neither indexed nor stored.
+    ///
+    /// The vector must have between 1 and [MAX_VECTOR_DIMENSIONS] finite values, and may not be all zeros if
+    /// `similarity` is [VectorSimilarityFunction::Cosine].
+    pub fn knn_vector(name: &str, vector: Vec<f32>, similarity: VectorSimilarityFunction) -> BoxResult<Self> {
+        if vector.is_empty() || vector.len() > MAX_VECTOR_DIMENSIONS {
+            return Err(LuceneError::InvalidArgument(format!(
+                "vector of {name} has {} dimensions; it must have between 1 and {MAX_VECTOR_DIMENSIONS}",
+                vector.len()
+            ))
+            .into());
+        }
+        if vector.iter().any(|value| !value.is_finite()) {
+            return Err(LuceneError::InvalidArgument(format!("vector of {name} has a non-finite value")).into());
+        }
+        if similarity == VectorSimilarityFunction::Cosine && vector.iter().all(|&value| value == 0.0) {
+            return Err(
+                LuceneError::InvalidArgument(format!("vector of {name} is all zeros, which has no cosine")).into()
+            );
+        }
+
+        Ok(Self {
+            name: name.to_string(),
+            value: FieldValue::FloatVector(vector),
+            indexed: false,
+            tokenized: false,
+            stored: false,
+            doc_values: None,
+            term_freq: None,
+            term_vectors: None,
+            offsets: false,
+            vector_similarity: Some(similarity),
+        })
+    }
+
+    /// Stores a term vector for the field in each document: its terms and their frequencies, along with what
+    /// `options` asks for. Term vectors are read back per document with [crate::index::LeafReader::term_vectors].
+    ///
+    /// This fails if the field is not indexed, if it has a custom term frequency (as [Field::feature] fields do), or
+    /// if payloads are requested without positions.
+    pub fn with_term_vectors(mut self, options: TermVectorOptions) -> BoxResult<Self> {
+        if !self.indexed || self.term_freq.is_some() {
+            return Err(LuceneError::InvalidArgument(format!(
+                "can't store term vectors for field {:?}, which is not indexed with positions",
+                self.name
+            ))
+            .into());
+        }
+        if options.payloads && !options.positions {
+            return Err(LuceneError::InvalidArgument(format!(
+                "can't store term vector payloads without positions for field {:?}",
+                self.name
+            ))
+            .into());
+        }
+
+        self.term_vectors = Some(options);
+        Ok(self)
+    }
+
+    /// Indexes the start and end offsets of each token in the original text along with its position in the postings,
+    /// as Lucene's `IndexOptions.DOCS_AND_FREQS_AND_POSITIONS_AND_OFFSETS` does. Offsets are read back with
+    /// [crate::index::PostingsEnum::start_offset] and [crate::index::PostingsEnum::end_offset], so a highlighter can
+    /// find the matched text without term vectors or analyzing the stored value again.
+    ///
+    /// This fails if the field is not indexed, or if it has a custom term frequency (as [Field::feature] fields do).
+    pub fn with_offsets(mut self) -> BoxResult<Self> {
+        if !self.indexed || self.term_freq.is_some() {
+            return Err(LuceneError::InvalidArgument(format!(
+                "can't index offsets for field {:?}, which is not indexed with positions",
+                self.name
+            ))
+            .into());
+        }
+
+        self.offsets = true;
+        Ok(self)
+    }
+
+    /// Returns the name of the field.
+    #[inline]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the value of the field.
+    #[inline]
+    pub fn value(&self) -> &FieldValue {
+        &self.value
+    }
+
+    /// Returns the value of the field if it is a string.
+    #[inline]
+    pub fn string_value(&self) -> Option<&str> {
+        match &self.value {
+            FieldValue::Text(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// Returns the value of the field as bytes: the UTF-8 encoding for strings. Numeric values have no byte
+    /// representation.
+    #[inline]
+    pub fn bytes_value(&self) -> Option<&[u8]> {
+        match &self.value {
+            FieldValue::Text(s) => Some(s.as_bytes()),
+            FieldValue::Binary(b) => Some(b),
+            FieldValue::Long(_) | FieldValue::FloatVector(_) => None,
+        }
+    }
+
+    /// Returns the value of the field if it is numeric.
+    #[inline]
+    pub fn numeric_value(&self) -> Option<i64> {
+        match &self.value {
+            FieldValue::Long(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// Returns the value of the field if it is a vector. See [Field::knn_vector].
+    #[inline]
+    pub fn float_vector_value(&self) -> Option<&[f32]> {
+        match &self.value {
+            FieldValue::FloatVector(vector) => Some(vector),
+            _ => None,
+        }
+    }
+
+    /// Returns the function comparing the field's vector with others, if it is a [Field::knn_vector] field.
+    #[inline]
+    pub fn vector_similarity(&self) -> Option<VectorSimilarityFunction> {
+        self.vector_similarity
+    }
+
+    /// Indicates whether the field is indexed.
+    #[inline]
+    pub fn is_indexed(&self) -> bool {
+        self.indexed
+    }
+
+    /// Indicates whether the field's value is analyzed into tokens before indexing.
+    #[inline]
+    pub fn is_tokenized(&self) -> bool {
+        self.tokenized
+    }
+
+    /// Indicates whether the field's value is stored.
+    #[inline]
+    pub fn is_stored(&self) -> bool {
+        self.stored
+    }
+
+    /// Returns the custom frequency to index the field's single term with, in place of counting its occurrences. This
+    /// is set for [Field::feature] fields, which index no positions.
+    #[inline]
+    pub fn term_freq(&self) -> Option<u32> {
+        self.term_freq
+    }
+
+    /// Indicates whether a term vector is stored for the field. See [Field::with_term_vectors].
+    #[inline]
+    pub fn store_term_vectors(&self) -> bool {
+        self.term_vectors.is_some()
+    }
+
+    /// Indicates whether the offsets of the field's tokens are indexed in the postings. See [Field::with_offsets].
+    #[inline]
+    pub fn index_offsets(&self) -> bool {
+        self.offsets
+    }
+
+    /// Returns what the field's term vectors record, or `None` if it stores none.
+    #[inline]
+    pub fn term_vector_options(&self) -> Option<TermVectorOptions> {
+        self.term_vectors
+    }
+
+    /// Returns the kind of doc values recorded for the field, if any.
+    #[inline]
+    pub fn doc_values_type(&self) -> Option<DocValuesType> {
+        self.doc_values
+    }
+
+    /// Writes the field with everything needed to index it again, for [crate::index::Translog].
+    ///
+    /// Field --> Name + Flags + DocValuesType + TermFreq? + TermVectorFlags? + ValueType + Value
+    ///
+    /// * Name ([EncodingWriteExt::write_string]): The name of the field.
+    /// * Flags (u8): Whether the field is indexed, tokenized and stored, whether TermFreq and TermVectorFlags
+    ///   follow, and whether offsets are indexed.
+    /// * DocValuesType (u8): 0 for none, 1 for numeric and 2 for binary doc values.
+    /// * TermFreq (BE u32): The custom term frequency, if any.
+    /// * TermVectorFlags (u8): Whether term vectors record positions, offsets and payloads, if they're stored.
+    /// * ValueType (u8): 0 for text, 1 for binary, 2 for numeric and 3 for vector values.
+    /// * Value: A string ([EncodingWriteExt::write_string]), a byte string prefixed with its length
+    ///   ([EncodingWriteExt::write_vi32]), a BE i64, or a vector: its similarity function (u8) and number of
+    ///   dimensions ([EncodingWriteExt::write_vi32]) followed by the BE bits of each value (u32).
+    pub(crate) async fn write_to<W: EncodingWriteExt + Unpin>(&self, w: &mut W) -> BoxResult<()> {
+        w.write_string(&self.name).await?;
+
+        let mut flags = 0;
+        for (set, flag) in [
+            (self.indexed, FLAG_INDEXED),
+            (self.tokenized, FLAG_TOKENIZED),
+            (self.stored, FLAG_STORED),
+            (self.term_freq.is_some(), FLAG_TERM_FREQ),
+            (self.term_vectors.is_some(), FLAG_TERM_VECTORS),
+            (self.offsets, FLAG_OFFSETS),
+        ] {
+            if set {
+                flags |= flag;
+            }
+        }
+        w.write_u8(flags).await?;
+        w.write_u8(match self.doc_values {
+            None => 0,
+            Some(DocValuesType::Numeric) => 1,
+            Some(DocValuesType::Binary) => 2,
+        })
+        .await?;
+
+        if let Some(term_freq) = self.term_freq {
+            w.write_u32(term_freq).await?;
+        }
+        if let Some(options) = self.term_vectors {
+            let mut tv_flags = 0;
+            for (set, flag) in [
+                (options.positions, TERM_VECTOR_POSITIONS),
+                (options.offsets, TERM_VECTOR_OFFSETS),
+                (options.payloads, TERM_VECTOR_PAYLOADS),
+            ] {
+                if set {
+                    tv_flags |= flag;
+                }
+            }
+            w.write_u8(tv_flags).await?;
+        }
+
+        match &self.value {
+            FieldValue::Text(s) => {
+                w.write_u8(VALUE_TEXT).await?;
+                w.write_string(s).await?;
+            }
+            FieldValue::Binary(b) => {
+                let len = i32::try_from(b.len()).map_err(|_| {
+                    LuceneError::InvalidArgument(format!(
+                        "binary value of {} is too long: {} bytes",
+                        self.name,
+                        b.len()
+                    ))
+                })?;
+                w.write_u8(VALUE_BINARY).await?;
+                w.write_vi32(len).await?;
+                w.write_all(b).await?;
+            }
+            FieldValue::Long(value) => {
+                w.write_u8(VALUE_LONG).await?;
+                w.write_i64(*value).await?;
+            }
+            FieldValue::FloatVector(vector) => {
+                let similarity = self.vector_similarity.unwrap_or(VectorSimilarityFunction::Euclidean);
+                w.write_u8(VALUE_FLOAT_VECTOR).await?;
+                w.write_u8(similarity.to_byte()).await?;
+                w.write_vi32(vector.len() as i32).await?;
+                for value in vector {
+                    w.write_u32(value.to_bits()).await?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads a field written by [Field::write_to].
+    pub(crate) async fn read_from<R: EncodingReadExt + Unpin>(r: &mut R) -> BoxResult<Self> {
+        let corrupt = |message: String| LuceneError::CorruptIndex(message.into());
+
+        let name = r.read_string().await?;
+        let flags = r.read_u8().await?;
+        let doc_values = match r.read_u8().await? {
+            0 => None,
+            1 => Some(DocValuesType::Numeric),
+            2 => Some(DocValuesType::Binary),
+            other => return Err(corrupt(format!("invalid doc values type {other} for field {name}")).into()),
+        };
+        let term_freq = match flags & FLAG_TERM_FREQ {
+            0 => None,
+            _ => Some(r.read_u32().await?),
+        };
+        let term_vectors = match flags & FLAG_TERM_VECTORS {
+            0 => None,
+            _ => {
+                let tv_flags = r.read_u8().await?;
+                Some(TermVectorOptions {
+                    positions: tv_flags & TERM_VECTOR_POSITIONS != 0,
+                    offsets: tv_flags & TERM_VECTOR_OFFSETS != 0,
+                    payloads: tv_flags & TERM_VECTOR_PAYLOADS != 0,
+                })
+            }
+        };
+
+        let mut vector_similarity = None;
+        let value = match r.read_u8().await? {
+            VALUE_TEXT => FieldValue::Text(r.read_string().await?),
+            VALUE_BINARY => {
+                let len = r.read_vi32().await?;
+                let len = usize::try_from(len)
+                    .map_err(|_| corrupt(format!("invalid binary value length {len} for field {name}")))?;
+                let mut b = vec![0; len];
+                r.read_exact(&mut b).await?;
+                FieldValue::Binary(b)
+            }
+            VALUE_LONG => FieldValue::Long(r.read_i64().await?),
+            VALUE_FLOAT_VECTOR => {
+                let similarity = r.read_u8().await?;
+                vector_similarity = Some(VectorSimilarityFunction::from_byte(similarity).ok_or_else(|| {
+                    corrupt(format!("invalid vector similarity function {similarity} for field {name}"))
+                })?);
+                let len = r.read_vi32().await?;
+                let len = usize::try_from(len)
+                    .ok()
+                    .filter(|&len| len <= MAX_VECTOR_DIMENSIONS)
+                    .ok_or_else(|| corrupt(format!("invalid vector dimension {len} for field {name}")))?;
+                let mut vector = Vec::with_capacity(len);
+                for _ in 0..len {
+                    vector.push(f32::from_bits(r.read_u32().await?));
+                }
+                FieldValue::FloatVector(vector)
+            }
+            other => return Err(corrupt(format!("invalid value type {other} for field {name}")).into()),
+        };
+
+        Ok(Self {
+            name,
+            value,
+            indexed: flags & FLAG_INDEXED != 0,
+            tokenized: flags & FLAG_TOKENIZED != 0,
+            stored: flags & FLAG_STORED != 0,
+            doc_values,
+            term_freq,
+            term_vectors,
+            offsets: flags & FLAG_OFFSETS != 0,
+            vector_similarity,
+        })
+    }
+}
+
+impl Display for Field {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match &self.value {
+            FieldValue::Text(s) => write!(f, "{}:{s}", self.name),
+            FieldValue::Binary(b) => write!(f, "{}:{b:x?}", self.name),
+            FieldValue::Long(value) => write!(f, "{}:{value}", self.name),
+            FieldValue::FloatVector(vector) => write!(f, "{}:{vector:?}", self.name),
+        }
+    }
+}
+
+impl Accountable for Field {
+    fn ram_bytes_used(&self) -> usize {
+        size_of::<Self>()
+            + self.name.capacity()
+            + match &self.value {
+                FieldValue::Text(s) => s.capacity(),
+                FieldValue::Binary(b) => b.capacity(),
+                FieldValue::Long(_) => 0,
+                FieldValue::FloatVector(vector) => vector.capacity() * size_of::<f32>(),
+            }
+    }
+}
+
+/// The largest term frequency a feature value can be encoded as.
+pub const MAX_FEATURE_FREQ: u32 = f32::MAX.to_bits() >> 15;
+
+/// Encodes a positive feature value as a term frequency by keeping the upper 17 bits of its IEEE 754 representation.
+/// Frequencies preserve the ordering of values, and averaging them approximates the geometric mean of the values.
+#[inline]
+pub fn encode_feature_value(value: f32) -> u32 {
+    value.to_bits() >> 15
+}
+
+/// Decodes a term frequency produced by [encode_feature_value].
+#[inline]
+pub fn decode_feature_value(freq: f32) -> f32 {
+    if freq > MAX_FEATURE_FREQ as f32 {
+        // Scorers may ask for the value of an unbounded frequency when computing maximum scores.
+        return f32::MAX;
+    }
+
+    f32::from_bits((freq as u32) << 15)
+}
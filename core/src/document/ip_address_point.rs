@@ -0,0 +1,162 @@
+use {
+    crate::{
+        document::{range_field::encode_inet_address, Field, Store},
+        index::Term,
+        search::AutomatonQuery,
+        util::automaton::{make_binary_interval, CompiledAutomaton, DEFAULT_DETERMINIZE_WORK_LIMIT},
+        BoxResult, LuceneError,
+    },
+    std::net::{IpAddr, Ipv6Addr},
+};
+
+/// Factories for IP addresses, as in Lucene's `InetAddressPoint`.
+///
+/// Each address is indexed as a single term of 16 bytes: IPv6 addresses as they are, and IPv4 addresses mapped into
+/// IPv6 (`::ffff:a.b.c.d`), so both kinds share a field and sort together. Ranges and network prefixes match the
+/// terms between two addresses, which the term dictionary finds without checking every document.
+#[derive(Clone, Copy, Debug)]
+pub struct IpAddressPoint;
+
+impl IpAddressPoint {
+    /// The number of bytes of an encoded address.
+    pub const BYTES: usize = 16;
+
+    /// Creates a field indexing `address`. The field isn't stored.
+    pub fn new_field(name: &str, address: IpAddr) -> Field {
+        Field::string(name, Self::encode(address).to_vec(), Store::No)
+    }
+
+    /// Encodes `address` as the 16 bytes of its IPv6 form.
+    #[inline]
+    pub fn encode(address: IpAddr) -> [u8; Self::BYTES] {
+        encode_inet_address(address)
+    }
+
+    /// Decodes an address encoded with [IpAddressPoint::encode], returning IPv4-mapped addresses as IPv4. This fails
+    /// with [LuceneError::InvalidArgument] if `bytes` isn't 16 bytes long.
+    pub fn decode(bytes: &[u8]) -> BoxResult<IpAddr> {
+        let octets: [u8; Self::BYTES] = bytes.try_into().map_err(|_| {
+            LuceneError::InvalidArgument(format!("an encoded address has {} bytes, not {}", bytes.len(), Self::BYTES))
+        })?;
+        Ok(Ipv6Addr::from(octets).to_canonical())
+    }
+
+    /// Creates a query matching the documents with `address`.
+    pub fn new_exact_query(field: &str, address: IpAddr) -> BoxResult<AutomatonQuery> {
+        Self::new_query(field, address, address, format!("{field}:{address}"))
+    }
+
+    /// Creates a query matching the documents with an address from `lower` to `upper`, inclusive. IPv4 addresses sort
+    /// with the IPv6 addresses they're mapped to.
+    pub fn new_range_query(field: &str, lower: IpAddr, upper: IpAddr) -> BoxResult<AutomatonQuery> {
+        Self::new_query(field, lower, upper, format!("{field}:[{lower} TO {upper}]"))
+    }
+
+    /// Creates a query matching the documents with an address in the network of `address` whose prefix is
+    /// `prefix_length` bits long, such as `10.0.0.0/8`. This fails with [LuceneError::InvalidArgument] if the prefix
+    /// is longer than the address: 32 bits for IPv4 and 128 for IPv6.
+    pub fn new_prefix_query(field: &str, address: IpAddr, prefix_length: u8) -> BoxResult<AutomatonQuery> {
+        let (max_length, offset) = match address {
+            IpAddr::V4(_) => (32, 96),
+            IpAddr::V6(_) => (128, 0),
+        };
+        if prefix_length > max_length {
+            return Err(LuceneError::InvalidArgument(format!(
+                "prefix length {prefix_length} is longer than the {max_length} bits of {address}"
+            ))
+            .into());
+        }
+
+        let mask = u128::MAX.checked_shl(128 - u32::from(offset + prefix_length)).unwrap_or(0);
+        let network = u128::from_be_bytes(Self::encode(address)) & mask;
+        let lower = Ipv6Addr::from(network).to_canonical();
+        let upper = Ipv6Addr::from(network | !mask).to_canonical();
+        Self::new_query(field, lower, upper, format!("{field}:{lower}/{prefix_length}"))
+    }
+
+    /// Creates a query from a network in CIDR notation, such as `192.168.0.0/16` or `2001:db8::/32`; an address alone
+    /// matches itself. This fails with [LuceneError::InvalidArgument] if `cidr` can't be parsed, and as
+    /// [IpAddressPoint::new_prefix_query] does.
+    pub fn new_cidr_query(field: &str, cidr: &str) -> BoxResult<AutomatonQuery> {
+        let invalid = || LuceneError::InvalidArgument(format!("{cidr:?} isn't a network in CIDR notation"));
+        let (address, prefix_length) = match cidr.split_once('/') {
+            Some((address, prefix_length)) => (address, Some(prefix_length.parse().map_err(|_| invalid())?)),
+            None => (cidr, None),
+        };
+        let address: IpAddr = address.parse().map_err(|_| invalid())?;
+
+        match prefix_length {
+            Some(prefix_length) => Self::new_prefix_query(field, address, prefix_length),
+            None => Self::new_exact_query(field, address),
+        }
+    }
+
+    fn new_query(field: &str, lower: IpAddr, upper: IpAddr, description: String) -> BoxResult<AutomatonQuery> {
+        let automaton = make_binary_interval(&Self::encode(lower), &Self::encode(upper));
+        let automaton = CompiledAutomaton::new(&automaton, Some(true), true, DEFAULT_DETERMINIZE_WORK_LIMIT, true)?;
+        let term = Term::new(field, Self::encode(lower).to_vec());
+        Ok(AutomatonQuery::with_description(term, automaton, description))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{
+            document::{Document, IpAddressPoint},
+            search::{test_util::searcher, Query},
+        },
+        pretty_assertions::assert_eq,
+        std::net::IpAddr,
+    };
+
+    #[test]
+    fn test_ip_address_point() {
+        let addresses = ["10.0.0.1", "10.1.2.3", "10.255.255.255", "11.0.0.0", "192.168.1.20", "2001:db8::1", "::1"];
+        let mut documents = Vec::new();
+        for address in addresses {
+            let mut doc = Document::new();
+            doc.add(IpAddressPoint::new_field("ip", address.parse().unwrap()));
+            documents.push(doc);
+        }
+        let searcher = searcher([documents]);
+        let docs = |query: &dyn Query| {
+            let mut docs: Vec<u32> = searcher.search(query, 10).unwrap().score_docs.iter().map(|sd| sd.doc).collect();
+            docs.sort_unstable();
+            docs
+        };
+        let ip = |text: &str| text.parse::<IpAddr>().unwrap();
+
+        let encoded = IpAddressPoint::encode(ip("10.1.2.3"));
+        assert_eq!(&encoded[10..], &[0xff, 0xff, 10, 1, 2, 3]);
+        assert_eq!(IpAddressPoint::decode(&encoded).unwrap(), ip("10.1.2.3"));
+        assert!(IpAddressPoint::decode(&encoded[1..]).is_err());
+
+        let query = IpAddressPoint::new_exact_query("ip", ip("192.168.1.20")).unwrap();
+        assert_eq!(query.to_string(), "ip:192.168.1.20");
+        assert_eq!(docs(&query), vec![4]);
+        assert_eq!(docs(&IpAddressPoint::new_exact_query("ip", ip("192.168.1.21")).unwrap()), Vec::<u32>::new());
+
+        let query = IpAddressPoint::new_range_query("ip", ip("10.1.0.0"), ip("11.0.0.0")).unwrap();
+        assert_eq!(query.to_string(), "ip:[10.1.0.0 TO 11.0.0.0]");
+        assert_eq!(docs(&query), vec![1, 2, 3]);
+        // IPv4 addresses sort after ::1 and before 2001:db8::.
+        assert_eq!(
+            docs(&IpAddressPoint::new_range_query("ip", ip("::"), ip("2001::")).unwrap()),
+            vec![0, 1, 2, 3, 4, 6]
+        );
+
+        let query = IpAddressPoint::new_cidr_query("ip", "10.1.2.3/8").unwrap();
+        assert_eq!(query.to_string(), "ip:10.0.0.0/8");
+        assert_eq!(docs(&query), vec![0, 1, 2]);
+        assert_eq!(docs(&IpAddressPoint::new_cidr_query("ip", "10.0.0.0/15").unwrap()), vec![0, 1]);
+        assert_eq!(docs(&IpAddressPoint::new_cidr_query("ip", "0.0.0.0/0").unwrap()), vec![0, 1, 2, 3, 4]);
+        assert_eq!(docs(&IpAddressPoint::new_cidr_query("ip", "2001:db8::/32").unwrap()), vec![5]);
+        assert_eq!(docs(&IpAddressPoint::new_cidr_query("ip", "::/0").unwrap()), vec![0, 1, 2, 3, 4, 5, 6]);
+        assert_eq!(docs(&IpAddressPoint::new_cidr_query("ip", "::1").unwrap()), vec![6]);
+
+        for invalid in ["10.0.0.0/33", "10.0.0.0/x", "10.0.0/8", "::/129"] {
+            assert!(IpAddressPoint::new_cidr_query("ip", invalid).is_err(), "{invalid}");
+        }
+    }
+}
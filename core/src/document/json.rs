@@ -0,0 +1,97 @@
+use {
+    crate::{
+        document::{Document, Field, FieldValue, Store},
+        BoxResult, LuceneError,
+    },
+    serde_json::{Map, Value},
+};
+
+impl Document {
+    /// Creates a document from a JSON object, the form documents take in JSON APIs.
+    ///
+    /// Strings are indexed as analyzed text, integers are kept as numeric doc values, and arrays add a field per
+    /// element; every value is also stored. Other values, such as floating point numbers and nested objects, fail
+    /// with [LuceneError::InvalidArgument].
+    pub fn from_json(source: &Map<String, Value>) -> BoxResult<Self> {
+        let mut document = Document::new();
+        for (name, value) in source {
+            match value {
+                Value::Array(values) => {
+                    for value in values {
+                        document.add_json_value(name, value)?;
+                    }
+                }
+                value => document.add_json_value(name, value)?,
+            }
+        }
+        Ok(document)
+    }
+
+    /// Returns the stored text and numeric fields of the document as a JSON object, as [Document::from_json] reads
+    /// them. Fields with several values are returned as arrays; binary and vector values are left out.
+    pub fn to_json(&self) -> Map<String, Value> {
+        let mut fields = Map::new();
+        for field in self.fields().iter().filter(|field| field.is_stored()) {
+            let value = match field.value() {
+                FieldValue::Text(text) => Value::from(text.as_str()),
+                FieldValue::Long(value) => Value::from(*value),
+                FieldValue::Binary(_) | FieldValue::FloatVector(_) => continue,
+            };
+
+            match fields.get_mut(field.name()) {
+                Some(Value::Array(values)) => values.push(value),
+                Some(existing) => *existing = Value::Array(vec![existing.take(), value]),
+                None => {
+                    fields.insert(field.name().to_string(), value);
+                }
+            }
+        }
+        fields
+    }
+
+    /// Adds the fields of a single JSON value.
+    fn add_json_value(&mut self, name: &str, value: &Value) -> BoxResult<()> {
+        match value {
+            Value::String(text) => self.add(Field::text(name, text.as_str(), Store::Yes)),
+            Value::Number(number) => match number.as_i64() {
+                Some(number) => {
+                    self.add(Field::numeric_doc_values(name, number));
+                    self.add(Field::stored(name, number));
+                }
+                None => {
+                    return Err(
+                        LuceneError::InvalidArgument(format!("field {name} has unsupported number {number}")).into()
+                    )
+                }
+            },
+            _ => return Err(LuceneError::InvalidArgument(format!("field {name} has unsupported value {value}")).into()),
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::document::{Document, Field},
+        pretty_assertions::assert_eq,
+        serde_json::{json, Value},
+    };
+
+    #[test]
+    fn test_json_document() {
+        let source = json!({"title": "The quick fox", "tags": ["animal", "fast"], "year": 2020});
+        let mut document = Document::from_json(source.as_object().unwrap()).unwrap();
+        assert_eq!(document.get_values("tags"), vec!["animal", "fast"]);
+        assert!(document.get_field("title").unwrap().is_tokenized());
+        assert_eq!(document.fields().iter().filter(|field| field.name() == "year").count(), 2);
+
+        // Only stored values are returned.
+        document.add(Field::numeric_doc_values("rank", 3));
+        assert_eq!(Value::Object(document.to_json()), source);
+
+        for source in [json!({"price": 1.5}), json!({"nested": {"a": 1}}), json!({"tags": [null]})] {
+            assert!(Document::from_json(source.as_object().unwrap()).is_err());
+        }
+    }
+}
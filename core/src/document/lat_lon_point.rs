@@ -0,0 +1,46 @@
+use crate::{
+    document::Field,
+    search::{LatLonDistanceFeatureQuery, LatLonDistanceQuery, LatLonDistanceSortField},
+    BoxResult,
+};
+
+/// Factories for geographic points stored with [Field::lat_lon_doc_values], gathered in one place as in Lucene's
+/// `LatLonPoint` and `LatLonDocValuesField`. Coordinates are in degrees and distances in meters; every distance is
+/// computed with [crate::geo::haversin_meters].
+#[derive(Clone, Copy, Debug)]
+pub struct LatLonPoint;
+
+impl LatLonPoint {
+    /// Creates a field storing the point (`latitude`, `longitude`); see [Field::lat_lon_doc_values].
+    pub fn new_field(name: &str, latitude: f64, longitude: f64) -> BoxResult<Field> {
+        Field::lat_lon_doc_values(name, latitude, longitude)
+    }
+
+    /// Creates a sort field ordering documents by their distance from (`latitude`, `longitude`), nearest first.
+    pub fn new_distance_sort(field: &str, latitude: f64, longitude: f64) -> BoxResult<LatLonDistanceSortField> {
+        LatLonDistanceSortField::new(field, latitude, longitude)
+    }
+
+    /// Creates a query matching the documents within `radius_meters` of (`latitude`, `longitude`). Every document
+    /// with a point is checked, so this is best used as a filter.
+    pub fn new_slow_distance_query(
+        field: &str,
+        latitude: f64,
+        longitude: f64,
+        radius_meters: f64,
+    ) -> BoxResult<LatLonDistanceQuery> {
+        LatLonDistanceQuery::new(field, latitude, longitude, radius_meters)
+    }
+
+    /// Creates a query scoring documents by `weight * pivot_meters / (pivot_meters + distance)`, for boosting results
+    /// near (`latitude`, `longitude`).
+    pub fn new_distance_feature_query(
+        field: &str,
+        weight: f32,
+        latitude: f64,
+        longitude: f64,
+        pivot_meters: f64,
+    ) -> BoxResult<LatLonDistanceFeatureQuery> {
+        LatLonDistanceFeatureQuery::new(field, weight, latitude, longitude, pivot_meters)
+    }
+}
@@ -0,0 +1,59 @@
+use crate::{
+    document::Field,
+    geo::{
+        encode_latitude, encode_longitude, tessellate_line, tessellate_polygon, Line, Polygon, QueryRelation, Triangle,
+        TRIANGLE_BYTES,
+    },
+    search::LatLonShapeQuery,
+    BoxResult,
+};
+
+/// Factories for geographic shapes, as in Lucene's `LatLonShape`. Shapes are tessellated into triangles (see
+/// [crate::geo::Triangle]), which are stored together as a single binary doc value per document, and queried with
+/// [LatLonShapeQuery]. Coordinates are in degrees.
+#[derive(Clone, Copy, Debug)]
+pub struct LatLonShape;
+
+impl LatLonShape {
+    /// Creates a field holding a polygon. Fails if the polygon can't be tessellated, such as when it crosses itself.
+    pub fn create_polygon_field(name: &str, polygon: &Polygon) -> BoxResult<Field> {
+        Ok(Self::field(name, &tessellate_polygon(polygon)?))
+    }
+
+    /// Creates a field holding a line.
+    pub fn create_line_field(name: &str, line: &Line) -> BoxResult<Field> {
+        Ok(Self::field(name, &tessellate_line(line)?))
+    }
+
+    /// Creates a field holding a single point.
+    pub fn create_point_field(name: &str, latitude: f64, longitude: f64) -> BoxResult<Field> {
+        let point = (encode_longitude(longitude)?, encode_latitude(latitude)?);
+        Ok(Self::field(name, &[Triangle::point(point)]))
+    }
+
+    /// Creates a query matching the shapes of `field` that relate to `polygon` as given by `relation`.
+    pub fn new_polygon_query(field: &str, relation: QueryRelation, polygon: Polygon) -> BoxResult<LatLonShapeQuery> {
+        LatLonShapeQuery::new(field, relation, polygon)
+    }
+
+    /// Creates a query matching the shapes of `field` that relate to a latitude/longitude box as given by `relation`.
+    /// The box may not cross the dateline.
+    pub fn new_box_query(
+        field: &str,
+        relation: QueryRelation,
+        min_lat: f64,
+        max_lat: f64,
+        min_lon: f64,
+        max_lon: f64,
+    ) -> BoxResult<LatLonShapeQuery> {
+        LatLonShapeQuery::new(field, relation, Polygon::from_box(min_lat, max_lat, min_lon, max_lon)?)
+    }
+
+    fn field(name: &str, triangles: &[Triangle]) -> Field {
+        let mut value = Vec::with_capacity(triangles.len() * TRIANGLE_BYTES);
+        for triangle in triangles {
+            triangle.encode(&mut value);
+        }
+        Field::binary_doc_values(name, value)
+    }
+}
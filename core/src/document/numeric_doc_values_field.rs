@@ -0,0 +1,26 @@
+use crate::{document::Field, search::NumericDocValuesRangeQuery};
+
+/// Factories for numeric values stored with [Field::numeric_doc_values], gathered in one place as in Lucene's
+/// `NumericDocValuesField`.
+#[derive(Clone, Copy, Debug)]
+pub struct NumericDocValuesField;
+
+impl NumericDocValuesField {
+    /// Creates a field storing `value`; see [Field::numeric_doc_values].
+    pub fn new_field(name: &str, value: i64) -> Field {
+        Field::numeric_doc_values(name, value)
+    }
+
+    /// Creates a query matching the documents whose value is from `lower` to `upper`, inclusive. Values are checked
+    /// document by document, skipping the blocks of the segment's skip index that can't match, so this is best used
+    /// as a filter.
+    pub fn new_slow_range_query(field: &str, lower: i64, upper: i64) -> NumericDocValuesRangeQuery {
+        NumericDocValuesRangeQuery::new(field, lower, upper)
+    }
+
+    /// Creates a query matching the documents whose value is `value`; see
+    /// [NumericDocValuesField::new_slow_range_query].
+    pub fn new_slow_exact_query(field: &str, value: i64) -> NumericDocValuesRangeQuery {
+        NumericDocValuesRangeQuery::new(field, value, value)
+    }
+}
@@ -0,0 +1,193 @@
+use crate::document::{Document, Field, Store};
+
+/// The separator between the names of nested objects in a flattened field name, as in `user.address.city`.
+pub const FIELD_PATH_SEPARATOR: char = '.';
+
+/// Joins the names of nested objects into a flattened field name, such as `user.address.city`. Empty names are
+/// skipped, so that a value at the top level has no leading separator.
+pub fn field_path<S: AsRef<str>>(names: &[S]) -> String {
+    let mut path = String::new();
+    for name in names.iter().map(AsRef::as_ref).filter(|name| !name.is_empty()) {
+        if !path.is_empty() {
+            path.push(FIELD_PATH_SEPARATOR);
+        }
+        path.push_str(name);
+    }
+    path
+}
+
+/// Returns the path of the object holding the flattened field `field`, or `None` if the field is at the top level.
+pub fn parent_path(field: &str) -> Option<&str> {
+    field.rfind(FIELD_PATH_SEPARATOR).map(|end| &field[..end])
+}
+
+/// Indicates whether the flattened field `field` is `path` itself, or a field of the object at `path`, however deeply
+/// nested.
+pub fn is_within_path(field: &str, path: &str) -> bool {
+    match field.strip_prefix(path) {
+        Some(rest) => rest.is_empty() || rest.starts_with(FIELD_PATH_SEPARATOR),
+        None => false,
+    }
+}
+
+/// A semi-structured value, such as a parsed JSON document, to be indexed as flattened fields with
+/// [Document::add_object].
+///
+/// Each leaf value becomes a field named by the path of keys leading to it, joined with [FIELD_PATH_SEPARATOR]:
+/// `{"user": {"name": "Ann"}}` is indexed as the field `user.name`. The elements of an array are indexed under the
+/// array's own path, so the field has one value per element.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum ObjectValue {
+    /// A missing value, which isn't indexed.
+    #[default]
+    Null,
+
+    /// A boolean, indexed as the single term `true` or `false`.
+    Bool(bool),
+
+    /// An integer, indexed as a single term of its decimal form. Outside of arrays, it's also recorded as numeric doc
+    /// values, for sorting and range queries.
+    Long(i64),
+
+    /// Text, analyzed into tokens for full-text search.
+    Text(String),
+
+    /// A list of values, each indexed under the array's path.
+    Array(Vec<ObjectValue>),
+
+    /// An object, whose values are indexed under its path followed by their keys.
+    Object(Vec<(String, ObjectValue)>),
+}
+
+impl ObjectValue {
+    /// Creates an object from its keys and values, in order.
+    pub fn object<K: Into<String>, I: IntoIterator<Item = (K, ObjectValue)>>(entries: I) -> Self {
+        Self::Object(entries.into_iter().map(|(key, value)| (key.into(), value)).collect())
+    }
+
+    /// Returns the flattened fields of the value at `path`, in order, as [Document::add_object] indexes them.
+    pub fn to_fields(&self, path: &str, store: Store) -> Vec<Field> {
+        let mut fields = Vec::new();
+        self.flatten(path, store, false, &mut fields);
+        fields
+    }
+
+    fn flatten(&self, path: &str, store: Store, in_array: bool, fields: &mut Vec<Field>) {
+        match self {
+            Self::Null => (),
+            Self::Bool(value) => fields.push(Field::string(path, value.to_string(), store)),
+            Self::Long(value) => {
+                fields.push(Field::string(path, value.to_string(), store));
+                // A document holds at most one doc value per field.
+                if !in_array {
+                    fields.push(Field::numeric_doc_values(path, *value));
+                }
+            }
+            Self::Text(value) => fields.push(Field::text(path, value.as_str(), store)),
+            Self::Array(values) => {
+                for value in values {
+                    value.flatten(path, store, true, fields);
+                }
+            }
+            Self::Object(entries) => {
+                for (key, value) in entries {
+                    value.flatten(&field_path(&[path, key]), store, in_array, fields);
+                }
+            }
+        }
+    }
+}
+
+impl From<bool> for ObjectValue {
+    fn from(value: bool) -> Self {
+        Self::Bool(value)
+    }
+}
+
+impl From<i64> for ObjectValue {
+    fn from(value: i64) -> Self {
+        Self::Long(value)
+    }
+}
+
+impl From<&str> for ObjectValue {
+    fn from(value: &str) -> Self {
+        Self::Text(value.to_string())
+    }
+}
+
+impl From<String> for ObjectValue {
+    fn from(value: String) -> Self {
+        Self::Text(value)
+    }
+}
+
+impl<T: Into<ObjectValue>> From<Vec<T>> for ObjectValue {
+    fn from(values: Vec<T>) -> Self {
+        Self::Array(values.into_iter().map(Into::into).collect())
+    }
+}
+
+impl<T: Into<ObjectValue>> From<Option<T>> for ObjectValue {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(Self::Null, Into::into)
+    }
+}
+
+impl Document {
+    /// Adds the leaves of `value` as flattened fields under `path`, which may be empty to add an object's keys at the
+    /// top level. See [ObjectValue] for how each kind of value is indexed.
+    pub fn add_object(&mut self, path: &str, value: &ObjectValue, store: Store) {
+        for field in value.to_fields(path, store) {
+            self.add(field);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::document::{field_path, is_within_path, parent_path, Document, ObjectValue, Store},
+        pretty_assertions::assert_eq,
+    };
+
+    #[test]
+    fn test_flatten() {
+        assert_eq!(field_path(&["user", "", "address", "city"]), "user.address.city");
+        assert_eq!(parent_path("user.address.city"), Some("user.address"));
+        assert_eq!(parent_path("user"), None);
+        assert!(is_within_path("user.address.city", "user.address"));
+        assert!(is_within_path("user", "user"));
+        assert!(!is_within_path("username", "user"));
+
+        let value = ObjectValue::object([
+            ("title", "Nested fields".into()),
+            ("draft", false.into()),
+            ("deleted", None::<bool>.into()),
+            ("author", ObjectValue::object([("name", "Ann".into()), ("age", 41.into())])),
+            ("tags", vec!["search", "json"].into()),
+            ("scores", vec![3_i64, 5].into()),
+        ]);
+        let mut doc = Document::new();
+        doc.add_object("", &value, Store::Yes);
+
+        let names: Vec<&str> = doc.fields().iter().map(|field| field.name()).collect();
+        assert_eq!(
+            names,
+            vec!["title", "draft", "author.name", "author.age", "author.age", "tags", "tags", "scores", "scores"]
+        );
+        assert_eq!(doc.get("author.name"), Some("Ann"));
+        assert_eq!(doc.get_values("tags"), vec!["search", "json"]);
+        assert_eq!(doc.get("draft"), Some("false"));
+
+        // Only an integer outside of an array has doc values.
+        let doc_values: Vec<&str> =
+            doc.fields().iter().filter(|field| field.doc_values_type().is_some()).map(|field| field.name()).collect();
+        assert_eq!(doc_values, vec!["author.age"]);
+
+        let mut doc = Document::new();
+        doc.add_object("meta", &ObjectValue::object([("lang", "en".into())]), Store::No);
+        assert_eq!(doc.fields()[0].name(), "meta.lang");
+        assert!(!doc.fields()[0].is_stored());
+    }
+}
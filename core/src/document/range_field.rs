@@ -0,0 +1,306 @@
+use {
+    crate::{
+        document::Field,
+        search::{RangeFieldQuery, RangeQueryType},
+        BoxResult, LuceneError,
+    },
+    std::{
+        fmt::Display,
+        net::{IpAddr, Ipv6Addr},
+    },
+};
+
+/// The most dimensions a [LongRange] or [DoubleRange] may have.
+pub const MAX_RANGE_DIMENSIONS: usize = 4;
+
+/// The kind of value a range field holds, which determines how each dimension is encoded.
+///
+/// Every bound is encoded into bytes that sort in the same order as the values, so ranges of any kind are compared
+/// dimension by dimension as plain byte strings. A range is stored as all of its minimums followed by all of its
+/// maximums.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum RangeType {
+    /// 64-bit integers, such as timestamps.
+    Long,
+
+    /// 64-bit floating point numbers.
+    Double,
+
+    /// IPv4 and IPv6 addresses, IPv4 addresses being mapped into IPv6.
+    InetAddress,
+}
+
+impl RangeType {
+    /// Returns the number of bytes each bound takes in a dimension.
+    #[inline]
+    pub fn bytes_per_dim(self) -> usize {
+        match self {
+            Self::Long | Self::Double => 8,
+            Self::InetAddress => 16,
+        }
+    }
+
+    /// Formats an encoded bound.
+    pub(crate) fn format_bound(self, bytes: &[u8]) -> String {
+        match self {
+            Self::Long => decode_long(bytes).to_string(),
+            Self::Double => decode_double(bytes).to_string(),
+            Self::InetAddress => {
+                let mut octets = [0; 16];
+                octets.copy_from_slice(bytes);
+                Ipv6Addr::from(octets).to_canonical().to_string()
+            }
+        }
+    }
+}
+
+/// Factories for ranges of 64-bit integers in up to [MAX_RANGE_DIMENSIONS] dimensions, as in Lucene's `LongRange`.
+/// A range includes its bounds.
+///
+/// Ranges are stored as binary doc values (see [Field::binary_doc_values]), so a document can hold a single range per
+/// field, and queries check every document that has one.
+#[derive(Clone, Copy, Debug)]
+pub struct LongRange;
+
+impl LongRange {
+    /// Creates a field storing the range from `min` to `max`, which hold one bound per dimension.
+    pub fn new_field(name: &str, min: &[i64], max: &[i64]) -> BoxResult<Field> {
+        Ok(Field::binary_doc_values(name, Self::encode(min, max)?))
+    }
+
+    /// Creates a query matching the ranges that share at least one point with the range from `min` to `max`.
+    pub fn new_intersects_query(field: &str, min: &[i64], max: &[i64]) -> BoxResult<RangeFieldQuery> {
+        Self::new_query(field, RangeQueryType::Intersects, min, max)
+    }
+
+    /// Creates a query matching the ranges that contain the range from `min` to `max`.
+    pub fn new_contains_query(field: &str, min: &[i64], max: &[i64]) -> BoxResult<RangeFieldQuery> {
+        Self::new_query(field, RangeQueryType::Contains, min, max)
+    }
+
+    /// Creates a query matching the ranges that lie within the range from `min` to `max`.
+    pub fn new_within_query(field: &str, min: &[i64], max: &[i64]) -> BoxResult<RangeFieldQuery> {
+        Self::new_query(field, RangeQueryType::Within, min, max)
+    }
+
+    /// Creates a query matching the ranges that intersect the range from `min` to `max` without lying within it.
+    pub fn new_crosses_query(field: &str, min: &[i64], max: &[i64]) -> BoxResult<RangeFieldQuery> {
+        Self::new_query(field, RangeQueryType::Crosses, min, max)
+    }
+
+    fn new_query(field: &str, query_type: RangeQueryType, min: &[i64], max: &[i64]) -> BoxResult<RangeFieldQuery> {
+        RangeFieldQuery::new(field, query_type, RangeType::Long, Self::encode(min, max)?)
+    }
+
+    fn encode(min: &[i64], max: &[i64]) -> BoxResult<Vec<u8>> {
+        encode_range("LongRange", MAX_RANGE_DIMENSIONS, min, max, |v| encode_long(*v).to_vec())
+    }
+}
+
+/// Factories for ranges of 64-bit floating point numbers in up to [MAX_RANGE_DIMENSIONS] dimensions, as in Lucene's
+/// `DoubleRange`. A range includes its bounds, which may be infinite but not NaN.
+///
+/// As with [LongRange], a document can hold a single range per field.
+#[derive(Clone, Copy, Debug)]
+pub struct DoubleRange;
+
+impl DoubleRange {
+    /// Creates a field storing the range from `min` to `max`, which hold one bound per dimension.
+    pub fn new_field(name: &str, min: &[f64], max: &[f64]) -> BoxResult<Field> {
+        Ok(Field::binary_doc_values(name, Self::encode(min, max)?))
+    }
+
+    /// Creates a query matching the ranges that share at least one point with the range from `min` to `max`.
+    pub fn new_intersects_query(field: &str, min: &[f64], max: &[f64]) -> BoxResult<RangeFieldQuery> {
+        Self::new_query(field, RangeQueryType::Intersects, min, max)
+    }
+
+    /// Creates a query matching the ranges that contain the range from `min` to `max`.
+    pub fn new_contains_query(field: &str, min: &[f64], max: &[f64]) -> BoxResult<RangeFieldQuery> {
+        Self::new_query(field, RangeQueryType::Contains, min, max)
+    }
+
+    /// Creates a query matching the ranges that lie within the range from `min` to `max`.
+    pub fn new_within_query(field: &str, min: &[f64], max: &[f64]) -> BoxResult<RangeFieldQuery> {
+        Self::new_query(field, RangeQueryType::Within, min, max)
+    }
+
+    /// Creates a query matching the ranges that intersect the range from `min` to `max` without lying within it.
+    pub fn new_crosses_query(field: &str, min: &[f64], max: &[f64]) -> BoxResult<RangeFieldQuery> {
+        Self::new_query(field, RangeQueryType::Crosses, min, max)
+    }
+
+    fn new_query(field: &str, query_type: RangeQueryType, min: &[f64], max: &[f64]) -> BoxResult<RangeFieldQuery> {
+        RangeFieldQuery::new(field, query_type, RangeType::Double, Self::encode(min, max)?)
+    }
+
+    fn encode(min: &[f64], max: &[f64]) -> BoxResult<Vec<u8>> {
+        encode_range("DoubleRange", MAX_RANGE_DIMENSIONS, min, max, |v| encode_double(*v).to_vec())
+    }
+}
+
+/// Factories for ranges of IP addresses, as in Lucene's `InetAddressRange`, such as the blocks assigned to a network.
+/// A range includes its bounds. IPv4 addresses are mapped into IPv6, so both kinds can be mixed in one field.
+///
+/// As with [LongRange], a document can hold a single range per field.
+#[derive(Clone, Copy, Debug)]
+pub struct InetAddressRange;
+
+impl InetAddressRange {
+    /// Creates a field storing the range from `min` to `max`.
+    pub fn new_field(name: &str, min: IpAddr, max: IpAddr) -> BoxResult<Field> {
+        Ok(Field::binary_doc_values(name, Self::encode(min, max)?))
+    }
+
+    /// Creates a query matching the ranges that share at least one address with the range from `min` to `max`.
+    pub fn new_intersects_query(field: &str, min: IpAddr, max: IpAddr) -> BoxResult<RangeFieldQuery> {
+        Self::new_query(field, RangeQueryType::Intersects, min, max)
+    }
+
+    /// Creates a query matching the ranges that contain the range from `min` to `max`.
+    pub fn new_contains_query(field: &str, min: IpAddr, max: IpAddr) -> BoxResult<RangeFieldQuery> {
+        Self::new_query(field, RangeQueryType::Contains, min, max)
+    }
+
+    /// Creates a query matching the ranges that lie within the range from `min` to `max`.
+    pub fn new_within_query(field: &str, min: IpAddr, max: IpAddr) -> BoxResult<RangeFieldQuery> {
+        Self::new_query(field, RangeQueryType::Within, min, max)
+    }
+
+    /// Creates a query matching the ranges that intersect the range from `min` to `max` without lying within it.
+    pub fn new_crosses_query(field: &str, min: IpAddr, max: IpAddr) -> BoxResult<RangeFieldQuery> {
+        Self::new_query(field, RangeQueryType::Crosses, min, max)
+    }
+
+    fn new_query(field: &str, query_type: RangeQueryType, min: IpAddr, max: IpAddr) -> BoxResult<RangeFieldQuery> {
+        RangeFieldQuery::new(field, query_type, RangeType::InetAddress, Self::encode(min, max)?)
+    }
+
+    fn encode(min: IpAddr, max: IpAddr) -> BoxResult<Vec<u8>> {
+        encode_range("InetAddressRange", 1, &[min], &[max], |v| encode_inet_address(*v).to_vec())
+    }
+}
+
+/// Packs the bounds of a range, checking that there is a bound per dimension and that no minimum exceeds its
+/// maximum.
+fn encode_range<T: Display + PartialOrd>(
+    name: &str,
+    max_dims: usize,
+    min: &[T],
+    max: &[T],
+    encode: impl Fn(&T) -> Vec<u8>,
+) -> BoxResult<Vec<u8>> {
+    if min.len() != max.len() {
+        return Err(LuceneError::InvalidArgument(format!(
+            "{name} has {} minimums but {} maximums",
+            min.len(),
+            max.len()
+        ))
+        .into());
+    }
+
+    if min.is_empty() || min.len() > max_dims {
+        return Err(LuceneError::InvalidArgument(format!(
+            "{name} must have between 1 and {max_dims} dimensions; got {}",
+            min.len()
+        ))
+        .into());
+    }
+
+    let mut packed = Vec::new();
+    for (dim, (lower, upper)) in min.iter().zip(max.iter()).enumerate() {
+        // Written so that NaN, which isn't ordered, is rejected as well.
+        if !(lower <= upper && encode(lower) <= encode(upper)) {
+            return Err(LuceneError::InvalidArgument(format!(
+                "{name} minimum {lower} isn't at most maximum {upper} in dimension {dim}"
+            ))
+            .into());
+        }
+
+        packed.extend(encode(lower));
+    }
+
+    for upper in max.iter() {
+        packed.extend(encode(upper));
+    }
+
+    Ok(packed)
+}
+
+/// Encodes an integer into big-endian bytes with the sign bit flipped, so that the bytes sort like the integers.
+#[inline]
+pub(crate) fn encode_long(value: i64) -> [u8; 8] {
+    ((value as u64) ^ (1 << 63)).to_be_bytes()
+}
+
+/// Decodes an integer encoded with [encode_long].
+pub(crate) fn decode_long(bytes: &[u8]) -> i64 {
+    let mut be = [0; 8];
+    be.copy_from_slice(bytes);
+    (u64::from_be_bytes(be) ^ (1 << 63)) as i64
+}
+
+/// Encodes a double into bytes that sort like the doubles, by flipping the magnitude bits of negative numbers before
+/// encoding the bits as with [encode_long]. `-0.0` sorts before `0.0`.
+#[inline]
+pub(crate) fn encode_double(value: f64) -> [u8; 8] {
+    let bits = value.to_bits() as i64;
+    encode_long(bits ^ ((bits >> 63) & i64::MAX))
+}
+
+/// Decodes a double encoded with [encode_double].
+pub(crate) fn decode_double(bytes: &[u8]) -> f64 {
+    let bits = decode_long(bytes);
+    f64::from_bits((bits ^ ((bits >> 63) & i64::MAX)) as u64)
+}
+
+/// Encodes an address as the 16 bytes of its IPv6 form.
+#[inline]
+pub(crate) fn encode_inet_address(address: IpAddr) -> [u8; 16] {
+    match address {
+        IpAddr::V4(v4) => v4.to_ipv6_mapped().octets(),
+        IpAddr::V6(v6) => v6.octets(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::{decode_double, decode_long, encode_double, encode_inet_address, encode_long},
+        crate::document::{DoubleRange, LongRange, RangeType},
+        pretty_assertions::assert_eq,
+        std::net::IpAddr,
+    };
+
+    #[test]
+    fn test_sortable_encoding() {
+        let longs = [i64::MIN, -1_000, -1, 0, 1, 1_000, i64::MAX];
+        for pair in longs.windows(2) {
+            assert!(encode_long(pair[0]) < encode_long(pair[1]));
+        }
+        for value in longs {
+            assert_eq!(decode_long(&encode_long(value)), value);
+        }
+
+        let doubles = [f64::NEG_INFINITY, -1e300, -2.5, -0.0, 0.0, 1e-300, 2.5, f64::INFINITY];
+        for pair in doubles.windows(2) {
+            assert!(encode_double(pair[0]) < encode_double(pair[1]));
+        }
+        for value in doubles {
+            assert_eq!(decode_double(&encode_double(value)).to_bits(), value.to_bits());
+        }
+
+        let v4: IpAddr = "192.168.1.1".parse().unwrap();
+        let v6: IpAddr = "2001:db8::1".parse().unwrap();
+        assert!(encode_inet_address(v4) < encode_inet_address(v6));
+        assert_eq!(RangeType::InetAddress.format_bound(&encode_inet_address(v4)), "192.168.1.1");
+        assert_eq!(RangeType::InetAddress.format_bound(&encode_inet_address(v6)), "2001:db8::1");
+
+        assert!(LongRange::new_field("r", &[1, 2], &[3]).is_err());
+        assert!(LongRange::new_field("r", &[], &[]).is_err());
+        assert!(LongRange::new_field("r", &[1, 2, 3, 4, 5], &[6, 7, 8, 9, 10]).is_err());
+        assert!(LongRange::new_field("r", &[5], &[3]).is_err());
+        assert!(DoubleRange::new_field("r", &[f64::NAN], &[1.0]).is_err());
+        assert!(DoubleRange::new_field("r", &[-0.0], &[0.0]).is_ok());
+    }
+}
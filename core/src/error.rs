@@ -1,26 +1,61 @@
 use {
-    crate::{codec::CODEC_MAGIC, index::MAX_DOCS},
+    crate::{codec::CODEC_MAGIC, index::MAX_DOCS, search::MemoryLimitExceeded},
     std::{
         error::Error,
         fmt::{Display, Formatter, Result as FmtResult},
+        io::{Error as IoError, ErrorKind as IoErrorKind},
     },
 };
 
 /// Errors that can occur in Lucene.
+///
+/// Each variant has a stable [LuceneError::code] for programs that need to tell errors apart without matching on
+/// their messages. Errors can be annotated with what was being done when they occurred (see [ErrorContext]), which
+/// wraps them in [LuceneError::WithContext]; use [LuceneError::find] to look through the annotations, and through
+/// I/O errors carrying a [LuceneError], for the underlying error.
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum LuceneError {
+    /// An object (such as a lock or writer) was used after it was closed.
+    AlreadyClosed(String),
+
+    /// A [crate::search::LeafCollector] doesn't need any more documents from its segment. Searchers catch this and
+    /// move on to the next segment.
+    CollectionTerminated,
+
     /// The index is corrupt.
-    CorruptIndex(String),
+    CorruptIndex(CorruptIndexError),
 
     /// The codec name in the index is incorrect and was expected to be something else.
     IncorrectCodecName(Vec<u8> /* name */, String /* expected */),
 
+    /// The index was written in a format newer than this version supports.
+    IndexFormatTooNew(String /* resource */, u32 /* version */, u32 /* min */, u32 /* max */),
+
+    /// The index was written in a format older than this version supports.
+    IndexFormatTooOld(String /* resource */, u32 /* version */, u32 /* min */, u32 /* max */),
+
+    /// No index was found in a directory.
+    IndexNotFound(String),
+
+    /// An operation was called at the wrong time, such as finishing a commit that was never prepared.
+    IllegalState(String /* message */),
+
+    /// An argument passed to a function was invalid.
+    InvalidArgument(String /* message */),
+
     /// A codec name was invalid (not a valid ASCII string under 128 bytes).
     InvalidCodecName(String),
 
     /// The codec header magic bytes were incorrect.
     InvalidCodecHeaderMagic([u8; 4]),
 
+    /// An expression could not be parsed.
+    InvalidExpression(String /* message */),
+
+    /// A regular expression could not be parsed.
+    InvalidRegExp(String /* message */),
+
     /// A sort field specification was invalid.
     InvalidSortField(String /* message */),
 
@@ -30,9 +65,34 @@ pub enum LuceneError {
     /// A version number in a stream was invalid.
     InvalidVersionStreamData(i32, i32, i32),
 
+    /// A lock could not be obtained because it is held by another writer.
+    LockObtainFailed(String),
+
+    /// A lock could not be released.
+    LockReleaseFailed(String),
+
+    /// A reservation would have taken a search over its memory budget, so the search was aborted.
+    MemoryLimitExceeded(MemoryLimitExceeded),
+
     /// A sort field was missing.
     MissingSortDirectives,
 
+    /// A [crate::search::PointInTime] was closed, expired, or never opened.
+    PointInTimeNotFound(String /* id */),
+
+    /// A document doesn't match the [crate::index::Schema] of the index, or a schema is incompatible with the one the
+    /// index was written with.
+    SchemaViolation(String /* message */),
+
+    /// A search was stopped because its [crate::search::QueryTimeout] expired or was cancelled.
+    SearchAborted,
+
+    /// An automaton operation would require too much work to determinize.
+    TooComplexToDeterminize(String /* message */),
+
+    /// A query expanded to more terms or clauses than its budget allows.
+    TooManyClauses(String /* message */),
+
     /// Too many documents (beyond [crate::index::MAX_DOCS]) were encountered.
     TooManyDocs(u64 /* actual */),
 
@@ -45,17 +105,75 @@ pub enum LuceneError {
     /// A sort field type was unknown.
     UnknownSortFieldType(String),
 
-    /// A given codec version is unsupported.
-    UnsupportedCodecVersion(String, u32, u32, u32),
-
     /// The Lucene version of the data is unsupported.
     UnsupportedLuceneVersion(String),
+
+    /// Another error, annotated with what was being done when it occurred.
+    WithContext(String /* context */, BoxError /* source */),
+}
+
+impl LuceneError {
+    /// Returns a stable, machine-readable code for the kind of error, such as `"corrupt_index"`. For
+    /// [LuceneError::WithContext], this is the code of the underlying error, or `"other"` if that isn't a
+    /// [LuceneError].
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::AlreadyClosed(_) => "already_closed",
+            Self::CollectionTerminated => "collection_terminated",
+            Self::CorruptIndex(_) => "corrupt_index",
+            Self::IncorrectCodecName(..) => "incorrect_codec_name",
+            Self::IndexFormatTooNew(..) => "index_format_too_new",
+            Self::IndexFormatTooOld(..) => "index_format_too_old",
+            Self::IndexNotFound(_) => "index_not_found",
+            Self::IllegalState(_) => "illegal_state",
+            Self::InvalidArgument(_) => "invalid_argument",
+            Self::InvalidCodecName(_) => "invalid_codec_name",
+            Self::InvalidCodecHeaderMagic(_) => "invalid_codec_header_magic",
+            Self::InvalidExpression(_) => "invalid_expression",
+            Self::InvalidRegExp(_) => "invalid_reg_exp",
+            Self::InvalidSortField(_) => "invalid_sort_field",
+            Self::InvalidVersionString(_) => "invalid_version_string",
+            Self::InvalidVersionStreamData(..) => "invalid_version_stream_data",
+            Self::LockObtainFailed(_) => "lock_obtain_failed",
+            Self::LockReleaseFailed(_) => "lock_release_failed",
+            Self::MemoryLimitExceeded(_) => "memory_limit_exceeded",
+            Self::MissingSortDirectives => "missing_sort_directives",
+            Self::PointInTimeNotFound(_) => "point_in_time_not_found",
+            Self::SchemaViolation(_) => "schema_violation",
+            Self::SearchAborted => "search_aborted",
+            Self::TooComplexToDeterminize(_) => "too_complex_to_determinize",
+            Self::TooManyClauses(_) => "too_many_clauses",
+            Self::TooManyDocs(_) => "too_many_docs",
+            Self::UnknownCodec(_) => "unknown_codec",
+            Self::UnknownSortFieldProvider(_) => "unknown_sort_field_provider",
+            Self::UnknownSortFieldType(_) => "unknown_sort_field_type",
+            Self::UnsupportedLuceneVersion(_) => "unsupported_lucene_version",
+            Self::WithContext(_, source) => Self::find(source.as_ref()).map_or("other", Self::code),
+        }
+    }
+
+    /// Returns the underlying [LuceneError] of an error, looking through [LuceneError::WithContext] annotations and
+    /// I/O errors that carry a [LuceneError], or `None` if there is none.
+    pub fn find<'a>(error: &'a (dyn Error + 'static)) -> Option<&'a LuceneError> {
+        if let Some(error) = error.downcast_ref::<LuceneError>() {
+            match error {
+                Self::WithContext(_, source) => Self::find(source.as_ref()),
+                _ => Some(error),
+            }
+        } else if let Some(error) = error.downcast_ref::<IoError>() {
+            error.get_ref().and_then(|inner| Self::find(inner))
+        } else {
+            None
+        }
+    }
 }
 
 impl Display for LuceneError {
     fn fmt(&self, f: &mut Formatter) -> FmtResult {
         match self {
-            Self::CorruptIndex(message) => write!(f, "Corrupt index: {message}"),
+            Self::AlreadyClosed(message) => write!(f, "Already closed: {message}"),
+            Self::CollectionTerminated => write!(f, "Collection terminated"),
+            Self::CorruptIndex(error) => write!(f, "Corrupt index: {error}"),
             Self::IncorrectCodecName(actual, expected) => {
                 if let Ok(actual) = String::from_utf8(actual.clone()) {
                     write!(f, "Incorrect codec name: got {actual:?}, expected {expected:?}")
@@ -63,35 +181,236 @@ impl Display for LuceneError {
                     write!(f, "Incorrect codec name: got {actual:#x?}, expected {expected:?}")
                 }
             }
+            Self::IndexFormatTooNew(resource, version, min, max) => write!(
+                f,
+                "Index format too new: {resource} has version {version}, but this version supports {min} to {max}"
+            ),
+            Self::IndexFormatTooOld(resource, version, min, max) => write!(
+                f,
+                "Index format too old: {resource} has version {version}, but this version supports {min} to {max}"
+            ),
+            Self::IndexNotFound(message) => write!(f, "Index not found: {message}"),
+            Self::IllegalState(message) => write!(f, "Illegal state: {message}"),
+            Self::InvalidArgument(message) => write!(f, "Invalid argument: {message}"),
             Self::InvalidCodecHeaderMagic(actual) => {
                 write!(f, "Invalid codec header: got {actual:#x?}, expected {CODEC_MAGIC:#x?}")
             }
+            Self::InvalidExpression(message) => write!(f, "Invalid expression: {message}"),
             Self::InvalidCodecName(codec_name) => {
                 write!(f, "Invalid codec name: {codec_name:?} is not a valid ASCII string under 128 bytes")
             }
+            Self::InvalidRegExp(message) => write!(f, "Invalid regular expression: {message}"),
             Self::InvalidSortField(message) => write!(f, "Invalid sort field: {message}"),
             Self::InvalidVersionString(version) => write!(f, "Invalid version string: {version}"),
             Self::InvalidVersionStreamData(major, minor, bugfix) => {
                 write!(f, "Invalid version data in stream: {major}.{minor}.{bugfix}")
             }
+            Self::LockObtainFailed(message) => write!(f, "Lock obtain failed: {message}"),
+            Self::LockReleaseFailed(message) => write!(f, "Lock release failed: {message}"),
+            Self::MemoryLimitExceeded(exceeded) => write!(f, "Memory limit exceeded: {exceeded}"),
             Self::MissingSortDirectives => write!(f, "Missing sort directives"),
+            Self::PointInTimeNotFound(id) => write!(f, "Point in time not found: {id} is closed or has expired"),
+            Self::SchemaViolation(message) => write!(f, "Schema violation: {message}"),
+            Self::SearchAborted => write!(f, "Search aborted: timed out or cancelled"),
+            Self::TooComplexToDeterminize(message) => write!(f, "Automaton too complex to determinize: {message}"),
+            Self::TooManyClauses(message) => write!(f, "Too many clauses: {message}"),
             Self::TooManyDocs(actual) => write!(f, "Too many docs: {actual} exceeds MAX_DOCS value of {MAX_DOCS}"),
             Self::UnknownCodec(name) => write!(f, "Unknown codec: {name}"),
             Self::UnknownSortFieldProvider(name) => write!(f, "Unknown sort directive provider: {name}"),
             Self::UnknownSortFieldType(name) => write!(f, "Unknown sort field type: {name}"),
-            Self::UnsupportedCodecVersion(name, actual, min, max) => write!(
-                f,
-                "Codec version mismatch: {name} version {actual} is not supported (must be between {min} and {max}"
-            ),
             Self::UnsupportedLuceneVersion(version) => write!(f, "Unsupported Lucene version: {version}"),
+            Self::WithContext(context, source) => write!(f, "{context}: {source}"),
         }
     }
 }
 
-impl Error for LuceneError {}
+impl Error for LuceneError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::WithContext(_, source) => Some(source.as_ref()),
+            _ => None,
+        }
+    }
+}
 
 /// A type alias for any kind of error. The error is boxed and must be `Send`, `Sync`, and `'static`.
 pub type BoxError = Box<dyn Error + Send + Sync + 'static>;
 
 /// A type alias for a `Result` with a [BoxError].
 pub type BoxResult<T> = Result<T, BoxError>;
+
+/// Describes corrupt index data: what is wrong with it and, where known, the file it was found in and the position
+/// in the file, as Lucene's `CorruptIndexException` does. Decoders report the first; the resource and position are
+/// usually filled in by whoever opened the file, with [locate_corruption].
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct CorruptIndexError {
+    message: String,
+    resource: Option<String>,
+    position: Option<u64>,
+}
+
+impl CorruptIndexError {
+    /// Creates an error with the given description and no location.
+    pub fn new(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+            resource: None,
+            position: None,
+        }
+    }
+
+    /// Sets the file or other resource holding the corrupt data.
+    pub fn with_resource(mut self, resource: impl Into<String>) -> Self {
+        self.resource = Some(resource.into());
+        self
+    }
+
+    /// Sets the position of the corrupt data within its resource.
+    pub fn with_position(mut self, position: u64) -> Self {
+        self.position = Some(position);
+        self
+    }
+
+    /// Returns the description of what is wrong.
+    #[inline]
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Returns the file or other resource holding the corrupt data, if known.
+    #[inline]
+    pub fn resource(&self) -> Option<&str> {
+        self.resource.as_deref()
+    }
+
+    /// Returns the position of the corrupt data within its resource, if known.
+    #[inline]
+    pub fn position(&self) -> Option<u64> {
+        self.position
+    }
+}
+
+impl From<String> for CorruptIndexError {
+    fn from(message: String) -> Self {
+        Self::new(message)
+    }
+}
+
+impl From<&str> for CorruptIndexError {
+    fn from(message: &str) -> Self {
+        Self::new(message)
+    }
+}
+
+impl Display for CorruptIndexError {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "{}", self.message)?;
+        match (&self.resource, self.position) {
+            (Some(resource), Some(position)) => write!(f, " (resource={resource}, position={position})"),
+            (Some(resource), None) => write!(f, " (resource={resource})"),
+            (None, Some(position)) => write!(f, " (position={position})"),
+            (None, None) => Ok(()),
+        }
+    }
+}
+
+/// Records where corrupt data was found. If `error` is a [LuceneError::CorruptIndex], its missing resource and
+/// position are set to those given. If it is an I/O error from decoding invalid or truncated data, it is turned into
+/// a [LuceneError::CorruptIndex] at that location. Any other error is returned unchanged.
+pub fn locate_corruption(error: BoxError, resource: &str, position: u64) -> BoxError {
+    let error = match error.downcast::<LuceneError>() {
+        Ok(error) => {
+            return match *error {
+                LuceneError::CorruptIndex(mut corrupt) => {
+                    corrupt.resource.get_or_insert_with(|| resource.to_string());
+                    corrupt.position.get_or_insert(position);
+                    LuceneError::CorruptIndex(corrupt).into()
+                }
+                error => error.into(),
+            }
+        }
+        Err(error) => error,
+    };
+
+    match error.downcast::<IoError>() {
+        Ok(error) if matches!(error.kind(), IoErrorKind::InvalidData | IoErrorKind::UnexpectedEof) => {
+            let corrupt = match LuceneError::find(error.as_ref()) {
+                Some(LuceneError::CorruptIndex(corrupt)) => corrupt.clone(),
+                _ if error.kind() == IoErrorKind::UnexpectedEof => CorruptIndexError::new("unexpected end of file"),
+                _ => CorruptIndexError::new(error.to_string()),
+            };
+            locate_corruption(LuceneError::CorruptIndex(corrupt).into(), resource, position)
+        }
+        Ok(error) => error,
+        Err(error) => error,
+    }
+}
+
+/// Annotates the errors of a result with what was being done when they occurred, wrapping them in
+/// [LuceneError::WithContext].
+pub trait ErrorContext<T> {
+    /// Annotates an error with the given context.
+    fn context(self, context: impl Into<String>) -> BoxResult<T>;
+
+    /// Annotates an error with the context returned by `f`, which is only called if there is an error.
+    fn with_context<C: Into<String>, F: FnOnce() -> C>(self, f: F) -> BoxResult<T>;
+}
+
+impl<T, E: Into<BoxError>> ErrorContext<T> for Result<T, E> {
+    fn context(self, context: impl Into<String>) -> BoxResult<T> {
+        self.map_err(|e| LuceneError::WithContext(context.into(), e.into()).into())
+    }
+
+    fn with_context<C: Into<String>, F: FnOnce() -> C>(self, f: F) -> BoxResult<T> {
+        self.map_err(|e| LuceneError::WithContext(f().into(), e.into()).into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{locate_corruption, BoxError, BoxResult, CorruptIndexError, ErrorContext, LuceneError},
+        pretty_assertions::assert_eq,
+        std::io::{Error as IoError, ErrorKind as IoErrorKind},
+    };
+
+    #[test]
+    fn test_error_context() {
+        let result: BoxResult<()> = Err(LuceneError::CorruptIndex("checksum mismatch".into()).into());
+        let error = result.context("reading _0.si").with_context(|| "opening index").unwrap_err();
+        assert_eq!(error.to_string(), "opening index: reading _0.si: Corrupt index: checksum mismatch");
+        assert!(matches!(LuceneError::find(error.as_ref()), Some(LuceneError::CorruptIndex(_))));
+        assert_eq!(error.downcast_ref::<LuceneError>().unwrap().code(), "corrupt_index");
+        assert_eq!(error.source().unwrap().to_string(), "reading _0.si: Corrupt index: checksum mismatch");
+
+        let io_error = IoError::new(IoErrorKind::InvalidData, LuceneError::CorruptIndex("bad vint".into()));
+        let error: BoxError = io_error.into();
+        assert_eq!(LuceneError::find(error.as_ref()).unwrap().code(), "corrupt_index");
+
+        let error: BoxError = IoError::new(IoErrorKind::NotFound, "segments_1").into();
+        assert!(LuceneError::find(error.as_ref()).is_none());
+        let error = Err::<(), _>(error).context("opening index").unwrap_err();
+        assert_eq!(error.downcast_ref::<LuceneError>().unwrap().code(), "other");
+    }
+
+    #[test]
+    fn test_locate_corruption() {
+        let error = locate_corruption(LuceneError::CorruptIndex("bad count".into()).into(), "_0.si", 12);
+        assert_eq!(error.to_string(), "Corrupt index: bad count (resource=_0.si, position=12)");
+
+        // A location that is already known is kept.
+        let corrupt = CorruptIndexError::new("bad count").with_resource("_1.si");
+        let error = locate_corruption(LuceneError::CorruptIndex(corrupt).into(), "segments_2", 40);
+        assert_eq!(error.to_string(), "Corrupt index: bad count (resource=_1.si, position=40)");
+
+        let io_error = IoError::new(IoErrorKind::InvalidData, LuceneError::CorruptIndex("bad vint".into()));
+        let error = locate_corruption(io_error.into(), "segments_1", 7);
+        assert_eq!(error.to_string(), "Corrupt index: bad vint (resource=segments_1, position=7)");
+
+        let error = locate_corruption(IoError::from(IoErrorKind::UnexpectedEof).into(), "segments_1", 318);
+        assert_eq!(error.to_string(), "Corrupt index: unexpected end of file (resource=segments_1, position=318)");
+
+        let error = locate_corruption(IoError::from(IoErrorKind::NotFound).into(), "segments_1", 0);
+        assert_eq!(error.downcast_ref::<IoError>().unwrap().kind(), IoErrorKind::NotFound);
+    }
+}
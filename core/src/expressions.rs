@@ -0,0 +1,5 @@
+mod bindings;
+mod expression;
+mod parser;
+
+pub use {bindings::*, expression::*};
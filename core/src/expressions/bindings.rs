@@ -0,0 +1,41 @@
+use {
+    crate::search::{DoubleValuesSource, ScoreValuesSource},
+    std::{collections::HashMap, sync::Arc},
+};
+
+/// Binds the variable names used in an [crate::expressions::Expression] to sources of per-document values.
+///
+/// The variable `_score` is bound to the document's score by default.
+#[derive(Clone, Debug)]
+pub struct Bindings {
+    sources: HashMap<String, Arc<dyn DoubleValuesSource>>,
+}
+
+impl Bindings {
+    /// Creates bindings with only `_score` bound.
+    pub fn new() -> Self {
+        let mut sources: HashMap<String, Arc<dyn DoubleValuesSource>> = HashMap::new();
+        sources.insert("_score".to_string(), Arc::new(ScoreValuesSource));
+        Self {
+            sources,
+        }
+    }
+
+    /// Binds `name` to `source`, replacing any previous binding.
+    pub fn add(&mut self, name: &str, source: Arc<dyn DoubleValuesSource>) -> &mut Self {
+        self.sources.insert(name.to_string(), source);
+        self
+    }
+
+    /// Returns the source bound to `name`, if any.
+    #[inline]
+    pub fn get(&self, name: &str) -> Option<&Arc<dyn DoubleValuesSource>> {
+        self.sources.get(name)
+    }
+}
+
+impl Default for Bindings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
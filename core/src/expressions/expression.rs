@@ -0,0 +1,260 @@
+use {
+    crate::{
+        expressions::{
+            parser::{BinaryOp, Node, Parser, UnaryOp},
+            Bindings,
+        },
+        index::LeafReaderContext,
+        search::{DoubleValues, DoubleValuesSource, Explanation},
+        BoxResult, LuceneError,
+    },
+    std::{
+        fmt::{Display, Formatter, Result as FmtResult},
+        sync::Arc,
+    },
+};
+
+/// A compiled arithmetic expression over per-document variables, such as `_score * log(1 + popularity)`.
+///
+/// Expressions use JavaScript-like syntax: numbers, variables, the operators `+ - * / %`, comparisons
+/// (`< <= > >= == !=`), logical `&& || !`, the conditional `a ? b : c`, and the functions `abs`, `ceil`, `exp`,
+/// `floor`, `ln` (or `log`), `log10`, `log1p`, `max`, `min`, `pow`, `sqrt`, and the decay functions
+/// `decay_exp`, `decay_gauss` and `decay_linear` taking `(value, origin, scale, decay)`. Comparisons and logical
+/// operators evaluate to 1 for true and 0 for false.
+///
+/// Variables are resolved against [Bindings] when the expression is turned into a [DoubleValuesSource].
+#[derive(Clone, Debug)]
+pub struct Expression {
+    text: String,
+    root: Arc<Node>,
+    variables: Vec<String>,
+}
+
+impl Expression {
+    /// Parses `text` into an expression.
+    pub fn compile(text: &str) -> BoxResult<Self> {
+        let (root, variables) = Parser::parse(text)?;
+        Ok(Self {
+            text: text.to_string(),
+            root: Arc::new(root),
+            variables,
+        })
+    }
+
+    /// Returns the source text of the expression.
+    #[inline]
+    pub fn source_text(&self) -> &str {
+        &self.text
+    }
+
+    /// Returns the variables referenced by the expression, in order of first appearance.
+    #[inline]
+    pub fn variables(&self) -> &[String] {
+        &self.variables
+    }
+
+    /// Evaluates the expression with the given variable values, indexed as in [Expression::variables].
+    pub fn evaluate(&self, values: &[f64]) -> f64 {
+        evaluate(&self.root, values)
+    }
+
+    /// Returns a [DoubleValuesSource] computing the expression, with variables resolved through `bindings`.
+    pub fn values_source(&self, bindings: &Bindings) -> BoxResult<Arc<dyn DoubleValuesSource>> {
+        let mut sources = Vec::with_capacity(self.variables.len());
+        for name in &self.variables {
+            let Some(source) = bindings.get(name) else {
+                return Err(LuceneError::InvalidArgument(format!(
+                    "Variable {name:?} in expression {:?} is not bound",
+                    self.text
+                ))
+                .into());
+            };
+            sources.push(source.clone());
+        }
+
+        Ok(Arc::new(ExpressionValuesSource {
+            expression: self.clone(),
+            sources,
+        }))
+    }
+}
+
+impl Display for Expression {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        f.write_str(&self.text)
+    }
+}
+
+#[inline]
+fn truth(value: bool) -> f64 {
+    if value {
+        1.0
+    } else {
+        0.0
+    }
+}
+
+fn evaluate(node: &Node, values: &[f64]) -> f64 {
+    match node {
+        Node::Number(n) => *n,
+        Node::Variable(index) => values[*index],
+        Node::Unary(UnaryOp::Negate, operand) => -evaluate(operand, values),
+        Node::Unary(UnaryOp::Not, operand) => truth(evaluate(operand, values) == 0.0),
+        Node::Binary(BinaryOp::And, left, right) => {
+            truth(evaluate(left, values) != 0.0 && evaluate(right, values) != 0.0)
+        }
+        Node::Binary(BinaryOp::Or, left, right) => {
+            truth(evaluate(left, values) != 0.0 || evaluate(right, values) != 0.0)
+        }
+        Node::Binary(op, left, right) => {
+            let (left, right) = (evaluate(left, values), evaluate(right, values));
+            match op {
+                BinaryOp::Add => left + right,
+                BinaryOp::Subtract => left - right,
+                BinaryOp::Multiply => left * right,
+                BinaryOp::Divide => left / right,
+                BinaryOp::Remainder => left % right,
+                BinaryOp::Less => truth(left < right),
+                BinaryOp::LessOrEqual => truth(left <= right),
+                BinaryOp::Greater => truth(left > right),
+                BinaryOp::GreaterOrEqual => truth(left >= right),
+                BinaryOp::Equal => truth(left == right),
+                BinaryOp::NotEqual => truth(left != right),
+                BinaryOp::And | BinaryOp::Or => unreachable!(),
+            }
+        }
+        Node::Conditional(condition, then, otherwise) => {
+            if evaluate(condition, values) != 0.0 {
+                evaluate(then, values)
+            } else {
+                evaluate(otherwise, values)
+            }
+        }
+        Node::Call(function, args) => {
+            let args: Vec<f64> = args.iter().map(|arg| evaluate(arg, values)).collect();
+            function.apply(&args)
+        }
+    }
+}
+
+/// Computes an expression from the values of its bound variables. Variables without a value are treated as 0.
+#[derive(Debug)]
+struct ExpressionValuesSource {
+    expression: Expression,
+    sources: Vec<Arc<dyn DoubleValuesSource>>,
+}
+
+impl DoubleValuesSource for ExpressionValuesSource {
+    fn values(&self, context: &LeafReaderContext) -> BoxResult<Box<dyn DoubleValues>> {
+        let mut values = Vec::with_capacity(self.sources.len());
+        for source in &self.sources {
+            values.push(source.values(context)?);
+        }
+
+        Ok(Box::new(ExpressionValues {
+            root: self.expression.root.clone(),
+            scratch: vec![0.0; values.len()],
+            values,
+        }))
+    }
+
+    fn needs_scores(&self) -> bool {
+        self.sources.iter().any(|source| source.needs_scores())
+    }
+
+    fn explain(&self, context: &LeafReaderContext, doc: u32, score: f32) -> BoxResult<Explanation> {
+        let mut details = Vec::with_capacity(self.sources.len());
+        let mut scratch = Vec::with_capacity(self.sources.len());
+        for (name, source) in self.expression.variables.iter().zip(&self.sources) {
+            let explanation = source.explain(context, doc, score)?;
+            let value = if explanation.is_match() {
+                explanation.value() as f64
+            } else {
+                0.0
+            };
+            scratch.push(value);
+            details.push(Explanation::matched(value as f32, format!("{name}, computed from:"), vec![explanation]));
+        }
+
+        let value = self.expression.evaluate(&scratch);
+        Ok(Explanation::matched(value as f32, format!("expression({}), computed from:", self.expression), details))
+    }
+}
+
+impl Display for ExpressionValuesSource {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "expression({})", self.expression)
+    }
+}
+
+#[derive(Debug)]
+struct ExpressionValues {
+    root: Arc<Node>,
+    values: Vec<Box<dyn DoubleValues>>,
+    scratch: Vec<f64>,
+}
+
+impl DoubleValues for ExpressionValues {
+    fn value(&mut self, doc: u32, score: f32) -> BoxResult<Option<f64>> {
+        for (values, slot) in self.values.iter_mut().zip(self.scratch.iter_mut()) {
+            *slot = values.value(doc, score)?.unwrap_or(0.0);
+        }
+
+        Ok(Some(evaluate(&self.root, &self.scratch)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{
+            expressions::{Bindings, Expression},
+            LuceneError,
+        },
+        pretty_assertions::assert_eq,
+    };
+
+    fn eval(text: &str, values: &[f64]) -> f64 {
+        Expression::compile(text).unwrap().evaluate(values)
+    }
+
+    #[test]
+    fn test_evaluate() {
+        assert_eq!(eval("1 + 2 * 3", &[]), 7.0);
+        assert_eq!(eval("(1 + 2) * 3", &[]), 9.0);
+        assert_eq!(eval("10 - 4 - 3", &[]), 3.0);
+        assert_eq!(eval("7 % 4 / 2", &[]), 1.5);
+        assert_eq!(eval("-2 * -x", &[3.0]), 6.0);
+        assert_eq!(eval("1.5e1 + .5", &[]), 15.5);
+        assert_eq!(eval("a > b ? a : b", &[2.0, 5.0]), 5.0);
+        assert_eq!(eval("a >= 1 && !(b == 2) || 0", &[1.0, 3.0]), 1.0);
+        assert_eq!(eval("max(abs(-3), sqrt(16)) + min(1, 2)", &[]), 5.0);
+        assert_eq!(eval("pow(2, 10) + floor(1.7) + ceil(1.2)", &[]), 1027.0);
+        assert_eq!(eval("log(1) + ln(1) + log10(100) + log1p(0) + exp(0)", &[]), 3.0);
+        assert_eq!(eval("decay_exp(x, 0, 10, 0.5)", &[10.0]), 0.5);
+        assert_eq!(eval("decay_gauss(x, 5, 10, 0.5)", &[25.0]), 0.0625);
+        assert_eq!(eval("decay_linear(x, 0, 10, 0.5)", &[-10.0]), 0.5);
+        assert_eq!(eval("decay_linear(x, 0, 10, 0.5)", &[100.0]), 0.0);
+    }
+
+    #[test]
+    fn test_variables() {
+        let expression = Expression::compile("doc.price * _score + doc.price / weight_2").unwrap();
+        assert_eq!(expression.variables(), &["doc.price", "_score", "weight_2"]);
+        assert_eq!(expression.source_text(), "doc.price * _score + doc.price / weight_2");
+
+        let error = expression.values_source(&Bindings::new()).unwrap_err();
+        assert!(matches!(error.downcast_ref::<LuceneError>(), Some(LuceneError::InvalidArgument(_))), "{error}");
+    }
+
+    #[test]
+    fn test_invalid() {
+        for text in ["", "1 +", "(1", "1 2", "foo(1)", "max(1)", "a ? b", "1 $ 2", "sqrt(1,)"] {
+            let error = Expression::compile(text).unwrap_err();
+            assert!(
+                matches!(error.downcast_ref::<LuceneError>(), Some(LuceneError::InvalidExpression(_))),
+                "{text:?}: {error}"
+            );
+        }
+    }
+}
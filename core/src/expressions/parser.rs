@@ -0,0 +1,365 @@
+use crate::{BoxError, BoxResult, LuceneError};
+
+/// A node of a parsed expression.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Node {
+    Number(f64),
+
+    /// A variable, identified by its index in the expression's variable list.
+    Variable(usize),
+    Unary(UnaryOp, Box<Node>),
+    Binary(BinaryOp, Box<Node>, Box<Node>),
+    Conditional(Box<Node>, Box<Node>, Box<Node>),
+    Call(Function, Vec<Node>),
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum UnaryOp {
+    Negate,
+    Not,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum BinaryOp {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Remainder,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+    Equal,
+    NotEqual,
+    And,
+    Or,
+}
+
+/// The built-in functions.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum Function {
+    Abs,
+    Ceil,
+    Exp,
+    Floor,
+    Ln,
+    Log10,
+    Log1p,
+    Max,
+    Min,
+    Pow,
+    Sqrt,
+    DecayExp,
+    DecayGauss,
+    DecayLinear,
+}
+
+impl Function {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "abs" => Some(Self::Abs),
+            "ceil" => Some(Self::Ceil),
+            "exp" => Some(Self::Exp),
+            "floor" => Some(Self::Floor),
+            "ln" | "log" => Some(Self::Ln),
+            "log10" => Some(Self::Log10),
+            "log1p" => Some(Self::Log1p),
+            "max" => Some(Self::Max),
+            "min" => Some(Self::Min),
+            "pow" => Some(Self::Pow),
+            "sqrt" => Some(Self::Sqrt),
+            "decay_exp" => Some(Self::DecayExp),
+            "decay_gauss" => Some(Self::DecayGauss),
+            "decay_linear" => Some(Self::DecayLinear),
+            _ => None,
+        }
+    }
+
+    fn arity(self) -> usize {
+        match self {
+            Self::Max | Self::Min | Self::Pow => 2,
+            Self::DecayExp | Self::DecayGauss | Self::DecayLinear => 4,
+            _ => 1,
+        }
+    }
+
+    /// Applies the function to arguments of the correct arity.
+    pub(crate) fn apply(self, args: &[f64]) -> f64 {
+        match self {
+            Self::Abs => args[0].abs(),
+            Self::Ceil => args[0].ceil(),
+            Self::Exp => args[0].exp(),
+            Self::Floor => args[0].floor(),
+            Self::Ln => args[0].ln(),
+            Self::Log10 => args[0].log10(),
+            Self::Log1p => args[0].ln_1p(),
+            Self::Max => args[0].max(args[1]),
+            Self::Min => args[0].min(args[1]),
+            Self::Pow => args[0].powf(args[1]),
+            Self::Sqrt => args[0].sqrt(),
+            Self::DecayExp | Self::DecayGauss | Self::DecayLinear => {
+                // decay_*(value, origin, scale, decay): 1 at the origin, `decay` at `scale` away from it.
+                let (distance, scale, decay) = ((args[0] - args[1]).abs(), args[2], args[3]);
+                match self {
+                    Self::DecayExp => decay.powf(distance / scale),
+                    Self::DecayGauss => decay.powf((distance / scale).powi(2)),
+                    _ => (1.0 - distance * (1.0 - decay) / scale).max(0.0),
+                }
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Number(f64),
+    Identifier(String),
+    Operator(&'static str),
+    LeftParen,
+    RightParen,
+    Comma,
+    Question,
+    Colon,
+}
+
+/// Operators, longest first so that e.g. `<=` is preferred over `<`.
+const OPERATORS: [&str; 14] = ["&&", "||", "<=", ">=", "==", "!=", "+", "-", "*", "/", "%", "<", ">", "!"];
+
+fn tokenize(text: &str) -> BoxResult<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut rest = text;
+
+    while let Some(c) = rest.chars().next() {
+        if c.is_whitespace() {
+            rest = &rest[c.len_utf8()..];
+            continue;
+        }
+
+        if c.is_ascii_digit() || (c == '.' && rest[1..].starts_with(|d: char| d.is_ascii_digit())) {
+            let mut end = rest.find(|d: char| !d.is_ascii_digit() && d != '.').unwrap_or(rest.len());
+            // Exponents, as in 1e-3.
+            if rest[end..].starts_with(['e', 'E']) {
+                let exponent = &rest[end + 1..];
+                let sign = usize::from(exponent.starts_with(['+', '-']));
+                let digits = exponent[sign..].find(|d: char| !d.is_ascii_digit()).unwrap_or(exponent.len() - sign);
+                if digits > 0 {
+                    end += 1 + sign + digits;
+                }
+            }
+
+            let number = rest[..end].parse().map_err(|_| {
+                LuceneError::InvalidExpression(format!("invalid number {:?} in {text:?}", &rest[..end]))
+            })?;
+            tokens.push(Token::Number(number));
+            rest = &rest[end..];
+            continue;
+        }
+
+        if c.is_alphabetic() || c == '_' {
+            let end = rest.find(|d: char| !(d.is_alphanumeric() || d == '_' || d == '.')).unwrap_or(rest.len());
+            tokens.push(Token::Identifier(rest[..end].to_string()));
+            rest = &rest[end..];
+            continue;
+        }
+
+        let token = match c {
+            '(' => Token::LeftParen,
+            ')' => Token::RightParen,
+            ',' => Token::Comma,
+            ':' => Token::Colon,
+            '?' => Token::Question,
+            _ => match OPERATORS.iter().find(|op| rest.starts_with(**op)) {
+                Some(op) => Token::Operator(op),
+                None => {
+                    return Err(LuceneError::InvalidExpression(format!("unexpected character {c:?} in {text:?}")).into())
+                }
+            },
+        };
+
+        rest = match &token {
+            Token::Operator(op) => &rest[op.len()..],
+            _ => &rest[1..],
+        };
+        tokens.push(token);
+    }
+
+    Ok(tokens)
+}
+
+/// A recursive-descent parser over JavaScript-like expression syntax.
+pub(crate) struct Parser<'a> {
+    text: &'a str,
+    tokens: Vec<Token>,
+    pos: usize,
+    variables: Vec<String>,
+}
+
+impl<'a> Parser<'a> {
+    /// Parses an expression, returning its root node and the names of its variables in order of first appearance.
+    pub(crate) fn parse(text: &'a str) -> BoxResult<(Node, Vec<String>)> {
+        let mut parser = Self {
+            text,
+            tokens: tokenize(text)?,
+            pos: 0,
+            variables: Vec::new(),
+        };
+
+        let node = parser.conditional()?;
+        if parser.pos < parser.tokens.len() {
+            return Err(parser.error("unexpected trailing input"));
+        }
+
+        Ok((node, parser.variables))
+    }
+
+    fn error(&self, message: &str) -> BoxError {
+        LuceneError::InvalidExpression(format!("{message} at token {} of {:?}", self.pos, self.text)).into()
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn eat(&mut self, token: &Token) -> bool {
+        if self.peek() == Some(token) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, token: &Token) -> BoxResult<()> {
+        if self.eat(token) {
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected {token:?}")))
+        }
+    }
+
+    fn conditional(&mut self) -> BoxResult<Node> {
+        let condition = self.binary(0)?;
+        if !self.eat(&Token::Question) {
+            return Ok(condition);
+        }
+
+        let then = self.conditional()?;
+        self.expect(&Token::Colon)?;
+        let otherwise = self.conditional()?;
+        Ok(Node::Conditional(Box::new(condition), Box::new(then), Box::new(otherwise)))
+    }
+
+    /// Parses binary operators by precedence level, lowest first.
+    fn binary(&mut self, level: usize) -> BoxResult<Node> {
+        const LEVELS: [&[(&str, BinaryOp)]; 6] = [
+            &[("||", BinaryOp::Or)],
+            &[("&&", BinaryOp::And)],
+            &[("==", BinaryOp::Equal), ("!=", BinaryOp::NotEqual)],
+            &[
+                ("<", BinaryOp::Less),
+                ("<=", BinaryOp::LessOrEqual),
+                (">", BinaryOp::Greater),
+                (">=", BinaryOp::GreaterOrEqual),
+            ],
+            &[("+", BinaryOp::Add), ("-", BinaryOp::Subtract)],
+            &[("*", BinaryOp::Multiply), ("/", BinaryOp::Divide), ("%", BinaryOp::Remainder)],
+        ];
+
+        if level == LEVELS.len() {
+            return self.unary();
+        }
+
+        let mut left = self.binary(level + 1)?;
+        loop {
+            let op = match self.peek() {
+                Some(Token::Operator(op)) => LEVELS[level].iter().find(|(s, _)| s == op).map(|(_, op)| *op),
+                _ => None,
+            };
+            let Some(op) = op else {
+                return Ok(left);
+            };
+
+            self.pos += 1;
+            let right = self.binary(level + 1)?;
+            left = Node::Binary(op, Box::new(left), Box::new(right));
+        }
+    }
+
+    fn unary(&mut self) -> BoxResult<Node> {
+        match self.peek() {
+            Some(Token::Operator("-")) => {
+                self.pos += 1;
+                Ok(Node::Unary(UnaryOp::Negate, Box::new(self.unary()?)))
+            }
+            Some(Token::Operator("+")) => {
+                self.pos += 1;
+                self.unary()
+            }
+            Some(Token::Operator("!")) => {
+                self.pos += 1;
+                Ok(Node::Unary(UnaryOp::Not, Box::new(self.unary()?)))
+            }
+            _ => self.primary(),
+        }
+    }
+
+    fn primary(&mut self) -> BoxResult<Node> {
+        match self.tokens.get(self.pos).cloned() {
+            Some(Token::Number(n)) => {
+                self.pos += 1;
+                Ok(Node::Number(n))
+            }
+            Some(Token::LeftParen) => {
+                self.pos += 1;
+                let node = self.conditional()?;
+                self.expect(&Token::RightParen)?;
+                Ok(node)
+            }
+            Some(Token::Identifier(name)) => {
+                self.pos += 1;
+                if self.eat(&Token::LeftParen) {
+                    return self.call(&name);
+                }
+
+                let index = match self.variables.iter().position(|v| *v == name) {
+                    Some(index) => index,
+                    None => {
+                        self.variables.push(name);
+                        self.variables.len() - 1
+                    }
+                };
+                Ok(Node::Variable(index))
+            }
+            _ => Err(self.error("expected a number, variable, function call or parenthesized expression")),
+        }
+    }
+
+    /// Parses the arguments of a call to `name`, whose opening parenthesis has been consumed.
+    fn call(&mut self, name: &str) -> BoxResult<Node> {
+        let Some(function) = Function::from_name(name) else {
+            return Err(self.error(&format!("unknown function {name:?}")));
+        };
+
+        let mut args = Vec::new();
+        if !self.eat(&Token::RightParen) {
+            loop {
+                args.push(self.conditional()?);
+                if self.eat(&Token::RightParen) {
+                    break;
+                }
+                self.expect(&Token::Comma)?;
+            }
+        }
+
+        if args.len() != function.arity() {
+            return Err(self.error(&format!(
+                "function {name:?} takes {} arguments but {} were given",
+                function.arity(),
+                args.len()
+            )));
+        }
+
+        Ok(Node::Call(function, args))
+    }
+}
@@ -0,0 +1,6 @@
+mod facet_result;
+mod facets_collector;
+mod long_value_facet_counts;
+mod random_sampling_facets_collector;
+
+pub use {facet_result::*, facets_collector::*, long_value_facet_counts::*, random_sampling_facets_collector::*};
@@ -0,0 +1,47 @@
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+/// A facet value (label) and its count.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct LabelAndValue {
+    /// The facet value.
+    pub label: String,
+
+    /// The number of matching documents with the value.
+    pub value: u64,
+}
+
+impl LabelAndValue {
+    /// Creates a new label and count.
+    pub fn new(label: impl Into<String>, value: u64) -> Self {
+        Self {
+            label: label.into(),
+            value,
+        }
+    }
+}
+
+/// The counts of a facet dimension's values.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FacetResult {
+    /// The dimension (usually the field) the values belong to.
+    pub dim: String,
+
+    /// The number of matching documents with any value for the dimension.
+    pub value: u64,
+
+    /// The number of distinct values among the matching documents, which may exceed the number of labels returned.
+    pub child_count: usize,
+
+    /// The values and their counts.
+    pub label_values: Vec<LabelAndValue>,
+}
+
+impl Display for FacetResult {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        writeln!(f, "dim={} value={} childCount={}", self.dim, self.value, self.child_count)?;
+        for label_value in &self.label_values {
+            writeln!(f, "  {} ({})", label_value.label, label_value.value)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,126 @@
+use crate::{
+    index::LeafReaderContext,
+    search::{Collector, CollectorManager, LeafCollector, Scorable, ScoreMode},
+    util::size_of_vec,
+    BoxResult,
+};
+
+/// The documents of a single segment that matched a query.
+#[derive(Clone, Debug)]
+pub struct MatchingDocs {
+    /// The segment.
+    pub context: LeafReaderContext,
+
+    /// The segment-local ids of the matching documents, in increasing order.
+    pub docs: Vec<u32>,
+
+    /// The scores of the matching documents, if they were kept.
+    pub scores: Option<Vec<f32>>,
+}
+
+/// A [Collector] that records the matching documents of each segment, for counting facets afterwards with an
+/// implementation such as [crate::facet::LongValueFacetCounts].
+#[derive(Debug, Default)]
+pub struct FacetsCollector {
+    keep_scores: bool,
+    pub(super) matching_docs: Vec<MatchingDocs>,
+}
+
+impl FacetsCollector {
+    /// Creates a collector, which also records scores if `keep_scores` is true.
+    pub fn new(keep_scores: bool) -> Self {
+        Self {
+            keep_scores,
+            matching_docs: Vec::new(),
+        }
+    }
+
+    /// Returns the matching documents of each segment that had any.
+    #[inline]
+    pub fn matching_docs(&self) -> &[MatchingDocs] {
+        &self.matching_docs
+    }
+
+    /// Returns the total number of matching documents.
+    pub fn total_hits(&self) -> u64 {
+        self.matching_docs.iter().map(|m| m.docs.len() as u64).sum()
+    }
+}
+
+impl Collector for FacetsCollector {
+    fn leaf_collector(&mut self, context: &LeafReaderContext) -> BoxResult<Box<dyn LeafCollector + '_>> {
+        self.matching_docs.push(MatchingDocs {
+            context: context.clone(),
+            docs: Vec::new(),
+            scores: self.keep_scores.then(Vec::new),
+        });
+
+        Ok(Box::new(FacetsLeafCollector {
+            matching_docs: self.matching_docs.last_mut().unwrap(),
+        }))
+    }
+
+    fn score_mode(&self) -> ScoreMode {
+        if self.keep_scores {
+            ScoreMode::Complete
+        } else {
+            ScoreMode::CompleteNoScores
+        }
+    }
+
+    fn ram_bytes_used(&self) -> usize {
+        size_of_vec(&self.matching_docs)
+            + self
+                .matching_docs
+                .iter()
+                .map(|m| size_of_vec(&m.docs) + m.scores.as_ref().map_or(0, size_of_vec))
+                .sum::<usize>()
+    }
+}
+
+struct FacetsLeafCollector<'a> {
+    matching_docs: &'a mut MatchingDocs,
+}
+
+impl LeafCollector for FacetsLeafCollector<'_> {
+    fn collect(&mut self, doc: u32, scorer: &mut dyn Scorable) -> BoxResult<()> {
+        self.matching_docs.docs.push(doc);
+        if let Some(scores) = &mut self.matching_docs.scores {
+            scores.push(scorer.score()?);
+        }
+        Ok(())
+    }
+}
+
+/// A [CollectorManager] for [FacetsCollector]s, whose matching documents are concatenated in segment order.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FacetsCollectorManager {
+    keep_scores: bool,
+}
+
+impl FacetsCollectorManager {
+    /// Creates a manager whose collectors also record scores if `keep_scores` is true.
+    pub fn new(keep_scores: bool) -> Self {
+        Self {
+            keep_scores,
+        }
+    }
+}
+
+impl CollectorManager for FacetsCollectorManager {
+    type Collector = FacetsCollector;
+    type Result = FacetsCollector;
+
+    fn new_collector(&self) -> BoxResult<FacetsCollector> {
+        Ok(FacetsCollector::new(self.keep_scores))
+    }
+
+    fn reduce(&self, collectors: Vec<FacetsCollector>) -> BoxResult<FacetsCollector> {
+        let mut result = FacetsCollector::new(self.keep_scores);
+        for collector in collectors {
+            result.matching_docs.extend(collector.matching_docs);
+        }
+        result.matching_docs.sort_by_key(|m| m.context.ord());
+        Ok(result)
+    }
+}
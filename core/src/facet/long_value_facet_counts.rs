@@ -0,0 +1,132 @@
+use {
+    crate::{
+        facet::{FacetResult, FacetsCollector, LabelAndValue},
+        BoxResult,
+    },
+    std::collections::BTreeMap,
+};
+
+/// Counts the numeric doc values of a field among the documents matched by a query.
+#[derive(Clone, Debug)]
+pub struct LongValueFacetCounts {
+    field: String,
+    counts: BTreeMap<i64, u64>,
+    total_count: u64,
+}
+
+impl LongValueFacetCounts {
+    /// Counts the values of `field` in the documents recorded by `hits`.
+    pub fn new(field: &str, hits: &FacetsCollector) -> BoxResult<Self> {
+        let mut counts = BTreeMap::new();
+        let mut total_count = 0;
+
+        for matching in hits.matching_docs() {
+            let Some(mut doc_values) = matching.context.reader().numeric_doc_values(field)? else {
+                continue;
+            };
+
+            for &doc in &matching.docs {
+                if doc_values.advance_exact(doc)? {
+                    *counts.entry(doc_values.long_value()?).or_insert(0) += 1;
+                    total_count += 1;
+                }
+            }
+        }
+
+        Ok(Self {
+            field: field.to_string(),
+            counts,
+            total_count,
+        })
+    }
+
+    /// Returns the number of matching documents with the given value.
+    pub fn count(&self, value: i64) -> u64 {
+        self.counts.get(&value).copied().unwrap_or(0)
+    }
+
+    /// Returns the `top_n` most frequent values, breaking ties in favor of the smaller value.
+    pub fn top_children(&self, top_n: usize) -> FacetResult {
+        let mut children: Vec<(i64, u64)> = self.counts.iter().map(|(&value, &count)| (value, count)).collect();
+        children.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        children.truncate(top_n);
+        self.result(children)
+    }
+
+    /// Returns every value in increasing order.
+    pub fn all_children_sorted_by_value(&self) -> FacetResult {
+        self.result(self.counts.iter().map(|(&value, &count)| (value, count)).collect())
+    }
+
+    fn result(&self, children: Vec<(i64, u64)>) -> FacetResult {
+        FacetResult {
+            dim: self.field.clone(),
+            value: self.total_count,
+            child_count: self.counts.len(),
+            label_values: children
+                .into_iter()
+                .map(|(value, count)| LabelAndValue::new(value.to_string(), count))
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{
+            document::{Document, Field, Store},
+            facet::{FacetsCollectorManager, LabelAndValue, LongValueFacetCounts},
+            index::Term,
+            search::{test_util::searcher, MultiCollectorManager, TermQuery, TopScoreDocCollectorManager},
+        },
+        pretty_assertions::assert_eq,
+    };
+
+    #[test]
+    fn test_hits_and_facets_in_one_pass() {
+        let mut segments = Vec::new();
+        for segment in 0..8 {
+            let mut documents = Vec::new();
+            for i in 0..10 {
+                let mut doc = Document::new();
+                let body = if i % 2 == 0 {
+                    "even"
+                } else {
+                    "odd"
+                };
+                doc.add(Field::text("body", body, Store::No));
+                doc.add(Field::numeric_doc_values("year", 2000 + (segment * 10 + i) % 3));
+                documents.push(doc);
+            }
+            segments.push(documents);
+        }
+
+        let mut searcher = searcher(segments);
+        assert_eq!(searcher.slices().len(), 2);
+
+        let query = TermQuery::new(Term::from_text("body", "even"));
+        let manager =
+            MultiCollectorManager::new(TopScoreDocCollectorManager::new(5), FacetsCollectorManager::new(false));
+
+        let mut results = Vec::new();
+        for concurrent in [false, true] {
+            searcher.set_concurrent(concurrent);
+            let (top_docs, facets) = searcher.search_with_manager(&query, &manager).unwrap();
+            assert_eq!(top_docs.total_hits.value, 40);
+            assert_eq!(facets.total_hits(), 40);
+
+            let counts = LongValueFacetCounts::new("year", &facets).unwrap();
+            let top = counts.top_children(2);
+            assert_eq!(top.value, 40);
+            assert_eq!(top.child_count, 3);
+            assert_eq!(top.label_values, vec![LabelAndValue::new("2000", 14), LabelAndValue::new("2001", 13)]);
+            assert_eq!(counts.count(2002), 13);
+            assert_eq!(counts.all_children_sorted_by_value().label_values.len(), 3);
+            results.push(top_docs);
+        }
+
+        assert_eq!(results[0], results[1]);
+        assert_eq!(results[0], searcher.search(&query, 5).unwrap());
+    }
+}
@@ -0,0 +1,293 @@
+use {
+    crate::{
+        facet::{FacetResult, FacetsCollector, LabelAndValue, MatchingDocs},
+        index::LeafReaderContext,
+        search::{Collector, CollectorManager, LeafCollector, Scorable, ScoreMode},
+        BoxResult, LuceneError,
+    },
+    rand::{rngs::StdRng, Rng, SeedableRng},
+};
+
+/// A [Collector] that counts every hit but records only a uniform sample of them, so that facets can be counted over
+/// the sample and scaled up (see [RandomSamplingFacetsCollector::amortize]) to estimate the counts of a large result
+/// set cheaply, as in Lucene's `RandomSamplingFacetsCollector`.
+///
+/// Each segment's matches are divided into windows of `stride` consecutive matches, and one match of each window is
+/// kept at a random position. Every match is thus sampled with probability `1 / stride`, and the sample is spread
+/// evenly across the segment. The positions are drawn from the seed and the segment, so the same search over the same
+/// reader gives the same sample, whether or not the segments are searched concurrently.
+#[derive(Debug)]
+pub struct RandomSamplingFacetsCollector {
+    stride: u32,
+    seed: u64,
+    total_hits: u64,
+    samples: FacetsCollector,
+}
+
+impl RandomSamplingFacetsCollector {
+    /// Creates a collector keeping one match in every `stride`, at positions drawn from `seed`. This fails with
+    /// [LuceneError::InvalidArgument] if `stride` is zero.
+    pub fn new(stride: u32, seed: u64) -> BoxResult<Self> {
+        if stride == 0 {
+            return Err(LuceneError::InvalidArgument("the sampling stride must be positive".to_string()).into());
+        }
+
+        Ok(Self {
+            stride,
+            seed,
+            total_hits: 0,
+            samples: FacetsCollector::new(false),
+        })
+    }
+
+    /// Returns the number of matches per sampled match.
+    #[inline]
+    pub fn stride(&self) -> u32 {
+        self.stride
+    }
+
+    /// Returns the seed the sampled positions are drawn from.
+    #[inline]
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Returns the number of matches, sampled or not.
+    #[inline]
+    pub fn total_hits(&self) -> u64 {
+        self.total_hits
+    }
+
+    /// Returns the sampled matches, for counting facets with an implementation such as
+    /// [crate::facet::LongValueFacetCounts].
+    #[inline]
+    pub fn samples(&self) -> &FacetsCollector {
+        &self.samples
+    }
+
+    /// Returns the fraction of the matches that were sampled, which is 1 if there were none.
+    pub fn sampling_rate(&self) -> f64 {
+        match self.total_hits {
+            0 => 1.0,
+            total_hits => self.samples.total_hits() as f64 / total_hits as f64,
+        }
+    }
+
+    /// Estimates the number of matches of which `sampled_count` were among the samples, such as the matches with a
+    /// facet value. An estimate is at most the total hit count.
+    pub fn estimate(&self, sampled_count: u64) -> u64 {
+        let sampling_rate = self.sampling_rate();
+        if sampling_rate == 0.0 {
+            return 0;
+        }
+        ((sampled_count as f64 / sampling_rate).round() as u64).min(self.total_hits)
+    }
+
+    /// Scales the counts of facets counted over the samples into estimates for all the matches (see
+    /// [RandomSamplingFacetsCollector::estimate]).
+    pub fn amortize(&self, result: &FacetResult) -> FacetResult {
+        FacetResult {
+            dim: result.dim.clone(),
+            value: self.estimate(result.value),
+            child_count: result.child_count,
+            label_values: result
+                .label_values
+                .iter()
+                .map(|label_value| LabelAndValue::new(label_value.label.clone(), self.estimate(label_value.value)))
+                .collect(),
+        }
+    }
+}
+
+impl Collector for RandomSamplingFacetsCollector {
+    fn leaf_collector(&mut self, context: &LeafReaderContext) -> BoxResult<Box<dyn LeafCollector + '_>> {
+        self.samples.matching_docs.push(MatchingDocs {
+            context: context.clone(),
+            docs: Vec::new(),
+            scores: None,
+        });
+
+        let mut rng = StdRng::seed_from_u64(self.seed.wrapping_add(context.ord() as u64));
+        let sampled = rng.gen_range(0..self.stride);
+        Ok(Box::new(RandomSamplingLeafCollector {
+            stride: self.stride,
+            rng,
+            position: 0,
+            sampled,
+            total_hits: &mut self.total_hits,
+            matching_docs: self.samples.matching_docs.last_mut().unwrap(),
+        }))
+    }
+
+    #[inline]
+    fn score_mode(&self) -> ScoreMode {
+        ScoreMode::CompleteNoScores
+    }
+
+    #[inline]
+    fn ram_bytes_used(&self) -> usize {
+        self.samples.ram_bytes_used()
+    }
+}
+
+struct RandomSamplingLeafCollector<'a> {
+    stride: u32,
+    rng: StdRng,
+
+    /// The position of the next match within the current window.
+    position: u32,
+
+    /// The position of the match sampled from the current window.
+    sampled: u32,
+    total_hits: &'a mut u64,
+    matching_docs: &'a mut MatchingDocs,
+}
+
+impl LeafCollector for RandomSamplingLeafCollector<'_> {
+    fn collect(&mut self, doc: u32, _scorer: &mut dyn Scorable) -> BoxResult<()> {
+        *self.total_hits += 1;
+        if self.position == self.sampled {
+            self.matching_docs.docs.push(doc);
+        }
+
+        self.position += 1;
+        if self.position == self.stride {
+            self.position = 0;
+            self.sampled = self.rng.gen_range(0..self.stride);
+        }
+        Ok(())
+    }
+}
+
+/// A [CollectorManager] for [RandomSamplingFacetsCollector]s, whose hit counts are summed and whose samples are
+/// concatenated in segment order.
+#[derive(Clone, Copy, Debug)]
+pub struct RandomSamplingFacetsCollectorManager {
+    stride: u32,
+    seed: u64,
+}
+
+impl RandomSamplingFacetsCollectorManager {
+    /// Creates a manager whose collectors keep one match in every `stride`, at positions drawn from `seed`. This fails
+    /// with [LuceneError::InvalidArgument] if `stride` is zero.
+    pub fn new(stride: u32, seed: u64) -> BoxResult<Self> {
+        RandomSamplingFacetsCollector::new(stride, seed)?;
+        Ok(Self {
+            stride,
+            seed,
+        })
+    }
+}
+
+impl CollectorManager for RandomSamplingFacetsCollectorManager {
+    type Collector = RandomSamplingFacetsCollector;
+    type Result = RandomSamplingFacetsCollector;
+
+    fn new_collector(&self) -> BoxResult<RandomSamplingFacetsCollector> {
+        RandomSamplingFacetsCollector::new(self.stride, self.seed)
+    }
+
+    fn reduce(&self, collectors: Vec<RandomSamplingFacetsCollector>) -> BoxResult<RandomSamplingFacetsCollector> {
+        let mut result = RandomSamplingFacetsCollector::new(self.stride, self.seed)?;
+        for collector in collectors {
+            result.total_hits += collector.total_hits;
+            result.samples.matching_docs.extend(collector.samples.matching_docs);
+        }
+        result.samples.matching_docs.sort_by_key(|m| m.context.ord());
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{
+            document::{Document, Field, Store},
+            facet::{LongValueFacetCounts, RandomSamplingFacetsCollector, RandomSamplingFacetsCollectorManager},
+            index::Term,
+            search::{test_util, IndexSearcher, MatchAllDocsQuery, TermQuery},
+        },
+        pretty_assertions::assert_eq,
+    };
+
+    fn searcher() -> IndexSearcher {
+        test_util::searcher((0..8).map(|segment| {
+            (0..1_000).map(move |i| {
+                let body = if i % 4 == 0 {
+                    "rare"
+                } else {
+                    "common"
+                };
+                Document::from_iter([
+                    Field::text("body", body, Store::No),
+                    Field::numeric_doc_values("color", (segment * 1_000 + i) % 3),
+                ])
+            })
+        }))
+    }
+
+    #[test]
+    fn test_sampled_facets() {
+        let mut searcher = searcher();
+        let manager = RandomSamplingFacetsCollectorManager::new(20, 42).unwrap();
+        let sampled = searcher.search_with_manager(&MatchAllDocsQuery, &manager).unwrap();
+
+        // One match of every full window of 20 is sampled.
+        assert_eq!(sampled.total_hits(), 8_000);
+        assert_eq!(sampled.samples().total_hits(), 400);
+        assert_eq!(sampled.sampling_rate(), 0.05);
+        for matching in sampled.samples().matching_docs() {
+            for (window, doc) in matching.docs.iter().enumerate() {
+                assert_eq!(*doc as usize / 20, window);
+            }
+        }
+
+        // Counts over the sample scale up to estimates near the exact counts of about 2,667 per color.
+        let counts = LongValueFacetCounts::new("color", sampled.samples()).unwrap();
+        let estimated = sampled.amortize(&counts.all_children_sorted_by_value());
+        assert_eq!(estimated.value, 8_000);
+        assert_eq!(estimated.child_count, 3);
+        for label_value in &estimated.label_values {
+            assert!(label_value.value.abs_diff(2_667) < 400, "{estimated}");
+        }
+
+        // The sample depends on the seed alone, not on how segments are searched.
+        searcher.set_concurrent(true);
+        assert!(searcher.slices().len() > 1);
+        let concurrent = searcher.search_with_manager(&MatchAllDocsQuery, &manager).unwrap();
+        let docs = |collector: &RandomSamplingFacetsCollector| {
+            collector.samples().matching_docs().iter().map(|m| m.docs.clone()).collect::<Vec<_>>()
+        };
+        assert_eq!(docs(&concurrent), docs(&sampled));
+        let reseeded = RandomSamplingFacetsCollectorManager::new(20, 43).unwrap();
+        assert_ne!(docs(&searcher.search_with_manager(&MatchAllDocsQuery, &reseeded).unwrap()), docs(&sampled));
+    }
+
+    #[test]
+    fn test_sparse_matches() {
+        let searcher = searcher();
+        let query = TermQuery::new(Term::from_text("body", "rare"));
+
+        // Each segment has 250 matches: 2 full windows of 100 and a partial one, which may or may not be sampled.
+        let mut collector = RandomSamplingFacetsCollector::new(100, 7).unwrap();
+        searcher.search_with_collector(&query, &mut collector).unwrap();
+        assert_eq!(collector.total_hits(), 2_000);
+        for matching in collector.samples().matching_docs() {
+            assert!((2..=3).contains(&matching.docs.len()));
+            assert!(matching.docs.iter().all(|doc| doc % 4 == 0));
+        }
+        assert_eq!(collector.estimate(collector.samples().total_hits()), 2_000);
+
+        // A stride of 1 keeps every match.
+        let mut collector = RandomSamplingFacetsCollector::new(1, 7).unwrap();
+        searcher.search_with_collector(&query, &mut collector).unwrap();
+        assert_eq!(collector.samples().total_hits(), 2_000);
+        assert_eq!(collector.estimate(10), 10);
+
+        let empty = RandomSamplingFacetsCollector::new(10, 7).unwrap();
+        assert_eq!(empty.sampling_rate(), 1.0);
+        assert_eq!(empty.estimate(0), 0);
+        assert!(RandomSamplingFacetsCollector::new(0, 7).is_err());
+        assert!(RandomSamplingFacetsCollectorManager::new(0, 7).is_err());
+    }
+}
@@ -1,5 +1,8 @@
 use {
-    crate::io::Directory,
+    crate::{
+        io::{Directory, IoContext, Lock, LockFactory, NativeFsLockFactory},
+        BoxResult,
+    },
     async_trait::async_trait,
     log::error,
     std::{
@@ -7,17 +10,22 @@ use {
         io::{Error as IoError, ErrorKind as IoErrorKind, Result as IoResult},
         path::{Path, PathBuf},
         pin::Pin,
+        sync::Arc,
     },
     tokio::{
-        fs::{create_dir_all, metadata, read_dir, remove_dir_all, remove_file, rename, OpenOptions},
+        fs::{create_dir_all, metadata, read_dir, remove_dir_all, remove_file, rename, File, OpenOptions},
         io::{AsyncRead, AsyncWrite},
     },
 };
 
 /// Implementation of a Lucene directory (database) that stores index files on te file system.
+///
+/// Locks are obtained using a [NativeFsLockFactory] unless another factory is set with
+/// [FilesystemDirectory::set_lock_factory].
 #[derive(Debug)]
 pub struct FilesystemDirectory {
     path: PathBuf,
+    lock_factory: Arc<dyn LockFactory>,
 }
 
 impl FilesystemDirectory {
@@ -27,6 +35,24 @@ impl FilesystemDirectory {
         &self.path
     }
 
+    /// Returns the lock factory used to obtain locks in this directory.
+    #[inline]
+    pub fn lock_factory(&self) -> &Arc<dyn LockFactory> {
+        &self.lock_factory
+    }
+
+    /// Replaces the lock factory used to obtain locks in this directory.
+    pub fn set_lock_factory(&mut self, lock_factory: Arc<dyn LockFactory>) {
+        self.lock_factory = lock_factory;
+    }
+
+    fn new(path: &Path) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            lock_factory: Arc::new(NativeFsLockFactory::default()),
+        }
+    }
+
     /// Open a directory at the given path.
     ///
     /// This will return an error if the directory does not exist.
@@ -34,12 +60,10 @@ impl FilesystemDirectory {
         let path = path.as_ref();
         let md = metadata(path).await?;
         if !md.is_dir() {
-            return Err(IoError::new(IoErrorKind::Other, format!("{} is not a directory", path.display())));
+            return Err(IoError::other(format!("{} is not a directory", path.display())));
         }
 
-        Ok(Self {
-            path: path.to_path_buf(),
-        })
+        Ok(Self::new(path))
     }
 
     /// Opens a directory at the given path, creating it if it does not exist.
@@ -48,18 +72,14 @@ impl FilesystemDirectory {
         match metadata(path).await {
             Ok(md) => {
                 if !md.is_dir() {
-                    return Err(IoError::new(IoErrorKind::Other, format!("{} is not a directory", path.display())));
+                    return Err(IoError::other(format!("{} is not a directory", path.display())));
                 }
-                Ok(Self {
-                    path: path.to_path_buf(),
-                })
+                Ok(Self::new(path))
             }
             Err(e) => {
                 if e.kind() == IoErrorKind::NotFound {
                     create_dir_all(path).await?;
-                    Ok(Self {
-                        path: path.to_path_buf(),
-                    })
+                    Ok(Self::new(path))
                 } else {
                     Err(e)
                 }
@@ -76,9 +96,7 @@ impl FilesystemDirectory {
             remove_dir_all(path).await?;
         }
         create_dir_all(path).await?;
-        Ok(Self {
-            path: path.to_path_buf(),
-        })
+        Ok(Self::new(path))
     }
 }
 
@@ -115,7 +133,7 @@ impl Directory for FilesystemDirectory {
         Ok(result)
     }
 
-    async fn create(&mut self, file_name: &str) -> IoResult<Pin<Box<dyn AsyncWrite>>> {
+    async fn create(&mut self, file_name: &str, _context: &IoContext) -> IoResult<Pin<Box<dyn AsyncWrite + Send>>> {
         let mut options = OpenOptions::new();
         options.write(true);
         options.truncate(true);
@@ -124,7 +142,7 @@ impl Directory for FilesystemDirectory {
         Ok(Box::pin(f))
     }
 
-    async fn open(&mut self, file_name: &str) -> IoResult<Pin<Box<dyn AsyncRead>>> {
+    async fn open(&mut self, file_name: &str, _context: &IoContext) -> IoResult<Pin<Box<dyn AsyncRead>>> {
         let mut options = OpenOptions::new();
         options.read(true);
         let f = options.open(self.path.join(file_name)).await?;
@@ -138,4 +156,25 @@ impl Directory for FilesystemDirectory {
     async fn rename(&mut self, old_file_name: &str, new_file_name: &str) -> IoResult<()> {
         rename(self.path.join(old_file_name), self.path.join(new_file_name)).await
     }
+
+    async fn sync(&mut self, file_names: &[&str]) -> IoResult<()> {
+        for file_name in file_names {
+            let mut options = OpenOptions::new();
+            options.write(true);
+            options.open(self.path.join(file_name)).await?.sync_all().await?;
+        }
+        Ok(())
+    }
+
+    async fn sync_meta_data(&mut self) -> IoResult<()> {
+        // Windows can't open a directory as a file, and doesn't need it to make renames durable.
+        if cfg!(windows) {
+            return Ok(());
+        }
+        File::open(&self.path).await?.sync_all().await
+    }
+
+    async fn obtain_lock(&mut self, lock_name: &str) -> BoxResult<Box<dyn Lock>> {
+        self.lock_factory.obtain_lock(&self.path, lock_name)
+    }
 }
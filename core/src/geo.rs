@@ -0,0 +1,8 @@
+mod geo_encoding;
+mod geo_utils;
+mod polygon;
+mod polygon_2d;
+mod tessellator;
+mod triangle;
+
+pub use {geo_encoding::*, geo_utils::*, polygon::*, polygon_2d::*, tessellator::*, triangle::*};
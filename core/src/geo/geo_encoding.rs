@@ -0,0 +1,117 @@
+use crate::{BoxResult, LuceneError};
+
+/// The minimum latitude, in degrees.
+pub const MIN_LAT_INCL: f64 = -90.0;
+
+/// The maximum latitude, in degrees.
+pub const MAX_LAT_INCL: f64 = 90.0;
+
+/// The minimum longitude, in degrees.
+pub const MIN_LON_INCL: f64 = -180.0;
+
+/// The maximum longitude, in degrees.
+pub const MAX_LON_INCL: f64 = 180.0;
+
+/// The size of one step of an encoded latitude, in degrees.
+const LAT_DECODE: f64 = 180.0 / (1u64 << 32) as f64;
+
+/// The size of one step of an encoded longitude, in degrees.
+const LON_DECODE: f64 = 360.0 / (1u64 << 32) as f64;
+
+/// Checks that `latitude` is a number of degrees in `-90..=90`.
+pub fn check_latitude(latitude: f64) -> BoxResult<()> {
+    if latitude.is_nan() || !(MIN_LAT_INCL..=MAX_LAT_INCL).contains(&latitude) {
+        return Err(LuceneError::InvalidArgument(format!(
+            "invalid latitude {latitude}; must be between {MIN_LAT_INCL} and {MAX_LAT_INCL}"
+        ))
+        .into());
+    }
+
+    Ok(())
+}
+
+/// Checks that `longitude` is a number of degrees in `-180..=180`.
+pub fn check_longitude(longitude: f64) -> BoxResult<()> {
+    if longitude.is_nan() || !(MIN_LON_INCL..=MAX_LON_INCL).contains(&longitude) {
+        return Err(LuceneError::InvalidArgument(format!(
+            "invalid longitude {longitude}; must be between {MIN_LON_INCL} and {MAX_LON_INCL}"
+        ))
+        .into());
+    }
+
+    Ok(())
+}
+
+/// Quantizes a latitude to 32 bits, rounding down. The encoding preserves order, and decoding is accurate to within
+/// about 4.2e-8 degrees (under a centimeter).
+pub fn encode_latitude(latitude: f64) -> BoxResult<i32> {
+    check_latitude(latitude)?;
+
+    // The largest latitude is folded into the last step so that it fits.
+    let latitude = if latitude == MAX_LAT_INCL {
+        latitude.next_down()
+    } else {
+        latitude
+    };
+    Ok((latitude / LAT_DECODE).floor() as i32)
+}
+
+/// Quantizes a longitude to 32 bits, rounding down. The encoding preserves order.
+pub fn encode_longitude(longitude: f64) -> BoxResult<i32> {
+    check_longitude(longitude)?;
+
+    let longitude = if longitude == MAX_LON_INCL {
+        longitude.next_down()
+    } else {
+        longitude
+    };
+    Ok((longitude / LON_DECODE).floor() as i32)
+}
+
+/// Returns the latitude, in degrees, at the start of the step of an encoded latitude.
+#[inline]
+pub fn decode_latitude(encoded: i32) -> f64 {
+    encoded as f64 * LAT_DECODE
+}
+
+/// Returns the longitude, in degrees, at the start of the step of an encoded longitude.
+#[inline]
+pub fn decode_longitude(encoded: i32) -> f64 {
+    encoded as f64 * LON_DECODE
+}
+
+/// Packs an encoded point into a single 64-bit value, latitude in the high bits, as stored in doc values.
+pub fn encode_lat_lon(latitude: f64, longitude: f64) -> BoxResult<i64> {
+    Ok(((encode_latitude(latitude)? as i64) << 32) | (encode_longitude(longitude)? as u32 as i64))
+}
+
+/// Unpacks a value produced by [encode_lat_lon] into its latitude and longitude, in degrees.
+#[inline]
+pub fn decode_lat_lon(encoded: i64) -> (f64, f64) {
+    (decode_latitude((encoded >> 32) as i32), decode_longitude(encoded as i32))
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::geo::{decode_lat_lon, decode_latitude, encode_lat_lon, encode_latitude, encode_longitude},
+        pretty_assertions::assert_eq,
+    };
+
+    #[test]
+    fn test_encoding() {
+        assert_eq!(encode_latitude(-90.0).unwrap(), i32::MIN);
+        assert_eq!(encode_latitude(90.0).unwrap(), i32::MAX);
+        assert_eq!(encode_longitude(-180.0).unwrap(), i32::MIN);
+        assert_eq!(encode_longitude(180.0).unwrap(), i32::MAX);
+        assert_eq!(decode_latitude(encode_latitude(0.0).unwrap()), 0.0);
+        assert!(encode_latitude(90.5).is_err());
+        assert!(encode_longitude(f64::NAN).is_err());
+
+        for (lat, lon) in [(40.7128, -74.006), (-33.8688, 151.2093), (0.0, 0.0), (89.9, -179.9)] {
+            let (decoded_lat, decoded_lon) = decode_lat_lon(encode_lat_lon(lat, lon).unwrap());
+            assert!((decoded_lat - lat).abs() < 1e-7 && decoded_lat <= lat);
+            assert!((decoded_lon - lon).abs() < 1e-7 && decoded_lon <= lon);
+        }
+    }
+}
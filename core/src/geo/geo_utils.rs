@@ -0,0 +1,30 @@
+/// The mean radius of the Earth, in meters, as defined by the International Union of Geodesy and Geophysics.
+pub const EARTH_MEAN_RADIUS_METERS: f64 = 6_371_008.771_4;
+
+/// Returns the great-circle distance, in meters, between two points given in degrees, using the haversine formula
+/// on a sphere of radius [EARTH_MEAN_RADIUS_METERS].
+pub fn haversin_meters(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
+    let half_dlat = (lat2 - lat1).to_radians() / 2.0;
+    let half_dlon = (lon2 - lon1).to_radians() / 2.0;
+    let h = half_dlat.sin().powi(2) + lat1.to_radians().cos() * lat2.to_radians().cos() * half_dlon.sin().powi(2);
+    2.0 * EARTH_MEAN_RADIUS_METERS * h.sqrt().min(1.0).asin()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::geo::{haversin_meters, EARTH_MEAN_RADIUS_METERS};
+
+    #[test]
+    fn test_haversin() {
+        assert_eq!(haversin_meters(12.5, -45.0, 12.5, -45.0), 0.0);
+
+        // A quarter of a meridian, and half of the equator.
+        let quarter = std::f64::consts::FRAC_PI_2 * EARTH_MEAN_RADIUS_METERS;
+        assert!((haversin_meters(0.0, 0.0, 90.0, 0.0) - quarter).abs() < 1e-6);
+        assert!((haversin_meters(0.0, -90.0, 0.0, 90.0) - 2.0 * quarter).abs() < 1e-6);
+
+        // New York to London is about 5,570 km.
+        let distance = haversin_meters(40.7128, -74.006, 51.5074, -0.1278);
+        assert!((distance - 5_570_000.0).abs() < 10_000.0, "{distance}");
+    }
+}
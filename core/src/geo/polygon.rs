@@ -0,0 +1,177 @@
+use {
+    crate::{
+        geo::{check_latitude, check_longitude},
+        BoxResult, LuceneError,
+    },
+    std::fmt::{Display, Formatter, Result as FmtResult},
+};
+
+/// A polygon on the globe, as a closed ring of points with optional holes. Coordinates are in degrees; edges are
+/// straight lines in the latitude/longitude plane and may not cross the dateline.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Polygon {
+    lats: Vec<f64>,
+    lons: Vec<f64>,
+    holes: Vec<Polygon>,
+}
+
+impl Polygon {
+    /// Creates a polygon from the points of its ring. The ring must have at least four points, the last repeating
+    /// the first.
+    pub fn new(lats: Vec<f64>, lons: Vec<f64>) -> BoxResult<Self> {
+        Self::with_holes(lats, lons, Vec::new())
+    }
+
+    /// Creates a polygon with holes, which must lie inside the polygon and must not have holes of their own.
+    pub fn with_holes(lats: Vec<f64>, lons: Vec<f64>, holes: Vec<Polygon>) -> BoxResult<Self> {
+        if lats.len() != lons.len() {
+            return Err(LuceneError::InvalidArgument(format!(
+                "Polygon has {} latitudes but {} longitudes",
+                lats.len(),
+                lons.len()
+            ))
+            .into());
+        }
+
+        if lats.len() < 4 {
+            return Err(LuceneError::InvalidArgument(format!(
+                "Polygon needs at least 4 points, the last closing the ring; got {}",
+                lats.len()
+            ))
+            .into());
+        }
+
+        if lats[0] != lats[lats.len() - 1] || lons[0] != lons[lons.len() - 1] {
+            return Err(LuceneError::InvalidArgument(
+                "Polygon is not closed: the first and last points differ".to_string(),
+            )
+            .into());
+        }
+
+        for (&lat, &lon) in lats.iter().zip(lons.iter()) {
+            check_latitude(lat)?;
+            check_longitude(lon)?;
+        }
+
+        if holes.iter().any(|hole| !hole.holes.is_empty()) {
+            return Err(LuceneError::InvalidArgument("Polygon holes can't have holes".to_string()).into());
+        }
+
+        Ok(Self {
+            lats,
+            lons,
+            holes,
+        })
+    }
+
+    /// Creates the polygon covering a latitude/longitude box. The box may not cross the dateline.
+    pub fn from_box(min_lat: f64, max_lat: f64, min_lon: f64, max_lon: f64) -> BoxResult<Self> {
+        if min_lat > max_lat || min_lon > max_lon {
+            return Err(LuceneError::InvalidArgument(format!(
+                "Invalid box: latitudes [{min_lat}, {max_lat}], longitudes [{min_lon}, {max_lon}]"
+            ))
+            .into());
+        }
+
+        Self::new(vec![min_lat, min_lat, max_lat, max_lat, min_lat], vec![min_lon, max_lon, max_lon, min_lon, min_lon])
+    }
+
+    /// Returns the latitudes of the ring, the last repeating the first.
+    #[inline]
+    pub fn lats(&self) -> &[f64] {
+        &self.lats
+    }
+
+    /// Returns the longitudes of the ring, the last repeating the first.
+    #[inline]
+    pub fn lons(&self) -> &[f64] {
+        &self.lons
+    }
+
+    /// Returns the holes.
+    #[inline]
+    pub fn holes(&self) -> &[Polygon] {
+        &self.holes
+    }
+}
+
+impl Display for Polygon {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "POLYGON(")?;
+        write_ring(f, &self.lats, &self.lons)?;
+        for hole in self.holes.iter() {
+            write!(f, ", ")?;
+            write_ring(f, &hole.lats, &hole.lons)?;
+        }
+        write!(f, ")")
+    }
+}
+
+/// A line on the globe, as a sequence of at least two points in degrees. Segments are straight lines in the
+/// latitude/longitude plane and may not cross the dateline.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Line {
+    lats: Vec<f64>,
+    lons: Vec<f64>,
+}
+
+impl Line {
+    /// Creates a line through the given points.
+    pub fn new(lats: Vec<f64>, lons: Vec<f64>) -> BoxResult<Self> {
+        if lats.len() != lons.len() {
+            return Err(LuceneError::InvalidArgument(format!(
+                "Line has {} latitudes but {} longitudes",
+                lats.len(),
+                lons.len()
+            ))
+            .into());
+        }
+
+        if lats.len() < 2 {
+            return Err(
+                LuceneError::InvalidArgument(format!("Line needs at least 2 points; got {}", lats.len())).into()
+            );
+        }
+
+        for (&lat, &lon) in lats.iter().zip(lons.iter()) {
+            check_latitude(lat)?;
+            check_longitude(lon)?;
+        }
+
+        Ok(Self {
+            lats,
+            lons,
+        })
+    }
+
+    /// Returns the latitudes of the points.
+    #[inline]
+    pub fn lats(&self) -> &[f64] {
+        &self.lats
+    }
+
+    /// Returns the longitudes of the points.
+    #[inline]
+    pub fn lons(&self) -> &[f64] {
+        &self.lons
+    }
+}
+
+impl Display for Line {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "LINESTRING")?;
+        write_ring(f, &self.lats, &self.lons)
+    }
+}
+
+/// Writes points as `(lon lat, lon lat, ...)`, in the order of well-known text.
+fn write_ring(f: &mut Formatter, lats: &[f64], lons: &[f64]) -> FmtResult {
+    write!(f, "(")?;
+    for (i, (lat, lon)) in lats.iter().zip(lons.iter()).enumerate() {
+        if i > 0 {
+            write!(f, ", ")?;
+        }
+        write!(f, "{lon} {lat}")?;
+    }
+    write!(f, ")")
+}
@@ -0,0 +1,219 @@
+use crate::{
+    geo::{encode_latitude, encode_longitude, orient, Polygon, Triangle},
+    BoxResult,
+};
+
+/// How a shape relates to a query geometry.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Relation {
+    /// The shape is entirely inside the query geometry, not touching its boundary.
+    Inside,
+
+    /// The shape and the query geometry share no point.
+    Outside,
+
+    /// The shape touches or crosses the boundary of the query geometry, or contains it.
+    Crosses,
+}
+
+/// The relation between an indexed shape and a query geometry that a shape query matches.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum QueryRelation {
+    /// The shape shares at least one point with the query geometry.
+    Intersects,
+
+    /// The shape lies entirely within the query geometry.
+    Within,
+
+    /// The shape shares no point with the query geometry.
+    Disjoint,
+}
+
+/// A [Polygon] in encoded coordinates, prepared for relating triangles to it. `x` is an encoded longitude and `y` an
+/// encoded latitude, as in [Triangle].
+#[derive(Clone, Debug)]
+pub struct Polygon2D {
+    /// The rings, outer first, without the closing points.
+    rings: Vec<Vec<(i32, i32)>>,
+    min_x: i32,
+    max_x: i32,
+    min_y: i32,
+    max_y: i32,
+}
+
+impl Polygon2D {
+    /// Encodes a polygon and its holes.
+    pub fn new(polygon: &Polygon) -> BoxResult<Self> {
+        let mut rings = Vec::with_capacity(polygon.holes().len() + 1);
+        for ring in std::iter::once(polygon).chain(polygon.holes()) {
+            let mut points = Vec::with_capacity(ring.lats().len() - 1);
+            for (&lat, &lon) in ring.lats().iter().zip(ring.lons().iter()).skip(1) {
+                points.push((encode_longitude(lon)?, encode_latitude(lat)?));
+            }
+            rings.push(points);
+        }
+
+        let outer = &rings[0];
+        Ok(Self {
+            min_x: outer.iter().map(|p| p.0).min().unwrap_or(0),
+            max_x: outer.iter().map(|p| p.0).max().unwrap_or(0),
+            min_y: outer.iter().map(|p| p.1).min().unwrap_or(0),
+            max_y: outer.iter().map(|p| p.1).max().unwrap_or(0),
+            rings,
+        })
+    }
+
+    /// Indicates whether a point is inside the polygon and outside of its holes. Points on the boundary may go
+    /// either way.
+    pub fn contains(&self, p: (i32, i32)) -> bool {
+        if p.0 < self.min_x || p.0 > self.max_x || p.1 < self.min_y || p.1 > self.max_y {
+            return false;
+        }
+
+        let mut rings = self.rings.iter();
+        rings.next().is_some_and(|outer| ring_contains(outer, p)) && !rings.any(|hole| ring_contains(hole, p))
+    }
+
+    /// Relates a triangle, which may be a segment or a point, to the polygon.
+    pub fn relate_triangle(&self, triangle: &Triangle) -> Relation {
+        let [a, b, c] = triangle.vertices;
+        let (min_x, max_x) = (a.0.min(b.0).min(c.0), a.0.max(b.0).max(c.0));
+        let (min_y, max_y) = (a.1.min(b.1).min(c.1), a.1.max(b.1).max(c.1));
+        if max_x < self.min_x || min_x > self.max_x || max_y < self.min_y || min_y > self.max_y {
+            return Relation::Outside;
+        }
+
+        for ring in self.rings.iter() {
+            for (k, &p) in ring.iter().enumerate() {
+                let q = ring[(k + 1) % ring.len()];
+                if segments_intersect(a, b, p, q) || segments_intersect(b, c, p, q) || segments_intersect(c, a, p, q) {
+                    return Relation::Crosses;
+                }
+            }
+        }
+
+        // Without boundary contact, the triangle is either inside, around part of the polygon, or outside.
+        if self.contains(a) {
+            if self.rings[1..].iter().any(|hole| point_in_triangle(hole[0], triangle)) {
+                Relation::Crosses
+            } else {
+                Relation::Inside
+            }
+        } else if point_in_triangle(self.rings[0][0], triangle) {
+            Relation::Crosses
+        } else {
+            Relation::Outside
+        }
+    }
+
+    /// Relates a shape, given as its triangles, to the polygon according to `relation`.
+    pub fn matches(&self, triangles: &[Triangle], relation: QueryRelation) -> bool {
+        match relation {
+            QueryRelation::Intersects => triangles.iter().any(|t| self.relate_triangle(t) != Relation::Outside),
+            QueryRelation::Within => {
+                !triangles.is_empty() && triangles.iter().all(|t| self.relate_triangle(t) == Relation::Inside)
+            }
+            QueryRelation::Disjoint => triangles.iter().all(|t| self.relate_triangle(t) == Relation::Outside),
+        }
+    }
+}
+
+/// Indicates whether a point is inside a ring, by counting the crossings of a ray cast towards positive `x`.
+fn ring_contains(ring: &[(i32, i32)], (px, py): (i32, i32)) -> bool {
+    let mut inside = false;
+    for (k, &(x1, y1)) in ring.iter().enumerate() {
+        let (x2, y2) = ring[(k + 1) % ring.len()];
+        if (y1 > py) != (y2 > py) {
+            // The edge crosses the ray's line; check that it does so to the right of the point.
+            let side = orient((x1, y1), (x2, y2), (px, py));
+            if (side > 0) == (y2 > y1) {
+                inside = !inside;
+            }
+        }
+    }
+
+    inside
+}
+
+/// Indicates whether the segments `p1-q1` and `p2-q2` intersect, touching included. Either may be a single point.
+fn segments_intersect(p1: (i32, i32), q1: (i32, i32), p2: (i32, i32), q2: (i32, i32)) -> bool {
+    let o1 = orient(p1, q1, p2).signum();
+    let o2 = orient(p1, q1, q2).signum();
+    let o3 = orient(p2, q2, p1).signum();
+    let o4 = orient(p2, q2, q1).signum();
+    (o1 != o2 && o3 != o4)
+        || (o1 == 0 && on_segment(p1, p2, q1))
+        || (o2 == 0 && on_segment(p1, q2, q1))
+        || (o3 == 0 && on_segment(p2, p1, q2))
+        || (o4 == 0 && on_segment(p2, q1, q2))
+}
+
+/// Indicates whether `q`, collinear with `p-r`, lies within its bounding box.
+fn on_segment(p: (i32, i32), q: (i32, i32), r: (i32, i32)) -> bool {
+    q.0 <= p.0.max(r.0) && q.0 >= p.0.min(r.0) && q.1 <= p.1.max(r.1) && q.1 >= p.1.min(r.1)
+}
+
+/// Indicates whether a point is inside a triangle, boundary included. Degenerate triangles contain no point.
+fn point_in_triangle(p: (i32, i32), triangle: &Triangle) -> bool {
+    let [a, b, c] = triangle.vertices;
+    let area = orient(a, b, c);
+    if area == 0 {
+        return false;
+    }
+
+    let (s1, s2, s3) = (orient(a, b, p), orient(b, c, p), orient(c, a, p));
+    if area > 0 {
+        s1 >= 0 && s2 >= 0 && s3 >= 0
+    } else {
+        s1 <= 0 && s2 <= 0 && s3 <= 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::geo::{encode_latitude, encode_longitude, Polygon, Polygon2D, QueryRelation, Relation, Triangle},
+        pretty_assertions::assert_eq,
+    };
+
+    fn point(lat: f64, lon: f64) -> (i32, i32) {
+        (encode_longitude(lon).unwrap(), encode_latitude(lat).unwrap())
+    }
+
+    #[test]
+    fn test_relate_triangle() {
+        let hole = Polygon::from_box(4.0, 6.0, 4.0, 6.0).unwrap();
+        let outer = Polygon::from_box(0.0, 10.0, 0.0, 10.0).unwrap();
+        let polygon =
+            Polygon2D::new(&Polygon::with_holes(outer.lats().to_vec(), outer.lons().to_vec(), vec![hole]).unwrap())
+                .unwrap();
+
+        assert!(polygon.contains(point(1.0, 1.0)));
+        assert!(!polygon.contains(point(5.0, 5.0)));
+        assert!(!polygon.contains(point(11.0, 5.0)));
+
+        let inside = Triangle::new([point(1.0, 1.0), point(1.0, 3.0), point(3.0, 1.0)], [true; 3]);
+        assert_eq!(polygon.relate_triangle(&inside), Relation::Inside);
+
+        let in_hole = Triangle::point(point(5.0, 5.0));
+        assert_eq!(polygon.relate_triangle(&in_hole), Relation::Outside);
+
+        let around_hole = Triangle::new([point(3.0, 3.0), point(3.0, 9.0), point(9.0, 3.0)], [true; 3]);
+        assert_eq!(polygon.relate_triangle(&around_hole), Relation::Crosses);
+
+        let crossing = Triangle::segment(point(5.0, -1.0), point(5.0, 1.0));
+        assert_eq!(polygon.relate_triangle(&crossing), Relation::Crosses);
+
+        let around_polygon = Triangle::new([point(-20.0, -20.0), point(-20.0, 40.0), point(40.0, -20.0)], [true; 3]);
+        assert_eq!(polygon.relate_triangle(&around_polygon), Relation::Crosses);
+
+        let outside = Triangle::segment(point(20.0, 20.0), point(30.0, 25.0));
+        assert_eq!(polygon.relate_triangle(&outside), Relation::Outside);
+
+        assert!(polygon.matches(&[inside, outside], QueryRelation::Intersects));
+        assert!(!polygon.matches(&[inside, outside], QueryRelation::Within));
+        assert!(polygon.matches(&[inside], QueryRelation::Within));
+        assert!(polygon.matches(&[in_hole, outside], QueryRelation::Disjoint));
+        assert!(!polygon.matches(&[in_hole, crossing], QueryRelation::Disjoint));
+    }
+}
@@ -0,0 +1,637 @@
+use crate::{
+    geo::{encode_latitude, encode_longitude, orient, Line, Polygon, Triangle},
+    BoxError, BoxResult, LuceneError,
+};
+
+/// A vertex of the polygon being tessellated, linked to its neighbors in a ring. Vertices are never freed: removing
+/// one only unlinks it.
+#[derive(Clone, Copy, Debug)]
+struct Node {
+    /// The index of the original vertex, shared by the copies made when bridging holes.
+    i: usize,
+    x: i64,
+    y: i64,
+    prev: usize,
+    next: usize,
+    steiner: bool,
+}
+
+/// The rings of a polygon in encoded coordinates, and the linked vertices carved into triangles.
+struct Tessellator {
+    /// The original vertices, ring after ring, without the closing points.
+    vertices: Vec<(i32, i32)>,
+
+    /// The first vertex and the length of the ring of each original vertex.
+    rings: Vec<(usize, usize)>,
+    nodes: Vec<Node>,
+    triangles: Vec<[usize; 3]>,
+}
+
+/// Splits a polygon into triangles with ear clipping, bridging holes into the outer ring first. Coordinates are
+/// encoded before tessellating, so the triangles cover the quantized polygon exactly.
+///
+/// Fails if the polygon is malformed, such as when its ring crosses itself or a hole lies outside of it, so that the
+/// triangles don't cover its area.
+pub fn tessellate_polygon(polygon: &Polygon) -> BoxResult<Vec<Triangle>> {
+    let mut tessellator = Tessellator {
+        vertices: Vec::new(),
+        rings: Vec::new(),
+        nodes: Vec::new(),
+        triangles: Vec::new(),
+    };
+
+    let Some(mut outer) = tessellator.linked_list(polygon.lats(), polygon.lons(), true)? else {
+        return Err(malformed("the polygon has no area"));
+    };
+
+    let mut expected_area = ring_area(&tessellator.vertices, 0, tessellator.rings[0].1).abs();
+    if !polygon.holes().is_empty() {
+        let mut holes = Vec::with_capacity(polygon.holes().len());
+        for hole in polygon.holes() {
+            let start = tessellator.vertices.len();
+            if let Some(list) = tessellator.linked_list(hole.lats(), hole.lons(), false)? {
+                expected_area -= ring_area(&tessellator.vertices, start, tessellator.vertices.len() - start).abs();
+                if tessellator.nodes[list].next == list {
+                    tessellator.nodes[list].steiner = true;
+                }
+                holes.push(tessellator.leftmost(list));
+            }
+        }
+
+        holes.sort_by_key(|&hole| (tessellator.nodes[hole].x, tessellator.nodes[hole].y));
+        for hole in holes {
+            outer = tessellator.eliminate_hole(hole, outer);
+        }
+    }
+
+    tessellator.earcut_linked(Some(outer), 0);
+
+    let area: i128 = tessellator
+        .triangles
+        .iter()
+        .map(|t| orient(tessellator.vertices[t[0]], tessellator.vertices[t[1]], tessellator.vertices[t[2]]).abs())
+        .sum();
+    if area != expected_area {
+        return Err(malformed("the triangles don't cover the polygon; it may cross itself"));
+    }
+
+    Ok(tessellator
+        .triangles
+        .iter()
+        .map(|&[a, b, c]| {
+            Triangle::new(
+                [tessellator.vertices[a], tessellator.vertices[b], tessellator.vertices[c]],
+                [
+                    tessellator.is_edge_from_polygon(a, b),
+                    tessellator.is_edge_from_polygon(b, c),
+                    tessellator.is_edge_from_polygon(c, a),
+                ],
+            )
+        })
+        .collect())
+}
+
+/// Splits a line into one degenerate triangle per segment.
+pub fn tessellate_line(line: &Line) -> BoxResult<Vec<Triangle>> {
+    let mut points = Vec::with_capacity(line.lats().len());
+    for (&lat, &lon) in line.lats().iter().zip(line.lons().iter()) {
+        points.push((encode_longitude(lon)?, encode_latitude(lat)?));
+    }
+
+    Ok(points.windows(2).map(|segment| Triangle::segment(segment[0], segment[1])).collect())
+}
+
+fn malformed(reason: &str) -> BoxError {
+    LuceneError::InvalidArgument(format!("Unable to tessellate shape: {reason}")).into()
+}
+
+/// Returns twice the signed area of a ring of vertices: positive if counter-clockwise.
+fn ring_area(vertices: &[(i32, i32)], start: usize, len: usize) -> i128 {
+    (0..len)
+        .map(|k| {
+            let (x1, y1) = vertices[start + k];
+            let (x2, y2) = vertices[start + (k + 1) % len];
+            x1 as i128 * y2 as i128 - x2 as i128 * y1 as i128
+        })
+        .sum()
+}
+
+/// Returns twice the signed area of `p, q, r`: negative if they turn counter-clockwise.
+#[inline]
+fn area(p: &Node, q: &Node, r: &Node) -> i128 {
+    (q.y - p.y) as i128 * (r.x - q.x) as i128 - (q.x - p.x) as i128 * (r.y - q.y) as i128
+}
+
+#[inline]
+fn equals(p: &Node, q: &Node) -> bool {
+    p.x == q.x && p.y == q.y
+}
+
+/// Indicates whether `(px, py)` is inside the counter-clockwise triangle `a, b, c`, boundary included.
+#[allow(clippy::too_many_arguments)]
+fn point_in_triangle(ax: i64, ay: i64, bx: i64, by: i64, cx: i64, cy: i64, px: i64, py: i64) -> bool {
+    let (ax, ay, bx, by, cx, cy, px, py) =
+        (ax as i128, ay as i128, bx as i128, by as i128, cx as i128, cy as i128, px as i128, py as i128);
+    (cx - px) * (ay - py) >= (ax - px) * (cy - py)
+        && (ax - px) * (by - py) >= (bx - px) * (ay - py)
+        && (bx - px) * (cy - py) >= (cx - px) * (by - py)
+}
+
+impl Tessellator {
+    #[inline]
+    fn node(&self, n: usize) -> &Node {
+        &self.nodes[n]
+    }
+
+    #[inline]
+    fn next(&self, n: usize) -> usize {
+        self.nodes[n].next
+    }
+
+    #[inline]
+    fn prev(&self, n: usize) -> usize {
+        self.nodes[n].prev
+    }
+
+    #[inline]
+    fn area(&self, p: usize, q: usize, r: usize) -> i128 {
+        area(self.node(p), self.node(q), self.node(r))
+    }
+
+    #[inline]
+    fn equals(&self, p: usize, q: usize) -> bool {
+        equals(self.node(p), self.node(q))
+    }
+
+    /// Encodes a ring and links its vertices, counter-clockwise for the outer ring and clockwise for holes. Returns
+    /// the last node, or `None` if the ring is empty.
+    fn linked_list(&mut self, lats: &[f64], lons: &[f64], outer: bool) -> BoxResult<Option<usize>> {
+        // The closing point repeats the first one.
+        let len = lats.len() - 1;
+        let start = self.vertices.len();
+        for k in 0..len {
+            self.vertices.push((encode_longitude(lons[k])?, encode_latitude(lats[k])?));
+            self.rings.push((start, len));
+        }
+
+        let counter_clockwise = ring_area(&self.vertices, start, len) > 0;
+        let mut last = None;
+        let order: Box<dyn Iterator<Item = usize>> = if counter_clockwise == outer {
+            Box::new(start..start + len)
+        } else {
+            Box::new((start..start + len).rev())
+        };
+        for i in order {
+            last = Some(self.insert_node(i, last));
+        }
+
+        if let Some(node) = last {
+            if self.equals(node, self.next(node)) {
+                self.remove_node(node);
+                last = Some(self.next(node));
+            }
+        }
+
+        Ok(last)
+    }
+
+    fn insert_node(&mut self, i: usize, last: Option<usize>) -> usize {
+        let (x, y) = self.vertices[i];
+        let n = self.nodes.len();
+        let mut node = Node {
+            i,
+            x: x as i64,
+            y: y as i64,
+            prev: n,
+            next: n,
+            steiner: false,
+        };
+
+        if let Some(last) = last {
+            node.next = self.next(last);
+            node.prev = last;
+            let last_next = self.next(last);
+            self.nodes[last_next].prev = n;
+            self.nodes[last].next = n;
+        }
+
+        self.nodes.push(node);
+        n
+    }
+
+    fn remove_node(&mut self, p: usize) {
+        let (prev, next) = (self.prev(p), self.next(p));
+        self.nodes[next].prev = prev;
+        self.nodes[prev].next = next;
+    }
+
+    /// Removes duplicate and collinear points between `start` and `end`.
+    fn filter_points(&mut self, start: usize, end: Option<usize>) -> usize {
+        let mut end = end.unwrap_or(start);
+        let mut p = start;
+        loop {
+            let mut again = false;
+            if !self.node(p).steiner && (self.equals(p, self.next(p)) || self.area(self.prev(p), p, self.next(p)) == 0)
+            {
+                self.remove_node(p);
+                p = self.prev(p);
+                end = p;
+                if p == self.next(p) {
+                    break;
+                }
+                again = true;
+            } else {
+                p = self.next(p);
+            }
+
+            if !again && p == end {
+                break;
+            }
+        }
+
+        end
+    }
+
+    /// Clips ears until the ring is a single triangle. When no ear is left, retries after removing collinear
+    /// points, then after curing small self-intersections, and finally by splitting the ring in two.
+    fn earcut_linked(&mut self, ear: Option<usize>, pass: u8) {
+        let Some(mut ear) = ear else {
+            return;
+        };
+
+        let mut stop = ear;
+        while self.prev(ear) != self.next(ear) {
+            let (prev, next) = (self.prev(ear), self.next(ear));
+            if self.is_ear(ear) {
+                self.triangles.push([self.node(prev).i, self.node(ear).i, self.node(next).i]);
+                self.remove_node(ear);
+                ear = self.next(next);
+                stop = ear;
+                continue;
+            }
+
+            ear = next;
+            if ear == stop {
+                match pass {
+                    0 => {
+                        let ear = self.filter_points(ear, None);
+                        self.earcut_linked(Some(ear), 1);
+                    }
+                    1 => {
+                        let filtered = self.filter_points(ear, None);
+                        let ear = self.cure_local_intersections(filtered);
+                        self.earcut_linked(Some(ear), 2);
+                    }
+                    _ => self.split_earcut(ear),
+                }
+                break;
+            }
+        }
+    }
+
+    fn is_ear(&self, ear: usize) -> bool {
+        let (a, b, c) = (self.node(self.prev(ear)), self.node(ear), self.node(self.next(ear)));
+        if area(a, b, c) >= 0 {
+            // Reflex: the ring turns clockwise here.
+            return false;
+        }
+
+        let (min_x, max_x) = (a.x.min(b.x).min(c.x), a.x.max(b.x).max(c.x));
+        let (min_y, max_y) = (a.y.min(b.y).min(c.y), a.y.max(b.y).max(c.y));
+        let mut p = c.next;
+        while p != b.prev {
+            let node = self.node(p);
+            if node.x >= min_x
+                && node.x <= max_x
+                && node.y >= min_y
+                && node.y <= max_y
+                && point_in_triangle(a.x, a.y, b.x, b.y, c.x, c.y, node.x, node.y)
+                && self.area(node.prev, p, node.next) >= 0
+            {
+                return false;
+            }
+            p = node.next;
+        }
+
+        true
+    }
+
+    /// Clips the triangles formed where the ring crosses itself locally.
+    fn cure_local_intersections(&mut self, mut start: usize) -> usize {
+        let mut p = start;
+        loop {
+            let a = self.prev(p);
+            let b = self.next(self.next(p));
+            if !self.equals(a, b)
+                && self.intersects(a, p, self.next(p), b)
+                && self.locally_inside(a, b)
+                && self.locally_inside(b, a)
+            {
+                self.triangles.push([self.node(a).i, self.node(p).i, self.node(b).i]);
+                let p_next = self.next(p);
+                self.remove_node(p);
+                self.remove_node(p_next);
+                p = b;
+                start = b;
+            }
+
+            p = self.next(p);
+            if p == start {
+                break;
+            }
+        }
+
+        self.filter_points(p, None)
+    }
+
+    /// Splits the ring in two along a valid diagonal and tessellates both halves.
+    fn split_earcut(&mut self, start: usize) {
+        let mut a = start;
+        loop {
+            let mut b = self.next(self.next(a));
+            while b != self.prev(a) {
+                if self.node(a).i != self.node(b).i && self.is_valid_diagonal(a, b) {
+                    let c = self.split_polygon(a, b);
+                    let a = self.filter_points(a, Some(self.next(a)));
+                    let c = self.filter_points(c, Some(self.next(c)));
+                    self.earcut_linked(Some(a), 0);
+                    self.earcut_linked(Some(c), 0);
+                    return;
+                }
+                b = self.next(b);
+            }
+
+            a = self.next(a);
+            if a == start {
+                return;
+            }
+        }
+    }
+
+    /// Links a hole into the outer ring through a bridge to a visible vertex, returning the outer ring.
+    fn eliminate_hole(&mut self, hole: usize, outer: usize) -> usize {
+        let Some(bridge) = self.find_hole_bridge(hole, outer) else {
+            return outer;
+        };
+
+        let bridge_reverse = self.split_polygon(bridge, hole);
+        self.filter_points(bridge_reverse, Some(self.next(bridge_reverse)));
+        self.filter_points(bridge, Some(self.next(bridge)))
+    }
+
+    /// Finds a vertex of the outer ring that the hole's leftmost vertex can see, by casting a ray to the left.
+    fn find_hole_bridge(&self, hole: usize, outer: usize) -> Option<usize> {
+        let (hx, hy) = (self.node(hole).x, self.node(hole).y);
+        let mut qx = f64::NEG_INFINITY;
+        let mut m = None;
+        let mut p = outer;
+        loop {
+            let (node, next) = (self.node(p), self.node(self.next(p)));
+            if hy <= node.y && hy >= next.y && next.y != node.y {
+                let x = node.x as f64 + (hy - node.y) as f64 * (next.x - node.x) as f64 / (next.y - node.y) as f64;
+                if x <= hx as f64 && x > qx {
+                    qx = x;
+                    m = Some(if node.x < next.x {
+                        p
+                    } else {
+                        self.next(p)
+                    });
+                    if x == hx as f64 {
+                        return m;
+                    }
+                }
+            }
+
+            p = self.next(p);
+            if p == outer {
+                break;
+            }
+        }
+
+        let mut m = m?;
+
+        // Look for the vertex closest in angle to the ray among those inside the triangle formed by the hole vertex,
+        // the intersection, and the candidate.
+        let stop = m;
+        let (mx, my) = (self.node(m).x, self.node(m).y);
+        let qx = qx.floor() as i64;
+        let mut tan_min = f64::INFINITY;
+        p = m;
+        loop {
+            let node = self.node(p);
+            let (ax, cx) = if hy < my {
+                (hx, qx)
+            } else {
+                (qx, hx)
+            };
+            if hx >= node.x && node.x >= mx && hx != node.x && point_in_triangle(ax, hy, mx, my, cx, hy, node.x, node.y)
+            {
+                let tan = (hy - node.y).abs() as f64 / (hx - node.x) as f64;
+                if self.locally_inside(p, hole)
+                    && (tan < tan_min
+                        || (tan == tan_min
+                            && (node.x > self.node(m).x
+                                || (node.x == self.node(m).x && self.sector_contains_sector(m, p)))))
+                {
+                    m = p;
+                    tan_min = tan;
+                }
+            }
+
+            p = self.next(p);
+            if p == stop {
+                break;
+            }
+        }
+
+        Some(m)
+    }
+
+    fn sector_contains_sector(&self, m: usize, p: usize) -> bool {
+        self.area(self.prev(m), m, self.prev(p)) < 0 && self.area(self.next(p), m, self.next(m)) < 0
+    }
+
+    fn leftmost(&self, start: usize) -> usize {
+        let mut p = start;
+        let mut leftmost = start;
+        loop {
+            let (node, best) = (self.node(p), self.node(leftmost));
+            if node.x < best.x || (node.x == best.x && node.y < best.y) {
+                leftmost = p;
+            }
+            p = self.next(p);
+            if p == start {
+                return leftmost;
+            }
+        }
+    }
+
+    fn is_valid_diagonal(&self, a: usize, b: usize) -> bool {
+        let (na, nb) = (self.node(a), self.node(b));
+        self.node(na.next).i != nb.i
+            && self.node(na.prev).i != nb.i
+            && !self.intersects_polygon(a, b)
+            && ((self.locally_inside(a, b)
+                && self.locally_inside(b, a)
+                && self.middle_inside(a, b)
+                && (self.area(na.prev, a, nb.prev) != 0 || self.area(a, nb.prev, b) != 0))
+                || (self.equals(a, b) && self.area(na.prev, a, na.next) > 0 && self.area(nb.prev, b, nb.next) > 0))
+    }
+
+    /// Indicates whether the segments `p1-q1` and `p2-q2` intersect, touching included.
+    fn intersects(&self, p1: usize, q1: usize, p2: usize, q2: usize) -> bool {
+        let o1 = self.area(p1, q1, p2).signum();
+        let o2 = self.area(p1, q1, q2).signum();
+        let o3 = self.area(p2, q2, p1).signum();
+        let o4 = self.area(p2, q2, q1).signum();
+        (o1 != o2 && o3 != o4)
+            || (o1 == 0 && self.on_segment(p1, p2, q1))
+            || (o2 == 0 && self.on_segment(p1, q2, q1))
+            || (o3 == 0 && self.on_segment(p2, p1, q2))
+            || (o4 == 0 && self.on_segment(p2, q1, q2))
+    }
+
+    /// Indicates whether `q`, collinear with `p-r`, lies within its bounding box.
+    fn on_segment(&self, p: usize, q: usize, r: usize) -> bool {
+        let (p, q, r) = (self.node(p), self.node(q), self.node(r));
+        q.x <= p.x.max(r.x) && q.x >= p.x.min(r.x) && q.y <= p.y.max(r.y) && q.y >= p.y.min(r.y)
+    }
+
+    fn intersects_polygon(&self, a: usize, b: usize) -> bool {
+        let (ai, bi) = (self.node(a).i, self.node(b).i);
+        let mut p = a;
+        loop {
+            let next = self.next(p);
+            let (pi, ni) = (self.node(p).i, self.node(next).i);
+            if pi != ai && ni != ai && pi != bi && ni != bi && self.intersects(p, next, a, b) {
+                return true;
+            }
+            p = next;
+            if p == a {
+                return false;
+            }
+        }
+    }
+
+    /// Indicates whether the diagonal `a-b` starts inside the ring at `a`.
+    fn locally_inside(&self, a: usize, b: usize) -> bool {
+        let (prev, next) = (self.prev(a), self.next(a));
+        if self.area(prev, a, next) < 0 {
+            self.area(a, b, next) >= 0 && self.area(a, prev, b) >= 0
+        } else {
+            self.area(a, b, prev) < 0 || self.area(a, next, b) < 0
+        }
+    }
+
+    /// Indicates whether the middle of the diagonal `a-b` is inside the ring.
+    fn middle_inside(&self, a: usize, b: usize) -> bool {
+        let (px, py) = ((self.node(a).x + self.node(b).x) as f64 / 2.0, (self.node(a).y + self.node(b).y) as f64 / 2.0);
+        let mut inside = false;
+        let mut p = a;
+        loop {
+            let (node, next) = (self.node(p), self.node(self.next(p)));
+            if ((node.y as f64 > py) != (next.y as f64 > py))
+                && next.y != node.y
+                && px < (next.x - node.x) as f64 * (py - node.y as f64) / (next.y - node.y) as f64 + node.x as f64
+            {
+                inside = !inside;
+            }
+            p = self.next(p);
+            if p == a {
+                return inside;
+            }
+        }
+    }
+
+    /// Splits the ring in two with a bridge between `a` and `b`, duplicating both. Returns the copy of `b`, which
+    /// lies on the ring not containing `a`.
+    fn split_polygon(&mut self, a: usize, b: usize) -> usize {
+        let a2 = self.nodes.len();
+        let b2 = a2 + 1;
+        let (an, bp) = (self.next(a), self.prev(b));
+        self.nodes.push(Node {
+            prev: b2,
+            next: an,
+            steiner: false,
+            ..self.nodes[a]
+        });
+        self.nodes.push(Node {
+            prev: bp,
+            next: a2,
+            steiner: false,
+            ..self.nodes[b]
+        });
+
+        self.nodes[a].next = b;
+        self.nodes[b].prev = a;
+        self.nodes[an].prev = a2;
+        self.nodes[bp].next = b2;
+        b2
+    }
+
+    /// Indicates whether the original vertices `a` and `b` are adjacent in their ring.
+    fn is_edge_from_polygon(&self, a: usize, b: usize) -> bool {
+        let (start, len) = self.rings[a];
+        if self.rings[b].0 != start {
+            return false;
+        }
+
+        let next = |i: usize| start + (i - start + 1) % len;
+        next(a) == b || next(b) == a
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::geo::{orient, tessellate_line, tessellate_polygon, Line, Polygon},
+        pretty_assertions::assert_eq,
+    };
+
+    fn area(triangles: &[crate::geo::Triangle]) -> i128 {
+        triangles.iter().map(|t| orient(t.vertices[0], t.vertices[1], t.vertices[2]).abs()).sum()
+    }
+
+    #[test]
+    fn test_tessellate_polygon() {
+        let square = Polygon::from_box(0.0, 10.0, 0.0, 10.0).unwrap();
+        let triangles = tessellate_polygon(&square).unwrap();
+        assert_eq!(triangles.len(), 2);
+
+        // Each triangle of a square has two edges on the square and one diagonal.
+        for triangle in triangles.iter() {
+            assert_eq!(triangle.edges_from_shape.iter().filter(|&&edge| edge).count(), 2);
+        }
+
+        // A concave "U" shape, given clockwise.
+        let u = Polygon::new(
+            vec![0.0, 3.0, 3.0, 1.0, 1.0, 3.0, 3.0, 0.0, 0.0],
+            vec![0.0, 0.0, 1.0, 1.0, 2.0, 2.0, 3.0, 3.0, 0.0],
+        )
+        .unwrap();
+        assert_eq!(tessellate_polygon(&u).unwrap().len(), 6);
+
+        let hole = Polygon::from_box(4.0, 6.0, 4.0, 6.0).unwrap();
+        let with_hole =
+            Polygon::with_holes(square.lats().to_vec(), square.lons().to_vec(), vec![hole.clone()]).unwrap();
+        let triangles = tessellate_polygon(&with_hole).unwrap();
+        assert_eq!(triangles.len(), 8);
+        assert_eq!(
+            area(&triangles),
+            area(&tessellate_polygon(&square).unwrap()) - area(&tessellate_polygon(&hole).unwrap())
+        );
+
+        // A bow tie crosses itself.
+        let bow_tie = Polygon::new(vec![0.0, 1.0, 0.0, 1.0, 0.0], vec![0.0, 1.0, 1.0, 0.0, 0.0]).unwrap();
+        assert!(tessellate_polygon(&bow_tie).is_err());
+    }
+
+    #[test]
+    fn test_tessellate_line() {
+        let line = Line::new(vec![0.0, 1.0, 2.0], vec![0.0, 1.0, 0.0]).unwrap();
+        let triangles = tessellate_line(&line).unwrap();
+        assert_eq!(triangles.len(), 2);
+        assert_eq!(triangles[0].vertices[0], triangles[0].vertices[2]);
+        assert_eq!(triangles[0].vertices[1], triangles[1].vertices[0]);
+    }
+}
@@ -0,0 +1,140 @@
+use crate::{BoxResult, LuceneError};
+
+/// The number of bytes of an encoded [Triangle].
+pub const TRIANGLE_BYTES: usize = 25;
+
+/// A triangle of a tessellated shape, with vertices in encoded coordinates: `x` is an encoded longitude and `y` an
+/// encoded latitude (see [crate::geo::encode_longitude] and [crate::geo::encode_latitude]).
+///
+/// Lines are stored as degenerate triangles whose third vertex repeats the first, and points as triangles whose
+/// three vertices are equal. Each edge records whether it lies on the boundary of the original shape, as opposed to
+/// being a diagonal introduced by tessellation.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Triangle {
+    /// The vertices, as `(x, y)` pairs.
+    pub vertices: [(i32, i32); 3],
+
+    /// Whether the edges `vertices[0]-vertices[1]`, `vertices[1]-vertices[2]` and `vertices[2]-vertices[0]` are part of
+    /// the original shape.
+    pub edges_from_shape: [bool; 3],
+}
+
+impl Triangle {
+    /// Creates a triangle from its vertices and edge flags.
+    pub fn new(vertices: [(i32, i32); 3], edges_from_shape: [bool; 3]) -> Self {
+        Self {
+            vertices,
+            edges_from_shape,
+        }
+    }
+
+    /// Creates the degenerate triangle representing the segment from `a` to `b`.
+    pub fn segment(a: (i32, i32), b: (i32, i32)) -> Self {
+        Self::new([a, b, a], [true, true, true])
+    }
+
+    /// Creates the degenerate triangle representing a point.
+    pub fn point(p: (i32, i32)) -> Self {
+        Self::new([p, p, p], [true, true, true])
+    }
+
+    /// Returns the triangle in canonical form: counter-clockwise, starting from the vertex with the smallest `x`
+    /// (then `y`). Equal triangles encode identically whatever their original orientation and starting vertex.
+    pub fn normalize(&self) -> Self {
+        let [a, b, c] = self.vertices;
+        let [ab, bc, ca] = self.edges_from_shape;
+        let mut triangle = if orient(a, b, c) < 0 {
+            Self::new([a, c, b], [ca, bc, ab])
+        } else {
+            *self
+        };
+
+        let first = (0..3).min_by_key(|&i| triangle.vertices[i]).unwrap_or(0);
+        triangle.vertices.rotate_left(first);
+        triangle.edges_from_shape.rotate_left(first);
+        triangle
+    }
+
+    /// Encodes the triangle in canonical form: the three vertices as big-endian `x, y` pairs, followed by a byte
+    /// holding the edge flags.
+    pub fn encode(&self, out: &mut Vec<u8>) {
+        let triangle = self.normalize();
+        for (x, y) in triangle.vertices {
+            out.extend_from_slice(&x.to_be_bytes());
+            out.extend_from_slice(&y.to_be_bytes());
+        }
+
+        let flags =
+            triangle.edges_from_shape.iter().enumerate().fold(0u8, |flags, (i, &edge)| flags | (edge as u8) << i);
+        out.push(flags);
+    }
+
+    /// Decodes a triangle written by [Triangle::encode] from the first [TRIANGLE_BYTES] bytes of `bytes`.
+    pub fn decode(bytes: &[u8]) -> BoxResult<Self> {
+        if bytes.len() < TRIANGLE_BYTES {
+            return Err(LuceneError::CorruptIndex(
+                format!("encoded triangle needs {TRIANGLE_BYTES} bytes; got {}", bytes.len()).into(),
+            )
+            .into());
+        }
+
+        let int = |i: usize| i32::from_be_bytes([bytes[i * 4], bytes[i * 4 + 1], bytes[i * 4 + 2], bytes[i * 4 + 3]]);
+        let flags = bytes[TRIANGLE_BYTES - 1];
+        Ok(Self::new(
+            [(int(0), int(1)), (int(2), int(3)), (int(4), int(5))],
+            [flags & 1 != 0, flags & 2 != 0, flags & 4 != 0],
+        ))
+    }
+
+    /// Decodes every triangle of a shape encoded as consecutive triangles.
+    pub fn decode_all(bytes: &[u8]) -> BoxResult<Vec<Self>> {
+        if !bytes.len().is_multiple_of(TRIANGLE_BYTES) {
+            return Err(LuceneError::CorruptIndex(
+                format!("encoded shape length {} is not a multiple of {TRIANGLE_BYTES}", bytes.len()).into(),
+            )
+            .into());
+        }
+
+        bytes.chunks_exact(TRIANGLE_BYTES).map(Self::decode).collect()
+    }
+}
+
+/// Returns twice the signed area of the triangle `a, b, c`: positive if the points turn counter-clockwise, negative
+/// if clockwise, and zero if they are collinear.
+#[inline]
+pub(crate) fn orient(a: (i32, i32), b: (i32, i32), c: (i32, i32)) -> i128 {
+    let (ax, ay, bx, by, cx, cy) = (a.0 as i128, a.1 as i128, b.0 as i128, b.1 as i128, c.0 as i128, c.1 as i128);
+    (bx - ax) * (cy - ay) - (by - ay) * (cx - ax)
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::geo::{Triangle, TRIANGLE_BYTES},
+        pretty_assertions::assert_eq,
+    };
+
+    #[test]
+    fn test_encoding() {
+        let clockwise = Triangle::new([(5, 0), (0, 0), (0, 5)], [true, false, true]);
+        let mut bytes = Vec::new();
+        clockwise.encode(&mut bytes);
+        assert_eq!(bytes.len(), TRIANGLE_BYTES);
+
+        let decoded = Triangle::decode(&bytes).unwrap();
+        assert_eq!(decoded, Triangle::new([(0, 0), (5, 0), (0, 5)], [true, true, false]));
+        assert_eq!(decoded, clockwise.normalize());
+
+        // Any rotation of the same triangle encodes the same way.
+        let rotated = Triangle::new([(0, 5), (0, 0), (5, 0)], [false, true, true]);
+        let mut rotated_bytes = Vec::new();
+        rotated.encode(&mut rotated_bytes);
+        assert_eq!(rotated_bytes, bytes);
+
+        Triangle::segment((i32::MIN, i32::MAX), (3, -4)).encode(&mut bytes);
+        let triangles = Triangle::decode_all(&bytes).unwrap();
+        assert_eq!(triangles.len(), 2);
+        assert_eq!(triangles[1].vertices, [(i32::MIN, i32::MAX), (3, -4), (i32::MIN, i32::MAX)]);
+        assert!(Triangle::decode_all(&bytes[1..]).is_err());
+    }
+}
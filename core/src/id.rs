@@ -4,7 +4,7 @@ use {
         fmt::{Debug, Display, Formatter, Result as FmtResult},
         io::Result as IoResult,
     },
-    tokio::io::{AsyncRead, AsyncReadExt},
+    tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
 };
 
 /// The length of identifiers.
@@ -44,6 +44,20 @@ impl Id {
         }
     }
 
+    /// Creates an id from its bytes.
+    #[inline]
+    pub const fn from_bytes(id: [u8; ID_LENGTH]) -> Self {
+        Self {
+            id,
+        }
+    }
+
+    /// Returns the bytes of the id.
+    #[inline]
+    pub fn as_bytes(&self) -> &[u8; ID_LENGTH] {
+        &self.id
+    }
+
     /// Read an id from a stream. Returns the id.
     pub async fn read_from<R: AsyncRead + Unpin>(r: &mut R) -> IoResult<Self> {
         let mut id = [0u8; ID_LENGTH];
@@ -52,4 +66,9 @@ impl Id {
             id,
         })
     }
+
+    /// Writes the id to a stream.
+    pub async fn write_to<W: AsyncWrite + Unpin>(&self, w: &mut W) -> IoResult<()> {
+        w.write_all(&self.id).await
+    }
 }
@@ -1,7 +1,52 @@
+mod automaton_terms_enum;
+mod bloom_filtered_reader;
+mod cache_helper;
+mod disk_usage;
+mod doc_map;
+mod doc_values;
+mod doc_values_skipper;
+mod documents_writer;
+mod exitable_reader;
+mod field_stats;
+mod float_vector_values;
+mod flush_policy;
+mod fst_terms;
 mod header;
+mod id_terms;
+mod index_commit;
+mod index_manager;
+mod ingest_stats;
+mod leaf_reader;
+mod memory_segment;
+mod memory_terms;
+mod merge_stats;
+mod postings_enum;
 mod reader;
+mod schema;
 mod segment_index;
 mod segment_info;
+mod segment_reader;
+mod segment_warmer;
+mod single_terms_enum;
+mod sorting_codec_reader;
+mod stored_fields_cache;
+mod sync_writer;
+mod term;
+mod term_vectors;
+mod terms;
+mod terms_hash;
+mod translog;
+mod vector_similarity_function;
 mod writer;
+mod writer_config;
+mod writer_events;
 
-pub use {header::*, reader::*, segment_index::*, segment_info::*, writer::*};
+pub use {
+    automaton_terms_enum::*, bloom_filtered_reader::*, cache_helper::*, disk_usage::*, doc_map::*, doc_values::*,
+    doc_values_skipper::*, documents_writer::*, exitable_reader::*, field_stats::*, float_vector_values::*,
+    flush_policy::*, fst_terms::*, header::*, id_terms::*, index_commit::*, index_manager::*, ingest_stats::*,
+    leaf_reader::*, memory_segment::*, memory_terms::*, merge_stats::*, postings_enum::*, reader::*, schema::*,
+    segment_index::*, segment_info::*, segment_reader::*, segment_warmer::*, single_terms_enum::*,
+    sorting_codec_reader::*, stored_fields_cache::*, sync_writer::*, term::*, term_vectors::*, terms::*, terms_hash::*,
+    translog::*, vector_similarity_function::*, writer::*, writer_config::*, writer_events::*,
+};
@@ -0,0 +1,320 @@
+use {
+    crate::{
+        index::{PostingsEnum, SeekStatus, TermsEnum},
+        util::automaton::{Automaton, AutomatonType, ByteRunAutomaton, CompiledAutomaton},
+        BoxResult, LuceneError,
+    },
+    std::{
+        fmt::{Debug, Formatter, Result as FmtResult},
+        sync::Arc,
+    },
+};
+
+/// A [TermsEnum] that returns only the terms accepted by a [CompiledAutomaton], seeking over the terms dictionary
+/// rather than testing every term.
+///
+/// Whenever a term is visited, the automaton is used to compute the next string (in byte order) that could possibly
+/// be accepted, and the underlying enum seeks straight to it. For a finite language this visits only the accepted
+/// terms plus a few rejected neighbours; for an infinite language the cost depends on the structure of the automaton
+/// rather than the size of the dictionary.
+pub struct AutomatonTermsEnum<'a> {
+    inner: Box<dyn TermsEnum + 'a>,
+    run_automaton: Arc<ByteRunAutomaton>,
+    automaton: Arc<Automaton>,
+    common_suffix: Option<Vec<u8>>,
+    finite: bool,
+    start_term: Option<Vec<u8>>,
+
+    /// The string being built for the next seek.
+    seek_bytes: Vec<u8>,
+
+    /// The automaton state after each byte of `seek_bytes`.
+    saved_states: Vec<usize>,
+
+    /// The generation in which each state was last visited, for loop detection.
+    visited: Vec<u64>,
+    cur_gen: u64,
+
+    started: bool,
+    do_seek: bool,
+    exhausted: bool,
+}
+
+impl<'a> AutomatonTermsEnum<'a> {
+    /// Creates a new enum over the terms of `inner` accepted by `compiled`, which must be of type
+    /// [AutomatonType::Normal]. If `start_term` is given, only terms after it are returned.
+    pub fn new(
+        inner: Box<dyn TermsEnum + 'a>,
+        compiled: &CompiledAutomaton,
+        start_term: Option<&[u8]>,
+    ) -> BoxResult<Self> {
+        let Some((automaton, run_automaton)) = compiled.shared_automata() else {
+            return Err(LuceneError::InvalidArgument(format!(
+                "AutomatonTermsEnum requires an automaton of type Normal, got {:?}",
+                compiled.automaton_type()
+            ))
+            .into());
+        };
+        debug_assert_eq!(compiled.automaton_type(), AutomatonType::Normal);
+
+        Ok(Self {
+            inner,
+            visited: vec![0; run_automaton.size()],
+            run_automaton,
+            automaton,
+            common_suffix: compiled.common_suffix().map(|s| s.to_vec()),
+            finite: compiled.is_finite(),
+            start_term: start_term.map(|t| t.to_vec()),
+            seek_bytes: Vec::new(),
+            saved_states: Vec::new(),
+            cur_gen: 0,
+            started: false,
+            do_seek: true,
+            exhausted: false,
+        })
+    }
+
+    fn accept(&self, term: &[u8]) -> bool {
+        if let Some(suffix) = &self.common_suffix {
+            if !term.ends_with(suffix) {
+                return false;
+            }
+        }
+
+        self.run_automaton.run(term)
+    }
+
+    /// Computes the next term to seek to after `term` (or the first one, if `term` is `None`) into `seek_bytes`.
+    /// Returns false if no further terms can be accepted.
+    fn next_seek_term(&mut self, term: Option<Vec<u8>>) -> bool {
+        match term {
+            None => {
+                self.seek_bytes.clear();
+                // The empty term is valid, and the smallest possible.
+                if self.run_automaton.is_accept(0) {
+                    return true;
+                }
+            }
+            Some(term) => self.seek_bytes = term,
+        }
+
+        self.next_string()
+    }
+
+    /// Increments `seek_bytes` to the next string in byte order that the automaton could possibly accept.
+    fn next_string(&mut self) -> bool {
+        let mut pos = 0;
+        self.saved_states.resize(self.seek_bytes.len() + 1, 0);
+        self.saved_states[0] = 0;
+
+        loop {
+            self.cur_gen += 1;
+
+            // Walk the automaton until a byte is rejected.
+            let mut state = self.saved_states[pos];
+            while pos < self.seek_bytes.len() {
+                self.visited[state] = self.cur_gen;
+                match self.run_automaton.step(state, self.seek_bytes[pos] as u32) {
+                    Some(next) => {
+                        self.saved_states[pos + 1] = next;
+                        state = next;
+                        pos += 1;
+                    }
+                    None => break,
+                }
+            }
+
+            // Keep the useful portion and try to append bytes that will match from the last non-rejecting state.
+            if self.next_string_from(state, pos) {
+                return true;
+            }
+
+            // No solutions exist from the useful portion; backtrack.
+            let Some(new_pos) = self.backtrack(pos) else {
+                return false;
+            };
+            pos = new_pos;
+
+            if let Some(next) = self.run_automaton.step(self.saved_states[pos], self.seek_bytes[pos] as u32) {
+                if self.run_automaton.is_accept(next) {
+                    return true;
+                }
+            }
+
+            // Loops in an infinite automaton make the saved states unreliable after backtracking; start over.
+            if !self.finite {
+                pos = 0;
+            }
+        }
+    }
+
+    /// Appends the smallest bytes to `seek_bytes[..position]` that lead from `state` to an accept state or a loop, with
+    /// the byte at `position` being greater than the one currently there.
+    fn next_string_from(&mut self, state: usize, position: usize) -> bool {
+        let mut c = 0u32;
+        if position < self.seek_bytes.len() {
+            c = self.seek_bytes[position] as u32;
+            // If the next byte is 0xff and not part of the useful portion, there can be no higher transition.
+            if c == 0xff {
+                return false;
+            }
+            c += 1;
+        }
+
+        self.seek_bytes.truncate(position);
+        self.visited[state] = self.cur_gen;
+
+        let Some(t) = self.automaton.transitions(state).iter().find(|t| t.max >= c) else {
+            return false;
+        };
+
+        self.seek_bytes.push(c.max(t.min) as u8);
+        let mut state = t.dest;
+
+        // Continue down the minimal path in byte order until a loop or accept state is found. The automaton has no
+        // dead states, so a non-accepting state always has a transition.
+        while self.visited[state] != self.cur_gen && !self.run_automaton.is_accept(state) {
+            self.visited[state] = self.cur_gen;
+            let t = self.automaton.transitions(state)[0];
+            self.seek_bytes.push(t.min as u8);
+            state = t.dest;
+        }
+
+        true
+    }
+
+    /// Increments the last byte of `seek_bytes[..position]` that can be incremented, truncating after it. Returns its
+    /// position, or `None` if every byte is 0xff.
+    fn backtrack(&mut self, mut position: usize) -> Option<usize> {
+        while position > 0 {
+            position -= 1;
+            let b = self.seek_bytes[position];
+            if b != 0xff {
+                self.seek_bytes[position] = b + 1;
+                self.seek_bytes.truncate(position + 1);
+                return Some(position);
+            }
+        }
+
+        None
+    }
+}
+
+impl Debug for AutomatonTermsEnum<'_> {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        f.debug_struct("AutomatonTermsEnum")
+            .field("inner", &self.inner)
+            .field("finite", &self.finite)
+            .field("common_suffix", &self.common_suffix)
+            .field("seek_bytes", &self.seek_bytes)
+            .finish()
+    }
+}
+
+impl TermsEnum for AutomatonTermsEnum<'_> {
+    fn next(&mut self) -> BoxResult<Option<&[u8]>> {
+        if self.exhausted {
+            return Ok(None);
+        }
+
+        loop {
+            if self.do_seek {
+                self.do_seek = false;
+                let from = if self.started {
+                    Some(self.inner.term().to_vec())
+                } else {
+                    self.started = true;
+                    self.start_term.clone()
+                };
+
+                if !self.next_seek_term(from) || self.inner.seek_ceil(&self.seek_bytes)? == SeekStatus::End {
+                    self.exhausted = true;
+                    return Ok(None);
+                }
+            } else if self.inner.next()?.is_none() {
+                self.exhausted = true;
+                return Ok(None);
+            }
+
+            // Whether or not the term is accepted, the next candidate is found by seeking.
+            self.do_seek = true;
+            if self.accept(self.inner.term()) {
+                return Ok(Some(self.inner.term()));
+            }
+        }
+    }
+
+    fn term(&self) -> &[u8] {
+        self.inner.term()
+    }
+
+    fn seek_ceil(&mut self, target: &[u8]) -> BoxResult<SeekStatus> {
+        self.started = true;
+        self.exhausted = false;
+
+        let status = self.inner.seek_ceil(target)?;
+        if status == SeekStatus::End {
+            self.exhausted = true;
+            return Ok(SeekStatus::End);
+        }
+
+        self.do_seek = true;
+        if self.accept(self.inner.term()) {
+            return Ok(status);
+        }
+
+        match self.next()? {
+            Some(_) => Ok(SeekStatus::NotFound),
+            None => Ok(SeekStatus::End),
+        }
+    }
+
+    fn doc_freq(&self) -> BoxResult<u32> {
+        self.inner.doc_freq()
+    }
+
+    fn total_term_freq(&self) -> BoxResult<u64> {
+        self.inner.total_term_freq()
+    }
+
+    fn postings(&self) -> BoxResult<Box<dyn PostingsEnum>> {
+        self.inner.postings()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{
+            index::{MemoryTerms, Terms},
+            util::automaton::{CompiledAutomaton, RegExp, DEFAULT_DETERMINIZE_WORK_LIMIT},
+        },
+        pretty_assertions::assert_eq,
+    };
+
+    fn intersect(terms: &MemoryTerms, regexp: &str, start: Option<&str>) -> Vec<String> {
+        let a = RegExp::new(regexp).unwrap().to_automaton().unwrap();
+        let compiled = CompiledAutomaton::new(&a, None, false, DEFAULT_DETERMINIZE_WORK_LIMIT, false).unwrap();
+        let mut te = terms.intersect(&compiled, start.map(str::as_bytes)).unwrap();
+        let mut result = Vec::new();
+        while let Some(term) = te.next().unwrap() {
+            result.push(String::from_utf8(term.to_vec()).unwrap());
+        }
+        result
+    }
+
+    #[test]
+    fn test_intersect() {
+        let words = ["", "a", "ab", "abc", "abd", "abbbbc", "ac", "b", "bc", "café", "cafés", "zzz", "\u{1f600}x"];
+        let terms = MemoryTerms::from_strs(&words);
+
+        assert_eq!(intersect(&terms, "ab*c", None), vec!["abbbbc", "abc", "ac"]);
+        assert_eq!(intersect(&terms, "ab.", None), vec!["abc", "abd"]);
+        assert_eq!(intersect(&terms, "a?", None), vec!["", "a"]);
+        assert_eq!(intersect(&terms, "caf.s?", None), vec!["café", "cafés"]);
+        assert_eq!(intersect(&terms, ".x", None), vec!["\u{1f600}x"]);
+        assert_eq!(intersect(&terms, "@", None).len(), words.len());
+        assert_eq!(intersect(&terms, "a.*", Some("ab")), vec!["abbbbc", "abc", "abd", "ac"]);
+        assert_eq!(intersect(&terms, "q.*", None), Vec::<String>::new());
+    }
+}
@@ -0,0 +1,388 @@
+use {
+    crate::{
+        document::Document,
+        index::{
+            BinaryDocValues, CacheHelper, DocValuesSkipper, DocValuesType, FloatVectorValues, LeafReader,
+            NumericDocValues, PostingsEnum, SeekStatus, TermVectors, Terms, TermsEnum,
+        },
+        search::Sort,
+        util::{Accountable, FixedBitSet, FuzzySet, NamedAccountable},
+        BoxResult, LuceneError,
+    },
+    std::{collections::HashMap, sync::Arc},
+};
+
+/// The default false positive probability of the bloom filters of a [BloomFilteredLeafReader].
+pub const DEFAULT_BLOOM_FILTER_FPP: f64 = 0.1;
+
+/// A [LeafReader] that keeps a bloom filter of the terms of some of its fields, and consults it before seeking the
+/// term dictionary for an exact term.
+///
+/// This suits fields of unique keys, such as document ids, that are looked up one term at a time: most segments
+/// don't hold a given key, and the filter rules them out without touching their terms. Other fields, and the other
+/// operations on filtered fields, are passed through to the wrapped reader.
+#[derive(Debug)]
+pub struct BloomFilteredLeafReader {
+    inner: Arc<dyn LeafReader>,
+    terms: HashMap<String, BloomFilteredTerms>,
+}
+
+impl BloomFilteredLeafReader {
+    /// Wraps `inner`, building a bloom filter with a false positive probability of at most `max_fpp` over the terms
+    /// of each of `fields`. Fields that aren't indexed in the segment are ignored.
+    ///
+    /// This fails with [LuceneError::InvalidArgument] unless `max_fpp` is strictly between 0 and 1.
+    pub fn new(inner: Arc<dyn LeafReader>, fields: &[&str], max_fpp: f64) -> BoxResult<Self> {
+        let mut terms = HashMap::with_capacity(fields.len());
+        for &field in fields {
+            if let Some(field_terms) = BloomFilteredTerms::new(inner.clone(), field, max_fpp)? {
+                terms.insert(field.to_string(), field_terms);
+            }
+        }
+
+        Ok(Self {
+            inner,
+            terms,
+        })
+    }
+
+    /// Returns the wrapped reader.
+    #[inline]
+    pub fn inner(&self) -> &Arc<dyn LeafReader> {
+        &self.inner
+    }
+
+    /// Returns the bloom filter of the terms of `field`, or `None` if the field isn't filtered.
+    pub fn filter(&self, field: &str) -> Option<&FuzzySet> {
+        self.terms.get(field).map(|terms| &terms.filter)
+    }
+}
+
+impl LeafReader for BloomFilteredLeafReader {
+    #[inline]
+    fn max_doc(&self) -> u32 {
+        self.inner.max_doc()
+    }
+
+    #[inline]
+    fn num_docs(&self) -> u32 {
+        self.inner.num_docs()
+    }
+
+    #[inline]
+    fn live_docs(&self) -> Option<&FixedBitSet> {
+        self.inner.live_docs()
+    }
+
+    fn indexed_fields(&self) -> Vec<&str> {
+        self.inner.indexed_fields()
+    }
+
+    fn doc_values_fields(&self) -> Vec<(&str, DocValuesType)> {
+        self.inner.doc_values_fields()
+    }
+
+    fn terms(&self, field: &str) -> BoxResult<Option<&dyn Terms>> {
+        match self.terms.get(field) {
+            Some(terms) => Ok(Some(terms)),
+            None => self.inner.terms(field),
+        }
+    }
+
+    fn norms(&self, field: &str) -> BoxResult<Option<Arc<[i64]>>> {
+        self.inner.norms(field)
+    }
+
+    fn numeric_doc_values(&self, field: &str) -> BoxResult<Option<Box<dyn NumericDocValues>>> {
+        self.inner.numeric_doc_values(field)
+    }
+
+    fn doc_values_skipper(&self, field: &str) -> BoxResult<Option<Box<dyn DocValuesSkipper>>> {
+        self.inner.doc_values_skipper(field)
+    }
+
+    fn binary_doc_values(&self, field: &str) -> BoxResult<Option<Box<dyn BinaryDocValues>>> {
+        self.inner.binary_doc_values(field)
+    }
+
+    fn document(&self, doc: u32) -> BoxResult<Document> {
+        self.inner.document(doc)
+    }
+
+    fn term_vectors(&self, doc: u32) -> BoxResult<Option<TermVectors>> {
+        self.inner.term_vectors(doc)
+    }
+
+    fn vector_fields(&self) -> Vec<&str> {
+        self.inner.vector_fields()
+    }
+
+    fn float_vector_values(&self, field: &str) -> BoxResult<Option<&FloatVectorValues>> {
+        self.inner.float_vector_values(field)
+    }
+
+    #[inline]
+    fn index_sort(&self) -> Option<&Sort> {
+        self.inner.index_sort()
+    }
+
+    #[inline]
+    fn segment_name(&self) -> Option<&str> {
+        self.inner.segment_name()
+    }
+
+    #[inline]
+    fn core_cache_helper(&self) -> Option<&CacheHelper> {
+        self.inner.core_cache_helper()
+    }
+
+    #[inline]
+    fn reader_cache_helper(&self) -> Option<&CacheHelper> {
+        self.inner.reader_cache_helper()
+    }
+}
+
+impl Accountable for BloomFilteredLeafReader {
+    fn ram_bytes_used(&self) -> usize {
+        self.inner.ram_bytes_used()
+            + self.terms.iter().map(|(field, terms)| field.capacity() + terms.filter.ram_bytes_used()).sum::<usize>()
+    }
+
+    fn child_resources(&self) -> Vec<NamedAccountable> {
+        let mut resources = self.inner.child_resources();
+        for (field, terms) in &self.terms {
+            resources.push(NamedAccountable::new(format!("bloom filter \"{field}\""), &terms.filter));
+        }
+        resources
+    }
+}
+
+/// The [Terms] of a field of a [BloomFilteredLeafReader], whose enums rule out missing terms with the field's bloom
+/// filter before seeking.
+#[derive(Debug)]
+pub struct BloomFilteredTerms {
+    reader: Arc<dyn LeafReader>,
+    field: String,
+    filter: FuzzySet,
+    size: Option<u64>,
+    sum_total_term_freq: u64,
+    sum_doc_freq: u64,
+    doc_count: u32,
+    has_freqs: bool,
+    has_positions: bool,
+    has_offsets: bool,
+}
+
+impl BloomFilteredTerms {
+    /// Builds the filter of `field` of `reader`, returning `None` if the field isn't indexed.
+    fn new(reader: Arc<dyn LeafReader>, field: &str, max_fpp: f64) -> BoxResult<Option<Self>> {
+        let Some(terms) = reader.terms(field)? else {
+            return Ok(None);
+        };
+
+        let size = terms.size();
+        let mut filter = FuzzySet::new(size.unwrap_or_else(|| terms.sum_doc_freq()) as usize, max_fpp)?;
+        let mut te = terms.iterator()?;
+        while let Some(term) = te.next()? {
+            filter.add(term);
+        }
+        drop(te);
+
+        let (sum_total_term_freq, sum_doc_freq, doc_count, has_freqs, has_positions, has_offsets) = (
+            terms.sum_total_term_freq(),
+            terms.sum_doc_freq(),
+            terms.doc_count(),
+            terms.has_freqs(),
+            terms.has_positions(),
+            terms.has_offsets(),
+        );
+        Ok(Some(Self {
+            reader,
+            field: field.to_string(),
+            filter,
+            size,
+            sum_total_term_freq,
+            sum_doc_freq,
+            doc_count,
+            has_freqs,
+            has_positions,
+            has_offsets,
+        }))
+    }
+
+    /// Returns the bloom filter of the field's terms.
+    #[inline]
+    pub fn filter(&self) -> &FuzzySet {
+        &self.filter
+    }
+
+    fn inner(&self) -> BoxResult<&dyn Terms> {
+        self.reader.terms(&self.field)?.ok_or_else(|| {
+            LuceneError::IllegalState(format!("field \"{}\" is no longer indexed in the segment", self.field)).into()
+        })
+    }
+}
+
+impl Terms for BloomFilteredTerms {
+    fn iterator(&self) -> BoxResult<Box<dyn TermsEnum + '_>> {
+        Ok(Box::new(BloomFilteredTermsEnum {
+            terms: self.inner()?,
+            filter: &self.filter,
+            inner: None,
+        }))
+    }
+
+    #[inline]
+    fn size(&self) -> Option<u64> {
+        self.size
+    }
+
+    #[inline]
+    fn sum_total_term_freq(&self) -> u64 {
+        self.sum_total_term_freq
+    }
+
+    #[inline]
+    fn sum_doc_freq(&self) -> u64 {
+        self.sum_doc_freq
+    }
+
+    #[inline]
+    fn doc_count(&self) -> u32 {
+        self.doc_count
+    }
+
+    #[inline]
+    fn has_freqs(&self) -> bool {
+        self.has_freqs
+    }
+
+    #[inline]
+    fn has_positions(&self) -> bool {
+        self.has_positions
+    }
+
+    #[inline]
+    fn has_offsets(&self) -> bool {
+        self.has_offsets
+    }
+}
+
+/// A [TermsEnum] that answers [TermsEnum::seek_exact] from a bloom filter when it can. The wrapped enum is only
+/// created once a call needs it, so lookups of missing terms never touch the term dictionary.
+#[derive(Debug)]
+struct BloomFilteredTermsEnum<'a> {
+    terms: &'a dyn Terms,
+    filter: &'a FuzzySet,
+    inner: Option<Box<dyn TermsEnum + 'a>>,
+}
+
+impl<'a> BloomFilteredTermsEnum<'a> {
+    fn inner(&mut self) -> BoxResult<&mut Box<dyn TermsEnum + 'a>> {
+        if self.inner.is_none() {
+            self.inner = Some(self.terms.iterator()?);
+        }
+        Ok(self.inner.as_mut().unwrap())
+    }
+
+    fn positioned(&self) -> &dyn TermsEnum {
+        self.inner.as_deref().expect("BloomFilteredTermsEnum is not positioned")
+    }
+}
+
+impl TermsEnum for BloomFilteredTermsEnum<'_> {
+    fn next(&mut self) -> BoxResult<Option<&[u8]>> {
+        self.inner()?.next()
+    }
+
+    fn term(&self) -> &[u8] {
+        self.positioned().term()
+    }
+
+    fn seek_ceil(&mut self, target: &[u8]) -> BoxResult<SeekStatus> {
+        self.inner()?.seek_ceil(target)
+    }
+
+    fn seek_exact(&mut self, target: &[u8]) -> BoxResult<bool> {
+        if !self.filter.may_contain(target) {
+            // The enum is unpositioned after a failed seek, so the wrapped one can be dropped.
+            self.inner = None;
+            return Ok(false);
+        }
+        self.inner()?.seek_exact(target)
+    }
+
+    fn doc_freq(&self) -> BoxResult<u32> {
+        self.positioned().doc_freq()
+    }
+
+    fn total_term_freq(&self) -> BoxResult<u64> {
+        self.positioned().total_term_freq()
+    }
+
+    fn postings(&self) -> BoxResult<Box<dyn PostingsEnum>> {
+        self.positioned().postings()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{
+            analysis::SimpleAnalyzer,
+            document::{Document, Field, Store},
+            index::{
+                BloomFilteredLeafReader, IndexReader, LeafReader, MemorySegmentBuilder, MultiReader, Term,
+                DEFAULT_BLOOM_FILTER_FPP,
+            },
+            search::{IndexSearcher, TermQuery},
+        },
+        pretty_assertions::assert_eq,
+        std::sync::Arc,
+    };
+
+    #[test]
+    fn test_bloom_filtered_reader() {
+        let mut builder = MemorySegmentBuilder::new(Arc::new(SimpleAnalyzer));
+        for i in 0..100 {
+            let mut doc = Document::new();
+            doc.add(Field::string("id", format!("doc{i}"), Store::No));
+            doc.add(Field::text("body", "some text", Store::No));
+            builder.add_document(&doc).unwrap();
+        }
+        let segment: Arc<dyn LeafReader> = Arc::new(builder.build());
+
+        let reader = BloomFilteredLeafReader::new(segment, &["id", "missing"], DEFAULT_BLOOM_FILTER_FPP).unwrap();
+        assert!(reader.filter("id").is_some());
+        assert!(reader.filter("body").is_none());
+        assert!(reader.filter("missing").is_none());
+
+        {
+            let terms = reader.terms("id").unwrap().unwrap();
+            assert_eq!(terms.size(), Some(100));
+            assert_eq!(terms.doc_count(), 100);
+
+            let mut te = terms.iterator().unwrap();
+            for i in 0..100 {
+                assert!(te.seek_exact(format!("doc{i}").as_bytes()).unwrap());
+                assert_eq!(te.doc_freq().unwrap(), 1);
+            }
+            let found = (100..1100).filter(|i| te.seek_exact(format!("doc{i}").as_bytes()).unwrap()).count();
+            assert_eq!(found, 0);
+
+            // Other operations go to the wrapped enum.
+            let mut te = terms.iterator().unwrap();
+            assert_eq!(te.next().unwrap(), Some(&b"doc0"[..]));
+            assert_eq!(te.next().unwrap(), Some(&b"doc1"[..]));
+        }
+
+        let reader = MultiReader::new(vec![Arc::new(reader)]).unwrap();
+        assert_eq!(reader.doc_freq(&Term::new("id", "doc42")).unwrap(), 1);
+        assert_eq!(reader.doc_freq(&Term::new("id", "doc420")).unwrap(), 0);
+        assert_eq!(reader.doc_freq(&Term::new("body", "text")).unwrap(), 100);
+
+        let searcher = IndexSearcher::new(Arc::new(reader));
+        let top_docs = searcher.search(&TermQuery::new(Term::new("id", "doc7")), 10).unwrap();
+        assert_eq!(top_docs.score_docs.iter().map(|score_doc| score_doc.doc).collect::<Vec<_>>(), vec![7]);
+    }
+}
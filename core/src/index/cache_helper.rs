@@ -0,0 +1,105 @@
+use std::{
+    fmt::{Debug, Display, Formatter, Result as FmtResult},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+};
+
+/// The next key handed out by [CacheHelper::new].
+static NEXT_CACHE_KEY: AtomicU64 = AtomicU64::new(0);
+
+/// Identifies the data of a reader for caching: two readers with the same key hold the same documents, so results
+/// computed on one can be reused on the other. Keys are never reused within a process.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct CacheKey(u64);
+
+impl Display for CacheKey {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "CacheKey({})", self.0)
+    }
+}
+
+/// A function called with a [CacheKey] once the data it identifies is released.
+pub type ClosedListener = Box<dyn FnOnce(CacheKey) + Send>;
+
+/// Gives applications a key to cache results computed on a reader under, and tells them when to evict those results,
+/// as Lucene's `IndexReader.CacheHelper` does. See [crate::index::LeafReader::core_cache_helper] and
+/// [crate::index::LeafReader::reader_cache_helper].
+///
+/// The helper belongs to the reader owning the data, and its closed listeners are called when that reader is dropped.
+pub struct CacheHelper {
+    key: CacheKey,
+    listeners: Mutex<Vec<ClosedListener>>,
+}
+
+impl CacheHelper {
+    /// Creates a helper with a new key.
+    pub fn new() -> Self {
+        Self {
+            key: CacheKey(NEXT_CACHE_KEY.fetch_add(1, Ordering::Relaxed)),
+            listeners: Mutex::default(),
+        }
+    }
+
+    /// Returns the key identifying the data.
+    #[inline]
+    pub fn key(&self) -> CacheKey {
+        self.key
+    }
+
+    /// Adds a listener called with the key once the data is released, so that what was cached under the key can be
+    /// evicted.
+    pub fn add_closed_listener(&self, listener: impl FnOnce(CacheKey) + Send + 'static) {
+        self.listeners.lock().unwrap().push(Box::new(listener));
+    }
+}
+
+impl Default for CacheHelper {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Debug for CacheHelper {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        let listeners = self.listeners.lock().map_or(0, |listeners| listeners.len());
+        f.debug_struct("CacheHelper").field("key", &self.key).field("listeners", &listeners).finish()
+    }
+}
+
+impl Drop for CacheHelper {
+    fn drop(&mut self) {
+        let listeners = std::mem::take(self.listeners.get_mut().unwrap_or_else(|poisoned| poisoned.into_inner()));
+        for listener in listeners {
+            listener(self.key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::index::CacheHelper,
+        pretty_assertions::assert_eq,
+        std::sync::{Arc, Mutex},
+    };
+
+    #[test]
+    fn test_closed_listeners() {
+        let closed = Arc::new(Mutex::new(Vec::new()));
+        let helper = CacheHelper::new();
+        let other = CacheHelper::new();
+        assert_ne!(helper.key(), other.key());
+
+        let key = helper.key();
+        for _ in 0..2 {
+            let closed = closed.clone();
+            helper.add_closed_listener(move |key| closed.lock().unwrap().push(key));
+        }
+        drop(other);
+        assert!(closed.lock().unwrap().is_empty());
+        drop(helper);
+        assert_eq!(*closed.lock().unwrap(), vec![key, key]);
+    }
+}
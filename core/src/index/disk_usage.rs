@@ -0,0 +1,494 @@
+use {
+    crate::{
+        codec::CompoundDirectory,
+        index::{segment_codec, segment_index_file_name, SegmentCommitInfo, SegmentIndex},
+        io::{CountingInput, Directory, IoContext},
+        BoxResult, ErrorContext,
+    },
+    std::{
+        collections::{BTreeMap, BTreeSet, HashSet},
+        fmt::{Display, Formatter, Result as FmtResult},
+    },
+    tokio::io::{self as tokio_io},
+};
+
+/// The data structure an index file holds, as identified by its extension.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum IndexFileKind {
+    /// A commit point (`segments_N`).
+    CommitPoint,
+
+    /// Segment metadata (`.si`).
+    SegmentInfo,
+
+    /// A compound file holding the other files of a segment (`.cfs`, `.cfe`).
+    CompoundFile,
+
+    /// Field metadata (`.fnm`).
+    FieldInfos,
+
+    /// Stored fields (`.fdt`, `.fdx`, `.fdm`).
+    StoredFields,
+
+    /// The term dictionary and its index (`.tim`, `.tip`, `.tmd`).
+    TermDictionary,
+
+    /// Document ids and term frequencies (`.doc`).
+    Postings,
+
+    /// Term positions (`.pos`).
+    Positions,
+
+    /// Payloads and offsets (`.pay`).
+    Payloads,
+
+    /// Norms (`.nvd`, `.nvm`).
+    Norms,
+
+    /// Doc values (`.dvd`, `.dvm`).
+    DocValues,
+
+    /// Term vectors (`.tvd`, `.tvx`, `.tvm`).
+    TermVectors,
+
+    /// Points (`.kdd`, `.kdi`, `.kdm`).
+    Points,
+
+    /// Vectors (`.vec`, `.vex`, `.vem`).
+    Vectors,
+
+    /// Live documents, recording deletions (`.liv`).
+    LiveDocs,
+
+    /// A file of unknown kind.
+    Other,
+}
+
+impl IndexFileKind {
+    /// Identifies the kind of a file from its name.
+    pub fn from_file_name(file_name: &str) -> Self {
+        if file_name.starts_with("segments") {
+            return Self::CommitPoint;
+        }
+
+        match file_name.rsplit_once('.').map(|(_, extension)| extension) {
+            Some("si") => Self::SegmentInfo,
+            Some("cfs" | "cfe") => Self::CompoundFile,
+            Some("fnm") => Self::FieldInfos,
+            Some("fdt" | "fdx" | "fdm") => Self::StoredFields,
+            Some("tim" | "tip" | "tmd") => Self::TermDictionary,
+            Some("doc") => Self::Postings,
+            Some("pos") => Self::Positions,
+            Some("pay") => Self::Payloads,
+            Some("nvd" | "nvm") => Self::Norms,
+            Some("dvd" | "dvm") => Self::DocValues,
+            Some("tvd" | "tvx" | "tvm") => Self::TermVectors,
+            Some("kdd" | "kdi" | "kdm") => Self::Points,
+            Some("vec" | "vex" | "vem") => Self::Vectors,
+            Some("liv") => Self::LiveDocs,
+            _ => Self::Other,
+        }
+    }
+
+    /// Indicates whether the bytes of this kind are broken down by field (see [SegmentDiskUsage::by_field]). Only
+    /// norms and term vectors are: lucene-core can't read postings, doc values, stored fields, points or vectors
+    /// from disk yet, so those are only counted as a whole.
+    pub fn has_field_breakdown(&self) -> bool {
+        matches!(self, Self::Norms | Self::TermVectors)
+    }
+
+    /// Returns a short description of the kind.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::CommitPoint => "commit point",
+            Self::SegmentInfo => "segment info",
+            Self::CompoundFile => "compound file",
+            Self::FieldInfos => "field infos",
+            Self::StoredFields => "stored fields",
+            Self::TermDictionary => "term dictionary",
+            Self::Postings => "postings",
+            Self::Positions => "positions",
+            Self::Payloads => "payloads",
+            Self::Norms => "norms",
+            Self::DocValues => "doc values",
+            Self::TermVectors => "term vectors",
+            Self::Points => "points",
+            Self::Vectors => "vectors",
+            Self::LiveDocs => "live docs",
+            Self::Other => "other",
+        }
+    }
+}
+
+impl Display for IndexFileKind {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        f.write_str(self.as_str())
+    }
+}
+
+/// The disk usage of one segment, broken down by data structure.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct SegmentDiskUsage {
+    /// The name of the segment.
+    pub name: String,
+
+    /// The number of documents in the segment, including deleted ones.
+    pub max_doc: u32,
+
+    /// The bytes used by each data structure. The files held by a compound file are attributed to their own data
+    /// structures; only the headers, padding and entries of the compound file are [IndexFileKind::CompoundFile].
+    pub by_kind: BTreeMap<IndexFileKind, u64>,
+
+    /// The bytes used by each field in norms and term vectors, the only data structures that lucene-core breaks down
+    /// by field (see [IndexFileKind::has_field_breakdown]). The other data structures, and the headers, metadata and
+    /// footers of these, aren't attributed to fields, so a field's total isn't all the space it uses.
+    pub by_field: BTreeMap<String, BTreeMap<IndexFileKind, u64>>,
+}
+
+impl SegmentDiskUsage {
+    /// Adds a file of the segment to the totals.
+    pub fn add_file(&mut self, file_name: &str, size: u64) {
+        *self.by_kind.entry(IndexFileKind::from_file_name(file_name)).or_default() += size;
+    }
+
+    /// Adds the bytes a field uses in a data structure to the totals.
+    pub fn add_field(&mut self, field: &str, kind: IndexFileKind, size: u64) {
+        *self.by_field.entry(field.to_string()).or_default().entry(kind).or_default() += size;
+    }
+
+    /// Analyzes a segment of the latest commit of `directory`. File sizes and the bytes used by each field are
+    /// counted by reading the files through a [CountingInput], and compound files are broken down by opening the
+    /// files they hold.
+    pub async fn analyze<D: Directory>(directory: &mut D, commit_info: &SegmentCommitInfo) -> BoxResult<Self> {
+        let info = commit_info.get_segment_info();
+        let codec = segment_codec(info)?;
+        let context = IoContext::Read;
+
+        let mut usage = Self {
+            name: info.get_name().to_string(),
+            max_doc: info.get_max_doc(),
+            ..Self::default()
+        };
+
+        let live_docs_files = codec.live_docs_format().files(commit_info);
+        let mut files: BTreeSet<&String> = info.get_files().iter().collect();
+        files.extend(&live_docs_files);
+        files.extend(commit_info.get_field_infos_files());
+        files.extend(commit_info.get_doc_values_update_files().values().flatten());
+        for file_name in files {
+            usage.add_file(file_name, file_size(directory, file_name).await?);
+        }
+
+        let mut compound;
+        let (directory, files): (&mut dyn Directory, HashSet<String>) = if info.is_compound_file() {
+            compound = CompoundDirectory::open(directory, codec.compound_format().as_ref(), info, &context).await?;
+            let mut files = HashSet::new();
+            for (file_name, entry) in compound.entries() {
+                // The held files are counted as their own data structures rather than as part of the compound file.
+                let compound_bytes = usage.by_kind.entry(IndexFileKind::CompoundFile).or_default();
+                *compound_bytes = compound_bytes.saturating_sub(entry.length);
+                usage.add_file(&file_name, entry.length);
+                files.insert(file_name);
+            }
+            (&mut compound, files)
+        } else {
+            (directory, info.get_files().clone())
+        };
+        let has_files = |names: Vec<String>| names.iter().all(|name| files.contains(name));
+
+        let norms_format = codec.norms_format();
+        if has_files(norms_format.files(info)) {
+            for (field, size) in norms_format.field_disk_usage(directory, info, &context).await? {
+                usage.add_field(&field, IndexFileKind::Norms, size);
+            }
+        }
+        let term_vectors_format = codec.term_vectors_format();
+        if has_files(term_vectors_format.files(info)) {
+            for (field, size) in term_vectors_format.field_disk_usage(directory, info, &context).await? {
+                usage.add_field(&field, IndexFileKind::TermVectors, size);
+            }
+        }
+        Ok(usage)
+    }
+
+    /// Returns the total bytes used by the segment.
+    pub fn total(&self) -> u64 {
+        self.by_kind.values().sum()
+    }
+}
+
+/// The disk usage of the latest commit of an index, attributed to segments and, within them, to data structures
+/// (postings, doc values, points, stored fields, vectors and so on) as identified by file extensions, like Lucene's
+/// `IndexDiskUsageAnalyzer`. Unlike Lucene's, only norms and term vectors are also attributed to fields (see
+/// [SegmentDiskUsage::by_field]).
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct IndexDiskUsage {
+    /// The generation of the commit.
+    pub generation: u64,
+
+    /// The bytes used by the commit point itself.
+    pub commit_bytes: u64,
+
+    /// The disk usage of each segment of the commit.
+    pub segments: Vec<SegmentDiskUsage>,
+}
+
+impl IndexDiskUsage {
+    /// Analyzes the latest commit of the index in the directory (see [SegmentDiskUsage::analyze]). Only the files
+    /// referenced by the commit are counted. Every file is read, so this takes as long as reading the whole index.
+    pub async fn analyze<D: Directory>(directory: &mut D) -> BoxResult<Self> {
+        let segment_index = SegmentIndex::open(directory).await?;
+        let generation = segment_index.get_last_generation();
+        let mut usage = Self {
+            generation,
+            commit_bytes: file_size(directory, &segment_index_file_name(generation)).await?,
+            segments: Vec::with_capacity(segment_index.get_segments().len()),
+        };
+
+        for commit_info in segment_index.get_segments() {
+            let segment = SegmentDiskUsage::analyze(directory, commit_info)
+                .await
+                .with_context(|| format!("analyzing segment {}", commit_info.get_segment_info().get_name()))?;
+            usage.segments.push(segment);
+        }
+        Ok(usage)
+    }
+
+    /// Returns the bytes used by each data structure across all segments, including the commit point.
+    pub fn by_kind(&self) -> BTreeMap<IndexFileKind, u64> {
+        let mut by_kind = BTreeMap::new();
+        if self.commit_bytes > 0 {
+            by_kind.insert(IndexFileKind::CommitPoint, self.commit_bytes);
+        }
+        for segment in &self.segments {
+            for (kind, size) in &segment.by_kind {
+                *by_kind.entry(*kind).or_default() += size;
+            }
+        }
+        by_kind
+    }
+
+    /// Returns the bytes used by each field across all segments, by data structure. As in
+    /// [SegmentDiskUsage::by_field], only norms and term vectors are broken down by field.
+    pub fn by_field(&self) -> BTreeMap<String, BTreeMap<IndexFileKind, u64>> {
+        let mut by_field = BTreeMap::<String, BTreeMap<IndexFileKind, u64>>::new();
+        for segment in &self.segments {
+            for (field, by_kind) in &segment.by_field {
+                let totals = by_field.entry(field.clone()).or_default();
+                for (kind, size) in by_kind {
+                    *totals.entry(*kind).or_default() += size;
+                }
+            }
+        }
+        by_field
+    }
+
+    /// Returns the total bytes used by the commit.
+    pub fn total(&self) -> u64 {
+        self.commit_bytes + self.segments.iter().map(SegmentDiskUsage::total).sum::<u64>()
+    }
+}
+
+impl Display for IndexDiskUsage {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        let total = self.total();
+        let percent = |size: u64| {
+            if total == 0 {
+                0.0
+            } else {
+                size as f64 * 100.0 / total as f64
+            }
+        };
+
+        writeln!(f, "Commit generation {}", self.generation)?;
+        writeln!(f, "By segment:")?;
+        for segment in &self.segments {
+            let size = segment.total();
+            writeln!(f, "  {:<24} {size:>14} {:>6.1}%", segment.name, percent(size))?;
+        }
+
+        writeln!(f, "By data structure:")?;
+        let mut by_kind: Vec<_> = self.by_kind().into_iter().collect();
+        by_kind.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        for (kind, size) in by_kind {
+            writeln!(f, "  {:<24} {size:>14} {:>6.1}%", kind.as_str(), percent(size))?;
+        }
+
+        let by_field = self.by_field();
+        if !by_field.is_empty() {
+            writeln!(f, "By field (norms and term vectors only):")?;
+            for (field, by_kind) in by_field {
+                let size = by_kind.values().sum();
+                let kinds: Vec<_> = by_kind.iter().map(|(kind, size)| format!("{kind} {size}")).collect();
+                writeln!(f, "  {field:<24} {size:>14} {:>6.1}%  ({})", percent(size), kinds.join(", "))?;
+            }
+        }
+
+        writeln!(f, "Total: {total} bytes")
+    }
+}
+
+/// Returns the size of a file, counted by reading it through a [CountingInput].
+async fn file_size<D: Directory + ?Sized>(directory: &mut D, file_name: &str) -> BoxResult<u64> {
+    let mut r = CountingInput::new(directory.open(file_name, &IoContext::Read).await?);
+    tokio_io::copy(&mut r, &mut tokio_io::sink()).await.with_context(|| format!("reading {file_name}"))?;
+    Ok(r.bytes_read())
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{
+            analysis::SimpleAnalyzer,
+            codec::get_codec,
+            document::{Document, Field, Store, TermVectorOptions},
+            index::{
+                IndexDiskUsage, IndexFileKind, LeafReader, MemorySegmentBuilder, SegmentCommitInfo, SegmentDiskUsage,
+                SegmentIndex, SegmentInfo,
+            },
+            io::{ByteBuffersDirectory, Directory, IoContext},
+            Id, LATEST,
+        },
+        pretty_assertions::assert_eq,
+        std::{
+            collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+            sync::Arc,
+        },
+        tokio::io::AsyncReadExt,
+    };
+
+    #[test]
+    fn test_disk_usage() {
+        assert_eq!(IndexFileKind::from_file_name("segments_2"), IndexFileKind::CommitPoint);
+        assert_eq!(IndexFileKind::from_file_name("_c_Lucene90_0.tim"), IndexFileKind::TermDictionary);
+        assert_eq!(IndexFileKind::from_file_name("_c_Lucene90_0.dvd"), IndexFileKind::DocValues);
+        assert_eq!(IndexFileKind::from_file_name("_c.fdt"), IndexFileKind::StoredFields);
+        assert_eq!(IndexFileKind::from_file_name("_c_1.liv"), IndexFileKind::LiveDocs);
+        assert_eq!(IndexFileKind::from_file_name("README"), IndexFileKind::Other);
+
+        let mut compound = SegmentDiskUsage {
+            name: "_0".to_string(),
+            max_doc: 10,
+            ..SegmentDiskUsage::default()
+        };
+        compound.add_file("_0.cfs", 600);
+        compound.add_file("_0.cfe", 50);
+        compound.add_file("_0.si", 50);
+
+        let mut flat = SegmentDiskUsage {
+            name: "_c".to_string(),
+            max_doc: 3,
+            ..SegmentDiskUsage::default()
+        };
+        flat.add_file("_c.fdt", 150);
+        flat.add_file("_c_Lucene90_0.doc", 40);
+        flat.add_file("_c.si", 10);
+        assert_eq!(flat.total(), 200);
+
+        let usage = IndexDiskUsage {
+            generation: 2,
+            commit_bytes: 100,
+            segments: vec![compound, flat],
+        };
+        assert_eq!(usage.total(), 1000);
+        assert_eq!(
+            usage.by_kind().into_iter().collect::<Vec<_>>(),
+            vec![
+                (IndexFileKind::CommitPoint, 100),
+                (IndexFileKind::SegmentInfo, 60),
+                (IndexFileKind::CompoundFile, 650),
+                (IndexFileKind::StoredFields, 150),
+                (IndexFileKind::Postings, 40),
+            ]
+        );
+        assert!(usage.to_string().contains("  compound file                       650   65.0%\n"));
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_analyze_disk_usage() {
+        let mut builder = MemorySegmentBuilder::new(Arc::new(SimpleAnalyzer));
+        for body in ["quick fox", "lazy dog", "quick lazy fox"] {
+            let mut doc = Document::new();
+            let field = Field::text("body", body, Store::No);
+            doc.add(field.with_term_vectors(TermVectorOptions::default()).unwrap());
+            doc.add(Field::text("title", "untitled", Store::No));
+            builder.add_document(&doc).unwrap();
+        }
+        let segment = builder.build();
+
+        // The same documents are written to a segment of plain files and to a compound segment.
+        let codec = get_codec("Lucene95").unwrap();
+        let context = IoContext::Default;
+        let mut dir = ByteBuffersDirectory::new();
+        let mut segment_index = SegmentIndex::new(LATEST.major()).unwrap();
+        for is_compound_file in [false, true] {
+            let mut info = SegmentInfo {
+                name: segment_index.new_segment_name(),
+                id: Id::random_id(),
+                max_doc: segment.max_doc(),
+                attributes: HashMap::new(),
+                diagnostics: HashMap::new(),
+                files: HashSet::new(),
+                version: LATEST,
+                min_version: Some(LATEST),
+                is_compound_file,
+                index_sort: None,
+                codec: None,
+            };
+            info.set_codec_name("Lucene95");
+            codec.norms_format().write_norms(&segment, &mut dir, &info, &context).await.unwrap();
+            codec.term_vectors_format().write_term_vectors(&segment, &mut dir, &info, &context).await.unwrap();
+            info.files.extend(codec.norms_format().files(&info));
+            info.files.extend(codec.term_vectors_format().files(&info));
+            if is_compound_file {
+                codec.compound_format().write_compound(&mut dir, &info, &context).await.unwrap();
+                for file_name in info.files.drain() {
+                    dir.remove(&file_name).await.unwrap();
+                }
+                info.files.extend(codec.compound_format().files(&info));
+            }
+            info.files.insert(format!("{}.si", info.name));
+            codec.segment_info_format().write_segment_info(&mut dir, &info, &context).await.unwrap();
+            segment_index.add_segment(SegmentCommitInfo::new(info, 0, 0, None, None, None, None)).unwrap();
+        }
+        segment_index.commit(&mut dir).await.unwrap();
+
+        let usage = IndexDiskUsage::analyze(&mut dir).await.unwrap();
+        assert_eq!(usage.generation, 1);
+        let mut total = 0;
+        for file_name in dir.read_dir().await.unwrap() {
+            total += dir.open(&file_name, &IoContext::Read).await.unwrap().read_to_end(&mut Vec::new()).await.unwrap();
+        }
+        assert_eq!(usage.total(), total as u64);
+
+        // The files of the compound segment are attributed to their data structures, as those of the other are.
+        let [flat, compound] = &usage.segments[..] else {
+            panic!("expected two segments, got {:?}", usage.segments);
+        };
+        let kinds = |segment: &SegmentDiskUsage| segment.by_kind.keys().copied().collect::<Vec<_>>();
+        let flat_kinds = [IndexFileKind::SegmentInfo, IndexFileKind::Norms, IndexFileKind::TermVectors];
+        assert_eq!(kinds(flat), flat_kinds);
+        assert_eq!(kinds(compound)[0], IndexFileKind::SegmentInfo);
+        assert_eq!(kinds(compound)[1], IndexFileKind::CompoundFile);
+        assert_eq!(kinds(compound)[2..], flat_kinds[1..]);
+        assert_eq!(compound.by_kind[&IndexFileKind::Norms], flat.by_kind[&IndexFileKind::Norms]);
+        assert_eq!(compound.by_kind[&IndexFileKind::TermVectors], flat.by_kind[&IndexFileKind::TermVectors]);
+
+        // Each document has a one-byte body norm, and the same title norm, which takes no bytes.
+        assert_eq!(flat.by_field, compound.by_field);
+        assert_eq!(flat.by_field["body"][&IndexFileKind::Norms], 3);
+        assert!(flat.by_field["body"][&IndexFileKind::TermVectors] > 0);
+        assert_eq!(flat.by_field["title"], BTreeMap::from([(IndexFileKind::Norms, 0)]));
+        assert_eq!(usage.by_field()["body"][&IndexFileKind::Norms], 6);
+        assert!(usage.to_string().contains("By field (norms and term vectors only):\n"));
+        let field_kinds: BTreeSet<IndexFileKind> =
+            usage.by_field().values().flat_map(|by_kind| by_kind.keys().copied()).collect();
+        assert_eq!(field_kinds, BTreeSet::from([IndexFileKind::Norms, IndexFileKind::TermVectors]));
+        assert!(field_kinds.iter().all(IndexFileKind::has_field_breakdown));
+        assert!(!IndexFileKind::Postings.has_field_breakdown());
+    }
+}
@@ -0,0 +1,220 @@
+use crate::{
+    index::LeafReader,
+    search::{compare_field_docs, FieldDoc, Sort, SortFieldType, SortKey, NO_MORE_DOCS},
+    util::{size_of_vec, Accountable},
+    BoxResult, LuceneError,
+};
+
+/// A mapping between the document ids of a segment before and after its documents are renumbered, as when a
+/// segment is sorted by its index sort or its documents are added to another index.
+///
+/// The mapping is a permutation: every old document has exactly one new id, and the other way around.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DocMap {
+    old_to_new: Vec<u32>,
+    new_to_old: Vec<u32>,
+}
+
+impl DocMap {
+    /// Creates a mapping that leaves every one of `max_doc` documents in place.
+    pub fn identity(max_doc: u32) -> Self {
+        let docs: Vec<u32> = (0..max_doc).collect();
+        Self {
+            old_to_new: docs.clone(),
+            new_to_old: docs,
+        }
+    }
+
+    /// Creates a mapping from the old id of each new document: `new_to_old[new]` is the document that becomes `new`.
+    ///
+    /// This returns an error if `new_to_old` is not a permutation of the document ids.
+    pub fn from_new_to_old(new_to_old: Vec<u32>) -> BoxResult<Self> {
+        let old_to_new = invert(&new_to_old)?;
+        Ok(Self {
+            old_to_new,
+            new_to_old,
+        })
+    }
+
+    /// Creates a mapping from the new id of each old document: `old_to_new[old]` is the id that `old` becomes.
+    ///
+    /// This returns an error if `old_to_new` is not a permutation of the document ids.
+    pub fn from_old_to_new(old_to_new: Vec<u32>) -> BoxResult<Self> {
+        let new_to_old = invert(&old_to_new)?;
+        Ok(Self {
+            old_to_new,
+            new_to_old,
+        })
+    }
+
+    /// Computes the mapping that sorts the documents of `reader` by `sort`, breaking ties by their current order,
+    /// or `None` if they are already sorted.
+    ///
+    /// Like [crate::index::MemorySegmentBuilder::set_index_sort], only document order and numeric doc values
+    /// fields may be used.
+    pub fn sort(reader: &dyn LeafReader, sort: &Sort) -> BoxResult<Option<Self>> {
+        let keys = resolve_index_sort(sort)?;
+        let max_doc = reader.max_doc();
+
+        let mut values: Vec<Vec<Option<i64>>> = vec![Vec::with_capacity(keys.len()); max_doc as usize];
+        for key in &keys {
+            let mut column = vec![None; max_doc as usize];
+            if let Some(mut doc_values) =
+                key.field.as_ref().map(|field| reader.numeric_doc_values(field)).transpose()?.flatten()
+            {
+                loop {
+                    let doc = doc_values.next_doc()?;
+                    if doc == NO_MORE_DOCS {
+                        break;
+                    }
+                    column[doc as usize] = Some(doc_values.long_value()?);
+                }
+            }
+
+            for (value, column_value) in values.iter_mut().zip(column) {
+                value.push(column_value);
+            }
+        }
+
+        let doc_map = Self::sort_values(&keys, values);
+        Ok((!doc_map.is_identity()).then_some(doc_map))
+    }
+
+    /// Computes the mapping that sorts documents by `keys`, given the numeric doc value of each key's field for
+    /// every document. Ties are broken by the current order.
+    pub(crate) fn sort_values(keys: &[SortKey], values: Vec<Vec<Option<i64>>>) -> Self {
+        let mut field_docs: Vec<FieldDoc> = values
+            .into_iter()
+            .enumerate()
+            .map(|(doc, values)| FieldDoc {
+                doc: doc as u32,
+                score: f32::NAN,
+                fields: keys.iter().zip(values).map(|(key, value)| key.value(doc as u32, f32::NAN, value)).collect(),
+                shard_index: None,
+            })
+            .collect();
+        field_docs.sort_by(|a, b| compare_field_docs(keys, a, b));
+
+        let new_to_old: Vec<u32> = field_docs.iter().map(|field_doc| field_doc.doc).collect();
+        let mut old_to_new = vec![0; new_to_old.len()];
+        for (new_doc, &old_doc) in new_to_old.iter().enumerate() {
+            old_to_new[old_doc as usize] = new_doc as u32;
+        }
+
+        Self {
+            old_to_new,
+            new_to_old,
+        }
+    }
+
+    /// Returns the new id of the document whose id was `doc`.
+    #[inline]
+    pub fn old_to_new(&self, doc: u32) -> u32 {
+        self.old_to_new[doc as usize]
+    }
+
+    /// Returns the old id of the document whose id is now `doc`.
+    #[inline]
+    pub fn new_to_old(&self, doc: u32) -> u32 {
+        self.new_to_old[doc as usize]
+    }
+
+    /// Returns the number of documents mapped.
+    #[inline]
+    pub fn size(&self) -> u32 {
+        self.old_to_new.len() as u32
+    }
+
+    /// Returns whether every document keeps its id.
+    pub fn is_identity(&self) -> bool {
+        self.old_to_new.iter().enumerate().all(|(old_doc, &new_doc)| old_doc as u32 == new_doc)
+    }
+}
+
+impl Accountable for DocMap {
+    fn ram_bytes_used(&self) -> usize {
+        size_of::<Self>() + size_of_vec(&self.old_to_new) + size_of_vec(&self.new_to_old)
+    }
+}
+
+/// Returns the inverse of the permutation `docs`, failing if it isn't one.
+fn invert(docs: &[u32]) -> BoxResult<Vec<u32>> {
+    let mut inverse = vec![u32::MAX; docs.len()];
+    for (i, &doc) in docs.iter().enumerate() {
+        match inverse.get_mut(doc as usize) {
+            Some(slot) if *slot == u32::MAX => *slot = i as u32,
+            Some(_) => {
+                return Err(LuceneError::InvalidArgument(format!("document {doc} is mapped more than once")).into());
+            }
+            None => {
+                return Err(LuceneError::InvalidArgument(format!(
+                    "document {doc} is out of bounds (max_doc is {})",
+                    docs.len()
+                ))
+                .into());
+            }
+        }
+    }
+    Ok(inverse)
+}
+
+/// Resolves the fields of an index sort, failing for sorts by score or by computed values, which can't be derived
+/// from the documents alone.
+pub(crate) fn resolve_index_sort(sort: &Sort) -> BoxResult<Vec<SortKey>> {
+    let keys = SortKey::resolve(sort)?;
+    if keys.iter().any(|key| key.field_type == SortFieldType::DocumentScore) {
+        return Err(LuceneError::InvalidSortField("an index can't be sorted by score".to_string()).into());
+    }
+    if keys.iter().any(|key| key.source.is_some()) {
+        return Err(LuceneError::InvalidSortField("an index can't be sorted by computed values".to_string()).into());
+    }
+    Ok(keys)
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{
+            analysis::SimpleAnalyzer,
+            document::{Document, Field},
+            index::{DocMap, MemorySegmentBuilder},
+            search::{BasicSortField, Sort},
+        },
+        pretty_assertions::assert_eq,
+        std::sync::Arc,
+    };
+
+    #[test]
+    fn test_doc_map() {
+        let doc_map = DocMap::from_new_to_old(vec![2, 0, 1]).unwrap();
+        assert_eq!(doc_map.size(), 3);
+        assert_eq!(doc_map.old_to_new(2), 0);
+        assert_eq!(doc_map.new_to_old(0), 2);
+        assert!(!doc_map.is_identity());
+        assert_eq!(DocMap::from_old_to_new(vec![1, 2, 0]).unwrap(), doc_map);
+        assert!(DocMap::identity(3).is_identity());
+
+        assert!(DocMap::from_new_to_old(vec![0, 0, 1]).is_err());
+        assert!(DocMap::from_old_to_new(vec![0, 3, 1]).is_err());
+    }
+
+    #[test]
+    fn test_sort_reader() {
+        let mut builder = MemorySegmentBuilder::new(Arc::new(SimpleAnalyzer));
+        for rank in [5, 1, 3] {
+            let mut doc = Document::new();
+            doc.add(Field::numeric_doc_values("rank", rank));
+            builder.add_document(&doc).unwrap();
+        }
+        builder.add_document(&Document::new()).unwrap();
+        let segment = builder.build();
+
+        let sort = Sort::from_fields(vec![Box::new(BasicSortField::for_i64_field("rank", Some(i64::MAX)))]).unwrap();
+        let doc_map = DocMap::sort(&segment, &sort).unwrap().unwrap();
+        assert_eq!((0..4).map(|doc| doc_map.new_to_old(doc)).collect::<Vec<_>>(), vec![1, 2, 0, 3]);
+
+        let sort = Sort::from_fields(vec![Box::new(BasicSortField::document_index_order())]).unwrap();
+        assert!(DocMap::sort(&segment, &sort).unwrap().is_none());
+        assert!(DocMap::sort(&segment, &Sort::by_relevance()).is_err());
+    }
+}
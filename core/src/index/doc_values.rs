@@ -0,0 +1,221 @@
+use {
+    crate::{
+        search::{DocIdSetIterator, NO_MORE_DOCS},
+        util::Accountable,
+        BoxResult,
+    },
+    std::sync::Arc,
+};
+
+/// The kind of doc values (column-stride per-document values) recorded for a field.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum DocValuesType {
+    /// A single 64-bit integer per document.
+    Numeric,
+
+    /// A single byte string per document.
+    Binary,
+}
+
+/// Iterates over the documents that have a numeric doc value for a field, giving access to each value.
+pub trait NumericDocValues: DocIdSetIterator {
+    /// Positions on `target`, returning whether it has a value. Unlike [DocIdSetIterator::advance], this doesn't move
+    /// past `target`; `target` must be at least the current document.
+    fn advance_exact(&mut self, target: u32) -> BoxResult<bool>;
+
+    /// Returns the value of the current document.
+    fn long_value(&self) -> BoxResult<i64>;
+}
+
+/// [NumericDocValues] over values held in memory.
+#[derive(Debug)]
+pub struct MemoryNumericDocValues {
+    docs: Arc<[u32]>,
+    values: Arc<[i64]>,
+    index: usize,
+    doc: Option<u32>,
+}
+
+impl MemoryNumericDocValues {
+    /// Creates doc values where `values[i]` is the value of document `docs[i]`. The documents must be sorted and
+    /// distinct.
+    pub fn new(docs: Arc<[u32]>, values: Arc<[i64]>) -> Self {
+        debug_assert_eq!(docs.len(), values.len());
+        debug_assert!(docs.windows(2).all(|w| w[0] < w[1]), "document ids must be sorted and distinct");
+        Self {
+            docs,
+            values,
+            index: 0,
+            doc: None,
+        }
+    }
+
+    /// Moves `index` to the first document at or after `target`.
+    fn seek(&mut self, target: u32) {
+        self.index += self.docs[self.index..].partition_point(|&d| d < target);
+    }
+}
+
+impl Accountable for MemoryNumericDocValues {
+    fn ram_bytes_used(&self) -> usize {
+        size_of::<Self>() + self.docs.len() * size_of::<u32>() + self.values.len() * size_of::<i64>()
+    }
+}
+
+impl DocIdSetIterator for MemoryNumericDocValues {
+    #[inline]
+    fn doc_id(&self) -> u32 {
+        self.doc.unwrap_or(NO_MORE_DOCS)
+    }
+
+    fn next_doc(&mut self) -> BoxResult<u32> {
+        match self.doc {
+            None => self.advance(0),
+            Some(NO_MORE_DOCS) => Ok(NO_MORE_DOCS),
+            Some(doc) => self.advance(doc + 1),
+        }
+    }
+
+    fn advance(&mut self, target: u32) -> BoxResult<u32> {
+        self.seek(target);
+        let doc = self.docs.get(self.index).copied().unwrap_or(NO_MORE_DOCS);
+        self.doc = Some(doc);
+        Ok(doc)
+    }
+
+    #[inline]
+    fn cost(&self) -> u64 {
+        self.docs.len() as u64
+    }
+}
+
+impl NumericDocValues for MemoryNumericDocValues {
+    fn advance_exact(&mut self, target: u32) -> BoxResult<bool> {
+        self.seek(target);
+        self.doc = Some(target);
+        Ok(self.docs.get(self.index) == Some(&target))
+    }
+
+    fn long_value(&self) -> BoxResult<i64> {
+        Ok(self.values[self.index])
+    }
+}
+
+/// Iterates over the documents that have a binary doc value for a field, giving access to each value.
+pub trait BinaryDocValues: DocIdSetIterator {
+    /// Positions on `target`, returning whether it has a value. Unlike [DocIdSetIterator::advance], this doesn't move
+    /// past `target`; `target` must be at least the current document.
+    fn advance_exact(&mut self, target: u32) -> BoxResult<bool>;
+
+    /// Returns the value of the current document.
+    fn binary_value(&self) -> BoxResult<&[u8]>;
+}
+
+/// [BinaryDocValues] over values held in memory.
+#[derive(Debug)]
+pub struct MemoryBinaryDocValues {
+    docs: Arc<[u32]>,
+    values: Arc<[Vec<u8>]>,
+    index: usize,
+    doc: Option<u32>,
+}
+
+impl MemoryBinaryDocValues {
+    /// Creates doc values where `values[i]` is the value of document `docs[i]`. The documents must be sorted and
+    /// distinct.
+    pub fn new(docs: Arc<[u32]>, values: Arc<[Vec<u8>]>) -> Self {
+        debug_assert_eq!(docs.len(), values.len());
+        debug_assert!(docs.windows(2).all(|w| w[0] < w[1]), "document ids must be sorted and distinct");
+        Self {
+            docs,
+            values,
+            index: 0,
+            doc: None,
+        }
+    }
+
+    /// Moves `index` to the first document at or after `target`.
+    fn seek(&mut self, target: u32) {
+        self.index += self.docs[self.index..].partition_point(|&d| d < target);
+    }
+}
+
+impl Accountable for MemoryBinaryDocValues {
+    fn ram_bytes_used(&self) -> usize {
+        size_of::<Self>()
+            + self.docs.len() * size_of::<u32>()
+            + self.values.iter().map(|value| size_of::<Vec<u8>>() + value.capacity()).sum::<usize>()
+    }
+}
+
+impl DocIdSetIterator for MemoryBinaryDocValues {
+    #[inline]
+    fn doc_id(&self) -> u32 {
+        self.doc.unwrap_or(NO_MORE_DOCS)
+    }
+
+    fn next_doc(&mut self) -> BoxResult<u32> {
+        match self.doc {
+            None => self.advance(0),
+            Some(NO_MORE_DOCS) => Ok(NO_MORE_DOCS),
+            Some(doc) => self.advance(doc + 1),
+        }
+    }
+
+    fn advance(&mut self, target: u32) -> BoxResult<u32> {
+        self.seek(target);
+        let doc = self.docs.get(self.index).copied().unwrap_or(NO_MORE_DOCS);
+        self.doc = Some(doc);
+        Ok(doc)
+    }
+
+    #[inline]
+    fn cost(&self) -> u64 {
+        self.docs.len() as u64
+    }
+}
+
+impl BinaryDocValues for MemoryBinaryDocValues {
+    fn advance_exact(&mut self, target: u32) -> BoxResult<bool> {
+        self.seek(target);
+        self.doc = Some(target);
+        Ok(self.docs.get(self.index) == Some(&target))
+    }
+
+    fn binary_value(&self) -> BoxResult<&[u8]> {
+        Ok(&self.values[self.index])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{
+            index::{BinaryDocValues, MemoryBinaryDocValues, MemoryNumericDocValues, NumericDocValues},
+            search::{DocIdSetIterator, NO_MORE_DOCS},
+        },
+        pretty_assertions::assert_eq,
+    };
+
+    #[test]
+    fn test_numeric_doc_values() {
+        let mut dv = MemoryNumericDocValues::new(vec![1, 4, 5].into(), vec![10, -40, 50].into());
+        assert_eq!(dv.cost(), 3);
+        assert!(!dv.advance_exact(0).unwrap());
+        assert!(dv.advance_exact(4).unwrap());
+        assert_eq!(dv.long_value().unwrap(), -40);
+        assert_eq!(dv.next_doc().unwrap(), 5);
+        assert_eq!(dv.long_value().unwrap(), 50);
+        assert_eq!(dv.next_doc().unwrap(), NO_MORE_DOCS);
+    }
+
+    #[test]
+    fn test_binary_doc_values() {
+        let mut dv = MemoryBinaryDocValues::new(vec![0, 3].into(), vec![b"a".to_vec(), b"bc".to_vec()].into());
+        assert_eq!(dv.cost(), 2);
+        assert!(!dv.advance_exact(2).unwrap());
+        assert!(dv.advance_exact(3).unwrap());
+        assert_eq!(dv.binary_value().unwrap(), b"bc");
+        assert_eq!(dv.next_doc().unwrap(), NO_MORE_DOCS);
+    }
+}
@@ -0,0 +1,197 @@
+use {
+    crate::{search::NO_MORE_DOCS, BoxResult},
+    std::{fmt::Debug, sync::Arc},
+};
+
+/// The default number of documents with a value summarized by each block of a skip index.
+pub const DEFAULT_SKIP_INDEX_INTERVAL: usize = 4096;
+
+/// Skips over blocks of documents by the range of their numeric doc values, so that range queries on doc values can
+/// rule out whole blocks without reading their values.
+///
+/// The skipper starts on the first block, and only moves forward.
+pub trait DocValuesSkipper: Debug + Send {
+    /// Moves to the first block that ends at or after `target`, if the current block ends before it.
+    fn advance(&mut self, target: u32) -> BoxResult<()>;
+
+    /// Returns the first document with a value in the current block, or [NO_MORE_DOCS] once past the last block.
+    fn min_doc_id(&self) -> u32;
+
+    /// Returns the last document with a value in the current block, or [NO_MORE_DOCS] once past the last block.
+    fn max_doc_id(&self) -> u32;
+
+    /// Returns the smallest value in the current block.
+    fn min_value(&self) -> i64;
+
+    /// Returns the largest value in the current block.
+    fn max_value(&self) -> i64;
+
+    /// Returns the number of documents with a value in the current block.
+    fn doc_count(&self) -> u32;
+
+    /// Returns the smallest value of the field in the segment, or [i64::MAX] if it has none.
+    fn global_min_value(&self) -> i64;
+
+    /// Returns the largest value of the field in the segment, or [i64::MIN] if it has none.
+    fn global_max_value(&self) -> i64;
+
+    /// Returns the number of documents with a value for the field in the segment.
+    fn global_doc_count(&self) -> u32;
+}
+
+/// The summary of a block of documents with a numeric doc value, as kept by a skip index.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct DocValuesSkipBlock {
+    /// The first document of the block.
+    pub min_doc: u32,
+
+    /// The last document of the block.
+    pub max_doc: u32,
+
+    /// The smallest value in the block.
+    pub min_value: i64,
+
+    /// The largest value in the block.
+    pub max_value: i64,
+
+    /// The number of documents in the block.
+    pub doc_count: u32,
+}
+
+impl DocValuesSkipBlock {
+    /// Summarizes doc values, where `values[i]` is the value of document `docs[i]`, in blocks of `interval`
+    /// documents. The documents must be sorted and distinct.
+    pub fn build(docs: &[u32], values: &[i64], interval: usize) -> Vec<Self> {
+        debug_assert_eq!(docs.len(), values.len());
+        docs.chunks(interval.max(1))
+            .zip(values.chunks(interval.max(1)))
+            .map(|(docs, values)| Self {
+                min_doc: docs[0],
+                max_doc: docs[docs.len() - 1],
+                min_value: values.iter().copied().min().unwrap(),
+                max_value: values.iter().copied().max().unwrap(),
+                doc_count: docs.len() as u32,
+            })
+            .collect()
+    }
+}
+
+/// A [DocValuesSkipper] over blocks held in memory.
+#[derive(Debug)]
+pub struct MemoryDocValuesSkipper {
+    blocks: Arc<[DocValuesSkipBlock]>,
+    index: usize,
+}
+
+impl MemoryDocValuesSkipper {
+    /// Creates a skipper over `blocks`, which must be in document order and must not overlap.
+    pub fn new(blocks: Arc<[DocValuesSkipBlock]>) -> Self {
+        debug_assert!(blocks.windows(2).all(|w| w[0].max_doc < w[1].min_doc), "blocks must be sorted and disjoint");
+        Self {
+            blocks,
+            index: 0,
+        }
+    }
+
+    #[inline]
+    fn current(&self) -> Option<&DocValuesSkipBlock> {
+        self.blocks.get(self.index)
+    }
+}
+
+impl DocValuesSkipper for MemoryDocValuesSkipper {
+    fn advance(&mut self, target: u32) -> BoxResult<()> {
+        self.index += self.blocks[self.index.min(self.blocks.len())..].partition_point(|block| block.max_doc < target);
+        Ok(())
+    }
+
+    #[inline]
+    fn min_doc_id(&self) -> u32 {
+        self.current().map_or(NO_MORE_DOCS, |block| block.min_doc)
+    }
+
+    #[inline]
+    fn max_doc_id(&self) -> u32 {
+        self.current().map_or(NO_MORE_DOCS, |block| block.max_doc)
+    }
+
+    #[inline]
+    fn min_value(&self) -> i64 {
+        self.current().map_or(i64::MAX, |block| block.min_value)
+    }
+
+    #[inline]
+    fn max_value(&self) -> i64 {
+        self.current().map_or(i64::MIN, |block| block.max_value)
+    }
+
+    #[inline]
+    fn doc_count(&self) -> u32 {
+        self.current().map_or(0, |block| block.doc_count)
+    }
+
+    fn global_min_value(&self) -> i64 {
+        self.blocks.iter().map(|block| block.min_value).min().unwrap_or(i64::MAX)
+    }
+
+    fn global_max_value(&self) -> i64 {
+        self.blocks.iter().map(|block| block.max_value).max().unwrap_or(i64::MIN)
+    }
+
+    fn global_doc_count(&self) -> u32 {
+        self.blocks.iter().map(|block| block.doc_count).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{
+            index::{DocValuesSkipBlock, DocValuesSkipper, MemoryDocValuesSkipper},
+            search::NO_MORE_DOCS,
+        },
+        pretty_assertions::assert_eq,
+    };
+
+    #[test]
+    fn test_skipper() {
+        let docs: Vec<u32> = (0..10).map(|i| i * 3).collect();
+        let values: Vec<i64> = (0..10).map(|i| 100 - i * i).collect();
+        let blocks = DocValuesSkipBlock::build(&docs, &values, 4);
+        assert_eq!(blocks.len(), 3);
+        assert_eq!(
+            blocks[1],
+            DocValuesSkipBlock {
+                min_doc: 12,
+                max_doc: 21,
+                min_value: 51,
+                max_value: 84,
+                doc_count: 4,
+            }
+        );
+
+        let mut skipper = MemoryDocValuesSkipper::new(blocks.into());
+        assert_eq!(skipper.global_min_value(), 19);
+        assert_eq!(skipper.global_max_value(), 100);
+        assert_eq!(skipper.global_doc_count(), 10);
+        assert_eq!((skipper.min_doc_id(), skipper.max_doc_id()), (0, 9));
+
+        // Targets within the current block, or between blocks, move to the block that ends at or after them.
+        skipper.advance(5).unwrap();
+        assert_eq!(skipper.min_doc_id(), 0);
+        skipper.advance(10).unwrap();
+        assert_eq!((skipper.min_doc_id(), skipper.max_doc_id(), skipper.doc_count()), (12, 21, 4));
+        skipper.advance(24).unwrap();
+        assert_eq!((skipper.min_value(), skipper.max_value()), (19, 36));
+        skipper.advance(28).unwrap();
+        assert_eq!((skipper.min_doc_id(), skipper.max_doc_id(), skipper.doc_count()), (NO_MORE_DOCS, NO_MORE_DOCS, 0));
+        skipper.advance(NO_MORE_DOCS).unwrap();
+        assert_eq!(skipper.min_doc_id(), NO_MORE_DOCS);
+
+        let empty = MemoryDocValuesSkipper::new(Vec::new().into());
+        assert_eq!(
+            (empty.min_doc_id(), empty.global_min_value(), empty.global_doc_count()),
+            (NO_MORE_DOCS, i64::MAX, 0)
+        );
+    }
+}
@@ -0,0 +1,759 @@
+use {
+    crate::{
+        document::Document,
+        index::{
+            generation_to_string, FlushEvent, FlushState, IndexWriterConfig, LeafReader, MemorySegmentBuilder,
+            MergeFinishEvent, MergeStartEvent, MergeStats, SegmentReader, Term,
+        },
+        search::NO_MORE_DOCS,
+        util::{BitSet, FixedBitSet},
+        BoxResult, LuceneError,
+    },
+    log::warn,
+    std::{
+        fmt::{Debug, Formatter, Result as FmtResult},
+        mem,
+        sync::{
+            atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+            Arc, Mutex,
+        },
+        thread,
+        time::Instant,
+    },
+};
+
+/// An in-memory segment being built by one thread at a time.
+#[derive(Debug)]
+pub struct DocumentsWriterPerThread {
+    id: usize,
+    builder: MemorySegmentBuilder,
+}
+
+impl DocumentsWriterPerThread {
+    fn new(id: usize, config: &IndexWriterConfig) -> Self {
+        let mut builder = MemorySegmentBuilder::new(config.indexing_analyzer());
+        builder.set_similarity(config.similarity().clone());
+        builder.set_terms_formats(config.terms_formats().clone());
+        builder.set_metrics(config.metrics().cloned());
+        Self {
+            id,
+            builder,
+        }
+    }
+
+    /// Returns the id of this writer, unique within its [DocumentsWriter].
+    #[inline]
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    /// Returns the number of documents buffered by this writer.
+    #[inline]
+    pub fn num_docs(&self) -> u32 {
+        self.builder.max_doc()
+    }
+
+    /// Returns an estimate of the memory used by this writer's documents, in bytes.
+    #[inline]
+    pub fn ram_bytes_used(&self) -> usize {
+        self.builder.ram_bytes_used()
+    }
+}
+
+/// Buffers added documents in memory and flushes them to segments, following Lucene's per-thread indexing model.
+///
+/// Each call to [DocumentsWriter::add_document] checks out a [DocumentsWriterPerThread] that no other thread is
+/// using, creating one if they are all busy, and indexes the document into it without holding any shared lock.
+/// Concurrent callers therefore build independent segments in parallel. Once the configured [crate::index::FlushPolicy]
+/// decides to flush, by default when the documents buffered across all writers use more than the RAM buffer size, the
+/// caller that triggered it flushes the writer it holds, on its own thread, while other threads keep indexing.
+///
+/// A `DocumentsWriter` is shared between threads through an [Arc]; see
+/// [IndexWriter::documents_writer](crate::index::IndexWriter::documents_writer).
+///
+/// With the `tracing` feature enabled, force merges and each merge they run emit `tracing` spans at debug level,
+/// naming the segments merged and the segment they are merged into, along with a `flush` span for each segment built.
+pub struct DocumentsWriter {
+    config: IndexWriterConfig,
+    idle: Mutex<Vec<DocumentsWriterPerThread>>,
+    segments: Mutex<Vec<Arc<dyn LeafReader>>>,
+    next_id: AtomicUsize,
+    next_segment: AtomicU64,
+    num_buffered_docs: AtomicUsize,
+    ram_bytes_used: AtomicUsize,
+    closed: AtomicBool,
+    force_merging: AtomicBool,
+}
+
+impl DocumentsWriter {
+    /// Creates a writer that analyzes documents and flushes segments according to the given configuration.
+    pub fn new(config: IndexWriterConfig) -> Self {
+        Self {
+            config,
+            idle: Mutex::new(Vec::new()),
+            segments: Mutex::new(Vec::new()),
+            next_id: AtomicUsize::new(0),
+            next_segment: AtomicU64::new(0),
+            num_buffered_docs: AtomicUsize::new(0),
+            ram_bytes_used: AtomicUsize::new(0),
+            closed: AtomicBool::new(false),
+            force_merging: AtomicBool::new(false),
+        }
+    }
+
+    /// Returns the configuration of this writer.
+    #[inline]
+    pub fn config(&self) -> &IndexWriterConfig {
+        &self.config
+    }
+
+    /// Returns a name for a new segment, as Lucene names segments: `_0`, `_1`, and so on, counting in base 36. Every
+    /// segment flushed or merged by this writer is named, so that it can be told apart in traces.
+    pub fn new_segment_name(&self) -> String {
+        format!("_{}", generation_to_string(self.next_segment.fetch_add(1, Ordering::Relaxed)))
+    }
+
+    /// Adds a document, flushing the segment it was added to if the flush policy then asks for it (see
+    /// [IndexWriterConfig::set_flush_policy]). Returns whether a flush happened. This fails with
+    /// [LuceneError::SchemaViolation] if the configuration has a schema the document doesn't fit.
+    pub fn add_document(&self, document: &Document) -> BoxResult<bool> {
+        self.ensure_open()?;
+        if let Some(schema) = self.config.schema() {
+            schema.validate(document)?;
+        }
+
+        let mut dwpt = self.checkout();
+        let ram_before = dwpt.ram_bytes_used();
+        let result = dwpt.builder.add_document(document);
+        self.ram_bytes_used.fetch_add(dwpt.ram_bytes_used() - ram_before, Ordering::Relaxed);
+        if let Err(e) = result {
+            self.checkin(dwpt);
+            return Err(e);
+        }
+
+        self.num_buffered_docs.fetch_add(1, Ordering::Relaxed);
+        let state = FlushState {
+            ram_bytes_used: self.ram_bytes_used(),
+            num_buffered_docs: self.num_buffered_docs(),
+            segment_ram_bytes_used: dwpt.ram_bytes_used(),
+            segment_num_docs: dwpt.num_docs(),
+        };
+        if self.config.flush_policy().on_insert(&self.config, &state) {
+            self.flush_dwpt(dwpt);
+            return Ok(true);
+        }
+
+        self.checkin(dwpt);
+        Ok(false)
+    }
+
+    /// Flushes the documents buffered by every writer not in use by another thread, building their segments in
+    /// parallel. Documents being added concurrently are flushed later.
+    pub fn flush(&self) -> BoxResult<()> {
+        self.ensure_open()?;
+
+        let dwpts: Vec<_> =
+            mem::take(&mut *self.idle.lock().unwrap()).into_iter().filter(|dwpt| dwpt.num_docs() > 0).collect();
+        match dwpts.len() {
+            0 => {}
+            1 => dwpts.into_iter().for_each(|dwpt| self.flush_dwpt(dwpt)),
+            _ => thread::scope(|scope| {
+                for dwpt in dwpts {
+                    scope.spawn(move || self.flush_dwpt(dwpt));
+                }
+            }),
+        }
+
+        Ok(())
+    }
+
+    /// Returns the number of documents buffered since they were last flushed.
+    #[inline]
+    pub fn num_buffered_docs(&self) -> usize {
+        self.num_buffered_docs.load(Ordering::Relaxed)
+    }
+
+    /// Returns an estimate of the memory used by buffered documents, in bytes.
+    #[inline]
+    pub fn ram_bytes_used(&self) -> usize {
+        self.ram_bytes_used.load(Ordering::Relaxed)
+    }
+
+    /// Adds a segment built outside of this writer, such as by [crate::index::IndexWriter::add_indexes_from_readers],
+    /// after the segments flushed so far.
+    pub fn add_segment(&self, segment: Arc<dyn LeafReader>) -> BoxResult<()> {
+        self.ensure_open()?;
+        self.warm(&segment);
+        self.segments.lock().unwrap().push(segment);
+        Ok(())
+    }
+
+    /// Flushes the buffered documents, then deletes the documents containing any of `terms` from the segments,
+    /// returning the number of documents deleted, like Lucene's `IndexWriter.deleteDocuments`. A segment with deleted
+    /// documents is replaced by a [SegmentReader] hiding them, and they are dropped once the segment is merged. A
+    /// segment whose documents are all deleted is dropped right away.
+    ///
+    /// Documents added by other threads while this runs may be flushed after the deletes are applied, and are then
+    /// kept. This fails with [LuceneError::IllegalState] if a force merge is running.
+    pub fn delete_documents(&self, terms: &[Term]) -> BoxResult<u32> {
+        let _guard = self.begin_force_merge()?;
+        self.flush()?;
+
+        let mut num_deleted = 0;
+        let mut segments = self.segments.lock().unwrap();
+        let mut kept = Vec::with_capacity(segments.len());
+        for segment in segments.iter() {
+            let Some(live_docs) = Self::live_docs_without(segment.as_ref(), terms)? else {
+                kept.push(segment.clone());
+                continue;
+            };
+            num_deleted += segment.num_docs() - live_docs.cardinality();
+            if live_docs.cardinality() > 0 {
+                kept.push(Arc::new(SegmentReader::new(segment.clone(), Some(live_docs))?));
+            }
+        }
+        *segments = kept;
+        Ok(num_deleted)
+    }
+
+    /// Returns the live documents of `segment` once the documents containing any of `terms` are deleted, or `None` if
+    /// none of its live documents contains them.
+    fn live_docs_without(segment: &dyn LeafReader, terms: &[Term]) -> BoxResult<Option<FixedBitSet>> {
+        let mut live_docs = segment.live_docs().cloned().unwrap_or_else(|| {
+            let mut live_docs = FixedBitSet::new(segment.max_doc());
+            live_docs.set_range(0, segment.max_doc());
+            live_docs
+        });
+        let mut changed = false;
+        for term in terms {
+            let Some(field_terms) = segment.terms(term.field())? else {
+                continue;
+            };
+            let mut terms_enum = field_terms.iterator()?;
+            if !terms_enum.seek_exact(term.bytes())? {
+                continue;
+            }
+
+            let mut postings = terms_enum.postings()?;
+            loop {
+                let doc = postings.next_doc()?;
+                if doc == NO_MORE_DOCS {
+                    break;
+                }
+                changed |= live_docs.get(doc);
+                live_docs.clear(doc);
+            }
+        }
+        Ok(changed.then_some(live_docs))
+    }
+
+    /// Returns the segments flushed so far, in the order their flushes finished.
+    pub fn segments(&self) -> Vec<Arc<dyn LeafReader>> {
+        self.segments.lock().unwrap().clone()
+    }
+
+    /// Flushes the buffered documents, then merges the segments until there are at most `max_num_segments`,
+    /// returning once the merge has finished. The smallest run of adjacent segments is merged, so documents keep
+    /// their relative order. Deleted documents are dropped from the merged segment, and merging down to a single
+    /// segment also rewrites a lone segment that has deletions.
+    ///
+    /// Documents can be added while the segments are merged; segments flushed in the meantime are kept as they are.
+    /// Returns the statistics of the merge. This fails with [LuceneError::IllegalState] if another force merge is
+    /// running.
+    pub fn force_merge(&self, max_num_segments: usize) -> BoxResult<MergeStats> {
+        if max_num_segments == 0 {
+            return Err(LuceneError::InvalidArgument("max_num_segments must be at least 1".to_string()).into());
+        }
+
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!("force_merge", max_num_segments).entered();
+
+        let _guard = self.begin_force_merge()?;
+        self.flush()?;
+
+        let segments = self.segments();
+        let merge_size = if segments.len() > max_num_segments {
+            segments.len() - max_num_segments + 1
+        } else if max_num_segments == 1 && segments.len() == 1 && segments[0].num_docs() < segments[0].max_doc() {
+            1
+        } else {
+            return Ok(MergeStats::default());
+        };
+
+        let start = (0..=segments.len() - merge_size)
+            .min_by_key(|&start| segments[start..start + merge_size].iter().map(|s| s.max_doc() as u64).sum::<u64>())
+            .expect("there are at least merge_size segments");
+        let mut stats = self.new_merge_stats();
+        self.merge(&segments[start..start + merge_size], &mut stats)?;
+        Ok(stats)
+    }
+
+    /// Flushes the buffered documents, then rewrites every segment whose percentage of deleted documents is more
+    /// than `pct_allowed`, dropping the deleted documents, and returns the statistics of the rewrites once they
+    /// have finished.
+    ///
+    /// This fails with [LuceneError::IllegalState] if a force merge is running.
+    pub fn force_merge_deletes(&self, pct_allowed: f64) -> BoxResult<MergeStats> {
+        if !(0.0..=100.0).contains(&pct_allowed) {
+            return Err(LuceneError::InvalidArgument(format!(
+                "pct_allowed must be between 0 and 100, got {pct_allowed}"
+            ))
+            .into());
+        }
+
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!("force_merge_deletes", pct_allowed).entered();
+
+        let _guard = self.begin_force_merge()?;
+        self.flush()?;
+
+        let mut stats = self.new_merge_stats();
+        for segment in self.segments() {
+            let deleted = segment.max_doc() - segment.num_docs();
+            if deleted > 0 && deleted as f64 * 100.0 > pct_allowed * segment.max_doc() as f64 {
+                self.merge(&[segment], &mut stats)?;
+            }
+        }
+        Ok(stats)
+    }
+
+    /// Indicates whether a force merge is running.
+    #[inline]
+    pub fn is_force_merging(&self) -> bool {
+        self.force_merging.load(Ordering::Acquire)
+    }
+
+    /// Returns the number of per-thread writers created so far, which is the largest number of threads that have
+    /// added documents at the same time.
+    #[inline]
+    pub fn num_thread_states(&self) -> usize {
+        self.next_id.load(Ordering::Relaxed)
+    }
+
+    /// Indicates whether this writer is still open.
+    #[inline]
+    pub fn is_open(&self) -> bool {
+        !self.closed.load(Ordering::Acquire)
+    }
+
+    /// Closes this writer: documents can no longer be added or flushed.
+    pub fn close(&self) {
+        self.closed.store(true, Ordering::Release);
+    }
+
+    fn ensure_open(&self) -> BoxResult<()> {
+        if self.is_open() {
+            Ok(())
+        } else {
+            Err(LuceneError::AlreadyClosed("This IndexWriter is closed".to_string()).into())
+        }
+    }
+
+    fn begin_force_merge(&self) -> BoxResult<ForceMergeGuard<'_>> {
+        self.ensure_open()?;
+        if self.force_merging.compare_exchange(false, true, Ordering::AcqRel, Ordering::Acquire).is_err() {
+            return Err(LuceneError::IllegalState("a force merge is already running".to_string()).into());
+        }
+        Ok(ForceMergeGuard(&self.force_merging))
+    }
+
+    /// Replaces adjacent flushed segments with a single segment holding their live documents, or removes them if
+    /// they have none, adding the statistics of the merge to `stats`. The segments are read without holding the
+    /// lock, so flushes can proceed meanwhile.
+    fn merge(&self, merging: &[Arc<dyn LeafReader>], stats: &mut MergeStats) -> BoxResult<()> {
+        let listener = self.config.event_listener();
+        let started = listener.is_some().then(Instant::now);
+        let input_ram_bytes_used = merging.iter().map(|segment| segment.ram_bytes_used()).sum();
+        if let Some(listener) = listener {
+            listener.on_merge_start(&MergeStartEvent {
+                segments: merging,
+                num_docs: merging.iter().map(|segment| segment.num_docs() as u64).sum(),
+                ram_bytes_used: input_ram_bytes_used,
+            });
+        }
+
+        let name = self.new_segment_name();
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!(
+            "merge",
+            segment = name,
+            merging =
+                merging.iter().map(|segment| segment.segment_name().unwrap_or_default()).collect::<Vec<_>>().join(" ")
+        )
+        .entered();
+
+        let mut builder = MemorySegmentBuilder::new(self.config.indexing_analyzer());
+        builder.set_name(name);
+        builder.set_similarity(self.config.similarity().clone());
+        builder.set_terms_formats(self.config.terms_formats().clone());
+        builder.set_seed_vector_graphs(self.config.seed_merged_vector_graphs());
+        let mut merge_stats = self.new_merge_stats();
+        let mut num_docs = 0;
+        for segment in merging {
+            num_docs += builder.add_reader_with_stats(segment.as_ref(), &mut merge_stats)?;
+        }
+
+        let merged =
+            (num_docs > 0).then(|| Arc::new(builder.build_with_stats(&mut merge_stats)) as Arc<dyn LeafReader>);
+        merge_stats.merges += 1;
+        stats.add(&merge_stats);
+        if let Some(merged) = &merged {
+            self.warm(merged);
+        }
+
+        // Segments are only ever appended outside of force merges, so the merged ones are still adjacent.
+        let mut segments = self.segments.lock().unwrap();
+        let start = segments
+            .iter()
+            .position(|segment| Arc::ptr_eq(segment, &merging[0]))
+            .expect("segments are only removed by force merges");
+        segments.splice(start..start + merging.len(), merged.clone());
+        drop(segments);
+
+        if let (Some(listener), Some(started)) = (listener, started) {
+            listener.on_merge_finish(&MergeFinishEvent {
+                segments: merging,
+                merged: merged.as_ref(),
+                input_ram_bytes_used,
+                ram_bytes_used: merged.as_ref().map_or(0, |merged| merged.ram_bytes_used()),
+                stats: &merge_stats,
+                elapsed: started.elapsed(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Returns empty merge statistics, timed if the configuration asks for it.
+    fn new_merge_stats(&self) -> MergeStats {
+        if self.config.merge_timing() {
+            MergeStats::timed()
+        } else {
+            MergeStats::default()
+        }
+    }
+
+    fn checkout(&self) -> DocumentsWriterPerThread {
+        // Reuse the largest idle writer, so that segments grow toward the flush size.
+        let mut idle = self.idle.lock().unwrap();
+        let largest = (0..idle.len()).max_by_key(|&i| idle[i].ram_bytes_used());
+        match largest {
+            Some(i) => idle.swap_remove(i),
+            None => {
+                drop(idle);
+                DocumentsWriterPerThread::new(self.next_id.fetch_add(1, Ordering::Relaxed), &self.config)
+            }
+        }
+    }
+
+    fn checkin(&self, dwpt: DocumentsWriterPerThread) {
+        self.idle.lock().unwrap().push(dwpt);
+    }
+
+    /// Builds the segment of a writer on the calling thread. The writer's id is reused by a fresh writer.
+    fn flush_dwpt(&self, dwpt: DocumentsWriterPerThread) {
+        self.ram_bytes_used.fetch_sub(dwpt.ram_bytes_used(), Ordering::Relaxed);
+        self.num_buffered_docs.fetch_sub(dwpt.num_docs() as usize, Ordering::Relaxed);
+
+        let fresh = DocumentsWriterPerThread::new(dwpt.id, &self.config);
+        let listener = self.config.event_listener();
+        let start = listener.is_some().then(Instant::now);
+        let mut builder = dwpt.builder;
+        builder.set_name(self.new_segment_name());
+        let segment: Arc<dyn LeafReader> = Arc::new(builder.build());
+        let elapsed = start.map(|start| start.elapsed());
+        self.warm(&segment);
+        self.segments.lock().unwrap().push(segment.clone());
+        self.checkin(fresh);
+
+        if let (Some(listener), Some(elapsed)) = (listener, elapsed) {
+            listener.on_flush(&FlushEvent {
+                segment: &segment,
+                num_docs: segment.max_doc(),
+                ram_bytes_used: segment.ram_bytes_used(),
+                elapsed,
+            });
+        }
+    }
+
+    /// Warms a new segment with the configured warmer, if any, before it's published. Warming only speeds up the
+    /// first searches, so a failure is logged rather than losing the segment.
+    fn warm(&self, segment: &Arc<dyn LeafReader>) {
+        let Some(warmer) = self.config.segment_warmer() else {
+            return;
+        };
+        if let Err(e) = warmer.warm(segment) {
+            warn!("Failed to warm a segment of {} documents: {e}", segment.max_doc());
+        }
+    }
+}
+
+/// Marks a [DocumentsWriter] as running a force merge until it is dropped.
+struct ForceMergeGuard<'a>(&'a AtomicBool);
+
+impl Drop for ForceMergeGuard<'_> {
+    fn drop(&mut self) {
+        self.0.store(false, Ordering::Release);
+    }
+}
+
+impl Debug for DocumentsWriter {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        f.debug_struct("DocumentsWriter")
+            .field("num_buffered_docs", &self.num_buffered_docs())
+            .field("ram_bytes_used", &self.ram_bytes_used())
+            .field("segments", &self.segments.lock().unwrap().len())
+            .field("force_merging", &self.is_force_merging())
+            .field("open", &self.is_open())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{
+            document::{Document, Field, Store},
+            index::{
+                DocumentsWriter, IndexReader, IndexWriterConfig, MergeStats, MultiReader, SegmentReader, Term,
+                VectorSimilarityFunction,
+            },
+            search::{test_util::leaf_searcher, IndexSearcher, TermQuery},
+            util::{BitSet, FixedBitSet},
+            LuceneError,
+        },
+        pretty_assertions::assert_eq,
+        std::{sync::Arc, thread, time::Duration},
+    };
+
+    #[cfg(feature = "tracing")]
+    use crate::search::test_util::SpanRecorder;
+
+    #[test]
+    fn test_concurrent_add_document() {
+        let mut config = IndexWriterConfig::new();
+        config.set_ram_buffer_size_mb(0.01).unwrap();
+        let writer = Arc::new(DocumentsWriter::new(config));
+
+        thread::scope(|scope| {
+            for t in ["alpha", "beta", "gamma", "delta"] {
+                let writer = writer.clone();
+                scope.spawn(move || {
+                    for _ in 0..250 {
+                        let mut document = Document::new();
+                        document.add(Field::text("body", format!("{t} quick fox"), Store::No));
+                        writer.add_document(&document).unwrap();
+                    }
+                });
+            }
+        });
+
+        assert!(writer.num_thread_states() >= 1 && writer.num_thread_states() <= 4);
+        let flushed: u32 = writer.segments().iter().map(|segment| segment.max_doc()).sum();
+        assert!(writer.segments().len() > 1);
+        assert_eq!(flushed as usize + writer.num_buffered_docs(), 1000);
+
+        writer.flush().unwrap();
+        assert_eq!(writer.num_buffered_docs(), 0);
+        assert_eq!(writer.ram_bytes_used(), 0);
+
+        let reader = MultiReader::new(writer.segments()).unwrap();
+        assert_eq!(reader.max_doc(), 1000);
+        let searcher = IndexSearcher::new(Arc::new(reader));
+        assert_eq!(searcher.count(&TermQuery::new(Term::new("body", "fox"))).unwrap(), 1000);
+        assert_eq!(searcher.count(&TermQuery::new(Term::new("body", "beta"))).unwrap(), 250);
+
+        writer.close();
+        let err = writer.add_document(&Document::new()).unwrap_err();
+        assert!(matches!(err.downcast_ref::<LuceneError>(), Some(LuceneError::AlreadyClosed(_))));
+    }
+
+    #[test]
+    fn test_force_merge() {
+        let writer = DocumentsWriter::new(IndexWriterConfig::new());
+        for i in 0..10 {
+            let mut document = Document::new();
+            document.add(Field::string("id", i.to_string(), Store::Yes));
+            document.add(Field::text(
+                "body",
+                if i % 2 == 0 {
+                    "even"
+                } else {
+                    "odd"
+                },
+                Store::No,
+            ));
+            writer.add_document(&document).unwrap();
+            if i % 2 == 1 {
+                writer.flush().unwrap();
+            }
+        }
+        assert_eq!(writer.segments().len(), 5);
+        assert!(writer.force_merge(0).is_err());
+
+        let stats = writer.force_merge(3).unwrap();
+        assert_eq!((stats.merges, stats.segments, stats.docs), (1, 3, 6));
+        // Merges are only timed when the configuration asks for it.
+        assert!(!stats.is_timed());
+        assert_eq!(stats.elapsed(), Duration::ZERO);
+        assert_eq!(writer.segments().iter().map(|segment| segment.max_doc()).collect::<Vec<_>>(), vec![6, 2, 2]);
+
+        writer.force_merge(1).unwrap();
+        let segments = writer.segments();
+        assert_eq!(segments.len(), 1);
+        let ids: Vec<String> =
+            (0..10).map(|doc| segments[0].document(doc).unwrap().get("id").unwrap().to_string()).collect();
+        assert_eq!(ids, (0..10).map(|i| i.to_string()).collect::<Vec<_>>());
+        let searcher = leaf_searcher(segments);
+        assert_eq!(searcher.count(&TermQuery::new(Term::new("body", "odd"))).unwrap(), 5);
+
+        // A segment with too many deletes is rewritten without them.
+        let mut live_docs = FixedBitSet::new(10);
+        live_docs.set_range(0, 10);
+        live_docs.clear(3);
+        let with_deletes = SegmentReader::new(writer.segments()[0].clone(), Some(live_docs)).unwrap();
+        writer.add_segment(Arc::new(with_deletes)).unwrap();
+        assert_eq!(writer.force_merge_deletes(10.0).unwrap(), MergeStats::default());
+        assert_eq!(writer.segments()[1].max_doc(), 10);
+        assert_eq!(writer.force_merge_deletes(5.0).unwrap().docs, 9);
+        assert_eq!(writer.segments()[1].max_doc(), 9);
+        assert_eq!(writer.segments()[1].document(3).unwrap().get("id"), Some("4"));
+        assert!(writer.force_merge_deletes(101.0).is_err());
+
+        // Only one force merge can run at a time.
+        let _guard = writer.begin_force_merge().unwrap();
+        let err = writer.force_merge(1).unwrap_err();
+        assert!(matches!(err.downcast_ref::<LuceneError>(), Some(LuceneError::IllegalState(_))));
+    }
+
+    #[test]
+    fn test_delete_documents() {
+        let writer = DocumentsWriter::new(IndexWriterConfig::new());
+        for i in 0..6 {
+            let mut document = Document::new();
+            document.add(Field::string("id", i.to_string(), Store::Yes));
+            writer.add_document(&document).unwrap();
+            if i == 2 {
+                writer.flush().unwrap();
+            }
+        }
+
+        // Buffered documents are flushed, so they can be deleted too.
+        let terms = [Term::new("id", "1"), Term::new("id", "4"), Term::new("id", "9"), Term::new("body", "1")];
+        assert_eq!(writer.delete_documents(&terms).unwrap(), 2);
+        assert_eq!(writer.num_buffered_docs(), 0);
+        assert_eq!(writer.segments().iter().map(|segment| segment.num_docs()).collect::<Vec<_>>(), vec![2, 2]);
+        assert_eq!(writer.delete_documents(&terms).unwrap(), 0);
+        assert_eq!(writer.delete_documents(&[Term::new("id", "0")]).unwrap(), 1);
+
+        let searcher = IndexSearcher::new(Arc::new(MultiReader::new(writer.segments()).unwrap()));
+        assert_eq!(searcher.count(&TermQuery::new(Term::new("id", "0"))).unwrap(), 0);
+        assert_eq!(searcher.count(&TermQuery::new(Term::new("id", "2"))).unwrap(), 1);
+
+        // Merging drops the deleted documents, and a segment without live documents is dropped.
+        writer.force_merge(1).unwrap();
+        let segments = writer.segments();
+        let ids: Vec<String> =
+            (0..3).map(|doc| segments[0].document(doc).unwrap().get("id").unwrap().to_string()).collect();
+        assert_eq!((segments[0].max_doc(), ids), (3, vec!["2".to_string(), "3".to_string(), "5".to_string()]));
+        let ids = ["2", "3", "5"].map(|id| Term::new("id", id));
+        assert_eq!(writer.delete_documents(&ids).unwrap(), 3);
+        assert!(writer.segments().is_empty());
+
+        let _guard = writer.begin_force_merge().unwrap();
+        let err = writer.delete_documents(&terms).unwrap_err();
+        assert!(matches!(err.downcast_ref::<LuceneError>(), Some(LuceneError::IllegalState(_))));
+    }
+
+    #[test]
+    fn test_force_merge_vectors() {
+        let vector = |i: u32| {
+            let angle = i as f32 / 10.0;
+            vec![angle.cos(), angle.sin(), i as f32 / 100.0]
+        };
+
+        for seed in [true, false] {
+            let mut config = IndexWriterConfig::new();
+            config.set_seed_merged_vector_graphs(seed).set_merge_timing(true);
+            let writer = DocumentsWriter::new(config);
+            let mut i = 0;
+            for size in [60, 30, 10] {
+                for _ in 0..size {
+                    let mut document = Document::new();
+                    document.add(Field::string("id", i.to_string(), Store::Yes));
+                    let field = Field::knn_vector("embedding", vector(i), VectorSimilarityFunction::Euclidean);
+                    document.add(field.unwrap());
+                    writer.add_document(&document).unwrap();
+                    i += 1;
+                }
+                writer.flush().unwrap();
+            }
+            let largest = writer.segments()[0].float_vector_values("embedding").unwrap().unwrap().graph().clone();
+            assert_eq!(largest.size(), 60);
+
+            // The merged graph starts from that of the largest segment, whose nodes keep their levels.
+            let stats = writer.force_merge(1).unwrap();
+            assert!(stats.is_timed());
+            assert_eq!(stats.seeded_vector_graphs, seed as usize);
+            assert!(stats.vectors > Duration::ZERO);
+            assert!(stats.elapsed() >= stats.vectors + stats.build);
+
+            let merged = writer.segments()[0].clone();
+            let values = merged.float_vector_values("embedding").unwrap().unwrap();
+            assert_eq!((values.size(), values.graph().size()), (100, 100));
+            if seed {
+                for level in 1..largest.num_levels() {
+                    let nodes: Vec<u32> =
+                        values.graph().nodes_on_level(level).into_iter().filter(|&node| node < 60).collect();
+                    assert_eq!(nodes, largest.nodes_on_level(level));
+                }
+            }
+            for i in [0, 42, 75, 99] {
+                assert_eq!(values.search(&vector(i), 1, &|_| true).unwrap(), vec![(i, 1.0)]);
+            }
+
+            // A segment with deletions can't seed the graph, and the deleted vectors are dropped.
+            let mut live_docs = FixedBitSet::new(100);
+            live_docs.set_range(0, 100);
+            live_docs.clear(42);
+            writer.add_segment(Arc::new(SegmentReader::new(merged, Some(live_docs)).unwrap())).unwrap();
+            let stats = writer.force_merge_deletes(0.0).unwrap();
+            assert_eq!((stats.docs, stats.seeded_vector_graphs), (99, 0));
+            let rewritten = writer.segments()[1].clone();
+            let values = rewritten.float_vector_values("embedding").unwrap().unwrap();
+            assert_eq!(values.size(), 99);
+            assert_eq!(values.search(&vector(42), 1, &|_| true).unwrap()[0].0, 41);
+        }
+    }
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn test_tracing_spans() {
+        let recorder = SpanRecorder::default();
+        let writer = DocumentsWriter::new(IndexWriterConfig::new());
+        recorder.record(|| {
+            for i in 0..3 {
+                let mut document = Document::new();
+                document.add(Field::string("id", i.to_string(), Store::Yes));
+                document.add(Field::text("body", "quick fox", Store::No));
+                writer.add_document(&document).unwrap();
+                if i == 1 {
+                    writer.flush().unwrap();
+                }
+            }
+            writer.force_merge(1).unwrap();
+            writer.delete_documents(&[Term::new("id", "0")]).unwrap();
+            writer.force_merge_deletes(0.0).unwrap();
+            leaf_searcher(writer.segments()).search(&TermQuery::new(Term::new("body", "fox")), 10).unwrap();
+        });
+
+        // Segments are named in the order they are flushed or merged, and the spans of a merge name both sides.
+        assert_eq!(recorder.spans("force_merge"), ["max_num_segments=1"]);
+        assert_eq!(recorder.spans("force_merge_deletes"), ["pct_allowed=0.0"]);
+        assert_eq!(recorder.spans("merge"), ["segment=_2 merging=_0 _1", "segment=_3 merging=_2"]);
+        assert_eq!(
+            recorder.spans("flush"),
+            ["segment=_0 docs=2", "segment=_1 docs=1", "segment=_2 docs=3", "segment=_3 docs=2"]
+        );
+        assert_eq!(recorder.spans("score_segment"), ["segment=_3 doc_base=0 max_doc=2"]);
+    }
+}
@@ -0,0 +1,306 @@
+use {
+    crate::{
+        document::Document,
+        index::{
+            BinaryDocValues, CacheHelper, DocValuesSkipper, DocValuesType, FloatVectorValues, IndexCommit, IndexReader,
+            LeafReader, LeafReaderContext, NumericDocValues, TermVectors, Terms,
+        },
+        search::{check_timeout, DocIdSetIterator, QueryTimeout, Sort},
+        util::{Accountable, FixedBitSet, NamedAccountable},
+        BoxResult,
+    },
+    std::sync::Arc,
+};
+
+/// The number of doc values calls between timeout checks.
+const DOCS_BETWEEN_TIMEOUT_CHECK: u32 = 1000;
+
+/// An [IndexReader] that fails with [crate::LuceneError::SearchAborted] once a [QueryTimeout] expires.
+///
+/// Every access to terms, norms and stored fields checks the timeout, and doc values check it every
+/// [DOCS_BETWEEN_TIMEOUT_CHECK] documents. This bounds the time spent outside of scoring, such as rewriting a
+/// multi-term query over a large term dictionary; [crate::search::IndexSearcher::set_timeout] bounds scoring itself.
+#[derive(Debug)]
+pub struct ExitableIndexReader {
+    inner: Arc<dyn IndexReader>,
+    leaves: Vec<LeafReaderContext>,
+}
+
+impl ExitableIndexReader {
+    /// Wraps every segment of `inner` so that reads fail once `timeout` expires.
+    pub fn new(inner: Arc<dyn IndexReader>, timeout: Arc<dyn QueryTimeout>) -> Self {
+        let leaves = inner
+            .leaves()
+            .iter()
+            .map(|leaf| {
+                let reader = ExitableLeafReader::new(leaf.reader_arc().clone(), timeout.clone());
+                LeafReaderContext::new(leaf.ord(), leaf.doc_base(), Arc::new(reader))
+            })
+            .collect();
+
+        Self {
+            inner,
+            leaves,
+        }
+    }
+
+    /// Returns the wrapped reader.
+    #[inline]
+    pub fn inner(&self) -> &Arc<dyn IndexReader> {
+        &self.inner
+    }
+}
+
+impl IndexReader for ExitableIndexReader {
+    #[inline]
+    fn leaves(&self) -> &[LeafReaderContext] {
+        &self.leaves
+    }
+
+    #[inline]
+    fn index_commit(&self) -> Option<&IndexCommit> {
+        self.inner.index_commit()
+    }
+}
+
+impl Accountable for ExitableIndexReader {
+    fn ram_bytes_used(&self) -> usize {
+        self.inner.ram_bytes_used()
+    }
+
+    fn child_resources(&self) -> Vec<NamedAccountable> {
+        self.inner.child_resources()
+    }
+}
+
+/// A [LeafReader] that fails with [crate::LuceneError::SearchAborted] once a [QueryTimeout] expires.
+#[derive(Debug)]
+pub struct ExitableLeafReader {
+    inner: Arc<dyn LeafReader>,
+    timeout: Arc<dyn QueryTimeout>,
+}
+
+impl ExitableLeafReader {
+    /// Wraps `inner` so that reads fail once `timeout` expires.
+    pub fn new(inner: Arc<dyn LeafReader>, timeout: Arc<dyn QueryTimeout>) -> Self {
+        Self {
+            inner,
+            timeout,
+        }
+    }
+}
+
+impl LeafReader for ExitableLeafReader {
+    #[inline]
+    fn max_doc(&self) -> u32 {
+        self.inner.max_doc()
+    }
+
+    #[inline]
+    fn num_docs(&self) -> u32 {
+        self.inner.num_docs()
+    }
+
+    #[inline]
+    fn live_docs(&self) -> Option<&FixedBitSet> {
+        self.inner.live_docs()
+    }
+
+    fn indexed_fields(&self) -> Vec<&str> {
+        self.inner.indexed_fields()
+    }
+
+    fn doc_values_fields(&self) -> Vec<(&str, DocValuesType)> {
+        self.inner.doc_values_fields()
+    }
+
+    fn terms(&self, field: &str) -> BoxResult<Option<&dyn Terms>> {
+        check_timeout(self.timeout.as_ref())?;
+        self.inner.terms(field)
+    }
+
+    fn norms(&self, field: &str) -> BoxResult<Option<Arc<[i64]>>> {
+        check_timeout(self.timeout.as_ref())?;
+        self.inner.norms(field)
+    }
+
+    fn numeric_doc_values(&self, field: &str) -> BoxResult<Option<Box<dyn NumericDocValues>>> {
+        check_timeout(self.timeout.as_ref())?;
+        Ok(self.inner.numeric_doc_values(field)?.map(|inner| {
+            Box::new(ExitableNumericDocValues {
+                inner,
+                sampler: TimeoutSampler::new(self.timeout.clone()),
+            }) as Box<dyn NumericDocValues>
+        }))
+    }
+
+    fn doc_values_skipper(&self, field: &str) -> BoxResult<Option<Box<dyn DocValuesSkipper>>> {
+        check_timeout(self.timeout.as_ref())?;
+        self.inner.doc_values_skipper(field)
+    }
+
+    fn binary_doc_values(&self, field: &str) -> BoxResult<Option<Box<dyn BinaryDocValues>>> {
+        check_timeout(self.timeout.as_ref())?;
+        Ok(self.inner.binary_doc_values(field)?.map(|inner| {
+            Box::new(ExitableBinaryDocValues {
+                inner,
+                sampler: TimeoutSampler::new(self.timeout.clone()),
+            }) as Box<dyn BinaryDocValues>
+        }))
+    }
+
+    fn document(&self, doc: u32) -> BoxResult<Document> {
+        check_timeout(self.timeout.as_ref())?;
+        self.inner.document(doc)
+    }
+
+    fn term_vectors(&self, doc: u32) -> BoxResult<Option<TermVectors>> {
+        check_timeout(self.timeout.as_ref())?;
+        self.inner.term_vectors(doc)
+    }
+
+    fn vector_fields(&self) -> Vec<&str> {
+        self.inner.vector_fields()
+    }
+
+    fn float_vector_values(&self, field: &str) -> BoxResult<Option<&FloatVectorValues>> {
+        check_timeout(self.timeout.as_ref())?;
+        self.inner.float_vector_values(field)
+    }
+
+    #[inline]
+    fn index_sort(&self) -> Option<&Sort> {
+        self.inner.index_sort()
+    }
+
+    #[inline]
+    fn segment_name(&self) -> Option<&str> {
+        self.inner.segment_name()
+    }
+
+    #[inline]
+    fn core_cache_helper(&self) -> Option<&CacheHelper> {
+        self.inner.core_cache_helper()
+    }
+
+    #[inline]
+    fn reader_cache_helper(&self) -> Option<&CacheHelper> {
+        self.inner.reader_cache_helper()
+    }
+}
+
+impl Accountable for ExitableLeafReader {
+    fn ram_bytes_used(&self) -> usize {
+        self.inner.ram_bytes_used()
+    }
+
+    fn child_resources(&self) -> Vec<NamedAccountable> {
+        self.inner.child_resources()
+    }
+}
+
+/// Checks the timeout every [DOCS_BETWEEN_TIMEOUT_CHECK] calls.
+#[derive(Debug)]
+struct TimeoutSampler {
+    timeout: Arc<dyn QueryTimeout>,
+    calls: u32,
+}
+
+impl TimeoutSampler {
+    fn new(timeout: Arc<dyn QueryTimeout>) -> Self {
+        Self {
+            timeout,
+            calls: 0,
+        }
+    }
+
+    fn check(&mut self) -> BoxResult<()> {
+        self.calls += 1;
+        if self.calls.is_multiple_of(DOCS_BETWEEN_TIMEOUT_CHECK) {
+            check_timeout(self.timeout.as_ref())?;
+        }
+        Ok(())
+    }
+}
+
+/// [NumericDocValues] that check the timeout every [DOCS_BETWEEN_TIMEOUT_CHECK] calls that move to a document.
+#[derive(Debug)]
+struct ExitableNumericDocValues {
+    inner: Box<dyn NumericDocValues>,
+    sampler: TimeoutSampler,
+}
+
+impl DocIdSetIterator for ExitableNumericDocValues {
+    #[inline]
+    fn doc_id(&self) -> u32 {
+        self.inner.doc_id()
+    }
+
+    fn next_doc(&mut self) -> BoxResult<u32> {
+        self.sampler.check()?;
+        self.inner.next_doc()
+    }
+
+    fn advance(&mut self, target: u32) -> BoxResult<u32> {
+        self.sampler.check()?;
+        self.inner.advance(target)
+    }
+
+    #[inline]
+    fn cost(&self) -> u64 {
+        self.inner.cost()
+    }
+}
+
+impl NumericDocValues for ExitableNumericDocValues {
+    fn advance_exact(&mut self, target: u32) -> BoxResult<bool> {
+        self.sampler.check()?;
+        self.inner.advance_exact(target)
+    }
+
+    #[inline]
+    fn long_value(&self) -> BoxResult<i64> {
+        self.inner.long_value()
+    }
+}
+
+/// [BinaryDocValues] that check the timeout every [DOCS_BETWEEN_TIMEOUT_CHECK] calls that move to a document.
+#[derive(Debug)]
+struct ExitableBinaryDocValues {
+    inner: Box<dyn BinaryDocValues>,
+    sampler: TimeoutSampler,
+}
+
+impl DocIdSetIterator for ExitableBinaryDocValues {
+    #[inline]
+    fn doc_id(&self) -> u32 {
+        self.inner.doc_id()
+    }
+
+    fn next_doc(&mut self) -> BoxResult<u32> {
+        self.sampler.check()?;
+        self.inner.next_doc()
+    }
+
+    fn advance(&mut self, target: u32) -> BoxResult<u32> {
+        self.sampler.check()?;
+        self.inner.advance(target)
+    }
+
+    #[inline]
+    fn cost(&self) -> u64 {
+        self.inner.cost()
+    }
+}
+
+impl BinaryDocValues for ExitableBinaryDocValues {
+    fn advance_exact(&mut self, target: u32) -> BoxResult<bool> {
+        self.sampler.check()?;
+        self.inner.advance_exact(target)
+    }
+
+    #[inline]
+    fn binary_value(&self) -> BoxResult<&[u8]> {
+        self.inner.binary_value()
+    }
+}
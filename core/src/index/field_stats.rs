@@ -0,0 +1,306 @@
+use {
+    crate::{
+        codec::{get_codec, Codec, CompoundDirectory},
+        index::{SegmentCommitInfo, SegmentIndex, SegmentInfo, TermStats, TermVectors},
+        io::{Directory, IoContext},
+        util::{BitSet, FixedBitSet},
+        BoxResult, LuceneError,
+    },
+    std::{
+        collections::{BTreeMap, HashMap, HashSet},
+        fmt::{Display, Formatter, Result as FmtResult},
+        sync::Arc,
+    },
+};
+
+/// The per-field data of a segment that can be read back from disk: its live docs, norms and term vectors.
+///
+/// lucene-core has no readers for field infos, postings or stored fields yet, so this is all that is known about the
+/// fields of a segment on disk. Fields that are indexed without norms or term vectors don't appear in it.
+#[derive(Debug, Default)]
+pub struct SegmentFieldData {
+    /// The live docs of the segment, or `None` if none of its documents were deleted.
+    pub live_docs: Option<FixedBitSet>,
+
+    /// The norms of each field that has them, indexed by document id.
+    pub norms: HashMap<String, Arc<[i64]>>,
+
+    /// The term vectors of each document, in document order. Empty if the segment has no term vectors.
+    pub term_vectors: Vec<TermVectors>,
+}
+
+impl SegmentFieldData {
+    /// Reads the per-field data of a segment of the latest commit of `directory` with the segment's codec. The files
+    /// of compound segments are read from their compound file.
+    pub async fn read<D: Directory>(directory: &mut D, commit_info: &SegmentCommitInfo) -> BoxResult<Self> {
+        let info = commit_info.get_segment_info();
+        let codec = segment_codec(info)?;
+        let context = IoContext::Read;
+
+        let mut data = Self::default();
+        if commit_info.has_deletions() {
+            data.live_docs = Some(codec.live_docs_format().read_live_docs(directory, commit_info, &context).await?);
+        }
+
+        // Live docs are written next to the compound file rather than into it, so they are read first.
+        let mut compound;
+        let (directory, files): (&mut dyn Directory, HashSet<String>) = if info.is_compound_file() {
+            compound = CompoundDirectory::open(directory, codec.compound_format().as_ref(), info, &context).await?;
+            let files = compound.entries().map(|(name, _)| name).collect();
+            (&mut compound, files)
+        } else {
+            (directory, info.get_files().clone())
+        };
+        let has_files = |names: Vec<String>| names.iter().all(|name| files.contains(name));
+
+        let norms_format = codec.norms_format();
+        if has_files(norms_format.files(info)) {
+            data.norms = norms_format.read_norms(directory, info, &context).await?;
+        }
+        let term_vectors_format = codec.term_vectors_format();
+        if has_files(term_vectors_format.files(info)) {
+            data.term_vectors = term_vectors_format.read_term_vectors(directory, info, &context).await?;
+        }
+        Ok(data)
+    }
+
+    /// Indicates whether a document of the segment is live.
+    #[inline]
+    pub fn is_live(&self, doc: u32) -> bool {
+        self.live_docs.as_ref().is_none_or(|live_docs| live_docs.get(doc))
+    }
+}
+
+/// Returns the codec that wrote a segment.
+pub(crate) fn segment_codec(info: &SegmentInfo) -> BoxResult<Box<dyn Codec>> {
+    let codec_name = info
+        .get_codec_name()
+        .ok_or_else(|| LuceneError::IllegalState(format!("segment {} has no codec name", info.get_name())))?;
+    Ok(get_codec(codec_name)?)
+}
+
+/// The statistics of a field, counted over live documents.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct FieldStats {
+    /// The number of documents with a norm for the field.
+    pub docs_with_norms: u64,
+
+    /// The number of documents with a term vector for the field.
+    pub docs_with_term_vectors: u64,
+
+    /// The statistics of each term of the field's term vectors, counted over the live documents whose term vectors
+    /// hold it, ordered by term.
+    pub terms: BTreeMap<Vec<u8>, TermStats>,
+}
+
+impl FieldStats {
+    /// Returns the number of occurrences of all terms of the field's term vectors.
+    pub fn sum_total_term_freq(&self) -> u64 {
+        self.terms.values().map(|stats| stats.total_term_freq).sum()
+    }
+}
+
+/// The statistics of the fields of the latest commit of an index, gathered from the norms and term vectors of its
+/// segments, like the field and term overviews of Luke.
+///
+/// Term statistics only cover fields indexed with term vectors (see [crate::document::Field::with_term_vectors]), as
+/// postings can't be read from disk yet.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct IndexFieldStats {
+    /// The generation of the commit.
+    pub generation: u64,
+
+    /// The number of live documents in the commit.
+    pub num_docs: u64,
+
+    /// The statistics of each field, ordered by name.
+    pub fields: BTreeMap<String, FieldStats>,
+}
+
+impl IndexFieldStats {
+    /// Analyzes the latest commit of the index in the directory.
+    pub async fn analyze<D: Directory>(directory: &mut D) -> BoxResult<Self> {
+        let segment_index = SegmentIndex::open(directory).await?;
+        let mut stats = Self {
+            generation: segment_index.get_generation(),
+            ..Self::default()
+        };
+
+        for commit_info in segment_index.get_segments() {
+            let info = commit_info.get_segment_info();
+            let data = SegmentFieldData::read(directory, commit_info).await?;
+
+            let live_docs = (0..info.get_max_doc()).filter(|&doc| data.is_live(doc));
+            stats.num_docs += live_docs.clone().count() as u64;
+            for (field, norms) in &data.norms {
+                let docs = live_docs.clone().filter(|&doc| norms.get(doc as usize).is_some_and(|&norm| norm != 0));
+                stats.fields.entry(field.clone()).or_default().docs_with_norms += docs.count() as u64;
+            }
+            for doc in live_docs {
+                let Some(term_vectors) = data.term_vectors.get(doc as usize) else {
+                    break;
+                };
+                for (field, term_vector) in term_vectors.iter() {
+                    let field_stats = stats.fields.entry(field.to_string()).or_default();
+                    field_stats.docs_with_term_vectors += 1;
+                    for term in term_vector.terms() {
+                        let term_stats = field_stats.terms.entry(term.term.clone()).or_default();
+                        term_stats.doc_freq += 1;
+                        term_stats.total_term_freq += term.freq as u64;
+                    }
+                }
+            }
+        }
+
+        Ok(stats)
+    }
+}
+
+impl Display for IndexFieldStats {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        writeln!(f, "Commit generation {}, {} docs", self.generation, self.num_docs)?;
+        writeln!(f, "  {:<24} {:>10} {:>12} {:>10} {:>14}", "Field", "Norms", "Term vectors", "Terms", "Term freq")?;
+        for (name, field) in &self.fields {
+            writeln!(
+                f,
+                "  {name:<24} {:>10} {:>12} {:>10} {:>14}",
+                field.docs_with_norms,
+                field.docs_with_term_vectors,
+                field.terms.len(),
+                field.sum_total_term_freq()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{
+            analysis::SimpleAnalyzer,
+            codec::get_codec,
+            document::{Document, Field, Store, TermVectorOptions},
+            index::{
+                FieldStats, IndexFieldStats, LeafReader, MemorySegmentBuilder, SegmentCommitInfo, SegmentFieldData,
+                SegmentIndex, SegmentInfo, TermStats,
+            },
+            io::{ByteBuffersDirectory, Directory, IoContext},
+            util::FixedBitSet,
+            Id, LATEST,
+        },
+        pretty_assertions::assert_eq,
+        std::{
+            collections::{BTreeMap, HashMap, HashSet},
+            sync::Arc,
+        },
+    };
+
+    /// Commits an index with two segments holding the given bodies, indexed with norms and term vectors: one with
+    /// the first document deleted, and one packed into a compound file.
+    async fn create_index(dir: &mut ByteBuffersDirectory, bodies: &[&str]) {
+        let mut builder = MemorySegmentBuilder::new(Arc::new(SimpleAnalyzer));
+        for body in bodies {
+            let mut doc = Document::new();
+            let field = Field::text("body", *body, Store::No);
+            doc.add(field.with_term_vectors(TermVectorOptions::default()).unwrap());
+            doc.add(Field::text("title", "untitled", Store::No));
+            builder.add_document(&doc).unwrap();
+        }
+        let segment = builder.build();
+
+        let codec = get_codec("Lucene95").unwrap();
+        let mut segment_index = SegmentIndex::new(LATEST.major()).unwrap();
+        for is_compound_file in [false, true] {
+            let mut info = SegmentInfo {
+                name: segment_index.new_segment_name(),
+                id: Id::random_id(),
+                max_doc: segment.max_doc(),
+                attributes: HashMap::new(),
+                diagnostics: HashMap::new(),
+                files: HashSet::new(),
+                version: LATEST,
+                min_version: Some(LATEST),
+                is_compound_file,
+                index_sort: None,
+                codec: None,
+            };
+            info.set_codec_name("Lucene95");
+            let context = IoContext::Default;
+            codec.norms_format().write_norms(&segment, dir, &info, &context).await.unwrap();
+            codec.term_vectors_format().write_term_vectors(&segment, dir, &info, &context).await.unwrap();
+            info.files.extend(codec.norms_format().files(&info));
+            info.files.extend(codec.term_vectors_format().files(&info));
+            if is_compound_file {
+                codec.compound_format().write_compound(dir, &info, &context).await.unwrap();
+                for file_name in info.files.drain() {
+                    dir.remove(&file_name).await.unwrap();
+                }
+                info.files.extend(codec.compound_format().files(&info));
+            }
+            codec.segment_info_format().write_segment_info(dir, &info, &IoContext::Default).await.unwrap();
+            segment_index.add_segment(SegmentCommitInfo::new(info, 0, 0, None, None, None, None)).unwrap();
+        }
+
+        let commit_info = &mut segment_index.get_segments_mut()[0];
+        let mut live_docs = FixedBitSet::new(bodies.len() as u32);
+        live_docs.set_range(1, bodies.len() as u32);
+        codec.live_docs_format().write_live_docs(&live_docs, dir, commit_info, 1, &IoContext::Default).await.unwrap();
+        commit_info.advance_del_gen();
+        commit_info.set_del_count(1).unwrap();
+        segment_index.commit(dir).await.unwrap();
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_field_stats() {
+        let mut dir = ByteBuffersDirectory::new();
+        create_index(&mut dir, &["deleted fox", "quick fox", "lazy lazy dog"]).await;
+
+        let stats = IndexFieldStats::analyze(&mut dir).await.unwrap();
+        let term_stats = |doc_freq, total_term_freq| TermStats {
+            doc_freq,
+            total_term_freq,
+        };
+        let body = FieldStats {
+            docs_with_norms: 5,
+            docs_with_term_vectors: 5,
+            terms: BTreeMap::from([
+                (b"deleted".to_vec(), term_stats(1, 1)),
+                (b"dog".to_vec(), term_stats(2, 2)),
+                (b"fox".to_vec(), term_stats(3, 3)),
+                (b"lazy".to_vec(), term_stats(2, 4)),
+                (b"quick".to_vec(), term_stats(2, 2)),
+            ]),
+        };
+        let title = FieldStats {
+            docs_with_norms: 5,
+            ..FieldStats::default()
+        };
+        assert_eq!(
+            stats,
+            IndexFieldStats {
+                generation: 1,
+                num_docs: 5,
+                fields: BTreeMap::from([("body".to_string(), body), ("title".to_string(), title)]),
+            }
+        );
+        assert_eq!(stats.fields["body"].sum_total_term_freq(), 12);
+        assert!(stats
+            .to_string()
+            .contains("  body                              5            5          5             12\n"));
+
+        let segment_index = SegmentIndex::open(&mut dir).await.unwrap();
+        let data = SegmentFieldData::read(&mut dir, &segment_index.get_segments()[0]).await.unwrap();
+        assert!(!data.is_live(0));
+        assert!(data.is_live(2));
+        assert_eq!(data.term_vectors[2].get("body").unwrap().size(), 2);
+
+        // The compound segment's norms and term vectors are read from its compound file.
+        let data = SegmentFieldData::read(&mut dir, &segment_index.get_segments()[1]).await.unwrap();
+        assert!(data.is_live(0));
+        assert_eq!(data.norms["title"].len(), 3);
+        assert_eq!(data.term_vectors[0].get("body").unwrap().size(), 2);
+    }
+}
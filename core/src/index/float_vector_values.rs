@@ -0,0 +1,161 @@
+use {
+    crate::{
+        index::VectorSimilarityFunction,
+        util::{
+            hnsw::{search_graph, OnHeapHnswGraph},
+            Accountable,
+        },
+        BoxResult, LuceneError,
+    },
+    std::sync::Arc,
+};
+
+/// The vectors of a [crate::document::Field::knn_vector] field in a segment, along with the HNSW graph linking them,
+/// as Lucene's `FloatVectorValues` and `HnswGraph` are read together. Vectors are numbered by ordinal, in increasing
+/// order of the documents that hold them; each document has at most one vector per field.
+#[derive(Clone, Debug)]
+pub struct FloatVectorValues {
+    similarity: VectorSimilarityFunction,
+    dimension: usize,
+    docs: Arc<[u32]>,
+    vectors: Arc<[f32]>,
+    graph: Arc<OnHeapHnswGraph>,
+}
+
+impl FloatVectorValues {
+    /// Creates the values of a field from the documents holding a vector, in increasing order, their vectors of
+    /// `dimension` values each, one after another, and the graph over them.
+    ///
+    /// This fails with [LuceneError::InvalidArgument] if the documents are out of order or don't match the number of
+    /// vectors, or if the graph has a node that isn't one of the vectors.
+    pub fn new(
+        similarity: VectorSimilarityFunction,
+        dimension: usize,
+        docs: Arc<[u32]>,
+        vectors: Arc<[f32]>,
+        graph: Arc<OnHeapHnswGraph>,
+    ) -> BoxResult<Self> {
+        if dimension == 0 || vectors.len() != docs.len() * dimension {
+            return Err(LuceneError::InvalidArgument(format!(
+                "{} values don't hold {} vectors of {dimension} dimensions",
+                vectors.len(),
+                docs.len()
+            ))
+            .into());
+        }
+        if docs.windows(2).any(|pair| pair[0] >= pair[1]) {
+            return Err(LuceneError::InvalidArgument("vector documents are not in increasing order".into()).into());
+        }
+        if graph.nodes_on_level(0).last().is_some_and(|&node| node as usize >= docs.len()) {
+            return Err(
+                LuceneError::InvalidArgument(format!("the graph has nodes beyond the {} vectors", docs.len())).into()
+            );
+        }
+
+        Ok(Self {
+            similarity,
+            dimension,
+            docs,
+            vectors,
+            graph,
+        })
+    }
+
+    /// Returns the function vectors are compared with.
+    #[inline]
+    pub fn similarity(&self) -> VectorSimilarityFunction {
+        self.similarity
+    }
+
+    /// Returns the number of values in each vector.
+    #[inline]
+    pub fn dimension(&self) -> usize {
+        self.dimension
+    }
+
+    /// Returns the number of vectors.
+    #[inline]
+    pub fn size(&self) -> usize {
+        self.docs.len()
+    }
+
+    /// Returns the documents holding a vector, indexed by ordinal.
+    #[inline]
+    pub fn docs(&self) -> &[u32] {
+        &self.docs
+    }
+
+    /// Returns the document holding the vector with the given ordinal.
+    #[inline]
+    pub fn ord_to_doc(&self, ord: u32) -> u32 {
+        self.docs[ord as usize]
+    }
+
+    /// Returns the ordinal of the vector of `doc`, or `None` if it has none.
+    #[inline]
+    pub fn doc_to_ord(&self, doc: u32) -> Option<u32> {
+        self.docs.binary_search(&doc).ok().map(|ord| ord as u32)
+    }
+
+    /// Returns the vector with the given ordinal.
+    #[inline]
+    pub fn vector(&self, ord: u32) -> &[f32] {
+        &self.vectors[ord as usize * self.dimension..(ord as usize + 1) * self.dimension]
+    }
+
+    /// Returns every vector, one after another, in order of ordinal.
+    #[inline]
+    pub fn vectors(&self) -> &[f32] {
+        &self.vectors
+    }
+
+    /// Returns the graph linking the vectors.
+    #[inline]
+    pub fn graph(&self) -> &Arc<OnHeapHnswGraph> {
+        &self.graph
+    }
+
+    /// Returns the similarity of `target` to the vector with the given ordinal.
+    #[inline]
+    pub fn score(&self, target: &[f32], ord: u32) -> f32 {
+        self.similarity.compare(target, self.vector(ord))
+    }
+
+    /// Returns up to `k` documents whose vectors are nearest to `target`, with their scores, highest scores first.
+    /// Only the documents `accept` accepts are returned. Fields with no more than `k` vectors are searched exactly;
+    /// larger ones are searched through the graph, which is approximate.
+    ///
+    /// This fails with [LuceneError::InvalidArgument] if `target` doesn't have [FloatVectorValues::dimension] values.
+    pub fn search(&self, target: &[f32], k: usize, accept: &dyn Fn(u32) -> bool) -> BoxResult<Vec<(u32, f32)>> {
+        if target.len() != self.dimension {
+            return Err(LuceneError::InvalidArgument(format!(
+                "the target has {} dimensions, but the field's vectors have {}",
+                target.len(),
+                self.dimension
+            ))
+            .into());
+        }
+
+        let hits = if self.size() <= k || self.graph.size() < self.size() {
+            let mut hits: Vec<(u32, f32)> = (0..self.size() as u32)
+                .filter(|&ord| accept(self.ord_to_doc(ord)))
+                .map(|ord| (ord, self.score(target, ord)))
+                .collect();
+            hits.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+            hits.truncate(k);
+            hits
+        } else {
+            search_graph(&self.graph, &|ord| self.score(target, ord), k, &|ord| accept(self.ord_to_doc(ord)))
+        };
+        Ok(hits.into_iter().map(|(ord, score)| (self.ord_to_doc(ord), score)).collect())
+    }
+}
+
+impl Accountable for FloatVectorValues {
+    fn ram_bytes_used(&self) -> usize {
+        size_of::<Self>()
+            + size_of_val(self.docs.as_ref())
+            + size_of_val(self.vectors.as_ref())
+            + self.graph.ram_bytes_used()
+    }
+}
@@ -0,0 +1,103 @@
+use {crate::index::IndexWriterConfig, std::fmt::Debug};
+
+/// The state of a [crate::index::DocumentsWriter] after a document was added, for a [FlushPolicy] to decide from.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct FlushState {
+    /// The memory used by every buffered document, in bytes.
+    pub ram_bytes_used: usize,
+
+    /// The number of buffered documents.
+    pub num_buffered_docs: usize,
+
+    /// The memory used by the documents buffered for the segment the document was added to, in bytes.
+    pub segment_ram_bytes_used: usize,
+
+    /// The number of documents buffered for the segment the document was added to, including the new one.
+    pub segment_num_docs: u32,
+}
+
+/// Decides when the documents buffered by a writer are flushed to a new segment, as Lucene's `FlushPolicy` does.
+///
+/// The policy set with [IndexWriterConfig::set_flush_policy] is asked after every added document whether the segment
+/// the document was added to should be flushed; the segment is then built on the adding thread. Policies that flush
+/// on other grounds, such as the time since the last flush when ingesting logs, can keep their own state, as long
+/// as it's safe to use from several threads. Buffered documents are also flushed by
+/// [crate::index::IndexWriter::flush], whatever the policy.
+pub trait FlushPolicy: Debug + Send + Sync {
+    /// Returns whether the segment a document was just added to should be flushed.
+    fn on_insert(&self, config: &IndexWriterConfig, state: &FlushState) -> bool;
+}
+
+/// The default [FlushPolicy], which flushes once the buffered documents use more memory than the RAM buffer size
+/// (see [IndexWriterConfig::set_ram_buffer_size_mb]), or once a segment buffers the maximum number of documents, if
+/// one is set (see [IndexWriterConfig::set_max_buffered_docs]).
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FlushByRamOrCountsPolicy;
+
+impl FlushPolicy for FlushByRamOrCountsPolicy {
+    fn on_insert(&self, config: &IndexWriterConfig, state: &FlushState) -> bool {
+        state.ram_bytes_used > config.ram_buffer_size_bytes()
+            || config.max_buffered_docs().is_some_and(|max| state.segment_num_docs >= max)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{
+            document::{Document, Field, Store},
+            index::{DocumentsWriter, FlushPolicy, FlushState, IndexWriterConfig},
+        },
+        pretty_assertions::assert_eq,
+        std::sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+    };
+
+    fn document() -> Document {
+        let mut document = Document::new();
+        document.add(Field::text("body", "log line", Store::No));
+        document
+    }
+
+    /// Flushes every third call, as a stand-in for a policy flushing on a timer.
+    #[derive(Debug, Default)]
+    struct EveryThirdPolicy {
+        calls: AtomicUsize,
+    }
+
+    impl FlushPolicy for EveryThirdPolicy {
+        fn on_insert(&self, _config: &IndexWriterConfig, state: &FlushState) -> bool {
+            assert!(state.ram_bytes_used >= state.segment_ram_bytes_used);
+            self.calls.fetch_add(1, Ordering::Relaxed) % 3 == 2
+        }
+    }
+
+    #[test]
+    fn test_max_buffered_docs() {
+        let mut config = IndexWriterConfig::new();
+        assert!(config.set_max_buffered_docs(Some(0)).is_err());
+        config.set_max_buffered_docs(Some(4)).unwrap();
+        let writer = DocumentsWriter::new(config);
+
+        let flushes: Vec<bool> = (0..10).map(|_| writer.add_document(&document()).unwrap()).collect();
+        assert_eq!(flushes.iter().filter(|&&flushed| flushed).count(), 2);
+        assert!(flushes[3] && flushes[7]);
+        assert_eq!(writer.segments().iter().map(|segment| segment.max_doc()).collect::<Vec<_>>(), vec![4, 4]);
+        assert_eq!(writer.num_buffered_docs(), 2);
+    }
+
+    #[test]
+    fn test_custom_policy() {
+        let mut config = IndexWriterConfig::new();
+        config.set_flush_policy(Arc::new(EveryThirdPolicy::default()));
+        let writer = DocumentsWriter::new(config);
+
+        for _ in 0..7 {
+            writer.add_document(&document()).unwrap();
+        }
+        assert_eq!(writer.segments().len(), 2);
+        assert_eq!(writer.num_buffered_docs(), 1);
+    }
+}
@@ -0,0 +1,257 @@
+use {
+    crate::{
+        index::{MemoryPosting, MemoryPostingsEnum, PostingsEnum, SeekStatus, TermStats, Terms, TermsEnum},
+        util::{size_of_vec, Accountable, Fst, FstBuilder, FstEnum, NamedAccountable},
+        BoxResult,
+    },
+    std::{
+        collections::{BTreeMap, HashSet},
+        sync::Arc,
+    },
+};
+
+/// A [Terms] implementation whose term dictionary is an [Fst] mapping each term to its ordinal.
+///
+/// Terms sharing prefixes or suffixes share the FST's arcs, so the dictionary is usually smaller than the sorted list
+/// of terms of [crate::index::MemoryTerms], and a seek follows one arc per byte of the target instead of comparing
+/// it against whole terms. The statistics and postings of each term are found by its ordinal.
+#[derive(Clone, Debug, Default)]
+pub struct FstTerms {
+    fst: Fst,
+    stats: Vec<TermStats>,
+    postings: Vec<Arc<[MemoryPosting]>>,
+    doc_count: u32,
+    sum_doc_freq: u64,
+    sum_total_term_freq: u64,
+    has_positions: bool,
+    has_offsets: bool,
+}
+
+impl FstTerms {
+    /// Creates a new set of terms with postings, which must be sorted by document. The terms have offsets if any
+    /// posting does.
+    pub fn from_postings(terms: BTreeMap<Vec<u8>, Vec<MemoryPosting>>, has_positions: bool) -> Self {
+        let mut builder = FstBuilder::new();
+        let mut docs = HashSet::new();
+        let mut result = Self {
+            has_positions,
+            ..Default::default()
+        };
+
+        for (ord, (term, postings)) in terms.into_iter().enumerate() {
+            builder.add(&term, ord as u64).expect("terms are sorted and unique");
+            let stats = TermStats {
+                doc_freq: postings.len() as u32,
+                total_term_freq: postings.iter().map(|p| p.freq as u64).sum(),
+            };
+            docs.extend(postings.iter().map(|p| p.doc));
+            result.has_offsets |= postings.iter().any(|p| !p.offsets.is_empty());
+            result.sum_doc_freq += stats.doc_freq as u64;
+            result.sum_total_term_freq += stats.total_term_freq;
+            result.stats.push(stats);
+            result.postings.push(postings.into());
+        }
+
+        result.fst = builder.finish();
+        result.doc_count = docs.len() as u32;
+        result
+    }
+
+    /// Returns the term dictionary.
+    #[inline]
+    pub fn fst(&self) -> &Fst {
+        &self.fst
+    }
+
+    fn postings_bytes(&self) -> usize {
+        size_of_vec(&self.stats)
+            + size_of_vec(&self.postings)
+            + self
+                .postings
+                .iter()
+                .flat_map(|postings| postings.iter())
+                .map(MemoryPosting::ram_bytes_used)
+                .sum::<usize>()
+    }
+}
+
+impl Accountable for FstTerms {
+    fn ram_bytes_used(&self) -> usize {
+        size_of::<Self>() + self.fst.ram_bytes_used() - size_of::<Fst>() + self.postings_bytes()
+    }
+
+    fn child_resources(&self) -> Vec<NamedAccountable> {
+        vec![
+            NamedAccountable::from_bytes("term index", self.fst.ram_bytes_used() - size_of::<Fst>()),
+            NamedAccountable::from_bytes("postings", self.postings_bytes()),
+        ]
+    }
+}
+
+impl Terms for FstTerms {
+    fn iterator(&self) -> BoxResult<Box<dyn TermsEnum + '_>> {
+        Ok(Box::new(FstTermsEnum {
+            terms: self,
+            fst_enum: FstEnum::new(&self.fst),
+            ord: None,
+        }))
+    }
+
+    #[inline]
+    fn size(&self) -> Option<u64> {
+        Some(self.stats.len() as u64)
+    }
+
+    #[inline]
+    fn sum_total_term_freq(&self) -> u64 {
+        self.sum_total_term_freq
+    }
+
+    #[inline]
+    fn sum_doc_freq(&self) -> u64 {
+        self.sum_doc_freq
+    }
+
+    #[inline]
+    fn doc_count(&self) -> u32 {
+        self.doc_count
+    }
+
+    #[inline]
+    fn has_freqs(&self) -> bool {
+        true
+    }
+
+    #[inline]
+    fn has_positions(&self) -> bool {
+        self.has_positions
+    }
+
+    #[inline]
+    fn has_offsets(&self) -> bool {
+        self.has_offsets
+    }
+}
+
+/// The [TermsEnum] returned by [FstTerms::iterator].
+#[derive(Debug)]
+pub struct FstTermsEnum<'a> {
+    terms: &'a FstTerms,
+    fst_enum: FstEnum<'a>,
+    ord: Option<usize>,
+}
+
+impl FstTermsEnum<'_> {
+    fn current(&self) -> usize {
+        self.ord.expect("TermsEnum is unpositioned")
+    }
+}
+
+impl TermsEnum for FstTermsEnum<'_> {
+    fn next(&mut self) -> BoxResult<Option<&[u8]>> {
+        self.ord = self.fst_enum.next_input().map(|ord| ord as usize);
+        Ok(self.ord.map(|_| self.fst_enum.input()))
+    }
+
+    fn term(&self) -> &[u8] {
+        self.current();
+        self.fst_enum.input()
+    }
+
+    fn seek_ceil(&mut self, target: &[u8]) -> BoxResult<SeekStatus> {
+        self.ord = self.fst_enum.seek_ceil(target).map(|ord| ord as usize);
+        Ok(match self.ord {
+            None => SeekStatus::End,
+            Some(_) if self.fst_enum.input() == target => SeekStatus::Found,
+            Some(_) => SeekStatus::NotFound,
+        })
+    }
+
+    fn seek_exact(&mut self, target: &[u8]) -> BoxResult<bool> {
+        // A lookup is cheaper than a seek, and rules out missing terms without moving the enum's path.
+        if self.terms.fst.get(target).is_none() {
+            self.ord = None;
+            return Ok(false);
+        }
+        Ok(self.seek_ceil(target)? == SeekStatus::Found)
+    }
+
+    fn doc_freq(&self) -> BoxResult<u32> {
+        Ok(self.terms.stats[self.current()].doc_freq)
+    }
+
+    fn total_term_freq(&self) -> BoxResult<u64> {
+        Ok(self.terms.stats[self.current()].total_term_freq)
+    }
+
+    fn postings(&self) -> BoxResult<Box<dyn PostingsEnum>> {
+        Ok(Box::new(MemoryPostingsEnum::new(self.terms.postings[self.current()].clone())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{
+            index::{FstTerms, MemoryPosting, SeekStatus, Terms},
+            search::NO_MORE_DOCS,
+            util::Accountable,
+        },
+        pretty_assertions::assert_eq,
+    };
+
+    #[test]
+    fn test_fst_terms() {
+        let terms = FstTerms::from_postings(
+            [
+                (
+                    b"fox".to_vec(),
+                    vec![MemoryPosting::with_positions(1, vec![0]), MemoryPosting::with_positions(4, vec![1, 5])],
+                ),
+                (b"dog".to_vec(), vec![MemoryPosting::with_positions(2, vec![3])]),
+                (b"dogs".to_vec(), vec![MemoryPosting::with_positions(2, vec![7])]),
+            ]
+            .into_iter()
+            .collect(),
+            true,
+        );
+        assert_eq!(terms.size(), Some(3));
+        assert_eq!(terms.doc_count(), 3);
+        assert_eq!(terms.sum_doc_freq(), 4);
+        assert_eq!(terms.sum_total_term_freq(), 5);
+
+        let mut te = terms.iterator().unwrap();
+        let mut seen = Vec::new();
+        while let Some(term) = te.next().unwrap() {
+            seen.push(String::from_utf8(term.to_vec()).unwrap());
+        }
+        assert_eq!(seen, vec!["dog", "dogs", "fox"]);
+
+        assert_eq!(te.seek_ceil(b"dogg").unwrap(), SeekStatus::NotFound);
+        assert_eq!(te.term(), b"dogs");
+        assert_eq!(te.next().unwrap(), Some(&b"fox"[..]));
+        assert_eq!(te.seek_ceil(b"zebra").unwrap(), SeekStatus::End);
+        assert!(!te.seek_exact(b"cat").unwrap());
+        assert!(te.seek_exact(b"fox").unwrap());
+        assert_eq!(te.doc_freq().unwrap(), 2);
+        assert_eq!(te.total_term_freq().unwrap(), 3);
+
+        let mut postings = te.postings().unwrap();
+        assert_eq!(postings.next_doc().unwrap(), 1);
+        assert_eq!(postings.next_doc().unwrap(), 4);
+        assert_eq!(postings.next_position().unwrap(), Some(1));
+        assert_eq!(postings.next_doc().unwrap(), NO_MORE_DOCS);
+    }
+
+    #[test]
+    fn test_ram_bytes_used() {
+        let terms = FstTerms::from_postings(
+            (0..100).map(|i| (format!("term{i:03}").into_bytes(), vec![MemoryPosting::with_freq(i, 1)])).collect(),
+            false,
+        );
+        let children = terms.child_resources();
+        assert_eq!(children.iter().map(|child| child.name()).collect::<Vec<_>>(), vec!["term index", "postings"]);
+        assert!(children.iter().all(|child| child.ram_bytes_used() > 0));
+        assert!(terms.ram_bytes_used() > children.iter().map(|child| child.ram_bytes_used()).sum());
+    }
+}
@@ -1,5 +1,9 @@
 use {
-    crate::{codec::CodecHeader, BoxResult, Id, LuceneError},
+    crate::{
+        codec::CodecHeader,
+        io::{EncodingWriteExt, MAX_SHORT_STRING_LENGTH},
+        BoxResult, Id, LuceneError,
+    },
     tokio::io::AsyncRead,
 };
 
@@ -11,6 +15,16 @@ pub struct IndexHeader {
 }
 
 impl IndexHeader {
+    /// Creates an index header for the given codec name, version and id.
+    ///
+    /// This returns an error if the codec name is too long or contains invalid characters.
+    pub fn new(codec: &str, version: u32, id: Id) -> Result<Self, LuceneError> {
+        Ok(Self {
+            codec_header: CodecHeader::new(codec, version)?,
+            id,
+        })
+    }
+
     /// The name of the codec used to encode the data.
     #[inline]
     pub fn codec(&self) -> &str {
@@ -44,9 +58,9 @@ impl IndexHeader {
 
         if let Some(expected_id) = expected_id {
             if id != expected_id {
-                return Err(LuceneError::CorruptIndex(format!(
-                    "Index header contained invalid id: got {id}, expected {expected_id}",
-                ))
+                return Err(LuceneError::CorruptIndex(
+                    format!("Index header contained invalid id: got {id}, expected {expected_id}",).into(),
+                )
                 .into());
             }
         }
@@ -58,4 +72,26 @@ impl IndexHeader {
             id,
         })
     }
+
+    /// Writes the index header followed by `suffix`, which distinguishes files of the same codec within a segment
+    /// (such as per-field postings files) and is checked by [IndexHeader::read_from].
+    ///
+    /// IndexHeader --> CodecHeader + Id + Suffix
+    ///
+    /// * CodecHeader: See [CodecHeader::write].
+    /// * Id (16 bytes): The id of the segment or commit the file belongs to.
+    /// * Suffix ([EncodingWriteExt::write_short_string]): At most 255 bytes of ASCII.
+    pub async fn write<W: EncodingWriteExt + Unpin>(&self, w: &mut W, suffix: &str) -> BoxResult<()> {
+        if suffix.len() > MAX_SHORT_STRING_LENGTH || !suffix.is_ascii() {
+            return Err(LuceneError::InvalidArgument(format!(
+                "index header suffix must be ASCII and at most {MAX_SHORT_STRING_LENGTH} bytes: {suffix:?}"
+            ))
+            .into());
+        }
+
+        self.codec_header.write(w).await?;
+        self.id.write_to(w).await?;
+        w.write_short_string(suffix).await?;
+        Ok(())
+    }
 }
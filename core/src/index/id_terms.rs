@@ -0,0 +1,292 @@
+use {
+    crate::{
+        index::{PostingsEnum, SeekStatus, Terms, TermsEnum},
+        search::{DocIdSetIterator, NO_MORE_DOCS},
+        util::{size_of_vec, Accountable},
+        BoxResult, LuceneError,
+    },
+    std::cmp::Ordering,
+};
+
+/// A [Terms] implementation for a field of unique keys, such as document ids, where every term occurs once in a
+/// single document.
+///
+/// The terms are concatenated in byte order into one buffer, with a monotonic array of their start offsets, and are
+/// found by binary search. Since each term has exactly one posting with a frequency of 1, only its document is kept:
+/// there are no per-term statistics, frequencies or positions, which makes the field far smaller than with
+/// [crate::index::MemoryTerms].
+#[derive(Clone, Debug, Default)]
+pub struct IdTerms {
+    bytes: Vec<u8>,
+    offsets: Vec<u32>,
+    docs: Vec<u32>,
+}
+
+impl IdTerms {
+    /// Creates the terms of a field from its keys and the document holding each, in any order. This fails with
+    /// [LuceneError::InvalidArgument] if a key occurs more than once.
+    pub fn new<I: IntoIterator<Item = (Vec<u8>, u32)>>(keys: I) -> BoxResult<Self> {
+        let mut keys: Vec<(Vec<u8>, u32)> = keys.into_iter().collect();
+        keys.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+        if let Some(pair) = keys.windows(2).find(|pair| pair[0].0 == pair[1].0) {
+            return Err(LuceneError::InvalidArgument(format!(
+                "key {:?} occurs in documents {} and {}",
+                String::from_utf8_lossy(&pair[0].0),
+                pair[0].1,
+                pair[1].1
+            ))
+            .into());
+        }
+
+        let mut result = Self {
+            bytes: Vec::with_capacity(keys.iter().map(|(key, _)| key.len()).sum()),
+            offsets: Vec::with_capacity(keys.len() + 1),
+            docs: Vec::with_capacity(keys.len()),
+        };
+        for (key, doc) in keys {
+            result.offsets.push(result.bytes.len() as u32);
+            result.bytes.extend_from_slice(&key);
+            result.docs.push(doc);
+        }
+        result.offsets.push(result.bytes.len() as u32);
+        Ok(result)
+    }
+
+    /// Returns the number of keys.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.docs.len()
+    }
+
+    /// Indicates whether there are no keys.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.docs.is_empty()
+    }
+
+    /// Returns the document holding `key`, or `None` if no document does.
+    pub fn doc(&self, key: &[u8]) -> Option<u32> {
+        self.find(key).ok().map(|ord| self.docs[ord])
+    }
+
+    /// Returns the key at `ord` in byte order.
+    #[inline]
+    fn key(&self, ord: usize) -> &[u8] {
+        &self.bytes[self.offsets[ord] as usize..self.offsets[ord + 1] as usize]
+    }
+
+    /// Binary searches for `key`, returning its ord if found, or the ord it would be inserted at otherwise.
+    fn find(&self, key: &[u8]) -> Result<usize, usize> {
+        let (mut low, mut high) = (0, self.docs.len());
+        while low < high {
+            let mid = low + (high - low) / 2;
+            match self.key(mid).cmp(key) {
+                Ordering::Less => low = mid + 1,
+                Ordering::Greater => high = mid,
+                Ordering::Equal => return Ok(mid),
+            }
+        }
+        Err(low)
+    }
+}
+
+impl Accountable for IdTerms {
+    fn ram_bytes_used(&self) -> usize {
+        size_of::<Self>() + size_of_vec(&self.bytes) + size_of_vec(&self.offsets) + size_of_vec(&self.docs)
+    }
+}
+
+impl Terms for IdTerms {
+    fn iterator(&self) -> BoxResult<Box<dyn TermsEnum + '_>> {
+        Ok(Box::new(IdTermsEnum {
+            terms: self,
+            ord: None,
+        }))
+    }
+
+    #[inline]
+    fn size(&self) -> Option<u64> {
+        Some(self.docs.len() as u64)
+    }
+
+    #[inline]
+    fn sum_total_term_freq(&self) -> u64 {
+        self.docs.len() as u64
+    }
+
+    #[inline]
+    fn sum_doc_freq(&self) -> u64 {
+        self.docs.len() as u64
+    }
+
+    #[inline]
+    fn doc_count(&self) -> u32 {
+        self.docs.len() as u32
+    }
+
+    #[inline]
+    fn has_freqs(&self) -> bool {
+        false
+    }
+
+    #[inline]
+    fn has_positions(&self) -> bool {
+        false
+    }
+}
+
+/// The [TermsEnum] returned by [IdTerms::iterator].
+#[derive(Debug)]
+pub struct IdTermsEnum<'a> {
+    terms: &'a IdTerms,
+    ord: Option<usize>,
+}
+
+impl IdTermsEnum<'_> {
+    fn current(&self) -> usize {
+        self.ord.expect("TermsEnum is unpositioned")
+    }
+}
+
+impl TermsEnum for IdTermsEnum<'_> {
+    fn next(&mut self) -> BoxResult<Option<&[u8]>> {
+        let next = self.ord.map_or(0, |ord| ord + 1);
+        if next >= self.terms.len() {
+            self.ord = Some(self.terms.len());
+            return Ok(None);
+        }
+
+        self.ord = Some(next);
+        Ok(Some(self.terms.key(next)))
+    }
+
+    fn term(&self) -> &[u8] {
+        self.terms.key(self.current())
+    }
+
+    fn seek_ceil(&mut self, target: &[u8]) -> BoxResult<SeekStatus> {
+        match self.terms.find(target) {
+            Ok(ord) => {
+                self.ord = Some(ord);
+                Ok(SeekStatus::Found)
+            }
+            Err(ord) if ord < self.terms.len() => {
+                self.ord = Some(ord);
+                Ok(SeekStatus::NotFound)
+            }
+            Err(_) => {
+                self.ord = None;
+                Ok(SeekStatus::End)
+            }
+        }
+    }
+
+    fn doc_freq(&self) -> BoxResult<u32> {
+        self.current();
+        Ok(1)
+    }
+
+    fn total_term_freq(&self) -> BoxResult<u64> {
+        self.current();
+        Ok(1)
+    }
+
+    fn postings(&self) -> BoxResult<Box<dyn PostingsEnum>> {
+        Ok(Box::new(IdPostingsEnum {
+            doc: self.terms.docs[self.current()],
+            current: None,
+        }))
+    }
+}
+
+/// The [PostingsEnum] returned by [IdTermsEnum::postings]: a single document, with a frequency of 1 and no
+/// positions.
+#[derive(Debug)]
+struct IdPostingsEnum {
+    doc: u32,
+    current: Option<u32>,
+}
+
+impl DocIdSetIterator for IdPostingsEnum {
+    #[inline]
+    fn doc_id(&self) -> u32 {
+        self.current.unwrap_or(NO_MORE_DOCS)
+    }
+
+    fn next_doc(&mut self) -> BoxResult<u32> {
+        self.current = Some(match self.current {
+            None => self.doc,
+            Some(_) => NO_MORE_DOCS,
+        });
+        Ok(self.doc_id())
+    }
+
+    fn advance(&mut self, target: u32) -> BoxResult<u32> {
+        self.current = Some(match self.current {
+            None if self.doc >= target => self.doc,
+            _ => NO_MORE_DOCS,
+        });
+        Ok(self.doc_id())
+    }
+
+    #[inline]
+    fn cost(&self) -> u64 {
+        1
+    }
+}
+
+impl PostingsEnum for IdPostingsEnum {
+    fn freq(&self) -> BoxResult<u32> {
+        Ok(1)
+    }
+
+    fn next_position(&mut self) -> BoxResult<Option<u32>> {
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{
+            index::{IdTerms, SeekStatus, Terms},
+            search::NO_MORE_DOCS,
+        },
+        pretty_assertions::assert_eq,
+    };
+
+    #[test]
+    fn test_id_terms() {
+        let terms = IdTerms::new([(b"id2".to_vec(), 0), (b"id10".to_vec(), 1), (b"id1".to_vec(), 2)]).unwrap();
+        assert_eq!(terms.size(), Some(3));
+        assert_eq!(terms.doc_count(), 3);
+        assert!(!terms.has_freqs());
+        assert_eq!(terms.doc(b"id10"), Some(1));
+        assert_eq!(terms.doc(b"id3"), None);
+
+        let mut te = terms.iterator().unwrap();
+        let mut seen = Vec::new();
+        while let Some(term) = te.next().unwrap() {
+            seen.push(String::from_utf8(term.to_vec()).unwrap());
+        }
+        assert_eq!(seen, vec!["id1", "id10", "id2"]);
+
+        assert_eq!(te.seek_ceil(b"id11").unwrap(), SeekStatus::NotFound);
+        assert_eq!(te.term(), b"id2");
+        assert_eq!(te.seek_ceil(b"id3").unwrap(), SeekStatus::End);
+        assert!(!te.seek_exact(b"id0").unwrap());
+        assert!(te.seek_exact(b"id1").unwrap());
+        assert_eq!(te.doc_freq().unwrap(), 1);
+
+        let mut postings = te.postings().unwrap();
+        assert_eq!(postings.doc_id(), NO_MORE_DOCS);
+        assert_eq!(postings.next_doc().unwrap(), 2);
+        assert_eq!(postings.freq().unwrap(), 1);
+        assert_eq!(postings.next_position().unwrap(), None);
+        assert_eq!(postings.next_doc().unwrap(), NO_MORE_DOCS);
+        assert_eq!(te.postings().unwrap().advance(3).unwrap(), NO_MORE_DOCS);
+
+        assert!(IdTerms::new([(b"id1".to_vec(), 0), (b"id1".to_vec(), 1)]).is_err());
+        assert!(IdTerms::new([]).unwrap().iterator().unwrap().next().unwrap().is_none());
+    }
+}
@@ -0,0 +1,57 @@
+use {
+    crate::{
+        index::{segment_index_file_name, SegmentIndex},
+        io::Directory,
+        BoxResult,
+    },
+    std::collections::HashMap,
+};
+
+/// A commit of an index, the `segments_N` file written by [crate::index::IndexWriter::commit], like Lucene's
+/// `IndexCommit`.
+///
+/// A reader opened from an index returns the commit it was opened on from
+/// [crate::index::IndexReader::index_commit], along with the user data set with
+/// [crate::index::IndexWriter::set_live_commit_data] when the commit was written.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct IndexCommit {
+    segments_file_name: String,
+    generation: u64,
+    user_data: HashMap<String, String>,
+}
+
+impl IndexCommit {
+    /// Returns the commit last read or written by `segment_index`.
+    pub(crate) fn from_segment_index(segment_index: &SegmentIndex) -> Self {
+        let generation = segment_index.get_last_generation();
+        Self {
+            segments_file_name: segment_index_file_name(generation),
+            generation,
+            user_data: segment_index.get_user_data().clone(),
+        }
+    }
+
+    /// Reads the latest commit of `directory`. This fails with [crate::LuceneError::IndexNotFound] if the
+    /// directory has no commit.
+    pub async fn read_latest<D: Directory>(directory: &mut D) -> BoxResult<Self> {
+        Ok(Self::from_segment_index(&SegmentIndex::open(directory).await?))
+    }
+
+    /// Returns the name of the `segments_N` file of the commit.
+    #[inline]
+    pub fn get_segments_file_name(&self) -> &str {
+        &self.segments_file_name
+    }
+
+    /// Returns the generation of the commit.
+    #[inline]
+    pub fn get_generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// Returns the user data written with the commit.
+    #[inline]
+    pub fn get_user_data(&self) -> &HashMap<String, String> {
+        &self.user_data
+    }
+}
@@ -0,0 +1,462 @@
+use {
+    crate::{
+        document::Document,
+        index::{IndexWriter, IndexWriterConfig, Term, Translog, TranslogDurability},
+        io::Directory,
+        search::{IndexSearcher, QueueSizeBasedExecutor},
+        BoxResult, ErrorContext, LuceneError,
+    },
+    log::{debug, info, warn},
+    std::{
+        collections::HashMap,
+        fmt::{Debug, Formatter, Result as FmtResult},
+        sync::Arc,
+    },
+};
+
+/// The default memory that every index of an [IndexManager] may use together, in megabytes.
+pub const DEFAULT_RAM_BUDGET_MB: f64 = 256.0;
+
+/// Opens the directory of an index managed by an [IndexManager], given the name of its tenant.
+pub type DirectoryFactory = Box<dyn Fn(&str) -> BoxResult<Box<dyn Directory + Send + Sync>> + Send + Sync>;
+
+/// Manages many small indexes, one per tenant, for multi-tenant deployments that can't keep a writer open for every
+/// index at once.
+///
+/// Indexes are opened lazily, the first time a tenant is used, with a writer over the directory from the manager's
+/// [DirectoryFactory] and a copy of its writer configuration. Documents are added and deleted through the manager,
+/// which logs them in a [Translog] kept in the same directory (the factory is called a second time for it), so
+/// closing an index loses nothing: reopening it replays its translog. At most [IndexManager::max_open_indexes] stay
+/// open; opening another closes the least recently used one. Every open index also shares a RAM budget (see
+/// [IndexManager::set_ram_budget_mb]) for its buffered documents and flushed segments: once they use more, the least
+/// recently used indexes are closed until they fit. Searchers share the manager's executor, if any, so the searches
+/// of every tenant run on the same bounded threads.
+///
+/// There is no on-disk segment writer yet, so a writer can't commit the documents it holds in memory (see
+/// [IndexWriter::commit]), and its translog can't be trimmed: reopening an index replays every document added to it.
+pub struct IndexManager {
+    directory_factory: DirectoryFactory,
+    config: IndexWriterConfig,
+    translog_durability: TranslogDurability,
+    max_open_indexes: usize,
+    ram_budget_mb: f64,
+    executor: Option<Arc<QueueSizeBasedExecutor>>,
+    indexes: HashMap<String, OpenIndex>,
+    clock: u64,
+    ram_bytes_used: usize,
+    evictions: u64,
+}
+
+/// An index opened by an [IndexManager].
+struct OpenIndex {
+    writer: IndexWriter,
+    translog: Translog,
+    last_used: u64,
+    ram_bytes_used: usize,
+}
+
+impl OpenIndex {
+    /// Returns the memory used by the buffered documents and flushed segments of the index, in bytes.
+    fn current_ram_bytes_used(&self) -> usize {
+        self.writer.ram_bytes_used()
+            + self.writer.segments().iter().map(|segment| segment.ram_bytes_used()).sum::<usize>()
+    }
+}
+
+impl IndexManager {
+    /// Creates a manager that opens the index of each tenant with the directory from `directory_factory` and a copy of
+    /// `config`, keeping at most `max_open_indexes` open. This fails with [LuceneError::InvalidArgument] if
+    /// `max_open_indexes` is zero.
+    pub fn new(
+        directory_factory: DirectoryFactory,
+        config: IndexWriterConfig,
+        max_open_indexes: usize,
+    ) -> BoxResult<Self> {
+        if max_open_indexes == 0 {
+            return Err(LuceneError::InvalidArgument("max_open_indexes must be at least 1".to_string()).into());
+        }
+
+        Ok(Self {
+            directory_factory,
+            config,
+            translog_durability: TranslogDurability::default(),
+            max_open_indexes,
+            ram_budget_mb: DEFAULT_RAM_BUDGET_MB,
+            executor: None,
+            indexes: HashMap::new(),
+            clock: 0,
+            ram_bytes_used: 0,
+            evictions: 0,
+        })
+    }
+
+    /// Returns the configuration the writer of each index is opened with.
+    #[inline]
+    pub fn config(&self) -> &IndexWriterConfig {
+        &self.config
+    }
+
+    /// Returns when the translog of each index makes the operations it logs durable.
+    #[inline]
+    pub fn translog_durability(&self) -> TranslogDurability {
+        self.translog_durability
+    }
+
+    /// Sets when the translog of each index opened from now on makes the operations it logs durable. Defaults to
+    /// [TranslogDurability::Request]. Closing an index syncs its translog whatever the durability.
+    pub fn set_translog_durability(&mut self, durability: TranslogDurability) -> &mut Self {
+        self.translog_durability = durability;
+        self
+    }
+
+    /// Returns the most indexes kept open.
+    #[inline]
+    pub fn max_open_indexes(&self) -> usize {
+        self.max_open_indexes
+    }
+
+    /// Returns the memory every open index may use together, in megabytes.
+    #[inline]
+    pub fn ram_budget_mb(&self) -> f64 {
+        self.ram_budget_mb
+    }
+
+    /// Sets the memory that the buffered documents and flushed segments of every open index may use together, in
+    /// megabytes. Defaults to [DEFAULT_RAM_BUDGET_MB]. Each writer also flushes on its own once it reaches the limits
+    /// of its configuration.
+    pub fn set_ram_budget_mb(&mut self, ram_budget_mb: f64) -> BoxResult<&mut Self> {
+        if ram_budget_mb.is_nan() || ram_budget_mb <= 0.0 {
+            return Err(
+                LuceneError::InvalidArgument(format!("RAM budget must be positive, got {ram_budget_mb}")).into()
+            );
+        }
+
+        self.ram_budget_mb = ram_budget_mb;
+        Ok(self)
+    }
+
+    /// Returns the executor that the searchers of every index run their slices on, if any.
+    #[inline]
+    pub fn executor(&self) -> Option<&Arc<QueueSizeBasedExecutor>> {
+        self.executor.as_ref()
+    }
+
+    /// Sets the executor that the searchers returned by [IndexManager::searcher] run their slices on, so that the
+    /// searches of every tenant share a bounded number of threads. Defaults to none.
+    pub fn set_executor(&mut self, executor: Option<Arc<QueueSizeBasedExecutor>>) -> &mut Self {
+        self.executor = executor;
+        self
+    }
+
+    /// Returns the number of open indexes.
+    #[inline]
+    pub fn num_open_indexes(&self) -> usize {
+        self.indexes.len()
+    }
+
+    /// Indicates whether the index of a tenant is open.
+    #[inline]
+    pub fn is_open(&self, tenant: &str) -> bool {
+        self.indexes.contains_key(tenant)
+    }
+
+    /// Returns the number of indexes closed to make room for others or to fit in the RAM budget.
+    #[inline]
+    pub fn evictions(&self) -> u64 {
+        self.evictions
+    }
+
+    /// Returns the memory used by the buffered documents and flushed segments of every open index, in bytes, as of
+    /// the last time each was used through this manager.
+    #[inline]
+    pub fn ram_bytes_used(&self) -> usize {
+        self.ram_bytes_used
+    }
+
+    /// Opens a tenant's index, if it isn't open yet, replaying its translog, and marks it as the most recently used.
+    pub async fn open(&mut self, tenant: &str) -> BoxResult<()> {
+        self.open_index(tenant).await.map(|_| ())
+    }
+
+    /// Returns a tenant's index, opening it if needed, and marks it as the most recently used. Opening an index may
+    /// close others to fit in the RAM budget.
+    async fn open_index(&mut self, tenant: &str) -> BoxResult<&mut OpenIndex> {
+        self.clock += 1;
+        if !self.indexes.contains_key(tenant) {
+            self.evict(self.max_open_indexes - 1, tenant).await;
+            let index = self.open_new(tenant).await.context(format!("opening the index of {tenant}"))?;
+            self.indexes.insert(tenant.to_string(), index);
+            self.refresh_ram_bytes_used(tenant);
+            self.enforce_ram_budget(tenant).await;
+        }
+
+        let index = self.indexes.get_mut(tenant).expect("the index was just opened");
+        index.last_used = self.clock;
+        Ok(index)
+    }
+
+    /// Opens the writer and translog of a tenant's index, replaying the translog into the writer.
+    async fn open_new(&self, tenant: &str) -> BoxResult<OpenIndex> {
+        let directory = (self.directory_factory)(tenant)?;
+        let writer = IndexWriter::new(directory, self.config.clone()).await?;
+        let directory = (self.directory_factory)(tenant)?;
+        let mut translog = Translog::open(directory, self.translog_durability).await?;
+        let replayed = translog.recover(&writer).await?;
+        debug!("Opened the index of {tenant}, replaying {replayed} operations");
+        Ok(OpenIndex {
+            writer,
+            translog,
+            last_used: 0,
+            ram_bytes_used: 0,
+        })
+    }
+
+    /// Adds a document to a tenant's index, opening the index if needed, after logging it in the index's translog
+    /// (see [Translog::add_document]). Then, if the RAM budget is exceeded, the least recently used other indexes are
+    /// closed. Returns whether adding the document flushed a segment of the tenant's index.
+    pub async fn add_document(&mut self, tenant: &str, document: &Document) -> BoxResult<bool> {
+        let index = self.open_index(tenant).await?;
+        let flushed = index.translog.add_document(&index.writer, document).await?;
+        self.refresh_ram_bytes_used(tenant);
+        self.enforce_ram_budget(tenant).await;
+        Ok(flushed)
+    }
+
+    /// Deletes the documents containing any of `terms` from a tenant's index, opening the index if needed, after
+    /// logging the deletion in the index's translog (see [Translog::delete_documents]). Returns the number of
+    /// documents deleted.
+    pub async fn delete_documents(&mut self, tenant: &str, terms: &[Term]) -> BoxResult<u32> {
+        let index = self.open_index(tenant).await?;
+        let deleted = index.translog.delete_documents(&index.writer, terms).await?;
+        self.refresh_ram_bytes_used(tenant);
+        Ok(deleted)
+    }
+
+    /// Flushes the buffered documents of a tenant's index, opening the index if needed, so that its searchers see
+    /// them (see [IndexWriter::flush]).
+    pub async fn flush(&mut self, tenant: &str) -> BoxResult<()> {
+        self.open_index(tenant).await?.writer.flush()?;
+        self.refresh_ram_bytes_used(tenant);
+        Ok(())
+    }
+
+    /// Commits a tenant's index, opening it if needed, and returns the name of the `segments_N` file written (see
+    /// [Translog::commit]). This fails while the index holds added documents in memory.
+    pub async fn commit(&mut self, tenant: &str) -> BoxResult<String> {
+        let index = self.open_index(tenant).await?;
+        index.translog.commit(&mut index.writer).await
+    }
+
+    /// Returns a searcher over the flushed segments of a tenant's index, opening the index if needed. The searcher
+    /// runs on the manager's executor, if any.
+    pub async fn searcher(&mut self, tenant: &str) -> BoxResult<IndexSearcher> {
+        let reader = self.open_index(tenant).await?.writer.reader()?;
+        let mut searcher = IndexSearcher::new(Arc::new(reader));
+        searcher.set_executor(self.executor.clone());
+        Ok(searcher)
+    }
+
+    /// Closes a tenant's index, if it's open, after syncing its translog. If syncing fails, the index stays open.
+    pub async fn close(&mut self, tenant: &str) -> BoxResult<()> {
+        let Some(index) = self.indexes.get_mut(tenant) else {
+            return Ok(());
+        };
+        index.translog.sync().await.context(format!("closing the index of {tenant}"))?;
+
+        let mut index = self.indexes.remove(tenant).expect("the index is open");
+        self.ram_bytes_used -= index.ram_bytes_used;
+        index.writer.close().await
+    }
+
+    /// Closes every open index, returning the first error after trying them all. Indexes whose translog can't be
+    /// synced stay open.
+    pub async fn close_all(&mut self) -> BoxResult<()> {
+        let mut result = Ok(());
+        let tenants: Vec<String> = self.indexes.keys().cloned().collect();
+        for tenant in tenants {
+            if let Err(e) = self.close(&tenant).await {
+                warn!("Failed to close the index of {tenant}: {e}");
+                if result.is_ok() {
+                    result = Err(e);
+                }
+            }
+        }
+        result
+    }
+
+    /// Closes the least recently used indexes other than `keep`'s until at most `max_open` are open, or none of the
+    /// others can be closed.
+    async fn evict(&mut self, max_open: usize, keep: &str) {
+        let mut candidates: Vec<(u64, String)> = self
+            .indexes
+            .iter()
+            .filter(|(tenant, _)| tenant.as_str() != keep)
+            .map(|(tenant, index)| (index.last_used, tenant.clone()))
+            .collect();
+        candidates.sort_unstable();
+
+        for (_, tenant) in candidates {
+            if self.indexes.len() <= max_open {
+                break;
+            }
+            match self.close(&tenant).await {
+                Ok(()) => {
+                    self.evictions += 1;
+                    debug!("Closed the least recently used index, of {tenant}");
+                }
+                Err(e) => warn!("Failed to close the index of {tenant}: {e}"),
+            }
+        }
+    }
+
+    /// Updates the memory used by a tenant's index.
+    fn refresh_ram_bytes_used(&mut self, tenant: &str) {
+        if let Some(index) = self.indexes.get_mut(tenant) {
+            let ram_bytes_used = index.current_ram_bytes_used();
+            self.ram_bytes_used = self.ram_bytes_used + ram_bytes_used - index.ram_bytes_used;
+            index.ram_bytes_used = ram_bytes_used;
+        }
+    }
+
+    /// Closes the least recently used indexes other than `tenant`'s until every open index fits in the RAM budget.
+    async fn enforce_ram_budget(&mut self, tenant: &str) {
+        let budget = (self.ram_budget_mb * 1024.0 * 1024.0) as usize;
+        let mut candidates: Vec<(u64, String)> = self
+            .indexes
+            .iter()
+            .filter(|(other, _)| other.as_str() != tenant)
+            .map(|(other, index)| (index.last_used, other.clone()))
+            .collect();
+        candidates.sort_unstable();
+
+        for (_, other) in candidates {
+            if self.ram_bytes_used <= budget {
+                return;
+            }
+            match self.close(&other).await {
+                Ok(()) => {
+                    self.evictions += 1;
+                    debug!("Closed the index of {other} to fit in the RAM budget of {} MB", self.ram_budget_mb);
+                }
+                Err(e) => warn!("Failed to close the index of {other}: {e}"),
+            }
+        }
+        if self.ram_bytes_used > budget {
+            info!("The index of {tenant} alone exceeds the RAM budget of {} MB", self.ram_budget_mb);
+        }
+    }
+}
+
+impl Debug for IndexManager {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        f.debug_struct("IndexManager")
+            .field("config", &self.config)
+            .field("max_open_indexes", &self.max_open_indexes)
+            .field("ram_budget_mb", &self.ram_budget_mb)
+            .field("open_indexes", &self.indexes.len())
+            .field("ram_bytes_used", &self.ram_bytes_used)
+            .field("evictions", &self.evictions)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{
+            document::{Document, Field, Store},
+            index::{IndexManager, IndexWriterConfig, Term},
+            io::{ByteBuffersDirectory, Directory},
+            search::{QueueSizeBasedExecutor, TermQuery},
+        },
+        pretty_assertions::assert_eq,
+        std::{
+            collections::HashMap,
+            sync::{Arc, Mutex},
+        },
+    };
+
+    /// Creates a manager whose tenants' directories are kept in memory, so that they outlive their writers.
+    fn manager(max_open_indexes: usize) -> IndexManager {
+        let directories: Mutex<HashMap<String, ByteBuffersDirectory>> = Mutex::default();
+        IndexManager::new(
+            Box::new(move |tenant| {
+                let directory = directories.lock().unwrap().entry(tenant.to_string()).or_default().clone();
+                Ok(Box::new(directory) as Box<dyn Directory + Send + Sync>)
+            }),
+            IndexWriterConfig::new(),
+            max_open_indexes,
+        )
+        .unwrap()
+    }
+
+    fn document(body: &str) -> Document {
+        let mut document = Document::new();
+        document.add(Field::text("body", body, Store::Yes));
+        document
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_lru_eviction() {
+        assert!(IndexManager::new(Box::new(|_| unreachable!()), IndexWriterConfig::new(), 0).is_err());
+        let mut manager = manager(2);
+        manager.open("a").await.unwrap();
+        manager.open("b").await.unwrap();
+        manager.open("a").await.unwrap();
+        manager.open("c").await.unwrap();
+        assert!(manager.is_open("a") && !manager.is_open("b") && manager.is_open("c"));
+        assert_eq!(manager.evictions(), 1);
+
+        // A closed index is reopened the next time it's used.
+        manager.open("b").await.unwrap();
+        assert!(!manager.is_open("a"));
+
+        // Indexes holding documents are closed too, and get them back from their translog when reopened.
+        manager.add_document("b", &document("quick fox")).await.unwrap();
+        manager.add_document("b", &document("quick dog")).await.unwrap();
+        manager.add_document("c", &document("lazy dog")).await.unwrap();
+        manager.flush("c").await.unwrap();
+        assert_eq!(manager.delete_documents("c", &[Term::new("body", "lazy")]).await.unwrap(), 1);
+        manager.open("d").await.unwrap();
+        manager.open("e").await.unwrap();
+        assert_eq!(manager.num_open_indexes(), 2);
+        assert!(!manager.is_open("b") && !manager.is_open("c"));
+
+        manager.flush("b").await.unwrap();
+        let searcher = manager.searcher("b").await.unwrap();
+        assert_eq!(searcher.count(&TermQuery::new(Term::new("body", "quick"))).unwrap(), 2);
+        manager.flush("c").await.unwrap();
+        let searcher = manager.searcher("c").await.unwrap();
+        assert_eq!(searcher.count(&TermQuery::new(Term::new("body", "dog"))).unwrap(), 0);
+        assert_eq!(manager.evictions(), 6);
+
+        manager.close("b").await.unwrap();
+        manager.close_all().await.unwrap();
+        assert_eq!(manager.num_open_indexes(), 0);
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_ram_budget() {
+        let mut manager = manager(10);
+        assert!(manager.set_ram_budget_mb(0.0).is_err());
+        manager.set_ram_budget_mb(0.01).unwrap();
+        manager.set_executor(Some(Arc::new(QueueSizeBasedExecutor::new(2).unwrap())));
+
+        // Flushed segments count against the budget, so flushing alone doesn't make room: the other index is closed.
+        for i in 0..20 {
+            manager.add_document("small", &document("quick")).await.unwrap();
+            manager.add_document("big", &document(&format!("the quick brown fox {i} ").repeat(20))).await.unwrap();
+            manager.flush("big").await.unwrap();
+        }
+        assert!(manager.ram_bytes_used() > 10485);
+        assert!(!manager.is_open("small"));
+        assert!(manager.evictions() >= 19);
+
+        manager.flush("small").await.unwrap();
+        let searcher = manager.searcher("small").await.unwrap();
+        assert!(searcher.executor().is_some());
+        assert_eq!(searcher.count(&TermQuery::new(Term::new("body", "quick"))).unwrap(), 20);
+        assert!(!manager.is_open("big"));
+        manager.close_all().await.unwrap();
+    }
+}
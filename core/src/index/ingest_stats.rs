@@ -0,0 +1,63 @@
+use std::time::Duration;
+
+/// Statistics for one batch of documents ingested by
+/// [IndexWriter::add_documents_stream](crate::index::IndexWriter::add_documents_stream).
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct IngestBatchStats {
+    /// The number of the batch, starting from 0.
+    pub batch: usize,
+
+    /// The number of documents in the batch.
+    pub docs: usize,
+
+    /// The time taken to index the batch, including any flush it triggered, if the writer has a metrics recorder (see
+    /// [IndexWriterConfig::set_metrics](crate::index::IndexWriterConfig::set_metrics)). The clock isn't read
+    /// otherwise.
+    pub elapsed: Option<Duration>,
+
+    /// The estimated memory used by buffered documents after the batch, in bytes.
+    pub ram_bytes_used: usize,
+
+    /// The number of times the RAM buffer filled up and was flushed to a new segment during the batch.
+    pub flushes: usize,
+}
+
+/// Totals for a stream of documents ingested by
+/// [IndexWriter::add_documents_stream](crate::index::IndexWriter::add_documents_stream).
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct IngestStats {
+    /// The number of batches the stream was split into.
+    pub batches: usize,
+
+    /// The number of documents indexed.
+    pub docs: usize,
+
+    /// The number of times the RAM buffer was flushed.
+    pub flushes: usize,
+
+    /// The time spent indexing, excluding time spent waiting on the stream, if the batches were timed.
+    pub elapsed: Option<Duration>,
+}
+
+impl IngestStats {
+    /// Adds a batch to the totals.
+    pub fn add_batch(&mut self, batch: &IngestBatchStats) {
+        self.batches += 1;
+        self.docs += batch.docs;
+        self.flushes += batch.flushes;
+        if let Some(elapsed) = batch.elapsed {
+            *self.elapsed.get_or_insert(Duration::ZERO) += elapsed;
+        }
+    }
+
+    /// Returns the indexing throughput in documents per second, or 0 if the batches weren't timed or no time was
+    /// spent.
+    pub fn docs_per_second(&self) -> f64 {
+        let secs = self.elapsed.unwrap_or_default().as_secs_f64();
+        if secs > 0.0 {
+            self.docs as f64 / secs
+        } else {
+            0.0
+        }
+    }
+}
@@ -0,0 +1,153 @@
+use {
+    crate::{
+        document::Document,
+        index::{
+            BinaryDocValues, CacheHelper, DocValuesSkipper, DocValuesType, FloatVectorValues, NumericDocValues,
+            TermVectors, Terms,
+        },
+        search::Sort,
+        util::{Accountable, FixedBitSet},
+        BoxResult,
+    },
+    std::{fmt::Debug, sync::Arc},
+};
+
+/// Read access to a single segment of an index. Document ids are local to the segment, from 0 to
+/// [LeafReader::max_doc].
+pub trait LeafReader: Accountable + Debug + Send + Sync {
+    /// Returns one greater than the largest document id in the segment.
+    fn max_doc(&self) -> u32;
+
+    /// Returns the number of live (not deleted) documents in the segment.
+    fn num_docs(&self) -> u32 {
+        self.max_doc()
+    }
+
+    /// Returns the live documents of the segment, with a bit set for each document that has not been deleted, or
+    /// `None` if no document has been deleted. Searches skip the documents whose bit is clear.
+    fn live_docs(&self) -> Option<&FixedBitSet> {
+        None
+    }
+
+    /// Returns the names of the fields indexed in this segment, those for which [LeafReader::terms] returns terms, in
+    /// no particular order.
+    fn indexed_fields(&self) -> Vec<&str>;
+
+    /// Returns the names of the fields with doc values in this segment, along with their type, in no particular
+    /// order.
+    fn doc_values_fields(&self) -> Vec<(&str, DocValuesType)>;
+
+    /// Returns the terms of the given field, or `None` if the field is not indexed in this segment.
+    fn terms(&self, field: &str) -> BoxResult<Option<&dyn Terms>>;
+
+    /// Returns the norms (per-document length normalization values) of the given field, indexed by document id, or
+    /// `None` if the field has no norms. Documents without the field have a norm of 0.
+    fn norms(&self, field: &str) -> BoxResult<Option<Arc<[i64]>>>;
+
+    /// Returns the numeric doc values of the given field, or `None` if the field has no numeric doc values in this
+    /// segment.
+    fn numeric_doc_values(&self, field: &str) -> BoxResult<Option<Box<dyn NumericDocValues>>>;
+
+    /// Returns a skipper over blocks of the numeric doc values of the given field, or `None` if the field has no
+    /// numeric doc values or no skip index in this segment.
+    fn doc_values_skipper(&self, _field: &str) -> BoxResult<Option<Box<dyn DocValuesSkipper>>> {
+        Ok(None)
+    }
+
+    /// Returns the binary doc values of the given field, or `None` if the field has no binary doc values in this
+    /// segment.
+    fn binary_doc_values(&self, field: &str) -> BoxResult<Option<Box<dyn BinaryDocValues>>>;
+
+    /// Returns the stored fields of the given document.
+    fn document(&self, doc: u32) -> BoxResult<Document>;
+
+    /// Returns the term vectors of the given document, or `None` if none of its fields store them.
+    fn term_vectors(&self, _doc: u32) -> BoxResult<Option<TermVectors>> {
+        Ok(None)
+    }
+
+    /// Returns the names of the fields with vectors in this segment, those for which [LeafReader::float_vector_values]
+    /// returns values, in no particular order.
+    fn vector_fields(&self) -> Vec<&str> {
+        Vec::new()
+    }
+
+    /// Returns the vectors of the given field and the HNSW graph over them, or `None` if the field has no vectors in
+    /// this segment.
+    fn float_vector_values(&self, _field: &str) -> BoxResult<Option<&FloatVectorValues>> {
+        Ok(None)
+    }
+
+    /// Returns the order of the documents in this segment, or `None` if they are in insertion order.
+    fn index_sort(&self) -> Option<&Sort> {
+        None
+    }
+
+    /// Returns the name of this segment, such as `_0`, or `None` if it wasn't given one.
+    fn segment_name(&self) -> Option<&str> {
+        None
+    }
+
+    /// Returns the helper for caching results computed on the segment's data, ignoring deletions, or `None` if the
+    /// data can't be cached. Every reader over the same data, such as the readers of a segment before and after
+    /// documents are deleted from it, shares the key, so results that don't depend on deletions, such as the matches
+    /// of a filter, stay valid across refreshes of a near-real-time reader.
+    fn core_cache_helper(&self) -> Option<&CacheHelper> {
+        None
+    }
+
+    /// Returns the helper for caching results computed on this reader, including its deletions, or `None` if they
+    /// can't be cached. The key changes whenever documents are deleted.
+    fn reader_cache_helper(&self) -> Option<&CacheHelper> {
+        None
+    }
+}
+
+/// A [LeafReader] along with its position within the top-level reader.
+#[derive(Clone, Debug)]
+pub struct LeafReaderContext {
+    ord: usize,
+    doc_base: u32,
+    reader: Arc<dyn LeafReader>,
+}
+
+impl LeafReaderContext {
+    /// Creates a new context for the leaf at index `ord` of its parent, whose first document has the global id
+    /// `doc_base`.
+    pub fn new(ord: usize, doc_base: u32, reader: Arc<dyn LeafReader>) -> Self {
+        Self {
+            ord,
+            doc_base,
+            reader,
+        }
+    }
+
+    /// Returns the index of this leaf within its parent reader.
+    #[inline]
+    pub fn ord(&self) -> usize {
+        self.ord
+    }
+
+    /// Returns the global document id of the first document in this leaf.
+    #[inline]
+    pub fn doc_base(&self) -> u32 {
+        self.doc_base
+    }
+
+    /// Returns the leaf reader.
+    #[inline]
+    pub fn reader(&self) -> &dyn LeafReader {
+        self.reader.as_ref()
+    }
+
+    /// Returns a shared handle to the leaf reader.
+    #[inline]
+    pub fn reader_arc(&self) -> &Arc<dyn LeafReader> {
+        &self.reader
+    }
+}
+
+/// Returns the index of the leaf containing the given global document id.
+pub fn sub_index(doc: u32, leaves: &[LeafReaderContext]) -> usize {
+    leaves.partition_point(|leaf| leaf.doc_base() <= doc).saturating_sub(1)
+}
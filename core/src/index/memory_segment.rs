@@ -0,0 +1,1372 @@
+use {
+    crate::{
+        analysis::Analyzer,
+        document::{Document, Field, TermVectorOptions},
+        index::{
+            resolve_index_sort, BinaryDocValues, CacheHelper, DocMap, DocValuesSkipBlock, DocValuesSkipper,
+            DocValuesType, FloatVectorValues, FstTerms, IdTerms, LeafReader, MemoryBinaryDocValues,
+            MemoryDocValuesSkipper, MemoryNumericDocValues, MemoryPosting, MemoryTerms, MergeStats, NumericDocValues,
+            TermVector, TermVectorTerm, TermVectors, Terms, TermsFormat, TermsHash, VectorSimilarityFunction,
+            DEFAULT_SKIP_INDEX_INTERVAL, MAX_DOCS,
+        },
+        metrics::{MetricsRecorder, FLUSH_COUNT, FLUSH_DOCS, FLUSH_LATENCY_SECONDS},
+        search::{BM25Similarity, FieldInvertState, Similarity, Sort, SortKey, NO_MORE_DOCS},
+        util::{
+            hnsw::{HnswGraphBuilder, OnHeapHnswGraph, DEFAULT_BEAM_WIDTH, DEFAULT_HNSW_SEED, DEFAULT_MAX_CONN},
+            size_of_vec, Accountable, BitSet, BytesRefArray, NamedAccountable, MAX_TERM_LENGTH,
+        },
+        BoxResult, LuceneError,
+    },
+    std::{
+        collections::{BTreeMap, HashMap},
+        sync::Arc,
+        time::Instant,
+    },
+};
+
+/// The documents with a numeric doc value for a field, their values, and the skip index over them.
+type NumericColumn = (Arc<[u32]>, Arc<[i64]>, Arc<[DocValuesSkipBlock]>);
+
+/// The documents with a binary doc value for a field, and their values.
+type BinaryColumn = (Arc<[u32]>, Arc<[Vec<u8>]>);
+
+/// The start and end offsets of the occurrences of a term.
+type TermOffsets = Vec<(u32, u32)>;
+
+/// A term of a field value, with its position increment, start and end offsets, and payload.
+type FieldToken = (Vec<u8>, u32, u32, u32, Vec<u8>);
+
+/// The vectors of a field buffered by a [MemorySegmentBuilder], with the documents holding them in increasing order.
+#[derive(Debug)]
+struct BufferedVectors {
+    similarity: VectorSimilarityFunction,
+    dimension: usize,
+    docs: Vec<u32>,
+    vectors: Vec<f32>,
+}
+
+impl BufferedVectors {
+    /// Links the vectors into an HNSW graph, starting from `seed` if there is one.
+    fn build(self, seed: Option<VectorGraphSeed>) -> FloatVectorValues {
+        let mut builder = HnswGraphBuilder::new(
+            self.similarity,
+            self.dimension,
+            &self.vectors,
+            DEFAULT_MAX_CONN,
+            DEFAULT_BEAM_WIDTH,
+            DEFAULT_HNSW_SEED,
+        )
+        .expect("vectors have the dimension of their field");
+        if let Some(seed) = seed {
+            let ord_map: Vec<u32> = seed
+                .docs
+                .iter()
+                .map(|doc| self.docs.binary_search(doc).expect("seeded documents have vectors") as u32)
+                .collect();
+            builder.initialize_from(&seed.graph, &ord_map).expect("seeded nodes have distinct documents");
+        }
+        let graph = builder.build();
+        FloatVectorValues::new(self.similarity, self.dimension, self.docs.into(), self.vectors.into(), Arc::new(graph))
+            .expect("documents are in increasing order")
+    }
+}
+
+/// The graph of a segment added to a [MemorySegmentBuilder] that the graph of a vector field may start from, along
+/// with the document in the builder of each of its nodes.
+#[derive(Debug)]
+struct VectorGraphSeed {
+    graph: Arc<OnHeapHnswGraph>,
+    docs: Vec<u32>,
+}
+
+/// The terms of a field of a [MemorySegment], held as its [TermsFormat] asks.
+#[derive(Debug)]
+enum FieldTerms {
+    Memory(MemoryTerms),
+    Fst(FstTerms),
+    Id(IdTerms),
+}
+
+impl FieldTerms {
+    fn new(format: TermsFormat, postings: BTreeMap<Vec<u8>, Vec<MemoryPosting>>) -> Self {
+        match format {
+            TermsFormat::Default => Self::Memory(MemoryTerms::from_postings(postings, true)),
+            TermsFormat::Fst => Self::Fst(FstTerms::from_postings(postings, true)),
+            TermsFormat::UniqueKeys if postings.values().all(|postings| postings.len() == 1) => {
+                let keys = postings.into_iter().map(|(term, postings)| (term, postings[0].doc));
+                Self::Id(IdTerms::new(keys).expect("terms are unique"))
+            }
+            TermsFormat::UniqueKeys => Self::Memory(MemoryTerms::from_postings(postings, true)),
+        }
+    }
+
+    fn as_terms(&self) -> &dyn Terms {
+        match self {
+            Self::Memory(terms) => terms,
+            Self::Fst(terms) => terms,
+            Self::Id(terms) => terms,
+        }
+    }
+
+    fn ram_bytes_used(&self) -> usize {
+        match self {
+            Self::Memory(terms) => terms.ram_bytes_used(),
+            Self::Fst(terms) => terms.ram_bytes_used(),
+            Self::Id(terms) => terms.ram_bytes_used(),
+        }
+    }
+}
+
+/// A [LeafReader] over a segment held entirely in memory.
+///
+/// Segments are created with a [MemorySegmentBuilder]. Every indexed field records term frequencies and positions,
+/// along with the payloads of tokens that have one (see [crate::analysis::Token::payload]); tokenized fields also
+/// record norms, computed by the builder's similarity (by default, the number of tokens in the field, encoded with
+/// [crate::util::int_to_byte4]). Fields that store term vectors (see [Field::with_term_vectors]) also record them
+/// per document. The terms of each field are held as its [TermsFormat] asks (see
+/// [MemorySegmentBuilder::set_terms_formats]). The vectors of each vector field
+/// (see [Field::knn_vector]) are linked into an HNSW graph.
+#[derive(Debug)]
+pub struct MemorySegment {
+    name: Option<String>,
+    max_doc: u32,
+    terms: HashMap<String, FieldTerms>,
+    norms: HashMap<String, Arc<[i64]>>,
+    numeric_doc_values: HashMap<String, NumericColumn>,
+    binary_doc_values: HashMap<String, BinaryColumn>,
+    vectors: HashMap<String, FloatVectorValues>,
+    stored: Vec<Document>,
+    term_vectors: Vec<TermVectors>,
+    index_sort: Option<Sort>,
+    cache_helper: CacheHelper,
+}
+
+impl LeafReader for MemorySegment {
+    #[inline]
+    fn max_doc(&self) -> u32 {
+        self.max_doc
+    }
+
+    fn indexed_fields(&self) -> Vec<&str> {
+        self.terms.keys().map(String::as_str).collect()
+    }
+
+    fn doc_values_fields(&self) -> Vec<(&str, DocValuesType)> {
+        let numeric = self.numeric_doc_values.keys().map(|field| (field.as_str(), DocValuesType::Numeric));
+        let binary = self.binary_doc_values.keys().map(|field| (field.as_str(), DocValuesType::Binary));
+        numeric.chain(binary).collect()
+    }
+
+    fn terms(&self, field: &str) -> BoxResult<Option<&dyn Terms>> {
+        Ok(self.terms.get(field).map(FieldTerms::as_terms))
+    }
+
+    fn norms(&self, field: &str) -> BoxResult<Option<Arc<[i64]>>> {
+        Ok(self.norms.get(field).cloned())
+    }
+
+    fn numeric_doc_values(&self, field: &str) -> BoxResult<Option<Box<dyn NumericDocValues>>> {
+        Ok(self.numeric_doc_values.get(field).map(|(docs, values, _)| {
+            Box::new(MemoryNumericDocValues::new(docs.clone(), values.clone())) as Box<dyn NumericDocValues>
+        }))
+    }
+
+    fn doc_values_skipper(&self, field: &str) -> BoxResult<Option<Box<dyn DocValuesSkipper>>> {
+        Ok(self
+            .numeric_doc_values
+            .get(field)
+            .map(|(_, _, blocks)| Box::new(MemoryDocValuesSkipper::new(blocks.clone())) as Box<dyn DocValuesSkipper>))
+    }
+
+    fn binary_doc_values(&self, field: &str) -> BoxResult<Option<Box<dyn BinaryDocValues>>> {
+        Ok(self.binary_doc_values.get(field).map(|(docs, values)| {
+            Box::new(MemoryBinaryDocValues::new(docs.clone(), values.clone())) as Box<dyn BinaryDocValues>
+        }))
+    }
+
+    fn document(&self, doc: u32) -> BoxResult<Document> {
+        match self.stored.get(doc as usize) {
+            Some(document) => Ok(document.clone()),
+            None => Err(LuceneError::InvalidArgument(format!(
+                "document {doc} is out of bounds (max_doc is {})",
+                self.max_doc
+            ))
+            .into()),
+        }
+    }
+
+    fn term_vectors(&self, doc: u32) -> BoxResult<Option<TermVectors>> {
+        match self.term_vectors.get(doc as usize) {
+            Some(term_vectors) => Ok((!term_vectors.is_empty()).then(|| term_vectors.clone())),
+            None => Err(LuceneError::InvalidArgument(format!(
+                "document {doc} is out of bounds (max_doc is {})",
+                self.max_doc
+            ))
+            .into()),
+        }
+    }
+
+    fn vector_fields(&self) -> Vec<&str> {
+        self.vectors.keys().map(String::as_str).collect()
+    }
+
+    fn float_vector_values(&self, field: &str) -> BoxResult<Option<&FloatVectorValues>> {
+        Ok(self.vectors.get(field))
+    }
+
+    #[inline]
+    fn index_sort(&self) -> Option<&Sort> {
+        self.index_sort.as_ref()
+    }
+
+    #[inline]
+    fn segment_name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    #[inline]
+    fn core_cache_helper(&self) -> Option<&CacheHelper> {
+        Some(&self.cache_helper)
+    }
+
+    // A segment in memory has no deletions, so it's its own core.
+    #[inline]
+    fn reader_cache_helper(&self) -> Option<&CacheHelper> {
+        Some(&self.cache_helper)
+    }
+}
+
+impl Accountable for MemorySegment {
+    fn ram_bytes_used(&self) -> usize {
+        size_of::<Self>() + self.child_resources().iter().map(Accountable::ram_bytes_used).sum::<usize>()
+    }
+
+    /// Breaks the memory down by data structure. The maps' own tables are not counted, so this is an estimate.
+    fn child_resources(&self) -> Vec<NamedAccountable> {
+        let terms = self.terms.iter().map(|(field, terms)| field.capacity() + terms.ram_bytes_used()).sum();
+        let norms = self.norms.iter().map(|(field, norms)| field.capacity() + size_of_val(norms.as_ref())).sum();
+        let numeric_doc_values = self
+            .numeric_doc_values
+            .iter()
+            .map(|(field, (docs, values, blocks))| {
+                field.capacity()
+                    + size_of_val(docs.as_ref())
+                    + size_of_val(values.as_ref())
+                    + size_of_val(blocks.as_ref())
+            })
+            .sum::<usize>();
+        let binary_doc_values = self
+            .binary_doc_values
+            .iter()
+            .map(|(field, (docs, values))| {
+                field.capacity()
+                    + size_of_val(docs.as_ref())
+                    + values.iter().map(|value| size_of::<Vec<u8>>() + value.capacity()).sum::<usize>()
+            })
+            .sum::<usize>();
+        let vectors = self.vectors.iter().map(|(field, values)| field.capacity() + values.ram_bytes_used()).sum();
+        let stored = size_of_vec(&self.stored)
+            + self.stored.iter().map(|document| document.ram_bytes_used() - size_of::<Document>()).sum::<usize>();
+        let term_vectors = size_of_vec(&self.term_vectors)
+            + self
+                .term_vectors
+                .iter()
+                .map(|term_vectors| term_vectors.ram_bytes_used() - size_of::<TermVectors>())
+                .sum::<usize>();
+
+        vec![
+            NamedAccountable::from_bytes("terms", terms),
+            NamedAccountable::from_bytes("norms", norms),
+            NamedAccountable::from_bytes("doc values", numeric_doc_values + binary_doc_values),
+            NamedAccountable::from_bytes("vectors", vectors),
+            NamedAccountable::from_bytes("stored fields", stored),
+            NamedAccountable::from_bytes("term vectors", term_vectors),
+        ]
+    }
+}
+
+/// Builds a [MemorySegment] from a sequence of documents.
+#[derive(Debug)]
+pub struct MemorySegmentBuilder {
+    name: Option<String>,
+    analyzer: Arc<dyn Analyzer>,
+    similarity: Arc<dyn Similarity>,
+    max_doc: u32,
+    postings: TermsHash,
+    norms: HashMap<String, Vec<i64>>,
+    numeric_doc_values: HashMap<String, (Vec<u32>, Vec<i64>)>,
+    binary_doc_values: HashMap<String, (Vec<u32>, BytesRefArray)>,
+    vectors: HashMap<String, BufferedVectors>,
+    vector_graph_seeds: HashMap<String, VectorGraphSeed>,
+    seed_vector_graphs: bool,
+    stored: Vec<Document>,
+    term_vectors: Vec<TermVectors>,
+    index_sort: Option<(Sort, Vec<SortKey>)>,
+    terms_formats: HashMap<String, TermsFormat>,
+    metrics: Option<Arc<dyn MetricsRecorder>>,
+    ram_bytes_used: usize,
+}
+
+impl MemorySegmentBuilder {
+    /// Creates a new builder that analyzes tokenized fields with the given analyzer.
+    pub fn new(analyzer: Arc<dyn Analyzer>) -> Self {
+        Self {
+            name: None,
+            analyzer,
+            similarity: Arc::new(BM25Similarity::default()),
+            max_doc: 0,
+            postings: TermsHash::new(),
+            norms: HashMap::new(),
+            numeric_doc_values: HashMap::new(),
+            binary_doc_values: HashMap::new(),
+            vectors: HashMap::new(),
+            vector_graph_seeds: HashMap::new(),
+            seed_vector_graphs: true,
+            stored: Vec::new(),
+            term_vectors: Vec::new(),
+            index_sort: None,
+            terms_formats: HashMap::new(),
+            metrics: None,
+            ram_bytes_used: 0,
+        }
+    }
+
+    /// Sets the name of the segment, such as `_0`, which identifies it in traces. Segments have no name by default.
+    pub fn set_name(&mut self, name: impl Into<String>) -> &mut Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Sets the recorder that flush metrics are reported to when the segment is built: the flush count, the time
+    /// taken to build the segment, and its number of documents.
+    pub fn set_metrics(&mut self, metrics: Option<Arc<dyn MetricsRecorder>>) -> &mut Self {
+        self.metrics = metrics;
+        self
+    }
+
+    /// Sets the similarity that computes norms, which should match the one used for searching. See
+    /// [crate::index::IndexWriterConfig::set_similarity].
+    pub fn set_similarity(&mut self, similarity: Arc<dyn Similarity>) -> &mut Self {
+        self.similarity = similarity;
+        self
+    }
+
+    /// Sorts the documents of the segment by `sort` when it is built. Ties are broken by insertion order. Only
+    /// document order and numeric doc values fields may be used; sorting by score is rejected.
+    pub fn set_index_sort(&mut self, sort: Sort) -> BoxResult<&mut Self> {
+        let keys = resolve_index_sort(&sort)?;
+        self.index_sort = Some((sort, keys));
+        Ok(self)
+    }
+
+    /// Sets how the terms of each field are held once the segment is built. Fields not listed use
+    /// [TermsFormat::Default].
+    pub fn set_terms_formats(&mut self, formats: HashMap<String, TermsFormat>) -> &mut Self {
+        self.terms_formats = formats;
+        self
+    }
+
+    /// Sets whether the HNSW graph of each vector field starts from the graph of the largest segment without deletions
+    /// added with [MemorySegmentBuilder::add_reader], so that only the other vectors are inserted when the segment is
+    /// built. Defaults to true. See [crate::index::IndexWriterConfig::set_seed_merged_vector_graphs].
+    pub fn set_seed_vector_graphs(&mut self, seed: bool) -> &mut Self {
+        self.seed_vector_graphs = seed;
+        self
+    }
+
+    /// Returns the number of documents added so far.
+    #[inline]
+    pub fn max_doc(&self) -> u32 {
+        self.max_doc
+    }
+
+    /// Returns an estimate of the memory held by the documents added so far, in bytes. The inverted index is
+    /// accounted for exactly (see [TermsHash::bytes_used]), and norms, doc values and stored fields approximately.
+    pub fn ram_bytes_used(&self) -> usize {
+        self.ram_bytes_used + self.postings.bytes_used()
+    }
+
+    /// Adds a document, returning its id within the segment.
+    pub fn add_document(&mut self, document: &Document) -> BoxResult<u32> {
+        if self.max_doc >= MAX_DOCS {
+            return Err(LuceneError::TooManyDocs(self.max_doc as u64 + 1).into());
+        }
+
+        let doc = self.max_doc;
+
+        // Validate doc values before anything is recorded, so a rejected document leaves no trace.
+        let mut doc_values_fields = Vec::new();
+        for field in document.fields().iter() {
+            if let Some(doc_values_type) = field.doc_values_type() {
+                if doc_values_fields.contains(&field.name()) {
+                    return Err(LuceneError::InvalidArgument(format!(
+                        "{} doc values field {:?} appears more than once in this document",
+                        match doc_values_type {
+                            DocValuesType::Numeric => "numeric",
+                            DocValuesType::Binary => "binary",
+                        },
+                        field.name()
+                    ))
+                    .into());
+                }
+                doc_values_fields.push(field.name());
+            }
+        }
+
+        let mut vector_fields = Vec::new();
+        for field in document.fields().iter() {
+            let (Some(vector), Some(similarity)) = (field.float_vector_value(), field.vector_similarity()) else {
+                continue;
+            };
+            if vector_fields.contains(&field.name()) {
+                return Err(LuceneError::InvalidArgument(format!(
+                    "vector field {:?} appears more than once in this document",
+                    field.name()
+                ))
+                .into());
+            }
+            self.check_vector_field(field.name(), vector.len(), similarity)?;
+            vector_fields.push(field.name());
+        }
+
+        let mut term_vector_options: HashMap<&str, Option<TermVectorOptions>> = HashMap::new();
+        for field in document.fields().iter().filter(|f| f.is_indexed() && f.term_freq().is_none()) {
+            let options = *term_vector_options.entry(field.name()).or_insert(field.term_vector_options());
+            if options != field.term_vector_options() {
+                return Err(LuceneError::InvalidArgument(format!(
+                    "values of field {:?} store different term vectors in this document",
+                    field.name()
+                ))
+                .into());
+            }
+        }
+
+        let mut index_offsets: HashMap<&str, bool> = HashMap::new();
+        for field in document.fields().iter().filter(|f| f.is_indexed() && f.term_freq().is_none()) {
+            if *index_offsets.entry(field.name()).or_insert(field.index_offsets()) != field.index_offsets() {
+                return Err(LuceneError::InvalidArgument(format!(
+                    "values of field {:?} index offsets differently in this document",
+                    field.name()
+                ))
+                .into());
+            }
+        }
+
+        let mut term_freqs: BTreeMap<(&str, Vec<u8>), u32> = BTreeMap::new();
+        for field in document.fields().iter().filter(|f| f.is_indexed()) {
+            let (Some(freq), Some(term)) = (field.term_freq(), field.bytes_value()) else {
+                continue;
+            };
+
+            if term_freqs.insert((field.name(), term.to_vec()), freq).is_some() {
+                return Err(LuceneError::InvalidArgument(format!(
+                    "term {:?} of field {:?} has a custom frequency and appears more than once in this document",
+                    String::from_utf8_lossy(term),
+                    field.name()
+                ))
+                .into());
+            }
+        }
+
+        let mut positions: BTreeMap<(&str, Vec<u8>), Vec<u32>> = BTreeMap::new();
+        // The payload at each position of each term; empty where a token has none.
+        let mut payloads: BTreeMap<(&str, Vec<u8>), Vec<Vec<u8>>> = BTreeMap::new();
+        // The start and end offsets of each term of the fields that index offsets or whose term vectors record them.
+        let mut offsets: BTreeMap<(&str, Vec<u8>), TermOffsets> = BTreeMap::new();
+        // The last position, length and number of overlapping tokens of each field.
+        let mut field_state: HashMap<&str, (Option<u32>, u32, u32)> = HashMap::new();
+        // The offset just past the end of the last value of each field.
+        let mut end_offsets: HashMap<&str, u32> = HashMap::new();
+
+        for field in document.fields().iter().filter(|f| f.is_indexed() && f.term_freq().is_none()) {
+            let (last_position, length, num_overlap) = field_state.entry(field.name()).or_insert((None, 0, 0));
+
+            // Multiple values of the same field continue from the previous value's last position.
+            let mut position = match *last_position {
+                Some(last) => last + self.analyzer.position_increment_gap(field.name()),
+                None => 0,
+            };
+            let mut first = last_position.is_none();
+
+            let base_offset = match end_offsets.get(field.name()) {
+                Some(end) => end + self.analyzer.offset_gap(field.name()),
+                None => 0,
+            };
+            end_offsets.insert(field.name(), base_offset + field.bytes_value().map_or(0, |bytes| bytes.len() as u32));
+            let record_offsets =
+                field.index_offsets() || field.term_vector_options().is_some_and(|options| options.offsets);
+
+            for (term, increment, start_offset, end_offset, payload) in Self::tokens(self.analyzer.as_ref(), field) {
+                if first {
+                    position += increment.saturating_sub(1);
+                    first = false;
+                } else {
+                    position += increment;
+                }
+                if record_offsets {
+                    offsets
+                        .entry((field.name(), term.clone()))
+                        .or_default()
+                        .push((base_offset + start_offset, base_offset + end_offset));
+                }
+                payloads.entry((field.name(), term.clone())).or_default().push(payload);
+                positions.entry((field.name(), term)).or_default().push(position);
+                *last_position = Some(position);
+                if field.is_tokenized() {
+                    *length += 1;
+                    if increment == 0 {
+                        *num_overlap += 1;
+                    }
+                }
+            }
+        }
+
+        for (field, term) in positions.keys().chain(term_freqs.keys()) {
+            if term.len() > MAX_TERM_LENGTH {
+                return Err(LuceneError::InvalidArgument(format!(
+                    "field {field:?} has a term of {} bytes, longer than the maximum of {MAX_TERM_LENGTH} bytes",
+                    term.len()
+                ))
+                .into());
+            }
+        }
+        if let Some((field, term)) = term_freqs.keys().find(|key| positions.contains_key(*key)) {
+            return Err(LuceneError::InvalidArgument(format!(
+                "term {:?} of field {field:?} has a custom frequency and is also indexed with positions",
+                String::from_utf8_lossy(term)
+            ))
+            .into());
+        }
+
+        for field in document.fields().iter().filter(|f| f.is_indexed() && f.is_tokenized()) {
+            let mut state = FieldInvertState {
+                field: field.name().to_string(),
+                length: field_state[field.name()].1,
+                num_overlap: field_state[field.name()].2,
+                unique_term_count: 0,
+                max_term_frequency: 0,
+            };
+            for term_positions in positions
+                .range((field.name(), Vec::new())..)
+                .take_while(|((f, _), _)| *f == field.name())
+                .map(|(_, p)| p)
+            {
+                state.unique_term_count += 1;
+                state.max_term_frequency = state.max_term_frequency.max(term_positions.len() as u32);
+            }
+
+            let norms = self.norms.entry(field.name().to_string()).or_default();
+            self.ram_bytes_used += (doc as usize + 1).saturating_sub(norms.len()) * size_of::<i64>();
+            norms.resize(doc as usize + 1, 0);
+            norms[doc as usize] = self.similarity.compute_norm(&state);
+        }
+
+        let mut term_vectors = TermVectors::new();
+        for (field, options) in term_vector_options {
+            let Some(options) = options else {
+                continue;
+            };
+
+            let mut term_vector = TermVector::new(options);
+            for ((_, term), term_positions) in
+                positions.range((field, Vec::new())..).take_while(|((f, _), _)| *f == field)
+            {
+                let freq = term_positions.len();
+                term_vector.add(TermVectorTerm {
+                    term: term.clone(),
+                    freq: freq as u32,
+                    positions: if options.positions {
+                        term_positions.clone()
+                    } else {
+                        Vec::new()
+                    },
+                    offsets: if options.offsets {
+                        offsets.get(&(field, term.clone())).cloned().unwrap_or_default()
+                    } else {
+                        Vec::new()
+                    },
+                    payloads: if options.payloads {
+                        payloads[&(field, term.clone())].clone()
+                    } else {
+                        Vec::new()
+                    },
+                })?;
+            }
+            if term_vector.size() > 0 {
+                term_vectors.insert(field, term_vector);
+            }
+        }
+
+        for ((field, term), term_positions) in positions {
+            let term_payloads = payloads
+                .remove(&(field, term.clone()))
+                .filter(|payloads| payloads.iter().any(|payload| !payload.is_empty()))
+                .unwrap_or_default();
+            let term_offsets = if index_offsets[field] {
+                offsets.remove(&(field, term.clone())).unwrap_or_default()
+            } else {
+                Vec::new()
+            };
+            self.postings.add_positions(field, &term, doc, &term_positions, &term_payloads, &term_offsets)?;
+        }
+
+        for ((field, term), freq) in term_freqs {
+            self.postings.add_freq(field, &term, doc, freq)?;
+        }
+
+        for field in document.fields().iter() {
+            if let (Some(DocValuesType::Numeric), Some(value)) = (field.doc_values_type(), field.numeric_value()) {
+                let (docs, values) = self.numeric_doc_values.entry(field.name().to_string()).or_default();
+                docs.push(doc);
+                values.push(value);
+                self.ram_bytes_used += size_of::<u32>() + size_of::<i64>();
+            }
+
+            if let (Some(DocValuesType::Binary), Some(value)) = (field.doc_values_type(), field.bytes_value()) {
+                let (docs, values) = self.binary_doc_values.entry(field.name().to_string()).or_default();
+                docs.push(doc);
+                values.append(value);
+                self.ram_bytes_used += size_of::<u32>() + size_of::<usize>() + value.len();
+            }
+
+            if let (Some(vector), Some(similarity)) = (field.float_vector_value(), field.vector_similarity()) {
+                let buffered = self.buffered_vectors(field.name(), vector.len(), similarity);
+                buffered.docs.push(doc);
+                buffered.vectors.extend_from_slice(vector);
+                self.ram_bytes_used += size_of::<u32>() + size_of_val(vector);
+            }
+        }
+
+        let stored: Document = document.fields().iter().filter(|f| f.is_stored()).cloned().collect();
+        self.add_stored(stored);
+        self.add_term_vectors(term_vectors);
+        self.max_doc += 1;
+        Ok(doc)
+    }
+
+    /// Adds the live documents of another segment, in order, returning the number of documents added.
+    ///
+    /// Terms, postings, norms, doc values, vectors, stored fields and term vectors are copied rather than re-analyzed,
+    /// so the norms keep the values computed by the similarity that indexed `reader`. The HNSW graph of a vector field
+    /// may start from the graph of `reader` (see [MemorySegmentBuilder::set_seed_vector_graphs]). If this fails, some
+    /// of the documents may have been partially added, and the builder should be discarded.
+    pub fn add_reader(&mut self, reader: &dyn LeafReader) -> BoxResult<u32> {
+        self.add_reader_with_stats(reader, &mut MergeStats::default())
+    }
+
+    /// Adds the live documents of another segment as [MemorySegmentBuilder::add_reader] does, adding its statistics
+    /// to `stats`, along with the time spent on each kind of data if `stats` is timed.
+    pub fn add_reader_with_stats(&mut self, reader: &dyn LeafReader, stats: &mut MergeStats) -> BoxResult<u32> {
+        let base = self.max_doc;
+        let live_docs = reader.live_docs();
+
+        // new_docs[old] is the id in this segment of the reader's document `old`, or `None` if it was deleted.
+        let mut new_docs = Vec::with_capacity(reader.max_doc() as usize);
+        let mut next_doc = base as u64;
+        for doc in 0..reader.max_doc() {
+            if live_docs.is_none_or(|live_docs| live_docs.get(doc)) {
+                new_docs.push(Some(next_doc as u32));
+                next_doc += 1;
+            } else {
+                new_docs.push(None);
+            }
+        }
+        if next_doc > MAX_DOCS as u64 {
+            return Err(LuceneError::TooManyDocs(next_doc).into());
+        }
+        let num_docs = (next_doc - base as u64) as u32;
+
+        for field in reader.indexed_fields() {
+            let start = stats.start();
+            let Some(terms) = reader.terms(field)? else {
+                continue;
+            };
+
+            let mut terms_enum = terms.iterator()?;
+            while let Some(term) = terms_enum.next()? {
+                let term = term.to_vec();
+                let mut postings = terms_enum.postings()?;
+                loop {
+                    let doc = postings.next_doc()?;
+                    if doc == NO_MORE_DOCS {
+                        break;
+                    }
+                    let Some(new_doc) = new_docs[doc as usize] else {
+                        continue;
+                    };
+
+                    let freq = postings.freq()?;
+                    if terms.has_positions() {
+                        let mut positions = Vec::with_capacity(freq as usize);
+                        let mut payloads = Vec::new();
+                        let mut offsets = Vec::new();
+                        while let Some(position) = postings.next_position()? {
+                            positions.push(position);
+                            payloads.push(postings.payload()?.map(<[u8]>::to_vec).unwrap_or_default());
+                            if let (Some(start), Some(end)) = (postings.start_offset()?, postings.end_offset()?) {
+                                offsets.push((start, end));
+                            }
+                        }
+                        if payloads.iter().all(Vec::is_empty) {
+                            payloads.clear();
+                        }
+                        if offsets.len() != positions.len() {
+                            offsets.clear();
+                        }
+                        self.postings.add_positions(field, &term, new_doc, &positions, &payloads, &offsets)?;
+                    } else {
+                        self.postings.add_freq(field, &term, new_doc, freq)?;
+                    }
+                }
+            }
+
+            stats.postings += MergeStats::since(start);
+
+            let start = stats.start();
+            if let Some(reader_norms) = reader.norms(field)? {
+                let norms = self.norms.entry(field.to_string()).or_default();
+                self.ram_bytes_used += (next_doc as usize).saturating_sub(norms.len()) * size_of::<i64>();
+                norms.resize(next_doc as usize, 0);
+                for (norm, new_doc) in reader_norms.iter().zip(&new_docs) {
+                    if let Some(new_doc) = new_doc {
+                        norms[*new_doc as usize] = *norm;
+                    }
+                }
+            }
+            stats.norms += MergeStats::since(start);
+        }
+
+        let start = stats.start();
+        for (field, doc_values_type) in reader.doc_values_fields() {
+            match doc_values_type {
+                DocValuesType::Numeric => {
+                    let Some(mut doc_values) = reader.numeric_doc_values(field)? else {
+                        continue;
+                    };
+                    let (docs, values) = self.numeric_doc_values.entry(field.to_string()).or_default();
+                    loop {
+                        let doc = doc_values.next_doc()?;
+                        if doc == NO_MORE_DOCS {
+                            break;
+                        }
+                        if let Some(new_doc) = new_docs[doc as usize] {
+                            docs.push(new_doc);
+                            values.push(doc_values.long_value()?);
+                            self.ram_bytes_used += size_of::<u32>() + size_of::<i64>();
+                        }
+                    }
+                }
+                DocValuesType::Binary => {
+                    let Some(mut doc_values) = reader.binary_doc_values(field)? else {
+                        continue;
+                    };
+                    let (docs, values) = self.binary_doc_values.entry(field.to_string()).or_default();
+                    loop {
+                        let doc = doc_values.next_doc()?;
+                        if doc == NO_MORE_DOCS {
+                            break;
+                        }
+                        if let Some(new_doc) = new_docs[doc as usize] {
+                            let value = doc_values.binary_value()?;
+                            docs.push(new_doc);
+                            values.append(value);
+                            self.ram_bytes_used += size_of::<u32>() + size_of::<usize>() + value.len();
+                        }
+                    }
+                }
+            }
+        }
+
+        stats.doc_values += MergeStats::since(start);
+
+        let start = stats.start();
+        for field in reader.vector_fields() {
+            let Some(values) = reader.float_vector_values(field)? else {
+                continue;
+            };
+            self.check_vector_field(field, values.dimension(), values.similarity())?;
+
+            let buffered = self.buffered_vectors(field, values.dimension(), values.similarity());
+            let mut docs = Vec::with_capacity(values.size());
+            for ord in 0..values.size() as u32 {
+                if let Some(new_doc) = new_docs[values.ord_to_doc(ord) as usize] {
+                    buffered.docs.push(new_doc);
+                    buffered.vectors.extend_from_slice(values.vector(ord));
+                    docs.push(new_doc);
+                }
+            }
+            self.ram_bytes_used += docs.len() * (size_of::<u32>() + values.dimension() * size_of::<f32>());
+
+            // Only a complete graph over every vector can be reused, and the largest saves the most insertions.
+            let complete = num_docs == reader.max_doc() && values.graph().size() == values.size();
+            let largest = self.vector_graph_seeds.get(field).is_none_or(|seed| seed.docs.len() < docs.len());
+            if complete && largest && !docs.is_empty() {
+                let seed = VectorGraphSeed {
+                    graph: values.graph().clone(),
+                    docs,
+                };
+                self.vector_graph_seeds.insert(field.to_string(), seed);
+            }
+        }
+        stats.vectors += MergeStats::since(start);
+
+        let start = stats.start();
+        for (doc, new_doc) in new_docs.iter().enumerate() {
+            if new_doc.is_some() {
+                self.add_stored(reader.document(doc as u32)?);
+            }
+        }
+        stats.stored_fields += MergeStats::since(start);
+
+        let start = stats.start();
+        for (doc, new_doc) in new_docs.iter().enumerate() {
+            if new_doc.is_some() {
+                self.add_term_vectors(reader.term_vectors(doc as u32)?.unwrap_or_default());
+            }
+        }
+        stats.term_vectors += MergeStats::since(start);
+
+        self.max_doc += num_docs;
+        stats.segments += 1;
+        stats.docs += num_docs as u64;
+        Ok(num_docs)
+    }
+
+    /// Checks that the vectors of `field` have the dimension and similarity function of those added before.
+    fn check_vector_field(&self, field: &str, dimension: usize, similarity: VectorSimilarityFunction) -> BoxResult<()> {
+        match self.vectors.get(field) {
+            Some(buffered) if buffered.dimension != dimension || buffered.similarity != similarity => {
+                Err(LuceneError::InvalidArgument(format!(
+                    "vector field {field:?} has {dimension} dimensions compared by {similarity}, but earlier \
+                     documents have {} compared by {}",
+                    buffered.dimension, buffered.similarity
+                ))
+                .into())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns the buffered vectors of `field`, which must have been checked with
+    /// [MemorySegmentBuilder::check_vector_field].
+    fn buffered_vectors(
+        &mut self,
+        field: &str,
+        dimension: usize,
+        similarity: VectorSimilarityFunction,
+    ) -> &mut BufferedVectors {
+        self.vectors.entry(field.to_string()).or_insert_with(|| BufferedVectors {
+            similarity,
+            dimension,
+            docs: Vec::new(),
+            vectors: Vec::new(),
+        })
+    }
+
+    /// Records the stored fields of the next document.
+    fn add_stored(&mut self, stored: Document) {
+        self.ram_bytes_used += stored
+            .fields()
+            .iter()
+            .map(|f| size_of::<Field>() + f.name().len() + f.bytes_value().map_or(0, |bytes| bytes.len()))
+            .sum::<usize>();
+        self.stored.push(stored);
+    }
+
+    /// Records the term vectors of the next document.
+    fn add_term_vectors(&mut self, term_vectors: TermVectors) {
+        self.ram_bytes_used += term_vectors.ram_bytes_used();
+        self.term_vectors.push(term_vectors);
+    }
+
+    /// Returns the terms of a field value along with their position increments, start and end offsets, and payloads.
+    fn tokens(analyzer: &dyn Analyzer, field: &Field) -> Vec<FieldToken> {
+        if !field.is_tokenized() {
+            return field
+                .bytes_value()
+                .map(|bytes| vec![(bytes.to_vec(), 1, 0, bytes.len() as u32, Vec::new())])
+                .unwrap_or_default();
+        }
+
+        let Some(text) = field.string_value() else {
+            return Vec::new();
+        };
+
+        analyzer
+            .analyze(field.name(), text)
+            .into_iter()
+            .map(|t| (t.term.into_bytes(), t.position_increment, t.start_offset, t.end_offset, t.payload))
+            .collect()
+    }
+
+    /// Finishes building the segment.
+    pub fn build(self) -> MemorySegment {
+        self.build_with_stats(&mut MergeStats::default())
+    }
+
+    /// Finishes building the segment as [MemorySegmentBuilder::build] does, adding the time spent building it and its
+    /// HNSW graphs to `stats` if it is timed, and counting the graphs that started from a seed.
+    pub fn build_with_stats(mut self, stats: &mut MergeStats) -> MemorySegment {
+        #[cfg(feature = "tracing")]
+        let _span =
+            tracing::debug_span!("flush", segment = self.name.as_deref().unwrap_or_default(), docs = self.max_doc)
+                .entered();
+
+        let start = self.metrics.as_ref().map(|_| Instant::now());
+        let build_start = stats.start();
+        let mut postings = std::mem::take(&mut self.postings).into_postings();
+        let index_sort = self.index_sort.take().map(|(sort, keys)| {
+            self.sort_documents(&keys, &mut postings);
+            sort
+        });
+
+        let max_doc = self.max_doc;
+        let terms = postings
+            .into_iter()
+            .map(|(field, postings)| {
+                let format = self.terms_formats.get(&field).copied().unwrap_or_default();
+                let terms = FieldTerms::new(format, postings);
+                (field, terms)
+            })
+            .collect();
+        let norms = self
+            .norms
+            .into_iter()
+            .map(|(field, mut norms)| {
+                norms.resize(max_doc as usize, 0);
+                (field, norms.into())
+            })
+            .collect();
+
+        let numeric_doc_values = self
+            .numeric_doc_values
+            .into_iter()
+            .map(|(field, (docs, values))| {
+                let blocks = DocValuesSkipBlock::build(&docs, &values, DEFAULT_SKIP_INDEX_INTERVAL);
+                (field, (docs.into(), values.into(), blocks.into()))
+            })
+            .collect();
+        let binary_doc_values = self
+            .binary_doc_values
+            .into_iter()
+            .map(|(field, (docs, values))| (field, (docs.into(), values.iter().map(<[u8]>::to_vec).collect())))
+            .collect();
+        stats.build += MergeStats::since(build_start);
+
+        let vectors_start = stats.start();
+        let mut vector_graph_seeds = std::mem::take(&mut self.vector_graph_seeds);
+        let vectors = std::mem::take(&mut self.vectors)
+            .into_iter()
+            .map(|(field, buffered)| {
+                let seed = vector_graph_seeds.remove(&field).filter(|_| self.seed_vector_graphs);
+                stats.seeded_vector_graphs += seed.is_some() as usize;
+                (field, buffered.build(seed))
+            })
+            .collect();
+        stats.vectors += MergeStats::since(vectors_start);
+
+        if let (Some(metrics), Some(start)) = (&self.metrics, start) {
+            metrics.increment_counter(FLUSH_COUNT, 1);
+            metrics.record_histogram(FLUSH_LATENCY_SECONDS, start.elapsed().as_secs_f64());
+            metrics.record_histogram(FLUSH_DOCS, max_doc as f64);
+        }
+
+        MemorySegment {
+            name: self.name,
+            max_doc,
+            terms,
+            norms,
+            numeric_doc_values,
+            binary_doc_values,
+            vectors,
+            stored: self.stored,
+            term_vectors: self.term_vectors,
+            index_sort,
+            cache_helper: CacheHelper::new(),
+        }
+    }
+
+    /// Renumbers the documents added so far in the order given by `keys`.
+    fn sort_documents(
+        &mut self,
+        keys: &[SortKey],
+        postings: &mut BTreeMap<String, BTreeMap<Vec<u8>, Vec<MemoryPosting>>>,
+    ) {
+        let mut doc_values: Vec<Vec<Option<i64>>> = vec![Vec::with_capacity(keys.len()); self.max_doc as usize];
+        for key in keys {
+            let column = key.field.as_ref().and_then(|field| self.numeric_doc_values.get(field));
+            for (doc, value) in doc_values.iter_mut().enumerate() {
+                value.push(column.and_then(|(docs, values)| docs.binary_search(&(doc as u32)).ok().map(|i| values[i])));
+            }
+        }
+        let doc_map = DocMap::sort_values(keys, doc_values);
+
+        for postings in postings.values_mut().flat_map(|terms| terms.values_mut()) {
+            for posting in postings.iter_mut() {
+                posting.doc = doc_map.old_to_new(posting.doc);
+            }
+            postings.sort_by_key(|posting| posting.doc);
+        }
+
+        for norms in self.norms.values_mut() {
+            let mut sorted = vec![0; self.max_doc as usize];
+            for (old_doc, norm) in norms.iter().enumerate() {
+                sorted[doc_map.old_to_new(old_doc as u32) as usize] = *norm;
+            }
+            *norms = sorted;
+        }
+
+        for (docs, values) in self.numeric_doc_values.values_mut() {
+            let mut column: Vec<(u32, i64)> =
+                docs.iter().zip(values.iter()).map(|(&doc, &value)| (doc_map.old_to_new(doc), value)).collect();
+            column.sort_by_key(|(doc, _)| *doc);
+            (*docs, *values) = column.into_iter().unzip();
+        }
+
+        for (docs, values) in self.binary_doc_values.values_mut() {
+            let mut column: Vec<(u32, usize)> =
+                docs.iter().enumerate().map(|(i, &doc)| (doc_map.old_to_new(doc), i)).collect();
+            column.sort_by_key(|(doc, _)| *doc);
+            *docs = column.iter().map(|(doc, _)| *doc).collect();
+            *values = column.iter().map(|&(_, i)| values.get(i)).collect();
+        }
+
+        for buffered in self.vectors.values_mut() {
+            let mut ords: Vec<(u32, usize)> =
+                buffered.docs.iter().enumerate().map(|(ord, &doc)| (doc_map.old_to_new(doc), ord)).collect();
+            ords.sort_by_key(|(doc, _)| *doc);
+            buffered.docs = ords.iter().map(|(doc, _)| *doc).collect();
+            buffered.vectors = ords
+                .iter()
+                .flat_map(|&(_, ord)| &buffered.vectors[ord * buffered.dimension..(ord + 1) * buffered.dimension])
+                .copied()
+                .collect();
+        }
+        for seed in self.vector_graph_seeds.values_mut() {
+            for doc in seed.docs.iter_mut() {
+                *doc = doc_map.old_to_new(*doc);
+            }
+        }
+
+        let mut stored: Vec<Option<Document>> = std::mem::take(&mut self.stored).into_iter().map(Some).collect();
+        self.stored =
+            (0..self.max_doc).map(|doc| stored[doc_map.new_to_old(doc) as usize].take().unwrap_or_default()).collect();
+
+        let mut term_vectors: Vec<Option<TermVectors>> =
+            std::mem::take(&mut self.term_vectors).into_iter().map(Some).collect();
+        self.term_vectors = (0..self.max_doc)
+            .map(|doc| term_vectors[doc_map.new_to_old(doc) as usize].take().unwrap_or_default())
+            .collect();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{
+            analysis::SimpleAnalyzer,
+            document::{Document, Field, Store, TermVectorOptions},
+            index::{
+                IndexReader, LeafReader, MemorySegmentBuilder, MultiReader, Term, TermsFormat, VectorSimilarityFunction,
+            },
+            search::{
+                BM25Similarity, BasicSortField, CollectionStatistics, FieldInvertState, SimScorer, Similarity, Sort,
+                TermStatistics, NO_MORE_DOCS,
+            },
+            util::{Accountable, NamedAccountable},
+        },
+        pretty_assertions::assert_eq,
+        std::{collections::HashMap, sync::Arc},
+    };
+
+    /// Records the distinct and most frequent terms of a field in its norm.
+    #[derive(Debug)]
+    struct TermCountSimilarity;
+
+    impl Similarity for TermCountSimilarity {
+        fn compute_norm(&self, state: &FieldInvertState) -> i64 {
+            (state.unique_term_count * 100 + state.max_term_frequency) as i64
+        }
+
+        fn scorer(&self, boost: f32, cs: &CollectionStatistics, ts: &[TermStatistics]) -> Box<dyn SimScorer> {
+            BM25Similarity::default().scorer(boost, cs, ts)
+        }
+    }
+
+    #[test]
+    fn test_build_segment() {
+        let mut builder = MemorySegmentBuilder::new(Arc::new(SimpleAnalyzer));
+        for (id, body) in [("a", "The quick fox"), ("b", "the lazy dog and the fox")] {
+            let mut doc = Document::new();
+            doc.add(Field::string("id", id, Store::Yes));
+            doc.add(Field::text("body", body, Store::No));
+            builder.add_document(&doc).unwrap();
+        }
+        let segment = builder.build();
+        assert_eq!(segment.max_doc(), 2);
+
+        let body = segment.terms("body").unwrap().unwrap();
+        assert_eq!(body.doc_count(), 2);
+        assert_eq!(body.sum_total_term_freq(), 9);
+
+        let mut te = body.iterator().unwrap();
+        assert!(te.seek_exact(b"the").unwrap());
+        let mut postings = te.postings().unwrap();
+        assert_eq!(postings.advance(1).unwrap(), 1);
+        assert_eq!(postings.freq().unwrap(), 2);
+        assert_eq!(postings.next_position().unwrap(), Some(0));
+        assert_eq!(postings.next_position().unwrap(), Some(4));
+
+        assert_eq!(&*segment.norms("body").unwrap().unwrap(), &[3, 6]);
+        assert!(segment.norms("id").unwrap().is_none());
+        assert_eq!(segment.document(1).unwrap().get("id"), Some("b"));
+        assert!(segment.document(2).is_err());
+    }
+
+    #[test]
+    fn test_ram_bytes_used() {
+        let mut builder = MemorySegmentBuilder::new(Arc::new(SimpleAnalyzer));
+        for i in 0..100 {
+            let mut doc = Document::new();
+            doc.add(Field::text("body", format!("document number {i}"), Store::Yes));
+            doc.add(Field::numeric_doc_values("rank", i));
+            doc.add(Field::knn_vector("embedding", vec![i as f32, 1.0], VectorSimilarityFunction::Euclidean).unwrap());
+            builder.add_document(&doc).unwrap();
+        }
+        let segment = Arc::new(builder.build());
+
+        let children = segment.child_resources();
+        let names: Vec<&str> = children.iter().map(NamedAccountable::name).collect();
+        assert_eq!(names, ["terms", "norms", "doc values", "vectors", "stored fields", "term vectors"]);
+        assert!(children.iter().all(|child| child.ram_bytes_used() > 0));
+        assert_eq!(
+            segment.ram_bytes_used(),
+            size_of_val(segment.as_ref()) + children.iter().map(Accountable::ram_bytes_used).sum::<usize>()
+        );
+
+        let reader = MultiReader::new(vec![segment.clone() as Arc<dyn LeafReader>, segment.clone()]).unwrap();
+        assert!(reader.ram_bytes_used() > 2 * segment.ram_bytes_used());
+        let report = NamedAccountable::new("index", &reader).to_string();
+        assert!(report.starts_with("index: "), "{report}");
+        assert!(report.contains("\n|-- segment 1: "), "{report}");
+        assert!(report.contains("\n    |-- stored fields: "), "{report}");
+    }
+
+    #[test]
+    fn test_similarity_norms() {
+        let mut builder = MemorySegmentBuilder::new(Arc::new(SimpleAnalyzer));
+        builder.set_similarity(Arc::new(TermCountSimilarity));
+        for body in ["The quick fox", "the lazy dog and the fox"] {
+            let mut doc = Document::new();
+            doc.add(Field::text("body", body, Store::No));
+            doc.add(Field::text("title", "fox fox", Store::No));
+            builder.add_document(&doc).unwrap();
+        }
+        let segment = builder.build();
+
+        assert_eq!(&*segment.norms("body").unwrap().unwrap(), &[301, 502]);
+        assert_eq!(&*segment.norms("title").unwrap().unwrap(), &[102, 102]);
+    }
+
+    #[test]
+    fn test_numeric_doc_values() {
+        let mut builder = MemorySegmentBuilder::new(Arc::new(SimpleAnalyzer));
+        for value in [Some(5), None, Some(-3)] {
+            let mut doc = Document::new();
+            if let Some(value) = value {
+                doc.add(Field::numeric_doc_values("price", value));
+            }
+            builder.add_document(&doc).unwrap();
+        }
+
+        let mut doc = Document::new();
+        doc.add(Field::numeric_doc_values("price", 1));
+        doc.add(Field::numeric_doc_values("price", 2));
+        assert!(builder.add_document(&doc).is_err());
+        assert_eq!(builder.max_doc(), 3);
+
+        let segment = builder.build();
+        let mut dv = segment.numeric_doc_values("price").unwrap().unwrap();
+        assert!(dv.advance_exact(0).unwrap());
+        assert_eq!(dv.long_value().unwrap(), 5);
+        assert!(!dv.advance_exact(1).unwrap());
+        assert!(dv.advance_exact(2).unwrap());
+        assert_eq!(dv.long_value().unwrap(), -3);
+        assert!(segment.numeric_doc_values("missing").unwrap().is_none());
+        assert!(segment.terms("price").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_binary_doc_values() {
+        let mut builder = MemorySegmentBuilder::new(Arc::new(SimpleAnalyzer));
+        for value in [Some(&b"first"[..]), None, Some(&b"third"[..])] {
+            let mut doc = Document::new();
+            if let Some(value) = value {
+                doc.add(Field::binary_doc_values("shape", value));
+            }
+            builder.add_document(&doc).unwrap();
+        }
+
+        let mut doc = Document::new();
+        doc.add(Field::binary_doc_values("shape", b"a".to_vec()));
+        doc.add(Field::binary_doc_values("shape", b"b".to_vec()));
+        assert!(builder.add_document(&doc).is_err());
+
+        let segment = builder.build();
+        let mut dv = segment.binary_doc_values("shape").unwrap().unwrap();
+        assert!(dv.advance_exact(0).unwrap());
+        assert_eq!(dv.binary_value().unwrap(), b"first");
+        assert!(!dv.advance_exact(1).unwrap());
+        assert!(dv.advance_exact(2).unwrap());
+        assert_eq!(dv.binary_value().unwrap(), b"third");
+        assert!(segment.binary_doc_values("missing").unwrap().is_none());
+        assert!(segment.numeric_doc_values("shape").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_term_vectors() {
+        let mut builder = MemorySegmentBuilder::new(Arc::new(SimpleAnalyzer));
+        builder
+            .set_index_sort(Sort::from_fields(vec![Box::new(BasicSortField::for_i64_field("rank", None))]).unwrap())
+            .unwrap();
+
+        let mut doc = Document::new();
+        for body in ["Quick fox", "lazy fox"] {
+            let field = Field::text("body", body, Store::No);
+            doc.add(field.with_term_vectors(TermVectorOptions::POSITIONS_AND_OFFSETS).unwrap());
+        }
+        doc.add(Field::text("title", "fox", Store::No));
+        doc.add(Field::numeric_doc_values("rank", 2));
+        builder.add_document(&doc).unwrap();
+
+        let mut doc = Document::new();
+        doc.add(Field::text("body", "fox", Store::No));
+        doc.add(Field::numeric_doc_values("rank", 1));
+        builder.add_document(&doc).unwrap();
+
+        // All values of a field must store the same term vectors.
+        let mut doc = Document::new();
+        doc.add(Field::text("body", "fox", Store::No).with_term_vectors(TermVectorOptions::default()).unwrap());
+        doc.add(Field::text("body", "dog", Store::No));
+        assert!(builder.add_document(&doc).is_err());
+        assert!(Field::stored("body", "fox").with_term_vectors(TermVectorOptions::default()).is_err());
+
+        let segment = builder.build();
+        assert!(segment.term_vectors(0).unwrap().is_none());
+        assert!(segment.term_vectors(2).is_err());
+
+        let term_vectors = segment.term_vectors(1).unwrap().unwrap();
+        assert_eq!(term_vectors.iter().map(|(field, _)| field).collect::<Vec<_>>(), vec!["body"]);
+        let body = term_vectors.get("body").unwrap();
+        let terms: Vec<&[u8]> = body.terms().iter().map(|term| term.term.as_slice()).collect();
+        assert_eq!(terms, vec![&b"fox"[..], b"lazy", b"quick"]);
+        let fox = body.get(b"fox").unwrap();
+        assert_eq!(fox.freq, 2);
+        assert_eq!(fox.positions, vec![1, 3]);
+        assert_eq!(fox.offsets, vec![(6, 9), (15, 18)]);
+        assert!(fox.payloads.is_empty());
+
+        let mut builder = MemorySegmentBuilder::new(Arc::new(SimpleAnalyzer));
+        builder.add_reader(&segment).unwrap();
+        let copy = builder.build();
+        assert!(copy.term_vectors(0).unwrap().is_none());
+        assert_eq!(copy.term_vectors(1).unwrap(), Some(term_vectors));
+    }
+
+    #[test]
+    fn test_postings_offsets() {
+        let mut builder = MemorySegmentBuilder::new(Arc::new(SimpleAnalyzer));
+        builder.set_terms_formats(HashMap::from([("body".to_string(), TermsFormat::Fst)]));
+        let mut doc = Document::new();
+        for body in ["Quick fox", "lazy fox"] {
+            doc.add(Field::text("body", body, Store::No).with_offsets().unwrap());
+        }
+        doc.add(Field::text("title", "fox", Store::No));
+        builder.add_document(&doc).unwrap();
+
+        let mut doc = Document::new();
+        doc.add(Field::text("body", "fox", Store::No).with_offsets().unwrap());
+        builder.add_document(&doc).unwrap();
+
+        // All values of a field must index offsets alike.
+        let mut doc = Document::new();
+        doc.add(Field::text("body", "fox", Store::No).with_offsets().unwrap());
+        doc.add(Field::text("body", "dog", Store::No));
+        assert!(builder.add_document(&doc).is_err());
+        assert!(Field::stored("body", "fox").with_offsets().is_err());
+
+        let segment = builder.build();
+        assert!(segment.term_vectors(0).unwrap().is_none());
+        assert!(!segment.terms("title").unwrap().unwrap().has_offsets());
+
+        let fox_offsets = |reader: &dyn LeafReader| {
+            let terms = reader.terms("body").unwrap().unwrap();
+            assert!(terms.has_offsets());
+            let mut te = terms.iterator().unwrap();
+            assert!(te.seek_exact(b"fox").unwrap());
+            let mut postings = te.postings().unwrap();
+            let mut offsets = Vec::new();
+            while postings.next_doc().unwrap() != NO_MORE_DOCS {
+                while let Some(position) = postings.next_position().unwrap() {
+                    let (start, end) = (postings.start_offset().unwrap(), postings.end_offset().unwrap());
+                    offsets.push((postings.doc_id(), position, start.unwrap(), end.unwrap()));
+                }
+            }
+            offsets
+        };
+        let expected = vec![(0, 1, 6, 9), (0, 3, 15, 18), (1, 0, 0, 3)];
+        assert_eq!(fox_offsets(&segment), expected);
+
+        // Offsets are kept when segments are merged.
+        let mut builder = MemorySegmentBuilder::new(Arc::new(SimpleAnalyzer));
+        builder.add_reader(&segment).unwrap();
+        assert_eq!(fox_offsets(&builder.build()), expected);
+    }
+
+    #[test]
+    fn test_terms_formats() {
+        let build = |formats: &[(&str, TermsFormat)], duplicate: bool| {
+            let mut builder = MemorySegmentBuilder::new(Arc::new(SimpleAnalyzer));
+            builder.set_terms_formats(formats.iter().map(|(field, format)| (field.to_string(), *format)).collect());
+            for i in 0..100 {
+                let mut doc = Document::new();
+                let id = if duplicate && i == 99 {
+                    "doc0".to_string()
+                } else {
+                    format!("doc{i}")
+                };
+                doc.add(Field::string("id", id, Store::No));
+                doc.add(Field::text("body", format!("some text number {i}"), Store::No));
+                builder.add_document(&doc).unwrap();
+            }
+            builder.build()
+        };
+
+        let segment = build(&[("id", TermsFormat::UniqueKeys), ("body", TermsFormat::Fst)], false);
+        let mut fields = segment.indexed_fields();
+        fields.sort_unstable();
+        assert_eq!(fields, vec!["body", "id"]);
+
+        // The ids are held compactly, as are the body's terms, which share their prefixes in the FST.
+        let ids = segment.terms("id").unwrap().unwrap();
+        assert!(!ids.has_freqs());
+        assert_eq!(ids.size(), Some(100));
+        let body = segment.terms("body").unwrap().unwrap();
+        assert!(body.has_positions());
+        assert_eq!(body.size(), Some(103));
+        assert!(segment.ram_bytes_used() < build(&[], false).ram_bytes_used());
+
+        // Ids that aren't unique are held like any other field.
+        assert!(build(&[("id", TermsFormat::UniqueKeys)], true).terms("id").unwrap().unwrap().has_freqs());
+
+        // Merging keeps the terms searchable.
+        let mut builder = MemorySegmentBuilder::new(Arc::new(SimpleAnalyzer));
+        builder.set_terms_formats(HashMap::from([("id".to_string(), TermsFormat::Fst)]));
+        builder.add_reader(&segment).unwrap();
+        let reader = MultiReader::new(vec![Arc::new(segment), Arc::new(builder.build())]).unwrap();
+        assert_eq!(reader.doc_freq(&Term::new("id", "doc42")).unwrap(), 2);
+        assert_eq!(reader.doc_freq(&Term::new("id", "doc420")).unwrap(), 0);
+        assert_eq!(reader.doc_freq(&Term::new("body", "number")).unwrap(), 200);
+        assert_eq!(reader.doc_freq(&Term::new("body", "42")).unwrap(), 2);
+    }
+}
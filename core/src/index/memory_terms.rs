@@ -0,0 +1,448 @@
+use {
+    crate::{
+        index::{PostingsEnum, SeekStatus, Terms, TermsEnum},
+        search::{DocIdSetIterator, NO_MORE_DOCS},
+        util::{size_of_vec, Accountable},
+        BoxResult,
+    },
+    std::{
+        collections::{BTreeMap, HashSet},
+        sync::Arc,
+    },
+};
+
+/// Statistics for a single term held by [MemoryTerms].
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct TermStats {
+    /// The number of documents containing the term.
+    pub doc_freq: u32,
+
+    /// The total number of occurrences of the term across all documents.
+    pub total_term_freq: u64,
+}
+
+/// The occurrences of a term within a single document, as held by [MemoryTerms].
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct MemoryPosting {
+    /// The document id.
+    pub doc: u32,
+
+    /// The number of occurrences of the term in the document.
+    pub freq: u32,
+
+    /// The positions of the occurrences, in increasing order, if positions are indexed.
+    pub positions: Vec<u32>,
+
+    /// The payload of each occurrence, if any occurrence has one; empty otherwise. Occurrences without a payload have
+    /// an empty one.
+    pub payloads: Vec<Vec<u8>>,
+
+    /// The start and end offsets of each occurrence, if offsets are indexed; empty otherwise.
+    pub offsets: Vec<(u32, u32)>,
+}
+
+impl MemoryPosting {
+    /// Creates a posting for a document with the given term positions.
+    pub fn with_positions(doc: u32, positions: Vec<u32>) -> Self {
+        Self {
+            doc,
+            freq: positions.len() as u32,
+            positions,
+            payloads: Vec::new(),
+            offsets: Vec::new(),
+        }
+    }
+
+    /// Creates a posting for a document with the given term positions and the payload at each.
+    pub fn with_payloads(doc: u32, positions: Vec<u32>, payloads: Vec<Vec<u8>>) -> Self {
+        debug_assert!(payloads.is_empty() || payloads.len() == positions.len());
+        Self {
+            payloads,
+            ..Self::with_positions(doc, positions)
+        }
+    }
+
+    /// Returns this posting with the start and end offsets of each of its positions.
+    pub fn with_offsets(self, offsets: Vec<(u32, u32)>) -> Self {
+        debug_assert!(offsets.is_empty() || offsets.len() == self.positions.len());
+        Self {
+            offsets,
+            ..self
+        }
+    }
+
+    /// Creates a posting for a document with the given term frequency and no positions.
+    pub fn with_freq(doc: u32, freq: u32) -> Self {
+        Self {
+            doc,
+            freq,
+            positions: Vec::new(),
+            payloads: Vec::new(),
+            offsets: Vec::new(),
+        }
+    }
+}
+
+impl Accountable for MemoryPosting {
+    fn ram_bytes_used(&self) -> usize {
+        size_of::<Self>()
+            + size_of_vec(&self.positions)
+            + size_of_vec(&self.payloads)
+            + size_of_vec(&self.offsets)
+            + self.payloads.iter().map(Vec::capacity).sum::<usize>()
+    }
+}
+
+/// A [Terms] implementation over a sorted, in-memory list of terms.
+#[derive(Clone, Debug, Default)]
+pub struct MemoryTerms {
+    terms: Vec<Vec<u8>>,
+    stats: Vec<TermStats>,
+    postings: Vec<Arc<[MemoryPosting]>>,
+    doc_count: u32,
+    sum_doc_freq: u64,
+    sum_total_term_freq: u64,
+    has_freqs: bool,
+    has_positions: bool,
+    has_offsets: bool,
+}
+
+impl MemoryTerms {
+    /// Creates a new set of terms. The terms may be given in any order; statistics for duplicate terms are added
+    /// together. `doc_count` is the number of documents with at least one term in the field.
+    pub fn new<I: IntoIterator<Item = (Vec<u8>, TermStats)>>(terms: I, doc_count: u32) -> Self {
+        let mut sorted: BTreeMap<Vec<u8>, TermStats> = BTreeMap::new();
+        for (term, stats) in terms {
+            let entry = sorted.entry(term).or_default();
+            entry.doc_freq += stats.doc_freq;
+            entry.total_term_freq += stats.total_term_freq;
+        }
+
+        let sum_doc_freq = sorted.values().map(|s| s.doc_freq as u64).sum();
+        let sum_total_term_freq = sorted.values().map(|s| s.total_term_freq).sum();
+        let (terms, stats): (Vec<_>, Vec<_>) = sorted.into_iter().unzip();
+        let postings = vec![Arc::from(Vec::new()); terms.len()];
+
+        Self {
+            terms,
+            stats,
+            postings,
+            doc_count,
+            sum_doc_freq,
+            sum_total_term_freq,
+            has_freqs: true,
+            has_positions: false,
+            has_offsets: false,
+        }
+    }
+
+    /// Creates a new set of terms with postings. Statistics are computed from the postings; the postings for each
+    /// term are sorted by document, and those for duplicate terms are merged. The terms have offsets if any posting
+    /// does.
+    pub fn from_postings<I: IntoIterator<Item = (Vec<u8>, Vec<MemoryPosting>)>>(terms: I, has_positions: bool) -> Self {
+        let mut sorted: BTreeMap<Vec<u8>, Vec<MemoryPosting>> = BTreeMap::new();
+        for (term, postings) in terms {
+            sorted.entry(term).or_default().extend(postings);
+        }
+
+        let mut docs = HashSet::new();
+        let mut result = Self {
+            has_freqs: true,
+            has_positions,
+            ..Default::default()
+        };
+
+        for (term, mut postings) in sorted {
+            postings.sort_by_key(|p| p.doc);
+            let stats = TermStats {
+                doc_freq: postings.len() as u32,
+                total_term_freq: postings.iter().map(|p| p.freq as u64).sum(),
+            };
+            docs.extend(postings.iter().map(|p| p.doc));
+            result.has_offsets |= postings.iter().any(|p| !p.offsets.is_empty());
+            result.sum_doc_freq += stats.doc_freq as u64;
+            result.sum_total_term_freq += stats.total_term_freq;
+            result.terms.push(term);
+            result.stats.push(stats);
+            result.postings.push(postings.into());
+        }
+
+        result.doc_count = docs.len() as u32;
+        result
+    }
+
+    /// Creates a new set of terms from plain strings, each occurring once in a single document.
+    pub fn from_strs<S: AsRef<str>>(terms: &[S]) -> Self {
+        let stats = TermStats {
+            doc_freq: 1,
+            total_term_freq: 1,
+        };
+        Self::new(terms.iter().map(|t| (t.as_ref().as_bytes().to_vec(), stats)), terms.len() as u32)
+    }
+}
+
+impl Accountable for MemoryTerms {
+    fn ram_bytes_used(&self) -> usize {
+        size_of::<Self>()
+            + size_of_vec(&self.terms)
+            + self.terms.iter().map(Vec::capacity).sum::<usize>()
+            + size_of_vec(&self.stats)
+            + size_of_vec(&self.postings)
+            + self
+                .postings
+                .iter()
+                .flat_map(|postings| postings.iter())
+                .map(MemoryPosting::ram_bytes_used)
+                .sum::<usize>()
+    }
+}
+
+impl Terms for MemoryTerms {
+    fn iterator(&self) -> BoxResult<Box<dyn TermsEnum + '_>> {
+        Ok(Box::new(MemoryTermsEnum {
+            terms: self,
+            ord: None,
+        }))
+    }
+
+    #[inline]
+    fn size(&self) -> Option<u64> {
+        Some(self.terms.len() as u64)
+    }
+
+    #[inline]
+    fn sum_total_term_freq(&self) -> u64 {
+        self.sum_total_term_freq
+    }
+
+    #[inline]
+    fn sum_doc_freq(&self) -> u64 {
+        self.sum_doc_freq
+    }
+
+    #[inline]
+    fn doc_count(&self) -> u32 {
+        self.doc_count
+    }
+
+    #[inline]
+    fn has_freqs(&self) -> bool {
+        self.has_freqs
+    }
+
+    #[inline]
+    fn has_positions(&self) -> bool {
+        self.has_positions
+    }
+
+    #[inline]
+    fn has_offsets(&self) -> bool {
+        self.has_offsets
+    }
+}
+
+/// The [TermsEnum] returned by [MemoryTerms::iterator].
+#[derive(Debug)]
+pub struct MemoryTermsEnum<'a> {
+    terms: &'a MemoryTerms,
+    ord: Option<usize>,
+}
+
+impl MemoryTermsEnum<'_> {
+    fn current(&self) -> usize {
+        self.ord.expect("TermsEnum is unpositioned")
+    }
+}
+
+impl TermsEnum for MemoryTermsEnum<'_> {
+    fn next(&mut self) -> BoxResult<Option<&[u8]>> {
+        let next = self.ord.map_or(0, |ord| ord + 1);
+        if next >= self.terms.terms.len() {
+            self.ord = Some(self.terms.terms.len());
+            return Ok(None);
+        }
+
+        self.ord = Some(next);
+        Ok(Some(&self.terms.terms[next]))
+    }
+
+    fn term(&self) -> &[u8] {
+        &self.terms.terms[self.current()]
+    }
+
+    fn seek_ceil(&mut self, target: &[u8]) -> BoxResult<SeekStatus> {
+        match self.terms.terms.binary_search_by(|t| t.as_slice().cmp(target)) {
+            Ok(ord) => {
+                self.ord = Some(ord);
+                Ok(SeekStatus::Found)
+            }
+            Err(ord) if ord < self.terms.terms.len() => {
+                self.ord = Some(ord);
+                Ok(SeekStatus::NotFound)
+            }
+            Err(_) => {
+                self.ord = None;
+                Ok(SeekStatus::End)
+            }
+        }
+    }
+
+    fn doc_freq(&self) -> BoxResult<u32> {
+        Ok(self.terms.stats[self.current()].doc_freq)
+    }
+
+    fn total_term_freq(&self) -> BoxResult<u64> {
+        Ok(self.terms.stats[self.current()].total_term_freq)
+    }
+
+    fn postings(&self) -> BoxResult<Box<dyn PostingsEnum>> {
+        Ok(Box::new(MemoryPostingsEnum::new(self.terms.postings[self.current()].clone())))
+    }
+}
+
+/// The [PostingsEnum] returned by [MemoryTermsEnum::postings].
+#[derive(Debug)]
+pub struct MemoryPostingsEnum {
+    postings: Arc<[MemoryPosting]>,
+    index: Option<usize>,
+    position: usize,
+}
+
+impl MemoryPostingsEnum {
+    /// Creates an enum over the given postings, which must be sorted by document.
+    pub fn new(postings: Arc<[MemoryPosting]>) -> Self {
+        Self {
+            postings,
+            index: None,
+            position: 0,
+        }
+    }
+
+    fn current(&self) -> &MemoryPosting {
+        &self.postings[self.index.expect("PostingsEnum is unpositioned")]
+    }
+
+    /// Returns the offsets of the position last returned, if offsets were indexed.
+    fn current_offsets(&self) -> Option<(u32, u32)> {
+        self.position.checked_sub(1).and_then(|position| self.current().offsets.get(position)).copied()
+    }
+
+    fn position_at(&mut self, index: usize) -> u32 {
+        self.index = Some(index);
+        self.position = 0;
+        self.doc_id()
+    }
+}
+
+impl DocIdSetIterator for MemoryPostingsEnum {
+    #[inline]
+    fn doc_id(&self) -> u32 {
+        match self.index {
+            Some(index) => self.postings.get(index).map_or(NO_MORE_DOCS, |p| p.doc),
+            None => NO_MORE_DOCS,
+        }
+    }
+
+    fn next_doc(&mut self) -> BoxResult<u32> {
+        let index = self.index.map_or(0, |i| (i + 1).min(self.postings.len()));
+        Ok(self.position_at(index))
+    }
+
+    fn advance(&mut self, target: u32) -> BoxResult<u32> {
+        let start = self.index.map_or(0, |i| (i + 1).min(self.postings.len()));
+        let index = start + self.postings[start..].partition_point(|p| p.doc < target);
+        Ok(self.position_at(index))
+    }
+
+    #[inline]
+    fn cost(&self) -> u64 {
+        self.postings.len() as u64
+    }
+}
+
+impl PostingsEnum for MemoryPostingsEnum {
+    fn freq(&self) -> BoxResult<u32> {
+        Ok(self.current().freq)
+    }
+
+    fn next_position(&mut self) -> BoxResult<Option<u32>> {
+        let position = self.current().positions.get(self.position).copied();
+        self.position += 1;
+        Ok(position)
+    }
+
+    fn payload(&self) -> BoxResult<Option<&[u8]>> {
+        let payload = self.position.checked_sub(1).and_then(|position| self.current().payloads.get(position));
+        Ok(payload.map(Vec::as_slice).filter(|payload| !payload.is_empty()))
+    }
+
+    fn start_offset(&self) -> BoxResult<Option<u32>> {
+        Ok(self.current_offsets().map(|(start, _)| start))
+    }
+
+    fn end_offset(&self) -> BoxResult<Option<u32>> {
+        Ok(self.current_offsets().map(|(_, end)| end))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{
+            index::{MemoryPosting, MemoryTerms, SeekStatus, Terms},
+            search::NO_MORE_DOCS,
+        },
+        pretty_assertions::assert_eq,
+    };
+
+    #[test]
+    fn test_iterate_and_seek() {
+        let terms = MemoryTerms::from_strs(&["cherry", "apple", "banana", "apple"]);
+        assert_eq!(terms.size(), Some(3));
+        assert_eq!(terms.sum_doc_freq(), 4);
+
+        let mut te = terms.iterator().unwrap();
+        let mut seen = Vec::new();
+        while let Some(term) = te.next().unwrap() {
+            seen.push(String::from_utf8(term.to_vec()).unwrap());
+        }
+        assert_eq!(seen, vec!["apple", "banana", "cherry"]);
+
+        assert_eq!(te.seek_ceil(b"b").unwrap(), SeekStatus::NotFound);
+        assert_eq!(te.term(), b"banana");
+        assert_eq!(te.next().unwrap(), Some(&b"cherry"[..]));
+        assert!(te.seek_exact(b"apple").unwrap());
+        assert_eq!(te.doc_freq().unwrap(), 2);
+        assert_eq!(te.seek_ceil(b"zebra").unwrap(), SeekStatus::End);
+    }
+
+    #[test]
+    fn test_postings() {
+        let terms = MemoryTerms::from_postings(
+            [
+                (
+                    b"fox".to_vec(),
+                    vec![MemoryPosting::with_positions(4, vec![1, 5]), MemoryPosting::with_positions(1, vec![0])],
+                ),
+                (b"dog".to_vec(), vec![MemoryPosting::with_positions(2, vec![3])]),
+            ],
+            true,
+        );
+        assert_eq!(terms.doc_count(), 3);
+        assert_eq!(terms.sum_total_term_freq(), 4);
+
+        let mut te = terms.iterator().unwrap();
+        assert!(te.seek_exact(b"fox").unwrap());
+        assert_eq!(te.total_term_freq().unwrap(), 3);
+
+        let mut postings = te.postings().unwrap();
+        assert_eq!(postings.next_doc().unwrap(), 1);
+        assert_eq!(postings.next_doc().unwrap(), 4);
+        assert_eq!(postings.freq().unwrap(), 2);
+        assert_eq!(postings.next_position().unwrap(), Some(1));
+        assert_eq!(postings.next_position().unwrap(), Some(5));
+        assert_eq!(postings.next_position().unwrap(), None);
+        assert_eq!(postings.advance(5).unwrap(), NO_MORE_DOCS);
+    }
+}
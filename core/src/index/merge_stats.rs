@@ -0,0 +1,98 @@
+use std::time::{Duration, Instant};
+
+/// Statistics for the merges run by a force merge (see [DocumentsWriter::force_merge](crate::index::DocumentsWriter)).
+/// Timed statistics (see [MergeStats::timed]) also hold the time spent on each kind of data, so that slow merges can be
+/// attributed to the data structure responsible; the durations of untimed statistics stay zero.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MergeStats {
+    /// The number of merges run, each producing one segment.
+    pub merges: usize,
+
+    /// The number of segments merged.
+    pub segments: usize,
+
+    /// The number of live documents copied to the merged segments.
+    pub docs: u64,
+
+    /// The time spent copying terms and postings.
+    pub postings: Duration,
+
+    /// The time spent copying norms.
+    pub norms: Duration,
+
+    /// The time spent copying numeric and binary doc values.
+    pub doc_values: Duration,
+
+    /// The time spent copying stored fields.
+    pub stored_fields: Duration,
+
+    /// The time spent copying term vectors.
+    pub term_vectors: Duration,
+
+    /// The time spent copying vectors and building the HNSW graphs of the merged segments.
+    pub vectors: Duration,
+
+    /// The number of merged HNSW graphs started from the graph of a merged segment rather than built from scratch.
+    /// See [crate::index::IndexWriterConfig::set_seed_merged_vector_graphs].
+    pub seeded_vector_graphs: usize,
+
+    /// The time spent building the merged segments from the copied data, other than their HNSW graphs.
+    pub build: Duration,
+
+    timed: bool,
+}
+
+impl MergeStats {
+    /// Creates empty statistics that measure the time spent on each kind of data. [MergeStats::default] creates
+    /// statistics that only count, without reading the clock.
+    pub fn timed() -> Self {
+        Self {
+            timed: true,
+            ..Self::default()
+        }
+    }
+
+    /// Indicates whether these statistics measure the time spent on each kind of data.
+    #[inline]
+    pub fn is_timed(&self) -> bool {
+        self.timed
+    }
+
+    /// Starts timing a step of a merge, returning `None` without reading the clock if these statistics are untimed.
+    #[inline]
+    pub(crate) fn start(&self) -> Option<Instant> {
+        self.timed.then(Instant::now)
+    }
+
+    /// Returns the time since a step started with [MergeStats::start], or zero if it wasn't timed.
+    #[inline]
+    pub(crate) fn since(start: Option<Instant>) -> Duration {
+        start.map_or(Duration::ZERO, |start| start.elapsed())
+    }
+
+    /// Adds the statistics of another merge to these.
+    pub fn add(&mut self, other: &MergeStats) {
+        self.merges += other.merges;
+        self.segments += other.segments;
+        self.docs += other.docs;
+        self.postings += other.postings;
+        self.norms += other.norms;
+        self.doc_values += other.doc_values;
+        self.stored_fields += other.stored_fields;
+        self.term_vectors += other.term_vectors;
+        self.vectors += other.vectors;
+        self.seeded_vector_graphs += other.seeded_vector_graphs;
+        self.build += other.build;
+    }
+
+    /// Returns the total time spent merging.
+    pub fn elapsed(&self) -> Duration {
+        self.postings
+            + self.norms
+            + self.doc_values
+            + self.stored_fields
+            + self.term_vectors
+            + self.vectors
+            + self.build
+    }
+}
@@ -0,0 +1,31 @@
+use crate::{search::DocIdSetIterator, BoxResult};
+
+/// Iterates through the postings of a term: the documents containing it, along with the frequency, positions and, if
+/// indexed, offsets of the term within each document.
+pub trait PostingsEnum: DocIdSetIterator {
+    /// Returns the number of occurrences of the term in the current document. If term frequencies were not indexed,
+    /// this returns 1.
+    fn freq(&self) -> BoxResult<u32>;
+
+    /// Returns the next position of the term in the current document, or `None` if positions were not indexed or
+    /// all [PostingsEnum::freq] positions have been returned.
+    fn next_position(&mut self) -> BoxResult<Option<u32>>;
+
+    /// Returns the payload of the position last returned by [PostingsEnum::next_position], or `None` if it has none
+    /// or payloads were not indexed.
+    fn payload(&self) -> BoxResult<Option<&[u8]>> {
+        Ok(None)
+    }
+
+    /// Returns the start offset of the position last returned by [PostingsEnum::next_position] in the original text,
+    /// or `None` if offsets were not indexed for it. See [crate::document::Field::with_offsets].
+    fn start_offset(&self) -> BoxResult<Option<u32>> {
+        Ok(None)
+    }
+
+    /// Returns the end offset of the position last returned by [PostingsEnum::next_position] in the original text,
+    /// or `None` if offsets were not indexed for it.
+    fn end_offset(&self) -> BoxResult<Option<u32>> {
+        Ok(None)
+    }
+}
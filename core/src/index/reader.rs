@@ -1,4 +1,116 @@
-use std::fmt::Debug;
+use {
+    crate::{
+        document::Document,
+        index::{sub_index, IndexCommit, LeafReader, LeafReaderContext, Term, MAX_DOCS},
+        util::{Accountable, NamedAccountable},
+        BoxResult, LuceneError,
+    },
+    std::{fmt::Debug, sync::Arc},
+};
 
 /// Trait for reading a Lucene index (database).
-pub trait IndexReader: Debug {}
+pub trait IndexReader: Accountable + Debug + Send + Sync {
+    /// Returns the segments of the index, in document id order.
+    fn leaves(&self) -> &[LeafReaderContext];
+
+    /// Returns one greater than the largest document id in the index.
+    fn max_doc(&self) -> u32 {
+        self.leaves().iter().map(|leaf| leaf.reader().max_doc()).sum()
+    }
+
+    /// Returns the number of live (not deleted) documents in the index.
+    fn num_docs(&self) -> u32 {
+        self.leaves().iter().map(|leaf| leaf.reader().num_docs()).sum()
+    }
+
+    /// Returns the stored fields of the document with the given global id.
+    fn document(&self, doc: u32) -> BoxResult<Document> {
+        let leaves = self.leaves();
+        let leaf = leaves.get(sub_index(doc, leaves)).filter(|leaf| doc - leaf.doc_base() < leaf.reader().max_doc());
+        match leaf {
+            Some(leaf) => leaf.reader().document(doc - leaf.doc_base()),
+            None => Err(LuceneError::InvalidArgument(format!("document {doc} is out of bounds")).into()),
+        }
+    }
+
+    /// Returns the number of documents containing the given term.
+    fn doc_freq(&self, term: &Term) -> BoxResult<u32> {
+        let mut total = 0;
+        for leaf in self.leaves() {
+            if let Some(terms) = leaf.reader().terms(term.field())? {
+                let mut te = terms.iterator()?;
+                if te.seek_exact(term.bytes())? {
+                    total += te.doc_freq()?;
+                }
+            }
+        }
+        Ok(total)
+    }
+
+    /// Returns the commit this reader was opened on, if any, like Lucene's `DirectoryReader.getIndexCommit`.
+    fn index_commit(&self) -> Option<&IndexCommit> {
+        None
+    }
+}
+
+/// An [IndexReader] composed of a list of segments.
+#[derive(Debug)]
+pub struct MultiReader {
+    leaves: Vec<LeafReaderContext>,
+    index_commit: Option<IndexCommit>,
+}
+
+impl MultiReader {
+    /// Creates a reader over the given segments. This fails with [LuceneError::TooManyDocs] if they hold more than
+    /// [MAX_DOCS] documents in total.
+    pub fn new(readers: Vec<Arc<dyn LeafReader>>) -> BoxResult<Self> {
+        let mut leaves = Vec::with_capacity(readers.len());
+        let mut doc_base = 0u64;
+        for (ord, reader) in readers.into_iter().enumerate() {
+            let max_doc = reader.max_doc() as u64;
+            leaves.push(LeafReaderContext::new(ord, doc_base as u32, reader));
+            doc_base += max_doc;
+            if doc_base > MAX_DOCS as u64 {
+                return Err(LuceneError::TooManyDocs(doc_base).into());
+            }
+        }
+
+        Ok(Self {
+            leaves,
+            index_commit: None,
+        })
+    }
+
+    /// Sets the commit the segments were read from, returned by [IndexReader::index_commit].
+    pub fn set_index_commit(&mut self, index_commit: Option<IndexCommit>) -> &mut Self {
+        self.index_commit = index_commit;
+        self
+    }
+}
+
+impl IndexReader for MultiReader {
+    #[inline]
+    fn leaves(&self) -> &[LeafReaderContext] {
+        &self.leaves
+    }
+
+    #[inline]
+    fn index_commit(&self) -> Option<&IndexCommit> {
+        self.index_commit.as_ref()
+    }
+}
+
+impl Accountable for MultiReader {
+    fn ram_bytes_used(&self) -> usize {
+        size_of::<Self>()
+            + self
+                .leaves
+                .iter()
+                .map(|leaf| size_of::<LeafReaderContext>() + leaf.reader().ram_bytes_used())
+                .sum::<usize>()
+    }
+
+    fn child_resources(&self) -> Vec<NamedAccountable> {
+        self.leaves.iter().map(|leaf| NamedAccountable::new(format!("segment {}", leaf.ord()), leaf.reader())).collect()
+    }
+}
@@ -0,0 +1,703 @@
+use {
+    crate::{
+        analysis::{Analyzer, PerFieldAnalyzerWrapper},
+        document::{Document, FieldValue, Store},
+        index::DocValuesType,
+        BoxResult, LuceneError,
+    },
+    std::{
+        collections::{BTreeMap, HashMap},
+        fmt::{Debug, Formatter, Result as FmtResult},
+        sync::Arc,
+    },
+};
+
+/// The key of the commit user data that a [Schema] is persisted under.
+pub const SCHEMA_USER_DATA_KEY: &str = "schema";
+
+/// The version of the format written by [Schema::encode].
+const SCHEMA_FORMAT_VERSION: &str = "1";
+
+/// The kind of value a field holds.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ValueType {
+    /// A string value.
+    Text,
+
+    /// A binary value.
+    Binary,
+
+    /// A 64-bit integer value.
+    Long,
+
+    /// A vector of floats.
+    FloatVector,
+}
+
+impl ValueType {
+    /// Returns the kind of `value`.
+    pub fn of(value: &FieldValue) -> Self {
+        match value {
+            FieldValue::Text(_) => Self::Text,
+            FieldValue::Binary(_) => Self::Binary,
+            FieldValue::Long(_) => Self::Long,
+            FieldValue::FloatVector(_) => Self::FloatVector,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Text => "text",
+            Self::Binary => "binary",
+            Self::Long => "long",
+            Self::FloatVector => "float_vector",
+        }
+    }
+}
+
+/// How the fields with a given name are indexed and stored, as declared in a [Schema]. The constructors mirror those
+/// of [crate::document::Field].
+#[derive(Clone)]
+pub struct FieldType {
+    value_type: ValueType,
+    indexed: bool,
+    tokenized: bool,
+    stored: bool,
+    doc_values: Option<DocValuesType>,
+    point_num_bytes: Option<usize>,
+    multi_valued: bool,
+    required: bool,
+    analyzer_name: Option<String>,
+    analyzer: Option<Arc<dyn Analyzer>>,
+}
+
+impl FieldType {
+    fn new(value_type: ValueType, indexed: bool, tokenized: bool, stored: bool) -> Self {
+        Self {
+            value_type,
+            indexed,
+            tokenized,
+            stored,
+            doc_values: None,
+            point_num_bytes: None,
+            multi_valued: true,
+            required: false,
+            analyzer_name: None,
+            analyzer: None,
+        }
+    }
+
+    /// The type of [crate::document::Field::text] fields: analyzed text.
+    pub fn text(store: Store) -> Self {
+        Self::new(ValueType::Text, true, true, store == Store::Yes)
+    }
+
+    /// The type of [crate::document::Field::string] fields with values of the given kind: single indexed terms.
+    pub fn string(value_type: ValueType, store: Store) -> Self {
+        Self::new(value_type, true, false, store == Store::Yes)
+    }
+
+    /// The type of [crate::document::Field::stored] fields with values of the given kind.
+    pub fn stored(value_type: ValueType) -> Self {
+        Self::new(value_type, false, false, true)
+    }
+
+    /// The type of [crate::document::Field::numeric_doc_values] fields, which are single-valued.
+    pub fn numeric_doc_values() -> Self {
+        Self {
+            doc_values: Some(DocValuesType::Numeric),
+            multi_valued: false,
+            ..Self::new(ValueType::Long, false, false, false)
+        }
+    }
+
+    /// The type of [crate::document::Field::binary_doc_values] fields, which are single-valued.
+    pub fn binary_doc_values() -> Self {
+        Self {
+            doc_values: Some(DocValuesType::Binary),
+            multi_valued: false,
+            ..Self::new(ValueType::Binary, false, false, false)
+        }
+    }
+
+    /// The type of [crate::document::Field::knn_vector] fields, which are single-valued.
+    pub fn knn_vector() -> Self {
+        Self {
+            multi_valued: false,
+            ..Self::new(ValueType::FloatVector, false, false, false)
+        }
+    }
+
+    /// The type of point fields, such as [crate::document::IpAddressPoint] fields, whose values are indexed as single
+    /// terms of exactly `num_bytes` bytes.
+    pub fn point(num_bytes: usize) -> Self {
+        Self {
+            point_num_bytes: Some(num_bytes),
+            ..Self::new(ValueType::Binary, true, false, false)
+        }
+    }
+
+    /// Sets whether a document may have several fields with this name. Only doc values fields are single-valued by
+    /// default.
+    pub fn with_multi_valued(mut self, multi_valued: bool) -> Self {
+        self.multi_valued = multi_valued;
+        self
+    }
+
+    /// Sets whether every document must have a field with this name. Fields are optional by default.
+    pub fn with_required(mut self, required: bool) -> Self {
+        self.required = required;
+        self
+    }
+
+    /// Analyzes tokenized fields with `analyzer` rather than the writer's, recording `name` in the persisted schema so
+    /// that the index can't be reopened with a different analyzer by mistake.
+    pub fn with_analyzer(mut self, name: &str, analyzer: Arc<dyn Analyzer>) -> Self {
+        self.analyzer_name = Some(name.to_string());
+        self.analyzer = Some(analyzer);
+        self
+    }
+
+    /// Returns the kind of value the fields hold.
+    #[inline]
+    pub fn value_type(&self) -> ValueType {
+        self.value_type
+    }
+
+    /// Indicates whether the fields are indexed.
+    #[inline]
+    pub fn is_indexed(&self) -> bool {
+        self.indexed
+    }
+
+    /// Indicates whether the fields are analyzed into tokens before indexing.
+    #[inline]
+    pub fn is_tokenized(&self) -> bool {
+        self.tokenized
+    }
+
+    /// Indicates whether the fields' values are stored.
+    #[inline]
+    pub fn is_stored(&self) -> bool {
+        self.stored
+    }
+
+    /// Returns the kind of doc values recorded for the fields, if any.
+    #[inline]
+    pub fn doc_values_type(&self) -> Option<DocValuesType> {
+        self.doc_values
+    }
+
+    /// Returns the length of the fields' values if they're points.
+    #[inline]
+    pub fn point_num_bytes(&self) -> Option<usize> {
+        self.point_num_bytes
+    }
+
+    /// Indicates whether a document may have several fields with this name.
+    #[inline]
+    pub fn is_multi_valued(&self) -> bool {
+        self.multi_valued
+    }
+
+    /// Indicates whether every document must have a field with this name.
+    #[inline]
+    pub fn is_required(&self) -> bool {
+        self.required
+    }
+
+    /// Returns the name of the analyzer the fields are analyzed with, if it isn't the writer's.
+    #[inline]
+    pub fn analyzer_name(&self) -> Option<&str> {
+        self.analyzer_name.as_deref()
+    }
+
+    /// Indicates whether fields of both types are laid out the same way in the index. Whether they're stored,
+    /// multi-valued or required may change between schemas.
+    fn same_structure(&self, other: &Self) -> bool {
+        self.value_type == other.value_type
+            && self.indexed == other.indexed
+            && self.tokenized == other.tokenized
+            && self.doc_values == other.doc_values
+            && self.point_num_bytes == other.point_num_bytes
+            && self.analyzer_name == other.analyzer_name
+    }
+
+    /// Describes the type for error messages.
+    fn describe(&self) -> String {
+        let mut description = self.value_type.name().to_string();
+        for (set, name) in [(self.indexed, "indexed"), (self.tokenized, "tokenized"), (self.stored, "stored")] {
+            if set {
+                description += ", ";
+                description += name;
+            }
+        }
+        match self.doc_values {
+            Some(DocValuesType::Numeric) => description += ", numeric doc values",
+            Some(DocValuesType::Binary) => description += ", binary doc values",
+            None => {}
+        }
+        if let Some(num_bytes) = self.point_num_bytes {
+            description += &format!(", {num_bytes}-byte points");
+        }
+        if let Some(analyzer_name) = &self.analyzer_name {
+            description += &format!(", analyzed with {analyzer_name}");
+        }
+        description
+    }
+}
+
+impl Debug for FieldType {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        f.debug_struct("FieldType")
+            .field("value_type", &self.value_type)
+            .field("indexed", &self.indexed)
+            .field("tokenized", &self.tokenized)
+            .field("stored", &self.stored)
+            .field("doc_values", &self.doc_values)
+            .field("point_num_bytes", &self.point_num_bytes)
+            .field("multi_valued", &self.multi_valued)
+            .field("required", &self.required)
+            .field("analyzer_name", &self.analyzer_name)
+            .finish()
+    }
+}
+
+/// Declares the fields of an index and how each is indexed and stored, so that documents that don't fit are rejected
+/// when they're added rather than once their segments are merged or searched.
+///
+/// A schema set with [crate::index::IndexWriterConfig::set_schema] validates every document added to the writer (see
+/// [Schema::validate]), analyzes each field with its own analyzer, if it has one, and is persisted in the user data
+/// of the next commit under [SCHEMA_USER_DATA_KEY]. When the index is opened again, the schema must be compatible with
+/// the persisted one (see [Schema::check_compatible]).
+#[derive(Clone, Debug, Default)]
+pub struct Schema {
+    fields: BTreeMap<String, FieldType>,
+    allow_unknown_fields: bool,
+}
+
+impl Schema {
+    /// Creates a schema without fields, which rejects fields it doesn't declare.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Declares a field. This fails with [LuceneError::InvalidArgument] if it's already declared, or if its type is
+    /// inconsistent, such as a tokenized field that isn't text.
+    pub fn add_field(&mut self, name: &str, field_type: FieldType) -> BoxResult<&mut Self> {
+        if self.fields.contains_key(name) {
+            return Err(LuceneError::InvalidArgument(format!("field {name} is already declared")).into());
+        }
+        if field_type.tokenized && field_type.value_type != ValueType::Text {
+            return Err(LuceneError::InvalidArgument(format!("tokenized field {name} must hold text")).into());
+        }
+        if field_type.analyzer_name.as_ref().is_some_and(|analyzer_name| analyzer_name.is_empty()) {
+            return Err(LuceneError::InvalidArgument(format!("the analyzer of field {name} has an empty name")).into());
+        }
+
+        self.fields.insert(name.to_string(), field_type);
+        Ok(self)
+    }
+
+    /// Returns the type of a field, if it's declared.
+    #[inline]
+    pub fn field(&self, name: &str) -> Option<&FieldType> {
+        self.fields.get(name)
+    }
+
+    /// Returns the declared fields, by name.
+    pub fn fields(&self) -> impl Iterator<Item = (&str, &FieldType)> {
+        self.fields.iter().map(|(name, field_type)| (name.as_str(), field_type))
+    }
+
+    /// Indicates whether fields that aren't declared are accepted.
+    #[inline]
+    pub fn allow_unknown_fields(&self) -> bool {
+        self.allow_unknown_fields
+    }
+
+    /// Sets whether fields that aren't declared are accepted as they are. They're rejected by default.
+    pub fn set_allow_unknown_fields(&mut self, allow_unknown_fields: bool) -> &mut Self {
+        self.allow_unknown_fields = allow_unknown_fields;
+        self
+    }
+
+    /// Checks that a document fits the schema: each of its fields is declared, unless unknown fields are allowed, with
+    /// the same kind of value, indexing, storage and doc values; points have the declared length; single-valued fields
+    /// appear at most once; and required fields appear. This fails with [LuceneError::SchemaViolation] otherwise.
+    pub fn validate(&self, document: &Document) -> BoxResult<()> {
+        let violation = |message: String| -> BoxResult<()> { Err(LuceneError::SchemaViolation(message).into()) };
+
+        let mut counts: HashMap<&str, usize> = HashMap::new();
+        for field in document.fields() {
+            let name = field.name();
+            let Some(field_type) = self.fields.get(name) else {
+                if self.allow_unknown_fields {
+                    continue;
+                }
+                return violation(format!("field {name} isn't declared"));
+            };
+
+            let value_type = ValueType::of(field.value());
+            if value_type != field_type.value_type
+                || field.is_indexed() != field_type.indexed
+                || field.is_tokenized() != field_type.tokenized
+                || field.is_stored() != field_type.stored
+                || field.doc_values_type() != field_type.doc_values
+            {
+                return violation(format!(
+                    "field {name} is declared as {}, but a document has {}{}{}{}",
+                    field_type.describe(),
+                    value_type.name(),
+                    if field.is_indexed() {
+                        ", indexed"
+                    } else {
+                        ""
+                    },
+                    if field.is_tokenized() {
+                        ", tokenized"
+                    } else {
+                        ""
+                    },
+                    if field.is_stored() {
+                        ", stored"
+                    } else {
+                        ""
+                    },
+                ));
+            }
+
+            if let (Some(num_bytes), Some(bytes)) = (field_type.point_num_bytes, field.bytes_value()) {
+                if bytes.len() != num_bytes {
+                    return violation(format!(
+                        "field {name} holds {num_bytes}-byte points, but a document has {} bytes",
+                        bytes.len()
+                    ));
+                }
+            }
+
+            let count = counts.entry(name).or_default();
+            *count += 1;
+            if *count > 1 && !field_type.multi_valued {
+                return violation(format!("field {name} is single-valued, but a document has several values"));
+            }
+        }
+
+        if let Some((name, _)) =
+            self.fields.iter().find(|(name, field_type)| field_type.required && !counts.contains_key(name.as_str()))
+        {
+            return violation(format!("field {name} is required, but a document doesn't have it"));
+        }
+        Ok(())
+    }
+
+    /// Checks that an index written with `previous` can be written with this schema: every field it declares must be
+    /// declared here with the same structure, meaning the same kind of value, indexing, doc values, point length and
+    /// analyzer name. Fields may be added, and may change whether they're stored, multi-valued or required. This fails
+    /// with [LuceneError::SchemaViolation] otherwise.
+    pub fn check_compatible(&self, previous: &Schema) -> BoxResult<()> {
+        for (name, previous_type) in &previous.fields {
+            match self.fields.get(name) {
+                Some(field_type) if field_type.same_structure(previous_type) => {}
+                Some(field_type) => {
+                    return Err(LuceneError::SchemaViolation(format!(
+                        "field {name} was declared as {}, but is now declared as {}",
+                        previous_type.describe(),
+                        field_type.describe()
+                    ))
+                    .into());
+                }
+                None => {
+                    return Err(LuceneError::SchemaViolation(format!(
+                        "field {name} was declared as {}, but is no longer declared",
+                        previous_type.describe()
+                    ))
+                    .into());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns an analyzer that analyzes each field with the analyzer the schema declares for it, if any, and every
+    /// other field with `default`.
+    pub fn wrap_analyzer(&self, default: Arc<dyn Analyzer>) -> Arc<dyn Analyzer> {
+        let mut analyzers =
+            self.fields.iter().filter_map(|(name, field_type)| Some((name, field_type.analyzer.as_ref()?))).peekable();
+        if analyzers.peek().is_none() {
+            return default;
+        }
+
+        let mut wrapper = PerFieldAnalyzerWrapper::new(default);
+        for (name, analyzer) in analyzers {
+            wrapper.set(name, analyzer.clone());
+        }
+        Arc::new(wrapper)
+    }
+
+    /// Encodes the schema for the commit user data. Analyzers are recorded by name, so a decoded schema has none.
+    ///
+    /// The first line holds the format version, and each following line a field, as tab-separated columns: the name,
+    /// the kind of value, flags (`i`ndexed, `t`okenized, `s`tored, `m`ulti-valued and `r`equired), the doc values
+    /// type, the point length and the analyzer name, with the last three empty if there are none. Backslashes, tabs
+    /// and newlines in names are escaped. A last line of `*` allows unknown fields.
+    pub fn encode(&self) -> String {
+        let mut encoded = SCHEMA_FORMAT_VERSION.to_string();
+        for (name, field_type) in &self.fields {
+            let flags: String = [
+                (field_type.indexed, 'i'),
+                (field_type.tokenized, 't'),
+                (field_type.stored, 's'),
+                (field_type.multi_valued, 'm'),
+                (field_type.required, 'r'),
+            ]
+            .into_iter()
+            .filter_map(|(set, flag)| set.then_some(flag))
+            .collect();
+            let doc_values = match field_type.doc_values {
+                Some(DocValuesType::Numeric) => "numeric",
+                Some(DocValuesType::Binary) => "binary",
+                None => "",
+            };
+            encoded += &format!(
+                "\n{}\t{}\t{flags}\t{doc_values}\t{}\t{}",
+                escape(name),
+                field_type.value_type.name(),
+                field_type.point_num_bytes.map(|num_bytes| num_bytes.to_string()).unwrap_or_default(),
+                field_type.analyzer_name.as_deref().map(escape).unwrap_or_default(),
+            );
+        }
+        if self.allow_unknown_fields {
+            encoded += "\n*";
+        }
+        encoded
+    }
+
+    /// Decodes a schema encoded with [Schema::encode]. This fails with [LuceneError::CorruptIndex] if it's malformed.
+    pub fn decode(encoded: &str) -> BoxResult<Self> {
+        let corrupt = |message: String| LuceneError::CorruptIndex(format!("invalid schema: {message}").into());
+
+        let mut lines = encoded.split('\n');
+        let version = lines.next().unwrap_or_default();
+        if version != SCHEMA_FORMAT_VERSION {
+            return Err(corrupt(format!("unknown format version {version:?}")).into());
+        }
+
+        let mut schema = Schema::new();
+        for line in lines {
+            if line == "*" {
+                schema.allow_unknown_fields = true;
+                continue;
+            }
+
+            let columns: Vec<&str> = line.split('\t').collect();
+            let [name, value_type, flags, doc_values, point_num_bytes, analyzer_name] = columns[..] else {
+                return Err(corrupt(format!("expected 6 columns: {line:?}")).into());
+            };
+            let value_type = match value_type {
+                "text" => ValueType::Text,
+                "binary" => ValueType::Binary,
+                "long" => ValueType::Long,
+                "float_vector" => ValueType::FloatVector,
+                _ => return Err(corrupt(format!("unknown value type {value_type:?}")).into()),
+            };
+            let field_type = FieldType {
+                doc_values: match doc_values {
+                    "" => None,
+                    "numeric" => Some(DocValuesType::Numeric),
+                    "binary" => Some(DocValuesType::Binary),
+                    _ => return Err(corrupt(format!("unknown doc values type {doc_values:?}")).into()),
+                },
+                point_num_bytes: match point_num_bytes {
+                    "" => None,
+                    _ => Some(
+                        point_num_bytes
+                            .parse()
+                            .map_err(|_| corrupt(format!("invalid point length {point_num_bytes:?}")))?,
+                    ),
+                },
+                multi_valued: flags.contains('m'),
+                required: flags.contains('r'),
+                analyzer_name: match analyzer_name {
+                    "" => None,
+                    _ => Some(unescape(analyzer_name)),
+                },
+                ..FieldType::new(value_type, flags.contains('i'), flags.contains('t'), flags.contains('s'))
+            };
+            schema.fields.insert(unescape(name), field_type);
+        }
+        Ok(schema)
+    }
+}
+
+/// Escapes the characters that separate the columns and lines of an encoded schema.
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('\t', "\\t").replace('\n', "\\n")
+}
+
+/// Reverses [escape].
+fn unescape(s: &str) -> String {
+    let mut unescaped = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('t') => unescaped.push('\t'),
+            Some('n') => unescaped.push('\n'),
+            Some(escaped) => unescaped.push(escaped),
+            None => unescaped.push('\\'),
+        }
+    }
+    unescaped
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{
+            analysis::CJKBigramAnalyzer,
+            document::{Document, Field, IpAddressPoint, Store},
+            index::{
+                FieldType, IndexWriter, IndexWriterConfig, OpenMode, Schema, ValueType, VectorSimilarityFunction,
+                SCHEMA_USER_DATA_KEY,
+            },
+            io::ByteBuffersDirectory,
+            LuceneError,
+        },
+        pretty_assertions::assert_eq,
+        std::{net::IpAddr, sync::Arc},
+    };
+
+    fn schema() -> Schema {
+        let mut schema = Schema::new();
+        schema
+            .add_field(
+                "id",
+                FieldType::string(ValueType::Text, Store::Yes).with_multi_valued(false).with_required(true),
+            )
+            .unwrap()
+            .add_field("body", FieldType::text(Store::No))
+            .unwrap()
+            .add_field("title", FieldType::text(Store::Yes).with_analyzer("cjk", Arc::new(CJKBigramAnalyzer)))
+            .unwrap()
+            .add_field("price", FieldType::numeric_doc_values())
+            .unwrap()
+            .add_field("ip", FieldType::point(IpAddressPoint::BYTES))
+            .unwrap()
+            .add_field("embedding", FieldType::knn_vector())
+            .unwrap();
+        schema
+    }
+
+    fn document() -> Document {
+        let mut document = Document::new();
+        document.add(Field::string("id", "1", Store::Yes));
+        document.add(Field::text("body", "quick fox", Store::No));
+        document.add(Field::text("title", "東京都", Store::Yes));
+        document.add(Field::numeric_doc_values("price", 10));
+        document.add(IpAddressPoint::new_field("ip", "10.0.0.1".parse::<IpAddr>().unwrap()));
+        document.add(Field::knn_vector("embedding", vec![0.5, 1.0], VectorSimilarityFunction::Cosine).unwrap());
+        document
+    }
+
+    fn assert_violation(schema: &Schema, document: &Document, expected: &str) {
+        let err = schema.validate(document).unwrap_err();
+        let message = match err.downcast_ref::<LuceneError>() {
+            Some(LuceneError::SchemaViolation(message)) => message,
+            _ => panic!("unexpected error: {err}"),
+        };
+        assert!(message.contains(expected), "unexpected message: {message}");
+    }
+
+    #[test]
+    fn test_validate() {
+        let schema = schema();
+        assert!(Schema::new()
+            .add_field("n", FieldType::string(ValueType::Long, Store::No).with_multi_valued(true))
+            .is_ok());
+        assert!(schema.clone().add_field("body", FieldType::text(Store::Yes)).is_err());
+        schema.validate(&document()).unwrap();
+
+        let mut document = document();
+        document.add(Field::text("price", "ten", Store::No));
+        assert_violation(&schema, &document, "field price is declared as long, numeric doc values");
+
+        let mut document = self::document();
+        document.add(Field::string("id", "2", Store::Yes));
+        assert_violation(&schema, &document, "field id is single-valued");
+
+        let document: Document = self::document().fields().iter().filter(|f| f.name() != "id").cloned().collect();
+        assert_violation(&schema, &document, "field id is required");
+
+        let mut document = self::document();
+        document.add(Field::string("ip", vec![1, 2, 3, 4], Store::No));
+        assert_violation(&schema, &document, "16-byte points, but a document has 4 bytes");
+
+        let mut document = self::document();
+        document.add(Field::stored("extra", "value"));
+        assert_violation(&schema, &document, "field extra isn't declared");
+        let mut lenient = schema.clone();
+        lenient.set_allow_unknown_fields(true);
+        lenient.validate(&document).unwrap();
+    }
+
+    #[test]
+    fn test_encode() {
+        let mut schema = schema();
+        schema.add_field("odd\tname\\", FieldType::stored(ValueType::Binary)).unwrap().set_allow_unknown_fields(true);
+        let decoded = Schema::decode(&schema.encode()).unwrap();
+        assert_eq!(decoded.encode(), schema.encode());
+        assert_eq!(decoded.field("title").unwrap().analyzer_name(), Some("cjk"));
+        assert_eq!(decoded.field("odd\tname\\").unwrap().value_type(), ValueType::Binary);
+        assert_eq!(decoded.field("embedding").unwrap().value_type(), ValueType::FloatVector);
+        assert!(decoded.allow_unknown_fields());
+        schema.check_compatible(&decoded).unwrap();
+        assert!(Schema::decode("2").is_err());
+        assert!(Schema::decode("1\nbody\ttext").is_err());
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_persisted_schema() {
+        let dir = ByteBuffersDirectory::new();
+        let mut config = IndexWriterConfig::new();
+        config.set_schema(Some(Arc::new(schema())));
+        let mut writer = IndexWriter::new(Box::new(dir.clone()), config.clone()).await.unwrap();
+        writer.commit().await.unwrap();
+        writer.add_document(&document()).unwrap();
+        assert!(writer.add_document(&Document::new()).is_err());
+        assert_eq!(writer.num_buffered_docs(), 1);
+
+        // The title is analyzed into bigrams by its own analyzer.
+        writer.flush().unwrap();
+        let segment = writer.segments()[0].clone();
+        assert_eq!(segment.terms("title").unwrap().unwrap().size(), Some(2));
+        writer.close().await.unwrap();
+
+        // Adding a field and changing what's stored is compatible; changing how a field is indexed isn't.
+        let mut evolved = schema();
+        evolved.add_field("tags", FieldType::string(ValueType::Text, Store::Yes)).unwrap();
+        config.set_schema(Some(Arc::new(evolved)));
+        let mut writer = IndexWriter::new(Box::new(dir.clone()), config.clone()).await.unwrap();
+        assert!(writer.segment_index().get_user_data()[SCHEMA_USER_DATA_KEY].contains("tags"));
+        writer.close().await.unwrap();
+
+        let mut changed = Schema::new();
+        changed
+            .set_allow_unknown_fields(true)
+            .add_field("body", FieldType::string(ValueType::Text, Store::No))
+            .unwrap();
+        config.set_schema(Some(Arc::new(changed)));
+        let err = IndexWriter::new(Box::new(dir.clone()), config.clone()).await.unwrap_err();
+        assert!(
+            err.to_string().contains("field body was declared as text, indexed, tokenized"),
+            "unexpected error: {err}"
+        );
+
+        // Recreating the index discards the persisted schema.
+        config.set_open_mode(OpenMode::Create);
+        IndexWriter::new(Box::new(dir), config).await.unwrap();
+    }
+}
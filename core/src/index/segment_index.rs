@@ -1,13 +1,16 @@
 use {
     crate::{
-        codec::get_codec,
+        codec::{check_footer, checksum_entire_file, get_codec, write_footer},
         index::{IndexHeader, SegmentCommitInfo, MAX_DOCS},
-        io::{Crc32Reader, Directory, EncodingReadExt},
-        BoxResult, Id, LuceneError, Version,
+        io::{Crc32Reader, Crc32Writer, Directory, EncodingReadExt, EncodingWriteExt, IoContext},
+        locate_corruption, BoxError, BoxResult, ErrorContext, Id, LuceneError, Version, LATEST,
     },
-    log::{debug, error},
-    std::collections::HashMap,
-    tokio::io::AsyncReadExt,
+    log::{debug, error, warn},
+    std::{
+        collections::{BTreeSet, HashMap},
+        ops::AsyncFnMut,
+    },
+    tokio::io::{AsyncReadExt, AsyncWrite, AsyncWriteExt},
 };
 
 /// Index segment file name prefix.
@@ -30,6 +33,9 @@ const SEGMENT_INDEX_VERSION_CURRENT: u32 = SEGMENT_INDEX_VERSION_8_6;
 /// The name of the segment index codec.
 pub const SEGMENT_CODEC_NAME: &str = "segments";
 
+/// The most times [find_segments_file] lists the directory before giving up on an index that keeps changing.
+const MAX_FIND_SEGMENTS_FILE_ATTEMPTS: usize = 10;
+
 /// Information about a Lucene index. This is in the `segments_N` file.
 ///
 /// In the Lucene Java implementation, this is called `SegmentInfos`.
@@ -62,9 +68,38 @@ pub struct SegmentIndex {
 
     /// The Lucene version major that was used to create the index.
     index_created_version_major: u8,
+
+    /// Whether a `pending_segments_N` file has been written by [SegmentIndex::prepare_commit] but not yet renamed.
+    pending_commit: bool,
 }
 
 impl SegmentIndex {
+    /// Creates the segment index of a new index, with no segments, that has never been committed.
+    ///
+    /// This returns an error if `index_created_version_major` is newer than this version of Lucene, or older than
+    /// Lucene 6, the oldest version whose indexes record it.
+    pub fn new(index_created_version_major: u8) -> Result<Self, LuceneError> {
+        if index_created_version_major > LATEST.major() || index_created_version_major < 6 {
+            return Err(LuceneError::InvalidArgument(format!(
+                "index_created_version_major must be between 6 and {}, got {index_created_version_major}",
+                LATEST.major()
+            )));
+        }
+
+        Ok(Self {
+            counter: 0,
+            version: 0,
+            generation: 0,
+            last_generation: 0,
+            user_data: HashMap::new(),
+            segments: Vec::new(),
+            id: Id::random_id(),
+            lucene_version: LATEST,
+            index_created_version_major,
+            pending_commit: false,
+        })
+    }
+
     /// Returns the id of the segment index.
     #[inline]
     pub fn get_id(&self) -> Id {
@@ -119,16 +154,313 @@ impl SegmentIndex {
         &self.segments
     }
 
+    /// Returns the segments of the index for updating, such as to record new deletes.
+    #[inline]
+    pub fn get_segments_mut(&mut self) -> &mut [SegmentCommitInfo] {
+        &mut self.segments
+    }
+
+    /// Sets the opaque user data that will be written with the next commit.
+    pub fn set_user_data(&mut self, user_data: HashMap<String, String>) {
+        self.user_data = user_data;
+        self.changed();
+    }
+
+    /// Adds a segment to the index.
+    ///
+    /// This returns an error if the segment's codec isn't set, or if it doesn't record the minimum version that
+    /// contributed to it, which indexes created by Lucene 7 or later require.
+    pub fn add_segment(&mut self, segment: SegmentCommitInfo) -> Result<(), LuceneError> {
+        let info = segment.get_segment_info();
+        if info.get_codec_name().is_none() {
+            return Err(LuceneError::InvalidArgument(format!("segment {} has no codec", info.get_name())));
+        }
+        if self.index_created_version_major >= 7 && info.get_min_version().is_none() {
+            return Err(LuceneError::InvalidArgument(format!(
+                "segment {} must record a min version, as the index was created with Lucene {}",
+                info.get_name(),
+                self.index_created_version_major
+            )));
+        }
+
+        self.segments.push(segment);
+        self.changed();
+        Ok(())
+    }
+
+    /// Removes every segment from the index, keeping its generation and counter so that the next commit supersedes
+    /// the earlier ones rather than reusing their file names.
+    pub fn clear(&mut self) {
+        self.segments.clear();
+        self.changed();
+    }
+
+    /// Returns the names of the files referenced by the segments of the index, sorted, not including the
+    /// `segments_N` file itself, like Lucene's `SegmentInfos.files(false)`.
+    pub fn files(&self) -> BoxResult<Vec<String>> {
+        let mut files = BTreeSet::new();
+        for segment in &self.segments {
+            let info = segment.get_segment_info();
+            files.extend(info.get_files().iter().cloned());
+            files.extend(segment.get_field_infos_files().iter().cloned());
+            files.extend(segment.get_doc_values_update_files().values().flatten().cloned());
+            if let Some(codec_name) = info.get_codec_name() {
+                files.extend(get_codec(codec_name)?.live_docs_format().files(segment));
+            }
+        }
+        Ok(files.into_iter().collect())
+    }
+
+    /// Consumes the segment index, returning its segments.
+    pub fn into_segments(self) -> Vec<SegmentCommitInfo> {
+        self.segments
+    }
+
+    /// Records that the index has changed, so that readers can tell it apart from earlier commits.
+    pub fn changed(&mut self) {
+        self.version += 1;
+    }
+
+    /// Returns a name for a new segment, unique within the index, and advances the counter used to name segments.
+    pub fn new_segment_name(&mut self) -> String {
+        let name = format!("_{}", generation_to_string(self.counter));
+        self.counter += 1;
+        self.changed();
+        name
+    }
+
+    /// Returns the generation of the next commit: one more than the last one, or 1 if the index has never been
+    /// committed.
+    #[inline]
+    pub fn get_next_pending_generation(&self) -> u64 {
+        self.generation + 1
+    }
+
+    /// Indicates whether a commit has been prepared with [SegmentIndex::prepare_commit] but not yet finished.
+    #[inline]
+    pub fn is_pending_commit(&self) -> bool {
+        self.pending_commit
+    }
+
+    /// Commits the segment index to `directory`, returning the name of the new `segments_N` file. This is
+    /// [SegmentIndex::prepare_commit] followed by [SegmentIndex::finish_commit].
+    pub async fn commit<D: Directory + ?Sized>(&mut self, directory: &mut D) -> BoxResult<String> {
+        self.prepare_commit(directory).await?;
+        self.finish_commit(directory).await
+    }
+
+    /// Writes the segment index to a `pending_segments_N` file for the next generation, the first phase of a
+    /// two-phase commit. Readers ignore pending files, so the commit isn't visible until
+    /// [SegmentIndex::finish_commit] renames it; until then it can be abandoned with
+    /// [SegmentIndex::rollback_commit].
+    ///
+    /// The files of the segments and the pending file are synced (see [Directory::sync]), so that the commit can't be
+    /// published before everything it references is durable. The generation advances even if writing fails, so a
+    /// partially written file is never reused. On failure, the pending file is removed.
+    pub async fn prepare_commit<D: Directory + ?Sized>(&mut self, directory: &mut D) -> BoxResult<()> {
+        if self.pending_commit {
+            return Err(LuceneError::IllegalState("prepare_commit was already called".to_string()).into());
+        }
+
+        self.generation = self.get_next_pending_generation();
+        let file_name = pending_segment_index_file_name(self.generation);
+        self.id = Id::random_id();
+        self.lucene_version = LATEST;
+
+        // The file counts as pending as soon as it's created, so that a failure removes it.
+        let result = match directory.create(&file_name, &IoContext::Default).await {
+            Ok(file) => {
+                self.pending_commit = true;
+                let mut w = Crc32Writer::new(file);
+                match self.write_to(&mut w).await {
+                    Ok(()) => w.shutdown().await.map_err(BoxError::from),
+                    Err(e) => Err(e),
+                }
+            }
+            Err(e) => Err(e.into()),
+        };
+        let result = match result {
+            Ok(()) => self.sync_commit_files(directory, &file_name).await,
+            Err(e) => Err(e),
+        };
+
+        if let Err(e) = result {
+            self.rollback_commit(directory).await;
+            return Err(e).with_context(|| format!("writing segment index {file_name}"));
+        }
+
+        debug!("Prepared commit {file_name}");
+        Ok(())
+    }
+
+    /// Syncs the files of the segments and the pending `segments_N` file of a commit being prepared.
+    async fn sync_commit_files<D: Directory + ?Sized>(
+        &self,
+        directory: &mut D,
+        pending_file_name: &str,
+    ) -> BoxResult<()> {
+        let mut files = self.files()?;
+        files.push(pending_file_name.to_string());
+        let files: Vec<&str> = files.iter().map(String::as_str).collect();
+        directory.sync(&files).await?;
+        Ok(())
+    }
+
+    /// Renames the `pending_segments_N` file written by [SegmentIndex::prepare_commit] to `segments_N`, the second
+    /// phase of a two-phase commit, returning its new name. Readers see the commit from then on. The directory is
+    /// then synced (see [Directory::sync_meta_data]) so that the rename survives a crash.
+    ///
+    /// This fails with [LuceneError::IllegalState] if no commit was prepared. On failure, the pending file is
+    /// removed, as is the `segments_N` file if the rename couldn't be synced.
+    pub async fn finish_commit<D: Directory + ?Sized>(&mut self, directory: &mut D) -> BoxResult<String> {
+        if !self.pending_commit {
+            return Err(LuceneError::IllegalState("prepare_commit was not called".to_string()).into());
+        }
+
+        let pending_file_name = pending_segment_index_file_name(self.generation);
+        let file_name = segment_index_file_name(self.generation);
+        if let Err(e) = directory.rename(&pending_file_name, &file_name).await {
+            self.rollback_commit(directory).await;
+            return Err(e).with_context(|| format!("renaming {pending_file_name} to {file_name}"));
+        }
+
+        self.pending_commit = false;
+        if let Err(e) = directory.sync_meta_data().await {
+            if let Err(e) = directory.remove(&file_name).await {
+                warn!("Failed to remove commit {file_name} whose rename couldn't be synced: {e}");
+            }
+            return Err(e).with_context(|| format!("syncing the rename of {pending_file_name} to {file_name}"));
+        }
+
+        self.last_generation = self.generation;
+        debug!("Finished commit {file_name}");
+        Ok(file_name)
+    }
+
+    /// Abandons a commit prepared by [SegmentIndex::prepare_commit], removing its `pending_segments_N` file. This
+    /// does nothing if no commit is pending. Failures to remove the file are logged and otherwise ignored, as the
+    /// file is invisible to readers.
+    pub async fn rollback_commit<D: Directory + ?Sized>(&mut self, directory: &mut D) {
+        if !self.pending_commit {
+            return;
+        }
+
+        self.pending_commit = false;
+        let file_name = pending_segment_index_file_name(self.generation);
+        if let Err(e) = directory.remove(&file_name).await {
+            warn!("Failed to remove abandoned commit {file_name}: {e}");
+        }
+    }
+
+    /// Writes the segment index in the `segments_N` format, as read by [SegmentIndex::read_from], for the current
+    /// generation.
+    pub async fn write_to<W: AsyncWrite + Unpin>(&self, w: &mut Crc32Writer<W>) -> BoxResult<()> {
+        IndexHeader::new(SEGMENT_CODEC_NAME, SEGMENT_INDEX_VERSION_CURRENT, self.id)?
+            .write(w, &generation_to_string(self.generation))
+            .await?;
+        LATEST.write_to_vi32(w).await?;
+        w.write_vi32(self.index_created_version_major as i32).await?;
+
+        w.write_i64(self.version as i64).await?;
+        w.write_vi64(self.counter as i64).await?;
+        w.write_i32(self.segments.len() as i32).await?;
+
+        if let Some(min_segment_version) = self.segments.iter().map(SegmentCommitInfo::get_version).min() {
+            min_segment_version.write_to_vi32(w).await?;
+        }
+
+        for commit_info in &self.segments {
+            let info = commit_info.get_segment_info();
+            let Some(codec_name) = info.get_codec_name() else {
+                return Err(LuceneError::InvalidArgument(format!("segment {} has no codec", info.get_name())).into());
+            };
+
+            w.write_string(info.get_name()).await?;
+            info.get_id().write_to(w).await?;
+            w.write_string(codec_name).await?;
+            w.write_i64(commit_info.get_del_gen().map_or(-1, |del_gen| del_gen as i64)).await?;
+            w.write_i32(commit_info.get_del_count() as i32).await?;
+            w.write_i64(commit_info.get_field_infos_gen().map_or(-1, |gen| gen as i64)).await?;
+            w.write_i64(commit_info.get_doc_values_gen().map_or(-1, |gen| gen as i64)).await?;
+            w.write_i32(commit_info.get_soft_del_count() as i32).await?;
+
+            match commit_info.get_id() {
+                Some(id) => {
+                    w.write_u8(1).await?;
+                    id.write_to(w).await?;
+                }
+                None => w.write_u8(0).await?,
+            }
+
+            w.write_string_set(commit_info.get_field_infos_files()).await?;
+            let mut dv_fields: Vec<_> = commit_info.get_doc_values_update_files().iter().collect();
+            dv_fields.sort_by_key(|(field, _)| **field);
+            w.write_i32(dv_fields.len() as i32).await?;
+            for (field, files) in dv_fields {
+                w.write_i32(*field).await?;
+                w.write_string_set(files).await?;
+            }
+        }
+
+        w.write_string_map(&self.user_data).await?;
+        write_footer(w).await
+    }
+
     /// Open a segment index from the given directory.
+    ///
+    /// The footers of the segment index file and of each segment's info file are checked as they are read, so
+    /// corruption of these files is detected. Corruption of the segments' other files is only detected by
+    /// [SegmentIndex::verify_checksums] or [SegmentIndex::open_verified].
+    ///
+    /// The latest commit is found with [find_segments_file], so a commit made while the index is being opened is
+    /// either fully seen or not seen at all.
     pub async fn open<D: Directory>(directory: &mut D) -> BoxResult<Self> {
-        let dir_entries = directory.read_dir().await?;
-        let Some((segment_index_file_name, generation)) = get_latest_segment_index_file_name_and_generation(&dir_entries)? else {
-            return Err(LuceneError::CorruptIndex(format!("No segment index file found in directory: {directory:?}")).into());
-        };
+        find_segments_file(directory, async |directory: &mut D, segment_index_file_name: &str, generation| {
+            Self::read_commit(directory, segment_index_file_name, generation).await
+        })
+        .await
+    }
+
+    /// Reads the segment index of the commit in `segment_index_file_name`.
+    async fn read_commit<D: Directory>(
+        directory: &mut D,
+        segment_index_file_name: &str,
+        generation: u64,
+    ) -> BoxResult<Self> {
+        let segment_index_file = directory.open(segment_index_file_name, &IoContext::ReadOnce).await?;
+        let mut segment_index_reader = Crc32Reader::buffered(segment_index_file);
+        Self::read_from(directory, &mut segment_index_reader, generation)
+            .await
+            .map_err(|e| locate_corruption(e, segment_index_file_name, segment_index_reader.position()))
+            .with_context(|| format!("reading segment index {segment_index_file_name}"))
+    }
+
+    /// Opens a segment index from the given directory as [SegmentIndex::open] does, then verifies the checksums of
+    /// every file of every segment (see [SegmentIndex::verify_checksums]). This reads the whole index, so it is
+    /// slow for large indexes, but it detects bit rot before the index is used.
+    pub async fn open_verified<D: Directory>(directory: &mut D) -> BoxResult<Self> {
+        let segment_index = Self::open(directory).await?;
+        segment_index.verify_checksums(directory).await?;
+        Ok(segment_index)
+    }
+
+    /// Reads every file of every segment to its end and verifies its checksum against its codec footer. This fails
+    /// with [LuceneError::CorruptIndex], naming the file, for the first file whose data doesn't match its checksum.
+    pub async fn verify_checksums<D: Directory>(&self, directory: &mut D) -> BoxResult<()> {
+        for commit_info in &self.segments {
+            let mut files: Vec<&String> = commit_info.get_segment_info().get_files().iter().collect();
+            files.sort();
+            for file_name in files {
+                let mut file = directory.open(file_name, &IoContext::ReadOnce).await?;
+                let checksum = checksum_entire_file(&mut file)
+                    .await
+                    .map_err(|e| locate_corruption(e, file_name, 0))
+                    .with_context(|| format!("verifying checksum of {file_name}"))?;
+                debug!("File {file_name} has checksum {checksum:#x}");
+            }
+        }
 
-        let segment_index_file = directory.open(&segment_index_file_name).await?;
-        let mut segment_index_reader = Crc32Reader::new(segment_index_file);
-        Self::read_from(directory, &mut segment_index_reader, generation).await
+        Ok(())
     }
 
     /// Read the segment index from the given reader.
@@ -157,13 +489,13 @@ impl SegmentIndex {
         debug!("SegmentIndex has index created version major {index_created_version_major}");
 
         if (lucene_version.major() as i32) < index_created_version_major {
-            return Err(LuceneError::CorruptIndex(format!("Segment index has version {index_created_version_major} but is greater than the Lucene version that created it: {lucene_version}")).into());
+            return Err(LuceneError::CorruptIndex(format!("Segment index has version {index_created_version_major} but is greater than the Lucene version that created it: {lucene_version}").into()).into());
         }
 
         let index_created_version_major: u8 = index_created_version_major.try_into().map_err(|_| {
-            LuceneError::CorruptIndex(format!(
-                "Index created version {index_created_version_major} is too large to fit in a u8"
-            ))
+            LuceneError::CorruptIndex(
+                format!("Index created version {index_created_version_major} is too large to fit in a u8").into(),
+            )
         })?;
 
         // From SegmentInfos#parseSegmentInfos(Directory, DataInput, SegmentInfos, int)
@@ -180,9 +512,9 @@ impl SegmentIndex {
         debug!("SegmentIndex has {num_segments} segments; version={version}, counter={counter}");
 
         if num_segments < 0 {
-            return Err(LuceneError::CorruptIndex(format!(
-                "Segment index has negative number of segments: {num_segments}"
-            ))
+            return Err(LuceneError::CorruptIndex(
+                format!("Segment index has negative number of segments: {num_segments}").into(),
+            )
             .into());
         }
 
@@ -204,7 +536,9 @@ impl SegmentIndex {
 
             let codec = get_codec(&codec_name)?;
             let segment_info_format = codec.segment_info_format();
-            let segment_info = segment_info_format.read_segment_info(directory, &seg_name, seg_id).await?;
+            let mut segment_info =
+                segment_info_format.read_segment_info(directory, &seg_name, seg_id, &IoContext::ReadOnce).await?;
+            segment_info.set_codec_name(codec_name);
 
             let max_doc = segment_info.get_max_doc();
             total_docs += max_doc;
@@ -226,10 +560,13 @@ impl SegmentIndex {
 
             // Ensure del_count is valid and Rust friendly.
             if del_count < 0 || del_count as u32 > max_doc {
-                return Err(LuceneError::CorruptIndex(format!(
-                    "Segment index has deletion count {del_count} greater than max docs {}",
-                    segment_info.get_max_doc()
-                ))
+                return Err(LuceneError::CorruptIndex(
+                    format!(
+                        "Segment index has deletion count {del_count} greater than max docs {}",
+                        segment_info.get_max_doc()
+                    )
+                    .into(),
+                )
                 .into());
             }
             let del_count = del_count as u32;
@@ -250,21 +587,27 @@ impl SegmentIndex {
 
             // Ensure soft_del_count is valid and Rust friendly.
             if soft_del_count < 0 || soft_del_count as u32 > max_doc {
-                return Err(LuceneError::CorruptIndex(format!(
-                    "Segment index has soft deletion count {soft_del_count} greater than max docs {}",
-                    segment_info.get_max_doc()
-                ))
+                return Err(LuceneError::CorruptIndex(
+                    format!(
+                        "Segment index has soft deletion count {soft_del_count} greater than max docs {}",
+                        segment_info.get_max_doc()
+                    )
+                    .into(),
+                )
                 .into());
             }
             let soft_del_count = soft_del_count as u32;
 
             // Make sure we don't have more deleted documents than the total number of documents.
             if soft_del_count + del_count > max_doc {
-                return Err(LuceneError::CorruptIndex(format!(
-                    "Segment index has invalid total deletion count {} greater than max docs {}",
-                    soft_del_count + del_count,
-                    segment_info.get_max_doc()
-                ))
+                return Err(LuceneError::CorruptIndex(
+                    format!(
+                        "Segment index has invalid total deletion count {} greater than max docs {}",
+                        soft_del_count + del_count,
+                        segment_info.get_max_doc()
+                    )
+                    .into(),
+                )
                 .into());
             }
 
@@ -273,9 +616,9 @@ impl SegmentIndex {
                     1 => Some(Id::read_from(r).await?),
                     0 => None,
                     other => {
-                        return Err(LuceneError::CorruptIndex(format!(
-                            "Segment index has SegmentCommitInfo marker: {other}"
-                        ))
+                        return Err(LuceneError::CorruptIndex(
+                            format!("Segment index has SegmentCommitInfo marker: {other}").into(),
+                        )
                         .into())
                     }
                 }
@@ -310,26 +653,30 @@ impl SegmentIndex {
 
             // We guarantee that min_segment_lucene_version is not None because num_segments > 0
             if segment_version < min_segment_lucene_version.unwrap() {
-                return Err(LuceneError::CorruptIndex(format!(
-                    "Segment index has segment version {segment_version} less than min segment version {}",
-                    min_segment_lucene_version.unwrap()
-                ))
+                return Err(LuceneError::CorruptIndex(
+                    format!(
+                        "Segment index has segment version {segment_version} less than min segment version {}",
+                        min_segment_lucene_version.unwrap()
+                    )
+                    .into(),
+                )
                 .into());
             }
 
             if index_created_version_major >= 7 && segment_version.major() < index_created_version_major {
                 return Err(LuceneError::CorruptIndex(format!(
-                    "Segment index has segment version {segment_version} less than index created version {index_created_version_major}")).into());
+                    "Segment index has segment version {segment_version} less than index created version {index_created_version_major}").into()).into());
             }
 
             if index_created_version_major >= 7 && si_per_commit.get_min_version().is_none() {
                 return Err(LuceneError::CorruptIndex(format!(
-                    "Segment infos must record a min version when created with index major version {index_created_version_major}")).into());
+                    "Segment infos must record a min version when created with index major version {index_created_version_major}").into()).into());
             }
             segments.push(si_per_commit);
         }
 
         let user_data = r.read_string_map().await?;
+        check_footer(r).await?;
 
         let segment_index = Self {
             id: index_header.id(),
@@ -341,6 +688,7 @@ impl SegmentIndex {
             counter,
             user_data,
             segments,
+            pending_commit: false,
         };
 
         if total_docs > MAX_DOCS {
@@ -378,7 +726,7 @@ pub fn get_latest_segment_index_file_name_and_generation<T: AsRef<str>>(
             debug!("File {file_name:?} has no generation suffix, using 0");
             0
         } else {
-            let Ok(generation) = suffix[1..].parse::<u64>() else {
+            let Some(generation) = suffix.strip_prefix('_').and_then(|gen| u64::from_str_radix(gen, 36).ok()) else {
                 error!("Failed to parse generation from file name {:?}", file_name);
                 continue;
             };
@@ -407,6 +755,97 @@ pub fn get_latest_segment_index_file_name_and_generation<T: AsRef<str>>(
     Ok(result)
 }
 
+/// Returns the name of the `segments_N` file of a commit.
+pub fn segment_index_file_name(generation: u64) -> String {
+    match generation {
+        0 => INDEX_SEGMENT_FILE_NAME_PREFIX.to_string(),
+        _ => format!("{INDEX_SEGMENT_FILE_NAME_PREFIX}_{}", generation_to_string(generation)),
+    }
+}
+
+/// Returns the name of the `pending_segments_N` file written while a commit is being prepared.
+pub fn pending_segment_index_file_name(generation: u64) -> String {
+    match generation {
+        0 => PENDING_INDEX_SEGMENT_FILE_NAME_PREFIX.to_string(),
+        _ => format!("{PENDING_INDEX_SEGMENT_FILE_NAME_PREFIX}_{}", generation_to_string(generation)),
+    }
+}
+
+/// Finds the latest commit in `directory` and passes the name and generation of its `segments_N` file to `body`,
+/// retrying if the index changes underneath it, as Lucene's `SegmentInfos.FindSegmentsFile` does.
+///
+/// A writer may commit, and delete older commits, while the latest one is being read. So the directory is listed
+/// twice, and listed again if the listings differ. If `body` fails, the directory is listed again, and `body` is
+/// retried if a newer commit has appeared since; otherwise the first error is returned. The directory is listed at
+/// most ten times; if it's still changing then, the first error is returned, or [LuceneError::IllegalState] if `body`
+/// never ran. This fails with [LuceneError::IndexNotFound] if the directory has no commit.
+pub async fn find_segments_file<D, T, F>(directory: &mut D, mut body: F) -> BoxResult<T>
+where
+    D: Directory + ?Sized,
+    F: AsyncFnMut(&mut D, &str, u64) -> BoxResult<T>,
+{
+    let mut last_generation = None;
+    let mut first_error = None;
+
+    for _ in 0..MAX_FIND_SEGMENTS_FILE_ATTEMPTS {
+        let mut files = directory.read_dir().await?;
+        let mut files_again = directory.read_dir().await?;
+        files.sort();
+        files_again.sort();
+        if files != files_again {
+            debug!("Directory {directory:?} changed while it was listed; listing it again");
+            continue;
+        }
+
+        let Some((file_name, generation)) = get_latest_segment_index_file_name_and_generation(&files)? else {
+            return Err(LuceneError::IndexNotFound(format!(
+                "No segment index file found in directory {directory:?}: files: {files:?}"
+            ))
+            .into());
+        };
+
+        if last_generation.is_some_and(|last| generation <= last) {
+            // Nothing has been committed since the last attempt, so retrying wouldn't help.
+            return Err(first_error.expect("an attempt was made"));
+        }
+
+        match body(directory, &file_name, generation).await {
+            Ok(result) => return Ok(result),
+            Err(e) => {
+                debug!("Failed to read commit {file_name}, retrying if a newer commit appears: {e}");
+                first_error.get_or_insert(e);
+                last_generation = Some(generation);
+            }
+        }
+    }
+
+    Err(first_error.unwrap_or_else(|| {
+        LuceneError::IllegalState(format!(
+            "directory {directory:?} kept changing while its latest commit was looked up \
+             ({MAX_FIND_SEGMENTS_FILE_ATTEMPTS} attempts)"
+        ))
+        .into()
+    }))
+}
+
+/// Returns the name of generation `generation` of a per-commit file of segment `base`, such as `_3_1.liv` for the
+/// first live docs generation of segment `_3`. Generation 0 has no generation suffix.
+pub fn file_name_from_generation(base: &str, extension: &str, generation: u64) -> String {
+    if generation == 0 {
+        format!("{base}.{extension}")
+    } else {
+        format!("{base}_{}.{extension}", generation_to_string(generation))
+    }
+}
+
+/// Returns the name of a file of a segment without the segment's name, such as `.nvd` for `_3.nvd` or
+/// `_Lucene90_0.doc` for `_3_Lucene90_0.doc`, which is how compound files name the files they hold.
+pub fn strip_segment_name(file_name: &str) -> &str {
+    let start =
+        file_name.get(1..).and_then(|rest| rest.find('_')).map(|index| index + 1).or_else(|| file_name.find('.'));
+    start.map_or(file_name, |start| &file_name[start..])
+}
+
 /// Convert a generation to its string representation (in base-36)
 pub fn generation_to_string(mut gen: u64) -> String {
     let mut result = Vec::with_capacity(10);
@@ -423,3 +862,192 @@ pub fn generation_to_string(mut gen: u64) -> String {
 
     result.iter().rev().collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{
+            codec::get_codec,
+            index::{
+                find_segments_file, get_latest_segment_index_file_name_and_generation, strip_segment_name,
+                SegmentCommitInfo, SegmentIndex, SegmentInfo,
+            },
+            io::{test_util::SyncRecordingDirectory, ByteBuffersDirectory, Directory, IoContext},
+            Id, LuceneError, LATEST,
+        },
+        pretty_assertions::assert_eq,
+        std::{
+            collections::{HashMap, HashSet},
+            io::{Error as IoError, ErrorKind as IoErrorKind},
+        },
+    };
+
+    async fn add_segment(directory: &mut ByteBuffersDirectory, segment_index: &mut SegmentIndex, max_doc: u32) {
+        let name = segment_index.new_segment_name();
+        let mut info = SegmentInfo {
+            name: name.clone(),
+            id: Id::random_id(),
+            max_doc,
+            attributes: HashMap::new(),
+            diagnostics: HashMap::from([("source".to_string(), "flush".to_string())]),
+            files: HashSet::from([format!("{name}.si")]),
+            version: LATEST,
+            min_version: Some(LATEST),
+            is_compound_file: false,
+            index_sort: None,
+            codec: None,
+        };
+        info.set_codec_name("Lucene95");
+
+        let codec = get_codec("Lucene95").unwrap();
+        codec.segment_info_format().write_segment_info(directory, &info, &IoContext::Default).await.unwrap();
+        segment_index.add_segment(SegmentCommitInfo::new(info, 0, 0, None, None, None, Some(Id::random_id()))).unwrap();
+    }
+
+    fn is_lucene_error(error: &crate::BoxError, predicate: impl Fn(&LuceneError) -> bool) -> bool {
+        LuceneError::find(error.as_ref()).is_some_and(predicate)
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_commit() {
+        let mut dir = ByteBuffersDirectory::new();
+        let error = SegmentIndex::open(&mut dir).await.unwrap_err();
+        assert!(is_lucene_error(&error, |e| matches!(e, LuceneError::IndexNotFound(_))), "{error}");
+
+        let mut segment_index = SegmentIndex::new(LATEST.major()).unwrap();
+        add_segment(&mut dir, &mut segment_index, 10).await;
+        add_segment(&mut dir, &mut segment_index, 3).await;
+        segment_index.set_user_data(HashMap::from([("checkpoint".to_string(), "42".to_string())]));
+        assert_eq!(segment_index.commit(&mut dir).await.unwrap(), "segments_1");
+        assert_eq!(segment_index.get_last_generation(), 1);
+
+        let read = SegmentIndex::open(&mut dir).await.unwrap();
+        assert_eq!(read.get_generation(), 1);
+        assert_eq!(read.get_id(), segment_index.get_id());
+        assert_eq!(read.get_version(), segment_index.get_version());
+        assert_eq!(read.get_counter(), 2);
+        assert_eq!(read.get_user_data()["checkpoint"], "42");
+        let segments: Vec<_> = read
+            .get_segments()
+            .iter()
+            .map(|s| (s.get_segment_info().get_name(), s.get_segment_info().get_max_doc(), s.get_id()))
+            .collect();
+        assert_eq!(
+            segments,
+            vec![
+                ("_0", 10, segment_index.get_segments()[0].get_id()),
+                ("_1", 3, segment_index.get_segments()[1].get_id())
+            ]
+        );
+        assert_eq!(read.get_segments()[0].get_segment_info().get_codec_name(), Some("Lucene95"));
+        assert_eq!(read.get_segments()[0].get_segment_info().get_diagnostics()["source"], "flush");
+
+        // A prepared commit is invisible to readers until it's finished.
+        segment_index.get_segments_mut()[1].advance_del_gen();
+        segment_index.get_segments_mut()[1].set_del_count(1).unwrap();
+        segment_index.prepare_commit(&mut dir).await.unwrap();
+        assert!(dir.read_dir().await.unwrap().contains(&"pending_segments_2".to_string()));
+        assert_eq!(SegmentIndex::open(&mut dir).await.unwrap().get_generation(), 1);
+        let error = segment_index.prepare_commit(&mut dir).await.unwrap_err();
+        assert!(is_lucene_error(&error, |e| matches!(e, LuceneError::IllegalState(_))), "{error}");
+
+        // An abandoned commit's generation isn't reused.
+        segment_index.rollback_commit(&mut dir).await;
+        assert!(!dir.read_dir().await.unwrap().contains(&"pending_segments_2".to_string()));
+        let error = segment_index.finish_commit(&mut dir).await.unwrap_err();
+        assert!(is_lucene_error(&error, |e| matches!(e, LuceneError::IllegalState(_))), "{error}");
+        assert_eq!(segment_index.commit(&mut dir).await.unwrap(), "segments_3");
+
+        let read = SegmentIndex::open(&mut dir).await.unwrap();
+        assert_eq!(read.get_generation(), 3);
+        assert_eq!(read.get_segments()[1].get_del_gen(), Some(1));
+        assert_eq!(read.get_segments()[1].get_del_count(), 1);
+        assert!(SegmentIndex::new(5).is_err());
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_commit_syncs() {
+        let mut dir = SyncRecordingDirectory::default();
+        let mut segment_index = SegmentIndex::new(LATEST.major()).unwrap();
+        add_segment(&mut dir.inner, &mut segment_index, 10).await;
+        add_segment(&mut dir.inner, &mut segment_index, 3).await;
+        segment_index.get_segments_mut()[1].advance_del_gen();
+        segment_index.commit(&mut dir).await.unwrap();
+        assert_eq!(segment_index.files().unwrap(), vec!["_0.si", "_1.si", "_1_1.liv"]);
+
+        // The segments and the pending file are durable before the rename, which is synced in turn.
+        assert_eq!(
+            dir.take_events(),
+            vec![
+                "sync _0.si _1.si _1_1.liv pending_segments_1",
+                "rename pending_segments_1 segments_1",
+                "sync_meta_data"
+            ]
+        );
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_find_segments_file_retries() {
+        let mut dir = ByteBuffersDirectory::new();
+        let mut writer = SegmentIndex::new(LATEST.major()).unwrap();
+        writer.commit(&mut dir).await.unwrap();
+
+        // A commit that appears while the latest one is read causes a retry.
+        let mut attempts = Vec::new();
+        let generation =
+            find_segments_file(&mut dir, async |dir: &mut ByteBuffersDirectory, name: &str, generation| {
+                attempts.push(name.to_string());
+                if generation == 1 {
+                    writer.commit(dir).await?;
+                    return Err(IoError::new(IoErrorKind::NotFound, "segments_1 was removed").into());
+                }
+                Ok(generation)
+            })
+            .await
+            .unwrap();
+        assert_eq!(generation, 2);
+        assert_eq!(attempts, vec!["segments_1", "segments_2"]);
+
+        // Without a newer commit, the first error is returned.
+        let mut attempts = 0;
+        let error =
+            find_segments_file(&mut dir, async |_: &mut ByteBuffersDirectory, _: &str, _| -> crate::BoxResult<()> {
+                attempts += 1;
+                Err(LuceneError::CorruptIndex("truncated".into()).into())
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(attempts, 1);
+        assert!(is_lucene_error(&error, |e| matches!(e, LuceneError::CorruptIndex(_))), "{error}");
+
+        // An index that keeps changing is given up on.
+        let mut attempts = 0;
+        let error =
+            find_segments_file(&mut dir, async |dir: &mut ByteBuffersDirectory, _: &str, _| -> crate::BoxResult<()> {
+                attempts += 1;
+                writer.commit(dir).await?;
+                Err(IoError::new(IoErrorKind::NotFound, "superseded").into())
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(attempts, 10);
+        assert!(error.to_string().contains("superseded"), "{error}");
+    }
+
+    #[test]
+    fn test_generations_are_base_36() {
+        let files = ["segments_9", "segments_a", "segments_10", "pending_segments_11", "_0.si"];
+        assert_eq!(
+            get_latest_segment_index_file_name_and_generation(&files).unwrap(),
+            Some(("segments_10".to_string(), 36))
+        );
+    }
+
+    #[test]
+    fn test_strip_segment_name() {
+        assert_eq!(strip_segment_name("_3.nvd"), ".nvd");
+        assert_eq!(strip_segment_name("_3_Lucene90_0.doc"), "_Lucene90_0.doc");
+        assert_eq!(strip_segment_name("_a1_2.liv"), "_2.liv");
+        assert_eq!(strip_segment_name("README"), "README");
+    }
+}
@@ -1,5 +1,5 @@
 use {
-    crate::{search::Sort, Id, Version},
+    crate::{search::Sort, Id, LuceneError, Version, LATEST},
     std::collections::{HashMap, HashSet},
 };
 
@@ -16,9 +16,28 @@ pub struct SegmentInfo {
     pub(crate) min_version: Option<Version>,
     pub(crate) is_compound_file: bool,
     pub(crate) index_sort: Option<Sort>,
+    pub(crate) codec: Option<String>,
 }
 
 impl SegmentInfo {
+    /// Creates the info of a segment written by the current version of Lucene, with no files, attributes,
+    /// diagnostics or index sort. The files written for the segment are recorded with [SegmentInfo::add_file].
+    pub fn new(name: impl Into<String>, id: Id, max_doc: u32, is_compound_file: bool) -> Self {
+        Self {
+            name: name.into(),
+            id,
+            max_doc,
+            attributes: HashMap::new(),
+            diagnostics: HashMap::new(),
+            files: HashSet::new(),
+            version: LATEST,
+            min_version: Some(LATEST),
+            is_compound_file,
+            index_sort: None,
+            codec: None,
+        }
+    }
+
     /// Returns the name of the segment.
     #[inline]
     pub fn get_name(&self) -> &str {
@@ -55,6 +74,11 @@ impl SegmentInfo {
         &self.files
     }
 
+    /// Records a file written for the segment.
+    pub fn add_file(&mut self, file_name: impl Into<String>) {
+        self.files.insert(file_name.into());
+    }
+
     /// Returns the Lucene version used to create the segment.
     #[inline]
     pub fn get_version(&self) -> Version {
@@ -62,8 +86,8 @@ impl SegmentInfo {
     }
 
     /// Returns the minimum Lucene version that contributed documents to the segment.
-    /// 
-    /// For `flush` segments, this is the version that created the segment. For `merge` segments, this is the 
+    ///
+    /// For `flush` segments, this is the version that created the segment. For `merge` segments, this is the
     /// minimum version of all segments that were merged into this segment.
     #[inline]
     pub fn get_min_version(&self) -> Option<Version> {
@@ -76,6 +100,18 @@ impl SegmentInfo {
         self.is_compound_file
     }
 
+    /// Returns the name of the codec that wrote the segment, or `None` if it hasn't been set yet. Segment info
+    /// formats don't record the codec; the segment index does.
+    #[inline]
+    pub fn get_codec_name(&self) -> Option<&str> {
+        self.codec.as_deref()
+    }
+
+    /// Sets the name of the codec that wrote the segment.
+    pub fn set_codec_name(&mut self, codec: impl Into<String>) {
+        self.codec = Some(codec.into());
+    }
+
     /// Returns the sort order of the segment, or `None` if the index has no sort.
     #[inline]
     pub fn get_index_sort(&self) -> Option<&Sort> {
@@ -188,6 +224,38 @@ impl SegmentCommitInfo {
         self.next_write_del_gen
     }
 
+    /// Indicates whether any document of the segment has been deleted, so that it has a live docs file.
+    #[inline]
+    pub fn has_deletions(&self) -> bool {
+        self.del_gen.is_some()
+    }
+
+    /// Makes the next deletion generation current, once its live docs file has been written.
+    pub fn advance_del_gen(&mut self) {
+        self.del_gen = Some(self.next_write_del_gen);
+        self.next_write_del_gen += 1;
+    }
+
+    /// Skips the next deletion generation, after an attempt to write its live docs file failed, so that a partially
+    /// written file is never overwritten.
+    pub fn advance_next_write_del_gen(&mut self) {
+        self.next_write_del_gen += 1;
+    }
+
+    /// Sets the number of deleted documents in the segment.
+    ///
+    /// This returns an error if the count exceeds the number of documents in the segment.
+    pub fn set_del_count(&mut self, del_count: u32) -> Result<(), LuceneError> {
+        if del_count > self.info.max_doc {
+            return Err(LuceneError::InvalidArgument(format!(
+                "del_count {del_count} exceeds max_doc {} of segment {}",
+                self.info.max_doc, self.info.name
+            )));
+        }
+        self.del_count = del_count;
+        Ok(())
+    }
+
     /// Returns the generation number of the FieldInfos, or `None` if there are no updates.
     #[inline]
     pub fn get_field_infos_gen(&self) -> Option<u64> {
@@ -231,8 +299,8 @@ impl SegmentCommitInfo {
     }
 
     /// Returns the minimum Lucene version that contributed documents to the segment.
-    /// 
-    /// For `flush` segments, this is the version that created the segment. For `merge` segments, this is the 
+    ///
+    /// For `flush` segments, this is the version that created the segment. For `merge` segments, this is the
     /// minimum version of all segments that were merged into this segment.
     #[inline]
     pub fn get_min_version(&self) -> Option<Version> {
@@ -0,0 +1,279 @@
+use {
+    crate::{
+        codec::Codec,
+        document::Document,
+        index::{
+            BinaryDocValues, CacheHelper, DocValuesSkipper, DocValuesType, FloatVectorValues, LeafReader,
+            NumericDocValues, SegmentCommitInfo, TermVectors, Terms,
+        },
+        io::{Directory, IoContext},
+        search::Sort,
+        util::{Accountable, BitSet, FixedBitSet, NamedAccountable},
+        BoxResult, LuceneError,
+    },
+    std::sync::Arc,
+};
+
+/// A [LeafReader] over a committed segment, which hides the documents deleted as of the commit.
+///
+/// The segment's data is read by the wrapped reader; the deletes come from the live docs file of the commit's
+/// deletion generation (see [crate::codec::LiveDocsFormat]). Searches skip deleted documents, and
+/// [LeafReader::num_docs] excludes them.
+#[derive(Debug)]
+pub struct SegmentReader {
+    core: Arc<dyn LeafReader>,
+    live_docs: Option<FixedBitSet>,
+    num_docs: u32,
+    cache_helper: CacheHelper,
+}
+
+impl SegmentReader {
+    /// Creates a reader over `core` whose live documents are `live_docs`, or every document if that is `None`.
+    ///
+    /// This returns an error if `live_docs` doesn't hold exactly one bit per document of `core`.
+    pub fn new(core: Arc<dyn LeafReader>, live_docs: Option<FixedBitSet>) -> BoxResult<Self> {
+        let num_docs = match &live_docs {
+            Some(live_docs) if live_docs.num_bits() != core.max_doc() => {
+                return Err(LuceneError::InvalidArgument(format!(
+                    "live docs hold {} bits, but the segment has {} documents",
+                    live_docs.num_bits(),
+                    core.max_doc()
+                ))
+                .into());
+            }
+            Some(live_docs) => live_docs.cardinality(),
+            None => core.max_doc(),
+        };
+
+        Ok(Self {
+            core,
+            live_docs,
+            num_docs,
+            cache_helper: CacheHelper::new(),
+        })
+    }
+
+    /// Opens a reader over `core`, the data of the segment described by `info`, reading its live docs from
+    /// `directory` with `codec`'s [crate::codec::LiveDocsFormat] if any of its documents were deleted.
+    pub async fn open(
+        core: Arc<dyn LeafReader>,
+        info: &SegmentCommitInfo,
+        codec: &dyn Codec,
+        directory: &mut dyn Directory,
+        context: &IoContext,
+    ) -> BoxResult<Self> {
+        let segment = info.get_segment_info();
+        if core.max_doc() != segment.get_max_doc() {
+            return Err(LuceneError::InvalidArgument(format!(
+                "segment {} has {} documents, but its reader has {}",
+                segment.get_name(),
+                segment.get_max_doc(),
+                core.max_doc()
+            ))
+            .into());
+        }
+
+        let live_docs = if info.has_deletions() {
+            Some(codec.live_docs_format().read_live_docs(directory, info, context).await?)
+        } else {
+            None
+        };
+
+        Self::new(core, live_docs)
+    }
+
+    /// Returns the reader of the segment's data, which includes the deleted documents.
+    #[inline]
+    pub fn core(&self) -> &Arc<dyn LeafReader> {
+        &self.core
+    }
+}
+
+impl LeafReader for SegmentReader {
+    #[inline]
+    fn max_doc(&self) -> u32 {
+        self.core.max_doc()
+    }
+
+    #[inline]
+    fn num_docs(&self) -> u32 {
+        self.num_docs
+    }
+
+    #[inline]
+    fn live_docs(&self) -> Option<&FixedBitSet> {
+        self.live_docs.as_ref()
+    }
+
+    fn indexed_fields(&self) -> Vec<&str> {
+        self.core.indexed_fields()
+    }
+
+    fn doc_values_fields(&self) -> Vec<(&str, DocValuesType)> {
+        self.core.doc_values_fields()
+    }
+
+    fn terms(&self, field: &str) -> BoxResult<Option<&dyn Terms>> {
+        self.core.terms(field)
+    }
+
+    fn norms(&self, field: &str) -> BoxResult<Option<Arc<[i64]>>> {
+        self.core.norms(field)
+    }
+
+    fn numeric_doc_values(&self, field: &str) -> BoxResult<Option<Box<dyn NumericDocValues>>> {
+        self.core.numeric_doc_values(field)
+    }
+
+    fn doc_values_skipper(&self, field: &str) -> BoxResult<Option<Box<dyn DocValuesSkipper>>> {
+        self.core.doc_values_skipper(field)
+    }
+
+    fn binary_doc_values(&self, field: &str) -> BoxResult<Option<Box<dyn BinaryDocValues>>> {
+        self.core.binary_doc_values(field)
+    }
+
+    fn document(&self, doc: u32) -> BoxResult<Document> {
+        self.core.document(doc)
+    }
+
+    fn term_vectors(&self, doc: u32) -> BoxResult<Option<TermVectors>> {
+        self.core.term_vectors(doc)
+    }
+
+    fn vector_fields(&self) -> Vec<&str> {
+        self.core.vector_fields()
+    }
+
+    fn float_vector_values(&self, field: &str) -> BoxResult<Option<&FloatVectorValues>> {
+        self.core.float_vector_values(field)
+    }
+
+    #[inline]
+    fn index_sort(&self) -> Option<&Sort> {
+        self.core.index_sort()
+    }
+
+    #[inline]
+    fn segment_name(&self) -> Option<&str> {
+        self.core.segment_name()
+    }
+
+    #[inline]
+    fn core_cache_helper(&self) -> Option<&CacheHelper> {
+        self.core.core_cache_helper()
+    }
+
+    // The deletions are this reader's own, so it has its own key, unless its data can't be cached at all.
+    #[inline]
+    fn reader_cache_helper(&self) -> Option<&CacheHelper> {
+        self.core.core_cache_helper().map(|_| &self.cache_helper)
+    }
+}
+
+impl Accountable for SegmentReader {
+    fn ram_bytes_used(&self) -> usize {
+        size_of::<Self>() + self.child_resources().iter().map(Accountable::ram_bytes_used).sum::<usize>()
+    }
+
+    fn child_resources(&self) -> Vec<NamedAccountable> {
+        let mut resources = vec![NamedAccountable::new("core", self.core.as_ref())];
+        if let Some(live_docs) = &self.live_docs {
+            resources.push(NamedAccountable::new("live docs", live_docs));
+        }
+        resources
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{
+            analysis::SimpleAnalyzer,
+            codec::get_codec,
+            document::{Document, Field, Store},
+            index::{
+                IndexReader, LeafReader, MemorySegmentBuilder, MultiReader, SegmentCommitInfo, SegmentInfo,
+                SegmentReader, Term,
+            },
+            io::{ByteBuffersDirectory, IoContext},
+            search::{IndexSearcher, MatchAllDocsQuery, TermQuery},
+            util::{BitSet, FixedBitSet},
+            Id, LATEST,
+        },
+        pretty_assertions::assert_eq,
+        std::{
+            collections::{HashMap, HashSet},
+            sync::Arc,
+        },
+    };
+
+    #[test_log::test(tokio::test)]
+    async fn test_deleted_docs_are_not_searched() {
+        let mut builder = MemorySegmentBuilder::new(Arc::new(SimpleAnalyzer));
+        for i in 0..10 {
+            let mut doc = Document::new();
+            doc.add(Field::text(
+                "body",
+                if i % 2 == 0 {
+                    "even"
+                } else {
+                    "odd"
+                },
+                Store::No,
+            ));
+            builder.add_document(&doc).unwrap();
+        }
+        let core: Arc<dyn LeafReader> = Arc::new(builder.build());
+
+        let info = SegmentInfo {
+            name: "_0".to_string(),
+            id: Id::random_id(),
+            max_doc: 10,
+            attributes: HashMap::new(),
+            diagnostics: HashMap::new(),
+            files: HashSet::new(),
+            version: LATEST,
+            min_version: Some(LATEST),
+            is_compound_file: false,
+            index_sort: None,
+            codec: None,
+        };
+        let mut info = SegmentCommitInfo::new(info, 0, 0, None, None, None, None);
+        let codec = get_codec("Lucene95").unwrap();
+        let mut dir = ByteBuffersDirectory::new();
+
+        let reader =
+            SegmentReader::open(core.clone(), &info, codec.as_ref(), &mut dir, &IoContext::Read).await.unwrap();
+        assert!(reader.live_docs().is_none());
+        assert_eq!(reader.num_docs(), 10);
+
+        // Delete documents 0, 4 and 7, then commit the deletes.
+        let mut live_docs = FixedBitSet::new(10);
+        live_docs.set_range(0, 10);
+        for doc in [0, 4, 7] {
+            live_docs.clear(doc);
+        }
+        codec.live_docs_format().write_live_docs(&live_docs, &mut dir, &info, 3, &IoContext::Default).await.unwrap();
+        info.advance_del_gen();
+        info.set_del_count(3).unwrap();
+
+        let reader =
+            SegmentReader::open(core.clone(), &info, codec.as_ref(), &mut dir, &IoContext::Read).await.unwrap();
+        assert_eq!(reader.num_docs(), 7);
+        assert_eq!(reader.max_doc(), 10);
+
+        let reader = MultiReader::new(vec![Arc::new(reader)]).unwrap();
+        assert_eq!(reader.num_docs(), 7);
+        let searcher = IndexSearcher::new(Arc::new(reader));
+        assert_eq!(searcher.count(&MatchAllDocsQuery).unwrap(), 7);
+
+        let top_docs = searcher.search(&TermQuery::new(Term::new("body", "even")), 10).unwrap();
+        assert_eq!(top_docs.total_hits.value, 3);
+        let mut docs: Vec<u32> = top_docs.score_docs.iter().map(|score_doc| score_doc.doc).collect();
+        docs.sort_unstable();
+        assert_eq!(docs, vec![2, 6, 8]);
+
+        assert!(SegmentReader::new(core, Some(FixedBitSet::new(9))).is_err());
+    }
+}
@@ -0,0 +1,162 @@
+use {
+    crate::{
+        index::{LeafReader, MultiReader},
+        search::{IndexSearcher, Query, NO_MORE_DOCS},
+        BoxResult,
+    },
+    std::{fmt::Debug, sync::Arc},
+};
+
+/// Prepares new segments for searching before they're published, as Lucene's `IndexReaderWarmer` and
+/// `SearcherFactory` do, so that the first searches after a flush, merge or refresh don't pay for loading their data.
+///
+/// The warmer set with [crate::index::IndexWriterConfig::set_segment_warmer] is called with every segment flushed,
+/// merged or added by a writer before the segment becomes visible through
+/// [crate::index::IndexWriter::segments] and [crate::index::IndexWriter::reader]. If warming fails, the error is
+/// logged and the segment is published anyway.
+pub trait SegmentWarmer: Debug + Send + Sync {
+    /// Warms `segment`. This is called on the flushing or merging thread.
+    fn warm(&self, segment: &Arc<dyn LeafReader>) -> BoxResult<()>;
+}
+
+/// A [SegmentWarmer] that runs queries against new segments and loads the doc values of fields, such as those facets
+/// are counted over.
+#[derive(Clone, Debug, Default)]
+pub struct QueryWarmer {
+    queries: Vec<Arc<dyn Query>>,
+    doc_values_fields: Vec<String>,
+}
+
+impl QueryWarmer {
+    /// Creates a warmer that doesn't warm anything yet.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the queries run against new segments.
+    #[inline]
+    pub fn queries(&self) -> &[Arc<dyn Query>] {
+        &self.queries
+    }
+
+    /// Adds a query to run against new segments, such as a common filter or a sort's query.
+    pub fn add_query(&mut self, query: Arc<dyn Query>) -> &mut Self {
+        self.queries.push(query);
+        self
+    }
+
+    /// Returns the fields whose doc values are loaded.
+    #[inline]
+    pub fn doc_values_fields(&self) -> &[String] {
+        &self.doc_values_fields
+    }
+
+    /// Adds a field whose numeric or binary doc values are read in full from new segments, such as a field that
+    /// facets are counted over or that results are sorted by.
+    pub fn add_doc_values_field(&mut self, field: impl Into<String>) -> &mut Self {
+        self.doc_values_fields.push(field.into());
+        self
+    }
+}
+
+impl SegmentWarmer for QueryWarmer {
+    fn warm(&self, segment: &Arc<dyn LeafReader>) -> BoxResult<()> {
+        if !self.queries.is_empty() {
+            let searcher = IndexSearcher::new(Arc::new(MultiReader::new(vec![segment.clone()])?));
+            for query in &self.queries {
+                searcher.count(query.as_ref())?;
+            }
+        }
+
+        for field in &self.doc_values_fields {
+            if let Some(mut values) = segment.numeric_doc_values(field)? {
+                while values.next_doc()? != NO_MORE_DOCS {
+                    values.long_value()?;
+                }
+            }
+            if let Some(mut values) = segment.binary_doc_values(field)? {
+                while values.next_doc()? != NO_MORE_DOCS {
+                    values.binary_value()?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{
+            document::{Document, Field, Store},
+            index::{DocumentsWriter, IndexWriterConfig, LeafReader, QueryWarmer, SegmentWarmer, Term},
+            search::TermQuery,
+            BoxResult, LuceneError,
+        },
+        pretty_assertions::assert_eq,
+        std::sync::{Arc, Mutex},
+    };
+
+    /// Records the size of the segments it warms, and whether they were visible to the writer's readers by then.
+    #[derive(Debug, Default)]
+    struct RecordingWarmer {
+        writer: Mutex<Option<Arc<DocumentsWriter>>>,
+        warmed: Mutex<Vec<(u32, bool)>>,
+    }
+
+    impl SegmentWarmer for RecordingWarmer {
+        fn warm(&self, segment: &Arc<dyn LeafReader>) -> BoxResult<()> {
+            let writer = self.writer.lock().unwrap().clone().unwrap();
+            let published = writer.segments().iter().any(|published| Arc::ptr_eq(published, segment));
+            self.warmed.lock().unwrap().push((segment.max_doc(), published));
+            Err(LuceneError::IllegalState("warming failed".to_string()).into())
+        }
+    }
+
+    fn document(i: u32) -> Document {
+        let mut document = Document::new();
+        document.add(Field::text("body", "quick fox", Store::No));
+        document.add(Field::numeric_doc_values("price", i as i64));
+        document
+    }
+
+    #[test]
+    fn test_warm_before_publishing() {
+        let warmer = Arc::new(RecordingWarmer::default());
+        let mut config = IndexWriterConfig::new();
+        config.set_segment_warmer(Some(warmer.clone()));
+        let writer = Arc::new(DocumentsWriter::new(config));
+        *warmer.writer.lock().unwrap() = Some(writer.clone());
+
+        for i in 0..4 {
+            writer.add_document(&document(i)).unwrap();
+            if i % 2 == 1 {
+                writer.flush().unwrap();
+            }
+        }
+        writer.force_merge(1).unwrap();
+
+        // Flushed and merged segments are warmed before they're published, and published even if warming fails.
+        assert_eq!(*warmer.warmed.lock().unwrap(), vec![(2, false), (2, false), (4, false)]);
+        assert_eq!(writer.segments().len(), 1);
+        assert_eq!(writer.segments()[0].max_doc(), 4);
+        *warmer.writer.lock().unwrap() = None;
+    }
+
+    #[test]
+    fn test_query_warmer() {
+        let mut warmer = QueryWarmer::new();
+        warmer.add_query(Arc::new(TermQuery::new(Term::new("body", "fox")))).add_doc_values_field("price");
+        assert_eq!(warmer.queries().len(), 1);
+        assert_eq!(warmer.doc_values_fields(), ["price"]);
+
+        let mut config = IndexWriterConfig::new();
+        config.set_segment_warmer(Some(Arc::new(warmer.clone())));
+        let writer = DocumentsWriter::new(config);
+        writer.add_document(&document(0)).unwrap();
+        writer.flush().unwrap();
+        assert_eq!(writer.segments().len(), 1);
+        warmer.warm(&writer.segments()[0]).unwrap();
+        QueryWarmer::new().warm(&writer.segments()[0]).unwrap();
+    }
+}
@@ -0,0 +1,75 @@
+use {
+    crate::{
+        index::{PostingsEnum, SeekStatus, TermsEnum},
+        BoxResult,
+    },
+    std::fmt::Debug,
+};
+
+/// A [TermsEnum] that returns at most one term: the given term, if it exists in the wrapped enum.
+#[derive(Debug)]
+pub struct SingleTermsEnum<'a> {
+    inner: Box<dyn TermsEnum + 'a>,
+    term: Vec<u8>,
+    done: bool,
+}
+
+impl<'a> SingleTermsEnum<'a> {
+    /// Creates a new enum that returns only `term` from `inner`.
+    pub fn new(inner: Box<dyn TermsEnum + 'a>, term: Vec<u8>) -> Self {
+        Self {
+            inner,
+            term,
+            done: false,
+        }
+    }
+}
+
+impl TermsEnum for SingleTermsEnum<'_> {
+    fn next(&mut self) -> BoxResult<Option<&[u8]>> {
+        if self.done {
+            return Ok(None);
+        }
+
+        self.done = true;
+        if self.inner.seek_exact(&self.term)? {
+            Ok(Some(&self.term))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn term(&self) -> &[u8] {
+        &self.term
+    }
+
+    fn seek_ceil(&mut self, target: &[u8]) -> BoxResult<SeekStatus> {
+        if target > self.term.as_slice() {
+            self.done = true;
+            return Ok(SeekStatus::End);
+        }
+
+        self.done = true;
+        if !self.inner.seek_exact(&self.term)? {
+            return Ok(SeekStatus::End);
+        }
+
+        if target == self.term.as_slice() {
+            Ok(SeekStatus::Found)
+        } else {
+            Ok(SeekStatus::NotFound)
+        }
+    }
+
+    fn doc_freq(&self) -> BoxResult<u32> {
+        self.inner.doc_freq()
+    }
+
+    fn total_term_freq(&self) -> BoxResult<u64> {
+        self.inner.total_term_freq()
+    }
+
+    fn postings(&self) -> BoxResult<Box<dyn PostingsEnum>> {
+        self.inner.postings()
+    }
+}
@@ -0,0 +1,529 @@
+use {
+    crate::{
+        document::Document,
+        index::{
+            BinaryDocValues, DocMap, DocValuesType, FloatVectorValues, LeafReader, MemoryBinaryDocValues,
+            MemoryNumericDocValues, MemoryPosting, MemoryPostingsEnum, NumericDocValues, PostingsEnum, SeekStatus,
+            TermVectors, Terms, TermsEnum,
+        },
+        search::{Sort, NO_MORE_DOCS},
+        util::{
+            hnsw::{HnswGraphBuilder, DEFAULT_BEAM_WIDTH, DEFAULT_HNSW_SEED, DEFAULT_MAX_CONN},
+            Accountable, BitSet, FixedBitSet, NamedAccountable,
+        },
+        BoxResult, LuceneError,
+    },
+    std::{collections::HashMap, sync::Arc},
+};
+
+/// A [LeafReader] that presents the documents of another segment in the order of an index sort.
+///
+/// Every document id this reader returns or accepts is a new (sorted) id; the [DocMap] translates them to the ids of
+/// the wrapped reader. Postings and doc values are re-sorted when they are requested, so this is intended for
+/// one-off passes over a segment, such as rewriting it into an index with a different sort, rather than for
+/// searching. Vectors are renumbered up front, with their HNSW graphs relabeled rather than rebuilt.
+#[derive(Debug)]
+pub struct SortingCodecReader {
+    inner: Arc<dyn LeafReader>,
+    sort: Sort,
+    doc_map: Arc<DocMap>,
+    terms: HashMap<String, SortingTerms>,
+    vectors: HashMap<String, FloatVectorValues>,
+    live_docs: Option<FixedBitSet>,
+}
+
+impl SortingCodecReader {
+    /// Wraps `inner` so that its documents are sorted by `sort`. Only document order and numeric doc values fields
+    /// may be used; see [DocMap::sort].
+    pub fn wrap(inner: Arc<dyn LeafReader>, sort: Sort) -> BoxResult<Self> {
+        let doc_map = match DocMap::sort(inner.as_ref(), &sort)? {
+            Some(doc_map) => doc_map,
+            None => DocMap::identity(inner.max_doc()),
+        };
+        Self::new(inner, sort, doc_map)
+    }
+
+    /// Wraps `inner`, renumbering its documents with `doc_map`, which must put them in the order of `sort`.
+    pub(crate) fn new(inner: Arc<dyn LeafReader>, sort: Sort, doc_map: DocMap) -> BoxResult<Self> {
+        if doc_map.size() != inner.max_doc() {
+            return Err(LuceneError::InvalidArgument(format!(
+                "the doc map holds {} documents, but the segment has {}",
+                doc_map.size(),
+                inner.max_doc()
+            ))
+            .into());
+        }
+
+        let doc_map = Arc::new(doc_map);
+        let mut terms = HashMap::new();
+        for field in inner.indexed_fields() {
+            if let Some(field_terms) = inner.terms(field)? {
+                terms.insert(field.to_string(), SortingTerms::new(inner.clone(), field, field_terms, doc_map.clone()));
+            }
+        }
+
+        let mut vectors = HashMap::new();
+        for field in inner.vector_fields() {
+            if let Some(values) = inner.float_vector_values(field)? {
+                vectors.insert(field.to_string(), Self::sort_vectors(values, &doc_map)?);
+            }
+        }
+
+        let live_docs = inner.live_docs().map(|live_docs| {
+            let mut sorted = FixedBitSet::new(live_docs.num_bits());
+            for new_doc in 0..doc_map.size() {
+                if live_docs.get(doc_map.new_to_old(new_doc)) {
+                    sorted.set(new_doc);
+                }
+            }
+            sorted
+        });
+
+        Ok(Self {
+            inner,
+            sort,
+            doc_map,
+            terms,
+            vectors,
+            live_docs,
+        })
+    }
+
+    /// Renumbers the vectors of a field, and the nodes of their graph, in the order of the sorted documents.
+    fn sort_vectors(values: &FloatVectorValues, doc_map: &DocMap) -> BoxResult<FloatVectorValues> {
+        let mut ords: Vec<(u32, u32)> =
+            (0..values.size() as u32).map(|ord| (doc_map.old_to_new(values.ord_to_doc(ord)), ord)).collect();
+        ords.sort_unstable();
+
+        let mut ord_map = vec![0; ords.len()];
+        for (new_ord, &(_, ord)) in ords.iter().enumerate() {
+            ord_map[ord as usize] = new_ord as u32;
+        }
+        let docs: Vec<u32> = ords.iter().map(|&(doc, _)| doc).collect();
+        let vectors: Vec<f32> = ords.iter().flat_map(|&(_, ord)| values.vector(ord)).copied().collect();
+
+        let mut builder = HnswGraphBuilder::new(
+            values.similarity(),
+            values.dimension(),
+            &vectors,
+            DEFAULT_MAX_CONN,
+            DEFAULT_BEAM_WIDTH,
+            DEFAULT_HNSW_SEED,
+        )?;
+        builder.initialize_from(values.graph(), &ord_map)?;
+        let graph = builder.build();
+        FloatVectorValues::new(values.similarity(), values.dimension(), docs.into(), vectors.into(), Arc::new(graph))
+    }
+
+    /// Returns the wrapped reader, whose documents are in their original order.
+    #[inline]
+    pub fn inner(&self) -> &Arc<dyn LeafReader> {
+        &self.inner
+    }
+
+    /// Returns the mapping from the document ids of the wrapped reader to those of this one.
+    #[inline]
+    pub fn doc_map(&self) -> &DocMap {
+        &self.doc_map
+    }
+
+    /// Returns the id in the wrapped reader of the document whose id is `doc`, failing if it is out of bounds.
+    fn old_doc(&self, doc: u32) -> BoxResult<u32> {
+        if doc >= self.max_doc() {
+            return Err(LuceneError::InvalidArgument(format!(
+                "document {doc} is out of bounds (max_doc is {})",
+                self.max_doc()
+            ))
+            .into());
+        }
+        Ok(self.doc_map.new_to_old(doc))
+    }
+}
+
+impl LeafReader for SortingCodecReader {
+    #[inline]
+    fn max_doc(&self) -> u32 {
+        self.inner.max_doc()
+    }
+
+    #[inline]
+    fn num_docs(&self) -> u32 {
+        self.inner.num_docs()
+    }
+
+    #[inline]
+    fn live_docs(&self) -> Option<&FixedBitSet> {
+        self.live_docs.as_ref()
+    }
+
+    fn indexed_fields(&self) -> Vec<&str> {
+        self.terms.keys().map(String::as_str).collect()
+    }
+
+    fn doc_values_fields(&self) -> Vec<(&str, DocValuesType)> {
+        self.inner.doc_values_fields()
+    }
+
+    fn terms(&self, field: &str) -> BoxResult<Option<&dyn Terms>> {
+        Ok(self.terms.get(field).map(|terms| terms as &dyn Terms))
+    }
+
+    fn norms(&self, field: &str) -> BoxResult<Option<Arc<[i64]>>> {
+        Ok(self
+            .inner
+            .norms(field)?
+            .map(|norms| (0..self.max_doc()).map(|doc| norms[self.doc_map.new_to_old(doc) as usize]).collect()))
+    }
+
+    fn numeric_doc_values(&self, field: &str) -> BoxResult<Option<Box<dyn NumericDocValues>>> {
+        let Some(mut doc_values) = self.inner.numeric_doc_values(field)? else {
+            return Ok(None);
+        };
+
+        let mut column = Vec::new();
+        loop {
+            let doc = doc_values.next_doc()?;
+            if doc == NO_MORE_DOCS {
+                break;
+            }
+            column.push((self.doc_map.old_to_new(doc), doc_values.long_value()?));
+        }
+        column.sort_unstable_by_key(|(doc, _)| *doc);
+
+        let (docs, values): (Vec<u32>, Vec<i64>) = column.into_iter().unzip();
+        Ok(Some(Box::new(MemoryNumericDocValues::new(docs.into(), values.into()))))
+    }
+
+    fn binary_doc_values(&self, field: &str) -> BoxResult<Option<Box<dyn BinaryDocValues>>> {
+        let Some(mut doc_values) = self.inner.binary_doc_values(field)? else {
+            return Ok(None);
+        };
+
+        let mut column = Vec::new();
+        loop {
+            let doc = doc_values.next_doc()?;
+            if doc == NO_MORE_DOCS {
+                break;
+            }
+            column.push((self.doc_map.old_to_new(doc), doc_values.binary_value()?.to_vec()));
+        }
+        column.sort_unstable_by_key(|(doc, _)| *doc);
+
+        let (docs, values): (Vec<u32>, Vec<Vec<u8>>) = column.into_iter().unzip();
+        Ok(Some(Box::new(MemoryBinaryDocValues::new(docs.into(), values.into()))))
+    }
+
+    fn document(&self, doc: u32) -> BoxResult<Document> {
+        self.inner.document(self.old_doc(doc)?)
+    }
+
+    fn term_vectors(&self, doc: u32) -> BoxResult<Option<TermVectors>> {
+        self.inner.term_vectors(self.old_doc(doc)?)
+    }
+
+    fn vector_fields(&self) -> Vec<&str> {
+        self.vectors.keys().map(String::as_str).collect()
+    }
+
+    fn float_vector_values(&self, field: &str) -> BoxResult<Option<&FloatVectorValues>> {
+        Ok(self.vectors.get(field))
+    }
+
+    #[inline]
+    fn index_sort(&self) -> Option<&Sort> {
+        Some(&self.sort)
+    }
+
+    #[inline]
+    fn segment_name(&self) -> Option<&str> {
+        self.inner.segment_name()
+    }
+}
+
+impl Accountable for SortingCodecReader {
+    fn ram_bytes_used(&self) -> usize {
+        size_of::<Self>() + self.child_resources().iter().map(Accountable::ram_bytes_used).sum::<usize>()
+    }
+
+    fn child_resources(&self) -> Vec<NamedAccountable> {
+        let mut resources = vec![
+            NamedAccountable::new("inner", self.inner.as_ref()),
+            NamedAccountable::new("doc map", self.doc_map.as_ref()),
+        ];
+        if let Some(live_docs) = &self.live_docs {
+            resources.push(NamedAccountable::new("live docs", live_docs));
+        }
+        if !self.vectors.is_empty() {
+            let vectors = self.vectors.values().map(Accountable::ram_bytes_used).sum();
+            resources.push(NamedAccountable::from_bytes("vectors", vectors));
+        }
+        resources
+    }
+}
+
+/// The [Terms] of a field of a [SortingCodecReader], whose postings are renumbered with its [DocMap].
+///
+/// Term statistics don't depend on the order of the documents, so they are copied from the wrapped terms up front.
+#[derive(Debug)]
+struct SortingTerms {
+    inner: Arc<dyn LeafReader>,
+    field: String,
+    doc_map: Arc<DocMap>,
+    size: Option<u64>,
+    sum_total_term_freq: u64,
+    sum_doc_freq: u64,
+    doc_count: u32,
+    has_freqs: bool,
+    has_positions: bool,
+    has_offsets: bool,
+}
+
+impl SortingTerms {
+    fn new(inner: Arc<dyn LeafReader>, field: &str, terms: &dyn Terms, doc_map: Arc<DocMap>) -> Self {
+        Self {
+            field: field.to_string(),
+            doc_map,
+            size: terms.size(),
+            sum_total_term_freq: terms.sum_total_term_freq(),
+            sum_doc_freq: terms.sum_doc_freq(),
+            doc_count: terms.doc_count(),
+            has_freqs: terms.has_freqs(),
+            has_positions: terms.has_positions(),
+            has_offsets: terms.has_offsets(),
+            inner,
+        }
+    }
+}
+
+impl Terms for SortingTerms {
+    fn iterator(&self) -> BoxResult<Box<dyn TermsEnum + '_>> {
+        let Some(terms) = self.inner.terms(&self.field)? else {
+            return Err(LuceneError::IllegalState(format!("field {} is no longer indexed", self.field)).into());
+        };
+
+        Ok(Box::new(SortingTermsEnum {
+            inner: terms.iterator()?,
+            doc_map: &self.doc_map,
+        }))
+    }
+
+    #[inline]
+    fn size(&self) -> Option<u64> {
+        self.size
+    }
+
+    #[inline]
+    fn sum_total_term_freq(&self) -> u64 {
+        self.sum_total_term_freq
+    }
+
+    #[inline]
+    fn sum_doc_freq(&self) -> u64 {
+        self.sum_doc_freq
+    }
+
+    #[inline]
+    fn doc_count(&self) -> u32 {
+        self.doc_count
+    }
+
+    #[inline]
+    fn has_freqs(&self) -> bool {
+        self.has_freqs
+    }
+
+    #[inline]
+    fn has_positions(&self) -> bool {
+        self.has_positions
+    }
+
+    #[inline]
+    fn has_offsets(&self) -> bool {
+        self.has_offsets
+    }
+}
+
+/// A [TermsEnum] whose postings are renumbered with a [DocMap].
+#[derive(Debug)]
+struct SortingTermsEnum<'a> {
+    inner: Box<dyn TermsEnum + 'a>,
+    doc_map: &'a DocMap,
+}
+
+impl TermsEnum for SortingTermsEnum<'_> {
+    fn next(&mut self) -> BoxResult<Option<&[u8]>> {
+        self.inner.next()
+    }
+
+    fn term(&self) -> &[u8] {
+        self.inner.term()
+    }
+
+    fn seek_ceil(&mut self, target: &[u8]) -> BoxResult<SeekStatus> {
+        self.inner.seek_ceil(target)
+    }
+
+    fn seek_exact(&mut self, target: &[u8]) -> BoxResult<bool> {
+        self.inner.seek_exact(target)
+    }
+
+    fn doc_freq(&self) -> BoxResult<u32> {
+        self.inner.doc_freq()
+    }
+
+    fn total_term_freq(&self) -> BoxResult<u64> {
+        self.inner.total_term_freq()
+    }
+
+    /// Reads all of the postings of the term, since they must be returned in the new order.
+    fn postings(&self) -> BoxResult<Box<dyn PostingsEnum>> {
+        let mut postings = self.inner.postings()?;
+        let mut sorted = Vec::new();
+        loop {
+            let doc = postings.next_doc()?;
+            if doc == NO_MORE_DOCS {
+                break;
+            }
+
+            let freq = postings.freq()?;
+            let mut positions = Vec::new();
+            let mut payloads = Vec::new();
+            let mut offsets = Vec::new();
+            while positions.len() < freq as usize {
+                match postings.next_position()? {
+                    Some(position) => {
+                        positions.push(position);
+                        payloads.push(postings.payload()?.map(<[u8]>::to_vec).unwrap_or_default());
+                        if let (Some(start), Some(end)) = (postings.start_offset()?, postings.end_offset()?) {
+                            offsets.push((start, end));
+                        }
+                    }
+                    None => break,
+                }
+            }
+            if payloads.iter().all(Vec::is_empty) {
+                payloads.clear();
+            }
+            if offsets.len() != positions.len() {
+                offsets.clear();
+            }
+            sorted.push(MemoryPosting {
+                doc: self.doc_map.old_to_new(doc),
+                freq,
+                positions,
+                payloads,
+                offsets,
+            });
+        }
+        sorted.sort_unstable_by_key(|posting| posting.doc);
+
+        Ok(Box::new(MemoryPostingsEnum::new(sorted.into())))
+    }
+
+    fn boost(&self) -> f32 {
+        self.inner.boost()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{
+            analysis::SimpleAnalyzer,
+            document::{Document, Field, Store},
+            index::{LeafReader, MemorySegmentBuilder, SegmentReader, SortingCodecReader, VectorSimilarityFunction},
+            search::{BasicSortField, Sort},
+            util::{BitSet, FixedBitSet},
+        },
+        pretty_assertions::assert_eq,
+        std::sync::Arc,
+    };
+
+    fn rank_sort() -> Sort {
+        Sort::from_fields(vec![Box::new(BasicSortField::for_i64_field("rank", None))]).unwrap()
+    }
+
+    #[test]
+    fn test_sorting_codec_reader() {
+        let mut builder = MemorySegmentBuilder::new(Arc::new(SimpleAnalyzer));
+        for (id, rank, body) in [("a", 30, "fox fox"), ("b", 10, "dog"), ("c", 20, "the fox")] {
+            let mut doc = Document::new();
+            doc.add(Field::string("id", id, Store::Yes));
+            doc.add(Field::text("body", body, Store::No));
+            doc.add(Field::numeric_doc_values("rank", rank));
+            doc.add(Field::binary_doc_values("tag", id.as_bytes().to_vec()));
+            doc.add(
+                Field::knn_vector("embedding", vec![rank as f32, 1.0], VectorSimilarityFunction::Euclidean).unwrap(),
+            );
+            builder.add_document(&doc).unwrap();
+        }
+        let unsorted = builder.build();
+        let body_norms = unsorted.norms("body").unwrap().unwrap();
+
+        let mut live_docs = FixedBitSet::new(3);
+        live_docs.set_range(0, 3);
+        live_docs.clear(1);
+        let segment = Arc::new(SegmentReader::new(Arc::new(unsorted), Some(live_docs)).unwrap());
+
+        let reader = SortingCodecReader::wrap(segment, rank_sort()).unwrap();
+        assert_eq!(reader.doc_map().new_to_old(0), 1);
+        assert!(reader.index_sort().is_some());
+        assert_eq!(reader.num_docs(), 2);
+
+        let ids: Vec<String> = (0..3).map(|doc| reader.document(doc).unwrap().get("id").unwrap().to_string()).collect();
+        assert_eq!(ids, vec!["b", "c", "a"]);
+        assert!(reader.document(3).is_err());
+
+        // Document "b", deleted in the wrapped reader, is now the first.
+        let live_docs = reader.live_docs().unwrap();
+        assert_eq!((0..3).map(|doc| live_docs.get(doc)).collect::<Vec<_>>(), vec![false, true, true]);
+
+        let norms = reader.norms("body").unwrap().unwrap();
+        assert_eq!(&*norms, &[body_norms[1], body_norms[2], body_norms[0]]);
+
+        let mut ranks = reader.numeric_doc_values("rank").unwrap().unwrap();
+        for (doc, rank) in [(0, 10), (1, 20), (2, 30)] {
+            assert!(ranks.advance_exact(doc).unwrap());
+            assert_eq!(ranks.long_value().unwrap(), rank);
+        }
+        let mut tags = reader.binary_doc_values("tag").unwrap().unwrap();
+        assert!(tags.advance_exact(2).unwrap());
+        assert_eq!(tags.binary_value().unwrap(), b"a");
+
+        assert_eq!(reader.indexed_fields().len(), 2);
+        let body = reader.terms("body").unwrap().unwrap();
+        assert_eq!(body.doc_count(), 3);
+        let mut te = body.iterator().unwrap();
+        assert!(te.seek_exact(b"fox").unwrap());
+        let mut postings = te.postings().unwrap();
+        assert_eq!(postings.next_doc().unwrap(), 1);
+        assert_eq!(postings.freq().unwrap(), 1);
+        assert_eq!(postings.next_position().unwrap(), Some(1));
+        assert_eq!(postings.next_doc().unwrap(), 2);
+        assert_eq!(postings.freq().unwrap(), 2);
+        assert_eq!(postings.next_position().unwrap(), Some(0));
+        assert_eq!(postings.next_position().unwrap(), Some(1));
+
+        let vectors = reader.float_vector_values("embedding").unwrap().unwrap();
+        assert_eq!(vectors.docs(), &[0, 1, 2]);
+        assert_eq!((vectors.vector(0), vectors.vector(2)), (&[10.0, 1.0][..], &[30.0, 1.0][..]));
+        assert_eq!(vectors.graph().size(), 3);
+        assert_eq!(vectors.search(&[29.0, 1.0], 1, &|_| true).unwrap()[0].0, 2);
+    }
+
+    #[test]
+    fn test_already_sorted() {
+        let mut builder = MemorySegmentBuilder::new(Arc::new(SimpleAnalyzer));
+        builder.set_index_sort(rank_sort()).unwrap();
+        for rank in [3, 1, 2] {
+            let mut doc = Document::new();
+            doc.add(Field::string("rank", rank.to_string(), Store::Yes));
+            doc.add(Field::numeric_doc_values("rank", rank));
+            builder.add_document(&doc).unwrap();
+        }
+
+        let reader = SortingCodecReader::wrap(Arc::new(builder.build()), rank_sort()).unwrap();
+        assert!(reader.doc_map().is_identity());
+        assert_eq!(reader.document(0).unwrap().get("rank"), Some("1"));
+    }
+}
@@ -0,0 +1,518 @@
+use {
+    crate::{
+        document::Document,
+        index::{
+            BinaryDocValues, CacheHelper, CacheKey, DocValuesSkipper, DocValuesType, FloatVectorValues, IndexCommit,
+            IndexReader, LeafReader, LeafReaderContext, NumericDocValues, TermVectors, Terms,
+        },
+        metrics::{
+            MetricsRecorder, STORED_FIELDS_CACHE_EVICTIONS, STORED_FIELDS_CACHE_HITS, STORED_FIELDS_CACHE_MISSES,
+            STORED_FIELDS_CACHE_RAM_BYTES,
+        },
+        search::Sort,
+        util::{Accountable, FixedBitSet, NamedAccountable},
+        BoxResult, LuceneError,
+    },
+    std::{
+        collections::{BTreeMap, HashMap, HashSet},
+        sync::{Arc, Mutex, Weak},
+    },
+};
+
+/// Identifies a block of documents by the core key of its segment and its index in the segment.
+type BlockKey = (CacheKey, u32);
+
+/// A least recently used cache of the stored fields of blocks of consecutive documents, shared by the segments of a
+/// reader (see [CachingStoredFieldsIndexReader]), so that the hot documents of a result page are rendered without
+/// reading and decoding them again.
+///
+/// Blocks are keyed by the core cache key of their segment (see [LeafReader::core_cache_helper]), so they stay valid
+/// across near-real-time refreshes that only delete documents, and are dropped once the segment's core is. The least
+/// recently used blocks are evicted once the cache takes more than its maximum number of bytes. Segments without a
+/// core cache key are read directly.
+///
+/// Hits, misses, evictions and the memory used are reported to the recorder set with
+/// [StoredFieldsCache::set_metrics], if any.
+#[derive(Debug)]
+pub struct StoredFieldsCache {
+    block_size: u32,
+    max_ram_bytes: usize,
+    metrics: Option<Arc<dyn MetricsRecorder>>,
+    state: Mutex<CacheState>,
+}
+
+#[derive(Debug, Default)]
+struct CacheState {
+    /// The documents of each block, the bytes they take, and when they were last used.
+    blocks: HashMap<BlockKey, (Arc<[Document]>, usize, u64)>,
+
+    /// The cached blocks, ordered by when they were last used.
+    lru: BTreeMap<u64, BlockKey>,
+
+    /// The cores whose blocks are evicted once they're dropped.
+    cores: HashSet<CacheKey>,
+    clock: u64,
+    ram_bytes_used: usize,
+    hit_count: u64,
+    miss_count: u64,
+    eviction_count: u64,
+}
+
+impl StoredFieldsCache {
+    /// Creates a cache of blocks of `block_size` documents, taking up to `max_ram_bytes` bytes. This fails with
+    /// [LuceneError::InvalidArgument] if `block_size` is zero.
+    pub fn new(block_size: u32, max_ram_bytes: usize) -> BoxResult<Self> {
+        if block_size == 0 {
+            return Err(LuceneError::InvalidArgument("the block size must be positive".to_string()).into());
+        }
+
+        Ok(Self {
+            block_size,
+            max_ram_bytes,
+            metrics: None,
+            state: Mutex::default(),
+        })
+    }
+
+    /// Returns the number of consecutive documents loaded and cached together.
+    #[inline]
+    pub fn block_size(&self) -> u32 {
+        self.block_size
+    }
+
+    /// Returns the most memory the cache uses, in bytes.
+    #[inline]
+    pub fn max_ram_bytes(&self) -> usize {
+        self.max_ram_bytes
+    }
+
+    /// Returns the recorder that cache metrics are reported to, if any.
+    #[inline]
+    pub fn metrics(&self) -> Option<&Arc<dyn MetricsRecorder>> {
+        self.metrics.as_ref()
+    }
+
+    /// Sets the recorder that cache metrics are reported to: the hits, misses and evictions, and the memory used.
+    pub fn set_metrics(&mut self, metrics: Option<Arc<dyn MetricsRecorder>>) -> &mut Self {
+        self.metrics = metrics;
+        self
+    }
+
+    /// Returns the number of blocks in the cache.
+    pub fn size(&self) -> usize {
+        self.state.lock().unwrap().lru.len()
+    }
+
+    /// Returns the memory used by the cached blocks, in bytes.
+    pub fn ram_bytes_used(&self) -> usize {
+        self.state.lock().unwrap().ram_bytes_used
+    }
+
+    /// Returns the number of times a document's block was found in the cache.
+    pub fn hit_count(&self) -> u64 {
+        self.state.lock().unwrap().hit_count
+    }
+
+    /// Returns the number of times a document's block was loaded.
+    pub fn miss_count(&self) -> u64 {
+        self.state.lock().unwrap().miss_count
+    }
+
+    /// Returns the number of blocks evicted to make room for others.
+    pub fn eviction_count(&self) -> u64 {
+        self.state.lock().unwrap().eviction_count
+    }
+
+    /// Removes every block from the cache.
+    pub fn clear(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.blocks.clear();
+        state.lru.clear();
+        state.ram_bytes_used = 0;
+        drop(state);
+        self.record(STORED_FIELDS_CACHE_RAM_BYTES, 0);
+    }
+
+    /// Returns the stored fields of a segment-local document of `reader`, from the cache if possible.
+    pub fn document(self: &Arc<Self>, reader: &dyn LeafReader, doc: u32) -> BoxResult<Document> {
+        let Some(helper) = reader.core_cache_helper() else {
+            return reader.document(doc);
+        };
+        if doc >= reader.max_doc() {
+            return reader.document(doc);
+        }
+
+        let key = (helper.key(), doc / self.block_size);
+        let offset = (doc % self.block_size) as usize;
+        if let Some(block) = self.get(&key) {
+            self.record(STORED_FIELDS_CACHE_HITS, 1);
+            return Ok(block[offset].clone());
+        }
+        self.record(STORED_FIELDS_CACHE_MISSES, 1);
+
+        let start = key.1 * self.block_size;
+        let end = reader.max_doc().min(start.saturating_add(self.block_size));
+        let block: Arc<[Document]> = (start..end).map(|doc| reader.document(doc)).collect::<BoxResult<_>>()?;
+        let document = block[offset].clone();
+        self.put(helper, key, block);
+        Ok(document)
+    }
+
+    fn get(&self, key: &BlockKey) -> Option<Arc<[Document]>> {
+        let mut state = self.state.lock().unwrap();
+        let stamp = state.clock;
+        state.clock += 1;
+
+        let found =
+            state.blocks.get_mut(key).map(|(block, _, last_used)| (block.clone(), std::mem::replace(last_used, stamp)));
+        match found {
+            Some((block, last_used)) => {
+                state.hit_count += 1;
+                state.lru.remove(&last_used);
+                state.lru.insert(stamp, *key);
+                Some(block)
+            }
+            None => {
+                state.miss_count += 1;
+                None
+            }
+        }
+    }
+
+    fn put(self: &Arc<Self>, helper: &CacheHelper, key: BlockKey, block: Arc<[Document]>) {
+        let ram_bytes = block.iter().map(Accountable::ram_bytes_used).sum::<usize>();
+        if ram_bytes > self.max_ram_bytes {
+            return;
+        }
+
+        let mut state = self.state.lock().unwrap();
+        if state.blocks.contains_key(&key) {
+            return;
+        }
+        if state.cores.insert(key.0) {
+            let cache: Weak<Self> = Arc::downgrade(self);
+            helper.add_closed_listener(move |core| {
+                if let Some(cache) = cache.upgrade() {
+                    cache.remove_core(core);
+                }
+            });
+        }
+
+        let stamp = state.clock;
+        state.clock += 1;
+        state.blocks.insert(key, (block, ram_bytes, stamp));
+        state.lru.insert(stamp, key);
+        state.ram_bytes_used += ram_bytes;
+
+        let mut evicted = 0;
+        while state.ram_bytes_used > self.max_ram_bytes {
+            let Some((_, key)) = state.lru.pop_first() else {
+                break;
+            };
+            if let Some((_, ram_bytes, _)) = state.blocks.remove(&key) {
+                state.ram_bytes_used -= ram_bytes;
+            }
+            evicted += 1;
+        }
+        state.eviction_count += evicted;
+        let ram_bytes_used = state.ram_bytes_used;
+        drop(state);
+
+        if evicted > 0 {
+            self.record(STORED_FIELDS_CACHE_EVICTIONS, evicted);
+        }
+        self.record(STORED_FIELDS_CACHE_RAM_BYTES, ram_bytes_used as u64);
+    }
+
+    /// Drops the blocks of a core that was dropped.
+    fn remove_core(&self, core: CacheKey) {
+        let mut state = self.state.lock().unwrap();
+        state.cores.remove(&core);
+        let keys: Vec<BlockKey> = state.blocks.keys().filter(|key| key.0 == core).copied().collect();
+        for key in keys {
+            if let Some((_, ram_bytes, last_used)) = state.blocks.remove(&key) {
+                state.lru.remove(&last_used);
+                state.ram_bytes_used -= ram_bytes;
+            }
+        }
+        let ram_bytes_used = state.ram_bytes_used;
+        drop(state);
+        self.record(STORED_FIELDS_CACHE_RAM_BYTES, ram_bytes_used as u64);
+    }
+
+    fn record(&self, name: &str, value: u64) {
+        let Some(metrics) = &self.metrics else {
+            return;
+        };
+        if name == STORED_FIELDS_CACHE_RAM_BYTES {
+            metrics.set_gauge(name, value as f64);
+        } else {
+            metrics.increment_counter(name, value);
+        }
+    }
+}
+
+/// An [IndexReader] whose segments read stored fields through a shared [StoredFieldsCache].
+#[derive(Debug)]
+pub struct CachingStoredFieldsIndexReader {
+    inner: Arc<dyn IndexReader>,
+    cache: Arc<StoredFieldsCache>,
+    leaves: Vec<LeafReaderContext>,
+}
+
+impl CachingStoredFieldsIndexReader {
+    /// Wraps every segment of `inner` so that stored fields are read through `cache`.
+    pub fn new(inner: Arc<dyn IndexReader>, cache: Arc<StoredFieldsCache>) -> Self {
+        let leaves = inner
+            .leaves()
+            .iter()
+            .map(|leaf| {
+                let reader = CachingStoredFieldsLeafReader::new(leaf.reader_arc().clone(), cache.clone());
+                LeafReaderContext::new(leaf.ord(), leaf.doc_base(), Arc::new(reader))
+            })
+            .collect();
+
+        Self {
+            inner,
+            cache,
+            leaves,
+        }
+    }
+
+    /// Returns the wrapped reader.
+    #[inline]
+    pub fn inner(&self) -> &Arc<dyn IndexReader> {
+        &self.inner
+    }
+
+    /// Returns the cache stored fields are read through.
+    #[inline]
+    pub fn cache(&self) -> &Arc<StoredFieldsCache> {
+        &self.cache
+    }
+}
+
+impl IndexReader for CachingStoredFieldsIndexReader {
+    #[inline]
+    fn leaves(&self) -> &[LeafReaderContext] {
+        &self.leaves
+    }
+
+    #[inline]
+    fn index_commit(&self) -> Option<&IndexCommit> {
+        self.inner.index_commit()
+    }
+}
+
+impl Accountable for CachingStoredFieldsIndexReader {
+    fn ram_bytes_used(&self) -> usize {
+        self.inner.ram_bytes_used()
+    }
+
+    fn child_resources(&self) -> Vec<NamedAccountable> {
+        self.inner.child_resources()
+    }
+}
+
+/// A [LeafReader] that reads stored fields through a [StoredFieldsCache].
+#[derive(Debug)]
+pub struct CachingStoredFieldsLeafReader {
+    inner: Arc<dyn LeafReader>,
+    cache: Arc<StoredFieldsCache>,
+}
+
+impl CachingStoredFieldsLeafReader {
+    /// Wraps `inner` so that stored fields are read through `cache`.
+    pub fn new(inner: Arc<dyn LeafReader>, cache: Arc<StoredFieldsCache>) -> Self {
+        Self {
+            inner,
+            cache,
+        }
+    }
+}
+
+impl LeafReader for CachingStoredFieldsLeafReader {
+    #[inline]
+    fn max_doc(&self) -> u32 {
+        self.inner.max_doc()
+    }
+
+    #[inline]
+    fn num_docs(&self) -> u32 {
+        self.inner.num_docs()
+    }
+
+    #[inline]
+    fn live_docs(&self) -> Option<&FixedBitSet> {
+        self.inner.live_docs()
+    }
+
+    fn indexed_fields(&self) -> Vec<&str> {
+        self.inner.indexed_fields()
+    }
+
+    fn doc_values_fields(&self) -> Vec<(&str, DocValuesType)> {
+        self.inner.doc_values_fields()
+    }
+
+    fn terms(&self, field: &str) -> BoxResult<Option<&dyn Terms>> {
+        self.inner.terms(field)
+    }
+
+    fn norms(&self, field: &str) -> BoxResult<Option<Arc<[i64]>>> {
+        self.inner.norms(field)
+    }
+
+    fn numeric_doc_values(&self, field: &str) -> BoxResult<Option<Box<dyn NumericDocValues>>> {
+        self.inner.numeric_doc_values(field)
+    }
+
+    fn doc_values_skipper(&self, field: &str) -> BoxResult<Option<Box<dyn DocValuesSkipper>>> {
+        self.inner.doc_values_skipper(field)
+    }
+
+    fn binary_doc_values(&self, field: &str) -> BoxResult<Option<Box<dyn BinaryDocValues>>> {
+        self.inner.binary_doc_values(field)
+    }
+
+    fn document(&self, doc: u32) -> BoxResult<Document> {
+        self.cache.document(self.inner.as_ref(), doc)
+    }
+
+    fn term_vectors(&self, doc: u32) -> BoxResult<Option<TermVectors>> {
+        self.inner.term_vectors(doc)
+    }
+
+    fn vector_fields(&self) -> Vec<&str> {
+        self.inner.vector_fields()
+    }
+
+    fn float_vector_values(&self, field: &str) -> BoxResult<Option<&FloatVectorValues>> {
+        self.inner.float_vector_values(field)
+    }
+
+    #[inline]
+    fn index_sort(&self) -> Option<&Sort> {
+        self.inner.index_sort()
+    }
+
+    #[inline]
+    fn segment_name(&self) -> Option<&str> {
+        self.inner.segment_name()
+    }
+
+    #[inline]
+    fn core_cache_helper(&self) -> Option<&CacheHelper> {
+        self.inner.core_cache_helper()
+    }
+
+    #[inline]
+    fn reader_cache_helper(&self) -> Option<&CacheHelper> {
+        self.inner.reader_cache_helper()
+    }
+}
+
+impl Accountable for CachingStoredFieldsLeafReader {
+    fn ram_bytes_used(&self) -> usize {
+        self.inner.ram_bytes_used()
+    }
+
+    fn child_resources(&self) -> Vec<NamedAccountable> {
+        self.inner.child_resources()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{
+            document::{Document, Field},
+            index::{
+                CachingStoredFieldsIndexReader, IndexReader, LeafReader, MultiReader, SegmentReader, StoredFieldsCache,
+            },
+            metrics::{
+                MemoryMetricsRecorder, STORED_FIELDS_CACHE_EVICTIONS, STORED_FIELDS_CACHE_HITS,
+                STORED_FIELDS_CACHE_MISSES, STORED_FIELDS_CACHE_RAM_BYTES,
+            },
+            search::test_util,
+            util::{Accountable, FixedBitSet},
+        },
+        pretty_assertions::assert_eq,
+        std::sync::Arc,
+    };
+
+    fn segment(names: &[&str]) -> Arc<dyn LeafReader> {
+        test_util::segment(names.iter().map(|name| Document::from_iter([Field::stored("name", *name)])))
+    }
+
+    fn name(reader: &dyn IndexReader, doc: u32) -> String {
+        reader.document(doc).unwrap().get("name").unwrap().to_string()
+    }
+
+    #[test]
+    fn test_block_cache() {
+        let metrics = Arc::new(MemoryMetricsRecorder::new());
+        let mut cache = StoredFieldsCache::new(2, 1 << 20).unwrap();
+        cache.set_metrics(Some(metrics.clone()));
+        let cache = Arc::new(cache);
+
+        let first = segment(&["a", "b", "c"]);
+        let second = segment(&["d", "e"]);
+        let segments: Vec<Arc<dyn LeafReader>> = vec![first.clone(), second.clone()];
+        let reader = CachingStoredFieldsIndexReader::new(Arc::new(MultiReader::new(segments).unwrap()), cache.clone());
+
+        // Documents are loaded two at a time.
+        let names: Vec<String> = (0..5).map(|doc| name(&reader, doc)).collect();
+        assert_eq!(names, ["a", "b", "c", "d", "e"]);
+        assert_eq!((cache.size(), cache.hit_count(), cache.miss_count()), (3, 2, 3));
+        assert_eq!(metrics.counter(STORED_FIELDS_CACHE_HITS), 2);
+        assert_eq!(metrics.counter(STORED_FIELDS_CACHE_MISSES), 3);
+        assert_eq!(metrics.gauge(STORED_FIELDS_CACHE_RAM_BYTES), Some(cache.ram_bytes_used() as f64));
+        assert!(reader.document(5).is_err());
+
+        // A refresh deleting a document shares the blocks of the segment's core.
+        let mut live_docs = FixedBitSet::new(3);
+        live_docs.set_range(1, 3);
+        let segments: Vec<Arc<dyn LeafReader>> =
+            vec![Arc::new(SegmentReader::new(first.clone(), Some(live_docs)).unwrap()), second.clone()];
+        let refreshed =
+            CachingStoredFieldsIndexReader::new(Arc::new(MultiReader::new(segments).unwrap()), cache.clone());
+        assert_eq!(name(&refreshed, 2), "c");
+        assert_eq!((cache.size(), cache.hit_count(), cache.miss_count()), (3, 3, 3));
+
+        // Blocks are dropped along with their segment.
+        drop((reader, refreshed, first));
+        assert_eq!(cache.size(), 1);
+        drop(second);
+        assert_eq!((cache.size(), cache.ram_bytes_used()), (0, 0));
+        assert_eq!(metrics.gauge(STORED_FIELDS_CACHE_RAM_BYTES), Some(0.0));
+        assert_eq!(metrics.counter(STORED_FIELDS_CACHE_EVICTIONS), 0);
+        assert!(StoredFieldsCache::new(0, 1 << 20).is_err());
+    }
+
+    #[test]
+    fn test_eviction() {
+        let segment = segment(&["a", "b", "c", "d", "e", "f"]);
+        let block_bytes = (0..2).map(|doc| segment.document(doc).unwrap().ram_bytes_used()).sum::<usize>();
+        let cache = Arc::new(StoredFieldsCache::new(2, block_bytes * 2).unwrap());
+
+        // The least recently used of three blocks is evicted.
+        for doc in [0, 2, 0, 4] {
+            cache.document(segment.as_ref(), doc).unwrap();
+        }
+        assert_eq!((cache.size(), cache.eviction_count()), (2, 1));
+        assert!(cache.ram_bytes_used() <= cache.max_ram_bytes());
+        assert_eq!(cache.document(segment.as_ref(), 1).unwrap().get("name"), Some("b"));
+        assert_eq!((cache.hit_count(), cache.miss_count()), (2, 3));
+        assert_eq!(cache.document(segment.as_ref(), 3).unwrap().get("name"), Some("d"));
+        assert_eq!((cache.miss_count(), cache.eviction_count()), (4, 2));
+
+        // Blocks larger than the cache aren't cached.
+        let tiny = Arc::new(StoredFieldsCache::new(2, 1).unwrap());
+        assert_eq!(tiny.document(segment.as_ref(), 5).unwrap().get("name"), Some("f"));
+        assert_eq!(tiny.size(), 0);
+
+        cache.clear();
+        assert_eq!((cache.size(), cache.ram_bytes_used()), (0, 0));
+    }
+}
@@ -0,0 +1,172 @@
+use {
+    crate::{
+        document::Document,
+        index::{IndexWriter, IndexWriterConfig, LeafReader, MergeStats},
+        io::{BlockingExecutor, Directory},
+        BoxResult,
+    },
+    std::{
+        fmt::{Debug, Formatter, Result as FmtResult},
+        sync::Arc,
+    },
+};
+
+/// A blocking facade over [IndexWriter], for command-line tools, game engines and other programs without an async
+/// runtime. Each operation is driven to completion on the calling thread by a [BlockingExecutor].
+///
+/// Searchers over the flushed segments are opened with [crate::search::SyncIndexSearcher::open].
+///
+/// A `SyncIndexWriter` must not be used from within an asynchronous context; use [IndexWriter] there instead.
+pub struct SyncIndexWriter {
+    writer: IndexWriter,
+    executor: BlockingExecutor,
+}
+
+impl SyncIndexWriter {
+    /// Opens an index writer on the given directory. See [IndexWriter::new].
+    pub fn new(directory: Box<dyn Directory + Send + Sync>, config: IndexWriterConfig) -> BoxResult<Self> {
+        let executor = BlockingExecutor::new()?;
+        let writer = executor.block_on(IndexWriter::new(directory, config))?;
+        Ok(Self {
+            writer,
+            executor,
+        })
+    }
+
+    /// Returns the directory this writer is writing to.
+    #[inline]
+    pub fn directory(&self) -> &dyn Directory {
+        self.writer.directory()
+    }
+
+    /// Returns the configuration used to create this writer.
+    #[inline]
+    pub fn config(&self) -> &IndexWriterConfig {
+        self.writer.config()
+    }
+
+    /// Indicates whether this writer is still open.
+    #[inline]
+    pub fn is_open(&self) -> bool {
+        self.writer.is_open()
+    }
+
+    /// Verifies that this writer is open and still holds a valid write lock.
+    pub fn ensure_open(&self) -> BoxResult<()> {
+        self.writer.ensure_open()
+    }
+
+    /// Adds a document, returning whether the buffered documents were flushed. See [IndexWriter::add_document].
+    pub fn add_document(&self, document: &Document) -> BoxResult<bool> {
+        self.writer.add_document(document)
+    }
+
+    /// Adds the documents of an iterator, returning the number of flushes. See [IndexWriter::add_document].
+    pub fn add_documents(&self, documents: impl IntoIterator<Item = Document>) -> BoxResult<usize> {
+        let mut flushes = 0;
+        for document in documents {
+            flushes += self.writer.add_document(&document)? as usize;
+        }
+        Ok(flushes)
+    }
+
+    /// Flushes the buffered documents to a new segment. See [IndexWriter::flush].
+    pub fn flush(&self) -> BoxResult<()> {
+        self.writer.flush()
+    }
+
+    /// Merges the flushed segments until there are at most `max_num_segments`. See [IndexWriter::force_merge].
+    pub fn force_merge(&self, max_num_segments: usize) -> BoxResult<MergeStats> {
+        self.writer.force_merge(max_num_segments)
+    }
+
+    /// Rewrites the flushed segments with more than `pct_allowed` percent of deleted documents. See
+    /// [IndexWriter::force_merge_deletes].
+    pub fn force_merge_deletes(&self, pct_allowed: f64) -> BoxResult<MergeStats> {
+        self.writer.force_merge_deletes(pct_allowed)
+    }
+
+    /// Adds the segments of the indexes in `directories` by copying their files. See [IndexWriter::add_indexes].
+    pub fn add_indexes<D: Directory>(&mut self, directories: &mut [D]) -> BoxResult<()> {
+        self.executor.block_on(self.writer.add_indexes(directories))
+    }
+
+    /// Adds the live documents of `readers` as a new segment. See [IndexWriter::add_indexes_from_readers].
+    pub fn add_indexes_from_readers(&self, readers: &[Arc<dyn LeafReader>]) -> BoxResult<u32> {
+        self.writer.add_indexes_from_readers(readers)
+    }
+
+    /// Commits the segments of the index, returning the name of the new `segments_N` file. See
+    /// [IndexWriter::commit].
+    pub fn commit(&mut self) -> BoxResult<String> {
+        self.executor.block_on(self.writer.commit())
+    }
+
+    /// Closes this writer, releasing the write lock.
+    pub fn close(&mut self) -> BoxResult<()> {
+        self.executor.block_on(self.writer.close())
+    }
+
+    /// Returns the executor driving the writer, which can also run other asynchronous operations, such as opening
+    /// a directory.
+    #[inline]
+    pub fn executor(&self) -> &BlockingExecutor {
+        &self.executor
+    }
+
+    /// Returns the wrapped writer.
+    #[inline]
+    pub fn inner(&self) -> &IndexWriter {
+        &self.writer
+    }
+
+    /// Unwraps this facade, returning the wrapped writer.
+    #[inline]
+    pub fn into_inner(self) -> IndexWriter {
+        self.writer
+    }
+}
+
+impl Debug for SyncIndexWriter {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        f.debug_struct("SyncIndexWriter").field("writer", &self.writer).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        index::{IndexWriterConfig, SyncIndexWriter},
+        io::ByteBuffersDirectory,
+        LuceneError,
+    };
+
+    #[test]
+    fn test_sync_index_writer() {
+        let dir = ByteBuffersDirectory::new();
+        let mut writer = SyncIndexWriter::new(Box::new(dir.clone()), IndexWriterConfig::new()).unwrap();
+        writer.ensure_open().unwrap();
+        let err = SyncIndexWriter::new(Box::new(dir), IndexWriterConfig::new()).unwrap_err();
+        assert!(matches!(err.downcast_ref::<LuceneError>(), Some(LuceneError::LockObtainFailed(_))));
+        writer.close().unwrap();
+        assert!(!writer.is_open());
+    }
+
+    #[cfg(feature = "tokio-runtime")]
+    #[test]
+    fn test_sync_index_writer_on_filesystem() {
+        use {
+            crate::{fs::FilesystemDirectory, io::BlockingExecutor},
+            std::fs::remove_dir_all,
+        };
+
+        // Tokio-backed filesystem I/O works without an ambient runtime.
+        let path = std::env::temp_dir().join(format!("lucene-sync-writer-{:016x}", rand::random::<u64>()));
+        let dir = BlockingExecutor::new().unwrap().block_on(FilesystemDirectory::open_or_create(&path)).unwrap();
+        let mut writer = SyncIndexWriter::new(Box::new(dir), IndexWriterConfig::new()).unwrap();
+        writer.ensure_open().unwrap();
+        assert_eq!(writer.commit().unwrap(), "segments_1");
+        writer.close().unwrap();
+        remove_dir_all(&path).unwrap();
+    }
+}
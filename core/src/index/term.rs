@@ -0,0 +1,52 @@
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+/// A word from text: the unit of search. A term is a field name plus the bytes of the token in that field.
+///
+/// Terms are ordered first by field name, then by their bytes (which, for UTF-8 text, is Unicode code point order).
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Term {
+    field: String,
+    bytes: Vec<u8>,
+}
+
+impl Term {
+    /// Creates a new term for the given field and raw bytes.
+    pub fn new<F: Into<String>, B: Into<Vec<u8>>>(field: F, bytes: B) -> Self {
+        Self {
+            field: field.into(),
+            bytes: bytes.into(),
+        }
+    }
+
+    /// Creates a new term for the given field and text. The text is stored as UTF-8.
+    pub fn from_text<F: Into<String>>(field: F, text: &str) -> Self {
+        Self::new(field, text.as_bytes())
+    }
+
+    /// Returns the field this term occurs in.
+    #[inline]
+    pub fn field(&self) -> &str {
+        &self.field
+    }
+
+    /// Returns the bytes of this term.
+    #[inline]
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// Returns the text of this term, if it is valid UTF-8.
+    #[inline]
+    pub fn text(&self) -> Option<&str> {
+        std::str::from_utf8(&self.bytes).ok()
+    }
+}
+
+impl Display for Term {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match self.text() {
+            Some(text) => write!(f, "{}:{text}", self.field),
+            None => write!(f, "{}:{:x?}", self.field, self.bytes),
+        }
+    }
+}
@@ -0,0 +1,236 @@
+use {
+    crate::{
+        document::TermVectorOptions,
+        util::{size_of_vec, Accountable},
+        BoxResult, LuceneError,
+    },
+    std::collections::BTreeMap,
+};
+
+/// The term vectors of a document: for each field that stores them (see
+/// [crate::document::Field::with_term_vectors]), the field's terms in the document.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TermVectors {
+    fields: BTreeMap<String, TermVector>,
+}
+
+impl TermVectors {
+    /// Creates a document's term vectors with no fields.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the term vector of `field`, replacing any previous one.
+    pub fn insert(&mut self, field: impl Into<String>, term_vector: TermVector) {
+        self.fields.insert(field.into(), term_vector);
+    }
+
+    /// Returns the term vector of `field`, or `None` if the document stores none for it.
+    pub fn get(&self, field: &str) -> Option<&TermVector> {
+        self.fields.get(field)
+    }
+
+    /// Returns the fields with term vectors and their term vectors, ordered by field name.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &TermVector)> {
+        self.fields.iter().map(|(field, term_vector)| (field.as_str(), term_vector))
+    }
+
+    /// Returns the number of fields with term vectors.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.fields.len()
+    }
+
+    /// Indicates whether no field of the document has a term vector.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+}
+
+impl Accountable for TermVectors {
+    fn ram_bytes_used(&self) -> usize {
+        size_of::<Self>()
+            + self
+                .fields
+                .iter()
+                .map(|(field, term_vector)| size_of::<String>() + field.capacity() + term_vector.ram_bytes_used())
+                .sum::<usize>()
+    }
+}
+
+/// The term vector of one field of a document: the field's distinct terms in byte order, with their frequencies and
+/// whatever else its [TermVectorOptions] record.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TermVector {
+    options: TermVectorOptions,
+    terms: Vec<TermVectorTerm>,
+}
+
+impl TermVector {
+    /// Creates an empty term vector recording what `options` asks for.
+    pub fn new(options: TermVectorOptions) -> Self {
+        Self {
+            options,
+            terms: Vec::new(),
+        }
+    }
+
+    /// Appends a term. Terms must be added in increasing byte order.
+    ///
+    /// This fails if the term is out of order, if its frequency is 0, if its positions decrease or an offset ends
+    /// before it starts, or if its positions, offsets or payloads don't match the options: each must hold one entry
+    /// per occurrence if recorded, and none otherwise.
+    pub fn add(&mut self, term: TermVectorTerm) -> BoxResult<()> {
+        if let Some(last) = self.terms.last() {
+            if last.term >= term.term {
+                return Err(LuceneError::InvalidArgument(format!(
+                    "term vector terms must be added in order: {:?} follows {:?}",
+                    String::from_utf8_lossy(&term.term),
+                    String::from_utf8_lossy(&last.term)
+                ))
+                .into());
+            }
+        }
+
+        let expected = |recorded: bool| {
+            if recorded {
+                term.freq as usize
+            } else {
+                0
+            }
+        };
+        if term.freq == 0
+            || term.positions.windows(2).any(|positions| positions[0] > positions[1])
+            || term.offsets.iter().any(|(start, end)| start > end)
+            || term.positions.len() != expected(self.options.positions)
+            || term.offsets.len() != expected(self.options.offsets)
+            || term.payloads.len() != expected(self.options.payloads)
+        {
+            return Err(LuceneError::InvalidArgument(format!(
+                "term vector term {:?} has a frequency of {} with {} positions, {} offsets and {} payloads, which \
+                 are out of order or don't match {:?}",
+                String::from_utf8_lossy(&term.term),
+                term.freq,
+                term.positions.len(),
+                term.offsets.len(),
+                term.payloads.len(),
+                self.options
+            ))
+            .into());
+        }
+
+        self.terms.push(term);
+        Ok(())
+    }
+
+    /// Returns what the term vector records beyond term frequencies.
+    #[inline]
+    pub fn options(&self) -> TermVectorOptions {
+        self.options
+    }
+
+    /// Returns the terms, in increasing byte order.
+    #[inline]
+    pub fn terms(&self) -> &[TermVectorTerm] {
+        &self.terms
+    }
+
+    /// Returns the entry of `term`, or `None` if it doesn't occur in the field.
+    pub fn get(&self, term: &[u8]) -> Option<&TermVectorTerm> {
+        self.terms.binary_search_by(|entry| entry.term.as_slice().cmp(term)).ok().map(|i| &self.terms[i])
+    }
+
+    /// Returns the number of distinct terms.
+    #[inline]
+    pub fn size(&self) -> usize {
+        self.terms.len()
+    }
+}
+
+impl Accountable for TermVector {
+    fn ram_bytes_used(&self) -> usize {
+        size_of::<Self>()
+            + size_of_vec(&self.terms)
+            + self
+                .terms
+                .iter()
+                .map(|term| {
+                    term.term.capacity()
+                        + size_of_vec(&term.positions)
+                        + size_of_vec(&term.offsets)
+                        + size_of_vec(&term.payloads)
+                        + term.payloads.iter().map(Vec::capacity).sum::<usize>()
+                })
+                .sum::<usize>()
+    }
+}
+
+/// A term of a [TermVector] and its occurrences in the field.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct TermVectorTerm {
+    /// The term.
+    pub term: Vec<u8>,
+
+    /// The number of occurrences of the term in the field.
+    pub freq: u32,
+
+    /// The position of each occurrence, in increasing order, if positions are recorded.
+    pub positions: Vec<u32>,
+
+    /// The start and end offsets of each occurrence, if offsets are recorded.
+    pub offsets: Vec<(u32, u32)>,
+
+    /// The payload of each occurrence, if payloads are recorded. Occurrences without a payload have an empty one.
+    pub payloads: Vec<Vec<u8>>,
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{
+            document::TermVectorOptions,
+            index::{TermVector, TermVectorTerm, TermVectors},
+        },
+        pretty_assertions::assert_eq,
+    };
+
+    fn term(term: &str, positions: Vec<u32>) -> TermVectorTerm {
+        TermVectorTerm {
+            term: term.as_bytes().to_vec(),
+            freq: positions.len() as u32,
+            positions,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_term_vector() {
+        let options = TermVectorOptions {
+            positions: true,
+            ..Default::default()
+        };
+        let mut term_vector = TermVector::new(options);
+        term_vector.add(term("brown", vec![2])).unwrap();
+        term_vector.add(term("fox", vec![3, 7])).unwrap();
+        assert_eq!(term_vector.size(), 2);
+        assert_eq!(term_vector.get(b"fox").unwrap().positions, vec![3, 7]);
+        assert!(term_vector.get(b"dog").is_none());
+
+        // Terms must be in order, and occurrences must match the options.
+        assert!(term_vector.add(term("fox", vec![9])).is_err());
+        assert!(term_vector.add(term("quick", vec![5, 1])).is_err());
+        assert!(term_vector.add(term("quick", Vec::new())).is_err());
+        let mut offsets = term("quick", vec![1]);
+        offsets.offsets.push((4, 9));
+        assert!(term_vector.add(offsets).is_err());
+        assert_eq!(term_vector.size(), 2);
+
+        let mut term_vectors = TermVectors::new();
+        assert!(term_vectors.is_empty());
+        term_vectors.insert("body", term_vector.clone());
+        assert_eq!(term_vectors.len(), 1);
+        assert_eq!(term_vectors.get("body"), Some(&term_vector));
+        assert!(term_vectors.get("title").is_none());
+    }
+}
@@ -0,0 +1,153 @@
+use {
+    crate::{
+        index::{AutomatonTermsEnum, PostingsEnum},
+        util::automaton::{AutomatonType, CompiledAutomaton},
+        BoxResult, LuceneError,
+    },
+    std::fmt::Debug,
+};
+
+/// Represents the result returned from [TermsEnum::seek_ceil].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SeekStatus {
+    /// The term was not found, and the end of iteration was hit.
+    End,
+
+    /// The precise term was found.
+    Found,
+
+    /// A different term was found after the requested term.
+    NotFound,
+}
+
+/// How the terms of a field are held in memory. See [crate::index::IndexWriterConfig::set_terms_format].
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum TermsFormat {
+    /// A sorted list of terms, as [crate::index::MemoryTerms].
+    #[default]
+    Default,
+
+    /// A term dictionary kept as an FST, as [crate::index::FstTerms]. This suits small to medium fields whose terms
+    /// share prefixes or suffixes, and speeds up seeks.
+    Fst,
+
+    /// Unique keys, such as document ids, whose every term occurs once in a single document, as
+    /// [crate::index::IdTerms], without frequencies or positions. If a term occurs in more than one document, the
+    /// field is held as [TermsFormat::Default] instead.
+    UniqueKeys,
+}
+
+/// Access to the terms in a specific field.
+pub trait Terms: Debug + Send + Sync {
+    /// Returns an iterator that will step through all terms. This method will not return `None`.
+    fn iterator(&self) -> BoxResult<Box<dyn TermsEnum + '_>>;
+
+    /// Returns the number of terms for this field, or `None` if this measure isn't stored by the codec.
+    fn size(&self) -> Option<u64>;
+
+    /// Returns the sum of [TermsEnum::total_term_freq] for all terms in this field.
+    fn sum_total_term_freq(&self) -> u64;
+
+    /// Returns the sum of [TermsEnum::doc_freq] for all terms in this field.
+    fn sum_doc_freq(&self) -> u64;
+
+    /// Returns the number of documents that have at least one term for this field.
+    fn doc_count(&self) -> u32;
+
+    /// Returns true if documents in this field store per-document term frequency.
+    fn has_freqs(&self) -> bool;
+
+    /// Returns true if documents in this field store positions.
+    fn has_positions(&self) -> bool;
+
+    /// Returns true if documents in this field store the start and end offsets of each position.
+    fn has_offsets(&self) -> bool {
+        false
+    }
+
+    /// Returns an enum over the terms accepted by the given automaton, which must be of type
+    /// [AutomatonType::Normal]; use [CompiledAutomaton::terms_enum] for the general case. If `start_term` is given,
+    /// only terms after it are returned.
+    fn intersect(&self, compiled: &CompiledAutomaton, start_term: Option<&[u8]>) -> BoxResult<Box<dyn TermsEnum + '_>> {
+        if compiled.automaton_type() != AutomatonType::Normal {
+            return Err(
+                LuceneError::InvalidArgument("please use CompiledAutomaton::terms_enum instead".to_string()).into()
+            );
+        }
+
+        Ok(Box::new(AutomatonTermsEnum::new(self.iterator()?, compiled, start_term)?))
+    }
+}
+
+/// Iterator to seek ([TermsEnum::seek_ceil], [TermsEnum::seek_exact]) or step through ([TermsEnum::next]) terms to
+/// obtain frequency information ([TermsEnum::doc_freq]).
+///
+/// Term enumerations are always ordered by the unsigned byte order of the terms, which is Unicode code point order
+/// if the terms are UTF-8. Each term in the enumeration is greater than the one before it.
+///
+/// The enum is unpositioned when first obtained; callers must first successfully call [TermsEnum::next] or one of
+/// the `seek` methods.
+pub trait TermsEnum: Debug {
+    /// Advances to the next term, returning it, or `None` if the end of the enumeration has been reached.
+    fn next(&mut self) -> BoxResult<Option<&[u8]>>;
+
+    /// Returns the current term. Do not call this when the enum is unpositioned.
+    fn term(&self) -> &[u8];
+
+    /// Seeks to the specified term, if it exists, or to the next (ceiling) term. The target may be before or after
+    /// the current term. If this returns [SeekStatus::End], the enum is unpositioned.
+    fn seek_ceil(&mut self, target: &[u8]) -> BoxResult<SeekStatus>;
+
+    /// Attempts to seek to the exact term, returning true if it is found. If this returns false, the enum is
+    /// unpositioned.
+    fn seek_exact(&mut self, target: &[u8]) -> BoxResult<bool> {
+        Ok(self.seek_ceil(target)? == SeekStatus::Found)
+    }
+
+    /// Returns the number of documents containing the current term.
+    fn doc_freq(&self) -> BoxResult<u32>;
+
+    /// Returns the total number of occurrences of the current term across all documents. Like other term measures,
+    /// this does not take deleted documents into account.
+    fn total_term_freq(&self) -> BoxResult<u64>;
+
+    /// Returns the postings of the current term: the documents containing it, with frequencies and positions where
+    /// they are indexed.
+    fn postings(&self) -> BoxResult<Box<dyn PostingsEnum>>;
+
+    /// Returns the boost to apply to the current term when it is used to build a query. Filtering enums such as
+    /// fuzzy enumerations use this to weight terms by similarity; it is `1.0` otherwise.
+    fn boost(&self) -> f32 {
+        1.0
+    }
+}
+
+/// A [TermsEnum] with no terms.
+#[derive(Debug, Default)]
+pub struct EmptyTermsEnum;
+
+impl TermsEnum for EmptyTermsEnum {
+    fn next(&mut self) -> BoxResult<Option<&[u8]>> {
+        Ok(None)
+    }
+
+    fn term(&self) -> &[u8] {
+        panic!("EmptyTermsEnum is never positioned")
+    }
+
+    fn seek_ceil(&mut self, _target: &[u8]) -> BoxResult<SeekStatus> {
+        Ok(SeekStatus::End)
+    }
+
+    fn doc_freq(&self) -> BoxResult<u32> {
+        panic!("EmptyTermsEnum is never positioned")
+    }
+
+    fn total_term_freq(&self) -> BoxResult<u64> {
+        panic!("EmptyTermsEnum is never positioned")
+    }
+
+    fn postings(&self) -> BoxResult<Box<dyn PostingsEnum>> {
+        panic!("EmptyTermsEnum is never positioned")
+    }
+}
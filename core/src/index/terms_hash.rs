@@ -0,0 +1,327 @@
+use {
+    crate::{
+        index::MemoryPosting,
+        util::{ByteBlockPool, ByteSliceReader, BytesRefHash, IntBlockPool},
+        BoxResult, LuceneError,
+    },
+    std::collections::{BTreeMap, HashMap},
+};
+
+/// The stream of document deltas and frequencies of a term.
+const FREQ_STREAM: usize = 0;
+
+/// The stream of position deltas, payloads and offsets of a term.
+const PROX_STREAM: usize = 1;
+
+/// The number of posting streams of each term.
+const STREAM_COUNT: usize = 2;
+
+/// Set in a document code when the frequency is 1 and isn't written.
+const FREQ_ONE: u32 = 1;
+
+/// Set in a document code when positions were written for the document.
+const HAS_POSITIONS: u32 = 2;
+
+/// Set in a document code when offsets were written along with the positions of the document.
+const HAS_OFFSETS: u32 = 4;
+
+/// The number of flag bits below the document delta in a document code.
+const DOC_CODE_SHIFT: u32 = 3;
+
+/// The in-memory inverted index of a segment being written: for each field, the distinct terms and, for each term,
+/// the documents containing it along with their frequencies, positions and offsets.
+///
+/// As in Lucene's indexing chain, term bytes are stored once in a shared [ByteBlockPool] and assigned ids by a
+/// per-field [BytesRefHash], and each term's postings are appended to two byte streams in a second pool: one for
+/// document deltas and frequencies, and one for position deltas, payloads and offsets. The write positions of each
+/// term's streams are kept in an [IntBlockPool], and other per-term state lives in parallel arrays indexed by term
+/// id. Memory is allocated in large blocks, and [TermsHash::bytes_used] accounts for all of it, so that the writer
+/// can flush once its RAM buffer is full.
+#[derive(Debug, Default)]
+pub struct TermsHash {
+    term_pool: ByteBlockPool,
+    stream_pool: ByteBlockPool,
+    int_pool: IntBlockPool,
+    fields: HashMap<String, TermsHashPerField>,
+}
+
+/// The terms of one field of a [TermsHash], with the state of each term's posting streams.
+#[derive(Debug, Default)]
+struct TermsHashPerField {
+    terms: BytesRefHash,
+    stream_starts: Vec<[u32; STREAM_COUNT]>,
+    int_starts: Vec<u32>,
+    last_docs: Vec<u32>,
+}
+
+impl TermsHash {
+    /// Creates an empty inverted index.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the memory used, in bytes.
+    pub fn bytes_used(&self) -> usize {
+        self.term_pool.bytes_allocated()
+            + self.stream_pool.bytes_allocated()
+            + self.int_pool.bytes_allocated()
+            + self
+                .fields
+                .iter()
+                .map(|(name, field)| {
+                    name.capacity()
+                        + field.terms.bytes_used()
+                        + field.stream_starts.capacity() * size_of::<[u32; STREAM_COUNT]>()
+                        + (field.int_starts.capacity() + field.last_docs.capacity()) * size_of::<u32>()
+                })
+                .sum::<usize>()
+    }
+
+    /// Returns the number of distinct terms of a field.
+    pub fn num_terms(&self, field: &str) -> usize {
+        self.fields.get(field).map_or(0, |field| field.terms.len())
+    }
+
+    /// Records the positions of a term in a document, along with the payload at each position, if `payloads` isn't
+    /// empty, and the start and end offsets of each position, if `offsets` isn't empty. Documents must be added in
+    /// increasing order.
+    ///
+    /// As in Lucene, start offsets must not go backwards, and each end offset must not be before its start offset.
+    pub fn add_positions(
+        &mut self,
+        field: &str,
+        term: &[u8],
+        doc: u32,
+        positions: &[u32],
+        payloads: &[Vec<u8>],
+        offsets: &[(u32, u32)],
+    ) -> BoxResult<()> {
+        debug_assert!(payloads.is_empty() || payloads.len() == positions.len());
+        debug_assert!(offsets.is_empty() || offsets.len() == positions.len());
+        let mut last_start = 0;
+        for &(start, end) in offsets {
+            if start < last_start || end < start {
+                return Err(LuceneError::InvalidArgument(format!(
+                    "offsets of term {:?} of field {field:?} in document {doc} go backwards: ({start}, {end}) after \
+                     start offset {last_start}",
+                    String::from_utf8_lossy(term)
+                ))
+                .into());
+            }
+            last_start = start;
+        }
+
+        let int_start = self.start_doc(field, term, doc, positions.len() as u32, true, !offsets.is_empty())?;
+        let mut prox_upto = self.int_pool.int(int_start + PROX_STREAM) as usize;
+        let mut last_position = 0;
+        let mut last_start = 0;
+        for (i, &position) in positions.iter().enumerate() {
+            // As in Lucene, the low bit of the position delta flags a payload, which follows with its length.
+            let delta = (position - last_position) << 1;
+            match payloads.get(i).filter(|payload| !payload.is_empty()) {
+                Some(payload) => {
+                    self.stream_pool.write_vint(&mut prox_upto, delta | 1);
+                    self.stream_pool.write_vint(&mut prox_upto, payload.len() as u32);
+                    for &byte in payload.iter() {
+                        self.stream_pool.write_byte(&mut prox_upto, byte);
+                    }
+                }
+                None => self.stream_pool.write_vint(&mut prox_upto, delta),
+            }
+            if let Some(&(start, end)) = offsets.get(i) {
+                self.stream_pool.write_vint(&mut prox_upto, start - last_start);
+                self.stream_pool.write_vint(&mut prox_upto, end - start);
+                last_start = start;
+            }
+            last_position = position;
+        }
+        self.int_pool.set_int(int_start + PROX_STREAM, prox_upto as u32);
+        Ok(())
+    }
+
+    /// Records the frequency of a term in a document, without positions. Documents must be added in increasing
+    /// order.
+    pub fn add_freq(&mut self, field: &str, term: &[u8], doc: u32, freq: u32) -> BoxResult<()> {
+        self.start_doc(field, term, doc, freq, false, false).map(|_| ())
+    }
+
+    /// Adds a term if it's new, and writes the document's code and frequency to its frequency stream. Returns the
+    /// offset of the term's stream positions in the int pool.
+    fn start_doc(
+        &mut self,
+        field: &str,
+        term: &[u8],
+        doc: u32,
+        freq: u32,
+        has_positions: bool,
+        has_offsets: bool,
+    ) -> BoxResult<usize> {
+        let per_field = match self.fields.get_mut(field) {
+            Some(per_field) => per_field,
+            None => self.fields.entry(field.to_string()).or_default(),
+        };
+
+        let (id, is_new) = per_field.terms.add(&mut self.term_pool, term)?;
+        let id_index = id as usize;
+        if is_new {
+            let starts = [self.stream_pool.new_slice() as u32, self.stream_pool.new_slice() as u32];
+            let int_start = self.int_pool.alloc(STREAM_COUNT);
+            for (stream, &start) in starts.iter().enumerate() {
+                self.int_pool.set_int(int_start + stream, start);
+            }
+            per_field.stream_starts.push(starts);
+            per_field.int_starts.push(int_start as u32);
+            per_field.last_docs.push(0);
+        } else if doc <= per_field.last_docs[id_index] {
+            return Err(LuceneError::InvalidArgument(format!(
+                "term {:?} of field {field:?} was added for document {doc} after document {}",
+                String::from_utf8_lossy(term),
+                per_field.last_docs[id_index]
+            ))
+            .into());
+        }
+
+        let delta = doc - per_field.last_docs[id_index];
+        let mut code = delta << DOC_CODE_SHIFT;
+        if freq == 1 {
+            code |= FREQ_ONE;
+        }
+        if has_positions {
+            code |= HAS_POSITIONS;
+        }
+        if has_offsets {
+            code |= HAS_OFFSETS;
+        }
+
+        let int_start = per_field.int_starts[id_index] as usize;
+        let mut freq_upto = self.int_pool.int(int_start + FREQ_STREAM) as usize;
+        self.stream_pool.write_vint(&mut freq_upto, code);
+        if freq != 1 {
+            self.stream_pool.write_vint(&mut freq_upto, freq);
+        }
+        self.int_pool.set_int(int_start + FREQ_STREAM, freq_upto as u32);
+        per_field.last_docs[id_index] = doc;
+        Ok(int_start)
+    }
+
+    /// Decodes the postings of every term, grouped by field and ordered by term.
+    pub fn into_postings(self) -> BTreeMap<String, BTreeMap<Vec<u8>, Vec<MemoryPosting>>> {
+        let mut fields = BTreeMap::new();
+        for (name, field) in self.fields {
+            let mut terms = BTreeMap::new();
+            for id in field.terms.sorted_ids(&self.term_pool) {
+                let starts = field.stream_starts[id as usize];
+                let int_start = field.int_starts[id as usize] as usize;
+                let uptos = [self.int_pool.int(int_start + FREQ_STREAM), self.int_pool.int(int_start + PROX_STREAM)];
+                let mut freqs =
+                    ByteSliceReader::new(&self.stream_pool, starts[FREQ_STREAM] as usize, uptos[FREQ_STREAM] as usize);
+                let mut prox =
+                    ByteSliceReader::new(&self.stream_pool, starts[PROX_STREAM] as usize, uptos[PROX_STREAM] as usize);
+
+                let mut postings = Vec::new();
+                let mut doc = 0;
+                while !freqs.eof() {
+                    let code = freqs.read_vint();
+                    doc += code >> DOC_CODE_SHIFT;
+                    let freq = if code & FREQ_ONE != 0 {
+                        1
+                    } else {
+                        freqs.read_vint()
+                    };
+
+                    if code & HAS_POSITIONS != 0 {
+                        let mut position = 0;
+                        let mut positions = Vec::with_capacity(freq as usize);
+                        let mut payloads = Vec::new();
+                        let mut offsets = Vec::new();
+                        let mut start = 0;
+                        for i in 0..freq as usize {
+                            let prox_code = prox.read_vint();
+                            position += prox_code >> 1;
+                            positions.push(position);
+                            if prox_code & 1 != 0 {
+                                payloads.resize(i, Vec::new());
+                                let len = prox.read_vint();
+                                payloads.push((0..len).map(|_| prox.read_byte()).collect());
+                            }
+                            if code & HAS_OFFSETS != 0 {
+                                start += prox.read_vint();
+                                offsets.push((start, start + prox.read_vint()));
+                            }
+                        }
+                        if !payloads.is_empty() {
+                            payloads.resize(positions.len(), Vec::new());
+                        }
+                        postings.push(MemoryPosting::with_payloads(doc, positions, payloads).with_offsets(offsets));
+                    } else {
+                        postings.push(MemoryPosting::with_freq(doc, freq));
+                    }
+                }
+
+                terms.insert(field.terms.get(&self.term_pool, id).to_vec(), postings);
+            }
+            fields.insert(name, terms);
+        }
+        fields
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::index::{MemoryPosting, TermsHash},
+        pretty_assertions::assert_eq,
+    };
+
+    #[test]
+    fn test_terms_hash() {
+        let mut hash = TermsHash::new();
+        assert_eq!(hash.bytes_used(), 0);
+
+        hash.add_positions("body", b"fox", 0, &[1, 5], &[], &[]).unwrap();
+        hash.add_positions("body", b"dog", 0, &[3], &[], &[]).unwrap();
+        hash.add_positions("body", b"fox", 300, &[0], &[], &[]).unwrap();
+        hash.add_positions("weights", b"fox", 1, &[0, 2, 4], &[vec![], b"0.5".to_vec(), vec![]], &[]).unwrap();
+        hash.add_freq("features", b"pagerank", 2, 17).unwrap();
+        hash.add_positions("text", b"fox", 4, &[1, 3], &[vec![7], vec![]], &[(4, 7), (12, 15)]).unwrap();
+        assert!(hash.add_positions("text", b"dog", 4, &[2, 5], &[], &[(8, 11), (6, 9)]).is_err());
+        assert!(hash.add_positions("text", b"dog", 4, &[2], &[], &[(8, 6)]).is_err());
+        assert!(hash.add_positions("body", b"fox", 300, &[2], &[], &[]).is_err());
+        assert_eq!(hash.num_terms("body"), 2);
+        assert!(hash.bytes_used() > 0);
+
+        // Many documents for one term, so its streams span several slices.
+        for doc in 1..2000 {
+            hash.add_positions("title", b"the", doc, &[0, doc % 7 + 1], &[], &[]).unwrap();
+        }
+
+        let postings = hash.into_postings();
+        assert_eq!(
+            postings["body"].iter().map(|(term, postings)| (term.as_slice(), postings.clone())).collect::<Vec<_>>(),
+            vec![
+                (&b"dog"[..], vec![MemoryPosting::with_positions(0, vec![3])]),
+                (
+                    &b"fox"[..],
+                    vec![MemoryPosting::with_positions(0, vec![1, 5]), MemoryPosting::with_positions(300, vec![0])]
+                ),
+            ]
+        );
+        assert_eq!(postings["features"][&b"pagerank"[..]], vec![MemoryPosting::with_freq(2, 17)]);
+        assert_eq!(
+            postings["weights"][&b"fox"[..]],
+            vec![MemoryPosting::with_payloads(1, vec![0, 2, 4], vec![vec![], b"0.5".to_vec(), vec![]])]
+        );
+
+        assert_eq!(
+            postings["text"][&b"fox"[..]],
+            vec![
+                MemoryPosting::with_payloads(4, vec![1, 3], vec![vec![7], vec![]]).with_offsets(vec![(4, 7), (12, 15)])
+            ]
+        );
+        assert!(!postings["text"].contains_key(&b"dog"[..]));
+
+        let the = &postings["title"][&b"the"[..]];
+        assert_eq!(the.len(), 1999);
+        assert!(the.iter().all(|posting| posting.positions == vec![0, posting.doc % 7 + 1]));
+    }
+}
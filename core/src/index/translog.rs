@@ -0,0 +1,732 @@
+use {
+    crate::{
+        codec::{header_length, CodecHeader},
+        document::Document,
+        index::{generation_to_string, IndexWriter, Term},
+        io::{Directory, EncodingReadExt, EncodingWriteExt, IoContext},
+        BoxError, BoxResult, ErrorContext, LuceneError,
+    },
+    log::{info, warn},
+    std::{
+        collections::HashSet,
+        fmt::{Debug, Formatter, Result as FmtResult},
+        mem,
+        pin::Pin,
+        time::{Duration, Instant},
+    },
+    tokio::io::{AsyncReadExt, AsyncWrite, AsyncWriteExt},
+};
+
+/// The prefix of the files written by a [Translog]: `translog_N`, where N is the base-36 generation of the file.
+pub const TRANSLOG_FILE_NAME_PREFIX: &str = "translog";
+
+/// The key of the commit user data that [Translog::commit] records the sequence number of the first operation the
+/// commit doesn't cover under.
+pub const TRANSLOG_SEQ_NO_USER_DATA_KEY: &str = "translog_seq_no";
+
+/// The codec name in the header of translog files.
+const TRANSLOG_CODEC: &str = "Translog";
+
+/// The first version of the translog format.
+const VERSION_START: u32 = 0;
+
+/// The current version of the translog format.
+const VERSION_CURRENT: u32 = VERSION_START;
+
+/// Marks an added document in a translog file.
+const OP_ADD_DOCUMENT: u8 = 0;
+
+/// Marks an operation logged earlier that the writer then rejected, so that it isn't replayed.
+const OP_DISCARD: u8 = 1;
+
+/// Marks deleted documents in a translog file.
+const OP_DELETE_DOCUMENTS: u8 = 2;
+
+/// The length of the sequence number and length that start a record.
+const RECORD_HEADER_LENGTH: usize = 12;
+
+/// The length of the checksum that ends a record.
+const RECORD_CHECKSUM_LENGTH: usize = 4;
+
+/// When a [Translog] makes the operations it logs durable.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum TranslogDurability {
+    /// Every operation is written and synced before it's applied, so nothing acknowledged is lost in a crash.
+    #[default]
+    Request,
+
+    /// Operations are buffered and written once the interval has passed since the last sync, by the next operation
+    /// logged, or by [Translog::sync]. The operations logged since the last sync are lost in a crash, in exchange for
+    /// fewer, larger writes. Callers that may stop logging for a while should call [Translog::sync] on a timer.
+    Interval(Duration),
+}
+
+/// A write-ahead log of the operations applied to an [IndexWriter], as Elasticsearch's translog is, so that the
+/// documents added and deleted since the last commit survive a crash.
+///
+/// Each time a translog is opened, it starts a new generation: a `translog_N` file holding a codec header followed
+/// by the operations appended to it. Every operation is a record with a sequence number and a checksum, and a sync
+/// appends the buffered records, then syncs the file (see [Directory::sync]) before the operations are acknowledged.
+/// A sync cut short by a crash leaves a record at the end of the file that is incomplete or fails its checksum; it
+/// was never acknowledged, so [Translog::recover] skips it. A failed sync moves on to a new generation, and records
+/// written again there are replayed once, by their sequence numbers.
+///
+/// Documents are validated against the writer's schema before they're logged. If the writer still rejects an
+/// operation once it's synced, a discard record follows it, so that it isn't replayed; should that record be lost
+/// too, [Translog::recover] skips the operations the writer rejects rather than failing.
+///
+/// On startup, [Translog::recover] replays the logged operations into a writer. [Translog::commit] commits the writer
+/// along with the sequence number of the next operation, then removes the files the commit covers; recovery skips
+/// the operations of any it failed to remove. Until flushed segments can be written to disk, a writer can't commit
+/// while added documents are held in memory (see [IndexWriter::commit]), so the translog stays the only durable copy
+/// of the documents added through it until they're deleted.
+pub struct Translog {
+    directory: Box<dyn Directory + Send + Sync>,
+    durability: TranslogDurability,
+    files: Vec<String>,
+    generation: u64,
+    writer: Option<Pin<Box<dyn AsyncWrite + Send>>>,
+    next_seq_no: u64,
+    num_ops: u64,
+    buffer: Vec<u8>,
+    num_buffered_ops: u32,
+    last_sync: Instant,
+}
+
+impl Translog {
+    /// Opens the translog in `directory`, which may be the index's directory or a separate one, finding the files
+    /// left by a previous process. Their operations are replayed by [Translog::recover]; new ones are appended to a
+    /// new generation.
+    pub async fn open(directory: Box<dyn Directory + Send + Sync>, durability: TranslogDurability) -> BoxResult<Self> {
+        let mut files: Vec<(u64, String)> = directory
+            .read_dir()
+            .await?
+            .into_iter()
+            .filter_map(|file_name| Some((parse_translog_file_name(&file_name)?, file_name)))
+            .collect();
+        files.sort();
+
+        Ok(Self {
+            directory,
+            durability,
+            generation: files.last().map_or(0, |(generation, _)| generation + 1),
+            files: files.into_iter().map(|(_, file_name)| file_name).collect(),
+            writer: None,
+            next_seq_no: 0,
+            num_ops: 0,
+            buffer: Vec::new(),
+            num_buffered_ops: 0,
+            last_sync: Instant::now(),
+        })
+    }
+
+    /// Returns when operations are made durable.
+    #[inline]
+    pub fn durability(&self) -> TranslogDurability {
+        self.durability
+    }
+
+    /// Returns the files holding the synced operations, in the order they were written.
+    #[inline]
+    pub fn files(&self) -> &[String] {
+        &self.files
+    }
+
+    /// Returns the number of operations logged or recovered, including those not synced yet.
+    #[inline]
+    pub fn num_ops(&self) -> u64 {
+        self.num_ops + self.num_buffered_ops as u64
+    }
+
+    /// Returns the number of operations logged since the last sync, which would be lost in a crash.
+    #[inline]
+    pub fn num_unsynced_ops(&self) -> u32 {
+        self.num_buffered_ops
+    }
+
+    /// Replays the operations left by a previous process into `writer`, returning how many were replayed. Call this
+    /// once, after opening the translog and before logging new operations.
+    ///
+    /// A record at the end of a file that is incomplete or fails its checksum was cut short by a crash and never
+    /// acknowledged, so it's skipped, as are records already replayed from an earlier generation and discarded
+    /// operations. Any other unreadable record fails the recovery with an error naming the file. Operations the
+    /// writer rejects, such as documents that don't fit a schema it has since been given, are skipped with a warning.
+    ///
+    /// Operations covered by the writer's latest commit, as recorded by [Translog::commit], aren't replayed.
+    pub async fn recover(&mut self, writer: &IndexWriter) -> BoxResult<u64> {
+        writer.ensure_open()?;
+        if let Some(seq_no) = writer.get_live_commit_data().get(TRANSLOG_SEQ_NO_USER_DATA_KEY) {
+            let seq_no = seq_no.parse::<u64>().map_err(|_| {
+                LuceneError::IllegalState(format!("invalid translog sequence number {seq_no} in the commit user data"))
+            })?;
+            self.next_seq_no = self.next_seq_no.max(seq_no);
+        }
+
+        let mut records = Vec::new();
+        for file_name in self.files.clone() {
+            let file_records =
+                self.read_file(&file_name).await.context(format!("replaying translog file {file_name}"))?;
+            records.extend(file_records);
+        }
+        let discarded: HashSet<u64> = records
+            .iter()
+            .filter_map(|(_, op)| match op {
+                Operation::Discard(seq_no) => Some(*seq_no),
+                _ => None,
+            })
+            .collect();
+
+        let mut replayed = 0;
+        let mut skipped = 0;
+        for (seq_no, op) in records {
+            if seq_no < self.next_seq_no {
+                continue;
+            }
+            self.next_seq_no = seq_no + 1;
+            if discarded.contains(&seq_no) {
+                continue;
+            }
+            let result = match op {
+                Operation::AddDocument(document) => writer.add_document(&document).map(|_| ()),
+                Operation::DeleteDocuments(terms) => writer.delete_documents(&terms).map(|_| ()),
+                Operation::Discard(_) => continue,
+            };
+            match result {
+                Ok(()) => replayed += 1,
+                Err(e) => {
+                    warn!("Skipping translog operation {seq_no}, which the writer rejects: {e}");
+                    skipped += 1;
+                }
+            }
+        }
+
+        self.num_ops += replayed;
+        info!("Replayed {replayed} operations from {} translog files, skipping {skipped}", self.files.len());
+        Ok(replayed)
+    }
+
+    /// Logs a document, syncing the log as the durability requires, then adds it to `writer`. Returns whether adding
+    /// it flushed a segment (see [IndexWriter::add_document]).
+    ///
+    /// A document the writer's schema rejects isn't logged. If the writer rejects it for another reason, it's
+    /// removed from the log, or discarded if it was already synced.
+    pub async fn add_document(&mut self, writer: &IndexWriter, document: &Document) -> BoxResult<bool> {
+        writer.ensure_open()?;
+        if let Some(schema) = writer.config().schema() {
+            schema.validate(document)?;
+        }
+        let mut op = vec![OP_ADD_DOCUMENT];
+        document.write_to(&mut op).await?;
+        self.log_and_apply(op, || writer.add_document(document)).await
+    }
+
+    /// Logs the deletion of the documents containing any of `terms`, syncing the log as the durability requires, then
+    /// deletes them from `writer`. Returns the number of documents deleted (see [IndexWriter::delete_documents]).
+    pub async fn delete_documents(&mut self, writer: &IndexWriter, terms: &[Term]) -> BoxResult<u32> {
+        writer.ensure_open()?;
+        let num_terms = i32::try_from(terms.len())
+            .map_err(|_| LuceneError::InvalidArgument(format!("too many terms: {}", terms.len())))?;
+        let mut op = vec![OP_DELETE_DOCUMENTS];
+        op.write_vi32(num_terms).await?;
+        for term in terms {
+            let length = i32::try_from(term.bytes().len())
+                .map_err(|_| LuceneError::InvalidArgument(format!("term {term} is too long")))?;
+            op.write_string(term.field()).await?;
+            op.write_vi32(length).await?;
+            op.extend_from_slice(term.bytes());
+        }
+        self.log_and_apply(op, || writer.delete_documents(terms)).await
+    }
+
+    /// Commits `writer` along with the sequence number of the next operation (see [IndexWriter::commit]), then
+    /// removes the translog files, whose operations the commit covers. Returns the name of the `segments_N` file
+    /// written. Later operations go to a new generation.
+    ///
+    /// The logged operations are synced first. If the commit fails, as it does while added documents are held in
+    /// memory, the writer's live commit data is restored and the translog is left as it is.
+    pub async fn commit(&mut self, writer: &mut IndexWriter) -> BoxResult<String> {
+        self.sync().await?;
+        let previous = writer.get_live_commit_data().clone();
+        let mut user_data = previous.clone();
+        user_data.insert(TRANSLOG_SEQ_NO_USER_DATA_KEY.to_string(), self.next_seq_no.to_string());
+        writer.set_live_commit_data(user_data);
+        let segments_file_name = match writer.commit().await {
+            Ok(segments_file_name) => segments_file_name,
+            Err(e) => {
+                writer.set_live_commit_data(previous);
+                return Err(e);
+            }
+        };
+
+        self.writer = None;
+        self.generation += 1;
+        for file_name in mem::take(&mut self.files) {
+            if let Err(e) = self.directory.remove(&file_name).await {
+                warn!("Failed to remove translog file {file_name}, covered by {segments_file_name}: {e}");
+                self.files.push(file_name);
+            }
+        }
+        Ok(segments_file_name)
+    }
+
+    /// Logs an operation, syncing the log as the durability requires, then applies it with `apply`. If `apply` fails,
+    /// the operation is removed from the buffer, or discarded if it was already synced.
+    async fn log_and_apply<T>(&mut self, op: Vec<u8>, apply: impl FnOnce() -> BoxResult<T>) -> BoxResult<T> {
+        let buffered_len = self.buffer.len();
+        let seq_no = self.log(&op)?;
+        if self.is_sync_due() {
+            if let Err(e) = self.sync().await {
+                // The operation isn't applied, so it mustn't be replayed either.
+                self.buffer.truncate(buffered_len);
+                self.num_buffered_ops -= 1;
+                return Err(e);
+            }
+        }
+
+        let result = apply();
+        if result.is_err() {
+            if seq_no >= self.next_seq_no {
+                self.buffer.truncate(buffered_len);
+                self.num_buffered_ops -= 1;
+            } else {
+                let mut discard = vec![OP_DISCARD];
+                discard.extend_from_slice(&seq_no.to_be_bytes());
+                self.log(&discard)?;
+                if self.is_sync_due() {
+                    if let Err(e) = self.sync().await {
+                        warn!("Failed to sync the discard of translog operation {seq_no}: {e}");
+                    }
+                }
+            }
+        }
+        result
+    }
+
+    /// Appends an operation to the buffer, returning its sequence number.
+    fn log(&mut self, op: &[u8]) -> BoxResult<u64> {
+        let seq_no = self.next_seq_no + self.num_buffered_ops as u64;
+        append_record(&mut self.buffer, seq_no, op)?;
+        self.num_buffered_ops += 1;
+        Ok(seq_no)
+    }
+
+    /// Indicates whether the durability asks for the buffered operations to be synced now.
+    fn is_sync_due(&self) -> bool {
+        match self.durability {
+            TranslogDurability::Request => true,
+            TranslogDurability::Interval(interval) => self.last_sync.elapsed() >= interval,
+        }
+    }
+
+    /// Appends the operations logged since the last sync to the current generation and syncs it. This does nothing
+    /// if there are none.
+    ///
+    /// If writing or syncing fails, the operations stay buffered and the next sync starts a new generation, so the
+    /// sync can be retried without appending to a file that may end with a partial record.
+    pub async fn sync(&mut self) -> BoxResult<()> {
+        if self.num_buffered_ops == 0 {
+            return Ok(());
+        }
+
+        let file_name = translog_file_name(self.generation);
+        if let Err(e) = self.write_buffer(&file_name).await {
+            self.writer = None;
+            self.generation += 1;
+            return Err(e).context(format!("syncing translog file {file_name}"));
+        }
+
+        self.next_seq_no += self.num_buffered_ops as u64;
+        self.num_ops += self.num_buffered_ops as u64;
+        self.buffer.clear();
+        self.num_buffered_ops = 0;
+        self.last_sync = Instant::now();
+        Ok(())
+    }
+
+    /// Appends the buffered records to `file_name`, creating it if needed, then syncs it.
+    async fn write_buffer(&mut self, file_name: &str) -> BoxResult<()> {
+        let writer = match &mut self.writer {
+            Some(writer) => writer,
+            None => {
+                let mut writer = self.directory.create(file_name, &IoContext::Default).await?;
+                if self.files.last().is_none_or(|last| last != file_name) {
+                    self.files.push(file_name.to_string());
+                }
+                CodecHeader::new(TRANSLOG_CODEC, VERSION_CURRENT)?.write(&mut writer).await?;
+                self.writer.insert(writer)
+            }
+        };
+        writer.write_all(&self.buffer).await?;
+        writer.flush().await?;
+        self.directory.sync(&[file_name]).await?;
+        Ok(())
+    }
+
+    /// Reads the operations of a file with their sequence numbers, stopping at a record cut short by a crash.
+    async fn read_file(&mut self, file_name: &str) -> BoxResult<Vec<(u64, Operation)>> {
+        let mut bytes = Vec::new();
+        self.directory.open(file_name, &IoContext::ReadOnce).await?.read_to_end(&mut bytes).await?;
+
+        // A crash right after the file was created can leave it without a complete header.
+        if bytes.len() < header_length(TRANSLOG_CODEC) {
+            warn!("Skipping translog file {file_name}, whose header was cut short by a crash");
+            return Ok(Vec::new());
+        }
+        let mut r = bytes.as_slice();
+        CodecHeader::read(&mut r, TRANSLOG_CODEC, VERSION_START, VERSION_CURRENT).await?;
+
+        let mut records = Vec::new();
+        while !r.is_empty() {
+            let offset = bytes.len() - r.len();
+            let Some((seq_no, mut op)) = split_record(&mut r)? else {
+                warn!("Skipping the record at offset {offset} of translog file {file_name}, cut short by a crash");
+                break;
+            };
+            match op.read_u8().await? {
+                OP_ADD_DOCUMENT => records.push((seq_no, Operation::AddDocument(Document::read_from(&mut op).await?))),
+                OP_DISCARD => records.push((seq_no, Operation::Discard(op.read_u64().await?))),
+                OP_DELETE_DOCUMENTS => records.push((seq_no, Operation::DeleteDocuments(read_terms(&mut op).await?))),
+                op => return Err(corrupt(format!("unknown operation {op} at offset {offset}"))),
+            }
+        }
+        Ok(records)
+    }
+}
+
+/// An operation read back from a translog file.
+enum Operation {
+    AddDocument(Document),
+    DeleteDocuments(Vec<Term>),
+    Discard(u64),
+}
+
+/// Reads the terms of an [OP_DELETE_DOCUMENTS] operation.
+async fn read_terms(r: &mut &[u8]) -> BoxResult<Vec<Term>> {
+    let num_terms = r.read_vi32().await?;
+    let num_terms =
+        usize::try_from(num_terms).map_err(|_| corrupt(format!("invalid number of deleted terms {num_terms}")))?;
+    let mut terms = Vec::with_capacity(num_terms.min(1024));
+    for _ in 0..num_terms {
+        let field = r.read_string().await?;
+        let length = r.read_vi32().await?;
+        let length = usize::try_from(length).map_err(|_| corrupt(format!("invalid term length {length}")))?;
+        let mut bytes = vec![0; length];
+        r.read_exact(&mut bytes).await?;
+        terms.push(Term::new(field, bytes));
+    }
+    Ok(terms)
+}
+
+impl Debug for Translog {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        f.debug_struct("Translog")
+            .field("durability", &self.durability)
+            .field("files", &self.files)
+            .field("generation", &self.generation)
+            .field("num_ops", &self.num_ops())
+            .field("num_unsynced_ops", &self.num_buffered_ops)
+            .finish()
+    }
+}
+
+/// Appends a record to `buffer`.
+///
+/// Record --> SeqNo, Length, Op, Checksum
+///
+/// * SeqNo (u64): The sequence number of the operation, counting from 0 across generations.
+/// * Length (u32): The length of Op.
+/// * Op: [OP_ADD_DOCUMENT] followed by the document (see [Document::write_to]), [OP_DELETE_DOCUMENTS] followed by
+///   the terms of the deleted documents, or [OP_DISCARD] followed by the sequence number (u64) of the operation
+///   discarded.
+/// * Terms --> NumTerms ([EncodingWriteExt::write_vi32]), (Field ([EncodingWriteExt::write_string]), Length
+///   ([EncodingWriteExt::write_vi32]), Bytes)<sup>NumTerms</sup>
+/// * Checksum (u32): The CRC-32 of SeqNo, Length and Op.
+fn append_record(buffer: &mut Vec<u8>, seq_no: u64, op: &[u8]) -> BoxResult<()> {
+    let length = u32::try_from(op.len())
+        .map_err(|_| LuceneError::InvalidArgument(format!("an operation of {} bytes is too large", op.len())))?;
+    let start = buffer.len();
+    buffer.extend_from_slice(&seq_no.to_be_bytes());
+    buffer.extend_from_slice(&length.to_be_bytes());
+    buffer.extend_from_slice(op);
+    let checksum = crc32fast::hash(&buffer[start..]);
+    buffer.extend_from_slice(&checksum.to_be_bytes());
+    Ok(())
+}
+
+/// Splits the next record written by [append_record] off `r`, returning its sequence number and operation, or `None`
+/// if it was cut short by a crash: it's incomplete, or it's the last record and fails its checksum. A record that
+/// fails its checksum with more records after it is corrupt.
+fn split_record<'a>(r: &mut &'a [u8]) -> BoxResult<Option<(u64, &'a [u8])>> {
+    let Some((header, rest)) = r.split_first_chunk::<RECORD_HEADER_LENGTH>() else {
+        return Ok(None);
+    };
+    let seq_no = u64::from_be_bytes(header[..8].try_into().unwrap());
+    let length = u32::from_be_bytes(header[8..].try_into().unwrap()) as usize;
+    if rest.len() < length + RECORD_CHECKSUM_LENGTH {
+        return Ok(None);
+    }
+
+    let (record, rest) = r.split_at(RECORD_HEADER_LENGTH + length);
+    let (checksum, rest) = rest.split_at(RECORD_CHECKSUM_LENGTH);
+    let expected = u32::from_be_bytes(checksum.try_into().unwrap());
+    let actual = crc32fast::hash(record);
+    if actual != expected {
+        if rest.is_empty() {
+            return Ok(None);
+        }
+        return Err(corrupt(format!("checksum failed for the record of sequence number {seq_no}")));
+    }
+
+    *r = rest;
+    Ok(Some((seq_no, &record[RECORD_HEADER_LENGTH..])))
+}
+
+fn corrupt(message: String) -> BoxError {
+    LuceneError::CorruptIndex(message.into()).into()
+}
+
+/// Returns the name of the translog file of the given generation.
+pub fn translog_file_name(generation: u64) -> String {
+    format!("{TRANSLOG_FILE_NAME_PREFIX}_{}", generation_to_string(generation))
+}
+
+/// Returns the generation of a translog file, or `None` if `file_name` doesn't name one.
+fn parse_translog_file_name(file_name: &str) -> Option<u64> {
+    let generation = file_name.strip_prefix(TRANSLOG_FILE_NAME_PREFIX)?.strip_prefix('_')?;
+    u64::from_str_radix(generation, 36).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{
+            codec::header_length,
+            document::{Document, Field, Store, TermVectorOptions},
+            index::{
+                FieldType, IndexReader, IndexWriter, IndexWriterConfig, Schema, Term, Translog, TranslogDurability,
+                ValueType, VectorSimilarityFunction, TRANSLOG_SEQ_NO_USER_DATA_KEY,
+            },
+            io::{test_util::SyncRecordingDirectory, ByteBuffersDirectory, Directory, IoContext},
+            search::{IndexSearcher, TermQuery},
+            util::MAX_TERM_LENGTH,
+        },
+        pretty_assertions::assert_eq,
+        std::{sync::Arc, time::Duration},
+        tokio::io::{AsyncReadExt, AsyncWriteExt},
+    };
+
+    fn document(i: i64) -> Document {
+        let mut document = Document::new();
+        document.add(Field::string("id", format!("{i}"), Store::Yes));
+        document.add(
+            Field::text("body", "quick brown fox", Store::No)
+                .with_term_vectors(TermVectorOptions::POSITIONS_AND_OFFSETS)
+                .unwrap(),
+        );
+        document.add(Field::numeric_doc_values("price", i * 10));
+        document.add(Field::binary_doc_values("shape", vec![i as u8, 0xff]));
+        document.add(Field::feature("features", "pagerank", 1.5).unwrap());
+        document.add(Field::knn_vector("embedding", vec![i as f32, -0.5], VectorSimilarityFunction::Cosine).unwrap());
+        document
+    }
+
+    async fn new_writer() -> IndexWriter {
+        IndexWriter::new(Box::new(ByteBuffersDirectory::new()), IndexWriterConfig::new()).await.unwrap()
+    }
+
+    async fn read_file(dir: &ByteBuffersDirectory, file_name: &str) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        dir.clone().open(file_name, &IoContext::ReadOnce).await.unwrap().read_to_end(&mut bytes).await.unwrap();
+        bytes
+    }
+
+    async fn write_file(dir: &ByteBuffersDirectory, file_name: &str, bytes: &[u8]) {
+        let mut w = dir.clone().create(file_name, &IoContext::Default).await.unwrap();
+        w.write_all(bytes).await.unwrap();
+        w.shutdown().await.unwrap();
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_recover_after_crash() {
+        let dir = SyncRecordingDirectory::default();
+        let mut translog = Translog::open(Box::new(dir.clone()), TranslogDurability::Request).await.unwrap();
+        let writer = new_writer().await;
+        for i in 0..3 {
+            translog.add_document(&writer, &document(i)).await.unwrap();
+        }
+
+        // Every operation is appended to the generation's file and synced before it's acknowledged.
+        assert_eq!(translog.files(), ["translog_0"]);
+        assert_eq!(translog.num_unsynced_ops(), 0);
+        assert_eq!(dir.take_events(), vec!["sync translog_0"; 3]);
+        drop(translog);
+
+        // Syncs cut short by a crash leave a last record that is incomplete or fails its checksum. These copies also
+        // repeat records of the first generation, as a sync retried in a new generation does.
+        let bytes = read_file(&dir.inner, "translog_0").await;
+        write_file(&dir.inner, "translog_1", &bytes[..bytes.len() - 3]).await;
+        let mut flipped = bytes.clone();
+        *flipped.last_mut().unwrap() ^= 0xff;
+        write_file(&dir.inner, "translog_2", &flipped).await;
+        write_file(&dir.inner, "translog_3", &bytes[..header_length("Translog") - 1]).await;
+
+        let mut translog = Translog::open(Box::new(dir.clone()), TranslogDurability::Request).await.unwrap();
+        let recovered = new_writer().await;
+        assert_eq!(translog.recover(&recovered).await.unwrap(), 3);
+        assert_eq!(translog.num_ops(), 3);
+
+        recovered.flush().unwrap();
+        let reader = recovered.reader().unwrap();
+        assert_eq!(reader.max_doc(), 3);
+        writer.flush().unwrap();
+        for doc in 0..3 {
+            assert_eq!(reader.document(doc).unwrap(), writer.reader().unwrap().document(doc).unwrap());
+        }
+        let vectors: Vec<f32> = reader
+            .leaves()
+            .iter()
+            .flat_map(|leaf| leaf.reader().float_vector_values("embedding").unwrap().unwrap().vectors().to_vec())
+            .collect();
+        assert_eq!(vectors, [0.0, -0.5, 1.0, -0.5, 2.0, -0.5]);
+
+        // New operations go to a new generation and continue the sequence numbers.
+        translog.add_document(&recovered, &document(3)).await.unwrap();
+        assert_eq!(translog.files().last().unwrap(), "translog_4");
+        drop(translog);
+        let mut reopened = Translog::open(Box::new(dir.inner.clone()), TranslogDurability::Request).await.unwrap();
+        assert_eq!(reopened.recover(&new_writer().await).await.unwrap(), 4);
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_interval_durability() {
+        let dir = SyncRecordingDirectory::default();
+        let durability = TranslogDurability::Interval(Duration::from_secs(3600));
+        let mut translog = Translog::open(Box::new(dir.clone()), durability).await.unwrap();
+        let writer = new_writer().await;
+        for i in 0..4 {
+            translog.add_document(&writer, &document(i)).await.unwrap();
+        }
+        assert_eq!(translog.num_unsynced_ops(), 4);
+        assert!(translog.files().is_empty());
+
+        translog.sync().await.unwrap();
+        translog.sync().await.unwrap();
+        assert_eq!(translog.files(), ["translog_0"]);
+        assert_eq!(translog.num_ops(), 4);
+        assert_eq!(dir.take_events(), ["sync translog_0"]);
+
+        drop(translog);
+        let mut reopened = Translog::open(Box::new(dir), durability).await.unwrap();
+        assert_eq!(reopened.recover(&new_writer().await).await.unwrap(), 4);
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_rejected_operations() {
+        let dir = SyncRecordingDirectory::default();
+        let mut schema = Schema::new();
+        schema.add_field("id", FieldType::string(ValueType::Text, Store::Yes)).unwrap();
+        let mut config = IndexWriterConfig::new();
+        config.set_schema(Some(Arc::new(schema)));
+        let writer = IndexWriter::new(Box::new(ByteBuffersDirectory::new()), config.clone()).await.unwrap();
+        let id = |id: &str| {
+            let mut document = Document::new();
+            document.add(Field::string("id", id, Store::Yes));
+            document
+        };
+
+        // A document the schema rejects is never logged.
+        let mut translog = Translog::open(Box::new(dir.clone()), TranslogDurability::Request).await.unwrap();
+        let mut undeclared = id("0");
+        undeclared.add(Field::string("undeclared", "x", Store::No));
+        assert!(translog.add_document(&writer, &undeclared).await.is_err());
+        assert_eq!(translog.num_ops(), 0);
+        assert!(dir.take_events().is_empty());
+
+        // A document the writer rejects once it's synced is discarded.
+        translog.add_document(&writer, &id("1")).await.unwrap();
+        assert!(translog.add_document(&writer, &id(&"x".repeat(MAX_TERM_LENGTH + 1))).await.is_err());
+        translog.add_document(&writer, &id("2")).await.unwrap();
+        assert_eq!(translog.num_ops(), 4);
+        assert_eq!(dir.take_events(), vec!["sync translog_0"; 4]);
+        drop(translog);
+
+        let mut translog = Translog::open(Box::new(dir.clone()), TranslogDurability::Request).await.unwrap();
+        let recovered = IndexWriter::new(Box::new(ByteBuffersDirectory::new()), config).await.unwrap();
+        assert_eq!(translog.recover(&recovered).await.unwrap(), 2);
+        recovered.flush().unwrap();
+        let reader = recovered.reader().unwrap();
+        let ids: Vec<_> = (0..2).map(|doc| reader.document(doc).unwrap().get("id").unwrap().to_string()).collect();
+        assert_eq!(ids, ["1", "2"]);
+
+        // Operations a writer rejects are skipped rather than failing the recovery.
+        let mut schema = Schema::new();
+        schema.add_field("id", FieldType::string(ValueType::Text, Store::Yes).with_required(true)).unwrap();
+        schema.add_field("year", FieldType::numeric_doc_values().with_required(true)).unwrap();
+        let mut config = IndexWriterConfig::new();
+        config.set_schema(Some(Arc::new(schema)));
+        let mut translog = Translog::open(Box::new(dir), TranslogDurability::Request).await.unwrap();
+        let strict = IndexWriter::new(Box::new(ByteBuffersDirectory::new()), config).await.unwrap();
+        assert_eq!(translog.recover(&strict).await.unwrap(), 0);
+        assert_eq!(strict.num_buffered_docs(), 0);
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_deletes_and_commits() {
+        let index_dir = ByteBuffersDirectory::new();
+        let dir = ByteBuffersDirectory::new();
+        let mut writer = IndexWriter::new(Box::new(index_dir.clone()), IndexWriterConfig::new()).await.unwrap();
+        let mut translog = Translog::open(Box::new(dir.clone()), TranslogDurability::Request).await.unwrap();
+        let id = |i: i64| Term::new("id", format!("{i}"));
+
+        // Deleting every added document leaves nothing in memory, so the writer can commit, and the files are removed.
+        translog.add_document(&writer, &document(0)).await.unwrap();
+        assert_eq!(translog.delete_documents(&writer, &[id(0)]).await.unwrap(), 1);
+        translog.commit(&mut writer).await.unwrap();
+        assert!(translog.files().is_empty());
+        assert!(dir.read_dir().await.unwrap().is_empty());
+        assert_eq!(writer.get_live_commit_data()[TRANSLOG_SEQ_NO_USER_DATA_KEY], "2");
+
+        // Later operations go to a new generation. A commit that fails leaves them, and the live commit data, alone.
+        for i in 1..4 {
+            translog.add_document(&writer, &document(i)).await.unwrap();
+        }
+        assert_eq!(translog.delete_documents(&writer, &[id(2), id(7)]).await.unwrap(), 1);
+        assert!(translog.commit(&mut writer).await.is_err());
+        assert_eq!(translog.files(), ["translog_1"]);
+        assert_eq!(writer.get_live_commit_data()[TRANSLOG_SEQ_NO_USER_DATA_KEY], "2");
+        drop(translog);
+        writer.close().await.unwrap();
+
+        // Recovery skips the committed operations, and replays the deletes in order.
+        let recovered = IndexWriter::new(Box::new(index_dir), IndexWriterConfig::new()).await.unwrap();
+        let mut translog = Translog::open(Box::new(dir), TranslogDurability::Request).await.unwrap();
+        assert_eq!(translog.recover(&recovered).await.unwrap(), 4);
+        recovered.flush().unwrap();
+        let searcher = IndexSearcher::new(Arc::new(recovered.reader().unwrap()));
+        let live: Vec<_> = (0..4).filter(|&i| searcher.count(&TermQuery::new(id(i))).unwrap() > 0).collect();
+        assert_eq!(live, [1, 3]);
+        translog.add_document(&recovered, &document(4)).await.unwrap();
+        assert_eq!(translog.files().last().unwrap(), "translog_2");
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_corrupt_record_fails_recovery() {
+        let dir = ByteBuffersDirectory::new();
+        let mut translog = Translog::open(Box::new(dir.clone()), TranslogDurability::Request).await.unwrap();
+        let writer = new_writer().await;
+        translog.add_document(&writer, &document(0)).await.unwrap();
+        translog.add_document(&writer, &document(1)).await.unwrap();
+        drop(translog);
+
+        // Only the last record of a file can have been cut short by a crash.
+        let mut bytes = read_file(&dir, "translog_0").await;
+        bytes[header_length("Translog") + 16] ^= 0xff;
+        dir.clone().remove("translog_0").await.unwrap();
+        write_file(&dir, "translog_0", &bytes).await;
+
+        let mut translog = Translog::open(Box::new(dir), TranslogDurability::Request).await.unwrap();
+        let err = translog.recover(&new_writer().await).await.unwrap_err();
+        assert!(err.to_string().contains("translog_0"), "unexpected error: {err}");
+        assert!(err.to_string().contains("checksum"), "unexpected error: {err}");
+    }
+}
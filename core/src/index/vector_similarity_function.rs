@@ -0,0 +1,119 @@
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+/// The largest number of dimensions a vector field may have.
+pub const MAX_VECTOR_DIMENSIONS: usize = 1024;
+
+/// How the similarity of two vectors is computed, for [crate::document::Field::knn_vector] fields. Each function
+/// returns a score that is higher for more similar vectors and never negative, as Lucene's `VectorSimilarityFunction`
+/// does.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum VectorSimilarityFunction {
+    /// Euclidean distance, scored as `1 / (1 + distance²)`.
+    Euclidean,
+
+    /// The dot product of vectors of unit length, scored as `(1 + dot) / 2`. Vectors must be normalized for scores
+    /// to be meaningful.
+    DotProduct,
+
+    /// The cosine of the angle between the vectors, scored as `(1 + cosine) / 2`. Vectors may not be all zeros.
+    Cosine,
+
+    /// The dot product of vectors that needn't be normalized, scaled so that scores are positive.
+    MaximumInnerProduct,
+}
+
+impl VectorSimilarityFunction {
+    /// Returns the similarity of two vectors of the same dimension.
+    pub fn compare(self, v1: &[f32], v2: &[f32]) -> f32 {
+        debug_assert_eq!(v1.len(), v2.len());
+        match self {
+            Self::Euclidean => 1.0 / (1.0 + square_distance(v1, v2)),
+            Self::DotProduct => ((1.0 + dot_product(v1, v2)) / 2.0).max(0.0),
+            Self::Cosine => (1.0 + cosine(v1, v2)) / 2.0,
+            Self::MaximumInnerProduct => {
+                let dot = dot_product(v1, v2);
+                if dot < 0.0 {
+                    1.0 / (1.0 - dot)
+                } else {
+                    dot + 1.0
+                }
+            }
+        }
+    }
+
+    /// Returns the byte this function is written as in the translog.
+    pub(crate) fn to_byte(self) -> u8 {
+        match self {
+            Self::Euclidean => 0,
+            Self::DotProduct => 1,
+            Self::Cosine => 2,
+            Self::MaximumInnerProduct => 3,
+        }
+    }
+
+    /// Returns the function written as `byte` by [VectorSimilarityFunction::to_byte].
+    pub(crate) fn from_byte(byte: u8) -> Option<Self> {
+        match byte {
+            0 => Some(Self::Euclidean),
+            1 => Some(Self::DotProduct),
+            2 => Some(Self::Cosine),
+            3 => Some(Self::MaximumInnerProduct),
+            _ => None,
+        }
+    }
+}
+
+impl Display for VectorSimilarityFunction {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        f.write_str(match self {
+            Self::Euclidean => "euclidean",
+            Self::DotProduct => "dot_product",
+            Self::Cosine => "cosine",
+            Self::MaximumInnerProduct => "maximum_inner_product",
+        })
+    }
+}
+
+fn dot_product(v1: &[f32], v2: &[f32]) -> f32 {
+    v1.iter().zip(v2).map(|(a, b)| a * b).sum()
+}
+
+fn square_distance(v1: &[f32], v2: &[f32]) -> f32 {
+    v1.iter().zip(v2).map(|(a, b)| (a - b) * (a - b)).sum()
+}
+
+fn cosine(v1: &[f32], v2: &[f32]) -> f32 {
+    let norm = (dot_product(v1, v1) * dot_product(v2, v2)).sqrt();
+    if norm == 0.0 {
+        0.0
+    } else {
+        dot_product(v1, v2) / norm
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {crate::index::VectorSimilarityFunction, pretty_assertions::assert_eq};
+
+    #[test]
+    fn test_compare() {
+        let (v1, v2) = ([1.0, 0.0], [0.0, 1.0]);
+        assert_eq!(VectorSimilarityFunction::Euclidean.compare(&v1, &v1), 1.0);
+        assert_eq!(VectorSimilarityFunction::Euclidean.compare(&v1, &v2), 1.0 / 3.0);
+        assert_eq!(VectorSimilarityFunction::DotProduct.compare(&v1, &v2), 0.5);
+        assert_eq!(VectorSimilarityFunction::DotProduct.compare(&v1, &[-1.0, 0.0]), 0.0);
+        assert_eq!(VectorSimilarityFunction::Cosine.compare(&[2.0, 0.0], &v1), 1.0);
+        assert_eq!(VectorSimilarityFunction::MaximumInnerProduct.compare(&[2.0, 0.0], &v1), 3.0);
+        assert_eq!(VectorSimilarityFunction::MaximumInnerProduct.compare(&[-1.0, 0.0], &v1), 0.5);
+
+        for function in [
+            VectorSimilarityFunction::Euclidean,
+            VectorSimilarityFunction::DotProduct,
+            VectorSimilarityFunction::Cosine,
+            VectorSimilarityFunction::MaximumInnerProduct,
+        ] {
+            assert_eq!(VectorSimilarityFunction::from_byte(function.to_byte()), Some(function));
+        }
+        assert_eq!(VectorSimilarityFunction::from_byte(4), None);
+    }
+}
@@ -4,3 +4,830 @@ pub const MAX_DOCS: u32 = i32::MAX as u32 - 128;
 
 /// Maximum value of the token position in an indexed field.
 pub const MAX_POSITION: u32 = i32::MAX as u32 - 128;
+
+use {
+    crate::{
+        codec::get_codec,
+        document::Document,
+        index::{
+            get_latest_segment_index_file_name_and_generation, CommitEvent, DocumentsWriter, IndexCommit,
+            IndexWriterConfig, IngestBatchStats, IngestStats, LeafReader, MemorySegmentBuilder, MergeStats,
+            MultiReader, OpenMode, Schema, SegmentCommitInfo, SegmentIndex, Term, SCHEMA_USER_DATA_KEY,
+        },
+        io::{Directory, IoContext, Lock, MergeInfo, WRITE_LOCK_NAME},
+        metrics::INGEST_BATCH_LATENCY_SECONDS,
+        BoxResult, Id, LuceneError, LATEST,
+    },
+    futures_core::Stream,
+    log::warn,
+    std::{
+        collections::HashMap,
+        fmt::{Debug, Formatter, Result as FmtResult},
+        future::poll_fn,
+        pin::pin,
+        sync::Arc,
+        time::Instant,
+    },
+    tokio::io::AsyncWriteExt,
+};
+
+/// Creates and maintains an index.
+///
+/// An `IndexWriter` holds the [WRITE_LOCK_NAME] lock of its directory for as long as it is open, so two writers
+/// (in this process or another one) can never modify the same index at the same time. Before any change is made to
+/// the index, the lock is verified to still be valid.
+///
+/// Added documents are buffered in memory, and flushed to new segments when the flush policy asks for it, by default
+/// once they use more than the configured RAM buffer size (see [IndexWriterConfig::set_flush_policy] and
+/// [IndexWriterConfig::set_ram_buffer_size_mb]), or when [IndexWriter::flush] is called. There is no
+/// on-disk segment writer yet, so flushed segments are held in memory, and can be searched through
+/// [IndexWriter::reader], but can't be committed.
+///
+/// Documents can be added from several threads at once through [IndexWriter::documents_writer]: each thread builds
+/// its own segment, and flushes it without blocking the others.
+///
+/// The segments of other indexes can be added with [IndexWriter::add_indexes], which copies their files, and are
+/// recorded in the index by [IndexWriter::commit].
+pub struct IndexWriter {
+    directory: Box<dyn Directory + Send + Sync>,
+    config: IndexWriterConfig,
+    write_lock: Option<Box<dyn Lock>>,
+    documents_writer: Arc<DocumentsWriter>,
+    segment_index: SegmentIndex,
+    last_commit: Option<IndexCommit>,
+}
+
+impl IndexWriter {
+    /// Opens an index writer on the given directory.
+    ///
+    /// This fails with [LuceneError::LockObtainFailed] if another writer already holds the write lock, with
+    /// [LuceneError::IndexNotFound] if the configuration's [OpenMode] is [OpenMode::Append] and the directory does
+    /// not contain an index, or with [LuceneError::SchemaViolation] if the configuration's schema is incompatible
+    /// with the one the index was committed with.
+    pub async fn new(mut directory: Box<dyn Directory + Send + Sync>, config: IndexWriterConfig) -> BoxResult<Self> {
+        let write_lock = directory.obtain_lock(WRITE_LOCK_NAME).await?;
+
+        let files = directory.read_dir().await?;
+        let index_exists = get_latest_segment_index_file_name_and_generation(&files)?.is_some();
+        let mut segment_index = match index_exists {
+            true => SegmentIndex::open(&mut directory).await?,
+            false if config.open_mode() == OpenMode::Append => {
+                return Err(LuceneError::IndexNotFound(format!("No segments file found in {directory:?}")).into());
+            }
+            false => SegmentIndex::new(LATEST.major())?,
+        };
+        let last_commit = index_exists.then(|| IndexCommit::from_segment_index(&segment_index));
+        if index_exists && config.open_mode() == OpenMode::Create {
+            // The generation of the existing index is kept, so the first commit replaces its latest commit.
+            segment_index.clear();
+        }
+
+        if let Some(schema) = config.schema() {
+            let mut user_data = segment_index.get_user_data().clone();
+            if let Some(previous) = user_data.get(SCHEMA_USER_DATA_KEY) {
+                if index_exists && config.open_mode() != OpenMode::Create {
+                    schema.check_compatible(&Schema::decode(previous)?)?;
+                }
+            }
+            user_data.insert(SCHEMA_USER_DATA_KEY.to_string(), schema.encode());
+            segment_index.set_user_data(user_data);
+        }
+
+        Ok(Self {
+            documents_writer: Arc::new(DocumentsWriter::new(config.clone())),
+            directory,
+            config,
+            write_lock: Some(write_lock),
+            segment_index,
+            last_commit,
+        })
+    }
+
+    /// Returns the directory this writer is writing to.
+    #[inline]
+    pub fn directory(&self) -> &dyn Directory {
+        self.directory.as_ref()
+    }
+
+    /// Returns the configuration used to create this writer.
+    #[inline]
+    pub fn config(&self) -> &IndexWriterConfig {
+        &self.config
+    }
+
+    /// Indicates whether this writer is still open.
+    #[inline]
+    pub fn is_open(&self) -> bool {
+        self.write_lock.is_some()
+    }
+
+    /// Verifies that this writer is open and still holds a valid write lock.
+    pub fn ensure_open(&self) -> BoxResult<()> {
+        match &self.write_lock {
+            Some(lock) => lock.ensure_valid(),
+            None => Err(LuceneError::AlreadyClosed("This IndexWriter is closed".to_string()).into()),
+        }
+    }
+
+    /// Adds a document, flushing a segment if the flush policy then asks for it. Returns whether a flush happened.
+    /// See [DocumentsWriter::add_document].
+    pub fn add_document(&self, document: &Document) -> BoxResult<bool> {
+        self.ensure_open()?;
+        self.documents_writer.add_document(document)
+    }
+
+    /// Deletes the documents containing any of `terms`, returning the number of documents deleted. Documents are
+    /// only deleted from the flushed segments, so the buffered documents are flushed first. See
+    /// [DocumentsWriter::delete_documents].
+    pub fn delete_documents(&self, terms: &[Term]) -> BoxResult<u32> {
+        self.ensure_open()?;
+        self.documents_writer.delete_documents(terms)
+    }
+
+    /// Returns the buffer of added documents, which can be shared with other threads to add documents concurrently.
+    /// It stops accepting documents once this writer is closed.
+    #[inline]
+    pub fn documents_writer(&self) -> &Arc<DocumentsWriter> {
+        &self.documents_writer
+    }
+
+    /// Adds the documents of a stream, taking [IndexWriterConfig::ingest_batch_size] documents at a time and calling
+    /// `on_batch` with the statistics of each batch. Batches are only timed if the configuration has a metrics
+    /// recorder (see [IndexWriterConfig::set_metrics]).
+    ///
+    /// The stream is only polled for more documents once the previous batch has been indexed and, if the RAM buffer
+    /// filled up, flushed, so producers feeding the stream through a bounded channel are slowed down to the rate at
+    /// which documents can be indexed.
+    ///
+    /// Documents indexed before an error remain buffered.
+    pub async fn add_documents_stream<S: Stream<Item = Document>>(
+        &self,
+        documents: S,
+        mut on_batch: impl FnMut(&IngestBatchStats),
+    ) -> BoxResult<IngestStats> {
+        self.ensure_open()?;
+
+        let mut documents = pin!(documents);
+        let batch_size = self.config.ingest_batch_size();
+        let mut batch = Vec::with_capacity(batch_size);
+        let mut stats = IngestStats::default();
+
+        loop {
+            while batch.len() < batch_size {
+                match poll_fn(|cx| documents.as_mut().poll_next(cx)).await {
+                    Some(document) => batch.push(document),
+                    None => break,
+                }
+            }
+
+            if batch.is_empty() {
+                return Ok(stats);
+            }
+
+            let start = self.config.metrics().map(|_| Instant::now());
+            let docs = batch.len();
+            let mut flushes = 0;
+            for document in batch.drain(..) {
+                flushes += self.add_document(&document)? as usize;
+            }
+
+            let elapsed = start.map(|start| start.elapsed());
+            if let (Some(metrics), Some(elapsed)) = (self.config.metrics(), elapsed) {
+                metrics.record_histogram(INGEST_BATCH_LATENCY_SECONDS, elapsed.as_secs_f64());
+            }
+
+            let batch_stats = IngestBatchStats {
+                batch: stats.batches,
+                docs,
+                elapsed,
+                ram_bytes_used: self.ram_bytes_used(),
+                flushes,
+            };
+            stats.add_batch(&batch_stats);
+            on_batch(&batch_stats);
+        }
+    }
+
+    /// Flushes the buffered documents to new segments. See [DocumentsWriter::flush].
+    pub fn flush(&self) -> BoxResult<()> {
+        self.ensure_open()?;
+        self.documents_writer.flush()
+    }
+
+    /// Merges the flushed segments until there are at most `max_num_segments`, returning the merge
+    /// statistics once it has finished. See [DocumentsWriter::force_merge].
+    ///
+    /// Only the segments held in memory are merged; those added by [IndexWriter::add_indexes] are kept as they are.
+    pub fn force_merge(&self, max_num_segments: usize) -> BoxResult<MergeStats> {
+        self.ensure_open()?;
+        self.documents_writer.force_merge(max_num_segments)
+    }
+
+    /// Rewrites the flushed segments with more than `pct_allowed` percent of deleted documents, returning once the
+    /// rewrites have finished with their statistics. See [DocumentsWriter::force_merge_deletes].
+    pub fn force_merge_deletes(&self, pct_allowed: f64) -> BoxResult<MergeStats> {
+        self.ensure_open()?;
+        self.documents_writer.force_merge_deletes(pct_allowed)
+    }
+
+    /// Returns the number of documents buffered since they were last flushed.
+    #[inline]
+    pub fn num_buffered_docs(&self) -> usize {
+        self.documents_writer.num_buffered_docs()
+    }
+
+    /// Returns an estimate of the memory used by the buffered documents, in bytes.
+    #[inline]
+    pub fn ram_bytes_used(&self) -> usize {
+        self.documents_writer.ram_bytes_used()
+    }
+
+    /// Adds the segments of the indexes in `directories` to this index, copying their files and renaming each
+    /// segment to a new name in this index. Deleted documents stay deleted, and the segments keep their ids, so
+    /// their files can be copied byte for byte; only the segment info file is rewritten with the new name.
+    ///
+    /// The write lock of every source directory is held while its files are copied, so the source indexes must not
+    /// be open in a writer. They must have been created by the same major version of Lucene as this index. The new
+    /// segments become visible once [IndexWriter::commit] is called. If copying fails, the files copied so far are
+    /// removed and this index is left unchanged.
+    pub async fn add_indexes<D: Directory>(&mut self, directories: &mut [D]) -> BoxResult<()> {
+        self.ensure_open()?;
+
+        let mut locks = Vec::with_capacity(directories.len());
+        let mut sources = Vec::with_capacity(directories.len());
+        let mut total_docs = self.max_doc() as u64;
+        for directory in directories.iter_mut() {
+            locks.push(directory.obtain_lock(WRITE_LOCK_NAME).await?);
+            let source = SegmentIndex::open(directory).await?;
+            let created = source.get_index_created_version_major();
+            if created != self.segment_index.get_index_created_version_major() {
+                return Err(LuceneError::InvalidArgument(format!(
+                    "cannot add an index created by Lucene {created} to one created by Lucene {}",
+                    self.segment_index.get_index_created_version_major()
+                ))
+                .into());
+            }
+
+            total_docs += source
+                .get_segments()
+                .iter()
+                .map(|segment| segment.get_segment_info().get_max_doc() as u64)
+                .sum::<u64>();
+            sources.push(source);
+        }
+        if total_docs > MAX_DOCS as u64 {
+            return Err(LuceneError::TooManyDocs(total_docs).into());
+        }
+
+        let mut copied = Vec::new();
+        let mut segments = Vec::new();
+        for (directory, source) in directories.iter_mut().zip(sources) {
+            for segment in source.into_segments() {
+                match self.copy_segment(directory, segment, &mut copied).await {
+                    Ok(segment) => segments.push(segment),
+                    Err(e) => {
+                        for file_name in &copied {
+                            if let Err(e) = self.directory.remove(file_name).await {
+                                warn!("Failed to remove {file_name} after add_indexes failed: {e}");
+                            }
+                        }
+                        return Err(e);
+                    }
+                }
+            }
+        }
+
+        for segment in segments {
+            self.segment_index.add_segment(segment)?;
+        }
+        for mut lock in locks {
+            lock.close()?;
+        }
+        Ok(())
+    }
+
+    /// Copies the files of a segment of another index to this index under a new segment name, recording the names
+    /// of the files created in `copied`, and returns the renamed segment.
+    async fn copy_segment<D: Directory>(
+        &mut self,
+        directory: &mut D,
+        mut segment: SegmentCommitInfo,
+        copied: &mut Vec<String>,
+    ) -> BoxResult<SegmentCommitInfo> {
+        let info = segment.get_segment_info();
+        let old_name = info.get_name().to_string();
+        if segment.get_field_infos_gen().is_some() || segment.get_doc_values_gen().is_some() {
+            return Err(LuceneError::InvalidArgument(format!(
+                "segment {old_name} has doc values updates, which can't be copied"
+            ))
+            .into());
+        }
+        let Some(codec_name) = info.get_codec_name() else {
+            return Err(LuceneError::IllegalState(format!("segment {old_name} has no codec")).into());
+        };
+        let codec = get_codec(codec_name)?;
+
+        let context = IoContext::Merge(MergeInfo {
+            total_max_doc: info.get_max_doc(),
+            estimated_merge_bytes: 0,
+            is_external: true,
+            merge_max_num_segments: None,
+        });
+        let new_name = self.segment_index.new_segment_name();
+        let rename = |file_name: &str| match file_name.strip_prefix(old_name.as_str()) {
+            Some(suffix) if suffix.starts_with(['.', '_']) => Ok(format!("{new_name}{suffix}")),
+            _ => {
+                Err(LuceneError::CorruptIndex(format!("file {file_name} doesn't belong to segment {old_name}").into()))
+            }
+        };
+
+        // The segment info file is copied too, then rewritten with the new name.
+        let mut files: Vec<String> = info.get_files().iter().cloned().collect();
+        files.extend(codec.live_docs_format().files(&segment));
+        files.sort();
+        for file_name in &files {
+            let new_file_name = rename(file_name)?;
+            let mut r = directory.open(file_name, &context).await?;
+            copied.push(new_file_name.clone());
+            let mut w = self.directory.create(&new_file_name, &context).await?;
+            tokio::io::copy(&mut r, &mut w).await?;
+            w.shutdown().await?;
+        }
+
+        let new_files = info.get_files().iter().map(|file_name| rename(file_name)).collect::<Result<_, _>>()?;
+        segment.info.files = new_files;
+        segment.info.name = new_name;
+        segment.id = Some(Id::random_id());
+        codec.segment_info_format().write_segment_info(self.directory.as_mut(), &segment.info, &context).await?;
+        Ok(segment)
+    }
+
+    /// Adds the live documents of `readers` to this index as one new segment, returning the number of documents
+    /// added. Their terms, postings, norms, doc values and stored fields are copied rather than re-analyzed (see
+    /// [MemorySegmentBuilder::add_reader]).
+    ///
+    /// Unlike [IndexWriter::add_indexes], this accepts any [LeafReader], such as a
+    /// [crate::index::SortingCodecReader] that re-sorts a segment. The new segment is held in memory like the
+    /// segments flushed from added documents.
+    pub fn add_indexes_from_readers(&self, readers: &[Arc<dyn LeafReader>]) -> BoxResult<u32> {
+        self.ensure_open()?;
+
+        let mut builder = MemorySegmentBuilder::new(self.config.indexing_analyzer());
+        builder.set_name(self.documents_writer.new_segment_name());
+        builder.set_similarity(self.config.similarity().clone());
+        builder.set_terms_formats(self.config.terms_formats().clone());
+        let mut num_docs = 0;
+        for reader in readers {
+            num_docs += builder.add_reader(reader.as_ref())?;
+        }
+
+        if num_docs > 0 {
+            self.documents_writer.add_segment(Arc::new(builder.build()))?;
+        }
+        Ok(num_docs)
+    }
+
+    /// Commits the segments of the index, writing a new `segments_N` file and returning its name. The user data set
+    /// with [IndexWriter::set_live_commit_data] is written with the segments.
+    ///
+    /// Only segments on disk, those added by [IndexWriter::add_indexes], can be committed. There is no on-disk
+    /// segment writer yet, so this fails with [LuceneError::IllegalState], without writing anything, while added
+    /// documents are buffered or held in flushed segments: committing would lose them while recording user data that
+    /// claims they were indexed.
+    pub async fn commit(&mut self) -> BoxResult<String> {
+        self.ensure_open()?;
+        let in_memory = self.num_buffered_docs() as u64
+            + self.segments().iter().map(|segment| segment.max_doc() as u64).sum::<u64>();
+        if in_memory > 0 {
+            return Err(LuceneError::IllegalState(format!(
+                "{in_memory} added documents are held in memory and can't be committed"
+            ))
+            .into());
+        }
+
+        let segments_file_name = self.segment_index.commit(&mut self.directory).await?;
+        self.last_commit = Some(IndexCommit::from_segment_index(&self.segment_index));
+        if let Some(listener) = self.config.event_listener() {
+            listener.on_commit(&CommitEvent {
+                segments_file_name: &segments_file_name,
+                generation: self.segment_index.get_last_generation(),
+                num_segments: self.segment_index.get_segments().len(),
+            });
+        }
+        Ok(segments_file_name)
+    }
+
+    /// Sets the user data written with the next commit, replacing the data set before or read from the latest
+    /// commit, as Lucene's `IndexWriter.setLiveCommitData` does.
+    ///
+    /// The data is written in the same `segments_N` file as the segments, and is read back from
+    /// [IndexCommit::get_user_data] through [crate::index::IndexReader::index_commit] or [IndexCommit::read_latest].
+    /// If the writer has a schema, its [SCHEMA_USER_DATA_KEY] entry is kept.
+    ///
+    /// Only segments added with [IndexWriter::add_indexes] can be committed (see [IndexWriter::commit]), so the data
+    /// can only describe those. While documents added with [IndexWriter::add_document] are held in memory, commits
+    /// fail and the data isn't written.
+    pub fn set_live_commit_data(&mut self, mut user_data: HashMap<String, String>) {
+        if let Some(schema) = self.segment_index.get_user_data().get(SCHEMA_USER_DATA_KEY) {
+            if self.config.schema().is_some() {
+                user_data.insert(SCHEMA_USER_DATA_KEY.to_string(), schema.clone());
+            }
+        }
+        self.segment_index.set_user_data(user_data);
+    }
+
+    /// Returns the user data that will be written with the next commit.
+    #[inline]
+    pub fn get_live_commit_data(&self) -> &HashMap<String, String> {
+        self.segment_index.get_user_data()
+    }
+
+    /// Returns the segments of the index as of the next commit.
+    #[inline]
+    pub fn segment_index(&self) -> &SegmentIndex {
+        &self.segment_index
+    }
+
+    /// Returns the number of documents in the index, including those in memory and deleted ones, but not those
+    /// still buffered.
+    pub fn max_doc(&self) -> u32 {
+        let on_disk: u32 =
+            self.segment_index.get_segments().iter().map(|segment| segment.get_segment_info().get_max_doc()).sum();
+        on_disk + self.segments().iter().map(|segment| segment.max_doc()).sum::<u32>()
+    }
+
+    /// Returns the segments flushed so far.
+    pub fn segments(&self) -> Vec<Arc<dyn LeafReader>> {
+        self.documents_writer.segments()
+    }
+
+    /// Returns a reader over the segments flushed so far. Buffered documents aren't visible until flushed. The
+    /// reader's [crate::index::IndexReader::index_commit] is the latest commit of the index, if any.
+    pub fn reader(&self) -> BoxResult<MultiReader> {
+        let mut reader = MultiReader::new(self.segments())?;
+        reader.set_index_commit(self.last_commit.clone());
+        Ok(reader)
+    }
+
+    /// Closes this writer, releasing the write lock.
+    pub async fn close(&mut self) -> BoxResult<()> {
+        self.documents_writer.close();
+        if let Some(mut lock) = self.write_lock.take() {
+            lock.close()?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Debug for IndexWriter {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        f.debug_struct("IndexWriter")
+            .field("directory", &self.directory)
+            .field("config", &self.config)
+            .field("open", &self.is_open())
+            .field("documents_writer", &self.documents_writer)
+            .field("segments", &self.segment_index.get_segments().len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{
+            analysis::SimpleAnalyzer,
+            codec::get_codec,
+            document::{Document, Field, Store},
+            index::{
+                IndexCommit, IndexReader, IndexWriter, IndexWriterConfig, LeafReader, MemorySegmentBuilder, OpenMode,
+                Schema, SegmentCommitInfo, SegmentIndex, SegmentInfo, SegmentReader, SortingCodecReader,
+                SCHEMA_USER_DATA_KEY,
+            },
+            io::{ByteBuffersDirectory, Directory, IoContext},
+            metrics::{MemoryMetricsRecorder, FLUSH_COUNT, FLUSH_DOCS, INGEST_BATCH_LATENCY_SECONDS},
+            search::{BasicSortField, Sort},
+            util::{BitSet, FixedBitSet},
+            Id, LuceneError, LATEST,
+        },
+        futures_util::{stream, StreamExt},
+        pretty_assertions::assert_eq,
+        std::{
+            cell::Cell,
+            collections::{HashMap, HashSet},
+            sync::Arc,
+        },
+        tokio::io::{AsyncReadExt, AsyncWriteExt},
+    };
+
+    fn assert_lucene_error(err: &crate::BoxError, f: impl Fn(&LuceneError) -> bool) {
+        assert!(err.downcast_ref::<LuceneError>().map(f).unwrap_or(false), "unexpected error: {err:?}");
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_two_writers_cannot_share_a_directory() {
+        let dir = ByteBuffersDirectory::new();
+        let mut w1 = IndexWriter::new(Box::new(dir.clone()), IndexWriterConfig::new()).await.unwrap();
+        w1.ensure_open().unwrap();
+
+        let err = IndexWriter::new(Box::new(dir.clone()), IndexWriterConfig::new()).await.unwrap_err();
+        assert_lucene_error(&err, |e| matches!(e, LuceneError::LockObtainFailed(_)));
+
+        w1.close().await.unwrap();
+        assert_lucene_error(&w1.ensure_open().unwrap_err(), |e| matches!(e, LuceneError::AlreadyClosed(_)));
+
+        let mut w2 = IndexWriter::new(Box::new(dir), IndexWriterConfig::new()).await.unwrap();
+        w2.close().await.unwrap();
+    }
+
+    #[cfg(feature = "tokio-runtime")]
+    #[test_log::test(tokio::test)]
+    async fn test_filesystem_writers_are_exclusive() {
+        use crate::{fs::FilesystemDirectory, io::SimpleFsLockFactory};
+
+        for simple in [false, true] {
+            let path = std::env::temp_dir().join(format!("lucene-writer-{:016x}", rand::random::<u64>()));
+            let mut dir1 = FilesystemDirectory::open_or_create(&path).await.unwrap();
+            let mut dir2 = FilesystemDirectory::open(&path).await.unwrap();
+            if simple {
+                dir1.set_lock_factory(Arc::new(SimpleFsLockFactory::default()));
+                dir2.set_lock_factory(Arc::new(SimpleFsLockFactory::default()));
+            }
+
+            let mut w1 = IndexWriter::new(Box::new(dir1), IndexWriterConfig::new()).await.unwrap();
+            let err = IndexWriter::new(Box::new(dir2), IndexWriterConfig::new()).await.unwrap_err();
+            assert_lucene_error(&err, |e| matches!(e, LuceneError::LockObtainFailed(_)));
+            w1.close().await.unwrap();
+            std::fs::remove_dir_all(&path).unwrap();
+        }
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_add_documents_stream() {
+        let mut config = IndexWriterConfig::new();
+        config.set_ram_buffer_size_mb(0.001).unwrap().set_ingest_batch_size(3).unwrap();
+        let mut writer = IndexWriter::new(Box::new(ByteBuffersDirectory::new()), config).await.unwrap();
+
+        let produced = Cell::new(0);
+        let documents = stream::iter(0..10)
+            .map(|i| {
+                let mut document = Document::new();
+                document.add(Field::text("body", format!("document number {i} about the quick brown fox"), Store::Yes));
+                document
+            })
+            .inspect(|_| produced.set(produced.get() + 1));
+
+        let mut batches = Vec::new();
+        let stats = writer
+            .add_documents_stream(documents, |batch| {
+                // The stream isn't read ahead of indexing.
+                assert_eq!(produced.get(), batch.batch * 3 + batch.docs);
+                batches.push(batch.clone());
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(batches.iter().map(|batch| batch.docs).collect::<Vec<_>>(), vec![3, 3, 3, 1]);
+        assert_eq!(stats.docs, 10);
+        assert_eq!(stats.batches, 4);
+        assert!(stats.flushes > 0);
+        assert_eq!(stats.flushes, batches.iter().map(|batch| batch.flushes).sum::<usize>());
+        assert_eq!(writer.segments().len(), stats.flushes);
+        // Batches aren't timed without a metrics recorder.
+        assert!(batches.iter().all(|batch| batch.elapsed.is_none()));
+        assert_eq!(stats.elapsed, None);
+
+        writer.flush().unwrap();
+        assert_eq!(writer.num_buffered_docs(), 0);
+        assert_eq!(writer.ram_bytes_used(), 0);
+        assert_eq!(writer.reader().unwrap().max_doc(), 10);
+
+        writer.close().await.unwrap();
+        let err = writer.add_documents_stream(stream::empty(), |_| {}).await.unwrap_err();
+        assert_lucene_error(&err, |e| matches!(e, LuceneError::AlreadyClosed(_)));
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_add_documents_stream_metrics() {
+        let metrics = Arc::new(MemoryMetricsRecorder::new());
+        let mut config = IndexWriterConfig::new();
+        config.set_ingest_batch_size(2).unwrap().set_metrics(Some(metrics.clone()));
+        let mut writer = IndexWriter::new(Box::new(ByteBuffersDirectory::new()), config).await.unwrap();
+
+        let documents = stream::iter(0..5).map(|i| {
+            let mut document = Document::new();
+            document.add(Field::text("body", format!("document number {i}"), Store::No));
+            document
+        });
+        let mut batches = Vec::new();
+        let stats = writer.add_documents_stream(documents, |batch| batches.push(batch.clone())).await.unwrap();
+        assert!(batches.iter().all(|batch| batch.elapsed.is_some()));
+        assert_eq!(stats.elapsed, Some(batches.iter().filter_map(|batch| batch.elapsed).sum()));
+        assert_eq!(metrics.histogram(INGEST_BATCH_LATENCY_SECONDS).len(), 3);
+
+        // Segments built from added documents report their flush metrics to the same recorder.
+        writer.flush().unwrap();
+        assert_eq!(metrics.counter(FLUSH_COUNT), 1);
+        assert_eq!(metrics.histogram(FLUSH_DOCS), vec![5.0]);
+        writer.close().await.unwrap();
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_append_requires_existing_index() {
+        let mut config = IndexWriterConfig::new();
+        config.set_open_mode(OpenMode::Append);
+        let err = IndexWriter::new(Box::new(ByteBuffersDirectory::new()), config).await.unwrap_err();
+        assert_lucene_error(&err, |e| matches!(e, LuceneError::IndexNotFound(_)));
+    }
+
+    /// Commits an index with a segment of each of the given sizes to `dir`. Each segment has a data file holding its
+    /// name, and the first document of the first segment is deleted.
+    async fn create_source_index(dir: &mut ByteBuffersDirectory, max_docs: &[u32]) {
+        let codec = get_codec("Lucene95").unwrap();
+        let mut segment_index = SegmentIndex::new(LATEST.major()).unwrap();
+        for &max_doc in max_docs {
+            let name = segment_index.new_segment_name();
+            let mut info = SegmentInfo {
+                name: name.clone(),
+                id: Id::random_id(),
+                max_doc,
+                attributes: HashMap::new(),
+                diagnostics: HashMap::new(),
+                files: HashSet::from([format!("{name}.si"), format!("{name}.dat")]),
+                version: LATEST,
+                min_version: Some(LATEST),
+                is_compound_file: false,
+                index_sort: None,
+                codec: None,
+            };
+            info.set_codec_name("Lucene95");
+            codec.segment_info_format().write_segment_info(dir, &info, &IoContext::Default).await.unwrap();
+
+            let mut w = dir.create(&format!("{name}.dat"), &IoContext::Default).await.unwrap();
+            w.write_all(name.as_bytes()).await.unwrap();
+            w.shutdown().await.unwrap();
+            segment_index.add_segment(SegmentCommitInfo::new(info, 0, 0, None, None, None, None)).unwrap();
+        }
+
+        let segment = &mut segment_index.get_segments_mut()[0];
+        let mut live_docs = FixedBitSet::new(max_docs[0]);
+        live_docs.set_range(1, max_docs[0]);
+        codec.live_docs_format().write_live_docs(&live_docs, dir, segment, 1, &IoContext::Default).await.unwrap();
+        segment.advance_del_gen();
+        segment.set_del_count(1).unwrap();
+        segment_index.commit(dir).await.unwrap();
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_add_indexes() {
+        let mut source1 = ByteBuffersDirectory::new();
+        create_source_index(&mut source1, &[5, 3]).await;
+        let mut source2 = ByteBuffersDirectory::new();
+        create_source_index(&mut source2, &[4]).await;
+
+        let mut dir = ByteBuffersDirectory::new();
+        let mut writer = IndexWriter::new(Box::new(dir.clone()), IndexWriterConfig::new()).await.unwrap();
+
+        // The source indexes can't be changed while they're copied.
+        let mut source_writer = IndexWriter::new(Box::new(source1.clone()), IndexWriterConfig::new()).await.unwrap();
+        let err = writer.add_indexes(&mut [source1.clone()]).await.unwrap_err();
+        assert_lucene_error(&err, |e| matches!(e, LuceneError::LockObtainFailed(_)));
+        source_writer.close().await.unwrap();
+
+        writer.add_indexes(&mut [source1.clone(), source2.clone()]).await.unwrap();
+        assert_eq!(writer.max_doc(), 12);
+        assert_eq!(writer.commit().await.unwrap(), "segments_1");
+        writer.close().await.unwrap();
+
+        let segment_index = SegmentIndex::open(&mut dir).await.unwrap();
+        let segments: Vec<_> = segment_index
+            .get_segments()
+            .iter()
+            .map(|s| (s.get_segment_info().get_name(), s.get_segment_info().get_max_doc(), s.get_del_count()))
+            .collect();
+        assert_eq!(segments, vec![("_0", 5, 1), ("_1", 3, 0), ("_2", 4, 1)]);
+        assert_eq!(
+            segment_index.get_segments()[2].get_segment_info().get_files(),
+            &HashSet::from(["_2.si".to_string(), "_2.dat".to_string()])
+        );
+
+        // Data files are copied byte for byte, and deletes are kept.
+        let mut data = String::new();
+        dir.open("_2.dat", &IoContext::Read).await.unwrap().read_to_string(&mut data).await.unwrap();
+        assert_eq!(data, "_0");
+        let codec = get_codec("Lucene95").unwrap();
+        let live_docs =
+            codec.live_docs_format().read_live_docs(&mut dir, &segment_index.get_segments()[2], &IoContext::Read).await;
+        assert_eq!(live_docs.unwrap().cardinality(), 3);
+
+        // Reopening the index keeps its segments, unless it's recreated.
+        let mut config = IndexWriterConfig::new();
+        config.set_open_mode(OpenMode::Append);
+        let mut writer = IndexWriter::new(Box::new(dir.clone()), config).await.unwrap();
+        assert_eq!(writer.segment_index().get_segments().len(), 3);
+        writer.close().await.unwrap();
+
+        let mut config = IndexWriterConfig::new();
+        config.set_open_mode(OpenMode::Create);
+        let mut writer = IndexWriter::new(Box::new(dir.clone()), config).await.unwrap();
+        assert!(writer.segment_index().get_segments().is_empty());
+        assert_eq!(writer.commit().await.unwrap(), "segments_2");
+        writer.close().await.unwrap();
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_live_commit_data() {
+        let mut source = ByteBuffersDirectory::new();
+        create_source_index(&mut source, &[5, 3]).await;
+        let source_data = |source: &str| HashMap::from([("source".to_string(), source.to_string())]);
+
+        let mut dir = ByteBuffersDirectory::new();
+        let mut schema = Schema::new();
+        schema.set_allow_unknown_fields(true);
+        let mut config = IndexWriterConfig::new();
+        config.set_schema(Some(Arc::new(schema)));
+        let mut writer = IndexWriter::new(Box::new(dir.clone()), config.clone()).await.unwrap();
+        assert!(writer.reader().unwrap().index_commit().is_none());
+        writer.add_indexes(&mut [source.clone()]).await.unwrap();
+        writer.set_live_commit_data(source_data("first"));
+        writer.commit().await.unwrap();
+        writer.set_live_commit_data(source_data("second"));
+
+        // Data set after a commit isn't visible until the next one, and the schema is kept.
+        let reader = writer.reader().unwrap();
+        let commit = reader.index_commit().unwrap();
+        assert_eq!((commit.get_segments_file_name(), commit.get_generation()), ("segments_1", 1));
+        assert_eq!(commit.get_user_data()["source"], "first");
+        assert!(commit.get_user_data().contains_key(SCHEMA_USER_DATA_KEY));
+        assert_eq!(&IndexCommit::read_latest(&mut dir).await.unwrap(), commit);
+        assert_eq!(SegmentIndex::open(&mut dir).await.unwrap().get_segments().len(), 2);
+
+        // Added documents can't be committed while they are held in memory, so neither can the data.
+        let mut document = Document::new();
+        document.add(Field::text("body", "quick fox", Store::Yes));
+        writer.add_document(&document).unwrap();
+        let err = writer.commit().await.unwrap_err();
+        assert_lucene_error(&err, |e| matches!(e, LuceneError::IllegalState(_)));
+        writer.flush().unwrap();
+        let err = writer.commit().await.unwrap_err();
+        assert_lucene_error(&err, |e| matches!(e, LuceneError::IllegalState(_)));
+        assert_eq!(IndexCommit::read_latest(&mut dir).await.unwrap().get_user_data()["source"], "first");
+        writer.close().await.unwrap();
+
+        // A reopened writer starts from the data of the latest commit, and commits it with the added segments.
+        let mut writer = IndexWriter::new(Box::new(dir.clone()), config).await.unwrap();
+        assert_eq!(writer.get_live_commit_data()["source"], "first");
+        assert_eq!(writer.reader().unwrap().index_commit().unwrap().get_user_data()["source"], "first");
+        writer.add_indexes(&mut [source]).await.unwrap();
+        writer.set_live_commit_data(source_data("second"));
+        assert_eq!(writer.commit().await.unwrap(), "segments_2");
+        assert_eq!(writer.reader().unwrap().index_commit().unwrap().get_user_data()["source"], "second");
+        assert_eq!(SegmentIndex::open(&mut dir).await.unwrap().get_segments().len(), 4);
+        writer.close().await.unwrap();
+
+        let err = IndexCommit::read_latest(&mut ByteBuffersDirectory::new()).await.unwrap_err();
+        assert_lucene_error(&err, |e| matches!(e, LuceneError::IndexNotFound(_)));
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_add_indexes_from_readers() {
+        let mut builder = MemorySegmentBuilder::new(Arc::new(SimpleAnalyzer));
+        for (id, rank) in [("a", 30), ("b", 10), ("c", 20)] {
+            let mut doc = Document::new();
+            doc.add(Field::string("id", id, Store::Yes));
+            doc.add(Field::text("body", format!("document {id}"), Store::No));
+            doc.add(Field::numeric_doc_values("rank", rank));
+            builder.add_document(&doc).unwrap();
+        }
+        let mut live_docs = FixedBitSet::new(3);
+        live_docs.set_range(0, 3);
+        live_docs.clear(2);
+        let segment = SegmentReader::new(Arc::new(builder.build()), Some(live_docs)).unwrap();
+        let sort = Sort::from_fields(vec![Box::new(BasicSortField::for_i64_field("rank", None))]).unwrap();
+        let sorted: Arc<dyn LeafReader> = Arc::new(SortingCodecReader::wrap(Arc::new(segment), sort).unwrap());
+
+        let mut writer =
+            IndexWriter::new(Box::new(ByteBuffersDirectory::new()), IndexWriterConfig::new()).await.unwrap();
+        assert_eq!(writer.add_indexes_from_readers(&[sorted.clone(), sorted]).unwrap(), 4);
+
+        // The deleted document is dropped, and the others keep their sorted order.
+        let reader = writer.reader().unwrap();
+        assert_eq!(reader.max_doc(), 4);
+        let segment = reader.leaves()[0].reader();
+        let ids: Vec<String> =
+            (0..4).map(|doc| segment.document(doc).unwrap().get("id").unwrap().to_string()).collect();
+        assert_eq!(ids, vec!["b", "a", "b", "a"]);
+        let body = segment.terms("body").unwrap().unwrap();
+        assert_eq!(body.doc_count(), 4);
+        let mut te = body.iterator().unwrap();
+        assert!(te.seek_exact(b"a").unwrap());
+        assert_eq!(te.doc_freq().unwrap(), 2);
+        let mut ranks = segment.numeric_doc_values("rank").unwrap().unwrap();
+        assert!(ranks.advance_exact(3).unwrap());
+        assert_eq!(ranks.long_value().unwrap(), 30);
+        assert_eq!(segment.norms("body").unwrap().unwrap().len(), 4);
+
+        writer.close().await.unwrap();
+        assert!(writer.add_indexes_from_readers(&[]).is_err());
+    }
+}
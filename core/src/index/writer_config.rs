@@ -0,0 +1,294 @@
+use {
+    crate::{
+        analysis::{Analyzer, SimpleAnalyzer},
+        index::{FlushByRamOrCountsPolicy, FlushPolicy, IndexWriterEventListener, Schema, SegmentWarmer, TermsFormat},
+        metrics::MetricsRecorder,
+        search::{BM25Similarity, Similarity},
+        BoxResult, LuceneError,
+    },
+    std::{collections::HashMap, sync::Arc},
+};
+
+/// The default size of the RAM buffer, in megabytes: buffered documents are flushed to a segment once they use more.
+pub const DEFAULT_RAM_BUFFER_SIZE_MB: f64 = 16.0;
+
+/// The default number of documents [IndexWriter::add_documents_stream](crate::index::IndexWriter) takes from a
+/// stream at a time.
+pub const DEFAULT_INGEST_BATCH_SIZE: usize = 1000;
+
+/// Specifies how an [IndexWriter](crate::index::IndexWriter) opens an index.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum OpenMode {
+    /// Creates a new index, or overwrites an existing one.
+    Create,
+
+    /// Opens an existing index. It is an error if the directory does not contain an index.
+    Append,
+
+    /// Opens an existing index if there is one, otherwise creates a new index.
+    #[default]
+    CreateOrAppend,
+}
+
+/// Holds the configuration used to create an [IndexWriter](crate::index::IndexWriter).
+#[derive(Clone, Debug)]
+pub struct IndexWriterConfig {
+    open_mode: OpenMode,
+    analyzer: Arc<dyn Analyzer>,
+    similarity: Arc<dyn Similarity>,
+    ram_buffer_size_mb: f64,
+    max_buffered_docs: Option<u32>,
+    flush_policy: Arc<dyn FlushPolicy>,
+    ingest_batch_size: usize,
+    terms_formats: HashMap<String, TermsFormat>,
+    seed_merged_vector_graphs: bool,
+    merge_timing: bool,
+    segment_warmer: Option<Arc<dyn SegmentWarmer>>,
+    event_listener: Option<Arc<dyn IndexWriterEventListener>>,
+    metrics: Option<Arc<dyn MetricsRecorder>>,
+    schema: Option<Arc<Schema>>,
+}
+
+impl Default for IndexWriterConfig {
+    fn default() -> Self {
+        Self {
+            open_mode: OpenMode::default(),
+            analyzer: Arc::new(SimpleAnalyzer),
+            similarity: Arc::new(BM25Similarity::default()),
+            ram_buffer_size_mb: DEFAULT_RAM_BUFFER_SIZE_MB,
+            max_buffered_docs: None,
+            flush_policy: Arc::new(FlushByRamOrCountsPolicy),
+            ingest_batch_size: DEFAULT_INGEST_BATCH_SIZE,
+            terms_formats: HashMap::new(),
+            seed_merged_vector_graphs: true,
+            merge_timing: false,
+            segment_warmer: None,
+            event_listener: None,
+            metrics: None,
+            schema: None,
+        }
+    }
+}
+
+impl IndexWriterConfig {
+    /// Creates a new configuration with default settings.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns how the index will be opened.
+    #[inline]
+    pub fn open_mode(&self) -> OpenMode {
+        self.open_mode
+    }
+
+    /// Sets how the index will be opened.
+    pub fn set_open_mode(&mut self, open_mode: OpenMode) -> &mut Self {
+        self.open_mode = open_mode;
+        self
+    }
+
+    /// Returns the analyzer that tokenizes the text of added documents.
+    #[inline]
+    pub fn analyzer(&self) -> &Arc<dyn Analyzer> {
+        &self.analyzer
+    }
+
+    /// Sets the analyzer that tokenizes the text of added documents. Defaults to [SimpleAnalyzer].
+    pub fn set_analyzer(&mut self, analyzer: Arc<dyn Analyzer>) -> &mut Self {
+        self.analyzer = analyzer;
+        self
+    }
+
+    /// Returns the analyzer documents are indexed with: the configured analyzer, except for the fields that the schema
+    /// gives their own.
+    pub(crate) fn indexing_analyzer(&self) -> Arc<dyn Analyzer> {
+        match &self.schema {
+            Some(schema) => schema.wrap_analyzer(self.analyzer.clone()),
+            None => self.analyzer.clone(),
+        }
+    }
+
+    /// Returns the size of the RAM buffer, in megabytes.
+    #[inline]
+    pub fn ram_buffer_size_mb(&self) -> f64 {
+        self.ram_buffer_size_mb
+    }
+
+    /// Sets the size of the RAM buffer, in megabytes. With the default flush policy, buffered documents are flushed
+    /// to a new segment once they use more memory than this. Defaults to [DEFAULT_RAM_BUFFER_SIZE_MB].
+    pub fn set_ram_buffer_size_mb(&mut self, ram_buffer_size_mb: f64) -> BoxResult<&mut Self> {
+        if ram_buffer_size_mb.is_nan() || ram_buffer_size_mb <= 0.0 {
+            return Err(LuceneError::InvalidArgument(format!(
+                "RAM buffer size must be positive, got {ram_buffer_size_mb}"
+            ))
+            .into());
+        }
+
+        self.ram_buffer_size_mb = ram_buffer_size_mb;
+        Ok(self)
+    }
+
+    /// Returns the size of the RAM buffer in bytes.
+    #[inline]
+    pub fn ram_buffer_size_bytes(&self) -> usize {
+        (self.ram_buffer_size_mb * 1024.0 * 1024.0) as usize
+    }
+
+    /// Returns the most documents a segment buffers before it's flushed, if there is a limit.
+    #[inline]
+    pub fn max_buffered_docs(&self) -> Option<u32> {
+        self.max_buffered_docs
+    }
+
+    /// Sets the most documents a segment buffers before it's flushed with the default flush policy, in addition to
+    /// the RAM buffer size. Defaults to no limit. This fails with [LuceneError::InvalidArgument] if `max_buffered_docs`
+    /// is zero.
+    pub fn set_max_buffered_docs(&mut self, max_buffered_docs: Option<u32>) -> BoxResult<&mut Self> {
+        if max_buffered_docs == Some(0) {
+            return Err(LuceneError::InvalidArgument("max buffered docs must be positive".to_string()).into());
+        }
+
+        self.max_buffered_docs = max_buffered_docs;
+        Ok(self)
+    }
+
+    /// Returns the policy deciding when buffered documents are flushed.
+    #[inline]
+    pub fn flush_policy(&self) -> &Arc<dyn FlushPolicy> {
+        &self.flush_policy
+    }
+
+    /// Sets the policy deciding when buffered documents are flushed. Defaults to [FlushByRamOrCountsPolicy].
+    pub fn set_flush_policy(&mut self, flush_policy: Arc<dyn FlushPolicy>) -> &mut Self {
+        self.flush_policy = flush_policy;
+        self
+    }
+
+    /// Returns the number of documents taken from a stream at a time when ingesting a stream.
+    #[inline]
+    pub fn ingest_batch_size(&self) -> usize {
+        self.ingest_batch_size
+    }
+
+    /// Sets the number of documents taken from a stream at a time when ingesting a stream; statistics are reported
+    /// for each batch. Defaults to [DEFAULT_INGEST_BATCH_SIZE].
+    pub fn set_ingest_batch_size(&mut self, ingest_batch_size: usize) -> BoxResult<&mut Self> {
+        if ingest_batch_size == 0 {
+            return Err(LuceneError::InvalidArgument("ingest batch size must be positive".to_string()).into());
+        }
+
+        self.ingest_batch_size = ingest_batch_size;
+        Ok(self)
+    }
+
+    /// Returns the similarity that computes norms when documents are indexed.
+    #[inline]
+    pub fn similarity(&self) -> &Arc<dyn Similarity> {
+        &self.similarity
+    }
+
+    /// Sets the similarity that computes norms when documents are indexed. This should be the similarity that will
+    /// score searches (see [crate::search::IndexSearcher::set_similarity]), or one that computes the same norms.
+    /// Defaults to [BM25Similarity].
+    pub fn set_similarity(&mut self, similarity: Arc<dyn Similarity>) -> &mut Self {
+        self.similarity = similarity;
+        self
+    }
+
+    /// Returns the terms formats set with [IndexWriterConfig::set_terms_format], by field.
+    #[inline]
+    pub fn terms_formats(&self) -> &HashMap<String, TermsFormat> {
+        &self.terms_formats
+    }
+
+    /// Sets how the terms of `field` are held in the segments written from now on. Defaults to
+    /// [TermsFormat::Default].
+    pub fn set_terms_format(&mut self, field: impl Into<String>, format: TermsFormat) -> &mut Self {
+        self.terms_formats.insert(field.into(), format);
+        self
+    }
+
+    /// Returns the warmer that new segments are warmed with before they're published, if any.
+    #[inline]
+    pub fn segment_warmer(&self) -> Option<&Arc<dyn SegmentWarmer>> {
+        self.segment_warmer.as_ref()
+    }
+
+    /// Sets the warmer that flushed, merged and added segments are warmed with before they become visible to
+    /// readers, such as a [crate::index::QueryWarmer]. Defaults to none.
+    pub fn set_segment_warmer(&mut self, warmer: Option<Arc<dyn SegmentWarmer>>) -> &mut Self {
+        self.segment_warmer = warmer;
+        self
+    }
+
+    /// Indicates whether merges start the HNSW graph of each vector field from that of the largest merged segment.
+    #[inline]
+    pub fn seed_merged_vector_graphs(&self) -> bool {
+        self.seed_merged_vector_graphs
+    }
+
+    /// Sets whether merges start the HNSW graph of each vector field from the graph of the largest merged segment
+    /// without deletions, inserting only the vectors of the other segments, rather than building it from scratch.
+    /// This makes merging vectors much cheaper, at the cost of a graph that depends on how the segments were merged.
+    /// Defaults to true. See [crate::index::MemorySegmentBuilder::set_seed_vector_graphs].
+    pub fn set_seed_merged_vector_graphs(&mut self, seed: bool) -> &mut Self {
+        self.seed_merged_vector_graphs = seed;
+        self
+    }
+
+    /// Indicates whether merges measure the time spent on each kind of data.
+    #[inline]
+    pub fn merge_timing(&self) -> bool {
+        self.merge_timing
+    }
+
+    /// Sets whether merges measure the time spent on each kind of data, reported in the [crate::index::MergeStats]
+    /// returned by force merges and given to the event listener. The clock is only read if this is set. Defaults to
+    /// false.
+    pub fn set_merge_timing(&mut self, merge_timing: bool) -> &mut Self {
+        self.merge_timing = merge_timing;
+        self
+    }
+
+    /// Returns the listener told about flushes, merges and commits, if any.
+    #[inline]
+    pub fn event_listener(&self) -> Option<&Arc<dyn IndexWriterEventListener>> {
+        self.event_listener.as_ref()
+    }
+
+    /// Sets the listener told about the segments flushed and merged and the commits written, for observability or
+    /// to trigger downstream work such as replication. Defaults to none.
+    pub fn set_event_listener(&mut self, listener: Option<Arc<dyn IndexWriterEventListener>>) -> &mut Self {
+        self.event_listener = listener;
+        self
+    }
+
+    /// Returns the recorder that indexing metrics are reported to, if any.
+    #[inline]
+    pub fn metrics(&self) -> Option<&Arc<dyn MetricsRecorder>> {
+        self.metrics.as_ref()
+    }
+
+    /// Sets the recorder that indexing metrics are reported to: the flush metrics of the segments built from added
+    /// documents (see [crate::index::MemorySegmentBuilder::set_metrics]) and the latency of the batches of
+    /// [IndexWriter::add_documents_stream](crate::index::IndexWriter::add_documents_stream). These are only timed
+    /// if a recorder is set. Defaults to none.
+    pub fn set_metrics(&mut self, metrics: Option<Arc<dyn MetricsRecorder>>) -> &mut Self {
+        self.metrics = metrics;
+        self
+    }
+
+    /// Returns the schema that added documents are validated against, if any.
+    #[inline]
+    pub fn schema(&self) -> Option<&Arc<Schema>> {
+        self.schema.as_ref()
+    }
+
+    /// Sets the schema that added documents are validated against, which is persisted in the commit user data and
+    /// checked against the persisted one when the index is opened again. Defaults to none.
+    pub fn set_schema(&mut self, schema: Option<Arc<Schema>>) -> &mut Self {
+        self.schema = schema;
+        self
+    }
+}
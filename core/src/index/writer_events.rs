@@ -0,0 +1,170 @@
+use {
+    crate::index::{LeafReader, MergeStats},
+    std::{fmt::Debug, sync::Arc, time::Duration},
+};
+
+/// A segment flushed from the documents buffered by a writer.
+#[derive(Debug)]
+pub struct FlushEvent<'a> {
+    /// The flushed segment, which has been published by the time listeners are told.
+    pub segment: &'a Arc<dyn LeafReader>,
+
+    /// The number of documents in the segment.
+    pub num_docs: u32,
+
+    /// The memory used by the segment, in bytes.
+    pub ram_bytes_used: usize,
+
+    /// The time spent building the segment.
+    pub elapsed: Duration,
+}
+
+/// A merge about to read its segments.
+#[derive(Debug)]
+pub struct MergeStartEvent<'a> {
+    /// The adjacent segments being merged.
+    pub segments: &'a [Arc<dyn LeafReader>],
+
+    /// The number of live documents in the segments, which the merged segment will hold.
+    pub num_docs: u64,
+
+    /// The memory used by the segments, in bytes.
+    pub ram_bytes_used: usize,
+}
+
+/// A merge whose segment has replaced the merged ones.
+#[derive(Debug)]
+pub struct MergeFinishEvent<'a> {
+    /// The segments that were merged, which are no longer part of the index.
+    pub segments: &'a [Arc<dyn LeafReader>],
+
+    /// The merged segment, or `None` if the segments had no live documents and were dropped.
+    pub merged: Option<&'a Arc<dyn LeafReader>>,
+
+    /// The memory used by the merged segments, in bytes.
+    pub input_ram_bytes_used: usize,
+
+    /// The memory used by the merged segment, in bytes.
+    pub ram_bytes_used: usize,
+
+    /// The statistics of this merge alone.
+    pub stats: &'a MergeStats,
+
+    /// The time spent on the merge, from reading the segments to publishing the merged one.
+    pub elapsed: Duration,
+}
+
+/// A commit that has been written and synced.
+#[derive(Debug)]
+pub struct CommitEvent<'a> {
+    /// The name of the `segments_N` file written.
+    pub segments_file_name: &'a str,
+
+    /// The generation of the commit.
+    pub generation: u64,
+
+    /// The number of segments committed.
+    pub num_segments: usize,
+}
+
+/// Receives the flushes, merges and commits of a writer as they happen, as Lucene's `IndexWriterEventListener` and
+/// `MergeScheduler` hooks do, so that applications can record metrics or start downstream work, such as replicating
+/// a commit, without polling.
+///
+/// The listener set with [crate::index::IndexWriterConfig::set_event_listener] is called on the thread that flushed,
+/// merged or committed, after the change is visible, so it should hand slow work off to another thread. Every method
+/// does nothing by default. A merge that fails is started but never finished.
+pub trait IndexWriterEventListener: Debug + Send + Sync {
+    /// Called once a segment has been flushed and published.
+    fn on_flush(&self, _event: &FlushEvent<'_>) {}
+
+    /// Called before a merge reads its segments.
+    fn on_merge_start(&self, _event: &MergeStartEvent<'_>) {}
+
+    /// Called once a merged segment has replaced the segments it was merged from.
+    fn on_merge_finish(&self, _event: &MergeFinishEvent<'_>) {}
+
+    /// Called once a commit has been written.
+    fn on_commit(&self, _event: &CommitEvent<'_>) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{
+            document::{Document, Field, Store},
+            index::{
+                CommitEvent, FlushEvent, IndexWriter, IndexWriterConfig, IndexWriterEventListener, MergeFinishEvent,
+                MergeStartEvent,
+            },
+            io::ByteBuffersDirectory,
+        },
+        pretty_assertions::assert_eq,
+        std::sync::{Arc, Mutex},
+    };
+
+    /// Records the events it receives as strings.
+    #[derive(Debug, Default)]
+    struct RecordingListener {
+        events: Mutex<Vec<String>>,
+    }
+
+    impl IndexWriterEventListener for RecordingListener {
+        fn on_flush(&self, event: &FlushEvent<'_>) {
+            assert_eq!(event.segment.max_doc(), event.num_docs);
+            assert!(event.ram_bytes_used > 0);
+            self.events.lock().unwrap().push(format!("flush {}", event.num_docs));
+        }
+
+        fn on_merge_start(&self, event: &MergeStartEvent<'_>) {
+            assert!(event.ram_bytes_used > 0);
+            self.events.lock().unwrap().push(format!("merge start {} {}", event.segments.len(), event.num_docs));
+        }
+
+        fn on_merge_finish(&self, event: &MergeFinishEvent<'_>) {
+            assert_eq!(event.stats.merges, 1);
+            assert_eq!(event.stats.docs, event.merged.map_or(0, |merged| merged.max_doc() as u64));
+            assert!(event.input_ram_bytes_used > 0 && event.ram_bytes_used > 0);
+            self.events.lock().unwrap().push(format!("merge finish {} {}", event.segments.len(), event.stats.docs));
+        }
+
+        fn on_commit(&self, event: &CommitEvent<'_>) {
+            self.events
+                .lock()
+                .unwrap()
+                .push(format!("commit {} {} {}", event.segments_file_name, event.generation, event.num_segments));
+        }
+    }
+
+    fn document() -> Document {
+        let mut document = Document::new();
+        document.add(Field::text("body", "quick fox", Store::Yes));
+        document
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_events() {
+        let listener = Arc::new(RecordingListener::default());
+        let mut config = IndexWriterConfig::new();
+        config.set_event_listener(Some(listener.clone()));
+        let mut writer = IndexWriter::new(Box::new(ByteBuffersDirectory::new()), config).await.unwrap();
+        assert_eq!(writer.commit().await.unwrap(), "segments_1");
+
+        for i in 0..5 {
+            writer.add_document(&document()).unwrap();
+            if i % 2 == 0 {
+                writer.flush().unwrap();
+            }
+        }
+        writer.flush().unwrap();
+        writer.force_merge(1).unwrap();
+        assert!(writer.commit().await.is_err());
+        writer.close().await.unwrap();
+
+        // Flushing with nothing buffered doesn't produce a segment, and in-memory segments can't be committed.
+        assert_eq!(
+            *listener.events.lock().unwrap(),
+            vec!["commit segments_1 1 0", "flush 1", "flush 2", "flush 2", "merge start 3 5", "merge finish 3 5"]
+        );
+    }
+}
@@ -1,9 +1,36 @@
 use tokio::io::{AsyncRead, AsyncWrite};
 
+mod byte_buffers_directory;
+mod counting_input;
 mod crc32_reader;
+mod crc32_writer;
 mod directory;
 mod encoding;
-pub use {crc32_reader::*, directory::*, encoding::*};
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+mod fetch_range_source;
+#[cfg(feature = "http")]
+mod http_range_source;
+mod io_context;
+mod lock;
+mod random_access_input;
+mod range_directory;
+mod rate_limited_directory;
+mod rate_limiter;
+mod runtime;
+#[cfg(test)]
+pub(crate) mod test_util;
+
+pub use {
+    byte_buffers_directory::*, counting_input::*, crc32_reader::*, crc32_writer::*, directory::*, encoding::*,
+    io_context::*, lock::*, random_access_input::*, range_directory::*, rate_limited_directory::*, rate_limiter::*,
+    runtime::*,
+};
+
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+pub use fetch_range_source::*;
+
+#[cfg(feature = "http")]
+pub use http_range_source::*;
 
 /// Type alias for [AsyncRead] types that can also be [Unpin]ned.
 pub trait AsyncReadUnpin: AsyncRead + Unpin {}
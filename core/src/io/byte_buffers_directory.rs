@@ -0,0 +1,359 @@
+use {
+    crate::{
+        io::{Directory, IoContext, Lock, LockFactory, SingleInstanceLockFactory},
+        BoxResult,
+    },
+    async_trait::async_trait,
+    std::{
+        collections::BTreeMap,
+        io::{Error as IoError, ErrorKind as IoErrorKind, Result as IoResult},
+        path::Path,
+        pin::Pin,
+        sync::{Arc, Mutex, MutexGuard},
+        task::{Context, Poll},
+    },
+    tokio::io::{AsyncRead, AsyncWrite, ReadBuf},
+};
+
+/// The size of the first block allocated for a file.
+pub const DEFAULT_MIN_BLOCK_SIZE: usize = 1 << 10;
+
+/// The maximum size of a block allocated for a file. Blocks double in size until they reach this size.
+pub const DEFAULT_MAX_BLOCK_SIZE: usize = 1 << 20;
+
+/// The contents of a file that has been completely written.
+#[derive(Debug, Default)]
+struct ByteBuffersFile {
+    blocks: Vec<Vec<u8>>,
+    length: u64,
+}
+
+/// The state of a file in a [ByteBuffersDirectory].
+#[derive(Debug)]
+enum FileEntry {
+    /// The file is still open for writing. Its contents are not visible yet.
+    Writing,
+
+    /// The file has been completely written.
+    Complete(Arc<ByteBuffersFile>),
+}
+
+type FileMap = Arc<Mutex<BTreeMap<String, FileEntry>>>;
+
+/// A heap-backed Lucene directory that stores each file as a list of growable byte buffers.
+///
+/// This is intended for unit tests and for small, transient indexes. Nothing is persisted and there is no
+/// equivalent of `fsync`; the contents are lost when the last clone of the directory is dropped.
+///
+/// As with Lucene's `ByteBuffersDirectory`, a file becomes readable only after the writer returned by
+/// [Directory::create] has been shut down (or dropped). Opening a file that is still being written is an error.
+///
+/// Cloning a `ByteBuffersDirectory` is cheap; all clones share the same underlying files and locks. Locks are
+/// obtained from a [SingleInstanceLockFactory], so they are exclusive across all clones of the directory.
+#[derive(Clone, Debug, Default)]
+pub struct ByteBuffersDirectory {
+    files: FileMap,
+    lock_factory: SingleInstanceLockFactory,
+}
+
+impl ByteBuffersDirectory {
+    /// Create a new, empty directory.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the length of the given file in bytes.
+    pub fn file_length(&self, file_name: &str) -> IoResult<u64> {
+        match lock_files(&self.files).get(file_name) {
+            Some(FileEntry::Complete(file)) => Ok(file.length),
+            Some(FileEntry::Writing) => Err(still_writing(file_name)),
+            None => Err(not_found(file_name)),
+        }
+    }
+
+    /// Returns the total number of bytes used by all completed files in the directory.
+    pub fn ram_bytes_used(&self) -> u64 {
+        lock_files(&self.files)
+            .values()
+            .map(|entry| match entry {
+                FileEntry::Complete(file) => file.blocks.iter().map(|b| b.capacity() as u64).sum(),
+                FileEntry::Writing => 0,
+            })
+            .sum()
+    }
+}
+
+#[async_trait(?Send)]
+impl Directory for ByteBuffersDirectory {
+    async fn read_dir(&self) -> IoResult<Vec<String>> {
+        Ok(lock_files(&self.files).keys().cloned().collect())
+    }
+
+    async fn create(&mut self, file_name: &str, _context: &IoContext) -> IoResult<Pin<Box<dyn AsyncWrite + Send>>> {
+        lock_files(&self.files).insert(file_name.to_string(), FileEntry::Writing);
+        Ok(Box::pin(ByteBuffersWriter::new(self.files.clone(), file_name)))
+    }
+
+    async fn open(&mut self, file_name: &str, _context: &IoContext) -> IoResult<Pin<Box<dyn AsyncRead>>> {
+        match lock_files(&self.files).get(file_name) {
+            Some(FileEntry::Complete(file)) => Ok(Box::pin(ByteBuffersReader::new(file.clone()))),
+            Some(FileEntry::Writing) => Err(still_writing(file_name)),
+            None => Err(not_found(file_name)),
+        }
+    }
+
+    async fn remove(&mut self, file_name: &str) -> IoResult<()> {
+        match lock_files(&self.files).remove(file_name) {
+            Some(_) => Ok(()),
+            None => Err(not_found(file_name)),
+        }
+    }
+
+    async fn rename(&mut self, old_file_name: &str, new_file_name: &str) -> IoResult<()> {
+        let mut files = lock_files(&self.files);
+        match files.remove(old_file_name) {
+            Some(entry) => {
+                files.insert(new_file_name.to_string(), entry);
+                Ok(())
+            }
+            None => Err(not_found(old_file_name)),
+        }
+    }
+
+    // Nothing is durable in memory.
+    async fn sync(&mut self, _file_names: &[&str]) -> IoResult<()> {
+        Ok(())
+    }
+
+    async fn sync_meta_data(&mut self) -> IoResult<()> {
+        Ok(())
+    }
+
+    async fn obtain_lock(&mut self, lock_name: &str) -> BoxResult<Box<dyn Lock>> {
+        self.lock_factory.obtain_lock(Path::new(""), lock_name)
+    }
+}
+
+/// Writes a file into a [ByteBuffersDirectory]. The contents are published when the writer is shut down or dropped.
+#[derive(Debug)]
+struct ByteBuffersWriter {
+    files: FileMap,
+    file_name: String,
+    blocks: Vec<Vec<u8>>,
+    length: u64,
+    published: bool,
+}
+
+impl ByteBuffersWriter {
+    fn new(files: FileMap, file_name: &str) -> Self {
+        Self {
+            files,
+            file_name: file_name.to_string(),
+            blocks: Vec::new(),
+            length: 0,
+            published: false,
+        }
+    }
+
+    fn append(&mut self, mut buf: &[u8]) {
+        while !buf.is_empty() {
+            let needs_block = match self.blocks.last() {
+                None => true,
+                Some(block) => block.len() == block.capacity(),
+            };
+
+            if needs_block {
+                let next_size = match self.blocks.last() {
+                    None => DEFAULT_MIN_BLOCK_SIZE,
+                    Some(block) => (block.capacity() * 2).min(DEFAULT_MAX_BLOCK_SIZE),
+                };
+                self.blocks.push(Vec::with_capacity(next_size));
+            }
+
+            // We just guaranteed that there is a block with room in it.
+            let block = self.blocks.last_mut().unwrap();
+            let n = (block.capacity() - block.len()).min(buf.len());
+            block.extend_from_slice(&buf[..n]);
+            buf = &buf[n..];
+            self.length += n as u64;
+        }
+    }
+
+    fn publish(&mut self) {
+        if self.published {
+            return;
+        }
+
+        self.published = true;
+        let file = ByteBuffersFile {
+            blocks: std::mem::take(&mut self.blocks),
+            length: self.length,
+        };
+
+        let mut files = lock_files(&self.files);
+
+        // Only publish if the placeholder is still present; if the file was removed or renamed while we were writing,
+        // the contents are discarded.
+        if let Some(entry @ FileEntry::Writing) = files.get_mut(&self.file_name) {
+            *entry = FileEntry::Complete(Arc::new(file));
+        }
+    }
+}
+
+impl AsyncWrite for ByteBuffersWriter {
+    fn poll_write(self: Pin<&mut Self>, _cx: &mut Context<'_>, buf: &[u8]) -> Poll<IoResult<usize>> {
+        let this = self.get_mut();
+        if this.published {
+            return Poll::Ready(Err(IoError::new(
+                IoErrorKind::BrokenPipe,
+                format!("File {} has already been closed", this.file_name),
+            )));
+        }
+
+        this.append(buf);
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<IoResult<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<IoResult<()>> {
+        self.get_mut().publish();
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl Drop for ByteBuffersWriter {
+    fn drop(&mut self) {
+        self.publish();
+    }
+}
+
+/// Reads a completed file from a [ByteBuffersDirectory].
+#[derive(Debug)]
+struct ByteBuffersReader {
+    file: Arc<ByteBuffersFile>,
+    block: usize,
+    offset: usize,
+}
+
+impl ByteBuffersReader {
+    fn new(file: Arc<ByteBuffersFile>) -> Self {
+        Self {
+            file,
+            block: 0,
+            offset: 0,
+        }
+    }
+}
+
+impl AsyncRead for ByteBuffersReader {
+    fn poll_read(self: Pin<&mut Self>, _cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<IoResult<()>> {
+        let this = self.get_mut();
+
+        while buf.remaining() > 0 && this.block < this.file.blocks.len() {
+            let block = &this.file.blocks[this.block];
+            let n = (block.len() - this.offset).min(buf.remaining());
+            buf.put_slice(&block[this.offset..this.offset + n]);
+            this.offset += n;
+
+            if this.offset == block.len() {
+                this.block += 1;
+                this.offset = 0;
+            }
+        }
+
+        Poll::Ready(Ok(()))
+    }
+}
+
+fn lock_files(files: &FileMap) -> MutexGuard<'_, BTreeMap<String, FileEntry>> {
+    // A poisoned lock only means another thread panicked while holding it; the map itself is always consistent.
+    files.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn not_found(file_name: &str) -> IoError {
+    IoError::new(IoErrorKind::NotFound, format!("File not found: {file_name}"))
+}
+
+fn still_writing(file_name: &str) -> IoError {
+    IoError::new(IoErrorKind::PermissionDenied, format!("File is still open for writing: {file_name}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{
+            codec::CodecHeader,
+            io::{ByteBuffersDirectory, Crc32Reader, Directory, IoContext},
+        },
+        pretty_assertions::assert_eq,
+        std::io::ErrorKind as IoErrorKind,
+        tokio::io::{AsyncReadExt, AsyncWriteExt},
+    };
+
+    #[test_log::test(tokio::test)]
+    async fn test_write_read_large_file() {
+        let mut dir = ByteBuffersDirectory::new();
+        let data: Vec<u8> = (0..5_000_000u32).map(|i| (i % 251) as u8).collect();
+
+        let mut w = dir.create("data.bin", &IoContext::Default).await.unwrap();
+        for chunk in data.chunks(7919) {
+            w.write_all(chunk).await.unwrap();
+        }
+        w.shutdown().await.unwrap();
+
+        assert_eq!(dir.file_length("data.bin").unwrap(), data.len() as u64);
+
+        let mut r = dir.open("data.bin", &IoContext::Read).await.unwrap();
+        let mut read_back = Vec::new();
+        r.read_to_end(&mut read_back).await.unwrap();
+        assert!(read_back == data);
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_unfinished_file_is_not_readable() {
+        let mut dir = ByteBuffersDirectory::new();
+        let mut w = dir.create("_0.si", &IoContext::Default).await.unwrap();
+        w.write_all(b"hello").await.unwrap();
+
+        assert_eq!(dir.read_dir().await.unwrap(), vec!["_0.si".to_string()]);
+        assert_eq!(dir.open("_0.si", &IoContext::Read).await.err().unwrap().kind(), IoErrorKind::PermissionDenied);
+
+        drop(w);
+        let mut contents = String::new();
+        dir.open("_0.si", &IoContext::Read).await.unwrap().read_to_string(&mut contents).await.unwrap();
+        assert_eq!(contents, "hello");
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_rename_remove() {
+        let mut dir = ByteBuffersDirectory::new();
+        let mut other = dir.clone();
+        dir.create("pending_segments_1", &IoContext::Default).await.unwrap().shutdown().await.unwrap();
+        dir.rename("pending_segments_1", "segments_1").await.unwrap();
+        assert_eq!(other.read_dir().await.unwrap(), vec!["segments_1".to_string()]);
+
+        other.remove("segments_1").await.unwrap();
+        assert!(dir.read_dir().await.unwrap().is_empty());
+        assert_eq!(dir.remove("segments_1").await.err().unwrap().kind(), IoErrorKind::NotFound);
+        assert_eq!(dir.open("segments_1", &IoContext::Read).await.err().unwrap().kind(), IoErrorKind::NotFound);
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_checksum_matches_written_data() {
+        let mut dir = ByteBuffersDirectory::new();
+        let mut w = dir.create("header", &IoContext::Default).await.unwrap();
+        CodecHeader::new("test", 1).unwrap().write(&mut w).await.unwrap();
+        w.shutdown().await.unwrap();
+
+        let mut r = Crc32Reader::new(dir.open("header", &IoContext::Read).await.unwrap());
+        let header = CodecHeader::read(&mut r, "test", 0, 1).await.unwrap();
+        assert_eq!(header.version(), 1);
+        assert_eq!(
+            r.digest(),
+            crc32fast::hash(&[0x3f, 0xd7, 0x6c, 0x17, 0x4, 0x74, 0x65, 0x73, 0x74, 0x0, 0x0, 0x0, 0x1])
+        );
+    }
+}
@@ -0,0 +1,84 @@
+use {
+    pin_project::pin_project,
+    std::{
+        io::Result as IoResult,
+        pin::Pin,
+        task::{Context, Poll},
+    },
+    tokio::io::{AsyncRead, ReadBuf},
+};
+
+/// A wrapper around an `AsyncRead` that counts the bytes read through it, like the tracking inputs of Lucene's
+/// `IndexDiskUsageAnalyzer`.
+///
+/// Decoders can measure the bytes a piece of data takes on disk by reading it through a counting input and taking
+/// the count with [CountingInput::take_bytes_read] once it's decoded.
+#[derive(Debug)]
+#[pin_project]
+pub struct CountingInput<R> {
+    #[pin]
+    inner: R,
+    bytes_read: u64,
+}
+
+impl<R> CountingInput<R> {
+    /// Wraps the given reader.
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            bytes_read: 0,
+        }
+    }
+
+    /// Returns the number of bytes read since the input was created or the count was last taken.
+    #[inline]
+    pub fn bytes_read(&self) -> u64 {
+        self.bytes_read
+    }
+
+    /// Returns the number of bytes read since the input was created or the count was last taken, and restarts the
+    /// count from 0.
+    pub fn take_bytes_read(&mut self) -> u64 {
+        std::mem::take(&mut self.bytes_read)
+    }
+
+    /// Returns the wrapped reader.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: AsyncRead> AsyncRead for CountingInput<R> {
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<IoResult<()>> {
+        let this = self.project();
+        let start = buf.filled().len();
+        let result = this.inner.poll_read(cx, buf);
+        if let Poll::Ready(Ok(())) = result {
+            *this.bytes_read += (buf.filled().len() - start) as u64;
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::io::{CountingInput, EncodingReadExt},
+        pretty_assertions::assert_eq,
+        tokio::io::AsyncReadExt,
+    };
+
+    #[test_log::test(tokio::test)]
+    async fn test_counting_input() {
+        let data = [0x81, 0x01, 7, 1, 2, 3, 4, 5];
+        let mut r = CountingInput::new(&data[..]);
+        assert_eq!(r.read_vi32().await.unwrap(), 129);
+        assert_eq!(r.take_bytes_read(), 2);
+        assert_eq!(r.read_u8().await.unwrap(), 7);
+        let mut rest = Vec::new();
+        r.read_to_end(&mut rest).await.unwrap();
+        assert_eq!(r.bytes_read(), 6);
+        assert_eq!(r.take_bytes_read(), 6);
+        assert_eq!(r.bytes_read(), 0);
+    }
+}
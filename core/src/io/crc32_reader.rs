@@ -7,15 +7,20 @@ use {
         pin::Pin,
         task::{Context, Poll},
     },
-    tokio::io::{AsyncRead, ReadBuf},
+    tokio::io::{AsyncRead, BufReader, ReadBuf},
 };
 
-/// A wrapper around an `AsyncRead` that computes the CRC32 of the data read.
+/// A wrapper around an `AsyncRead` that computes the CRC32 of the data read, and counts the bytes read so that
+/// errors can report where corrupt data was found.
+///
+/// Decoders read a few bytes at a time, so a reader over an unbuffered file should be created with
+/// [Crc32Reader::buffered]. This is Lucene's `BufferedChecksumIndexInput`.
 #[pin_project]
 pub struct Crc32Reader<T> {
     #[pin]
     wrapped: T,
     digest: Hasher,
+    position: u64,
 }
 
 impl<T> Crc32Reader<T> {
@@ -24,13 +29,32 @@ impl<T> Crc32Reader<T> {
         Self {
             wrapped,
             digest: Hasher::new(),
+            position: 0,
         }
     }
 
+    /// Returns the wrapped reader.
+    pub fn into_inner(self) -> T {
+        self.wrapped
+    }
+
     /// Returns the CRC32 of the data read so far.
     pub fn digest(&self) -> u32 {
         self.digest.clone().finalize()
     }
+
+    /// Returns the number of bytes read so far.
+    #[inline]
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+}
+
+impl<T: AsyncRead> Crc32Reader<BufReader<T>> {
+    /// Creates a new `Crc32Reader` that reads the given [AsyncRead] through a buffer.
+    pub fn buffered(wrapped: T) -> Self {
+        Self::new(BufReader::new(wrapped))
+    }
 }
 
 impl<T> Clone for Crc32Reader<T>
@@ -41,6 +65,7 @@ where
         Self {
             wrapped: self.wrapped.clone(),
             digest: self.digest.clone(),
+            position: self.position,
         }
     }
 }
@@ -50,7 +75,11 @@ where
     T: Debug,
 {
     fn fmt(&self, f: &mut Formatter) -> FmtResult {
-        f.debug_struct("Crc32Reader").field("wrapped", &self.wrapped).field("digest", &self.digest).finish()
+        f.debug_struct("Crc32Reader")
+            .field("wrapped", &self.wrapped)
+            .field("digest", &self.digest)
+            .field("position", &self.position)
+            .finish()
     }
 }
 
@@ -58,9 +87,13 @@ impl<T: AsyncRead> AsyncRead for Crc32Reader<T> {
     fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<IoResult<()>> {
         let this = self.project();
 
+        // The buffer may already hold data from an earlier read, such as a partial `read_exact`.
+        let start = buf.filled().len();
         match this.wrapped.poll_read(cx, buf) {
             Poll::Ready(Ok(())) => {
-                this.digest.update(buf.filled());
+                let read = &buf.filled()[start..];
+                this.digest.update(read);
+                *this.position += read.len() as u64;
                 Poll::Ready(Ok(()))
             }
             Poll::Ready(Err(e)) => Poll::Ready(Err(e)),
@@ -0,0 +1,84 @@
+use {
+    crc32fast::Hasher,
+    pin_project::pin_project,
+    std::{
+        fmt::{Debug, Formatter, Result as FmtResult},
+        io::Result as IoResult,
+        pin::Pin,
+        task::{Context, Poll},
+    },
+    tokio::io::AsyncWrite,
+};
+
+/// A wrapper around an `AsyncWrite` that computes the CRC32 of the data written, so that a codec footer can be
+/// appended (see [crate::codec::write_footer]).
+#[pin_project]
+pub struct Crc32Writer<T> {
+    #[pin]
+    wrapped: T,
+    digest: Hasher,
+    position: u64,
+}
+
+impl<T> Crc32Writer<T> {
+    /// Creates a new `Crc32Writer` that wraps the given [AsyncWrite].
+    pub fn new(wrapped: T) -> Self {
+        Self {
+            wrapped,
+            digest: Hasher::new(),
+            position: 0,
+        }
+    }
+
+    /// Returns the CRC32 of the data written so far.
+    pub fn digest(&self) -> u32 {
+        self.digest.clone().finalize()
+    }
+
+    /// Returns the number of bytes written so far.
+    #[inline]
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    /// Returns the wrapped writer.
+    pub fn into_inner(self) -> T {
+        self.wrapped
+    }
+}
+
+impl<T> Debug for Crc32Writer<T>
+where
+    T: Debug,
+{
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        f.debug_struct("Crc32Writer")
+            .field("wrapped", &self.wrapped)
+            .field("digest", &self.digest)
+            .field("position", &self.position)
+            .finish()
+    }
+}
+
+impl<T: AsyncWrite> AsyncWrite for Crc32Writer<T> {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<IoResult<usize>> {
+        let this = self.project();
+
+        match this.wrapped.poll_write(cx, buf) {
+            Poll::Ready(Ok(written)) => {
+                this.digest.update(&buf[..written]);
+                *this.position += written as u64;
+                Poll::Ready(Ok(written))
+            }
+            other => other,
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<IoResult<()>> {
+        self.project().wrapped.poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<IoResult<()>> {
+        self.project().wrapped.poll_shutdown(cx)
+    }
+}
@@ -1,4 +1,8 @@
 use {
+    crate::{
+        io::{IoContext, Lock},
+        BoxResult,
+    },
     async_trait::async_trait,
     chrono::{DateTime, Utc},
     std::{fmt::Debug, io::Result as IoResult, pin::Pin, time::SystemTime},
@@ -15,13 +19,14 @@ pub trait Directory: Debug {
     /// Returns a listing of the files in this directory.
     async fn read_dir(&self) -> IoResult<Vec<String>>;
 
-    /// Creates a new file for writing.
+    /// Creates a new file for writing. The `context` describes why the file is being written.
     ///
-    /// If the file already exists, it will be overwritten.
-    async fn create(&mut self, file_name: &str) -> IoResult<Pin<Box<dyn AsyncWrite>>>;
+    /// If the file already exists, it will be overwritten. The writer can be sent to other threads, so that types
+    /// that keep a file open, such as [crate::index::Translog], can be too.
+    async fn create(&mut self, file_name: &str, context: &IoContext) -> IoResult<Pin<Box<dyn AsyncWrite + Send>>>;
 
-    /// Opens an existing file for reading.
-    async fn open(&mut self, file_name: &str) -> IoResult<Pin<Box<dyn AsyncRead>>>;
+    /// Opens an existing file for reading. The `context` describes why the file is being read.
+    async fn open(&mut self, file_name: &str, context: &IoContext) -> IoResult<Pin<Box<dyn AsyncRead>>>;
 
     /// Removes the file with the given name.
     async fn remove(&mut self, file_name: &str) -> IoResult<()>;
@@ -31,6 +36,56 @@ pub trait Directory: Debug {
     /// This is not guaranteed to be atomic. In particular, the [Directory::read_dir] method may return both the old
     /// and new names during the rename.
     async fn rename(&mut self, old_file_name: &str, new_file_name: &str) -> IoResult<()>;
+
+    /// Ensures that the contents of the given files are on stable storage, like Lucene's `Directory.sync`. Files are
+    /// synced before a commit that references them is published, so that a crash can't leave a commit whose files
+    /// are incomplete. Directories that aren't durable, such as in-memory ones, do nothing.
+    async fn sync(&mut self, file_names: &[&str]) -> IoResult<()>;
+
+    /// Ensures that renames and removals of files in this directory are on stable storage, like Lucene's
+    /// `Directory.syncMetaData`. This is called after a commit's `segments_N` file is renamed into place.
+    async fn sync_meta_data(&mut self) -> IoResult<()>;
+
+    /// Obtains the lock with the given name (typically [WRITE_LOCK_NAME](crate::io::WRITE_LOCK_NAME)).
+    ///
+    /// This never blocks. If the lock is held elsewhere, a [LuceneError::LockObtainFailed](crate::LuceneError) error
+    /// is returned.
+    async fn obtain_lock(&mut self, lock_name: &str) -> BoxResult<Box<dyn Lock>>;
+}
+
+#[async_trait(?Send)]
+impl<D: Directory + ?Sized> Directory for Box<D> {
+    async fn read_dir(&self) -> IoResult<Vec<String>> {
+        (**self).read_dir().await
+    }
+
+    async fn create(&mut self, file_name: &str, context: &IoContext) -> IoResult<Pin<Box<dyn AsyncWrite + Send>>> {
+        (**self).create(file_name, context).await
+    }
+
+    async fn open(&mut self, file_name: &str, context: &IoContext) -> IoResult<Pin<Box<dyn AsyncRead>>> {
+        (**self).open(file_name, context).await
+    }
+
+    async fn remove(&mut self, file_name: &str) -> IoResult<()> {
+        (**self).remove(file_name).await
+    }
+
+    async fn rename(&mut self, old_file_name: &str, new_file_name: &str) -> IoResult<()> {
+        (**self).rename(old_file_name, new_file_name).await
+    }
+
+    async fn sync(&mut self, file_names: &[&str]) -> IoResult<()> {
+        (**self).sync(file_names).await
+    }
+
+    async fn sync_meta_data(&mut self) -> IoResult<()> {
+        (**self).sync_meta_data().await
+    }
+
+    async fn obtain_lock(&mut self, lock_name: &str) -> BoxResult<Box<dyn Lock>> {
+        (**self).obtain_lock(lock_name).await
+    }
 }
 
 /// A file timestamp, which can be either a [SystemTime] or [DateTime].
@@ -67,9 +122,7 @@ impl Eq for FileTimestamp {}
 
 impl PartialOrd for FileTimestamp {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        let self_dt: DateTime<Utc> = (*self).into();
-        let other_dt: DateTime<Utc> = (*other).into();
-        self_dt.partial_cmp(&other_dt)
+        Some(self.cmp(other))
     }
 }
 
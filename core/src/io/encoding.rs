@@ -1,5 +1,5 @@
 use {
-    crate::BoxResult,
+    crate::{BoxResult, LuceneError},
     async_trait::async_trait,
     std::{
         collections::{HashMap, HashSet},
@@ -8,6 +8,9 @@ use {
     tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
 };
 
+/// The most bytes a string written with [EncodingWriteExt::write_short_string] can hold.
+pub const MAX_SHORT_STRING_LENGTH: usize = u8::MAX as usize;
+
 /// Additional methods for Lucene decoding on top of the standard `AsyncRead` trait.
 ///
 /// # Lucene variable length integer encoding
@@ -102,7 +105,7 @@ pub trait EncodingReadExt: AsyncReadExt + Unpin {
 
         while (b & 0x80) != 0 {
             if n_read >= 5 {
-                return Err(IoError::new(IoErrorKind::InvalidData, "Cannot read a vi32 larger than 5 bytes"));
+                return Err(corrupt("Cannot read a vi32 larger than 5 bytes"));
             }
 
             b = self.read_u8().await?;
@@ -127,7 +130,7 @@ pub trait EncodingReadExt: AsyncReadExt + Unpin {
 
         while (b & 0x80) != 0 {
             if n_read >= 9 {
-                return Err(IoError::new(IoErrorKind::InvalidData, "Cannot read a vi64 larger than 9 bytes"));
+                return Err(corrupt("Cannot read a vi64 larger than 9 bytes"));
             }
 
             b = self.read_u8().await?;
@@ -213,8 +216,8 @@ pub trait EncodingWriteExt: AsyncWriteExt + Unpin {
     /// This method will return an error if the string is not less than 256 bytes or an underlying I/O error occurs.
     async fn write_short_string(&mut self, s: &str) -> IoResult<()> {
         let len = s.len();
-        if len > u8::MAX as usize {
-            return Err(IoError::new(IoErrorKind::InvalidData, "String too long"));
+        if len > MAX_SHORT_STRING_LENGTH {
+            return Err(invalid_argument(format!("String of {len} bytes is too long for a short string")));
         }
         self.write_u8(len as u8).await?;
         self.write_all(s.as_bytes()).await?;
@@ -228,7 +231,7 @@ pub trait EncodingWriteExt: AsyncWriteExt + Unpin {
     async fn write_string(&mut self, s: &str) -> IoResult<()> {
         let len = s.len();
         if len > i32::MAX as usize {
-            return Err(IoError::new(IoErrorKind::InvalidData, "String too long"));
+            return Err(invalid_argument(format!("String of {len} bytes is too long")));
         }
         self.write_vi32(len as i32).await?;
         self.write_all(s.as_bytes()).await?;
@@ -309,10 +312,24 @@ pub trait EncodingWriteExt: AsyncWriteExt + Unpin {
 
 impl<W: AsyncWrite + Unpin + ?Sized> EncodingWriteExt for W {}
 
+/// Returns an I/O error carrying [LuceneError::CorruptIndex], for data that can't have been written by Lucene.
+fn corrupt(message: &str) -> IoError {
+    IoError::new(IoErrorKind::InvalidData, LuceneError::CorruptIndex(message.to_string().into()))
+}
+
+/// Returns an I/O error carrying [LuceneError::InvalidArgument], for values that can't be encoded.
+fn invalid_argument(message: String) -> IoError {
+    IoError::new(IoErrorKind::InvalidInput, LuceneError::InvalidArgument(message))
+}
+
 #[cfg(test)]
 mod tests {
     use {
-        crate::{codec::CodecHeader, io::EncodingWriteExt},
+        crate::{
+            codec::CodecHeader,
+            io::{EncodingReadExt, EncodingWriteExt},
+            BoxError, LuceneError,
+        },
         pretty_assertions::assert_eq,
     };
 
@@ -324,6 +341,14 @@ mod tests {
         assert_eq!(buf, vec![0x3f, 0xd7, 0x6c, 0x17, 0x4, 0x74, 0x65, 0x73, 0x74, 0x0, 0x0, 0x0, 0x1]);
     }
 
+    #[test_log::test(tokio::test)]
+    async fn test_corrupt_vi32() {
+        let buf = [0xff_u8; 6];
+        let mut input = &buf[..];
+        let error: BoxError = input.read_vi32().await.unwrap_err().into();
+        assert_eq!(LuceneError::find(error.as_ref()).unwrap().code(), "corrupt_index");
+    }
+
     #[test_log::test(tokio::test)]
     async fn test_write_vi32() {
         let mut buf = Vec::new();
@@ -0,0 +1,122 @@
+use {
+    crate::io::RangeSource,
+    async_trait::async_trait,
+    js_sys::{Promise, Uint8Array},
+    std::io::{Error as IoError, ErrorKind as IoErrorKind, Result as IoResult},
+    wasm_bindgen::{prelude::wasm_bindgen, JsCast, JsValue},
+    wasm_bindgen_futures::JsFuture,
+    web_sys::{Headers, Request, RequestInit, Response},
+};
+
+#[wasm_bindgen]
+extern "C" {
+    /// The global `fetch` function, available in windows and workers alike.
+    #[wasm_bindgen(js_name = fetch)]
+    fn global_fetch(request: &Request) -> Promise;
+}
+
+/// A [RangeSource] reading the files of an index with the `fetch` API of a browser or worker, from a web server that
+/// answers HTTP range requests, such as a static file server or an object store. Lengths come from `HEAD` requests
+/// and blocks from `GET` requests with a `Range` header. This is the WebAssembly counterpart of the `http` feature's
+/// `HttpRangeSource`, and is only built for `wasm32-unknown-unknown`.
+///
+/// HTTP can't list a directory, so the names of the files are given when the source is created, typically from a
+/// listing published with the index. Requests to another origin are subject to CORS: the server must allow the
+/// `Range` request header, and expose the `Content-Length` response header.
+#[derive(Clone, Debug)]
+pub struct FetchRangeSource {
+    base_url: String,
+    file_names: Vec<String>,
+}
+
+impl FetchRangeSource {
+    /// Creates a source for the files with the given names under `base_url`, such as `https://example.com/index`.
+    /// Relative URLs are resolved against the page or worker's location.
+    pub fn new(base_url: &str, file_names: Vec<String>) -> Self {
+        Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            file_names,
+        }
+    }
+
+    /// Returns the URL the files are read from.
+    #[inline]
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// Fetches a file, failing unless the response has one of the `expected` statuses.
+    async fn fetch(
+        &self,
+        method: &str,
+        file_name: &str,
+        range: Option<String>,
+        expected: &[u16],
+    ) -> IoResult<Response> {
+        let url = format!("{}/{file_name}", self.base_url);
+        let headers = Headers::new().map_err(|e| js_error(&url, e))?;
+        if let Some(range) = range {
+            headers.set("Range", &range).map_err(|e| js_error(&url, e))?;
+        }
+        let init = RequestInit::new();
+        init.set_method(method);
+        init.set_headers(&headers);
+        let request = Request::new_with_str_and_init(&url, &init).map_err(|e| js_error(&url, e))?;
+
+        let response = JsFuture::from(global_fetch(&request)).await.map_err(|e| js_error(&url, e))?;
+        let response: Response = response.dyn_into().map_err(|e| js_error(&url, e))?;
+        match response.status() {
+            status if expected.contains(&status) => Ok(response),
+            404 => Err(IoError::new(IoErrorKind::NotFound, format!("File not found: {url}"))),
+            status => Err(IoError::other(format!("{url}: unexpected status {status}"))),
+        }
+    }
+}
+
+#[async_trait(?Send)]
+impl RangeSource for FetchRangeSource {
+    async fn list(&self) -> IoResult<Vec<String>> {
+        Ok(self.file_names.clone())
+    }
+
+    async fn file_length(&self, file_name: &str) -> IoResult<u64> {
+        let response = self.fetch("HEAD", file_name, None, &[200]).await?;
+        let length = response.headers().get("Content-Length").map_err(|e| js_error(file_name, e))?;
+        length
+            .and_then(|length| length.parse().ok())
+            .ok_or_else(|| IoError::new(IoErrorKind::InvalidData, format!("{file_name} has no valid Content-Length")))
+    }
+
+    async fn read_range(&self, file_name: &str, offset: u64, length: u64) -> IoResult<Vec<u8>> {
+        if length == 0 {
+            return Ok(Vec::new());
+        }
+
+        let range = format!("bytes={offset}-{}", offset + length - 1);
+        let response = self.fetch("GET", file_name, Some(range), &[206, 200]).await?;
+        let body = response.array_buffer().map_err(|e| js_error(file_name, e))?;
+        let body = JsFuture::from(body).await.map_err(|e| js_error(file_name, e))?;
+        let body = Uint8Array::new(&body).to_vec();
+
+        // A server that ignores the range sends the whole file.
+        let block = if response.status() == 200 {
+            body.get(offset as usize..).unwrap_or_default()
+        } else {
+            &body[..]
+        };
+        if (block.len() as u64) < length {
+            return Err(IoError::new(
+                IoErrorKind::UnexpectedEof,
+                format!("{file_name}: got {} of the {length} bytes at {offset}", block.len()),
+            ));
+        }
+        Ok(block[..length as usize].to_vec())
+    }
+}
+
+/// Converts a JavaScript exception into an I/O error about `name`.
+fn js_error(name: &str, error: impl Into<JsValue>) -> IoError {
+    let error = error.into();
+    let message = error.as_string().or_else(|| error.dyn_ref::<js_sys::Error>().map(|e| e.message().into()));
+    IoError::other(format!("{name}: {}", message.unwrap_or_else(|| format!("{error:?}"))))
+}
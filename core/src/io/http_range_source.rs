@@ -0,0 +1,219 @@
+use {
+    crate::{io::RangeSource, BoxResult, LuceneError},
+    async_trait::async_trait,
+    http_body_util::{BodyExt, Empty},
+    hyper::{
+        body::Bytes,
+        header::{CONTENT_LENGTH, RANGE},
+        Method, Request, Response, StatusCode, Uri,
+    },
+    hyper_util::{
+        client::legacy::{connect::HttpConnector, Client},
+        rt::TokioExecutor,
+    },
+    std::io::{Error as IoError, ErrorKind as IoErrorKind, Result as IoResult},
+};
+
+/// A [RangeSource] reading the files of an index from a web server that answers HTTP range requests, such as a
+/// static file server or an object store. Lengths come from `HEAD` requests and blocks from `GET` requests with a
+/// `Range` header.
+///
+/// HTTP can't list a directory, so the names of the files are given when the source is created, typically from a
+/// listing published with the index. Only `http` URLs are supported, and requests must be made from within a tokio
+/// runtime.
+#[derive(Clone, Debug)]
+pub struct HttpRangeSource {
+    base_url: String,
+    file_names: Vec<String>,
+    client: Client<HttpConnector, Empty<Bytes>>,
+}
+
+impl HttpRangeSource {
+    /// Creates a source for the files with the given names under `base_url`, such as `http://localhost:8080/index`.
+    pub fn new(base_url: &str, file_names: Vec<String>) -> BoxResult<Self> {
+        let uri: Uri =
+            base_url.parse().map_err(|e| LuceneError::InvalidArgument(format!("invalid URL {base_url}: {e}")))?;
+        if uri.scheme_str() != Some("http") {
+            return Err(
+                LuceneError::InvalidArgument(format!("unsupported URL {base_url}: only http is supported")).into()
+            );
+        }
+
+        Ok(Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            file_names,
+            client: Client::builder(TokioExecutor::new()).build_http(),
+        })
+    }
+
+    /// Returns the URL the files are read from.
+    #[inline]
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// Sends a request for a file, failing unless the response has one of the `expected` statuses.
+    async fn request(
+        &self,
+        method: Method,
+        file_name: &str,
+        range: Option<String>,
+        expected: &[StatusCode],
+    ) -> IoResult<Response<hyper::body::Incoming>> {
+        let url = format!("{}/{file_name}", self.base_url);
+        let mut request = Request::builder().method(method).uri(&url);
+        if let Some(range) = range {
+            request = request.header(RANGE, range);
+        }
+        let request = request.body(Empty::new()).map_err(|e| IoError::new(IoErrorKind::InvalidInput, e))?;
+
+        let response = self.client.request(request).await.map_err(|e| IoError::other(format!("{url}: {e}")))?;
+        match response.status() {
+            status if expected.contains(&status) => Ok(response),
+            StatusCode::NOT_FOUND => Err(IoError::new(IoErrorKind::NotFound, format!("File not found: {url}"))),
+            status => Err(IoError::other(format!("{url}: unexpected status {status}"))),
+        }
+    }
+}
+
+#[async_trait(?Send)]
+impl RangeSource for HttpRangeSource {
+    async fn list(&self) -> IoResult<Vec<String>> {
+        Ok(self.file_names.clone())
+    }
+
+    async fn file_length(&self, file_name: &str) -> IoResult<u64> {
+        let response = self.request(Method::HEAD, file_name, None, &[StatusCode::OK]).await?;
+        response
+            .headers()
+            .get(CONTENT_LENGTH)
+            .and_then(|length| length.to_str().ok()?.parse().ok())
+            .ok_or_else(|| IoError::new(IoErrorKind::InvalidData, format!("{file_name} has no valid Content-Length")))
+    }
+
+    async fn read_range(&self, file_name: &str, offset: u64, length: u64) -> IoResult<Vec<u8>> {
+        if length == 0 {
+            return Ok(Vec::new());
+        }
+
+        let range = format!("bytes={offset}-{}", offset + length - 1);
+        let expected = [StatusCode::PARTIAL_CONTENT, StatusCode::OK];
+        let response = self.request(Method::GET, file_name, Some(range), &expected).await?;
+        let status = response.status();
+        let body = response.into_body().collect().await.map_err(|e| IoError::other(format!("{file_name}: {e}")))?;
+        let body = body.to_bytes();
+
+        // A server that ignores the range sends the whole file.
+        let block = if status == StatusCode::OK {
+            body.get(offset as usize..).unwrap_or_default()
+        } else {
+            &body[..]
+        };
+        if (block.len() as u64) < length {
+            return Err(IoError::new(
+                IoErrorKind::UnexpectedEof,
+                format!("{file_name}: got {} of the {length} bytes at {offset}", block.len()),
+            ));
+        }
+        Ok(block[..length as usize].to_vec())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{
+            index::SegmentIndex,
+            io::{ByteBuffersDirectory, Directory, HttpRangeSource, IoContext, RangeDirectory, RangeSource},
+            LATEST,
+        },
+        http_body_util::Full,
+        hyper::{
+            body::{Bytes, Incoming},
+            header::{CONTENT_LENGTH, RANGE},
+            server::conn::http1,
+            service::service_fn,
+            Method, Request, Response, StatusCode,
+        },
+        hyper_util::rt::TokioIo,
+        pretty_assertions::assert_eq,
+        std::{
+            collections::HashMap,
+            convert::Infallible,
+            io::ErrorKind as IoErrorKind,
+            sync::{Arc, Mutex},
+        },
+        tokio::{io::AsyncReadExt, net::TcpListener},
+    };
+
+    /// Serves files from memory under `/index/`, answering `HEAD` requests and `GET` requests for a single range,
+    /// and records the ranges requested.
+    fn serve(
+        files: &HashMap<String, Bytes>,
+        ranges: &Mutex<Vec<String>>,
+        request: Request<Incoming>,
+    ) -> Response<Full<Bytes>> {
+        let Some(data) = request.uri().path().strip_prefix("/index/").and_then(|file_name| files.get(file_name)) else {
+            return Response::builder().status(StatusCode::NOT_FOUND).body(Full::default()).unwrap();
+        };
+        if request.method() == Method::HEAD {
+            return Response::builder().header(CONTENT_LENGTH, data.len()).body(Full::default()).unwrap();
+        }
+
+        let range = request.headers()[RANGE].to_str().unwrap().to_string();
+        let (start, end) = range.trim_start_matches("bytes=").split_once('-').unwrap();
+        let block = data.slice(start.parse::<usize>().unwrap()..end.parse::<usize>().unwrap() + 1);
+        ranges.lock().unwrap().push(range);
+        Response::builder().status(StatusCode::PARTIAL_CONTENT).body(Full::new(block)).unwrap()
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_http_range_source() {
+        let mut dir = ByteBuffersDirectory::new();
+        let mut segment_index = SegmentIndex::new(LATEST.major()).unwrap();
+        segment_index.commit(&mut dir).await.unwrap();
+        let mut files = HashMap::new();
+        for file_name in dir.read_dir().await.unwrap() {
+            let mut data = Vec::new();
+            dir.open(&file_name, &IoContext::Read).await.unwrap().read_to_end(&mut data).await.unwrap();
+            files.insert(file_name, Bytes::from(data));
+        }
+
+        let files = Arc::new(files);
+        let ranges = Arc::new(Mutex::new(Vec::new()));
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/index/", listener.local_addr().unwrap());
+        let (server_files, server_ranges) = (files.clone(), ranges.clone());
+        tokio::spawn(async move {
+            loop {
+                let (stream, _) = listener.accept().await.unwrap();
+                let (files, ranges) = (server_files.clone(), server_ranges.clone());
+                let service = service_fn(move |request: Request<Incoming>| {
+                    let response = serve(&files, &ranges, request);
+                    async move { Ok::<_, Infallible>(response) }
+                });
+                tokio::spawn(http1::Builder::new().serve_connection(TokioIo::new(stream), service));
+            }
+        });
+
+        let source = HttpRangeSource::new(&url, files.keys().cloned().collect()).unwrap();
+        assert_eq!(source.base_url(), url.trim_end_matches('/'));
+        assert_eq!(source.list().await.unwrap(), vec!["segments_1".to_string()]);
+        assert_eq!(source.file_length("segments_1").await.unwrap(), files["segments_1"].len() as u64);
+        assert_eq!(source.file_length("segments_2").await.unwrap_err().kind(), IoErrorKind::NotFound);
+
+        // The commit is read back over HTTP, a block at a time.
+        let mut range_dir = RangeDirectory::new(Arc::new(source));
+        range_dir.set_block_size(32).unwrap();
+        let read = SegmentIndex::open(&mut range_dir).await.unwrap();
+        assert_eq!(read.get_last_generation(), 1);
+        let expected: Vec<String> = (0..files["segments_1"].len())
+            .step_by(32)
+            .map(|start| format!("bytes={start}-{}", (start + 32).min(files["segments_1"].len()) - 1))
+            .collect();
+        assert_eq!(*ranges.lock().unwrap(), expected);
+
+        assert!(HttpRangeSource::new("https://example.com/index", Vec::new()).is_err());
+        assert!(HttpRangeSource::new("not a url", Vec::new()).is_err());
+    }
+}
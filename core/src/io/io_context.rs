@@ -0,0 +1,63 @@
+/// Hints passed to a [Directory](crate::io::Directory) when a file is opened or created, describing why the file is
+/// being accessed.
+///
+/// Directory implementations may use these hints to tune buffering, caching, or (as with
+/// [RateLimitedDirectory](crate::io::RateLimitedDirectory)) throttling.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum IoContext {
+    /// No specific information is available about how the file will be used.
+    #[default]
+    Default,
+
+    /// The file is being opened for reading, typically at search time.
+    Read,
+
+    /// The file will be read once, sequentially, and then closed (for example, segment metadata).
+    ReadOnce,
+
+    /// The file is being written as part of flushing an in-memory segment.
+    Flush(FlushInfo),
+
+    /// The file is being written or read as part of a merge.
+    Merge(MergeInfo),
+}
+
+impl IoContext {
+    /// Indicates whether this context is for a merge.
+    #[inline]
+    pub fn is_merge(&self) -> bool {
+        matches!(self, Self::Merge(_))
+    }
+
+    /// Indicates whether this context is for a flush.
+    #[inline]
+    pub fn is_flush(&self) -> bool {
+        matches!(self, Self::Flush(_))
+    }
+}
+
+/// Information about a segment flush, carried in [IoContext::Flush].
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct FlushInfo {
+    /// The number of documents in the segment being flushed.
+    pub num_docs: u32,
+
+    /// The estimated size of the flushed segment, in bytes.
+    pub estimated_segment_size: u64,
+}
+
+/// Information about a merge, carried in [IoContext::Merge].
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct MergeInfo {
+    /// The total number of documents (including deleted ones) in the segments being merged.
+    pub total_max_doc: u32,
+
+    /// The estimated number of bytes the merge will write.
+    pub estimated_merge_bytes: u64,
+
+    /// Whether the merge pulls in segments from another index (e.g. via `add_indexes`).
+    pub is_external: bool,
+
+    /// The target number of segments for a forced merge, or `None` for a natural merge.
+    pub merge_max_num_segments: Option<u32>,
+}
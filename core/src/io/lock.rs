@@ -0,0 +1,426 @@
+use {
+    crate::{BoxResult, LuceneError},
+    once_cell::sync::Lazy,
+    std::{
+        collections::HashSet,
+        fmt::Debug,
+        fs::{create_dir_all, metadata, remove_file, File, OpenOptions, TryLockError},
+        io::ErrorKind as IoErrorKind,
+        path::{Path, PathBuf},
+        sync::{Arc, Mutex},
+        time::SystemTime,
+    },
+};
+
+/// The name of the lock file used to prevent two [IndexWriter](crate::index::IndexWriter)s from modifying the same
+/// index at the same time.
+pub const WRITE_LOCK_NAME: &str = "write.lock";
+
+/// An interprocess mutex lock.
+///
+/// Locks are released when [Lock::close] is called or when the lock is dropped.
+pub trait Lock: Debug + Send + Sync {
+    /// Best-effort check that this lock is still valid and may be used to write to the index.
+    ///
+    /// This returns an error if the lock has been released or if it appears to have been stolen (for example, if the
+    /// lock file was removed or recreated by another process).
+    fn ensure_valid(&self) -> BoxResult<()>;
+
+    /// Releases the lock. Calling this more than once is a no-op.
+    fn close(&mut self) -> BoxResult<()>;
+}
+
+/// Creates [Lock]s for the files in a directory.
+///
+/// Locks are identified by the path of the directory being locked and a lock name. Factories that are not backed by a
+/// filesystem (such as [SingleInstanceLockFactory]) treat the directory path as an opaque key.
+pub trait LockFactory: Debug + Send + Sync {
+    /// Attempts to obtain the lock `lock_name` in the directory `dir_path`. This never blocks; if the lock is already
+    /// held, [LuceneError::LockObtainFailed] is returned.
+    fn obtain_lock(&self, dir_path: &Path, lock_name: &str) -> BoxResult<Box<dyn Lock>>;
+}
+
+/// Native file locks held by this process. Operating system locks are typically per-process, so we must also track
+/// which locks this process holds to prevent two writers in the same process from sharing a lock.
+static NATIVE_LOCKS_HELD: Lazy<Mutex<HashSet<PathBuf>>> = Lazy::new(|| Mutex::new(HashSet::new()));
+
+/// A [LockFactory] that uses the operating system's native file locking (`flock`/`LockFileEx`).
+///
+/// This is the default lock factory for [FilesystemDirectory](crate::fs::FilesystemDirectory). Native locks are
+/// released automatically by the operating system if the process dies, so stale locks are never left behind. The
+/// lock file itself is not removed when the lock is released.
+#[derive(Debug, Default)]
+pub struct NativeFsLockFactory {}
+
+impl LockFactory for NativeFsLockFactory {
+    fn obtain_lock(&self, dir_path: &Path, lock_name: &str) -> BoxResult<Box<dyn Lock>> {
+        create_dir_all(dir_path)?;
+        let lock_path = dir_path.join(lock_name);
+
+        // Ensure the file exists so we can canonicalize it; the file is intentionally never deleted.
+        let file = OpenOptions::new().read(true).write(true).create(true).truncate(false).open(&lock_path)?;
+        let real_path = lock_path.canonicalize()?;
+        let creation_time = creation_time(&real_path)?;
+
+        if !lock_native_locks_held().insert(real_path.clone()) {
+            return Err(
+                LuceneError::LockObtainFailed(format!("Lock held by this process: {}", real_path.display())).into()
+            );
+        }
+
+        match file.try_lock() {
+            Ok(()) => Ok(Box::new(NativeFsLock {
+                file: Some(file),
+                path: real_path,
+                creation_time,
+            })),
+            Err(e) => {
+                lock_native_locks_held().remove(&real_path);
+                match e {
+                    TryLockError::WouldBlock => Err(LuceneError::LockObtainFailed(format!(
+                        "Lock held by another program: {}",
+                        real_path.display()
+                    ))
+                    .into()),
+                    TryLockError::Error(e) => Err(e.into()),
+                }
+            }
+        }
+    }
+}
+
+/// A lock obtained from a [NativeFsLockFactory].
+#[derive(Debug)]
+struct NativeFsLock {
+    file: Option<File>,
+    path: PathBuf,
+    creation_time: Option<SystemTime>,
+}
+
+impl Lock for NativeFsLock {
+    fn ensure_valid(&self) -> BoxResult<()> {
+        if self.file.is_none() {
+            return Err(
+                LuceneError::AlreadyClosed(format!("Lock instance already released: {}", self.path.display())).into()
+            );
+        }
+
+        if !lock_native_locks_held().contains(&self.path) {
+            return Err(LuceneError::AlreadyClosed(format!(
+                "Lock path unexpectedly cleared from map: {}",
+                self.path.display()
+            ))
+            .into());
+        }
+
+        // If the lock file was deleted and recreated, someone else may have obtained a lock on the new file.
+        let current_creation_time = creation_time(&self.path)?;
+        if current_creation_time != self.creation_time {
+            return Err(LuceneError::AlreadyClosed(format!(
+                "Underlying file changed by an external force: {}",
+                self.path.display()
+            ))
+            .into());
+        }
+
+        Ok(())
+    }
+
+    fn close(&mut self) -> BoxResult<()> {
+        if let Some(file) = self.file.take() {
+            let result = file.unlock();
+            lock_native_locks_held().remove(&self.path);
+            result?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Drop for NativeFsLock {
+    fn drop(&mut self) {
+        let _ = self.close();
+    }
+}
+
+/// A [LockFactory] that creates a lock file with exclusive-create semantics.
+///
+/// Unlike [NativeFsLockFactory], a lock file left behind by a crashed process must be removed manually before the
+/// index can be written again. This is useful for filesystems where native locks are unreliable (such as some network
+/// filesystems).
+#[derive(Debug, Default)]
+pub struct SimpleFsLockFactory {}
+
+impl LockFactory for SimpleFsLockFactory {
+    fn obtain_lock(&self, dir_path: &Path, lock_name: &str) -> BoxResult<Box<dyn Lock>> {
+        create_dir_all(dir_path)?;
+        let lock_path = dir_path.join(lock_name);
+
+        match OpenOptions::new().write(true).create_new(true).open(&lock_path) {
+            Ok(_) => {
+                let real_path = lock_path.canonicalize()?;
+                let creation_time = creation_time(&real_path)?;
+                Ok(Box::new(SimpleFsLock {
+                    path: real_path,
+                    creation_time,
+                    closed: false,
+                }))
+            }
+            Err(e) if e.kind() == IoErrorKind::AlreadyExists => {
+                Err(LuceneError::LockObtainFailed(format!("Lock held elsewhere: {}", lock_path.display())).into())
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+/// A lock obtained from a [SimpleFsLockFactory].
+#[derive(Debug)]
+struct SimpleFsLock {
+    path: PathBuf,
+    creation_time: Option<SystemTime>,
+    closed: bool,
+}
+
+impl Lock for SimpleFsLock {
+    fn ensure_valid(&self) -> BoxResult<()> {
+        if self.closed {
+            return Err(
+                LuceneError::AlreadyClosed(format!("Lock instance already released: {}", self.path.display())).into()
+            );
+        }
+
+        match creation_time(&self.path) {
+            Ok(current_creation_time) if current_creation_time == self.creation_time => Ok(()),
+            Ok(_) => Err(LuceneError::AlreadyClosed(format!(
+                "Underlying file changed by an external force: {}",
+                self.path.display()
+            ))
+            .into()),
+            Err(e) if e.kind() == IoErrorKind::NotFound => Err(LuceneError::AlreadyClosed(format!(
+                "Lock file was removed by an external force: {}",
+                self.path.display()
+            ))
+            .into()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn close(&mut self) -> BoxResult<()> {
+        if self.closed {
+            return Ok(());
+        }
+
+        self.closed = true;
+
+        // Make sure we don't delete a lock file that someone else now owns.
+        self.ensure_valid_before_close()?;
+        remove_file(&self.path)?;
+        Ok(())
+    }
+}
+
+impl SimpleFsLock {
+    fn ensure_valid_before_close(&self) -> BoxResult<()> {
+        match creation_time(&self.path) {
+            Ok(current_creation_time) if current_creation_time == self.creation_time => Ok(()),
+            Ok(_) => Err(LuceneError::LockReleaseFailed(format!(
+                "Lock file changed by an external force, not removing it: {}",
+                self.path.display()
+            ))
+            .into()),
+            Err(e) => {
+                Err(LuceneError::LockReleaseFailed(format!("Unable to remove lock {}: {e}", self.path.display()))
+                    .into())
+            }
+        }
+    }
+}
+
+impl Drop for SimpleFsLock {
+    fn drop(&mut self) {
+        let _ = self.close();
+    }
+}
+
+/// A [LockFactory] for locks that only need to be exclusive within a single [Directory](crate::io::Directory)
+/// instance (and its clones). This is the lock factory used by
+/// [ByteBuffersDirectory](crate::io::ByteBuffersDirectory).
+#[derive(Clone, Debug, Default)]
+pub struct SingleInstanceLockFactory {
+    locks: Arc<Mutex<HashSet<PathBuf>>>,
+}
+
+impl LockFactory for SingleInstanceLockFactory {
+    fn obtain_lock(&self, dir_path: &Path, lock_name: &str) -> BoxResult<Box<dyn Lock>> {
+        let key = dir_path.join(lock_name);
+        let mut locks = self.locks.lock().unwrap_or_else(|e| e.into_inner());
+        if !locks.insert(key.clone()) {
+            return Err(
+                LuceneError::LockObtainFailed(format!("Lock instance already obtained: {}", key.display())).into()
+            );
+        }
+
+        Ok(Box::new(SingleInstanceLock {
+            locks: self.locks.clone(),
+            key,
+            closed: false,
+        }))
+    }
+}
+
+/// A lock obtained from a [SingleInstanceLockFactory].
+#[derive(Debug)]
+struct SingleInstanceLock {
+    locks: Arc<Mutex<HashSet<PathBuf>>>,
+    key: PathBuf,
+    closed: bool,
+}
+
+impl Lock for SingleInstanceLock {
+    fn ensure_valid(&self) -> BoxResult<()> {
+        if self.closed {
+            return Err(
+                LuceneError::AlreadyClosed(format!("Lock instance already released: {}", self.key.display())).into()
+            );
+        }
+
+        if !self.locks.lock().unwrap_or_else(|e| e.into_inner()).contains(&self.key) {
+            return Err(LuceneError::AlreadyClosed(format!(
+                "Lock instance was invalidated from map: {}",
+                self.key.display()
+            ))
+            .into());
+        }
+
+        Ok(())
+    }
+
+    fn close(&mut self) -> BoxResult<()> {
+        if !self.closed {
+            self.closed = true;
+            self.locks.lock().unwrap_or_else(|e| e.into_inner()).remove(&self.key);
+        }
+
+        Ok(())
+    }
+}
+
+impl Drop for SingleInstanceLock {
+    fn drop(&mut self) {
+        let _ = self.close();
+    }
+}
+
+/// A [LockFactory] that performs no locking at all. Use this only if you are certain that a single writer will ever
+/// access the index (for example, a read-only snapshot being rewritten offline).
+#[derive(Debug, Default)]
+pub struct NoLockFactory {}
+
+impl LockFactory for NoLockFactory {
+    fn obtain_lock(&self, _dir_path: &Path, _lock_name: &str) -> BoxResult<Box<dyn Lock>> {
+        Ok(Box::new(NoLock {}))
+    }
+}
+
+/// A lock obtained from a [NoLockFactory].
+#[derive(Debug)]
+struct NoLock {}
+
+impl Lock for NoLock {
+    fn ensure_valid(&self) -> BoxResult<()> {
+        Ok(())
+    }
+
+    fn close(&mut self) -> BoxResult<()> {
+        Ok(())
+    }
+}
+
+fn lock_native_locks_held() -> std::sync::MutexGuard<'static, HashSet<PathBuf>> {
+    NATIVE_LOCKS_HELD.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Returns the creation time of the file, or `None` if the platform does not record creation times.
+fn creation_time(path: &Path) -> std::io::Result<Option<SystemTime>> {
+    let md = metadata(path)?;
+    Ok(md.created().ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{
+            io::{LockFactory, NativeFsLockFactory, SimpleFsLockFactory, SingleInstanceLockFactory},
+            LuceneError,
+        },
+        rand::random,
+        std::{
+            fs::{create_dir_all, remove_dir_all, remove_file},
+            path::{Path, PathBuf},
+        },
+    };
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("lucene-lock-{name}-{:016x}", random::<u64>()));
+        create_dir_all(&path).unwrap();
+        path
+    }
+
+    fn assert_obtain_failed(factory: &dyn LockFactory, dir: &Path) {
+        let err = factory.obtain_lock(dir, "write.lock").unwrap_err();
+        assert!(matches!(err.downcast_ref::<LuceneError>(), Some(LuceneError::LockObtainFailed(_))), "{err:?}");
+    }
+
+    #[test]
+    fn test_native_lock_is_exclusive() {
+        let dir = temp_dir("native");
+        let factory = NativeFsLockFactory::default();
+
+        let mut lock = factory.obtain_lock(&dir, "write.lock").unwrap();
+        lock.ensure_valid().unwrap();
+        assert_obtain_failed(&factory, &dir);
+
+        lock.close().unwrap();
+        assert!(lock.ensure_valid().is_err());
+        assert!(dir.join("write.lock").exists());
+
+        let lock = factory.obtain_lock(&dir, "write.lock").unwrap();
+        drop(lock);
+        factory.obtain_lock(&dir, "write.lock").unwrap();
+        remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_simple_fs_lock() {
+        let dir = temp_dir("simple");
+        let factory = SimpleFsLockFactory::default();
+
+        let mut lock = factory.obtain_lock(&dir, "write.lock").unwrap();
+        lock.ensure_valid().unwrap();
+        assert_obtain_failed(&factory, &dir);
+
+        lock.close().unwrap();
+        assert!(!dir.join("write.lock").exists());
+
+        // Removing the lock file out from under the lock invalidates it.
+        let lock = factory.obtain_lock(&dir, "write.lock").unwrap();
+        remove_file(dir.join("write.lock")).unwrap();
+        assert!(lock.ensure_valid().is_err());
+        drop(lock);
+        remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_single_instance_lock() {
+        let factory = SingleInstanceLockFactory::default();
+        let dir = Path::new("");
+        let mut lock = factory.obtain_lock(dir, "write.lock").unwrap();
+        assert_obtain_failed(&factory.clone(), dir);
+        factory.obtain_lock(dir, "other.lock").unwrap();
+
+        lock.close().unwrap();
+        assert!(lock.ensure_valid().is_err());
+        factory.obtain_lock(dir, "write.lock").unwrap();
+    }
+}
@@ -0,0 +1,113 @@
+use std::{
+    fmt::Debug,
+    io::{Error as IoError, ErrorKind as IoErrorKind, Result as IoResult},
+    sync::Arc,
+};
+
+/// Positional reads of little-endian values, as used by on-disk structures that are looked up by index rather than
+/// read sequentially, such as those written by [DirectWriter](crate::util::packed::DirectWriter).
+///
+/// This is implemented for in-memory byte buffers, so a file (or a slice of one) can be loaded and then read at
+/// random.
+pub trait RandomAccessInput: Debug + Send + Sync {
+    /// Returns the number of bytes that can be read.
+    fn length(&self) -> u64;
+
+    /// Fills `buf` with the bytes starting at `pos`. This fails with [IoErrorKind::UnexpectedEof] if they run past
+    /// the end of the input.
+    fn read_bytes_at(&self, pos: u64, buf: &mut [u8]) -> IoResult<()>;
+
+    /// Reads the byte at `pos`.
+    fn read_u8_at(&self, pos: u64) -> IoResult<u8> {
+        let mut buf = [0; 1];
+        self.read_bytes_at(pos, &mut buf)?;
+        Ok(buf[0])
+    }
+
+    /// Reads a little-endian `u16` starting at `pos`.
+    fn read_u16_le_at(&self, pos: u64) -> IoResult<u16> {
+        let mut buf = [0; 2];
+        self.read_bytes_at(pos, &mut buf)?;
+        Ok(u16::from_le_bytes(buf))
+    }
+
+    /// Reads a little-endian `u32` starting at `pos`.
+    fn read_u32_le_at(&self, pos: u64) -> IoResult<u32> {
+        let mut buf = [0; 4];
+        self.read_bytes_at(pos, &mut buf)?;
+        Ok(u32::from_le_bytes(buf))
+    }
+
+    /// Reads a little-endian `u64` starting at `pos`.
+    fn read_u64_le_at(&self, pos: u64) -> IoResult<u64> {
+        let mut buf = [0; 8];
+        self.read_bytes_at(pos, &mut buf)?;
+        Ok(u64::from_le_bytes(buf))
+    }
+}
+
+impl RandomAccessInput for [u8] {
+    #[inline]
+    fn length(&self) -> u64 {
+        self.len() as u64
+    }
+
+    fn read_bytes_at(&self, pos: u64, buf: &mut [u8]) -> IoResult<()> {
+        let bytes = usize::try_from(pos)
+            .ok()
+            .and_then(|start| self.get(start..start.checked_add(buf.len())?))
+            .ok_or_else(|| {
+                IoError::new(
+                    IoErrorKind::UnexpectedEof,
+                    format!("Cannot read {} bytes at {pos} from an input of {} bytes", buf.len(), self.len()),
+                )
+            })?;
+        buf.copy_from_slice(bytes);
+        Ok(())
+    }
+}
+
+impl RandomAccessInput for Vec<u8> {
+    #[inline]
+    fn length(&self) -> u64 {
+        self.as_slice().length()
+    }
+
+    #[inline]
+    fn read_bytes_at(&self, pos: u64, buf: &mut [u8]) -> IoResult<()> {
+        self.as_slice().read_bytes_at(pos, buf)
+    }
+}
+
+impl RandomAccessInput for Arc<[u8]> {
+    #[inline]
+    fn length(&self) -> u64 {
+        self.as_ref().length()
+    }
+
+    #[inline]
+    fn read_bytes_at(&self, pos: u64, buf: &mut [u8]) -> IoResult<()> {
+        self.as_ref().read_bytes_at(pos, buf)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::io::RandomAccessInput,
+        pretty_assertions::assert_eq,
+        std::{io::ErrorKind as IoErrorKind, sync::Arc},
+    };
+
+    #[test]
+    fn test_random_access_input() {
+        let input: Arc<[u8]> = (1..=10).collect();
+        assert_eq!(input.length(), 10);
+        assert_eq!(input.read_u8_at(9).unwrap(), 10);
+        assert_eq!(input.read_u16_le_at(0).unwrap(), 0x0201);
+        assert_eq!(input.read_u32_le_at(1).unwrap(), 0x0504_0302);
+        assert_eq!(input.read_u64_le_at(2).unwrap(), 0x0a09_0807_0605_0403);
+        assert_eq!(input.read_u64_le_at(3).unwrap_err().kind(), IoErrorKind::UnexpectedEof);
+        assert_eq!(input.read_u8_at(u64::MAX).unwrap_err().kind(), IoErrorKind::UnexpectedEof);
+    }
+}
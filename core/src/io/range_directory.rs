@@ -0,0 +1,270 @@
+use {
+    crate::{
+        io::{Directory, IoContext, Lock},
+        BoxResult, LuceneError,
+    },
+    async_trait::async_trait,
+    std::{
+        fmt::{Debug, Formatter, Result as FmtResult},
+        future::Future,
+        io::{Error as IoError, ErrorKind as IoErrorKind, Result as IoResult},
+        pin::Pin,
+        sync::Arc,
+        task::{Context, Poll},
+    },
+    tokio::io::{AsyncRead, AsyncWrite, ReadBuf},
+};
+
+/// The number of bytes a [RangeDirectory] fetches at a time by default.
+pub const DEFAULT_RANGE_BLOCK_SIZE: u64 = 64 * 1024;
+
+/// A read-only store of files whose contents can be fetched in ranges.
+///
+/// This is the extension point for virtual directories, such as a web server answering HTTP range requests or a
+/// browser's IndexedDB, which let small indexes be searched from WebAssembly. Futures need not be [Send], so
+/// implementations can await JavaScript promises. With the `http` feature, `HttpRangeSource` reads from a web server
+/// natively; on `wasm32-unknown-unknown`, `FetchRangeSource` does the same from a browser or worker.
+#[async_trait(?Send)]
+pub trait RangeSource: Debug {
+    /// Returns the names of the files in the store.
+    async fn list(&self) -> IoResult<Vec<String>>;
+
+    /// Returns the length of a file in bytes.
+    async fn file_length(&self, file_name: &str) -> IoResult<u64>;
+
+    /// Returns `length` bytes of a file, starting at `offset`. The range never extends past the end of the file.
+    async fn read_range(&self, file_name: &str, offset: u64, length: u64) -> IoResult<Vec<u8>>;
+}
+
+/// A read-only [Directory] over a [RangeSource]. Files are fetched lazily, a block at a time, as they are read.
+///
+/// Creating, removing and renaming files fail with [IoErrorKind::Unsupported], and locks can't be obtained, so an
+/// [crate::index::IndexWriter] can't be opened on this directory.
+#[derive(Clone, Debug)]
+pub struct RangeDirectory {
+    source: Arc<dyn RangeSource>,
+    block_size: u64,
+}
+
+impl RangeDirectory {
+    /// Creates a directory reading from the given source in blocks of [DEFAULT_RANGE_BLOCK_SIZE] bytes.
+    pub fn new(source: Arc<dyn RangeSource>) -> Self {
+        Self {
+            source,
+            block_size: DEFAULT_RANGE_BLOCK_SIZE,
+        }
+    }
+
+    /// Returns the source files are read from.
+    #[inline]
+    pub fn source(&self) -> &Arc<dyn RangeSource> {
+        &self.source
+    }
+
+    /// Returns the number of bytes fetched at a time.
+    #[inline]
+    pub fn block_size(&self) -> u64 {
+        self.block_size
+    }
+
+    /// Sets the number of bytes fetched at a time. Larger blocks mean fewer, larger requests.
+    pub fn set_block_size(&mut self, block_size: u64) -> BoxResult<&mut Self> {
+        if block_size == 0 {
+            return Err(LuceneError::InvalidArgument("block size must be positive".to_string()).into());
+        }
+
+        self.block_size = block_size;
+        Ok(self)
+    }
+}
+
+#[async_trait(?Send)]
+impl Directory for RangeDirectory {
+    async fn read_dir(&self) -> IoResult<Vec<String>> {
+        self.source.list().await
+    }
+
+    async fn create(&mut self, file_name: &str, _context: &IoContext) -> IoResult<Pin<Box<dyn AsyncWrite + Send>>> {
+        Err(read_only(file_name))
+    }
+
+    async fn open(&mut self, file_name: &str, _context: &IoContext) -> IoResult<Pin<Box<dyn AsyncRead>>> {
+        let length = self.source.file_length(file_name).await?;
+        Ok(Box::pin(RangeReader {
+            source: self.source.clone(),
+            file_name: file_name.to_string(),
+            length,
+            position: 0,
+            block_size: self.block_size,
+            buffer: Vec::new(),
+            buffer_position: 0,
+            pending: None,
+        }))
+    }
+
+    async fn remove(&mut self, file_name: &str) -> IoResult<()> {
+        Err(read_only(file_name))
+    }
+
+    async fn rename(&mut self, old_file_name: &str, _new_file_name: &str) -> IoResult<()> {
+        Err(read_only(old_file_name))
+    }
+
+    // Nothing is written through a read-only directory, so there's nothing to make durable.
+    async fn sync(&mut self, _file_names: &[&str]) -> IoResult<()> {
+        Ok(())
+    }
+
+    async fn sync_meta_data(&mut self) -> IoResult<()> {
+        Ok(())
+    }
+
+    async fn obtain_lock(&mut self, lock_name: &str) -> BoxResult<Box<dyn Lock>> {
+        Err(LuceneError::LockObtainFailed(format!("Cannot obtain lock {lock_name}: the directory is read-only")).into())
+    }
+}
+
+fn read_only(file_name: &str) -> IoError {
+    IoError::new(IoErrorKind::Unsupported, format!("Cannot modify file {file_name}: the directory is read-only"))
+}
+
+type PendingRead = Pin<Box<dyn Future<Output = IoResult<Vec<u8>>>>>;
+
+/// Reads a file of a [RangeDirectory], fetching one block at a time.
+struct RangeReader {
+    source: Arc<dyn RangeSource>,
+    file_name: String,
+    length: u64,
+
+    /// The position in the file just past the buffered block.
+    position: u64,
+    block_size: u64,
+    buffer: Vec<u8>,
+    buffer_position: usize,
+    pending: Option<PendingRead>,
+}
+
+impl Debug for RangeReader {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        f.debug_struct("RangeReader")
+            .field("file_name", &self.file_name)
+            .field("length", &self.length)
+            .field("position", &self.position)
+            .finish()
+    }
+}
+
+impl AsyncRead for RangeReader {
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<IoResult<()>> {
+        let this = self.get_mut();
+
+        if this.buffer_position == this.buffer.len() {
+            if this.position >= this.length {
+                return Poll::Ready(Ok(()));
+            }
+
+            let pending = this.pending.get_or_insert_with(|| {
+                let source = this.source.clone();
+                let file_name = this.file_name.clone();
+                let offset = this.position;
+                let length = this.block_size.min(this.length - offset);
+                Box::pin(async move { source.read_range(&file_name, offset, length).await })
+            });
+
+            let block = match pending.as_mut().poll(cx) {
+                Poll::Pending => return Poll::Pending,
+                Poll::Ready(result) => {
+                    this.pending = None;
+                    result?
+                }
+            };
+
+            if block.is_empty() {
+                return Poll::Ready(Err(IoError::new(
+                    IoErrorKind::UnexpectedEof,
+                    format!("File {} ended at {} of {} bytes", this.file_name, this.position, this.length),
+                )));
+            }
+
+            this.position += block.len() as u64;
+            this.buffer = block;
+            this.buffer_position = 0;
+        }
+
+        let n = (this.buffer.len() - this.buffer_position).min(buf.remaining());
+        buf.put_slice(&this.buffer[this.buffer_position..this.buffer_position + n]);
+        this.buffer_position += n;
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{
+            io::{block_on, Directory, IoContext, RangeDirectory, RangeSource},
+            LuceneError,
+        },
+        async_trait::async_trait,
+        pretty_assertions::assert_eq,
+        std::{
+            collections::BTreeMap,
+            io::{Error as IoError, ErrorKind as IoErrorKind, Result as IoResult},
+            sync::{Arc, Mutex},
+        },
+        tokio::io::AsyncReadExt,
+    };
+
+    /// A source serving files from memory and recording the ranges requested.
+    #[derive(Debug, Default)]
+    struct MemorySource {
+        files: BTreeMap<String, Vec<u8>>,
+        requests: Mutex<Vec<(u64, u64)>>,
+    }
+
+    #[async_trait(?Send)]
+    impl RangeSource for MemorySource {
+        async fn list(&self) -> IoResult<Vec<String>> {
+            Ok(self.files.keys().cloned().collect())
+        }
+
+        async fn file_length(&self, file_name: &str) -> IoResult<u64> {
+            self.files
+                .get(file_name)
+                .map(|data| data.len() as u64)
+                .ok_or_else(|| IoError::new(IoErrorKind::NotFound, file_name.to_string()))
+        }
+
+        async fn read_range(&self, file_name: &str, offset: u64, length: u64) -> IoResult<Vec<u8>> {
+            self.requests.lock().unwrap().push((offset, length));
+            Ok(self.files[file_name][offset as usize..(offset + length) as usize].to_vec())
+        }
+    }
+
+    #[test]
+    fn test_range_directory() {
+        let data: Vec<u8> = (0..10).collect();
+        let source = Arc::new(MemorySource {
+            files: BTreeMap::from([("_0.cfs".to_string(), data.clone())]),
+            ..Default::default()
+        });
+        let mut dir = RangeDirectory::new(source.clone());
+        dir.set_block_size(4).unwrap();
+        assert!(dir.set_block_size(0).is_err());
+
+        let contents = block_on(async {
+            let mut contents = Vec::new();
+            dir.open("_0.cfs", &IoContext::Read).await.unwrap().read_to_end(&mut contents).await.unwrap();
+            contents
+        });
+        assert_eq!(contents, data);
+        assert_eq!(*source.requests.lock().unwrap(), vec![(0, 4), (4, 4), (8, 2)]);
+
+        assert_eq!(block_on(dir.read_dir()).unwrap(), vec!["_0.cfs".to_string()]);
+        assert!(block_on(dir.open("missing", &IoContext::Read)).is_err());
+        assert_eq!(block_on(dir.remove("_0.cfs")).unwrap_err().kind(), IoErrorKind::Unsupported);
+        assert!(block_on(dir.create("_1.cfs", &IoContext::Default)).is_err());
+        let err = block_on(dir.obtain_lock("write.lock")).unwrap_err();
+        assert!(matches!(err.downcast_ref::<LuceneError>(), Some(LuceneError::LockObtainFailed(_))));
+    }
+}
@@ -0,0 +1,230 @@
+use {
+    crate::{
+        io::{default_runtime, Directory, IoContext, Lock, RateLimiter, Runtime},
+        BoxResult,
+    },
+    async_trait::async_trait,
+    std::{
+        fmt::{Debug, Formatter, Result as FmtResult},
+        future::Future,
+        io::Result as IoResult,
+        pin::Pin,
+        sync::Arc,
+        task::{Context, Poll},
+    },
+    tokio::io::{AsyncRead, AsyncWrite},
+};
+
+/// A [Directory] wrapper that throttles writes made under an [IoContext::Merge] context to the rate allowed by a
+/// [RateLimiter].
+///
+/// Reads and non-merge writes (flushes, commits) pass through unthrottled, so background merges cannot starve
+/// query-time I/O or indexing.
+///
+/// Pauses are timed with [default_runtime] unless another [Runtime] is set with [RateLimitedDirectory::set_runtime].
+#[derive(Debug)]
+pub struct RateLimitedDirectory<D> {
+    inner: D,
+    merge_rate_limiter: Arc<dyn RateLimiter>,
+    runtime: Arc<dyn Runtime>,
+}
+
+impl<D: Directory> RateLimitedDirectory<D> {
+    /// Wrap the given directory, throttling merge writes with `merge_rate_limiter`.
+    pub fn new(inner: D, merge_rate_limiter: Arc<dyn RateLimiter>) -> Self {
+        Self {
+            inner,
+            merge_rate_limiter,
+            runtime: default_runtime(),
+        }
+    }
+
+    /// Sets the runtime used to time pauses.
+    pub fn set_runtime(&mut self, runtime: Arc<dyn Runtime>) -> &mut Self {
+        self.runtime = runtime;
+        self
+    }
+
+    /// Returns the wrapped directory.
+    #[inline]
+    pub fn inner(&self) -> &D {
+        &self.inner
+    }
+
+    /// Unwraps this directory, returning the wrapped directory.
+    #[inline]
+    pub fn into_inner(self) -> D {
+        self.inner
+    }
+
+    /// Returns the rate limiter applied to merge writes.
+    #[inline]
+    pub fn merge_rate_limiter(&self) -> &Arc<dyn RateLimiter> {
+        &self.merge_rate_limiter
+    }
+
+    /// Returns the runtime used to time pauses.
+    #[inline]
+    pub fn runtime(&self) -> &Arc<dyn Runtime> {
+        &self.runtime
+    }
+}
+
+#[async_trait(?Send)]
+impl<D: Directory> Directory for RateLimitedDirectory<D> {
+    async fn read_dir(&self) -> IoResult<Vec<String>> {
+        self.inner.read_dir().await
+    }
+
+    async fn create(&mut self, file_name: &str, context: &IoContext) -> IoResult<Pin<Box<dyn AsyncWrite + Send>>> {
+        let w = self.inner.create(file_name, context).await?;
+        if context.is_merge() {
+            Ok(Box::pin(RateLimitedWriter::new(w, self.merge_rate_limiter.clone(), self.runtime.clone())))
+        } else {
+            Ok(w)
+        }
+    }
+
+    async fn open(&mut self, file_name: &str, context: &IoContext) -> IoResult<Pin<Box<dyn AsyncRead>>> {
+        self.inner.open(file_name, context).await
+    }
+
+    async fn remove(&mut self, file_name: &str) -> IoResult<()> {
+        self.inner.remove(file_name).await
+    }
+
+    async fn rename(&mut self, old_file_name: &str, new_file_name: &str) -> IoResult<()> {
+        self.inner.rename(old_file_name, new_file_name).await
+    }
+
+    async fn sync(&mut self, file_names: &[&str]) -> IoResult<()> {
+        self.inner.sync(file_names).await
+    }
+
+    async fn sync_meta_data(&mut self) -> IoResult<()> {
+        self.inner.sync_meta_data().await
+    }
+
+    async fn obtain_lock(&mut self, lock_name: &str) -> BoxResult<Box<dyn Lock>> {
+        self.inner.obtain_lock(lock_name).await
+    }
+}
+
+/// An [AsyncWrite] wrapper that pauses periodically to honor a [RateLimiter].
+pub struct RateLimitedWriter {
+    inner: Pin<Box<dyn AsyncWrite + Send>>,
+    rate_limiter: Arc<dyn RateLimiter>,
+    runtime: Arc<dyn Runtime>,
+    bytes_since_last_pause: u64,
+    pending_pause: Option<Pin<Box<dyn Future<Output = ()> + Send>>>,
+}
+
+impl RateLimitedWriter {
+    /// Wrap the given writer, timing pauses with `runtime`.
+    pub fn new(
+        inner: Pin<Box<dyn AsyncWrite + Send>>,
+        rate_limiter: Arc<dyn RateLimiter>,
+        runtime: Arc<dyn Runtime>,
+    ) -> Self {
+        Self {
+            inner,
+            rate_limiter,
+            runtime,
+            bytes_since_last_pause: 0,
+            pending_pause: None,
+        }
+    }
+}
+
+impl Debug for RateLimitedWriter {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        f.debug_struct("RateLimitedWriter")
+            .field("rate_limiter", &self.rate_limiter)
+            .field("runtime", &self.runtime)
+            .field("bytes_since_last_pause", &self.bytes_since_last_pause)
+            .finish()
+    }
+}
+
+impl AsyncWrite for RateLimitedWriter {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<IoResult<usize>> {
+        let this = self.get_mut();
+
+        if let Some(pause) = this.pending_pause.as_mut() {
+            if pause.as_mut().poll(cx).is_pending() {
+                return Poll::Pending;
+            }
+            this.pending_pause = None;
+        }
+
+        let n = match this.inner.as_mut().poll_write(cx, buf) {
+            Poll::Ready(Ok(n)) => n,
+            other => return other,
+        };
+
+        this.bytes_since_last_pause += n as u64;
+        if this.bytes_since_last_pause > this.rate_limiter.min_pause_check_bytes() {
+            let delay = this.rate_limiter.pause(this.bytes_since_last_pause);
+            this.bytes_since_last_pause = 0;
+            if !delay.is_zero() {
+                // The pause is applied before the next write so the bytes just accepted are not lost.
+                this.pending_pause = Some(this.runtime.sleep(delay));
+            }
+        }
+
+        Poll::Ready(Ok(n))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<IoResult<()>> {
+        self.get_mut().inner.as_mut().poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<IoResult<()>> {
+        self.get_mut().inner.as_mut().poll_shutdown(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::io::{ByteBuffersDirectory, Directory, IoContext, MergeInfo, RateLimitedDirectory, SimpleRateLimiter},
+        std::{
+            sync::Arc,
+            time::{Duration, Instant},
+        },
+        tokio::io::{AsyncReadExt, AsyncWriteExt},
+    };
+
+    async fn timed_write(dir: &mut RateLimitedDirectory<ByteBuffersDirectory>, context: &IoContext) -> Duration {
+        let data = vec![7u8; 512 * 1024];
+        let start = Instant::now();
+        let mut w = dir.create("_0.cfs", context).await.unwrap();
+        for chunk in data.chunks(4096) {
+            w.write_all(chunk).await.unwrap();
+        }
+        w.shutdown().await.unwrap();
+        start.elapsed()
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_merge_writes_are_throttled() {
+        let limiter = Arc::new(SimpleRateLimiter::new(4.0));
+        let mut dir = RateLimitedDirectory::new(ByteBuffersDirectory::new(), limiter);
+
+        // 512 KiB at 4 MB/sec should take roughly 125ms.
+        let elapsed = timed_write(&mut dir, &IoContext::Merge(MergeInfo::default())).await;
+        assert!(elapsed >= Duration::from_millis(90), "merge write took {elapsed:?}");
+
+        let mut contents = Vec::new();
+        dir.open("_0.cfs", &IoContext::Read).await.unwrap().read_to_end(&mut contents).await.unwrap();
+        assert_eq!(contents.len(), 512 * 1024);
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_flush_writes_are_not_throttled() {
+        let limiter = Arc::new(SimpleRateLimiter::new(0.1));
+        let mut dir = RateLimitedDirectory::new(ByteBuffersDirectory::new(), limiter);
+        let elapsed = timed_write(&mut dir, &IoContext::Default).await;
+        assert!(elapsed < Duration::from_secs(1), "flush write took {elapsed:?}");
+    }
+}
@@ -0,0 +1,125 @@
+use std::{
+    fmt::Debug,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// The minimum amount of time between pause checks. Pausing more often than this is not worth the overhead.
+const MIN_PAUSE_CHECK: Duration = Duration::from_millis(5);
+
+const BYTES_PER_MB: f64 = 1024.0 * 1024.0;
+
+/// Abstract base for rate limiting I/O. Implementations must be safe to share across threads; the rate may be changed
+/// at any time (for example, by a merge scheduler reacting to load).
+pub trait RateLimiter: Debug + Send + Sync {
+    /// Returns the current rate limit in megabytes per second. [f64::INFINITY] means unlimited.
+    fn mb_per_sec(&self) -> f64;
+
+    /// Sets the rate limit in megabytes per second. [f64::INFINITY] means unlimited.
+    fn set_mb_per_sec(&self, mb_per_sec: f64);
+
+    /// Records that `bytes` bytes have been written since the last call and returns how long the caller should pause
+    /// to stay under the rate limit. A zero duration means no pause is needed.
+    fn pause(&self, bytes: u64) -> Duration;
+
+    /// Returns how many bytes the caller should write before calling [RateLimiter::pause].
+    fn min_pause_check_bytes(&self) -> u64;
+}
+
+#[derive(Debug)]
+struct SimpleRateLimiterState {
+    mb_per_sec: f64,
+    min_pause_check_bytes: u64,
+    last: Instant,
+}
+
+/// A simple [RateLimiter] that paces writes to a fixed number of megabytes per second.
+#[derive(Debug)]
+pub struct SimpleRateLimiter {
+    state: Mutex<SimpleRateLimiterState>,
+}
+
+impl SimpleRateLimiter {
+    /// Create a new rate limiter that allows `mb_per_sec` megabytes per second.
+    pub fn new(mb_per_sec: f64) -> Self {
+        Self {
+            state: Mutex::new(SimpleRateLimiterState {
+                mb_per_sec,
+                min_pause_check_bytes: min_pause_check_bytes(mb_per_sec),
+                last: Instant::now(),
+            }),
+        }
+    }
+}
+
+impl RateLimiter for SimpleRateLimiter {
+    fn mb_per_sec(&self) -> f64 {
+        self.state.lock().unwrap_or_else(|e| e.into_inner()).mb_per_sec
+    }
+
+    fn set_mb_per_sec(&self, mb_per_sec: f64) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.mb_per_sec = mb_per_sec;
+        state.min_pause_check_bytes = min_pause_check_bytes(mb_per_sec);
+    }
+
+    fn pause(&self, bytes: u64) -> Duration {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        if !state.mb_per_sec.is_finite() || state.mb_per_sec <= 0.0 {
+            return Duration::ZERO;
+        }
+
+        // Advance the "last" pointer by the time these bytes should have taken at the configured rate; if we're
+        // behind that target, the caller needs to wait for the difference.
+        let now = Instant::now();
+        let target = state.last + Duration::from_secs_f64(bytes as f64 / BYTES_PER_MB / state.mb_per_sec);
+        if target <= now {
+            state.last = now;
+            Duration::ZERO
+        } else {
+            state.last = target;
+            target - now
+        }
+    }
+
+    fn min_pause_check_bytes(&self) -> u64 {
+        self.state.lock().unwrap_or_else(|e| e.into_inner()).min_pause_check_bytes
+    }
+}
+
+fn min_pause_check_bytes(mb_per_sec: f64) -> u64 {
+    if !mb_per_sec.is_finite() || mb_per_sec <= 0.0 {
+        u64::MAX
+    } else {
+        ((mb_per_sec * BYTES_PER_MB * MIN_PAUSE_CHECK.as_secs_f64()) as u64).max(1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::io::{RateLimiter, SimpleRateLimiter},
+        std::time::Duration,
+    };
+
+    #[test]
+    fn test_unlimited_never_pauses() {
+        let limiter = SimpleRateLimiter::new(f64::INFINITY);
+        assert_eq!(limiter.min_pause_check_bytes(), u64::MAX);
+        assert_eq!(limiter.pause(1 << 30), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_pause_is_proportional_to_bytes() {
+        let limiter = SimpleRateLimiter::new(1.0);
+        assert_eq!(limiter.min_pause_check_bytes(), 5242);
+
+        // Writing 1 MB at 1 MB/sec should require pausing for close to a second.
+        let pause = limiter.pause(1024 * 1024);
+        assert!(pause > Duration::from_millis(900), "pause was {pause:?}");
+        assert!(pause <= Duration::from_secs(1), "pause was {pause:?}");
+
+        limiter.set_mb_per_sec(f64::INFINITY);
+        assert_eq!(limiter.pause(1024 * 1024), Duration::ZERO);
+    }
+}
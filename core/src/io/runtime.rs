@@ -0,0 +1,159 @@
+use std::{
+    fmt::Debug,
+    future::Future,
+    io::Result as IoResult,
+    pin::{pin, Pin},
+    sync::Arc,
+    task::{Context, Poll, Wake, Waker},
+    thread::{self, Thread},
+    time::Duration,
+};
+
+/// The services the crate needs from an async runtime.
+///
+/// I/O is written against the [AsyncRead](tokio::io::AsyncRead) and [AsyncWrite](tokio::io::AsyncWrite) traits,
+/// which don't depend on the tokio executor, so in-memory directories such as
+/// [ByteBuffersDirectory](crate::io::ByteBuffersDirectory) work under any executor, or with none at all through
+/// [block_on]. Only timers and the filesystem need an executor: timers go through this trait, and the filesystem
+/// directory in [crate::fs] is only available with the `tokio-runtime` feature (enabled by default).
+///
+/// To use another executor, such as async-std or smol, implement this trait with its timer and pass it to the
+/// components that need one, such as [RateLimitedDirectory::set_runtime](crate::io::RateLimitedDirectory).
+pub trait Runtime: Debug + Send + Sync {
+    /// Returns a future that completes once `duration` has elapsed.
+    fn sleep(&self, duration: Duration) -> Pin<Box<dyn Future<Output = ()> + Send>>;
+}
+
+/// A [Runtime] backed by tokio. Its futures must be polled within a tokio runtime.
+#[cfg(feature = "tokio-runtime")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TokioRuntime;
+
+#[cfg(feature = "tokio-runtime")]
+impl Runtime for TokioRuntime {
+    fn sleep(&self, duration: Duration) -> Pin<Box<dyn Future<Output = ()> + Send>> {
+        Box::pin(tokio::time::sleep(duration))
+    }
+}
+
+/// A [Runtime] for fully synchronous use, typically with [block_on]: sleeping blocks the calling thread.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct BlockingRuntime;
+
+impl Runtime for BlockingRuntime {
+    fn sleep(&self, duration: Duration) -> Pin<Box<dyn Future<Output = ()> + Send>> {
+        thread::sleep(duration);
+        Box::pin(std::future::ready(()))
+    }
+}
+
+/// Returns the runtime used when none is configured: [TokioRuntime] with the `tokio-runtime` feature, and
+/// [BlockingRuntime] otherwise.
+pub fn default_runtime() -> Arc<dyn Runtime> {
+    #[cfg(feature = "tokio-runtime")]
+    return Arc::new(TokioRuntime);
+
+    #[cfg(not(feature = "tokio-runtime"))]
+    return Arc::new(BlockingRuntime);
+}
+
+/// Runs a future to completion on the current thread, parking the thread while the future waits.
+///
+/// This lets the asynchronous APIs of the crate be called from synchronous code without an executor, as long as the
+/// future doesn't depend on a particular runtime: in-memory directories and the [BlockingRuntime] are fine, while
+/// the tokio-backed filesystem directory is not.
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = pin!(future);
+    let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+    let mut context = Context::from_waker(&waker);
+    loop {
+        match future.as_mut().poll(&mut context) {
+            Poll::Ready(output) => return output,
+            Poll::Pending => thread::park(),
+        }
+    }
+}
+
+/// Drives asynchronous code to completion from synchronous code, for callers without an async runtime.
+///
+/// With the `tokio-runtime` feature, futures run on a private single-threaded tokio runtime, so that tokio-backed
+/// I/O such as the filesystem directory works; otherwise they run with [block_on]. In either case, an executor must
+/// not be used from within an asynchronous context, as blocking there would stall the caller's runtime.
+#[derive(Debug)]
+pub struct BlockingExecutor {
+    #[cfg(feature = "tokio-runtime")]
+    runtime: tokio::runtime::Runtime,
+}
+
+impl BlockingExecutor {
+    /// Creates an executor.
+    pub fn new() -> IoResult<Self> {
+        Ok(Self {
+            #[cfg(feature = "tokio-runtime")]
+            runtime: tokio::runtime::Builder::new_current_thread().enable_all().build()?,
+        })
+    }
+
+    /// Runs a future to completion on the current thread.
+    pub fn block_on<F: Future>(&self, future: F) -> F::Output {
+        #[cfg(feature = "tokio-runtime")]
+        return self.runtime.block_on(future);
+
+        #[cfg(not(feature = "tokio-runtime"))]
+        return block_on(future);
+    }
+}
+
+/// Wakes a thread parked by [block_on].
+struct ThreadWaker(Thread);
+
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::io::{
+            block_on, BlockingRuntime, ByteBuffersDirectory, Directory, IoContext, MergeInfo, RateLimitedDirectory,
+            SimpleRateLimiter,
+        },
+        pretty_assertions::assert_eq,
+        std::{
+            sync::Arc,
+            time::{Duration, Instant},
+        },
+        tokio::io::{AsyncReadExt, AsyncWriteExt},
+    };
+
+    #[test]
+    fn test_block_on() {
+        let mut dir = RateLimitedDirectory::new(ByteBuffersDirectory::new(), Arc::new(SimpleRateLimiter::new(4.0)));
+        dir.set_runtime(Arc::new(BlockingRuntime));
+
+        // Throttled merge writes work without an executor: 512 KiB at 4 MB/sec takes roughly 125ms.
+        let start = Instant::now();
+        block_on(async {
+            let mut w = dir.create("_0.cfs", &IoContext::Merge(MergeInfo::default())).await.unwrap();
+            for chunk in vec![7u8; 512 * 1024].chunks(4096) {
+                w.write_all(chunk).await.unwrap();
+            }
+            w.shutdown().await.unwrap();
+        });
+        assert!(start.elapsed() >= Duration::from_millis(90), "merge write took {:?}", start.elapsed());
+
+        let contents = block_on(async {
+            let mut contents = Vec::new();
+            dir.open("_0.cfs", &IoContext::Read).await.unwrap().read_to_end(&mut contents).await.unwrap();
+            contents
+        });
+        assert_eq!(contents.len(), 512 * 1024);
+        assert_eq!(block_on(dir.read_dir()).unwrap(), vec!["_0.cfs".to_string()]);
+    }
+}
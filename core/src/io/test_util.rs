@@ -0,0 +1,71 @@
+//! Fixtures shared by the tests of code that writes to a [Directory].
+
+use {
+    crate::{
+        io::{ByteBuffersDirectory, Directory, IoContext, Lock},
+        BoxResult,
+    },
+    async_trait::async_trait,
+    std::{
+        io::Result as IoResult,
+        pin::Pin,
+        sync::{Arc, Mutex},
+    },
+    tokio::io::{AsyncRead, AsyncWrite},
+};
+
+/// An in-memory directory that records the renames and syncs made through it, shared by its clones.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct SyncRecordingDirectory {
+    pub(crate) inner: ByteBuffersDirectory,
+    events: Arc<Mutex<Vec<String>>>,
+}
+
+impl SyncRecordingDirectory {
+    /// Returns the events recorded so far, such as `"sync a b"` or `"rename a b"`, and clears them.
+    pub(crate) fn take_events(&self) -> Vec<String> {
+        std::mem::take(&mut self.events.lock().unwrap())
+    }
+
+    fn record(&self, event: String) {
+        self.events.lock().unwrap().push(event);
+    }
+}
+
+#[async_trait(?Send)]
+impl Directory for SyncRecordingDirectory {
+    async fn read_dir(&self) -> IoResult<Vec<String>> {
+        self.inner.read_dir().await
+    }
+
+    async fn create(&mut self, file_name: &str, context: &IoContext) -> IoResult<Pin<Box<dyn AsyncWrite + Send>>> {
+        self.inner.create(file_name, context).await
+    }
+
+    async fn open(&mut self, file_name: &str, context: &IoContext) -> IoResult<Pin<Box<dyn AsyncRead>>> {
+        self.inner.open(file_name, context).await
+    }
+
+    async fn remove(&mut self, file_name: &str) -> IoResult<()> {
+        self.inner.remove(file_name).await
+    }
+
+    async fn rename(&mut self, old_file_name: &str, new_file_name: &str) -> IoResult<()> {
+        self.record(format!("rename {old_file_name} {new_file_name}"));
+        self.inner.rename(old_file_name, new_file_name).await
+    }
+
+    async fn sync(&mut self, file_names: &[&str]) -> IoResult<()> {
+        self.record(format!("sync {}", file_names.join(" ")));
+        Ok(())
+    }
+
+    async fn sync_meta_data(&mut self) -> IoResult<()> {
+        self.record("sync_meta_data".to_string());
+        Ok(())
+    }
+
+    async fn obtain_lock(&mut self, lock_name: &str) -> BoxResult<Box<dyn Lock>> {
+        self.inner.obtain_lock(lock_name).await
+    }
+}
@@ -9,19 +9,48 @@ mod error;
 mod id;
 mod version;
 
+/// Text analysis: converting text into indexed tokens.
+pub mod analysis;
+
+/// Export of search results to Apache Arrow record batches and Parquet files.
+#[cfg(feature = "arrow")]
+pub mod arrow;
+
 /// Codec related types and functionality.
 pub mod codec;
 
+/// Documents and fields: the units of indexing.
+pub mod document;
+
+/// Expressions for computing per-document values and scores.
+pub mod expressions;
+
+/// Faceted search: counting the values of the documents matching a query.
+pub mod facet;
+
 /// Lucene index-on-disk types and functionality.
+#[cfg(feature = "tokio-runtime")]
 pub mod fs;
 
+/// Geospatial encoding and distance calculations.
+pub mod geo;
+
 /// Generic Lucene I/O types.
 pub mod io;
 
 /// Lucene index (database) types.
 pub mod index;
 
+/// Metrics: hooks reporting search and indexing activity for monitoring.
+pub mod metrics;
+
+/// Monitoring: matching documents against a set of stored queries.
+pub mod monitor;
+
 /// Lucene search types.
 pub mod search;
 
+/// Utility types and functionality.
+pub mod util;
+
 pub use {error::*, id::*, io::*, version::*};
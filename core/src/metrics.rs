@@ -0,0 +1,4 @@
+mod memory_metrics_recorder;
+mod metrics_recorder;
+
+pub use {memory_metrics_recorder::*, metrics_recorder::*};
@@ -0,0 +1,108 @@
+use {
+    crate::metrics::MetricsRecorder,
+    std::{collections::HashMap, sync::Mutex},
+};
+
+/// A [MetricsRecorder] that keeps metrics in memory, for tests and simple monitoring.
+#[derive(Debug, Default)]
+pub struct MemoryMetricsRecorder {
+    counters: Mutex<HashMap<String, u64>>,
+    gauges: Mutex<HashMap<String, f64>>,
+    histograms: Mutex<HashMap<String, Vec<f64>>>,
+}
+
+impl MemoryMetricsRecorder {
+    /// Creates a recorder with no metrics.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the value of a counter, or 0 if it was never incremented.
+    pub fn counter(&self, name: &str) -> u64 {
+        self.counters.lock().unwrap().get(name).copied().unwrap_or(0)
+    }
+
+    /// Returns the last value of a gauge, or `None` if it was never set.
+    pub fn gauge(&self, name: &str) -> Option<f64> {
+        self.gauges.lock().unwrap().get(name).copied()
+    }
+
+    /// Returns the observations of a histogram, in the order they were recorded.
+    pub fn histogram(&self, name: &str) -> Vec<f64> {
+        self.histograms.lock().unwrap().get(name).cloned().unwrap_or_default()
+    }
+
+    /// Clears every metric.
+    pub fn reset(&self) {
+        self.counters.lock().unwrap().clear();
+        self.gauges.lock().unwrap().clear();
+        self.histograms.lock().unwrap().clear();
+    }
+}
+
+impl MetricsRecorder for MemoryMetricsRecorder {
+    fn increment_counter(&self, name: &str, value: u64) {
+        *self.counters.lock().unwrap().entry(name.to_string()).or_default() += value;
+    }
+
+    fn set_gauge(&self, name: &str, value: f64) {
+        self.gauges.lock().unwrap().insert(name.to_string(), value);
+    }
+
+    fn record_histogram(&self, name: &str, value: f64) {
+        self.histograms.lock().unwrap().entry(name.to_string()).or_default().push(value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{
+            analysis::SimpleAnalyzer,
+            document::{Document, Field, Store},
+            index::{LeafReader, MemorySegmentBuilder},
+            metrics::{
+                MemoryMetricsRecorder, FLUSH_COUNT, FLUSH_DOCS, FLUSH_LATENCY_SECONDS, SEARCHER_SEGMENTS, SEARCH_COUNT,
+                SEARCH_ERRORS, SEARCH_LATENCY_SECONDS, SEARCH_TIMED_OUT,
+            },
+            search::{test_util::leaf_searcher, MatchAllDocsQuery, TopScoreDocCollectorManager},
+        },
+        pretty_assertions::assert_eq,
+        std::sync::Arc,
+    };
+
+    #[test]
+    fn test_metrics() {
+        let metrics = Arc::new(MemoryMetricsRecorder::new());
+        let mut segments: Vec<Arc<dyn LeafReader>> = Vec::new();
+        for docs in [2, 3] {
+            let mut builder = MemorySegmentBuilder::new(Arc::new(SimpleAnalyzer));
+            builder.set_metrics(Some(metrics.clone()));
+            for _ in 0..docs {
+                let mut doc = Document::new();
+                doc.add(Field::text("body", "rust", Store::No));
+                builder.add_document(&doc).unwrap();
+            }
+            segments.push(Arc::new(builder.build()));
+        }
+        assert_eq!(metrics.counter(FLUSH_COUNT), 2);
+        assert_eq!(metrics.histogram(FLUSH_DOCS), vec![2.0, 3.0]);
+        assert_eq!(metrics.histogram(FLUSH_LATENCY_SECONDS).len(), 2);
+
+        let mut searcher = leaf_searcher(segments);
+        searcher.set_metrics(Some(metrics.clone()));
+        assert_eq!(searcher.search(&MatchAllDocsQuery, 10).unwrap().total_hits.value, 5);
+        searcher.search_with_manager(&MatchAllDocsQuery, &TopScoreDocCollectorManager::new(10)).unwrap();
+        assert_eq!(metrics.counter(SEARCH_COUNT), 2);
+        assert_eq!(metrics.counter(SEARCH_ERRORS), 0);
+        assert_eq!(metrics.counter(SEARCH_TIMED_OUT), 0);
+        assert_eq!(metrics.gauge(SEARCHER_SEGMENTS), Some(2.0));
+        let latencies = metrics.histogram(SEARCH_LATENCY_SECONDS);
+        assert_eq!(latencies.len(), 2);
+        assert!(latencies.iter().all(|latency| *latency >= 0.0));
+
+        metrics.reset();
+        assert_eq!(metrics.counter(SEARCH_COUNT), 0);
+        assert_eq!(metrics.gauge(SEARCHER_SEGMENTS), None);
+    }
+}
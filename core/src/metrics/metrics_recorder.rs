@@ -0,0 +1,60 @@
+use std::fmt::Debug;
+
+/// Counter: the number of searches run, whether or not they succeeded.
+pub const SEARCH_COUNT: &str = "lucene.search.count";
+
+/// Counter: the number of searches that failed with an error.
+pub const SEARCH_ERRORS: &str = "lucene.search.errors";
+
+/// Counter: the number of searches stopped by their timeout, returning partial results.
+pub const SEARCH_TIMED_OUT: &str = "lucene.search.timed_out";
+
+/// Histogram: the wall-clock duration of searches, in seconds.
+pub const SEARCH_LATENCY_SECONDS: &str = "lucene.search.latency_seconds";
+
+/// Gauge: the number of segments of the reader being searched, updated on every search.
+pub const SEARCHER_SEGMENTS: &str = "lucene.searcher.segments";
+
+/// Counter: the number of segments flushed.
+pub const FLUSH_COUNT: &str = "lucene.flush.count";
+
+/// Histogram: the time taken to flush a segment, in seconds.
+pub const FLUSH_LATENCY_SECONDS: &str = "lucene.flush.latency_seconds";
+
+/// Histogram: the number of documents in each flushed segment.
+pub const FLUSH_DOCS: &str = "lucene.flush.docs";
+
+/// Histogram: the time taken to index a batch of documents of a stream, including any flush it triggered, in seconds.
+pub const INGEST_BATCH_LATENCY_SECONDS: &str = "lucene.ingest.batch_latency_seconds";
+
+/// Counter: the number of stored documents found in a [crate::index::StoredFieldsCache].
+pub const STORED_FIELDS_CACHE_HITS: &str = "lucene.stored_fields_cache.hits";
+
+/// Counter: the number of stored documents whose block was loaded into a [crate::index::StoredFieldsCache].
+pub const STORED_FIELDS_CACHE_MISSES: &str = "lucene.stored_fields_cache.misses";
+
+/// Counter: the number of blocks evicted from a [crate::index::StoredFieldsCache] to make room for others.
+pub const STORED_FIELDS_CACHE_EVICTIONS: &str = "lucene.stored_fields_cache.evictions";
+
+/// Gauge: the memory used by a [crate::index::StoredFieldsCache], in bytes.
+pub const STORED_FIELDS_CACHE_RAM_BYTES: &str = "lucene.stored_fields_cache.ram_bytes";
+
+/// Receives the metrics emitted by searchers, segment builders and caches, so that operators can monitor the engine.
+///
+/// A recorder is attached to each component that should report metrics, such as with
+/// [crate::search::IndexSearcher::set_metrics], [crate::index::IndexWriterConfig::set_metrics],
+/// [crate::index::MemorySegmentBuilder::set_metrics] and [crate::index::StoredFieldsCache::set_metrics]. The metric
+/// names are the constants of this module. Implementations typically forward to a metrics library; every method does
+/// nothing by default, so implementations only need to handle the kinds of metric they care about.
+///
+/// Recorders are called on the searching or indexing thread and should return quickly.
+pub trait MetricsRecorder: Debug + Send + Sync {
+    /// Adds `value` to a counter.
+    fn increment_counter(&self, _name: &str, _value: u64) {}
+
+    /// Sets a gauge to `value`.
+    fn set_gauge(&self, _name: &str, _value: f64) {}
+
+    /// Records an observation of a histogram.
+    fn record_histogram(&self, _name: &str, _value: f64) {}
+}
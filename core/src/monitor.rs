@@ -0,0 +1,7 @@
+mod matching_queries;
+#[allow(clippy::module_inception)]
+mod monitor;
+mod monitor_query;
+mod query_analyzer;
+
+pub use {matching_queries::*, monitor::*, monitor_query::*, query_analyzer::*};
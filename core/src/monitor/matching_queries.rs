@@ -0,0 +1,113 @@
+use {
+    crate::BoxError,
+    std::collections::{BTreeMap, BTreeSet},
+};
+
+/// The registered queries that matched a document, as returned by [crate::monitor::Monitor::match_document].
+#[derive(Debug)]
+pub struct MatchingQueries {
+    matches: BTreeSet<String>,
+    queries_run: usize,
+    errors: BTreeMap<String, BoxError>,
+}
+
+impl MatchingQueries {
+    pub(crate) fn new(matches: BTreeSet<String>, queries_run: usize, errors: BTreeMap<String, BoxError>) -> Self {
+        Self {
+            matches,
+            queries_run,
+            errors,
+        }
+    }
+
+    /// Returns the ids of the matching queries.
+    #[inline]
+    pub fn matches(&self) -> &BTreeSet<String> {
+        &self.matches
+    }
+
+    /// Indicates whether the query with the given id matched.
+    #[inline]
+    pub fn matches_query(&self, id: &str) -> bool {
+        self.matches.contains(id)
+    }
+
+    /// Returns the number of matching queries.
+    #[inline]
+    pub fn match_count(&self) -> usize {
+        self.matches.len()
+    }
+
+    /// Returns the number of queries that were run after pre-filtering.
+    #[inline]
+    pub fn queries_run(&self) -> usize {
+        self.queries_run
+    }
+
+    /// Returns the errors raised by queries, by query id. A failing query doesn't stop the others from running.
+    #[inline]
+    pub fn errors(&self) -> &BTreeMap<String, BoxError> {
+        &self.errors
+    }
+}
+
+/// The registered queries that matched each document of a batch, as returned by
+/// [crate::monitor::Monitor::match_documents].
+#[derive(Debug)]
+pub struct MultiMatchingQueries {
+    matches: Vec<BTreeSet<String>>,
+    queries_run: usize,
+    errors: BTreeMap<String, BoxError>,
+}
+
+impl MultiMatchingQueries {
+    pub(crate) fn new(matches: Vec<BTreeSet<String>>, queries_run: usize, errors: BTreeMap<String, BoxError>) -> Self {
+        Self {
+            matches,
+            queries_run,
+            errors,
+        }
+    }
+
+    /// Returns the number of documents in the batch.
+    #[inline]
+    pub fn batch_size(&self) -> usize {
+        self.matches.len()
+    }
+
+    /// Returns the ids of the queries matching the document at index `doc` in the batch.
+    ///
+    /// # Panics
+    /// Panics if `doc` is outside of the batch.
+    #[inline]
+    pub fn matches(&self, doc: usize) -> &BTreeSet<String> {
+        &self.matches[doc]
+    }
+
+    /// Returns the number of queries matching the document at index `doc` in the batch.
+    ///
+    /// # Panics
+    /// Panics if `doc` is outside of the batch.
+    #[inline]
+    pub fn match_count(&self, doc: usize) -> usize {
+        self.matches[doc].len()
+    }
+
+    /// Returns the number of queries that were run after pre-filtering. Each runs once against the whole batch.
+    #[inline]
+    pub fn queries_run(&self) -> usize {
+        self.queries_run
+    }
+
+    /// Returns the errors raised by queries, by query id. A failing query doesn't stop the others from running.
+    #[inline]
+    pub fn errors(&self) -> &BTreeMap<String, BoxError> {
+        &self.errors
+    }
+
+    /// Splits the results into those of the first document, for batches of one.
+    pub(crate) fn into_single(mut self) -> MatchingQueries {
+        let matches = self.matches.pop().unwrap_or_default();
+        MatchingQueries::new(matches, self.queries_run, self.errors)
+    }
+}
@@ -0,0 +1,271 @@
+use {
+    crate::{
+        analysis::Analyzer,
+        document::Document,
+        index::{LeafReader, MemorySegment, MemorySegmentBuilder, MultiReader, Term},
+        monitor::{extract_query_terms, MatchingQueries, MonitorQuery, MultiMatchingQueries},
+        search::IndexSearcher,
+        BoxResult,
+    },
+    std::{
+        collections::{BTreeMap, BTreeSet, HashMap},
+        sync::Arc,
+    },
+};
+
+/// Matches incoming documents against a set of registered queries, reporting which queries each document matches,
+/// as in Lucene's `Monitor`. This reverses the usual roles of documents and queries, as for alerting on new content.
+///
+/// Running every query against every document would be slow with thousands of queries, so queries are pre-filtered:
+/// when a query is registered, [extract_query_terms] finds terms at least one of which must be present in any matching
+/// document, and these are kept in an index from term to query. Documents are indexed into an in-memory segment and
+/// only the queries indexed under one of their terms, along with the queries no terms could be extracted from, are
+/// run.
+#[derive(Debug)]
+pub struct Monitor {
+    analyzer: Arc<dyn Analyzer>,
+    queries: BTreeMap<String, RegisteredQuery>,
+
+    /// The ids of the queries to run for documents containing a term, by field and then by term bytes.
+    term_index: HashMap<String, HashMap<Vec<u8>, BTreeSet<String>>>,
+
+    /// The ids of the queries that must run for every document.
+    unfiltered: BTreeSet<String>,
+}
+
+#[derive(Debug)]
+struct RegisteredQuery {
+    query: MonitorQuery,
+    terms: Option<BTreeSet<Term>>,
+}
+
+impl Monitor {
+    /// Creates a monitor with no queries that analyzes documents with the given analyzer, which should be the one the
+    /// queries were written for.
+    pub fn new(analyzer: Arc<dyn Analyzer>) -> Self {
+        Self {
+            analyzer,
+            queries: BTreeMap::new(),
+            term_index: HashMap::new(),
+            unfiltered: BTreeSet::new(),
+        }
+    }
+
+    /// Registers a query, replacing any query with the same id.
+    pub fn register(&mut self, query: MonitorQuery) {
+        self.delete_by_id(query.id());
+
+        let id = query.id().to_string();
+        let terms = extract_query_terms(query.query().as_ref());
+        match &terms {
+            Some(terms) => {
+                for term in terms {
+                    let by_term = self.term_index.entry(term.field().to_string()).or_default();
+                    by_term.entry(term.bytes().to_vec()).or_default().insert(id.clone());
+                }
+            }
+            None => {
+                self.unfiltered.insert(id.clone());
+            }
+        }
+
+        self.queries.insert(
+            id,
+            RegisteredQuery {
+                query,
+                terms,
+            },
+        );
+    }
+
+    /// Registers several queries; see [Monitor::register].
+    pub fn register_all(&mut self, queries: impl IntoIterator<Item = MonitorQuery>) {
+        for query in queries {
+            self.register(query);
+        }
+    }
+
+    /// Removes the query with the given id, returning it if it was registered.
+    pub fn delete_by_id(&mut self, id: &str) -> Option<MonitorQuery> {
+        let registered = self.queries.remove(id)?;
+        match &registered.terms {
+            Some(terms) => {
+                for term in terms {
+                    let Some(by_term) = self.term_index.get_mut(term.field()) else {
+                        continue;
+                    };
+
+                    if let Some(ids) = by_term.get_mut(term.bytes()) {
+                        ids.remove(id);
+                        if ids.is_empty() {
+                            by_term.remove(term.bytes());
+                        }
+                    }
+
+                    if by_term.is_empty() {
+                        self.term_index.remove(term.field());
+                    }
+                }
+            }
+            None => {
+                self.unfiltered.remove(id);
+            }
+        }
+
+        Some(registered.query)
+    }
+
+    /// Removes every query.
+    pub fn clear(&mut self) {
+        self.queries.clear();
+        self.term_index.clear();
+        self.unfiltered.clear();
+    }
+
+    /// Returns the query with the given id, if registered.
+    #[inline]
+    pub fn get_query(&self, id: &str) -> Option<&MonitorQuery> {
+        self.queries.get(id).map(|registered| &registered.query)
+    }
+
+    /// Returns the number of registered queries.
+    #[inline]
+    pub fn query_count(&self) -> usize {
+        self.queries.len()
+    }
+
+    /// Returns the queries matching a document.
+    pub fn match_document(&self, document: &Document) -> BoxResult<MatchingQueries> {
+        Ok(self.match_documents(std::slice::from_ref(document))?.into_single())
+    }
+
+    /// Returns the queries matching each document of a batch. The batch is indexed into a single segment, so each
+    /// candidate query runs once for the whole batch.
+    pub fn match_documents(&self, documents: &[Document]) -> BoxResult<MultiMatchingQueries> {
+        let mut builder = MemorySegmentBuilder::new(self.analyzer.clone());
+        for document in documents {
+            builder.add_document(document)?;
+        }
+        let segment = builder.build();
+
+        let candidates = self.candidates(&segment)?;
+        let segments: Vec<Arc<dyn LeafReader>> = vec![Arc::new(segment)];
+        let searcher = IndexSearcher::new(Arc::new(MultiReader::new(segments)?));
+
+        let mut matches = vec![BTreeSet::new(); documents.len()];
+        let mut errors = BTreeMap::new();
+        for id in candidates.iter() {
+            let query = self.queries[*id].query.query();
+            match searcher.search(query.as_ref(), documents.len().max(1)) {
+                Ok(top_docs) => {
+                    for score_doc in top_docs.score_docs {
+                        matches[score_doc.doc as usize].insert(id.to_string());
+                    }
+                }
+                Err(e) => {
+                    errors.insert(id.to_string(), e);
+                }
+            }
+        }
+
+        Ok(MultiMatchingQueries::new(matches, candidates.len(), errors))
+    }
+
+    /// Returns the ids of the queries that may match a document in the segment.
+    fn candidates(&self, segment: &MemorySegment) -> BoxResult<BTreeSet<&str>> {
+        let mut candidates: BTreeSet<&str> = self.unfiltered.iter().map(String::as_str).collect();
+        for (field, by_term) in self.term_index.iter() {
+            let Some(terms) = segment.terms(field)? else {
+                continue;
+            };
+
+            let mut terms_enum = terms.iterator()?;
+            while let Some(term) = terms_enum.next()? {
+                if let Some(ids) = by_term.get(term) {
+                    candidates.extend(ids.iter().map(String::as_str));
+                }
+            }
+        }
+
+        Ok(candidates)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{
+            analysis::SimpleAnalyzer,
+            document::{Document, Field, Store},
+            index::Term,
+            monitor::{Monitor, MonitorQuery},
+            search::{BooleanQuery, MatchAllDocsQuery, Occur, Query, TermQuery},
+        },
+        pretty_assertions::assert_eq,
+        std::{collections::BTreeSet, sync::Arc},
+    };
+
+    fn term(text: &str) -> Arc<dyn Query> {
+        Arc::new(TermQuery::new(Term::from_text("body", text)))
+    }
+
+    fn doc(text: &str) -> Document {
+        let mut doc = Document::new();
+        doc.add(Field::text("body", text, Store::No));
+        doc
+    }
+
+    fn ids(ids: &[&str]) -> BTreeSet<String> {
+        ids.iter().map(|id| id.to_string()).collect()
+    }
+
+    #[test]
+    fn test_monitor() {
+        let mut monitor = Monitor::new(Arc::new(SimpleAnalyzer));
+        monitor.register_all([
+            MonitorQuery::new("fox", term("fox")),
+            MonitorQuery::new(
+                "quick-dog",
+                Arc::new(BooleanQuery::builder().add(term("quick"), Occur::Must).add(term("dog"), Occur::Must).build()),
+            ),
+            MonitorQuery::new("not-cat", {
+                let query = BooleanQuery::builder()
+                    .add(Arc::new(MatchAllDocsQuery), Occur::Must)
+                    .add(term("cat"), Occur::MustNot)
+                    .build();
+                Arc::new(query)
+            }),
+            MonitorQuery::new("all", Arc::new(MatchAllDocsQuery)),
+        ]);
+        for i in 0..1000 {
+            monitor.register(MonitorQuery::new(format!("filler-{i}"), term(&format!("word{i}"))));
+        }
+        assert_eq!(monitor.query_count(), 1004);
+
+        let matches = monitor.match_document(&doc("The quick brown fox")).unwrap();
+        assert_eq!(matches.matches(), &ids(&["all", "fox", "not-cat"]));
+        // The conjunction is filtered by one of its terms; the unanalyzable queries always run.
+        assert_eq!(matches.queries_run(), 4);
+        assert!(matches.errors().is_empty());
+
+        let batch = [doc("a quick dog"), doc("the cat saw word7"), doc("nothing")];
+        let matches = monitor.match_documents(&batch).unwrap();
+        assert_eq!(matches.batch_size(), 3);
+        assert_eq!(matches.matches(0), &ids(&["all", "not-cat", "quick-dog"]));
+        assert_eq!(matches.matches(1), &ids(&["all", "filler-7"]));
+        assert_eq!(matches.matches(2), &ids(&["all", "not-cat"]));
+        assert_eq!(matches.queries_run(), 4);
+
+        // Replacing and deleting queries updates the pre-filter.
+        monitor.register(MonitorQuery::new("fox", term("cat")));
+        assert!(monitor.delete_by_id("filler-7").is_some());
+        assert!(monitor.delete_by_id("filler-7").is_none());
+        let matches = monitor.match_document(&doc("the cat saw word7 and a fox")).unwrap();
+        assert_eq!(matches.matches(), &ids(&["all", "fox"]));
+        assert_eq!(monitor.get_query("fox").unwrap().to_string(), "fox: body:cat");
+
+        monitor.clear();
+        assert_eq!(monitor.query_count(), 0);
+        assert_eq!(monitor.match_document(&doc("fox")).unwrap().match_count(), 0);
+    }
+}
@@ -0,0 +1,58 @@
+use {
+    crate::search::Query,
+    std::{
+        collections::BTreeMap,
+        fmt::{Display, Formatter, Result as FmtResult},
+        sync::Arc,
+    },
+};
+
+/// A query registered with a [crate::monitor::Monitor], identified by a unique id and optionally carrying metadata
+/// for the application, such as who to alert when it matches.
+#[derive(Clone, Debug)]
+pub struct MonitorQuery {
+    id: String,
+    query: Arc<dyn Query>,
+    metadata: BTreeMap<String, String>,
+}
+
+impl MonitorQuery {
+    /// Creates a query with the given id.
+    pub fn new(id: impl Into<String>, query: Arc<dyn Query>) -> Self {
+        Self {
+            id: id.into(),
+            query,
+            metadata: BTreeMap::new(),
+        }
+    }
+
+    /// Returns the id.
+    #[inline]
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Returns the query.
+    #[inline]
+    pub fn query(&self) -> &Arc<dyn Query> {
+        &self.query
+    }
+
+    /// Returns the metadata.
+    #[inline]
+    pub fn metadata(&self) -> &BTreeMap<String, String> {
+        &self.metadata
+    }
+
+    /// Sets a metadata entry, replacing any previous value for the key.
+    pub fn set_metadata(&mut self, key: impl Into<String>, value: impl Into<String>) -> &mut Self {
+        self.metadata.insert(key.into(), value.into());
+        self
+    }
+}
+
+impl Display for MonitorQuery {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "{}: {}", self.id, self.query)
+    }
+}
@@ -0,0 +1,141 @@
+use {
+    crate::{
+        index::Term,
+        search::{
+            BooleanQuery, BoostQuery, CombinedFieldQuery, ConstantScoreQuery, FunctionScoreQuery, MatchNoDocsQuery,
+            Occur, Query, TermInSetQuery, TermQuery,
+        },
+    },
+    std::{any::Any, collections::BTreeSet},
+};
+
+/// Extracts terms from a query such that every document matching the query contains at least one of them, or returns
+/// `None` if there are no such terms, as for a query matching every document or one the analysis doesn't understand.
+///
+/// A [crate::monitor::Monitor] uses this to skip the queries that can't match a document. For a conjunction, a single
+/// required clause is enough to guarantee a match is possible, so the clause with the fewest terms is chosen; this
+/// keeps the term index small and the pre-filter selective. Prohibited clauses are ignored.
+pub fn extract_query_terms(query: &dyn Query) -> Option<BTreeSet<Term>> {
+    let query = query as &dyn Any;
+
+    if let Some(query) = query.downcast_ref::<TermQuery>() {
+        return Some(BTreeSet::from([query.term().clone()]));
+    }
+
+    if let Some(query) = query.downcast_ref::<TermInSetQuery>() {
+        return Some(query.terms().iter().map(|bytes| Term::new(query.field(), bytes.clone())).collect());
+    }
+
+    if let Some(query) = query.downcast_ref::<CombinedFieldQuery>() {
+        let mut terms = BTreeSet::new();
+        for (field, _) in query.fields() {
+            terms.extend(query.terms().iter().map(|bytes| Term::new(field.as_str(), bytes.as_slice())));
+        }
+        return Some(terms);
+    }
+
+    if query.is::<MatchNoDocsQuery>() {
+        return Some(BTreeSet::new());
+    }
+
+    if let Some(query) = query.downcast_ref::<BoostQuery>() {
+        return extract_query_terms(query.query().as_ref());
+    }
+
+    if let Some(query) = query.downcast_ref::<ConstantScoreQuery>() {
+        return extract_query_terms(query.query().as_ref());
+    }
+
+    if let Some(query) = query.downcast_ref::<FunctionScoreQuery>() {
+        return extract_query_terms(query.query().as_ref());
+    }
+
+    if let Some(query) = query.downcast_ref::<BooleanQuery>() {
+        return extract_boolean_terms(query);
+    }
+
+    None
+}
+
+fn extract_boolean_terms(query: &BooleanQuery) -> Option<BTreeSet<Term>> {
+    let required: Vec<_> = query.clauses().iter().filter(|clause| clause.is_required()).collect();
+    if !required.is_empty() {
+        return required
+            .into_iter()
+            .filter_map(|clause| extract_query_terms(clause.query().as_ref()))
+            .min_by_key(|terms| terms.len());
+    }
+
+    let mut terms = BTreeSet::new();
+    let mut optional = query.clauses().iter().filter(|clause| clause.occur() == Occur::Should).peekable();
+
+    // Only prohibited clauses: this matches everything else.
+    optional.peek()?;
+
+    for clause in optional {
+        terms.append(&mut extract_query_terms(clause.query().as_ref())?);
+    }
+
+    Some(terms)
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{
+            document::LongRange,
+            index::Term,
+            monitor::extract_query_terms,
+            search::{
+                BooleanQuery, BoostQuery, CombinedFieldQuery, MatchAllDocsQuery, MatchNoDocsQuery, Occur,
+                TermInSetQuery, TermQuery,
+            },
+        },
+        pretty_assertions::assert_eq,
+        std::{collections::BTreeSet, sync::Arc},
+    };
+
+    fn term(text: &str) -> Arc<TermQuery> {
+        Arc::new(TermQuery::new(Term::from_text("body", text)))
+    }
+
+    fn terms(texts: &[&str]) -> Option<BTreeSet<Term>> {
+        Some(texts.iter().map(|text| Term::from_text("body", text)).collect())
+    }
+
+    #[test]
+    fn test_extract_query_terms() {
+        assert_eq!(extract_query_terms(term("a").as_ref()), terms(&["a"]));
+        assert_eq!(extract_query_terms(&TermInSetQuery::new("body", ["a", "b"])), terms(&["a", "b"]));
+        assert_eq!(extract_query_terms(&BoostQuery::new(term("a"), 2.0).unwrap()), terms(&["a"]));
+        assert_eq!(extract_query_terms(&MatchNoDocsQuery::new("nothing")), terms(&[]));
+
+        let combined = CombinedFieldQuery::builder().add_field("title", 2.0).unwrap().add_term("a").build();
+        assert_eq!(extract_query_terms(&combined), Some(BTreeSet::from([Term::from_text("title", "a")])));
+        assert_eq!(extract_query_terms(&MatchAllDocsQuery), None);
+        assert_eq!(extract_query_terms(&LongRange::new_intersects_query("hours", &[1], &[2]).unwrap()), None);
+
+        let disjunction =
+            Arc::new(BooleanQuery::builder().add(term("a"), Occur::Should).add(term("b"), Occur::Should).build());
+        assert_eq!(extract_query_terms(disjunction.as_ref()), terms(&["a", "b"]));
+
+        // The required clause with the fewest terms wins; unanalyzable ones are skipped.
+        let conjunction = BooleanQuery::builder()
+            .add(disjunction.clone(), Occur::Must)
+            .add(term("c"), Occur::Filter)
+            .add(Arc::new(MatchAllDocsQuery), Occur::Must)
+            .add(term("d"), Occur::Should)
+            .add(term("e"), Occur::MustNot)
+            .build();
+        assert_eq!(extract_query_terms(&conjunction), terms(&["c"]));
+
+        let unanalyzable = BooleanQuery::builder()
+            .add(term("a"), Occur::Should)
+            .add(Arc::new(MatchAllDocsQuery), Occur::Should)
+            .build();
+        assert_eq!(extract_query_terms(&unanalyzable), None);
+
+        let prohibited_only = BooleanQuery::builder().add(term("a"), Occur::MustNot).build();
+        assert_eq!(extract_query_terms(&prohibited_only), None);
+    }
+}
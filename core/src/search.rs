@@ -1,2 +1,112 @@
+mod automaton_query;
+mod bm25_similarity;
+mod boolean_clause;
+mod boolean_query;
+mod boolean_scorer;
+mod boolean_similarity;
+mod boost_query;
+mod bulk_scorer;
+mod caching_wrapper_query;
+mod circuit_breaker;
+mod collector;
+mod combined_field_query;
+mod conjunction_scorer;
+mod constant_score_query;
+mod constant_score_scorer;
+mod custom_score_query;
+mod dis_max_query_builder;
+mod disjunction_max_query;
+mod disjunction_max_scorer;
+mod disjunction_sum_scorer;
+mod doc_id_set;
+mod doc_id_set_builder;
+mod doc_id_set_iterator;
+mod doc_values_fetcher;
+mod double_values_source;
+mod explanation;
+mod feature_query;
+mod feature_rescorer;
+mod field_exists_query;
+mod function_score_query;
+mod fuzzy_query;
+mod fuzzy_terms_enum;
+mod global_statistics;
+mod index_or_doc_values_query;
+mod index_searcher;
+mod knn_float_vector_query;
+mod lat_lon_distance_feature_query;
+mod lat_lon_distance_query;
+mod lat_lon_distance_source;
+mod lat_lon_shape_query;
+mod match_all_docs_query;
+mod match_no_docs_query;
+mod min_should_match_sum_scorer;
+mod multi_collector;
+mod multi_phrase_query;
+mod n_gram_phrase_query;
+mod numeric_doc_values_range_query;
+mod payload_decoder;
+mod payload_score_query;
+mod per_field_similarity_wrapper;
+mod phrase_matcher;
+mod phrase_query;
+mod phrase_weight;
+mod point_in_time;
+mod prefix_query;
+mod query;
+mod query_builder;
+mod query_cache;
+#[cfg(feature = "serde")]
+mod query_dsl;
+mod query_rescorer;
+mod query_timeout;
+mod query_visitor;
+mod queue_size_based_executor;
+mod range_field_query;
+mod regexp_query;
+mod req_excl_scorer;
+mod req_opt_sum_scorer;
+mod rescorer;
+mod rewrite_pipeline;
+mod roaring_doc_id_set;
+mod scorer;
+mod scorer_supplier;
+mod similarity;
 mod sort;
-pub use sort::*;
+mod sync_searcher;
+mod term_in_set_query;
+mod term_query;
+#[cfg(test)]
+pub(crate) mod test_util;
+mod top_docs;
+mod top_field_collector;
+mod top_score_doc_collector;
+mod total_hit_count_collector;
+mod two_phase_iterator;
+mod weight;
+mod wildcard_query;
+
+pub use {
+    automaton_query::*, bm25_similarity::*, boolean_clause::*, boolean_query::*, boolean_scorer::*,
+    boolean_similarity::*, boost_query::*, bulk_scorer::*, caching_wrapper_query::*, circuit_breaker::*, collector::*,
+    combined_field_query::*, conjunction_scorer::*, constant_score_query::*, constant_score_scorer::*,
+    custom_score_query::*, dis_max_query_builder::*, disjunction_max_query::*, disjunction_max_scorer::*,
+    disjunction_sum_scorer::*, doc_id_set::*, doc_id_set_builder::*, doc_id_set_iterator::*, doc_values_fetcher::*,
+    double_values_source::*, explanation::*, feature_query::*, feature_rescorer::*, field_exists_query::*,
+    function_score_query::*, fuzzy_query::*, fuzzy_terms_enum::*, global_statistics::*, index_or_doc_values_query::*,
+    index_searcher::*, knn_float_vector_query::*, lat_lon_distance_feature_query::*, lat_lon_distance_query::*,
+    lat_lon_distance_source::*, lat_lon_shape_query::*, match_all_docs_query::*, match_no_docs_query::*,
+    min_should_match_sum_scorer::*, multi_collector::*, multi_phrase_query::*, n_gram_phrase_query::*,
+    numeric_doc_values_range_query::*, payload_decoder::*, payload_score_query::*, per_field_similarity_wrapper::*,
+    phrase_query::*, point_in_time::*, prefix_query::*, query::*, query_builder::*, query_cache::*, query_rescorer::*,
+    query_timeout::*, query_visitor::*, queue_size_based_executor::*, range_field_query::*, regexp_query::*,
+    req_excl_scorer::*, req_opt_sum_scorer::*, rescorer::*, rewrite_pipeline::*, roaring_doc_id_set::*, scorer::*,
+    scorer_supplier::*, similarity::*, sort::*, sync_searcher::*, term_in_set_query::*, term_query::*, top_docs::*,
+    top_field_collector::*, top_score_doc_collector::*, total_hit_count_collector::*, two_phase_iterator::*, weight::*,
+    wildcard_query::*,
+};
+
+#[cfg(feature = "serde")]
+pub use query_dsl::*;
+
+pub(crate) use {phrase_matcher::*, phrase_weight::*};
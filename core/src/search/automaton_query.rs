@@ -0,0 +1,396 @@
+use {
+    crate::{
+        index::{LeafReaderContext, PostingsEnum, Term},
+        search::{
+            BooleanQuery, ConstantScoreScorer, DisjunctionMaxScorer, DocIdSetBuilder, DocIdSetIterator, Explanation,
+            IndexSearcher, MatchNoDocsQuery, Query, QueryMemoryTracker, ScoreMode, Scorer, TermQuery, Weight,
+        },
+        util::automaton::CompiledAutomaton,
+        BoxError, BoxResult, LuceneError,
+    },
+    std::{
+        collections::BTreeMap,
+        fmt::{Display, Formatter, Result as FmtResult},
+        sync::Arc,
+    },
+};
+
+/// The most terms of a segment whose postings [RewriteMethod::ConstantScoreBlended] iterates separately.
+const BLENDED_MAX_TERMS: usize = 16;
+
+/// How a query over the terms accepted by an automaton, such as a [crate::search::PrefixQuery], is executed once its
+/// terms are known.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum RewriteMethod {
+    /// Matches with a constant score equal to the boost. In each segment, the postings of the 16 matching terms in the
+    /// most documents are iterated directly, and the documents of the other terms are gathered into one set, so the
+    /// cost doesn't grow with the number of terms.
+    #[default]
+    ConstantScoreBlended,
+
+    /// Rewrites to a [BooleanQuery] with an optional [TermQuery] for each matching term, so documents are scored by
+    /// the terms they contain.
+    ScoringBoolean,
+
+    /// Like [RewriteMethod::ScoringBoolean], but keeps only the given number of terms: those in the most documents.
+    TopTermsScoringBoolean(usize),
+}
+
+/// A query matching the documents containing a term accepted by an automaton.
+///
+/// This is the core of [crate::search::PrefixQuery], [crate::search::WildcardQuery] and
+/// [crate::search::RegexpQuery]. How the matching terms are turned into matches and scores is chosen by its
+/// [RewriteMethod].
+///
+/// A pattern such as a leading wildcard can match a large part of a field's terms, so a query expands to at most
+/// [AutomatonQuery::max_expansions] terms: its distinct terms in the index for the boolean rewrite methods, and its
+/// terms in each segment for [RewriteMethod::ConstantScoreBlended], which expands each segment separately. A query
+/// matching more terms fails with [LuceneError::TooManyClauses].
+///
+/// The boolean rewrite methods collect the matching terms of every segment while the query is rewritten. The segments
+/// are expanded per slice (see [IndexSearcher::map_slices]), so a concurrent searcher expands them in parallel.
+#[derive(Clone, Debug)]
+pub struct AutomatonQuery {
+    term: Term,
+    automaton: CompiledAutomaton,
+    rewrite_method: RewriteMethod,
+    max_expansions: usize,
+    description: String,
+}
+
+impl AutomatonQuery {
+    /// The default maximum number of terms a query expands to.
+    pub const DEFAULT_MAX_EXPANSIONS: usize = 1024;
+
+    /// Creates a query matching the terms of `term`'s field accepted by `automaton`. The text of `term` describes the
+    /// query.
+    pub fn new(term: Term, automaton: CompiledAutomaton) -> Self {
+        let description = term.to_string();
+        Self::with_description(term, automaton, description)
+    }
+
+    /// Creates a query rendered as `description`.
+    pub(crate) fn with_description(term: Term, automaton: CompiledAutomaton, description: String) -> Self {
+        Self {
+            term,
+            automaton,
+            rewrite_method: RewriteMethod::default(),
+            max_expansions: Self::DEFAULT_MAX_EXPANSIONS,
+            description,
+        }
+    }
+
+    /// Returns the term describing the query, in the field being queried.
+    #[inline]
+    pub fn term(&self) -> &Term {
+        &self.term
+    }
+
+    /// Returns the field being queried.
+    #[inline]
+    pub fn field(&self) -> &str {
+        self.term.field()
+    }
+
+    /// Returns the automaton accepting the matching terms.
+    #[inline]
+    pub fn automaton(&self) -> &CompiledAutomaton {
+        &self.automaton
+    }
+
+    /// Returns how the query is executed.
+    #[inline]
+    pub fn rewrite_method(&self) -> RewriteMethod {
+        self.rewrite_method
+    }
+
+    /// Sets how the query is executed. This is [RewriteMethod::ConstantScoreBlended] by default.
+    pub fn set_rewrite_method(&mut self, rewrite_method: RewriteMethod) -> &mut Self {
+        self.rewrite_method = rewrite_method;
+        self
+    }
+
+    /// Returns the most terms the query expands to.
+    #[inline]
+    pub fn max_expansions(&self) -> usize {
+        self.max_expansions
+    }
+
+    /// Sets the most terms the query expands to. This is [AutomatonQuery::DEFAULT_MAX_EXPANSIONS] by default.
+    pub fn set_max_expansions(&mut self, max_expansions: usize) -> &mut Self {
+        self.max_expansions = max_expansions;
+        self
+    }
+
+    fn too_many_terms(&self) -> BoxError {
+        LuceneError::TooManyClauses(format!("{self} expands to more than {} terms", self.max_expansions)).into()
+    }
+}
+
+impl Query for AutomatonQuery {
+    fn create_weight(
+        &self,
+        searcher: &IndexSearcher,
+        _score_mode: ScoreMode,
+        boost: f32,
+    ) -> BoxResult<Box<dyn Weight>> {
+        Ok(Box::new(AutomatonWeight {
+            query: self.clone(),
+            score: boost,
+            memory_tracker: searcher.memory_tracker(),
+        }))
+    }
+
+    fn rewrite(&self, searcher: &IndexSearcher) -> BoxResult<Option<Arc<dyn Query>>> {
+        let top = match self.rewrite_method {
+            RewriteMethod::ConstantScoreBlended => return Ok(None),
+            RewriteMethod::ScoringBoolean => None,
+            RewriteMethod::TopTermsScoringBoolean(n) => Some(n),
+        };
+
+        // Each slice of segments is expanded separately, on its own thread if the searcher is concurrent.
+        let slice_doc_freqs = searcher.map_slices(|leaves| {
+            let mut doc_freqs: BTreeMap<Vec<u8>, u64> = BTreeMap::new();
+            for leaf in leaves {
+                let Some(terms) = leaf.reader().terms(self.field())? else {
+                    continue;
+                };
+
+                let mut te = self.automaton.terms_enum(terms)?;
+                while let Some(term) = te.next()? {
+                    let term = term.to_vec();
+                    *doc_freqs.entry(term).or_default() += te.doc_freq()? as u64;
+                    if doc_freqs.len() > self.max_expansions {
+                        return Err(self.too_many_terms());
+                    }
+                }
+            }
+            Ok(doc_freqs)
+        })?;
+
+        let mut doc_freqs: BTreeMap<Vec<u8>, u64> = BTreeMap::new();
+        for (term, doc_freq) in slice_doc_freqs.into_iter().flatten() {
+            *doc_freqs.entry(term).or_default() += doc_freq;
+            if doc_freqs.len() > self.max_expansions {
+                return Err(self.too_many_terms());
+            }
+        }
+
+        let mut terms: Vec<(Vec<u8>, u64)> = doc_freqs.into_iter().collect();
+        if let Some(n) = top {
+            terms.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+            terms.truncate(n);
+            terms.sort_by(|a, b| a.0.cmp(&b.0));
+        }
+
+        let mut clauses: Vec<Arc<dyn Query>> = terms
+            .into_iter()
+            .map(|(term, _)| Arc::new(TermQuery::new(Term::new(self.field(), term))) as Arc<dyn Query>)
+            .collect();
+        match clauses.len() {
+            0 => Ok(Some(Arc::new(MatchNoDocsQuery::new(format!("no terms match {self}"))))),
+            1 => Ok(clauses.pop()),
+            _ => {
+                let mut builder = BooleanQuery::builder();
+                for clause in clauses {
+                    builder.should(clause);
+                }
+                Ok(Some(Arc::new(builder.build())))
+            }
+        }
+    }
+}
+
+impl Display for AutomatonQuery {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        f.write_str(&self.description)
+    }
+}
+
+/// The weight of an [AutomatonQuery] executed with [RewriteMethod::ConstantScoreBlended].
+#[derive(Debug)]
+struct AutomatonWeight {
+    query: AutomatonQuery,
+    score: f32,
+    memory_tracker: Arc<QueryMemoryTracker>,
+}
+
+impl Weight for AutomatonWeight {
+    fn scorer(&self, context: &LeafReaderContext) -> BoxResult<Option<Box<dyn Scorer>>> {
+        let Some(terms) = context.reader().terms(self.query.field())? else {
+            return Ok(None);
+        };
+
+        // The postings of the terms in the most documents are kept; the rarer terms' documents are gathered.
+        let mut kept: Vec<(u32, Box<dyn PostingsEnum>)> = Vec::new();
+        let mut gathered: Option<DocIdSetBuilder> = None;
+        let mut count = 0;
+        let mut te = self.query.automaton.terms_enum(terms)?;
+        while te.next()?.is_some() {
+            count += 1;
+            if count > self.query.max_expansions {
+                return Err(self.query.too_many_terms());
+            }
+
+            kept.push((te.doc_freq()?, te.postings()?));
+            if kept.len() > BLENDED_MAX_TERMS {
+                let rarest =
+                    kept.iter().enumerate().min_by_key(|(_, (doc_freq, _))| *doc_freq).map(|(i, _)| i).unwrap();
+                let (_, mut postings) = kept.swap_remove(rarest);
+                gathered
+                    .get_or_insert_with(|| DocIdSetBuilder::new(context.reader().max_doc()))
+                    .add(postings.as_mut())?;
+            }
+        }
+
+        let mut scorers: Vec<Box<dyn Scorer>> = kept
+            .into_iter()
+            .map(|(_, postings)| {
+                Box::new(ConstantScoreScorer::new(self.score, postings as Box<dyn DocIdSetIterator>)) as Box<dyn Scorer>
+            })
+            .collect();
+        if let Some(gathered) = gathered {
+            let docs = gathered.build();
+            self.memory_tracker.reserve(docs.ram_bytes_used(), "AutomatonQuery doc id set")?;
+            scorers.push(Box::new(ConstantScoreScorer::new(self.score, docs.iterator())));
+        }
+
+        // Every sub-scorer has the same score, so the best of them is the score.
+        match scorers.len() {
+            0 => Ok(None),
+            1 => Ok(scorers.pop()),
+            _ => Ok(Some(Box::new(DisjunctionMaxScorer::new(scorers, 0.0)))),
+        }
+    }
+
+    fn explain(&self, context: &LeafReaderContext, doc: u32) -> BoxResult<Explanation> {
+        match self.scorer_at(context, doc)? {
+            Some(_) => Ok(Explanation::constant(self.score, self.query.to_string())),
+            None => Ok(Explanation::no_match(format!("no term matching {} is in document {doc}", self.query), vec![])),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{
+            document::{Document, Field, Store},
+            index::Term,
+            search::{test_util, BooleanQuery, IndexSearcher, PrefixQuery, RewriteMethod, TermQuery},
+            LuceneError,
+        },
+        pretty_assertions::assert_eq,
+        std::any::Any,
+    };
+
+    /// Two segments of documents whose terms are `t0`, `t1`, ..., where `t{i}` is in `i % 5 + 1` documents.
+    fn searcher() -> IndexSearcher {
+        test_util::searcher((0..2).map(|segment| {
+            (0..20).flat_map(move |i| {
+                let body = format!("t{} other{segment}", i + segment * 20);
+                (0..=(i % 5)).map(move |_| Document::from_iter([Field::text("body", body.clone(), Store::No)]))
+            })
+        }))
+    }
+
+    #[test]
+    fn test_constant_score_blended() {
+        let searcher = searcher();
+
+        // More than 16 terms per segment, so the rarer ones are gathered into a set.
+        let query = PrefixQuery::new(Term::from_text("body", "t")).unwrap();
+        assert_eq!(query.automaton_query().rewrite_method(), RewriteMethod::ConstantScoreBlended);
+        assert_eq!(searcher.count(&query).unwrap(), 120);
+        let top = searcher.search(&query, 200).unwrap();
+        assert_eq!(top.score_docs.len(), 120);
+        assert!(top.score_docs.iter().all(|sd| sd.score == 1.0));
+
+        let query = PrefixQuery::new(Term::from_text("body", "t1")).unwrap();
+        assert_eq!(searcher.count(&query).unwrap(), 32);
+        assert!(!searcher.explain(&query, 0).unwrap().is_match());
+        assert_eq!(searcher.explain(&query, 1).unwrap().description(), "body:t1*");
+
+        // The budget is per segment, where there are 20 matching terms.
+        let mut query = PrefixQuery::new(Term::from_text("body", "t")).unwrap();
+        query.set_max_expansions(20);
+        assert_eq!(searcher.count(&query).unwrap(), 120);
+        query.set_max_expansions(19);
+        let error = searcher.count(&query).unwrap_err();
+        assert_eq!(LuceneError::find(error.as_ref()).unwrap().code(), "too_many_clauses");
+        assert_eq!(error.to_string(), "Too many clauses: body:t* expands to more than 19 terms");
+    }
+
+    #[test]
+    fn test_scoring_boolean() {
+        let searcher = searcher();
+
+        let mut query = PrefixQuery::new(Term::from_text("body", "t1")).unwrap();
+        query.set_rewrite_method(RewriteMethod::ScoringBoolean);
+        let rewritten = searcher.rewrite(&query).unwrap().unwrap();
+        let boolean = (rewritten.as_ref() as &dyn Any).downcast_ref::<BooleanQuery>().unwrap();
+        assert_eq!(boolean.clauses().len(), 11);
+        assert_eq!(searcher.count(&query).unwrap(), 32);
+
+        // Scores come from the terms: rarer terms score higher.
+        let top = searcher.search(&query, 1).unwrap();
+        let t10 = searcher.search(&TermQuery::new(Term::from_text("body", "t10")), 1).unwrap();
+        assert_eq!(top.score_docs[0].score, t10.score_docs[0].score);
+
+        // Only the terms in the most documents are kept.
+        query.set_rewrite_method(RewriteMethod::TopTermsScoringBoolean(2));
+        assert_eq!(searcher.rewrite(&query).unwrap().unwrap().to_string(), "body:t14 body:t19");
+        query.set_rewrite_method(RewriteMethod::TopTermsScoringBoolean(1));
+        assert_eq!(searcher.rewrite(&query).unwrap().unwrap().to_string(), "body:t14");
+
+        // The budget counts the distinct terms of the whole index.
+        let mut query = PrefixQuery::new(Term::from_text("body", "t")).unwrap();
+        query.set_rewrite_method(RewriteMethod::ScoringBoolean).set_max_expansions(39);
+        let error = searcher.search(&query, 10).unwrap_err();
+        assert!(matches!(LuceneError::find(error.as_ref()), Some(LuceneError::TooManyClauses(_))));
+        query.set_max_expansions(40);
+        assert_eq!(searcher.count(&query).unwrap(), 120);
+
+        let query = PrefixQuery::new(Term::from_text("body", "x")).unwrap();
+        assert_eq!(searcher.count(&query).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_concurrent_rewrite() {
+        // Enough segments for several slices, each with terms of its own and terms shared with the others.
+        let mut segments = Vec::new();
+        for segment in 0..12 {
+            let mut documents = Vec::new();
+            for i in 0..3 {
+                let mut doc = Document::new();
+                doc.add(Field::text("body", format!("shared{i} own{segment}"), Store::No));
+                documents.push(doc);
+            }
+            segments.push(documents);
+        }
+        let mut searcher = test_util::searcher(segments);
+        assert_eq!(searcher.slices().len(), 3);
+
+        let mut shared = PrefixQuery::new(Term::from_text("body", "shared")).unwrap();
+        shared.set_rewrite_method(RewriteMethod::TopTermsScoringBoolean(2));
+        let mut own = PrefixQuery::new(Term::from_text("body", "own")).unwrap();
+        own.set_rewrite_method(RewriteMethod::ScoringBoolean);
+
+        let sequential = searcher.rewrite(&own).unwrap().unwrap().to_string();
+        assert_eq!(searcher.rewrite(&shared).unwrap().unwrap().to_string(), "body:shared0 body:shared1");
+        searcher.set_concurrent(true);
+        assert_eq!(searcher.rewrite(&own).unwrap().unwrap().to_string(), sequential);
+        assert_eq!(searcher.rewrite(&shared).unwrap().unwrap().to_string(), "body:shared0 body:shared1");
+        assert_eq!(searcher.count(&own).unwrap(), 36);
+
+        let leaves = searcher.map_slices(|leaves| Ok(leaves.len())).unwrap();
+        assert_eq!(leaves, vec![5, 5, 2]);
+
+        // The budget still counts the distinct terms of the whole index, though each slice is within it.
+        own.set_max_expansions(11);
+        let error = searcher.rewrite(&own).unwrap_err();
+        assert!(matches!(LuceneError::find(error.as_ref()), Some(LuceneError::TooManyClauses(_))));
+        own.set_max_expansions(12);
+        assert!(searcher.rewrite(&own).is_ok());
+    }
+}
@@ -0,0 +1,316 @@
+use crate::{
+    search::{CollectionStatistics, Explanation, FieldInvertState, SimScorer, Similarity, TermStatistics},
+    util::{byte4_to_int, int_to_byte4},
+    BoxResult, LuceneError,
+};
+
+/// The Okapi BM25 similarity, the default scoring model.
+///
+/// Norms are field lengths (the number of tokens in the field), encoded into a byte with [int_to_byte4], so lengths
+/// above 24 are approximate.
+#[derive(Clone, Copy, Debug)]
+pub struct BM25Similarity {
+    k1: f32,
+    b: f32,
+    discount_overlaps: bool,
+}
+
+impl Default for BM25Similarity {
+    fn default() -> Self {
+        Self {
+            k1: 1.2,
+            b: 0.75,
+            discount_overlaps: true,
+        }
+    }
+}
+
+impl BM25Similarity {
+    /// Creates a BM25 similarity with the given parameters, discounting overlapping tokens; see
+    /// [BM25SimilarityBuilder] for the meaning of the parameters.
+    pub fn new(k1: f32, b: f32) -> BoxResult<Self> {
+        Self::builder().set_k1(k1).set_b(b).build()
+    }
+
+    /// Returns a builder for a similarity, starting from the default parameters.
+    #[inline]
+    pub fn builder() -> BM25SimilarityBuilder {
+        BM25SimilarityBuilder::new()
+    }
+
+    /// Returns the `k1` parameter.
+    #[inline]
+    pub fn k1(&self) -> f32 {
+        self.k1
+    }
+
+    /// Returns the `b` parameter.
+    #[inline]
+    pub fn b(&self) -> f32 {
+        self.b
+    }
+
+    /// Indicates whether overlapping tokens are left out of field lengths.
+    #[inline]
+    pub fn discount_overlaps(&self) -> bool {
+        self.discount_overlaps
+    }
+
+    /// Computes the inverse document frequency of a term: `ln(1 + (doc_count - doc_freq + 0.5) / (doc_freq + 0.5))`.
+    pub fn idf(doc_freq: u64, doc_count: u64) -> f32 {
+        (1.0 + (doc_count as f64 - doc_freq as f64 + 0.5) / (doc_freq as f64 + 0.5)).ln() as f32
+    }
+}
+
+impl Similarity for BM25Similarity {
+    fn compute_norm(&self, state: &FieldInvertState) -> i64 {
+        let length = if self.discount_overlaps {
+            state.length - state.num_overlap
+        } else {
+            state.length
+        };
+
+        int_to_byte4(length) as i64
+    }
+
+    fn scorer(
+        &self,
+        boost: f32,
+        collection_stats: &CollectionStatistics,
+        term_stats: &[TermStatistics],
+    ) -> Box<dyn SimScorer> {
+        // Documents without the field can't match, so the document count only covers documents with the field.
+        let doc_count = collection_stats.doc_count.max(1);
+        let idf_details: Vec<Explanation> = term_stats
+            .iter()
+            .map(|ts| {
+                Explanation::matched(
+                    Self::idf(ts.doc_freq, doc_count),
+                    "idf, computed as log(1 + (N - n + 0.5) / (n + 0.5)) from:",
+                    vec![
+                        Explanation::matched(ts.doc_freq as f32, "n, number of documents containing term", vec![]),
+                        Explanation::matched(doc_count as f32, "N, total number of documents with field", vec![]),
+                    ],
+                )
+            })
+            .collect();
+        let idf = match idf_details.len() {
+            1 => idf_details.into_iter().next().unwrap(),
+            _ => Explanation::matched(idf_details.iter().map(|e| e.value()).sum(), "idf, sum of:", idf_details),
+        };
+        let avgdl = (collection_stats.sum_total_term_freq as f32 / doc_count as f32).max(f32::MIN_POSITIVE);
+
+        // There are only 256 distinct norms, so the length normalization of each is computed up front.
+        let mut cache = [0.0; 256];
+        for (norm, inverse) in cache.iter_mut().enumerate() {
+            *inverse = 1.0 / (self.k1 * ((1.0 - self.b) + self.b * BM25Scorer::length(norm as i64) / avgdl));
+        }
+
+        Box::new(BM25Scorer {
+            weight: boost * idf.value(),
+            boost,
+            idf,
+            k1: self.k1,
+            b: self.b,
+            avgdl,
+            cache: Box::new(cache),
+        })
+    }
+}
+
+/// Builds a [BM25Similarity].
+#[derive(Clone, Copy, Debug)]
+pub struct BM25SimilarityBuilder {
+    k1: f32,
+    b: f32,
+    discount_overlaps: bool,
+}
+
+impl Default for BM25SimilarityBuilder {
+    fn default() -> Self {
+        let defaults = BM25Similarity::default();
+        Self {
+            k1: defaults.k1,
+            b: defaults.b,
+            discount_overlaps: defaults.discount_overlaps,
+        }
+    }
+}
+
+impl BM25SimilarityBuilder {
+    /// Creates a builder with the default parameters: `k1 = 1.2`, `b = 0.75`, discounting overlaps.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets `k1`, which controls non-linear term frequency normalization (saturation). Must be finite and
+    /// non-negative.
+    pub fn set_k1(&mut self, k1: f32) -> &mut Self {
+        self.k1 = k1;
+        self
+    }
+
+    /// Sets `b`, which controls to what degree document length normalizes term frequency values. Must be in
+    /// `[0, 1]`.
+    pub fn set_b(&mut self, b: f32) -> &mut Self {
+        self.b = b;
+        self
+    }
+
+    /// Sets whether tokens at the same position as the token before them, such as synonyms, are left out of field
+    /// lengths when computing norms, so that injecting them doesn't penalize a document.
+    pub fn set_discount_overlaps(&mut self, discount_overlaps: bool) -> &mut Self {
+        self.discount_overlaps = discount_overlaps;
+        self
+    }
+
+    /// Builds the similarity, checking the parameters.
+    pub fn build(&self) -> BoxResult<BM25Similarity> {
+        let (k1, b) = (self.k1, self.b);
+        if !k1.is_finite() || k1 < 0.0 {
+            return Err(LuceneError::InvalidArgument(format!(
+                "illegal k1 value: {k1}, must be a non-negative finite value"
+            ))
+            .into());
+        }
+
+        if b.is_nan() || !(0.0..=1.0).contains(&b) {
+            return Err(LuceneError::InvalidArgument(format!("illegal b value: {b}, must be between 0 and 1")).into());
+        }
+
+        Ok(BM25Similarity {
+            k1,
+            b,
+            discount_overlaps: self.discount_overlaps,
+        })
+    }
+}
+
+/// The [SimScorer] for [BM25Similarity].
+#[derive(Clone, Debug)]
+struct BM25Scorer {
+    weight: f32,
+    boost: f32,
+    idf: Explanation,
+    k1: f32,
+    b: f32,
+    avgdl: f32,
+
+    /// `1 / (k1 * (1 - b + b * dl / avgdl))` for each norm.
+    cache: Box<[f32; 256]>,
+}
+
+impl SimScorer for BM25Scorer {
+    fn score(&self, freq: f32, norm: i64) -> f32 {
+        let norm_inverse = self.cache[norm.clamp(0, 255) as usize];
+
+        // This is equivalent to weight * freq / (freq + k1 * (...)), but never decreases as freq increases, even with
+        // rounding.
+        self.weight - self.weight / (1.0 + freq * norm_inverse)
+    }
+
+    fn explain(&self, freq: f32, norm: i64) -> Explanation {
+        let score = self.score(freq, norm);
+        let length = Self::length(norm);
+        let tf = freq / (freq + self.k1 * (1.0 - self.b + self.b * length / self.avgdl));
+        let tf = Explanation::matched(
+            tf,
+            "tf, computed as freq / (freq + k1 * (1 - b + b * dl / avgdl)) from:",
+            vec![
+                Explanation::matched(freq, "freq, occurrences of term within document", vec![]),
+                Explanation::matched(self.k1, "k1, term saturation parameter", vec![]),
+                Explanation::matched(self.b, "b, length normalization parameter", vec![]),
+                Explanation::matched(length, "dl, length of field (approximate)", vec![]),
+                Explanation::matched(self.avgdl, "avgdl, average length of field", vec![]),
+            ],
+        );
+
+        Explanation::matched(
+            score,
+            format!("score(freq={freq}), computed as boost * idf * tf from:"),
+            vec![Explanation::matched(self.boost, "boost", vec![]), self.idf.clone(), tf],
+        )
+    }
+}
+
+impl BM25Scorer {
+    /// Returns the field length for a norm. A missing norm (for fields that don't record them) is treated as a length
+    /// of 1.
+    #[inline]
+    fn length(norm: i64) -> f32 {
+        if norm <= 0 {
+            1.0
+        } else {
+            byte4_to_int(norm.min(255) as u8) as f32
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{
+            search::{BM25Similarity, CollectionStatistics, FieldInvertState, Similarity, TermStatistics},
+            util::int_to_byte4,
+        },
+        pretty_assertions::assert_eq,
+    };
+
+    #[test]
+    fn test_bm25() {
+        assert!(BM25Similarity::new(-1.0, 0.5).is_err());
+        assert!(BM25Similarity::new(1.0, 1.5).is_err());
+
+        let cs = CollectionStatistics {
+            field: "body".to_string(),
+            max_doc: 10,
+            doc_count: 10,
+            sum_total_term_freq: 100,
+            sum_doc_freq: 50,
+        };
+        let ts = TermStatistics {
+            term: b"fox".to_vec(),
+            doc_freq: 2,
+            total_term_freq: 3,
+        };
+        let scorer = BM25Similarity::default().scorer(1.0, &cs, &[ts]);
+
+        let idf = BM25Similarity::idf(2, 10);
+        assert!((idf - (1.0f32 + 8.5 / 2.5).ln()).abs() < 1e-6);
+
+        // At average length with freq 1, tf normalization is 1 / (1 + k1).
+        let expected = idf * 1.0 / (1.0 + 1.2);
+        assert!((scorer.score(1.0, 10) - expected).abs() < 1e-6);
+
+        // Higher frequencies score higher; longer documents score lower.
+        assert!(scorer.score(2.0, 10) > scorer.score(1.0, 10));
+        assert!(scorer.score(1.0, 20) < scorer.score(1.0, 10));
+    }
+
+    #[test]
+    fn test_bm25_builder_and_norms() {
+        let similarity = BM25Similarity::builder().set_k1(2.0).set_b(0.5).set_discount_overlaps(false).build().unwrap();
+        assert_eq!((similarity.k1(), similarity.b(), similarity.discount_overlaps()), (2.0, 0.5, false));
+        assert!(BM25Similarity::builder().set_k1(f32::NAN).build().is_err());
+        assert!(BM25Similarity::default().discount_overlaps());
+
+        // Three of the ten tokens are synonyms stacked on the token before them.
+        let state = FieldInvertState {
+            field: "body".to_string(),
+            length: 10,
+            num_overlap: 3,
+            unique_term_count: 10,
+            max_term_frequency: 1,
+        };
+        assert_eq!(similarity.compute_norm(&state), 10);
+        assert_eq!(BM25Similarity::default().compute_norm(&state), 7);
+
+        let long = FieldInvertState {
+            length: 1_000,
+            num_overlap: 0,
+            ..state
+        };
+        assert_eq!(BM25Similarity::default().compute_norm(&long), int_to_byte4(1_000) as i64);
+        assert!(int_to_byte4(1_000) < 255);
+    }
+}
@@ -0,0 +1,81 @@
+use {
+    crate::search::Query,
+    std::{
+        fmt::{Display, Formatter, Result as FmtResult},
+        sync::Arc,
+    },
+};
+
+/// Specifies how a clause of a [crate::search::BooleanQuery] must occur in matching documents.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Occur {
+    /// The clause must match, and contributes to the score.
+    Must,
+
+    /// The clause must match, but doesn't contribute to the score.
+    Filter,
+
+    /// The clause should match; documents that match it score higher.
+    Should,
+
+    /// The clause must not match. This doesn't contribute to the score.
+    MustNot,
+}
+
+impl Display for Occur {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match self {
+            Self::Must => write!(f, "+"),
+            Self::Filter => write!(f, "#"),
+            Self::Should => Ok(()),
+            Self::MustNot => write!(f, "-"),
+        }
+    }
+}
+
+/// A clause of a [crate::search::BooleanQuery]: a query along with how it must occur.
+#[derive(Clone, Debug)]
+pub struct BooleanClause {
+    query: Arc<dyn Query>,
+    occur: Occur,
+}
+
+impl BooleanClause {
+    /// Creates a new clause.
+    pub fn new(query: Arc<dyn Query>, occur: Occur) -> Self {
+        Self {
+            query,
+            occur,
+        }
+    }
+
+    /// Returns the clause's query.
+    #[inline]
+    pub fn query(&self) -> &Arc<dyn Query> {
+        &self.query
+    }
+
+    /// Returns how the clause must occur.
+    #[inline]
+    pub fn occur(&self) -> Occur {
+        self.occur
+    }
+
+    /// Indicates whether the clause contributes to the score.
+    #[inline]
+    pub fn is_scoring(&self) -> bool {
+        matches!(self.occur, Occur::Must | Occur::Should)
+    }
+
+    /// Indicates whether the clause must match.
+    #[inline]
+    pub fn is_required(&self) -> bool {
+        matches!(self.occur, Occur::Must | Occur::Filter)
+    }
+
+    /// Indicates whether the clause must not match.
+    #[inline]
+    pub fn is_prohibited(&self) -> bool {
+        self.occur == Occur::MustNot
+    }
+}
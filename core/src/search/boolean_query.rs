@@ -0,0 +1,606 @@
+use {
+    crate::{
+        index::LeafReaderContext,
+        search::{
+            BooleanClause, BooleanScorer, BoostQuery, BulkScorer, ConjunctionScorer, ConstantScoreQuery,
+            DefaultBulkScorer, DisjunctionSumScorer, Explanation, IndexSearcher, MatchNoDocsQuery,
+            MinShouldMatchSumScorer, Occur, Query, ReqExclScorer, ReqOptSumScorer, ScoreMode, Scorer, Weight,
+        },
+        BoxResult,
+    },
+    std::{
+        any::Any,
+        fmt::{Display, Formatter, Result as FmtResult},
+        mem,
+        sync::Arc,
+    },
+};
+
+/// A query that combines other queries according to how each of them must occur in matching documents.
+///
+/// A document matches if it matches every [Occur::Must] and [Occur::Filter] clause and no [Occur::MustNot] clause.
+/// If there are no required clauses, it must also match at least one [Occur::Should] clause. The score is the sum of
+/// the scores of the matching [Occur::Must] and [Occur::Should] clauses.
+///
+/// A minimum number of [Occur::Should] clauses that must match can be set with
+/// [BooleanQueryBuilder::set_min_should_match]. The optional clauses then also restrict the matches when there are
+/// required clauses.
+#[derive(Clone, Debug, Default)]
+pub struct BooleanQuery {
+    clauses: Vec<BooleanClause>,
+    min_should_match: u32,
+}
+
+impl BooleanQuery {
+    /// Returns a builder for a new query.
+    #[inline]
+    pub fn builder() -> BooleanQueryBuilder {
+        BooleanQueryBuilder::new()
+    }
+
+    /// Returns the clauses of the query.
+    #[inline]
+    pub fn clauses(&self) -> &[BooleanClause] {
+        &self.clauses
+    }
+
+    /// Returns the number of [Occur::Should] clauses a matching document must match, or 0 if there's no minimum.
+    #[inline]
+    pub fn min_should_match(&self) -> u32 {
+        self.min_should_match
+    }
+
+    /// Returns a builder holding this query's minimum number of optional clauses, but none of its clauses.
+    pub(crate) fn to_empty_builder(&self) -> BooleanQueryBuilder {
+        let mut builder = BooleanQueryBuilder::new();
+        builder.set_min_should_match(self.min_should_match);
+        builder
+    }
+
+    /// Rewrites a query with a single clause into an equivalent query without the boolean, returning `None` if there
+    /// is more than one clause.
+    pub(crate) fn rewrite_single_clause(&self) -> BoxResult<Option<Arc<dyn Query>>> {
+        let [clause] = self.clauses.as_slice() else {
+            return Ok(None);
+        };
+
+        if self.min_should_match > 1 || (self.min_should_match == 1 && clause.occur() != Occur::Should) {
+            return Ok(Some(Arc::new(MatchNoDocsQuery::new("not enough optional clauses"))));
+        }
+
+        Ok(Some(match clause.occur() {
+            Occur::Must | Occur::Should => clause.query().clone(),
+            // A lone filter matches the same documents, but with a score of zero.
+            Occur::Filter => {
+                let constant_score = Arc::new(ConstantScoreQuery::new(clause.query().clone()));
+                Arc::new(BoostQuery::new(constant_score, 0.0)?)
+            }
+            Occur::MustNot => Arc::new(MatchNoDocsQuery::new("pure negative BooleanQuery")),
+        }))
+    }
+}
+
+impl Query for BooleanQuery {
+    fn create_weight(&self, searcher: &IndexSearcher, score_mode: ScoreMode, boost: f32) -> BoxResult<Box<dyn Weight>> {
+        let mut clauses = Vec::with_capacity(self.clauses.len());
+        for clause in self.clauses.iter() {
+            let clause_score_mode = if clause.is_scoring() {
+                score_mode
+            } else {
+                ScoreMode::CompleteNoScores
+            };
+            clauses.push(WeightClause {
+                occur: clause.occur(),
+                weight: searcher.create_weight(clause.query().as_ref(), clause_score_mode, boost)?,
+                description: clause.query().to_string(),
+            });
+        }
+
+        Ok(Box::new(BooleanWeight {
+            clauses,
+            min_should_match: self.min_should_match,
+            needs_scores: score_mode.needs_scores(),
+        }))
+    }
+
+    fn rewrite(&self, searcher: &IndexSearcher) -> BoxResult<Option<Arc<dyn Query>>> {
+        if self.clauses.is_empty() {
+            return Ok(Some(Arc::new(MatchNoDocsQuery::new("empty BooleanQuery"))));
+        }
+
+        if self.clauses.iter().all(BooleanClause::is_prohibited) {
+            return Ok(Some(Arc::new(MatchNoDocsQuery::new("pure negative BooleanQuery"))));
+        }
+
+        let optional = self.clauses.iter().filter(|clause| clause.occur() == Occur::Should).count();
+        if self.min_should_match as usize > optional {
+            return Ok(Some(Arc::new(MatchNoDocsQuery::new("not enough optional clauses"))));
+        }
+
+        if let Some(rewritten) = self.rewrite_single_clause()? {
+            return Ok(Some(rewritten));
+        }
+
+        let mut changed = false;
+        let mut builder = self.to_empty_builder();
+        for clause in self.clauses.iter() {
+            match searcher.rewrite(clause.query().as_ref())? {
+                Some(rewritten) => {
+                    changed = true;
+                    builder.add(rewritten, clause.occur());
+                }
+                None => {
+                    builder.add(clause.query().clone(), clause.occur());
+                }
+            }
+        }
+
+        Ok(changed.then(|| Arc::new(builder.build()) as Arc<dyn Query>))
+    }
+}
+
+impl Display for BooleanQuery {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        if self.min_should_match > 0 {
+            write!(f, "(")?;
+        }
+
+        for (i, clause) in self.clauses.iter().enumerate() {
+            if i > 0 {
+                write!(f, " ")?;
+            }
+
+            write!(f, "{}", clause.occur())?;
+            if (clause.query().as_ref() as &dyn Any).is::<BooleanQuery>() {
+                write!(f, "({})", clause.query())?;
+            } else {
+                write!(f, "{}", clause.query())?;
+            }
+        }
+
+        if self.min_should_match > 0 {
+            write!(f, ")~{}", self.min_should_match)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Builds a [BooleanQuery].
+#[derive(Clone, Debug, Default)]
+pub struct BooleanQueryBuilder {
+    clauses: Vec<BooleanClause>,
+    min_should_match: u32,
+}
+
+impl BooleanQueryBuilder {
+    /// Creates a builder with no clauses.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a clause.
+    pub fn add(&mut self, query: Arc<dyn Query>, occur: Occur) -> &mut Self {
+        self.clauses.push(BooleanClause::new(query, occur));
+        self
+    }
+
+    /// Adds a clause the documents must match, which contributes to their scores.
+    pub fn must(&mut self, query: Arc<dyn Query>) -> &mut Self {
+        self.add(query, Occur::Must)
+    }
+
+    /// Adds a clause the documents may match, which contributes to their scores.
+    pub fn should(&mut self, query: Arc<dyn Query>) -> &mut Self {
+        self.add(query, Occur::Should)
+    }
+
+    /// Adds a clause the documents must match, which doesn't contribute to their scores. Its matches can be cached
+    /// by the searcher's [crate::search::LruQueryCache].
+    pub fn filter(&mut self, query: Arc<dyn Query>) -> &mut Self {
+        self.add(query, Occur::Filter)
+    }
+
+    /// Adds a clause the documents must not match.
+    pub fn must_not(&mut self, query: Arc<dyn Query>) -> &mut Self {
+        self.add(query, Occur::MustNot)
+    }
+
+    /// Adds a pre-built clause.
+    pub fn add_clause(&mut self, clause: BooleanClause) -> &mut Self {
+        self.clauses.push(clause);
+        self
+    }
+
+    /// Sets the number of [Occur::Should] clauses a matching document must match. The default of 0 means one is
+    /// needed only if there are no required clauses. If the minimum is more than the number of optional clauses,
+    /// nothing matches.
+    pub fn set_min_should_match(&mut self, min_should_match: u32) -> &mut Self {
+        self.min_should_match = min_should_match;
+        self
+    }
+
+    /// Builds the query.
+    pub fn build(&self) -> BooleanQuery {
+        BooleanQuery {
+            clauses: self.clauses.clone(),
+            min_should_match: self.min_should_match,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct WeightClause {
+    occur: Occur,
+    weight: Box<dyn Weight>,
+    description: String,
+}
+
+#[derive(Debug)]
+struct BooleanWeight {
+    clauses: Vec<WeightClause>,
+    min_should_match: u32,
+    needs_scores: bool,
+}
+
+impl BooleanWeight {
+    /// Returns a scorer over the union of the given scorers.
+    fn disjunction(mut scorers: Vec<Box<dyn Scorer>>) -> Box<dyn Scorer> {
+        if scorers.len() == 1 {
+            scorers.pop().unwrap()
+        } else {
+            Box::new(DisjunctionSumScorer::new(scorers))
+        }
+    }
+
+    /// Returns a scorer over the documents matching at least `min_should_match` of the given scorers, of which there
+    /// must be at least that many.
+    fn at_least(scorers: Vec<Box<dyn Scorer>>, min_should_match: u32) -> Box<dyn Scorer> {
+        match min_should_match as usize {
+            0 | 1 => Self::disjunction(scorers),
+            n if n == scorers.len() => {
+                Box::new(ConjunctionScorer::new(scorers.into_iter().map(|s| (s, true)).collect()))
+            }
+            _ => Box::new(MinShouldMatchSumScorer::new(scorers, min_should_match)),
+        }
+    }
+}
+
+impl Weight for BooleanWeight {
+    fn scorer(&self, context: &LeafReaderContext) -> BoxResult<Option<Box<dyn Scorer>>> {
+        let mut required = Vec::new();
+        let mut optional = Vec::new();
+        let mut prohibited = Vec::new();
+
+        for clause in self.clauses.iter() {
+            match clause.occur {
+                Occur::Must | Occur::Filter => match clause.weight.scorer_supplier(context)? {
+                    Some(supplier) => required.push((supplier, clause.occur == Occur::Must)),
+                    None => return Ok(None),
+                },
+                Occur::Should => optional.extend(clause.weight.scorer(context)?),
+                Occur::MustNot => prohibited.extend(clause.weight.scorer(context)?),
+            }
+        }
+
+        // The cheapest required clause leads the conjunction, and the others are only advanced to its candidates.
+        let lead_cost = required.iter().map(|(supplier, _)| supplier.cost()).min().unwrap_or(u64::MAX);
+        let mut required = required
+            .into_iter()
+            .map(|(supplier, scores)| Ok((supplier.get(lead_cost)?, scores)))
+            .collect::<BoxResult<Vec<_>>>()?;
+
+        // With a minimum, the optional clauses must match together like one more required clause.
+        if self.min_should_match > 0 {
+            if optional.len() < self.min_should_match as usize {
+                return Ok(None);
+            }
+            required.push((Self::at_least(mem::take(&mut optional), self.min_should_match), true));
+        }
+
+        let positive = if required.is_empty() {
+            if optional.is_empty() {
+                return Ok(None);
+            }
+            Self::disjunction(optional)
+        } else {
+            let req: Box<dyn Scorer> = if required.len() == 1 && required[0].1 {
+                required.pop().unwrap().0
+            } else {
+                Box::new(ConjunctionScorer::new(required))
+            };
+
+            // Optional clauses only affect the score once there are required clauses.
+            if optional.is_empty() || !self.needs_scores {
+                req
+            } else {
+                Box::new(ReqOptSumScorer::new(req, Self::disjunction(optional)))
+            }
+        };
+
+        if prohibited.is_empty() {
+            Ok(Some(positive))
+        } else {
+            Ok(Some(Box::new(ReqExclScorer::new(positive, Self::disjunction(prohibited)))))
+        }
+    }
+
+    fn bulk_scorer(&self, context: &LeafReaderContext) -> BoxResult<Option<Box<dyn BulkScorer>>> {
+        // Pure disjunctions are scored a window at a time.
+        if self.clauses.len() > 1 && self.clauses.iter().all(|clause| clause.occur == Occur::Should) {
+            let mut subs = Vec::with_capacity(self.clauses.len());
+            for clause in self.clauses.iter() {
+                if let Some(sub) = clause.weight.bulk_scorer(context)? {
+                    subs.push(sub);
+                }
+            }
+
+            return Ok(match subs.len() {
+                n if n < self.min_should_match.max(1) as usize => None,
+                1 => subs.pop(),
+                _ => Some(Box::new(BooleanScorer::new(subs, self.min_should_match, self.needs_scores))),
+            });
+        }
+
+        Ok(self.scorer(context)?.map(|scorer| Box::new(DefaultBulkScorer::new(scorer)) as Box<dyn BulkScorer>))
+    }
+
+    fn explain(&self, context: &LeafReaderContext, doc: u32) -> BoxResult<Explanation> {
+        let mut details = Vec::with_capacity(self.clauses.len());
+        let mut score = 0.0f64;
+        let mut fail = false;
+        let mut matched_required = false;
+        let mut matched_optional = 0;
+
+        for clause in self.clauses.iter() {
+            let explanation = clause.weight.explain(context, doc)?;
+            match (clause.occur, explanation.is_match()) {
+                (Occur::Must, true) => {
+                    score += explanation.value() as f64;
+                    matched_required = true;
+                    details.push(explanation);
+                }
+                (Occur::Filter, true) => matched_required = true,
+                (Occur::Should, true) => {
+                    score += explanation.value() as f64;
+                    matched_optional += 1;
+                    details.push(explanation);
+                }
+                (Occur::Must | Occur::Filter, false) => {
+                    fail = true;
+                    details.push(Explanation::no_match(
+                        format!("no match on required clause ({})", clause.description),
+                        vec![explanation],
+                    ));
+                }
+                (Occur::MustNot, true) => {
+                    fail = true;
+                    details.push(Explanation::no_match(
+                        format!("match on prohibited clause ({})", clause.description),
+                        vec![explanation],
+                    ));
+                }
+                (Occur::Should | Occur::MustNot, false) => (),
+            }
+        }
+
+        if fail {
+            Ok(Explanation::no_match("failure to meet condition(s) of required/prohibited clause(s)", details))
+        } else if !matched_required && matched_optional == 0 {
+            Ok(Explanation::no_match("no matching clauses", details))
+        } else if matched_optional < self.min_should_match {
+            Ok(Explanation::no_match(
+                format!("failure to match minimum number of optional clauses: {}", self.min_should_match),
+                details,
+            ))
+        } else {
+            Ok(Explanation::matched(score as f32, "sum of:", details))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{
+            index::Term,
+            search::{
+                test_util::body_searcher, BooleanQuery, DefaultBulkScorer, IndexSearcher, LeafCollector, Occur, Query,
+                Scorable, ScoreMode, TermQuery, NO_MORE_DOCS,
+            },
+            BoxResult,
+        },
+        pretty_assertions::assert_eq,
+        std::sync::Arc,
+    };
+
+    fn term(text: &str) -> Arc<dyn Query> {
+        Arc::new(TermQuery::new(Term::from_text("body", text)))
+    }
+
+    fn docs(searcher: &IndexSearcher, query: &dyn Query) -> Vec<u32> {
+        let mut docs: Vec<u32> = searcher.search(query, 100).unwrap().score_docs.iter().map(|sd| sd.doc).collect();
+        docs.sort_unstable();
+        docs
+    }
+
+    #[derive(Debug, Default)]
+    struct HitsCollector {
+        hits: Vec<(u32, f32)>,
+    }
+
+    impl LeafCollector for HitsCollector {
+        fn collect(&mut self, doc: u32, scorer: &mut dyn Scorable) -> BoxResult<()> {
+            self.hits.push((doc, scorer.score()?));
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_occurs() {
+        let searcher = body_searcher(&["a b", "a", "b c", "c", "a c"]);
+
+        let query = BooleanQuery::builder().add(term("a"), Occur::Must).add(term("c"), Occur::MustNot).build();
+        assert_eq!(query.to_string(), "+body:a -body:c");
+        assert_eq!(docs(&searcher, &query), vec![0, 1]);
+
+        let query = BooleanQuery::builder().add(term("a"), Occur::Should).add(term("b"), Occur::Should).build();
+        assert_eq!(docs(&searcher, &query), vec![0, 1, 2, 4]);
+        let top = searcher.search(&query, 1).unwrap();
+        assert_eq!(top.score_docs[0].doc, 0);
+
+        let query = BooleanQuery::builder().add(term("a"), Occur::Must).add(term("b"), Occur::Filter).build();
+        assert_eq!(docs(&searcher, &query), vec![0]);
+
+        // Optional clauses add to the score of documents matching the required clauses.
+        let query = BooleanQuery::builder().add(term("c"), Occur::Must).add(term("a"), Occur::Should).build();
+        let top = searcher.search(&query, 10).unwrap();
+        assert_eq!(top.total_hits.value, 3);
+        assert_eq!(top.score_docs[0].doc, 4);
+
+        let nested =
+            Arc::new(BooleanQuery::builder().add(term("b"), Occur::Should).add(term("c"), Occur::Should).build());
+        let query = BooleanQuery::builder().add(term("a"), Occur::Must).add(nested, Occur::Must).build();
+        assert_eq!(query.to_string(), "+body:a +(body:b body:c)");
+        assert_eq!(docs(&searcher, &query), vec![0, 4]);
+    }
+
+    #[test]
+    fn test_builder_shorthands() {
+        let searcher = body_searcher(&["a b", "a", "b c", "c", "a c"]);
+
+        let query =
+            BooleanQuery::builder().must(term("a")).should(term("b")).filter(term("a")).must_not(term("c")).build();
+        assert_eq!(query.to_string(), "+body:a body:b #body:a -body:c");
+        assert_eq!(docs(&searcher, &query), vec![0, 1]);
+
+        // The filter narrows the matches without changing the scores of the scoring query.
+        let query = TermQuery::new(Term::from_text("body", "a")).filtered_by(term("c"));
+        assert_eq!(query.to_string(), "+body:a #body:c");
+        let filtered = searcher.search(&query, 10).unwrap();
+        let unfiltered = searcher.search(&TermQuery::new(Term::from_text("body", "a")), 10).unwrap();
+        assert_eq!(filtered.score_docs.len(), 1);
+        assert_eq!(filtered.score_docs[0].doc, 4);
+        assert_eq!(filtered.score_docs[0].score, unfiltered.score_docs.iter().find(|sd| sd.doc == 4).unwrap().score);
+
+        let query = TermQuery::new(Term::from_text("body", "c")).constant_score();
+        assert_eq!(query.to_string(), "ConstantScore(body:c)");
+        assert!(searcher.search(&query, 10).unwrap().score_docs.iter().all(|sd| sd.score == 1.0));
+    }
+
+    #[test]
+    fn test_rewrite() {
+        let searcher = body_searcher(&["a b", "a"]);
+
+        let empty = searcher.rewrite(&BooleanQuery::default()).unwrap().unwrap();
+        assert_eq!(searcher.count(empty.as_ref()).unwrap(), 0);
+
+        let single = searcher.rewrite(&BooleanQuery::builder().add(term("a"), Occur::Must).build()).unwrap().unwrap();
+        assert_eq!(single.to_string(), "body:a");
+
+        let filter = BooleanQuery::builder().add(term("a"), Occur::Filter).build();
+        assert_eq!(searcher.rewrite(&filter).unwrap().unwrap().to_string(), "(ConstantScore(body:a))^0");
+        assert!(searcher.search(&filter, 10).unwrap().score_docs.iter().all(|sd| sd.score == 0.0));
+
+        let negative = BooleanQuery::builder().add(term("a"), Occur::MustNot).build();
+        assert_eq!(searcher.count(&negative).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_min_should_match() {
+        let searcher = body_searcher(&["a b", "a", "b c", "c", "a c", "a b c", "d"]);
+        let at_least = |n: u32, clauses: &[(&str, Occur)]| {
+            let mut builder = BooleanQuery::builder();
+            for (text, occur) in clauses {
+                builder.add(term(text), *occur);
+            }
+            builder.set_min_should_match(n).build()
+        };
+        // Iterates the scorer itself, since pure disjunctions are otherwise collected by a BooleanScorer.
+        let scorer_docs = |query: &BooleanQuery| {
+            let weight = searcher.create_weight(query, ScoreMode::Complete, 1.0).unwrap();
+            let mut docs = Vec::new();
+            if let Some(mut scorer) = weight.scorer(&searcher.leaves()[0]).unwrap() {
+                while scorer.next_doc().unwrap() != NO_MORE_DOCS {
+                    docs.push(scorer.doc_id());
+                }
+            }
+            docs
+        };
+        let abc = [("a", Occur::Should), ("b", Occur::Should), ("c", Occur::Should)];
+
+        let query = at_least(2, &abc);
+        assert_eq!(query.to_string(), "(body:a body:b body:c)~2");
+        assert_eq!(docs(&searcher, &query), vec![0, 2, 4, 5]);
+        assert_eq!(scorer_docs(&query), vec![0, 2, 4, 5]);
+        assert_eq!(searcher.search(&query, 1).unwrap().score_docs[0].doc, 5);
+        assert_eq!(scorer_docs(&at_least(3, &abc)), vec![5]);
+        assert_eq!(searcher.count(&at_least(4, &abc)).unwrap(), 0);
+
+        // Clauses without matches in the segment still count.
+        let query = at_least(2, &[("a", Occur::Should), ("b", Occur::Should), ("z", Occur::Should)]);
+        assert_eq!(docs(&searcher, &query), vec![0, 5]);
+        assert_eq!(scorer_docs(&query), vec![0, 5]);
+
+        // With required clauses, the minimum restricts the matches instead of only adding to the score.
+        let query = at_least(1, &[("c", Occur::Must), ("a", Occur::Should), ("b", Occur::Should)]);
+        assert_eq!(docs(&searcher, &query), vec![2, 4, 5]);
+        let query =
+            at_least(1, &[("c", Occur::Filter), ("a", Occur::Should), ("b", Occur::Should), ("b", Occur::MustNot)]);
+        assert_eq!(docs(&searcher, &query), vec![4]);
+        let query = at_least(2, &[("c", Occur::Must), ("a", Occur::Should), ("b", Occur::Should)]);
+        assert_eq!(docs(&searcher, &query), vec![5]);
+
+        let query = at_least(2, &abc);
+        assert!(searcher.explain(&query, 0).unwrap().is_match());
+        let explanation = searcher.explain(&query, 1).unwrap();
+        assert!(!explanation.is_match());
+        assert_eq!(explanation.description(), "failure to match minimum number of optional clauses: 2");
+    }
+
+    #[test]
+    fn test_windowed_disjunction() {
+        // Enough documents to span several windows.
+        let bodies: Vec<String> = (0..5000u32)
+            .map(|i| {
+                let mut body = String::from("x");
+                for (n, word) in [(3, " three"), (5, " five"), (7, " seven")] {
+                    if i.is_multiple_of(n) {
+                        body.push_str(word);
+                    }
+                }
+                body
+            })
+            .collect();
+        let searcher = body_searcher(&bodies.iter().map(String::as_str).collect::<Vec<_>>());
+
+        let query = BooleanQuery::builder()
+            .add(term("three"), Occur::Should)
+            .add(term("five"), Occur::Should)
+            .add(term("seven"), Occur::Should)
+            .build();
+        let weight = searcher.create_weight(&query, ScoreMode::Complete, 1.0).unwrap();
+        let leaf = &searcher.leaves()[0];
+
+        let mut windowed = HitsCollector::default();
+        let mut bulk = weight.bulk_scorer(leaf).unwrap().unwrap();
+        assert_eq!(bulk.score(&mut windowed, 0, 3000).unwrap(), 3000);
+        assert_eq!(bulk.score(&mut windowed, 3000, NO_MORE_DOCS).unwrap(), NO_MORE_DOCS);
+
+        let mut iterated = HitsCollector::default();
+        DefaultBulkScorer::new(weight.scorer(leaf).unwrap().unwrap()).score_all(&mut iterated).unwrap();
+
+        let expected =
+            (0..5000u32).filter(|i| i.is_multiple_of(3) || i.is_multiple_of(5) || i.is_multiple_of(7)).count();
+        assert_eq!(windowed.hits.len(), expected);
+        assert_eq!(windowed.hits.len(), iterated.hits.len());
+        for ((doc1, score1), (doc2, score2)) in windowed.hits.iter().zip(iterated.hits.iter()) {
+            assert_eq!(doc1, doc2);
+            assert!((score1 - score2).abs() < 1e-6);
+        }
+
+        // Documents matching more clauses score higher.
+        let top = searcher.search(&query, 1).unwrap();
+        assert_eq!(top.score_docs[0].doc % 105, 0);
+    }
+}
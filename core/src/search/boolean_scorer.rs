@@ -0,0 +1,160 @@
+use {
+    crate::{
+        search::{BulkScorer, LeafCollector, Scorable, NO_MORE_DOCS},
+        BoxResult,
+    },
+    std::fmt::{Debug, Formatter, Result as FmtResult},
+};
+
+/// The number of documents scored at a time.
+const WINDOW_SIZE: u32 = 2048;
+const WINDOW_MASK: u32 = WINDOW_SIZE - 1;
+
+/// A [BulkScorer] for disjunctions of optional clauses that scores documents one window at a time.
+///
+/// For each window of [WINDOW_SIZE] documents, every clause bulk-scores its matches into a table of buckets that
+/// accumulate the score and number of matching clauses of each document. The matching documents are then replayed in
+/// order to the collector. This avoids the per-document cost of keeping many iterators ordered, which makes it
+/// efficient for disjunctions of many clauses when all matches must be visited.
+pub struct BooleanScorer {
+    subs: Vec<SubScorer>,
+    buckets: Box<[Bucket]>,
+
+    /// A bit set over the window of the buckets that have matched.
+    matching: Box<[u64]>,
+    min_should_match: u32,
+    needs_scores: bool,
+    cost: u64,
+}
+
+#[derive(Debug)]
+struct SubScorer {
+    scorer: Box<dyn BulkScorer>,
+
+    /// A lower bound on the next document this scorer can match.
+    next: u32,
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+struct Bucket {
+    score: f64,
+    freq: u32,
+}
+
+impl BooleanScorer {
+    /// Creates a scorer over the disjunction of the given bulk scorers. Documents are collected only if at least
+    /// `min_should_match` of them match; scores are summed only if `needs_scores` is set.
+    pub fn new(subs: Vec<Box<dyn BulkScorer>>, min_should_match: u32, needs_scores: bool) -> Self {
+        let cost = subs.iter().map(|s| s.cost()).sum();
+        Self {
+            subs: subs
+                .into_iter()
+                .map(|scorer| SubScorer {
+                    scorer,
+                    next: 0,
+                })
+                .collect(),
+            buckets: vec![Bucket::default(); WINDOW_SIZE as usize].into(),
+            matching: vec![0; WINDOW_SIZE as usize / 64].into(),
+            min_should_match: min_should_match.max(1),
+            needs_scores,
+            cost,
+        }
+    }
+
+    #[inline]
+    fn min_next(&self) -> u32 {
+        self.subs.iter().map(|s| s.next).min().unwrap_or(NO_MORE_DOCS)
+    }
+
+    /// Scores the documents in `min..max`, all of which lie in the window starting at `base`.
+    fn score_window(&mut self, collector: &mut dyn LeafCollector, base: u32, min: u32, max: u32) -> BoxResult<()> {
+        let mut or_collector = OrCollector {
+            buckets: &mut self.buckets,
+            matching: &mut self.matching,
+            base,
+            needs_scores: self.needs_scores,
+        };
+
+        for sub in self.subs.iter_mut().filter(|s| s.next < max) {
+            sub.next = sub.scorer.score(&mut or_collector, min, max)?;
+        }
+
+        for (word_index, word) in self.matching.iter_mut().enumerate() {
+            let mut bits = std::mem::take(word);
+            while bits != 0 {
+                let index = word_index * 64 + bits.trailing_zeros() as usize;
+                bits &= bits - 1;
+
+                let bucket = std::mem::take(&mut self.buckets[index]);
+                if bucket.freq >= self.min_should_match {
+                    collector.collect(base + index as u32, &mut BucketScore(bucket.score as f32))?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Debug for BooleanScorer {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        f.debug_struct("BooleanScorer")
+            .field("subs", &self.subs)
+            .field("min_should_match", &self.min_should_match)
+            .field("needs_scores", &self.needs_scores)
+            .finish()
+    }
+}
+
+impl BulkScorer for BooleanScorer {
+    fn score(&mut self, collector: &mut dyn LeafCollector, min: u32, max: u32) -> BoxResult<u32> {
+        let mut window_min = self.min_next().max(min);
+        while window_min < max {
+            let base = window_min & !WINDOW_MASK;
+            let window_max = (base + WINDOW_SIZE).min(max);
+            self.score_window(collector, base, window_min, window_max)?;
+            window_min = self.min_next();
+        }
+
+        Ok(self.min_next())
+    }
+
+    #[inline]
+    fn cost(&self) -> u64 {
+        self.cost
+    }
+}
+
+/// Accumulates the matches of a clause into the buckets of the current window.
+struct OrCollector<'a> {
+    buckets: &'a mut [Bucket],
+    matching: &'a mut [u64],
+    base: u32,
+    needs_scores: bool,
+}
+
+impl LeafCollector for OrCollector<'_> {
+    fn collect(&mut self, doc: u32, scorer: &mut dyn Scorable) -> BoxResult<()> {
+        let index = (doc - self.base) as usize;
+        self.matching[index / 64] |= 1 << (index % 64);
+
+        let bucket = &mut self.buckets[index];
+        bucket.freq += 1;
+        if self.needs_scores {
+            bucket.score += scorer.score()? as f64;
+        }
+
+        Ok(())
+    }
+}
+
+/// Reports the accumulated score of a bucket to the collector.
+struct BucketScore(f32);
+
+impl Scorable for BucketScore {
+    #[inline]
+    fn score(&mut self) -> BoxResult<f32> {
+        Ok(self.0)
+    }
+}
@@ -0,0 +1,42 @@
+use crate::search::{CollectionStatistics, Explanation, SimScorer, Similarity, TermStatistics};
+
+/// A similarity that only considers whether a term is present: every matching term scores its query boost, whatever
+/// its frequency or the length of the field, as in Lucene's `BooleanSimilarity`. This suits fields such as tags or
+/// keywords, where repetition and length carry no meaning.
+///
+/// Norms are computed as for [crate::search::BM25Similarity], so the similarity can be switched without reindexing.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct BooleanSimilarity;
+
+impl Similarity for BooleanSimilarity {
+    fn scorer(
+        &self,
+        boost: f32,
+        _collection_stats: &CollectionStatistics,
+        _term_stats: &[TermStatistics],
+    ) -> Box<dyn SimScorer> {
+        Box::new(BooleanSimScorer {
+            boost,
+        })
+    }
+}
+
+/// The [SimScorer] for [BooleanSimilarity].
+#[derive(Clone, Copy, Debug)]
+struct BooleanSimScorer {
+    boost: f32,
+}
+
+impl SimScorer for BooleanSimScorer {
+    fn score(&self, _freq: f32, _norm: i64) -> f32 {
+        self.boost
+    }
+
+    fn explain(&self, _freq: f32, _norm: i64) -> Explanation {
+        Explanation::matched(
+            self.boost,
+            "score(BooleanSimilarity), computed from:",
+            vec![Explanation::matched(self.boost, "boost, query boost", vec![])],
+        )
+    }
+}
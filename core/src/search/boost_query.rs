@@ -0,0 +1,151 @@
+use {
+    crate::{
+        search::{IndexSearcher, Query, ScoreMode, Weight},
+        BoxResult, LuceneError,
+    },
+    std::{
+        any::Any,
+        fmt::{Display, Formatter, Result as FmtResult},
+        sync::Arc,
+    },
+};
+
+/// A query that multiplies the scores of another query by a boost factor.
+#[derive(Clone, Debug)]
+pub struct BoostQuery {
+    query: Arc<dyn Query>,
+    boost: f32,
+}
+
+impl BoostQuery {
+    /// Wraps the given query. This fails with [LuceneError::InvalidArgument] if the boost is negative or not finite.
+    pub fn new(query: Arc<dyn Query>, boost: f32) -> BoxResult<Self> {
+        Self::check_boost(boost)?;
+        Ok(Self {
+            query,
+            boost,
+        })
+    }
+
+    /// Fails with [LuceneError::InvalidArgument] if `boost` is negative or not finite.
+    pub(crate) fn check_boost(boost: f32) -> BoxResult<()> {
+        if !boost.is_finite() || boost < 0.0 {
+            return Err(LuceneError::InvalidArgument(format!(
+                "boost must be a non-negative finite value, got {boost}"
+            ))
+            .into());
+        }
+        Ok(())
+    }
+
+    /// Returns the wrapped query.
+    #[inline]
+    pub fn query(&self) -> &Arc<dyn Query> {
+        &self.query
+    }
+
+    /// Returns the boost factor.
+    #[inline]
+    pub fn boost(&self) -> f32 {
+        self.boost
+    }
+}
+
+impl Query for BoostQuery {
+    fn create_weight(&self, searcher: &IndexSearcher, score_mode: ScoreMode, boost: f32) -> BoxResult<Box<dyn Weight>> {
+        self.query.create_weight(searcher, score_mode, boost * self.boost)
+    }
+
+    fn rewrite(&self, searcher: &IndexSearcher) -> BoxResult<Option<Arc<dyn Query>>> {
+        if self.boost == 1.0 {
+            return Ok(Some(self.query.clone()));
+        }
+
+        // Nested boosts are folded into one.
+        if let Some(inner) = (self.query.as_ref() as &dyn Any).downcast_ref::<BoostQuery>() {
+            return Ok(Some(Arc::new(Self::new(inner.query.clone(), self.boost * inner.boost)?)));
+        }
+
+        match searcher.rewrite(self.query.as_ref())? {
+            Some(rewritten) => Ok(Some(Arc::new(Self::new(rewritten, self.boost)?))),
+            None => Ok(None),
+        }
+    }
+}
+
+impl Display for BoostQuery {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "({})^{}", self.query, self.boost)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{
+            document::{Document, Field, Store},
+            index::Term,
+            search::{
+                test_util::searcher, BooleanQuery, BoostQuery, ConstantScoreQuery, DisjunctionMaxQuery,
+                FieldExistsQuery, FunctionScoreQuery, LongFieldSource, MatchAllDocsQuery, NumericDocValuesRangeQuery,
+                PhraseQuery, PrefixQuery, Query, TermInSetQuery, TermQuery, WildcardQuery,
+            },
+        },
+        std::sync::Arc,
+    };
+
+    #[test]
+    fn test_boost_propagation() {
+        let mut documents = Vec::new();
+        for (i, body) in ["quick brown fox", "quick fox", "lazy brown dog", "brown fox jumps"].into_iter().enumerate() {
+            let mut doc = Document::new();
+            doc.add(Field::text("body", body, Store::No));
+            doc.add(Field::numeric_doc_values("rank", i as i64 + 1));
+            documents.push(doc);
+        }
+        let searcher = searcher([documents]);
+
+        let term = |text: &str| -> Arc<dyn Query> { Arc::new(TermQuery::new(Term::from_text("body", text))) };
+        let mut phrase = PhraseQuery::builder();
+        phrase.add(Term::from_text("body", "brown")).add(Term::from_text("body", "fox"));
+        let queries: Vec<Arc<dyn Query>> = vec![
+            term("fox"),
+            Arc::new(phrase.build().unwrap()),
+            Arc::new(BooleanQuery::builder().should(term("quick")).should(term("fox")).filter(term("brown")).build()),
+            Arc::new(ConstantScoreQuery::new(term("brown"))),
+            Arc::new(DisjunctionMaxQuery::new(vec![term("quick"), term("fox")], 0.3).unwrap()),
+            Arc::new(PrefixQuery::new(Term::from_text("body", "bro")).unwrap()),
+            Arc::new(WildcardQuery::new(Term::from_text("body", "?ox")).unwrap()),
+            Arc::new(TermInSetQuery::new("body", ["dog", "fox"])),
+            Arc::new(MatchAllDocsQuery),
+            Arc::new(NumericDocValuesRangeQuery::new("rank", 2, 3)),
+            Arc::new(FieldExistsQuery::new("rank")),
+            Arc::new(FunctionScoreQuery::boost_by_value(term("brown"), Arc::new(LongFieldSource::new("rank")))),
+        ];
+
+        for query in queries {
+            let unboosted = searcher.search(query.as_ref(), 10).unwrap();
+            let boosted = BoostQuery::new(query.clone(), 2.5).unwrap();
+            let hits = searcher.search(&boosted, 10).unwrap();
+            assert!(!hits.score_docs.is_empty(), "{query} matches nothing");
+            assert_eq!(hits.score_docs.len(), unboosted.score_docs.len(), "{query}");
+            for (hit, unboosted) in hits.score_docs.iter().zip(&unboosted.score_docs) {
+                assert_eq!(hit.doc, unboosted.doc, "{query}");
+                assert!(
+                    (hit.score - 2.5 * unboosted.score).abs() < 1e-5,
+                    "{query}: {} vs {}",
+                    hit.score,
+                    unboosted.score
+                );
+                let explanation = searcher.explain(&boosted, hit.doc).unwrap();
+                assert!(explanation.is_match(), "{query}");
+                assert!((explanation.value() - hit.score).abs() < 1e-5, "{query}: explained {explanation}");
+            }
+        }
+
+        // Constant scores show the boost they were given.
+        let boosted = BoostQuery::new(Arc::new(MatchAllDocsQuery), 2.5).unwrap();
+        assert_eq!(searcher.explain(&boosted, 0).unwrap().description(), "*:*^2.5");
+        assert_eq!(searcher.explain(&MatchAllDocsQuery, 0).unwrap().description(), "*:*");
+    }
+}
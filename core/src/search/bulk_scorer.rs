@@ -0,0 +1,323 @@
+use {
+    crate::{
+        search::{LeafCollector, Scorable, Scorer, NO_MORE_DOCS},
+        BoxResult,
+    },
+    std::fmt::Debug,
+};
+
+/// Scores a range of documents of a segment at once, passing the matches to a [LeafCollector].
+///
+/// Driving iteration from a single call rather than one virtual call per document lets implementations amortize
+/// their overhead, for example by scoring a disjunction one window of documents at a time.
+pub trait BulkScorer: Debug + Send {
+    /// Collects the matching documents in `min..max`, returning the first matching document at or after `max`
+    /// (possibly only an approximation of it), or [NO_MORE_DOCS] if there are none.
+    ///
+    /// Successive calls must cover non-overlapping, increasing ranges.
+    fn score(&mut self, collector: &mut dyn LeafCollector, min: u32, max: u32) -> BoxResult<u32>;
+
+    /// Returns an estimate of the number of documents this scorer will match.
+    fn cost(&self) -> u64;
+}
+
+/// The default [BulkScorer], which iterates over a [Scorer] and collects each match.
+///
+/// If the scorer has a two-phase view, its approximation is iterated and each candidate is confirmed with
+/// [crate::search::TwoPhaseIterator::matches] before being collected.
+///
+/// Once the collector sets a minimum competitive score (see [Scorable::set_min_competitive_score]), documents are
+/// checked in aligned blocks of [SKIP_BLOCK_SIZE]: a block whose [Scorer::max_score] is below the minimum is skipped.
+#[derive(Debug)]
+pub struct DefaultBulkScorer {
+    scorer: Box<dyn Scorer>,
+
+    /// The document the scorer (or its approximation) is positioned on, or `None` if it hasn't been positioned yet.
+    doc: Option<u32>,
+
+    /// The score below which documents may be skipped.
+    min_competitive_score: f32,
+
+    /// The last document of the block whose maximum score was last found to be competitive.
+    competitive_up_to: Option<u32>,
+}
+
+/// The number of documents whose maximum score is checked at once when skipping uncompetitive documents.
+pub const SKIP_BLOCK_SIZE: u32 = 128;
+
+impl DefaultBulkScorer {
+    /// Creates a bulk scorer over the given scorer.
+    pub fn new(scorer: Box<dyn Scorer>) -> Self {
+        Self {
+            scorer,
+            doc: None,
+            min_competitive_score: f32::NEG_INFINITY,
+            competitive_up_to: None,
+        }
+    }
+
+    /// Collects every remaining matching document.
+    pub fn score_all(&mut self, collector: &mut dyn LeafCollector) -> BoxResult<()> {
+        self.score(collector, 0, NO_MORE_DOCS)?;
+        Ok(())
+    }
+
+    /// Advances the scorer, or its approximation, to the first document at or after `target`.
+    fn advance(&mut self, target: u32) -> BoxResult<u32> {
+        let unpositioned = self.doc.is_none();
+        match self.scorer.two_phase_iterator() {
+            Some(two_phase) if unpositioned && target == 0 => two_phase.approximation_mut().next_doc(),
+            Some(two_phase) => two_phase.approximation_mut().advance(target),
+            None if unpositioned && target == 0 => self.scorer.next_doc(),
+            None => self.scorer.advance(target),
+        }
+    }
+
+    fn next_doc(&mut self) -> BoxResult<u32> {
+        match self.scorer.two_phase_iterator() {
+            Some(two_phase) => two_phase.approximation_mut().next_doc(),
+            None => self.scorer.next_doc(),
+        }
+    }
+
+    /// Moves past the blocks, starting from the one containing `doc`, that can't hold a competitive document.
+    fn skip_uncompetitive(&mut self, mut doc: u32) -> BoxResult<u32> {
+        while doc != NO_MORE_DOCS && self.competitive_up_to.is_none_or(|up_to| doc > up_to) {
+            let up_to =
+                (doc / SKIP_BLOCK_SIZE * SKIP_BLOCK_SIZE).saturating_add(SKIP_BLOCK_SIZE - 1).min(NO_MORE_DOCS - 1);
+            if self.scorer.max_score(up_to)? >= self.min_competitive_score {
+                self.competitive_up_to = Some(up_to);
+                break;
+            }
+            doc = self.advance(up_to + 1)?;
+        }
+
+        Ok(doc)
+    }
+}
+
+impl BulkScorer for DefaultBulkScorer {
+    fn score(&mut self, collector: &mut dyn LeafCollector, min: u32, max: u32) -> BoxResult<u32> {
+        let mut doc = match self.doc {
+            Some(doc) if doc >= min => doc,
+            _ => self.advance(min)?,
+        };
+
+        while doc < max {
+            if self.min_competitive_score > f32::NEG_INFINITY {
+                doc = self.skip_uncompetitive(doc)?;
+                if doc >= max {
+                    break;
+                }
+            }
+
+            let matches = match self.scorer.two_phase_iterator() {
+                Some(two_phase) => two_phase.matches()?,
+                None => true,
+            };
+
+            if matches {
+                let mut scorer = CompetitiveScorable {
+                    scorer: self.scorer.as_mut(),
+                    min_competitive_score: &mut self.min_competitive_score,
+                };
+                collector.collect(doc, &mut scorer)?;
+            }
+
+            doc = self.next_doc()?;
+        }
+
+        self.doc = Some(doc);
+        Ok(doc)
+    }
+
+    #[inline]
+    fn cost(&self) -> u64 {
+        self.scorer.cost()
+    }
+}
+
+/// Passes scores through from a [Scorer], recording the minimum competitive score set by the collector.
+struct CompetitiveScorable<'a> {
+    scorer: &'a mut dyn Scorer,
+    min_competitive_score: &'a mut f32,
+}
+
+impl Scorable for CompetitiveScorable<'_> {
+    #[inline]
+    fn score(&mut self) -> BoxResult<f32> {
+        self.scorer.score()
+    }
+
+    fn set_min_competitive_score(&mut self, min_score: f32) -> BoxResult<()> {
+        *self.min_competitive_score = min_score;
+        self.scorer.set_min_competitive_score(min_score)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{
+            search::{
+                BulkScorer, ConstantScoreScorer, DefaultBulkScorer, DocIdSetIterator, LeafCollector,
+                RangeDocIdSetIterator, Scorable, Scorer, TwoPhaseIterator, NO_MORE_DOCS, SKIP_BLOCK_SIZE,
+            },
+            BoxResult,
+        },
+        pretty_assertions::assert_eq,
+    };
+
+    #[derive(Debug, Default)]
+    struct DocsCollector {
+        docs: Vec<u32>,
+    }
+
+    impl LeafCollector for DocsCollector {
+        fn collect(&mut self, doc: u32, scorer: &mut dyn Scorable) -> BoxResult<()> {
+            assert_eq!(scorer.score()?, 2.0);
+            self.docs.push(doc);
+            Ok(())
+        }
+    }
+
+    /// Approximates with every document, but only matches multiples of three.
+    #[derive(Debug)]
+    struct MultiplesOfThree {
+        approximation: RangeDocIdSetIterator,
+    }
+
+    impl TwoPhaseIterator for MultiplesOfThree {
+        fn approximation(&self) -> &dyn DocIdSetIterator {
+            &self.approximation
+        }
+
+        fn approximation_mut(&mut self) -> &mut dyn DocIdSetIterator {
+            &mut self.approximation
+        }
+
+        fn matches(&mut self) -> BoxResult<bool> {
+            Ok(self.approximation.doc_id().is_multiple_of(3))
+        }
+
+        fn match_cost(&self) -> f32 {
+            1.0
+        }
+    }
+
+    #[test]
+    fn test_windows() {
+        let scorer = ConstantScoreScorer::new(2.0, Box::new(RangeDocIdSetIterator::new(3, 10)));
+        let mut bulk = DefaultBulkScorer::new(Box::new(scorer));
+        assert_eq!(bulk.cost(), 7);
+
+        let mut collector = DocsCollector::default();
+        assert_eq!(bulk.score(&mut collector, 0, 5).unwrap(), 5);
+        assert_eq!(collector.docs, vec![3, 4]);
+
+        // Documents between windows are skipped.
+        assert_eq!(bulk.score(&mut collector, 7, 9).unwrap(), 9);
+        assert_eq!(bulk.score(&mut collector, 9, NO_MORE_DOCS).unwrap(), NO_MORE_DOCS);
+        assert_eq!(collector.docs, vec![3, 4, 7, 8, 9]);
+    }
+
+    #[test]
+    fn test_two_phase() {
+        let two_phase = MultiplesOfThree {
+            approximation: RangeDocIdSetIterator::all(10),
+        };
+        let mut bulk = DefaultBulkScorer::new(Box::new(ConstantScoreScorer::with_two_phase(2.0, Box::new(two_phase))));
+
+        let mut collector = DocsCollector::default();
+        bulk.score_all(&mut collector).unwrap();
+        assert_eq!(collector.docs, vec![0, 3, 6, 9]);
+
+        // The same scorer iterated directly only returns confirmed matches.
+        let two_phase = MultiplesOfThree {
+            approximation: RangeDocIdSetIterator::all(10),
+        };
+        let mut scorer = ConstantScoreScorer::with_two_phase(2.0, Box::new(two_phase));
+        assert_eq!(scorer.next_doc().unwrap(), 0);
+        assert_eq!(scorer.next_doc().unwrap(), 3);
+        assert_eq!(scorer.advance(4).unwrap(), 6);
+    }
+
+    /// Matches every document; documents in odd blocks score 5, the others 1.
+    #[derive(Debug)]
+    struct AlternatingBlocks {
+        docs: RangeDocIdSetIterator,
+    }
+
+    impl AlternatingBlocks {
+        fn block_score(doc: u32) -> f32 {
+            if (doc / SKIP_BLOCK_SIZE) % 2 == 1 {
+                5.0
+            } else {
+                1.0
+            }
+        }
+    }
+
+    impl DocIdSetIterator for AlternatingBlocks {
+        fn doc_id(&self) -> u32 {
+            self.docs.doc_id()
+        }
+
+        fn next_doc(&mut self) -> BoxResult<u32> {
+            self.docs.next_doc()
+        }
+
+        fn advance(&mut self, target: u32) -> BoxResult<u32> {
+            self.docs.advance(target)
+        }
+
+        fn cost(&self) -> u64 {
+            self.docs.cost()
+        }
+    }
+
+    impl Scorable for AlternatingBlocks {
+        fn score(&mut self) -> BoxResult<f32> {
+            Ok(Self::block_score(self.doc_id()))
+        }
+    }
+
+    impl Scorer for AlternatingBlocks {
+        fn max_score(&mut self, up_to: u32) -> BoxResult<f32> {
+            let doc = self.doc_id();
+            Ok(Self::block_score(doc).max(if up_to / SKIP_BLOCK_SIZE > doc / SKIP_BLOCK_SIZE {
+                5.0
+            } else {
+                1.0
+            }))
+        }
+    }
+
+    /// Collects every document it is given, raising the minimum competitive score to 5 after the first.
+    #[derive(Debug, Default)]
+    struct MinScoreCollector {
+        docs: Vec<u32>,
+    }
+
+    impl LeafCollector for MinScoreCollector {
+        fn collect(&mut self, doc: u32, scorer: &mut dyn Scorable) -> BoxResult<()> {
+            self.docs.push(doc);
+            scorer.set_min_competitive_score(5.0)
+        }
+    }
+
+    #[test]
+    fn test_skip_uncompetitive_blocks() {
+        let scorer = AlternatingBlocks {
+            docs: RangeDocIdSetIterator::all(4 * SKIP_BLOCK_SIZE),
+        };
+        let mut bulk = DefaultBulkScorer::new(Box::new(scorer));
+        let mut collector = MinScoreCollector::default();
+        bulk.score_all(&mut collector).unwrap();
+
+        let mut expected = vec![0];
+        expected.extend(SKIP_BLOCK_SIZE..2 * SKIP_BLOCK_SIZE);
+        expected.extend(3 * SKIP_BLOCK_SIZE..4 * SKIP_BLOCK_SIZE);
+        assert_eq!(collector.docs, expected);
+    }
+}
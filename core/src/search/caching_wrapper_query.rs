@@ -0,0 +1,239 @@
+use {
+    crate::{
+        index::{CacheKey, LeafReaderContext},
+        search::{
+            cacheable_doc_id_set, ConstantScoreScorer, DocIdSet, Explanation, IndexSearcher, Query, ScoreMode, Scorer,
+            Weight,
+        },
+        BoxResult,
+    },
+    std::{
+        collections::HashMap,
+        fmt::{Debug, Display, Formatter, Result as FmtResult},
+        sync::{Arc, Mutex, Weak},
+    },
+};
+
+/// The sets of documents cached by a [CachingWrapperQuery], by the core key of their segment.
+#[derive(Debug, Default)]
+struct SegmentSets {
+    sets: HashMap<CacheKey, Arc<dyn DocIdSet>>,
+    hit_count: u64,
+    miss_count: u64,
+}
+
+/// A filter that caches the documents matched by another query in each segment, under the segment's core cache key
+/// (see [crate::index::LeafReader::core_cache_helper]), as Lucene's `CachingWrapperQuery` does. Matches get a
+/// constant score equal to the boost.
+///
+/// Unlike the searcher's [crate::search::LruQueryCache], the cache belongs to the query and every clone of it, so an
+/// application can keep a filter, such as the documents a user may see, and reuse it across searches. Cached sets
+/// include deleted documents, which are skipped when hits are collected, so they stay valid when a near-real-time
+/// reader is refreshed after deletions; new segments are evaluated on first use. A segment's set is evicted once its
+/// core reader is dropped. Segments whose data can't be cached are evaluated on every search.
+#[derive(Clone)]
+pub struct CachingWrapperQuery {
+    query: Arc<dyn Query>,
+    cache: Arc<Mutex<SegmentSets>>,
+}
+
+impl CachingWrapperQuery {
+    /// Wraps `query`, with an empty cache.
+    pub fn new(query: Arc<dyn Query>) -> Self {
+        Self {
+            query,
+            cache: Arc::default(),
+        }
+    }
+
+    /// Returns the wrapped query.
+    #[inline]
+    pub fn query(&self) -> &Arc<dyn Query> {
+        &self.query
+    }
+
+    /// Returns the number of segments whose matches are cached.
+    pub fn size(&self) -> usize {
+        self.cache.lock().unwrap().sets.len()
+    }
+
+    /// Returns the memory used by the cached sets, in bytes.
+    pub fn ram_bytes_used(&self) -> usize {
+        self.cache.lock().unwrap().sets.values().map(|set| set.ram_bytes_used()).sum()
+    }
+
+    /// Returns the number of times a segment's matches were found in the cache.
+    pub fn hit_count(&self) -> u64 {
+        self.cache.lock().unwrap().hit_count
+    }
+
+    /// Returns the number of times a segment's matches were computed.
+    pub fn miss_count(&self) -> u64 {
+        self.cache.lock().unwrap().miss_count
+    }
+}
+
+impl Query for CachingWrapperQuery {
+    fn create_weight(
+        &self,
+        searcher: &IndexSearcher,
+        _score_mode: ScoreMode,
+        boost: f32,
+    ) -> BoxResult<Box<dyn Weight>> {
+        Ok(Box::new(CachingWrapperWeight {
+            inner: searcher.create_weight(self.query.as_ref(), ScoreMode::CompleteNoScores, 1.0)?,
+            cache: self.cache.clone(),
+            score: boost,
+            description: self.to_string(),
+        }))
+    }
+
+    fn rewrite(&self, searcher: &IndexSearcher) -> BoxResult<Option<Arc<dyn Query>>> {
+        // The rewritten query matches the same documents, so it keeps the cache.
+        Ok(searcher.rewrite(self.query.as_ref())?.map(|rewritten| {
+            Arc::new(Self {
+                query: rewritten,
+                cache: self.cache.clone(),
+            }) as Arc<dyn Query>
+        }))
+    }
+}
+
+// The cache is left out, as the query cache identifies queries by their debug form.
+impl Debug for CachingWrapperQuery {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        f.debug_struct("CachingWrapperQuery").field("query", &self.query).finish_non_exhaustive()
+    }
+}
+
+impl Display for CachingWrapperQuery {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "CachingWrapperQuery({})", self.query)
+    }
+}
+
+#[derive(Debug)]
+struct CachingWrapperWeight {
+    inner: Box<dyn Weight>,
+    cache: Arc<Mutex<SegmentSets>>,
+    score: f32,
+    description: String,
+}
+
+impl CachingWrapperWeight {
+    /// Returns the matches of the wrapped query in the segment, from the cache if possible.
+    fn doc_id_set(&self, context: &LeafReaderContext) -> BoxResult<Option<Arc<dyn DocIdSet>>> {
+        let Some(helper) = context.reader().core_cache_helper() else {
+            return self.compute(context);
+        };
+
+        let key = helper.key();
+        {
+            let mut cache = self.cache.lock().unwrap();
+            if let Some(set) = cache.sets.get(&key).cloned() {
+                cache.hit_count += 1;
+                return Ok(Some(set));
+            }
+            cache.miss_count += 1;
+        }
+
+        let Some(set) = self.compute(context)? else {
+            return Ok(None);
+        };
+        if self.cache.lock().unwrap().sets.insert(key, set.clone()).is_none() {
+            let cache: Weak<Mutex<SegmentSets>> = Arc::downgrade(&self.cache);
+            helper.add_closed_listener(move |key| {
+                if let Some(cache) = cache.upgrade() {
+                    cache.lock().unwrap().sets.remove(&key);
+                }
+            });
+        }
+        Ok(Some(set))
+    }
+
+    fn compute(&self, context: &LeafReaderContext) -> BoxResult<Option<Arc<dyn DocIdSet>>> {
+        let Some(mut scorer) = self.inner.scorer(context)? else {
+            return Ok(None);
+        };
+        Ok(Some(cacheable_doc_id_set(scorer.as_mut(), context.reader().max_doc())?))
+    }
+}
+
+impl Weight for CachingWrapperWeight {
+    fn scorer(&self, context: &LeafReaderContext) -> BoxResult<Option<Box<dyn Scorer>>> {
+        Ok(self
+            .doc_id_set(context)?
+            .map(|set| Box::new(ConstantScoreScorer::new(self.score, set.iterator())) as Box<dyn Scorer>))
+    }
+
+    fn explain(&self, context: &LeafReaderContext, doc: u32) -> BoxResult<Explanation> {
+        let inner = self.inner.explain(context, doc)?;
+        if inner.is_match() {
+            Ok(Explanation::constant(self.score, self.description.clone()))
+        } else {
+            Ok(Explanation::no_match(format!("{} doesn't match document {doc}", self.description), vec![inner]))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{
+            index::{LeafReader, SegmentReader, Term},
+            search::{
+                test_util::{body_segment, leaf_searcher},
+                BooleanQuery, CachingWrapperQuery, IndexSearcher, MatchAllDocsQuery, Query, TermQuery,
+            },
+            util::FixedBitSet,
+        },
+        pretty_assertions::assert_eq,
+        std::sync::Arc,
+    };
+
+    fn docs(searcher: &IndexSearcher, query: &dyn Query) -> Vec<u32> {
+        let mut docs: Vec<u32> = searcher.search(query, 10).unwrap().score_docs.iter().map(|sd| sd.doc).collect();
+        docs.sort_unstable();
+        docs
+    }
+
+    #[test]
+    fn test_cache_across_refreshes() {
+        let first = body_segment(&["a b", "a", "b c"]);
+        let second = body_segment(&["c", "a c"]);
+        let filter = CachingWrapperQuery::new(Arc::new(TermQuery::new(Term::from_text("body", "a"))));
+        assert_eq!(filter.to_string(), "CachingWrapperQuery(body:a)");
+        let query = BooleanQuery::builder().must(Arc::new(MatchAllDocsQuery)).filter(Arc::new(filter.clone())).build();
+
+        let searcher = leaf_searcher(vec![first.clone(), second.clone()]);
+        assert_eq!(docs(&searcher, &query), vec![0, 1, 4]);
+        assert_eq!((filter.size(), filter.hit_count(), filter.miss_count()), (2, 0, 2));
+        assert_eq!(docs(&searcher, &filter), vec![0, 1, 4]);
+        assert_eq!((filter.size(), filter.hit_count(), filter.miss_count()), (2, 2, 2));
+        assert!(filter.ram_bytes_used() > 0);
+
+        // A refresh deleting a document from the first segment and adding a third keeps the first two cached.
+        let mut live_docs = FixedBitSet::new(3);
+        live_docs.set_range(1, 3);
+        let refreshed: Vec<Arc<dyn LeafReader>> = vec![
+            Arc::new(SegmentReader::new(first.clone(), Some(live_docs)).unwrap()),
+            second.clone(),
+            body_segment(&["a"]),
+        ];
+        assert_eq!(refreshed[0].core_cache_helper().unwrap().key(), first.core_cache_helper().unwrap().key());
+        assert_ne!(refreshed[0].reader_cache_helper().unwrap().key(), first.reader_cache_helper().unwrap().key());
+        let refreshed = leaf_searcher(refreshed);
+        assert_eq!(docs(&refreshed, &query), vec![1, 4, 5]);
+        assert_eq!((filter.size(), filter.hit_count(), filter.miss_count()), (3, 4, 3));
+        assert!(refreshed.explain(&query, 5).unwrap().is_match());
+        assert!(!refreshed.explain(&query, 3).unwrap().is_match());
+
+        // Segments are evicted once their readers are dropped.
+        drop(refreshed);
+        assert_eq!(filter.size(), 2);
+        drop((searcher, first));
+        assert_eq!(filter.size(), 1);
+        drop(second);
+        assert_eq!(filter.size(), 0);
+    }
+}
@@ -0,0 +1,398 @@
+use {
+    crate::{util::human_readable_units, BoxError, BoxResult, LuceneError},
+    std::{
+        fmt::{Display, Formatter, Result as FmtResult},
+        sync::{
+            atomic::{AtomicU64, AtomicUsize, Ordering},
+            Arc,
+        },
+    },
+};
+
+/// The memory budget that a search exceeded.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum MemoryLimitScope {
+    /// The budget of a single search, set with [crate::search::IndexSearcher::set_query_memory_limit].
+    Query,
+
+    /// The budget shared by every search using the same [CircuitBreaker].
+    Global,
+}
+
+impl Display for MemoryLimitScope {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match self {
+            Self::Query => write!(f, "query"),
+            Self::Global => write!(f, "global"),
+        }
+    }
+}
+
+/// Describes the reservation that would have taken a search over its memory budget. This is carried by
+/// [LuceneError::MemoryLimitExceeded].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MemoryLimitExceeded {
+    /// The budget that was exceeded.
+    pub scope: MemoryLimitScope,
+
+    /// What the memory was reserved for, such as `"collector"`.
+    pub label: String,
+
+    /// The number of bytes requested.
+    pub requested_bytes: usize,
+
+    /// The number of bytes already reserved against the budget.
+    pub used_bytes: usize,
+
+    /// The size of the budget, in bytes.
+    pub limit_bytes: usize,
+}
+
+impl Display for MemoryLimitExceeded {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(
+            f,
+            "{} limit of {} exceeded by {}: {} requested with {} already in use",
+            self.scope,
+            human_readable_units(self.limit_bytes),
+            self.label,
+            human_readable_units(self.requested_bytes),
+            human_readable_units(self.used_bytes)
+        )
+    }
+}
+
+/// Returns the details of a [LuceneError::MemoryLimitExceeded] error, or `None` for any other error.
+pub fn memory_limit_exceeded(error: &BoxError) -> Option<&MemoryLimitExceeded> {
+    match error.downcast_ref::<LuceneError>() {
+        Some(LuceneError::MemoryLimitExceeded(exceeded)) => Some(exceeded),
+        _ => None,
+    }
+}
+
+/// A memory budget shared by concurrent searches, which keeps them together from exhausting the process's memory.
+///
+/// Share a breaker between searchers with [crate::search::IndexSearcher::set_circuit_breaker]. Each search reserves
+/// the memory it tracks against the breaker as well as against its own limit, and returns it once it finishes. A
+/// reservation that would take the total over the limit fails with [LuceneError::MemoryLimitExceeded] and "trips"
+/// the breaker, aborting that search while the others carry on.
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    limit: usize,
+    used: AtomicUsize,
+    trips: AtomicU64,
+}
+
+impl CircuitBreaker {
+    /// Creates a breaker that allows `limit` bytes to be reserved at once.
+    pub fn new(limit: usize) -> Self {
+        Self {
+            limit,
+            used: AtomicUsize::new(0),
+            trips: AtomicU64::new(0),
+        }
+    }
+
+    /// Returns the number of bytes that may be reserved at once.
+    #[inline]
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    /// Returns the number of bytes currently reserved.
+    #[inline]
+    pub fn used(&self) -> usize {
+        self.used.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of reservations that have been refused.
+    #[inline]
+    pub fn trips(&self) -> u64 {
+        self.trips.load(Ordering::Relaxed)
+    }
+
+    /// Reserves `bytes` for `label`, failing with [LuceneError::MemoryLimitExceeded] if that would take the total
+    /// over the limit. A failed reservation reserves nothing.
+    pub fn try_reserve(&self, bytes: usize, label: &str) -> BoxResult<()> {
+        let result = self.used.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |used| {
+            used.checked_add(bytes).filter(|&total| total <= self.limit)
+        });
+
+        result.map(|_| ()).map_err(|used| {
+            self.trips.fetch_add(1, Ordering::Relaxed);
+            exceeded(MemoryLimitScope::Global, label, bytes, used, self.limit)
+        })
+    }
+
+    /// Returns `bytes` that were reserved with [CircuitBreaker::try_reserve].
+    pub fn release(&self, bytes: usize) {
+        let _ = self.used.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |used| Some(used.saturating_sub(bytes)));
+    }
+}
+
+/// Tracks the memory reserved by a single search against its own limit and, optionally, a shared [CircuitBreaker].
+///
+/// Each search through a [crate::search::IndexSearcher] gets its own tracker (see
+/// [crate::search::IndexSearcher::memory_tracker]). The searcher reserves the memory held by collectors as they
+/// grow, and queries reserve the memory of the large structures they build while rewriting and scoring.
+/// Reservations are held until the search ends, when they are all returned to the breaker; dropping the tracker
+/// returns them as well.
+#[derive(Debug, Default)]
+pub struct QueryMemoryTracker {
+    limit: Option<usize>,
+    breaker: Option<Arc<CircuitBreaker>>,
+    used: AtomicUsize,
+    peak: AtomicUsize,
+}
+
+impl QueryMemoryTracker {
+    /// Creates a tracker with an optional limit of its own and an optional shared breaker. With neither, reservations
+    /// always succeed and are only counted.
+    pub fn new(limit: Option<usize>, breaker: Option<Arc<CircuitBreaker>>) -> Self {
+        Self {
+            limit,
+            breaker,
+            used: AtomicUsize::new(0),
+            peak: AtomicUsize::new(0),
+        }
+    }
+
+    /// Returns the number of bytes the search may reserve, if limited.
+    #[inline]
+    pub fn limit(&self) -> Option<usize> {
+        self.limit
+    }
+
+    /// Returns the shared breaker, if any.
+    #[inline]
+    pub fn breaker(&self) -> Option<&Arc<CircuitBreaker>> {
+        self.breaker.as_ref()
+    }
+
+    /// Returns the number of bytes currently reserved.
+    #[inline]
+    pub fn used(&self) -> usize {
+        self.used.load(Ordering::Relaxed)
+    }
+
+    /// Returns the most bytes reserved at once since the tracker was last reset.
+    #[inline]
+    pub fn peak(&self) -> usize {
+        self.peak.load(Ordering::Relaxed)
+    }
+
+    /// Reserves `bytes` for `label` against the search's limit, then against the breaker. Fails with
+    /// [LuceneError::MemoryLimitExceeded] if either would be exceeded, in which case nothing is reserved.
+    pub fn reserve(&self, bytes: usize, label: &str) -> BoxResult<()> {
+        if bytes == 0 {
+            return Ok(());
+        }
+
+        let limit = self.limit.unwrap_or(usize::MAX);
+        let used = self
+            .used
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |used| {
+                used.checked_add(bytes).filter(|&total| total <= limit)
+            })
+            .map_err(|used| exceeded(MemoryLimitScope::Query, label, bytes, used, limit))?;
+
+        if let Some(breaker) = &self.breaker {
+            if let Err(e) = breaker.try_reserve(bytes, label) {
+                self.used.fetch_sub(bytes, Ordering::Relaxed);
+                return Err(e);
+            }
+        }
+
+        self.peak.fetch_max(used + bytes, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Returns `bytes` that were reserved with [QueryMemoryTracker::reserve].
+    pub fn release(&self, bytes: usize) {
+        let used = self
+            .used
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |used| Some(used.saturating_sub(bytes)))
+            .unwrap_or_default();
+        if let Some(breaker) = &self.breaker {
+            breaker.release(bytes.min(used));
+        }
+    }
+
+    /// Returns every reservation, keeping the peak.
+    pub fn release_all(&self) {
+        let used = self.used.swap(0, Ordering::Relaxed);
+        if let Some(breaker) = &self.breaker {
+            breaker.release(used);
+        }
+    }
+
+    /// Returns every reservation and clears the peak, ready for a new search.
+    pub fn reset(&self) {
+        self.release_all();
+        self.peak.store(0, Ordering::Relaxed);
+    }
+}
+
+impl Drop for QueryMemoryTracker {
+    fn drop(&mut self) {
+        self.release_all();
+    }
+}
+
+fn exceeded(
+    scope: MemoryLimitScope,
+    label: &str,
+    requested_bytes: usize,
+    used_bytes: usize,
+    limit_bytes: usize,
+) -> BoxError {
+    LuceneError::MemoryLimitExceeded(MemoryLimitExceeded {
+        scope,
+        label: label.to_string(),
+        requested_bytes,
+        used_bytes,
+        limit_bytes,
+    })
+    .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{
+            document::{Document, Field, Store},
+            index::IndexReader,
+            search::{
+                memory_limit_exceeded, test_util, CircuitBreaker, IndexSearcher, MatchAllDocsQuery, MemoryLimitScope,
+                QueryMemoryTracker, TermInSetQuery,
+            },
+            util::ONE_KB,
+        },
+        pretty_assertions::assert_eq,
+        std::{sync::Arc, thread},
+    };
+
+    fn reader() -> Arc<dyn IndexReader> {
+        let mut segments = Vec::new();
+        for segment in 0..3 {
+            let mut documents = Vec::new();
+            for i in 0..1000 {
+                let mut doc = Document::new();
+                doc.add(Field::text("id", format!("id{segment}x{i}"), Store::No));
+                documents.push(doc);
+            }
+            segments.push(documents);
+        }
+        test_util::reader(segments)
+    }
+
+    #[test]
+    fn test_circuit_breaker() {
+        let breaker = CircuitBreaker::new(100);
+        breaker.try_reserve(60, "a").unwrap();
+        breaker.try_reserve(40, "b").unwrap();
+        assert_eq!(breaker.used(), 100);
+
+        let error = breaker.try_reserve(1, "c").unwrap_err();
+        let exceeded = memory_limit_exceeded(&error).unwrap();
+        assert_eq!(exceeded.scope, MemoryLimitScope::Global);
+        assert_eq!((exceeded.label.as_str(), exceeded.requested_bytes, exceeded.used_bytes), ("c", 1, 100));
+        assert_eq!(breaker.trips(), 1);
+        assert_eq!(breaker.used(), 100);
+
+        breaker.release(60);
+        breaker.try_reserve(1, "c").unwrap();
+        assert_eq!(breaker.used(), 41);
+        assert_eq!(
+            error.to_string(),
+            "Memory limit exceeded: global limit of 100 bytes exceeded by c: 1 bytes requested with 100 bytes already in \
+             use"
+        );
+    }
+
+    #[test]
+    fn test_query_memory_tracker() {
+        let breaker = Arc::new(CircuitBreaker::new(ONE_KB));
+        let first = QueryMemoryTracker::new(Some(600), Some(breaker.clone()));
+        let second = QueryMemoryTracker::new(None, Some(breaker.clone()));
+
+        first.reserve(500, "a").unwrap();
+        let error = first.reserve(200, "b").unwrap_err();
+        assert_eq!(memory_limit_exceeded(&error).unwrap().scope, MemoryLimitScope::Query);
+        assert_eq!(first.used(), 500);
+
+        // The second search has no limit of its own, but shares the breaker.
+        let error = second.reserve(600, "c").unwrap_err();
+        assert_eq!(memory_limit_exceeded(&error).unwrap().scope, MemoryLimitScope::Global);
+        assert_eq!(second.used(), 0);
+        second.reserve(400, "c").unwrap();
+        assert_eq!(breaker.used(), 900);
+
+        first.release(300);
+        assert_eq!((first.used(), first.peak(), breaker.used()), (200, 500, 600));
+
+        first.release_all();
+        assert_eq!((first.used(), first.peak(), breaker.used()), (0, 500, 400));
+        first.reset();
+        assert_eq!(first.peak(), 0);
+
+        drop(second);
+        assert_eq!(breaker.used(), 0);
+    }
+
+    #[test]
+    fn test_search_memory_limit() {
+        let mut searcher = IndexSearcher::new(reader());
+        let ids: Vec<String> = (0..3).flat_map(|s| (0..200).map(move |i| format!("id{s}x{i}"))).collect();
+        let query = TermInSetQuery::new("id", ids.iter().map(String::as_bytes));
+        assert_eq!(searcher.search(&query, 10).unwrap().total_hits.value, 600);
+        assert!(searcher.memory_tracker().peak() > 0);
+
+        searcher.set_query_memory_limit(Some(256));
+        let error = searcher.search(&MatchAllDocsQuery, 1000).unwrap_err();
+        let exceeded = memory_limit_exceeded(&error).unwrap();
+        assert_eq!((exceeded.scope, exceeded.label.as_str()), (MemoryLimitScope::Query, "collector"));
+        assert_eq!(searcher.memory_tracker().used(), 0);
+
+        searcher.set_query_memory_limit(None);
+        let breaker = Arc::new(CircuitBreaker::new(ONE_KB));
+        searcher.set_circuit_breaker(Some(breaker.clone()));
+        let error = searcher.search(&query, 10).unwrap_err();
+        assert_eq!(memory_limit_exceeded(&error).unwrap().scope, MemoryLimitScope::Global);
+        assert_eq!((breaker.used(), breaker.trips()), (0, 1));
+
+        assert_eq!(searcher.count(&MatchAllDocsQuery).unwrap(), 3000);
+    }
+
+    #[test]
+    fn test_concurrent_searches_track_memory_separately() {
+        let mut searcher = IndexSearcher::new(reader());
+        let breaker = Arc::new(CircuitBreaker::new(64 * ONE_KB));
+        searcher.set_query_memory_limit(Some(16 * ONE_KB));
+        searcher.set_circuit_breaker(Some(breaker.clone()));
+
+        // A search doesn't reset the reservations of another.
+        let before = searcher.memory_tracker();
+        before.reserve(100, "elsewhere").unwrap();
+        let ids: Vec<String> = (0..3).flat_map(|s| (0..200).map(move |i| format!("id{s}x{i}"))).collect();
+        let query = TermInSetQuery::new("id", ids.iter().map(String::as_bytes));
+        assert_eq!(searcher.search(&query, 10).unwrap().total_hits.value, 600);
+        assert_eq!(before.used(), 100);
+        let after = searcher.memory_tracker();
+        assert!(!Arc::ptr_eq(&before, &after));
+        assert!(after.peak() > 0);
+        assert_eq!(after.used(), 0);
+        drop(before);
+
+        thread::scope(|scope| {
+            for _ in 0..8 {
+                scope.spawn(|| {
+                    for _ in 0..10 {
+                        assert_eq!(searcher.search(&query, 10).unwrap().total_hits.value, 600);
+                    }
+                });
+            }
+        });
+        assert_eq!(breaker.used(), 0);
+    }
+}
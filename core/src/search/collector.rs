@@ -0,0 +1,91 @@
+use crate::{
+    index::LeafReaderContext,
+    search::{Scorable, ScoreMode},
+    util::{BitSet, FixedBitSet},
+    BoxError, BoxResult, LuceneError,
+};
+
+/// Receives the documents matched by a search.
+///
+/// The searcher asks for a [LeafCollector] for each segment, then passes it every matching document of that segment
+/// in increasing document id order.
+pub trait Collector: Send {
+    /// Returns the collector for the given segment.
+    fn leaf_collector(&mut self, context: &LeafReaderContext) -> BoxResult<Box<dyn LeafCollector + '_>>;
+
+    /// Indicates how this collector uses scores.
+    fn score_mode(&self) -> ScoreMode;
+
+    /// Returns the memory held by the collector's results so far, in bytes, which searchers reserve against their
+    /// memory budget between segments. Collectors that hold little memory can keep the default of 0.
+    fn ram_bytes_used(&self) -> usize {
+        0
+    }
+}
+
+/// Receives the matching documents of a single segment.
+pub trait LeafCollector {
+    /// Called for each matching document. `doc` is the segment-local document id; `scorer` can report its score.
+    ///
+    /// Returning [LuceneError::CollectionTerminated] (from here or from [Collector::leaf_collector]) skips the rest
+    /// of the segment; [LeafCollector::finish] is still called.
+    fn collect(&mut self, doc: u32, scorer: &mut dyn Scorable) -> BoxResult<()>;
+
+    /// Called once all documents of the segment have been collected.
+    fn finish(&mut self) -> BoxResult<()> {
+        Ok(())
+    }
+}
+
+/// A [LeafCollector] that passes on only the documents of a segment that have not been deleted.
+pub(crate) struct LiveDocsLeafCollector<'a> {
+    inner: Box<dyn LeafCollector + 'a>,
+    live_docs: &'a FixedBitSet,
+}
+
+impl<'a> LiveDocsLeafCollector<'a> {
+    pub(crate) fn new(inner: Box<dyn LeafCollector + 'a>, live_docs: &'a FixedBitSet) -> Self {
+        Self {
+            inner,
+            live_docs,
+        }
+    }
+}
+
+impl LeafCollector for LiveDocsLeafCollector<'_> {
+    fn collect(&mut self, doc: u32, scorer: &mut dyn Scorable) -> BoxResult<()> {
+        if self.live_docs.get(doc) {
+            self.inner.collect(doc, scorer)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn finish(&mut self) -> BoxResult<()> {
+        self.inner.finish()
+    }
+}
+
+/// Creates the [Collector]s for a search that may run over several slices of the index concurrently, then reduces
+/// their results into one.
+///
+/// Each slice gets its own collector from [CollectorManager::new_collector]. Once every slice has been searched,
+/// [CollectorManager::reduce] is called with all of the collectors, in slice order.
+pub trait CollectorManager: Sync {
+    /// The collector for a single slice.
+    type Collector: Collector;
+
+    /// The result of the search.
+    type Result;
+
+    /// Returns a new collector for a slice.
+    fn new_collector(&self) -> BoxResult<Self::Collector>;
+
+    /// Combines the collectors of all slices into the result.
+    fn reduce(&self, collectors: Vec<Self::Collector>) -> BoxResult<Self::Result>;
+}
+
+/// Indicates whether the error is [LuceneError::CollectionTerminated].
+pub(crate) fn is_collection_terminated(error: &BoxError) -> bool {
+    matches!(error.downcast_ref::<LuceneError>(), Some(LuceneError::CollectionTerminated))
+}
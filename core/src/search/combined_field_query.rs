@@ -0,0 +1,450 @@
+use {
+    crate::{
+        index::{LeafReaderContext, PostingsEnum, Term},
+        search::{
+            CollectionStatistics, DocIdSetIterator, Explanation, IndexSearcher, MatchNoDocsQuery, Query, Scorable,
+            ScoreMode, Scorer, SimScorer, TermStatistics, Weight, NO_MORE_DOCS,
+        },
+        util::{byte4_to_int, int_to_byte4},
+        BoxResult, LuceneError,
+    },
+    std::{
+        collections::{BTreeMap, BTreeSet},
+        fmt::{Display, Formatter, Result as FmtResult},
+        sync::Arc,
+    },
+};
+
+/// A query that scores terms across several fields as if the fields were a single combined field, following the
+/// BM25F model, as in Lucene's `CombinedFieldQuery`.
+///
+/// For each term, the frequencies in each field are multiplied by the field's weight and summed into a single
+/// frequency, and the field lengths are combined the same way. The searcher's similarity then scores this pseudo
+/// field with statistics merged across the fields. Unlike a disjunction of per-field term queries, a term isn't
+/// rewarded for appearing in several fields beyond its weighted frequency, and a rare term in one field doesn't get
+/// an inflated inverse document frequency. A document matches if it contains any of the terms in any of the fields.
+///
+/// All fields should be analyzed the same way, since the same terms are looked up in each.
+#[derive(Clone, Debug)]
+pub struct CombinedFieldQuery {
+    fields: Vec<(String, f32)>,
+    terms: Vec<Vec<u8>>,
+}
+
+impl CombinedFieldQuery {
+    /// Returns a builder for a new query.
+    #[inline]
+    pub fn builder() -> CombinedFieldQueryBuilder {
+        CombinedFieldQueryBuilder::new()
+    }
+
+    /// Returns the fields and their weights, ordered by field name.
+    #[inline]
+    pub fn fields(&self) -> &[(String, f32)] {
+        &self.fields
+    }
+
+    /// Returns the terms, in byte order.
+    #[inline]
+    pub fn terms(&self) -> &[Vec<u8>] {
+        &self.terms
+    }
+}
+
+impl Query for CombinedFieldQuery {
+    fn create_weight(
+        &self,
+        searcher: &IndexSearcher,
+        _score_mode: ScoreMode,
+        boost: f32,
+    ) -> BoxResult<Box<dyn Weight>> {
+        // The combined field is in as many documents as its most common field, and as long as the weighted fields.
+        let mut collection_stats: Option<CollectionStatistics> = None;
+        for (field, weight) in self.fields.iter() {
+            let Some(field_stats) = searcher.collection_statistics(field)? else {
+                continue;
+            };
+
+            let stats = collection_stats.get_or_insert_with(|| CollectionStatistics {
+                field: self.fields.iter().map(|(field, _)| field.as_str()).collect::<Vec<_>>().join(" "),
+                max_doc: field_stats.max_doc,
+                doc_count: 0,
+                sum_total_term_freq: 0,
+                sum_doc_freq: 0,
+            });
+            stats.doc_count = stats.doc_count.max(field_stats.doc_count);
+            stats.sum_doc_freq = stats.sum_doc_freq.max(field_stats.sum_doc_freq);
+            stats.sum_total_term_freq += (*weight as f64 * field_stats.sum_total_term_freq as f64).round() as u64;
+        }
+
+        let mut sim_scorers = Vec::with_capacity(self.terms.len());
+        for term in self.terms.iter() {
+            let mut term_stats = TermStatistics {
+                term: term.clone(),
+                doc_freq: 0,
+                total_term_freq: 0,
+            };
+
+            for (field, weight) in self.fields.iter() {
+                if let Some(field_stats) = searcher.term_statistics(&Term::new(field.as_str(), term.as_slice()))? {
+                    term_stats.doc_freq = term_stats.doc_freq.max(field_stats.doc_freq);
+                    term_stats.total_term_freq += (*weight as f64 * field_stats.total_term_freq as f64).round() as u64;
+                }
+            }
+
+            // Terms that occur nowhere can't match, and get no scorer.
+            let sim_scorer = match &collection_stats {
+                Some(cs) if term_stats.doc_freq > 0 => {
+                    Some(Arc::from(searcher.similarity().scorer(boost, cs, &[term_stats])))
+                }
+                _ => None,
+            };
+            sim_scorers.push(sim_scorer);
+        }
+
+        Ok(Box::new(CombinedFieldWeight {
+            query: self.clone(),
+            sim_scorers,
+        }))
+    }
+
+    fn rewrite(&self, _searcher: &IndexSearcher) -> BoxResult<Option<Arc<dyn Query>>> {
+        if self.fields.is_empty() || self.terms.is_empty() {
+            return Ok(Some(Arc::new(MatchNoDocsQuery::new("CombinedFieldQuery with no fields or no terms"))));
+        }
+
+        Ok(None)
+    }
+}
+
+impl Display for CombinedFieldQuery {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "CombinedFieldQuery((")?;
+        for (i, (field, weight)) in self.fields.iter().enumerate() {
+            if i > 0 {
+                write!(f, " ")?;
+            }
+
+            write!(f, "{field}")?;
+            if *weight != 1.0 {
+                write!(f, "^{weight}")?;
+            }
+        }
+
+        write!(f, ")(")?;
+        for (i, term) in self.terms.iter().enumerate() {
+            if i > 0 {
+                write!(f, " ")?;
+            }
+
+            write!(f, "{}", String::from_utf8_lossy(term))?;
+        }
+        write!(f, "))")
+    }
+}
+
+/// Builds a [CombinedFieldQuery].
+#[derive(Clone, Debug, Default)]
+pub struct CombinedFieldQueryBuilder {
+    fields: BTreeMap<String, f32>,
+    terms: BTreeSet<Vec<u8>>,
+}
+
+impl CombinedFieldQueryBuilder {
+    /// Creates a builder with no fields or terms.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a field with a weight, which multiplies the frequencies and lengths of the field. The weight must be
+    /// finite and at least 1. Adding a field again replaces its weight.
+    pub fn add_field(&mut self, field: &str, weight: f32) -> BoxResult<&mut Self> {
+        if !weight.is_finite() || weight < 1.0 {
+            return Err(LuceneError::InvalidArgument(format!(
+                "weight for field {field} must be finite and at least 1; got {weight}"
+            ))
+            .into());
+        }
+
+        self.fields.insert(field.to_string(), weight);
+        Ok(self)
+    }
+
+    /// Adds a term, given as the bytes of the token.
+    pub fn add_term(&mut self, term: impl AsRef<[u8]>) -> &mut Self {
+        self.terms.insert(term.as_ref().to_vec());
+        self
+    }
+
+    /// Builds the query.
+    pub fn build(&self) -> CombinedFieldQuery {
+        CombinedFieldQuery {
+            fields: self.fields.iter().map(|(field, weight)| (field.clone(), *weight)).collect(),
+            terms: self.terms.iter().cloned().collect(),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct CombinedFieldWeight {
+    query: CombinedFieldQuery,
+
+    /// The scorer of each term, or `None` if the term occurs nowhere.
+    sim_scorers: Vec<Option<Arc<dyn SimScorer>>>,
+}
+
+impl CombinedFieldWeight {
+    fn combined_scorer(&self, context: &LeafReaderContext) -> BoxResult<Option<CombinedFieldScorer>> {
+        let reader = context.reader();
+        let mut postings = Vec::new();
+        for (term_index, (term, sim_scorer)) in self.query.terms.iter().zip(self.sim_scorers.iter()).enumerate() {
+            if sim_scorer.is_none() {
+                continue;
+            }
+
+            for (field_index, (field, _)) in self.query.fields.iter().enumerate() {
+                let Some(terms) = reader.terms(field)? else {
+                    continue;
+                };
+
+                let mut te = terms.iterator()?;
+                if te.seek_exact(term)? {
+                    postings.push(FieldTermPostings {
+                        term_index,
+                        field_index,
+                        postings: te.postings()?,
+                        doc: None,
+                    });
+                }
+            }
+        }
+
+        if postings.is_empty() {
+            return Ok(None);
+        }
+
+        let mut norms = Vec::with_capacity(self.query.fields.len());
+        for (field, _) in self.query.fields.iter() {
+            norms.push(reader.norms(field)?);
+        }
+
+        Ok(Some(CombinedFieldScorer {
+            cost: postings.iter().map(|p| p.postings.cost()).sum(),
+            postings,
+            weights: self.query.fields.iter().map(|(_, weight)| *weight).collect(),
+            norms,
+            sim_scorers: self.sim_scorers.clone(),
+            doc: None,
+        }))
+    }
+}
+
+impl Weight for CombinedFieldWeight {
+    fn scorer(&self, context: &LeafReaderContext) -> BoxResult<Option<Box<dyn Scorer>>> {
+        Ok(self.combined_scorer(context)?.map(|scorer| Box::new(scorer) as Box<dyn Scorer>))
+    }
+
+    fn explain(&self, context: &LeafReaderContext, doc: u32) -> BoxResult<Explanation> {
+        let Some(mut scorer) = self.combined_scorer(context)? else {
+            return Ok(Explanation::no_match(format!("no matching terms for {}", self.query), vec![]));
+        };
+
+        if scorer.advance(doc)? != doc {
+            return Ok(Explanation::no_match(format!("no terms of {} occur in document {doc}", self.query), vec![]));
+        }
+
+        let norm = scorer.norm();
+        let mut details = Vec::new();
+        for (term_index, freq) in scorer.freqs()?.into_iter().enumerate() {
+            if let (Some(sim_scorer), true) = (&self.sim_scorers[term_index], freq > 0.0) {
+                let sim_explanation = sim_scorer.explain(freq, norm);
+                details.push(Explanation::matched(
+                    sim_explanation.value(),
+                    format!("weight({}) in {doc}, result of:", String::from_utf8_lossy(&self.query.terms[term_index])),
+                    vec![sim_explanation],
+                ));
+            }
+        }
+
+        Ok(Explanation::matched(
+            details.iter().map(|e| e.value()).sum(),
+            format!("weight({} in {doc}), sum of:", self.query),
+            details,
+        ))
+    }
+}
+
+/// The postings of a term in one of the fields.
+#[derive(Debug)]
+struct FieldTermPostings {
+    term_index: usize,
+    field_index: usize,
+    postings: Box<dyn PostingsEnum>,
+
+    /// The document the postings are positioned on; `None` until they have been positioned.
+    doc: Option<u32>,
+}
+
+/// A [Scorer] over the union of the postings of every term in every field, scoring each term once with its weighted
+/// frequency across the fields.
+#[derive(Debug)]
+struct CombinedFieldScorer {
+    postings: Vec<FieldTermPostings>,
+    weights: Vec<f32>,
+    norms: Vec<Option<Arc<[i64]>>>,
+    sim_scorers: Vec<Option<Arc<dyn SimScorer>>>,
+    doc: Option<u32>,
+    cost: u64,
+}
+
+impl CombinedFieldScorer {
+    /// Moves every postings list that is behind `target` to its first document at or after it, then positions this
+    /// scorer on the smallest of their documents.
+    fn advance_postings(&mut self, target: u32) -> BoxResult<u32> {
+        let mut doc = NO_MORE_DOCS;
+        for p in self.postings.iter_mut() {
+            let d = match p.doc {
+                Some(d) if d >= target => d,
+                None if target == 0 => p.postings.next_doc()?,
+                _ => p.postings.advance(target)?,
+            };
+            p.doc = Some(d);
+            doc = doc.min(d);
+        }
+
+        self.doc = Some(doc);
+        Ok(doc)
+    }
+
+    /// Returns the weighted frequency of each term in the current document.
+    fn freqs(&self) -> BoxResult<Vec<f32>> {
+        let doc = self.doc_id();
+        let mut freqs = vec![0.0; self.sim_scorers.len()];
+        for p in self.postings.iter() {
+            if p.doc == Some(doc) {
+                freqs[p.term_index] += self.weights[p.field_index] * p.postings.freq()? as f32;
+            }
+        }
+
+        Ok(freqs)
+    }
+
+    /// Returns the norm of the combined field in the current document: the weighted sum of the decoded field
+    /// lengths, encoded again.
+    fn norm(&self) -> i64 {
+        let doc = self.doc_id() as usize;
+        let mut length = 0.0f64;
+        for (weight, norms) in self.weights.iter().zip(self.norms.iter()) {
+            if let Some(&norm) = norms.as_ref().and_then(|norms| norms.get(doc)) {
+                length += *weight as f64 * byte4_to_int(norm.clamp(0, 255) as u8) as f64;
+            }
+        }
+
+        int_to_byte4(length.round() as u32) as i64
+    }
+}
+
+impl DocIdSetIterator for CombinedFieldScorer {
+    #[inline]
+    fn doc_id(&self) -> u32 {
+        self.doc.unwrap_or(NO_MORE_DOCS)
+    }
+
+    fn next_doc(&mut self) -> BoxResult<u32> {
+        match self.doc {
+            None => self.advance_postings(0),
+            Some(NO_MORE_DOCS) => Ok(NO_MORE_DOCS),
+            Some(doc) => self.advance_postings(doc + 1),
+        }
+    }
+
+    fn advance(&mut self, target: u32) -> BoxResult<u32> {
+        self.advance_postings(target)
+    }
+
+    #[inline]
+    fn cost(&self) -> u64 {
+        self.cost
+    }
+}
+
+impl Scorable for CombinedFieldScorer {
+    fn score(&mut self) -> BoxResult<f32> {
+        let norm = self.norm();
+        let mut score = 0.0f64;
+        for (freq, sim_scorer) in self.freqs()?.into_iter().zip(self.sim_scorers.iter()) {
+            if let (Some(sim_scorer), true) = (sim_scorer, freq > 0.0) {
+                score += sim_scorer.score(freq, norm) as f64;
+            }
+        }
+
+        Ok(score as f32)
+    }
+}
+
+impl Scorer for CombinedFieldScorer {
+    fn max_score(&mut self, _up_to: u32) -> BoxResult<f32> {
+        // As for term scorers: scores never decrease with frequency, and the shortest possible length is 1.
+        let mut max_score = 0.0f64;
+        for sim_scorer in self.sim_scorers.iter().flatten() {
+            max_score += sim_scorer.score(f32::MAX, 1) as f64;
+        }
+
+        Ok(max_score as f32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{
+            document::{Document, Field, Store},
+            search::{test_util::searcher, CombinedFieldQuery},
+        },
+        pretty_assertions::assert_eq,
+    };
+
+    #[test]
+    fn test_combined_field_query() {
+        let docs = [
+            ("fox", "a story about dogs"),
+            ("dogs", "the fox jumps over the dogs"),
+            ("cats", "nothing to see"),
+            ("fox fox", "fox"),
+        ];
+        let mut documents = Vec::new();
+        for (title, body) in docs {
+            let mut doc = Document::new();
+            doc.add(Field::text("title", title, Store::No));
+            doc.add(Field::text("body", body, Store::No));
+            documents.push(doc);
+        }
+        let searcher = searcher([documents]);
+
+        let query = CombinedFieldQuery::builder()
+            .add_field("title", 3.0)
+            .unwrap()
+            .add_field("body", 1.0)
+            .unwrap()
+            .add_term("fox")
+            .add_term("missing")
+            .build();
+        assert_eq!(query.to_string(), "CombinedFieldQuery((body title^3)(fox missing))");
+
+        let top_docs = searcher.search(&query, 10).unwrap();
+        let found: Vec<u32> = top_docs.score_docs.iter().map(|sd| sd.doc).collect();
+        // The weighted title occurrences outscore a single body occurrence.
+        assert_eq!(found, vec![3, 0, 1]);
+
+        for score_doc in top_docs.score_docs.iter() {
+            let explanation = searcher.explain(&query, score_doc.doc).unwrap();
+            assert!(explanation.is_match());
+            assert!((explanation.value() - score_doc.score).abs() < 1e-5);
+        }
+        assert!(!searcher.explain(&query, 2).unwrap().is_match());
+
+        assert!(CombinedFieldQuery::builder().add_field("title", 0.5).is_err());
+        let empty = CombinedFieldQuery::builder().add_term("fox").build();
+        assert_eq!(searcher.count(&empty).unwrap(), 0);
+    }
+}
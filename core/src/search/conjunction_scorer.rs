@@ -0,0 +1,106 @@
+use crate::{
+    search::{DocIdSetIterator, Scorable, Scorer, NO_MORE_DOCS},
+    BoxResult,
+};
+
+/// A [Scorer] over the intersection of its sub-scorers.
+///
+/// The sub-scorer with the lowest cost leads iteration and the others are advanced to its candidates. Only the
+/// sub-scorers marked as scoring contribute to the score; the others act as filters.
+#[derive(Debug)]
+pub struct ConjunctionScorer {
+    /// The sub-scorers ordered by increasing cost, each with a flag indicating whether it contributes to the score.
+    subs: Vec<(Box<dyn Scorer>, bool)>,
+
+    /// Whether each sub-scorer has been positioned.
+    positioned: Vec<bool>,
+    doc: Option<u32>,
+}
+
+impl ConjunctionScorer {
+    /// Creates a scorer over the intersection of the given scorers, each paired with whether it contributes to the
+    /// score. At least one scorer is required.
+    pub fn new(mut subs: Vec<(Box<dyn Scorer>, bool)>) -> Self {
+        assert!(!subs.is_empty(), "a conjunction requires at least one scorer");
+        subs.sort_by_key(|(sub, _)| sub.cost());
+        Self {
+            positioned: vec![false; subs.len()],
+            subs,
+            doc: None,
+        }
+    }
+
+    /// Given the lead's candidate `doc`, advances the other sub-scorers until they all agree.
+    fn do_next(&mut self, mut doc: u32) -> BoxResult<u32> {
+        'candidates: while doc != NO_MORE_DOCS {
+            for i in 1..self.subs.len() {
+                // Another sub-scorer may already be on or past `doc` from an earlier candidate.
+                let other = &mut self.subs[i].0;
+                let other_doc = if self.positioned[i] && other.doc_id() >= doc {
+                    other.doc_id()
+                } else {
+                    self.positioned[i] = true;
+                    other.advance(doc)?
+                };
+
+                if other_doc > doc {
+                    doc = self.subs[0].0.advance(other_doc)?;
+                    continue 'candidates;
+                }
+            }
+
+            break;
+        }
+
+        self.doc = Some(doc);
+        Ok(doc)
+    }
+}
+
+impl DocIdSetIterator for ConjunctionScorer {
+    #[inline]
+    fn doc_id(&self) -> u32 {
+        self.doc.unwrap_or(NO_MORE_DOCS)
+    }
+
+    fn next_doc(&mut self) -> BoxResult<u32> {
+        let doc = self.subs[0].0.next_doc()?;
+        self.do_next(doc)
+    }
+
+    fn advance(&mut self, target: u32) -> BoxResult<u32> {
+        let doc = self.subs[0].0.advance(target)?;
+        self.do_next(doc)
+    }
+
+    #[inline]
+    fn cost(&self) -> u64 {
+        self.subs[0].0.cost()
+    }
+}
+
+impl Scorable for ConjunctionScorer {
+    fn score(&mut self) -> BoxResult<f32> {
+        let mut score = 0.0f64;
+        for (sub, scoring) in self.subs.iter_mut() {
+            if *scoring {
+                score += sub.score()? as f64;
+            }
+        }
+
+        Ok(score as f32)
+    }
+}
+
+impl Scorer for ConjunctionScorer {
+    fn max_score(&mut self, up_to: u32) -> BoxResult<f32> {
+        let mut max_score = 0.0f64;
+        for (sub, scoring) in self.subs.iter_mut() {
+            if *scoring {
+                max_score += sub.max_score(up_to)? as f64;
+            }
+        }
+
+        Ok(max_score as f32)
+    }
+}
@@ -0,0 +1,90 @@
+use {
+    crate::{
+        index::LeafReaderContext,
+        search::{ConstantScoreScorer, Explanation, IndexSearcher, Query, ScoreMode, Scorer, Weight},
+        BoxResult,
+    },
+    std::{
+        any::Any,
+        fmt::{Display, Formatter, Result as FmtResult},
+        sync::Arc,
+    },
+};
+
+/// A query that matches the same documents as another query, but gives them all a constant score equal to the boost.
+#[derive(Clone, Debug)]
+pub struct ConstantScoreQuery {
+    query: Arc<dyn Query>,
+}
+
+impl ConstantScoreQuery {
+    /// Wraps the given query.
+    pub fn new(query: Arc<dyn Query>) -> Self {
+        Self {
+            query,
+        }
+    }
+
+    /// Returns the wrapped query.
+    #[inline]
+    pub fn query(&self) -> &Arc<dyn Query> {
+        &self.query
+    }
+}
+
+impl Query for ConstantScoreQuery {
+    fn create_weight(
+        &self,
+        searcher: &IndexSearcher,
+        _score_mode: ScoreMode,
+        boost: f32,
+    ) -> BoxResult<Box<dyn Weight>> {
+        let inner = searcher.create_weight(self.query.as_ref(), ScoreMode::CompleteNoScores, 1.0)?;
+        Ok(Box::new(ConstantScoreWeight {
+            inner,
+            score: boost,
+            description: self.to_string(),
+        }))
+    }
+
+    fn rewrite(&self, searcher: &IndexSearcher) -> BoxResult<Option<Arc<dyn Query>>> {
+        // Nested constant-score wrappers are redundant.
+        if let Some(inner) = (self.query.as_ref() as &dyn Any).downcast_ref::<ConstantScoreQuery>() {
+            return Ok(Some(Arc::new(inner.clone())));
+        }
+
+        Ok(searcher.rewrite(self.query.as_ref())?.map(|rewritten| Arc::new(Self::new(rewritten)) as Arc<dyn Query>))
+    }
+}
+
+impl Display for ConstantScoreQuery {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "ConstantScore({})", self.query)
+    }
+}
+
+#[derive(Debug)]
+struct ConstantScoreWeight {
+    inner: Box<dyn Weight>,
+    score: f32,
+    description: String,
+}
+
+impl Weight for ConstantScoreWeight {
+    fn scorer(&self, context: &LeafReaderContext) -> BoxResult<Option<Box<dyn Scorer>>> {
+        let Some(inner) = self.inner.scorer(context)? else {
+            return Ok(None);
+        };
+
+        Ok(Some(Box::new(ConstantScoreScorer::new(self.score, inner))))
+    }
+
+    fn explain(&self, context: &LeafReaderContext, doc: u32) -> BoxResult<Explanation> {
+        let inner = self.inner.explain(context, doc)?;
+        if inner.is_match() {
+            Ok(Explanation::constant(self.score, self.description.clone()))
+        } else {
+            Ok(Explanation::no_match(format!("{} doesn't match document {doc}", self.description), vec![inner]))
+        }
+    }
+}
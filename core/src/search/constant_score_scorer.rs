@@ -0,0 +1,94 @@
+use crate::{
+    search::{DocIdSetIterator, Scorable, Scorer, TwoPhaseDocIdSetIterator, TwoPhaseIterator},
+    BoxResult,
+};
+
+/// A [Scorer] that gives every document matched by an iterator the same score.
+#[derive(Debug)]
+pub struct ConstantScoreScorer {
+    score: f32,
+    iterator: DocIterator,
+}
+
+#[derive(Debug)]
+enum DocIterator {
+    Plain(Box<dyn DocIdSetIterator>),
+    TwoPhase(TwoPhaseDocIdSetIterator),
+}
+
+impl ConstantScoreScorer {
+    /// Creates a scorer that scores every document of `iterator` with `score`.
+    pub fn new(score: f32, iterator: Box<dyn DocIdSetIterator>) -> Self {
+        Self {
+            score,
+            iterator: DocIterator::Plain(iterator),
+        }
+    }
+
+    /// Creates a scorer that scores every document confirmed by `two_phase` with `score`. The two-phase iterator is
+    /// exposed through [Scorer::two_phase_iterator].
+    pub fn with_two_phase(score: f32, two_phase: Box<dyn TwoPhaseIterator>) -> Self {
+        Self {
+            score,
+            iterator: DocIterator::TwoPhase(TwoPhaseDocIdSetIterator::new(two_phase)),
+        }
+    }
+
+    #[inline]
+    fn iterator(&self) -> &dyn DocIdSetIterator {
+        match &self.iterator {
+            DocIterator::Plain(iterator) => iterator.as_ref(),
+            DocIterator::TwoPhase(iterator) => iterator,
+        }
+    }
+
+    #[inline]
+    fn iterator_mut(&mut self) -> &mut dyn DocIdSetIterator {
+        match &mut self.iterator {
+            DocIterator::Plain(iterator) => iterator.as_mut(),
+            DocIterator::TwoPhase(iterator) => iterator,
+        }
+    }
+}
+
+impl DocIdSetIterator for ConstantScoreScorer {
+    #[inline]
+    fn doc_id(&self) -> u32 {
+        self.iterator().doc_id()
+    }
+
+    #[inline]
+    fn next_doc(&mut self) -> BoxResult<u32> {
+        self.iterator_mut().next_doc()
+    }
+
+    #[inline]
+    fn advance(&mut self, target: u32) -> BoxResult<u32> {
+        self.iterator_mut().advance(target)
+    }
+
+    #[inline]
+    fn cost(&self) -> u64 {
+        self.iterator().cost()
+    }
+}
+
+impl Scorable for ConstantScoreScorer {
+    #[inline]
+    fn score(&mut self) -> BoxResult<f32> {
+        Ok(self.score)
+    }
+}
+
+impl Scorer for ConstantScoreScorer {
+    fn max_score(&mut self, _up_to: u32) -> BoxResult<f32> {
+        Ok(self.score)
+    }
+
+    fn two_phase_iterator(&mut self) -> Option<&mut dyn TwoPhaseIterator> {
+        match &mut self.iterator {
+            DocIterator::Plain(_) => None,
+            DocIterator::TwoPhase(iterator) => Some(iterator.two_phase()),
+        }
+    }
+}
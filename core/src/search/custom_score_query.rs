@@ -0,0 +1,286 @@
+use {
+    crate::{
+        index::LeafReaderContext,
+        search::{
+            DocIdSetIterator, Explanation, IndexSearcher, Query, Scorable, ScoreMode, Scorer, TwoPhaseIterator, Weight,
+        },
+        BoxResult,
+    },
+    std::{
+        fmt::{Debug, Display, Formatter, Result as FmtResult},
+        sync::Arc,
+    },
+};
+
+/// Adjusts the scores of a [CustomScoreQuery] within a single segment.
+pub trait CustomScorer: Debug + Send {
+    /// Returns the score of the given segment-local document, whose score from the wrapped query is `sub_score`.
+    /// Documents are requested in increasing order.
+    fn score(&mut self, doc: u32, sub_score: f32) -> BoxResult<f32>;
+
+    /// Explains the score of the given segment-local document, whose score from the wrapped query is explained by
+    /// `sub_explanation`.
+    fn explain(&mut self, doc: u32, sub_explanation: Explanation) -> BoxResult<Explanation> {
+        let score = self.score(doc, sub_explanation.value())?;
+        Ok(Explanation::matched(score, "custom score, computed from:", vec![sub_explanation]))
+    }
+}
+
+/// Creates the [CustomScorer] of each segment searched by a [CustomScoreQuery]. The segment's reader is available
+/// from the context, so that scorers can read doc values, such as a popularity or a promotion flag.
+pub trait CustomScoreProvider: Debug + Display + Send + Sync {
+    /// Returns the scorer for the given segment.
+    fn scorer(&self, context: &LeafReaderContext) -> BoxResult<Box<dyn CustomScorer>>;
+}
+
+/// A query that matches the same documents as another query, but lets a [CustomScoreProvider] compute their scores
+/// from the wrapped query's scores, as in Lucene's `CustomScoreQuery`. This allows rules such as boosting promoted
+/// documents without writing a new query.
+#[derive(Clone, Debug)]
+pub struct CustomScoreQuery {
+    query: Arc<dyn Query>,
+    provider: Arc<dyn CustomScoreProvider>,
+}
+
+impl CustomScoreQuery {
+    /// Wraps `query`, scoring its matches with the scorers of `provider`.
+    pub fn new(query: Arc<dyn Query>, provider: Arc<dyn CustomScoreProvider>) -> Self {
+        Self {
+            query,
+            provider,
+        }
+    }
+
+    /// Returns the wrapped query.
+    #[inline]
+    pub fn query(&self) -> &Arc<dyn Query> {
+        &self.query
+    }
+
+    /// Returns the provider of scorers.
+    #[inline]
+    pub fn provider(&self) -> &Arc<dyn CustomScoreProvider> {
+        &self.provider
+    }
+}
+
+impl Query for CustomScoreQuery {
+    fn create_weight(&self, searcher: &IndexSearcher, score_mode: ScoreMode, boost: f32) -> BoxResult<Box<dyn Weight>> {
+        let inner_score_mode = if score_mode.needs_scores() {
+            ScoreMode::Complete
+        } else {
+            ScoreMode::CompleteNoScores
+        };
+
+        Ok(Box::new(CustomScoreWeight {
+            inner: searcher.create_weight(self.query.as_ref(), inner_score_mode, 1.0)?,
+            provider: self.provider.clone(),
+            needs_scores: inner_score_mode.needs_scores(),
+            boost,
+        }))
+    }
+
+    fn rewrite(&self, searcher: &IndexSearcher) -> BoxResult<Option<Arc<dyn Query>>> {
+        Ok(searcher
+            .rewrite(self.query.as_ref())?
+            .map(|rewritten| Arc::new(Self::new(rewritten, self.provider.clone())) as Arc<dyn Query>))
+    }
+}
+
+impl Display for CustomScoreQuery {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "CustomScoreQuery({}, scored by {})", self.query, self.provider)
+    }
+}
+
+#[derive(Debug)]
+struct CustomScoreWeight {
+    inner: Box<dyn Weight>,
+    provider: Arc<dyn CustomScoreProvider>,
+    needs_scores: bool,
+    boost: f32,
+}
+
+impl Weight for CustomScoreWeight {
+    fn scorer(&self, context: &LeafReaderContext) -> BoxResult<Option<Box<dyn Scorer>>> {
+        let Some(inner) = self.inner.scorer(context)? else {
+            return Ok(None);
+        };
+
+        // Without scores, the custom scorer would only be read for nothing.
+        if !self.needs_scores {
+            return Ok(Some(inner));
+        }
+
+        Ok(Some(Box::new(CustomScoreScorer {
+            inner,
+            custom: self.provider.scorer(context)?,
+            boost: self.boost,
+        })))
+    }
+
+    fn explain(&self, context: &LeafReaderContext, doc: u32) -> BoxResult<Explanation> {
+        let inner = self.inner.explain(context, doc)?;
+        if !inner.is_match() {
+            return Ok(inner);
+        }
+
+        let custom = self.provider.scorer(context)?.explain(doc, inner)?;
+        if self.boost == 1.0 {
+            return Ok(custom);
+        }
+
+        let value = custom.value() * self.boost;
+        Ok(Explanation::matched(value, "product of:", vec![custom, Explanation::matched(self.boost, "boost", vec![])]))
+    }
+}
+
+#[derive(Debug)]
+struct CustomScoreScorer {
+    inner: Box<dyn Scorer>,
+    custom: Box<dyn CustomScorer>,
+    boost: f32,
+}
+
+impl DocIdSetIterator for CustomScoreScorer {
+    #[inline]
+    fn doc_id(&self) -> u32 {
+        self.inner.doc_id()
+    }
+
+    #[inline]
+    fn next_doc(&mut self) -> BoxResult<u32> {
+        self.inner.next_doc()
+    }
+
+    #[inline]
+    fn advance(&mut self, target: u32) -> BoxResult<u32> {
+        self.inner.advance(target)
+    }
+
+    #[inline]
+    fn cost(&self) -> u64 {
+        self.inner.cost()
+    }
+}
+
+impl Scorable for CustomScoreScorer {
+    fn score(&mut self) -> BoxResult<f32> {
+        let sub_score = self.inner.score()?;
+        Ok(self.custom.score(self.inner.doc_id(), sub_score)? * self.boost)
+    }
+}
+
+impl Scorer for CustomScoreScorer {
+    fn two_phase_iterator(&mut self) -> Option<&mut dyn TwoPhaseIterator> {
+        self.inner.two_phase_iterator()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{
+            document::{Document, Field, Store},
+            index::{LeafReaderContext, NumericDocValues, Term},
+            search::{
+                test_util::searcher, BoostQuery, CustomScoreProvider, CustomScoreQuery, CustomScorer, Explanation,
+                Query, TermQuery,
+            },
+            BoxResult,
+        },
+        pretty_assertions::assert_eq,
+        std::{
+            fmt::{Display, Formatter, Result as FmtResult},
+            sync::Arc,
+        },
+    };
+
+    /// Doubles the score of documents whose `promoted` doc value is set.
+    #[derive(Debug)]
+    struct PromotedProvider;
+
+    impl CustomScoreProvider for PromotedProvider {
+        fn scorer(&self, context: &LeafReaderContext) -> BoxResult<Box<dyn CustomScorer>> {
+            Ok(Box::new(PromotedScorer {
+                promoted: context.reader().numeric_doc_values("promoted")?,
+            }))
+        }
+    }
+
+    impl Display for PromotedProvider {
+        fn fmt(&self, f: &mut Formatter) -> FmtResult {
+            write!(f, "promoted")
+        }
+    }
+
+    #[derive(Debug)]
+    struct PromotedScorer {
+        promoted: Option<Box<dyn NumericDocValues>>,
+    }
+
+    impl PromotedScorer {
+        fn is_promoted(&mut self, doc: u32) -> BoxResult<bool> {
+            match self.promoted.as_mut() {
+                Some(promoted) => Ok(promoted.advance_exact(doc)? && promoted.long_value()? != 0),
+                None => Ok(false),
+            }
+        }
+    }
+
+    impl CustomScorer for PromotedScorer {
+        fn score(&mut self, doc: u32, sub_score: f32) -> BoxResult<f32> {
+            Ok(if self.is_promoted(doc)? {
+                sub_score * 2.0
+            } else {
+                sub_score
+            })
+        }
+
+        fn explain(&mut self, doc: u32, sub_explanation: Explanation) -> BoxResult<Explanation> {
+            if !self.is_promoted(doc)? {
+                return Ok(sub_explanation);
+            }
+            let value = sub_explanation.value() * 2.0;
+            Ok(Explanation::matched(value, "promoted, twice:", vec![sub_explanation]))
+        }
+    }
+
+    #[test]
+    fn test_custom_score() {
+        let mut segments = Vec::new();
+        for promoted in [[Some(1), None], [Some(0), Some(1)]] {
+            let mut documents = Vec::new();
+            for promoted in promoted {
+                let mut doc = Document::new();
+                doc.add(Field::text("body", "rust", Store::No));
+                if let Some(promoted) = promoted {
+                    doc.add(Field::numeric_doc_values("promoted", promoted));
+                }
+                documents.push(doc);
+            }
+            segments.push(documents);
+        }
+        let searcher = searcher(segments);
+
+        let rust: Arc<dyn Query> = Arc::new(TermQuery::new(Term::from_text("body", "rust")));
+        let plain = searcher.search(rust.as_ref(), 10).unwrap().score_docs[0].score;
+        let query = CustomScoreQuery::new(rust, Arc::new(PromotedProvider));
+        assert_eq!(query.to_string(), "CustomScoreQuery(body:rust, scored by promoted)");
+
+        let mut hits: Vec<(u32, f32)> =
+            searcher.search(&query, 10).unwrap().score_docs.iter().map(|sd| (sd.doc, sd.score)).collect();
+        hits.sort_by_key(|&(doc, _)| doc);
+        assert_eq!(hits, vec![(0, plain * 2.0), (1, plain), (2, plain), (3, plain * 2.0)]);
+
+        for (doc, score) in hits {
+            assert_eq!(searcher.explain(&query, doc).unwrap().value(), score);
+        }
+
+        let boosted = BoostQuery::new(Arc::new(query), 3.0).unwrap();
+        for sd in searcher.search(&boosted, 10).unwrap().score_docs {
+            assert!((searcher.explain(&boosted, sd.doc).unwrap().value() - sd.score).abs() < 1e-5);
+        }
+        assert_eq!(searcher.count(&boosted).unwrap(), 4);
+    }
+}
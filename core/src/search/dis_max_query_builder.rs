@@ -0,0 +1,192 @@
+use {
+    crate::{
+        analysis::Analyzer,
+        search::{BooleanQuery, BoostQuery, DisjunctionMaxQuery, Occur, Query, QueryBuilder},
+        BoxResult,
+    },
+    std::sync::Arc,
+};
+
+/// Creates the classic "dismax" query for user text searched across several weighted fields, as Solr's dismax and
+/// edismax parsers and Elasticsearch's `multi_match` query do.
+///
+/// The text is split on whitespace, and each word becomes a [DisjunctionMaxQuery] over the query fields, each
+/// weighted by its boost, so a word scores by the field it matches best. The words are optional clauses of a
+/// [BooleanQuery], of which a minimum number must match (see [DisMaxQueryBuilder::set_min_should_match]).
+///
+/// If phrase fields are added, documents containing the whole text as a phrase in them are boosted, by an optional
+/// clause that is another [DisjunctionMaxQuery] over a phrase query in each phrase field.
+#[derive(Clone, Debug)]
+pub struct DisMaxQueryBuilder {
+    query_builder: QueryBuilder,
+    fields: Vec<(String, f32)>,
+    phrase_fields: Vec<(String, f32)>,
+    tie_breaker_multiplier: f32,
+    min_should_match: u32,
+    phrase_slop: u32,
+}
+
+impl DisMaxQueryBuilder {
+    /// Creates a builder with no fields, analyzing text with `analyzer`.
+    pub fn new(analyzer: Arc<dyn Analyzer>) -> Self {
+        Self {
+            query_builder: QueryBuilder::new(analyzer),
+            fields: Vec::new(),
+            phrase_fields: Vec::new(),
+            tie_breaker_multiplier: 0.0,
+            min_should_match: 0,
+            phrase_slop: 0,
+        }
+    }
+
+    /// Adds a field each word is searched in, whose scores are multiplied by `boost`.
+    pub fn add_field(&mut self, field: &str, boost: f32) -> &mut Self {
+        self.fields.push((field.to_string(), boost));
+        self
+    }
+
+    /// Adds a field the whole text is searched in as a phrase, whose scores are multiplied by `boost`.
+    pub fn add_phrase_field(&mut self, field: &str, boost: f32) -> &mut Self {
+        self.phrase_fields.push((field.to_string(), boost));
+        self
+    }
+
+    /// Sets the tie-breaker of the [DisjunctionMaxQuery]s over the fields. This defaults to 0, scoring each word by
+    /// its best field alone.
+    pub fn set_tie_breaker_multiplier(&mut self, tie_breaker_multiplier: f32) -> &mut Self {
+        self.tie_breaker_multiplier = tie_breaker_multiplier;
+        self
+    }
+
+    /// Sets the number of words a matching document must match. The default of 0 means any one word suffices; a
+    /// minimum over the number of words in the text requires all of them.
+    pub fn set_min_should_match(&mut self, min_should_match: u32) -> &mut Self {
+        self.min_should_match = min_should_match;
+        self
+    }
+
+    /// Sets how many positions the terms of the phrases in the phrase fields may be moved by. This defaults to 0.
+    pub fn set_phrase_slop(&mut self, phrase_slop: u32) -> &mut Self {
+        self.phrase_slop = phrase_slop;
+        self
+    }
+
+    /// Creates the query for `text`, or `None` if no word of it has any terms in the query fields. This fails with
+    /// [crate::LuceneError::InvalidArgument] if the tie-breaker or a boost is invalid.
+    pub fn create_query(&self, text: &str) -> BoxResult<Option<Arc<dyn Query>>> {
+        let mut words = Vec::new();
+        for word in text.split_whitespace() {
+            if let Some(query) =
+                self.dis_max(&self.fields, |field| self.query_builder.create_boolean_query(field, word, Occur::Should))?
+            {
+                words.push(query);
+            }
+        }
+
+        if words.is_empty() {
+            return Ok(None);
+        }
+
+        let min_should_match = self.min_should_match.min(words.len() as u32);
+        let main: Arc<dyn Query> = match words.len() {
+            1 => words.pop().unwrap(),
+            _ => {
+                let mut builder = BooleanQuery::builder();
+                for word in words {
+                    builder.should(word);
+                }
+                Arc::new(builder.set_min_should_match(min_should_match).build())
+            }
+        };
+
+        let phrase = self.dis_max(&self.phrase_fields, |field| {
+            self.query_builder.create_phrase_query(field, text, self.phrase_slop)
+        })?;
+        match phrase {
+            Some(phrase) => Ok(Some(Arc::new(BooleanQuery::builder().must(main).should(phrase).build()))),
+            None => Ok(Some(main)),
+        }
+    }
+
+    /// Creates a [DisjunctionMaxQuery] over the queries `create` returns for each of the fields, boosted by the
+    /// field's boost, or `None` if there are none. A single query isn't wrapped.
+    fn dis_max<F>(&self, fields: &[(String, f32)], create: F) -> BoxResult<Option<Arc<dyn Query>>>
+    where
+        F: Fn(&str) -> Option<Arc<dyn Query>>,
+    {
+        let mut disjuncts = Vec::with_capacity(fields.len());
+        for (field, boost) in fields {
+            if let Some(query) = create(field) {
+                disjuncts.push(if *boost == 1.0 {
+                    query
+                } else {
+                    Arc::new(BoostQuery::new(query, *boost)?)
+                });
+            }
+        }
+
+        match disjuncts.len() {
+            0 => Ok(None),
+            1 => Ok(disjuncts.pop()),
+            _ => Ok(Some(Arc::new(DisjunctionMaxQuery::new(disjuncts, self.tie_breaker_multiplier)?))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{
+            analysis::SimpleAnalyzer,
+            document::{Document, Field, Store},
+            search::{test_util::searcher, DisMaxQueryBuilder},
+        },
+        pretty_assertions::assert_eq,
+        std::sync::Arc,
+    };
+
+    #[test]
+    fn test_dis_max_query_builder() {
+        let docs = [
+            ("rust search", "a library"),
+            ("rust", "search engines in rust"),
+            ("java", "search engines"),
+            ("go", "nothing"),
+        ];
+        let mut documents = Vec::new();
+        for (title, body) in docs {
+            let mut doc = Document::new();
+            doc.add(Field::text("title", title, Store::No));
+            doc.add(Field::text("body", body, Store::No));
+            documents.push(doc);
+        }
+        let searcher = searcher([documents]);
+
+        let mut builder = DisMaxQueryBuilder::new(Arc::new(SimpleAnalyzer));
+        builder.add_field("title", 2.0).add_field("body", 1.0).set_tie_breaker_multiplier(0.1);
+        let query = builder.create_query("Rust  search").unwrap().unwrap();
+        assert_eq!(query.to_string(), "((title:rust)^2 | body:rust)~0.1 ((title:search)^2 | body:search)~0.1");
+        assert_eq!(searcher.count(query.as_ref()).unwrap(), 3);
+        let top = searcher.search(query.as_ref(), 10).unwrap();
+        assert_eq!(top.score_docs[0].doc, 0);
+
+        // Every word must match.
+        builder.set_min_should_match(5);
+        let query = builder.create_query("rust search").unwrap().unwrap();
+        assert_eq!(searcher.count(query.as_ref()).unwrap(), 2);
+
+        // A phrase in the body boosts the document containing it.
+        builder.set_min_should_match(0).add_phrase_field("body", 3.0);
+        let query = builder.create_query("search engines").unwrap().unwrap();
+        assert_eq!(
+            query.to_string(),
+            "+(((title:search)^2 | body:search)~0.1 ((title:engines)^2 | body:engines)~0.1) (body:\"search engines\")^3"
+        );
+        let top = searcher.search(query.as_ref(), 10).unwrap();
+        assert_eq!(top.total_hits.value, 3);
+        assert!(top.score_docs[..2].iter().all(|sd| sd.doc == 1 || sd.doc == 2));
+
+        assert!(builder.create_query("  ").unwrap().is_none());
+        assert!(builder.set_tie_breaker_multiplier(2.0).create_query("rust").is_err());
+    }
+}
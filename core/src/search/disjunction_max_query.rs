@@ -0,0 +1,249 @@
+use {
+    crate::{
+        index::LeafReaderContext,
+        search::{
+            BooleanQuery, DisjunctionMaxScorer, Explanation, IndexSearcher, MatchNoDocsQuery, Query, ScoreMode, Scorer,
+            Weight,
+        },
+        BoxResult, LuceneError,
+    },
+    std::{
+        fmt::{Display, Formatter, Result as FmtResult},
+        sync::Arc,
+    },
+};
+
+/// A query matching the documents that match any of its disjuncts, scored by the best matching disjunct.
+///
+/// Unlike a [BooleanQuery] of optional clauses, which sums the scores of the clauses, this is suited to searching
+/// the same text in several fields: a document matching a term in both its title and its body shouldn't outscore a
+/// document matching both terms in one field. The scores of the other matching disjuncts are multiplied by a
+/// tie-breaker and added, so documents matching in several fields still rank above those matching in one. A
+/// tie-breaker of 0 scores by the best disjunct alone, and a tie-breaker of 1 sums the disjuncts as a
+/// [BooleanQuery] does.
+#[derive(Clone, Debug)]
+pub struct DisjunctionMaxQuery {
+    disjuncts: Vec<Arc<dyn Query>>,
+    tie_breaker_multiplier: f32,
+}
+
+impl DisjunctionMaxQuery {
+    /// Creates a query over the given disjuncts. This fails with [LuceneError::InvalidArgument] if the tie-breaker
+    /// isn't between 0 and 1.
+    pub fn new(disjuncts: Vec<Arc<dyn Query>>, tie_breaker_multiplier: f32) -> BoxResult<Self> {
+        if !(0.0..=1.0).contains(&tie_breaker_multiplier) {
+            return Err(LuceneError::InvalidArgument(format!(
+                "tie-breaker multiplier must be between 0 and 1, got {tie_breaker_multiplier}"
+            ))
+            .into());
+        }
+
+        Ok(Self {
+            disjuncts,
+            tie_breaker_multiplier,
+        })
+    }
+
+    /// Returns the disjuncts.
+    #[inline]
+    pub fn disjuncts(&self) -> &[Arc<dyn Query>] {
+        &self.disjuncts
+    }
+
+    /// Returns the multiplier of the scores of the disjuncts that aren't the best match.
+    #[inline]
+    pub fn tie_breaker_multiplier(&self) -> f32 {
+        self.tie_breaker_multiplier
+    }
+}
+
+impl Query for DisjunctionMaxQuery {
+    fn create_weight(&self, searcher: &IndexSearcher, score_mode: ScoreMode, boost: f32) -> BoxResult<Box<dyn Weight>> {
+        let mut weights = Vec::with_capacity(self.disjuncts.len());
+        for disjunct in self.disjuncts.iter() {
+            weights.push(searcher.create_weight(disjunct.as_ref(), score_mode, boost)?);
+        }
+
+        Ok(Box::new(DisjunctionMaxWeight {
+            weights,
+            tie_breaker_multiplier: self.tie_breaker_multiplier,
+        }))
+    }
+
+    fn rewrite(&self, searcher: &IndexSearcher) -> BoxResult<Option<Arc<dyn Query>>> {
+        match self.disjuncts.as_slice() {
+            [] => return Ok(Some(Arc::new(MatchNoDocsQuery::new("empty DisjunctionMaxQuery")))),
+            [disjunct] => return Ok(Some(disjunct.clone())),
+            _ => (),
+        }
+
+        // With a tie-breaker of 1, every matching disjunct counts fully, as in a disjunction.
+        if self.tie_breaker_multiplier == 1.0 {
+            let mut builder = BooleanQuery::builder();
+            for disjunct in self.disjuncts.iter() {
+                builder.should(disjunct.clone());
+            }
+            return Ok(Some(Arc::new(builder.build())));
+        }
+
+        let mut changed = false;
+        let mut disjuncts = Vec::with_capacity(self.disjuncts.len());
+        for disjunct in self.disjuncts.iter() {
+            match searcher.rewrite(disjunct.as_ref())? {
+                Some(rewritten) => {
+                    changed = true;
+                    disjuncts.push(rewritten);
+                }
+                None => disjuncts.push(disjunct.clone()),
+            }
+        }
+
+        Ok(changed.then(|| {
+            Arc::new(Self {
+                disjuncts,
+                tie_breaker_multiplier: self.tie_breaker_multiplier,
+            }) as Arc<dyn Query>
+        }))
+    }
+}
+
+impl Display for DisjunctionMaxQuery {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "(")?;
+        for (i, disjunct) in self.disjuncts.iter().enumerate() {
+            if i > 0 {
+                write!(f, " | ")?;
+            }
+            write!(f, "{disjunct}")?;
+        }
+        write!(f, ")")?;
+
+        if self.tie_breaker_multiplier != 0.0 {
+            write!(f, "~{}", self.tie_breaker_multiplier)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+struct DisjunctionMaxWeight {
+    weights: Vec<Box<dyn Weight>>,
+    tie_breaker_multiplier: f32,
+}
+
+impl Weight for DisjunctionMaxWeight {
+    fn scorer(&self, context: &LeafReaderContext) -> BoxResult<Option<Box<dyn Scorer>>> {
+        let mut scorers = Vec::with_capacity(self.weights.len());
+        for weight in self.weights.iter() {
+            if let Some(scorer) = weight.scorer(context)? {
+                scorers.push(scorer);
+            }
+        }
+
+        match scorers.len() {
+            0 => Ok(None),
+            1 => Ok(scorers.pop()),
+            _ => Ok(Some(Box::new(DisjunctionMaxScorer::new(scorers, self.tie_breaker_multiplier)))),
+        }
+    }
+
+    fn explain(&self, context: &LeafReaderContext, doc: u32) -> BoxResult<Explanation> {
+        let mut details = Vec::new();
+        let mut max = 0.0f32;
+        let mut sum = 0.0f64;
+        for weight in self.weights.iter() {
+            let explanation = weight.explain(context, doc)?;
+            if explanation.is_match() {
+                max = max.max(explanation.value());
+                sum += explanation.value() as f64;
+                details.push(explanation);
+            }
+        }
+
+        if details.is_empty() {
+            return Ok(Explanation::no_match("no matching clause", vec![]));
+        }
+
+        let score = (max as f64 + (sum - max as f64) * self.tie_breaker_multiplier as f64) as f32;
+        let description = if self.tie_breaker_multiplier == 0.0 {
+            "max of:".to_string()
+        } else {
+            format!("max plus {} times others of:", self.tie_breaker_multiplier)
+        };
+        Ok(Explanation::matched(score, description, details))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{
+            document::{Document, Field, Store},
+            index::Term,
+            search::{test_util, BooleanQuery, DisjunctionMaxQuery, IndexSearcher, MatchNoDocsQuery, Query, TermQuery},
+        },
+        pretty_assertions::assert_eq,
+        std::{any::Any, sync::Arc},
+    };
+
+    fn searcher(docs: &[(&str, &str)]) -> IndexSearcher {
+        test_util::searcher([docs.iter().map(|(title, body)| {
+            Document::from_iter([Field::text("title", *title, Store::No), Field::text("body", *body, Store::No)])
+        })])
+    }
+
+    fn term(field: &str, text: &str) -> Arc<dyn Query> {
+        Arc::new(TermQuery::new(Term::from_text(field, text)))
+    }
+
+    fn score(searcher: &IndexSearcher, query: &dyn Query, doc: u32) -> f32 {
+        searcher.search(query, 10).unwrap().score_docs.iter().find(|sd| sd.doc == doc).map_or(0.0, |sd| sd.score)
+    }
+
+    #[test]
+    fn test_tie_breaker() {
+        let searcher = searcher(&[("rust", "rust search"), ("search", "rust"), ("java", "java search"), ("go", "go")]);
+        let title = term("title", "rust");
+        let body = term("body", "rust");
+
+        let query = DisjunctionMaxQuery::new(vec![title.clone(), body.clone()], 0.0).unwrap();
+        assert_eq!(query.to_string(), "(title:rust | body:rust)");
+        assert_eq!(searcher.count(&query).unwrap(), 2);
+        let (title_score, body_score) = (score(&searcher, title.as_ref(), 0), score(&searcher, body.as_ref(), 0));
+        assert_eq!(score(&searcher, &query, 0), title_score.max(body_score));
+        assert_eq!(score(&searcher, &query, 1), score(&searcher, body.as_ref(), 1));
+
+        let query = DisjunctionMaxQuery::new(vec![title.clone(), body.clone()], 0.25).unwrap();
+        assert_eq!(query.to_string(), "(title:rust | body:rust)~0.25");
+        let expected = title_score.max(body_score) + title_score.min(body_score) * 0.25;
+        assert!((score(&searcher, &query, 0) - expected).abs() < 1e-6);
+
+        let explanation = searcher.explain(&query, 0).unwrap();
+        assert!(explanation.is_match());
+        assert_eq!(explanation.description(), "max plus 0.25 times others of:");
+        assert_eq!(explanation.details().len(), 2);
+        assert!((explanation.value() - expected).abs() < 1e-6);
+        assert!(!searcher.explain(&query, 2).unwrap().is_match());
+
+        assert!(DisjunctionMaxQuery::new(vec![title, body], 1.5).is_err());
+    }
+
+    #[test]
+    fn test_rewrite() {
+        let searcher = searcher(&[("rust", "search")]);
+
+        let empty = searcher.rewrite(&DisjunctionMaxQuery::new(vec![], 0.0).unwrap()).unwrap().unwrap();
+        assert!((empty.as_ref() as &dyn Any).is::<MatchNoDocsQuery>());
+
+        let single = DisjunctionMaxQuery::new(vec![term("title", "rust")], 0.1).unwrap();
+        assert_eq!(searcher.rewrite(&single).unwrap().unwrap().to_string(), "title:rust");
+
+        let sum = DisjunctionMaxQuery::new(vec![term("title", "rust"), term("body", "rust")], 1.0).unwrap();
+        let rewritten = searcher.rewrite(&sum).unwrap().unwrap();
+        assert!((rewritten.as_ref() as &dyn Any).is::<BooleanQuery>());
+        assert_eq!(rewritten.to_string(), "title:rust body:rust");
+
+        let nested = DisjunctionMaxQuery::new(vec![Arc::new(single), term("body", "rust")], 0.1).unwrap();
+        assert_eq!(searcher.rewrite(&nested).unwrap().unwrap().to_string(), "(title:rust | body:rust)~0.1");
+    }
+}
@@ -0,0 +1,111 @@
+use crate::{
+    search::{DocIdSetIterator, Scorable, Scorer, NO_MORE_DOCS},
+    BoxResult,
+};
+
+/// A [Scorer] over the union of its sub-scorers, scoring each document with the highest score of the sub-scorers that
+/// match it, plus the scores of the others multiplied by a tie-breaker.
+#[derive(Debug)]
+pub struct DisjunctionMaxScorer {
+    subs: Vec<Box<dyn Scorer>>,
+
+    /// The document each sub-scorer is positioned on; `None` until it has been positioned.
+    sub_docs: Vec<Option<u32>>,
+    tie_breaker_multiplier: f32,
+    doc: Option<u32>,
+    cost: u64,
+}
+
+impl DisjunctionMaxScorer {
+    /// Creates a scorer over the union of the given scorers, which adds the scores of the sub-scorers that aren't the
+    /// highest multiplied by `tie_breaker_multiplier`.
+    pub fn new(subs: Vec<Box<dyn Scorer>>, tie_breaker_multiplier: f32) -> Self {
+        let cost = subs.iter().map(|s| s.cost()).sum();
+        Self {
+            sub_docs: vec![None; subs.len()],
+            subs,
+            tie_breaker_multiplier,
+            doc: None,
+            cost,
+        }
+    }
+
+    /// Moves every sub-scorer that is behind `target` to its first document at or after it, then positions this scorer
+    /// on the smallest of their documents.
+    fn advance_subs(&mut self, target: u32) -> BoxResult<u32> {
+        let mut doc = NO_MORE_DOCS;
+        for (sub, sub_doc) in self.subs.iter_mut().zip(self.sub_docs.iter_mut()) {
+            let d = match *sub_doc {
+                Some(d) if d >= target => d,
+                None if target == 0 => sub.next_doc()?,
+                _ => sub.advance(target)?,
+            };
+            *sub_doc = Some(d);
+            doc = doc.min(d);
+        }
+
+        self.doc = Some(doc);
+        Ok(doc)
+    }
+
+    /// Combines the highest of a set of scores with their sum.
+    #[inline]
+    fn combine(&self, max: f32, sum: f64) -> f32 {
+        (max as f64 + (sum - max as f64) * self.tie_breaker_multiplier as f64) as f32
+    }
+}
+
+impl DocIdSetIterator for DisjunctionMaxScorer {
+    #[inline]
+    fn doc_id(&self) -> u32 {
+        self.doc.unwrap_or(NO_MORE_DOCS)
+    }
+
+    fn next_doc(&mut self) -> BoxResult<u32> {
+        match self.doc {
+            None => self.advance_subs(0),
+            Some(NO_MORE_DOCS) => Ok(NO_MORE_DOCS),
+            Some(doc) => self.advance_subs(doc + 1),
+        }
+    }
+
+    fn advance(&mut self, target: u32) -> BoxResult<u32> {
+        self.advance_subs(target)
+    }
+
+    #[inline]
+    fn cost(&self) -> u64 {
+        self.cost
+    }
+}
+
+impl Scorable for DisjunctionMaxScorer {
+    fn score(&mut self) -> BoxResult<f32> {
+        let doc = self.doc_id();
+        let mut max = 0.0f32;
+        let mut sum = 0.0f64;
+        for (sub, sub_doc) in self.subs.iter_mut().zip(self.sub_docs.iter()) {
+            if *sub_doc == Some(doc) {
+                let score = sub.score()?;
+                max = max.max(score);
+                sum += score as f64;
+            }
+        }
+
+        Ok(self.combine(max, sum))
+    }
+}
+
+impl Scorer for DisjunctionMaxScorer {
+    fn max_score(&mut self, up_to: u32) -> BoxResult<f32> {
+        let mut max = 0.0f32;
+        let mut sum = 0.0f64;
+        for sub in self.subs.iter_mut() {
+            let max_score = sub.max_score(up_to)?;
+            max = max.max(max_score);
+            sum += max_score as f64;
+        }
+
+        Ok(self.combine(max, sum))
+    }
+}
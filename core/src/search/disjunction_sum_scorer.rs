@@ -0,0 +1,96 @@
+use crate::{
+    search::{DocIdSetIterator, Scorable, Scorer, NO_MORE_DOCS},
+    BoxResult,
+};
+
+/// A [Scorer] over the union of its sub-scorers, scoring each document with the sum of the scores of the sub-scorers
+/// that match it.
+#[derive(Debug)]
+pub struct DisjunctionSumScorer {
+    subs: Vec<Box<dyn Scorer>>,
+
+    /// The document each sub-scorer is positioned on; `None` until it has been positioned.
+    sub_docs: Vec<Option<u32>>,
+    doc: Option<u32>,
+    cost: u64,
+}
+
+impl DisjunctionSumScorer {
+    /// Creates a scorer over the union of the given scorers.
+    pub fn new(subs: Vec<Box<dyn Scorer>>) -> Self {
+        let cost = subs.iter().map(|s| s.cost()).sum();
+        Self {
+            sub_docs: vec![None; subs.len()],
+            subs,
+            doc: None,
+            cost,
+        }
+    }
+
+    /// Moves every sub-scorer that is behind `target` to its first document at or after it, then positions this scorer
+    /// on the smallest of their documents.
+    fn advance_subs(&mut self, target: u32) -> BoxResult<u32> {
+        let mut doc = NO_MORE_DOCS;
+        for (sub, sub_doc) in self.subs.iter_mut().zip(self.sub_docs.iter_mut()) {
+            let d = match *sub_doc {
+                Some(d) if d >= target => d,
+                None if target == 0 => sub.next_doc()?,
+                _ => sub.advance(target)?,
+            };
+            *sub_doc = Some(d);
+            doc = doc.min(d);
+        }
+
+        self.doc = Some(doc);
+        Ok(doc)
+    }
+}
+
+impl DocIdSetIterator for DisjunctionSumScorer {
+    #[inline]
+    fn doc_id(&self) -> u32 {
+        self.doc.unwrap_or(NO_MORE_DOCS)
+    }
+
+    fn next_doc(&mut self) -> BoxResult<u32> {
+        match self.doc {
+            None => self.advance_subs(0),
+            Some(NO_MORE_DOCS) => Ok(NO_MORE_DOCS),
+            Some(doc) => self.advance_subs(doc + 1),
+        }
+    }
+
+    fn advance(&mut self, target: u32) -> BoxResult<u32> {
+        self.advance_subs(target)
+    }
+
+    #[inline]
+    fn cost(&self) -> u64 {
+        self.cost
+    }
+}
+
+impl Scorable for DisjunctionSumScorer {
+    fn score(&mut self) -> BoxResult<f32> {
+        let doc = self.doc_id();
+        let mut score = 0.0f64;
+        for (sub, sub_doc) in self.subs.iter_mut().zip(self.sub_docs.iter()) {
+            if *sub_doc == Some(doc) {
+                score += sub.score()? as f64;
+            }
+        }
+
+        Ok(score as f32)
+    }
+}
+
+impl Scorer for DisjunctionSumScorer {
+    fn max_score(&mut self, up_to: u32) -> BoxResult<f32> {
+        let mut max_score = 0.0f64;
+        for sub in self.subs.iter_mut() {
+            max_score += sub.max_score(up_to)? as f64;
+        }
+
+        Ok(max_score as f32)
+    }
+}
@@ -0,0 +1,214 @@
+use {
+    crate::{
+        search::{DocIdSetIterator, IntArrayDocIdSetIterator, RoaringDocIdSet, NO_MORE_DOCS},
+        util::{Accountable, BitSet, FixedBitSet},
+        BoxResult,
+    },
+    std::{fmt::Debug, sync::Arc},
+};
+
+/// A set of document ids that can be iterated over any number of times, such as a cached filter.
+pub trait DocIdSet: Accountable + Debug + Send + Sync {
+    /// Returns a new, unpositioned iterator over the documents of the set.
+    fn iterator(&self) -> Box<dyn DocIdSetIterator>;
+
+    /// Returns the set as a [BitSet] for random access, if it is backed by one.
+    fn bits(&self) -> Option<Arc<dyn BitSet>> {
+        None
+    }
+}
+
+/// A [DocIdSet] backed by a sorted array of document ids. This is compact for sparse sets.
+#[derive(Clone, Debug)]
+pub struct IntArrayDocIdSet {
+    docs: Arc<[u32]>,
+}
+
+impl IntArrayDocIdSet {
+    /// Creates a set of the given document ids, which must be sorted and distinct.
+    pub fn new(docs: Arc<[u32]>) -> Self {
+        debug_assert!(docs.windows(2).all(|w| w[0] < w[1]), "document ids must be sorted and distinct");
+        Self {
+            docs,
+        }
+    }
+
+    /// Creates an empty set.
+    pub fn empty() -> Self {
+        Self::new(Arc::new([]))
+    }
+
+    /// Returns the number of documents in the set.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.docs.len()
+    }
+
+    /// Indicates whether the set has no documents.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.docs.is_empty()
+    }
+}
+
+impl DocIdSet for IntArrayDocIdSet {
+    fn iterator(&self) -> Box<dyn DocIdSetIterator> {
+        Box::new(IntArrayDocIdSetIterator::new(self.docs.clone()))
+    }
+}
+
+impl Accountable for IntArrayDocIdSet {
+    fn ram_bytes_used(&self) -> usize {
+        size_of::<Self>() + self.docs.len() * size_of::<u32>()
+    }
+}
+
+/// A [DocIdSet] backed by a [BitSet]. This gives random access, and is compact for dense sets when backed by a
+/// [FixedBitSet].
+#[derive(Clone, Debug)]
+pub struct BitDocIdSet {
+    bits: Arc<dyn BitSet>,
+    cost: u64,
+}
+
+impl BitDocIdSet {
+    /// Creates a set of the bits set in `bits`. `cost` estimates their number, as for [DocIdSetIterator::cost].
+    pub fn new(bits: Arc<dyn BitSet>, cost: u64) -> Self {
+        Self {
+            bits,
+            cost,
+        }
+    }
+
+    /// Creates a set of the bits set in `bits`, using their exact number as the cost.
+    pub fn with_cardinality(bits: Arc<dyn BitSet>) -> Self {
+        let cost = bits.cardinality() as u64;
+        Self::new(bits, cost)
+    }
+}
+
+impl DocIdSet for BitDocIdSet {
+    fn iterator(&self) -> Box<dyn DocIdSetIterator> {
+        Box::new(BitSetIterator::new(self.bits.clone(), self.cost))
+    }
+
+    fn bits(&self) -> Option<Arc<dyn BitSet>> {
+        Some(self.bits.clone())
+    }
+}
+
+impl Accountable for BitDocIdSet {
+    fn ram_bytes_used(&self) -> usize {
+        size_of::<Self>() + self.bits.ram_bytes_used()
+    }
+}
+
+/// A [DocIdSetIterator] over the set bits of a [BitSet].
+#[derive(Debug)]
+pub struct BitSetIterator {
+    bits: Arc<dyn BitSet>,
+    cost: u64,
+    doc: Option<u32>,
+}
+
+impl BitSetIterator {
+    /// Creates an iterator over the set bits of `bits`, with the given cost.
+    pub fn new(bits: Arc<dyn BitSet>, cost: u64) -> Self {
+        Self {
+            bits,
+            cost,
+            doc: None,
+        }
+    }
+}
+
+impl DocIdSetIterator for BitSetIterator {
+    #[inline]
+    fn doc_id(&self) -> u32 {
+        self.doc.unwrap_or(NO_MORE_DOCS)
+    }
+
+    fn next_doc(&mut self) -> BoxResult<u32> {
+        match self.doc {
+            None => self.advance(0),
+            Some(doc) => self.advance(doc.saturating_add(1)),
+        }
+    }
+
+    fn advance(&mut self, target: u32) -> BoxResult<u32> {
+        let doc = self.bits.next_set_bit(target);
+        self.doc = Some(doc);
+        Ok(doc)
+    }
+
+    #[inline]
+    fn cost(&self) -> u64 {
+        self.cost
+    }
+
+    #[inline]
+    fn bit_set(&self) -> Option<&dyn BitSet> {
+        Some(self.bits.as_ref())
+    }
+}
+
+/// Collects the documents of an iterator into a [DocIdSet] suited to caching, as Lucene's query cache does: a
+/// [BitDocIdSet] when at least 1% of the documents match, and a [RoaringDocIdSet] otherwise.
+pub fn cacheable_doc_id_set(iterator: &mut dyn DocIdSetIterator, max_doc: u32) -> BoxResult<Arc<dyn DocIdSet>> {
+    if iterator.cost().saturating_mul(100) >= max_doc as u64 {
+        let mut bits = FixedBitSet::new(max_doc);
+        bits.or(iterator)?;
+        Ok(Arc::new(BitDocIdSet::with_cardinality(Arc::new(bits))))
+    } else {
+        Ok(Arc::new(RoaringDocIdSet::from_iterator(iterator, max_doc)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{
+            search::{
+                cacheable_doc_id_set, BitDocIdSet, DocIdSet, IntArrayDocIdSet, RangeDocIdSetIterator, NO_MORE_DOCS,
+            },
+            util::{BitSet, FixedBitSet},
+        },
+        pretty_assertions::assert_eq,
+        std::sync::Arc,
+    };
+
+    fn collect(set: &dyn DocIdSet) -> Vec<u32> {
+        let mut iterator = set.iterator();
+        let mut docs = Vec::new();
+        while iterator.next_doc().unwrap() != NO_MORE_DOCS {
+            docs.push(iterator.doc_id());
+        }
+        docs
+    }
+
+    #[test]
+    fn test_doc_id_sets() {
+        let array = IntArrayDocIdSet::new(vec![2, 9, 40].into());
+        assert_eq!(collect(&array), vec![2, 9, 40]);
+        assert!(array.bits().is_none());
+        assert!(collect(&IntArrayDocIdSet::empty()).is_empty());
+
+        let mut bits = FixedBitSet::new(100);
+        bits.set_range(10, 13);
+        bits.set(99);
+        let set = BitDocIdSet::with_cardinality(Arc::new(bits));
+        assert_eq!(collect(&set), vec![10, 11, 12, 99]);
+        assert!(set.bits().unwrap().get(11));
+        let mut iterator = set.iterator();
+        assert_eq!(iterator.cost(), 4);
+        assert_eq!(iterator.advance(13).unwrap(), 99);
+        assert_eq!(iterator.next_doc().unwrap(), NO_MORE_DOCS);
+
+        let dense = cacheable_doc_id_set(&mut RangeDocIdSetIterator::new(0, 500), 1000).unwrap();
+        assert!(dense.bits().is_some());
+        assert_eq!(collect(dense.as_ref()).len(), 500);
+        let sparse = cacheable_doc_id_set(&mut RangeDocIdSetIterator::new(7, 9), 1000).unwrap();
+        assert!(sparse.bits().is_none());
+        assert_eq!(collect(sparse.as_ref()), vec![7, 8]);
+    }
+}
@@ -0,0 +1,140 @@
+use {
+    crate::{
+        search::{BitDocIdSet, DocIdSet, DocIdSetIterator, IntArrayDocIdSet, NO_MORE_DOCS},
+        util::{BitSet, FixedBitSet},
+        BoxResult,
+    },
+    std::sync::Arc,
+};
+
+/// Collects document ids, in any order and possibly with duplicates, into a [DocIdSet], choosing its representation
+/// from the number of documents added.
+///
+/// As in Lucene, documents are first buffered in an array. Once more than 1/128th of the segment's documents have been
+/// added, the buffer is replaced by a [FixedBitSet], which is then smaller than the array and faster to fill. The
+/// built set is an [IntArrayDocIdSet] or a [BitDocIdSet] accordingly.
+#[derive(Debug)]
+pub struct DocIdSetBuilder {
+    max_doc: u32,
+    threshold: usize,
+    buffer: Vec<u32>,
+    bits: Option<FixedBitSet>,
+}
+
+impl DocIdSetBuilder {
+    /// Creates a builder for a segment of `max_doc` documents.
+    pub fn new(max_doc: u32) -> Self {
+        Self {
+            max_doc,
+            threshold: (max_doc >> 7) as usize,
+            buffer: Vec::new(),
+            bits: None,
+        }
+    }
+
+    /// Indicates whether the documents have moved to a bit set.
+    #[inline]
+    pub fn is_dense(&self) -> bool {
+        self.bits.is_some()
+    }
+
+    /// Adds a document, which must be less than `max_doc`.
+    pub fn add_doc(&mut self, doc: u32) {
+        debug_assert!(doc < self.max_doc, "document {doc} is out of bounds for {} documents", self.max_doc);
+        match &mut self.bits {
+            Some(bits) => bits.set(doc),
+            None => {
+                self.buffer.push(doc);
+                if self.buffer.len() > self.threshold {
+                    self.upgrade_to_bit_set();
+                }
+            }
+        }
+    }
+
+    /// Adds the documents of an unpositioned iterator. If its cost shows that the documents won't fit in the buffer,
+    /// they are written to a bit set straight away.
+    pub fn add(&mut self, iterator: &mut dyn DocIdSetIterator) -> BoxResult<()> {
+        if self.bits.is_none() && self.buffer.len() as u64 + iterator.cost() > self.threshold as u64 {
+            self.upgrade_to_bit_set();
+        }
+
+        match &mut self.bits {
+            Some(bits) => bits.or(iterator),
+            None => loop {
+                let doc = iterator.next_doc()?;
+                if doc == NO_MORE_DOCS {
+                    return Ok(());
+                }
+                self.add_doc(doc);
+            },
+        }
+    }
+
+    fn upgrade_to_bit_set(&mut self) {
+        let mut bits = FixedBitSet::new(self.max_doc);
+        for &doc in &self.buffer {
+            bits.set(doc);
+        }
+        self.buffer = Vec::new();
+        self.bits = Some(bits);
+    }
+
+    /// Builds the set of the documents added.
+    pub fn build(self) -> Arc<dyn DocIdSet> {
+        match self.bits {
+            Some(bits) => Arc::new(BitDocIdSet::with_cardinality(Arc::new(bits))),
+            None => {
+                let mut docs = self.buffer;
+                docs.sort_unstable();
+                docs.dedup();
+                Arc::new(IntArrayDocIdSet::new(docs.into()))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::search::{
+            DocIdSetBuilder, DocIdSetIterator, IntArrayDocIdSetIterator, RangeDocIdSetIterator, NO_MORE_DOCS,
+        },
+        pretty_assertions::assert_eq,
+    };
+
+    fn collect(iterator: &mut dyn DocIdSetIterator) -> Vec<u32> {
+        let mut docs = Vec::new();
+        while iterator.next_doc().unwrap() != NO_MORE_DOCS {
+            docs.push(iterator.doc_id());
+        }
+        docs
+    }
+
+    #[test]
+    fn test_doc_id_set_builder() {
+        let mut sparse = DocIdSetBuilder::new(10_000);
+        sparse.add_doc(70);
+        sparse.add(&mut IntArrayDocIdSetIterator::from_unsorted(vec![5, 70, 3])).unwrap();
+        assert!(!sparse.is_dense());
+        let set = sparse.build();
+        assert!(set.bits().is_none());
+        assert_eq!(collect(set.iterator().as_mut()), vec![3, 5, 70]);
+
+        // Adding documents one at a time crosses the threshold of max_doc / 128.
+        let mut dense = DocIdSetBuilder::new(10_000);
+        for doc in (0..10_000).rev().step_by(50) {
+            dense.add_doc(doc);
+        }
+        assert!(dense.is_dense());
+        let set = dense.build();
+        assert!(set.bits().is_some());
+        assert_eq!(collect(set.iterator().as_mut()).len(), 200);
+
+        // An iterator whose cost is above the threshold goes straight to a bit set.
+        let mut dense = DocIdSetBuilder::new(10_000);
+        dense.add(&mut RangeDocIdSetIterator::new(100, 300)).unwrap();
+        assert!(dense.is_dense());
+        assert_eq!(collect(dense.build().iterator().as_mut()), (100..300).collect::<Vec<_>>());
+    }
+}
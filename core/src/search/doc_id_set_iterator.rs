@@ -0,0 +1,172 @@
+use {
+    crate::{util::BitSet, BoxResult},
+    std::{fmt::Debug, sync::Arc},
+};
+
+/// The sentinel returned by [DocIdSetIterator::doc_id] once the iterator is exhausted. This is larger than any valid
+/// document id.
+pub const NO_MORE_DOCS: u32 = i32::MAX as u32;
+
+/// Iterates over a set of document ids in increasing order.
+///
+/// Iterators are unpositioned when created; [DocIdSetIterator::doc_id] must not be relied upon until
+/// [DocIdSetIterator::next_doc] or [DocIdSetIterator::advance] has been called.
+pub trait DocIdSetIterator: Debug + Send {
+    /// Returns the current document id, or [NO_MORE_DOCS] if the iterator is exhausted.
+    fn doc_id(&self) -> u32;
+
+    /// Advances to the next document, returning its id, or [NO_MORE_DOCS] if there are no more documents.
+    fn next_doc(&mut self) -> BoxResult<u32>;
+
+    /// Advances to the first document whose id is at least `target`, returning its id, or [NO_MORE_DOCS] if there is
+    /// no such document. `target` must be greater than the current document id.
+    fn advance(&mut self, target: u32) -> BoxResult<u32>;
+
+    /// Returns an estimate of the cost of iterating over all documents; usually an upper bound on the number of
+    /// documents.
+    fn cost(&self) -> u64;
+
+    /// Returns the [BitSet] this iterator walks over, if it is backed by one, so that bulk operations such as
+    /// [BitSet::or] can read it directly.
+    fn bit_set(&self) -> Option<&dyn BitSet> {
+        None
+    }
+}
+
+/// A [DocIdSetIterator] over a contiguous range of document ids.
+#[derive(Debug)]
+pub struct RangeDocIdSetIterator {
+    min: u32,
+    max: u32,
+    doc: Option<u32>,
+}
+
+impl RangeDocIdSetIterator {
+    /// Creates an iterator over the document ids in `min..max`.
+    pub fn new(min: u32, max: u32) -> Self {
+        Self {
+            min,
+            max: max.max(min),
+            doc: None,
+        }
+    }
+
+    /// Creates an iterator over every document id in `0..max_doc`.
+    pub fn all(max_doc: u32) -> Self {
+        Self::new(0, max_doc)
+    }
+
+    /// Creates an iterator over no documents.
+    pub fn empty() -> Self {
+        Self::new(0, 0)
+    }
+}
+
+impl DocIdSetIterator for RangeDocIdSetIterator {
+    #[inline]
+    fn doc_id(&self) -> u32 {
+        self.doc.unwrap_or(NO_MORE_DOCS)
+    }
+
+    fn next_doc(&mut self) -> BoxResult<u32> {
+        match self.doc {
+            None => self.advance(self.min),
+            Some(doc) => self.advance(doc.saturating_add(1)),
+        }
+    }
+
+    fn advance(&mut self, target: u32) -> BoxResult<u32> {
+        let target = target.max(self.min);
+        let doc = if target >= self.max {
+            NO_MORE_DOCS
+        } else {
+            target
+        };
+        self.doc = Some(doc);
+        Ok(doc)
+    }
+
+    #[inline]
+    fn cost(&self) -> u64 {
+        (self.max - self.min) as u64
+    }
+}
+
+/// A [DocIdSetIterator] over a sorted array of distinct document ids.
+#[derive(Debug)]
+pub struct IntArrayDocIdSetIterator {
+    docs: Arc<[u32]>,
+    index: Option<usize>,
+}
+
+impl IntArrayDocIdSetIterator {
+    /// Creates an iterator over the given document ids, which must be sorted and distinct.
+    pub fn new(docs: Arc<[u32]>) -> Self {
+        debug_assert!(docs.windows(2).all(|w| w[0] < w[1]), "document ids must be sorted and distinct");
+        Self {
+            docs,
+            index: None,
+        }
+    }
+
+    /// Creates an iterator over the given document ids, which may be in any order and contain duplicates.
+    pub fn from_unsorted(mut docs: Vec<u32>) -> Self {
+        docs.sort_unstable();
+        docs.dedup();
+        Self::new(docs.into())
+    }
+}
+
+impl DocIdSetIterator for IntArrayDocIdSetIterator {
+    #[inline]
+    fn doc_id(&self) -> u32 {
+        match self.index {
+            Some(index) => self.docs.get(index).copied().unwrap_or(NO_MORE_DOCS),
+            None => NO_MORE_DOCS,
+        }
+    }
+
+    fn next_doc(&mut self) -> BoxResult<u32> {
+        let index = self.index.map_or(0, |i| (i + 1).min(self.docs.len()));
+        self.index = Some(index);
+        Ok(self.doc_id())
+    }
+
+    fn advance(&mut self, target: u32) -> BoxResult<u32> {
+        let start = self.index.map_or(0, |i| (i + 1).min(self.docs.len()));
+        let index = start + self.docs[start..].partition_point(|&d| d < target);
+        self.index = Some(index);
+        Ok(self.doc_id())
+    }
+
+    #[inline]
+    fn cost(&self) -> u64 {
+        self.docs.len() as u64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::search::{DocIdSetIterator, IntArrayDocIdSetIterator, RangeDocIdSetIterator, NO_MORE_DOCS},
+        pretty_assertions::assert_eq,
+    };
+
+    #[test]
+    fn test_iterators() {
+        let mut range = RangeDocIdSetIterator::new(2, 5);
+        assert_eq!(range.cost(), 3);
+        assert_eq!(range.next_doc().unwrap(), 2);
+        assert_eq!(range.advance(4).unwrap(), 4);
+        assert_eq!(range.next_doc().unwrap(), NO_MORE_DOCS);
+        assert_eq!(RangeDocIdSetIterator::empty().next_doc().unwrap(), NO_MORE_DOCS);
+
+        let mut array = IntArrayDocIdSetIterator::from_unsorted(vec![9, 3, 3, 7, 1]);
+        assert_eq!(array.cost(), 4);
+        assert_eq!(array.next_doc().unwrap(), 1);
+        assert_eq!(array.advance(4).unwrap(), 7);
+        assert_eq!(array.advance(8).unwrap(), 9);
+        assert_eq!(array.next_doc().unwrap(), NO_MORE_DOCS);
+        assert_eq!(array.doc_id(), NO_MORE_DOCS);
+    }
+}
@@ -0,0 +1,236 @@
+use {
+    crate::{
+        index::{sub_index, DocValuesType, IndexReader},
+        BoxResult, LuceneError,
+    },
+    std::sync::Arc,
+};
+
+/// A doc value read for a hit by a [DocValuesFetcher].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum DocValue {
+    /// A numeric doc value.
+    Numeric(i64),
+
+    /// A binary doc value.
+    Binary(Vec<u8>),
+}
+
+impl DocValue {
+    /// Returns the value if it's numeric.
+    #[inline]
+    pub fn as_numeric(&self) -> Option<i64> {
+        match self {
+            Self::Numeric(value) => Some(*value),
+            Self::Binary(_) => None,
+        }
+    }
+
+    /// Returns the value if it's binary.
+    #[inline]
+    pub fn as_binary(&self) -> Option<&[u8]> {
+        match self {
+            Self::Numeric(_) => None,
+            Self::Binary(value) => Some(value),
+        }
+    }
+
+    /// Returns the value if it's binary and valid UTF-8, as for keywords.
+    #[inline]
+    pub fn as_str(&self) -> Option<&str> {
+        self.as_binary().and_then(|value| std::str::from_utf8(value).ok())
+    }
+}
+
+/// The doc values of one hit, as read by [DocValuesFetcher::fetch].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct HitDocValues {
+    doc: u32,
+    fields: Arc<[String]>,
+    values: Vec<Option<DocValue>>,
+}
+
+impl HitDocValues {
+    /// Returns the global document id of the hit.
+    #[inline]
+    pub fn doc(&self) -> u32 {
+        self.doc
+    }
+
+    /// Returns the value of `field`, or `None` if the hit has none or the field wasn't fetched.
+    pub fn get(&self, field: &str) -> Option<&DocValue> {
+        let index = self.fields.iter().position(|name| name == field)?;
+        self.values[index].as_ref()
+    }
+
+    /// Returns the numeric value of `field`, or `None` if the hit has no numeric value for it.
+    #[inline]
+    pub fn numeric(&self, field: &str) -> Option<i64> {
+        self.get(field).and_then(DocValue::as_numeric)
+    }
+
+    /// Returns the binary value of `field`, or `None` if the hit has no binary value for it.
+    #[inline]
+    pub fn binary(&self, field: &str) -> Option<&[u8]> {
+        self.get(field).and_then(DocValue::as_binary)
+    }
+
+    /// Returns the binary value of `field` as a string, or `None` if the hit has no binary value for it or the value
+    /// isn't UTF-8.
+    #[inline]
+    pub fn string(&self, field: &str) -> Option<&str> {
+        self.get(field).and_then(DocValue::as_str)
+    }
+
+    /// Returns the fetched values, in the order of [DocValuesFetcher::fields].
+    #[inline]
+    pub fn values(&self) -> &[Option<DocValue>] {
+        &self.values
+    }
+}
+
+/// Reads the doc values of a list of hits, such as the documents of [crate::search::TopDocs], so that fields
+/// displayed with results can be kept as doc values instead of stored fields, which need decompressing whole
+/// documents.
+///
+/// Hits are read in batches: they're sorted by document, and each segment's doc values are read in one forward pass
+/// per field. Values come back in the order the hits were given.
+#[derive(Clone, Debug)]
+pub struct DocValuesFetcher {
+    fields: Arc<[String]>,
+}
+
+impl DocValuesFetcher {
+    /// Creates a fetcher reading the doc values of `fields`, whether numeric or binary.
+    pub fn new<S: AsRef<str>>(fields: &[S]) -> Self {
+        Self {
+            fields: fields.iter().map(|field| field.as_ref().to_string()).collect(),
+        }
+    }
+
+    /// Returns the fields read.
+    #[inline]
+    pub fn fields(&self) -> &[String] {
+        &self.fields
+    }
+
+    /// Reads the values of `docs`, which are global document ids of `reader`, returning them in the same order. This
+    /// fails with [LuceneError::InvalidArgument] if a document is out of bounds.
+    pub fn fetch(&self, reader: &dyn IndexReader, docs: &[u32]) -> BoxResult<Vec<HitDocValues>> {
+        let max_doc = reader.max_doc();
+        if let Some(doc) = docs.iter().find(|&&doc| doc >= max_doc) {
+            return Err(LuceneError::InvalidArgument(format!("document {doc} is out of bounds ({max_doc})")).into());
+        }
+
+        let mut hits: Vec<HitDocValues> = docs
+            .iter()
+            .map(|&doc| HitDocValues {
+                doc,
+                fields: self.fields.clone(),
+                values: vec![None; self.fields.len()],
+            })
+            .collect();
+        let mut order: Vec<usize> = (0..docs.len()).collect();
+        order.sort_by_key(|&i| docs[i]);
+
+        let leaves = reader.leaves();
+        for batch in order.chunk_by(|&a, &b| sub_index(docs[a], leaves) == sub_index(docs[b], leaves)) {
+            let leaf = &leaves[sub_index(docs[batch[0]], leaves)];
+            let doc_values_fields = leaf.reader().doc_values_fields();
+            for (index, field) in self.fields.iter().enumerate() {
+                let Some(&(_, doc_values_type)) = doc_values_fields.iter().find(|(name, _)| name == field) else {
+                    continue;
+                };
+
+                match doc_values_type {
+                    DocValuesType::Numeric => {
+                        let Some(mut doc_values) = leaf.reader().numeric_doc_values(field)? else {
+                            continue;
+                        };
+                        for &i in batch {
+                            if doc_values.advance_exact(docs[i] - leaf.doc_base())? {
+                                hits[i].values[index] = Some(DocValue::Numeric(doc_values.long_value()?));
+                            }
+                        }
+                    }
+                    DocValuesType::Binary => {
+                        let Some(mut doc_values) = leaf.reader().binary_doc_values(field)? else {
+                            continue;
+                        };
+                        for &i in batch {
+                            if doc_values.advance_exact(docs[i] - leaf.doc_base())? {
+                                hits[i].values[index] = Some(DocValue::Binary(doc_values.binary_value()?.to_vec()));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(hits)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{
+            document::{Document, Field, Store},
+            index::{IndexReader, Term},
+            search::{test_util, DocValue, DocValuesFetcher, IndexSearcher, TermQuery},
+        },
+        pretty_assertions::assert_eq,
+        std::sync::Arc,
+    };
+
+    #[test]
+    fn test_fetch() {
+        let mut segments = Vec::new();
+        for segment in 0..3 {
+            let mut documents = Vec::new();
+            for i in 0..4 {
+                let id = segment * 4 + i;
+                let mut doc = Document::new();
+                doc.add(Field::text(
+                    "body",
+                    if id % 3 == 0 {
+                        "match"
+                    } else {
+                        "other"
+                    },
+                    Store::No,
+                ));
+                doc.add(Field::numeric_doc_values("price", id * 100));
+                if id % 2 == 0 {
+                    doc.add(Field::binary_doc_values("title", format!("title {id}")));
+                }
+                documents.push(doc);
+            }
+            segments.push(documents);
+        }
+        let reader: Arc<dyn IndexReader> = test_util::reader(segments);
+        let searcher = IndexSearcher::new(reader.clone());
+
+        let top_docs = searcher.search(&TermQuery::new(Term::from_text("body", "match")), 10).unwrap();
+        let docs: Vec<u32> = top_docs.score_docs.iter().map(|sd| sd.doc).collect();
+        let fetcher = DocValuesFetcher::new(&["title", "price", "missing"]);
+        let hits = fetcher.fetch(searcher.reader(), &docs).unwrap();
+        assert_eq!(hits.len(), 4);
+        for (hit, doc) in hits.iter().zip(&docs) {
+            assert_eq!(hit.doc(), *doc);
+            assert_eq!(hit.numeric("price"), Some(*doc as i64 * 100));
+            assert_eq!(hit.string("title"), (doc % 2 == 0).then(|| format!("title {doc}")).as_deref());
+            assert_eq!(hit.get("missing"), None);
+            assert_eq!(hit.binary("price"), None);
+        }
+
+        // Out of order and repeated documents come back as given.
+        let hits = fetcher.fetch(reader.as_ref(), &[11, 2, 5, 2]).unwrap();
+        let prices: Vec<Option<i64>> = hits.iter().map(|hit| hit.numeric("price")).collect();
+        assert_eq!(prices, vec![Some(1_100), Some(200), Some(500), Some(200)]);
+        assert_eq!(hits[1].values()[0], Some(DocValue::Binary(b"title 2".to_vec())));
+
+        assert!(fetcher.fetch(reader.as_ref(), &[12]).is_err());
+        assert!(fetcher.fetch(reader.as_ref(), &[]).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,161 @@
+use {
+    crate::{
+        index::{LeafReaderContext, NumericDocValues},
+        search::Explanation,
+        BoxResult,
+    },
+    std::fmt::{Debug, Display, Formatter, Result as FmtResult},
+};
+
+/// The values of a [DoubleValuesSource] within a single segment.
+pub trait DoubleValues: Debug + Send {
+    /// Returns the value of the given segment-local document, whose score is `score`, or `None` if the document has
+    /// no value. Documents must be requested in increasing order.
+    fn value(&mut self, doc: u32, score: f32) -> BoxResult<Option<f64>>;
+}
+
+/// A source of per-document `f64` values, such as field values, scores, or functions of them. These are used to
+/// modify scores with [crate::search::FunctionScoreQuery].
+pub trait DoubleValuesSource: Debug + Display + Send + Sync {
+    /// Returns the values for the given segment.
+    fn values(&self, context: &LeafReaderContext) -> BoxResult<Box<dyn DoubleValues>>;
+
+    /// Indicates whether the values depend on document scores.
+    fn needs_scores(&self) -> bool;
+
+    /// Explains the value of the given segment-local document, whose score is `score`.
+    fn explain(&self, context: &LeafReaderContext, doc: u32, score: f32) -> BoxResult<Explanation> {
+        match self.values(context)?.value(doc, score)? {
+            Some(value) => Ok(Explanation::matched(value as f32, self.to_string(), vec![])),
+            None => Ok(Explanation::no_match(format!("{self} has no value for document {doc}"), vec![])),
+        }
+    }
+}
+
+/// A [DoubleValuesSource] whose value is the score of the document.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ScoreValuesSource;
+
+impl DoubleValuesSource for ScoreValuesSource {
+    fn values(&self, _context: &LeafReaderContext) -> BoxResult<Box<dyn DoubleValues>> {
+        Ok(Box::new(ScoreValues))
+    }
+
+    #[inline]
+    fn needs_scores(&self) -> bool {
+        true
+    }
+}
+
+impl Display for ScoreValuesSource {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "score")
+    }
+}
+
+#[derive(Debug)]
+struct ScoreValues;
+
+impl DoubleValues for ScoreValues {
+    #[inline]
+    fn value(&mut self, _doc: u32, score: f32) -> BoxResult<Option<f64>> {
+        Ok(Some(score as f64))
+    }
+}
+
+/// A [DoubleValuesSource] with the same value for every document.
+#[derive(Clone, Copy, Debug)]
+pub struct ConstantValuesSource {
+    value: f64,
+}
+
+impl ConstantValuesSource {
+    /// Creates a source whose value is always `value`.
+    pub fn new(value: f64) -> Self {
+        Self {
+            value,
+        }
+    }
+}
+
+impl DoubleValuesSource for ConstantValuesSource {
+    fn values(&self, _context: &LeafReaderContext) -> BoxResult<Box<dyn DoubleValues>> {
+        Ok(Box::new(ConstantValues(self.value)))
+    }
+
+    #[inline]
+    fn needs_scores(&self) -> bool {
+        false
+    }
+}
+
+impl Display for ConstantValuesSource {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "constant({})", self.value)
+    }
+}
+
+#[derive(Debug)]
+struct ConstantValues(f64);
+
+impl DoubleValues for ConstantValues {
+    #[inline]
+    fn value(&mut self, _doc: u32, _score: f32) -> BoxResult<Option<f64>> {
+        Ok(Some(self.0))
+    }
+}
+
+/// A [DoubleValuesSource] reading a field's numeric doc values, converted to `f64`.
+#[derive(Clone, Debug)]
+pub struct LongFieldSource {
+    field: String,
+}
+
+impl LongFieldSource {
+    /// Creates a source over the numeric doc values of `field`.
+    pub fn new(field: &str) -> Self {
+        Self {
+            field: field.to_string(),
+        }
+    }
+
+    /// Returns the field being read.
+    #[inline]
+    pub fn field(&self) -> &str {
+        &self.field
+    }
+}
+
+impl DoubleValuesSource for LongFieldSource {
+    fn values(&self, context: &LeafReaderContext) -> BoxResult<Box<dyn DoubleValues>> {
+        Ok(Box::new(LongFieldValues(context.reader().numeric_doc_values(&self.field)?)))
+    }
+
+    #[inline]
+    fn needs_scores(&self) -> bool {
+        false
+    }
+}
+
+impl Display for LongFieldSource {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "long({})", self.field)
+    }
+}
+
+#[derive(Debug)]
+struct LongFieldValues(Option<Box<dyn NumericDocValues>>);
+
+impl DoubleValues for LongFieldValues {
+    fn value(&mut self, doc: u32, _score: f32) -> BoxResult<Option<f64>> {
+        let Some(dv) = &mut self.0 else {
+            return Ok(None);
+        };
+
+        if dv.advance_exact(doc)? {
+            Ok(Some(dv.long_value()? as f64))
+        } else {
+            Ok(None)
+        }
+    }
+}
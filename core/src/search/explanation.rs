@@ -0,0 +1,162 @@
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+/// Describes how the score of a document was computed, as a tree of values and descriptions.
+///
+/// Explanations are produced by [crate::search::IndexSearcher::explain] for debugging relevance. An explanation that
+/// doesn't match describes why the document isn't a hit.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Explanation {
+    is_match: bool,
+    value: f32,
+    description: String,
+    details: Vec<Explanation>,
+}
+
+impl Explanation {
+    /// Creates an explanation for a match with the given score.
+    pub fn matched(value: f32, description: impl Into<String>, details: Vec<Explanation>) -> Self {
+        Self {
+            is_match: true,
+            value,
+            description: description.into(),
+            details,
+        }
+    }
+
+    /// Creates an explanation for a match of a query with a constant score, which is the boost propagated to it. As
+    /// in the query's string form, a boost other than 1 follows the description as `^boost`.
+    pub fn constant(score: f32, description: impl Into<String>) -> Self {
+        let mut description = description.into();
+        if score != 1.0 {
+            description = format!("{description}^{score}");
+        }
+        Self::matched(score, description, vec![])
+    }
+
+    /// Creates an explanation for a document that doesn't match.
+    pub fn no_match(description: impl Into<String>, details: Vec<Explanation>) -> Self {
+        Self {
+            is_match: false,
+            value: 0.0,
+            description: description.into(),
+            details,
+        }
+    }
+
+    /// Indicates whether the document matched.
+    #[inline]
+    pub fn is_match(&self) -> bool {
+        self.is_match
+    }
+
+    /// Returns the value of this node; for the root, this is the score of the document.
+    #[inline]
+    pub fn value(&self) -> f32 {
+        self.value
+    }
+
+    /// Returns the description of how the value was computed.
+    #[inline]
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
+    /// Returns the explanations of the values this one was computed from.
+    #[inline]
+    pub fn details(&self) -> &[Explanation] {
+        &self.details
+    }
+
+    fn fmt_indented(&self, f: &mut Formatter, depth: usize) -> FmtResult {
+        writeln!(f, "{:indent$}{} = {}", "", self.value, self.description, indent = depth * 2)?;
+        for detail in self.details.iter() {
+            detail.fmt_indented(f, depth + 1)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Display for Explanation {
+    /// Formats the explanation as an indented tree, one node per line.
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        self.fmt_indented(f, 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{
+            document::{Document, Field, Store},
+            index::Term,
+            search::{
+                test_util::searcher, BooleanQuery, BoostQuery, ConstantScoreQuery, Explanation, MatchAllDocsQuery,
+                Occur, Query, TermInSetQuery, TermQuery,
+            },
+        },
+        pretty_assertions::assert_eq,
+        std::sync::Arc,
+    };
+
+    #[test]
+    fn test_display() {
+        let explanation = Explanation::matched(
+            3.0,
+            "sum of:",
+            vec![Explanation::matched(1.0, "a", vec![]), Explanation::matched(2.0, "b", vec![])],
+        );
+        assert_eq!(explanation.to_string(), "3 = sum of:\n  1 = a\n  2 = b\n");
+        assert!(!Explanation::no_match("no", vec![]).is_match());
+    }
+
+    #[test]
+    fn test_explain_matches_score() {
+        let mut segments = Vec::new();
+        for bodies in [&["the quick brown fox", "the lazy dog"][..], &["fox fox fox", "a brown dog"][..]] {
+            let mut documents = Vec::new();
+            for body in bodies {
+                let mut doc = Document::new();
+                doc.add(Field::text("body", *body, Store::No));
+                documents.push(doc);
+            }
+            segments.push(documents);
+        }
+        let searcher = searcher(segments);
+
+        let term = |text: &str| Arc::new(TermQuery::new(Term::from_text("body", text))) as Arc<dyn Query>;
+        let queries: Vec<Arc<dyn Query>> = vec![
+            term("fox"),
+            Arc::new(BoostQuery::new(term("brown"), 2.5).unwrap()),
+            Arc::new(ConstantScoreQuery::new(term("dog"))),
+            Arc::new(MatchAllDocsQuery),
+            Arc::new(TermInSetQuery::new("body", ["dog", "fox"])),
+            Arc::new(
+                BooleanQuery::builder()
+                    .add(term("brown"), Occur::Should)
+                    .add(term("fox"), Occur::Should)
+                    .add(term("lazy"), Occur::MustNot)
+                    .build(),
+            ),
+            Arc::new(BooleanQuery::builder().add(term("dog"), Occur::Must).add(term("brown"), Occur::Filter).build()),
+        ];
+
+        for query in queries {
+            let top_docs = searcher.search(query.as_ref(), 10).unwrap();
+            let hits: Vec<u32> = top_docs.score_docs.iter().map(|sd| sd.doc).collect();
+            for sd in top_docs.score_docs.iter() {
+                let explanation = searcher.explain(query.as_ref(), sd.doc).unwrap();
+                assert!(explanation.is_match(), "{query} should match {}", sd.doc);
+                assert!((explanation.value() - sd.score).abs() < 1e-5, "{query}: {explanation}");
+            }
+
+            for doc in (0..4).filter(|doc| !hits.contains(doc)) {
+                assert!(!searcher.explain(query.as_ref(), doc).unwrap().is_match(), "{query} shouldn't match {doc}");
+            }
+        }
+
+        let explanation = searcher.explain(&TermQuery::new(Term::from_text("body", "fox")), 2).unwrap();
+        assert_eq!(explanation.description(), "weight(body:fox in 0), result of:");
+        assert_eq!(explanation.details()[0].details().len(), 3);
+    }
+}
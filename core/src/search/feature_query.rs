@@ -0,0 +1,469 @@
+use {
+    crate::{
+        document::{decode_feature_value, MAX_FEATURE_FREQ},
+        index::{LeafReaderContext, PostingsEnum, Term},
+        search::{DocIdSetIterator, Explanation, IndexSearcher, Query, Scorable, ScoreMode, Scorer, Weight},
+        BoxResult, LuceneError,
+    },
+    std::fmt::{Display, Formatter, Result as FmtResult},
+};
+
+/// The largest weight a [FeatureQuery] accepts.
+pub const MAX_FEATURE_WEIGHT: f32 = 64.0;
+
+/// How a [FeatureQuery] turns a feature value `S` into a score. Every function is increasing in `S`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FeatureFunction {
+    /// `S`, for features whose values are already scores, such as the term weights of learned sparse retrieval models.
+    Linear,
+
+    /// `ln(scaling_factor + S)`.
+    Log {
+        /// Added to the feature value before taking the logarithm; at least 1 so that scores are non-negative.
+        scaling_factor: f32,
+    },
+
+    /// `S / (S + pivot)`, which approaches 1 as `S` grows and is 0.5 when `S` equals the pivot. If no pivot is given,
+    /// the approximate geometric mean of the feature's values is used, as computed by [FeatureQuery::pivot_value].
+    Saturation {
+        /// The feature value that scores 0.5.
+        pivot: Option<f32>,
+    },
+
+    /// `S^exp / (S^exp + pivot^exp)`: like [FeatureFunction::Saturation], with a tunable steepness.
+    Sigmoid {
+        /// The feature value that scores 0.5.
+        pivot: f32,
+
+        /// The exponent; larger values give a sharper transition around the pivot.
+        exp: f32,
+    },
+}
+
+impl FeatureFunction {
+    /// Applies the function, whose pivot must be resolved, to a feature value.
+    fn apply(self, value: f32) -> f32 {
+        match self {
+            Self::Linear => value,
+            Self::Log {
+                scaling_factor,
+            } => (scaling_factor + value).ln(),
+            Self::Saturation {
+                pivot,
+            } => value / (value + pivot.unwrap_or(1.0)),
+            Self::Sigmoid {
+                pivot,
+                exp,
+            } => {
+                let value = value.powf(exp);
+                value / (value + pivot.powf(exp))
+            }
+        }
+    }
+
+    fn explain(self, feature: &str, weight: f32, value: f32) -> Explanation {
+        let value_explanation = Explanation::matched(value, "S, feature value", vec![]);
+        let weight_explanation = Explanation::matched(weight, "w, weight of this function", vec![]);
+        let score = weight * self.apply(value);
+
+        let (description, parameters) = match self {
+            Self::Linear => ("w * S", vec![]),
+            Self::Log {
+                scaling_factor,
+            } => ("w * ln(a + S)", vec![Explanation::matched(scaling_factor, "a, scaling factor", vec![])]),
+            Self::Saturation {
+                pivot,
+            } => {
+                ("w * S / (S + k)", vec![Explanation::matched(pivot.unwrap_or(1.0), "k, pivot feature value", vec![])])
+            }
+            Self::Sigmoid {
+                pivot,
+                exp,
+            } => (
+                "w * S^a / (S^a + k^a)",
+                vec![
+                    Explanation::matched(pivot, "k, pivot feature value", vec![]),
+                    Explanation::matched(exp, "a, exponent", vec![]),
+                ],
+            ),
+        };
+
+        let mut details = vec![weight_explanation];
+        details.extend(parameters);
+        details.push(value_explanation);
+        Explanation::matched(
+            score,
+            format!("{} function on the {feature} feature, computed as {description} from:", self.name()),
+            details,
+        )
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Linear => "Linear",
+            Self::Log {
+                ..
+            } => "Log",
+            Self::Saturation {
+                ..
+            } => "Saturation",
+            Self::Sigmoid {
+                ..
+            } => "Sigmoid",
+        }
+    }
+}
+
+impl Display for FeatureFunction {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match self {
+            Self::Linear => write!(f, "linear"),
+            Self::Log {
+                scaling_factor,
+            } => write!(f, "log(scaling_factor={scaling_factor})"),
+            Self::Saturation {
+                pivot: Some(pivot),
+            } => write!(f, "saturation(pivot={pivot})"),
+            Self::Saturation {
+                pivot: None,
+            } => write!(f, "saturation"),
+            Self::Sigmoid {
+                pivot,
+                exp,
+            } => write!(f, "sigmoid(pivot={pivot}, exp={exp})"),
+        }
+    }
+}
+
+/// A query that scores documents by a static feature indexed with [crate::document::Field::feature], such as a page
+/// rank or a term weight from a learned sparse model. It matches the documents that have the feature.
+///
+/// Feature queries are typically added as [crate::search::Occur::Should] clauses next to a text query so that
+/// feature scores are added to the text scores.
+#[derive(Clone, Debug)]
+pub struct FeatureQuery {
+    field: String,
+    feature: String,
+    weight: f32,
+    function: FeatureFunction,
+}
+
+impl FeatureQuery {
+    /// Creates a query scoring `feature` of `field` as `weight * function(value)`. `weight` must be in
+    /// `(0, MAX_FEATURE_WEIGHT]`, and the function's parameters must be positive (at least 1 for the log scaling
+    /// factor).
+    pub fn new(field: &str, feature: &str, weight: f32, function: FeatureFunction) -> BoxResult<Self> {
+        if !(weight > 0.0 && weight <= MAX_FEATURE_WEIGHT) {
+            return Err(LuceneError::InvalidArgument(format!(
+                "Feature query weight must be in (0, {MAX_FEATURE_WEIGHT}]; got {weight}"
+            ))
+            .into());
+        }
+
+        let valid = match function {
+            FeatureFunction::Linear => true,
+            FeatureFunction::Log {
+                scaling_factor,
+            } => scaling_factor >= 1.0 && scaling_factor.is_finite(),
+            FeatureFunction::Saturation {
+                pivot,
+            } => pivot.is_none_or(|pivot| pivot > 0.0 && pivot.is_finite()),
+            FeatureFunction::Sigmoid {
+                pivot,
+                exp,
+            } => pivot > 0.0 && pivot.is_finite() && exp > 0.0 && exp.is_finite(),
+        };
+
+        if !valid {
+            return Err(LuceneError::InvalidArgument(format!("Invalid feature function parameters: {function}")).into());
+        }
+
+        Ok(Self {
+            field: field.to_string(),
+            feature: feature.to_string(),
+            weight,
+            function,
+        })
+    }
+
+    /// Creates a query scoring `weight * S`.
+    pub fn linear(field: &str, feature: &str, weight: f32) -> BoxResult<Self> {
+        Self::new(field, feature, weight, FeatureFunction::Linear)
+    }
+
+    /// Creates a query scoring `weight * ln(scaling_factor + S)`.
+    pub fn log(field: &str, feature: &str, weight: f32, scaling_factor: f32) -> BoxResult<Self> {
+        Self::new(
+            field,
+            feature,
+            weight,
+            FeatureFunction::Log {
+                scaling_factor,
+            },
+        )
+    }
+
+    /// Creates a query scoring `weight * S / (S + pivot)`, with the pivot computed from the index if it is `None`.
+    pub fn saturation(field: &str, feature: &str, weight: f32, pivot: Option<f32>) -> BoxResult<Self> {
+        Self::new(
+            field,
+            feature,
+            weight,
+            FeatureFunction::Saturation {
+                pivot,
+            },
+        )
+    }
+
+    /// Creates a query scoring `weight * S^exp / (S^exp + pivot^exp)`.
+    pub fn sigmoid(field: &str, feature: &str, weight: f32, pivot: f32, exp: f32) -> BoxResult<Self> {
+        Self::new(
+            field,
+            feature,
+            weight,
+            FeatureFunction::Sigmoid {
+                pivot,
+                exp,
+            },
+        )
+    }
+
+    /// Returns an approximation of the geometric mean of the values of `feature` across the index, which is a good
+    /// default pivot for the saturation and sigmoid functions. Returns 1 if no document has the feature.
+    pub fn pivot_value(searcher: &IndexSearcher, field: &str, feature: &str) -> BoxResult<f32> {
+        let Some(stats) = searcher.term_statistics(&Term::from_text(field, feature))? else {
+            return Ok(1.0);
+        };
+
+        // Frequencies are the high bits of the values, so their mean is close to the mean of the values' logarithms.
+        let average_freq = stats.total_term_freq as f64 / stats.doc_freq as f64;
+        Ok(decode_feature_value(average_freq as f32))
+    }
+
+    /// Returns the field holding the feature.
+    #[inline]
+    pub fn field(&self) -> &str {
+        &self.field
+    }
+
+    /// Returns the name of the feature.
+    #[inline]
+    pub fn feature(&self) -> &str {
+        &self.feature
+    }
+
+    /// Returns the weight the function's value is multiplied by.
+    #[inline]
+    pub fn weight(&self) -> f32 {
+        self.weight
+    }
+
+    /// Returns the scoring function.
+    #[inline]
+    pub fn function(&self) -> FeatureFunction {
+        self.function
+    }
+}
+
+impl Query for FeatureQuery {
+    fn create_weight(&self, searcher: &IndexSearcher, score_mode: ScoreMode, boost: f32) -> BoxResult<Box<dyn Weight>> {
+        let function = match self.function {
+            FeatureFunction::Saturation {
+                pivot: None,
+            } if score_mode.needs_scores() => FeatureFunction::Saturation {
+                pivot: Some(Self::pivot_value(searcher, &self.field, &self.feature)?),
+            },
+            function => function,
+        };
+
+        Ok(Box::new(FeatureWeight {
+            term: Term::from_text(&self.field, &self.feature),
+            weight: self.weight * boost,
+            function,
+        }))
+    }
+}
+
+impl Display for FeatureQuery {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(
+            f,
+            "FeatureQuery(field={}, feature={}, weight={}, function={})",
+            self.field, self.feature, self.weight, self.function
+        )
+    }
+}
+
+#[derive(Debug)]
+struct FeatureWeight {
+    term: Term,
+    weight: f32,
+    function: FeatureFunction,
+}
+
+impl FeatureWeight {
+    fn postings(&self, context: &LeafReaderContext) -> BoxResult<Option<Box<dyn PostingsEnum>>> {
+        let Some(terms) = context.reader().terms(self.term.field())? else {
+            return Ok(None);
+        };
+
+        let mut te = terms.iterator()?;
+        if !te.seek_exact(self.term.bytes())? {
+            return Ok(None);
+        }
+
+        Ok(Some(te.postings()?))
+    }
+}
+
+impl Weight for FeatureWeight {
+    fn scorer(&self, context: &LeafReaderContext) -> BoxResult<Option<Box<dyn Scorer>>> {
+        Ok(self.postings(context)?.map(|postings| {
+            Box::new(FeatureScorer {
+                postings,
+                weight: self.weight,
+                function: self.function,
+            }) as Box<dyn Scorer>
+        }))
+    }
+
+    fn explain(&self, context: &LeafReaderContext, doc: u32) -> BoxResult<Explanation> {
+        let Some(mut postings) = self.postings(context)? else {
+            return Ok(Explanation::no_match(format!("no feature {}", self.term), vec![]));
+        };
+
+        if postings.advance(doc)? != doc {
+            return Ok(Explanation::no_match(format!("document {doc} doesn't have the feature {}", self.term), vec![]));
+        }
+
+        let value = decode_feature_value(postings.freq()? as f32);
+        Ok(self.function.explain(&String::from_utf8_lossy(self.term.bytes()), self.weight, value))
+    }
+}
+
+#[derive(Debug)]
+struct FeatureScorer {
+    postings: Box<dyn PostingsEnum>,
+    weight: f32,
+    function: FeatureFunction,
+}
+
+impl DocIdSetIterator for FeatureScorer {
+    #[inline]
+    fn doc_id(&self) -> u32 {
+        self.postings.doc_id()
+    }
+
+    #[inline]
+    fn next_doc(&mut self) -> BoxResult<u32> {
+        self.postings.next_doc()
+    }
+
+    #[inline]
+    fn advance(&mut self, target: u32) -> BoxResult<u32> {
+        self.postings.advance(target)
+    }
+
+    #[inline]
+    fn cost(&self) -> u64 {
+        self.postings.cost()
+    }
+}
+
+impl Scorable for FeatureScorer {
+    fn score(&mut self) -> BoxResult<f32> {
+        let value = decode_feature_value(self.postings.freq()? as f32);
+        Ok(self.weight * self.function.apply(value))
+    }
+}
+
+impl Scorer for FeatureScorer {
+    fn max_score(&mut self, _up_to: u32) -> BoxResult<f32> {
+        Ok(self.weight * self.function.apply(decode_feature_value(MAX_FEATURE_FREQ as f32)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{
+            analysis::SimpleAnalyzer,
+            document::{Document, Field, Store},
+            index::{MemorySegmentBuilder, Term},
+            search::{test_util, BooleanQuery, FeatureQuery, IndexSearcher, Occur, TermQuery},
+        },
+        pretty_assertions::assert_eq,
+        std::sync::Arc,
+    };
+
+    fn searcher() -> IndexSearcher {
+        test_util::searcher([[("rust search engine", 1.0), ("rust", 16.0), ("search", 4.0)].map(|(body, pagerank)| {
+            Document::from_iter([
+                Field::text("body", body, Store::No),
+                Field::feature("features", "pagerank", pagerank).unwrap(),
+            ])
+        })])
+    }
+
+    fn scores(searcher: &IndexSearcher, query: &FeatureQuery) -> Vec<f32> {
+        let mut scores = vec![0.0; 3];
+        for sd in searcher.search(query, 10).unwrap().score_docs {
+            scores[sd.doc as usize] = sd.score;
+            assert_eq!(searcher.explain(query, sd.doc).unwrap().value(), sd.score);
+        }
+        scores
+    }
+
+    #[test]
+    fn test_functions() {
+        let searcher = searcher();
+        assert_eq!(FeatureQuery::pivot_value(&searcher, "features", "pagerank").unwrap(), 4.0);
+        assert_eq!(FeatureQuery::pivot_value(&searcher, "features", "missing").unwrap(), 1.0);
+
+        let saturation = FeatureQuery::saturation("features", "pagerank", 2.0, None).unwrap();
+        assert_eq!(scores(&searcher, &saturation), vec![0.4, 1.6, 1.0]);
+
+        let linear = FeatureQuery::linear("features", "pagerank", 0.5).unwrap();
+        assert_eq!(scores(&searcher, &linear), vec![0.5, 8.0, 2.0]);
+
+        let log = FeatureQuery::log("features", "pagerank", 1.0, 1.0).unwrap();
+        assert_eq!(scores(&searcher, &log), vec![2f32.ln(), 17f32.ln(), 5f32.ln()]);
+
+        let sigmoid = FeatureQuery::sigmoid("features", "pagerank", 1.0, 4.0, 2.0).unwrap();
+        assert_eq!(scores(&searcher, &sigmoid), vec![1.0 / 17.0, 256.0 / 272.0, 0.5]);
+        assert_eq!(
+            sigmoid.to_string(),
+            "FeatureQuery(field=features, feature=pagerank, weight=1, function=sigmoid(pivot=4, exp=2))"
+        );
+
+        assert!(FeatureQuery::linear("features", "pagerank", 0.0).is_err());
+        assert!(FeatureQuery::linear("features", "pagerank", 65.0).is_err());
+        assert!(FeatureQuery::log("features", "pagerank", 1.0, 0.5).is_err());
+        assert!(FeatureQuery::saturation("features", "pagerank", 1.0, Some(-1.0)).is_err());
+        assert!(Field::feature("features", "pagerank", 0.0).is_err());
+        assert!(Field::feature("features", "pagerank", f32::INFINITY).is_err());
+    }
+
+    #[test]
+    fn test_blend_with_text_scores() {
+        let searcher = searcher();
+        let text = Arc::new(TermQuery::new(Term::from_text("body", "rust")));
+        let feature = Arc::new(FeatureQuery::saturation("features", "pagerank", 1.0, Some(4.0)).unwrap());
+        let query = BooleanQuery::builder().add(text.clone(), Occur::Must).add(feature, Occur::Should).build();
+
+        let text_scores: Vec<f32> =
+            searcher.search(text.as_ref(), 10).unwrap().score_docs.iter().map(|sd| sd.score).collect();
+        let top_docs = searcher.search(&query, 10).unwrap();
+        let docs: Vec<u32> = top_docs.score_docs.iter().map(|sd| sd.doc).collect();
+        assert_eq!(docs, vec![1, 0]);
+        assert!((top_docs.score_docs[0].score - (text_scores[0] + 0.8)).abs() < 1e-6);
+        assert!((top_docs.score_docs[1].score - (text_scores[1] + 0.2)).abs() < 1e-6);
+
+        // A document can't hold the same feature twice.
+        let mut builder = MemorySegmentBuilder::new(Arc::new(SimpleAnalyzer));
+        let mut doc = Document::new();
+        doc.add(Field::feature("features", "pagerank", 1.0).unwrap());
+        doc.add(Field::feature("features", "pagerank", 2.0).unwrap());
+        assert!(builder.add_document(&doc).is_err());
+    }
+}
@@ -0,0 +1,216 @@
+use {
+    crate::{
+        search::{
+            query_scores, rescore_window, Explanation, IndexSearcher, Query, Rescorer, ScoreCombination, ScoreDoc,
+            TopDocs, DEFAULT_RESCORE_WINDOW_SIZE,
+        },
+        BoxResult, LuceneError,
+    },
+    std::{
+        fmt::{Debug, Formatter, Result as FmtResult},
+        sync::Arc,
+    },
+};
+
+/// Computes feature vectors for hits, as input to a learned ranking model used by a [FeatureRescorer].
+pub trait FeatureExtractor: Debug + Send + Sync {
+    /// Returns the names of the features, in the order they appear in each feature vector.
+    fn feature_names(&self) -> Vec<String>;
+
+    /// Returns one feature vector per hit. Hits are sorted by document id; their scores are the first-pass scores.
+    fn extract(&self, searcher: &IndexSearcher, hits: &[ScoreDoc]) -> BoxResult<Vec<Vec<f32>>>;
+}
+
+/// A [FeatureExtractor] whose features are the first-pass score followed by the scores of a list of queries. Hits
+/// that a query doesn't match get 0 for its feature.
+#[derive(Clone, Debug)]
+pub struct QueryFeatureExtractor {
+    queries: Vec<(String, Arc<dyn Query>)>,
+}
+
+impl QueryFeatureExtractor {
+    /// Creates an extractor over named queries.
+    pub fn new(queries: Vec<(String, Arc<dyn Query>)>) -> Self {
+        Self {
+            queries,
+        }
+    }
+}
+
+impl FeatureExtractor for QueryFeatureExtractor {
+    fn feature_names(&self) -> Vec<String> {
+        let mut names = vec!["_score".to_string()];
+        names.extend(self.queries.iter().map(|(name, _)| name.clone()));
+        names
+    }
+
+    fn extract(&self, searcher: &IndexSearcher, hits: &[ScoreDoc]) -> BoxResult<Vec<Vec<f32>>> {
+        let mut features: Vec<Vec<f32>> = hits.iter().map(|hit| vec![hit.score]).collect();
+        for (_, query) in &self.queries {
+            let scores = query_scores(searcher, query.as_ref(), hits)?;
+            for (vector, score) in features.iter_mut().zip(scores) {
+                vector.push(score.unwrap_or(0.0));
+            }
+        }
+
+        Ok(features)
+    }
+}
+
+/// A ranking model: maps a batch of feature vectors to one score per vector.
+pub type RankingModel = dyn Fn(&[Vec<f32>]) -> BoxResult<Vec<f32>> + Send + Sync;
+
+/// A [Rescorer] that extracts features for the top hits and scores them with a learned ranking model, such as a
+/// gradient-boosted tree ensemble or a neural model evaluated by an inference runtime. The whole window is passed
+/// to the model as one batch.
+#[derive(Clone)]
+pub struct FeatureRescorer {
+    extractor: Arc<dyn FeatureExtractor>,
+    model: Arc<RankingModel>,
+    window_size: usize,
+    combination: ScoreCombination,
+}
+
+impl FeatureRescorer {
+    /// Creates a rescorer whose model scores replace the first-pass scores of the top
+    /// [DEFAULT_RESCORE_WINDOW_SIZE] hits.
+    pub fn new(extractor: Arc<dyn FeatureExtractor>, model: Arc<RankingModel>) -> Self {
+        Self {
+            extractor,
+            model,
+            window_size: DEFAULT_RESCORE_WINDOW_SIZE,
+            combination: ScoreCombination::Replace,
+        }
+    }
+
+    /// Returns the feature extractor.
+    #[inline]
+    pub fn extractor(&self) -> &Arc<dyn FeatureExtractor> {
+        &self.extractor
+    }
+
+    /// Returns the number of top first-pass hits that are rescored.
+    #[inline]
+    pub fn window_size(&self) -> usize {
+        self.window_size
+    }
+
+    /// Sets the number of top first-pass hits that are rescored.
+    pub fn set_window_size(&mut self, window_size: usize) -> &mut Self {
+        self.window_size = window_size;
+        self
+    }
+
+    /// Returns how first-pass and model scores are combined.
+    #[inline]
+    pub fn combination(&self) -> ScoreCombination {
+        self.combination
+    }
+
+    /// Sets how first-pass and model scores are combined.
+    pub fn set_combination(&mut self, combination: ScoreCombination) -> &mut Self {
+        self.combination = combination;
+        self
+    }
+
+    fn predict(&self, features: &[Vec<f32>]) -> BoxResult<Vec<f32>> {
+        let scores = (self.model)(features)?;
+        if scores.len() != features.len() {
+            return Err(LuceneError::InvalidArgument(format!(
+                "ranking model returned {} scores for {} feature vectors",
+                scores.len(),
+                features.len()
+            ))
+            .into());
+        }
+
+        Ok(scores)
+    }
+}
+
+impl Debug for FeatureRescorer {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        f.debug_struct("FeatureRescorer")
+            .field("extractor", &self.extractor)
+            .field("window_size", &self.window_size)
+            .field("combination", &self.combination)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Rescorer for FeatureRescorer {
+    fn rescore(&self, searcher: &IndexSearcher, first_pass: &TopDocs, top_n: usize) -> BoxResult<TopDocs> {
+        rescore_window(first_pass, self.window_size, top_n, self.combination, |hits| {
+            let features = self.extractor.extract(searcher, hits)?;
+            Ok(self.predict(&features)?.into_iter().map(Some).collect())
+        })
+    }
+
+    fn explain(&self, searcher: &IndexSearcher, first_pass: Explanation, doc: u32) -> BoxResult<Explanation> {
+        let hit = ScoreDoc::new(doc, first_pass.value());
+        let features = self.extractor.extract(searcher, &[hit])?;
+        let score = self.predict(&features)?[0];
+
+        let details = self
+            .extractor
+            .feature_names()
+            .into_iter()
+            .zip(&features[0])
+            .map(|(name, value)| Explanation::matched(*value, name, vec![]))
+            .collect();
+        let second = Explanation::matched(score, "ranking model score, computed from features:", details);
+        Ok(self.combination.explain(first_pass, Some(second)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{
+            document::{Document, Field, Store},
+            index::Term,
+            search::{
+                test_util::searcher, FeatureExtractor, FeatureRescorer, Query, QueryFeatureExtractor, Rescorer,
+                TermQuery,
+            },
+        },
+        pretty_assertions::assert_eq,
+        std::sync::Arc,
+    };
+
+    #[test]
+    fn test_feature_rescorer() {
+        let mut documents = Vec::new();
+        for (title, body) in [("rust", "rust search"), ("lucene", "rust lucene"), ("java", "rust java java")] {
+            let mut doc = Document::new();
+            doc.add(Field::text("title", title, Store::No));
+            doc.add(Field::text("body", body, Store::No));
+            documents.push(doc);
+        }
+        let searcher = searcher([documents]);
+
+        let rust = TermQuery::new(Term::from_text("body", "rust"));
+        let first_pass = searcher.search(&rust, 10).unwrap();
+
+        let title: Arc<dyn Query> = Arc::new(TermQuery::new(Term::from_text("title", "lucene")));
+        let extractor = Arc::new(QueryFeatureExtractor::new(vec![("title_lucene".to_string(), title)]));
+        assert_eq!(extractor.feature_names(), vec!["_score", "title_lucene"]);
+
+        // A linear model that strongly prefers title matches.
+        let rescorer = FeatureRescorer::new(
+            extractor,
+            Arc::new(|features: &[Vec<f32>]| Ok(features.iter().map(|f| f[0] + 10.0 * f[1]).collect())),
+        );
+        let rescored = rescorer.rescore(&searcher, &first_pass, 2).unwrap();
+        assert_eq!(rescored.score_docs.len(), 2);
+        assert_eq!(rescored.score_docs[0].doc, 1);
+        assert_eq!(rescored.score_docs[1], first_pass.score_docs.iter().find(|sd| sd.doc != 1).copied().unwrap());
+
+        let first = searcher.explain(&rust, 1).unwrap();
+        let explanation = rescorer.explain(&searcher, first, 1).unwrap();
+        assert_eq!(explanation.value(), rescored.score_docs[0].score);
+
+        let broken = FeatureRescorer::new(rescorer.extractor().clone(), Arc::new(|_: &[Vec<f32>]| Ok(vec![])));
+        assert!(broken.rescore(&searcher, &first_pass, 2).is_err());
+    }
+}
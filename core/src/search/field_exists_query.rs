@@ -0,0 +1,233 @@
+use {
+    crate::{
+        document::{is_within_path, FIELD_PATH_SEPARATOR},
+        index::{DocValuesType, LeafReader, LeafReaderContext},
+        search::{ConstantScoreScorer, DocIdSetBuilder, Explanation, IndexSearcher, Query, ScoreMode, Scorer, Weight},
+        BoxResult,
+    },
+    std::fmt::{Display, Formatter, Result as FmtResult},
+};
+
+/// A query matching the documents that have a value for a field, with a constant score.
+///
+/// A document has a value if the field has doc values for it, or if it holds any term of the field. Where the field
+/// only has doc values, they're iterated directly; otherwise the postings of every term are gathered, which costs as
+/// much as reading the field's postings.
+///
+/// With [FieldExistsQuery::within], the query matches the documents with a value for any field of a flattened object
+/// (see [crate::document::ObjectValue]), however deeply nested.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FieldExistsQuery {
+    field: String,
+    nested: bool,
+}
+
+impl FieldExistsQuery {
+    /// Creates a query matching the documents with a value for `field`.
+    pub fn new(field: &str) -> Self {
+        Self {
+            field: field.to_string(),
+            nested: false,
+        }
+    }
+
+    /// Creates a query matching the documents with a value for `path`, or for any field under it, such as `user.name`
+    /// or `user.address.city` for the path `user`.
+    pub fn within(path: &str) -> Self {
+        Self {
+            field: path.to_string(),
+            nested: true,
+        }
+    }
+
+    /// Returns the field, or the object path if the query matches the fields under it.
+    #[inline]
+    pub fn field(&self) -> &str {
+        &self.field
+    }
+
+    /// Indicates whether the query also matches the fields under its path.
+    #[inline]
+    pub fn is_nested(&self) -> bool {
+        self.nested
+    }
+
+    /// Returns the fields of `reader` that the query checks, with their doc values type if they have doc values, and
+    /// whether they're indexed.
+    fn fields<'a>(&self, reader: &'a dyn LeafReader) -> Vec<(&'a str, Option<DocValuesType>, bool)> {
+        let matches = |field: &str| match self.nested {
+            true => is_within_path(field, &self.field),
+            false => field == self.field,
+        };
+
+        let indexed = reader.indexed_fields();
+        let mut fields: Vec<_> = reader
+            .doc_values_fields()
+            .into_iter()
+            .filter(|(field, _)| matches(field))
+            .map(|(field, doc_values_type)| (field, Some(doc_values_type), indexed.contains(&field)))
+            .collect();
+        for field in indexed {
+            if matches(field) && !fields.iter().any(|(name, _, _)| *name == field) {
+                fields.push((field, None, true));
+            }
+        }
+        fields
+    }
+}
+
+impl Query for FieldExistsQuery {
+    fn create_weight(
+        &self,
+        _searcher: &IndexSearcher,
+        _score_mode: ScoreMode,
+        boost: f32,
+    ) -> BoxResult<Box<dyn Weight>> {
+        Ok(Box::new(FieldExistsWeight {
+            query: self.clone(),
+            score: boost,
+        }))
+    }
+}
+
+impl Display for FieldExistsQuery {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match self.nested {
+            true => write!(f, "FieldExistsQuery [field={}{FIELD_PATH_SEPARATOR}*]", self.field),
+            false => write!(f, "FieldExistsQuery [field={}]", self.field),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct FieldExistsWeight {
+    query: FieldExistsQuery,
+    score: f32,
+}
+
+impl Weight for FieldExistsWeight {
+    fn scorer(&self, context: &LeafReaderContext) -> BoxResult<Option<Box<dyn Scorer>>> {
+        let reader = context.reader();
+        let fields = self.query.fields(reader);
+
+        // A single field with only doc values needs no copy of its documents.
+        if let [(field, Some(doc_values_type), false)] = fields[..] {
+            let iterator = match doc_values_type {
+                DocValuesType::Numeric => reader.numeric_doc_values(field)?.map(|dv| dv as Box<_>),
+                DocValuesType::Binary => reader.binary_doc_values(field)?.map(|dv| dv as Box<_>),
+            };
+            return Ok(iterator.map(|iterator| Box::new(ConstantScoreScorer::new(self.score, iterator)) as Box<_>));
+        }
+
+        let mut builder = DocIdSetBuilder::new(reader.max_doc());
+        let mut any = false;
+        for (field, doc_values_type, indexed) in fields {
+            match doc_values_type {
+                Some(DocValuesType::Numeric) => {
+                    if let Some(mut doc_values) = reader.numeric_doc_values(field)? {
+                        builder.add(&mut *doc_values)?;
+                        any = true;
+                    }
+                }
+                Some(DocValuesType::Binary) => {
+                    if let Some(mut doc_values) = reader.binary_doc_values(field)? {
+                        builder.add(&mut *doc_values)?;
+                        any = true;
+                    }
+                }
+                None => (),
+            }
+
+            // A field may have terms in documents without doc values, as the integers of an array do.
+            let Some(terms) = reader.terms(field)?.filter(|_| indexed) else {
+                continue;
+            };
+            let mut terms_enum = terms.iterator()?;
+            while terms_enum.next()?.is_some() {
+                builder.add(&mut *terms_enum.postings()?)?;
+                any = true;
+            }
+        }
+
+        if !any {
+            return Ok(None);
+        }
+        Ok(Some(Box::new(ConstantScoreScorer::new(self.score, builder.build().iterator()))))
+    }
+
+    fn explain(&self, context: &LeafReaderContext, doc: u32) -> BoxResult<Explanation> {
+        match self.scorer_at(context, doc)? {
+            Some(_) => Ok(Explanation::constant(self.score, self.query.to_string())),
+            None => Ok(Explanation::no_match(format!("{} has no value in document {doc}", self.query.field), vec![])),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{
+            document::{Document, Field, ObjectValue, Store},
+            index::Term,
+            search::{test_util::searcher, BooleanQuery, FieldExistsQuery, Query, TermQuery},
+        },
+        pretty_assertions::assert_eq,
+        std::sync::Arc,
+    };
+
+    #[test]
+    fn test_field_exists() {
+        let objects = [
+            ObjectValue::object([
+                ("title", "first".into()),
+                ("user", ObjectValue::object([("name", "ann".into()), ("age", 41.into())])),
+            ]),
+            ObjectValue::object([("title", "second".into()), ("user", ObjectValue::object([("age", 29.into())]))]),
+            ObjectValue::object([
+                ("title", "third".into()),
+                ("user", ObjectValue::object([("address", ObjectValue::object([("city", "oslo".into())]))])),
+            ]),
+            ObjectValue::object([("username", "bob".into()), ("scores", vec![3_i64, 5].into())]),
+        ];
+        let mut segments = Vec::new();
+        for objects in objects.chunks(2) {
+            let mut documents = Vec::new();
+            for object in objects {
+                let mut doc = Document::new();
+                doc.add_object("", object, Store::No);
+                doc.add(Field::binary_doc_values("raw", vec![1]));
+                documents.push(doc);
+            }
+            segments.push(documents);
+        }
+        let searcher = searcher(segments);
+        let docs = |query: &dyn Query| {
+            let mut docs: Vec<u32> = searcher.search(query, 10).unwrap().score_docs.iter().map(|sd| sd.doc).collect();
+            docs.sort_unstable();
+            docs
+        };
+
+        // Postings, numeric and binary doc values.
+        assert_eq!(docs(&FieldExistsQuery::new("user.name")), vec![0]);
+        assert_eq!(docs(&FieldExistsQuery::new("user.age")), vec![0, 1]);
+        assert_eq!(docs(&FieldExistsQuery::new("raw")), vec![0, 1, 2, 3]);
+        assert_eq!(docs(&FieldExistsQuery::new("scores")), vec![3]);
+        assert_eq!(docs(&FieldExistsQuery::new("user")), Vec::<u32>::new());
+        assert_eq!(docs(&FieldExistsQuery::new("missing")), Vec::<u32>::new());
+
+        // Any field of an object, but not a field that merely shares its prefix.
+        let query = FieldExistsQuery::within("user");
+        assert_eq!(query.to_string(), "FieldExistsQuery [field=user.*]");
+        assert_eq!(docs(&query), vec![0, 1, 2]);
+        assert_eq!(docs(&FieldExistsQuery::within("user.address")), vec![2]);
+
+        let query = BooleanQuery::builder()
+            .must(Arc::new(TermQuery::new(Term::from_text("title", "second"))))
+            .filter(Arc::new(FieldExistsQuery::new("user.age")))
+            .build();
+        assert_eq!(docs(&query), vec![1]);
+
+        assert!(searcher.explain(&FieldExistsQuery::within("user"), 2).unwrap().is_match());
+        assert!(!searcher.explain(&FieldExistsQuery::within("user"), 3).unwrap().is_match());
+    }
+}
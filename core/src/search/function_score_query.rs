@@ -0,0 +1,301 @@
+use {
+    crate::{
+        index::LeafReaderContext,
+        search::{
+            DocIdSetIterator, DoubleValues, DoubleValuesSource, Explanation, IndexSearcher, Query, Scorable, ScoreMode,
+            Scorer, TwoPhaseIterator, Weight,
+        },
+        BoxResult,
+    },
+    std::{
+        fmt::{Display, Formatter, Result as FmtResult},
+        sync::Arc,
+    },
+};
+
+/// A query that matches the same documents as another query, but replaces their scores with the values of a
+/// [DoubleValuesSource]. The source may use the wrapped query's scores.
+///
+/// Documents without a value, or whose value is negative or NaN, score zero.
+#[derive(Clone, Debug)]
+pub struct FunctionScoreQuery {
+    query: Arc<dyn Query>,
+    source: Arc<dyn DoubleValuesSource>,
+}
+
+impl FunctionScoreQuery {
+    /// Wraps `query`, scoring its matches with `source`.
+    pub fn new(query: Arc<dyn Query>, source: Arc<dyn DoubleValuesSource>) -> Self {
+        Self {
+            query,
+            source,
+        }
+    }
+
+    /// Wraps `query`, multiplying the score of each match by the value of `boost`. Documents without a boost value
+    /// keep their original score.
+    pub fn boost_by_value(query: Arc<dyn Query>, boost: Arc<dyn DoubleValuesSource>) -> Self {
+        Self::new(
+            query,
+            Arc::new(MultiplicativeBoostValuesSource {
+                boost,
+            }),
+        )
+    }
+
+    /// Returns the wrapped query.
+    #[inline]
+    pub fn query(&self) -> &Arc<dyn Query> {
+        &self.query
+    }
+
+    /// Returns the source of scores.
+    #[inline]
+    pub fn source(&self) -> &Arc<dyn DoubleValuesSource> {
+        &self.source
+    }
+}
+
+impl Query for FunctionScoreQuery {
+    fn create_weight(&self, searcher: &IndexSearcher, score_mode: ScoreMode, boost: f32) -> BoxResult<Box<dyn Weight>> {
+        let inner_score_mode = if score_mode.needs_scores() && self.source.needs_scores() {
+            ScoreMode::Complete
+        } else {
+            ScoreMode::CompleteNoScores
+        };
+
+        Ok(Box::new(FunctionScoreWeight {
+            inner: searcher.create_weight(self.query.as_ref(), inner_score_mode, 1.0)?,
+            source: self.source.clone(),
+            needs_scores: inner_score_mode.needs_scores(),
+            boost,
+        }))
+    }
+
+    fn rewrite(&self, searcher: &IndexSearcher) -> BoxResult<Option<Arc<dyn Query>>> {
+        Ok(searcher
+            .rewrite(self.query.as_ref())?
+            .map(|rewritten| Arc::new(Self::new(rewritten, self.source.clone())) as Arc<dyn Query>))
+    }
+}
+
+impl Display for FunctionScoreQuery {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "FunctionScoreQuery({}, scored by {})", self.query, self.source)
+    }
+}
+
+#[derive(Debug)]
+struct FunctionScoreWeight {
+    inner: Box<dyn Weight>,
+    source: Arc<dyn DoubleValuesSource>,
+    needs_scores: bool,
+    boost: f32,
+}
+
+impl Weight for FunctionScoreWeight {
+    fn scorer(&self, context: &LeafReaderContext) -> BoxResult<Option<Box<dyn Scorer>>> {
+        let Some(inner) = self.inner.scorer(context)? else {
+            return Ok(None);
+        };
+
+        Ok(Some(Box::new(FunctionScorer {
+            inner,
+            values: self.source.values(context)?,
+            needs_scores: self.needs_scores,
+            boost: self.boost,
+        })))
+    }
+
+    fn explain(&self, context: &LeafReaderContext, doc: u32) -> BoxResult<Explanation> {
+        let inner = self.inner.explain(context, doc)?;
+        if !inner.is_match() {
+            return Ok(inner);
+        }
+
+        let source = self.source.explain(context, doc, inner.value())?;
+        let value = sanitize(source.is_match().then_some(source.value() as f64));
+        let mut details = vec![source];
+        if self.boost != 1.0 {
+            details.push(Explanation::matched(self.boost, "boost", vec![]));
+        }
+
+        Ok(Explanation::matched((value * self.boost as f64) as f32, "product of:", details))
+    }
+}
+
+/// Returns the score for a source value: zero if it is missing, negative or NaN.
+#[inline]
+fn sanitize(value: Option<f64>) -> f64 {
+    match value {
+        Some(value) if value > 0.0 => value,
+        _ => 0.0,
+    }
+}
+
+#[derive(Debug)]
+struct FunctionScorer {
+    inner: Box<dyn Scorer>,
+    values: Box<dyn DoubleValues>,
+    needs_scores: bool,
+    boost: f32,
+}
+
+impl DocIdSetIterator for FunctionScorer {
+    #[inline]
+    fn doc_id(&self) -> u32 {
+        self.inner.doc_id()
+    }
+
+    #[inline]
+    fn next_doc(&mut self) -> BoxResult<u32> {
+        self.inner.next_doc()
+    }
+
+    #[inline]
+    fn advance(&mut self, target: u32) -> BoxResult<u32> {
+        self.inner.advance(target)
+    }
+
+    #[inline]
+    fn cost(&self) -> u64 {
+        self.inner.cost()
+    }
+}
+
+impl Scorable for FunctionScorer {
+    fn score(&mut self) -> BoxResult<f32> {
+        let score = if self.needs_scores {
+            self.inner.score()?
+        } else {
+            0.0
+        };
+
+        let value = sanitize(self.values.value(self.inner.doc_id(), score)?);
+        Ok((value * self.boost as f64) as f32)
+    }
+}
+
+impl Scorer for FunctionScorer {
+    fn two_phase_iterator(&mut self) -> Option<&mut dyn TwoPhaseIterator> {
+        self.inner.two_phase_iterator()
+    }
+}
+
+/// Multiplies scores by the values of another source, leaving them unchanged where it has no value.
+#[derive(Debug)]
+struct MultiplicativeBoostValuesSource {
+    boost: Arc<dyn DoubleValuesSource>,
+}
+
+impl DoubleValuesSource for MultiplicativeBoostValuesSource {
+    fn values(&self, context: &LeafReaderContext) -> BoxResult<Box<dyn DoubleValues>> {
+        Ok(Box::new(MultiplicativeBoostValues {
+            boost: self.boost.values(context)?,
+        }))
+    }
+
+    #[inline]
+    fn needs_scores(&self) -> bool {
+        true
+    }
+
+    fn explain(&self, context: &LeafReaderContext, doc: u32, score: f32) -> BoxResult<Explanation> {
+        let boost = self.boost.explain(context, doc, score)?;
+        let score_explanation = Explanation::matched(score, "score", vec![]);
+        if !boost.is_match() {
+            return Ok(Explanation::matched(score, "score, with no boost value", vec![score_explanation]));
+        }
+
+        Ok(Explanation::matched(score * boost.value(), "product of:", vec![score_explanation, boost]))
+    }
+}
+
+impl Display for MultiplicativeBoostValuesSource {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "boost({})", self.boost)
+    }
+}
+
+#[derive(Debug)]
+struct MultiplicativeBoostValues {
+    boost: Box<dyn DoubleValues>,
+}
+
+impl DoubleValues for MultiplicativeBoostValues {
+    fn value(&mut self, doc: u32, score: f32) -> BoxResult<Option<f64>> {
+        let boost = self.boost.value(doc, score)?.unwrap_or(1.0);
+        Ok(Some(score as f64 * boost))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{
+            document::{Document, Field, Store},
+            expressions::{Bindings, Expression},
+            index::Term,
+            search::{test_util, FunctionScoreQuery, IndexSearcher, LongFieldSource, Query, TermQuery},
+        },
+        pretty_assertions::assert_eq,
+        std::sync::Arc,
+    };
+
+    fn searcher() -> IndexSearcher {
+        test_util::searcher([[("rust search", Some(10)), ("rust rust rust", Some(1)), ("rust", None)].map(
+            |(body, popularity)| {
+                let mut doc = Document::from_iter([Field::text("body", body, Store::No)]);
+                if let Some(popularity) = popularity {
+                    doc.add(Field::numeric_doc_values("popularity", popularity));
+                }
+                doc
+            },
+        )])
+    }
+
+    #[test]
+    fn test_field_value_scores() {
+        let searcher = searcher();
+        let rust: Arc<dyn Query> = Arc::new(TermQuery::new(Term::from_text("body", "rust")));
+        let query = FunctionScoreQuery::new(rust, Arc::new(LongFieldSource::new("popularity")));
+        assert_eq!(query.to_string(), "FunctionScoreQuery(body:rust, scored by long(popularity))");
+
+        let top_docs = searcher.search(&query, 10).unwrap();
+        let hits: Vec<(u32, f32)> = top_docs.score_docs.iter().map(|sd| (sd.doc, sd.score)).collect();
+        assert_eq!(hits, vec![(0, 10.0), (1, 1.0), (2, 0.0)]);
+    }
+
+    #[test]
+    fn test_expression_and_boost() {
+        let searcher = searcher();
+        let rust: Arc<dyn Query> = Arc::new(TermQuery::new(Term::from_text("body", "rust")));
+        let plain: Vec<f32> = {
+            let mut scores = vec![0.0; 3];
+            for sd in searcher.search(rust.as_ref(), 10).unwrap().score_docs {
+                scores[sd.doc as usize] = sd.score;
+            }
+            scores
+        };
+
+        let mut bindings = Bindings::new();
+        bindings.add("popularity", Arc::new(LongFieldSource::new("popularity")));
+        let expression = Expression::compile("_score * log(1 + popularity)").unwrap();
+        let query = FunctionScoreQuery::new(rust.clone(), expression.values_source(&bindings).unwrap());
+
+        for sd in searcher.search(&query, 10).unwrap().score_docs {
+            let popularity = [10.0f32, 1.0, 0.0][sd.doc as usize];
+            assert!((sd.score - plain[sd.doc as usize] * (1.0 + popularity).ln()).abs() < 1e-5);
+
+            let explanation = searcher.explain(&query, sd.doc).unwrap();
+            assert!((explanation.value() - sd.score).abs() < 1e-5, "{explanation}");
+        }
+
+        let query = FunctionScoreQuery::boost_by_value(rust, Arc::new(LongFieldSource::new("popularity")));
+        for sd in searcher.search(&query, 10).unwrap().score_docs {
+            let boost = [10.0f32, 1.0, 1.0][sd.doc as usize];
+            assert!((sd.score - plain[sd.doc as usize] * boost).abs() < 1e-5);
+            assert!((searcher.explain(&query, sd.doc).unwrap().value() - sd.score).abs() < 1e-5);
+        }
+    }
+}
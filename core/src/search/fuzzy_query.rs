@@ -0,0 +1,232 @@
+use {
+    crate::{
+        index::{Term, Terms, TermsEnum},
+        search::FuzzyTermsEnum,
+        util::automaton::MAXIMUM_SUPPORTED_DISTANCE,
+        BoxResult, LuceneError,
+    },
+    std::{
+        cmp::Ordering,
+        fmt::{Display, Formatter, Result as FmtResult},
+    },
+};
+
+/// Implements the fuzzy search query. The similarity measurement is based on the Damerau-Levenshtein (optimal
+/// string alignment) algorithm, though you can explicitly choose classic Levenshtein by passing `false` for
+/// `transpositions`.
+///
+/// This query uses [FuzzyTermsEnum], which is backed by Levenshtein automata, so matching terms are found without
+/// computing the edit distance to every term in the index. At most [FuzzyQuery::max_expansions] terms (the most
+/// similar ones) are used.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct FuzzyQuery {
+    term: Term,
+    max_edits: u32,
+    prefix_length: usize,
+    max_expansions: usize,
+    transpositions: bool,
+}
+
+impl FuzzyQuery {
+    /// The default maximum number of edits.
+    pub const DEFAULT_MAX_EDITS: u32 = MAXIMUM_SUPPORTED_DISTANCE;
+
+    /// The default length of the common (non-fuzzy) prefix.
+    pub const DEFAULT_PREFIX_LENGTH: usize = 0;
+
+    /// The default maximum number of terms the query expands to.
+    pub const DEFAULT_MAX_EXPANSIONS: usize = 50;
+
+    /// By default, transpositions count as a single edit.
+    pub const DEFAULT_TRANSPOSITIONS: bool = true;
+
+    /// Creates a new fuzzy query for `term` with the default parameters.
+    pub fn new(term: Term) -> Self {
+        Self {
+            term,
+            max_edits: Self::DEFAULT_MAX_EDITS,
+            prefix_length: Self::DEFAULT_PREFIX_LENGTH,
+            max_expansions: Self::DEFAULT_MAX_EXPANSIONS,
+            transpositions: Self::DEFAULT_TRANSPOSITIONS,
+        }
+    }
+
+    /// Creates a new fuzzy query.
+    ///
+    /// # Parameters
+    /// * `term`: The term to search for.
+    /// * `max_edits`: The maximum edit distance; at most [MAXIMUM_SUPPORTED_DISTANCE].
+    /// * `prefix_length`: The length (in code points) of the common prefix that must match exactly.
+    /// * `max_expansions`: The maximum number of terms to match; must be greater than zero.
+    /// * `transpositions`: Whether a transposition of two adjacent characters counts as one edit (`true`) or two.
+    pub fn with_options(
+        term: Term,
+        max_edits: u32,
+        prefix_length: usize,
+        max_expansions: usize,
+        transpositions: bool,
+    ) -> BoxResult<Self> {
+        if max_edits > MAXIMUM_SUPPORTED_DISTANCE {
+            return Err(LuceneError::InvalidArgument(format!(
+                "max_edits must be between 0 and {MAXIMUM_SUPPORTED_DISTANCE}, got {max_edits}"
+            ))
+            .into());
+        }
+
+        if max_expansions == 0 {
+            return Err(LuceneError::InvalidArgument("max_expansions must be positive".to_string()).into());
+        }
+
+        Ok(Self {
+            term,
+            max_edits,
+            prefix_length,
+            max_expansions,
+            transpositions,
+        })
+    }
+
+    /// Returns the term being searched for.
+    #[inline]
+    pub fn term(&self) -> &Term {
+        &self.term
+    }
+
+    /// Returns the maximum number of edit distances allowed for this query to match.
+    #[inline]
+    pub fn max_edits(&self) -> u32 {
+        self.max_edits
+    }
+
+    /// Returns the non-fuzzy prefix length.
+    #[inline]
+    pub fn prefix_length(&self) -> usize {
+        self.prefix_length
+    }
+
+    /// Returns the maximum number of terms this query expands to.
+    #[inline]
+    pub fn max_expansions(&self) -> usize {
+        self.max_expansions
+    }
+
+    /// Returns true if transpositions should be treated as a primitive edit operation.
+    #[inline]
+    pub fn transpositions(&self) -> bool {
+        self.transpositions
+    }
+
+    /// Returns an enumeration of the terms in `terms` that match this query.
+    pub fn terms_enum<'a>(&self, terms: &'a dyn Terms) -> BoxResult<FuzzyTermsEnum<'a>> {
+        FuzzyTermsEnum::new(terms, &self.term, self.max_edits, self.prefix_length, self.transpositions)
+    }
+
+    /// Returns the (at most [FuzzyQuery::max_expansions]) most similar matching terms in `terms`, along with their
+    /// boosts. The result is sorted by term.
+    pub fn top_terms(&self, terms: &dyn Terms) -> BoxResult<Vec<(Vec<u8>, f32)>> {
+        let mut te = self.terms_enum(terms)?;
+        let mut top: Vec<(Vec<u8>, f32)> = Vec::new();
+
+        while let Some(term) = te.next()? {
+            let term = term.to_vec();
+            top.push((term, te.boost()));
+            if top.len() > self.max_expansions * 2 {
+                truncate_top(&mut top, self.max_expansions);
+            }
+        }
+
+        truncate_top(&mut top, self.max_expansions);
+        top.sort_by(|a, b| a.0.cmp(&b.0));
+        Ok(top)
+    }
+
+    /// Converts a similarity in the range `[0, 1)` to an edit distance for a term of the given length, capped at
+    /// [MAXIMUM_SUPPORTED_DISTANCE]. Values of 1 or more are taken to be edit distances already.
+    pub fn float_to_edits(min_similarity: f32, term_len: usize) -> u32 {
+        if min_similarity >= 1.0 {
+            (min_similarity as u32).min(MAXIMUM_SUPPORTED_DISTANCE)
+        } else if min_similarity == 0.0 {
+            0
+        } else {
+            (((1.0 - min_similarity) * term_len as f32) as u32).min(MAXIMUM_SUPPORTED_DISTANCE)
+        }
+    }
+}
+
+/// Keeps the `n` terms with the highest boost, preferring the smaller term on ties.
+fn truncate_top(top: &mut Vec<(Vec<u8>, f32)>, n: usize) {
+    top.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(Ordering::Equal).then_with(|| a.0.cmp(&b.0)));
+    top.truncate(n);
+}
+
+impl Display for FuzzyQuery {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match self.term.text() {
+            Some(text) => write!(f, "{}:{text}~{}", self.term.field(), self.max_edits),
+            None => write!(f, "{}:{:x?}~{}", self.term.field(), self.term.bytes(), self.max_edits),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{
+            index::{MemoryTerms, Term, TermsEnum},
+            search::FuzzyQuery,
+        },
+        pretty_assertions::assert_eq,
+    };
+
+    fn matching(query: &FuzzyQuery, terms: &MemoryTerms) -> Vec<(String, f32)> {
+        let mut te = query.terms_enum(terms).unwrap();
+        let mut result = Vec::new();
+        while let Some(term) = te.next().unwrap() {
+            result.push((String::from_utf8(term.to_vec()).unwrap(), te.boost()));
+        }
+        result
+    }
+
+    #[test]
+    fn test_fuzzy_terms_enum() {
+        let terms = MemoryTerms::from_strs(&["aaaaa", "aaaab", "aaabb", "aabbb", "abbbb", "bbbbb", "ddddd", "aaaaaa"]);
+
+        let query = FuzzyQuery::with_options(Term::from_text("field", "aaaaa"), 1, 0, 50, true).unwrap();
+        assert_eq!(
+            matching(&query, &terms),
+            vec![("aaaaa".to_string(), 1.0), ("aaaaaa".to_string(), 0.8), ("aaaab".to_string(), 0.8)]
+        );
+
+        let query = FuzzyQuery::new(Term::from_text("field", "aaaaa"));
+        let names: Vec<String> = matching(&query, &terms).into_iter().map(|(t, _)| t).collect();
+        assert_eq!(names, vec!["aaaaa", "aaaaaa", "aaaab", "aaabb"]);
+
+        // With a prefix, only terms sharing it are considered.
+        let query = FuzzyQuery::with_options(Term::from_text("field", "bbbba"), 2, 3, 50, true).unwrap();
+        let names: Vec<String> = matching(&query, &terms).into_iter().map(|(t, _)| t).collect();
+        assert_eq!(names, vec!["bbbbb"]);
+    }
+
+    #[test]
+    fn test_transpositions() {
+        let terms = MemoryTerms::from_strs(&["lucene", "lucnee", "ulcene"]);
+        let with = FuzzyQuery::with_options(Term::from_text("f", "lucene"), 1, 0, 50, true).unwrap();
+        assert_eq!(matching(&with, &terms).len(), 3);
+
+        let without = FuzzyQuery::with_options(Term::from_text("f", "lucene"), 1, 0, 50, false).unwrap();
+        assert_eq!(matching(&without, &terms).len(), 1);
+    }
+
+    #[test]
+    fn test_top_terms_and_validation() {
+        let terms = MemoryTerms::from_strs(&["abcd", "abce", "abxd", "xbcd", "abc"]);
+        let query = FuzzyQuery::with_options(Term::from_text("f", "abcd"), 2, 0, 2, true).unwrap();
+        let top = query.top_terms(&terms).unwrap();
+        assert_eq!(top, vec![(b"abcd".to_vec(), 1.0), (b"abce".to_vec(), 0.75)]);
+
+        assert!(FuzzyQuery::with_options(Term::from_text("f", "abcd"), 3, 0, 50, true).is_err());
+        assert!(FuzzyQuery::with_options(Term::from_text("f", "abcd"), 1, 0, 0, true).is_err());
+        assert_eq!(FuzzyQuery::float_to_edits(0.5, 4), 2);
+        assert_eq!(query.to_string(), "f:abcd~2");
+    }
+}
@@ -0,0 +1,150 @@
+use {
+    crate::{
+        index::{PostingsEnum, SeekStatus, Term, Terms, TermsEnum},
+        util::automaton::{
+            Automaton, ByteRunAutomaton, CompiledAutomaton, LevenshteinAutomata, DEFAULT_DETERMINIZE_WORK_LIMIT,
+            MAXIMUM_SUPPORTED_DISTANCE,
+        },
+        BoxResult, LuceneError,
+    },
+    std::fmt::{Debug, Formatter, Result as FmtResult},
+};
+
+/// A [TermsEnum] that returns only the terms within a maximum edit distance of a target term.
+///
+/// Terms are filtered with Levenshtein automata, one per edit distance up to the maximum, so the exact distance of
+/// each accepted term is known. [TermsEnum::boost] reports the similarity of the current term to the target:
+/// `1 - edits / min(term length, target length)`, in code points.
+///
+/// If a prefix length is given, the first `prefix_length` code points of the target must match exactly. The automaton
+/// for the maximum distance is intersected with the terms dictionary, so only terms that could match are visited.
+pub struct FuzzyTermsEnum<'a> {
+    inner: Box<dyn TermsEnum + 'a>,
+    run_automata: Vec<ByteRunAutomaton>,
+    max_edits: usize,
+    prefix_length: usize,
+    term_length: usize,
+    boost: f32,
+}
+
+impl<'a> FuzzyTermsEnum<'a> {
+    /// Creates a new enumeration over `terms` for terms within `max_edits` of `term`.
+    ///
+    /// This fails with [LuceneError::InvalidArgument] if `max_edits` exceeds [MAXIMUM_SUPPORTED_DISTANCE] or the
+    /// term is not valid UTF-8.
+    pub fn new(
+        terms: &'a dyn Terms,
+        term: &Term,
+        max_edits: u32,
+        prefix_length: usize,
+        transpositions: bool,
+    ) -> BoxResult<Self> {
+        if max_edits > MAXIMUM_SUPPORTED_DISTANCE {
+            return Err(LuceneError::InvalidArgument(format!(
+                "max_edits must be between 0 and {MAXIMUM_SUPPORTED_DISTANCE}, got {max_edits}"
+            ))
+            .into());
+        }
+
+        let Some(text) = term.text() else {
+            return Err(LuceneError::InvalidArgument(format!("fuzzy term {term} is not valid UTF-8")).into());
+        };
+
+        let term_length = text.chars().count();
+        let prefix_length = prefix_length.min(term_length);
+        let prefix: String = text.chars().take(prefix_length).collect();
+        let suffix: String = text.chars().skip(prefix_length).collect();
+
+        let builder = LevenshteinAutomata::new(&suffix, transpositions);
+        let automata: Vec<Automaton> = (0..=max_edits)
+            .map(|n| builder.to_automaton_with_prefix(n, &prefix).expect("distance is within the supported maximum"))
+            .collect();
+
+        // The automaton for the maximum distance drives the enumeration by seeking through the terms dictionary; the
+        // smaller distances are only used to compute the boost of each accepted term.
+        let max_edits = max_edits as usize;
+        let compiled =
+            CompiledAutomaton::new(&automata[max_edits], Some(true), true, DEFAULT_DETERMINIZE_WORK_LIMIT, false)?;
+        let run_automata = automata[..max_edits]
+            .iter()
+            .map(|a| ByteRunAutomaton::new(a, false, DEFAULT_DETERMINIZE_WORK_LIMIT))
+            .collect::<BoxResult<Vec<_>>>()?;
+
+        Ok(Self {
+            inner: compiled.terms_enum(terms)?,
+            run_automata,
+            max_edits,
+            prefix_length,
+            term_length,
+            boost: 1.0,
+        })
+    }
+
+    /// Computes the boost for the term the inner enum is positioned on, which is known to be within the maximum
+    /// distance.
+    fn update_boost(&mut self) {
+        let term = self.inner.term();
+        let edits = self.run_automata.iter().position(|a| a.run(term)).unwrap_or(self.max_edits);
+
+        self.boost = if edits == 0 {
+            1.0
+        } else {
+            // Count code points by skipping UTF-8 continuation bytes.
+            let length = term.iter().filter(|&&b| b & 0xc0 != 0x80).count();
+            let min_length = length.min(self.term_length).max(1);
+            (1.0 - edits as f32 / min_length as f32).max(0.0)
+        };
+    }
+}
+
+impl Debug for FuzzyTermsEnum<'_> {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        f.debug_struct("FuzzyTermsEnum")
+            .field("inner", &self.inner)
+            .field("max_edits", &self.max_edits)
+            .field("prefix_length", &self.prefix_length)
+            .field("term_length", &self.term_length)
+            .finish()
+    }
+}
+
+impl TermsEnum for FuzzyTermsEnum<'_> {
+    fn next(&mut self) -> BoxResult<Option<&[u8]>> {
+        if self.inner.next()?.is_none() {
+            return Ok(None);
+        }
+
+        self.update_boost();
+        Ok(Some(self.inner.term()))
+    }
+
+    fn term(&self) -> &[u8] {
+        self.inner.term()
+    }
+
+    fn seek_ceil(&mut self, target: &[u8]) -> BoxResult<SeekStatus> {
+        let status = self.inner.seek_ceil(target)?;
+        if status != SeekStatus::End {
+            self.update_boost();
+        }
+
+        Ok(status)
+    }
+
+    fn doc_freq(&self) -> BoxResult<u32> {
+        self.inner.doc_freq()
+    }
+
+    fn total_term_freq(&self) -> BoxResult<u64> {
+        self.inner.total_term_freq()
+    }
+
+    fn postings(&self) -> BoxResult<Box<dyn PostingsEnum>> {
+        self.inner.postings()
+    }
+
+    #[inline]
+    fn boost(&self) -> f32 {
+        self.boost
+    }
+}
@@ -0,0 +1,134 @@
+use {
+    crate::{
+        index::Term,
+        search::{CollectionStatistics, IndexSearcher, TermStatistics},
+        BoxResult,
+    },
+    std::collections::HashMap,
+};
+
+/// Field and term statistics combined across several indexes, such as the shards of a distributed index.
+///
+/// Each shard scores with its own statistics by default, so the same document can score differently depending on
+/// the shard it lives in, and scores from different shards can't be compared when merging hits with
+/// [crate::search::TopDocs::merge]. Gathering the statistics of every shard into a `GlobalStatistics` and handing it
+/// to each shard's searcher with [IndexSearcher::set_global_statistics] makes every shard score as if it held the
+/// whole index.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct GlobalStatistics {
+    collection_stats: HashMap<String, CollectionStatistics>,
+    term_stats: HashMap<Term, TermStatistics>,
+}
+
+impl GlobalStatistics {
+    /// Creates empty statistics.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the statistics of a field in one shard.
+    pub fn add_collection_statistics(&mut self, stats: &CollectionStatistics) -> BoxResult<&mut Self> {
+        match self.collection_stats.get_mut(&stats.field) {
+            Some(existing) => existing.merge(stats)?,
+            None => {
+                self.collection_stats.insert(stats.field.clone(), stats.clone());
+            }
+        }
+        Ok(self)
+    }
+
+    /// Adds the statistics of a term in one shard.
+    pub fn add_term_statistics(&mut self, term: &Term, stats: &TermStatistics) -> BoxResult<&mut Self> {
+        match self.term_stats.get_mut(term) {
+            Some(existing) => existing.merge(stats)?,
+            None => {
+                self.term_stats.insert(term.clone(), stats.clone());
+            }
+        }
+        Ok(self)
+    }
+
+    /// Adds the statistics of one shard's searcher for the given terms and their fields. The searcher's own
+    /// statistics are used, even if it has global statistics set.
+    pub fn add_searcher(&mut self, searcher: &IndexSearcher, terms: &[Term]) -> BoxResult<&mut Self> {
+        let mut fields: Vec<&str> = terms.iter().map(Term::field).collect();
+        fields.sort_unstable();
+        fields.dedup();
+        for field in fields {
+            if let Some(stats) = searcher.local_collection_statistics(field)? {
+                self.add_collection_statistics(&stats)?;
+            }
+        }
+
+        for term in terms {
+            if let Some(stats) = searcher.local_term_statistics(term)? {
+                self.add_term_statistics(term, &stats)?;
+            }
+        }
+        Ok(self)
+    }
+
+    /// Returns the combined statistics of a field, or `None` if no shard reported the field.
+    #[inline]
+    pub fn collection_statistics(&self, field: &str) -> Option<&CollectionStatistics> {
+        self.collection_stats.get(field)
+    }
+
+    /// Returns the combined statistics of a term, or `None` if no shard reported the term.
+    #[inline]
+    pub fn term_statistics(&self, term: &Term) -> Option<&TermStatistics> {
+        self.term_stats.get(term)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{
+            index::Term,
+            search::{test_util::body_searcher, GlobalStatistics, TermQuery, TopDocs},
+        },
+        pretty_assertions::assert_eq,
+        std::sync::Arc,
+    };
+
+    #[test]
+    fn test_global_statistics() {
+        let first = ["rust search", "rust", "other things"];
+        let second = ["search engine", "rust rust engine"];
+        let whole = body_searcher(&[&first[..], &second[..]].concat());
+        let mut shards = [body_searcher(&first), body_searcher(&second)];
+        let term = Term::from_text("body", "rust");
+        let query = TermQuery::new(term.clone());
+
+        // Each shard scores with its own statistics, so its scores differ from those of the whole index.
+        let expected = whole.search(&query, 10).unwrap();
+        let scores = |top_docs: &TopDocs| top_docs.score_docs.iter().map(|sd| sd.score).collect::<Vec<_>>();
+        let local = shards.iter().map(|shard| shard.search(&query, 10).unwrap()).collect::<Vec<_>>();
+        assert_ne!(scores(&TopDocs::merge(10, &local, true)), scores(&expected));
+
+        let mut global = GlobalStatistics::new();
+        for shard in &shards {
+            global.add_searcher(shard, std::slice::from_ref(&term)).unwrap();
+        }
+        assert_eq!(global.collection_statistics("body"), whole.collection_statistics("body").unwrap().as_ref());
+        assert_eq!(global.term_statistics(&term), whole.term_statistics(&term).unwrap().as_ref());
+        assert!(global.term_statistics(&Term::from_text("body", "missing")).is_none());
+
+        let global = Arc::new(global);
+        for shard in &mut shards {
+            shard.set_global_statistics(Some(global.clone()));
+        }
+        let results = shards.iter().map(|shard| shard.search(&query, 10).unwrap()).collect::<Vec<_>>();
+        let merged = TopDocs::merge(10, &results, true);
+        assert_eq!(scores(&merged), scores(&expected));
+        let hits = merged.score_docs.iter().map(|sd| (sd.shard_index, sd.doc)).collect::<Vec<_>>();
+        assert_eq!(hits, vec![(Some(0), 1), (Some(1), 1), (Some(0), 0)]);
+
+        // Merging statistics of different fields or terms is refused.
+        let mut stats = whole.collection_statistics("body").unwrap().unwrap();
+        let mut other = stats.clone();
+        other.field = "title".to_string();
+        assert!(stats.merge(&other).is_err());
+    }
+}
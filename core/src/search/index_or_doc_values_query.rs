@@ -0,0 +1,225 @@
+use {
+    crate::{
+        index::LeafReaderContext,
+        search::{Explanation, IndexSearcher, Query, ScoreMode, Scorer, ScorerSupplier, Weight},
+        BoxResult,
+    },
+    std::{
+        fmt::{Display, Formatter, Result as FmtResult},
+        sync::Arc,
+    },
+};
+
+/// Doc values are assumed to cost this many times as much per document as the index, since each candidate's value
+/// must be read and compared while the index only visits the matches.
+const DOC_VALUES_COST_FACTOR: u64 = 8;
+
+/// A query that matches the same documents two ways, and picks the cheaper one for each segment: an index query,
+/// such as a [crate::search::TermInSetQuery], which finds the matches by themselves, and a doc values query, such as
+/// a [crate::search::NumericDocValuesRangeQuery], which checks the value of each candidate document.
+///
+/// On its own, or when it leads a conjunction, the index query is used. When it is intersected with a clause that
+/// matches far fewer documents (see [ScorerSupplier]), only that clause's candidates need checking, and the doc
+/// values query is used if the index query's cost is more than [DOC_VALUES_COST_FACTOR] times theirs.
+///
+/// Both queries must match the same documents, and should produce the same scores.
+#[derive(Clone, Debug)]
+pub struct IndexOrDocValuesQuery {
+    index_query: Arc<dyn Query>,
+    dv_query: Arc<dyn Query>,
+}
+
+impl IndexOrDocValuesQuery {
+    /// Creates a query running either `index_query` or `dv_query`, which must match the same documents.
+    pub fn new(index_query: Arc<dyn Query>, dv_query: Arc<dyn Query>) -> Self {
+        Self {
+            index_query,
+            dv_query,
+        }
+    }
+
+    /// Returns the query that finds the matches with the index.
+    #[inline]
+    pub fn index_query(&self) -> &Arc<dyn Query> {
+        &self.index_query
+    }
+
+    /// Returns the query that checks the doc values of candidate documents.
+    #[inline]
+    pub fn dv_query(&self) -> &Arc<dyn Query> {
+        &self.dv_query
+    }
+}
+
+impl Query for IndexOrDocValuesQuery {
+    fn create_weight(&self, searcher: &IndexSearcher, score_mode: ScoreMode, boost: f32) -> BoxResult<Box<dyn Weight>> {
+        Ok(Box::new(IndexOrDocValuesWeight {
+            index_weight: self.index_query.create_weight(searcher, score_mode, boost)?,
+            dv_weight: self.dv_query.create_weight(searcher, score_mode, boost)?,
+        }))
+    }
+
+    fn rewrite(&self, searcher: &IndexSearcher) -> BoxResult<Option<Arc<dyn Query>>> {
+        let index_query = searcher.rewrite(self.index_query.as_ref())?;
+        let dv_query = searcher.rewrite(self.dv_query.as_ref())?;
+        if index_query.is_none() && dv_query.is_none() {
+            return Ok(None);
+        }
+
+        Ok(Some(Arc::new(Self::new(
+            index_query.unwrap_or_else(|| self.index_query.clone()),
+            dv_query.unwrap_or_else(|| self.dv_query.clone()),
+        ))))
+    }
+}
+
+impl Display for IndexOrDocValuesQuery {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "IndexOrDocValuesQuery(indexQuery={}, dvQuery={})", self.index_query, self.dv_query)
+    }
+}
+
+#[derive(Debug)]
+struct IndexOrDocValuesWeight {
+    index_weight: Box<dyn Weight>,
+    dv_weight: Box<dyn Weight>,
+}
+
+impl Weight for IndexOrDocValuesWeight {
+    fn scorer(&self, context: &LeafReaderContext) -> BoxResult<Option<Box<dyn Scorer>>> {
+        // The scorer leads iteration itself.
+        self.scorer_supplier(context)?.map(|supplier| supplier.get(u64::MAX)).transpose()
+    }
+
+    fn scorer_supplier(&self, context: &LeafReaderContext) -> BoxResult<Option<Box<dyn ScorerSupplier + '_>>> {
+        let Some(index) = self.index_weight.scorer_supplier(context)? else {
+            return Ok(None);
+        };
+        let Some(dv) = self.dv_weight.scorer_supplier(context)? else {
+            return Ok(None);
+        };
+
+        Ok(Some(Box::new(IndexOrDocValuesScorerSupplier {
+            index,
+            dv,
+        })))
+    }
+
+    fn explain(&self, context: &LeafReaderContext, doc: u32) -> BoxResult<Explanation> {
+        self.index_weight.explain(context, doc)
+    }
+
+    fn scorer_at(&self, context: &LeafReaderContext, doc: u32) -> BoxResult<Option<Box<dyn Scorer>>> {
+        // Only one document is checked.
+        let Some(supplier) = self.scorer_supplier(context)? else {
+            return Ok(None);
+        };
+
+        let mut scorer = supplier.get(1)?;
+        Ok((scorer.advance(doc)? == doc).then_some(scorer))
+    }
+}
+
+#[derive(Debug)]
+struct IndexOrDocValuesScorerSupplier<'a> {
+    index: Box<dyn ScorerSupplier + 'a>,
+    dv: Box<dyn ScorerSupplier + 'a>,
+}
+
+impl ScorerSupplier for IndexOrDocValuesScorerSupplier<'_> {
+    fn get(self: Box<Self>, lead_cost: u64) -> BoxResult<Box<dyn Scorer>> {
+        if self.index.cost() / DOC_VALUES_COST_FACTOR <= lead_cost {
+            self.index.get(lead_cost)
+        } else {
+            self.dv.get(lead_cost)
+        }
+    }
+
+    #[inline]
+    fn cost(&self) -> u64 {
+        self.index.cost()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{
+            document::{Document, Field, NumericDocValuesField, Store},
+            index::Term,
+            search::{
+                test_util, BooleanQuery, IndexOrDocValuesQuery, IndexSearcher, Occur, Query, ScoreMode, TermInSetQuery,
+                TermQuery,
+            },
+        },
+        pretty_assertions::assert_eq,
+        std::sync::Arc,
+    };
+
+    fn searcher() -> IndexSearcher {
+        test_util::searcher([(0..1_000).map(|i| {
+            Document::from_iter([
+                Field::string("id", format!("{i:04}"), Store::No),
+                Field::string("price", format!("{:03}", i % 100), Store::No),
+                NumericDocValuesField::new_field("price", i % 100),
+            ])
+        })])
+    }
+
+    /// Matches prices from 20 to 59 with the terms index or the doc values.
+    fn price_query() -> IndexOrDocValuesQuery {
+        IndexOrDocValuesQuery::new(
+            Arc::new(TermInSetQuery::new("price", (20..60).map(|price| format!("{price:03}")))),
+            Arc::new(NumericDocValuesField::new_slow_range_query("price", 20, 59)),
+        )
+    }
+
+    #[test]
+    fn test_index_or_doc_values_query() {
+        let searcher = searcher();
+        let query = price_query();
+        assert_eq!(
+            query.to_string(),
+            "IndexOrDocValuesQuery(indexQuery=price:(020 021 022 023 024 025 026 027 028 029 030 031 032 033 034 035 \
+             036 037 038 039 040 041 042 043 044 045 046 047 048 049 050 051 052 053 054 055 056 057 058 059), \
+             dvQuery=price:[20 TO 59])"
+        );
+        assert_eq!(searcher.count(&query).unwrap(), 400);
+
+        // The doc values query's scorer checks each candidate's value in a second phase; the index query's doesn't.
+        let weight = query.create_weight(&searcher, ScoreMode::CompleteNoScores, 1.0).unwrap();
+        let leaf = &searcher.reader().leaves()[0];
+        let supplier = weight.scorer_supplier(leaf).unwrap().unwrap();
+        assert_eq!(supplier.cost(), 400);
+        assert!(supplier.get(50).unwrap().two_phase_iterator().is_none());
+        let supplier = weight.scorer_supplier(leaf).unwrap().unwrap();
+        assert!(supplier.get(49).unwrap().two_phase_iterator().is_some());
+
+        // Leading iteration, the index is used.
+        assert!(weight.scorer(leaf).unwrap().unwrap().two_phase_iterator().is_none());
+        assert!(weight.scorer_at(leaf, 25).unwrap().unwrap().two_phase_iterator().is_some());
+        assert!(weight.scorer_at(leaf, 60).unwrap().is_none());
+        assert!(searcher.explain(&query, 125).unwrap().is_match());
+    }
+
+    #[test]
+    fn test_conjunction() {
+        let searcher = searcher();
+
+        // A selective clause leads, so prices are checked with doc values; a broad one doesn't.
+        for (ids, expected) in [(vec![21, 77, 530], 2), ((0..1_000).step_by(2).collect(), 200)] {
+            let ids: Vec<String> = ids.into_iter().map(|id: u32| format!("{id:04}")).collect();
+            let query = BooleanQuery::builder()
+                .add(Arc::new(TermInSetQuery::new("id", &ids)), Occur::Must)
+                .add(Arc::new(price_query()), Occur::Filter)
+                .build();
+            assert_eq!(searcher.count(&query).unwrap(), expected);
+        }
+
+        let query = BooleanQuery::builder()
+            .add(Arc::new(TermQuery::new(Term::from_text("id", "0042"))), Occur::Must)
+            .add(Arc::new(price_query()), Occur::Filter)
+            .build();
+        assert_eq!(searcher.count(&query).unwrap(), 1);
+    }
+}
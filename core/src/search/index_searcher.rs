@@ -0,0 +1,731 @@
+use {
+    crate::{
+        document::Document,
+        index::{sub_index, IndexReader, LeafReaderContext, Term},
+        metrics::{
+            MetricsRecorder, SEARCHER_SEGMENTS, SEARCH_COUNT, SEARCH_ERRORS, SEARCH_LATENCY_SECONDS, SEARCH_TIMED_OUT,
+        },
+        search::{
+            check_timeout, is_collection_terminated, is_search_aborted, BM25Similarity, CircuitBreaker,
+            CollectionStatistics, Collector, CollectorManager, Explanation, FieldDoc, GlobalStatistics,
+            LiveDocsLeafCollector, LruQueryCache, Query, QueryMemoryTracker, QueryTimeout, QueueSizeBasedExecutor,
+            RewritePipeline, ScoreDoc, ScoreMode, SearchPriority, Similarity, Sort, TermStatistics,
+            TimeLimitingBulkScorer, TimeLimitingLeafCollector, TopDocs, TopFieldCollector, TopFieldDocs,
+            TopScoreDocCollector, TotalHitCountCollector, TotalHitsThreshold, Weight, NO_MORE_DOCS,
+        },
+        BoxResult, LuceneError,
+    },
+    std::{
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc, Mutex,
+        },
+        thread,
+        time::Instant,
+    },
+};
+
+/// The most documents a slice holds, unless a single segment is larger.
+const MAX_DOCS_PER_SLICE: u64 = 250_000;
+
+/// The most segments a slice holds.
+const MAX_SEGMENTS_PER_SLICE: usize = 5;
+
+/// Executes queries against an [IndexReader].
+///
+/// A searcher is cheap to clone and safe to share between threads. Scores are computed with [BM25Similarity] unless
+/// another similarity is configured with [IndexSearcher::set_similarity].
+///
+/// Searches through a [CollectorManager] divide the segments into slices, which are searched on separate threads if
+/// the searcher is concurrent (see [IndexSearcher::set_concurrent]). Multi-term queries rewritten into their terms
+/// also collect them per slice, concurrently if the searcher is (see [IndexSearcher::map_slices]). By default, a
+/// concurrent search starts a thread per slice; with a [QueueSizeBasedExecutor] (see [IndexSearcher::set_executor]),
+/// the slices of every search sharing the executor run on a bounded number of threads, where searches with a
+/// [SearchPriority::Background] priority give way to interactive ones.
+///
+/// If a [QueryTimeout] is set (see [IndexSearcher::set_timeout]), searches stop once it expires and return the hits
+/// collected so far; [IndexSearcher::timed_out] then reports that the results are partial.
+///
+/// Searches can also be given a memory budget (see [IndexSearcher::set_query_memory_limit] and
+/// [IndexSearcher::set_circuit_breaker]). The memory held by collectors, and by large structures that queries build
+/// while rewriting and scoring, is reserved against it; a search that would exceed it fails with
+/// [LuceneError::MemoryLimitExceeded] rather than returning partial results.
+///
+/// Each search gets its own memory tracker and timeout flag, which its queries and collectors see through the
+/// searcher passed to [Query::create_weight], so concurrent searches through a shared searcher don't interfere.
+/// [IndexSearcher::timed_out] and [IndexSearcher::memory_tracker] report on the most recent search to finish; to
+/// inspect a particular search while others run, search through a clone of the searcher.
+///
+/// Before a query is executed, it is rewritten into primitive queries and then optimized by a [RewritePipeline] (see
+/// [IndexSearcher::set_rewrite_pipeline]), which simplifies the query tree as a whole.
+///
+/// With a [LruQueryCache] (see [IndexSearcher::set_query_cache]), the matches of queries whose scores aren't needed,
+/// such as [crate::search::Occur::Filter] clauses, are cached per segment and reused by later searches.
+///
+/// With the `tracing` feature enabled, searches emit `tracing` spans at debug level for the search as a whole, query
+/// rewriting, weight creation, and the scoring of each segment, identified by its name (see
+/// [crate::index::LeafReader::segment_name]), so slow phases of a query can be profiled.
+#[derive(Debug)]
+pub struct IndexSearcher {
+    reader: Arc<dyn IndexReader>,
+    similarity: Arc<dyn Similarity>,
+    concurrent: bool,
+    executor: Option<Arc<QueueSizeBasedExecutor>>,
+    priority: SearchPriority,
+    timeout: Option<Arc<dyn QueryTimeout>>,
+    global_statistics: Option<Arc<GlobalStatistics>>,
+    metrics: Option<Arc<dyn MetricsRecorder>>,
+    query_memory_limit: Option<usize>,
+    circuit_breaker: Option<Arc<CircuitBreaker>>,
+    /// Whether the running search, or the most recent one to finish, timed out.
+    timed_out: AtomicBool,
+    /// The tracker of the running search, or of the most recent one to finish.
+    memory_tracker: Mutex<Arc<QueryMemoryTracker>>,
+    rewrite_pipeline: Arc<RewritePipeline>,
+    query_cache: Option<Arc<LruQueryCache>>,
+}
+
+impl Clone for IndexSearcher {
+    fn clone(&self) -> Self {
+        Self {
+            reader: self.reader.clone(),
+            similarity: self.similarity.clone(),
+            concurrent: self.concurrent,
+            executor: self.executor.clone(),
+            priority: self.priority,
+            timeout: self.timeout.clone(),
+            global_statistics: self.global_statistics.clone(),
+            metrics: self.metrics.clone(),
+            query_memory_limit: self.query_memory_limit,
+            circuit_breaker: self.circuit_breaker.clone(),
+            timed_out: AtomicBool::new(self.timed_out()),
+            memory_tracker: Mutex::new(Arc::new(self.new_memory_tracker())),
+            rewrite_pipeline: self.rewrite_pipeline.clone(),
+            query_cache: self.query_cache.clone(),
+        }
+    }
+}
+
+impl IndexSearcher {
+    /// Creates a searcher over the given reader.
+    pub fn new(reader: Arc<dyn IndexReader>) -> Self {
+        Self {
+            reader,
+            similarity: Arc::new(BM25Similarity::default()),
+            concurrent: false,
+            executor: None,
+            priority: SearchPriority::default(),
+            timeout: None,
+            global_statistics: None,
+            metrics: None,
+            query_memory_limit: None,
+            circuit_breaker: None,
+            timed_out: AtomicBool::new(false),
+            memory_tracker: Mutex::default(),
+            rewrite_pipeline: Arc::new(RewritePipeline::standard()),
+            query_cache: None,
+        }
+    }
+
+    /// Returns the reader being searched.
+    #[inline]
+    pub fn reader(&self) -> &dyn IndexReader {
+        self.reader.as_ref()
+    }
+
+    /// Returns the segments of the reader being searched.
+    #[inline]
+    pub fn leaves(&self) -> &[LeafReaderContext] {
+        self.reader.leaves()
+    }
+
+    /// Returns the similarity used for scoring.
+    #[inline]
+    pub fn similarity(&self) -> &Arc<dyn Similarity> {
+        &self.similarity
+    }
+
+    /// Sets the similarity used for scoring.
+    pub fn set_similarity(&mut self, similarity: Arc<dyn Similarity>) {
+        self.similarity = similarity;
+    }
+
+    /// Returns the statistics shared with other shards, if any.
+    #[inline]
+    pub fn global_statistics(&self) -> Option<&Arc<GlobalStatistics>> {
+        self.global_statistics.as_ref()
+    }
+
+    /// Sets statistics combined across the shards of a distributed index, which are used for scoring instead of this
+    /// searcher's own statistics for the fields and terms they cover.
+    pub fn set_global_statistics(&mut self, global_statistics: Option<Arc<GlobalStatistics>>) {
+        self.global_statistics = global_statistics;
+    }
+
+    /// Returns the recorder that search metrics are reported to, if any.
+    #[inline]
+    pub fn metrics(&self) -> Option<&Arc<dyn MetricsRecorder>> {
+        self.metrics.as_ref()
+    }
+
+    /// Sets the recorder that search metrics are reported to: the number of searches, errors and timeouts, their
+    /// latency, and the number of segments searched.
+    pub fn set_metrics(&mut self, metrics: Option<Arc<dyn MetricsRecorder>>) {
+        self.metrics = metrics;
+    }
+
+    /// Indicates whether slices are searched concurrently.
+    #[inline]
+    pub fn is_concurrent(&self) -> bool {
+        self.concurrent
+    }
+
+    /// Sets whether slices are searched concurrently, with one thread per slice.
+    pub fn set_concurrent(&mut self, concurrent: bool) {
+        self.concurrent = concurrent;
+    }
+
+    /// Returns the executor running the slices of concurrent searches, if any.
+    #[inline]
+    pub fn executor(&self) -> Option<&Arc<QueueSizeBasedExecutor>> {
+        self.executor.as_ref()
+    }
+
+    /// Sets the executor running the slices of concurrent searches, which is usually shared by every searcher in the
+    /// process to bound the threads searching at once. Without one, a concurrent search starts a thread per slice.
+    /// This has no effect unless the searcher is concurrent.
+    pub fn set_executor(&mut self, executor: Option<Arc<QueueSizeBasedExecutor>>) {
+        self.executor = executor;
+    }
+
+    /// Returns the priority of this searcher's tasks on its executor.
+    #[inline]
+    pub fn priority(&self) -> SearchPriority {
+        self.priority
+    }
+
+    /// Sets the priority of this searcher's tasks on its executor. This is [SearchPriority::Interactive] by default;
+    /// searchers for heavy analytics queries can be cloned with [SearchPriority::Background] so that they don't delay
+    /// user-facing searches.
+    pub fn set_priority(&mut self, priority: SearchPriority) {
+        self.priority = priority;
+    }
+
+    /// Returns the timeout applied to searches, if any.
+    #[inline]
+    pub fn timeout(&self) -> Option<&Arc<dyn QueryTimeout>> {
+        self.timeout.as_ref()
+    }
+
+    /// Sets the timeout applied to searches. The timeout is polled while documents are scored and collected; once it
+    /// expires, the search stops and returns what has been collected so far.
+    pub fn set_timeout(&mut self, timeout: Option<Arc<dyn QueryTimeout>>) {
+        self.timeout = timeout;
+    }
+
+    /// Indicates whether the most recent search through this searcher to finish stopped early because its timeout
+    /// expired, in which case its results are partial.
+    #[inline]
+    pub fn timed_out(&self) -> bool {
+        self.timed_out.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of bytes a single search may reserve, if limited.
+    #[inline]
+    pub fn query_memory_limit(&self) -> Option<usize> {
+        self.query_memory_limit
+    }
+
+    /// Sets the number of bytes a single search may reserve. A search that would exceed it fails with
+    /// [LuceneError::MemoryLimitExceeded].
+    pub fn set_query_memory_limit(&mut self, limit: Option<usize>) {
+        self.query_memory_limit = limit;
+    }
+
+    /// Returns the breaker shared with other searches, if any.
+    #[inline]
+    pub fn circuit_breaker(&self) -> Option<&Arc<CircuitBreaker>> {
+        self.circuit_breaker.as_ref()
+    }
+
+    /// Sets a breaker whose budget is shared with other searches, typically every searcher in the process. Searches
+    /// reserve memory against it as well as against their own limit.
+    pub fn set_circuit_breaker(&mut self, breaker: Option<Arc<CircuitBreaker>>) {
+        self.circuit_breaker = breaker;
+    }
+
+    /// Returns the tracker for the memory reserved by the search running on this searcher, or by the most recent
+    /// search to finish. Queries reserve the memory of the large structures they build through the tracker of the
+    /// searcher their weights are created with; its peak reports how much the search needed.
+    pub fn memory_tracker(&self) -> Arc<QueryMemoryTracker> {
+        self.memory_tracker.lock().unwrap().clone()
+    }
+
+    fn new_memory_tracker(&self) -> QueryMemoryTracker {
+        QueryMemoryTracker::new(self.query_memory_limit, self.circuit_breaker.clone())
+    }
+
+    /// Returns a copy of this searcher with its own memory tracker and timeout flag, which a single search runs on.
+    fn fork(&self) -> Self {
+        Self {
+            timed_out: AtomicBool::new(false),
+            ..self.clone()
+        }
+    }
+
+    /// Publishes the outcome of a search that ran on `search`, a [IndexSearcher::fork] of this searcher.
+    fn finish_search(&self, search: &Self) {
+        let memory_tracker = search.memory_tracker();
+        memory_tracker.release_all();
+        self.timed_out.store(search.timed_out(), Ordering::Relaxed);
+        *self.memory_tracker.lock().unwrap() = memory_tracker;
+    }
+
+    /// Returns the passes run over query trees by [IndexSearcher::optimize].
+    #[inline]
+    pub fn rewrite_pipeline(&self) -> &Arc<RewritePipeline> {
+        &self.rewrite_pipeline
+    }
+
+    /// Sets the passes run over query trees by [IndexSearcher::optimize]. This is [RewritePipeline::standard] by
+    /// default; custom rules are added as [crate::search::QueryVisitor]s.
+    pub fn set_rewrite_pipeline(&mut self, rewrite_pipeline: Arc<RewritePipeline>) {
+        self.rewrite_pipeline = rewrite_pipeline;
+    }
+
+    /// Returns the cache of the matches of queries whose scores aren't needed, if any.
+    #[inline]
+    pub fn query_cache(&self) -> Option<&Arc<LruQueryCache>> {
+        self.query_cache.as_ref()
+    }
+
+    /// Sets the cache of the matches of queries whose scores aren't needed. There is none by default. A cache may be
+    /// shared by searchers over different readers; its entries are kept per segment.
+    pub fn set_query_cache(&mut self, query_cache: Option<Arc<LruQueryCache>>) {
+        self.query_cache = query_cache;
+    }
+
+    /// Groups the segments into the slices searched by [IndexSearcher::search_with_manager]. Consecutive segments are
+    /// grouped until a slice holds 250,000 documents or 5 segments.
+    pub fn slices(&self) -> Vec<&[LeafReaderContext]> {
+        let leaves = self.leaves();
+        let mut slices = Vec::new();
+        let mut start = 0;
+        let mut docs = 0;
+
+        for (i, leaf) in leaves.iter().enumerate() {
+            docs += leaf.reader().max_doc() as u64;
+            if docs >= MAX_DOCS_PER_SLICE || i + 1 - start >= MAX_SEGMENTS_PER_SLICE {
+                slices.push(&leaves[start..=i]);
+                start = i + 1;
+                docs = 0;
+            }
+        }
+
+        if start < leaves.len() {
+            slices.push(&leaves[start..]);
+        }
+
+        slices
+    }
+
+    /// Calls `f` on each slice of segments (see [IndexSearcher::slices]), returning the results in slice order. As for
+    /// searches, slices are processed on separate threads if the searcher is concurrent. Queries use this to spread
+    /// expensive work across segments, such as collecting the terms of a multi-term query while it's rewritten.
+    pub fn map_slices<T, F>(&self, f: F) -> BoxResult<Vec<T>>
+    where
+        T: Send,
+        F: Fn(&[LeafReaderContext]) -> BoxResult<T> + Sync,
+    {
+        let slices = self.slices();
+        if !self.concurrent || slices.len() <= 1 {
+            return slices.into_iter().map(&f).collect();
+        }
+
+        let f = &f;
+        self.run_concurrently(slices.into_iter().map(|slice| move || f(slice)).collect())
+    }
+
+    /// Runs `tasks` on the executor, or on a thread each if there is none, returning their results in order or the
+    /// first error once they've all finished.
+    fn run_concurrently<T, F>(&self, tasks: Vec<F>) -> BoxResult<Vec<T>>
+    where
+        T: Send,
+        F: FnOnce() -> BoxResult<T> + Send,
+    {
+        // Tasks run on other threads are traced within the caller's span.
+        #[cfg(feature = "tracing")]
+        let parent = tracing::Span::current();
+        #[cfg(feature = "tracing")]
+        let tasks: Vec<_> = tasks
+            .into_iter()
+            .map(|task| {
+                let parent = parent.clone();
+                move || {
+                    let _span = parent.entered();
+                    task()
+                }
+            })
+            .collect();
+
+        if let Some(executor) = &self.executor {
+            return executor.execute(self.priority, tasks).into_iter().collect();
+        }
+
+        thread::scope(|scope| {
+            let handles: Vec<_> = tasks.into_iter().map(|task| scope.spawn(task)).collect();
+
+            // Join every thread before reporting the first error.
+            let results: Vec<_> = handles
+                .into_iter()
+                .map(|handle| handle.join().unwrap_or_else(|panic| std::panic::resume_unwind(panic)))
+                .collect();
+            results.into_iter().collect()
+        })
+    }
+
+    /// Rewrites the query until it can't be rewritten further, returning `None` if it was already primitive.
+    pub fn rewrite(&self, query: &dyn Query) -> BoxResult<Option<Arc<dyn Query>>> {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!("rewrite", query = %query).entered();
+
+        let mut rewritten: Option<Arc<dyn Query>> = None;
+        loop {
+            let current = rewritten.as_deref().unwrap_or(query);
+            match current.rewrite(self)? {
+                Some(next) => rewritten = Some(next),
+                None => return Ok(rewritten),
+            }
+        }
+    }
+
+    /// Rewrites the query (see [IndexSearcher::rewrite]) and runs the rewrite pipeline over the result, repeating
+    /// both until neither changes the query. This returns `None` if the query was already optimal.
+    pub fn optimize(&self, query: &dyn Query) -> BoxResult<Option<Arc<dyn Query>>> {
+        let mut optimized = self.rewrite(query)?;
+        loop {
+            let current = optimized.as_deref().unwrap_or(query);
+            match self.rewrite_pipeline.rewrite(current, self)? {
+                Some(next) => optimized = Some(self.rewrite(next.as_ref())?.unwrap_or(next)),
+                None => return Ok(optimized),
+            }
+        }
+    }
+
+    /// Optimizes the query (see [IndexSearcher::optimize]) and creates its weight.
+    pub fn create_weight(&self, query: &dyn Query, score_mode: ScoreMode, boost: f32) -> BoxResult<Box<dyn Weight>> {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!("create_weight", query = %query, ?score_mode, boost).entered();
+
+        let optimized = self.optimize(query)?;
+        let query = optimized.as_deref().unwrap_or(query);
+        let weight = query.create_weight(self, score_mode, boost)?;
+        match &self.query_cache {
+            Some(cache) if !score_mode.needs_scores() => Ok(cache.do_cache(query, weight)),
+            _ => Ok(weight),
+        }
+    }
+
+    /// Returns the top `n` hits for the query.
+    pub fn search(&self, query: &dyn Query, n: usize) -> BoxResult<TopDocs> {
+        let mut collector = TopScoreDocCollector::new(n);
+        self.search_with_collector(query, &mut collector)?;
+        Ok(collector.top_docs())
+    }
+
+    /// Returns the top `n` hits for the query, counting hits exactly only up to `total_hits_threshold`. Past the
+    /// threshold, documents that can't make it into the top hits may be skipped and the total hit count becomes a
+    /// lower bound, which can make searches over many matches much faster.
+    pub fn search_with_total_hits_threshold(
+        &self,
+        query: &dyn Query,
+        n: usize,
+        total_hits_threshold: TotalHitsThreshold,
+    ) -> BoxResult<TopDocs> {
+        let mut collector = TopScoreDocCollector::new(n);
+        collector.set_total_hits_threshold(total_hits_threshold);
+        self.search_with_collector(query, &mut collector)?;
+        Ok(collector.top_docs())
+    }
+
+    /// Returns the next `n` hits for the query after `after`, the last hit of the previous page. Hits are ordered by
+    /// descending score, then by document id, so pages neither overlap nor skip hits as long as the index doesn't
+    /// change. The total hit count covers every hit, not just those after `after`.
+    pub fn search_after(&self, after: &ScoreDoc, query: &dyn Query, n: usize) -> BoxResult<TopDocs> {
+        let mut collector = TopScoreDocCollector::new(n);
+        collector.set_after(Some(*after));
+        self.search_with_collector(query, &mut collector)?;
+        Ok(collector.top_docs())
+    }
+
+    /// Returns the top `n` hits for the query in the order given by `sort`.
+    pub fn search_with_sort(&self, query: &dyn Query, n: usize, sort: &Sort) -> BoxResult<TopFieldDocs> {
+        let mut collector = TopFieldCollector::new(sort, n)?;
+        self.search_with_collector(query, &mut collector)?;
+        Ok(collector.top_docs())
+    }
+
+    /// Returns the next `n` hits for the query in the order given by `sort`, after `after`, the last hit of the
+    /// previous page. Ties on every sort field are broken by document id.
+    pub fn search_after_with_sort(
+        &self,
+        after: &FieldDoc,
+        query: &dyn Query,
+        n: usize,
+        sort: &Sort,
+    ) -> BoxResult<TopFieldDocs> {
+        let mut collector = TopFieldCollector::new(sort, n)?;
+        collector.set_after(Some(after.clone()))?;
+        self.search_with_collector(query, &mut collector)?;
+        Ok(collector.top_docs())
+    }
+
+    /// Returns the number of documents matching the query.
+    pub fn count(&self, query: &dyn Query) -> BoxResult<u64> {
+        let mut collector = TotalHitCountCollector::new();
+        self.search_with_collector(query, &mut collector)?;
+        Ok(collector.total_hits())
+    }
+
+    /// Passes every document matching the query to the collector.
+    pub fn search_with_collector(&self, query: &dyn Query, collector: &mut dyn Collector) -> BoxResult<()> {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!("search", query = %query).entered();
+
+        let start = self.metrics.as_ref().map(|_| Instant::now());
+        let search = self.fork();
+        let result = search
+            .create_weight(query, collector.score_mode(), 1.0)
+            .and_then(|weight| search.search_leaves(weight.as_ref(), search.leaves(), collector));
+        self.finish_search(&search);
+        search.record_search(start, &result);
+        result
+    }
+
+    /// Searches each slice of the index with its own collector from `manager`, then reduces the collectors into the
+    /// result. Slices are searched on separate threads if the searcher is concurrent.
+    pub fn search_with_manager<M: CollectorManager>(&self, query: &dyn Query, manager: &M) -> BoxResult<M::Result> {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!("search", query = %query).entered();
+
+        let start = self.metrics.as_ref().map(|_| Instant::now());
+        let search = self.fork();
+        let result = search.search_slices(query, manager);
+        self.finish_search(&search);
+        search.record_search(start, &result);
+        result
+    }
+
+    fn search_slices<M: CollectorManager>(&self, query: &dyn Query, manager: &M) -> BoxResult<M::Result> {
+        let slices = self.slices();
+        let mut collectors = Vec::with_capacity(slices.len().max(1));
+        collectors.push(manager.new_collector()?);
+        for _ in 1..slices.len() {
+            collectors.push(manager.new_collector()?);
+        }
+
+        let weight = self.create_weight(query, collectors[0].score_mode(), 1.0)?;
+        let weight = weight.as_ref();
+
+        if self.concurrent && slices.len() > 1 {
+            let tasks: Vec<_> = slices
+                .iter()
+                .zip(collectors.iter_mut())
+                .map(|(slice, collector)| move || self.search_leaves(weight, slice, collector))
+                .collect();
+            self.run_concurrently(tasks)?;
+        } else {
+            for (slice, collector) in slices.iter().zip(collectors.iter_mut()) {
+                self.search_leaves(weight, slice, collector)?;
+            }
+        }
+
+        manager.reduce(collectors)
+    }
+
+    /// Reports the outcome of a search that started at `start` to the metrics recorder, if any. The clock is only read
+    /// when metrics are recorded, as it isn't available on every target, such as `wasm32-unknown-unknown`.
+    fn record_search<T>(&self, start: Option<Instant>, result: &BoxResult<T>) {
+        let (Some(metrics), Some(start)) = (&self.metrics, start) else {
+            return;
+        };
+
+        metrics.increment_counter(SEARCH_COUNT, 1);
+        metrics.record_histogram(SEARCH_LATENCY_SECONDS, start.elapsed().as_secs_f64());
+        metrics.set_gauge(SEARCHER_SEGMENTS, self.leaves().len() as f64);
+        if result.is_err() {
+            metrics.increment_counter(SEARCH_ERRORS, 1);
+        } else if self.timed_out() {
+            metrics.increment_counter(SEARCH_TIMED_OUT, 1);
+        }
+    }
+
+    /// Passes the documents of the given leaves that match the weight's query to the collector. If the timeout
+    /// expires, the remaining documents are skipped and the searcher is marked as timed out.
+    fn search_leaves(
+        &self,
+        weight: &dyn Weight,
+        leaves: &[LeafReaderContext],
+        collector: &mut dyn Collector,
+    ) -> BoxResult<()> {
+        match self.search_leaves_until_timeout(weight, leaves, collector) {
+            Err(e) if is_search_aborted(&e) => {
+                self.timed_out.store(true, Ordering::Relaxed);
+                Ok(())
+            }
+            result => result,
+        }
+    }
+
+    fn search_leaves_until_timeout(
+        &self,
+        weight: &dyn Weight,
+        leaves: &[LeafReaderContext],
+        collector: &mut dyn Collector,
+    ) -> BoxResult<()> {
+        let timeout = self.timeout.as_deref();
+        let mut reserved = self.track_collector(collector, 0)?;
+        for context in leaves {
+            if let Some(timeout) = timeout {
+                check_timeout(timeout)?;
+            }
+
+            #[cfg(feature = "tracing")]
+            let _span = tracing::debug_span!(
+                "score_segment",
+                segment = context.reader().segment_name().unwrap_or_default(),
+                doc_base = context.doc_base(),
+                max_doc = context.reader().max_doc()
+            )
+            .entered();
+
+            let Some(mut scorer) = weight.bulk_scorer(context)? else {
+                continue;
+            };
+
+            let mut leaf_collector = match collector.leaf_collector(context) {
+                Ok(leaf_collector) => leaf_collector,
+                Err(e) if is_collection_terminated(&e) => continue,
+                Err(e) => return Err(e),
+            };
+
+            if let Some(live_docs) = context.reader().live_docs() {
+                leaf_collector = Box::new(LiveDocsLeafCollector::new(leaf_collector, live_docs));
+            }
+
+            if let Some(timeout) = &self.timeout {
+                scorer = Box::new(TimeLimitingBulkScorer::new(scorer, timeout.clone()));
+                leaf_collector = Box::new(TimeLimitingLeafCollector::new(leaf_collector, timeout.as_ref()));
+            }
+
+            let result = scorer.score(leaf_collector.as_mut(), 0, NO_MORE_DOCS);
+
+            // Hits collected before a timeout are kept, so the leaf collector is finished either way.
+            leaf_collector.finish()?;
+            drop(leaf_collector);
+            match result {
+                Err(e) if !is_collection_terminated(&e) => return Err(e),
+                _ => (),
+            }
+
+            reserved = self.track_collector(collector, reserved)?;
+        }
+
+        Ok(())
+    }
+
+    /// Brings the reservation for the collector's memory from `reserved` bytes to what it uses now, which is
+    /// returned. Collectors are measured between segments.
+    fn track_collector(&self, collector: &dyn Collector, reserved: usize) -> BoxResult<usize> {
+        let used = collector.ram_bytes_used();
+        let memory_tracker = self.memory_tracker();
+        if used > reserved {
+            memory_tracker.reserve(used - reserved, "collector")?;
+        } else {
+            memory_tracker.release(reserved - used);
+        }
+        Ok(used)
+    }
+
+    /// Explains how the score of the document with the given global id was computed for the query, or why it doesn't
+    /// match.
+    pub fn explain(&self, query: &dyn Query, doc: u32) -> BoxResult<Explanation> {
+        if doc >= self.reader.max_doc() {
+            return Err(LuceneError::InvalidArgument(format!(
+                "document {doc} is out of bounds (max_doc is {})",
+                self.reader.max_doc()
+            ))
+            .into());
+        }
+
+        let leaves = self.leaves();
+        let leaf = &leaves[sub_index(doc, leaves)];
+        let weight = self.create_weight(query, ScoreMode::Complete, 1.0)?;
+        weight.explain(leaf, doc - leaf.doc_base())
+    }
+
+    /// Returns the stored fields of the document with the given global id.
+    pub fn doc(&self, doc: u32) -> BoxResult<Document> {
+        self.reader.document(doc)
+    }
+
+    /// Returns statistics for the given field across the index, or `None` if no document has the field. If global
+    /// statistics are set and cover the field, they are returned instead.
+    pub fn collection_statistics(&self, field: &str) -> BoxResult<Option<CollectionStatistics>> {
+        if let Some(stats) = self.global_statistics.as_ref().and_then(|global| global.collection_statistics(field)) {
+            return Ok(Some(stats.clone()));
+        }
+        self.local_collection_statistics(field)
+    }
+
+    /// Returns statistics for the given field across this searcher's index only, ignoring global statistics.
+    pub fn local_collection_statistics(&self, field: &str) -> BoxResult<Option<CollectionStatistics>> {
+        let mut stats = CollectionStatistics {
+            field: field.to_string(),
+            max_doc: self.reader.max_doc() as u64,
+            doc_count: 0,
+            sum_total_term_freq: 0,
+            sum_doc_freq: 0,
+        };
+
+        for leaf in self.leaves() {
+            if let Some(terms) = leaf.reader().terms(field)? {
+                stats.doc_count += terms.doc_count() as u64;
+                stats.sum_total_term_freq += terms.sum_total_term_freq();
+                stats.sum_doc_freq += terms.sum_doc_freq();
+            }
+        }
+
+        Ok((stats.doc_count > 0).then_some(stats))
+    }
+
+    /// Returns statistics for the given term across the index, or `None` if no document contains it. If global
+    /// statistics are set and cover the term, they are returned instead.
+    pub fn term_statistics(&self, term: &Term) -> BoxResult<Option<TermStatistics>> {
+        if let Some(stats) = self.global_statistics.as_ref().and_then(|global| global.term_statistics(term)) {
+            return Ok(Some(stats.clone()));
+        }
+        self.local_term_statistics(term)
+    }
+
+    /// Returns statistics for the given term across this searcher's index only, ignoring global statistics.
+    pub fn local_term_statistics(&self, term: &Term) -> BoxResult<Option<TermStatistics>> {
+        let mut stats = TermStatistics {
+            term: term.bytes().to_vec(),
+            doc_freq: 0,
+            total_term_freq: 0,
+        };
+
+        for leaf in self.leaves() {
+            if let Some(terms) = leaf.reader().terms(term.field())? {
+                let mut te = terms.iterator()?;
+                if te.seek_exact(term.bytes())? {
+                    stats.doc_freq += te.doc_freq()? as u64;
+                    stats.total_term_freq += te.total_term_freq()?;
+                }
+            }
+        }
+
+        Ok((stats.doc_freq > 0).then_some(stats))
+    }
+}
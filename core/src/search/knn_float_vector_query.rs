@@ -0,0 +1,302 @@
+use {
+    crate::{
+        index::LeafReaderContext,
+        search::{
+            DocIdSetIterator, Explanation, IndexSearcher, Query, Scorable, ScoreMode, Scorer, Weight, NO_MORE_DOCS,
+        },
+        util::{BitSet, FixedBitSet},
+        BoxResult, LuceneError,
+    },
+    std::{
+        fmt::{Display, Formatter, Result as FmtResult},
+        sync::Arc,
+    },
+};
+
+/// A query matching the `k` documents whose [crate::document::Field::knn_vector] values are nearest to a target
+/// vector, scored by the similarity function of the field, as Lucene's `KnnFloatVectorQuery` does.
+///
+/// Each segment is searched through its HNSW graph for its own `k` nearest vectors when the weight is created, and
+/// the best `k` of those across segments are matched. The search is approximate, so a document may be missed that an
+/// exhaustive comparison would have found. With a filter, only documents matching it are returned, which still
+/// yields `k` hits when enough documents match.
+#[derive(Clone, Debug)]
+pub struct KnnFloatVectorQuery {
+    field: String,
+    target: Vec<f32>,
+    k: usize,
+    filter: Option<Arc<dyn Query>>,
+}
+
+impl KnnFloatVectorQuery {
+    /// Creates a query for the `k` documents nearest to `target` in `field`. This fails with
+    /// [LuceneError::InvalidArgument] if `k` is zero or `target` has a non-finite value.
+    pub fn new(field: &str, target: Vec<f32>, k: usize) -> BoxResult<Self> {
+        if k == 0 {
+            return Err(LuceneError::InvalidArgument("k must be at least 1".to_string()).into());
+        }
+        if target.iter().any(|value| !value.is_finite()) {
+            return Err(LuceneError::InvalidArgument("the target vector has a non-finite value".to_string()).into());
+        }
+
+        Ok(Self {
+            field: field.to_string(),
+            target,
+            k,
+            filter: None,
+        })
+    }
+
+    /// Only returns the documents matching `filter`.
+    pub fn with_filter(mut self, filter: Arc<dyn Query>) -> Self {
+        self.filter = Some(filter);
+        self
+    }
+
+    /// Returns the field searched.
+    #[inline]
+    pub fn field(&self) -> &str {
+        &self.field
+    }
+
+    /// Returns the target vector.
+    #[inline]
+    pub fn target(&self) -> &[f32] {
+        &self.target
+    }
+
+    /// Returns the number of documents matched.
+    #[inline]
+    pub fn k(&self) -> usize {
+        self.k
+    }
+
+    /// Returns the documents of a segment that the filter accepts, or `None` if every document is accepted, before
+    /// deletions are applied.
+    fn accepted_docs(&self, searcher: &IndexSearcher, context: &LeafReaderContext) -> BoxResult<Option<FixedBitSet>> {
+        let Some(filter) = &self.filter else {
+            return Ok(None);
+        };
+
+        let mut accepted = FixedBitSet::new(context.reader().max_doc());
+        let weight = searcher.create_weight(filter.as_ref(), ScoreMode::CompleteNoScores, 1.0)?;
+        if let Some(mut scorer) = weight.scorer(context)? {
+            loop {
+                let doc = scorer.next_doc()?;
+                if doc == NO_MORE_DOCS {
+                    break;
+                }
+                accepted.set(doc);
+            }
+        }
+        Ok(Some(accepted))
+    }
+}
+
+impl Query for KnnFloatVectorQuery {
+    fn create_weight(
+        &self,
+        searcher: &IndexSearcher,
+        _score_mode: ScoreMode,
+        boost: f32,
+    ) -> BoxResult<Box<dyn Weight>> {
+        // (leaf ord, doc, score) of the nearest documents of each segment.
+        let mut hits = Vec::new();
+        for context in searcher.leaves() {
+            let reader = context.reader();
+            let Some(values) = reader.float_vector_values(&self.field)? else {
+                continue;
+            };
+
+            let accepted = self.accepted_docs(searcher, context)?;
+            let live_docs = reader.live_docs();
+            let accept = |doc: u32| {
+                live_docs.is_none_or(|live_docs| live_docs.get(doc))
+                    && accepted.as_ref().is_none_or(|accepted| accepted.get(doc))
+            };
+            for (doc, score) in values.search(&self.target, self.k, &accept)? {
+                hits.push((context.ord(), doc, score));
+            }
+        }
+
+        hits.sort_by(|a, b| b.2.total_cmp(&a.2).then((a.0, a.1).cmp(&(b.0, b.1))));
+        hits.truncate(self.k);
+
+        let mut leaves = vec![Vec::new(); searcher.leaves().len()];
+        for (ord, doc, score) in hits {
+            leaves[ord].push((doc, score * boost));
+        }
+        for docs in &mut leaves {
+            docs.sort_unstable_by_key(|&(doc, _)| doc);
+        }
+
+        Ok(Box::new(KnnFloatVectorWeight {
+            description: self.to_string(),
+            leaves,
+        }))
+    }
+}
+
+impl Display for KnnFloatVectorQuery {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "KnnFloatVectorQuery:{}[{}", self.field, self.target.first().copied().unwrap_or_default())?;
+        if self.target.len() > 1 {
+            write!(f, ",...")?;
+        }
+        write!(f, "][{}]", self.k)?;
+        if let Some(filter) = &self.filter {
+            write!(f, "[{filter}]")?;
+        }
+        Ok(())
+    }
+}
+
+/// The nearest documents found when the weight was created, by segment.
+#[derive(Debug)]
+struct KnnFloatVectorWeight {
+    description: String,
+    leaves: Vec<Vec<(u32, f32)>>,
+}
+
+impl Weight for KnnFloatVectorWeight {
+    fn scorer(&self, context: &LeafReaderContext) -> BoxResult<Option<Box<dyn Scorer>>> {
+        match self.leaves.get(context.ord()) {
+            Some(docs) if !docs.is_empty() => Ok(Some(Box::new(DocAndScoreScorer {
+                docs: docs.clone().into(),
+                index: None,
+            }))),
+            _ => Ok(None),
+        }
+    }
+
+    fn explain(&self, context: &LeafReaderContext, doc: u32) -> BoxResult<Explanation> {
+        let docs = self.leaves.get(context.ord()).map_or(&[][..], Vec::as_slice);
+        match docs.binary_search_by_key(&doc, |&(doc, _)| doc) {
+            Ok(i) => Ok(Explanation::constant(docs[i].1, format!("within top k of {}", self.description))),
+            Err(_) => Ok(Explanation::no_match(format!("not in top k of {}", self.description), vec![])),
+        }
+    }
+}
+
+/// Iterates over the documents found by a [KnnFloatVectorQuery] in a segment, in order, with their scores.
+#[derive(Debug)]
+struct DocAndScoreScorer {
+    docs: Arc<[(u32, f32)]>,
+    index: Option<usize>,
+}
+
+impl DocIdSetIterator for DocAndScoreScorer {
+    #[inline]
+    fn doc_id(&self) -> u32 {
+        self.index.and_then(|index| self.docs.get(index)).map_or(NO_MORE_DOCS, |&(doc, _)| doc)
+    }
+
+    fn next_doc(&mut self) -> BoxResult<u32> {
+        self.index = Some(self.index.map_or(0, |index| index + 1).min(self.docs.len()));
+        Ok(self.doc_id())
+    }
+
+    fn advance(&mut self, target: u32) -> BoxResult<u32> {
+        let start = self.index.map_or(0, |index| index + 1).min(self.docs.len());
+        self.index = Some(start + self.docs[start..].partition_point(|&(doc, _)| doc < target));
+        Ok(self.doc_id())
+    }
+
+    #[inline]
+    fn cost(&self) -> u64 {
+        self.docs.len() as u64
+    }
+}
+
+impl Scorable for DocAndScoreScorer {
+    fn score(&mut self) -> BoxResult<f32> {
+        Ok(self.index.and_then(|index| self.docs.get(index)).map_or(0.0, |&(_, score)| score))
+    }
+}
+
+impl Scorer for DocAndScoreScorer {
+    fn max_score(&mut self, _up_to: u32) -> BoxResult<f32> {
+        Ok(self.docs.iter().map(|&(_, score)| score).fold(0.0, f32::max))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{
+            document::{Document, Field, Store},
+            index::{LeafReader, SegmentReader, Term, VectorSimilarityFunction},
+            search::{
+                test_util::{leaf_searcher, segment},
+                IndexSearcher, KnnFloatVectorQuery, TermQuery,
+            },
+            util::{BitSet, FixedBitSet},
+        },
+        pretty_assertions::assert_eq,
+        std::sync::Arc,
+    };
+
+    fn searcher() -> IndexSearcher {
+        // Two segments of points along a line, with every other document deleted from the second.
+        let mut segments: Vec<Arc<dyn LeafReader>> = [0, 50]
+            .into_iter()
+            .map(|base| {
+                segment((base..base + 50).map(|i| {
+                    let parity = if i % 2 == 0 {
+                        "even"
+                    } else {
+                        "odd"
+                    };
+                    let vector = vec![i as f32, 1.0];
+                    Document::from_iter([
+                        Field::string("parity", parity, Store::No),
+                        Field::knn_vector("embedding", vector, VectorSimilarityFunction::Euclidean).unwrap(),
+                    ])
+                }))
+            })
+            .collect();
+        let mut live_docs = FixedBitSet::new(50);
+        live_docs.set_range(0, 50);
+        for doc in (1..50).step_by(2) {
+            live_docs.clear(doc);
+        }
+        segments[1] = Arc::new(SegmentReader::new(segments[1].clone(), Some(live_docs)).unwrap());
+        leaf_searcher(segments)
+    }
+
+    fn docs(searcher: &IndexSearcher, query: &KnnFloatVectorQuery) -> Vec<u32> {
+        let mut docs: Vec<u32> = searcher.search(query, 100).unwrap().score_docs.iter().map(|sd| sd.doc).collect();
+        docs.sort_unstable();
+        docs
+    }
+
+    #[test]
+    fn test_knn_float_vector_query() {
+        let searcher = searcher();
+        let query = KnnFloatVectorQuery::new("embedding", vec![48.2, 1.0], 3).unwrap();
+        assert_eq!(query.to_string(), "KnnFloatVectorQuery:embedding[48.2,...][3]");
+        assert_eq!(docs(&searcher, &query), vec![47, 48, 49]);
+        let top = searcher.search(&query, 1).unwrap();
+        assert_eq!(
+            (top.score_docs[0].doc, top.score_docs[0].score),
+            (48, VectorSimilarityFunction::Euclidean.compare(&[48.2, 1.0], &[48.0, 1.0]))
+        );
+        assert!(searcher.explain(&query, 48).unwrap().is_match());
+        assert!(!searcher.explain(&query, 46).unwrap().is_match());
+
+        // Deleted documents are skipped, so the nearest of the second segment are the even ones.
+        let query = KnnFloatVectorQuery::new("embedding", vec![61.0, 1.0], 3).unwrap();
+        assert_eq!(docs(&searcher, &query), vec![58, 60, 62]);
+
+        // The filter still yields k hits.
+        let odd = Arc::new(TermQuery::new(Term::new("parity", "odd")));
+        let query = KnnFloatVectorQuery::new("embedding", vec![10.0, 1.0], 4).unwrap().with_filter(odd);
+        assert_eq!(docs(&searcher, &query), vec![7, 9, 11, 13]);
+        assert_eq!(searcher.count(&query).unwrap(), 4);
+
+        assert!(docs(&searcher, &KnnFloatVectorQuery::new("missing", vec![1.0, 1.0], 3).unwrap()).is_empty());
+        assert!(searcher.search(&KnnFloatVectorQuery::new("embedding", vec![1.0], 3).unwrap(), 3).is_err());
+        assert!(KnnFloatVectorQuery::new("embedding", vec![1.0], 0).is_err());
+        assert!(KnnFloatVectorQuery::new("embedding", vec![f32::NAN], 1).is_err());
+    }
+}
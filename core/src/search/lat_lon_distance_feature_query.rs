@@ -0,0 +1,239 @@
+use {
+    crate::{
+        index::{LeafReaderContext, NumericDocValues},
+        search::{
+            DocIdSetIterator, Explanation, IndexSearcher, LatLonDistanceSource, Query, Scorable, ScoreMode, Scorer,
+            Weight,
+        },
+        BoxResult, LuceneError,
+    },
+    std::fmt::{Display, Formatter, Result as FmtResult},
+};
+
+/// A query that scores documents by how close their point, stored with
+/// [crate::document::Field::lat_lon_doc_values], is to an origin: `weight * pivot / (pivot + distance)`. A document at
+/// the origin scores `weight`, and one `pivot_meters` away scores half of it. It matches the documents that have a
+/// point.
+///
+/// This is the equivalent of Lucene's `LatLonPoint.newDistanceFeatureQuery`. Like a [crate::search::FeatureQuery],
+/// it is typically added as a [crate::search::Occur::Should] clause next to a text query to boost nearby results.
+#[derive(Clone, Debug)]
+pub struct LatLonDistanceFeatureQuery {
+    source: LatLonDistanceSource,
+    weight: f32,
+    pivot_meters: f64,
+}
+
+impl LatLonDistanceFeatureQuery {
+    /// Creates a query scoring the points of `field` by their distance to (`latitude`, `longitude`), in degrees.
+    /// `weight` and `pivot_meters` must be positive and finite.
+    pub fn new(field: &str, weight: f32, latitude: f64, longitude: f64, pivot_meters: f64) -> BoxResult<Self> {
+        if !(weight > 0.0 && weight.is_finite()) {
+            return Err(
+                LuceneError::InvalidArgument(format!("Weight must be positive and finite; got {weight}")).into()
+            );
+        }
+
+        if !(pivot_meters > 0.0 && pivot_meters.is_finite()) {
+            return Err(LuceneError::InvalidArgument(format!(
+                "Pivot distance must be positive and finite; got {pivot_meters}"
+            ))
+            .into());
+        }
+
+        Ok(Self {
+            source: LatLonDistanceSource::new(field, latitude, longitude)?,
+            weight,
+            pivot_meters,
+        })
+    }
+
+    /// Returns the field holding the points.
+    #[inline]
+    pub fn field(&self) -> &str {
+        self.source.field()
+    }
+
+    /// Returns the score of a document at the origin.
+    #[inline]
+    pub fn weight(&self) -> f32 {
+        self.weight
+    }
+
+    /// Returns the distance, in meters, at which documents score half the weight.
+    #[inline]
+    pub fn pivot_meters(&self) -> f64 {
+        self.pivot_meters
+    }
+}
+
+impl Query for LatLonDistanceFeatureQuery {
+    fn create_weight(
+        &self,
+        _searcher: &IndexSearcher,
+        _score_mode: ScoreMode,
+        boost: f32,
+    ) -> BoxResult<Box<dyn Weight>> {
+        Ok(Box::new(LatLonDistanceFeatureWeight {
+            source: self.source.clone(),
+            weight: self.weight * boost,
+            pivot_meters: self.pivot_meters,
+        }))
+    }
+}
+
+impl Display for LatLonDistanceFeatureQuery {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(
+            f,
+            "LatLonDistanceFeatureQuery(field={}, origin={},{}, weight={}, pivot_distance={})",
+            self.source.field(),
+            self.source.latitude(),
+            self.source.longitude(),
+            self.weight,
+            self.pivot_meters
+        )
+    }
+}
+
+#[derive(Debug)]
+struct LatLonDistanceFeatureWeight {
+    source: LatLonDistanceSource,
+    weight: f32,
+    pivot_meters: f64,
+}
+
+impl LatLonDistanceFeatureWeight {
+    #[inline]
+    fn score(&self, distance: f64) -> f32 {
+        (self.weight as f64 * self.pivot_meters / (self.pivot_meters + distance)) as f32
+    }
+}
+
+impl Weight for LatLonDistanceFeatureWeight {
+    fn scorer(&self, context: &LeafReaderContext) -> BoxResult<Option<Box<dyn Scorer>>> {
+        Ok(context.reader().numeric_doc_values(self.source.field())?.map(|doc_values| {
+            Box::new(LatLonDistanceFeatureScorer {
+                doc_values,
+                weight: LatLonDistanceFeatureWeight {
+                    source: self.source.clone(),
+                    weight: self.weight,
+                    pivot_meters: self.pivot_meters,
+                },
+            }) as Box<dyn Scorer>
+        }))
+    }
+
+    fn explain(&self, context: &LeafReaderContext, doc: u32) -> BoxResult<Explanation> {
+        let Some(mut doc_values) = context.reader().numeric_doc_values(self.source.field())? else {
+            return Ok(Explanation::no_match(format!("no points in field {}", self.source.field()), vec![]));
+        };
+
+        if !doc_values.advance_exact(doc)? {
+            return Ok(Explanation::no_match(format!("document {doc} has no point"), vec![]));
+        }
+
+        let distance = self.source.distance(doc_values.long_value()?);
+        Ok(Explanation::matched(
+            self.score(distance),
+            format!("Distance feature on {}, computed as w * k / (k + d) from:", self.source.field()),
+            vec![
+                Explanation::matched(self.weight, "w, weight of this function", vec![]),
+                Explanation::matched(self.pivot_meters as f32, "k, pivot distance in meters", vec![]),
+                Explanation::matched(distance as f32, format!("d, distance in meters from {}", self.source), vec![]),
+            ],
+        ))
+    }
+}
+
+#[derive(Debug)]
+struct LatLonDistanceFeatureScorer {
+    doc_values: Box<dyn NumericDocValues>,
+    weight: LatLonDistanceFeatureWeight,
+}
+
+impl DocIdSetIterator for LatLonDistanceFeatureScorer {
+    #[inline]
+    fn doc_id(&self) -> u32 {
+        self.doc_values.doc_id()
+    }
+
+    #[inline]
+    fn next_doc(&mut self) -> BoxResult<u32> {
+        self.doc_values.next_doc()
+    }
+
+    #[inline]
+    fn advance(&mut self, target: u32) -> BoxResult<u32> {
+        self.doc_values.advance(target)
+    }
+
+    #[inline]
+    fn cost(&self) -> u64 {
+        self.doc_values.cost()
+    }
+}
+
+impl Scorable for LatLonDistanceFeatureScorer {
+    fn score(&mut self) -> BoxResult<f32> {
+        Ok(self.weight.score(self.weight.source.distance(self.doc_values.long_value()?)))
+    }
+}
+
+impl Scorer for LatLonDistanceFeatureScorer {
+    fn max_score(&mut self, _up_to: u32) -> BoxResult<f32> {
+        Ok(self.weight.weight)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{
+            document::{Document, Field, Store},
+            index::Term,
+            search::{test_util::searcher, BooleanQuery, LatLonDistanceFeatureQuery, Occur, TermQuery},
+        },
+        pretty_assertions::assert_eq,
+        std::sync::Arc,
+    };
+
+    #[test]
+    fn test_distance_feature_query() {
+        let mut documents = Vec::new();
+        let places =
+            [(Some((0.0, 0.0)), "hotel"), (Some((0.0, 1.0)), "hotel"), (None, "hotel"), (Some((0.0, 0.1)), "inn")];
+        for (point, kind) in places {
+            let mut doc = Document::new();
+            doc.add(Field::text("kind", kind, Store::No));
+            if let Some((latitude, longitude)) = point {
+                doc.add(Field::lat_lon_doc_values("location", latitude, longitude).unwrap());
+            }
+            documents.push(doc);
+        }
+        let searcher = searcher([documents]);
+
+        // A degree of longitude on the equator is about 111.2 km.
+        let query = LatLonDistanceFeatureQuery::new("location", 2.0, 0.0, 0.0, 111_195.0).unwrap();
+        let top_docs = searcher.search(&query, 10).unwrap();
+        let docs: Vec<u32> = top_docs.score_docs.iter().map(|sd| sd.doc).collect();
+        assert_eq!(docs, vec![0, 3, 1]);
+        assert_eq!(top_docs.score_docs[0].score, 2.0);
+        assert!((top_docs.score_docs[1].score - 2.0 / 1.1).abs() < 1e-4);
+        assert!((top_docs.score_docs[2].score - 1.0).abs() < 1e-4);
+        for sd in &top_docs.score_docs {
+            assert_eq!(searcher.explain(&query, sd.doc).unwrap().value(), sd.score);
+        }
+        assert!(!searcher.explain(&query, 2).unwrap().is_match());
+
+        // Boosting nearby hits of a text query.
+        let text = Arc::new(TermQuery::new(Term::from_text("kind", "hotel")));
+        let query = BooleanQuery::builder().add(text, Occur::Must).add(Arc::new(query), Occur::Should).build();
+        let docs: Vec<u32> = searcher.search(&query, 10).unwrap().score_docs.iter().map(|sd| sd.doc).collect();
+        assert_eq!(docs, vec![0, 1, 2]);
+
+        assert!(LatLonDistanceFeatureQuery::new("location", 0.0, 0.0, 0.0, 1.0).is_err());
+        assert!(LatLonDistanceFeatureQuery::new("location", 1.0, 0.0, 0.0, 0.0).is_err());
+        assert!(LatLonDistanceFeatureQuery::new("location", 1.0, 0.0, 200.0, 1.0).is_err());
+    }
+}
@@ -0,0 +1,205 @@
+use {
+    crate::{
+        index::{LeafReaderContext, NumericDocValues},
+        search::{
+            ConstantScoreScorer, DocIdSetIterator, Explanation, IndexSearcher, LatLonDistanceSource, Query, ScoreMode,
+            Scorer, TwoPhaseIterator, Weight,
+        },
+        BoxResult, LuceneError,
+    },
+    std::fmt::{Display, Formatter, Result as FmtResult},
+};
+
+/// A query matching the documents whose point, stored with [crate::document::Field::lat_lon_doc_values], is within a
+/// radius of a fixed point, with a constant score.
+///
+/// The query has no index to narrow down candidates: it decodes the point of every document with one and checks its
+/// distance. It is best used as a filter alongside a selective query, which leads iteration.
+#[derive(Clone, Debug)]
+pub struct LatLonDistanceQuery {
+    source: LatLonDistanceSource,
+    radius_meters: f64,
+}
+
+impl LatLonDistanceQuery {
+    /// Creates a query matching the points of `field` within `radius_meters` of (`latitude`, `longitude`), in
+    /// degrees. The radius must be non-negative and finite.
+    pub fn new(field: &str, latitude: f64, longitude: f64, radius_meters: f64) -> BoxResult<Self> {
+        if !(radius_meters >= 0.0 && radius_meters.is_finite()) {
+            return Err(LuceneError::InvalidArgument(format!(
+                "Radius must be non-negative and finite; got {radius_meters}"
+            ))
+            .into());
+        }
+
+        Ok(Self {
+            source: LatLonDistanceSource::new(field, latitude, longitude)?,
+            radius_meters,
+        })
+    }
+
+    /// Returns the field holding the points.
+    #[inline]
+    pub fn field(&self) -> &str {
+        self.source.field()
+    }
+
+    /// Returns the radius, in meters.
+    #[inline]
+    pub fn radius_meters(&self) -> f64 {
+        self.radius_meters
+    }
+}
+
+impl Query for LatLonDistanceQuery {
+    fn create_weight(
+        &self,
+        _searcher: &IndexSearcher,
+        _score_mode: ScoreMode,
+        boost: f32,
+    ) -> BoxResult<Box<dyn Weight>> {
+        Ok(Box::new(LatLonDistanceWeight {
+            query: self.clone(),
+            score: boost,
+        }))
+    }
+}
+
+impl Display for LatLonDistanceQuery {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(
+            f,
+            "{}:{},{} +/- {} meters",
+            self.source.field(),
+            self.source.latitude(),
+            self.source.longitude(),
+            self.radius_meters
+        )
+    }
+}
+
+#[derive(Debug)]
+struct LatLonDistanceWeight {
+    query: LatLonDistanceQuery,
+    score: f32,
+}
+
+impl Weight for LatLonDistanceWeight {
+    fn scorer(&self, context: &LeafReaderContext) -> BoxResult<Option<Box<dyn Scorer>>> {
+        let Some(doc_values) = context.reader().numeric_doc_values(self.query.field())? else {
+            return Ok(None);
+        };
+
+        let two_phase = WithinDistance {
+            doc_values,
+            query: self.query.clone(),
+        };
+        Ok(Some(Box::new(ConstantScoreScorer::with_two_phase(self.score, Box::new(two_phase)))))
+    }
+
+    fn explain(&self, context: &LeafReaderContext, doc: u32) -> BoxResult<Explanation> {
+        let Some(mut doc_values) = context.reader().numeric_doc_values(self.query.field())? else {
+            return Ok(Explanation::no_match(format!("no points in field {}", self.query.field()), vec![]));
+        };
+
+        if !doc_values.advance_exact(doc)? {
+            return Ok(Explanation::no_match(format!("document {doc} has no point"), vec![]));
+        }
+
+        let distance = self.query.source.distance(doc_values.long_value()?);
+        if distance <= self.query.radius_meters {
+            Ok(Explanation::constant(self.score, format!("{}, at {distance} meters", self.query)))
+        } else {
+            Ok(Explanation::no_match(format!("document {doc} is {distance} meters away"), vec![]))
+        }
+    }
+}
+
+/// Confirms the documents with a point whose distance is within the radius.
+#[derive(Debug)]
+struct WithinDistance {
+    doc_values: Box<dyn NumericDocValues>,
+    query: LatLonDistanceQuery,
+}
+
+impl TwoPhaseIterator for WithinDistance {
+    fn approximation(&self) -> &dyn DocIdSetIterator {
+        self.doc_values.as_ref()
+    }
+
+    fn approximation_mut(&mut self) -> &mut dyn DocIdSetIterator {
+        self.doc_values.as_mut()
+    }
+
+    fn matches(&mut self) -> BoxResult<bool> {
+        Ok(self.query.source.distance(self.doc_values.long_value()?) <= self.query.radius_meters)
+    }
+
+    fn match_cost(&self) -> f32 {
+        // Decoding plus a handful of trigonometric functions.
+        100.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{
+            document::{Document, Field, Store},
+            index::Term,
+            search::{test_util::searcher, BooleanQuery, LatLonDistanceQuery, Occur, TermQuery},
+        },
+        pretty_assertions::assert_eq,
+        std::sync::Arc,
+    };
+
+    #[test]
+    fn test_distance_query() {
+        let mut documents = Vec::new();
+        let places = [
+            ("cafe", Some((48.8584, 2.2945))),  // ~4.2 km from the origin
+            ("cafe", Some((48.8606, 2.3376))),  // ~1.2 km
+            ("museum", Some((48.8738, 2.295))), // ~4.6 km
+            ("cafe", None),
+            ("cafe", Some((45.764, 4.8357))), // ~392 km
+        ];
+        for (kind, point) in places {
+            let mut doc = Document::new();
+            doc.add(Field::text("kind", kind, Store::No));
+            if let Some((latitude, longitude)) = point {
+                doc.add(Field::lat_lon_doc_values("location", latitude, longitude).unwrap());
+            }
+            documents.push(doc);
+        }
+        let searcher = searcher([documents]);
+
+        let docs = |query: &LatLonDistanceQuery| {
+            let mut docs: Vec<u32> = searcher.search(query, 10).unwrap().score_docs.iter().map(|sd| sd.doc).collect();
+            docs.sort();
+            docs
+        };
+
+        let nearby = LatLonDistanceQuery::new("location", 48.8566, 2.3522, 5_000.0).unwrap();
+        assert_eq!(docs(&nearby), vec![0, 1, 2]);
+        assert_eq!(docs(&LatLonDistanceQuery::new("location", 48.8566, 2.3522, 2_000.0).unwrap()), vec![1]);
+        assert_eq!(docs(&LatLonDistanceQuery::new("location", 48.8566, 2.3522, 500_000.0).unwrap()), vec![0, 1, 2, 4]);
+        assert_eq!(nearby.to_string(), "location:48.8566,2.3522 +/- 5000 meters");
+
+        assert!(searcher.explain(&nearby, 1).unwrap().is_match());
+        assert!(!searcher.explain(&nearby, 3).unwrap().is_match());
+        assert!(!searcher.explain(&nearby, 4).unwrap().is_match());
+
+        // As a filter next to a text query.
+        let query = BooleanQuery::builder()
+            .add(Arc::new(TermQuery::new(Term::from_text("kind", "cafe"))), Occur::Must)
+            .add(Arc::new(nearby), Occur::Filter)
+            .build();
+        let mut docs: Vec<u32> = searcher.search(&query, 10).unwrap().score_docs.iter().map(|sd| sd.doc).collect();
+        docs.sort();
+        assert_eq!(docs, vec![0, 1]);
+
+        assert!(LatLonDistanceQuery::new("location", 0.0, 0.0, -1.0).is_err());
+        assert!(LatLonDistanceQuery::new("location", 0.0, 0.0, f64::NAN).is_err());
+        assert!(LatLonDistanceQuery::new("location", -90.5, 0.0, 1.0).is_err());
+    }
+}
@@ -0,0 +1,196 @@
+use {
+    crate::{
+        geo::{check_latitude, check_longitude, decode_lat_lon, haversin_meters},
+        index::{LeafReaderContext, NumericDocValues},
+        search::{DoubleValues, DoubleValuesSource, MissingValue, SortField, SortFieldType},
+        BoxResult,
+    },
+    std::{
+        fmt::{Display, Formatter, Result as FmtResult},
+        sync::Arc,
+    },
+};
+
+/// A [DoubleValuesSource] whose value is the distance, in meters, from a fixed point to the point stored in a field
+/// created with [crate::document::Field::lat_lon_doc_values]. Documents without a point have no value.
+#[derive(Clone, Debug)]
+pub struct LatLonDistanceSource {
+    field: String,
+    latitude: f64,
+    longitude: f64,
+}
+
+impl LatLonDistanceSource {
+    /// Creates a source measuring distances from (`latitude`, `longitude`), in degrees, to the points of `field`.
+    pub fn new(field: &str, latitude: f64, longitude: f64) -> BoxResult<Self> {
+        check_latitude(latitude)?;
+        check_longitude(longitude)?;
+        Ok(Self {
+            field: field.to_string(),
+            latitude,
+            longitude,
+        })
+    }
+
+    /// Returns the field holding the points.
+    #[inline]
+    pub fn field(&self) -> &str {
+        &self.field
+    }
+
+    /// Returns the latitude of the origin, in degrees.
+    #[inline]
+    pub fn latitude(&self) -> f64 {
+        self.latitude
+    }
+
+    /// Returns the longitude of the origin, in degrees.
+    #[inline]
+    pub fn longitude(&self) -> f64 {
+        self.longitude
+    }
+
+    /// Returns the distance, in meters, from the origin to an encoded point.
+    #[inline]
+    pub(crate) fn distance(&self, encoded: i64) -> f64 {
+        let (latitude, longitude) = decode_lat_lon(encoded);
+        haversin_meters(self.latitude, self.longitude, latitude, longitude)
+    }
+}
+
+impl DoubleValuesSource for LatLonDistanceSource {
+    fn values(&self, context: &LeafReaderContext) -> BoxResult<Box<dyn DoubleValues>> {
+        Ok(Box::new(LatLonDistanceValues {
+            source: self.clone(),
+            doc_values: context.reader().numeric_doc_values(&self.field)?,
+        }))
+    }
+
+    #[inline]
+    fn needs_scores(&self) -> bool {
+        false
+    }
+}
+
+impl Display for LatLonDistanceSource {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "distance({},{},{})", self.field, self.latitude, self.longitude)
+    }
+}
+
+#[derive(Debug)]
+struct LatLonDistanceValues {
+    source: LatLonDistanceSource,
+    doc_values: Option<Box<dyn NumericDocValues>>,
+}
+
+impl DoubleValues for LatLonDistanceValues {
+    fn value(&mut self, doc: u32, _score: f32) -> BoxResult<Option<f64>> {
+        let Some(dv) = &mut self.doc_values else {
+            return Ok(None);
+        };
+
+        if dv.advance_exact(doc)? {
+            Ok(Some(self.source.distance(dv.long_value()?)))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+/// A [SortField] ordering documents by their distance from a point, nearest first. Documents without a point sort
+/// last, as if infinitely far away.
+#[derive(Debug)]
+pub struct LatLonDistanceSortField {
+    source: Arc<LatLonDistanceSource>,
+    reverse: bool,
+}
+
+impl LatLonDistanceSortField {
+    /// Creates a sort field ordering documents by the distance from (`latitude`, `longitude`), in degrees, to the
+    /// points of `field`.
+    pub fn new(field: &str, latitude: f64, longitude: f64) -> BoxResult<Self> {
+        Ok(Self {
+            source: Arc::new(LatLonDistanceSource::new(field, latitude, longitude)?),
+            reverse: false,
+        })
+    }
+
+    /// Update the reverse flag, so that the farthest documents sort first.
+    pub fn set_reverse(&mut self, reverse: bool) {
+        self.reverse = reverse;
+    }
+}
+
+impl SortField for LatLonDistanceSortField {
+    fn get_field_type(&self) -> SortFieldType {
+        SortFieldType::Custom
+    }
+
+    fn get_field_name(&self) -> Option<&str> {
+        Some(self.source.field())
+    }
+
+    fn is_reverse(&self) -> bool {
+        self.reverse
+    }
+
+    fn missing_value(&self) -> Option<MissingValue> {
+        Some(MissingValue::F64(f64::INFINITY))
+    }
+
+    fn double_values_source(&self) -> Option<Arc<dyn DoubleValuesSource>> {
+        Some(self.source.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{
+            document::{Document, Field, Store},
+            search::{test_util::searcher, LatLonDistanceSortField, MatchAllDocsQuery, Sort, SortField, SortValue},
+        },
+        pretty_assertions::assert_eq,
+    };
+
+    #[test]
+    fn test_distance_sort() {
+        // Distances from Paris: Lyon ~392 km, London ~344 km, Berlin ~878 km; the third document has no point.
+        let mut segments = Vec::new();
+        for cities in [&[Some((45.764, 4.8357)), Some((51.5074, -0.1278))][..], &[None, Some((52.52, 13.405))]] {
+            let mut documents = Vec::new();
+            for city in cities {
+                let mut doc = Document::new();
+                doc.add(Field::text("body", "city", Store::No));
+                if let Some((latitude, longitude)) = city {
+                    doc.add(Field::lat_lon_doc_values("location", *latitude, *longitude).unwrap());
+                }
+                documents.push(doc);
+            }
+            segments.push(documents);
+        }
+        let searcher = searcher(segments);
+
+        let sorted = |reverse: bool| {
+            let mut sort_field = LatLonDistanceSortField::new("location", 48.8566, 2.3522).unwrap();
+            sort_field.set_reverse(reverse);
+            let sort = Sort::from_fields(vec![Box::new(sort_field) as Box<dyn SortField>]).unwrap();
+            searcher.search_with_sort(&MatchAllDocsQuery, 10, &sort).unwrap().field_docs
+        };
+
+        let hits = sorted(false);
+        assert_eq!(hits.iter().map(|hit| hit.doc).collect::<Vec<_>>(), vec![1, 0, 3, 2]);
+        let SortValue::Double(nearest) = hits[0].fields[0] else {
+            panic!("expected a distance, got {:?}", hits[0].fields[0]);
+        };
+        assert!((nearest - 343_500.0).abs() < 2_000.0, "{nearest}");
+        assert_eq!(hits[3].fields[0], SortValue::Double(f64::INFINITY));
+
+        let hits = sorted(true);
+        assert_eq!(hits.iter().map(|hit| hit.doc).collect::<Vec<_>>(), vec![2, 3, 0, 1]);
+
+        assert!(LatLonDistanceSortField::new("location", 91.0, 0.0).is_err());
+        assert!(LatLonDistanceSortField::new("location", 0.0, -180.5).is_err());
+    }
+}
@@ -0,0 +1,217 @@
+use {
+    crate::{
+        geo::{Polygon, Polygon2D, QueryRelation, Triangle},
+        index::{BinaryDocValues, LeafReaderContext},
+        search::{
+            ConstantScoreScorer, DocIdSetIterator, Explanation, IndexSearcher, Query, ScoreMode, Scorer,
+            TwoPhaseIterator, Weight,
+        },
+        BoxResult,
+    },
+    std::{
+        fmt::{Display, Formatter, Result as FmtResult},
+        sync::Arc,
+    },
+};
+
+/// A query matching the documents whose shape, indexed with [crate::document::LatLonShape], relates to a polygon as
+/// given by a [QueryRelation], with a constant score. Documents without a shape never match.
+///
+/// Shapes are stored as their triangles in binary doc values, and every document with a shape is checked triangle by
+/// triangle. Like [crate::search::LatLonDistanceQuery], this is best used as a filter alongside a selective query.
+#[derive(Clone, Debug)]
+pub struct LatLonShapeQuery {
+    field: String,
+    relation: QueryRelation,
+    polygon: Polygon,
+    component: Arc<Polygon2D>,
+}
+
+impl LatLonShapeQuery {
+    /// Creates a query matching the shapes of `field` that relate to `polygon` as given by `relation`.
+    pub fn new(field: &str, relation: QueryRelation, polygon: Polygon) -> BoxResult<Self> {
+        Ok(Self {
+            field: field.to_string(),
+            relation,
+            component: Arc::new(Polygon2D::new(&polygon)?),
+            polygon,
+        })
+    }
+
+    /// Returns the field holding the shapes.
+    #[inline]
+    pub fn field(&self) -> &str {
+        &self.field
+    }
+
+    /// Returns the relation that matching shapes have with the polygon.
+    #[inline]
+    pub fn relation(&self) -> QueryRelation {
+        self.relation
+    }
+
+    /// Returns the query polygon.
+    #[inline]
+    pub fn polygon(&self) -> &Polygon {
+        &self.polygon
+    }
+
+    /// Indicates whether an encoded shape matches.
+    fn matches(&self, shape: &[u8]) -> BoxResult<bool> {
+        Ok(self.component.matches(&Triangle::decode_all(shape)?, self.relation))
+    }
+}
+
+impl Query for LatLonShapeQuery {
+    fn create_weight(
+        &self,
+        _searcher: &IndexSearcher,
+        _score_mode: ScoreMode,
+        boost: f32,
+    ) -> BoxResult<Box<dyn Weight>> {
+        Ok(Box::new(LatLonShapeWeight {
+            query: self.clone(),
+            score: boost,
+        }))
+    }
+}
+
+impl Display for LatLonShapeQuery {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "{}:{:?}({})", self.field, self.relation, self.polygon)
+    }
+}
+
+#[derive(Debug)]
+struct LatLonShapeWeight {
+    query: LatLonShapeQuery,
+    score: f32,
+}
+
+impl Weight for LatLonShapeWeight {
+    fn scorer(&self, context: &LeafReaderContext) -> BoxResult<Option<Box<dyn Scorer>>> {
+        let Some(doc_values) = context.reader().binary_doc_values(&self.query.field)? else {
+            return Ok(None);
+        };
+
+        let two_phase = ShapeMatches {
+            doc_values,
+            query: self.query.clone(),
+        };
+        Ok(Some(Box::new(ConstantScoreScorer::with_two_phase(self.score, Box::new(two_phase)))))
+    }
+
+    fn explain(&self, context: &LeafReaderContext, doc: u32) -> BoxResult<Explanation> {
+        let Some(mut doc_values) = context.reader().binary_doc_values(&self.query.field)? else {
+            return Ok(Explanation::no_match(format!("no shapes in field {}", self.query.field), vec![]));
+        };
+
+        if !doc_values.advance_exact(doc)? {
+            return Ok(Explanation::no_match(format!("document {doc} has no shape"), vec![]));
+        }
+
+        if self.query.matches(doc_values.binary_value()?)? {
+            Ok(Explanation::constant(self.score, self.query.to_string()))
+        } else {
+            Ok(Explanation::no_match(format!("the shape of document {doc} doesn't match {}", self.query), vec![]))
+        }
+    }
+}
+
+/// Confirms the documents whose shape matches.
+#[derive(Debug)]
+struct ShapeMatches {
+    doc_values: Box<dyn BinaryDocValues>,
+    query: LatLonShapeQuery,
+}
+
+impl TwoPhaseIterator for ShapeMatches {
+    fn approximation(&self) -> &dyn DocIdSetIterator {
+        self.doc_values.as_ref()
+    }
+
+    fn approximation_mut(&mut self) -> &mut dyn DocIdSetIterator {
+        self.doc_values.as_mut()
+    }
+
+    fn matches(&mut self) -> BoxResult<bool> {
+        self.query.matches(self.doc_values.binary_value()?)
+    }
+
+    fn match_cost(&self) -> f32 {
+        // Relating a few triangles to every edge of the polygon.
+        1000.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{
+            document::{Document, Field, LatLonShape, Store},
+            geo::{Line, Polygon, QueryRelation},
+            search::{test_util, IndexSearcher, Query},
+        },
+        pretty_assertions::assert_eq,
+    };
+
+    fn searcher() -> IndexSearcher {
+        let shapes = [
+            // 0: a small square well inside the query box.
+            LatLonShape::create_polygon_field("shape", &Polygon::from_box(2.0, 3.0, 2.0, 3.0).unwrap()).unwrap(),
+            // 1: a line crossing the query box's edge.
+            LatLonShape::create_line_field("shape", &Line::new(vec![5.0, 5.0], vec![5.0, 15.0]).unwrap()).unwrap(),
+            // 2: a point far away.
+            LatLonShape::create_point_field("shape", -40.0, 120.0).unwrap(),
+            // 3: a square with a hole that the query box fits in.
+            LatLonShape::create_polygon_field(
+                "shape",
+                &Polygon::with_holes(
+                    vec![-20.0, -20.0, 30.0, 30.0, -20.0],
+                    vec![-20.0, 30.0, 30.0, -20.0, -20.0],
+                    vec![Polygon::from_box(-1.0, 11.0, -1.0, 11.0).unwrap()],
+                )
+                .unwrap(),
+            )
+            .unwrap(),
+        ];
+
+        let documents = shapes.map(|shape| Document::from_iter([shape]));
+        let unshaped = [Document::new(), Document::from_iter([Field::text("body", "no shape here", Store::No)])];
+        test_util::searcher([documents.into_iter().chain(unshaped)])
+    }
+
+    fn docs(searcher: &IndexSearcher, query: &dyn Query) -> Vec<u32> {
+        let mut docs: Vec<u32> = searcher.search(query, 10).unwrap().score_docs.iter().map(|sd| sd.doc).collect();
+        docs.sort();
+        docs
+    }
+
+    #[test]
+    fn test_shape_query_relations() {
+        let searcher = searcher();
+        let query_box = |relation| LatLonShape::new_box_query("shape", relation, 0.0, 10.0, 0.0, 10.0).unwrap();
+
+        assert_eq!(docs(&searcher, &query_box(QueryRelation::Intersects)), vec![0, 1]);
+        assert_eq!(docs(&searcher, &query_box(QueryRelation::Within)), vec![0]);
+        assert_eq!(docs(&searcher, &query_box(QueryRelation::Disjoint)), vec![2, 3]);
+
+        let query = query_box(QueryRelation::Within);
+        assert!(searcher.explain(&query, 0).unwrap().is_match());
+        assert!(!searcher.explain(&query, 1).unwrap().is_match());
+        assert!(!searcher.explain(&query, 4).unwrap().is_match());
+        assert_eq!(query.to_string(), "shape:Within(POLYGON((0 0, 10 0, 10 10, 0 10, 0 0)))");
+
+        // A triangle covering the square of document 0 and reaching into the hole of document 3.
+        let triangle = Polygon::new(vec![1.0, 1.0, 4.0, 1.0], vec![1.0, 4.0, 1.0, 1.0]).unwrap();
+        let query = LatLonShape::new_polygon_query("shape", QueryRelation::Intersects, triangle).unwrap();
+        assert_eq!(docs(&searcher, &query), vec![0]);
+
+        assert!(LatLonShape::new_box_query("shape", QueryRelation::Within, 10.0, 0.0, 0.0, 10.0).is_err());
+        assert!(LatLonShape::create_polygon_field(
+            "shape",
+            &Polygon::new(vec![0.0, 1.0, 0.0, 1.0, 0.0], vec![0.0, 1.0, 1.0, 0.0, 0.0]).unwrap()
+        )
+        .is_err());
+    }
+}
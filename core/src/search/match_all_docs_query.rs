@@ -0,0 +1,53 @@
+use {
+    crate::{
+        index::LeafReaderContext,
+        search::{
+            ConstantScoreScorer, Explanation, IndexSearcher, Query, RangeDocIdSetIterator, ScoreMode, Scorer, Weight,
+        },
+        BoxResult,
+    },
+    std::fmt::{Display, Formatter, Result as FmtResult},
+};
+
+/// A query that matches every document, with a constant score.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MatchAllDocsQuery;
+
+impl Query for MatchAllDocsQuery {
+    fn create_weight(
+        &self,
+        _searcher: &IndexSearcher,
+        _score_mode: ScoreMode,
+        boost: f32,
+    ) -> BoxResult<Box<dyn Weight>> {
+        Ok(Box::new(MatchAllDocsWeight {
+            score: boost,
+        }))
+    }
+}
+
+impl Display for MatchAllDocsQuery {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "*:*")
+    }
+}
+
+#[derive(Debug)]
+struct MatchAllDocsWeight {
+    score: f32,
+}
+
+impl Weight for MatchAllDocsWeight {
+    fn scorer(&self, context: &LeafReaderContext) -> BoxResult<Option<Box<dyn Scorer>>> {
+        let iterator = RangeDocIdSetIterator::all(context.reader().max_doc());
+        Ok(Some(Box::new(ConstantScoreScorer::new(self.score, Box::new(iterator)))))
+    }
+
+    fn explain(&self, context: &LeafReaderContext, doc: u32) -> BoxResult<Explanation> {
+        if doc < context.reader().max_doc() {
+            Ok(Explanation::constant(self.score, "*:*"))
+        } else {
+            Ok(Explanation::no_match(format!("document {doc} is out of bounds"), vec![]))
+        }
+    }
+}
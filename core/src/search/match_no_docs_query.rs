@@ -0,0 +1,63 @@
+use {
+    crate::{
+        index::LeafReaderContext,
+        search::{Explanation, IndexSearcher, Query, ScoreMode, Scorer, Weight},
+        BoxResult,
+    },
+    std::fmt::{Display, Formatter, Result as FmtResult},
+};
+
+/// A query that matches no documents. Rewrites produce this when they can determine that nothing will match.
+#[derive(Clone, Debug, Default)]
+pub struct MatchNoDocsQuery {
+    reason: String,
+}
+
+impl MatchNoDocsQuery {
+    /// Creates a new query, recording why it matches nothing.
+    pub fn new(reason: impl Into<String>) -> Self {
+        Self {
+            reason: reason.into(),
+        }
+    }
+
+    /// Returns the reason the query matches nothing.
+    #[inline]
+    pub fn reason(&self) -> &str {
+        &self.reason
+    }
+}
+
+impl Query for MatchNoDocsQuery {
+    fn create_weight(
+        &self,
+        _searcher: &IndexSearcher,
+        _score_mode: ScoreMode,
+        _boost: f32,
+    ) -> BoxResult<Box<dyn Weight>> {
+        Ok(Box::new(MatchNoDocsWeight {
+            reason: self.reason.clone(),
+        }))
+    }
+}
+
+impl Display for MatchNoDocsQuery {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "MatchNoDocsQuery({:?})", self.reason)
+    }
+}
+
+#[derive(Debug)]
+struct MatchNoDocsWeight {
+    reason: String,
+}
+
+impl Weight for MatchNoDocsWeight {
+    fn scorer(&self, _context: &LeafReaderContext) -> BoxResult<Option<Box<dyn Scorer>>> {
+        Ok(None)
+    }
+
+    fn explain(&self, _context: &LeafReaderContext, _doc: u32) -> BoxResult<Explanation> {
+        Ok(Explanation::no_match(self.reason.clone(), vec![]))
+    }
+}
@@ -0,0 +1,138 @@
+use crate::{
+    search::{DocIdSetIterator, Scorable, Scorer, NO_MORE_DOCS},
+    BoxResult,
+};
+
+/// A [Scorer] over the documents matched by at least a minimum number of its sub-scorers, scoring each document with
+/// the sum of the scores of the sub-scorers that match it.
+///
+/// With a minimum of `n`, a document can't match before the `n`-th smallest document the sub-scorers are positioned
+/// on, so the sub-scorers behind it are advanced straight to it rather than visiting every document of the union as
+/// [crate::search::DisjunctionSumScorer] does.
+#[derive(Debug)]
+pub struct MinShouldMatchSumScorer {
+    subs: Vec<Box<dyn Scorer>>,
+
+    /// The document each sub-scorer is positioned on; `None` until it has been positioned.
+    sub_docs: Vec<Option<u32>>,
+    min_should_match: usize,
+    doc: Option<u32>,
+    cost: u64,
+
+    /// Scratch space for finding the `min_should_match`-th smallest document.
+    sorted: Vec<u32>,
+}
+
+impl MinShouldMatchSumScorer {
+    /// Creates a scorer over the documents matched by at least `min_should_match` of the given scorers. The minimum
+    /// must be at least 1 and no more than the number of scorers.
+    pub fn new(subs: Vec<Box<dyn Scorer>>, min_should_match: u32) -> Self {
+        let min_should_match = min_should_match as usize;
+        assert!(
+            (1..=subs.len()).contains(&min_should_match),
+            "min_should_match must be from 1 to the number of scorers ({}), got {min_should_match}",
+            subs.len()
+        );
+
+        // A match needs one of every `len - min_should_match + 1` sub-scorers, so the cheapest of those bounds the
+        // number of matches.
+        let mut costs: Vec<u64> = subs.iter().map(|s| s.cost()).collect();
+        costs.sort_unstable();
+        let cost = costs[..subs.len() - min_should_match + 1].iter().sum();
+
+        Self {
+            sub_docs: vec![None; subs.len()],
+            sorted: Vec::with_capacity(subs.len()),
+            subs,
+            min_should_match,
+            doc: None,
+            cost,
+        }
+    }
+
+    /// Returns the number of sub-scorers that must match a document.
+    #[inline]
+    pub fn min_should_match(&self) -> u32 {
+        self.min_should_match as u32
+    }
+
+    /// Positions this scorer on the first document at or after `target` that enough sub-scorers match.
+    fn do_next(&mut self, mut target: u32) -> BoxResult<u32> {
+        loop {
+            self.sorted.clear();
+            for (sub, sub_doc) in self.subs.iter_mut().zip(self.sub_docs.iter_mut()) {
+                let d = match *sub_doc {
+                    Some(d) if d >= target => d,
+                    None if target == 0 => sub.next_doc()?,
+                    _ => sub.advance(target)?,
+                };
+                *sub_doc = Some(d);
+                self.sorted.push(d);
+            }
+
+            self.sorted.select_nth_unstable(self.min_should_match - 1);
+            let candidate = self.sorted[self.min_should_match - 1];
+            if candidate == NO_MORE_DOCS || self.sorted[..self.min_should_match].iter().all(|&d| d == candidate) {
+                self.doc = Some(candidate);
+                return Ok(candidate);
+            }
+
+            target = candidate;
+        }
+    }
+
+    /// Returns the number of sub-scorers matching the current document.
+    pub fn freq(&self) -> u32 {
+        let doc = self.doc_id();
+        self.sub_docs.iter().filter(|&&sub_doc| sub_doc == Some(doc)).count() as u32
+    }
+}
+
+impl DocIdSetIterator for MinShouldMatchSumScorer {
+    #[inline]
+    fn doc_id(&self) -> u32 {
+        self.doc.unwrap_or(NO_MORE_DOCS)
+    }
+
+    fn next_doc(&mut self) -> BoxResult<u32> {
+        match self.doc {
+            None => self.do_next(0),
+            Some(NO_MORE_DOCS) => Ok(NO_MORE_DOCS),
+            Some(doc) => self.do_next(doc + 1),
+        }
+    }
+
+    fn advance(&mut self, target: u32) -> BoxResult<u32> {
+        self.do_next(target)
+    }
+
+    #[inline]
+    fn cost(&self) -> u64 {
+        self.cost
+    }
+}
+
+impl Scorable for MinShouldMatchSumScorer {
+    fn score(&mut self) -> BoxResult<f32> {
+        let doc = self.doc_id();
+        let mut score = 0.0f64;
+        for (sub, sub_doc) in self.subs.iter_mut().zip(self.sub_docs.iter()) {
+            if *sub_doc == Some(doc) {
+                score += sub.score()? as f64;
+            }
+        }
+
+        Ok(score as f32)
+    }
+}
+
+impl Scorer for MinShouldMatchSumScorer {
+    fn max_score(&mut self, up_to: u32) -> BoxResult<f32> {
+        let mut max_score = 0.0f64;
+        for sub in self.subs.iter_mut() {
+            max_score += sub.max_score(up_to)? as f64;
+        }
+
+        Ok(max_score as f32)
+    }
+}
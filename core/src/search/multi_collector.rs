@@ -0,0 +1,173 @@
+use {
+    crate::{
+        index::LeafReaderContext,
+        search::{is_collection_terminated, Collector, CollectorManager, LeafCollector, Scorable, ScoreMode},
+        BoxResult, LuceneError,
+    },
+    std::fmt::Debug,
+};
+
+/// A [Collector] that passes every hit to two collectors, such as a top-hits collector and a facets collector.
+/// Nest multi-collectors to collect into more than two.
+///
+/// Scores are computed at most once per hit, however many of the collectors ask for them.
+#[derive(Debug)]
+pub struct MultiCollector<A, B> {
+    first: A,
+    second: B,
+}
+
+impl<A: Collector, B: Collector> MultiCollector<A, B> {
+    /// Creates a collector passing hits to both `first` and `second`.
+    pub fn new(first: A, second: B) -> Self {
+        Self {
+            first,
+            second,
+        }
+    }
+
+    /// Returns the wrapped collectors.
+    pub fn into_inner(self) -> (A, B) {
+        (self.first, self.second)
+    }
+}
+
+impl<A: Collector, B: Collector> Collector for MultiCollector<A, B> {
+    fn leaf_collector(&mut self, context: &LeafReaderContext) -> BoxResult<Box<dyn LeafCollector + '_>> {
+        let first = terminated_as_none(self.first.leaf_collector(context))?;
+        let second = terminated_as_none(self.second.leaf_collector(context))?;
+        if first.is_none() && second.is_none() {
+            return Err(LuceneError::CollectionTerminated.into());
+        }
+
+        Ok(Box::new(MultiLeafCollector {
+            first,
+            second,
+        }))
+    }
+
+    fn score_mode(&self) -> ScoreMode {
+        let (first, second) = (self.first.score_mode(), self.second.score_mode());
+        if first == second {
+            first
+        } else if first.needs_scores() || second.needs_scores() {
+            ScoreMode::Complete
+        } else {
+            ScoreMode::CompleteNoScores
+        }
+    }
+
+    fn ram_bytes_used(&self) -> usize {
+        self.first.ram_bytes_used() + self.second.ram_bytes_used()
+    }
+}
+
+/// Maps a leaf collector that terminated collection before it started to `None`.
+fn terminated_as_none<T>(result: BoxResult<T>) -> BoxResult<Option<T>> {
+    match result {
+        Ok(value) => Ok(Some(value)),
+        Err(e) if is_collection_terminated(&e) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// A leaf collector that has terminated collection is no longer called; collection terminates once both have.
+struct MultiLeafCollector<'a> {
+    first: Option<Box<dyn LeafCollector + 'a>>,
+    second: Option<Box<dyn LeafCollector + 'a>>,
+}
+
+impl MultiLeafCollector<'_> {
+    fn collect_into(
+        slot: &mut Option<Box<dyn LeafCollector + '_>>,
+        doc: u32,
+        scorer: &mut dyn Scorable,
+    ) -> BoxResult<()> {
+        if let Some(leaf_collector) = slot {
+            match leaf_collector.collect(doc, scorer) {
+                Err(e) if is_collection_terminated(&e) => {
+                    leaf_collector.finish()?;
+                    *slot = None;
+                }
+                result => result?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl LeafCollector for MultiLeafCollector<'_> {
+    fn collect(&mut self, doc: u32, scorer: &mut dyn Scorable) -> BoxResult<()> {
+        let mut scorer = ScoreCachingScorable {
+            inner: scorer,
+            score: None,
+        };
+        Self::collect_into(&mut self.first, doc, &mut scorer)?;
+        Self::collect_into(&mut self.second, doc, &mut scorer)?;
+
+        if self.first.is_none() && self.second.is_none() {
+            return Err(LuceneError::CollectionTerminated.into());
+        }
+
+        Ok(())
+    }
+
+    fn finish(&mut self) -> BoxResult<()> {
+        for leaf_collector in [&mut self.first, &mut self.second].into_iter().flatten() {
+            leaf_collector.finish()?;
+        }
+        Ok(())
+    }
+}
+
+/// Computes the score of the current document on first use and caches it.
+struct ScoreCachingScorable<'a> {
+    inner: &'a mut dyn Scorable,
+    score: Option<f32>,
+}
+
+impl Scorable for ScoreCachingScorable<'_> {
+    fn score(&mut self) -> BoxResult<f32> {
+        match self.score {
+            Some(score) => Ok(score),
+            None => {
+                let score = self.inner.score()?;
+                self.score = Some(score);
+                Ok(score)
+            }
+        }
+    }
+}
+
+/// A [CollectorManager] combining two managers, so that their results are computed in a single search. The result
+/// is the pair of their results.
+#[derive(Clone, Debug)]
+pub struct MultiCollectorManager<A, B> {
+    first: A,
+    second: B,
+}
+
+impl<A: CollectorManager, B: CollectorManager> MultiCollectorManager<A, B> {
+    /// Creates a manager combining `first` and `second`.
+    pub fn new(first: A, second: B) -> Self {
+        Self {
+            first,
+            second,
+        }
+    }
+}
+
+impl<A: CollectorManager, B: CollectorManager> CollectorManager for MultiCollectorManager<A, B> {
+    type Collector = MultiCollector<A::Collector, B::Collector>;
+    type Result = (A::Result, B::Result);
+
+    fn new_collector(&self) -> BoxResult<Self::Collector> {
+        Ok(MultiCollector::new(self.first.new_collector()?, self.second.new_collector()?))
+    }
+
+    fn reduce(&self, collectors: Vec<Self::Collector>) -> BoxResult<Self::Result> {
+        let (first, second): (Vec<_>, Vec<_>) = collectors.into_iter().map(MultiCollector::into_inner).unzip();
+        Ok((self.first.reduce(first)?, self.second.reduce(second)?))
+    }
+}
@@ -0,0 +1,274 @@
+use {
+    crate::{
+        analysis::Analyzer,
+        index::Term,
+        search::{
+            fmt_phrase, BooleanQuery, IndexSearcher, MatchNoDocsQuery, Occur, PhraseWeight, Query, ScoreMode,
+            TermQuery, Weight,
+        },
+        BoxResult, LuceneError,
+    },
+    std::{
+        fmt::{Display, Formatter, Result as FmtResult},
+        sync::Arc,
+    },
+};
+
+/// A [crate::search::PhraseQuery] whose positions may each hold several alternative terms, any of which matches
+/// there, such as synonyms injected by an analyzer or the expansions of a prefix.
+///
+/// The phrase is scored with the statistics of all of its terms, and its frequency counts the occurrences of any
+/// combination of the alternatives.
+#[derive(Clone, Debug)]
+pub struct MultiPhraseQuery {
+    field: String,
+    term_arrays: Vec<Vec<Term>>,
+    positions: Vec<u32>,
+    slop: u32,
+}
+
+impl MultiPhraseQuery {
+    /// Returns a builder for a new query.
+    #[inline]
+    pub fn builder() -> MultiPhraseQueryBuilder {
+        MultiPhraseQueryBuilder::default()
+    }
+
+    /// Creates a phrase from the tokens `analyzer` produces for `text`. Tokens at the same position, as with
+    /// synonyms, become alternatives, and gaps in the positions, as with removed stop words, are kept.
+    pub fn analyze(analyzer: &dyn Analyzer, field: &str, text: &str, slop: u32) -> Self {
+        let mut query = Self {
+            field: field.to_string(),
+            term_arrays: Vec::new(),
+            positions: Vec::new(),
+            slop,
+        };
+
+        let mut position = None;
+        for token in analyzer.analyze(field, text) {
+            let term = Term::from_text(field, &token.term);
+            match position {
+                Some(p) if token.position_increment == 0 => {
+                    debug_assert_eq!(query.positions.last(), Some(&p));
+                    query.term_arrays.last_mut().unwrap().push(term);
+                }
+                _ => {
+                    let next = position
+                        .map_or(token.position_increment.saturating_sub(1), |p: u32| p + token.position_increment);
+                    position = Some(next);
+                    query.term_arrays.push(vec![term]);
+                    query.positions.push(next);
+                }
+            }
+        }
+
+        query
+    }
+
+    /// Returns the field of the phrase.
+    #[inline]
+    pub fn field(&self) -> &str {
+        &self.field
+    }
+
+    /// Returns the alternative terms at each position of the phrase.
+    #[inline]
+    pub fn term_arrays(&self) -> &[Vec<Term>] {
+        &self.term_arrays
+    }
+
+    /// Returns each position of the phrase holding terms.
+    #[inline]
+    pub fn positions(&self) -> &[u32] {
+        &self.positions
+    }
+
+    /// Returns the number of positions the terms may be moved by.
+    #[inline]
+    pub fn slop(&self) -> u32 {
+        self.slop
+    }
+}
+
+impl Query for MultiPhraseQuery {
+    fn create_weight(
+        &self,
+        searcher: &IndexSearcher,
+        _score_mode: ScoreMode,
+        boost: f32,
+    ) -> BoxResult<Box<dyn Weight>> {
+        let positions = self.term_arrays.iter().cloned().zip(self.positions.iter().copied()).collect();
+        Ok(Box::new(PhraseWeight::new(searcher, &self.field, positions, self.slop, boost, self.to_string())?))
+    }
+
+    fn rewrite(&self, _searcher: &IndexSearcher) -> BoxResult<Option<Arc<dyn Query>>> {
+        match self.term_arrays.as_slice() {
+            [] => Ok(Some(Arc::new(MatchNoDocsQuery::new("empty MultiPhraseQuery")))),
+            [terms] if terms.len() == 1 => Ok(Some(Arc::new(TermQuery::new(terms[0].clone())))),
+            [terms] => {
+                let mut builder = BooleanQuery::builder();
+                for term in terms {
+                    builder.add(Arc::new(TermQuery::new(term.clone())), Occur::Should);
+                }
+                Ok(Some(Arc::new(builder.build())))
+            }
+            // Phrases are matched relative to their first position.
+            _ if self.positions[0] != 0 => {
+                let first = self.positions[0];
+                Ok(Some(Arc::new(Self {
+                    positions: self.positions.iter().map(|position| position - first).collect(),
+                    ..self.clone()
+                })))
+            }
+            _ => Ok(None),
+        }
+    }
+}
+
+impl Display for MultiPhraseQuery {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        let positions = self.term_arrays.iter().map(Vec::as_slice).zip(self.positions.iter().copied());
+        fmt_phrase(f, &self.field, positions, self.slop)
+    }
+}
+
+/// Builds a [MultiPhraseQuery].
+#[derive(Clone, Debug, Default)]
+pub struct MultiPhraseQueryBuilder {
+    term_arrays: Vec<Vec<Term>>,
+    positions: Vec<u32>,
+    slop: u32,
+}
+
+impl MultiPhraseQueryBuilder {
+    /// Adds alternative terms at the position after the last terms added.
+    pub fn add(&mut self, terms: Vec<Term>) -> &mut Self {
+        let position = self.positions.last().map_or(0, |last| last + 1);
+        self.add_at(terms, position)
+    }
+
+    /// Adds alternative terms at the given position, which may leave a gap after the last terms added.
+    pub fn add_at(&mut self, terms: Vec<Term>, position: u32) -> &mut Self {
+        self.term_arrays.push(terms);
+        self.positions.push(position);
+        self
+    }
+
+    /// Sets the number of positions the terms may be moved by.
+    pub fn set_slop(&mut self, slop: u32) -> &mut Self {
+        self.slop = slop;
+        self
+    }
+
+    /// Builds the query. This fails with [LuceneError::InvalidArgument] if a position has no terms, the terms aren't
+    /// all of the same field, or terms were added at a position before the last terms'.
+    pub fn build(&self) -> BoxResult<MultiPhraseQuery> {
+        if self.term_arrays.iter().any(Vec::is_empty) {
+            return Err(LuceneError::InvalidArgument("each position of a phrase needs a term".to_string()).into());
+        }
+
+        let field = self.term_arrays.first().map_or("", |terms| terms[0].field());
+        if let Some(term) = self.term_arrays.iter().flatten().find(|term| term.field() != field) {
+            return Err(LuceneError::InvalidArgument(format!(
+                "all terms of a phrase must be in the same field; got {field} and {}",
+                term.field()
+            ))
+            .into());
+        }
+
+        if let Some(pair) = self.positions.windows(2).find(|pair| pair[1] < pair[0]) {
+            return Err(LuceneError::InvalidArgument(format!(
+                "positions must be added in order; got {} after {}",
+                pair[1], pair[0]
+            ))
+            .into());
+        }
+
+        Ok(MultiPhraseQuery {
+            field: field.to_string(),
+            term_arrays: self.term_arrays.clone(),
+            positions: self.positions.clone(),
+            slop: self.slop,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{
+            analysis::{Analyzer, SimpleAnalyzer, Token},
+            index::Term,
+            search::{test_util::body_searcher, MultiPhraseQuery, Query},
+        },
+        pretty_assertions::assert_eq,
+    };
+
+    /// Injects "fast" as a synonym of "quick".
+    #[derive(Debug)]
+    struct SynonymAnalyzer;
+
+    impl Analyzer for SynonymAnalyzer {
+        fn analyze(&self, field: &str, text: &str) -> Vec<Token> {
+            let mut tokens = Vec::new();
+            for token in SimpleAnalyzer.analyze(field, text) {
+                let synonym = (token.term == "quick").then(|| Token {
+                    term: "fast".to_string(),
+                    position_increment: 0,
+                    ..token.clone()
+                });
+                tokens.push(token);
+                tokens.extend(synonym);
+            }
+            tokens
+        }
+    }
+
+    #[test]
+    fn test_multi_phrase_query() {
+        let searcher =
+            body_searcher(&["the quick brown fox", "the fast brown fox", "the slow brown fox", "quick and brown"]);
+
+        let query = MultiPhraseQuery::analyze(&SynonymAnalyzer, "body", "Quick brown", 0);
+        assert_eq!(query.positions(), &[0, 1]);
+        assert_eq!(query.term_arrays()[0], vec![Term::from_text("body", "quick"), Term::from_text("body", "fast")]);
+        assert_eq!(query.to_string(), "body:\"(quick fast) brown\"");
+
+        // Both synonyms match, scored with the idf of all three terms as Lucene does.
+        let top_docs = searcher.search(&query, 10).unwrap();
+        let mut docs: Vec<u32> = top_docs.score_docs.iter().map(|sd| sd.doc).collect();
+        docs.sort_unstable();
+        assert_eq!(docs, vec![0, 1]);
+        for sd in top_docs.score_docs.iter() {
+            assert!((sd.score - 0.8860533).abs() < 1e-5, "{sd:?}");
+        }
+
+        let query = MultiPhraseQuery::analyze(&SynonymAnalyzer, "body", "quick brown", 1);
+        assert_eq!(query.to_string(), "body:\"(quick fast) brown\"~1");
+        let top_docs = searcher.search(&query, 10).unwrap();
+        assert_eq!(top_docs.score_docs.len(), 3);
+        assert_eq!(top_docs.score_docs[2].doc, 3);
+        assert!((top_docs.score_docs[2].score - 0.6587107).abs() < 1e-5);
+        assert!(searcher.explain(&query, 3).unwrap().is_match());
+        assert!(!searcher.explain(&query, 2).unwrap().is_match());
+
+        // A single position is any of its terms.
+        let query = MultiPhraseQuery::analyze(&SynonymAnalyzer, "body", "quick", 0);
+        assert_eq!(query.rewrite(&searcher).unwrap().unwrap().to_string(), "body:quick body:fast");
+        assert_eq!(searcher.count(&query).unwrap(), 3);
+
+        let query = MultiPhraseQuery::builder()
+            .add(vec![Term::from_text("body", "the")])
+            .add_at(vec![Term::from_text("body", "fox"), Term::from_text("body", "dog")], 3)
+            .build()
+            .unwrap();
+        assert_eq!(query.to_string(), "body:\"the ? ? (fox dog)\"");
+        assert_eq!(searcher.count(&query).unwrap(), 3);
+
+        assert!(MultiPhraseQuery::builder().add(vec![]).build().is_err());
+        assert!(MultiPhraseQuery::builder()
+            .add(vec![Term::from_text("body", "quick"), Term::from_text("title", "fast")])
+            .build()
+            .is_err());
+    }
+}
@@ -0,0 +1,130 @@
+use {
+    crate::{
+        search::{IndexSearcher, PhraseQuery, Query, ScoreMode, Weight},
+        BoxResult,
+    },
+    std::{
+        fmt::{Display, Formatter, Result as FmtResult},
+        sync::Arc,
+    },
+};
+
+/// A [PhraseQuery] over the overlapping n-grams of a field, such as the bigrams of
+/// [crate::analysis::CJKBigramAnalyzer], that skips the terms covered by their neighbours.
+///
+/// Each n-gram of an exact phrase is implied by the n-grams at every n-th position and the last one, so the query is
+/// rewritten to match only those: `東京 京都 都庁` needs only `東京 ? 都庁`. This reads fewer postings, at the cost of
+/// matching n-grams from different runs of text that happen to be at the right positions.
+#[derive(Clone, Debug)]
+pub struct NGramPhraseQuery {
+    n: usize,
+    phrase_query: PhraseQuery,
+}
+
+impl NGramPhraseQuery {
+    /// Creates a query for a phrase of n-grams of size `n`.
+    pub fn new(n: usize, phrase_query: PhraseQuery) -> Self {
+        Self {
+            n,
+            phrase_query,
+        }
+    }
+
+    /// Returns the size of the n-grams.
+    #[inline]
+    pub fn n(&self) -> usize {
+        self.n
+    }
+
+    /// Returns the phrase of n-grams.
+    #[inline]
+    pub fn phrase_query(&self) -> &PhraseQuery {
+        &self.phrase_query
+    }
+}
+
+impl Query for NGramPhraseQuery {
+    fn create_weight(&self, searcher: &IndexSearcher, score_mode: ScoreMode, boost: f32) -> BoxResult<Box<dyn Weight>> {
+        self.phrase_query.create_weight(searcher, score_mode, boost)
+    }
+
+    fn rewrite(&self, _searcher: &IndexSearcher) -> BoxResult<Option<Arc<dyn Query>>> {
+        let terms = self.phrase_query.terms();
+        let positions = self.phrase_query.positions();
+
+        // Only an exact phrase of consecutive n-grams implies the n-grams that are skipped.
+        let optimizable = self.phrase_query.slop() == 0
+            && self.n >= 2
+            && terms.len() >= 3
+            && positions.iter().enumerate().all(|(i, &position)| position == i as u32);
+        if !optimizable {
+            return Ok(Some(Arc::new(self.phrase_query.clone())));
+        }
+
+        let mut builder = PhraseQuery::builder();
+        for (i, term) in terms.iter().enumerate() {
+            if i % self.n == 0 || i == terms.len() - 1 {
+                builder.add_at(term.clone(), i as u32);
+            }
+        }
+        Ok(Some(Arc::new(builder.build()?)))
+    }
+}
+
+impl Display for NGramPhraseQuery {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        self.phrase_query.fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{
+            analysis::CJKBigramAnalyzer,
+            document::{Document, Field, Store},
+            index::Term,
+            search::{
+                test_util::{leaf_searcher, segment_with_analyzer},
+                NGramPhraseQuery, PhraseQuery, Query,
+            },
+        },
+        pretty_assertions::assert_eq,
+        std::sync::Arc,
+    };
+
+    #[test]
+    fn test_n_gram_phrase_query() {
+        let documents = ["東京都庁の展望室", "京都の都庁", "東京と大阪"]
+            .map(|body| Document::from_iter([Field::text("body", body, Store::No)]));
+        let searcher = leaf_searcher(vec![segment_with_analyzer(Arc::new(CJKBigramAnalyzer), documents)]);
+
+        let query = NGramPhraseQuery::new(2, PhraseQuery::new("body", &["東京", "京都", "都庁"]));
+        assert_eq!(query.to_string(), "body:\"東京 京都 都庁\"");
+        let rewritten = query.rewrite(&searcher).unwrap().unwrap();
+        assert_eq!(rewritten.to_string(), "body:\"東京 ? 都庁\"");
+        assert_eq!(
+            searcher.search(&query, 10).unwrap().score_docs.iter().map(|sd| sd.doc).collect::<Vec<_>>(),
+            vec![0]
+        );
+
+        // The last n-gram is always kept.
+        let query = NGramPhraseQuery::new(2, PhraseQuery::new("body", &["東京", "京都", "都庁", "庁の"]));
+        assert_eq!(query.rewrite(&searcher).unwrap().unwrap().to_string(), "body:\"東京 ? 都庁 庁の\"");
+        assert_eq!(searcher.count(&query).unwrap(), 1);
+
+        // Sloppy or gapped phrases, and phrases too short to skip anything, are left alone.
+        let sloppy = PhraseQuery::builder()
+            .add(Term::from_text("body", "東京"))
+            .add(Term::from_text("body", "京都"))
+            .add(Term::from_text("body", "都庁"))
+            .set_slop(1)
+            .build()
+            .unwrap();
+        let rewritten = NGramPhraseQuery::new(2, sloppy).rewrite(&searcher).unwrap().unwrap();
+        assert_eq!(rewritten.to_string(), "body:\"東京 京都 都庁\"~1");
+        let short = NGramPhraseQuery::new(2, PhraseQuery::new("body", &["東京", "京都"]));
+        assert_eq!(short.rewrite(&searcher).unwrap().unwrap().to_string(), "body:\"東京 京都\"");
+        assert_eq!(searcher.count(&short).unwrap(), 1);
+    }
+}
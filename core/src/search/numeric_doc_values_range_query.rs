@@ -0,0 +1,290 @@
+use {
+    crate::{
+        index::{DocValuesSkipper, LeafReaderContext, NumericDocValues},
+        search::{
+            ConstantScoreScorer, DocIdSetIterator, Explanation, IndexSearcher, Query, ScoreMode, Scorer,
+            TwoPhaseIterator, Weight, NO_MORE_DOCS,
+        },
+        BoxResult,
+    },
+    std::fmt::{Display, Formatter, Result as FmtResult},
+};
+
+/// A query matching the documents whose numeric doc value, stored with [crate::document::Field::numeric_doc_values],
+/// is within an inclusive range, with a constant score.
+///
+/// There is no points index to find the matches: the query checks the value of each candidate document. Where the
+/// segment has a skip index over the field (see [crate::index::LeafReader::doc_values_skipper]), it rules out whole
+/// blocks of documents whose values are all outside the range, and accepts blocks whose values are all inside it
+/// without reading them.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct NumericDocValuesRangeQuery {
+    field: String,
+    lower: i64,
+    upper: i64,
+}
+
+impl NumericDocValuesRangeQuery {
+    /// Creates a query matching the values of `field` from `lower` to `upper`, inclusive. If `lower` is greater than
+    /// `upper`, nothing matches.
+    pub fn new(field: &str, lower: i64, upper: i64) -> Self {
+        Self {
+            field: field.to_string(),
+            lower,
+            upper,
+        }
+    }
+
+    /// Returns the field holding the values.
+    #[inline]
+    pub fn field(&self) -> &str {
+        &self.field
+    }
+
+    /// Returns the smallest matching value.
+    #[inline]
+    pub fn lower(&self) -> i64 {
+        self.lower
+    }
+
+    /// Returns the largest matching value.
+    #[inline]
+    pub fn upper(&self) -> i64 {
+        self.upper
+    }
+
+    #[inline]
+    fn contains(&self, value: i64) -> bool {
+        self.lower <= value && value <= self.upper
+    }
+}
+
+impl Query for NumericDocValuesRangeQuery {
+    fn create_weight(
+        &self,
+        _searcher: &IndexSearcher,
+        _score_mode: ScoreMode,
+        boost: f32,
+    ) -> BoxResult<Box<dyn Weight>> {
+        Ok(Box::new(NumericDocValuesRangeWeight {
+            query: self.clone(),
+            score: boost,
+        }))
+    }
+}
+
+impl Display for NumericDocValuesRangeQuery {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "{}:[{} TO {}]", self.field, self.lower, self.upper)
+    }
+}
+
+#[derive(Debug)]
+struct NumericDocValuesRangeWeight {
+    query: NumericDocValuesRangeQuery,
+    score: f32,
+}
+
+impl Weight for NumericDocValuesRangeWeight {
+    fn scorer(&self, context: &LeafReaderContext) -> BoxResult<Option<Box<dyn Scorer>>> {
+        if self.query.lower > self.query.upper {
+            return Ok(None);
+        }
+        let Some(doc_values) = context.reader().numeric_doc_values(self.query.field())? else {
+            return Ok(None);
+        };
+
+        let skipper = context.reader().doc_values_skipper(self.query.field())?;
+        if let Some(skipper) = &skipper {
+            if skipper.global_max_value() < self.query.lower || skipper.global_min_value() > self.query.upper {
+                return Ok(None);
+            }
+        }
+
+        let two_phase = InRange {
+            approximation: SkippingDocValues {
+                doc_values,
+                skipper,
+                lower: self.query.lower,
+                upper: self.query.upper,
+                all_match_up_to: None,
+                started: false,
+            },
+            query: self.query.clone(),
+        };
+        Ok(Some(Box::new(ConstantScoreScorer::with_two_phase(self.score, Box::new(two_phase)))))
+    }
+
+    fn explain(&self, context: &LeafReaderContext, doc: u32) -> BoxResult<Explanation> {
+        let Some(mut doc_values) = context.reader().numeric_doc_values(self.query.field())? else {
+            return Ok(Explanation::no_match(format!("no values in field {}", self.query.field()), vec![]));
+        };
+
+        if !doc_values.advance_exact(doc)? {
+            return Ok(Explanation::no_match(format!("document {doc} has no value"), vec![]));
+        }
+
+        let value = doc_values.long_value()?;
+        if self.query.contains(value) {
+            Ok(Explanation::constant(self.score, format!("{}, with value {value}", self.query)))
+        } else {
+            Ok(Explanation::no_match(format!("document {doc} has value {value}"), vec![]))
+        }
+    }
+}
+
+/// The documents with a value, skipping the blocks of the skip index whose values are all out of range.
+#[derive(Debug)]
+struct SkippingDocValues {
+    doc_values: Box<dyn NumericDocValues>,
+    skipper: Option<Box<dyn DocValuesSkipper>>,
+    lower: i64,
+    upper: i64,
+    /// The last document of the current block, if every value in the block is in range.
+    all_match_up_to: Option<u32>,
+    started: bool,
+}
+
+impl DocIdSetIterator for SkippingDocValues {
+    #[inline]
+    fn doc_id(&self) -> u32 {
+        self.doc_values.doc_id()
+    }
+
+    fn next_doc(&mut self) -> BoxResult<u32> {
+        match self.started {
+            false => self.advance(0),
+            true if self.doc_id() == NO_MORE_DOCS => Ok(NO_MORE_DOCS),
+            true => self.advance(self.doc_id() + 1),
+        }
+    }
+
+    fn advance(&mut self, mut target: u32) -> BoxResult<u32> {
+        self.started = true;
+        self.all_match_up_to = None;
+        if let Some(skipper) = &mut self.skipper {
+            while target != NO_MORE_DOCS {
+                skipper.advance(target)?;
+                if skipper.min_doc_id() == NO_MORE_DOCS {
+                    target = NO_MORE_DOCS;
+                } else if skipper.max_value() < self.lower || skipper.min_value() > self.upper {
+                    target = skipper.max_doc_id().saturating_add(1);
+                } else {
+                    if self.lower <= skipper.min_value() && skipper.max_value() <= self.upper {
+                        self.all_match_up_to = Some(skipper.max_doc_id());
+                    }
+                    break;
+                }
+            }
+        }
+
+        self.doc_values.advance(target)
+    }
+
+    #[inline]
+    fn cost(&self) -> u64 {
+        self.doc_values.cost()
+    }
+}
+
+/// Confirms the documents whose value is in range.
+#[derive(Debug)]
+struct InRange {
+    approximation: SkippingDocValues,
+    query: NumericDocValuesRangeQuery,
+}
+
+impl TwoPhaseIterator for InRange {
+    fn approximation(&self) -> &dyn DocIdSetIterator {
+        &self.approximation
+    }
+
+    fn approximation_mut(&mut self) -> &mut dyn DocIdSetIterator {
+        &mut self.approximation
+    }
+
+    fn matches(&mut self) -> BoxResult<bool> {
+        let doc = self.approximation.doc_id();
+        if self.approximation.all_match_up_to.is_some_and(|last| doc <= last) {
+            return Ok(true);
+        }
+        Ok(self.query.contains(self.approximation.doc_values.long_value()?))
+    }
+
+    fn match_cost(&self) -> f32 {
+        // Reading and comparing one value.
+        2.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{
+            document::{Document, Field, NumericDocValuesField, Store},
+            index::Term,
+            search::{
+                test_util, BooleanQuery, IndexSearcher, NumericDocValuesRangeQuery, Occur, Query, ScoreMode, TermQuery,
+                NO_MORE_DOCS,
+            },
+        },
+        pretty_assertions::assert_eq,
+        std::sync::Arc,
+    };
+
+    #[test]
+    fn test_range_query() {
+        // Timestamps increase with the document id, so the skip index can rule out most blocks.
+        let mut documents = Vec::new();
+        for i in 0..20_000 {
+            let mut doc = Document::new();
+            doc.add(Field::text(
+                "kind",
+                if i % 2 == 0 {
+                    "even"
+                } else {
+                    "odd"
+                },
+                Store::No,
+            ));
+            if i % 10 != 3 {
+                doc.add(Field::numeric_doc_values("timestamp", 1_000 + i));
+            }
+            documents.push(doc);
+        }
+        let reader = test_util::reader([documents]);
+        let searcher = IndexSearcher::new(reader.clone());
+
+        let query = NumericDocValuesField::new_slow_range_query("timestamp", 10_000, 10_019);
+        assert_eq!(query.to_string(), "timestamp:[10000 TO 10019]");
+        let mut docs: Vec<u32> = searcher.search(&query, 100).unwrap().score_docs.iter().map(|sd| sd.doc).collect();
+        docs.sort_unstable();
+        assert_eq!(docs, (9_000..9_020).filter(|doc| doc % 10 != 3).collect::<Vec<_>>());
+
+        // Whole blocks inside the range match without checking values; documents without a value never match.
+        assert_eq!(searcher.count(&NumericDocValuesRangeQuery::new("timestamp", 0, i64::MAX)).unwrap(), 18_000);
+        assert_eq!(searcher.count(&NumericDocValuesRangeQuery::new("timestamp", 5_000, 15_999)).unwrap(), 9_900);
+        assert_eq!(searcher.count(&NumericDocValuesRangeQuery::new("timestamp", 30_000, 40_000)).unwrap(), 0);
+        assert_eq!(searcher.count(&NumericDocValuesRangeQuery::new("timestamp", 10, 5)).unwrap(), 0);
+        assert_eq!(searcher.count(&NumericDocValuesRangeQuery::new("missing", 0, 10)).unwrap(), 0);
+        assert_eq!(searcher.count(&NumericDocValuesField::new_slow_exact_query("timestamp", 1_002)).unwrap(), 1);
+
+        // The approximation skips to the first block that can match.
+        let weight = query.create_weight(&searcher, ScoreMode::CompleteNoScores, 1.0).unwrap();
+        let mut scorer = weight.scorer(&reader.leaves()[0]).unwrap().unwrap();
+        let two_phase = scorer.two_phase_iterator().unwrap();
+        assert_eq!(two_phase.approximation_mut().next_doc().unwrap(), 4_551);
+        assert_eq!(two_phase.approximation_mut().advance(9_102).unwrap(), NO_MORE_DOCS);
+
+        assert!(searcher.explain(&query, 9_000).unwrap().is_match());
+        assert!(!searcher.explain(&query, 9_003).unwrap().is_match());
+        assert!(!searcher.explain(&query, 8_999).unwrap().is_match());
+
+        // As a filter next to a text query.
+        let query = BooleanQuery::builder()
+            .add(Arc::new(TermQuery::new(Term::from_text("kind", "odd"))), Occur::Must)
+            .add(Arc::new(query), Occur::Filter)
+            .build();
+        assert_eq!(searcher.count(&query).unwrap(), 8);
+    }
+}
@@ -0,0 +1,31 @@
+use std::fmt::Debug;
+
+/// Turns the payload of an occurrence of a term into a factor for the score of a
+/// [crate::search::PayloadScoreQuery].
+pub trait PayloadDecoder: Debug + Send + Sync {
+    /// Computes the factor of a payload, or of an occurrence without one.
+    fn compute_payload_factor(&self, payload: Option<&[u8]>) -> f32;
+}
+
+/// A [PayloadDecoder] for payloads holding a big-endian [f32], as written by [FloatPayloadDecoder::encode]. An
+/// occurrence without a payload, or with a payload of another length, has a factor of 1.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FloatPayloadDecoder;
+
+impl FloatPayloadDecoder {
+    /// Encodes a value as a payload, such as the weight of a term from a learned sparse model, for an analyzer to
+    /// attach to a token with [crate::analysis::Token::with_payload].
+    #[inline]
+    pub fn encode(value: f32) -> [u8; 4] {
+        value.to_be_bytes()
+    }
+}
+
+impl PayloadDecoder for FloatPayloadDecoder {
+    fn compute_payload_factor(&self, payload: Option<&[u8]>) -> f32 {
+        match payload.map(<[u8; 4]>::try_from) {
+            Some(Ok(bytes)) => f32::from_be_bytes(bytes),
+            _ => 1.0,
+        }
+    }
+}
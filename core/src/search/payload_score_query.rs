@@ -0,0 +1,426 @@
+use {
+    crate::{
+        index::{LeafReaderContext, PostingsEnum, Term},
+        search::{
+            DocIdSetIterator, Explanation, IndexSearcher, PayloadDecoder, Query, Scorable, ScoreMode, Scorer,
+            SimScorer, Weight,
+        },
+        BoxResult, LuceneError,
+    },
+    std::{
+        fmt::{Display, Formatter, Result as FmtResult},
+        sync::Arc,
+    },
+};
+
+/// How a [PayloadScoreQuery] combines the factors of the payloads of a term in a document. A document whose
+/// occurrences have no payloads scores a factor of 1, unless the [PayloadDecoder] says otherwise.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PayloadFunction {
+    /// The sum of the factors.
+    Sum,
+
+    /// The largest factor.
+    Max,
+
+    /// The smallest factor.
+    Min,
+
+    /// The mean of the factors.
+    Average,
+}
+
+impl PayloadFunction {
+    /// Folds the factor of the next payload into the score of the payloads seen so far.
+    fn current_score(self, payloads_seen: u32, current_score: f32, factor: f32) -> f32 {
+        match self {
+            _ if payloads_seen == 0 => factor,
+            Self::Sum | Self::Average => current_score + factor,
+            Self::Max => current_score.max(factor),
+            Self::Min => current_score.min(factor),
+        }
+    }
+
+    /// Returns the factor of a document from the score of its payloads.
+    fn doc_score(self, payloads_seen: u32, payload_score: f32) -> f32 {
+        match self {
+            _ if payloads_seen == 0 => 1.0,
+            Self::Average => payload_score / payloads_seen as f32,
+            _ => payload_score,
+        }
+    }
+}
+
+impl Display for PayloadFunction {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match self {
+            Self::Sum => write!(f, "sum"),
+            Self::Max => write!(f, "max"),
+            Self::Min => write!(f, "min"),
+            Self::Average => write!(f, "average"),
+        }
+    }
+}
+
+/// A query matching the documents containing a term, scored by the payloads of its occurrences, such as term weights
+/// from a learned sparse retrieval model attached at index time with [crate::analysis::Token::with_payload].
+///
+/// Each payload is turned into a factor by a [PayloadDecoder], and the factors of a document are combined by a
+/// [PayloadFunction]. The document's score is that factor, multiplied by the term's similarity score if it's
+/// included, or by the query's boost otherwise. A query of several weighted terms is a
+/// [crate::search::BooleanQuery] of [crate::search::BoostQuery] clauses around payload score queries.
+///
+/// The field must have been indexed with positions.
+#[derive(Clone, Debug)]
+pub struct PayloadScoreQuery {
+    term: Term,
+    function: PayloadFunction,
+    decoder: Arc<dyn PayloadDecoder>,
+    include_term_score: bool,
+}
+
+impl PayloadScoreQuery {
+    /// Creates a query for `term`, scored by `function` over the factors `decoder` computes from its payloads, times
+    /// the term's similarity score if `include_term_score` is set.
+    pub fn new(
+        term: Term,
+        function: PayloadFunction,
+        decoder: Arc<dyn PayloadDecoder>,
+        include_term_score: bool,
+    ) -> Self {
+        Self {
+            term,
+            function,
+            decoder,
+            include_term_score,
+        }
+    }
+
+    /// Returns the term being queried.
+    #[inline]
+    pub fn term(&self) -> &Term {
+        &self.term
+    }
+
+    /// Returns how the factors of the payloads in a document are combined.
+    #[inline]
+    pub fn function(&self) -> PayloadFunction {
+        self.function
+    }
+
+    /// Returns the decoder computing the factor of each payload.
+    #[inline]
+    pub fn decoder(&self) -> &Arc<dyn PayloadDecoder> {
+        &self.decoder
+    }
+
+    /// Indicates whether the score is multiplied by the term's similarity score.
+    #[inline]
+    pub fn include_term_score(&self) -> bool {
+        self.include_term_score
+    }
+}
+
+impl Query for PayloadScoreQuery {
+    fn create_weight(
+        &self,
+        searcher: &IndexSearcher,
+        _score_mode: ScoreMode,
+        boost: f32,
+    ) -> BoxResult<Box<dyn Weight>> {
+        let collection_stats = searcher.collection_statistics(self.term.field())?;
+        let term_stats = searcher.term_statistics(&self.term)?;
+
+        // If the term doesn't occur anywhere, no segment will produce a scorer.
+        let sim_scorer = match (collection_stats, term_stats) {
+            (Some(cs), Some(ts)) => Some(Arc::from(searcher.similarity().scorer(boost, &cs, &[ts]))),
+            _ => None,
+        };
+
+        Ok(Box::new(PayloadScoreWeight {
+            query: self.clone(),
+            sim_scorer,
+            boost,
+        }))
+    }
+}
+
+impl Display for PayloadScoreQuery {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(
+            f,
+            "PayloadScoreQuery({}, function={}, includeTermScore={})",
+            self.term, self.function, self.include_term_score
+        )
+    }
+}
+
+#[derive(Debug)]
+struct PayloadScoreWeight {
+    query: PayloadScoreQuery,
+    sim_scorer: Option<Arc<dyn SimScorer>>,
+    boost: f32,
+}
+
+impl PayloadScoreWeight {
+    fn payload_scorer(&self, context: &LeafReaderContext) -> BoxResult<Option<PayloadScorer>> {
+        let Some(sim_scorer) = &self.sim_scorer else {
+            return Ok(None);
+        };
+
+        let reader = context.reader();
+        let field = self.query.term.field();
+        let Some(terms) = reader.terms(field)? else {
+            return Ok(None);
+        };
+
+        if !terms.has_positions() {
+            return Err(LuceneError::IllegalState(format!(
+                "field {field} was indexed without position data; cannot run {}",
+                self.query
+            ))
+            .into());
+        }
+
+        let mut te = terms.iterator()?;
+        if !te.seek_exact(self.query.term.bytes())? {
+            return Ok(None);
+        }
+
+        Ok(Some(PayloadScorer {
+            postings: te.postings()?,
+            function: self.query.function,
+            decoder: self.query.decoder.clone(),
+            sim_scorer: self.query.include_term_score.then(|| sim_scorer.clone()),
+            norms: reader.norms(field)?,
+            boost: self.boost,
+            payload_score: None,
+        }))
+    }
+}
+
+impl Weight for PayloadScoreWeight {
+    fn scorer(&self, context: &LeafReaderContext) -> BoxResult<Option<Box<dyn Scorer>>> {
+        Ok(self.payload_scorer(context)?.map(|scorer| Box::new(scorer) as Box<dyn Scorer>))
+    }
+
+    fn explain(&self, context: &LeafReaderContext, doc: u32) -> BoxResult<Explanation> {
+        let Some(mut scorer) = self.payload_scorer(context)? else {
+            return Ok(Explanation::no_match(format!("no matching term for {}", self.query.term), vec![]));
+        };
+
+        if scorer.advance(doc)? != doc {
+            return Ok(Explanation::no_match(format!("{} doesn't occur in document {doc}", self.query.term), vec![]));
+        }
+
+        let (payloads_seen, payload_score) = scorer.payload_score()?;
+        let payload_explanation = Explanation::matched(
+            payload_score,
+            format!("{} function of {payloads_seen} payloads", self.query.function),
+            vec![],
+        );
+        let term_explanation = match &scorer.sim_scorer {
+            Some(sim_scorer) => sim_scorer.explain(scorer.postings.freq()? as f32, scorer.norm()),
+            None => Explanation::matched(self.boost, "boost", vec![]),
+        };
+
+        Ok(Explanation::matched(
+            payload_score * term_explanation.value(),
+            format!("weight({} in {doc}), product of:", self.query),
+            vec![payload_explanation, term_explanation],
+        ))
+    }
+}
+
+/// A [Scorer] over the postings of a term, scored by their payloads.
+#[derive(Debug)]
+struct PayloadScorer {
+    postings: Box<dyn PostingsEnum>,
+    function: PayloadFunction,
+    decoder: Arc<dyn PayloadDecoder>,
+
+    /// The term's similarity scorer, if its score is included.
+    sim_scorer: Option<Arc<dyn SimScorer>>,
+    norms: Option<Arc<[i64]>>,
+    boost: f32,
+
+    /// The document whose payloads were last read, with the number of payloads and their combined factor. Positions
+    /// can only be read once, so this is kept for repeated calls to [Scorable::score].
+    payload_score: Option<(u32, u32, f32)>,
+}
+
+impl PayloadScorer {
+    fn norm(&self) -> i64 {
+        self.norms.as_ref().and_then(|norms| norms.get(self.postings.doc_id() as usize).copied()).unwrap_or(1)
+    }
+
+    /// Returns the number of payloads of the current document and their combined factor.
+    fn payload_score(&mut self) -> BoxResult<(u32, f32)> {
+        let doc = self.postings.doc_id();
+        if let Some((scored_doc, payloads_seen, payload_score)) = self.payload_score {
+            if scored_doc == doc {
+                return Ok((payloads_seen, payload_score));
+            }
+        }
+
+        let (mut payloads_seen, mut current_score) = (0, 0.0);
+        for _ in 0..self.postings.freq()? {
+            if self.postings.next_position()?.is_none() {
+                break;
+            }
+            let factor = self.decoder.compute_payload_factor(self.postings.payload()?);
+            current_score = self.function.current_score(payloads_seen, current_score, factor);
+            payloads_seen += 1;
+        }
+
+        let payload_score = self.function.doc_score(payloads_seen, current_score);
+        self.payload_score = Some((doc, payloads_seen, payload_score));
+        Ok((payloads_seen, payload_score))
+    }
+}
+
+impl DocIdSetIterator for PayloadScorer {
+    #[inline]
+    fn doc_id(&self) -> u32 {
+        self.postings.doc_id()
+    }
+
+    #[inline]
+    fn next_doc(&mut self) -> BoxResult<u32> {
+        self.postings.next_doc()
+    }
+
+    #[inline]
+    fn advance(&mut self, target: u32) -> BoxResult<u32> {
+        self.postings.advance(target)
+    }
+
+    #[inline]
+    fn cost(&self) -> u64 {
+        self.postings.cost()
+    }
+}
+
+impl Scorable for PayloadScorer {
+    fn score(&mut self) -> BoxResult<f32> {
+        let (_, payload_score) = self.payload_score()?;
+        Ok(match &self.sim_scorer {
+            Some(sim_scorer) => payload_score * sim_scorer.score(self.postings.freq()? as f32, self.norm()),
+            None => payload_score * self.boost,
+        })
+    }
+}
+
+impl Scorer for PayloadScorer {}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{
+            analysis::{Analyzer, Token},
+            document::{Document, Field, Store},
+            index::Term,
+            search::{
+                test_util::{leaf_searcher, segment_with_analyzer},
+                BooleanQuery, BoostQuery, FloatPayloadDecoder, IndexSearcher, Occur, PayloadFunction,
+                PayloadScoreQuery, TermQuery,
+            },
+        },
+        pretty_assertions::assert_eq,
+        std::sync::Arc,
+    };
+
+    /// Splits text on whitespace into `term|weight` tokens, with the weight as a float payload.
+    #[derive(Debug)]
+    struct WeightedTermsAnalyzer;
+
+    impl Analyzer for WeightedTermsAnalyzer {
+        fn analyze(&self, _field: &str, text: &str) -> Vec<Token> {
+            let mut tokens = Vec::new();
+            let mut offset = 0;
+            for word in text.split(' ') {
+                let (term, weight) = word.split_once('|').unwrap_or((word, ""));
+                let token = Token::new(term, offset as u32, (offset + term.len()) as u32);
+                tokens.push(match weight.parse::<f32>() {
+                    Ok(weight) => token.with_payload(FloatPayloadDecoder::encode(weight)),
+                    Err(_) => token,
+                });
+                offset += word.len() + 1;
+            }
+            tokens
+        }
+    }
+
+    fn searcher() -> IndexSearcher {
+        let bodies = ["rust|2.5 search|0.5 rust|1.5", "rust|0.25 engine|3", "search engine", "rust"];
+        let documents = bodies.map(|body| Document::from_iter([Field::text("body", body, Store::No)]));
+        leaf_searcher(vec![segment_with_analyzer(Arc::new(WeightedTermsAnalyzer), documents)])
+    }
+
+    fn scores(searcher: &IndexSearcher, query: &PayloadScoreQuery) -> Vec<(u32, f32)> {
+        let mut scores: Vec<(u32, f32)> =
+            searcher.search(query, 10).unwrap().score_docs.iter().map(|sd| (sd.doc, sd.score)).collect();
+        scores.sort_by_key(|(doc, _)| *doc);
+        for &(doc, score) in scores.iter() {
+            assert!((searcher.explain(query, doc).unwrap().value() - score).abs() < 1e-6);
+        }
+        scores
+    }
+
+    #[test]
+    fn test_payload_functions() {
+        let searcher = searcher();
+        let rust = |function| {
+            PayloadScoreQuery::new(Term::from_text("body", "rust"), function, Arc::new(FloatPayloadDecoder), false)
+        };
+
+        // Document 3 has no payloads, so its factor is 1.
+        assert_eq!(scores(&searcher, &rust(PayloadFunction::Sum)), vec![(0, 4.0), (1, 0.25), (3, 1.0)]);
+        assert_eq!(scores(&searcher, &rust(PayloadFunction::Max)), vec![(0, 2.5), (1, 0.25), (3, 1.0)]);
+        assert_eq!(scores(&searcher, &rust(PayloadFunction::Min)), vec![(0, 1.5), (1, 0.25), (3, 1.0)]);
+        assert_eq!(scores(&searcher, &rust(PayloadFunction::Average)), vec![(0, 2.0), (1, 0.25), (3, 1.0)]);
+        assert_eq!(
+            rust(PayloadFunction::Average).to_string(),
+            "PayloadScoreQuery(body:rust, function=average, includeTermScore=false)"
+        );
+
+        let explanation = searcher.explain(&rust(PayloadFunction::Sum), 0).unwrap();
+        assert_eq!(explanation.details()[0].description(), "sum function of 2 payloads");
+        assert!(!searcher.explain(&rust(PayloadFunction::Sum), 2).unwrap().is_match());
+
+        // With the term score, the payload factor multiplies the similarity score.
+        let with_term_score = PayloadScoreQuery::new(
+            Term::from_text("body", "rust"),
+            PayloadFunction::Max,
+            Arc::new(FloatPayloadDecoder),
+            true,
+        );
+        let term_scores = searcher.search(&TermQuery::new(Term::from_text("body", "rust")), 10).unwrap().score_docs;
+        let term_score = |doc| term_scores.iter().find(|sd| sd.doc == doc).unwrap().score;
+        let scores = scores(&searcher, &with_term_score);
+        assert!((scores[0].1 - 2.5 * term_score(0)).abs() < 1e-6);
+        assert!((scores[1].1 - 0.25 * term_score(1)).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_learned_sparse_retrieval() {
+        let searcher = searcher();
+
+        // Each query term is weighted by the model, and each document term by its payload.
+        let mut builder = BooleanQuery::builder();
+        for (term, weight) in [("rust", 1.0), ("engine", 2.0)] {
+            let query = PayloadScoreQuery::new(
+                Term::from_text("body", term),
+                PayloadFunction::Max,
+                Arc::new(FloatPayloadDecoder),
+                false,
+            );
+            builder.add(Arc::new(BoostQuery::new(Arc::new(query), weight).unwrap()), Occur::Should);
+        }
+        let hits = searcher.search(&builder.build(), 10).unwrap().score_docs;
+        assert_eq!(
+            hits.iter().map(|sd| (sd.doc, sd.score)).collect::<Vec<_>>(),
+            vec![(1, 6.25), (0, 2.5), (2, 2.0), (3, 1.0)]
+        );
+    }
+}
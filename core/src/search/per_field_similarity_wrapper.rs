@@ -0,0 +1,107 @@
+use {
+    crate::search::{BM25Similarity, CollectionStatistics, FieldInvertState, SimScorer, Similarity, TermStatistics},
+    std::{collections::HashMap, sync::Arc},
+};
+
+/// A [Similarity] that delegates to a different similarity for each field, falling back to a default for fields
+/// without their own, as in Lucene's `PerFieldSimilarityWrapper`. This lets, for example, a body field be scored
+/// with [BM25Similarity] while tag fields are scored with [crate::search::BooleanSimilarity].
+///
+/// Norms are computed by the field's similarity, and scorers are created by the similarity of the field named in the
+/// collection statistics.
+#[derive(Clone, Debug)]
+pub struct PerFieldSimilarityWrapper {
+    default: Arc<dyn Similarity>,
+    fields: HashMap<String, Arc<dyn Similarity>>,
+}
+
+impl Default for PerFieldSimilarityWrapper {
+    fn default() -> Self {
+        Self::new(Arc::new(BM25Similarity::default()))
+    }
+}
+
+impl PerFieldSimilarityWrapper {
+    /// Creates a wrapper that uses `default` for every field.
+    pub fn new(default: Arc<dyn Similarity>) -> Self {
+        Self {
+            default,
+            fields: HashMap::new(),
+        }
+    }
+
+    /// Sets the similarity for a field.
+    pub fn set(&mut self, field: &str, similarity: Arc<dyn Similarity>) -> &mut Self {
+        self.fields.insert(field.to_string(), similarity);
+        self
+    }
+
+    /// Returns the similarity for a field.
+    #[inline]
+    pub fn get(&self, field: &str) -> &Arc<dyn Similarity> {
+        self.fields.get(field).unwrap_or(&self.default)
+    }
+
+    /// Returns the similarity for fields without their own.
+    #[inline]
+    pub fn default_similarity(&self) -> &Arc<dyn Similarity> {
+        &self.default
+    }
+}
+
+impl Similarity for PerFieldSimilarityWrapper {
+    fn compute_norm(&self, state: &FieldInvertState) -> i64 {
+        self.get(&state.field).compute_norm(state)
+    }
+
+    fn scorer(
+        &self,
+        boost: f32,
+        collection_stats: &CollectionStatistics,
+        term_stats: &[TermStatistics],
+    ) -> Box<dyn SimScorer> {
+        self.get(&collection_stats.field).scorer(boost, collection_stats, term_stats)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{
+            analysis::SimpleAnalyzer,
+            document::{Document, Field, Store},
+            index::{MemorySegmentBuilder, Term},
+            search::{test_util::leaf_searcher, BooleanSimilarity, PerFieldSimilarityWrapper, TermQuery},
+        },
+        pretty_assertions::assert_eq,
+        std::sync::Arc,
+    };
+
+    #[test]
+    fn test_per_field_similarity() {
+        let mut similarity = PerFieldSimilarityWrapper::default();
+        similarity.set("tags", Arc::new(BooleanSimilarity));
+        let similarity = Arc::new(similarity);
+
+        let mut builder = MemorySegmentBuilder::new(Arc::new(SimpleAnalyzer));
+        builder.set_similarity(similarity.clone());
+        for (body, tags) in [("rust rust rust", "rust rust search"), ("rust and other things", "rust")] {
+            let mut doc = Document::new();
+            doc.add(Field::text("body", body, Store::No));
+            doc.add(Field::text("tags", tags, Store::No));
+            builder.add_document(&doc).unwrap();
+        }
+        let mut searcher = leaf_searcher(vec![Arc::new(builder.build())]);
+        searcher.set_similarity(similarity);
+
+        // BM25 on the body prefers the repeated term in the shorter field.
+        let body = searcher.search(&TermQuery::new(Term::from_text("body", "rust")), 10).unwrap();
+        assert!(body.score_docs[0].score > body.score_docs[1].score);
+        assert_eq!(body.score_docs[0].doc, 0);
+
+        // Tags only record whether the term is present.
+        let tags = searcher.search(&TermQuery::new(Term::from_text("tags", "rust")), 10).unwrap();
+        let scores: Vec<f32> = tags.score_docs.iter().map(|sd| sd.score).collect();
+        assert_eq!(scores, vec![1.0, 1.0]);
+    }
+}
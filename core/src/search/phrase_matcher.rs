@@ -0,0 +1,238 @@
+/// The positions in the current document of one position of a phrase, as matched by a [PhraseMatcher].
+#[derive(Clone, Debug, Default)]
+pub(crate) struct PhrasePositions {
+    /// The positions of the phrase's term (or any of its alternative terms) in the document, in increasing order.
+    pub(crate) positions: Vec<u32>,
+
+    /// The position of this term within the phrase.
+    pub(crate) offset: u32,
+
+    /// Identifies the terms at this position of the phrase: phrase positions with the same terms share a group, and
+    /// are kept from matching the same position of the document.
+    pub(crate) group: usize,
+}
+
+/// Computes how often a phrase occurs in a document, from the positions of each of its terms.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum PhraseMatcher {
+    /// Every term must be at its exact position relative to the others. The frequency is the number of occurrences
+    /// of the phrase.
+    Exact,
+
+    /// The terms may be moved, in total, up to this many positions from their places in the phrase, including out of
+    /// order. Each occurrence adds `1 / (1 + distance)` to the frequency, where `distance` is how far the terms had to
+    /// move, so nearer matches score higher.
+    Sloppy(u32),
+}
+
+impl PhraseMatcher {
+    /// Returns the frequency of the phrase in the document, or 0 if it doesn't occur.
+    pub(crate) fn freq(self, terms: &[PhrasePositions]) -> f32 {
+        if terms.iter().any(|term| term.positions.is_empty()) {
+            return 0.0;
+        }
+
+        match self {
+            Self::Exact => Self::exact_freq(terms) as f32,
+            Self::Sloppy(slop) => SloppyState::new(terms).freq(slop as i64),
+        }
+    }
+
+    /// Counts the starting positions at which every term is found at its offset.
+    fn exact_freq(terms: &[PhrasePositions]) -> usize {
+        let (first, others) = terms.split_first().expect("a phrase has at least one term");
+        first
+            .positions
+            .iter()
+            .map(|&position| position as i64 - first.offset as i64)
+            .filter(|&start| {
+                others.iter().all(|term| {
+                    let position = start + term.offset as i64;
+                    position >= 0 && term.positions.binary_search(&(position as u32)).is_ok()
+                })
+            })
+            .count()
+    }
+}
+
+/// The state of a sloppy match over a document, following Lucene's `SloppyPhraseMatcher`: each term is on one of its
+/// positions, and its phrase position is that position less its offset. A match spans from the smallest phrase
+/// position to the largest, `end`.
+struct SloppyState<'a> {
+    terms: &'a [PhrasePositions],
+
+    /// The index of the document position each term is on.
+    indexes: Vec<usize>,
+
+    /// The phrase position of each term.
+    positions: Vec<i64>,
+
+    /// Whether each term is waiting in the queue of terms ordered by phrase position, rather than being advanced.
+    queued: Vec<bool>,
+    end: i64,
+    has_repeats: bool,
+}
+
+impl<'a> SloppyState<'a> {
+    fn new(terms: &'a [PhrasePositions]) -> Self {
+        let positions: Vec<i64> = terms.iter().map(|term| term.positions[0] as i64 - term.offset as i64).collect();
+        let has_repeats = terms.iter().enumerate().any(|(i, term)| terms[..i].iter().any(|t| t.group == term.group));
+        Self {
+            terms,
+            indexes: vec![0; terms.len()],
+            end: positions.iter().copied().max().unwrap_or(0),
+            positions,
+            queued: vec![true; terms.len()],
+            has_repeats,
+        }
+    }
+
+    /// Moves a term to its next position, returning false if it has no more.
+    fn advance(&mut self, i: usize) -> bool {
+        let term = &self.terms[i];
+        self.indexes[i] += 1;
+        let Some(&position) = term.positions.get(self.indexes[i]) else {
+            return false;
+        };
+
+        self.positions[i] = position as i64 - term.offset as i64;
+        self.end = self.end.max(self.positions[i]);
+        true
+    }
+
+    /// Returns whether term `a` comes before term `b` in the queue.
+    #[inline]
+    fn less_than(&self, a: usize, b: usize) -> bool {
+        (self.positions[a], self.terms[a].offset, a) < (self.positions[b], self.terms[b].offset, b)
+    }
+
+    /// Returns the queued term with the smallest phrase position.
+    fn top(&self) -> usize {
+        (0..self.terms.len())
+            .filter(|&i| self.queued[i])
+            .reduce(|a, b| {
+                if self.less_than(b, a) {
+                    b
+                } else {
+                    a
+                }
+            })
+            .unwrap()
+    }
+
+    /// Returns a term with the same terms as term `i` on the same document position, if there is one.
+    fn collision(&self, i: usize) -> Option<usize> {
+        let actual = |j: usize| self.positions[j] + self.terms[j].offset as i64;
+        (0..self.terms.len()).find(|&j| j != i && self.terms[j].group == self.terms[i].group && actual(j) == actual(i))
+    }
+
+    /// Moves terms sharing their terms with term `i` off of its document position, advancing whichever of two
+    /// colliding terms is earlier in the phrase. Returns false if a term runs out of positions.
+    fn advance_repeats(&mut self, mut i: usize) -> bool {
+        if !self.has_repeats {
+            return true;
+        }
+
+        while let Some(j) = self.collision(i) {
+            if self.less_than(j, i) {
+                i = j;
+            }
+            if !self.advance(i) {
+                return false;
+            }
+        }
+
+        true
+    }
+
+    /// Sums the weights of the matches within `slop`.
+    fn freq(mut self, slop: i64) -> f32 {
+        for i in 0..self.terms.len() {
+            if !self.advance_repeats(i) {
+                return 0.0;
+            }
+        }
+
+        let mut freq = 0.0;
+        'matches: loop {
+            let mut term = self.top();
+            self.queued[term] = false;
+            let mut match_length = self.end - self.positions[term];
+            let mut next = self.positions[self.top()];
+
+            while self.advance(term) && self.advance_repeats(term) {
+                if self.positions[term] > next {
+                    // The match can't get any shorter by advancing this term.
+                    self.queued[term] = true;
+                    if match_length <= slop {
+                        freq += 1.0 / (1.0 + match_length as f32);
+                        continue 'matches;
+                    }
+
+                    term = self.top();
+                    self.queued[term] = false;
+                    next = self.positions[self.top()];
+                    match_length = self.end - self.positions[term];
+                } else {
+                    match_length = match_length.min(self.end - self.positions[term]);
+                }
+            }
+
+            if match_length <= slop {
+                freq += 1.0 / (1.0 + match_length as f32);
+            }
+            return freq;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::{PhraseMatcher, PhrasePositions},
+        pretty_assertions::assert_eq,
+    };
+
+    /// Returns the positions of each phrase term in a document; terms are grouped by their text.
+    fn positions(doc: &str, phrase: &[&str]) -> Vec<PhrasePositions> {
+        let tokens: Vec<&str> = doc.split(' ').collect();
+        phrase
+            .iter()
+            .enumerate()
+            .map(|(offset, term)| PhrasePositions {
+                positions: (0..tokens.len() as u32).filter(|&i| tokens[i as usize] == *term).collect(),
+                offset: offset as u32,
+                group: phrase.iter().position(|t| t == term).unwrap(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_exact() {
+        let freq = |doc, phrase| PhraseMatcher::Exact.freq(&positions(doc, phrase));
+        assert_eq!(freq("a b c a b", &["a", "b"]), 2.0);
+        assert_eq!(freq("a b c a b", &["b", "a"]), 0.0);
+        assert_eq!(freq("a a a", &["a", "a"]), 2.0);
+        assert_eq!(freq("a b c", &["a", "d"]), 0.0);
+    }
+
+    #[test]
+    fn test_sloppy() {
+        // These match the phrase frequencies computed by Lucene's SloppyPhraseMatcher.
+        let freq = |slop, doc, phrase| PhraseMatcher::Sloppy(slop).freq(&positions(doc, phrase));
+        assert_eq!(freq(0, "w1 w2 w3", &["w1", "w2"]), 1.0);
+        assert_eq!(freq(0, "w1 w2 w3", &["w1", "w3"]), 0.0);
+        assert_eq!(freq(1, "w1 w2 w3", &["w1", "w3"]), 0.5);
+
+        // Reversing two adjacent terms takes a slop of 2.
+        assert_eq!(freq(1, "w2 w1", &["w1", "w2"]), 0.0);
+        assert_eq!(freq(2, "w2 w1", &["w1", "w2"]), 1.0 / 3.0);
+
+        // Each occurrence counts.
+        assert_eq!(freq(1, "w1 w2 x w1 x w2", &["w1", "w2"]), 1.5);
+
+        // A repeated term can't match the same position twice.
+        assert_eq!(freq(5, "a b", &["a", "a"]), 0.0);
+        assert_eq!(freq(1, "a b a", &["a", "a"]), 0.5);
+    }
+}
@@ -0,0 +1,282 @@
+use {
+    crate::{
+        index::Term,
+        search::{fmt_phrase, IndexSearcher, MatchNoDocsQuery, PhraseWeight, Query, ScoreMode, TermQuery, Weight},
+        BoxResult, LuceneError,
+    },
+    std::{
+        fmt::{Display, Formatter, Result as FmtResult},
+        slice,
+        sync::Arc,
+    },
+};
+
+/// A query that matches documents containing a sequence of terms, such as a quoted query, scored by the searcher's
+/// similarity with the number of times the phrase occurs.
+///
+/// With a slop of 0, the terms must be at exactly their positions relative to each other. With a larger slop, they
+/// may be moved, in total, up to that many positions, including out of order: swapping two adjacent terms takes a
+/// slop of 2. Nearer matches count more towards the phrase frequency.
+///
+/// The field must have been indexed with positions.
+#[derive(Clone, Debug)]
+pub struct PhraseQuery {
+    field: String,
+    terms: Vec<Term>,
+    positions: Vec<u32>,
+    slop: u32,
+}
+
+impl PhraseQuery {
+    /// Creates an exact phrase of the given words of `field` at consecutive positions.
+    pub fn new(field: &str, words: &[&str]) -> Self {
+        Self {
+            field: field.to_string(),
+            terms: words.iter().map(|word| Term::from_text(field, word)).collect(),
+            positions: (0..words.len() as u32).collect(),
+            slop: 0,
+        }
+    }
+
+    /// Returns a builder for a new query.
+    #[inline]
+    pub fn builder() -> PhraseQueryBuilder {
+        PhraseQueryBuilder::default()
+    }
+
+    /// Returns the field of the phrase.
+    #[inline]
+    pub fn field(&self) -> &str {
+        &self.field
+    }
+
+    /// Returns the terms of the phrase.
+    #[inline]
+    pub fn terms(&self) -> &[Term] {
+        &self.terms
+    }
+
+    /// Returns the position of each term within the phrase.
+    #[inline]
+    pub fn positions(&self) -> &[u32] {
+        &self.positions
+    }
+
+    /// Returns the number of positions the terms may be moved by.
+    #[inline]
+    pub fn slop(&self) -> u32 {
+        self.slop
+    }
+}
+
+impl Query for PhraseQuery {
+    fn create_weight(
+        &self,
+        searcher: &IndexSearcher,
+        _score_mode: ScoreMode,
+        boost: f32,
+    ) -> BoxResult<Box<dyn Weight>> {
+        let positions = self.terms.iter().cloned().map(|term| vec![term]).zip(self.positions.iter().copied()).collect();
+        Ok(Box::new(PhraseWeight::new(searcher, &self.field, positions, self.slop, boost, self.to_string())?))
+    }
+
+    fn rewrite(&self, _searcher: &IndexSearcher) -> BoxResult<Option<Arc<dyn Query>>> {
+        match self.terms.as_slice() {
+            [] => Ok(Some(Arc::new(MatchNoDocsQuery::new("empty PhraseQuery")))),
+            [term] => Ok(Some(Arc::new(TermQuery::new(term.clone())))),
+            // Phrases are matched relative to their first position.
+            _ if self.positions[0] != 0 => {
+                let first = self.positions[0];
+                Ok(Some(Arc::new(Self {
+                    positions: self.positions.iter().map(|position| position - first).collect(),
+                    ..self.clone()
+                })))
+            }
+            _ => Ok(None),
+        }
+    }
+}
+
+impl Display for PhraseQuery {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        let positions = self.terms.iter().map(slice::from_ref).zip(self.positions.iter().copied());
+        fmt_phrase(f, &self.field, positions, self.slop)
+    }
+}
+
+/// Builds a [PhraseQuery].
+#[derive(Clone, Debug, Default)]
+pub struct PhraseQueryBuilder {
+    terms: Vec<Term>,
+    positions: Vec<u32>,
+    slop: u32,
+}
+
+impl PhraseQueryBuilder {
+    /// Adds a term at the position after the last term added.
+    pub fn add(&mut self, term: Term) -> &mut Self {
+        let position = self.positions.last().map_or(0, |last| last + 1);
+        self.add_at(term, position)
+    }
+
+    /// Adds a term at the given position, which may leave a gap after the last term added, as when a stop word was
+    /// removed from the phrase, or be the same as its position.
+    pub fn add_at(&mut self, term: Term, position: u32) -> &mut Self {
+        self.terms.push(term);
+        self.positions.push(position);
+        self
+    }
+
+    /// Sets the number of positions the terms may be moved by.
+    pub fn set_slop(&mut self, slop: u32) -> &mut Self {
+        self.slop = slop;
+        self
+    }
+
+    /// Builds the query. This fails with [LuceneError::InvalidArgument] if the terms aren't all of the same field or
+    /// a term was added at a position before the last term's.
+    pub fn build(&self) -> BoxResult<PhraseQuery> {
+        let field = self.terms.first().map_or("", Term::field);
+        if let Some(term) = self.terms.iter().find(|term| term.field() != field) {
+            return Err(LuceneError::InvalidArgument(format!(
+                "all terms of a phrase must be in the same field; got {field} and {}",
+                term.field()
+            ))
+            .into());
+        }
+
+        if let Some(pair) = self.positions.windows(2).find(|pair| pair[1] < pair[0]) {
+            return Err(LuceneError::InvalidArgument(format!(
+                "positions must be added in order; got {} after {}",
+                pair[1], pair[0]
+            ))
+            .into());
+        }
+
+        Ok(PhraseQuery {
+            field: field.to_string(),
+            terms: self.terms.clone(),
+            positions: self.positions.clone(),
+            slop: self.slop,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{
+            index::Term,
+            search::{test_util::body_searcher, IndexSearcher, PhraseQuery, Query, TermQuery},
+        },
+        pretty_assertions::assert_eq,
+        std::any::Any,
+    };
+
+    fn searcher() -> IndexSearcher {
+        body_searcher(&[
+            "the quick brown fox jumps high",
+            "the brown quick fox jumps high",
+            "quick red brown fox jumps high",
+            "a lazy dog sleeps all day",
+        ])
+    }
+
+    fn search(searcher: &IndexSearcher, query: &dyn Query) -> Vec<(u32, f32)> {
+        searcher.search(query, 10).unwrap().score_docs.iter().map(|sd| (sd.doc, sd.score)).collect()
+    }
+
+    #[test]
+    fn test_phrase_query() {
+        let searcher = searcher();
+
+        // Scores are those Lucene computes with BM25: the idf of "quick" and "brown" summed, with every document at
+        // the average length.
+        let query = PhraseQuery::new("body", &["quick", "brown"]);
+        assert_eq!(query.to_string(), "body:\"quick brown\"");
+        let hits = search(&searcher, &query);
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].0, 0);
+        assert!((hits[0].1 - 0.32424995).abs() < 1e-6);
+
+        let explanation = searcher.explain(&query, 0).unwrap();
+        assert_eq!(explanation.description(), "weight(body:\"quick brown\" in 0), result of:");
+        assert!((explanation.value() - hits[0].1).abs() < 1e-6);
+        assert!(!searcher.explain(&query, 1).unwrap().is_match());
+        assert!(!searcher.explain(&query, 3).unwrap().is_match());
+
+        // A gap in the phrase matches any term there.
+        let query = PhraseQuery::builder()
+            .add(Term::from_text("body", "quick"))
+            .add_at(Term::from_text("body", "fox"), 2)
+            .build()
+            .unwrap();
+        assert_eq!(query.to_string(), "body:\"quick ? fox\"");
+        assert_eq!(search(&searcher, &query).iter().map(|hit| hit.0).collect::<Vec<_>>(), vec![0]);
+        assert_eq!(searcher.count(&PhraseQuery::new("body", &["quick", "cat"])).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_sloppy_phrase_query() {
+        let searcher = searcher();
+
+        // A slop of 1 allows one term in between; swapping the terms takes 2.
+        let query = PhraseQuery::builder()
+            .add(Term::from_text("body", "quick"))
+            .add(Term::from_text("body", "brown"))
+            .set_slop(1)
+            .build()
+            .unwrap();
+        assert_eq!(query.to_string(), "body:\"quick brown\"~1");
+        assert_eq!(search(&searcher, &query).iter().map(|hit| hit.0).collect::<Vec<_>>(), vec![0, 2]);
+
+        // Each match counts 1 / (1 + distance) towards the frequency, so nearer matches score higher.
+        let query = PhraseQuery::builder()
+            .add(Term::from_text("body", "quick"))
+            .add(Term::from_text("body", "brown"))
+            .set_slop(2)
+            .build()
+            .unwrap();
+        let hits = search(&searcher, &query);
+        assert_eq!(hits.iter().map(|hit| hit.0).collect::<Vec<_>>(), vec![0, 2, 1]);
+        for (hit, expected) in hits.iter().zip([0.32424995, 0.2098088, 0.15507606]) {
+            assert!((hit.1 - expected).abs() < 1e-6, "{hit:?} != {expected}");
+        }
+
+        let explanation = searcher.explain(&query, 2).unwrap();
+        assert!((explanation.value() - hits[1].1).abs() < 1e-6);
+        assert!(explanation.details()[0].description().starts_with("score(freq=0.5)"));
+    }
+
+    #[test]
+    fn test_rewrite_and_builder() {
+        let searcher = searcher();
+
+        let query = PhraseQuery::new("body", &["fox"]);
+        let rewritten = query.rewrite(&searcher).unwrap().unwrap();
+        assert!((rewritten.as_ref() as &dyn Any).downcast_ref::<TermQuery>().is_some());
+        assert_eq!(searcher.count(&PhraseQuery::new("body", &[])).unwrap(), 0);
+
+        // Positions are made relative to the first.
+        let query = PhraseQuery::builder()
+            .add_at(Term::from_text("body", "brown"), 3)
+            .add_at(Term::from_text("body", "fox"), 4)
+            .build()
+            .unwrap();
+        assert_eq!(query.to_string(), "body:\"? ? ? brown fox\"");
+        let rewritten = query.rewrite(&searcher).unwrap().unwrap();
+        assert_eq!(rewritten.to_string(), "body:\"brown fox\"");
+        assert_eq!(searcher.count(&query).unwrap(), 2);
+
+        assert!(PhraseQuery::builder()
+            .add(Term::from_text("body", "quick"))
+            .add(Term::from_text("title", "brown"))
+            .build()
+            .is_err());
+        assert!(PhraseQuery::builder()
+            .add_at(Term::from_text("body", "quick"), 2)
+            .add_at(Term::from_text("body", "brown"), 1)
+            .build()
+            .is_err());
+    }
+}
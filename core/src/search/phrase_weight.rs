@@ -0,0 +1,346 @@
+use {
+    crate::{
+        index::{LeafReaderContext, PostingsEnum, Term},
+        search::{
+            DocIdSetIterator, Explanation, IndexSearcher, PhraseMatcher, PhrasePositions, Scorable, Scorer, SimScorer,
+            Weight, NO_MORE_DOCS,
+        },
+        BoxResult, LuceneError,
+    },
+    std::{
+        fmt::{Formatter, Result as FmtResult},
+        sync::Arc,
+    },
+};
+
+/// The [Weight] of [crate::search::PhraseQuery] and [crate::search::MultiPhraseQuery]: each position of the phrase
+/// holds one or more alternative terms, at an offset within the phrase.
+#[derive(Debug)]
+pub(crate) struct PhraseWeight {
+    field: String,
+    positions: Vec<(Vec<Term>, u32)>,
+    matcher: PhraseMatcher,
+    sim_scorer: Option<Arc<dyn SimScorer>>,
+    description: String,
+}
+
+impl PhraseWeight {
+    /// Creates the weight of a phrase with the given terms at each position, scored with statistics of every term.
+    pub(crate) fn new(
+        searcher: &IndexSearcher,
+        field: &str,
+        positions: Vec<(Vec<Term>, u32)>,
+        slop: u32,
+        boost: f32,
+        description: String,
+    ) -> BoxResult<Self> {
+        let mut term_stats = Vec::new();
+        for (terms, _) in positions.iter() {
+            for term in terms {
+                term_stats.extend(searcher.term_statistics(term)?);
+            }
+        }
+
+        // If a term doesn't occur anywhere, no segment will produce a scorer.
+        let sim_scorer = match searcher.collection_statistics(field)? {
+            Some(cs) if !term_stats.is_empty() => {
+                Some(Arc::from(searcher.similarity().scorer(boost, &cs, &term_stats)))
+            }
+            _ => None,
+        };
+
+        Ok(Self {
+            field: field.to_string(),
+            positions,
+            matcher: match slop {
+                0 => PhraseMatcher::Exact,
+                slop => PhraseMatcher::Sloppy(slop),
+            },
+            sim_scorer,
+            description,
+        })
+    }
+
+    fn phrase_scorer(&self, context: &LeafReaderContext) -> BoxResult<Option<PhraseScorer>> {
+        let Some(sim_scorer) = &self.sim_scorer else {
+            return Ok(None);
+        };
+
+        let reader = context.reader();
+        let Some(terms) = reader.terms(&self.field)? else {
+            return Ok(None);
+        };
+
+        if !terms.has_positions() {
+            return Err(LuceneError::IllegalState(format!(
+                "field {} was indexed without position data; cannot run {}",
+                self.field, self.description
+            ))
+            .into());
+        }
+
+        let mut phrase_terms = Vec::with_capacity(self.positions.len());
+        for (group, (alternatives, offset)) in self.positions.iter().enumerate() {
+            let mut postings = Vec::with_capacity(alternatives.len());
+            let mut te = terms.iterator()?;
+            for term in alternatives {
+                if te.seek_exact(term.bytes())? {
+                    postings.push(te.postings()?);
+                }
+            }
+
+            // Every position of the phrase must have a term in the segment.
+            if postings.is_empty() {
+                return Ok(None);
+            }
+
+            // Positions with the same terms share a group, so a sloppy match keeps them apart.
+            let group = self.positions[..group].iter().position(|(other, _)| other == alternatives).unwrap_or(group);
+            phrase_terms.push(PhraseTerm {
+                sub_docs: vec![None; postings.len()],
+                postings,
+                offset: *offset,
+                group,
+            });
+        }
+
+        Ok(Some(PhraseScorer::new(phrase_terms, self.matcher, sim_scorer.clone(), reader.norms(&self.field)?)))
+    }
+}
+
+impl Weight for PhraseWeight {
+    fn scorer(&self, context: &LeafReaderContext) -> BoxResult<Option<Box<dyn Scorer>>> {
+        Ok(self.phrase_scorer(context)?.map(|scorer| Box::new(scorer) as Box<dyn Scorer>))
+    }
+
+    fn explain(&self, context: &LeafReaderContext, doc: u32) -> BoxResult<Explanation> {
+        let Some(mut scorer) = self.phrase_scorer(context)? else {
+            return Ok(Explanation::no_match(format!("no matching terms for {}", self.description), vec![]));
+        };
+
+        if scorer.advance(doc)? != doc {
+            return Ok(Explanation::no_match(format!("phrase not found in document {doc}"), vec![]));
+        }
+
+        let sim_explanation = scorer.sim_scorer.explain(scorer.freq, scorer.norm());
+        Ok(Explanation::matched(
+            sim_explanation.value(),
+            format!("weight({} in {doc}), result of:", self.description),
+            vec![sim_explanation],
+        ))
+    }
+}
+
+/// The postings of the alternative terms at one position of a phrase.
+#[derive(Debug)]
+struct PhraseTerm {
+    postings: Vec<Box<dyn PostingsEnum>>,
+
+    /// The document each of the postings is positioned on; `None` until it has been positioned.
+    sub_docs: Vec<Option<u32>>,
+    offset: u32,
+    group: usize,
+}
+
+impl PhraseTerm {
+    /// Moves the postings behind `target` to their first document at or after it, returning the first document
+    /// any of them is on.
+    fn advance(&mut self, target: u32) -> BoxResult<u32> {
+        let mut doc = NO_MORE_DOCS;
+        for (postings, sub_doc) in self.postings.iter_mut().zip(self.sub_docs.iter_mut()) {
+            let d = match *sub_doc {
+                Some(d) if d >= target => d,
+                _ => postings.advance(target)?,
+            };
+            *sub_doc = Some(d);
+            doc = doc.min(d);
+        }
+
+        Ok(doc)
+    }
+
+    /// Reads the positions of the terms in `doc`, which the postings must be positioned on.
+    fn positions(&mut self, doc: u32, phrase_positions: &mut PhrasePositions) -> BoxResult<()> {
+        phrase_positions.positions.clear();
+        for (postings, sub_doc) in self.postings.iter_mut().zip(self.sub_docs.iter()) {
+            if *sub_doc == Some(doc) {
+                for _ in 0..postings.freq()? {
+                    phrase_positions.positions.extend(postings.next_position()?);
+                }
+            }
+        }
+
+        if self.postings.len() > 1 {
+            phrase_positions.positions.sort_unstable();
+            phrase_positions.positions.dedup();
+        }
+        Ok(())
+    }
+
+    fn cost(&self) -> u64 {
+        self.postings.iter().map(|postings| postings.cost()).sum()
+    }
+}
+
+/// A [Scorer] over the documents containing a phrase: the conjunction of its terms, confirmed by a [PhraseMatcher]
+/// and scored with the phrase's frequency.
+#[derive(Debug)]
+struct PhraseScorer {
+    /// The terms ordered by increasing cost, so the rarest leads the conjunction.
+    terms: Vec<PhraseTerm>,
+    positions: Vec<PhrasePositions>,
+    matcher: PhraseMatcher,
+    sim_scorer: Arc<dyn SimScorer>,
+    norms: Option<Arc<[i64]>>,
+    doc: Option<u32>,
+    freq: f32,
+}
+
+impl PhraseScorer {
+    fn new(
+        mut terms: Vec<PhraseTerm>,
+        matcher: PhraseMatcher,
+        sim_scorer: Arc<dyn SimScorer>,
+        norms: Option<Arc<[i64]>>,
+    ) -> Self {
+        terms.sort_by_key(PhraseTerm::cost);
+        Self {
+            positions: terms
+                .iter()
+                .map(|term| PhrasePositions {
+                    positions: Vec::new(),
+                    offset: term.offset,
+                    group: term.group,
+                })
+                .collect(),
+            terms,
+            matcher,
+            sim_scorer,
+            norms,
+            doc: None,
+            freq: 0.0,
+        }
+    }
+
+    fn norm(&self) -> i64 {
+        self.norms.as_ref().and_then(|norms| norms.get(self.doc_id() as usize).copied()).unwrap_or(1)
+    }
+
+    /// Positions this scorer on the first document at or after `target` containing the phrase.
+    fn do_next(&mut self, target: u32) -> BoxResult<u32> {
+        let mut doc = self.terms[0].advance(target)?;
+        'candidates: while doc != NO_MORE_DOCS {
+            for i in 1..self.terms.len() {
+                let other_doc = self.terms[i].advance(doc)?;
+                if other_doc > doc {
+                    doc = self.terms[0].advance(other_doc)?;
+                    continue 'candidates;
+                }
+            }
+
+            for (term, positions) in self.terms.iter_mut().zip(self.positions.iter_mut()) {
+                term.positions(doc, positions)?;
+            }
+
+            self.freq = self.matcher.freq(&self.positions);
+            if self.freq > 0.0 {
+                break;
+            }
+
+            doc = self.terms[0].advance(doc + 1)?;
+        }
+
+        self.doc = Some(doc);
+        Ok(doc)
+    }
+}
+
+impl DocIdSetIterator for PhraseScorer {
+    #[inline]
+    fn doc_id(&self) -> u32 {
+        self.doc.unwrap_or(NO_MORE_DOCS)
+    }
+
+    fn next_doc(&mut self) -> BoxResult<u32> {
+        match self.doc {
+            None => self.do_next(0),
+            Some(NO_MORE_DOCS) => Ok(NO_MORE_DOCS),
+            Some(doc) => self.do_next(doc + 1),
+        }
+    }
+
+    fn advance(&mut self, target: u32) -> BoxResult<u32> {
+        self.do_next(target)
+    }
+
+    #[inline]
+    fn cost(&self) -> u64 {
+        self.terms[0].cost()
+    }
+}
+
+impl Scorable for PhraseScorer {
+    fn score(&mut self) -> BoxResult<f32> {
+        Ok(self.sim_scorer.score(self.freq, self.norm()))
+    }
+}
+
+impl Scorer for PhraseScorer {
+    fn max_score(&mut self, _up_to: u32) -> BoxResult<f32> {
+        Ok(self.sim_scorer.score(f32::MAX, 1))
+    }
+}
+
+/// Renders a phrase as `field:"a b ? d"~slop`, where each element lists the terms at a position of the phrase, `?`
+/// marks positions without terms, alternative terms at one position are rendered as `(a b)`, and terms added at the
+/// same position are separated by `|`.
+pub(crate) fn fmt_phrase<'a, I>(f: &mut Formatter, field: &str, positions: I, slop: u32) -> FmtResult
+where
+    I: IntoIterator<Item = (&'a [Term], u32)>,
+{
+    write!(f, "{field}:\"")?;
+    let mut last = None;
+    for (terms, position) in positions {
+        if let Some(last) = last {
+            write!(
+                f,
+                "{}",
+                if position == last {
+                    "|"
+                } else {
+                    " "
+                }
+            )?;
+            for _ in last + 1..position {
+                write!(f, "? ")?;
+            }
+        } else {
+            for _ in 0..position {
+                write!(f, "? ")?;
+            }
+        }
+
+        if terms.len() > 1 {
+            write!(f, "(")?;
+        }
+        for (i, term) in terms.iter().enumerate() {
+            if i > 0 {
+                write!(f, " ")?;
+            }
+            match term.text() {
+                Some(text) => write!(f, "{text}")?,
+                None => write!(f, "{:x?}", term.bytes())?,
+            }
+        }
+        if terms.len() > 1 {
+            write!(f, ")")?;
+        }
+        last = Some(position);
+    }
+
+    write!(f, "\"")?;
+    if slop > 0 {
+        write!(f, "~{slop}")?;
+    }
+    Ok(())
+}
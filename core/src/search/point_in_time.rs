@@ -0,0 +1,327 @@
+use {
+    crate::{io::Runtime, search::IndexSearcher, BoxResult, Id, LuceneError},
+    log::debug,
+    std::{
+        collections::HashMap,
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Mutex,
+        },
+        time::{Duration, Instant},
+    },
+};
+
+/// How long a point in time is kept open after it was last used, unless another keep-alive is given.
+pub const DEFAULT_POINT_IN_TIME_KEEP_ALIVE: Duration = Duration::from_secs(300);
+
+/// A searcher kept open under an id, so that the pages of a paginated search all see the same documents while the
+/// index keeps changing, as Elasticsearch's point in time does.
+///
+/// A searcher over the segments of an [crate::index::IndexWriter] sees the segments as they were when its reader was
+/// opened: later flushes and merges publish new segments without changing the ones it holds. Holding the searcher
+/// also holds the memory of those segments, so a point in time is only leased for a while (see
+/// [PointInTimeManager]).
+#[derive(Clone, Debug)]
+pub struct PointInTime {
+    id: String,
+    searcher: IndexSearcher,
+    keep_alive: Duration,
+    expires_at: Instant,
+}
+
+impl PointInTime {
+    /// Returns the id that clients pass back to search the same point in time again.
+    #[inline]
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Returns the searcher of the point in time.
+    #[inline]
+    pub fn searcher(&self) -> &IndexSearcher {
+        &self.searcher
+    }
+
+    /// Returns how long the point in time is kept open after it is used.
+    #[inline]
+    pub fn keep_alive(&self) -> Duration {
+        self.keep_alive
+    }
+
+    /// Returns when the lease of the point in time runs out, as of the call that returned it.
+    #[inline]
+    pub fn expires_at(&self) -> Instant {
+        self.expires_at
+    }
+
+    /// Indicates whether the lease has run out at `now`.
+    #[inline]
+    pub fn is_expired(&self, now: Instant) -> bool {
+        now >= self.expires_at
+    }
+}
+
+/// Leases [PointInTime]s to clients by id.
+///
+/// Each use of a point in time through [PointInTimeManager::get] renews its lease for its keep-alive. Points in time
+/// whose lease has run out are dropped the next time they are looked up, by [PointInTimeManager::remove_expired],
+/// or in the background by [PointInTimeManager::run_cleanup], releasing the segments they held.
+#[derive(Debug)]
+pub struct PointInTimeManager {
+    keep_alive: Duration,
+    max_open: usize,
+    points: Mutex<HashMap<String, PointInTime>>,
+    closed: AtomicBool,
+}
+
+impl PointInTimeManager {
+    /// Creates a manager that keeps points in time open for `keep_alive` unless told otherwise, and that holds at
+    /// most `max_open` of them at once.
+    pub fn new(keep_alive: Duration, max_open: usize) -> BoxResult<Self> {
+        if max_open == 0 {
+            return Err(LuceneError::InvalidArgument("max_open must be at least 1".to_string()).into());
+        }
+
+        Ok(Self {
+            keep_alive,
+            max_open,
+            points: Mutex::new(HashMap::new()),
+            closed: AtomicBool::new(false),
+        })
+    }
+
+    /// Returns the keep-alive of points in time opened without one.
+    #[inline]
+    pub fn keep_alive(&self) -> Duration {
+        self.keep_alive
+    }
+
+    /// Returns the most points in time held at once.
+    #[inline]
+    pub fn max_open(&self) -> usize {
+        self.max_open
+    }
+
+    /// Returns the number of points in time held, including expired ones that haven't been removed yet.
+    pub fn num_open(&self) -> usize {
+        self.points.lock().unwrap().len()
+    }
+
+    /// Opens a point in time over `searcher` under a new random id, leased for `keep_alive`, or for the manager's
+    /// keep-alive if `None`.
+    ///
+    /// This fails with [LuceneError::IllegalState] if [PointInTimeManager::max_open] points in time are already
+    /// open once the expired ones are removed, with [LuceneError::InvalidArgument] if the keep-alive is too long to
+    /// compute its expiry, and with [LuceneError::AlreadyClosed] once the manager is closed.
+    pub fn open(&self, searcher: IndexSearcher, keep_alive: Option<Duration>) -> BoxResult<PointInTime> {
+        self.ensure_open()?;
+
+        let now = Instant::now();
+        let mut points = self.points.lock().unwrap();
+        if points.len() >= self.max_open {
+            points.retain(|_, point| !point.is_expired(now));
+            if points.len() >= self.max_open {
+                return Err(LuceneError::IllegalState(format!(
+                    "too many points in time are open: the limit is {}",
+                    self.max_open
+                ))
+                .into());
+            }
+        }
+
+        let keep_alive = keep_alive.unwrap_or(self.keep_alive);
+        let point = PointInTime {
+            id: Id::random_id().to_string(),
+            searcher,
+            keep_alive,
+            expires_at: expiry(now, keep_alive)?,
+        };
+        points.insert(point.id.clone(), point.clone());
+        Ok(point)
+    }
+
+    /// Returns the point in time with the given id, renewing its lease for `keep_alive`, or for its current
+    /// keep-alive if `None`.
+    ///
+    /// This fails with [LuceneError::PointInTimeNotFound] if the point in time was closed or its lease ran out, and
+    /// with [LuceneError::InvalidArgument] if the keep-alive is too long to compute its expiry.
+    pub fn get(&self, id: &str, keep_alive: Option<Duration>) -> BoxResult<PointInTime> {
+        self.ensure_open()?;
+
+        let now = Instant::now();
+        let mut points = self.points.lock().unwrap();
+        let Some(point) = points.get_mut(id) else {
+            return Err(LuceneError::PointInTimeNotFound(id.to_string()).into());
+        };
+        if point.is_expired(now) {
+            points.remove(id);
+            return Err(LuceneError::PointInTimeNotFound(id.to_string()).into());
+        }
+
+        let keep_alive = keep_alive.unwrap_or(point.keep_alive);
+        point.expires_at = expiry(now, keep_alive)?;
+        point.keep_alive = keep_alive;
+        Ok(point.clone())
+    }
+
+    /// Closes the point in time with the given id before its lease runs out. Returns whether it was open.
+    pub fn close(&self, id: &str) -> bool {
+        self.points.lock().unwrap().remove(id).is_some()
+    }
+
+    /// Removes the points in time whose lease has run out, returning how many were removed.
+    pub fn remove_expired(&self) -> usize {
+        let now = Instant::now();
+        let mut points = self.points.lock().unwrap();
+        let before = points.len();
+        points.retain(|_, point| !point.is_expired(now));
+        before - points.len()
+    }
+
+    /// Removes the expired points in time every `interval` until the manager is closed. This is meant to be
+    /// spawned on the application's executor, with `runtime` providing its timer.
+    pub async fn run_cleanup(&self, runtime: &dyn Runtime, interval: Duration) {
+        while !self.is_closed() {
+            runtime.sleep(interval).await;
+            let removed = self.remove_expired();
+            if removed > 0 {
+                debug!("Removed {removed} expired points in time");
+            }
+        }
+    }
+
+    /// Indicates whether the manager has been closed.
+    #[inline]
+    pub fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::Acquire)
+    }
+
+    /// Closes every point in time and stops the manager from opening more. [PointInTimeManager::run_cleanup]
+    /// returns after its current sleep.
+    pub fn close_all(&self) {
+        self.closed.store(true, Ordering::Release);
+        self.points.lock().unwrap().clear();
+    }
+
+    fn ensure_open(&self) -> BoxResult<()> {
+        if self.is_closed() {
+            return Err(LuceneError::AlreadyClosed("This PointInTimeManager is closed".to_string()).into());
+        }
+        Ok(())
+    }
+}
+
+/// Returns when a lease for `keep_alive` starting at `now` runs out.
+fn expiry(now: Instant, keep_alive: Duration) -> BoxResult<Instant> {
+    now.checked_add(keep_alive)
+        .ok_or_else(|| LuceneError::InvalidArgument(format!("keep-alive of {keep_alive:?} is too long")).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{
+            document::{Document, Field, Store},
+            index::{IndexWriter, IndexWriterConfig, Term},
+            io::ByteBuffersDirectory,
+            search::{IndexSearcher, PointInTimeManager, TermQuery},
+            LuceneError,
+        },
+        pretty_assertions::assert_eq,
+        std::{sync::Arc, time::Duration},
+    };
+
+    fn assert_lucene_error(err: &crate::BoxError, f: impl Fn(&LuceneError) -> bool) {
+        assert!(err.downcast_ref::<LuceneError>().map(f).unwrap_or(false), "unexpected error: {err:?}");
+    }
+
+    fn add_documents(writer: &IndexWriter, n: usize) {
+        for _ in 0..n {
+            let mut document = Document::new();
+            document.add(Field::text("body", "quick fox", Store::No));
+            writer.add_document(&document).unwrap();
+        }
+        writer.flush().unwrap();
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_point_in_time() {
+        let mut writer =
+            IndexWriter::new(Box::new(ByteBuffersDirectory::new()), IndexWriterConfig::new()).await.unwrap();
+        add_documents(&writer, 3);
+        let manager = PointInTimeManager::new(Duration::from_secs(60), 2).unwrap();
+        let point = manager.open(IndexSearcher::new(Arc::new(writer.reader().unwrap())), None).unwrap();
+        assert_eq!(point.keep_alive(), Duration::from_secs(60));
+
+        // Documents indexed and merged afterwards aren't seen by the point in time.
+        add_documents(&writer, 2);
+        writer.force_merge(1).unwrap();
+        let query = TermQuery::new(Term::new("body", "quick"));
+        let again = manager.get(point.id(), None).unwrap();
+        assert!(again.expires_at() >= point.expires_at());
+        assert_eq!(again.searcher().count(&query).unwrap(), 3);
+        assert_eq!(IndexSearcher::new(Arc::new(writer.reader().unwrap())).count(&query).unwrap(), 5);
+
+        // Closed and expired points in time can't be searched.
+        assert!(manager.close(point.id()));
+        assert!(!manager.close(point.id()));
+        let err = manager.get(point.id(), None).unwrap_err();
+        assert_lucene_error(&err, |e| matches!(e, LuceneError::PointInTimeNotFound(_)));
+
+        let searcher = IndexSearcher::new(Arc::new(writer.reader().unwrap()));
+        let expired = manager.open(searcher.clone(), Some(Duration::ZERO)).unwrap();
+        let err = manager.get(expired.id(), None).unwrap_err();
+        assert_lucene_error(&err, |e| matches!(e, LuceneError::PointInTimeNotFound(_)));
+        assert_eq!(manager.num_open(), 0);
+
+        // Keep-alives too long to compute an expiry for are rejected.
+        let err = manager.open(searcher.clone(), Some(Duration::MAX)).unwrap_err();
+        assert_lucene_error(&err, |e| matches!(e, LuceneError::InvalidArgument(_)));
+        let kept = manager.open(searcher.clone(), None).unwrap();
+        let err = manager.get(kept.id(), Some(Duration::MAX)).unwrap_err();
+        assert_lucene_error(&err, |e| matches!(e, LuceneError::InvalidArgument(_)));
+        assert_eq!(manager.get(kept.id(), None).unwrap().keep_alive(), Duration::from_secs(60));
+        assert!(manager.close(kept.id()));
+
+        // The limit counts live points in time only.
+        manager.open(searcher.clone(), None).unwrap();
+        manager.open(searcher.clone(), Some(Duration::ZERO)).unwrap();
+        manager.open(searcher.clone(), None).unwrap();
+        let err = manager.open(searcher.clone(), None).unwrap_err();
+        assert_lucene_error(&err, |e| matches!(e, LuceneError::IllegalState(_)));
+
+        manager.close_all();
+        assert_eq!(manager.num_open(), 0);
+        let err = manager.open(searcher, None).unwrap_err();
+        assert_lucene_error(&err, |e| matches!(e, LuceneError::AlreadyClosed(_)));
+        writer.close().await.unwrap();
+    }
+
+    #[cfg(feature = "tokio-runtime")]
+    #[test_log::test(tokio::test)]
+    async fn test_run_cleanup() {
+        use crate::io::TokioRuntime;
+
+        let mut writer =
+            IndexWriter::new(Box::new(ByteBuffersDirectory::new()), IndexWriterConfig::new()).await.unwrap();
+        add_documents(&writer, 1);
+        let searcher = IndexSearcher::new(Arc::new(writer.reader().unwrap()));
+
+        let manager = Arc::new(PointInTimeManager::new(Duration::from_millis(20), 10).unwrap());
+        let cleanup = tokio::spawn({
+            let manager = manager.clone();
+            async move { manager.run_cleanup(&TokioRuntime, Duration::from_millis(10)).await }
+        });
+        let kept = manager.open(searcher.clone(), Some(Duration::from_secs(60))).unwrap();
+        manager.open(searcher, None).unwrap();
+        assert_eq!(manager.num_open(), 2);
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(manager.num_open(), 1);
+        manager.get(kept.id(), None).unwrap();
+
+        manager.close_all();
+        cleanup.await.unwrap();
+        writer.close().await.unwrap();
+    }
+}
@@ -0,0 +1,104 @@
+use {
+    crate::{
+        index::Term,
+        search::{AutomatonQuery, IndexSearcher, Query, RewriteMethod, ScoreMode, Weight},
+        util::automaton::{
+            concatenate, make_any_binary, make_binary, CompiledAutomaton, DEFAULT_DETERMINIZE_WORK_LIMIT,
+        },
+        BoxResult,
+    },
+    std::{
+        fmt::{Display, Formatter, Result as FmtResult},
+        sync::Arc,
+    },
+};
+
+/// A query matching the documents containing a term that starts with a prefix, such as `luc*`.
+///
+/// This is an [AutomatonQuery], executed as its [RewriteMethod] chooses.
+#[derive(Clone, Debug)]
+pub struct PrefixQuery {
+    prefix: Term,
+    query: AutomatonQuery,
+}
+
+impl PrefixQuery {
+    /// Creates a query for the terms starting with `prefix`, in its field.
+    pub fn new(prefix: Term) -> BoxResult<Self> {
+        let automaton = concatenate(&make_binary(prefix.bytes()), &make_any_binary());
+        let automaton = CompiledAutomaton::new(&automaton, Some(false), true, DEFAULT_DETERMINIZE_WORK_LIMIT, true)?;
+        let description = format!("{prefix}*");
+        Ok(Self {
+            query: AutomatonQuery::with_description(prefix.clone(), automaton, description),
+            prefix,
+        })
+    }
+
+    /// Returns the prefix of the matching terms.
+    #[inline]
+    pub fn prefix(&self) -> &Term {
+        &self.prefix
+    }
+
+    /// Returns the query over the prefix's automaton.
+    #[inline]
+    pub fn automaton_query(&self) -> &AutomatonQuery {
+        &self.query
+    }
+
+    /// Sets how the query is executed; see [AutomatonQuery::set_rewrite_method].
+    pub fn set_rewrite_method(&mut self, rewrite_method: RewriteMethod) -> &mut Self {
+        self.query.set_rewrite_method(rewrite_method);
+        self
+    }
+
+    /// Sets the most terms the query expands to; see [AutomatonQuery::set_max_expansions].
+    pub fn set_max_expansions(&mut self, max_expansions: usize) -> &mut Self {
+        self.query.set_max_expansions(max_expansions);
+        self
+    }
+}
+
+impl Query for PrefixQuery {
+    fn create_weight(&self, searcher: &IndexSearcher, score_mode: ScoreMode, boost: f32) -> BoxResult<Box<dyn Weight>> {
+        self.query.create_weight(searcher, score_mode, boost)
+    }
+
+    fn rewrite(&self, searcher: &IndexSearcher) -> BoxResult<Option<Arc<dyn Query>>> {
+        self.query.rewrite(searcher)
+    }
+}
+
+impl Display for PrefixQuery {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        Display::fmt(&self.query, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{
+            index::Term,
+            search::{test_util::body_searcher, PrefixQuery},
+            util::automaton::AutomatonType,
+        },
+        pretty_assertions::assert_eq,
+    };
+
+    #[test]
+    fn test_prefix_query() {
+        let searcher = body_searcher(&["lucene", "lucid dreams", "luck", "lunar", "glucose"]);
+
+        let query = PrefixQuery::new(Term::from_text("body", "luc")).unwrap();
+        assert_eq!(query.to_string(), "body:luc*");
+        assert_eq!(searcher.count(&query).unwrap(), 3);
+        assert_eq!(searcher.count(&PrefixQuery::new(Term::from_text("body", "lucene")).unwrap()).unwrap(), 1);
+        assert_eq!(searcher.count(&PrefixQuery::new(Term::from_text("other", "luc")).unwrap()).unwrap(), 0);
+
+        // An empty prefix matches every term of the field.
+        let query = PrefixQuery::new(Term::from_text("body", "")).unwrap();
+        assert_eq!(query.automaton_query().automaton().automaton_type(), AutomatonType::All);
+        assert_eq!(searcher.count(&query).unwrap(), 5);
+    }
+}
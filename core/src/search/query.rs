@@ -0,0 +1,49 @@
+use {
+    crate::{
+        search::{BooleanQuery, ConstantScoreQuery, IndexSearcher, ScoreMode, Weight},
+        BoxResult,
+    },
+    std::{
+        any::Any,
+        fmt::{Debug, Display},
+        sync::Arc,
+    },
+};
+
+/// A query that can be executed by an [IndexSearcher].
+///
+/// Queries are immutable descriptions of what to match. Before execution they are rewritten into primitive queries
+/// (see [Query::rewrite]), then turned into a [Weight] for the searcher. The [Display] implementation renders the
+/// query in a form similar to the classic query parser syntax.
+pub trait Query: Any + Debug + Display + Send + Sync {
+    /// Creates the weight for this query.
+    ///
+    /// # Parameters
+    /// * `searcher`: The searcher executing the query.
+    /// * `score_mode`: How scores will be consumed.
+    /// * `boost`: The boost to apply to scores, propagated from enclosing queries.
+    fn create_weight(&self, searcher: &IndexSearcher, score_mode: ScoreMode, boost: f32) -> BoxResult<Box<dyn Weight>>;
+
+    /// Rewrites this query into a simpler or more primitive form, returning `None` if it can't be rewritten further.
+    /// [IndexSearcher::rewrite] calls this repeatedly until no further rewriting is possible.
+    fn rewrite(&self, _searcher: &IndexSearcher) -> BoxResult<Option<Arc<dyn Query>>> {
+        Ok(None)
+    }
+
+    /// Returns a query matching the documents that match both this query and `filter`, scored by this query alone.
+    /// The filter is an [crate::search::Occur::Filter] clause, so its matches can be cached.
+    fn filtered_by(self, filter: Arc<dyn Query>) -> BooleanQuery
+    where
+        Self: Sized,
+    {
+        BooleanQuery::builder().must(Arc::new(self)).filter(filter).build()
+    }
+
+    /// Returns a query matching the same documents as this query with a constant score; see [ConstantScoreQuery].
+    fn constant_score(self) -> ConstantScoreQuery
+    where
+        Self: Sized,
+    {
+        ConstantScoreQuery::new(Arc::new(self))
+    }
+}
@@ -0,0 +1,285 @@
+use {
+    crate::{
+        analysis::{Analyzer, Token},
+        index::Term,
+        search::{BooleanQuery, BoostQuery, MultiPhraseQuery, NGramPhraseQuery, Occur, PhraseQuery, Query, TermQuery},
+        BoxResult,
+    },
+    std::{collections::HashMap, sync::Arc},
+};
+
+/// Creates queries from text with the analyzer its field was indexed with, as a query parser does for each field
+/// clause.
+///
+/// Terms the analyzer puts at the same position, such as synonyms, are alternatives. A phrase whose terms are the
+/// overlapping n-grams of one run of text, as [crate::analysis::CJKBigramAnalyzer] produces, is created as an
+/// [NGramPhraseQuery] so it skips the n-grams its neighbours imply.
+///
+/// Queries on a field with a boost (see [QueryBuilder::set_field_boost]) are wrapped in a [BoostQuery], so matches in
+/// that field weigh more, or less, than matches elsewhere.
+#[derive(Clone, Debug)]
+pub struct QueryBuilder {
+    analyzer: Arc<dyn Analyzer>,
+    auto_generate_phrase_queries: bool,
+    field_boosts: HashMap<String, f32>,
+}
+
+impl QueryBuilder {
+    /// Creates a builder analyzing text with `analyzer`.
+    pub fn new(analyzer: Arc<dyn Analyzer>) -> Self {
+        Self {
+            analyzer,
+            auto_generate_phrase_queries: false,
+            field_boosts: HashMap::new(),
+        }
+    }
+
+    /// Returns the analyzer text is analyzed with.
+    #[inline]
+    pub fn analyzer(&self) -> &Arc<dyn Analyzer> {
+        &self.analyzer
+    }
+
+    /// Indicates whether [QueryBuilder::create_boolean_query] matches words that analyze to several terms as phrases.
+    #[inline]
+    pub fn auto_generate_phrase_queries(&self) -> bool {
+        self.auto_generate_phrase_queries
+    }
+
+    /// Sets whether [QueryBuilder::create_boolean_query] matches each word that analyzes to several terms, such as a
+    /// run of CJK text split into bigrams, as a phrase instead of as separate clauses. This defaults to `false`.
+    pub fn set_auto_generate_phrase_queries(&mut self, auto_generate_phrase_queries: bool) -> &mut Self {
+        self.auto_generate_phrase_queries = auto_generate_phrase_queries;
+        self
+    }
+
+    /// Returns the boost of queries on `field`, which is 1 unless set with [QueryBuilder::set_field_boost].
+    #[inline]
+    pub fn field_boost(&self, field: &str) -> f32 {
+        self.field_boosts.get(field).copied().unwrap_or(1.0)
+    }
+
+    /// Sets the boost of queries on `field`. This fails with [crate::LuceneError::InvalidArgument] if the boost is
+    /// negative or not finite, as [BoostQuery::new] does.
+    pub fn set_field_boost(&mut self, field: &str, boost: f32) -> BoxResult<&mut Self> {
+        BoostQuery::check_boost(boost)?;
+        self.field_boosts.insert(field.to_string(), boost);
+        Ok(self)
+    }
+
+    /// Creates a query matching the terms of `text` in `field`, each as a clause with the given occurrence, or `None`
+    /// if the text has no terms.
+    pub fn create_boolean_query(&self, field: &str, text: &str, occur: Occur) -> Option<Arc<dyn Query>> {
+        self.create_field_boolean_query(field, text, occur).map(|query| self.boosted(field, query))
+    }
+
+    fn create_field_boolean_query(&self, field: &str, text: &str, occur: Occur) -> Option<Arc<dyn Query>> {
+        let tokens = self.analyzer.analyze(field, text);
+
+        // Each word is the terms whose text overlaps, such as bigrams of one run or synonyms of one term.
+        let mut words: Vec<&[Token]> = Vec::new();
+        let mut start = 0;
+        for i in 1..=tokens.len() {
+            let split = match i {
+                i if i == tokens.len() => true,
+                i if self.auto_generate_phrase_queries => tokens[i].start_offset >= tokens[i - 1].end_offset,
+                i => tokens[i].position_increment > 0,
+            };
+            if split {
+                words.push(&tokens[start..i]);
+                start = i;
+            }
+        }
+
+        let mut clauses: Vec<Arc<dyn Query>> = words.into_iter().filter_map(|word| phrase(field, word, 0)).collect();
+        match clauses.len() {
+            0 => None,
+            1 => clauses.pop(),
+            _ => {
+                let mut builder = BooleanQuery::builder();
+                for clause in clauses {
+                    builder.add(clause, occur);
+                }
+                Some(Arc::new(builder.build()))
+            }
+        }
+    }
+
+    /// Creates a query matching `text` in `field` as a phrase, whose terms may be moved by up to `slop` positions,
+    /// or `None` if the text has no terms.
+    pub fn create_phrase_query(&self, field: &str, text: &str, slop: u32) -> Option<Arc<dyn Query>> {
+        phrase(field, &self.analyzer.analyze(field, text), slop).map(|query| self.boosted(field, query))
+    }
+
+    /// Creates a query matching `text` in any of `fields`, each queried as with [QueryBuilder::create_boolean_query]
+    /// and boosted by its field boost, or `None` if the text has no terms in any field.
+    pub fn create_multi_field_query(&self, fields: &[&str], text: &str, occur: Occur) -> Option<Arc<dyn Query>> {
+        let mut clauses: Vec<Arc<dyn Query>> =
+            fields.iter().filter_map(|field| self.create_boolean_query(field, text, occur)).collect();
+        match clauses.len() {
+            0 => None,
+            1 => clauses.pop(),
+            _ => {
+                let mut builder = BooleanQuery::builder();
+                for clause in clauses {
+                    builder.should(clause);
+                }
+                Some(Arc::new(builder.build()))
+            }
+        }
+    }
+
+    /// Wraps a query on `field` in its field boost, if it has one.
+    fn boosted(&self, field: &str, query: Arc<dyn Query>) -> Arc<dyn Query> {
+        let boost = self.field_boost(field);
+        if boost == 1.0 {
+            query
+        } else {
+            Arc::new(BoostQuery::new(query, boost).expect("field boosts are validated when set"))
+        }
+    }
+}
+
+/// Creates the query for a phrase of tokens.
+fn phrase(field: &str, tokens: &[Token], slop: u32) -> Option<Arc<dyn Query>> {
+    let mut term_arrays: Vec<Vec<Term>> = Vec::new();
+    let mut positions: Vec<u32> = Vec::new();
+    for token in tokens {
+        let term = Term::from_text(field, &token.term);
+        match positions.last() {
+            Some(_) if token.position_increment == 0 => term_arrays.last_mut().unwrap().push(term),
+            last => {
+                positions.push(last.map_or(0, |last| last + token.position_increment));
+                term_arrays.push(vec![term]);
+            }
+        }
+    }
+
+    match term_arrays.as_slice() {
+        [] => None,
+        [terms] if terms.len() == 1 => Some(Arc::new(TermQuery::new(terms[0].clone()))),
+        [terms] => {
+            let mut builder = BooleanQuery::builder();
+            for term in terms {
+                builder.add(Arc::new(TermQuery::new(term.clone())), Occur::Should);
+            }
+            Some(Arc::new(builder.build()))
+        }
+        _ if term_arrays.iter().any(|terms| terms.len() > 1) => {
+            let mut builder = MultiPhraseQuery::builder();
+            for (terms, position) in term_arrays.into_iter().zip(positions) {
+                builder.add_at(terms, position);
+            }
+            builder.set_slop(slop);
+            Some(Arc::new(builder.build().expect("terms are in one field and in order")))
+        }
+        _ => {
+            let mut builder = PhraseQuery::builder();
+            for (mut terms, position) in term_arrays.into_iter().zip(positions) {
+                builder.add_at(terms.pop().unwrap(), position);
+            }
+            builder.set_slop(slop);
+            let query = builder.build().expect("terms are in one field and in order");
+            match n_gram_size(tokens) {
+                Some(n) if slop == 0 => Some(Arc::new(NGramPhraseQuery::new(n, query))),
+                _ => Some(Arc::new(query)),
+            }
+        }
+    }
+}
+
+/// Returns the size of the n-grams if the tokens are the overlapping n-grams of one run of text, each starting a
+/// character after the last.
+fn n_gram_size(tokens: &[Token]) -> Option<usize> {
+    let n = tokens[0].term.chars().count();
+    let first_char_len = |token: &Token| token.term.chars().next().map_or(0, char::len_utf8) as u32;
+    let is_run = n >= 2
+        && tokens.iter().all(|token| token.position_increment == 1 && token.term.chars().count() == n)
+        && tokens.windows(2).all(|pair| pair[1].start_offset == pair[0].start_offset + first_char_len(&pair[0]));
+    is_run.then_some(n)
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{
+            analysis::{CJKBigramAnalyzer, SimpleAnalyzer},
+            document::{Document, Field, Store},
+            search::{
+                test_util::{leaf_searcher, searcher, segment_with_analyzer},
+                NGramPhraseQuery, Occur, QueryBuilder,
+            },
+        },
+        pretty_assertions::assert_eq,
+        std::{any::Any, sync::Arc},
+    };
+
+    #[test]
+    fn test_query_builder() {
+        let documents = ["東京都庁の展望室", "京都の都庁", "東京と大阪", "Tokyo tower"]
+            .map(|body| Document::from_iter([Field::text("body", body, Store::No)]));
+        let searcher = leaf_searcher(vec![segment_with_analyzer(Arc::new(CJKBigramAnalyzer), documents)]);
+
+        let mut query_builder = QueryBuilder::new(Arc::new(CJKBigramAnalyzer));
+        let query = query_builder.create_phrase_query("body", "東京都庁", 0).unwrap();
+        assert!((query.as_ref() as &dyn Any).downcast_ref::<NGramPhraseQuery>().is_some());
+        assert_eq!(query.to_string(), "body:\"東京 京都 都庁\"");
+        assert_eq!(searcher.search(query.as_ref(), 10).unwrap().score_docs[0].doc, 0);
+        assert_eq!(searcher.count(query.as_ref()).unwrap(), 1);
+
+        // Without phrases, any bigram of the words matches.
+        let query = query_builder.create_boolean_query("body", "東京都庁 tower", Occur::Should).unwrap();
+        assert_eq!(query.to_string(), "body:東京 body:京都 body:都庁 body:tower");
+        assert_eq!(searcher.count(query.as_ref()).unwrap(), 4);
+
+        // With phrases, each word must match in full.
+        query_builder.set_auto_generate_phrase_queries(true);
+        let query = query_builder.create_boolean_query("body", "東京都庁 tower", Occur::Should).unwrap();
+        assert_eq!(query.to_string(), "body:\"東京 京都 都庁\" body:tower");
+        assert_eq!(searcher.count(query.as_ref()).unwrap(), 2);
+        let query = query_builder.create_boolean_query("body", "京都 大阪", Occur::Must).unwrap();
+        assert_eq!(query.to_string(), "+body:京都 +body:大阪");
+        assert_eq!(searcher.count(query.as_ref()).unwrap(), 0);
+
+        // A sloppy phrase, or one of whole words, isn't made of n-grams.
+        let query = query_builder.create_phrase_query("body", "東京都", 1).unwrap();
+        assert_eq!(query.to_string(), "body:\"東京 京都\"~1");
+        let query = QueryBuilder::new(Arc::new(SimpleAnalyzer)).create_phrase_query("body", "tokyo tower", 0).unwrap();
+        assert!((query.as_ref() as &dyn Any).downcast_ref::<NGramPhraseQuery>().is_none());
+        assert_eq!(searcher.count(query.as_ref()).unwrap(), 1);
+
+        assert!(query_builder.create_phrase_query("body", " ,. ", 0).is_none());
+    }
+
+    #[test]
+    fn test_field_boosts() {
+        let mut documents = Vec::new();
+        for (title, body) in [("rust search", "a library"), ("a library", "rust search")] {
+            let mut doc = Document::new();
+            doc.add(Field::text("title", title, Store::No));
+            doc.add(Field::text("body", body, Store::No));
+            documents.push(doc);
+        }
+        let searcher = searcher([documents]);
+
+        let mut query_builder = QueryBuilder::new(Arc::new(SimpleAnalyzer));
+        query_builder.set_field_boost("title", 3.0).unwrap();
+        assert_eq!(query_builder.field_boost("title"), 3.0);
+        assert_eq!(query_builder.field_boost("body"), 1.0);
+        assert!(query_builder.set_field_boost("body", -1.0).is_err());
+        assert!(query_builder.set_field_boost("body", f32::NAN).is_err());
+
+        let query = query_builder.create_multi_field_query(&["title", "body"], "rust search", Occur::Should).unwrap();
+        assert_eq!(query.to_string(), "(title:rust title:search)^3 (body:rust body:search)");
+        let hits = searcher.search(query.as_ref(), 10).unwrap().score_docs;
+        assert_eq!(hits[0].doc, 0);
+        assert!((hits[0].score - 3.0 * hits[1].score).abs() < 1e-5);
+        let explanation = searcher.explain(query.as_ref(), 0).unwrap();
+        assert!((explanation.value() - hits[0].score).abs() < 1e-5);
+        assert!(explanation.to_string().contains("3 = boost"), "{explanation}");
+
+        let query = query_builder.create_phrase_query("title", "rust search", 0).unwrap();
+        assert_eq!(query.to_string(), "(title:\"rust search\")^3");
+        assert!(query_builder.create_multi_field_query(&["title", "body"], " ", Occur::Should).is_none());
+    }
+}
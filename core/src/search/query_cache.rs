@@ -0,0 +1,363 @@
+use {
+    crate::{
+        index::{LeafReader, LeafReaderContext},
+        search::{
+            cacheable_doc_id_set, ConstantScoreScorer, DocIdSet, EagerScorerSupplier, Explanation, Query, Scorer,
+            ScorerSupplier, Weight,
+        },
+        BoxResult,
+    },
+    std::{
+        any::{Any, TypeId},
+        collections::{BTreeMap, HashMap},
+        sync::{Arc, Mutex, Weak},
+    },
+};
+
+/// A filter isn't cached when it would match this many times more documents than the lead clause it's intersected
+/// with: evaluating it in full would cost far more than advancing it to the lead's candidates.
+const SKIP_CACHE_FACTOR: u64 = 250;
+
+/// Identifies a query in the cache by its type and its [std::fmt::Debug] form, since queries aren't comparable. Unlike the
+/// [std::fmt::Display] form, this covers every option of the query, such as whether a pattern ignores case.
+type QueryKey = (TypeId, String);
+
+/// A cache of the documents matched by queries whose scores aren't needed, such as [crate::search::Occur::Filter]
+/// clauses and the queries wrapped by [crate::search::ConstantScoreQuery], for each segment.
+///
+/// A searcher uses the cache once it's set with [crate::search::IndexSearcher::set_query_cache]. The first time a
+/// query runs on a segment, its matches are collected into a [DocIdSet]; later searches with the same query iterate
+/// that set instead. Deleted documents are included, and are skipped when hits are collected, so a set stays valid
+/// as documents are deleted. The least recently used sets are evicted once the cache holds more than its maximum
+/// number of sets or bytes.
+///
+/// Entries are tied to the segment's reader, and are dropped once that reader is.
+#[derive(Debug)]
+pub struct LruQueryCache {
+    max_size: usize,
+    max_ram_bytes: usize,
+    state: Mutex<CacheState>,
+}
+
+#[derive(Debug, Default)]
+struct CacheState {
+    /// The cached sets of each segment, by the address of its reader.
+    segments: HashMap<usize, SegmentCache>,
+
+    /// The segment and query of each cached set, ordered by when it was last used.
+    lru: BTreeMap<u64, (usize, QueryKey)>,
+    clock: u64,
+    ram_bytes_used: usize,
+    hit_count: u64,
+    miss_count: u64,
+}
+
+#[derive(Debug)]
+struct SegmentCache {
+    reader: Weak<dyn LeafReader>,
+    sets: HashMap<QueryKey, (Arc<dyn DocIdSet>, u64)>,
+}
+
+impl LruQueryCache {
+    /// Creates a cache holding up to `max_size` sets of documents, taking up to `max_ram_bytes` bytes.
+    pub fn new(max_size: usize, max_ram_bytes: usize) -> Self {
+        Self {
+            max_size,
+            max_ram_bytes,
+            state: Mutex::default(),
+        }
+    }
+
+    /// Returns the most sets of documents the cache holds.
+    #[inline]
+    pub fn max_size(&self) -> usize {
+        self.max_size
+    }
+
+    /// Returns the most memory the cache uses, in bytes.
+    #[inline]
+    pub fn max_ram_bytes(&self) -> usize {
+        self.max_ram_bytes
+    }
+
+    /// Returns the number of sets of documents in the cache.
+    pub fn size(&self) -> usize {
+        self.state.lock().unwrap().lru.len()
+    }
+
+    /// Returns the memory used by the cached sets, in bytes.
+    pub fn ram_bytes_used(&self) -> usize {
+        self.state.lock().unwrap().ram_bytes_used
+    }
+
+    /// Returns the number of times a query's documents were found in the cache.
+    pub fn hit_count(&self) -> u64 {
+        self.state.lock().unwrap().hit_count
+    }
+
+    /// Returns the number of times a query's documents weren't in the cache.
+    pub fn miss_count(&self) -> u64 {
+        self.state.lock().unwrap().miss_count
+    }
+
+    /// Removes every set from the cache.
+    pub fn clear(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.segments.clear();
+        state.lru.clear();
+        state.ram_bytes_used = 0;
+    }
+
+    /// Wraps the weight of a query whose scores aren't needed so that its matches are cached.
+    pub(crate) fn do_cache(self: &Arc<Self>, query: &dyn Query, weight: Box<dyn Weight>) -> Box<dyn Weight> {
+        Box::new(CachingWrapperWeight {
+            cache: self.clone(),
+            key: (Any::type_id(query), format!("{query:?}")),
+            weight,
+        })
+    }
+
+    fn get(&self, reader: &Arc<dyn LeafReader>, key: &QueryKey) -> Option<Arc<dyn DocIdSet>> {
+        let mut state = self.state.lock().unwrap();
+        let segment_key = Arc::as_ptr(reader) as *const () as usize;
+        let stamp = state.clock;
+        state.clock += 1;
+
+        let found = state
+            .segments
+            .get_mut(&segment_key)
+            .filter(|segment| segment.is_for(reader))
+            .and_then(|segment| segment.sets.get_mut(key))
+            .map(|(set, last_used)| (set.clone(), std::mem::replace(last_used, stamp)));
+
+        match found {
+            Some((set, last_used)) => {
+                state.hit_count += 1;
+                state.lru.remove(&last_used);
+                state.lru.insert(stamp, (segment_key, key.clone()));
+                Some(set)
+            }
+            None => {
+                state.miss_count += 1;
+                None
+            }
+        }
+    }
+
+    fn put(&self, reader: &Arc<dyn LeafReader>, key: QueryKey, set: Arc<dyn DocIdSet>) {
+        let ram_bytes = set.ram_bytes_used() + key.1.len();
+        if ram_bytes > self.max_ram_bytes || self.max_size == 0 {
+            return;
+        }
+
+        let mut state = self.state.lock().unwrap();
+        let segment_key = Arc::as_ptr(reader) as *const () as usize;
+
+        // A reader at the same address as a dropped one is a different segment.
+        if state.segments.get(&segment_key).is_some_and(|segment| !segment.is_for(reader)) {
+            state.remove_segment(segment_key);
+        }
+
+        let stamp = state.clock;
+        state.clock += 1;
+        let segment = state.segments.entry(segment_key).or_insert_with(|| SegmentCache {
+            reader: Arc::downgrade(reader),
+            sets: HashMap::new(),
+        });
+        if segment.sets.contains_key(&key) {
+            return;
+        }
+        segment.sets.insert(key.clone(), (set, stamp));
+        state.lru.insert(stamp, (segment_key, key));
+        state.ram_bytes_used += ram_bytes;
+
+        while state.lru.len() > self.max_size || state.ram_bytes_used > self.max_ram_bytes {
+            let Some((_, (segment_key, key))) = state.lru.pop_first() else {
+                break;
+            };
+            state.remove_set(segment_key, &key);
+        }
+    }
+}
+
+impl CacheState {
+    fn remove_set(&mut self, segment_key: usize, key: &QueryKey) {
+        let Some(segment) = self.segments.get_mut(&segment_key) else {
+            return;
+        };
+        if let Some((set, _)) = segment.sets.remove(key) {
+            self.ram_bytes_used -= set.ram_bytes_used() + key.1.len();
+        }
+        if segment.sets.is_empty() {
+            self.segments.remove(&segment_key);
+        }
+    }
+
+    fn remove_segment(&mut self, segment_key: usize) {
+        if let Some(segment) = self.segments.remove(&segment_key) {
+            for (key, (set, last_used)) in segment.sets {
+                self.lru.remove(&last_used);
+                self.ram_bytes_used -= set.ram_bytes_used() + key.1.len();
+            }
+        }
+    }
+}
+
+impl SegmentCache {
+    /// Indicates whether this cache is for `reader`, rather than for a dropped reader at the same address.
+    fn is_for(&self, reader: &Arc<dyn LeafReader>) -> bool {
+        self.reader.upgrade().is_some_and(|cached| Arc::ptr_eq(&cached, reader))
+    }
+}
+
+/// The weight of a query whose matches are cached by a [LruQueryCache].
+#[derive(Debug)]
+struct CachingWrapperWeight {
+    cache: Arc<LruQueryCache>,
+    key: QueryKey,
+    weight: Box<dyn Weight>,
+}
+
+impl Weight for CachingWrapperWeight {
+    fn scorer(&self, context: &LeafReaderContext) -> BoxResult<Option<Box<dyn Scorer>>> {
+        self.scorer_supplier(context)?.map(|supplier| supplier.get(u64::MAX)).transpose()
+    }
+
+    fn scorer_supplier(&self, context: &LeafReaderContext) -> BoxResult<Option<Box<dyn ScorerSupplier + '_>>> {
+        if let Some(set) = self.cache.get(context.reader_arc(), &self.key) {
+            let scorer = Box::new(ConstantScoreScorer::new(0.0, set.iterator()));
+            return Ok(Some(Box::new(EagerScorerSupplier::new(scorer))));
+        }
+
+        let Some(supplier) = self.weight.scorer_supplier(context)? else {
+            return Ok(None);
+        };
+
+        Ok(Some(Box::new(CachingScorerSupplier {
+            weight: self,
+            reader: context.reader_arc().clone(),
+            supplier,
+        })))
+    }
+
+    fn explain(&self, context: &LeafReaderContext, doc: u32) -> BoxResult<Explanation> {
+        self.weight.explain(context, doc)
+    }
+}
+
+/// Supplies the scorer of a query that isn't cached for a segment yet, caching its matches if the whole segment is
+/// worth evaluating.
+#[derive(Debug)]
+struct CachingScorerSupplier<'a> {
+    weight: &'a CachingWrapperWeight,
+    reader: Arc<dyn LeafReader>,
+    supplier: Box<dyn ScorerSupplier + 'a>,
+}
+
+impl ScorerSupplier for CachingScorerSupplier<'_> {
+    fn get(self: Box<Self>, lead_cost: u64) -> BoxResult<Box<dyn Scorer>> {
+        if self.supplier.cost() / SKIP_CACHE_FACTOR > lead_cost {
+            return self.supplier.get(lead_cost);
+        }
+
+        let mut scorer = self.supplier.get(u64::MAX)?;
+        let set = cacheable_doc_id_set(scorer.as_mut(), self.reader.max_doc())?;
+        self.weight.cache.put(&self.reader, self.weight.key.clone(), set.clone());
+        Ok(Box::new(ConstantScoreScorer::new(0.0, set.iterator())))
+    }
+
+    fn cost(&self) -> u64 {
+        self.supplier.cost()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{
+            index::{IndexReader, MultiReader, Term},
+            search::{
+                test_util::{body_searcher, body_segment},
+                BooleanQuery, ConstantScoreQuery, IndexSearcher, LruQueryCache, Query, TermQuery, WildcardQuery,
+            },
+        },
+        pretty_assertions::assert_eq,
+        std::sync::Arc,
+    };
+
+    fn term(text: &str) -> Arc<dyn Query> {
+        Arc::new(TermQuery::new(Term::from_text("body", text)))
+    }
+
+    #[test]
+    fn test_filter_caching() {
+        let segments = vec![body_segment(&["a b", "a", "b c"]), body_segment(&["c", "a c", "a b c"])];
+        let reader: Arc<dyn IndexReader> = Arc::new(MultiReader::new(segments).unwrap());
+        let mut searcher = IndexSearcher::new(reader.clone());
+        let cache = Arc::new(LruQueryCache::new(100, 1 << 20));
+        searcher.set_query_cache(Some(cache.clone()));
+
+        // The first search caches the filter in each segment; the second reuses it.
+        let query = BooleanQuery::builder().must(term("a")).filter(term("c")).build();
+        let uncached = IndexSearcher::new(reader).search(&query, 10).unwrap();
+        assert_eq!(searcher.search(&query, 10).unwrap().score_docs, uncached.score_docs);
+        assert_eq!((cache.size(), cache.hit_count(), cache.miss_count()), (2, 0, 2));
+        assert_eq!(searcher.search(&query, 10).unwrap().score_docs, uncached.score_docs);
+        assert_eq!((cache.size(), cache.hit_count(), cache.miss_count()), (2, 2, 2));
+        assert!(cache.ram_bytes_used() > 0);
+
+        // The same filter next to another scoring query, and the same query wrapped for a constant score.
+        let query = BooleanQuery::builder().must(term("b")).filter(term("c")).build();
+        assert_eq!(searcher.search(&query, 10).unwrap().total_hits.value, 2);
+        assert_eq!(cache.hit_count(), 4);
+        let constant = ConstantScoreQuery::new(term("c"));
+        assert_eq!(searcher.search(&constant, 10).unwrap().total_hits.value, 4);
+        assert_eq!(cache.hit_count(), 6);
+
+        // Scoring clauses aren't cached; when nothing is scored, as when counting, every clause is.
+        assert_eq!(searcher.search(&*term("a"), 10).unwrap().total_hits.value, 4);
+        assert_eq!(cache.size(), 2);
+        assert_eq!(searcher.count(&query).unwrap(), 2);
+        assert_eq!((cache.size(), cache.hit_count()), (6, 8));
+
+        cache.clear();
+        assert_eq!((cache.size(), cache.ram_bytes_used()), (0, 0));
+    }
+
+    #[test]
+    fn test_eviction() {
+        let mut searcher = body_searcher(&["a b", "a", "b c", "c"]);
+        let cache = Arc::new(LruQueryCache::new(2, 1 << 20));
+        searcher.set_query_cache(Some(cache.clone()));
+
+        for text in ["a", "b", "a", "c"] {
+            searcher.count(&*term(text)).unwrap();
+        }
+        // "b" was the least recently used when "c" was added.
+        assert_eq!((cache.size(), cache.hit_count(), cache.miss_count()), (2, 1, 3));
+        searcher.count(&*term("a")).unwrap();
+        searcher.count(&*term("b")).unwrap();
+        assert_eq!((cache.hit_count(), cache.miss_count()), (2, 4));
+
+        // Sets larger than the whole budget aren't cached.
+        let tiny = Arc::new(LruQueryCache::new(100, 8));
+        searcher.set_query_cache(Some(tiny.clone()));
+        assert_eq!(searcher.count(&*term("a")).unwrap(), 2);
+        assert_eq!((tiny.size(), tiny.ram_bytes_used()), (0, 0));
+    }
+
+    #[test]
+    fn test_distinct_options() {
+        let mut searcher = body_searcher(&["a b", "b c", "ab"]);
+        let cache = Arc::new(LruQueryCache::new(100, 1 << 20));
+        searcher.set_query_cache(Some(cache.clone()));
+
+        // Both queries print as body:A*, but only one of them ignores case, so they can't share an entry.
+        let sensitive = WildcardQuery::new(Term::from_text("body", "A*")).unwrap();
+        let insensitive = WildcardQuery::new_case_insensitive(Term::from_text("body", "A*")).unwrap();
+        assert_eq!(sensitive.to_string(), insensitive.to_string());
+        assert_eq!(searcher.count(&insensitive).unwrap(), 2);
+        assert_eq!(searcher.count(&sensitive).unwrap(), 0);
+        assert_eq!((cache.size(), cache.hit_count()), (1, 0));
+    }
+}
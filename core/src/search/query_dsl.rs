@@ -0,0 +1,190 @@
+use {
+    crate::{
+        analysis::Analyzer,
+        index::Term,
+        search::{
+            BooleanQuery, BoostQuery, ConstantScoreQuery, MatchAllDocsQuery, MatchNoDocsQuery, Occur, Query,
+            TermInSetQuery, TermQuery,
+        },
+        BoxResult,
+    },
+    serde::{Deserialize, Serialize},
+    std::sync::Arc,
+};
+
+/// A query in a JSON query language, for applications that take queries from outside, such as a search server.
+///
+/// Each query is an object with a single key naming its type, for example:
+///
+/// ```json
+/// {"bool": {"must": [{"match": {"field": "body", "text": "quick fox"}}],
+///           "must_not": [{"term": {"field": "tags", "value": "draft"}}]}}
+/// ```
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QueryDsl {
+    /// Matches every document.
+    MatchAll {},
+
+    /// Matches no documents.
+    MatchNone {},
+
+    /// Matches documents containing an exact term.
+    Term {
+        /// The field to search.
+        field: String,
+
+        /// The term, which is not analyzed.
+        value: String,
+    },
+
+    /// Matches documents containing any of a set of exact terms, without scoring.
+    Terms {
+        /// The field to search.
+        field: String,
+
+        /// The terms, which are not analyzed.
+        values: Vec<String>,
+    },
+
+    /// Matches documents containing any of the terms the text analyzes into, scoring documents that contain more of
+    /// them higher.
+    Match {
+        /// The field to search.
+        field: String,
+
+        /// The text, which is analyzed as the field is when indexing.
+        text: String,
+    },
+
+    /// Combines queries with boolean logic.
+    Bool {
+        /// Queries that must match and contribute to the score.
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        must: Vec<QueryDsl>,
+
+        /// Queries that must match without contributing to the score.
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        filter: Vec<QueryDsl>,
+
+        /// Queries that should match; documents that match more of them score higher.
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        should: Vec<QueryDsl>,
+
+        /// Queries that must not match.
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        must_not: Vec<QueryDsl>,
+    },
+
+    /// Multiplies the scores of a query.
+    Boost {
+        /// The query to boost.
+        query: Box<QueryDsl>,
+
+        /// The multiplier, which must be finite and non-negative.
+        boost: f32,
+    },
+
+    /// Matches the documents of a query, giving each a score of 1.
+    ConstantScore {
+        /// The query to match.
+        query: Box<QueryDsl>,
+    },
+}
+
+impl QueryDsl {
+    /// Converts the query into a [Query], analyzing the text of `match` queries with `analyzer`.
+    pub fn to_query(&self, analyzer: &dyn Analyzer) -> BoxResult<Arc<dyn Query>> {
+        Ok(match self {
+            Self::MatchAll {} => Arc::new(MatchAllDocsQuery),
+            Self::MatchNone {} => Arc::new(MatchNoDocsQuery::new("match_none query")),
+            Self::Term {
+                field,
+                value,
+            } => Arc::new(TermQuery::new(Term::from_text(field, value))),
+            Self::Terms {
+                field,
+                values,
+            } => Arc::new(TermInSetQuery::new(field, values.iter().map(|value| value.as_bytes()))),
+            Self::Match {
+                field,
+                text,
+            } => {
+                let mut terms: Vec<String> =
+                    analyzer.analyze(field, text).into_iter().map(|token| token.term).collect();
+                terms.sort();
+                terms.dedup();
+                match terms.as_slice() {
+                    [] => Arc::new(MatchNoDocsQuery::new(format!("no terms in {text:?}"))),
+                    [term] => Arc::new(TermQuery::new(Term::from_text(field, term))),
+                    _ => {
+                        let mut builder = BooleanQuery::builder();
+                        for term in &terms {
+                            builder.add(Arc::new(TermQuery::new(Term::from_text(field, term))), Occur::Should);
+                        }
+                        Arc::new(builder.build())
+                    }
+                }
+            }
+            Self::Bool {
+                must,
+                filter,
+                should,
+                must_not,
+            } => {
+                let mut builder = BooleanQuery::builder();
+                for (queries, occur) in
+                    [(must, Occur::Must), (filter, Occur::Filter), (should, Occur::Should), (must_not, Occur::MustNot)]
+                {
+                    for query in queries {
+                        builder.add(query.to_query(analyzer)?, occur);
+                    }
+                }
+                Arc::new(builder.build())
+            }
+            Self::Boost {
+                query,
+                boost,
+            } => Arc::new(BoostQuery::new(query.to_query(analyzer)?, *boost)?),
+            Self::ConstantScore {
+                query,
+            } => Arc::new(ConstantScoreQuery::new(query.to_query(analyzer)?)),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{analysis::SimpleAnalyzer, search::QueryDsl},
+        pretty_assertions::assert_eq,
+    };
+
+    fn parse(json: &str) -> String {
+        let dsl: QueryDsl = serde_json::from_str(json).unwrap();
+        dsl.to_query(&SimpleAnalyzer).unwrap().to_string()
+    }
+
+    #[test]
+    fn test_query_dsl() {
+        assert_eq!(parse(r#"{"match_all": {}}"#), "*:*");
+        assert_eq!(parse(r#"{"term": {"field": "tags", "value": "Rust"}}"#), "tags:Rust");
+        assert_eq!(parse(r#"{"match": {"field": "body", "text": "Quick FOX"}}"#), "body:fox body:quick");
+        assert_eq!(parse(r#"{"match": {"field": "body", "text": "Rust"}}"#), "body:rust");
+        assert_eq!(
+            parse(
+                r#"{"bool": {"must": [{"match": {"field": "body", "text": "fox"}}],
+                             "must_not": [{"term": {"field": "tags", "value": "draft"}}]}}"#
+            ),
+            "+body:fox -tags:draft"
+        );
+        assert_eq!(
+            parse(r#"{"boost": {"query": {"term": {"field": "title", "value": "fox"}}, "boost": 2.0}}"#),
+            "(title:fox)^2"
+        );
+
+        assert!(serde_json::from_str::<QueryDsl>(r#"{"unknown": {}}"#).is_err());
+        let dsl: QueryDsl = serde_json::from_str(r#"{"boost": {"query": {"match_all": {}}, "boost": -1.0}}"#).unwrap();
+        assert!(dsl.to_query(&SimpleAnalyzer).is_err());
+    }
+}
@@ -0,0 +1,181 @@
+use {
+    crate::{
+        index::sub_index,
+        search::{
+            rescore_window, Explanation, IndexSearcher, Query, Rescorer, ScoreCombination, ScoreDoc, ScoreMode,
+            TopDocs, DEFAULT_RESCORE_WINDOW_SIZE,
+        },
+        BoxResult,
+    },
+    std::sync::Arc,
+};
+
+/// A [Rescorer] that runs a second query over the top hits, combining its scores with the first-pass scores.
+#[derive(Clone, Debug)]
+pub struct QueryRescorer {
+    query: Arc<dyn Query>,
+    window_size: usize,
+    combination: ScoreCombination,
+}
+
+impl QueryRescorer {
+    /// Creates a rescorer adding the scores of `query` to the first-pass scores of the top
+    /// [DEFAULT_RESCORE_WINDOW_SIZE] hits.
+    pub fn new(query: Arc<dyn Query>) -> Self {
+        Self {
+            query,
+            window_size: DEFAULT_RESCORE_WINDOW_SIZE,
+            combination: ScoreCombination::default(),
+        }
+    }
+
+    /// Returns the second-pass query.
+    #[inline]
+    pub fn query(&self) -> &Arc<dyn Query> {
+        &self.query
+    }
+
+    /// Returns the number of top first-pass hits that are rescored.
+    #[inline]
+    pub fn window_size(&self) -> usize {
+        self.window_size
+    }
+
+    /// Sets the number of top first-pass hits that are rescored.
+    pub fn set_window_size(&mut self, window_size: usize) -> &mut Self {
+        self.window_size = window_size;
+        self
+    }
+
+    /// Returns how first- and second-pass scores are combined.
+    #[inline]
+    pub fn combination(&self) -> ScoreCombination {
+        self.combination
+    }
+
+    /// Sets how first- and second-pass scores are combined.
+    pub fn set_combination(&mut self, combination: ScoreCombination) -> &mut Self {
+        self.combination = combination;
+        self
+    }
+}
+
+impl Rescorer for QueryRescorer {
+    fn rescore(&self, searcher: &IndexSearcher, first_pass: &TopDocs, top_n: usize) -> BoxResult<TopDocs> {
+        rescore_window(first_pass, self.window_size, top_n, self.combination, |hits| {
+            query_scores(searcher, self.query.as_ref(), hits)
+        })
+    }
+
+    fn explain(&self, searcher: &IndexSearcher, first_pass: Explanation, doc: u32) -> BoxResult<Explanation> {
+        let second = searcher.explain(self.query.as_ref(), doc)?;
+        Ok(self.combination.explain(first_pass, Some(second)))
+    }
+}
+
+/// Returns the scores of `query` for the given hits, which must be sorted by document id, or `None` for hits it
+/// doesn't match.
+pub(crate) fn query_scores(
+    searcher: &IndexSearcher,
+    query: &dyn Query,
+    hits: &[ScoreDoc],
+) -> BoxResult<Vec<Option<f32>>> {
+    let weight = searcher.create_weight(query, ScoreMode::Complete, 1.0)?;
+    let leaves = searcher.leaves();
+    let mut scores = Vec::with_capacity(hits.len());
+    let mut current_leaf = None;
+    let mut scorer = None;
+
+    // The scorer's current document, which may already be past the next hit.
+    let mut scorer_doc = None;
+
+    for hit in hits {
+        let leaf_index = sub_index(hit.doc, leaves);
+        let leaf = &leaves[leaf_index];
+        if current_leaf != Some(leaf_index) {
+            current_leaf = Some(leaf_index);
+            scorer = weight.scorer(leaf)?;
+            scorer_doc = None;
+        }
+
+        let target = hit.doc - leaf.doc_base();
+        let mut score = None;
+        if let Some(scorer) = &mut scorer {
+            let doc = match scorer_doc {
+                Some(doc) if doc >= target => doc,
+                _ => scorer.advance(target)?,
+            };
+            scorer_doc = Some(doc);
+
+            if doc == target {
+                score = Some(scorer.score()?);
+            }
+        }
+        scores.push(score);
+    }
+
+    Ok(scores)
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{
+            document::{Document, Field, Store},
+            index::Term,
+            search::{test_util::searcher, QueryRescorer, Rescorer, ScoreCombination, TermQuery},
+        },
+        pretty_assertions::assert_eq,
+        std::sync::Arc,
+    };
+
+    #[test]
+    fn test_query_rescorer() {
+        let mut segments = Vec::new();
+        for bodies in [&["fox", "fox dog dog"][..], &["fox fox dog", "fox cat"][..]] {
+            let mut documents = Vec::new();
+            for body in bodies {
+                let mut doc = Document::new();
+                doc.add(Field::text("body", *body, Store::No));
+                documents.push(doc);
+            }
+            segments.push(documents);
+        }
+        let searcher = searcher(segments);
+
+        let fox = TermQuery::new(Term::from_text("body", "fox"));
+        let first_pass = searcher.search(&fox, 10).unwrap();
+        assert_eq!(first_pass.score_docs.len(), 4);
+
+        let dog = Arc::new(TermQuery::new(Term::from_text("body", "dog")));
+        let mut rescorer = QueryRescorer::new(dog.clone());
+        let rescored = rescorer.rescore(&searcher, &first_pass, 10).unwrap();
+        assert_eq!(rescored.total_hits, first_pass.total_hits);
+        let mut docs: Vec<u32> = rescored.score_docs.iter().map(|sd| sd.doc).collect();
+        // Matching "dog" as well as "fox" moves documents 1 and 2 ahead of the others.
+        docs[..2].sort();
+        assert_eq!(&docs[..2], &[1, 2]);
+
+        for sd in &rescored.score_docs {
+            let first = searcher.explain(&fox, sd.doc).unwrap();
+            assert_eq!(rescorer.explain(&searcher, first, sd.doc).unwrap().value(), sd.score);
+        }
+
+        // Documents the second query doesn't match keep their first-pass score.
+        rescorer.set_combination(ScoreCombination::Replace);
+        let rescored = rescorer.rescore(&searcher, &first_pass, 10).unwrap();
+        for sd in &rescored.score_docs {
+            let expected = match sd.doc {
+                1 | 2 => searcher.explain(dog.as_ref(), sd.doc).unwrap().value(),
+                _ => searcher.explain(&fox, sd.doc).unwrap().value(),
+            };
+            assert_eq!(sd.score, expected);
+        }
+
+        // Only the top hit is rescored; the window is re-sorted in front of the remaining hits.
+        rescorer.set_window_size(1).set_combination(ScoreCombination::default());
+        let rescored = rescorer.rescore(&searcher, &first_pass, 3).unwrap();
+        assert_eq!(rescored.score_docs.len(), 3);
+        assert_eq!(rescored.score_docs[1..], first_pass.score_docs[1..3]);
+    }
+}
@@ -0,0 +1,254 @@
+use {
+    crate::{
+        search::{BulkScorer, LeafCollector, Scorable},
+        BoxError, BoxResult, LuceneError,
+    },
+    std::{
+        fmt::Debug,
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc,
+        },
+        time::{Duration, Instant},
+    },
+};
+
+/// Decides when a running search should stop. Searches poll [QueryTimeout::should_exit] periodically, as often as
+/// every few hundred documents, so implementations should be cheap.
+pub trait QueryTimeout: Debug + Send + Sync {
+    /// Indicates whether the search should stop now.
+    fn should_exit(&self) -> bool;
+}
+
+/// A [QueryTimeout] that expires at a deadline or when cancelled from another thread, whichever comes first.
+///
+/// Clones share their cancellation state, so a clone can be handed to the thread that may cancel the search.
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+    deadline: Option<Instant>,
+}
+
+impl CancellationToken {
+    /// Creates a token that expires only when cancelled.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a token that expires at `deadline`, or earlier if cancelled.
+    pub fn with_deadline(deadline: Instant) -> Self {
+        Self {
+            cancelled: Arc::default(),
+            deadline: Some(deadline),
+        }
+    }
+
+    /// Creates a token that expires once `timeout` has elapsed from now, or earlier if cancelled.
+    pub fn with_timeout(timeout: Duration) -> Self {
+        Self::with_deadline(Instant::now() + timeout)
+    }
+
+    /// Returns the deadline, if any.
+    #[inline]
+    pub fn deadline(&self) -> Option<Instant> {
+        self.deadline
+    }
+
+    /// Cancels searches using this token or any of its clones.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    /// Indicates whether [CancellationToken::cancel] has been called.
+    #[inline]
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+impl QueryTimeout for CancellationToken {
+    fn should_exit(&self) -> bool {
+        self.is_cancelled() || self.deadline.is_some_and(|deadline| Instant::now() >= deadline)
+    }
+}
+
+/// Fails with [LuceneError::SearchAborted] if the search should stop.
+pub(crate) fn check_timeout(timeout: &dyn QueryTimeout) -> BoxResult<()> {
+    if timeout.should_exit() {
+        Err(LuceneError::SearchAborted.into())
+    } else {
+        Ok(())
+    }
+}
+
+/// Indicates whether the error is [LuceneError::SearchAborted].
+pub(crate) fn is_search_aborted(error: &BoxError) -> bool {
+    matches!(error.downcast_ref::<LuceneError>(), Some(LuceneError::SearchAborted))
+}
+
+/// The number of documents scored in the first window of a [TimeLimitingBulkScorer].
+const INITIAL_WINDOW: u32 = 100;
+
+/// A [BulkScorer] that scores documents in growing windows, checking the timeout between windows.
+#[derive(Debug)]
+pub(crate) struct TimeLimitingBulkScorer {
+    inner: Box<dyn BulkScorer>,
+    timeout: Arc<dyn QueryTimeout>,
+}
+
+impl TimeLimitingBulkScorer {
+    pub(crate) fn new(inner: Box<dyn BulkScorer>, timeout: Arc<dyn QueryTimeout>) -> Self {
+        Self {
+            inner,
+            timeout,
+        }
+    }
+}
+
+impl BulkScorer for TimeLimitingBulkScorer {
+    fn score(&mut self, collector: &mut dyn LeafCollector, mut min: u32, max: u32) -> BoxResult<u32> {
+        let mut window = INITIAL_WINDOW;
+        while min < max {
+            check_timeout(self.timeout.as_ref())?;
+            let window_max = min.saturating_add(window).min(max);
+            min = self.inner.score(collector, min, window_max)?;
+
+            // Grow the window by half so the timeout is checked less often on long-running segments.
+            window = window.saturating_add(window / 2);
+        }
+
+        Ok(min)
+    }
+
+    #[inline]
+    fn cost(&self) -> u64 {
+        self.inner.cost()
+    }
+}
+
+/// The number of documents collected between timeout checks by a [TimeLimitingLeafCollector].
+const COLLECT_CHECK_INTERVAL: u32 = 256;
+
+/// A [LeafCollector] that checks the timeout periodically as documents are collected, for bulk scorers that visit
+/// many documents in a single window.
+pub(crate) struct TimeLimitingLeafCollector<'a> {
+    inner: Box<dyn LeafCollector + 'a>,
+    timeout: &'a dyn QueryTimeout,
+    calls: u32,
+}
+
+impl<'a> TimeLimitingLeafCollector<'a> {
+    pub(crate) fn new(inner: Box<dyn LeafCollector + 'a>, timeout: &'a dyn QueryTimeout) -> Self {
+        Self {
+            inner,
+            timeout,
+            calls: 0,
+        }
+    }
+}
+
+impl LeafCollector for TimeLimitingLeafCollector<'_> {
+    fn collect(&mut self, doc: u32, scorer: &mut dyn Scorable) -> BoxResult<()> {
+        self.calls += 1;
+        if self.calls.is_multiple_of(COLLECT_CHECK_INTERVAL) {
+            check_timeout(self.timeout)?;
+        }
+        self.inner.collect(doc, scorer)
+    }
+
+    fn finish(&mut self) -> BoxResult<()> {
+        self.inner.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{
+            document::{Document, Field, Store},
+            index::{ExitableIndexReader, IndexReader, Term},
+            search::{
+                is_search_aborted, test_util, CancellationToken, IndexSearcher, MatchAllDocsQuery, QueryTimeout,
+                TermQuery,
+            },
+        },
+        pretty_assertions::assert_eq,
+        std::{
+            sync::{
+                atomic::{AtomicU32, Ordering},
+                Arc,
+            },
+            time::{Duration, Instant},
+        },
+    };
+
+    /// Expires after it has been polled a fixed number of times.
+    #[derive(Debug)]
+    struct ExitAfterPolls(AtomicU32);
+
+    impl QueryTimeout for ExitAfterPolls {
+        fn should_exit(&self) -> bool {
+            self.0.fetch_sub(1, Ordering::Relaxed) == 0
+        }
+    }
+
+    fn reader() -> Arc<dyn IndexReader> {
+        let mut segments = Vec::new();
+        for _ in 0..3 {
+            let mut documents = Vec::new();
+            for _ in 0..500 {
+                let mut doc = Document::new();
+                doc.add(Field::text("body", "item", Store::No));
+                documents.push(doc);
+            }
+            segments.push(documents);
+        }
+        test_util::reader(segments)
+    }
+
+    #[test]
+    fn test_cancellation_token() {
+        let token = CancellationToken::new();
+        assert!(!token.should_exit());
+        token.clone().cancel();
+        assert!(token.is_cancelled());
+        assert!(token.should_exit());
+
+        let token = CancellationToken::with_timeout(Duration::from_secs(3600));
+        assert!(!token.should_exit());
+        assert!(CancellationToken::with_deadline(Instant::now()).should_exit());
+    }
+
+    #[test]
+    fn test_search_timeout_returns_partial_results() {
+        let mut searcher = IndexSearcher::new(reader());
+        assert_eq!(searcher.count(&MatchAllDocsQuery).unwrap(), 1500);
+        assert!(!searcher.timed_out());
+
+        searcher.set_timeout(Some(Arc::new(ExitAfterPolls(AtomicU32::new(3)))));
+        let count = searcher.count(&MatchAllDocsQuery).unwrap();
+        assert!(count > 0 && count < 1500, "expected partial results, got {count} hits");
+        assert!(searcher.timed_out());
+
+        let token = CancellationToken::new();
+        searcher.set_timeout(Some(Arc::new(token.clone())));
+        assert_eq!(searcher.count(&MatchAllDocsQuery).unwrap(), 1500);
+        assert!(!searcher.timed_out());
+
+        token.cancel();
+        assert_eq!(searcher.count(&MatchAllDocsQuery).unwrap(), 0);
+        assert!(searcher.timed_out());
+    }
+
+    #[test]
+    fn test_exitable_reader() {
+        let token = CancellationToken::new();
+        let searcher = IndexSearcher::new(Arc::new(ExitableIndexReader::new(reader(), Arc::new(token.clone()))));
+        let query = TermQuery::new(Term::from_text("body", "item"));
+        assert_eq!(searcher.count(&query).unwrap(), 1500);
+
+        token.cancel();
+        let error = searcher.count(&query).unwrap_err();
+        assert!(is_search_aborted(&error));
+    }
+}
@@ -0,0 +1,18 @@
+use {
+    crate::{
+        search::{IndexSearcher, Query},
+        BoxResult,
+    },
+    std::{fmt::Debug, sync::Arc},
+};
+
+/// A rewrite rule applied to every query of a tree by a [crate::search::RewritePipeline].
+///
+/// Unlike [Query::rewrite], which a query applies to itself, a visitor sees queries from the outside, so it can
+/// rewrite queries it doesn't own, such as user-defined optimizations over a whole query tree. The pipeline visits
+/// sub-queries before the queries that hold them.
+pub trait QueryVisitor: Debug + Send + Sync {
+    /// Returns a replacement for `query` that matches the same documents with the same scores, or `None` to leave it
+    /// as it is. A visitor must return `None` for a query it has nothing more to do with, or rewriting never ends.
+    fn visit(&self, query: &dyn Query, searcher: &IndexSearcher) -> BoxResult<Option<Arc<dyn Query>>>;
+}
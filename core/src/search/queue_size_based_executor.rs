@@ -0,0 +1,361 @@
+use {
+    crate::{BoxResult, LuceneError},
+    std::{
+        sync::{Condvar, Mutex},
+        thread,
+    },
+};
+
+/// The priority of a search's tasks on a [QueueSizeBasedExecutor].
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum SearchPriority {
+    /// A user-facing search, whose tasks run before any waiting background task.
+    #[default]
+    Interactive,
+
+    /// A search that may wait, such as an analytics or export query. Its tasks only run when no interactive task is
+    /// waiting, and hold at most [QueueSizeBasedExecutor::max_background_concurrency] threads at once.
+    Background,
+}
+
+/// Runs the slices of concurrent searches (see [crate::search::IndexSearcher::set_executor]) with bounded
+/// concurrency, as in Lucene's `QueueSizeBasedExecutor`.
+///
+/// At most [QueueSizeBasedExecutor::max_concurrency] tasks run at once across every search sharing the executor; the
+/// others wait in a queue, where interactive tasks go before background ones (see [SearchPriority]). Once
+/// [QueueSizeBasedExecutor::max_queue_size] tasks are waiting, further tasks run on the thread of the search that
+/// submitted them instead, so a busy executor slows searches down rather than letting its queue grow without bound.
+///
+/// A task must not submit tasks to the same executor, as it could wait for a thread held by itself.
+#[derive(Debug)]
+pub struct QueueSizeBasedExecutor {
+    max_concurrency: usize,
+    max_background_concurrency: usize,
+    max_queue_size: usize,
+    state: Mutex<ExecutorState>,
+    available: Condvar,
+}
+
+#[derive(Debug, Default)]
+struct ExecutorState {
+    running: usize,
+    running_background: usize,
+    queued: usize,
+    queued_interactive: usize,
+}
+
+impl QueueSizeBasedExecutor {
+    /// Creates an executor running at most `max_concurrency` tasks at once, whose queue holds half as many tasks
+    /// again. This fails with [LuceneError::InvalidArgument] if `max_concurrency` is zero.
+    pub fn new(max_concurrency: usize) -> BoxResult<Self> {
+        if max_concurrency == 0 {
+            return Err(LuceneError::InvalidArgument("an executor must run at least one task".to_string()).into());
+        }
+
+        Ok(Self {
+            max_concurrency,
+            max_background_concurrency: max_concurrency,
+            max_queue_size: max_concurrency + max_concurrency / 2,
+            state: Mutex::default(),
+            available: Condvar::new(),
+        })
+    }
+
+    /// Returns the most tasks running at once.
+    #[inline]
+    pub fn max_concurrency(&self) -> usize {
+        self.max_concurrency
+    }
+
+    /// Returns the most background tasks running at once.
+    #[inline]
+    pub fn max_background_concurrency(&self) -> usize {
+        self.max_background_concurrency
+    }
+
+    /// Sets the most background tasks running at once, so that some threads are left for interactive searches. This
+    /// is [QueueSizeBasedExecutor::max_concurrency] by default, and fails with [LuceneError::InvalidArgument] if it's
+    /// zero or more than that.
+    pub fn set_max_background_concurrency(&mut self, max_background_concurrency: usize) -> BoxResult<&mut Self> {
+        if max_background_concurrency == 0 || max_background_concurrency > self.max_concurrency {
+            return Err(LuceneError::InvalidArgument(format!(
+                "the background concurrency must be from 1 to {}, not {max_background_concurrency}",
+                self.max_concurrency
+            ))
+            .into());
+        }
+
+        self.max_background_concurrency = max_background_concurrency;
+        Ok(self)
+    }
+
+    /// Returns the most tasks waiting to run before tasks run on their search's thread.
+    #[inline]
+    pub fn max_queue_size(&self) -> usize {
+        self.max_queue_size
+    }
+
+    /// Sets the most tasks waiting to run before tasks run on their search's thread. This is 1.5 times
+    /// [QueueSizeBasedExecutor::max_concurrency] by default; with zero, every task runs on its search's thread.
+    pub fn set_max_queue_size(&mut self, max_queue_size: usize) -> &mut Self {
+        self.max_queue_size = max_queue_size;
+        self
+    }
+
+    /// Returns the number of tasks running.
+    pub fn running(&self) -> usize {
+        self.state.lock().unwrap().running
+    }
+
+    /// Returns the number of tasks waiting to run.
+    pub fn queued(&self) -> usize {
+        self.state.lock().unwrap().queued
+    }
+
+    /// Runs `tasks` with the given priority, returning their results in order once they've all finished. If a task
+    /// panics, the panic is resumed on the calling thread after the other tasks have finished.
+    pub fn execute<T, F>(&self, priority: SearchPriority, tasks: Vec<F>) -> Vec<T>
+    where
+        T: Send,
+        F: FnOnce() -> T + Send,
+    {
+        thread::scope(|scope| {
+            let mut results: Vec<Option<T>> = Vec::with_capacity(tasks.len());
+            let mut handles = Vec::new();
+            for (i, task) in tasks.into_iter().enumerate() {
+                if self.enqueue(priority) {
+                    handles.push((
+                        i,
+                        scope.spawn(move || {
+                            let _permit = self.acquire(priority);
+                            task()
+                        }),
+                    ));
+                    results.push(None);
+                } else {
+                    results.push(Some(task()));
+                }
+            }
+
+            // Join every thread before resuming a panic.
+            let joined: Vec<_> = handles.into_iter().map(|(i, handle)| (i, handle.join())).collect();
+            for (i, result) in joined {
+                results[i] = Some(result.unwrap_or_else(|panic| std::panic::resume_unwind(panic)));
+            }
+            results.into_iter().map(|result| result.unwrap()).collect()
+        })
+    }
+
+    /// Adds a task to the queue, returning false if the queue is full.
+    fn enqueue(&self, priority: SearchPriority) -> bool {
+        let mut state = self.state.lock().unwrap();
+        if state.queued >= self.max_queue_size {
+            return false;
+        }
+
+        state.queued += 1;
+        if priority == SearchPriority::Interactive {
+            state.queued_interactive += 1;
+        }
+        true
+    }
+
+    /// Waits until a queued task may run, then counts it as running until the permit is dropped.
+    fn acquire(&self, priority: SearchPriority) -> Permit<'_> {
+        let can_run = |state: &ExecutorState| {
+            state.running < self.max_concurrency
+                && match priority {
+                    SearchPriority::Interactive => true,
+                    SearchPriority::Background => {
+                        state.queued_interactive == 0 && state.running_background < self.max_background_concurrency
+                    }
+                }
+        };
+
+        let mut state = self.available.wait_while(self.state.lock().unwrap(), |state| !can_run(state)).unwrap();
+        state.queued -= 1;
+        state.running += 1;
+        match priority {
+            SearchPriority::Interactive => state.queued_interactive -= 1,
+            SearchPriority::Background => state.running_background += 1,
+        }
+
+        // Another waiting task may be able to run now that the queue has changed.
+        self.available.notify_all();
+        Permit {
+            executor: self,
+            priority,
+        }
+    }
+}
+
+/// Releases a running task's place when dropped, even if the task panics.
+struct Permit<'a> {
+    executor: &'a QueueSizeBasedExecutor,
+    priority: SearchPriority,
+}
+
+impl Drop for Permit<'_> {
+    fn drop(&mut self) {
+        let mut state = self.executor.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        state.running -= 1;
+        if self.priority == SearchPriority::Background {
+            state.running_background -= 1;
+        }
+        self.executor.available.notify_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{
+            document::{Document, Field, Store},
+            index::Term,
+            search::{
+                test_util::searcher, PrefixQuery, QueueSizeBasedExecutor, RewriteMethod, SearchPriority, TermQuery,
+            },
+        },
+        pretty_assertions::assert_eq,
+        std::{
+            sync::{
+                atomic::{AtomicUsize, Ordering},
+                mpsc, Arc, Mutex,
+            },
+            thread,
+            time::Duration,
+        },
+    };
+
+    /// Waits until `condition` holds, polling it.
+    fn wait_until(condition: impl Fn() -> bool) {
+        while !condition() {
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    #[test]
+    fn test_bounded_concurrency() {
+        let mut executor = QueueSizeBasedExecutor::new(2).unwrap();
+        executor.set_max_queue_size(100);
+        let running = AtomicUsize::new(0);
+        let peak = AtomicUsize::new(0);
+        let tasks: Vec<_> = (0..12)
+            .map(|i| {
+                let (running, peak) = (&running, &peak);
+                move || {
+                    peak.fetch_max(running.fetch_add(1, Ordering::SeqCst) + 1, Ordering::SeqCst);
+                    thread::sleep(Duration::from_millis(2));
+                    running.fetch_sub(1, Ordering::SeqCst);
+                    i * 10
+                }
+            })
+            .collect();
+
+        let results = executor.execute(SearchPriority::Interactive, tasks);
+        assert_eq!(results, (0..12).map(|i| i * 10).collect::<Vec<_>>());
+        assert!(peak.load(Ordering::SeqCst) <= 2);
+        assert_eq!((executor.running(), executor.queued()), (0, 0));
+
+        assert!(QueueSizeBasedExecutor::new(0).is_err());
+        assert!(executor.set_max_background_concurrency(3).is_err());
+        assert!(executor.set_max_background_concurrency(0).is_err());
+        assert_eq!(QueueSizeBasedExecutor::new(4).unwrap().max_queue_size(), 6);
+    }
+
+    #[test]
+    fn test_full_queue_runs_on_caller() {
+        let mut executor = QueueSizeBasedExecutor::new(2).unwrap();
+        executor.set_max_queue_size(0);
+        let caller = thread::current().id();
+        let tasks: Vec<_> = (0..3).map(|_| || thread::current().id()).collect();
+        assert!(executor.execute(SearchPriority::Background, tasks).into_iter().all(|id| id == caller));
+    }
+
+    #[test]
+    fn test_interactive_before_background() {
+        let mut executor = QueueSizeBasedExecutor::new(1).unwrap();
+        executor.set_max_queue_size(10);
+        let order = Mutex::new(Vec::new());
+        let (release, blocked) = mpsc::channel::<()>();
+        let blocked = Mutex::new(blocked);
+
+        thread::scope(|scope| {
+            // Hold the only thread until both other searches are queued.
+            scope.spawn(|| {
+                executor.execute(SearchPriority::Interactive, vec![|| blocked.lock().unwrap().recv().unwrap()]);
+            });
+            wait_until(|| executor.running() == 1);
+
+            scope.spawn(|| {
+                executor.execute(SearchPriority::Background, vec![|| order.lock().unwrap().push("background")]);
+            });
+            wait_until(|| executor.queued() == 1);
+            scope.spawn(|| {
+                executor.execute(SearchPriority::Interactive, vec![|| order.lock().unwrap().push("interactive")]);
+            });
+            wait_until(|| executor.queued() == 2);
+
+            release.send(()).unwrap();
+        });
+        assert_eq!(order.into_inner().unwrap(), vec!["interactive", "background"]);
+    }
+
+    #[test]
+    fn test_background_concurrency() {
+        let mut executor = QueueSizeBasedExecutor::new(3).unwrap();
+        executor.set_max_background_concurrency(1).unwrap().set_max_queue_size(10);
+        let running = AtomicUsize::new(0);
+        let peak = AtomicUsize::new(0);
+        let tasks: Vec<_> = (0..4)
+            .map(|_| {
+                || {
+                    peak.fetch_max(running.fetch_add(1, Ordering::SeqCst) + 1, Ordering::SeqCst);
+                    thread::sleep(Duration::from_millis(2));
+                    running.fetch_sub(1, Ordering::SeqCst);
+                }
+            })
+            .collect();
+        executor.execute(SearchPriority::Background, tasks);
+        assert_eq!(peak.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_searcher_executor() {
+        let mut segments = Vec::new();
+        for segment in 0..12 {
+            let mut documents = Vec::new();
+            for i in 0..5 {
+                let mut doc = Document::new();
+                let body = if (segment + i) % 3 == 0 {
+                    "rust rust"
+                } else {
+                    "rust search"
+                };
+                doc.add(Field::text("body", body, Store::No));
+                documents.push(doc);
+            }
+            segments.push(documents);
+        }
+        let mut searcher = searcher(segments);
+        let query = TermQuery::new(Term::from_text("body", "rust"));
+        let expected = searcher.search(&query, 20).unwrap();
+
+        let executor = Arc::new(QueueSizeBasedExecutor::new(2).unwrap());
+        searcher.set_concurrent(true);
+        searcher.set_executor(Some(executor.clone()));
+        assert_eq!(searcher.search(&query, 20).unwrap(), expected);
+
+        let mut background = searcher.clone();
+        background.set_priority(SearchPriority::Background);
+        assert_eq!(background.priority(), SearchPriority::Background);
+        assert!(Arc::ptr_eq(background.executor().unwrap(), &executor));
+        assert_eq!(background.search(&query, 20).unwrap(), expected);
+
+        // Rewrites spread over the slices run on the executor too.
+        let mut prefix = PrefixQuery::new(Term::from_text("body", "r")).unwrap();
+        prefix.set_rewrite_method(RewriteMethod::ScoringBoolean);
+        assert_eq!(background.rewrite(&prefix).unwrap().unwrap().to_string(), "body:rust");
+        assert_eq!((executor.running(), executor.queued()), (0, 0));
+    }
+}
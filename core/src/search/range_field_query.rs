@@ -0,0 +1,307 @@
+use {
+    crate::{
+        document::RangeType,
+        index::{BinaryDocValues, LeafReaderContext},
+        search::{
+            ConstantScoreScorer, DocIdSetIterator, Explanation, IndexSearcher, Query, ScoreMode, Scorer,
+            TwoPhaseIterator, Weight,
+        },
+        BoxResult, LuceneError,
+    },
+    std::fmt::{Display, Formatter, Result as FmtResult},
+};
+
+/// The relation between an indexed range and a query range that a [RangeFieldQuery] matches. Each relation must hold
+/// in every dimension, except for [RangeQueryType::Crosses].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum RangeQueryType {
+    /// The indexed range shares at least one point with the query range.
+    Intersects,
+
+    /// The indexed range contains the query range.
+    Contains,
+
+    /// The indexed range lies within the query range.
+    Within,
+
+    /// The indexed range intersects the query range but doesn't lie within it.
+    Crosses,
+}
+
+/// A query matching the documents whose range, indexed with [crate::document::LongRange],
+/// [crate::document::DoubleRange] or [crate::document::InetAddressRange], relates to a query range as given by a
+/// [RangeQueryType], with a constant score. Documents without a range never match.
+///
+/// Every document with a range is checked, so this is best used as a filter alongside a selective query.
+#[derive(Clone, Debug)]
+pub struct RangeFieldQuery {
+    field: String,
+    query_type: RangeQueryType,
+    range_type: RangeType,
+    ranges: Vec<u8>,
+}
+
+impl RangeFieldQuery {
+    /// Creates a query from an encoded range: the minimums of every dimension followed by the maximums, each encoded
+    /// as `range_type` does.
+    pub fn new(field: &str, query_type: RangeQueryType, range_type: RangeType, ranges: Vec<u8>) -> BoxResult<Self> {
+        let bytes_per_range = 2 * range_type.bytes_per_dim();
+        if ranges.is_empty() || !ranges.len().is_multiple_of(bytes_per_range) {
+            return Err(LuceneError::InvalidArgument(format!(
+                "Encoded {range_type:?} range has {} bytes, which isn't a positive multiple of {bytes_per_range}",
+                ranges.len()
+            ))
+            .into());
+        }
+
+        Ok(Self {
+            field: field.to_string(),
+            query_type,
+            range_type,
+            ranges,
+        })
+    }
+
+    /// Returns the field holding the ranges.
+    #[inline]
+    pub fn field(&self) -> &str {
+        &self.field
+    }
+
+    /// Returns the relation that matching ranges have with the query range.
+    #[inline]
+    pub fn query_type(&self) -> RangeQueryType {
+        self.query_type
+    }
+
+    /// Returns the kind of values in the ranges.
+    #[inline]
+    pub fn range_type(&self) -> RangeType {
+        self.range_type
+    }
+
+    /// Returns the number of dimensions of the query range.
+    #[inline]
+    pub fn num_dims(&self) -> usize {
+        self.ranges.len() / (2 * self.range_type.bytes_per_dim())
+    }
+
+    /// Returns the bounds of the query range in a dimension.
+    fn bounds<'a>(&self, ranges: &'a [u8], dim: usize) -> (&'a [u8], &'a [u8]) {
+        let bytes = self.range_type.bytes_per_dim();
+        let min = dim * bytes;
+        let max = (self.num_dims() + dim) * bytes;
+        (&ranges[min..min + bytes], &ranges[max..max + bytes])
+    }
+
+    /// Indicates whether an encoded range matches.
+    fn matches(&self, range: &[u8]) -> BoxResult<bool> {
+        if range.len() != self.ranges.len() {
+            return Err(LuceneError::InvalidArgument(format!(
+                "Field {} holds ranges of {} bytes, but the query range has {} ({} dimensions of {:?})",
+                self.field,
+                range.len(),
+                self.ranges.len(),
+                self.num_dims(),
+                self.range_type
+            ))
+            .into());
+        }
+
+        let mut within = true;
+        let mut contains = true;
+        for dim in 0..self.num_dims() {
+            let (doc_min, doc_max) = self.bounds(range, dim);
+            let (query_min, query_max) = self.bounds(&self.ranges, dim);
+            if doc_min > query_max || doc_max < query_min {
+                return Ok(false);
+            }
+
+            within &= query_min <= doc_min && doc_max <= query_max;
+            contains &= doc_min <= query_min && query_max <= doc_max;
+        }
+
+        Ok(match self.query_type {
+            RangeQueryType::Intersects => true,
+            RangeQueryType::Contains => contains,
+            RangeQueryType::Within => within,
+            RangeQueryType::Crosses => !within,
+        })
+    }
+}
+
+impl Query for RangeFieldQuery {
+    fn create_weight(
+        &self,
+        _searcher: &IndexSearcher,
+        _score_mode: ScoreMode,
+        boost: f32,
+    ) -> BoxResult<Box<dyn Weight>> {
+        Ok(Box::new(RangeFieldWeight {
+            query: self.clone(),
+            score: boost,
+        }))
+    }
+}
+
+impl Display for RangeFieldQuery {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "{}:{:?}(", self.field, self.query_type)?;
+        for dim in 0..self.num_dims() {
+            if dim > 0 {
+                write!(f, ", ")?;
+            }
+
+            let (min, max) = self.bounds(&self.ranges, dim);
+            write!(f, "[{} TO {}]", self.range_type.format_bound(min), self.range_type.format_bound(max))?;
+        }
+        write!(f, ")")
+    }
+}
+
+#[derive(Debug)]
+struct RangeFieldWeight {
+    query: RangeFieldQuery,
+    score: f32,
+}
+
+impl Weight for RangeFieldWeight {
+    fn scorer(&self, context: &LeafReaderContext) -> BoxResult<Option<Box<dyn Scorer>>> {
+        let Some(doc_values) = context.reader().binary_doc_values(&self.query.field)? else {
+            return Ok(None);
+        };
+
+        let two_phase = RangeMatches {
+            doc_values,
+            query: self.query.clone(),
+        };
+        Ok(Some(Box::new(ConstantScoreScorer::with_two_phase(self.score, Box::new(two_phase)))))
+    }
+
+    fn explain(&self, context: &LeafReaderContext, doc: u32) -> BoxResult<Explanation> {
+        let Some(mut doc_values) = context.reader().binary_doc_values(&self.query.field)? else {
+            return Ok(Explanation::no_match(format!("no ranges in field {}", self.query.field), vec![]));
+        };
+
+        if !doc_values.advance_exact(doc)? {
+            return Ok(Explanation::no_match(format!("document {doc} has no range"), vec![]));
+        }
+
+        if self.query.matches(doc_values.binary_value()?)? {
+            Ok(Explanation::constant(self.score, self.query.to_string()))
+        } else {
+            Ok(Explanation::no_match(format!("the range of document {doc} doesn't match {}", self.query), vec![]))
+        }
+    }
+}
+
+/// Confirms the documents whose range matches.
+#[derive(Debug)]
+struct RangeMatches {
+    doc_values: Box<dyn BinaryDocValues>,
+    query: RangeFieldQuery,
+}
+
+impl TwoPhaseIterator for RangeMatches {
+    fn approximation(&self) -> &dyn DocIdSetIterator {
+        self.doc_values.as_ref()
+    }
+
+    fn approximation_mut(&mut self) -> &mut dyn DocIdSetIterator {
+        self.doc_values.as_mut()
+    }
+
+    fn matches(&mut self) -> BoxResult<bool> {
+        self.query.matches(self.doc_values.binary_value()?)
+    }
+
+    fn match_cost(&self) -> f32 {
+        // Comparing the bounds of every dimension.
+        (4 * self.query.num_dims()) as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{
+            document::{Document, DoubleRange, Field, InetAddressRange, LongRange},
+            search::{test_util, IndexSearcher, Query},
+        },
+        pretty_assertions::assert_eq,
+        std::net::IpAddr,
+    };
+
+    fn searcher(fields: Vec<Field>) -> IndexSearcher {
+        let documents = fields.into_iter().map(|field| Document::from_iter([field]));
+        test_util::searcher([documents.chain([Document::new()])])
+    }
+
+    fn docs(searcher: &IndexSearcher, query: &dyn Query) -> Vec<u32> {
+        let mut docs: Vec<u32> = searcher.search(query, 10).unwrap().score_docs.iter().map(|sd| sd.doc).collect();
+        docs.sort();
+        docs
+    }
+
+    fn ip(address: &str) -> IpAddr {
+        address.parse().unwrap()
+    }
+
+    #[test]
+    fn test_long_range_queries() {
+        // Meetings booked from hour `min` to hour `max`.
+        let searcher = searcher(vec![
+            LongRange::new_field("booked", &[9], &[10]).unwrap(),
+            LongRange::new_field("booked", &[11], &[15]).unwrap(),
+            LongRange::new_field("booked", &[8], &[18]).unwrap(),
+            LongRange::new_field("booked", &[16], &[17]).unwrap(),
+        ]);
+
+        let (min, max) = (&[10], &[12]);
+        assert_eq!(docs(&searcher, &LongRange::new_intersects_query("booked", min, max).unwrap()), vec![0, 1, 2]);
+        assert_eq!(docs(&searcher, &LongRange::new_contains_query("booked", min, max).unwrap()), vec![2]);
+        assert_eq!(docs(&searcher, &LongRange::new_within_query("booked", &[9], &[15]).unwrap()), vec![0, 1]);
+        assert_eq!(docs(&searcher, &LongRange::new_crosses_query("booked", min, max).unwrap()), vec![0, 1, 2]);
+        assert_eq!(docs(&searcher, &LongRange::new_crosses_query("booked", &[9], &[15]).unwrap()), vec![2]);
+
+        let query = LongRange::new_contains_query("booked", min, max).unwrap();
+        assert!(searcher.explain(&query, 2).unwrap().is_match());
+        assert!(!searcher.explain(&query, 0).unwrap().is_match());
+        assert!(!searcher.explain(&query, 4).unwrap().is_match());
+        assert_eq!(query.to_string(), "booked:Contains([10 TO 12])");
+
+        // A query with the wrong number of dimensions for the field.
+        let query = LongRange::new_intersects_query("booked", &[0, 0], &[1, 1]).unwrap();
+        assert!(searcher.search(&query, 10).is_err());
+    }
+
+    #[test]
+    fn test_double_and_inet_address_range_queries() {
+        let boxes = searcher(vec![
+            DoubleRange::new_field("box", &[0.0, 0.0], &[1.0, 1.0]).unwrap(),
+            DoubleRange::new_field("box", &[-1.5, 0.5], &[0.5, 2.0]).unwrap(),
+            DoubleRange::new_field("box", &[f64::NEG_INFINITY, -2.0], &[f64::INFINITY, -1.0]).unwrap(),
+        ]);
+
+        let query = DoubleRange::new_intersects_query("box", &[0.25, 0.25], &[0.75, 0.75]).unwrap();
+        assert_eq!(docs(&boxes, &query), vec![0, 1]);
+        assert_eq!(query.to_string(), "box:Intersects([0.25 TO 0.75], [0.25 TO 0.75])");
+        let query = DoubleRange::new_within_query("box", &[-2.0, -1.0], &[2.0, 2.0]).unwrap();
+        assert_eq!(docs(&boxes, &query), vec![0, 1]);
+        let query = DoubleRange::new_contains_query("box", &[-1e9, -1.5], &[1e9, -1.5]).unwrap();
+        assert_eq!(docs(&boxes, &query), vec![2]);
+
+        let blocks = searcher(vec![
+            InetAddressRange::new_field("block", ip("10.0.0.0"), ip("10.255.255.255")).unwrap(),
+            InetAddressRange::new_field("block", ip("192.168.0.0"), ip("192.168.255.255")).unwrap(),
+            InetAddressRange::new_field("block", ip("2001:db8::"), ip("2001:db8::ffff")).unwrap(),
+        ]);
+
+        let query = InetAddressRange::new_contains_query("block", ip("10.1.2.3"), ip("10.1.2.3")).unwrap();
+        assert_eq!(docs(&blocks, &query), vec![0]);
+        assert_eq!(query.to_string(), "block:Contains([10.1.2.3 TO 10.1.2.3])");
+        let query = InetAddressRange::new_intersects_query("block", ip("192.168.255.0"), ip("2001:db8::1")).unwrap();
+        assert_eq!(docs(&blocks, &query), vec![1, 2]);
+        assert!(InetAddressRange::new_field("block", ip("10.0.0.1"), ip("10.0.0.0")).is_err());
+    }
+}
@@ -0,0 +1,155 @@
+use {
+    crate::{
+        index::Term,
+        search::{AutomatonQuery, IndexSearcher, Query, RewriteMethod, ScoreMode, Weight},
+        util::automaton::{CompiledAutomaton, RegExp, DEFAULT_DETERMINIZE_WORK_LIMIT},
+        BoxResult, LuceneError,
+    },
+    std::{
+        fmt::{Display, Formatter, Result as FmtResult},
+        sync::Arc,
+    },
+};
+
+/// A query matching the documents containing a term that matches a regular expression, in the syntax of [RegExp],
+/// such as `luc[a-z]+`. The expression must match the whole term.
+///
+/// With the [RegExp::CASE_INSENSITIVE] flag, the literal characters of the expression match terms in any case, by
+/// their Unicode simple case folding, so terms don't have to be lowercased when indexed.
+#[derive(Clone, Debug)]
+pub struct RegexpQuery {
+    regexp: Term,
+    flags: u32,
+    query: AutomatonQuery,
+}
+
+impl RegexpQuery {
+    /// Creates a query for the terms matching the expression `regexp`, in its field, with all optional syntax
+    /// enabled ([RegExp::ALL]).
+    pub fn new(regexp: Term) -> BoxResult<Self> {
+        Self::with_flags(regexp, RegExp::ALL)
+    }
+
+    /// Creates a query for the terms matching the expression `regexp`, with only the optional syntax given by `flags`
+    /// enabled. This fails with [LuceneError::InvalidRegExp] if the expression can't be parsed, and with
+    /// [LuceneError::TooComplexToDeterminize] if its automaton is too complex.
+    pub fn with_flags(regexp: Term, flags: u32) -> BoxResult<Self> {
+        let Some(text) = regexp.text() else {
+            return Err(LuceneError::InvalidRegExp(format!("{regexp} isn't UTF-8")).into());
+        };
+
+        let automaton = RegExp::with_flags(text, flags)?.to_automaton()?;
+        let automaton = CompiledAutomaton::from_automaton(&automaton, DEFAULT_DETERMINIZE_WORK_LIMIT)?;
+        let description = format!("{}:/{text}/", regexp.field());
+        Ok(Self {
+            query: AutomatonQuery::with_description(regexp.clone(), automaton, description),
+            regexp,
+            flags,
+        })
+    }
+
+    /// Returns the regular expression.
+    #[inline]
+    pub fn regexp(&self) -> &Term {
+        &self.regexp
+    }
+
+    /// Returns the optional syntax and matching flags enabled in the expression.
+    #[inline]
+    pub fn flags(&self) -> u32 {
+        self.flags
+    }
+
+    /// Indicates whether the expression matches terms in any case.
+    #[inline]
+    pub fn is_case_insensitive(&self) -> bool {
+        self.flags & RegExp::CASE_INSENSITIVE != 0
+    }
+
+    /// Returns the query over the expression's automaton.
+    #[inline]
+    pub fn automaton_query(&self) -> &AutomatonQuery {
+        &self.query
+    }
+
+    /// Sets how the query is executed; see [AutomatonQuery::set_rewrite_method].
+    pub fn set_rewrite_method(&mut self, rewrite_method: RewriteMethod) -> &mut Self {
+        self.query.set_rewrite_method(rewrite_method);
+        self
+    }
+
+    /// Sets the most terms the query expands to; see [AutomatonQuery::set_max_expansions].
+    pub fn set_max_expansions(&mut self, max_expansions: usize) -> &mut Self {
+        self.query.set_max_expansions(max_expansions);
+        self
+    }
+}
+
+impl Query for RegexpQuery {
+    fn create_weight(&self, searcher: &IndexSearcher, score_mode: ScoreMode, boost: f32) -> BoxResult<Box<dyn Weight>> {
+        self.query.create_weight(searcher, score_mode, boost)
+    }
+
+    fn rewrite(&self, searcher: &IndexSearcher) -> BoxResult<Option<Arc<dyn Query>>> {
+        self.query.rewrite(searcher)
+    }
+}
+
+impl Display for RegexpQuery {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        Display::fmt(&self.query, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{
+            document::{Document, Field, Store},
+            index::Term,
+            search::{test_util::searcher, RegexpQuery, RewriteMethod},
+            util::automaton::RegExp,
+        },
+        pretty_assertions::assert_eq,
+    };
+
+    #[test]
+    fn test_regexp_query() {
+        let mut documents = Vec::new();
+        for (id, body) in ["AB-1", "ab-2", "Ac-3", "xAB-4", "b-5"].into_iter().zip([
+            "lucene",
+            "lucid dreams",
+            "luck",
+            "lunar",
+            "glucose",
+        ]) {
+            let mut doc = Document::new();
+            doc.add(Field::string("id", id, Store::No));
+            doc.add(Field::text("body", body, Store::No));
+            documents.push(doc);
+        }
+        let searcher = searcher([documents]);
+
+        let mut query = RegexpQuery::new(Term::from_text("body", "luc[a-z]+")).unwrap();
+        assert_eq!(query.to_string(), "body:/luc[a-z]+/");
+        assert_eq!(query.flags(), RegExp::ALL);
+        assert_eq!(searcher.count(&query).unwrap(), 3);
+        query.set_rewrite_method(RewriteMethod::ScoringBoolean);
+        assert_eq!(searcher.rewrite(&query).unwrap().unwrap().to_string(), "body:lucene body:lucid body:luck");
+
+        // The expression must match whole terms.
+        assert_eq!(searcher.count(&RegexpQuery::new(Term::from_text("body", "uc")).unwrap()).unwrap(), 0);
+        assert_eq!(searcher.count(&RegexpQuery::new(Term::from_text("body", ".*uc.*")).unwrap()).unwrap(), 4);
+        assert_eq!(searcher.count(&RegexpQuery::new(Term::from_text("body", "lu(ck|nar)")).unwrap()).unwrap(), 2);
+
+        // Indexed terms keep their case here, which a case-insensitive expression matches.
+        let query = RegexpQuery::with_flags(Term::from_text("id", "ab-[0-9]"), RegExp::ALL | RegExp::CASE_INSENSITIVE);
+        let query = query.unwrap();
+        assert!(query.is_case_insensitive());
+        assert_eq!(searcher.count(&query).unwrap(), 2);
+        assert_eq!(searcher.count(&RegexpQuery::new(Term::from_text("id", "ab-[0-9]")).unwrap()).unwrap(), 1);
+
+        assert!(RegexpQuery::new(Term::from_text("body", "luc[")).is_err());
+        assert!(RegexpQuery::with_flags(Term::from_text("body", "a&b"), RegExp::NONE).is_ok());
+    }
+}
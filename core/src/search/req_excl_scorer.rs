@@ -0,0 +1,80 @@
+use crate::{
+    search::{DocIdSetIterator, Scorable, Scorer, NO_MORE_DOCS},
+    BoxResult,
+};
+
+/// A [Scorer] over the documents of a required scorer that are not matched by an excluded iterator. Scores come from
+/// the required scorer alone.
+#[derive(Debug)]
+pub struct ReqExclScorer {
+    req: Box<dyn Scorer>,
+    excl: Box<dyn DocIdSetIterator>,
+
+    /// The document the excluded iterator is positioned on; `None` until it has been positioned.
+    excl_doc: Option<u32>,
+}
+
+impl ReqExclScorer {
+    /// Creates a scorer over the documents of `req` that are not in `excl`.
+    pub fn new(req: Box<dyn Scorer>, excl: Box<dyn DocIdSetIterator>) -> Self {
+        Self {
+            req,
+            excl,
+            excl_doc: None,
+        }
+    }
+
+    /// Moves forward from the required scorer's candidate `doc` until it isn't excluded.
+    fn do_next(&mut self, mut doc: u32) -> BoxResult<u32> {
+        while doc != NO_MORE_DOCS {
+            let excl_doc = match self.excl_doc {
+                Some(excl_doc) if excl_doc >= doc => excl_doc,
+                _ => self.excl.advance(doc)?,
+            };
+            self.excl_doc = Some(excl_doc);
+
+            if excl_doc != doc {
+                break;
+            }
+
+            doc = self.req.next_doc()?;
+        }
+
+        Ok(doc)
+    }
+}
+
+impl DocIdSetIterator for ReqExclScorer {
+    #[inline]
+    fn doc_id(&self) -> u32 {
+        self.req.doc_id()
+    }
+
+    fn next_doc(&mut self) -> BoxResult<u32> {
+        let doc = self.req.next_doc()?;
+        self.do_next(doc)
+    }
+
+    fn advance(&mut self, target: u32) -> BoxResult<u32> {
+        let doc = self.req.advance(target)?;
+        self.do_next(doc)
+    }
+
+    #[inline]
+    fn cost(&self) -> u64 {
+        self.req.cost()
+    }
+}
+
+impl Scorable for ReqExclScorer {
+    #[inline]
+    fn score(&mut self) -> BoxResult<f32> {
+        self.req.score()
+    }
+}
+
+impl Scorer for ReqExclScorer {
+    fn max_score(&mut self, up_to: u32) -> BoxResult<f32> {
+        self.req.max_score(up_to)
+    }
+}
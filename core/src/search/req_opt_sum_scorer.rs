@@ -0,0 +1,73 @@
+use crate::{
+    search::{DocIdSetIterator, Scorable, Scorer},
+    BoxResult,
+};
+
+/// A [Scorer] over the documents of a required scorer, adding the score of an optional scorer to the documents that
+/// it also matches.
+#[derive(Debug)]
+pub struct ReqOptSumScorer {
+    req: Box<dyn Scorer>,
+    opt: Box<dyn Scorer>,
+
+    /// The document the optional scorer is positioned on; `None` until it has been positioned.
+    opt_doc: Option<u32>,
+}
+
+impl ReqOptSumScorer {
+    /// Creates a scorer over the documents of `req`, boosted by `opt` where it also matches.
+    pub fn new(req: Box<dyn Scorer>, opt: Box<dyn Scorer>) -> Self {
+        Self {
+            req,
+            opt,
+            opt_doc: None,
+        }
+    }
+}
+
+impl DocIdSetIterator for ReqOptSumScorer {
+    #[inline]
+    fn doc_id(&self) -> u32 {
+        self.req.doc_id()
+    }
+
+    #[inline]
+    fn next_doc(&mut self) -> BoxResult<u32> {
+        self.req.next_doc()
+    }
+
+    #[inline]
+    fn advance(&mut self, target: u32) -> BoxResult<u32> {
+        self.req.advance(target)
+    }
+
+    #[inline]
+    fn cost(&self) -> u64 {
+        self.req.cost()
+    }
+}
+
+impl Scorable for ReqOptSumScorer {
+    fn score(&mut self) -> BoxResult<f32> {
+        let doc = self.req.doc_id();
+        let mut score = self.req.score()?;
+
+        let opt_doc = match self.opt_doc {
+            Some(opt_doc) if opt_doc >= doc => opt_doc,
+            _ => self.opt.advance(doc)?,
+        };
+        self.opt_doc = Some(opt_doc);
+
+        if opt_doc == doc {
+            score += self.opt.score()?;
+        }
+
+        Ok(score)
+    }
+}
+
+impl Scorer for ReqOptSumScorer {
+    fn max_score(&mut self, up_to: u32) -> BoxResult<f32> {
+        Ok(self.req.max_score(up_to)? + self.opt.max_score(up_to)?)
+    }
+}
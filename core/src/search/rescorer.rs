@@ -0,0 +1,130 @@
+use {
+    crate::{
+        search::{Explanation, IndexSearcher, ScoreDoc, TopDocs},
+        BoxResult,
+    },
+    std::{cmp::Ordering, fmt::Debug},
+};
+
+/// The number of first-pass hits rescored by default.
+pub const DEFAULT_RESCORE_WINDOW_SIZE: usize = 100;
+
+/// Re-ranks the top hits of a first-pass search with a more expensive second pass, such as another query or a
+/// learned ranking model.
+pub trait Rescorer: Debug + Send + Sync {
+    /// Rescores the hits of `first_pass` and returns the best `top_n` of them, best first.
+    fn rescore(&self, searcher: &IndexSearcher, first_pass: &TopDocs, top_n: usize) -> BoxResult<TopDocs>;
+
+    /// Explains the rescored score of the global document `doc`, whose first-pass score is explained by
+    /// `first_pass`.
+    fn explain(&self, searcher: &IndexSearcher, first_pass: Explanation, doc: u32) -> BoxResult<Explanation>;
+}
+
+/// How a [Rescorer] combines a hit's first-pass score with its second-pass score. Hits without a second-pass score
+/// keep their first-pass score.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ScoreCombination {
+    /// The second-pass score replaces the first-pass score.
+    Replace,
+
+    /// `first_pass_weight * first + second_pass_weight * second`.
+    Linear {
+        /// The weight of the first-pass score.
+        first_pass_weight: f32,
+
+        /// The weight of the second-pass score.
+        second_pass_weight: f32,
+    },
+
+    /// `first * second`.
+    Multiply,
+}
+
+impl ScoreCombination {
+    /// Combines a first-pass score with an optional second-pass score.
+    pub fn combine(self, first: f32, second: Option<f32>) -> f32 {
+        let Some(second) = second else {
+            return first;
+        };
+
+        match self {
+            Self::Replace => second,
+            Self::Linear {
+                first_pass_weight,
+                second_pass_weight,
+            } => first_pass_weight * first + second_pass_weight * second,
+            Self::Multiply => first * second,
+        }
+    }
+
+    /// Explains [ScoreCombination::combine] given explanations of the two scores.
+    pub fn explain(self, first: Explanation, second: Option<Explanation>) -> Explanation {
+        let Some(second) = second.filter(|second| second.is_match()) else {
+            let value = first.value();
+            return Explanation::matched(value, "first pass score, with no second pass match", vec![first]);
+        };
+
+        let value = self.combine(first.value(), Some(second.value()));
+        match self {
+            Self::Replace => Explanation::matched(value, "second pass score, replacing:", vec![second, first]),
+            Self::Linear {
+                first_pass_weight,
+                second_pass_weight,
+            } => Explanation::matched(
+                value,
+                "weighted sum of:",
+                vec![
+                    Explanation::matched(
+                        first_pass_weight * first.value(),
+                        format!("first pass score, weighted by {first_pass_weight}"),
+                        vec![first],
+                    ),
+                    Explanation::matched(
+                        second_pass_weight * second.value(),
+                        format!("second pass score, weighted by {second_pass_weight}"),
+                        vec![second],
+                    ),
+                ],
+            ),
+            Self::Multiply => Explanation::matched(value, "product of:", vec![first, second]),
+        }
+    }
+}
+
+impl Default for ScoreCombination {
+    fn default() -> Self {
+        Self::Linear {
+            first_pass_weight: 1.0,
+            second_pass_weight: 1.0,
+        }
+    }
+}
+
+/// Rescores the first `window_size` hits of `first_pass` with `second_pass`, which is given those hits sorted by
+/// document id and returns their second-pass scores in the same order. The rescored hits are re-sorted; hits beyond
+/// the window follow them in their original order. The result is truncated to `top_n` hits.
+pub(crate) fn rescore_window<F>(
+    first_pass: &TopDocs,
+    window_size: usize,
+    top_n: usize,
+    combination: ScoreCombination,
+    second_pass: F,
+) -> BoxResult<TopDocs>
+where
+    F: FnOnce(&[ScoreDoc]) -> BoxResult<Vec<Option<f32>>>,
+{
+    let window_size = window_size.min(first_pass.score_docs.len());
+    let mut window = first_pass.score_docs[..window_size].to_vec();
+    window.sort_by_key(|hit| hit.doc);
+
+    let second_scores = second_pass(&window)?;
+    debug_assert_eq!(second_scores.len(), window.len());
+    for (hit, second) in window.iter_mut().zip(second_scores) {
+        hit.score = combination.combine(hit.score, second);
+    }
+
+    window.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(Ordering::Equal).then(a.doc.cmp(&b.doc)));
+    window.extend_from_slice(&first_pass.score_docs[window_size..]);
+    window.truncate(top_n);
+    Ok(TopDocs::new(first_pass.total_hits, window))
+}
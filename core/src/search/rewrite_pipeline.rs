@@ -0,0 +1,505 @@
+use {
+    crate::{
+        search::{
+            BooleanClause, BooleanQuery, BoostQuery, ConstantScoreQuery, DisjunctionMaxQuery, IndexSearcher, Occur,
+            Query, QueryVisitor,
+        },
+        BoxResult,
+    },
+    std::{
+        any::{Any, TypeId},
+        collections::HashMap,
+        sync::Arc,
+    },
+};
+
+/// The passes run by [IndexSearcher::optimize] on a query tree after the queries have rewritten themselves.
+///
+/// Each [QueryVisitor] in the pipeline is applied, in order, to every query of the tree, sub-queries first. The
+/// sub-queries of [BooleanQuery], [ConstantScoreQuery], [BoostQuery] and [DisjunctionMaxQuery] are visited; other
+/// queries are treated as leaves.
+#[derive(Clone, Debug, Default)]
+pub struct RewritePipeline {
+    passes: Vec<Arc<dyn QueryVisitor>>,
+}
+
+impl RewritePipeline {
+    /// Creates a pipeline with no passes.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates the pipeline used by default, which runs [FlattenBooleans], [DeduplicateClauses], [PushDownFilters]
+    /// and [SingleClauseBooleans].
+    pub fn standard() -> Self {
+        Self {
+            passes: vec![
+                Arc::new(FlattenBooleans),
+                Arc::new(DeduplicateClauses),
+                Arc::new(PushDownFilters),
+                Arc::new(SingleClauseBooleans),
+            ],
+        }
+    }
+
+    /// Appends a pass.
+    pub fn add(&mut self, pass: Arc<dyn QueryVisitor>) -> &mut Self {
+        self.passes.push(pass);
+        self
+    }
+
+    /// Returns the passes, in the order they run.
+    #[inline]
+    pub fn passes(&self) -> &[Arc<dyn QueryVisitor>] {
+        &self.passes
+    }
+
+    /// Runs every pass over the query tree once, returning `None` if nothing changed.
+    pub fn rewrite(&self, query: &dyn Query, searcher: &IndexSearcher) -> BoxResult<Option<Arc<dyn Query>>> {
+        let mut rewritten = self.rewrite_children(query, searcher)?;
+        for pass in self.passes.iter() {
+            if let Some(next) = pass.visit(rewritten.as_deref().unwrap_or(query), searcher)? {
+                rewritten = Some(next);
+            }
+        }
+
+        Ok(rewritten)
+    }
+
+    fn rewrite_children(&self, query: &dyn Query, searcher: &IndexSearcher) -> BoxResult<Option<Arc<dyn Query>>> {
+        let query = query as &dyn Any;
+        if let Some(boolean) = query.downcast_ref::<BooleanQuery>() {
+            let mut changed = false;
+            let mut builder = boolean.to_empty_builder();
+            for clause in boolean.clauses() {
+                match self.rewrite(clause.query().as_ref(), searcher)? {
+                    Some(rewritten) => {
+                        changed = true;
+                        builder.add(rewritten, clause.occur());
+                    }
+                    None => {
+                        builder.add_clause(clause.clone());
+                    }
+                }
+            }
+
+            return Ok(changed.then(|| Arc::new(builder.build()) as Arc<dyn Query>));
+        }
+
+        if let Some(constant_score) = query.downcast_ref::<ConstantScoreQuery>() {
+            return Ok(self
+                .rewrite(constant_score.query().as_ref(), searcher)?
+                .map(|rewritten| Arc::new(ConstantScoreQuery::new(rewritten)) as Arc<dyn Query>));
+        }
+
+        if let Some(boost) = query.downcast_ref::<BoostQuery>() {
+            return match self.rewrite(boost.query().as_ref(), searcher)? {
+                Some(rewritten) => Ok(Some(Arc::new(BoostQuery::new(rewritten, boost.boost())?))),
+                None => Ok(None),
+            };
+        }
+
+        if let Some(dis_max) = query.downcast_ref::<DisjunctionMaxQuery>() {
+            let mut changed = false;
+            let mut disjuncts = Vec::with_capacity(dis_max.disjuncts().len());
+            for disjunct in dis_max.disjuncts() {
+                match self.rewrite(disjunct.as_ref(), searcher)? {
+                    Some(rewritten) => {
+                        changed = true;
+                        disjuncts.push(rewritten);
+                    }
+                    None => disjuncts.push(disjunct.clone()),
+                }
+            }
+
+            if !changed {
+                return Ok(None);
+            }
+            return Ok(Some(Arc::new(DisjunctionMaxQuery::new(disjuncts, dis_max.tie_breaker_multiplier())?)));
+        }
+
+        Ok(None)
+    }
+}
+
+/// Returns `query` as a [BooleanQuery], if it is one.
+fn as_boolean(query: &dyn Query) -> Option<&BooleanQuery> {
+    (query as &dyn Any).downcast_ref::<BooleanQuery>()
+}
+
+/// Merges nested [BooleanQuery]s into the query holding them, where that doesn't change matches or scores:
+/// * A required clause holding a conjunction (only [Occur::Must], [Occur::Filter] and [Occur::MustNot] clauses)
+///   contributes its clauses directly, as filters if the clause was a filter.
+/// * An [Occur::Should] clause holding a pure disjunction contributes its clauses as optional clauses.
+/// * An [Occur::MustNot] clause holding a pure disjunction contributes each of its clauses as a prohibited clause.
+///
+/// Booleans with a minimum number of optional clauses (see [BooleanQuery::min_should_match]) aren't merged into
+/// another, and only have their required clauses merged into them.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FlattenBooleans;
+
+impl FlattenBooleans {
+    /// Returns the clauses that replace `clause`, or `None` if it can't be flattened.
+    fn flatten(boolean: &BooleanQuery, clause: &BooleanClause) -> Option<Vec<BooleanClause>> {
+        let nested = as_boolean(clause.query().as_ref()).filter(|nested| nested.min_should_match() == 0)?;
+        let is_conjunction = nested.clauses().iter().any(BooleanClause::is_required)
+            && nested.clauses().iter().all(|nested| nested.occur() != Occur::Should);
+        let is_disjunction =
+            !nested.clauses().is_empty() && nested.clauses().iter().all(|nested| nested.occur() == Occur::Should);
+
+        let clauses: Vec<BooleanClause> = match clause.occur() {
+            Occur::Must if is_conjunction => nested.clauses().to_vec(),
+            Occur::Filter if is_conjunction => nested
+                .clauses()
+                .iter()
+                .map(|nested| match nested.occur() {
+                    Occur::Must => BooleanClause::new(nested.query().clone(), Occur::Filter),
+                    _ => nested.clone(),
+                })
+                .collect(),
+            Occur::Should if is_disjunction && boolean.min_should_match() == 0 => nested.clauses().to_vec(),
+            Occur::MustNot if is_disjunction => nested
+                .clauses()
+                .iter()
+                .map(|nested| BooleanClause::new(nested.query().clone(), Occur::MustNot))
+                .collect(),
+            _ => return None,
+        };
+
+        Some(clauses)
+    }
+}
+
+impl QueryVisitor for FlattenBooleans {
+    fn visit(&self, query: &dyn Query, _searcher: &IndexSearcher) -> BoxResult<Option<Arc<dyn Query>>> {
+        let Some(boolean) = as_boolean(query) else {
+            return Ok(None);
+        };
+
+        let mut changed = false;
+        let mut builder = boolean.to_empty_builder();
+        for clause in boolean.clauses() {
+            match Self::flatten(boolean, clause) {
+                Some(clauses) => {
+                    changed = true;
+                    for clause in clauses {
+                        builder.add_clause(clause);
+                    }
+                }
+                None => {
+                    builder.add_clause(clause.clone());
+                }
+            }
+        }
+
+        Ok(changed.then(|| Arc::new(builder.build()) as Arc<dyn Query>))
+    }
+}
+
+/// Removes repeated clauses from a [BooleanQuery]. Queries are considered the same if they have the same type and
+/// render the same way.
+///
+/// Repeated [Occur::Filter] and [Occur::MustNot] clauses are dropped, as is a filter repeating an [Occur::Must]
+/// clause. Repeated [Occur::Must] or [Occur::Should] clauses are merged into one clause boosted by the number of
+/// repetitions, since each adds its score, unless a minimum number of optional clauses must match, which counts each
+/// repetition.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DeduplicateClauses;
+
+impl QueryVisitor for DeduplicateClauses {
+    fn visit(&self, query: &dyn Query, _searcher: &IndexSearcher) -> BoxResult<Option<Arc<dyn Query>>> {
+        let Some(boolean) = as_boolean(query) else {
+            return Ok(None);
+        };
+
+        let key =
+            |clause: &BooleanClause| ((clause.query().as_ref() as &dyn Any).type_id(), clause.query().to_string());
+        // Each optional clause counts towards a minimum.
+        let is_counted = |clause: &BooleanClause| clause.occur() == Occur::Should && boolean.min_should_match() > 0;
+        let mut counts: HashMap<(Occur, (TypeId, String)), usize> = HashMap::new();
+        for clause in boolean.clauses().iter().filter(|clause| !is_counted(clause)) {
+            *counts.entry((clause.occur(), key(clause))).or_default() += 1;
+        }
+
+        let is_filtering_must = |clause: &BooleanClause| {
+            clause.occur() == Occur::Filter && counts.contains_key(&(Occur::Must, key(clause)))
+        };
+        let filtering_must: Vec<bool> = boolean.clauses().iter().map(is_filtering_must).collect();
+        if counts.values().all(|&count| count == 1) && !filtering_must.contains(&true) {
+            return Ok(None);
+        }
+
+        let mut builder = boolean.to_empty_builder();
+        for (clause, filtering_must) in boolean.clauses().iter().zip(filtering_must) {
+            if filtering_must {
+                continue;
+            }
+
+            if is_counted(clause) {
+                builder.add_clause(clause.clone());
+                continue;
+            }
+
+            // The first of the repeated clauses takes the place of all of them.
+            let Some(count) = counts.remove(&(clause.occur(), key(clause))) else {
+                continue;
+            };
+            match clause.occur() {
+                Occur::Must | Occur::Should if count > 1 => {
+                    builder.add(Arc::new(BoostQuery::new(clause.query().clone(), count as f32)?), clause.occur());
+                }
+                _ => {
+                    builder.add_clause(clause.clone());
+                }
+            }
+        }
+
+        Ok(Some(Arc::new(builder.build())))
+    }
+}
+
+/// Strips scoring from the queries of clauses that only filter: the [Occur::Filter] and [Occur::MustNot] clauses of
+/// a [BooleanQuery], and the query wrapped by a [ConstantScoreQuery].
+///
+/// In those places, [BoostQuery] and [ConstantScoreQuery] wrappers are removed, and the [Occur::Must] clauses of a
+/// nested [BooleanQuery] become [Occur::Filter] clauses, dropping its [Occur::Should] clauses if it has required
+/// clauses and no minimum number of them must match, since they would only have added to the score.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PushDownFilters;
+
+impl PushDownFilters {
+    /// Returns `query` without the parts that only compute scores, or `None` if it has none.
+    fn without_scores(query: &Arc<dyn Query>) -> BoxResult<Option<Arc<dyn Query>>> {
+        let any = query.as_ref() as &dyn Any;
+        if let Some(boost) = any.downcast_ref::<BoostQuery>() {
+            return Ok(Some(Self::without_scores(boost.query())?.unwrap_or_else(|| boost.query().clone())));
+        }
+
+        if let Some(constant_score) = any.downcast_ref::<ConstantScoreQuery>() {
+            return Ok(Some(
+                Self::without_scores(constant_score.query())?.unwrap_or_else(|| constant_score.query().clone()),
+            ));
+        }
+
+        let Some(boolean) = as_boolean(query.as_ref()) else {
+            return Ok(None);
+        };
+
+        let has_required = boolean.clauses().iter().any(BooleanClause::is_required) && boolean.min_should_match() == 0;
+        let is_scoring =
+            |clause: &BooleanClause| clause.occur() == Occur::Must || (has_required && clause.occur() == Occur::Should);
+        if !boolean.clauses().iter().any(is_scoring) {
+            return Ok(None);
+        }
+
+        let mut builder = boolean.to_empty_builder();
+        for clause in boolean.clauses() {
+            match clause.occur() {
+                Occur::Must => {
+                    builder.add(clause.query().clone(), Occur::Filter);
+                }
+                Occur::Should if has_required => (),
+                _ => {
+                    builder.add_clause(clause.clone());
+                }
+            }
+        }
+
+        Ok(Some(Arc::new(builder.build())))
+    }
+}
+
+impl QueryVisitor for PushDownFilters {
+    fn visit(&self, query: &dyn Query, _searcher: &IndexSearcher) -> BoxResult<Option<Arc<dyn Query>>> {
+        if let Some(constant_score) = (query as &dyn Any).downcast_ref::<ConstantScoreQuery>() {
+            return Ok(Self::without_scores(constant_score.query())?
+                .map(|inner| Arc::new(ConstantScoreQuery::new(inner)) as Arc<dyn Query>));
+        }
+
+        let Some(boolean) = as_boolean(query) else {
+            return Ok(None);
+        };
+
+        let mut changed = false;
+        let mut builder = boolean.to_empty_builder();
+        for clause in boolean.clauses() {
+            let stripped = match clause.occur() {
+                Occur::Filter | Occur::MustNot => Self::without_scores(clause.query())?,
+                Occur::Must | Occur::Should => None,
+            };
+            match stripped {
+                Some(query) => {
+                    changed = true;
+                    builder.add(query, clause.occur());
+                }
+                None => {
+                    builder.add_clause(clause.clone());
+                }
+            }
+        }
+
+        Ok(changed.then(|| Arc::new(builder.build()) as Arc<dyn Query>))
+    }
+}
+
+/// Replaces a [BooleanQuery] with a single clause by an equivalent query without the boolean, wherever it is in the
+/// tree. A lone [Occur::Filter] clause becomes a [ConstantScoreQuery] with a score of zero.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SingleClauseBooleans;
+
+impl QueryVisitor for SingleClauseBooleans {
+    fn visit(&self, query: &dyn Query, _searcher: &IndexSearcher) -> BoxResult<Option<Arc<dyn Query>>> {
+        match as_boolean(query) {
+            Some(boolean) => boolean.rewrite_single_clause(),
+            None => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{
+            index::Term,
+            search::{
+                test_util::body_searcher, BooleanQuery, BoostQuery, ConstantScoreQuery, DeduplicateClauses,
+                FlattenBooleans, IndexSearcher, Occur, PushDownFilters, Query, QueryVisitor, RewritePipeline,
+                SingleClauseBooleans, TermQuery,
+            },
+            BoxResult,
+        },
+        pretty_assertions::assert_eq,
+        std::{any::Any, sync::Arc},
+    };
+
+    fn searcher() -> IndexSearcher {
+        body_searcher(&["a b", "a b c", "a c d", "b d e", "a e", "a b d e", "color a"])
+    }
+
+    fn term(text: &str) -> Arc<dyn Query> {
+        Arc::new(TermQuery::new(Term::from_text("body", text)))
+    }
+
+    fn boolean(clauses: &[(Arc<dyn Query>, Occur)]) -> Arc<dyn Query> {
+        let mut builder = BooleanQuery::builder();
+        for (query, occur) in clauses {
+            builder.add(query.clone(), *occur);
+        }
+        Arc::new(builder.build())
+    }
+
+    fn run(pass: Arc<dyn QueryVisitor>, query: &Arc<dyn Query>) -> String {
+        let mut pipeline = RewritePipeline::new();
+        pipeline.add(pass);
+        pipeline.rewrite(query.as_ref(), &searcher()).unwrap().unwrap().to_string()
+    }
+
+    fn run_none(pass: Arc<dyn QueryVisitor>, query: &Arc<dyn Query>) -> bool {
+        let mut pipeline = RewritePipeline::new();
+        pipeline.add(pass);
+        pipeline.rewrite(query.as_ref(), &searcher()).unwrap().is_none()
+    }
+
+    #[test]
+    fn test_passes() {
+        let query = boolean(&[
+            (term("a"), Occur::Must),
+            (boolean(&[(term("b"), Occur::Must), (term("c"), Occur::MustNot)]), Occur::Filter),
+            (boolean(&[(term("d"), Occur::Should), (term("e"), Occur::Should)]), Occur::MustNot),
+            (boolean(&[(term("b"), Occur::Should), (term("c"), Occur::Should)]), Occur::Must),
+        ]);
+        assert_eq!(run(Arc::new(FlattenBooleans), &query), "+body:a #body:b -body:c -body:d -body:e +(body:b body:c)");
+
+        let query = boolean(&[
+            (term("a"), Occur::Must),
+            (term("a"), Occur::Must),
+            (term("a"), Occur::Filter),
+            (term("b"), Occur::Should),
+            (term("b"), Occur::Should),
+            (term("c"), Occur::MustNot),
+            (term("c"), Occur::MustNot),
+            (term("d"), Occur::Filter),
+            (term("d"), Occur::Filter),
+        ]);
+        assert_eq!(run(Arc::new(DeduplicateClauses), &query), "+(body:a)^2 (body:b)^2 -body:c #body:d");
+        let mut builder = BooleanQuery::builder();
+        builder.add(term("a"), Occur::Should).add(term("a"), Occur::Should).add(term("a"), Occur::Filter);
+        let query: Arc<dyn Query> = Arc::new(builder.set_min_should_match(2).build());
+        assert!(run_none(Arc::new(DeduplicateClauses), &query));
+        assert!(run_none(
+            Arc::new(FlattenBooleans),
+            &boolean(&[(query.clone(), Occur::Should), (term("b"), Occur::Should)])
+        ));
+
+        let query = boolean(&[
+            (term("a"), Occur::Must),
+            (Arc::new(BoostQuery::new(term("b"), 3.0).unwrap()), Occur::Filter),
+            (
+                boolean(&[(term("c"), Occur::Must), (term("d"), Occur::Should), (term("e"), Occur::Filter)]),
+                Occur::MustNot,
+            ),
+        ]);
+        assert_eq!(run(Arc::new(PushDownFilters), &query), "+body:a #body:b -(#body:c #body:e)");
+        let query: Arc<dyn Query> =
+            Arc::new(ConstantScoreQuery::new(boolean(&[(term("a"), Occur::Must), (term("b"), Occur::Must)])));
+        assert_eq!(run(Arc::new(PushDownFilters), &query), "ConstantScore(#body:a #body:b)");
+
+        // Nested queries are visited too.
+        let query = boolean(&[(term("a"), Occur::Must), (boolean(&[(term("b"), Occur::Filter)]), Occur::Must)]);
+        assert_eq!(run(Arc::new(SingleClauseBooleans), &query), "+body:a +(ConstantScore(body:b))^0");
+
+        assert!(RewritePipeline::standard().rewrite(term("a").as_ref(), &searcher()).unwrap().is_none());
+    }
+
+    /// Rewrites the British spelling of "colour".
+    #[derive(Debug)]
+    struct Spelling;
+
+    impl QueryVisitor for Spelling {
+        fn visit(&self, query: &dyn Query, _searcher: &IndexSearcher) -> BoxResult<Option<Arc<dyn Query>>> {
+            match (query as &dyn Any).downcast_ref::<TermQuery>() {
+                Some(query) if query.term().text() == Some("colour") => {
+                    Ok(Some(Arc::new(TermQuery::new(Term::from_text(query.term().field(), "color")))))
+                }
+                _ => Ok(None),
+            }
+        }
+    }
+
+    #[test]
+    fn test_optimize() {
+        let mut searcher = searcher();
+        let query = boolean(&[
+            (boolean(&[(term("a"), Occur::Must), (term("b"), Occur::Must)]), Occur::Must),
+            (boolean(&[(term("d"), Occur::Should), (term("e"), Occur::Should)]), Occur::Should),
+            (term("e"), Occur::Should),
+            (boolean(&[(term("c"), Occur::Must)]), Occur::Filter),
+            (term("b"), Occur::Filter),
+        ]);
+        assert_eq!(
+            searcher.optimize(query.as_ref()).unwrap().unwrap().to_string(),
+            "+body:a +body:b body:d (body:e)^2 #body:c"
+        );
+
+        // Optimizing doesn't change the hits or their scores.
+        let optimized = searcher.search(query.as_ref(), 10).unwrap();
+        let mut unoptimized = searcher.clone();
+        unoptimized.set_rewrite_pipeline(Arc::new(RewritePipeline::new()));
+        let expected = unoptimized.search(query.as_ref(), 10).unwrap();
+        assert_eq!(optimized.total_hits.value, 1);
+        assert_eq!(optimized.score_docs.len(), expected.score_docs.len());
+        for (actual, expected) in optimized.score_docs.iter().zip(expected.score_docs.iter()) {
+            assert_eq!(actual.doc, expected.doc);
+            assert!((actual.score - expected.score).abs() < 1e-5);
+        }
+
+        // Custom rules run after the standard passes.
+        let query = boolean(&[(term("colour"), Occur::Should), (term("e"), Occur::Should)]);
+        assert_eq!(searcher.count(query.as_ref()).unwrap(), 3);
+        let mut pipeline = RewritePipeline::standard();
+        pipeline.add(Arc::new(Spelling));
+        searcher.set_rewrite_pipeline(Arc::new(pipeline));
+        assert_eq!(searcher.rewrite_pipeline().passes().len(), 5);
+        assert_eq!(searcher.count(query.as_ref()).unwrap(), 4);
+    }
+}
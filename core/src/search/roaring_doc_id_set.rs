@@ -0,0 +1,292 @@
+use {
+    crate::{
+        search::{DocIdSet, DocIdSetIterator, NO_MORE_DOCS},
+        util::{Accountable, BitSet, FixedBitSet},
+        BoxResult, LuceneError,
+    },
+    std::sync::Arc,
+};
+
+/// The number of documents in each block of a [RoaringDocIdSet].
+const BLOCK_SIZE: u32 = 1 << 16;
+
+/// Blocks with fewer documents than this, or fewer missing documents, are stored as arrays rather than bit sets.
+const MAX_ARRAY_LENGTH: usize = 1 << 12;
+
+/// The documents of one block of a [RoaringDocIdSet], relative to the start of the block.
+#[derive(Clone, Debug)]
+enum RoaringBlock {
+    /// No documents.
+    Empty,
+
+    /// The documents, sorted.
+    Sparse(Arc<[u16]>),
+
+    /// A bit set of the documents.
+    Dense(Arc<FixedBitSet>),
+
+    /// The documents that are missing, sorted.
+    Inverse(Arc<[u16]>),
+}
+
+impl RoaringBlock {
+    /// Returns the first document at or after `target`, if any.
+    fn advance(&self, target: u16) -> Option<u16> {
+        match self {
+            Self::Empty => None,
+            Self::Sparse(docs) => docs.get(docs.partition_point(|&doc| doc < target)).copied(),
+            Self::Dense(bits) => match bits.next_set_bit(target as u32) {
+                NO_MORE_DOCS => None,
+                doc => Some(doc as u16),
+            },
+            Self::Inverse(missing) => {
+                let mut doc = target as u32;
+                for &m in &missing[missing.partition_point(|&m| m < target)..] {
+                    if m as u32 != doc {
+                        break;
+                    }
+                    doc += 1;
+                }
+                (doc < BLOCK_SIZE).then_some(doc as u16)
+            }
+        }
+    }
+
+    fn ram_bytes_used(&self) -> usize {
+        size_of::<Self>()
+            + match self {
+                Self::Empty => 0,
+                Self::Sparse(docs) | Self::Inverse(docs) => docs.len() * size_of::<u16>(),
+                Self::Dense(bits) => bits.ram_bytes_used(),
+            }
+    }
+}
+
+/// A compressed [DocIdSet], after the Roaring bitmaps used by Lucene's query cache.
+///
+/// The document id space is split into blocks of 65536 documents, and each block is encoded independently: as a
+/// sorted array of 16-bit ids when it holds fewer than 4096 documents, as a sorted array of the missing ids when it
+/// lacks fewer than 4096 documents, and as a bit set otherwise. This keeps sets small whatever their density, while
+/// iteration remains fast. Sets are built with a [RoaringDocIdSetBuilder].
+#[derive(Clone, Debug)]
+pub struct RoaringDocIdSet {
+    blocks: Arc<[RoaringBlock]>,
+    cardinality: u32,
+}
+
+impl RoaringDocIdSet {
+    /// Collects the documents of an unpositioned iterator into a set. `max_doc` is the number of documents in the
+    /// segment.
+    pub fn from_iterator(iterator: &mut dyn DocIdSetIterator, max_doc: u32) -> BoxResult<Self> {
+        let mut builder = RoaringDocIdSetBuilder::new(max_doc);
+        loop {
+            let doc = iterator.next_doc()?;
+            if doc == NO_MORE_DOCS {
+                return Ok(builder.build());
+            }
+            builder.add(doc)?;
+        }
+    }
+
+    /// Returns the number of documents in the set.
+    #[inline]
+    pub fn cardinality(&self) -> u32 {
+        self.cardinality
+    }
+}
+
+impl DocIdSet for RoaringDocIdSet {
+    fn iterator(&self) -> Box<dyn DocIdSetIterator> {
+        Box::new(RoaringIterator {
+            blocks: self.blocks.clone(),
+            cardinality: self.cardinality,
+            doc: None,
+        })
+    }
+}
+
+impl Accountable for RoaringDocIdSet {
+    fn ram_bytes_used(&self) -> usize {
+        size_of::<Self>() + self.blocks.iter().map(RoaringBlock::ram_bytes_used).sum::<usize>()
+    }
+}
+
+/// Builds a [RoaringDocIdSet] from documents added in increasing order.
+#[derive(Debug)]
+pub struct RoaringDocIdSetBuilder {
+    max_doc: u32,
+    blocks: Vec<RoaringBlock>,
+    last_doc: Option<u32>,
+    buffer: Vec<u16>,
+    dense: Option<FixedBitSet>,
+    cardinality: u32,
+}
+
+impl RoaringDocIdSetBuilder {
+    /// Creates a builder for a segment of `max_doc` documents.
+    pub fn new(max_doc: u32) -> Self {
+        Self {
+            max_doc,
+            blocks: Vec::with_capacity(max_doc.div_ceil(BLOCK_SIZE) as usize),
+            last_doc: None,
+            buffer: Vec::new(),
+            dense: None,
+            cardinality: 0,
+        }
+    }
+
+    /// Adds a document, which must be less than `max_doc` and greater than any document added before. This fails
+    /// with [LuceneError::InvalidArgument] otherwise.
+    pub fn add(&mut self, doc: u32) -> BoxResult<&mut Self> {
+        if doc >= self.max_doc || self.last_doc.is_some_and(|last| doc <= last) {
+            return Err(LuceneError::InvalidArgument(format!(
+                "document {doc} must be less than {} and greater than {:?}",
+                self.max_doc, self.last_doc
+            ))
+            .into());
+        }
+
+        let block = (doc / BLOCK_SIZE) as usize;
+        while self.blocks.len() < block {
+            self.flush_block();
+        }
+
+        let doc_in_block = (doc % BLOCK_SIZE) as u16;
+        match &mut self.dense {
+            Some(dense) => dense.set(doc_in_block as u32),
+            None if self.buffer.len() < MAX_ARRAY_LENGTH => self.buffer.push(doc_in_block),
+            None => {
+                let mut dense = FixedBitSet::new(BLOCK_SIZE);
+                for &buffered in &self.buffer {
+                    dense.set(buffered as u32);
+                }
+                dense.set(doc_in_block as u32);
+                self.buffer.clear();
+                self.dense = Some(dense);
+            }
+        }
+
+        self.last_doc = Some(doc);
+        self.cardinality += 1;
+        Ok(self)
+    }
+
+    /// Encodes the documents of the current block and moves on to the next block.
+    fn flush_block(&mut self) {
+        let block = match self.dense.take() {
+            Some(dense) => {
+                let cardinality = dense.cardinality() as usize;
+                if cardinality > BLOCK_SIZE as usize - MAX_ARRAY_LENGTH {
+                    let missing: Vec<u16> =
+                        (0..BLOCK_SIZE).filter(|&doc| !dense.get(doc)).map(|doc| doc as u16).collect();
+                    RoaringBlock::Inverse(missing.into())
+                } else {
+                    RoaringBlock::Dense(Arc::new(dense))
+                }
+            }
+            None if self.buffer.is_empty() => RoaringBlock::Empty,
+            None => RoaringBlock::Sparse(std::mem::take(&mut self.buffer).into()),
+        };
+        self.blocks.push(block);
+    }
+
+    /// Builds the set.
+    pub fn build(mut self) -> RoaringDocIdSet {
+        if let Some(last_doc) = self.last_doc {
+            if self.blocks.len() <= (last_doc / BLOCK_SIZE) as usize {
+                self.flush_block();
+            }
+        }
+        RoaringDocIdSet {
+            blocks: self.blocks.into(),
+            cardinality: self.cardinality,
+        }
+    }
+}
+
+/// Iterates over a [RoaringDocIdSet].
+#[derive(Debug)]
+struct RoaringIterator {
+    blocks: Arc<[RoaringBlock]>,
+    cardinality: u32,
+    doc: Option<u32>,
+}
+
+impl DocIdSetIterator for RoaringIterator {
+    #[inline]
+    fn doc_id(&self) -> u32 {
+        self.doc.unwrap_or(NO_MORE_DOCS)
+    }
+
+    fn next_doc(&mut self) -> BoxResult<u32> {
+        match self.doc {
+            None => self.advance(0),
+            Some(doc) => self.advance(doc.saturating_add(1)),
+        }
+    }
+
+    fn advance(&mut self, mut target: u32) -> BoxResult<u32> {
+        let doc = loop {
+            let block = (target / BLOCK_SIZE) as usize;
+            let Some(encoded) = self.blocks.get(block) else {
+                break NO_MORE_DOCS;
+            };
+            match encoded.advance((target % BLOCK_SIZE) as u16) {
+                Some(doc) => break block as u32 * BLOCK_SIZE + doc as u32,
+                None => target = (block as u32 + 1) * BLOCK_SIZE,
+            }
+        };
+        self.doc = Some(doc);
+        Ok(doc)
+    }
+
+    #[inline]
+    fn cost(&self) -> u64 {
+        self.cardinality as u64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{
+            search::{DocIdSet, IntArrayDocIdSetIterator, RoaringDocIdSet, RoaringDocIdSetBuilder, NO_MORE_DOCS},
+            util::Accountable,
+        },
+        pretty_assertions::assert_eq,
+    };
+
+    #[test]
+    fn test_roaring_doc_id_set() {
+        // A sparse block, an empty block, a dense block, and an almost full block.
+        let mut docs: Vec<u32> = (0..100).map(|i| i * 600).collect();
+        docs.extend((2 * 65536..3 * 65536).step_by(3));
+        docs.extend((3 * 65536..4 * 65536).filter(|doc| doc % 1000 != 0));
+        let max_doc = 4 * 65536 + 10;
+
+        let set =
+            RoaringDocIdSet::from_iterator(&mut IntArrayDocIdSetIterator::new(docs.clone().into()), max_doc).unwrap();
+        assert_eq!(set.cardinality() as usize, docs.len());
+        assert!(set.ram_bytes_used() < docs.len() * 4);
+
+        let mut iterator = set.iterator();
+        let mut collected = Vec::new();
+        while iterator.next_doc().unwrap() != NO_MORE_DOCS {
+            collected.push(iterator.doc_id());
+        }
+        assert_eq!(collected, docs);
+
+        let mut iterator = set.iterator();
+        assert_eq!(iterator.advance(599).unwrap(), 600);
+        assert_eq!(iterator.advance(60_000).unwrap(), 2 * 65536);
+        assert_eq!(iterator.advance(3 * 65536).unwrap(), 3 * 65536);
+        assert_eq!(iterator.advance(197_000).unwrap(), 197_001);
+        assert_eq!(iterator.advance(4 * 65536).unwrap(), NO_MORE_DOCS);
+
+        let mut builder = RoaringDocIdSetBuilder::new(10);
+        builder.add(5).unwrap();
+        assert!(builder.add(5).is_err());
+        assert!(builder.add(10).is_err());
+        assert_eq!(builder.build().cardinality(), 1);
+    }
+}
@@ -0,0 +1,59 @@
+use crate::{
+    search::{DocIdSetIterator, TwoPhaseIterator},
+    BoxResult,
+};
+
+/// Indicates how a query's scores will be consumed, which lets scorers skip work that isn't needed.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ScoreMode {
+    /// All matching documents are visited and scored.
+    Complete,
+
+    /// All matching documents are visited but scores are not needed.
+    CompleteNoScores,
+
+    /// Only the top-scoring documents are needed, so documents that can't compete may be skipped.
+    TopScores,
+}
+
+impl ScoreMode {
+    /// Indicates whether scores are needed.
+    #[inline]
+    pub fn needs_scores(self) -> bool {
+        self != Self::CompleteNoScores
+    }
+
+    /// Indicates whether every matching document must be visited.
+    #[inline]
+    pub fn is_exhaustive(self) -> bool {
+        self != Self::TopScores
+    }
+}
+
+/// Something that can report the score of the current document.
+pub trait Scorable {
+    /// Returns the score of the current document.
+    fn score(&mut self) -> BoxResult<f32>;
+
+    /// Tells the scorer that documents scoring below `min_score` are no longer competitive and may be skipped. This
+    /// may only be called when the weight was created with [ScoreMode::TopScores]; the default ignores it.
+    fn set_min_competitive_score(&mut self, _min_score: f32) -> BoxResult<()> {
+        Ok(())
+    }
+}
+
+/// Iterates over the documents matching a query in a single segment, scoring each one.
+pub trait Scorer: Scorable + DocIdSetIterator {
+    /// Returns an upper bound on the score of any document up to and including `up_to`.
+    fn max_score(&mut self, _up_to: u32) -> BoxResult<f32> {
+        Ok(f32::INFINITY)
+    }
+
+    /// Returns the two-phase view of this scorer, if it has one.
+    ///
+    /// When present, callers may iterate the approximation and call [TwoPhaseIterator::matches] themselves instead of
+    /// using this scorer's [DocIdSetIterator] methods; the scorer stays positioned on the approximation's document.
+    fn two_phase_iterator(&mut self) -> Option<&mut dyn TwoPhaseIterator> {
+        None
+    }
+}
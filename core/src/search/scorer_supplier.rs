@@ -0,0 +1,47 @@
+use {
+    crate::{search::Scorer, BoxResult},
+    std::fmt::Debug,
+};
+
+/// Supplies the [Scorer] of a query for a segment once the caller knows how it will be iterated.
+///
+/// A weight may have several ways of matching documents whose relative costs depend on how the scorer is used: when
+/// it is intersected with a cheaper clause, the lead clause picks the candidates and this scorer is only advanced to
+/// them. The supplier reports its [ScorerSupplier::cost] up front, and the caller passes the cost of the lead
+/// iterator to [ScorerSupplier::get] so the supplier can choose.
+pub trait ScorerSupplier: Debug + Send {
+    /// Returns the scorer, given `lead_cost`, the estimated number of documents the scorer will be advanced to. This
+    /// is [u64::MAX] when the scorer leads iteration itself.
+    fn get(self: Box<Self>, lead_cost: u64) -> BoxResult<Box<dyn Scorer>>;
+
+    /// Returns an estimate of the number of documents the scorer will match.
+    fn cost(&self) -> u64;
+}
+
+/// A [ScorerSupplier] for a scorer that has already been created, which is what [crate::search::Weight]s with only
+/// one way of matching documents supply.
+#[derive(Debug)]
+pub struct EagerScorerSupplier {
+    scorer: Box<dyn Scorer>,
+}
+
+impl EagerScorerSupplier {
+    /// Creates a supplier of the given scorer.
+    pub fn new(scorer: Box<dyn Scorer>) -> Self {
+        Self {
+            scorer,
+        }
+    }
+}
+
+impl ScorerSupplier for EagerScorerSupplier {
+    #[inline]
+    fn get(self: Box<Self>, _lead_cost: u64) -> BoxResult<Box<dyn Scorer>> {
+        Ok(self.scorer)
+    }
+
+    #[inline]
+    fn cost(&self) -> u64 {
+        self.scorer.cost()
+    }
+}
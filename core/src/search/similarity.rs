@@ -0,0 +1,133 @@
+use {
+    crate::{search::Explanation, util::int_to_byte4, BoxResult, LuceneError},
+    std::fmt::Debug,
+};
+
+/// Statistics about a field across all documents in the index, used for scoring.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CollectionStatistics {
+    /// The name of the field.
+    pub field: String,
+
+    /// The total number of documents in the index, whether or not they have the field.
+    pub max_doc: u64,
+
+    /// The number of documents that have at least one term for the field.
+    pub doc_count: u64,
+
+    /// The total number of tokens in the field across all documents.
+    pub sum_total_term_freq: u64,
+
+    /// The total number of postings (document/term pairs) in the field.
+    pub sum_doc_freq: u64,
+}
+
+impl CollectionStatistics {
+    /// Adds the statistics of the same field in another index, such as another shard, so that the result describes
+    /// both indexes together. Returns an error if the statistics are for a different field.
+    pub fn merge(&mut self, other: &CollectionStatistics) -> BoxResult<()> {
+        if other.field != self.field {
+            return Err(LuceneError::InvalidArgument(format!(
+                "cannot merge statistics of field {} into statistics of field {}",
+                other.field, self.field
+            ))
+            .into());
+        }
+
+        self.max_doc += other.max_doc;
+        self.doc_count += other.doc_count;
+        self.sum_total_term_freq += other.sum_total_term_freq;
+        self.sum_doc_freq += other.sum_doc_freq;
+        Ok(())
+    }
+}
+
+/// Statistics about a term across all documents in the index, used for scoring.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TermStatistics {
+    /// The term.
+    pub term: Vec<u8>,
+
+    /// The number of documents containing the term.
+    pub doc_freq: u64,
+
+    /// The total number of occurrences of the term across all documents.
+    pub total_term_freq: u64,
+}
+
+impl TermStatistics {
+    /// Adds the statistics of the same term in another index, such as another shard, so that the result describes
+    /// both indexes together. Returns an error if the statistics are for a different term.
+    pub fn merge(&mut self, other: &TermStatistics) -> BoxResult<()> {
+        if other.term != self.term {
+            return Err(LuceneError::InvalidArgument(format!(
+                "cannot merge statistics of term {} into statistics of term {}",
+                String::from_utf8_lossy(&other.term),
+                String::from_utf8_lossy(&self.term)
+            ))
+            .into());
+        }
+
+        self.doc_freq += other.doc_freq;
+        self.total_term_freq += other.total_term_freq;
+        Ok(())
+    }
+}
+
+/// Statistics about a field of a single document, gathered while it is indexed and used to compute its norm.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FieldInvertState {
+    /// The name of the field.
+    pub field: String,
+
+    /// The number of tokens in the field.
+    pub length: u32,
+
+    /// The number of tokens at the same position as the token before them, such as synonyms injected by an analyzer.
+    pub num_overlap: u32,
+
+    /// The number of distinct terms in the field.
+    pub unique_term_count: u32,
+
+    /// The highest number of occurrences of a single term in the field.
+    pub max_term_frequency: u32,
+}
+
+/// A scoring model: computes how well a document matches a term given its frequency and the field's norm.
+///
+/// The same similarity should be used when indexing, where it computes norms (see
+/// [crate::index::IndexWriterConfig::set_similarity]), and when searching (see
+/// [crate::search::IndexSearcher::set_similarity]). To use different models for different fields, see
+/// [crate::search::PerFieldSimilarityWrapper].
+pub trait Similarity: Debug + Send + Sync {
+    /// Computes the norm recorded for a field of a document. Norms of 0 are treated as missing by the built-in
+    /// similarities. By default, this is the number of tokens in the field, not counting overlapping tokens, encoded
+    /// into a byte with [int_to_byte4].
+    fn compute_norm(&self, state: &FieldInvertState) -> i64 {
+        int_to_byte4(state.length - state.num_overlap) as i64
+    }
+
+    /// Creates a scorer for a query term (or group of terms, as in a phrase) with the given statistics.
+    fn scorer(
+        &self,
+        boost: f32,
+        collection_stats: &CollectionStatistics,
+        term_stats: &[TermStatistics],
+    ) -> Box<dyn SimScorer>;
+}
+
+/// Scores documents for a single query term, as created by [Similarity::scorer].
+pub trait SimScorer: Debug + Send + Sync {
+    /// Returns the score of a document in which the term occurs `freq` times, given the document's norm for the field.
+    /// This must not decrease as `freq` increases.
+    fn score(&self, freq: f32, norm: i64) -> f32;
+
+    /// Explains the score computed by [SimScorer::score] for the same arguments.
+    fn explain(&self, freq: f32, norm: i64) -> Explanation {
+        Explanation::matched(
+            self.score(freq, norm),
+            format!("score(freq={freq}, norm={norm})"),
+            vec![Explanation::matched(freq, "freq, occurrences of term within document", vec![])],
+        )
+    }
+}
@@ -1,10 +1,11 @@
 use {
     crate::{
         io::{AsyncReadUnpin, AsyncWriteUnpin, EncodingReadExt, EncodingWriteExt},
+        search::DoubleValuesSource,
         BoxResult, LuceneError,
     },
     async_trait::async_trait,
-    std::fmt::Debug,
+    std::{fmt::Debug, sync::Arc},
 };
 
 /// Encapsulates sort criteria for returned hits.
@@ -126,7 +127,7 @@ impl SortFieldType {
 /// Sorting on a Sorted or SortedSet field that is indexed with both doc values and term index may use an  optimization
 /// to skip non-competitive documents. This optimization relies on the assumption that the same data is stored in these
 /// term index and doc values.
-pub trait SortField: Debug {
+pub trait SortField: Debug + Send + Sync {
     /// Returns the type of sort.
     fn get_field_type(&self) -> SortFieldType;
 
@@ -143,6 +144,12 @@ pub trait SortField: Debug {
 
     /// What to replace missing values with.
     fn missing_value(&self) -> Option<MissingValue>;
+
+    /// Returns the source of the values to sort by, for [SortFieldType::Custom] sort fields whose values are computed
+    /// per document, such as distances. Missing values are replaced with the `F64` missing value.
+    fn double_values_source(&self) -> Option<Arc<dyn DoubleValuesSource>> {
+        None
+    }
 }
 
 /// The value to subsitute when a document is missing a value for the sort field.
@@ -351,7 +358,7 @@ impl SortField for BasicSortField {
 }
 
 /// The basic (base) sort field provider. This provider is used by default.
-/// 
+///
 /// In Java, this is the `SortFieldProvider` class. However, Rust does not allow for base classes or inheritance,
 /// so we use this struct instead and have it implement the [SortFieldProvider] trait.
 #[derive(Debug, Default)]
@@ -564,10 +571,10 @@ impl SortFieldProvider for BasicSortFieldProvider {
 }
 
 /// Returns the sort field provider for the given name.
-/// 
+///
 /// TODO: SortedNumericSortField is not implemented.
-/// 
-/// TODO: SortedSetSortField is not implemented. 
+///
+/// TODO: SortedSetSortField is not implemented.
 pub fn get_sort_field_provider(name: &str) -> Result<Box<dyn SortFieldProvider>, LuceneError> {
     match name {
         "SortField" => Ok(Box::<BasicSortFieldProvider>::default()),
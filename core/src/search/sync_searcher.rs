@@ -0,0 +1,134 @@
+use {
+    crate::{
+        document::Document,
+        index::{IndexReader, SyncIndexWriter},
+        search::{Explanation, IndexSearcher, Query, ScoreDoc, Sort, TopDocs, TopFieldDocs},
+        BoxResult,
+    },
+    std::{
+        fmt::{Debug, Formatter, Result as FmtResult},
+        sync::Arc,
+    },
+};
+
+/// A blocking facade over [IndexSearcher], the search-side counterpart of [SyncIndexWriter] for command-line tools,
+/// game engines and other programs without an async runtime.
+///
+/// Searches are already synchronous, so this only wraps the searcher, giving such programs one blocking API for
+/// indexing and searching.
+#[derive(Clone)]
+pub struct SyncIndexSearcher {
+    searcher: IndexSearcher,
+}
+
+impl SyncIndexSearcher {
+    /// Creates a searcher over the given reader.
+    pub fn new(reader: Arc<dyn IndexReader>) -> Self {
+        Self {
+            searcher: IndexSearcher::new(reader),
+        }
+    }
+
+    /// Opens a searcher over the segments flushed by `writer` so far. Documents flushed later aren't visible to it;
+    /// open another searcher to see them.
+    pub fn open(writer: &SyncIndexWriter) -> BoxResult<Self> {
+        Ok(Self::new(Arc::new(writer.inner().reader()?)))
+    }
+
+    /// Returns the top `n` hits for the query. See [IndexSearcher::search].
+    pub fn search(&self, query: &dyn Query, n: usize) -> BoxResult<TopDocs> {
+        self.searcher.search(query, n)
+    }
+
+    /// Returns the next `n` hits for the query after `after`. See [IndexSearcher::search_after].
+    pub fn search_after(&self, after: &ScoreDoc, query: &dyn Query, n: usize) -> BoxResult<TopDocs> {
+        self.searcher.search_after(after, query, n)
+    }
+
+    /// Returns the top `n` hits for the query in the order given by `sort`. See [IndexSearcher::search_with_sort].
+    pub fn search_with_sort(&self, query: &dyn Query, n: usize, sort: &Sort) -> BoxResult<TopFieldDocs> {
+        self.searcher.search_with_sort(query, n, sort)
+    }
+
+    /// Returns the number of documents matching the query.
+    pub fn count(&self, query: &dyn Query) -> BoxResult<u64> {
+        self.searcher.count(query)
+    }
+
+    /// Explains how the score of a document was computed for the query. See [IndexSearcher::explain].
+    pub fn explain(&self, query: &dyn Query, doc: u32) -> BoxResult<Explanation> {
+        self.searcher.explain(query, doc)
+    }
+
+    /// Returns the stored fields of the document with the given global id.
+    pub fn doc(&self, doc: u32) -> BoxResult<Document> {
+        self.searcher.doc(doc)
+    }
+
+    /// Returns the wrapped searcher.
+    #[inline]
+    pub fn inner(&self) -> &IndexSearcher {
+        &self.searcher
+    }
+
+    /// Returns the wrapped searcher mutably, to configure its similarity, timeout and other settings.
+    #[inline]
+    pub fn inner_mut(&mut self) -> &mut IndexSearcher {
+        &mut self.searcher
+    }
+
+    /// Unwraps this facade, returning the wrapped searcher.
+    #[inline]
+    pub fn into_inner(self) -> IndexSearcher {
+        self.searcher
+    }
+}
+
+impl Debug for SyncIndexSearcher {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        f.debug_struct("SyncIndexSearcher").field("max_doc", &self.searcher.reader().max_doc()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{
+            document::{Document, Field, Store},
+            index::{IndexWriterConfig, SyncIndexWriter, Term},
+            io::ByteBuffersDirectory,
+            search::{SyncIndexSearcher, TermQuery},
+        },
+        pretty_assertions::assert_eq,
+    };
+
+    #[test]
+    fn test_sync_index_searcher() {
+        let mut writer = SyncIndexWriter::new(Box::new(ByteBuffersDirectory::new()), IndexWriterConfig::new()).unwrap();
+        let documents = ["quick fox", "lazy dog", "quick dog"].map(|body| {
+            let mut document = Document::new();
+            document.add(Field::text("body", body, Store::Yes));
+            document
+        });
+        writer.add_documents(documents).unwrap();
+        writer.flush().unwrap();
+
+        let searcher = SyncIndexSearcher::open(&writer).unwrap();
+        let query = TermQuery::new(Term::new("body", "quick"));
+        assert_eq!(searcher.count(&query).unwrap(), 2);
+        let top_docs = searcher.search(&query, 1).unwrap();
+        assert_eq!(top_docs.score_docs.len(), 1);
+        let after = searcher.search_after(&top_docs.score_docs[0], &query, 10).unwrap();
+        assert_eq!(after.score_docs.len(), 1);
+        assert!(searcher.explain(&query, after.score_docs[0].doc).unwrap().is_match());
+
+        // Documents flushed after the searcher was opened are only seen by a new searcher.
+        let mut document = Document::new();
+        document.add(Field::text("body", "quick cat", Store::Yes));
+        writer.add_document(&document).unwrap();
+        writer.flush().unwrap();
+        assert_eq!(searcher.count(&query).unwrap(), 2);
+        assert_eq!(SyncIndexSearcher::open(&writer).unwrap().count(&query).unwrap(), 3);
+        writer.close().unwrap();
+    }
+}
@@ -0,0 +1,321 @@
+use {
+    crate::{
+        index::{LeafReaderContext, Term, Terms},
+        search::{
+            ConstantScoreQuery, ConstantScoreScorer, DocIdSetBuilder, DocIdSetIterator, Explanation, IndexSearcher,
+            MatchNoDocsQuery, Query, QueryMemoryTracker, ScoreMode, Scorer, TermQuery, Weight,
+        },
+        util::{
+            automaton::{CompiledAutomaton, DaciukMihovAutomatonBuilder, DEFAULT_DETERMINIZE_WORK_LIMIT},
+            Accountable,
+        },
+        BoxResult,
+    },
+    std::{
+        fmt::{Display, Formatter, Result as FmtResult},
+        sync::Arc,
+    },
+};
+
+/// The minimum number of query terms for which intersecting an automaton with the terms dictionary is considered.
+/// Below this, seeking each term is always cheaper than building the automaton.
+const AUTOMATON_MIN_TERM_COUNT: usize = 16;
+
+/// Intersecting an automaton is preferred once the query holds at least `1 / AUTOMATON_DENSITY_DIVISOR` of a
+/// segment's terms.
+const AUTOMATON_DENSITY_DIVISOR: u64 = 8;
+
+/// A query that matches documents containing any of a set of terms in a field, with a constant score.
+///
+/// This is the efficient way to filter by a large list of values, such as `WHERE id IN (...)`. For each segment it
+/// chooses between two strategies:
+/// * Seeking each query term in the terms dictionary and reading its postings, which is best when the query holds
+///   few terms relative to the field.
+/// * Building a minimal automaton over the query terms and intersecting it with the terms dictionary, which visits
+///   the dictionary in a single ordered pass and is best when the query holds a large fraction of the field's terms.
+///
+/// Either way, the matching documents are gathered into a single doc id set. The automaton and the doc id sets are
+/// reserved against the searcher's memory budget (see [IndexSearcher::memory_tracker]).
+#[derive(Clone, Debug)]
+pub struct TermInSetQuery {
+    field: String,
+    terms: Arc<[Vec<u8>]>,
+}
+
+impl TermInSetQuery {
+    /// Creates a query for documents whose `field` contains any of the given terms. The terms may be given in any
+    /// order and may contain duplicates.
+    pub fn new<I, T>(field: &str, terms: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: AsRef<[u8]>,
+    {
+        let mut terms: Vec<Vec<u8>> = terms.into_iter().map(|t| t.as_ref().to_vec()).collect();
+        terms.sort_unstable();
+        terms.dedup();
+
+        Self {
+            field: field.to_string(),
+            terms: terms.into(),
+        }
+    }
+
+    /// Returns the field being queried.
+    #[inline]
+    pub fn field(&self) -> &str {
+        &self.field
+    }
+
+    /// Returns the query terms, sorted and without duplicates.
+    #[inline]
+    pub fn terms(&self) -> &[Vec<u8>] {
+        &self.terms
+    }
+}
+
+impl Query for TermInSetQuery {
+    fn create_weight(
+        &self,
+        searcher: &IndexSearcher,
+        _score_mode: ScoreMode,
+        boost: f32,
+    ) -> BoxResult<Box<dyn Weight>> {
+        Ok(Box::new(TermInSetWeight::new(self, boost, searcher.memory_tracker())?))
+    }
+
+    fn rewrite(&self, _searcher: &IndexSearcher) -> BoxResult<Option<Arc<dyn Query>>> {
+        match self.terms.len() {
+            0 => Ok(Some(Arc::new(MatchNoDocsQuery::new("empty TermInSetQuery")))),
+            1 => {
+                let term_query = TermQuery::new(Term::new(self.field.as_str(), self.terms[0].clone()));
+                Ok(Some(Arc::new(ConstantScoreQuery::new(Arc::new(term_query)))))
+            }
+            _ => Ok(None),
+        }
+    }
+}
+
+impl Display for TermInSetQuery {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "{}:(", self.field)?;
+        for (i, term) in self.terms.iter().enumerate() {
+            if i > 0 {
+                write!(f, " ")?;
+            }
+            write!(f, "{}", String::from_utf8_lossy(term))?;
+        }
+        write!(f, ")")
+    }
+}
+
+/// How a segment's matching documents are found.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Strategy {
+    /// Seek each query term in the terms dictionary.
+    SeekTerms,
+
+    /// Intersect the automaton over the query terms with the terms dictionary.
+    Automaton,
+}
+
+#[derive(Debug)]
+struct TermInSetWeight {
+    field: String,
+    terms: Arc<[Vec<u8>]>,
+    automaton: Option<CompiledAutomaton>,
+    score: f32,
+    memory_tracker: Arc<QueryMemoryTracker>,
+}
+
+impl TermInSetWeight {
+    fn new(query: &TermInSetQuery, score: f32, memory_tracker: Arc<QueryMemoryTracker>) -> BoxResult<Self> {
+        // The automaton is built once for all segments, and only if some segment could use it.
+        let automaton = if query.terms.len() >= AUTOMATON_MIN_TERM_COUNT {
+            let a = DaciukMihovAutomatonBuilder::build_binary(query.terms.iter())?;
+            let automaton = CompiledAutomaton::new(&a, Some(true), false, DEFAULT_DETERMINIZE_WORK_LIMIT, true)?;
+            memory_tracker.reserve(automaton.ram_bytes_used(), "TermInSetQuery automaton")?;
+            Some(automaton)
+        } else {
+            None
+        };
+
+        Ok(Self {
+            field: query.field.clone(),
+            terms: query.terms.clone(),
+            automaton,
+            score,
+            memory_tracker,
+        })
+    }
+
+    fn strategy(&self, terms: &dyn Terms) -> Strategy {
+        let dense = terms.size().is_some_and(|size| self.terms.len() as u64 * AUTOMATON_DENSITY_DIVISOR >= size);
+        if self.automaton.is_some() && dense {
+            Strategy::Automaton
+        } else {
+            Strategy::SeekTerms
+        }
+    }
+
+    /// Returns the documents of the segment matching any query term, using the given strategy.
+    fn matching_docs(
+        &self,
+        terms: &dyn Terms,
+        max_doc: u32,
+        strategy: Strategy,
+    ) -> BoxResult<Option<Box<dyn DocIdSetIterator>>> {
+        let mut matched = Vec::new();
+        match (strategy, &self.automaton) {
+            (Strategy::Automaton, Some(automaton)) => {
+                let mut te = terms.intersect(automaton, None)?;
+                while te.next()?.is_some() {
+                    matched.push(te.postings()?);
+                }
+            }
+            _ => {
+                let mut te = terms.iterator()?;
+                for term in self.terms.iter() {
+                    if te.seek_exact(term)? {
+                        matched.push(te.postings()?);
+                    }
+                }
+            }
+        }
+
+        // A single term's postings can be used directly; otherwise their union is gathered into one set.
+        if matched.len() <= 1 {
+            return Ok(matched.pop().map(|postings| postings as Box<dyn DocIdSetIterator>));
+        }
+
+        let mut builder = DocIdSetBuilder::new(max_doc);
+        for mut postings in matched {
+            builder.add(postings.as_mut())?;
+        }
+
+        let docs = builder.build();
+        self.memory_tracker.reserve(docs.ram_bytes_used(), "TermInSetQuery doc id set")?;
+        Ok(Some(docs.iterator()))
+    }
+}
+
+impl Weight for TermInSetWeight {
+    fn scorer(&self, context: &LeafReaderContext) -> BoxResult<Option<Box<dyn Scorer>>> {
+        let Some(terms) = context.reader().terms(&self.field)? else {
+            return Ok(None);
+        };
+
+        let strategy = self.strategy(terms);
+        Ok(self
+            .matching_docs(terms, context.reader().max_doc(), strategy)?
+            .map(|docs| Box::new(ConstantScoreScorer::new(self.score, docs)) as Box<dyn Scorer>))
+    }
+
+    fn explain(&self, context: &LeafReaderContext, doc: u32) -> BoxResult<Explanation> {
+        match self.scorer_at(context, doc)? {
+            Some(_) => Ok(Explanation::constant(self.score, format!("{}:({} terms)", self.field, self.terms.len()))),
+            None => Ok(Explanation::no_match(format!("no term of {} matches document {doc}", self.field), vec![])),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::{Strategy, TermInSetWeight},
+        crate::{
+            document::{Document, Field, Store},
+            search::{test_util, IndexSearcher, Query, TermInSetQuery, NO_MORE_DOCS},
+        },
+        pretty_assertions::assert_eq,
+        std::sync::Arc,
+    };
+
+    fn searcher(num_segments: u32, docs_per_segment: u32) -> IndexSearcher {
+        test_util::searcher((0..num_segments).map(|segment| {
+            (0..docs_per_segment).map(move |i| {
+                let id = segment * docs_per_segment + i;
+                let parity = if id.is_multiple_of(2) {
+                    "even"
+                } else {
+                    "odd"
+                };
+                Document::from_iter([
+                    Field::string("id", format!("{id:04}"), Store::Yes),
+                    Field::string("parity", parity, Store::No),
+                ])
+            })
+        }))
+    }
+
+    fn hits(searcher: &IndexSearcher, query: &dyn Query) -> Vec<String> {
+        let top_docs = searcher.search(query, 1000).unwrap();
+        let mut ids: Vec<String> =
+            top_docs.score_docs.iter().map(|sd| searcher.doc(sd.doc).unwrap().get("id").unwrap().to_string()).collect();
+        ids.sort();
+        ids
+    }
+
+    #[test]
+    fn test_small_set() {
+        let searcher = searcher(2, 10);
+        let query = TermInSetQuery::new("id", ["0013", "0002", "0099", "0002"]);
+        assert_eq!(query.to_string(), "id:(0002 0013 0099)");
+        assert_eq!(hits(&searcher, &query), vec!["0002", "0013"]);
+
+        let top_docs = searcher.search(&query, 10).unwrap();
+        assert!(top_docs.score_docs.iter().all(|sd| sd.score == 1.0));
+
+        // Terms shared by many documents are unioned.
+        assert_eq!(searcher.count(&TermInSetQuery::new("parity", ["even", "odd"])).unwrap(), 20);
+        assert_eq!(searcher.count(&TermInSetQuery::new("missing", ["even", "odd"])).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_rewrite() {
+        let searcher = searcher(1, 5);
+        let empty = searcher.rewrite(&TermInSetQuery::new("id", Vec::<&str>::new())).unwrap().unwrap();
+        assert_eq!(searcher.count(empty.as_ref()).unwrap(), 0);
+
+        let single = searcher.rewrite(&TermInSetQuery::new("id", ["0003"])).unwrap().unwrap();
+        assert_eq!(single.to_string(), "ConstantScore(id:0003)");
+        assert_eq!(searcher.count(single.as_ref()).unwrap(), 1);
+
+        assert!(searcher.rewrite(&TermInSetQuery::new("id", ["0003", "0004"])).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_strategies_agree() {
+        let searcher = searcher(3, 200);
+        let ids: Vec<String> = (0..600).step_by(3).map(|id| format!("{id:04}")).chain(["9999".to_string()]).collect();
+        let query = TermInSetQuery::new("id", &ids);
+        let weight = TermInSetWeight::new(&query, 1.0, Arc::default()).unwrap();
+
+        for leaf in searcher.reader().leaves() {
+            let terms = leaf.reader().terms("id").unwrap().unwrap();
+
+            // A third of the segment's terms is dense enough to intersect an automaton.
+            assert_eq!(weight.strategy(terms), Strategy::Automaton);
+
+            let mut results = Vec::new();
+            for strategy in [Strategy::Automaton, Strategy::SeekTerms] {
+                let mut docs = weight.matching_docs(terms, leaf.reader().max_doc(), strategy).unwrap().unwrap();
+                let mut matched = Vec::new();
+                while docs.next_doc().unwrap() != NO_MORE_DOCS {
+                    matched.push(docs.doc_id());
+                }
+                results.push(matched);
+            }
+            assert_eq!(results[0].len(), [67, 67, 66][leaf.ord()]);
+            assert_eq!(results[0], results[1]);
+        }
+
+        assert_eq!(hits(&searcher, &query).len(), 200);
+
+        // A sparse set seeks each term instead.
+        let sparse = TermInSetQuery::new("id", (0..20).map(|id| format!("{id:04}")));
+        let weight = TermInSetWeight::new(&sparse, 1.0, Arc::default()).unwrap();
+        let terms = searcher.reader().leaves()[0].reader().terms("id").unwrap().unwrap();
+        assert_eq!(weight.strategy(terms), Strategy::SeekTerms);
+        assert_eq!(searcher.count(&sparse).unwrap(), 20);
+    }
+}
@@ -0,0 +1,220 @@
+use {
+    crate::{
+        index::{LeafReaderContext, PostingsEnum, Term},
+        search::{DocIdSetIterator, Explanation, IndexSearcher, Query, Scorable, ScoreMode, Scorer, SimScorer, Weight},
+        BoxResult,
+    },
+    std::{
+        fmt::{Display, Formatter, Result as FmtResult},
+        sync::Arc,
+    },
+};
+
+/// A query that matches documents containing a term, scored by the searcher's similarity.
+#[derive(Clone, Debug)]
+pub struct TermQuery {
+    term: Term,
+}
+
+impl TermQuery {
+    /// Creates a query for the given term.
+    pub fn new(term: Term) -> Self {
+        Self {
+            term,
+        }
+    }
+
+    /// Returns the term being queried.
+    #[inline]
+    pub fn term(&self) -> &Term {
+        &self.term
+    }
+}
+
+impl Query for TermQuery {
+    fn create_weight(
+        &self,
+        searcher: &IndexSearcher,
+        _score_mode: ScoreMode,
+        boost: f32,
+    ) -> BoxResult<Box<dyn Weight>> {
+        let collection_stats = searcher.collection_statistics(self.term.field())?;
+        let term_stats = searcher.term_statistics(&self.term)?;
+
+        // If the term doesn't occur anywhere, no segment will produce a scorer.
+        let sim_scorer = match (collection_stats, term_stats) {
+            (Some(cs), Some(ts)) => Some(Arc::from(searcher.similarity().scorer(boost, &cs, &[ts]))),
+            _ => None,
+        };
+
+        Ok(Box::new(TermWeight {
+            term: self.term.clone(),
+            sim_scorer,
+        }))
+    }
+}
+
+impl Display for TermQuery {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        Display::fmt(&self.term, f)
+    }
+}
+
+#[derive(Debug)]
+struct TermWeight {
+    term: Term,
+    sim_scorer: Option<Arc<dyn SimScorer>>,
+}
+
+impl Weight for TermWeight {
+    fn scorer(&self, context: &LeafReaderContext) -> BoxResult<Option<Box<dyn Scorer>>> {
+        let Some(sim_scorer) = &self.sim_scorer else {
+            return Ok(None);
+        };
+
+        let reader = context.reader();
+        let Some(terms) = reader.terms(self.term.field())? else {
+            return Ok(None);
+        };
+
+        let mut te = terms.iterator()?;
+        if !te.seek_exact(self.term.bytes())? {
+            return Ok(None);
+        }
+
+        Ok(Some(Box::new(TermScorer::new(te.postings()?, sim_scorer.clone(), reader.norms(self.term.field())?))))
+    }
+
+    fn explain(&self, context: &LeafReaderContext, doc: u32) -> BoxResult<Explanation> {
+        let Some(sim_scorer) = &self.sim_scorer else {
+            return Ok(Explanation::no_match(format!("no matching term for {}", self.term), vec![]));
+        };
+
+        let reader = context.reader();
+        let Some(terms) = reader.terms(self.term.field())? else {
+            return Ok(Explanation::no_match(format!("no matching term for {}", self.term), vec![]));
+        };
+
+        let mut te = terms.iterator()?;
+        if !te.seek_exact(self.term.bytes())? {
+            return Ok(Explanation::no_match(format!("no matching term for {}", self.term), vec![]));
+        }
+
+        let mut scorer = TermScorer::new(te.postings()?, sim_scorer.clone(), reader.norms(self.term.field())?);
+        if scorer.advance(doc)? != doc {
+            return Ok(Explanation::no_match(format!("{} doesn't occur in document {doc}", self.term), vec![]));
+        }
+
+        let sim_explanation = sim_scorer.explain(scorer.freq()? as f32, scorer.norm());
+        Ok(Explanation::matched(
+            sim_explanation.value(),
+            format!("weight({} in {doc}), result of:", self.term),
+            vec![sim_explanation],
+        ))
+    }
+}
+
+/// A [Scorer] over the postings of a single term.
+#[derive(Debug)]
+pub struct TermScorer {
+    postings: Box<dyn PostingsEnum>,
+    sim_scorer: Arc<dyn SimScorer>,
+    norms: Option<Arc<[i64]>>,
+}
+
+impl TermScorer {
+    /// Creates a scorer over the given postings, scoring with `sim_scorer` and the field's norms.
+    pub fn new(postings: Box<dyn PostingsEnum>, sim_scorer: Arc<dyn SimScorer>, norms: Option<Arc<[i64]>>) -> Self {
+        Self {
+            postings,
+            sim_scorer,
+            norms,
+        }
+    }
+
+    /// Returns the frequency of the term in the current document.
+    #[inline]
+    pub fn freq(&self) -> BoxResult<u32> {
+        self.postings.freq()
+    }
+
+    fn norm(&self) -> i64 {
+        self.norms.as_ref().and_then(|norms| norms.get(self.postings.doc_id() as usize).copied()).unwrap_or(1)
+    }
+}
+
+impl DocIdSetIterator for TermScorer {
+    #[inline]
+    fn doc_id(&self) -> u32 {
+        self.postings.doc_id()
+    }
+
+    #[inline]
+    fn next_doc(&mut self) -> BoxResult<u32> {
+        self.postings.next_doc()
+    }
+
+    #[inline]
+    fn advance(&mut self, target: u32) -> BoxResult<u32> {
+        self.postings.advance(target)
+    }
+
+    #[inline]
+    fn cost(&self) -> u64 {
+        self.postings.cost()
+    }
+}
+
+impl Scorable for TermScorer {
+    fn score(&mut self) -> BoxResult<f32> {
+        Ok(self.sim_scorer.score(self.postings.freq()? as f32, self.norm()))
+    }
+}
+
+impl Scorer for TermScorer {
+    fn max_score(&mut self, _up_to: u32) -> BoxResult<f32> {
+        // Scores never decrease as frequency increases, and the shortest possible document has a norm of 1.
+        Ok(self.sim_scorer.score(f32::MAX, 1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{
+            document::{Document, Field, Store},
+            index::Term,
+            search::{test_util::searcher, TermQuery},
+        },
+        pretty_assertions::assert_eq,
+    };
+
+    #[test]
+    fn test_term_query() {
+        let mut segments = Vec::new();
+        for bodies in [&["the quick brown fox", "the lazy dog"][..], &["fox fox fox", "no match here"][..]] {
+            let mut documents = Vec::new();
+            for body in bodies {
+                let mut doc = Document::new();
+                doc.add(Field::text("body", *body, Store::Yes));
+                documents.push(doc);
+            }
+            segments.push(documents);
+        }
+        let searcher = searcher(segments);
+
+        let query = TermQuery::new(Term::from_text("body", "fox"));
+        assert_eq!(query.to_string(), "body:fox");
+
+        let top_docs = searcher.search(&query, 10).unwrap();
+        assert_eq!(top_docs.total_hits.value, 2);
+        let docs: Vec<u32> = top_docs.score_docs.iter().map(|sd| sd.doc).collect();
+        assert_eq!(docs, vec![2, 0]);
+        assert!(top_docs.score_docs[0].score > top_docs.score_docs[1].score);
+        assert_eq!(searcher.doc(2).unwrap().get("body"), Some("fox fox fox"));
+
+        assert_eq!(searcher.count(&TermQuery::new(Term::from_text("body", "the"))).unwrap(), 2);
+        assert_eq!(searcher.count(&TermQuery::new(Term::from_text("body", "cat"))).unwrap(), 0);
+        assert_eq!(searcher.count(&TermQuery::new(Term::from_text("title", "fox"))).unwrap(), 0);
+    }
+}
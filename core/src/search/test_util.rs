@@ -0,0 +1,103 @@
+//! Fixtures shared by the search tests.
+
+use {
+    crate::{
+        analysis::{Analyzer, SimpleAnalyzer},
+        document::{Document, Field, Store},
+        index::{IndexReader, LeafReader, MemorySegmentBuilder, MultiReader},
+        search::IndexSearcher,
+    },
+    std::sync::Arc,
+};
+
+/// Returns an in-memory segment holding `documents`, analyzed with [SimpleAnalyzer].
+pub(crate) fn segment(documents: impl IntoIterator<Item = Document>) -> Arc<dyn LeafReader> {
+    segment_with_analyzer(Arc::new(SimpleAnalyzer), documents)
+}
+
+/// Returns an in-memory segment holding `documents`, analyzed with `analyzer`.
+pub(crate) fn segment_with_analyzer(
+    analyzer: Arc<dyn Analyzer>,
+    documents: impl IntoIterator<Item = Document>,
+) -> Arc<dyn LeafReader> {
+    let mut builder = MemorySegmentBuilder::new(analyzer);
+    for document in documents {
+        builder.add_document(&document).unwrap();
+    }
+    Arc::new(builder.build())
+}
+
+/// Returns a searcher over `segments`, in order.
+pub(crate) fn leaf_searcher(segments: Vec<Arc<dyn LeafReader>>) -> IndexSearcher {
+    IndexSearcher::new(Arc::new(MultiReader::new(segments).unwrap()))
+}
+
+/// Returns a reader over one segment per list of documents, analyzed with [SimpleAnalyzer].
+pub(crate) fn reader<D: IntoIterator<Item = Document>>(segments: impl IntoIterator<Item = D>) -> Arc<dyn IndexReader> {
+    Arc::new(MultiReader::new(segments.into_iter().map(segment).collect()).unwrap())
+}
+
+/// Returns a searcher over one segment per list of documents, analyzed with [SimpleAnalyzer].
+pub(crate) fn searcher<D: IntoIterator<Item = Document>>(segments: impl IntoIterator<Item = D>) -> IndexSearcher {
+    IndexSearcher::new(reader(segments))
+}
+
+/// Returns a segment with a document per body, each indexed as the unstored text field `body`.
+pub(crate) fn body_segment(bodies: &[&str]) -> Arc<dyn LeafReader> {
+    segment(bodies.iter().map(|body| Document::from_iter([Field::text("body", *body, Store::No)])))
+}
+
+/// Returns a searcher over a single [body_segment].
+pub(crate) fn body_searcher(bodies: &[&str]) -> IndexSearcher {
+    leaf_searcher(vec![body_segment(bodies)])
+}
+
+/// A tracing layer recording the name and fields of every span created while it is the default subscriber.
+#[cfg(feature = "tracing")]
+#[derive(Clone, Debug, Default)]
+pub(crate) struct SpanRecorder(Arc<std::sync::Mutex<Vec<(&'static str, String)>>>);
+
+#[cfg(feature = "tracing")]
+impl SpanRecorder {
+    /// Runs `f` with this recorder as the default subscriber of the current thread.
+    pub(crate) fn record<T>(&self, f: impl FnOnce() -> T) -> T {
+        use tracing_subscriber::layer::SubscriberExt;
+        tracing::subscriber::with_default(tracing_subscriber::registry().with(self.clone()), f)
+    }
+
+    /// Returns the fields of the spans named `name` recorded so far, in order, each as `field=value` pairs separated
+    /// by spaces.
+    pub(crate) fn spans(&self, name: &str) -> Vec<String> {
+        let spans = self.0.lock().unwrap();
+        spans.iter().filter(|(span, _)| *span == name).map(|(_, fields)| fields.clone()).collect()
+    }
+}
+
+#[cfg(feature = "tracing")]
+impl<S: tracing::Subscriber> tracing_subscriber::Layer<S> for SpanRecorder {
+    fn on_new_span(
+        &self,
+        attributes: &tracing::span::Attributes<'_>,
+        _id: &tracing::span::Id,
+        _context: tracing_subscriber::layer::Context<'_, S>,
+    ) {
+        let mut fields = FieldRecorder(Vec::new());
+        attributes.record(&mut fields);
+        self.0.lock().unwrap().push((attributes.metadata().name(), fields.0.join(" ")));
+    }
+}
+
+/// Formats the fields of a span for a [SpanRecorder].
+#[cfg(feature = "tracing")]
+struct FieldRecorder(Vec<String>);
+
+#[cfg(feature = "tracing")]
+impl tracing::field::Visit for FieldRecorder {
+    fn record_str(&mut self, field: &tracing::field::Field, value: &str) {
+        self.0.push(format!("{}={value}", field.name()));
+    }
+
+    fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn std::fmt::Debug) {
+        self.0.push(format!("{}={value:?}", field.name()));
+    }
+}
@@ -0,0 +1,262 @@
+use std::{
+    cmp::Ordering,
+    fmt::{Display, Formatter, Result as FmtResult},
+};
+
+/// A single hit: a document and its score.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ScoreDoc {
+    /// The global document id.
+    pub doc: u32,
+
+    /// The score of the document.
+    pub score: f32,
+
+    /// The index of the shard the hit came from, when merging results from several searches.
+    pub shard_index: Option<usize>,
+}
+
+impl ScoreDoc {
+    /// Creates a new hit without a shard index.
+    pub fn new(doc: u32, score: f32) -> Self {
+        Self {
+            doc,
+            score,
+            shard_index: None,
+        }
+    }
+}
+
+/// How [TotalHits::value] relates to the actual number of hits.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TotalHitsRelation {
+    /// The value is exact.
+    EqualTo,
+
+    /// The value is a lower bound.
+    GreaterThanOrEqualTo,
+}
+
+/// How many hits a collector counts exactly. Past the threshold, a collector may stop counting and skip documents that
+/// can't make it into the top hits, reporting the hit count as a lower bound.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TotalHitsThreshold {
+    /// Every hit is counted, so the hit count is always exact.
+    Exact,
+
+    /// Hits are counted exactly until there are more than the given number.
+    AtLeast(u64),
+}
+
+impl TotalHitsThreshold {
+    /// Returns the number of hits counted exactly.
+    #[inline]
+    pub fn limit(self) -> u64 {
+        match self {
+            Self::Exact => u64::MAX,
+            Self::AtLeast(limit) => limit,
+        }
+    }
+
+    /// Indicates whether `total_hits` hits exceed the threshold.
+    #[inline]
+    pub fn is_exceeded(self, total_hits: u64) -> bool {
+        total_hits > self.limit()
+    }
+}
+
+/// The threshold used by default by collectors that sort hits by field.
+pub const DEFAULT_TOTAL_HITS_THRESHOLD: TotalHitsThreshold = TotalHitsThreshold::AtLeast(1000);
+
+/// The number of hits of a search, which may be a lower bound.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct TotalHits {
+    /// The hit count.
+    pub value: u64,
+
+    /// Whether the count is exact or a lower bound.
+    pub relation: TotalHitsRelation,
+}
+
+impl TotalHits {
+    /// Creates a new hit count.
+    pub fn new(value: u64, relation: TotalHitsRelation) -> Self {
+        Self {
+            value,
+            relation,
+        }
+    }
+}
+
+impl Display for TotalHits {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match self.relation {
+            TotalHitsRelation::EqualTo => write!(f, "{} hits", self.value),
+            TotalHitsRelation::GreaterThanOrEqualTo => write!(f, "{}+ hits", self.value),
+        }
+    }
+}
+
+/// The results of a search: the total hit count and the top hits.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TopDocs {
+    /// The total number of hits.
+    pub total_hits: TotalHits,
+
+    /// The top hits, best first.
+    pub score_docs: Vec<ScoreDoc>,
+}
+
+impl TopDocs {
+    /// Creates new search results.
+    pub fn new(total_hits: TotalHits, score_docs: Vec<ScoreDoc>) -> Self {
+        Self {
+            total_hits,
+            score_docs,
+        }
+    }
+
+    /// Merges the results of several searches into the best `top_n` hits. See [TopDocs::merge_page].
+    #[inline]
+    pub fn merge(top_n: usize, shard_hits: &[TopDocs], set_shard_index: bool) -> TopDocs {
+        Self::merge_page(0, top_n, shard_hits, set_shard_index)
+    }
+
+    /// Merges the results of several searches, skipping the best `start` hits and keeping the next `top_n`. Each
+    /// search must have returned at least `start + top_n` hits, if it has that many. The total hit count is the sum
+    /// of the counts, and is exact only if every count is exact.
+    ///
+    /// Hits are ordered by descending score, with NaN scores last. Ties are broken by [ScoreDoc::shard_index] and then
+    /// by document id, so that hits are in the same order whichever page is merged, and a hit never appears on two
+    /// pages. If `set_shard_index` is true, each hit's shard index is set to the index of the results it came from;
+    /// otherwise the hits keep their shard index, which is either unset because they share a document id space, as
+    /// with the per-slice results of a single searcher, or was set by an earlier merge.
+    pub fn merge_page(start: usize, top_n: usize, shard_hits: &[TopDocs], set_shard_index: bool) -> TopDocs {
+        let mut total_hits = TotalHits::new(0, TotalHitsRelation::EqualTo);
+        let mut score_docs = Vec::new();
+        for (shard_index, top_docs) in shard_hits.iter().enumerate() {
+            total_hits.value += top_docs.total_hits.value;
+            if top_docs.total_hits.relation == TotalHitsRelation::GreaterThanOrEqualTo {
+                total_hits.relation = TotalHitsRelation::GreaterThanOrEqualTo;
+            }
+
+            score_docs.extend(top_docs.score_docs.iter().map(|sd| ScoreDoc {
+                shard_index: if set_shard_index {
+                    Some(shard_index)
+                } else {
+                    sd.shard_index
+                },
+                ..*sd
+            }));
+        }
+
+        score_docs.sort_by(compare_score_docs);
+        let score_docs = score_docs.into_iter().skip(start).take(top_n).collect();
+        TopDocs::new(total_hits, score_docs)
+    }
+}
+
+/// Compares hits in the order of [TopDocs::merge_page]: by descending score with NaN last, then by shard, then by
+/// document id.
+fn compare_score_docs(a: &ScoreDoc, b: &ScoreDoc) -> Ordering {
+    let by_score = match (a.score.is_nan(), b.score.is_nan()) {
+        (false, false) => b.score.partial_cmp(&a.score).unwrap_or(Ordering::Equal),
+        (a_is_nan, b_is_nan) => a_is_nan.cmp(&b_is_nan),
+    };
+    by_score.then(a.shard_index.cmp(&b.shard_index)).then(a.doc.cmp(&b.doc))
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::search::{ScoreDoc, TopDocs, TotalHits, TotalHitsRelation},
+        pretty_assertions::assert_eq,
+    };
+
+    fn top_docs(hits: &[(u32, f32)]) -> TopDocs {
+        let score_docs = hits.iter().map(|&(doc, score)| ScoreDoc::new(doc, score)).collect();
+        TopDocs::new(TotalHits::new(hits.len() as u64, TotalHitsRelation::EqualTo), score_docs)
+    }
+
+    fn hits(top_docs: &TopDocs) -> Vec<(Option<usize>, u32)> {
+        top_docs.score_docs.iter().map(|sd| (sd.shard_index, sd.doc)).collect()
+    }
+
+    #[test]
+    fn test_merge_ties() {
+        let shards = [
+            top_docs(&[(4, 2.0), (1, 1.0), (3, 1.0)]),
+            top_docs(&[(0, 2.0), (2, 1.0), (5, f32::NAN)]),
+            top_docs(&[(1, 1.0), (0, 0.5)]),
+        ];
+
+        // Equal scores are ordered by shard, then by document, and NaN sorts last.
+        let merged = TopDocs::merge(10, &shards, true);
+        assert_eq!(merged.total_hits, TotalHits::new(8, TotalHitsRelation::EqualTo));
+        assert_eq!(
+            hits(&merged),
+            vec![
+                (Some(0), 4),
+                (Some(1), 0),
+                (Some(0), 1),
+                (Some(0), 3),
+                (Some(1), 2),
+                (Some(2), 1),
+                (Some(2), 0),
+                (Some(1), 5)
+            ]
+        );
+
+        // Without shard indexes, hits share a document id space and ties are broken by document alone.
+        let merged = TopDocs::merge(5, &shards, false);
+        assert_eq!(hits(&merged), vec![(None, 0), (None, 4), (None, 1), (None, 1), (None, 2)]);
+
+        // Signed zeros are equal scores.
+        let merged = TopDocs::merge(2, &[top_docs(&[(3, 0.0)]), top_docs(&[(2, -0.0)])], false);
+        assert_eq!(hits(&merged), vec![(None, 2), (None, 3)]);
+    }
+
+    #[test]
+    fn test_merge_pages() {
+        let shards: Vec<TopDocs> = (0..3)
+            .map(|shard| {
+                let hits: Vec<(u32, f32)> = (0..10).map(|doc| (doc, ((doc + shard) % 3) as f32)).collect();
+                let mut top_docs = top_docs(&hits);
+                top_docs.score_docs.sort_by(|a, b| b.score.total_cmp(&a.score).then(a.doc.cmp(&b.doc)));
+                top_docs
+            })
+            .collect();
+        let all = TopDocs::merge(30, &shards, true);
+        assert_eq!(all.score_docs.len(), 30);
+
+        // Pages follow one another exactly, however they're cut.
+        for page_size in [1, 4, 7, 30] {
+            let mut paged = Vec::new();
+            for start in (0..30).step_by(page_size) {
+                let page = TopDocs::merge_page(start, page_size, &shards, true);
+                assert_eq!(page.total_hits, all.total_hits);
+                paged.extend(page.score_docs);
+            }
+            assert_eq!(paged, all.score_docs, "{page_size}");
+        }
+        assert!(TopDocs::merge_page(30, 10, &shards, true).score_docs.is_empty());
+    }
+
+    #[test]
+    fn test_merge_merged() {
+        // Merging in two levels gives the same order as merging at once, as the first level's shard indexes are kept.
+        let shards =
+            [top_docs(&[(0, 3.0), (1, 1.0)]), top_docs(&[(0, 1.0), (1, 1.0)]), top_docs(&[(2, 3.0), (0, 1.0)])];
+        let all = TopDocs::merge(6, &shards, true);
+
+        let mut first = TopDocs::merge(6, &shards[..2], true);
+        let mut second = TopDocs::merge(6, &shards[2..], true);
+        for sd in &mut second.score_docs {
+            sd.shard_index = sd.shard_index.map(|shard_index| shard_index + 2);
+        }
+        first.total_hits.relation = TotalHitsRelation::GreaterThanOrEqualTo;
+        let merged = TopDocs::merge(6, &[first, second], false);
+        assert_eq!(merged.score_docs, all.score_docs);
+        assert_eq!(merged.total_hits, TotalHits::new(6, TotalHitsRelation::GreaterThanOrEqualTo));
+    }
+}
@@ -0,0 +1,874 @@
+use {
+    crate::{
+        index::{LeafReaderContext, NumericDocValues},
+        search::{
+            Collector, CollectorManager, DoubleValues, DoubleValuesSource, LeafCollector, MissingValue, Scorable,
+            ScoreMode, Sort, SortFieldType, TotalHits, TotalHitsRelation, TotalHitsThreshold,
+            DEFAULT_TOTAL_HITS_THRESHOLD,
+        },
+        util::size_of_vec,
+        BoxResult, LuceneError,
+    },
+    std::{
+        cmp::Ordering,
+        fmt::{Display, Formatter, Result as FmtResult},
+        str::FromStr,
+        sync::Arc,
+    },
+};
+
+/// The value a hit was sorted by for one [crate::search::SortField].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SortValue {
+    /// The hit's score.
+    Score(f32),
+
+    /// The hit's global document id.
+    Doc(u32),
+
+    /// An integer field value, for `I32` and `I64` sort fields.
+    Long(i64),
+
+    /// A floating point field value, for `F32` and `F64` sort fields.
+    Double(f64),
+}
+
+impl SortValue {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (Self::Score(a), Self::Score(b)) => b.total_cmp(a),
+            (Self::Doc(a), Self::Doc(b)) => a.cmp(b),
+            (Self::Long(a), Self::Long(b)) => a.cmp(b),
+            (Self::Double(a), Self::Double(b)) => a.total_cmp(b),
+            _ => Ordering::Equal,
+        }
+    }
+}
+
+impl Display for SortValue {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match self {
+            Self::Score(score) => write!(f, "score:{score}"),
+            Self::Doc(doc) => write!(f, "doc:{doc}"),
+            Self::Long(value) => write!(f, "long:{value}"),
+            Self::Double(value) => write!(f, "double:{value}"),
+        }
+    }
+}
+
+impl FromStr for SortValue {
+    type Err = LuceneError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || LuceneError::InvalidArgument(format!("invalid sort value {s:?}"));
+        let (kind, value) = s.split_once(':').ok_or_else(invalid)?;
+        match kind {
+            "score" => value.parse().map(Self::Score).map_err(|_| invalid()),
+            "doc" => value.parse().map(Self::Doc).map_err(|_| invalid()),
+            "long" => value.parse().map(Self::Long).map_err(|_| invalid()),
+            "double" => value.parse().map(Self::Double).map_err(|_| invalid()),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+/// A hit of a search sorted by fields: a document, its score, and the values it was sorted by.
+///
+/// A hit converts to and from a string (`doc;score;value;...`) so that the last hit of a page can be handed to a
+/// client and passed back to [crate::search::IndexSearcher::search_after_with_sort] for the next page.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FieldDoc {
+    /// The global document id.
+    pub doc: u32,
+
+    /// The score of the document, or NaN if the sort doesn't use scores.
+    pub score: f32,
+
+    /// The values of the sort fields for this document, in sort order.
+    pub fields: Vec<SortValue>,
+
+    /// The index of the shard the hit came from, set by [TopFieldDocs::merge].
+    pub shard_index: Option<usize>,
+}
+
+impl Display for FieldDoc {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "{};{}", self.doc, self.score)?;
+        for value in &self.fields {
+            write!(f, ";{value}")?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for FieldDoc {
+    type Err = LuceneError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || LuceneError::InvalidArgument(format!("invalid field doc {s:?}"));
+        let mut parts = s.split(';');
+        let doc = parts.next().and_then(|doc| doc.parse().ok()).ok_or_else(invalid)?;
+        let score = parts.next().and_then(|score| score.parse().ok()).ok_or_else(invalid)?;
+        let fields = parts.map(str::parse).collect::<Result<_, _>>()?;
+
+        Ok(Self {
+            doc,
+            score,
+            fields,
+            shard_index: None,
+        })
+    }
+}
+
+/// The results of a search sorted by fields.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TopFieldDocs {
+    /// The total number of hits.
+    pub total_hits: TotalHits,
+
+    /// The top hits, in sort order.
+    pub field_docs: Vec<FieldDoc>,
+}
+
+impl TopFieldDocs {
+    /// Creates results from the total hit count and the top hits.
+    pub fn new(total_hits: TotalHits, field_docs: Vec<FieldDoc>) -> Self {
+        Self {
+            total_hits,
+            field_docs,
+        }
+    }
+
+    /// Merges the results of several searches sorted by `sort` into the best `top_n` hits. See
+    /// [TopFieldDocs::merge_page].
+    #[inline]
+    pub fn merge(
+        sort: &Sort,
+        top_n: usize,
+        shard_hits: &[TopFieldDocs],
+        set_shard_index: bool,
+    ) -> BoxResult<TopFieldDocs> {
+        Self::merge_page(sort, 0, top_n, shard_hits, set_shard_index)
+    }
+
+    /// Merges the results of several searches sorted by `sort`, skipping the first `start` hits and keeping the next
+    /// `top_n`, as [crate::search::TopDocs::merge_page] does for hits sorted by score. Hits that sort equally are
+    /// ordered by shard, then by document id, so that a hit never appears on two pages.
+    ///
+    /// If `set_shard_index` is true, each hit's [FieldDoc::shard_index] is set to the index of the results it came
+    /// from; otherwise hits keep their shard index. Returns an error if a hit doesn't have a value for each field of
+    /// the sort.
+    pub fn merge_page(
+        sort: &Sort,
+        start: usize,
+        top_n: usize,
+        shard_hits: &[TopFieldDocs],
+        set_shard_index: bool,
+    ) -> BoxResult<TopFieldDocs> {
+        let keys = SortKey::resolve(sort)?;
+        let mut field_docs = Vec::new();
+        for (shard_index, top_docs) in shard_hits.iter().enumerate() {
+            for field_doc in &top_docs.field_docs {
+                if field_doc.fields.len() != keys.len() {
+                    return Err(LuceneError::InvalidArgument(format!(
+                        "hit {field_doc} of shard {shard_index} has {} sort values, expected {}",
+                        field_doc.fields.len(),
+                        keys.len()
+                    ))
+                    .into());
+                }
+
+                let mut field_doc = field_doc.clone();
+                if set_shard_index {
+                    field_doc.shard_index = Some(shard_index);
+                }
+                field_docs.push(field_doc);
+            }
+        }
+
+        Ok(merge_field_docs(&keys, start, top_n, shard_hits.iter().map(|top_docs| top_docs.total_hits), field_docs))
+    }
+}
+
+/// Sorts the hits of several searches, whose total hit counts are `shard_totals`, and keeps `top_n` of them from
+/// `start`. Hits that sort equally are ordered by shard, then by document id.
+fn merge_field_docs(
+    keys: &[SortKey],
+    start: usize,
+    top_n: usize,
+    shard_totals: impl IntoIterator<Item = TotalHits>,
+    mut field_docs: Vec<FieldDoc>,
+) -> TopFieldDocs {
+    let mut total_hits = TotalHits::new(0, TotalHitsRelation::EqualTo);
+    for shard_total in shard_totals {
+        total_hits.value += shard_total.value;
+        if shard_total.relation == TotalHitsRelation::GreaterThanOrEqualTo {
+            total_hits.relation = TotalHitsRelation::GreaterThanOrEqualTo;
+        }
+    }
+
+    field_docs.sort_by(|a, b| {
+        keys.iter()
+            .zip(a.fields.iter().zip(&b.fields))
+            .map(|(key, (a, b))| key.compare(a, b))
+            .find(|ordering| ordering.is_ne())
+            .unwrap_or(Ordering::Equal)
+            .then(a.shard_index.cmp(&b.shard_index))
+            .then(a.doc.cmp(&b.doc))
+    });
+    TopFieldDocs::new(total_hits, field_docs.into_iter().skip(start).take(top_n).collect())
+}
+
+/// A resolved [crate::search::SortField] that can be shared between threads.
+#[derive(Clone, Debug)]
+pub(crate) struct SortKey {
+    pub(crate) field_type: SortFieldType,
+    pub(crate) field: Option<String>,
+    pub(crate) reverse: bool,
+    missing: SortValue,
+
+    /// The source of the values of a custom sort field.
+    pub(crate) source: Option<Arc<dyn DoubleValuesSource>>,
+}
+
+impl PartialEq for SortKey {
+    fn eq(&self, other: &Self) -> bool {
+        let same_source = match (&self.source, &other.source) {
+            (Some(a), Some(b)) => Arc::ptr_eq(a, b),
+            (a, b) => a.is_none() && b.is_none(),
+        };
+
+        same_source
+            && self.field_type == other.field_type
+            && self.field == other.field
+            && self.reverse == other.reverse
+            && self.missing == other.missing
+    }
+}
+
+impl SortKey {
+    /// Resolves the fields of a sort, failing for sort types that can't be computed from numeric doc values.
+    pub(crate) fn resolve(sort: &Sort) -> BoxResult<Vec<SortKey>> {
+        let mut keys = Vec::with_capacity(sort.get_fields().len());
+        for sort_field in sort.get_fields() {
+            let field_type = sort_field.get_field_type();
+            let source = sort_field.double_values_source();
+            let missing = match (field_type, sort_field.missing_value()) {
+                (SortFieldType::Custom, missing) if source.is_some() => match missing {
+                    Some(MissingValue::F64(value)) => SortValue::Double(value),
+                    None => SortValue::Double(0.0),
+                    Some(missing) => {
+                        return Err(LuceneError::InvalidSortField(format!(
+                            "missing value {missing:?} of a custom sort field must be an f64"
+                        ))
+                        .into())
+                    }
+                },
+                (SortFieldType::DocumentScore | SortFieldType::DocumentIndexOrder, _) => SortValue::Doc(0),
+                (SortFieldType::I32, Some(MissingValue::I32(value))) => SortValue::Long(value as i64),
+                (SortFieldType::I64, Some(MissingValue::I64(value))) => SortValue::Long(value),
+                (SortFieldType::I32 | SortFieldType::I64, None) => SortValue::Long(0),
+                (SortFieldType::F32, Some(MissingValue::F32(value))) => SortValue::Double(value as f64),
+                (SortFieldType::F64, Some(MissingValue::F64(value))) => SortValue::Double(value),
+                (SortFieldType::F32 | SortFieldType::F64, None) => SortValue::Double(0.0),
+                (SortFieldType::I32 | SortFieldType::I64 | SortFieldType::F32 | SortFieldType::F64, Some(missing)) => {
+                    return Err(LuceneError::InvalidSortField(format!(
+                        "missing value {missing:?} doesn't match sort field type {field_type:?}"
+                    ))
+                    .into())
+                }
+                _ => {
+                    return Err(LuceneError::InvalidSortField(format!(
+                        "sorting by {field_type:?} fields is not supported"
+                    ))
+                    .into())
+                }
+            };
+
+            keys.push(SortKey {
+                field_type,
+                field: sort_field.get_field_name().map(str::to_string),
+                reverse: sort_field.is_reverse(),
+                missing,
+                source,
+            });
+        }
+
+        Ok(keys)
+    }
+
+    /// Returns the value of this key for the document with global id `doc`, given its score and its doc value for
+    /// the key's field, if any.
+    pub(crate) fn value(&self, doc: u32, score: f32, doc_value: Option<i64>) -> SortValue {
+        match (self.field_type, doc_value) {
+            (SortFieldType::DocumentScore, _) => SortValue::Score(score),
+            (SortFieldType::DocumentIndexOrder, _) => SortValue::Doc(doc),
+            (_, None) => self.missing,
+            (SortFieldType::F32, Some(bits)) => SortValue::Double(f32::from_bits(bits as u32) as f64),
+            (SortFieldType::F64, Some(bits)) => SortValue::Double(f64::from_bits(bits as u64)),
+            (_, Some(value)) => SortValue::Long(value),
+        }
+    }
+
+    /// Checks that `after` holds one value of the right kind for each key.
+    pub(crate) fn check_after(keys: &[SortKey], after: &FieldDoc) -> BoxResult<()> {
+        if after.fields.len() != keys.len() {
+            return Err(LuceneError::InvalidArgument(format!(
+                "after has {} sort values but the sort has {} fields",
+                after.fields.len(),
+                keys.len()
+            ))
+            .into());
+        }
+
+        for (key, value) in keys.iter().zip(&after.fields) {
+            let matches = match key.field_type {
+                SortFieldType::DocumentScore => matches!(value, SortValue::Score(_)),
+                SortFieldType::DocumentIndexOrder => matches!(value, SortValue::Doc(_)),
+                SortFieldType::F32 | SortFieldType::F64 | SortFieldType::Custom => {
+                    matches!(value, SortValue::Double(_))
+                }
+                _ => matches!(value, SortValue::Long(_)),
+            };
+
+            if !matches {
+                return Err(LuceneError::InvalidArgument(format!(
+                    "sort value {value} doesn't match sort field type {:?}",
+                    key.field_type
+                ))
+                .into());
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Indicates whether documents sorted by `index_sort` are also sorted by `search_sort`: the search sort must be a
+    /// non-empty prefix of the index sort.
+    pub(crate) fn is_prefix(search_sort: &[SortKey], index_sort: &[SortKey]) -> bool {
+        !search_sort.is_empty() && index_sort.starts_with(search_sort)
+    }
+
+    /// Compares two values of this key in sort order.
+    #[inline]
+    fn compare(&self, a: &SortValue, b: &SortValue) -> Ordering {
+        let ordering = a.cmp(b);
+        if self.reverse {
+            ordering.reverse()
+        } else {
+            ordering
+        }
+    }
+}
+
+/// Compares two hits in sort order, breaking ties by document id.
+pub(crate) fn compare_field_docs(keys: &[SortKey], a: &FieldDoc, b: &FieldDoc) -> Ordering {
+    keys.iter()
+        .zip(a.fields.iter().zip(&b.fields))
+        .map(|(key, (a, b))| key.compare(a, b))
+        .find(|ordering| ordering.is_ne())
+        .unwrap_or_else(|| a.doc.cmp(&b.doc))
+}
+
+/// A [Collector] that keeps the top hits according to a [Sort]. Scores, document order and numeric doc values
+/// fields (`I32`, `I64`, and `F32` and `F64` whose doc values hold the bits of the float) are supported.
+///
+/// If a segment's index sort (see [crate::index::LeafReader::index_sort]) begins with the search sort, the segment's
+/// documents arrive in sort order, so collection of the segment terminates once it has produced `num_hits` hits and
+/// more than the total hits threshold have been counted. The total hit count is then a lower bound.
+///
+/// For deep paging, [TopFieldCollector::set_after] makes the collector keep only hits that sort after the last hit of
+/// the previous page.
+#[derive(Debug)]
+pub struct TopFieldCollector {
+    keys: Arc<[SortKey]>,
+    num_hits: usize,
+    after: Option<FieldDoc>,
+    total_hits_threshold: TotalHitsThreshold,
+    total_hits: u64,
+    early_terminated: bool,
+    hits: Vec<FieldDoc>,
+}
+
+impl TopFieldCollector {
+    /// Creates a collector that keeps the first `num_hits` hits in the order given by `sort`, counting at least
+    /// [DEFAULT_TOTAL_HITS_THRESHOLD] hits exactly.
+    pub fn new(sort: &Sort, num_hits: usize) -> BoxResult<Self> {
+        Ok(Self::with_keys(SortKey::resolve(sort)?.into(), num_hits, DEFAULT_TOTAL_HITS_THRESHOLD))
+    }
+
+    fn with_keys(keys: Arc<[SortKey]>, num_hits: usize, total_hits_threshold: TotalHitsThreshold) -> Self {
+        Self {
+            keys,
+            num_hits,
+            after: None,
+            total_hits_threshold,
+            total_hits: 0,
+            early_terminated: false,
+            hits: Vec::new(),
+        }
+    }
+
+    /// Sets the number of hits to count exactly before collection may terminate early.
+    pub fn set_total_hits_threshold(&mut self, total_hits_threshold: TotalHitsThreshold) -> &mut Self {
+        self.total_hits_threshold = total_hits_threshold;
+        self
+    }
+
+    /// Sets the last hit of the previous page; only hits that sort after it are kept. This fails if its sort values
+    /// don't match the sort.
+    pub fn set_after(&mut self, after: Option<FieldDoc>) -> BoxResult<&mut Self> {
+        if let Some(after) = &after {
+            SortKey::check_after(&self.keys, after)?;
+        }
+
+        self.after = after;
+        Ok(self)
+    }
+
+    /// Returns the collected hits in sort order.
+    pub fn top_docs(&self) -> TopFieldDocs {
+        let mut hits = self.hits.clone();
+        self.sort_and_truncate(&mut hits);
+        TopFieldDocs {
+            total_hits: TotalHits::new(self.total_hits, self.relation()),
+            field_docs: hits,
+        }
+    }
+
+    fn relation(&self) -> TotalHitsRelation {
+        if self.early_terminated {
+            TotalHitsRelation::GreaterThanOrEqualTo
+        } else {
+            TotalHitsRelation::EqualTo
+        }
+    }
+
+    fn sort_and_truncate(&self, hits: &mut Vec<FieldDoc>) {
+        hits.sort_by(|a, b| compare_field_docs(&self.keys, a, b));
+        hits.truncate(self.num_hits);
+    }
+
+    fn needs_scores(&self) -> bool {
+        self.keys.iter().any(|key| {
+            key.field_type == SortFieldType::DocumentScore || key.source.as_ref().is_some_and(|s| s.needs_scores())
+        })
+    }
+}
+
+impl Collector for TopFieldCollector {
+    fn leaf_collector(&mut self, context: &LeafReaderContext) -> BoxResult<Box<dyn LeafCollector + '_>> {
+        let mut values = Vec::with_capacity(self.keys.len());
+        for key in self.keys.iter() {
+            values.push(match (&key.source, &key.field) {
+                (Some(source), _) => LeafSortValues::Computed(source.values(context)?),
+                (None, Some(field)) => match context.reader().numeric_doc_values(field)? {
+                    Some(doc_values) => LeafSortValues::Numeric(doc_values),
+                    None => LeafSortValues::Missing,
+                },
+                (None, None) => LeafSortValues::Missing,
+            });
+        }
+
+        let index_sort = match context.reader().index_sort() {
+            Some(sort) => SortKey::resolve(sort)?,
+            None => Vec::new(),
+        };
+
+        Ok(Box::new(TopFieldLeafCollector {
+            doc_base: context.doc_base(),
+            needs_scores: self.needs_scores(),
+            values,
+            can_early_terminate: SortKey::is_prefix(&self.keys, &index_sort),
+            collected: 0,
+            parent: self,
+        }))
+    }
+
+    fn score_mode(&self) -> ScoreMode {
+        if self.needs_scores() {
+            ScoreMode::Complete
+        } else {
+            ScoreMode::CompleteNoScores
+        }
+    }
+
+    fn ram_bytes_used(&self) -> usize {
+        size_of_vec(&self.hits) + self.hits.iter().map(|hit| size_of_vec(&hit.fields)).sum::<usize>()
+    }
+}
+
+/// Where a leaf collector reads the values of one sort key from.
+enum LeafSortValues {
+    /// The key doesn't read per-document values, or its field has none in this segment.
+    Missing,
+
+    /// The numeric doc values of the key's field.
+    Numeric(Box<dyn NumericDocValues>),
+
+    /// The values of a custom sort field's source.
+    Computed(Box<dyn DoubleValues>),
+}
+
+struct TopFieldLeafCollector<'a> {
+    doc_base: u32,
+    needs_scores: bool,
+    values: Vec<LeafSortValues>,
+
+    /// Whether the segment's documents arrive in sort order.
+    can_early_terminate: bool,
+
+    /// The number of hits collected from this segment.
+    collected: usize,
+    parent: &'a mut TopFieldCollector,
+}
+
+impl LeafCollector for TopFieldLeafCollector<'_> {
+    fn collect(&mut self, doc: u32, scorer: &mut dyn Scorable) -> BoxResult<()> {
+        let parent = &mut *self.parent;
+        parent.total_hits += 1;
+
+        if self.collected >= parent.num_hits && (self.can_early_terminate || parent.num_hits == 0) {
+            // No later document of this segment can be competitive; keep counting only up to the threshold.
+            if self.can_early_terminate && parent.total_hits_threshold.is_exceeded(parent.total_hits) {
+                parent.early_terminated = true;
+                return Err(LuceneError::CollectionTerminated.into());
+            }
+            return Ok(());
+        }
+
+        let score = if self.needs_scores {
+            scorer.score()?
+        } else {
+            f32::NAN
+        };
+
+        let mut fields = Vec::with_capacity(self.values.len());
+        for (key, values) in parent.keys.iter().zip(self.values.iter_mut()) {
+            let value = match values {
+                LeafSortValues::Computed(values) => match values.value(doc, score)? {
+                    Some(value) => SortValue::Double(value),
+                    None => key.missing,
+                },
+                LeafSortValues::Numeric(doc_values) => {
+                    let mut doc_value = None;
+                    if doc_values.advance_exact(doc)? {
+                        doc_value = Some(doc_values.long_value()?);
+                    }
+                    key.value(self.doc_base + doc, score, doc_value)
+                }
+                LeafSortValues::Missing => key.value(self.doc_base + doc, score, None),
+            };
+            fields.push(value);
+        }
+
+        let hit = FieldDoc {
+            doc: self.doc_base + doc,
+            score,
+            fields,
+            shard_index: None,
+        };
+
+        // Hits up to and including the previous page's last hit were already returned.
+        if let Some(after) = &parent.after {
+            if compare_field_docs(&parent.keys, &hit, after).is_le() {
+                return Ok(());
+            }
+        }
+
+        parent.hits.push(hit);
+        self.collected += 1;
+
+        // Buffer up to twice the hits needed before discarding the uncompetitive ones.
+        if parent.hits.len() >= parent.num_hits.saturating_mul(2).max(64) {
+            let mut hits = std::mem::take(&mut parent.hits);
+            parent.sort_and_truncate(&mut hits);
+            parent.hits = hits;
+        }
+
+        Ok(())
+    }
+}
+
+/// A [CollectorManager] for [TopFieldCollector]s, whose results are merged into the top hits overall.
+#[derive(Clone, Debug)]
+pub struct TopFieldCollectorManager {
+    keys: Arc<[SortKey]>,
+    num_hits: usize,
+    after: Option<FieldDoc>,
+    total_hits_threshold: TotalHitsThreshold,
+}
+
+impl TopFieldCollectorManager {
+    /// Creates a manager whose collectors keep the first `num_hits` hits in the order given by `sort`, each counting
+    /// at least [DEFAULT_TOTAL_HITS_THRESHOLD] hits exactly.
+    pub fn new(sort: &Sort, num_hits: usize) -> BoxResult<Self> {
+        Ok(Self {
+            keys: SortKey::resolve(sort)?.into(),
+            num_hits,
+            after: None,
+            total_hits_threshold: DEFAULT_TOTAL_HITS_THRESHOLD,
+        })
+    }
+
+    /// Sets the last hit of the previous page; see [TopFieldCollector::set_after].
+    pub fn set_after(&mut self, after: Option<FieldDoc>) -> BoxResult<&mut Self> {
+        if let Some(after) = &after {
+            SortKey::check_after(&self.keys, after)?;
+        }
+
+        self.after = after;
+        Ok(self)
+    }
+
+    /// Sets the number of hits each collector counts exactly before collection may terminate early.
+    pub fn set_total_hits_threshold(&mut self, total_hits_threshold: TotalHitsThreshold) -> &mut Self {
+        self.total_hits_threshold = total_hits_threshold;
+        self
+    }
+}
+
+impl CollectorManager for TopFieldCollectorManager {
+    type Collector = TopFieldCollector;
+    type Result = TopFieldDocs;
+
+    fn new_collector(&self) -> BoxResult<TopFieldCollector> {
+        let mut collector = TopFieldCollector::with_keys(self.keys.clone(), self.num_hits, self.total_hits_threshold);
+        collector.after = self.after.clone();
+        Ok(collector)
+    }
+
+    fn reduce(&self, collectors: Vec<TopFieldCollector>) -> BoxResult<TopFieldDocs> {
+        // The slices share the searcher's document ids, so hits are merged without shard indexes.
+        let totals: Vec<TotalHits> = collectors
+            .iter()
+            .map(|collector| {
+                let relation = if collector.early_terminated {
+                    TotalHitsRelation::GreaterThanOrEqualTo
+                } else {
+                    TotalHitsRelation::EqualTo
+                };
+                TotalHits::new(collector.total_hits, relation)
+            })
+            .collect();
+        let hits = collectors.into_iter().flat_map(|collector| collector.hits).collect();
+        Ok(merge_field_docs(&self.keys, 0, self.num_hits, totals, hits))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{
+            analysis::SimpleAnalyzer,
+            document::{Document, Field, Store},
+            index::{LeafReader, MemorySegmentBuilder},
+            search::{
+                test_util::{leaf_searcher, searcher},
+                BasicSortField, FieldDoc, IndexSearcher, MatchAllDocsQuery, Sort, SortValue, TopFieldCollector,
+                TopFieldCollectorManager, TopFieldDocs, TotalHits, TotalHitsRelation, TotalHitsThreshold,
+            },
+        },
+        pretty_assertions::assert_eq,
+        std::sync::Arc,
+    };
+
+    #[test]
+    fn test_sort_by_field() {
+        let mut segments = Vec::new();
+        for prices in [&[Some(30), None, Some(10)][..], &[Some(20), Some(10)][..]] {
+            let mut documents = Vec::new();
+            for price in prices {
+                let mut doc = Document::new();
+                doc.add(Field::text("body", "item", Store::No));
+                if let Some(price) = price {
+                    doc.add(Field::numeric_doc_values("price", *price));
+                }
+                documents.push(doc);
+            }
+            segments.push(documents);
+        }
+        let mut searcher = searcher(segments);
+
+        let mut price = BasicSortField::for_i64_field("price", Some(i64::MAX));
+        let sort = Sort::from_fields(vec![Box::new(BasicSortField::for_i64_field("price", Some(i64::MAX)))]).unwrap();
+        let mut collector = TopFieldCollector::new(&sort, 4).unwrap();
+        searcher.search_with_collector(&MatchAllDocsQuery, &mut collector).unwrap();
+        let top_docs = collector.top_docs();
+        assert_eq!(top_docs.total_hits.value, 5);
+        let docs: Vec<u32> = top_docs.field_docs.iter().map(|fd| fd.doc).collect();
+        assert_eq!(docs, vec![2, 4, 3, 0]);
+        assert_eq!(top_docs.field_docs[0].fields, vec![SortValue::Long(10)]);
+        assert!(top_docs.field_docs[0].score.is_nan());
+
+        // Descending, so the document missing a price (which sorts as i64::MAX) comes first; ties in price are broken
+        // by document order.
+        price.set_reverse(true);
+        let sort = Sort::from_fields(vec![Box::new(price), Box::new(BasicSortField::document_index_order())]).unwrap();
+        let manager = TopFieldCollectorManager::new(&sort, 10).unwrap();
+        for concurrent in [false, true] {
+            searcher.set_concurrent(concurrent);
+            let top_docs = searcher.search_with_manager(&MatchAllDocsQuery, &manager).unwrap();
+            let docs: Vec<u32> = top_docs.field_docs.iter().map(|fd| fd.doc).collect();
+            assert_eq!(docs, vec![1, 0, 3, 2, 4]);
+        }
+
+        let sort = Sort::from_fields(vec![Box::new(BasicSortField::for_string_field("body", None))]).unwrap();
+        assert!(TopFieldCollector::new(&sort, 10).is_err());
+    }
+
+    fn price_sort() -> Sort {
+        Sort::from_fields(vec![Box::new(BasicSortField::for_i64_field("price", None))]).unwrap()
+    }
+
+    #[test]
+    fn test_early_termination_on_sorted_index() {
+        let mut sorted_segments: Vec<Arc<dyn LeafReader>> = Vec::new();
+        let mut unsorted_segments: Vec<Arc<dyn LeafReader>> = Vec::new();
+        for segment in 0..2 {
+            let mut sorted = MemorySegmentBuilder::new(Arc::new(SimpleAnalyzer));
+            sorted.set_index_sort(price_sort()).unwrap();
+            let mut unsorted = MemorySegmentBuilder::new(Arc::new(SimpleAnalyzer));
+            for i in 0..50 {
+                let mut doc = Document::new();
+                doc.add(Field::text("body", "item", Store::No));
+                doc.add(Field::numeric_doc_values("price", (i * 37 + segment * 11) % 100));
+                sorted.add_document(&doc).unwrap();
+                unsorted.add_document(&doc).unwrap();
+            }
+            sorted_segments.push(Arc::new(sorted.build()));
+            unsorted_segments.push(Arc::new(unsorted.build()));
+        }
+        let sorted = leaf_searcher(sorted_segments);
+        let unsorted = leaf_searcher(unsorted_segments);
+
+        let prices = |searcher: &IndexSearcher, threshold: TotalHitsThreshold| {
+            let mut collector = TopFieldCollector::new(&price_sort(), 5).unwrap();
+            collector.set_total_hits_threshold(threshold);
+            searcher.search_with_collector(&MatchAllDocsQuery, &mut collector).unwrap();
+            let top_docs = collector.top_docs();
+            let prices: Vec<SortValue> = top_docs.field_docs.into_iter().flat_map(|fd| fd.fields).collect();
+            (top_docs.total_hits, prices)
+        };
+
+        let (total_hits, expected) = prices(&unsorted, TotalHitsThreshold::AtLeast(0));
+        assert_eq!(total_hits.value, 100);
+        assert_eq!(total_hits.relation, TotalHitsRelation::EqualTo);
+
+        // Each segment stops at its sixth hit once more than 3 hits have been counted.
+        let (total_hits, actual) = prices(&sorted, TotalHitsThreshold::AtLeast(3));
+        assert_eq!(actual, expected);
+        assert_eq!(total_hits.value, 12);
+        assert_eq!(total_hits.relation, TotalHitsRelation::GreaterThanOrEqualTo);
+
+        // Below the threshold, every hit is still counted.
+        let (total_hits, actual) = prices(&sorted, TotalHitsThreshold::AtLeast(1000));
+        assert_eq!(actual, expected);
+        assert_eq!(total_hits.value, 100);
+        assert_eq!(total_hits.relation, TotalHitsRelation::EqualTo);
+
+        // A sort that isn't a prefix of the index sort never terminates early.
+        let sort = Sort::from_fields(vec![Box::new(BasicSortField::document_index_order())]).unwrap();
+        let mut collector = TopFieldCollector::new(&sort, 5).unwrap();
+        collector.set_total_hits_threshold(TotalHitsThreshold::AtLeast(0));
+        sorted.search_with_collector(&MatchAllDocsQuery, &mut collector).unwrap();
+        assert_eq!(collector.top_docs().total_hits.value, 100);
+
+        // The stored documents and doc values follow the new order.
+        let reader = sorted.leaves()[0].reader();
+        let mut doc_values = reader.numeric_doc_values("price").unwrap().unwrap();
+        assert!(doc_values.advance_exact(0).unwrap());
+        assert_eq!(doc_values.long_value().unwrap(), 0);
+    }
+
+    #[test]
+    fn test_search_after_with_sort() {
+        let mut segments = Vec::new();
+        for segment in 0..2 {
+            let mut documents = Vec::new();
+            for i in 0..15 {
+                let mut doc = Document::new();
+                doc.add(Field::text("body", "item", Store::No));
+                if (i + segment) % 4 != 0 {
+                    doc.add(Field::numeric_doc_values("price", (i % 5) as i64));
+                }
+                documents.push(doc);
+            }
+            segments.push(documents);
+        }
+        let searcher = searcher(segments);
+
+        let mut price = BasicSortField::for_i64_field("price", Some(-1));
+        price.set_reverse(true);
+        let sort = Sort::from_fields(vec![Box::new(price)]).unwrap();
+        let all = searcher.search_with_sort(&MatchAllDocsQuery, 100, &sort).unwrap();
+        assert_eq!(all.field_docs.len(), 30);
+
+        // Each page resumes from the string form of the previous page's last hit.
+        let mut paged = searcher.search_with_sort(&MatchAllDocsQuery, 4, &sort).unwrap().field_docs;
+        loop {
+            let token = paged.last().unwrap().to_string();
+            let after: FieldDoc = token.parse().unwrap();
+            assert_eq!(after.to_string(), token);
+
+            let page = searcher.search_after_with_sort(&after, &MatchAllDocsQuery, 4, &sort).unwrap();
+            if page.field_docs.is_empty() {
+                break;
+            }
+            paged.extend(page.field_docs);
+        }
+        let docs = |hits: &[FieldDoc]| hits.iter().map(|fd| (fd.doc, fd.fields.clone())).collect::<Vec<_>>();
+        assert_eq!(docs(&paged), docs(&all.field_docs));
+
+        let mismatched = FieldDoc {
+            doc: 0,
+            score: f32::NAN,
+            fields: vec![SortValue::Double(1.0)],
+            shard_index: None,
+        };
+        assert!(searcher.search_after_with_sort(&mismatched, &MatchAllDocsQuery, 4, &sort).is_err());
+        assert!("1;NaN;long".parse::<FieldDoc>().is_err());
+    }
+
+    #[test]
+    fn test_merge_shards() {
+        let hit = |doc, price| FieldDoc {
+            doc,
+            score: f32::NAN,
+            fields: vec![SortValue::Long(price)],
+            shard_index: None,
+        };
+        let shards = [
+            TopFieldDocs::new(TotalHits::new(3, TotalHitsRelation::EqualTo), vec![hit(2, 10), hit(0, 30)]),
+            TopFieldDocs::new(TotalHits::new(5, TotalHitsRelation::GreaterThanOrEqualTo), vec![hit(1, 10), hit(0, 20)]),
+        ];
+
+        // Equal prices are ordered by shard, then by document.
+        let merged = TopFieldDocs::merge(&price_sort(), 3, &shards, true).unwrap();
+        assert_eq!(merged.total_hits, TotalHits::new(8, TotalHitsRelation::GreaterThanOrEqualTo));
+        let hits: Vec<(Option<usize>, u32)> = merged.field_docs.iter().map(|fd| (fd.shard_index, fd.doc)).collect();
+        assert_eq!(hits, vec![(Some(0), 2), (Some(1), 1), (Some(1), 0)]);
+
+        let merged = TopFieldDocs::merge(&price_sort(), 10, &shards, false).unwrap();
+        let hits: Vec<(Option<usize>, u32)> = merged.field_docs.iter().map(|fd| (fd.shard_index, fd.doc)).collect();
+        assert_eq!(hits, vec![(None, 1), (None, 2), (None, 0), (None, 0)]);
+
+        // Pages are cut from the same order, and a second merge keeps the shard indexes of the first.
+        let all = TopFieldDocs::merge(&price_sort(), 10, &shards, true).unwrap();
+        let page = TopFieldDocs::merge_page(&price_sort(), 1, 2, &shards, true).unwrap();
+        let hits: Vec<(Option<usize>, u32)> = page.field_docs.iter().map(|fd| (fd.shard_index, fd.doc)).collect();
+        assert_eq!(hits, vec![(Some(1), 1), (Some(1), 0)]);
+        assert!(TopFieldDocs::merge_page(&price_sort(), 4, 2, &shards, true).unwrap().field_docs.is_empty());
+        let remerged = TopFieldDocs::merge(&price_sort(), 10, &[page, all.clone()], false).unwrap();
+        let hits: Vec<(Option<usize>, u32)> = remerged.field_docs.iter().map(|fd| (fd.shard_index, fd.doc)).collect();
+        assert_eq!(hits, vec![(Some(0), 2), (Some(1), 1), (Some(1), 1), (Some(1), 0), (Some(1), 0), (Some(0), 0)]);
+
+        let unsorted = [TopFieldDocs::new(TotalHits::new(1, TotalHitsRelation::EqualTo), vec![hit(0, 1)])];
+        let sort = Sort::from_fields(vec![
+            Box::new(BasicSortField::document_index_order()),
+            Box::new(BasicSortField::document_index_order()),
+        ])
+        .unwrap();
+        assert!(TopFieldDocs::merge(&sort, 10, &unsorted, false).is_err());
+    }
+}
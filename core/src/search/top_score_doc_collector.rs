@@ -0,0 +1,300 @@
+use {
+    crate::{
+        index::LeafReaderContext,
+        search::{
+            Collector, CollectorManager, LeafCollector, Scorable, ScoreDoc, ScoreMode, TopDocs, TotalHits,
+            TotalHitsRelation, TotalHitsThreshold,
+        },
+        BoxResult, LuceneError,
+    },
+    std::{cmp::Ordering, collections::BinaryHeap},
+};
+
+/// A hit in the priority queue. Entries order so that the greatest is the least competitive hit: the lowest score,
+/// with ties broken in favor of the lower document id.
+#[derive(Clone, Copy, Debug)]
+struct HitEntry {
+    doc: u32,
+    score: f32,
+}
+
+impl PartialEq for HitEntry {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for HitEntry {}
+
+impl PartialOrd for HitEntry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for HitEntry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.score.total_cmp(&self.score).then(self.doc.cmp(&other.doc))
+    }
+}
+
+/// A [Collector] that keeps the top-scoring hits, breaking ties by document id.
+///
+/// By default every hit is counted. With a [TotalHitsThreshold::AtLeast] threshold, once more hits than the threshold
+/// have been counted and the queue is full, the collector passes the lowest score in the queue to the scorer as the
+/// minimum competitive score, letting it skip documents that can't make it into the top hits. The total hit count is
+/// then a lower bound.
+///
+/// For deep paging, [TopScoreDocCollector::set_after] makes the collector keep only hits that sort after the last hit
+/// of the previous page. Every hit is still counted.
+#[derive(Debug)]
+pub struct TopScoreDocCollector {
+    num_hits: usize,
+    after: Option<ScoreDoc>,
+    total_hits_threshold: TotalHitsThreshold,
+    total_hits: u64,
+    total_hits_relation: TotalHitsRelation,
+    queue: BinaryHeap<HitEntry>,
+}
+
+impl TopScoreDocCollector {
+    /// Creates a collector that keeps the best `num_hits` hits and counts every hit.
+    pub fn new(num_hits: usize) -> Self {
+        Self {
+            num_hits,
+            after: None,
+            total_hits_threshold: TotalHitsThreshold::Exact,
+            total_hits: 0,
+            total_hits_relation: TotalHitsRelation::EqualTo,
+            queue: BinaryHeap::with_capacity(num_hits.min(1024)),
+        }
+    }
+
+    /// Sets the number of hits to count exactly before uncompetitive documents may be skipped.
+    pub fn set_total_hits_threshold(&mut self, total_hits_threshold: TotalHitsThreshold) -> &mut Self {
+        self.total_hits_threshold = total_hits_threshold;
+        self
+    }
+
+    /// Sets the last hit of the previous page. Only hits with a lower score, or the same score and a greater document
+    /// id, are kept.
+    pub fn set_after(&mut self, after: Option<ScoreDoc>) -> &mut Self {
+        self.after = after;
+        self
+    }
+
+    /// Returns the collected hits, best first.
+    pub fn top_docs(&self) -> TopDocs {
+        let mut hits = self.queue.clone().into_vec();
+        hits.sort();
+        TopDocs::new(
+            TotalHits::new(self.total_hits, self.total_hits_relation),
+            hits.into_iter().map(|h| ScoreDoc::new(h.doc, h.score)).collect(),
+        )
+    }
+}
+
+impl Collector for TopScoreDocCollector {
+    fn leaf_collector(&mut self, context: &LeafReaderContext) -> BoxResult<Box<dyn LeafCollector + '_>> {
+        if self.num_hits == 0 && self.total_hits_threshold.is_exceeded(self.total_hits) {
+            return Err(LuceneError::CollectionTerminated.into());
+        }
+
+        Ok(Box::new(TopScoreLeafCollector {
+            doc_base: context.doc_base(),
+            min_competitive_score: f32::NEG_INFINITY,
+            parent: self,
+        }))
+    }
+
+    fn ram_bytes_used(&self) -> usize {
+        self.queue.capacity() * size_of::<HitEntry>()
+    }
+
+    #[inline]
+    fn score_mode(&self) -> ScoreMode {
+        match self.total_hits_threshold {
+            TotalHitsThreshold::Exact => ScoreMode::Complete,
+            TotalHitsThreshold::AtLeast(_) => ScoreMode::TopScores,
+        }
+    }
+}
+
+/// A [CollectorManager] for [TopScoreDocCollector]s, whose results are merged into the best hits overall.
+#[derive(Clone, Copy, Debug)]
+pub struct TopScoreDocCollectorManager {
+    num_hits: usize,
+    after: Option<ScoreDoc>,
+    total_hits_threshold: TotalHitsThreshold,
+}
+
+impl TopScoreDocCollectorManager {
+    /// Creates a manager whose collectors keep the best `num_hits` hits and count every hit.
+    pub fn new(num_hits: usize) -> Self {
+        Self {
+            num_hits,
+            after: None,
+            total_hits_threshold: TotalHitsThreshold::Exact,
+        }
+    }
+
+    /// Sets the number of hits each collector counts exactly before uncompetitive documents may be skipped.
+    pub fn set_total_hits_threshold(&mut self, total_hits_threshold: TotalHitsThreshold) -> &mut Self {
+        self.total_hits_threshold = total_hits_threshold;
+        self
+    }
+
+    /// Sets the last hit of the previous page; see [TopScoreDocCollector::set_after].
+    pub fn set_after(&mut self, after: Option<ScoreDoc>) -> &mut Self {
+        self.after = after;
+        self
+    }
+}
+
+impl CollectorManager for TopScoreDocCollectorManager {
+    type Collector = TopScoreDocCollector;
+    type Result = TopDocs;
+
+    fn new_collector(&self) -> BoxResult<TopScoreDocCollector> {
+        let mut collector = TopScoreDocCollector::new(self.num_hits);
+        collector.set_total_hits_threshold(self.total_hits_threshold).set_after(self.after);
+        Ok(collector)
+    }
+
+    fn reduce(&self, collectors: Vec<TopScoreDocCollector>) -> BoxResult<TopDocs> {
+        let shard_hits: Vec<TopDocs> = collectors.iter().map(|c| c.top_docs()).collect();
+        Ok(TopDocs::merge(self.num_hits, &shard_hits, false))
+    }
+}
+
+struct TopScoreLeafCollector<'a> {
+    doc_base: u32,
+
+    /// The minimum competitive score last passed to this segment's scorer.
+    min_competitive_score: f32,
+    parent: &'a mut TopScoreDocCollector,
+}
+
+impl LeafCollector for TopScoreLeafCollector<'_> {
+    fn collect(&mut self, doc: u32, scorer: &mut dyn Scorable) -> BoxResult<()> {
+        let parent = &mut *self.parent;
+        parent.total_hits += 1;
+        if parent.num_hits == 0 {
+            // Only the count is wanted, and nothing more needs to be counted past the threshold.
+            if parent.total_hits_threshold.is_exceeded(parent.total_hits) {
+                parent.total_hits_relation = TotalHitsRelation::GreaterThanOrEqualTo;
+                return Err(LuceneError::CollectionTerminated.into());
+            }
+            return Ok(());
+        }
+
+        let entry = HitEntry {
+            doc: self.doc_base + doc,
+            score: scorer.score()?,
+        };
+
+        // Hits up to and including the previous page's last hit were already returned.
+        if let Some(after) = &parent.after {
+            if entry.score > after.score || (entry.score == after.score && entry.doc <= after.doc) {
+                return Ok(());
+            }
+        }
+
+        if parent.queue.len() < parent.num_hits {
+            parent.queue.push(entry);
+        } else if let Some(mut top) = parent.queue.peek_mut() {
+            // Documents are collected in increasing id order, so a tie with the least competitive hit never wins.
+            if entry.score > top.score {
+                *top = entry;
+            }
+        }
+
+        if parent.queue.len() == parent.num_hits && parent.total_hits_threshold.is_exceeded(parent.total_hits) {
+            let min_score = parent.queue.peek().map_or(f32::NEG_INFINITY, |bottom| bottom.score);
+            if min_score > self.min_competitive_score {
+                scorer.set_min_competitive_score(min_score)?;
+                self.min_competitive_score = min_score;
+            }
+            parent.total_hits_relation = TotalHitsRelation::GreaterThanOrEqualTo;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{
+            document::{Document, Field, Store},
+            index::Term,
+            search::{test_util::searcher, TermQuery, TotalHits, TotalHitsRelation, TotalHitsThreshold},
+        },
+        pretty_assertions::assert_eq,
+    };
+
+    #[test]
+    fn test_total_hits_threshold() {
+        let mut segments = Vec::new();
+        for segment in 0..2 {
+            let mut documents = Vec::new();
+            for i in 0..50 {
+                let mut doc = Document::new();
+                let body = if (i + segment) % 7 == 0 {
+                    "fox fox"
+                } else {
+                    "fox and some other words"
+                };
+                doc.add(Field::text("body", body, Store::No));
+                documents.push(doc);
+            }
+            segments.push(documents);
+        }
+        let searcher = searcher(segments);
+        let query = TermQuery::new(Term::from_text("body", "fox"));
+
+        let exact = searcher.search(&query, 5).unwrap();
+        assert_eq!(exact.total_hits, TotalHits::new(100, TotalHitsRelation::EqualTo));
+
+        let top_docs = searcher.search_with_total_hits_threshold(&query, 5, TotalHitsThreshold::AtLeast(10)).unwrap();
+        assert_eq!(top_docs.score_docs, exact.score_docs);
+        assert!(top_docs.total_hits.value > 10);
+        assert_eq!(top_docs.total_hits.relation, TotalHitsRelation::GreaterThanOrEqualTo);
+
+        // Without hits to keep, counting stops at the threshold in each segment.
+        let top_docs = searcher.search_with_total_hits_threshold(&query, 0, TotalHitsThreshold::AtLeast(10)).unwrap();
+        assert_eq!(top_docs.total_hits, TotalHits::new(11, TotalHitsRelation::GreaterThanOrEqualTo));
+
+        let top_docs = searcher.search_with_total_hits_threshold(&query, 5, TotalHitsThreshold::AtLeast(100)).unwrap();
+        assert_eq!(top_docs, exact);
+    }
+
+    #[test]
+    fn test_search_after() {
+        let mut segments = Vec::new();
+        for _ in 0..3 {
+            let mut documents = Vec::new();
+            for i in 0..20 {
+                let mut doc = Document::new();
+                doc.add(Field::text("body", ["fox", "fox fox", "fox dog"][i % 3], Store::No));
+                documents.push(doc);
+            }
+            segments.push(documents);
+        }
+        let searcher = searcher(segments);
+        let query = TermQuery::new(Term::from_text("body", "fox"));
+        let all = searcher.search(&query, 100).unwrap();
+
+        // Many hits tie on score, so pages must break ties by document id to neither repeat nor skip hits.
+        let mut paged = searcher.search(&query, 7).unwrap().score_docs;
+        loop {
+            let page = searcher.search_after(paged.last().unwrap(), &query, 7).unwrap();
+            assert_eq!(page.total_hits, all.total_hits);
+            if page.score_docs.is_empty() {
+                break;
+            }
+            paged.extend(page.score_docs);
+        }
+        assert_eq!(paged, all.score_docs);
+    }
+}
@@ -0,0 +1,65 @@
+use crate::{
+    index::LeafReaderContext,
+    search::{Collector, CollectorManager, LeafCollector, Scorable, ScoreMode},
+    BoxResult,
+};
+
+/// A [Collector] that only counts hits.
+#[derive(Debug, Default)]
+pub struct TotalHitCountCollector {
+    total_hits: u64,
+}
+
+impl TotalHitCountCollector {
+    /// Creates a new collector.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the number of hits collected.
+    #[inline]
+    pub fn total_hits(&self) -> u64 {
+        self.total_hits
+    }
+}
+
+impl Collector for TotalHitCountCollector {
+    fn leaf_collector(&mut self, _context: &LeafReaderContext) -> BoxResult<Box<dyn LeafCollector + '_>> {
+        Ok(Box::new(TotalHitCountLeafCollector {
+            total_hits: &mut self.total_hits,
+        }))
+    }
+
+    #[inline]
+    fn score_mode(&self) -> ScoreMode {
+        ScoreMode::CompleteNoScores
+    }
+}
+
+/// A [CollectorManager] for [TotalHitCountCollector]s, whose counts are summed.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TotalHitCountCollectorManager;
+
+impl CollectorManager for TotalHitCountCollectorManager {
+    type Collector = TotalHitCountCollector;
+    type Result = u64;
+
+    fn new_collector(&self) -> BoxResult<TotalHitCountCollector> {
+        Ok(TotalHitCountCollector::new())
+    }
+
+    fn reduce(&self, collectors: Vec<TotalHitCountCollector>) -> BoxResult<u64> {
+        Ok(collectors.iter().map(|c| c.total_hits()).sum())
+    }
+}
+
+struct TotalHitCountLeafCollector<'a> {
+    total_hits: &'a mut u64,
+}
+
+impl LeafCollector for TotalHitCountLeafCollector<'_> {
+    fn collect(&mut self, _doc: u32, _scorer: &mut dyn Scorable) -> BoxResult<()> {
+        *self.total_hits += 1;
+        Ok(())
+    }
+}
@@ -0,0 +1,80 @@
+use {
+    crate::{
+        search::{DocIdSetIterator, NO_MORE_DOCS},
+        BoxResult,
+    },
+    std::fmt::Debug,
+};
+
+/// Splits iteration over a set of documents into two phases: a cheap approximation that may return false positives,
+/// and a potentially expensive check that confirms whether the current document really matches.
+///
+/// This allows composite scorers and bulk scorers to narrow down candidates with the approximations of all their
+/// sub-iterators before paying for any confirmations.
+pub trait TwoPhaseIterator: Debug + Send {
+    /// Returns the approximation, which iterates over a superset of the matching documents.
+    fn approximation(&self) -> &dyn DocIdSetIterator;
+
+    /// Returns the approximation for advancing.
+    fn approximation_mut(&mut self) -> &mut dyn DocIdSetIterator;
+
+    /// Indicates whether the approximation's current document really matches. This may only be called once per
+    /// document.
+    fn matches(&mut self) -> BoxResult<bool>;
+
+    /// Returns an estimate of the cost of a call to [TwoPhaseIterator::matches], relative to advancing the
+    /// approximation by one document.
+    fn match_cost(&self) -> f32;
+}
+
+/// Presents a [TwoPhaseIterator] as a [DocIdSetIterator] that returns only the confirmed matches.
+#[derive(Debug)]
+pub struct TwoPhaseDocIdSetIterator {
+    two_phase: Box<dyn TwoPhaseIterator>,
+}
+
+impl TwoPhaseDocIdSetIterator {
+    /// Wraps the given two-phase iterator.
+    pub fn new(two_phase: Box<dyn TwoPhaseIterator>) -> Self {
+        Self {
+            two_phase,
+        }
+    }
+
+    /// Returns the wrapped two-phase iterator.
+    #[inline]
+    pub fn two_phase(&mut self) -> &mut dyn TwoPhaseIterator {
+        self.two_phase.as_mut()
+    }
+
+    /// Moves forward from `doc` until it is a confirmed match or [NO_MORE_DOCS].
+    fn do_next(&mut self, mut doc: u32) -> BoxResult<u32> {
+        while doc != NO_MORE_DOCS && !self.two_phase.matches()? {
+            doc = self.two_phase.approximation_mut().next_doc()?;
+        }
+
+        Ok(doc)
+    }
+}
+
+impl DocIdSetIterator for TwoPhaseDocIdSetIterator {
+    #[inline]
+    fn doc_id(&self) -> u32 {
+        self.two_phase.approximation().doc_id()
+    }
+
+    fn next_doc(&mut self) -> BoxResult<u32> {
+        let doc = self.two_phase.approximation_mut().next_doc()?;
+        self.do_next(doc)
+    }
+
+    fn advance(&mut self, target: u32) -> BoxResult<u32> {
+        let doc = self.two_phase.approximation_mut().advance(target)?;
+        self.do_next(doc)
+    }
+
+    #[inline]
+    fn cost(&self) -> u64 {
+        self.two_phase.approximation().cost()
+    }
+}
@@ -0,0 +1,47 @@
+use {
+    crate::{
+        index::LeafReaderContext,
+        search::{BulkScorer, DefaultBulkScorer, EagerScorerSupplier, Explanation, Scorer, ScorerSupplier},
+        BoxResult,
+    },
+    std::fmt::Debug,
+};
+
+/// The internal representation of a query that has been prepared for a particular searcher.
+///
+/// A weight holds the searcher-wide state of a query (such as term statistics) and creates a [Scorer] for each
+/// segment.
+pub trait Weight: Debug + Send + Sync {
+    /// Returns a scorer for the documents matching the query in the given segment, or `None` if no documents can
+    /// match.
+    fn scorer(&self, context: &LeafReaderContext) -> BoxResult<Option<Box<dyn Scorer>>>;
+
+    /// Returns a [ScorerSupplier] for the given segment, or `None` if no documents can match. Callers that intersect
+    /// this weight with others use it to tell the weight how its scorer will be iterated before it is created.
+    ///
+    /// By default this creates the scorer right away; weights with more than one way of matching documents override
+    /// it to defer the choice to [ScorerSupplier::get].
+    fn scorer_supplier(&self, context: &LeafReaderContext) -> BoxResult<Option<Box<dyn ScorerSupplier + '_>>> {
+        Ok(self
+            .scorer(context)?
+            .map(|scorer| Box::new(EagerScorerSupplier::new(scorer)) as Box<dyn ScorerSupplier + '_>))
+    }
+
+    /// Returns a [BulkScorer] that scores all of the matching documents in the given segment at once, or `None` if no
+    /// documents can match. By default this drives the [Scorer] with a [DefaultBulkScorer].
+    fn bulk_scorer(&self, context: &LeafReaderContext) -> BoxResult<Option<Box<dyn BulkScorer>>> {
+        Ok(self.scorer(context)?.map(|scorer| Box::new(DefaultBulkScorer::new(scorer)) as Box<dyn BulkScorer>))
+    }
+
+    /// Explains how the score of the given segment-local document was computed, or why it doesn't match.
+    fn explain(&self, context: &LeafReaderContext, doc: u32) -> BoxResult<Explanation>;
+
+    /// Returns a scorer positioned on the given segment-local document, or `None` if the document doesn't match.
+    fn scorer_at(&self, context: &LeafReaderContext, doc: u32) -> BoxResult<Option<Box<dyn Scorer>>> {
+        let Some(mut scorer) = self.scorer(context)? else {
+            return Ok(None);
+        };
+
+        Ok((scorer.advance(doc)? == doc).then_some(scorer))
+    }
+}
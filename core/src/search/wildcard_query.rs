@@ -0,0 +1,205 @@
+use {
+    crate::{
+        index::Term,
+        search::{AutomatonQuery, IndexSearcher, Query, RewriteMethod, ScoreMode, Weight},
+        util::automaton::{
+            concatenate_all, make_any_char, make_any_string, make_char, make_char_case_insensitive, Automaton,
+            CompiledAutomaton, DEFAULT_DETERMINIZE_WORK_LIMIT,
+        },
+        BoxResult, LuceneError,
+    },
+    std::{
+        fmt::{Display, Formatter, Result as FmtResult},
+        sync::Arc,
+    },
+};
+
+/// A query matching the documents containing a term that matches a wildcard pattern, such as `l?c*e`.
+///
+/// In the pattern, `*` matches any sequence of characters, including none, and `?` matches any one character. A
+/// backslash escapes the character after it. A pattern starting with a wildcard has to check every term of the field;
+/// the query's [AutomatonQuery::max_expansions] bounds how many it may match.
+///
+/// A case-insensitive query (see [WildcardQuery::new_case_insensitive]) matches the characters of the pattern in
+/// any case, by their Unicode simple case folding, so terms don't have to be lowercased when indexed.
+#[derive(Clone, Debug)]
+pub struct WildcardQuery {
+    pattern: Term,
+    case_insensitive: bool,
+    query: AutomatonQuery,
+}
+
+impl WildcardQuery {
+    /// The wildcard matching any sequence of characters.
+    pub const WILDCARD_STRING: char = '*';
+
+    /// The wildcard matching one character.
+    pub const WILDCARD_CHAR: char = '?';
+
+    /// The character escaping the character after it.
+    pub const WILDCARD_ESCAPE: char = '\\';
+
+    /// Creates a query for the terms matching `pattern`, in its field. This fails with [LuceneError::InvalidArgument]
+    /// if the pattern isn't UTF-8, and with [LuceneError::TooComplexToDeterminize] if its automaton is too complex.
+    pub fn new(pattern: Term) -> BoxResult<Self> {
+        Self::with_case_sensitivity(pattern, false)
+    }
+
+    /// Creates a query for the terms matching `pattern` in any case. This fails as [WildcardQuery::new] does.
+    pub fn new_case_insensitive(pattern: Term) -> BoxResult<Self> {
+        Self::with_case_sensitivity(pattern, true)
+    }
+
+    fn with_case_sensitivity(pattern: Term, case_insensitive: bool) -> BoxResult<Self> {
+        let Some(text) = pattern.text() else {
+            return Err(LuceneError::InvalidArgument(format!("wildcard pattern {pattern} isn't UTF-8")).into());
+        };
+
+        let automaton = Self::to_automaton(text, case_insensitive);
+        let automaton = CompiledAutomaton::from_automaton(&automaton, DEFAULT_DETERMINIZE_WORK_LIMIT)?;
+        let description = pattern.to_string();
+        Ok(Self {
+            query: AutomatonQuery::with_description(pattern.clone(), automaton, description),
+            pattern,
+            case_insensitive,
+        })
+    }
+
+    /// Converts a wildcard pattern to an automaton over code points, which matches the pattern's characters in any
+    /// case if `case_insensitive` is set.
+    pub fn to_automaton(pattern: &str, case_insensitive: bool) -> Automaton {
+        let make_char = |c: char| match case_insensitive {
+            true => make_char_case_insensitive(c as u32),
+            false => make_char(c as u32),
+        };
+
+        let mut parts = Vec::new();
+        let mut chars = pattern.chars();
+        while let Some(c) = chars.next() {
+            parts.push(match c {
+                Self::WILDCARD_STRING => make_any_string(),
+                Self::WILDCARD_CHAR => make_any_char(),
+                // A trailing escape matches itself.
+                Self::WILDCARD_ESCAPE => make_char(chars.next().unwrap_or(c)),
+                c => make_char(c),
+            });
+        }
+
+        concatenate_all(&parts.iter().collect::<Vec<_>>())
+    }
+
+    /// Returns the wildcard pattern.
+    #[inline]
+    pub fn pattern(&self) -> &Term {
+        &self.pattern
+    }
+
+    /// Indicates whether the pattern matches terms in any case.
+    #[inline]
+    pub fn is_case_insensitive(&self) -> bool {
+        self.case_insensitive
+    }
+
+    /// Returns the query over the pattern's automaton.
+    #[inline]
+    pub fn automaton_query(&self) -> &AutomatonQuery {
+        &self.query
+    }
+
+    /// Sets how the query is executed; see [AutomatonQuery::set_rewrite_method].
+    pub fn set_rewrite_method(&mut self, rewrite_method: RewriteMethod) -> &mut Self {
+        self.query.set_rewrite_method(rewrite_method);
+        self
+    }
+
+    /// Sets the most terms the query expands to; see [AutomatonQuery::set_max_expansions].
+    pub fn set_max_expansions(&mut self, max_expansions: usize) -> &mut Self {
+        self.query.set_max_expansions(max_expansions);
+        self
+    }
+}
+
+impl Query for WildcardQuery {
+    fn create_weight(&self, searcher: &IndexSearcher, score_mode: ScoreMode, boost: f32) -> BoxResult<Box<dyn Weight>> {
+        self.query.create_weight(searcher, score_mode, boost)
+    }
+
+    fn rewrite(&self, searcher: &IndexSearcher) -> BoxResult<Option<Arc<dyn Query>>> {
+        self.query.rewrite(searcher)
+    }
+}
+
+impl Display for WildcardQuery {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        Display::fmt(&self.query, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{
+            document::{Document, Field, Store},
+            index::Term,
+            search::{test_util::searcher, WildcardQuery},
+            util::automaton::run,
+        },
+        pretty_assertions::assert_eq,
+    };
+
+    #[test]
+    fn test_to_automaton() {
+        let automaton = WildcardQuery::to_automaton("l?c*e", false);
+        assert!(run(&automaton, "lucene"));
+        assert!(run(&automaton, "lace"));
+        assert!(!run(&automaton, "lce"));
+        assert!(!run(&automaton, "lucent"));
+
+        let escaped = WildcardQuery::to_automaton(r"a\*b\", false);
+        assert!(run(&escaped, r"a*b\"));
+        assert!(!run(&escaped, r"axb\"));
+
+        let automaton = WildcardQuery::to_automaton("Ö?c*", true);
+        assert!(run(&automaton, "öLCE"));
+        assert!(run(&automaton, "ÖXc"));
+        assert!(!run(&automaton, "oxc"));
+    }
+
+    #[test]
+    fn test_wildcard_query() {
+        let mut documents = Vec::new();
+        for (id, body) in ["AB-1", "ab-2", "Ac-3", "xAB-4", "b-5"].into_iter().zip([
+            "lucene",
+            "lucid dreams",
+            "luck",
+            "lunar",
+            "glucose",
+        ]) {
+            let mut doc = Document::new();
+            doc.add(Field::string("id", id, Store::No));
+            doc.add(Field::text("body", body, Store::No));
+            documents.push(doc);
+        }
+        let searcher = searcher([documents]);
+
+        let query = WildcardQuery::new(Term::from_text("body", "lu?*")).unwrap();
+        assert_eq!(query.to_string(), "body:lu?*");
+        assert_eq!(searcher.count(&query).unwrap(), 4);
+        assert_eq!(searcher.count(&WildcardQuery::new(Term::from_text("body", "*uc*")).unwrap()).unwrap(), 4);
+        assert_eq!(searcher.count(&WildcardQuery::new(Term::from_text("body", "luc?")).unwrap()).unwrap(), 1);
+        assert_eq!(searcher.count(&WildcardQuery::new(Term::from_text("body", "*s")).unwrap()).unwrap(), 1);
+
+        // A leading wildcard can't match more terms than the budget allows.
+        let mut query = WildcardQuery::new(Term::from_text("body", "*")).unwrap();
+        query.set_max_expansions(5);
+        assert!(searcher.count(&query).is_err());
+
+        // Indexed terms keep their case here, which a case-insensitive pattern matches.
+        let query = WildcardQuery::new_case_insensitive(Term::from_text("id", "ab-*")).unwrap();
+        assert!(query.is_case_insensitive());
+        assert_eq!(searcher.count(&query).unwrap(), 2);
+        assert_eq!(searcher.count(&WildcardQuery::new(Term::from_text("id", "ab-*")).unwrap()).unwrap(), 1);
+
+        assert!(WildcardQuery::new(Term::new("body", vec![0xff])).is_err());
+    }
+}
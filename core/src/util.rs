@@ -0,0 +1,30 @@
+mod accountable;
+mod bit_set;
+mod byte_block_pool;
+mod bytes_ref_array;
+mod bytes_ref_hash;
+mod fixed_bit_set;
+mod fst;
+mod fuzzy_set;
+mod int_block_pool;
+mod long_bit_set;
+mod offline_sorter;
+mod ram_usage_estimator;
+mod small_float;
+mod sparse_fixed_bit_set;
+mod sparse_long_set;
+
+/// Finite-state automata and regular expressions used for multi-term queries.
+pub mod automaton;
+
+/// Hierarchical navigable small world graphs for approximate nearest neighbor search over vectors.
+pub mod hnsw;
+
+/// Packed integer arrays and paged byte storage for large in-memory structures.
+pub mod packed;
+
+pub use {
+    accountable::*, bit_set::*, byte_block_pool::*, bytes_ref_array::*, bytes_ref_hash::*, fixed_bit_set::*, fst::*,
+    fuzzy_set::*, int_block_pool::*, long_bit_set::*, offline_sorter::*, ram_usage_estimator::*, small_float::*,
+    sparse_fixed_bit_set::*, sparse_long_set::*,
+};
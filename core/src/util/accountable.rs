@@ -0,0 +1,131 @@
+use {
+    crate::util::human_readable_units,
+    std::fmt::{Display, Formatter, Result as FmtResult},
+};
+
+/// An object whose memory use can be measured, so that applications can report and bound the memory held by readers
+/// and in-memory structures. This is Lucene's `Accountable`.
+pub trait Accountable {
+    /// Returns the memory used by the object, in bytes.
+    fn ram_bytes_used(&self) -> usize;
+
+    /// Returns a breakdown of [Accountable::ram_bytes_used] by the object's largest parts. This is empty by default.
+    fn child_resources(&self) -> Vec<NamedAccountable> {
+        Vec::new()
+    }
+}
+
+/// A named snapshot of the memory used by an [Accountable] and its children, for reporting.
+///
+/// Displaying it prints the tree of resources with their sizes:
+///
+/// ```text
+/// segment: 1.2 MB
+/// |-- postings: 1 MB
+/// |-- stored fields: 200 KB
+/// ```
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct NamedAccountable {
+    name: String,
+    ram_bytes_used: usize,
+    children: Vec<NamedAccountable>,
+}
+
+impl NamedAccountable {
+    /// Takes a snapshot of the memory used by `accountable` and its children under the given name.
+    pub fn new<A: Accountable + ?Sized>(name: impl Into<String>, accountable: &A) -> Self {
+        Self {
+            name: name.into(),
+            ram_bytes_used: accountable.ram_bytes_used(),
+            children: accountable.child_resources(),
+        }
+    }
+
+    /// Creates a resource without children that uses `ram_bytes_used` bytes.
+    pub fn from_bytes(name: impl Into<String>, ram_bytes_used: usize) -> Self {
+        Self {
+            name: name.into(),
+            ram_bytes_used,
+            children: Vec::new(),
+        }
+    }
+
+    /// Returns the name of the resource.
+    #[inline]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn fmt_tree(&self, f: &mut Formatter<'_>, depth: usize) -> FmtResult {
+        if depth > 0 {
+            write!(f, "\n{:indent$}|-- ", "", indent = 4 * (depth - 1))?;
+        }
+        write!(f, "{}: {}", self.name, human_readable_units(self.ram_bytes_used))?;
+        for child in &self.children {
+            child.fmt_tree(f, depth + 1)?;
+        }
+        Ok(())
+    }
+}
+
+impl Accountable for NamedAccountable {
+    #[inline]
+    fn ram_bytes_used(&self) -> usize {
+        self.ram_bytes_used
+    }
+
+    fn child_resources(&self) -> Vec<NamedAccountable> {
+        self.children.clone()
+    }
+}
+
+impl Display for NamedAccountable {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        self.fmt_tree(f, 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::util::{Accountable, FixedBitSet, NamedAccountable},
+        pretty_assertions::assert_eq,
+    };
+
+    #[derive(Debug)]
+    struct Pair(FixedBitSet, FixedBitSet);
+
+    impl Accountable for Pair {
+        fn ram_bytes_used(&self) -> usize {
+            self.0.ram_bytes_used() + self.1.ram_bytes_used()
+        }
+
+        fn child_resources(&self) -> Vec<NamedAccountable> {
+            vec![NamedAccountable::new("first", &self.0), NamedAccountable::new("second", &self.1)]
+        }
+    }
+
+    #[test]
+    fn test_named_accountable() {
+        let pair = Pair(FixedBitSet::new(64), FixedBitSet::new(1 << 20));
+        let named = NamedAccountable::new("pair", &pair);
+        assert_eq!(named.name(), "pair");
+        assert_eq!(named.ram_bytes_used(), pair.ram_bytes_used());
+        assert_eq!(named.child_resources().len(), 2);
+        assert_eq!(
+            named.child_resources().iter().map(Accountable::ram_bytes_used).sum::<usize>(),
+            pair.ram_bytes_used()
+        );
+
+        let nested = NamedAccountable {
+            name: "outer".to_string(),
+            ram_bytes_used: 3072,
+            children: vec![NamedAccountable {
+                name: "inner".to_string(),
+                ram_bytes_used: 2048,
+                children: vec![NamedAccountable::from_bytes("leaf", 100)],
+            }],
+        };
+        assert_eq!(nested.to_string(), "outer: 3 KB\n|-- inner: 2 KB\n    |-- leaf: 100 bytes");
+    }
+}
@@ -0,0 +1,17 @@
+mod automata;
+#[allow(clippy::module_inception)]
+mod automaton;
+mod case_folding;
+mod compiled_automaton;
+mod daciuk_mihov_automaton_builder;
+mod levenshtein_automata;
+mod minimization_operations;
+mod operations;
+mod reg_exp;
+mod run_automaton;
+mod utf32_to_utf8;
+
+pub use {
+    automata::*, automaton::*, case_folding::*, compiled_automaton::*, daciuk_mihov_automaton_builder::*,
+    levenshtein_automata::*, minimization_operations::*, operations::*, reg_exp::*, run_automaton::*, utf32_to_utf8::*,
+};
@@ -0,0 +1,341 @@
+use {
+    crate::util::automaton::{Automaton, DaciukMihovAutomatonBuilder, MAX_CODE_POINT},
+    std::collections::HashMap,
+};
+
+/// Returns a new (deterministic) automaton with the empty language.
+pub fn make_empty() -> Automaton {
+    let mut a = Automaton::new();
+    a.finish();
+    a
+}
+
+/// Returns a new (deterministic) automaton that accepts only the empty string.
+pub fn make_empty_string() -> Automaton {
+    let mut a = Automaton::new();
+    let s = a.create_state();
+    a.set_accept(s, true);
+    a.finish();
+    a
+}
+
+/// Returns a new (deterministic) automaton that accepts all strings.
+pub fn make_any_string() -> Automaton {
+    let mut a = Automaton::new();
+    let s = a.create_state();
+    a.set_accept(s, true);
+    a.add_transition_range(s, s, 0, MAX_CODE_POINT);
+    a.finish();
+    a
+}
+
+/// Returns a new (deterministic) automaton that accepts all binary terms.
+pub fn make_any_binary() -> Automaton {
+    let mut a = Automaton::new();
+    let s = a.create_state();
+    a.set_accept(s, true);
+    a.add_transition_range(s, s, 0, 0xff);
+    a.finish();
+    a
+}
+
+/// Returns a new (deterministic) automaton that accepts any single code point.
+pub fn make_any_char() -> Automaton {
+    make_char_range(0, MAX_CODE_POINT)
+}
+
+/// Returns a new (deterministic) automaton that accepts a single code point of the given value.
+pub fn make_char(c: u32) -> Automaton {
+    make_char_range(c, c)
+}
+
+/// Returns a new (deterministic) automaton that accepts a single code point whose value is in the given interval
+/// (including both end points). If `min > max`, the automaton accepts nothing.
+pub fn make_char_range(min: u32, max: u32) -> Automaton {
+    let mut a = Automaton::new();
+    if min > max {
+        a.finish();
+        return a;
+    }
+
+    let s1 = a.create_state();
+    let s2 = a.create_state();
+    a.set_accept(s2, true);
+    a.add_transition_range(s1, s2, min, max);
+    a.finish();
+    a
+}
+
+/// Returns a new (deterministic) automaton that accepts the single given string.
+pub fn make_string(s: &str) -> Automaton {
+    let code_points: Vec<u32> = s.chars().map(|c| c as u32).collect();
+    make_code_points(&code_points)
+}
+
+/// Returns a new (deterministic) automaton that accepts the single given binary term.
+pub fn make_binary(term: &[u8]) -> Automaton {
+    let labels: Vec<u32> = term.iter().map(|&b| b as u32).collect();
+    make_code_points(&labels)
+}
+
+/// Returns a new (deterministic) automaton that accepts the single given sequence of labels.
+pub fn make_code_points(labels: &[u32]) -> Automaton {
+    let mut a = Automaton::new();
+    let mut last = a.create_state();
+    for &label in labels {
+        let state = a.create_state();
+        a.add_transition(last, state, label);
+        last = state;
+    }
+    a.set_accept(last, true);
+    a.finish();
+    a
+}
+
+/// Returns a new automaton that accepts strings representing decimal (base 10) non-negative integers in the
+/// interval `[min, max]`.
+///
+/// If `digits` is greater than zero, only strings of exactly that many digits (zero-padded if necessary) are
+/// accepted. Otherwise, any number of leading zeros is allowed.
+///
+/// Returns `None` if `min > max`, or if `digits` is non-zero and too small to represent `max`.
+pub fn make_decimal_interval(min: u64, max: u64, digits: usize) -> Option<Automaton> {
+    if min > max {
+        return None;
+    }
+
+    let max_str = max.to_string();
+    if digits > 0 {
+        if max_str.len() > digits {
+            return None;
+        }
+
+        let lo = format!("{min:0digits$}");
+        let hi = format!("{max:0digits$}");
+        let mut a = Automaton::new();
+        let start = a.create_state();
+        let end = a.create_state();
+        a.set_accept(end, true);
+        add_digit_range(&mut a, start, end, lo.as_bytes(), hi.as_bytes());
+        a.finish();
+        return Some(a);
+    }
+
+    // Variable width: allow any number of leading zeros, then a number with no leading zero (or zero itself).
+    let mut a = Automaton::new();
+    let start = a.create_state();
+    let body = a.create_state();
+    let end = a.create_state();
+    a.set_accept(end, true);
+    a.add_transition(start, start, b'0' as u32);
+    let min_str = min.to_string();
+
+    for len in min_str.len()..=max_str.len() {
+        let len_min = if len == 1 {
+            0
+        } else {
+            10u64.pow(len as u32 - 1)
+        };
+        let len_max = if len >= 20 {
+            u64::MAX
+        } else {
+            10u64.pow(len as u32) - 1
+        };
+        let lo = min.max(len_min);
+        let hi = max.min(len_max);
+        if lo > hi {
+            continue;
+        }
+
+        let lo = lo.to_string();
+        let hi = hi.to_string();
+        let s = a.create_state();
+        add_digit_range(&mut a, s, end, lo.as_bytes(), hi.as_bytes());
+        a.add_epsilon(body, s);
+    }
+
+    // The zero-prefix loop feeds into the numbers; if zero itself is in range, a string of only zeros is accepted.
+    a.add_epsilon(start, body);
+    if min == 0 {
+        a.set_accept(start, true);
+    }
+    a.finish();
+    Some(crate::util::automaton::remove_dead_states(&a))
+}
+
+/// Adds paths from `from` to `to` accepting all equal-length digit strings between `lo` and `hi` (inclusive).
+fn add_digit_range(a: &mut Automaton, from: usize, to: usize, lo: &[u8], hi: &[u8]) {
+    debug_assert_eq!(lo.len(), hi.len());
+    if lo.is_empty() {
+        // Only reachable when both are empty; connect directly.
+        a.add_epsilon(from, to);
+        return;
+    }
+
+    let lo0 = lo[0] as u32;
+    let hi0 = hi[0] as u32;
+    let rest = lo.len() - 1;
+
+    if rest == 0 {
+        a.add_transition_range(from, to, lo0, hi0);
+        return;
+    }
+
+    if lo0 == hi0 {
+        let next = a.create_state();
+        a.add_transition(from, next, lo0);
+        add_digit_range(a, next, to, &lo[1..], &hi[1..]);
+        return;
+    }
+
+    let nines = vec![b'9'; rest];
+    let zeros = vec![b'0'; rest];
+
+    // First digit equal to lo's first digit: remainder must be >= lo's remainder.
+    let s_lo = a.create_state();
+    a.add_transition(from, s_lo, lo0);
+    add_digit_range(a, s_lo, to, &lo[1..], &nines);
+
+    // First digit strictly between: any remainder.
+    if hi0 - lo0 > 1 {
+        let s_mid = a.create_state();
+        a.add_transition_range(from, s_mid, lo0 + 1, hi0 - 1);
+        add_digit_range(a, s_mid, to, &zeros, &nines);
+    }
+
+    // First digit equal to hi's first digit: remainder must be <= hi's remainder.
+    let s_hi = a.create_state();
+    a.add_transition(from, s_hi, hi0);
+    add_digit_range(a, s_hi, to, &zeros, &hi[1..]);
+}
+
+/// Returns a new (deterministic) automaton that accepts the binary terms from `min` to `max` in byte order, both
+/// included. If `min > max`, the automaton accepts nothing.
+pub fn make_binary_interval(min: &[u8], max: &[u8]) -> Automaton {
+    let mut a = Automaton::new();
+    if min > max {
+        a.finish();
+        return a;
+    }
+
+    // A state is the number of bytes read, and whether they're a prefix of `min` and of `max`: while they are, the
+    // next byte is bounded by the bound's next byte. Once they're a prefix of neither, any suffix matches.
+    let mut states = HashMap::new();
+    let mut pending = vec![(0, true, true)];
+    let start = a.create_state();
+    states.insert((0, true, true), start);
+    let free = a.create_state();
+    a.set_accept(free, true);
+    a.add_transition_range(free, free, 0, 0xff);
+    states.insert((0, false, false), free);
+
+    while let Some((depth, on_min, on_max)) = pending.pop() {
+        let state = states[&(depth, on_min, on_max)];
+        // A proper prefix of `min` sorts before it.
+        let on_min = on_min && depth < min.len();
+        a.set_accept(state, !on_min);
+        if on_max && depth == max.len() {
+            continue;
+        }
+
+        let low = if on_min {
+            min[depth] as u32
+        } else {
+            0
+        };
+        let high = if on_max {
+            max[depth] as u32
+        } else {
+            0xff
+        };
+        let mut ranges = vec![(low, low)];
+        if high > low {
+            ranges.push((high, high));
+        }
+        if high > low + 1 {
+            ranges.push((low + 1, high - 1));
+        }
+
+        for (from, to) in ranges {
+            let key = match (on_min && from == low, on_max && to == high) {
+                (false, false) => (0, false, false),
+                (next_on_min, next_on_max) => (depth + 1, next_on_min, next_on_max),
+            };
+            let next = *states.entry(key).or_insert_with(|| {
+                pending.push(key);
+                a.create_state()
+            });
+            a.add_transition_range(state, next, from, to);
+        }
+    }
+
+    a.finish();
+    a
+}
+
+/// Returns a new (deterministic, minimal) automaton that accepts any of the given strings, in any order.
+pub fn make_string_union<S: AsRef<str>>(strings: &[S]) -> Automaton {
+    let mut sorted: Vec<&str> = strings.iter().map(|s| s.as_ref()).collect();
+    sorted.sort_unstable();
+    DaciukMihovAutomatonBuilder::build(sorted).expect("terms are sorted")
+}
+
+/// Returns a new (deterministic, minimal) automaton that accepts any of the given binary terms, in any order.
+pub fn make_binary_string_union<B: AsRef<[u8]>>(terms: &[B]) -> Automaton {
+    let mut sorted: Vec<&[u8]> = terms.iter().map(|t| t.as_ref()).collect();
+    sorted.sort_unstable();
+    DaciukMihovAutomatonBuilder::build_binary(sorted).expect("terms are sorted")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::util::automaton::{make_binary_interval, make_decimal_interval, run, run_labels};
+
+    #[test]
+    fn test_decimal_interval() {
+        let a = make_decimal_interval(7, 123, 0).unwrap();
+        for accepted in ["7", "8", "10", "99", "100", "119", "123", "007", "0123"] {
+            assert!(run(&a, accepted), "{accepted} should be accepted");
+        }
+        for rejected in ["", "0", "6", "124", "200", "1000", "12a"] {
+            assert!(!run(&a, rejected), "{rejected} should be rejected");
+        }
+
+        let a = make_decimal_interval(5, 42, 3).unwrap();
+        assert!(run(&a, "005"));
+        assert!(run(&a, "042"));
+        assert!(!run(&a, "42"));
+        assert!(!run(&a, "043"));
+
+        let a = make_decimal_interval(0, 3, 0).unwrap();
+        assert!(run(&a, "0"));
+        assert!(run(&a, "000"));
+        assert!(run(&a, "03"));
+        assert!(!run(&a, "4"));
+
+        assert!(make_decimal_interval(5, 1000, 3).is_none());
+    }
+
+    #[test]
+    fn test_binary_interval() {
+        let bytes = |term: &[u8]| term.iter().map(|&b| b as u32).collect::<Vec<_>>();
+        let a = make_binary_interval(b"ab", b"ad\xff");
+        for accepted in [&b"ab"[..], b"ab\x00", b"abzz", b"ac", b"ad", b"ad\x00", b"ad\xff"] {
+            assert!(run_labels(&a, &bytes(accepted)), "{accepted:?} should be accepted");
+        }
+        for rejected in [&b""[..], b"a", b"aa\xff", b"ad\xff\x00", b"ae", b"b"] {
+            assert!(!run_labels(&a, &bytes(rejected)), "{rejected:?} should be rejected");
+        }
+
+        // Fixed-length bounds, as for encoded numbers.
+        let a = make_binary_interval(&[1, 0xf0], &[2, 0x10]);
+        assert!(run_labels(&a, &[1, 0xf0]));
+        assert!(run_labels(&a, &[1, 0xff]));
+        assert!(run_labels(&a, &[2, 0x00]));
+        assert!(!run_labels(&a, &[1, 0xef]));
+        assert!(!run_labels(&a, &[2, 0x11]));
+
+        assert!(run_labels(&make_binary_interval(b"", b""), &[]));
+        assert!(!run(&make_binary_interval(b"b", b"a"), "a"));
+    }
+}
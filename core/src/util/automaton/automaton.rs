@@ -0,0 +1,274 @@
+use {
+    crate::util::{size_of_vec, Accountable},
+    std::fmt::{Debug, Formatter, Result as FmtResult, Write},
+};
+
+/// The maximum Unicode code point, which is the largest label used by character automata.
+pub const MAX_CODE_POINT: u32 = 0x10ffff;
+
+/// The largest label used by binary (byte-labeled) automata.
+pub const MAX_BYTE: u32 = 0xff;
+
+/// A transition from one state to another on any label between `min` and `max` (inclusive).
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Transition {
+    /// The minimum label (code point or byte) accepted by this transition.
+    pub min: u32,
+
+    /// The maximum label (code point or byte) accepted by this transition.
+    pub max: u32,
+
+    /// The destination state.
+    pub dest: usize,
+}
+
+/// Represents an automaton and all its states and transitions.
+///
+/// States are numbered sequentially and must be created using [Automaton::create_state]. State 0 is always the
+/// initial state; an automaton with no states accepts nothing. Transitions are labeled with ranges of Unicode code
+/// points (or bytes, for binary automata).
+///
+/// Transitions may be added in any order. Once building is complete, call [Automaton::finish] to sort the
+/// transitions of every state (by min, then max, then dest), merge transitions with adjacent labels going to the
+/// same state, and compute whether the automaton is deterministic.
+#[derive(Clone, Default)]
+pub struct Automaton {
+    transitions: Vec<Vec<Transition>>,
+    accept: Vec<bool>,
+    deterministic: bool,
+}
+
+impl Automaton {
+    /// Creates a new, empty automaton. It has no states and accepts nothing.
+    pub fn new() -> Self {
+        Self {
+            transitions: Vec::new(),
+            accept: Vec::new(),
+            deterministic: true,
+        }
+    }
+
+    /// Creates a new state and returns its number.
+    pub fn create_state(&mut self) -> usize {
+        self.transitions.push(Vec::new());
+        self.accept.push(false);
+        self.transitions.len() - 1
+    }
+
+    /// Returns the number of states in this automaton.
+    #[inline]
+    pub fn num_states(&self) -> usize {
+        self.transitions.len()
+    }
+
+    /// Returns the total number of transitions in this automaton.
+    pub fn num_transitions(&self) -> usize {
+        self.transitions.iter().map(Vec::len).sum()
+    }
+
+    /// Marks or unmarks the given state as an accept state.
+    #[inline]
+    pub fn set_accept(&mut self, state: usize, accept: bool) {
+        self.accept[state] = accept;
+    }
+
+    /// Indicates whether the given state is an accept state.
+    #[inline]
+    pub fn is_accept(&self, state: usize) -> bool {
+        self.accept[state]
+    }
+
+    /// Returns the numbers of all accept states.
+    pub fn accept_states(&self) -> impl Iterator<Item = usize> + '_ {
+        self.accept.iter().enumerate().filter_map(|(state, &accept)| accept.then_some(state))
+    }
+
+    /// Adds a transition from `source` to `dest` on the single label `label`.
+    #[inline]
+    pub fn add_transition(&mut self, source: usize, dest: usize, label: u32) {
+        self.add_transition_range(source, dest, label, label);
+    }
+
+    /// Adds a transition from `source` to `dest` on any label between `min` and `max` (inclusive).
+    pub fn add_transition_range(&mut self, source: usize, dest: usize, min: u32, max: u32) {
+        assert!(dest < self.num_states(), "dest state {dest} does not exist");
+        assert!(min <= max, "min label {min} is greater than max label {max}");
+        self.transitions[source].push(Transition {
+            min,
+            max,
+            dest,
+        });
+        self.deterministic = false;
+    }
+
+    /// Adds a (virtual) epsilon transition between `source` and `dest`. This copies the transitions currently
+    /// leaving `dest` to `source`, and makes `source` an accept state if `dest` is one. Consequently, `dest` must
+    /// already have all of its transitions added.
+    pub fn add_epsilon(&mut self, source: usize, dest: usize) {
+        let copied = self.transitions[dest].clone();
+        self.transitions[source].extend(copied);
+        if self.accept[dest] {
+            self.accept[source] = true;
+        }
+        self.deterministic = false;
+    }
+
+    /// Copies all states and transitions from `other` into this automaton, renumbering them by appending them
+    /// after the existing states. Returns the offset added to `other`'s state numbers.
+    pub fn copy_from(&mut self, other: &Automaton) -> usize {
+        let offset = self.num_states();
+        for (transitions, &accept) in other.transitions.iter().zip(other.accept.iter()) {
+            self.transitions.push(
+                transitions
+                    .iter()
+                    .map(|t| Transition {
+                        dest: t.dest + offset,
+                        ..*t
+                    })
+                    .collect(),
+            );
+            self.accept.push(accept);
+        }
+        self.deterministic = false;
+        offset
+    }
+
+    /// Sorts and reduces the transitions of every state and recomputes whether the automaton is deterministic.
+    /// This must be called after building an automaton with [Automaton::add_transition] and friends.
+    pub fn finish(&mut self) {
+        let mut deterministic = true;
+
+        for transitions in self.transitions.iter_mut() {
+            // Sort by dest first so transitions to the same state with adjacent or overlapping labels can be merged.
+            transitions.sort_by_key(|t| (t.dest, t.min, t.max));
+            let mut merged: Vec<Transition> = Vec::with_capacity(transitions.len());
+            for t in transitions.iter() {
+                match merged.last_mut() {
+                    Some(last) if last.dest == t.dest && t.min <= last.max.saturating_add(1) => {
+                        last.max = last.max.max(t.max);
+                    }
+                    _ => merged.push(*t),
+                }
+            }
+
+            merged.sort_by_key(|t| (t.min, t.max, t.dest));
+            for pair in merged.windows(2) {
+                if pair[1].min <= pair[0].max {
+                    deterministic = false;
+                }
+            }
+
+            *transitions = merged;
+        }
+
+        self.deterministic = deterministic;
+    }
+
+    /// Returns true if no state has two transitions leaving with the same label. This is only accurate after
+    /// [Automaton::finish] has been called.
+    #[inline]
+    pub fn is_deterministic(&self) -> bool {
+        self.deterministic
+    }
+
+    /// Returns the transitions leaving the given state. After [Automaton::finish], these are sorted by min, then
+    /// max, then dest.
+    #[inline]
+    pub fn transitions(&self, state: usize) -> &[Transition] {
+        &self.transitions[state]
+    }
+
+    /// Returns the state reached from `state` by following `label`, or `None` if there is no such transition. The
+    /// automaton must be deterministic.
+    pub fn step(&self, state: usize, label: u32) -> Option<usize> {
+        let transitions = &self.transitions[state];
+        let idx = transitions.partition_point(|t| t.max < label);
+        match transitions.get(idx) {
+            Some(t) if t.min <= label => Some(t.dest),
+            _ => None,
+        }
+    }
+
+    /// Returns the sorted set of labels at which the outgoing transitions of any state change. This partitions the
+    /// label space into intervals on which every state behaves uniformly.
+    pub fn start_points(&self) -> Vec<u32> {
+        let mut points = vec![0];
+        for transitions in self.transitions.iter() {
+            for t in transitions.iter() {
+                points.push(t.min);
+                if t.max < u32::MAX {
+                    points.push(t.max + 1);
+                }
+            }
+        }
+        points.sort_unstable();
+        points.dedup();
+        points
+    }
+
+    /// Formats this automaton in the Graphviz dot language, for debugging.
+    pub fn to_dot(&self) -> String {
+        let mut s = String::from("digraph Automaton {\n  rankdir = LR\n");
+        for state in 0..self.num_states() {
+            let shape = if self.accept[state] {
+                "doublecircle"
+            } else {
+                "circle"
+            };
+            let _ = writeln!(s, "  {state} [shape={shape},label=\"{state}\"]");
+            for t in self.transitions[state].iter() {
+                let _ = writeln!(s, "  {state} -> {} [label=\"{}\"]", t.dest, label_range(t.min, t.max));
+            }
+        }
+        s.push('}');
+        s
+    }
+}
+
+impl Accountable for Automaton {
+    fn ram_bytes_used(&self) -> usize {
+        size_of::<Self>()
+            + size_of_vec(&self.transitions)
+            + self.transitions.iter().map(size_of_vec).sum::<usize>()
+            + size_of_vec(&self.accept)
+    }
+}
+
+impl Debug for Automaton {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "Automaton(states={}, transitions=[", self.num_states())?;
+        for (state, transitions) in self.transitions.iter().enumerate() {
+            if state > 0 {
+                f.write_str(", ")?;
+            }
+            write!(
+                f,
+                "{state}{}:",
+                if self.accept[state] {
+                    "*"
+                } else {
+                    ""
+                }
+            )?;
+            for t in transitions.iter() {
+                write!(f, " {}->{}", label_range(t.min, t.max), t.dest)?;
+            }
+        }
+        f.write_str("])")
+    }
+}
+
+fn label_range(min: u32, max: u32) -> String {
+    fn label(c: u32) -> String {
+        match char::from_u32(c) {
+            Some(ch) if ch.is_ascii_graphic() => ch.to_string(),
+            _ => format!("\\u{c:x}"),
+        }
+    }
+
+    if min == max {
+        label(min)
+    } else {
+        format!("{}-{}", label(min), label(max))
+    }
+}
@@ -0,0 +1,108 @@
+use {
+    crate::util::automaton::{concatenate_all, make_char, make_code_points, Automaton, MAX_CODE_POINT},
+    std::{collections::HashMap, sync::OnceLock},
+};
+
+/// The code points equal to each other when case is ignored, by their simple case fold, for every code point with
+/// another case.
+static CASE_CLASSES: OnceLock<HashMap<u32, Vec<u32>>> = OnceLock::new();
+
+/// Returns the simple case fold of a code point: the single code point it maps to when case is ignored. Code points
+/// whose case mapping isn't a single code point fold to themselves.
+pub fn simple_case_fold(code_point: u32) -> u32 {
+    let Some(c) = char::from_u32(code_point) else {
+        return code_point;
+    };
+
+    let upper = single(c.to_uppercase()).unwrap_or(c);
+    single(upper.to_lowercase()).unwrap_or(upper) as u32
+}
+
+/// Returns the code points that match `code_point` when case is ignored, including itself, in ascending order.
+///
+/// These are the code points with the same simple case fold, so besides the upper and lower case of a letter, this
+/// finds variants such as the Kelvin sign for `k` and final sigma for `σ`.
+pub fn case_insensitive_alternatives(code_point: u32) -> Vec<u32> {
+    let classes = CASE_CLASSES.get_or_init(|| {
+        let mut classes: HashMap<u32, Vec<u32>> = HashMap::new();
+        for c in (0..=MAX_CODE_POINT).filter_map(char::from_u32) {
+            let folded = simple_case_fold(c as u32);
+            if folded != c as u32 {
+                classes.entry(folded).or_insert_with(|| vec![folded]).push(c as u32);
+            }
+        }
+        for class in classes.values_mut() {
+            class.sort_unstable();
+        }
+        classes
+    });
+
+    classes.get(&simple_case_fold(code_point)).cloned().unwrap_or_else(|| vec![code_point])
+}
+
+/// Returns a new (deterministic) automaton that accepts any code point matching `code_point` when case is ignored.
+pub fn make_char_case_insensitive(code_point: u32) -> Automaton {
+    let alternatives = case_insensitive_alternatives(code_point);
+    if alternatives.len() == 1 {
+        return make_char(code_point);
+    }
+
+    let mut a = Automaton::new();
+    let start = a.create_state();
+    let end = a.create_state();
+    a.set_accept(end, true);
+    for alternative in alternatives {
+        a.add_transition_range(start, end, alternative, alternative);
+    }
+    a.finish();
+    a
+}
+
+/// Returns a new automaton that accepts the given string when case is ignored.
+pub fn make_string_case_insensitive(s: &str) -> Automaton {
+    if s.chars().all(|c| case_insensitive_alternatives(c as u32).len() == 1) {
+        return make_code_points(&s.chars().map(|c| c as u32).collect::<Vec<_>>());
+    }
+
+    let chars: Vec<Automaton> = s.chars().map(|c| make_char_case_insensitive(c as u32)).collect();
+    concatenate_all(&chars.iter().collect::<Vec<_>>())
+}
+
+/// Returns the only item of an iterator, or `None` if it has none or several.
+fn single<I: Iterator<Item = char>>(mut chars: I) -> Option<char> {
+    let first = chars.next()?;
+    chars.next().is_none().then_some(first)
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::util::automaton::{
+            case_insensitive_alternatives, make_char_case_insensitive, make_string_case_insensitive, run,
+            simple_case_fold,
+        },
+        pretty_assertions::assert_eq,
+    };
+
+    #[test]
+    fn test_case_folding() {
+        assert_eq!(simple_case_fold('A' as u32), 'a' as u32);
+        assert_eq!(simple_case_fold('ς' as u32), 'σ' as u32);
+        assert_eq!(simple_case_fold('1' as u32), '1' as u32);
+
+        assert_eq!(case_insensitive_alternatives('a' as u32), vec!['A' as u32, 'a' as u32]);
+        assert_eq!(case_insensitive_alternatives('K' as u32), vec!['K' as u32, 'k' as u32, '\u{212a}' as u32]);
+        assert_eq!(case_insensitive_alternatives('Σ' as u32), vec!['Σ' as u32, 'ς' as u32, 'σ' as u32]);
+        assert_eq!(case_insensitive_alternatives('-' as u32), vec!['-' as u32]);
+
+        let a = make_char_case_insensitive('é' as u32);
+        assert!(run(&a, "É"));
+        assert!(run(&a, "é"));
+        assert!(!run(&a, "e"));
+
+        let a = make_string_case_insensitive("Straße");
+        assert!(run(&a, "STRAẞE"));
+        assert!(!run(&a, "strasse"));
+        assert!(run(&make_string_case_insensitive("123"), "123"));
+    }
+}
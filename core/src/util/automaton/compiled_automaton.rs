@@ -0,0 +1,252 @@
+use {
+    crate::{
+        index::{EmptyTermsEnum, SingleTermsEnum, Terms, TermsEnum},
+        util::{
+            automaton::{
+                determinize, get_common_suffix, get_singleton, is_empty, is_finite, is_total, minimize,
+                remove_dead_states, utf32_to_utf8, Automaton, ByteRunAutomaton, MAX_BYTE, MAX_CODE_POINT,
+            },
+            size_of_vec, Accountable, NamedAccountable,
+        },
+        BoxResult,
+    },
+    std::sync::Arc,
+};
+
+/// The kind of language a [CompiledAutomaton] accepts. Special cases are executed without an automaton.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum AutomatonType {
+    /// The automaton accepts nothing.
+    None,
+
+    /// The automaton accepts every term.
+    All,
+
+    /// The automaton accepts exactly one term.
+    Single,
+
+    /// The general case: terms are matched by intersecting the automaton with the terms dictionary.
+    Normal,
+}
+
+/// An automaton prepared for efficient execution against a terms dictionary.
+///
+/// The automaton is converted to operate on UTF-8 bytes (unless it is binary already), determinized, and compiled
+/// into a [ByteRunAutomaton]. The common cases of accepting nothing, everything, or a single term are detected and
+/// handled specially. Cloning is cheap; the compiled data is shared.
+#[derive(Clone, Debug)]
+pub struct CompiledAutomaton {
+    automaton_type: AutomatonType,
+    term: Option<Vec<u8>>,
+    automaton: Option<Arc<Automaton>>,
+    run_automaton: Option<Arc<ByteRunAutomaton>>,
+    common_suffix: Option<Vec<u8>>,
+    finite: bool,
+}
+
+impl CompiledAutomaton {
+    /// Compiles the given automaton.
+    ///
+    /// # Parameters
+    /// * `automaton`: The automaton to compile. Its labels are code points, unless `is_binary` is true, in which case
+    ///   they are bytes.
+    /// * `finite`: Whether the language is known to be finite, or `None` to compute it.
+    /// * `simplify`: Whether to detect the special cases described by [AutomatonType].
+    /// * `work_limit`: The maximum effort to spend determinizing.
+    /// * `is_binary`: Whether the automaton's labels are bytes rather than code points.
+    pub fn new(
+        automaton: &Automaton,
+        finite: Option<bool>,
+        simplify: bool,
+        work_limit: usize,
+        is_binary: bool,
+    ) -> BoxResult<Self> {
+        if simplify {
+            if is_empty(automaton) {
+                return Ok(Self::special(AutomatonType::None, None));
+            }
+
+            let minimal = minimize(automaton, work_limit)?;
+            let max_label = if is_binary {
+                MAX_BYTE
+            } else {
+                MAX_CODE_POINT
+            };
+            if is_total(&minimal, 0, max_label) {
+                return Ok(Self::special(AutomatonType::All, None));
+            }
+
+            if let Some(singleton) = get_singleton(&minimal) {
+                let term = if is_binary {
+                    singleton.into_iter().map(|b| b as u8).collect()
+                } else {
+                    singleton.into_iter().filter_map(char::from_u32).collect::<String>().into_bytes()
+                };
+                return Ok(Self::special(AutomatonType::Single, Some(term)));
+            }
+        }
+
+        let binary = if is_binary {
+            automaton.clone()
+        } else {
+            utf32_to_utf8(automaton)
+        };
+        let binary = remove_dead_states(&determinize(&binary, work_limit)?);
+
+        let finite = match finite {
+            Some(finite) => finite,
+            None => is_finite(&binary),
+        };
+
+        // The common suffix lets the terms enum reject most non-matching terms cheaply. It is only worth computing
+        // for infinite languages, where the suffix can't be found by seeking.
+        let common_suffix = if finite {
+            None
+        } else {
+            let suffix: Vec<u8> = get_common_suffix(&binary, work_limit)?.into_iter().map(|b| b as u8).collect();
+            (!suffix.is_empty()).then_some(suffix)
+        };
+
+        let run_automaton = ByteRunAutomaton::new(&binary, true, work_limit)?;
+
+        Ok(Self {
+            automaton_type: AutomatonType::Normal,
+            term: None,
+            automaton: Some(Arc::new(binary)),
+            run_automaton: Some(Arc::new(run_automaton)),
+            common_suffix,
+            finite,
+        })
+    }
+
+    /// Compiles the given code point automaton with simplification, computing finiteness.
+    pub fn from_automaton(automaton: &Automaton, work_limit: usize) -> BoxResult<Self> {
+        Self::new(automaton, None, true, work_limit, false)
+    }
+
+    fn special(automaton_type: AutomatonType, term: Option<Vec<u8>>) -> Self {
+        Self {
+            automaton_type,
+            term,
+            automaton: None,
+            run_automaton: None,
+            common_suffix: None,
+            finite: automaton_type != AutomatonType::All,
+        }
+    }
+
+    /// Returns the kind of language this automaton accepts.
+    #[inline]
+    pub fn automaton_type(&self) -> AutomatonType {
+        self.automaton_type
+    }
+
+    /// For [AutomatonType::Single], returns the only accepted term.
+    #[inline]
+    pub fn term(&self) -> Option<&[u8]> {
+        self.term.as_deref()
+    }
+
+    /// For [AutomatonType::Normal], returns the deterministic byte automaton.
+    #[inline]
+    pub fn automaton(&self) -> Option<&Automaton> {
+        self.automaton.as_deref()
+    }
+
+    /// For [AutomatonType::Normal], returns the compiled run automaton.
+    #[inline]
+    pub fn run_automaton(&self) -> Option<&ByteRunAutomaton> {
+        self.run_automaton.as_deref()
+    }
+
+    /// For [AutomatonType::Normal], returns shared handles to the byte automaton and its compiled form.
+    pub(crate) fn shared_automata(&self) -> Option<(Arc<Automaton>, Arc<ByteRunAutomaton>)> {
+        Some((self.automaton.clone()?, self.run_automaton.clone()?))
+    }
+
+    /// For [AutomatonType::Normal], returns the suffix shared by every accepted term, if there is one and the language
+    /// is infinite.
+    #[inline]
+    pub fn common_suffix(&self) -> Option<&[u8]> {
+        self.common_suffix.as_deref()
+    }
+
+    /// Indicates whether the language is finite.
+    #[inline]
+    pub fn is_finite(&self) -> bool {
+        self.finite
+    }
+
+    /// Indicates whether the given term is accepted.
+    pub fn accepts(&self, term: &[u8]) -> bool {
+        match self.automaton_type {
+            AutomatonType::None => false,
+            AutomatonType::All => true,
+            AutomatonType::Single => self.term.as_deref() == Some(term),
+            AutomatonType::Normal => self.run_automaton.as_ref().is_some_and(|r| r.run(term)),
+        }
+    }
+
+    /// Returns an enumeration of the terms in `terms` accepted by this automaton.
+    pub fn terms_enum<'a>(&self, terms: &'a dyn Terms) -> BoxResult<Box<dyn TermsEnum + 'a>> {
+        match self.automaton_type {
+            AutomatonType::None => Ok(Box::new(EmptyTermsEnum)),
+            AutomatonType::All => terms.iterator(),
+            AutomatonType::Single => {
+                let term = self.term.clone().unwrap_or_default();
+                Ok(Box::new(SingleTermsEnum::new(terms.iterator()?, term)))
+            }
+            AutomatonType::Normal => terms.intersect(self, None),
+        }
+    }
+}
+
+impl Accountable for CompiledAutomaton {
+    fn ram_bytes_used(&self) -> usize {
+        size_of::<Self>()
+            + self.term.as_ref().map_or(0, size_of_vec)
+            + self.automaton.as_ref().map_or(0, |a| a.ram_bytes_used())
+            + self.run_automaton.as_ref().map_or(0, |r| r.ram_bytes_used())
+            + self.common_suffix.as_ref().map_or(0, size_of_vec)
+    }
+
+    fn child_resources(&self) -> Vec<NamedAccountable> {
+        let mut children = Vec::new();
+        if let Some(automaton) = &self.automaton {
+            children.push(NamedAccountable::new("automaton", automaton.as_ref()));
+        }
+        if let Some(run_automaton) = &self.run_automaton {
+            children.push(NamedAccountable::new("run automaton", &***run_automaton));
+        }
+        children
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::util::automaton::{
+        make_any_string, make_empty, make_string, AutomatonType, CompiledAutomaton, RegExp,
+        DEFAULT_DETERMINIZE_WORK_LIMIT,
+    };
+
+    fn compile(a: &crate::util::automaton::Automaton) -> CompiledAutomaton {
+        CompiledAutomaton::from_automaton(a, DEFAULT_DETERMINIZE_WORK_LIMIT).unwrap()
+    }
+
+    #[test]
+    fn test_special_cases() {
+        assert_eq!(compile(&make_empty()).automaton_type(), AutomatonType::None);
+        assert_eq!(compile(&make_any_string()).automaton_type(), AutomatonType::All);
+
+        let single = compile(&make_string("héllo"));
+        assert_eq!(single.automaton_type(), AutomatonType::Single);
+        assert_eq!(single.term(), Some("héllo".as_bytes()));
+
+        let normal = compile(&RegExp::new("ab*c").unwrap().to_automaton().unwrap());
+        assert_eq!(normal.automaton_type(), AutomatonType::Normal);
+        assert!(!normal.is_finite());
+        assert_eq!(normal.common_suffix(), Some(&b"c"[..]));
+        assert!(normal.accepts(b"abbc"));
+        assert!(!normal.accepts(b"abb"));
+    }
+}
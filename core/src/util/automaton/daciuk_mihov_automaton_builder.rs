@@ -0,0 +1,240 @@
+use {
+    crate::{util::automaton::Automaton, BoxResult, LuceneError},
+    std::collections::HashMap,
+};
+
+/// A state in the automaton under construction. Transitions are kept sorted by label, and only the last one may
+/// still change as terms are added.
+#[derive(Clone, Debug, Default)]
+struct State {
+    labels: Vec<u32>,
+    children: Vec<usize>,
+    is_final: bool,
+}
+
+/// The equivalence key of a state whose children have all been registered: two such states with the same key accept
+/// the same language and can be merged.
+type Signature = (Vec<u32>, Vec<usize>, bool);
+
+/// Builds a minimal, deterministic automaton accepting a set of terms, which must be added in sorted order.
+///
+/// This implements the incremental algorithm by Daciuk, Mihov, Watson and Watson ("Incremental Construction of
+/// Minimal Acyclic Finite-State Automata", 2000). Because terms arrive in order, only the path of the last term can
+/// change; everything to its left is frozen and merged with equivalent states as soon as it is. Memory use is
+/// proportional to the size of the minimal automaton rather than the total length of the terms, which makes this
+/// suitable for large term sets such as lists of IDs.
+#[derive(Debug)]
+pub struct DaciukMihovAutomatonBuilder {
+    states: Vec<State>,
+    register: HashMap<Signature, usize>,
+    previous: Option<Vec<u32>>,
+}
+
+/// The root state of the builder.
+const ROOT: usize = 0;
+
+impl Default for DaciukMihovAutomatonBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DaciukMihovAutomatonBuilder {
+    /// Creates a new builder for an empty set of terms.
+    pub fn new() -> Self {
+        Self {
+            states: vec![State::default()],
+            register: HashMap::new(),
+            previous: None,
+        }
+    }
+
+    /// Builds a minimal automaton over code points accepting the given strings, which must be sorted in code point
+    /// (equivalently, UTF-8 byte) order. Duplicates are permitted.
+    pub fn build<I, S>(terms: I) -> BoxResult<Automaton>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut builder = Self::new();
+        for term in terms {
+            let labels: Vec<u32> = term.as_ref().chars().map(|c| c as u32).collect();
+            builder.add(&labels)?;
+        }
+        Ok(builder.complete())
+    }
+
+    /// Builds a minimal automaton over bytes accepting the given binary terms, which must be sorted in unsigned byte
+    /// order. Duplicates are permitted.
+    pub fn build_binary<I, B>(terms: I) -> BoxResult<Automaton>
+    where
+        I: IntoIterator<Item = B>,
+        B: AsRef<[u8]>,
+    {
+        let mut builder = Self::new();
+        for term in terms {
+            let labels: Vec<u32> = term.as_ref().iter().map(|&b| b as u32).collect();
+            builder.add(&labels)?;
+        }
+        Ok(builder.complete())
+    }
+
+    /// Adds a term, given as a sequence of labels. This fails with [LuceneError::InvalidArgument] if the term sorts
+    /// before the previously added term; adding the same term again has no effect.
+    pub fn add(&mut self, term: &[u32]) -> BoxResult<()> {
+        if let Some(previous) = &self.previous {
+            if term < previous.as_slice() {
+                return Err(LuceneError::InvalidArgument(format!(
+                    "terms must be added in sorted order: {term:?} sorts before {previous:?}"
+                ))
+                .into());
+            }
+
+            if term == previous.as_slice() {
+                return Ok(());
+            }
+        }
+
+        // Descend along the prefix shared with the previous term; it can only be on the last transitions.
+        let mut state = ROOT;
+        let mut pos = 0;
+        while pos < term.len() {
+            match self.last_child(state) {
+                Some((label, child)) if label == term[pos] => {
+                    state = child;
+                    pos += 1;
+                }
+                _ => break,
+            }
+        }
+
+        // The remainder of the previous term's path is now frozen.
+        if !self.states[state].children.is_empty() {
+            self.replace_or_register(state);
+        }
+
+        for &label in &term[pos..] {
+            let child = self.states.len();
+            self.states.push(State::default());
+            self.states[state].labels.push(label);
+            self.states[state].children.push(child);
+            state = child;
+        }
+        self.states[state].is_final = true;
+
+        self.previous = Some(term.to_vec());
+        Ok(())
+    }
+
+    /// Finishes construction and returns the minimal automaton. If no terms were added, the automaton accepts
+    /// nothing.
+    pub fn complete(mut self) -> Automaton {
+        if !self.states[ROOT].children.is_empty() {
+            self.replace_or_register(ROOT);
+        }
+
+        let mut result = Automaton::new();
+        if self.previous.is_none() {
+            result.finish();
+            return result;
+        }
+
+        // Number the reachable states depth-first, so the root becomes state 0.
+        let mut numbering: HashMap<usize, usize> = HashMap::new();
+        let mut stack = vec![ROOT];
+        numbering.insert(ROOT, result.create_state());
+        while let Some(s) = stack.pop() {
+            let from = numbering[&s];
+            result.set_accept(from, self.states[s].is_final);
+            for (&label, &child) in self.states[s].labels.iter().zip(self.states[s].children.iter()) {
+                let to = match numbering.get(&child) {
+                    Some(&to) => to,
+                    None => {
+                        let to = result.create_state();
+                        numbering.insert(child, to);
+                        stack.push(child);
+                        to
+                    }
+                };
+                result.add_transition(from, to, label);
+            }
+        }
+
+        result.finish();
+        result
+    }
+
+    #[inline]
+    fn last_child(&self, state: usize) -> Option<(u32, usize)> {
+        let state = &self.states[state];
+        Some((*state.labels.last()?, *state.children.last()?))
+    }
+
+    /// Replaces the last child of `state` (after recursively doing the same to its own descendants) with an
+    /// equivalent registered state, or registers it if there is none.
+    fn replace_or_register(&mut self, state: usize) {
+        let Some((_, child)) = self.last_child(state) else {
+            return;
+        };
+
+        if !self.states[child].children.is_empty() {
+            self.replace_or_register(child);
+        }
+
+        let c = &self.states[child];
+        let signature = (c.labels.clone(), c.children.clone(), c.is_final);
+        match self.register.get(&signature) {
+            Some(&registered) => {
+                *self.states[state].children.last_mut().expect("state has a last child") = registered;
+            }
+            None => {
+                self.register.insert(signature, child);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::util::automaton::{minimize, run, run_labels, DaciukMihovAutomatonBuilder},
+        pretty_assertions::assert_eq,
+    };
+
+    #[test]
+    fn test_build_minimal() {
+        let terms = ["", "cat", "cats", "dog", "dogs", "dogs", "élan", "élans"];
+        let a = DaciukMihovAutomatonBuilder::build(terms).unwrap();
+        assert!(a.is_deterministic());
+
+        for term in terms {
+            assert!(run(&a, term), "{term} should be accepted");
+        }
+        for term in ["c", "ca", "catss", "do", "élanss", "x"] {
+            assert!(!run(&a, term), "{term} should be rejected");
+        }
+
+        // The suffixes "s" and "" are shared, so the result is already minimal.
+        let minimal = minimize(&a, 10000).unwrap();
+        assert_eq!(a.num_states(), minimal.num_states());
+    }
+
+    #[test]
+    fn test_build_binary() {
+        let terms: [&[u8]; 4] = [b"\x00", b"\x00\xff", b"\x01", b"\xff\xff"];
+        let a = DaciukMihovAutomatonBuilder::build_binary(terms).unwrap();
+        for term in terms {
+            let labels: Vec<u32> = term.iter().map(|&b| b as u32).collect();
+            assert!(run_labels(&a, &labels));
+        }
+        assert!(!run_labels(&a, &[0xff]));
+    }
+
+    #[test]
+    fn test_unsorted() {
+        assert!(DaciukMihovAutomatonBuilder::build(["b", "a"]).is_err());
+
+        let empty = DaciukMihovAutomatonBuilder::build(Vec::<String>::new()).unwrap();
+        assert_eq!(empty.num_states(), 0);
+    }
+}
@@ -0,0 +1,392 @@
+use {
+    crate::util::automaton::{concatenate, make_code_points, Automaton, MAX_CODE_POINT},
+    std::collections::{hash_map::Entry, HashMap, VecDeque},
+};
+
+/// The maximum edit distance supported by [LevenshteinAutomata].
+pub const MAXIMUM_SUPPORTED_DISTANCE: u32 = 2;
+
+/// Builds deterministic automata that accept all strings within a given Levenshtein (or Damerau-Levenshtein, if
+/// transpositions are enabled) edit distance of a word.
+///
+/// Construction follows Schulz and Mihov's parametric approach: states of the underlying non-deterministic automaton
+/// are sets of positions `(offset, edits)` relative to a base offset into the word. Transitions between these
+/// parametric states depend only on the characteristic vector of the input character against a small window of the
+/// word, so they are computed once per `(state, vector)` pair and then instantiated at every base offset. The
+/// resulting automaton has `O(word length)` states for a fixed distance.
+#[derive(Clone, Debug)]
+pub struct LevenshteinAutomata {
+    word: Vec<u32>,
+    alphabet_max: u32,
+    with_transpositions: bool,
+}
+
+impl LevenshteinAutomata {
+    /// Creates a builder for the given word, treated as a sequence of Unicode code points.
+    pub fn new(input: &str, with_transpositions: bool) -> Self {
+        Self::from_code_points(input.chars().map(|c| c as u32).collect(), MAX_CODE_POINT, with_transpositions)
+    }
+
+    /// Creates a builder for the given word as a sequence of labels, each of which is at most `alphabet_max`.
+    pub fn from_code_points(word: Vec<u32>, alphabet_max: u32, with_transpositions: bool) -> Self {
+        debug_assert!(word.iter().all(|&c| c <= alphabet_max));
+        Self {
+            word,
+            alphabet_max,
+            with_transpositions,
+        }
+    }
+
+    /// Returns a deterministic automaton accepting all strings within edit distance `n` of the word, or `None` if
+    /// `n` exceeds [MAXIMUM_SUPPORTED_DISTANCE].
+    pub fn to_automaton(&self, n: u32) -> Option<Automaton> {
+        self.to_automaton_with_prefix(n, "")
+    }
+
+    /// Returns a deterministic automaton accepting `prefix` followed by any string within edit distance `n` of the
+    /// word, or `None` if `n` exceeds [MAXIMUM_SUPPORTED_DISTANCE].
+    pub fn to_automaton_with_prefix(&self, n: u32, prefix: &str) -> Option<Automaton> {
+        if n > MAXIMUM_SUPPORTED_DISTANCE {
+            return None;
+        }
+
+        let lev = if n == 0 {
+            make_code_points(&self.word)
+        } else {
+            self.build(n)
+        };
+
+        if prefix.is_empty() {
+            Some(lev)
+        } else {
+            let prefix: Vec<u32> = prefix.chars().map(|c| c as u32).collect();
+            Some(concatenate(&make_code_points(&prefix), &lev))
+        }
+    }
+
+    fn build(&self, n: u32) -> Automaton {
+        let mut description = ParametricDescription::new(n, self.with_transpositions);
+        let window = description.window();
+        let len = self.word.len();
+
+        let mut result = Automaton::new();
+        let mut states: HashMap<(usize, usize), usize> = HashMap::new();
+        let mut queue: VecDeque<(usize, usize)> = VecDeque::new();
+
+        let initial = (description.initial_state(), 0);
+        states.insert(initial, result.create_state());
+        queue.push_back(initial);
+
+        while let Some((param_state, offset)) = queue.pop_front() {
+            let source = states[&(param_state, offset)];
+            result.set_accept(source, description.is_accept(param_state, len - offset));
+
+            let avail = (len - offset).min(window);
+            let window_chars = &self.word[offset..offset + avail];
+            let mut distinct: Vec<u32> = window_chars.to_vec();
+            distinct.sort_unstable();
+            distinct.dedup();
+
+            // Each distinct character in the window has its own characteristic vector; every other character has an
+            // all-zero vector.
+            let mut targets: Vec<(u32, u32, Option<StateAtOffset>)> = Vec::with_capacity(distinct.len() + 1);
+            for &c in distinct.iter() {
+                let vector = window_chars.iter().enumerate().fold(0u32, |v, (i, &wc)| {
+                    if wc == c {
+                        v | (1 << i)
+                    } else {
+                        v
+                    }
+                });
+                targets.push((c, c, description.step(param_state, vector, avail).map(|(s, d)| (s, offset + d))));
+            }
+
+            let other = description.step(param_state, 0, avail).map(|(s, d)| (s, offset + d));
+            let mut next = 0u32;
+            for &c in distinct.iter() {
+                if c > next {
+                    targets.push((next, c - 1, other));
+                }
+                next = c + 1;
+            }
+            if next <= self.alphabet_max {
+                targets.push((next, self.alphabet_max, other));
+            }
+
+            for (min, max, target) in targets {
+                let Some(target) = target else {
+                    continue;
+                };
+
+                let dest = match states.entry(target) {
+                    Entry::Occupied(e) => *e.get(),
+                    Entry::Vacant(e) => {
+                        let dest = result.create_state();
+                        e.insert(dest);
+                        queue.push_back(target);
+                        dest
+                    }
+                };
+                result.add_transition_range(source, dest, min, max);
+            }
+        }
+
+        result.finish();
+        result
+    }
+}
+
+/// A state of the instantiated automaton: a parametric state and the base offset it applies at.
+type StateAtOffset = (usize, usize);
+
+/// A position in the non-deterministic Levenshtein automaton, relative to a parametric state's base offset.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+struct Position {
+    offset: usize,
+    edits: u32,
+
+    /// Whether this position is halfway through a transposition: the character after `offset` has been read, and
+    /// the character at `offset` must come next.
+    transposed: bool,
+}
+
+/// The parametric states and transitions for a given maximum distance, independent of any particular word.
+struct ParametricDescription {
+    n: u32,
+    with_transpositions: bool,
+    states: Vec<Vec<Position>>,
+    state_ids: HashMap<Vec<Position>, usize>,
+    transitions: HashMap<(usize, u32, usize), Option<(usize, usize)>>,
+}
+
+impl ParametricDescription {
+    fn new(n: u32, with_transpositions: bool) -> Self {
+        Self {
+            n,
+            with_transpositions,
+            states: Vec::new(),
+            state_ids: HashMap::new(),
+            transitions: HashMap::new(),
+        }
+    }
+
+    /// The number of word characters (starting at the base offset) that can influence a transition.
+    #[inline]
+    fn window(&self) -> usize {
+        3 * self.n as usize + 2
+    }
+
+    fn initial_state(&mut self) -> usize {
+        self.intern(vec![Position {
+            offset: 0,
+            edits: 0,
+            transposed: false,
+        }])
+    }
+
+    fn intern(&mut self, positions: Vec<Position>) -> usize {
+        if let Some(&id) = self.state_ids.get(&positions) {
+            return id;
+        }
+
+        let id = self.states.len();
+        self.states.push(positions.clone());
+        self.state_ids.insert(positions, id);
+        id
+    }
+
+    /// Indicates whether the state accepts when `remaining` characters of the word follow the base offset.
+    fn is_accept(&self, state: usize, remaining: usize) -> bool {
+        self.states[state]
+            .iter()
+            .any(|p| !p.transposed && p.offset <= remaining && (remaining - p.offset) as u32 <= self.n - p.edits)
+    }
+
+    /// Returns the parametric state reached from `state` on a character with the given characteristic vector, and
+    /// how far the base offset moves. `avail` is the number of word characters available in the window.
+    fn step(&mut self, state: usize, vector: u32, avail: usize) -> Option<(usize, usize)> {
+        if let Some(&cached) = self.transitions.get(&(state, vector, avail)) {
+            return cached;
+        }
+
+        let matches = |i: usize| i < avail && vector & (1 << i) != 0;
+        let n = self.n;
+        let mut next: Vec<Position> = Vec::new();
+
+        for &p in self.states[state].iter() {
+            if p.transposed {
+                if matches(p.offset) {
+                    next.push(Position {
+                        offset: p.offset + 2,
+                        edits: p.edits,
+                        transposed: false,
+                    });
+                }
+                continue;
+            }
+
+            if matches(p.offset) {
+                next.push(Position {
+                    offset: p.offset + 1,
+                    edits: p.edits,
+                    transposed: false,
+                });
+            }
+
+            if p.edits < n {
+                // Insertion.
+                next.push(Position {
+                    offset: p.offset,
+                    edits: p.edits + 1,
+                    transposed: false,
+                });
+
+                // Substitution.
+                if p.offset < avail {
+                    next.push(Position {
+                        offset: p.offset + 1,
+                        edits: p.edits + 1,
+                        transposed: false,
+                    });
+                }
+
+                // Deletion of k characters followed by a match.
+                for k in 1..=(n - p.edits) as usize {
+                    if matches(p.offset + k) {
+                        next.push(Position {
+                            offset: p.offset + k + 1,
+                            edits: p.edits + k as u32,
+                            transposed: false,
+                        });
+                    }
+                }
+
+                if self.with_transpositions && matches(p.offset + 1) {
+                    next.push(Position {
+                        offset: p.offset,
+                        edits: p.edits + 1,
+                        transposed: true,
+                    });
+                }
+            }
+        }
+
+        let result = if next.is_empty() {
+            None
+        } else {
+            let positions = reduce(next);
+            let delta = positions.iter().map(|p| p.offset).min().unwrap_or(0);
+            let normalized = positions
+                .into_iter()
+                .map(|p| Position {
+                    offset: p.offset - delta,
+                    ..p
+                })
+                .collect();
+            Some((self.intern(normalized), delta))
+        };
+
+        self.transitions.insert((state, vector, avail), result);
+        result
+    }
+}
+
+/// Sorts and deduplicates positions, removing those subsumed by another position.
+///
+/// A position `i` with `e` edits subsumes a position `j` with `f > e` edits when `|j - i| <= f - e`: every string
+/// accepted from `j` is also accepted from `i`. Transposition positions are never considered subsumed.
+fn reduce(mut positions: Vec<Position>) -> Vec<Position> {
+    positions.sort_unstable();
+    positions.dedup();
+
+    let subsumed = |q: &Position| {
+        !q.transposed
+            && positions
+                .iter()
+                .any(|p| !p.transposed && p.edits < q.edits && p.offset.abs_diff(q.offset) as u32 <= q.edits - p.edits)
+    };
+
+    positions.iter().filter(|q| !subsumed(q)).copied().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::util::automaton::{run, LevenshteinAutomata};
+
+    /// Optimal string alignment distance, used to check the automata against a brute-force computation.
+    fn distance(a: &[char], b: &[char], transpositions: bool) -> usize {
+        let mut d = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+        for (i, row) in d.iter_mut().enumerate() {
+            row[0] = i;
+        }
+        for (j, cell) in d[0].iter_mut().enumerate() {
+            *cell = j;
+        }
+
+        for i in 1..=a.len() {
+            for j in 1..=b.len() {
+                let cost = usize::from(a[i - 1] != b[j - 1]);
+                d[i][j] = (d[i - 1][j] + 1).min(d[i][j - 1] + 1).min(d[i - 1][j - 1] + cost);
+                if transpositions && i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                    d[i][j] = d[i][j].min(d[i - 2][j - 2] + 1);
+                }
+            }
+        }
+
+        d[a.len()][b.len()]
+    }
+
+    /// Returns every string over `alphabet` of length up to `max_len`.
+    fn all_strings(alphabet: &[char], max_len: usize) -> Vec<Vec<char>> {
+        let mut result = vec![Vec::new()];
+        let mut frontier = vec![Vec::new()];
+        for _ in 0..max_len {
+            let mut next = Vec::new();
+            for s in frontier.iter() {
+                for &c in alphabet {
+                    let mut t: Vec<char> = s.clone();
+                    t.push(c);
+                    next.push(t);
+                }
+            }
+            result.extend(next.iter().cloned());
+            frontier = next;
+        }
+        result
+    }
+
+    #[test]
+    fn test_matches_brute_force() {
+        let candidates = all_strings(&['a', 'b', 'c', 'x'], 5);
+        for word in ["", "a", "ab", "abc", "abca", "aabb"] {
+            let word_chars: Vec<char> = word.chars().collect();
+            for transpositions in [false, true] {
+                let builder = LevenshteinAutomata::new(word, transpositions);
+                for n in 0..=2 {
+                    let a = builder.to_automaton(n).unwrap();
+                    assert!(a.is_deterministic());
+                    for candidate in candidates.iter() {
+                        let s: String = candidate.iter().collect();
+                        let expected = distance(&word_chars, candidate, transpositions) <= n as usize;
+                        assert_eq!(
+                            run(&a, &s),
+                            expected,
+                            "word={word:?} candidate={s:?} n={n} transpositions={transpositions}"
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_prefix_and_limits() {
+        let builder = LevenshteinAutomata::new("cene", true);
+        let a = builder.to_automaton_with_prefix(1, "lu").unwrap();
+        assert!(run(&a, "lucene"));
+        assert!(run(&a, "lucnee"));
+        assert!(run(&a, "lucen"));
+        assert!(!run(&a, "xucene"));
+        assert!(builder.to_automaton(3).is_none());
+    }
+}
@@ -0,0 +1,187 @@
+use {
+    crate::{
+        util::automaton::{determinize, remove_dead_states, Automaton},
+        BoxResult,
+    },
+    std::collections::{HashMap, HashSet, VecDeque},
+};
+
+/// Minimizes the given automaton using Hopcroft's partition refinement algorithm.
+///
+/// The automaton is determinized first (which may fail with [crate::LuceneError::TooComplexToDeterminize] if that
+/// would take more than `work_limit` effort). The result is the unique minimal deterministic automaton for the
+/// language, without dead states.
+pub fn minimize(a: &Automaton, work_limit: usize) -> BoxResult<Automaton> {
+    let a = remove_dead_states(&determinize(a, work_limit)?);
+    if a.num_states() == 0 {
+        return Ok(a);
+    }
+
+    // The alphabet is partitioned into intervals on which every state behaves uniformly; interval `i` starts at
+    // `points[i]`.
+    let points = a.start_points();
+    let sigma = points.len();
+    let dead = a.num_states();
+    let n = dead + 1;
+
+    let mut delta = vec![dead; n * sigma];
+    for state in 0..a.num_states() {
+        for (i, &point) in points.iter().enumerate() {
+            if let Some(dest) = a.step(state, point) {
+                delta[state * sigma + i] = dest;
+            }
+        }
+    }
+
+    let mut inverse: Vec<Vec<usize>> = vec![Vec::new(); n * sigma];
+    for state in 0..n {
+        for i in 0..sigma {
+            inverse[delta[state * sigma + i] * sigma + i].push(state);
+        }
+    }
+
+    // Initial partition: accepting and non-accepting states (the dead state is non-accepting).
+    let (accepting, rejecting): (Vec<usize>, Vec<usize>) = (0..n).partition(|&s| s < dead && a.is_accept(s));
+    let mut blocks: Vec<Vec<usize>> = Vec::new();
+    let mut block_of = vec![0; n];
+    for block in [accepting, rejecting] {
+        if !block.is_empty() {
+            for &s in block.iter() {
+                block_of[s] = blocks.len();
+            }
+            blocks.push(block);
+        }
+    }
+
+    let mut pending: VecDeque<(usize, usize)> = VecDeque::new();
+    let mut in_pending: HashSet<(usize, usize)> = HashSet::new();
+    if blocks.len() == 2 {
+        let smaller = if blocks[0].len() <= blocks[1].len() {
+            0
+        } else {
+            1
+        };
+        for i in 0..sigma {
+            pending.push_back((smaller, i));
+            in_pending.insert((smaller, i));
+        }
+    }
+
+    let mut in_splitter = vec![false; n];
+    while let Some((splitter, i)) = pending.pop_front() {
+        in_pending.remove(&(splitter, i));
+
+        // Find all states that move into the splitter block on interval `i`.
+        let mut predecessors = Vec::new();
+        for &s in blocks[splitter].iter() {
+            for &p in inverse[s * sigma + i].iter() {
+                if !in_splitter[p] {
+                    in_splitter[p] = true;
+                    predecessors.push(p);
+                }
+            }
+        }
+
+        let mut touched: HashMap<usize, Vec<usize>> = HashMap::new();
+        for &p in predecessors.iter() {
+            touched.entry(block_of[p]).or_default().push(p);
+        }
+
+        for (block, members) in touched {
+            if members.len() == blocks[block].len() {
+                continue;
+            }
+
+            // Split the block into the states that move into the splitter and those that don't.
+            let new_block = blocks.len();
+            blocks[block].retain(|&s| !in_splitter[s]);
+            for &s in members.iter() {
+                block_of[s] = new_block;
+            }
+            blocks.push(members);
+
+            for j in 0..sigma {
+                if in_pending.contains(&(block, j)) {
+                    pending.push_back((new_block, j));
+                    in_pending.insert((new_block, j));
+                } else {
+                    let smaller = if blocks[block].len() <= blocks[new_block].len() {
+                        block
+                    } else {
+                        new_block
+                    };
+                    pending.push_back((smaller, j));
+                    in_pending.insert((smaller, j));
+                }
+            }
+        }
+
+        for p in predecessors {
+            in_splitter[p] = false;
+        }
+    }
+
+    // Build the result with one state per block, excluding the block holding the dead state. The initial state's
+    // block becomes state 0.
+    let dead_block = block_of[dead];
+    let mut result = Automaton::new();
+    if block_of[0] == dead_block {
+        result.finish();
+        return Ok(result);
+    }
+
+    let mut new_state = vec![usize::MAX; blocks.len()];
+    let mut order = vec![block_of[0]];
+    order.extend((0..blocks.len()).filter(|&b| b != block_of[0] && b != dead_block && !blocks[b].is_empty()));
+    for &b in order.iter() {
+        new_state[b] = result.create_state();
+        result.set_accept(new_state[b], a.is_accept(blocks[b][0]));
+    }
+
+    for &b in order.iter() {
+        let representative = blocks[b][0];
+        for (i, &point) in points.iter().enumerate() {
+            let dest_block = block_of[delta[representative * sigma + i]];
+            if dest_block == dead_block {
+                continue;
+            }
+
+            let end = match points.get(i + 1) {
+                Some(next) => next - 1,
+                None => u32::MAX,
+            };
+            result.add_transition_range(new_state[b], new_state[dest_block], point, end);
+        }
+    }
+
+    result.finish();
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::util::automaton::{
+        determinize, make_string, minimize, run, union, RegExp, DEFAULT_DETERMINIZE_WORK_LIMIT,
+    };
+
+    #[test]
+    fn test_minimize() {
+        // "ab|cb" needs only three states: initial, after the first character, and accepting.
+        let a = union(&[&make_string("ab"), &make_string("cb")]);
+        let d = determinize(&a, DEFAULT_DETERMINIZE_WORK_LIMIT).unwrap();
+        assert!(d.num_states() > 3);
+
+        let m = minimize(&a, DEFAULT_DETERMINIZE_WORK_LIMIT).unwrap();
+        assert_eq!(m.num_states(), 3);
+        assert!(m.is_deterministic());
+        for s in ["ab", "cb", "b", "abb", ""] {
+            assert_eq!(run(&a, s), run(&m, s), "{s}");
+        }
+
+        let m = minimize(&RegExp::new("(a|b)*abb").unwrap().to_automaton().unwrap(), 10000).unwrap();
+        assert_eq!(m.num_states(), 4);
+
+        let m = minimize(&RegExp::new("a#").unwrap().to_automaton().unwrap(), 10000).unwrap();
+        assert_eq!(m.num_states(), 0);
+    }
+}
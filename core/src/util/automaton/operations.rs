@@ -0,0 +1,651 @@
+use {
+    crate::{
+        util::automaton::{make_empty_string, Automaton, MAX_CODE_POINT},
+        BoxResult, LuceneError,
+    },
+    std::collections::{hash_map::Entry, BTreeMap, HashMap, VecDeque},
+};
+
+/// The default maximum effort to spend while determinizing an automaton before giving up with
+/// [LuceneError::TooComplexToDeterminize].
+pub const DEFAULT_DETERMINIZE_WORK_LIMIT: usize = 10000;
+
+/// Returns an automaton that accepts the concatenation of the languages of `a` and `b`.
+pub fn concatenate(a: &Automaton, b: &Automaton) -> Automaton {
+    let mut result = Automaton::new();
+    if a.num_states() == 0 || b.num_states() == 0 {
+        result.finish();
+        return result;
+    }
+
+    result.copy_from(a);
+    let b_start = result.copy_from(b);
+
+    // Every accept state of `a` now behaves like the start state of `b`.
+    let a_accepts: Vec<usize> = a.accept_states().collect();
+    for state in a_accepts {
+        result.set_accept(state, false);
+        result.add_epsilon(state, b_start);
+    }
+
+    result.finish();
+    result
+}
+
+/// Returns an automaton that accepts the concatenation of the languages of all the given automata, in order.
+pub fn concatenate_all(automata: &[&Automaton]) -> Automaton {
+    automata.iter().fold(make_empty_string(), |result, a| concatenate(&result, a))
+}
+
+/// Returns an automaton that accepts the union of the languages of all the given automata.
+pub fn union(automata: &[&Automaton]) -> Automaton {
+    let mut result = Automaton::new();
+    let start = result.create_state();
+    for a in automata {
+        if a.num_states() > 0 {
+            let offset = result.copy_from(a);
+            result.add_epsilon(start, offset);
+        }
+    }
+
+    result.finish();
+    result
+}
+
+/// Returns an automaton that accepts the empty string in addition to the language of `a`.
+pub fn optional(a: &Automaton) -> Automaton {
+    let mut result = Automaton::new();
+    let start = result.create_state();
+    result.set_accept(start, true);
+    if a.num_states() > 0 {
+        let offset = result.copy_from(a);
+        result.add_epsilon(start, offset);
+    }
+
+    result.finish();
+    result
+}
+
+/// Returns an automaton that accepts the Kleene star (zero or more concatenated repetitions) of the language of `a`.
+pub fn repeat(a: &Automaton) -> Automaton {
+    let mut result = Automaton::new();
+    let start = result.create_state();
+    result.set_accept(start, true);
+    if a.num_states() == 0 {
+        result.finish();
+        return result;
+    }
+
+    let offset = result.copy_from(a);
+    result.add_epsilon(start, offset);
+    let accepts: Vec<usize> = a.accept_states().map(|s| s + offset).collect();
+    for state in accepts {
+        result.add_epsilon(state, start);
+    }
+
+    result.finish();
+    result
+}
+
+/// Returns an automaton that accepts `min` or more concatenated repetitions of the language of `a`.
+pub fn repeat_min(a: &Automaton, min: usize) -> Automaton {
+    let mut parts: Vec<&Automaton> = vec![a; min];
+    let star = repeat(a);
+    parts.push(&star);
+    concatenate_all(&parts)
+}
+
+/// Returns an automaton that accepts between `min` and `max` (inclusive) concatenated repetitions of the language
+/// of `a`. If `min > max`, the resulting automaton accepts nothing.
+pub fn repeat_range(a: &Automaton, min: usize, max: usize) -> Automaton {
+    if min > max {
+        let mut result = Automaton::new();
+        result.finish();
+        return result;
+    }
+
+    let prefix = concatenate_all(&vec![a; min]);
+    if min == max {
+        return prefix;
+    }
+
+    // Build (a(a(a)?)?)? from the inside out so the optional tail stays linear in size.
+    let mut tail = optional(a);
+    for _ in min + 1..max {
+        tail = optional(&concatenate(a, &tail));
+    }
+
+    concatenate(&prefix, &tail)
+}
+
+/// Determinizes the given automaton using the subset construction.
+///
+/// Returns [LuceneError::TooComplexToDeterminize] if doing so would take more than (roughly) `work_limit` units of
+/// effort.
+pub fn determinize(a: &Automaton, work_limit: usize) -> BoxResult<Automaton> {
+    if a.is_deterministic() || a.num_states() == 0 {
+        return Ok(a.clone());
+    }
+
+    let effort_limit = work_limit.saturating_mul(10);
+    let mut effort = 0usize;
+    let mut result = Automaton::new();
+    let mut sets: HashMap<Vec<usize>, usize> = HashMap::new();
+    let mut queue: VecDeque<Vec<usize>> = VecDeque::new();
+
+    let initial = vec![0];
+    let start = result.create_state();
+    result.set_accept(start, a.is_accept(0));
+    sets.insert(initial.clone(), start);
+    queue.push_back(initial);
+
+    while let Some(set) = queue.pop_front() {
+        effort += set.len();
+        if effort >= effort_limit {
+            return Err(LuceneError::TooComplexToDeterminize(format!(
+                "determinizing an automaton with {} states and {} transitions would exceed a work limit of \
+                 {work_limit}",
+                a.num_states(),
+                a.num_transitions()
+            ))
+            .into());
+        }
+
+        let source = sets[&set];
+
+        // Sweep over the label space, tracking which destination states are reachable at each point.
+        let mut events: Vec<(u32, bool, usize)> = Vec::new();
+        for &state in set.iter() {
+            for t in a.transitions(state) {
+                events.push((t.min, true, t.dest));
+                if t.max < u32::MAX {
+                    events.push((t.max + 1, false, t.dest));
+                }
+            }
+        }
+        events.sort_unstable_by_key(|e| e.0);
+
+        let mut active: BTreeMap<usize, usize> = BTreeMap::new();
+        let mut i = 0;
+        while i < events.len() {
+            let point = events[i].0;
+            while i < events.len() && events[i].0 == point {
+                let (_, is_start, dest) = events[i];
+                if is_start {
+                    *active.entry(dest).or_default() += 1;
+                } else if let Some(count) = active.get_mut(&dest) {
+                    *count -= 1;
+                    if *count == 0 {
+                        active.remove(&dest);
+                    }
+                }
+                i += 1;
+            }
+
+            if active.is_empty() {
+                continue;
+            }
+
+            let end = match events.get(i) {
+                Some(next) => next.0 - 1,
+                None => u32::MAX,
+            };
+
+            let dest_set: Vec<usize> = active.keys().copied().collect();
+            let dest = match sets.entry(dest_set) {
+                Entry::Occupied(e) => *e.get(),
+                Entry::Vacant(e) => {
+                    let dest = result.create_state();
+                    result.set_accept(dest, e.key().iter().any(|&s| a.is_accept(s)));
+                    queue.push_back(e.key().clone());
+                    e.insert(dest);
+                    dest
+                }
+            };
+            result.add_transition_range(source, dest, point, end);
+        }
+    }
+
+    result.finish();
+    Ok(result)
+}
+
+/// Returns a copy of the deterministic automaton `a` where every state has a transition for every label between 0
+/// and `max_label`. Missing transitions go to a new, non-accepting dead state.
+pub fn totalize(a: &Automaton, max_label: u32) -> Automaton {
+    debug_assert!(a.is_deterministic());
+    let mut result = a.clone();
+    if result.num_states() == 0 {
+        let start = result.create_state();
+        result.add_transition_range(start, start, 0, max_label);
+        result.finish();
+        return result;
+    }
+
+    let dead = result.create_state();
+    result.add_transition_range(dead, dead, 0, max_label);
+    for state in 0..a.num_states() {
+        let mut next_label = 0u32;
+        let mut covered_all = false;
+        for t in a.transitions(state) {
+            if t.min > next_label {
+                result.add_transition_range(state, dead, next_label, t.min - 1);
+            }
+            if t.max >= max_label {
+                covered_all = true;
+                break;
+            }
+            next_label = next_label.max(t.max + 1);
+        }
+
+        if !covered_all {
+            result.add_transition_range(state, dead, next_label, max_label);
+        }
+    }
+
+    result.finish();
+    result
+}
+
+/// Returns a (deterministic) automaton that accepts every string of code points not accepted by `a`.
+pub fn complement(a: &Automaton, work_limit: usize) -> BoxResult<Automaton> {
+    let mut result = totalize(&determinize(a, work_limit)?, MAX_CODE_POINT);
+    for state in 0..result.num_states() {
+        let accept = result.is_accept(state);
+        result.set_accept(state, !accept);
+    }
+
+    Ok(remove_dead_states(&result))
+}
+
+/// Returns an automaton that accepts the strings accepted by `a` but not by `b`.
+pub fn minus(a: &Automaton, b: &Automaton, work_limit: usize) -> BoxResult<Automaton> {
+    if a.num_states() == 0 || b.num_states() == 0 {
+        return Ok(a.clone());
+    }
+
+    Ok(intersection(a, &complement(b, work_limit)?))
+}
+
+/// Returns an automaton that accepts the intersection of the languages of `a` and `b`. If both are deterministic,
+/// so is the result.
+pub fn intersection(a: &Automaton, b: &Automaton) -> Automaton {
+    let mut result = Automaton::new();
+    if a.num_states() == 0 || b.num_states() == 0 {
+        result.finish();
+        return result;
+    }
+
+    let mut states: HashMap<(usize, usize), usize> = HashMap::new();
+    let mut queue: VecDeque<(usize, usize)> = VecDeque::new();
+    let start = result.create_state();
+    result.set_accept(start, a.is_accept(0) && b.is_accept(0));
+    states.insert((0, 0), start);
+    queue.push_back((0, 0));
+
+    while let Some((sa, sb)) = queue.pop_front() {
+        let source = states[&(sa, sb)];
+        for ta in a.transitions(sa) {
+            for tb in b.transitions(sb) {
+                let min = ta.min.max(tb.min);
+                let max = ta.max.min(tb.max);
+                if min > max {
+                    continue;
+                }
+
+                let key = (ta.dest, tb.dest);
+                let dest = match states.entry(key) {
+                    Entry::Occupied(e) => *e.get(),
+                    Entry::Vacant(e) => {
+                        let dest = result.create_state();
+                        result.set_accept(dest, a.is_accept(ta.dest) && b.is_accept(tb.dest));
+                        queue.push_back(key);
+                        e.insert(dest);
+                        dest
+                    }
+                };
+                result.add_transition_range(source, dest, min, max);
+            }
+        }
+    }
+
+    result.finish();
+    remove_dead_states(&result)
+}
+
+/// Returns a copy of `a` without any states that are unreachable from the initial state or cannot reach an accept
+/// state. If the language of `a` is empty, the result has no states.
+pub fn remove_dead_states(a: &Automaton) -> Automaton {
+    let n = a.num_states();
+    let mut result = Automaton::new();
+    if n == 0 {
+        result.finish();
+        return result;
+    }
+
+    // Forward reachability from the initial state.
+    let mut reachable = vec![false; n];
+    let mut stack = vec![0];
+    reachable[0] = true;
+    while let Some(state) = stack.pop() {
+        for t in a.transitions(state) {
+            if !reachable[t.dest] {
+                reachable[t.dest] = true;
+                stack.push(t.dest);
+            }
+        }
+    }
+
+    // Backward reachability from the accept states.
+    let mut reverse: Vec<Vec<usize>> = vec![Vec::new(); n];
+    for state in 0..n {
+        for t in a.transitions(state) {
+            reverse[t.dest].push(state);
+        }
+    }
+    let mut live = vec![false; n];
+    let mut stack: Vec<usize> = a.accept_states().collect();
+    for &state in stack.iter() {
+        live[state] = true;
+    }
+    while let Some(state) = stack.pop() {
+        for &source in reverse[state].iter() {
+            if !live[source] {
+                live[source] = true;
+                stack.push(source);
+            }
+        }
+    }
+
+    if !(reachable[0] && live[0]) {
+        result.finish();
+        return result;
+    }
+
+    let mut map = vec![usize::MAX; n];
+    for state in 0..n {
+        if reachable[state] && live[state] {
+            map[state] = result.create_state();
+            result.set_accept(map[state], a.is_accept(state));
+        }
+    }
+
+    for state in 0..n {
+        if map[state] == usize::MAX {
+            continue;
+        }
+
+        for t in a.transitions(state) {
+            if map[t.dest] != usize::MAX {
+                result.add_transition_range(map[state], map[t.dest], t.min, t.max);
+            }
+        }
+    }
+
+    result.finish();
+    result
+}
+
+/// Returns an automaton that accepts the reverse of every string accepted by `a`. The result is generally not
+/// deterministic.
+pub fn reverse(a: &Automaton) -> Automaton {
+    let mut result = Automaton::new();
+    if a.num_states() == 0 {
+        result.finish();
+        return result;
+    }
+
+    // State 0 is a new initial state; every original state `s` becomes `s + 1`.
+    let start = result.create_state();
+    for _ in 0..a.num_states() {
+        result.create_state();
+    }
+
+    for state in 0..a.num_states() {
+        for t in a.transitions(state) {
+            result.add_transition_range(t.dest + 1, state + 1, t.min, t.max);
+        }
+    }
+
+    result.set_accept(1, true);
+    let accepts: Vec<usize> = a.accept_states().collect();
+    for state in accepts {
+        result.add_epsilon(start, state + 1);
+    }
+
+    result.finish();
+    remove_dead_states(&result)
+}
+
+/// Indicates whether the language of `a` is finite. `a` must not have dead states (see [remove_dead_states]).
+pub fn is_finite(a: &Automaton) -> bool {
+    if a.num_states() == 0 {
+        return true;
+    }
+
+    // Iterative depth-first search for a cycle. Each state is 0 (unvisited), 1 (on the stack) or 2 (done).
+    let mut color = vec![0u8; a.num_states()];
+    let mut stack: Vec<(usize, usize)> = vec![(0, 0)];
+    color[0] = 1;
+    while let Some((state, next)) = stack.last_mut() {
+        let transitions = a.transitions(*state);
+        if *next < transitions.len() {
+            let dest = transitions[*next].dest;
+            *next += 1;
+            match color[dest] {
+                0 => {
+                    color[dest] = 1;
+                    stack.push((dest, 0));
+                }
+                1 => return false,
+                _ => (),
+            }
+        } else {
+            color[*state] = 2;
+            stack.pop();
+        }
+    }
+
+    true
+}
+
+/// Indicates whether the minimal deterministic automaton `a` accepts every string of labels between `min_label` and
+/// `max_label`.
+pub fn is_total(a: &Automaton, min_label: u32, max_label: u32) -> bool {
+    if a.num_states() == 0 || !a.is_accept(0) {
+        return false;
+    }
+
+    match a.transitions(0) {
+        [t] => t.dest == 0 && t.min == min_label && t.max == max_label,
+        _ => false,
+    }
+}
+
+/// If the deterministic automaton `a` accepts exactly one string, returns its labels.
+pub fn get_singleton(a: &Automaton) -> Option<Vec<u32>> {
+    if a.num_states() == 0 {
+        return None;
+    }
+
+    let mut labels = Vec::new();
+    let mut visited = vec![false; a.num_states()];
+    let mut state = 0;
+    loop {
+        visited[state] = true;
+        let transitions = a.transitions(state);
+        if a.is_accept(state) {
+            return transitions.is_empty().then_some(labels);
+        }
+
+        match transitions {
+            [t] if t.min == t.max && !visited[t.dest] => {
+                labels.push(t.min);
+                state = t.dest;
+            }
+            _ => return None,
+        }
+    }
+}
+
+/// Returns the longest string of labels that is a prefix of every string accepted by the deterministic automaton
+/// `a`.
+pub fn get_common_prefix(a: &Automaton) -> Vec<u32> {
+    let mut prefix = Vec::new();
+    if a.num_states() == 0 {
+        return prefix;
+    }
+
+    let mut visited = vec![false; a.num_states()];
+    let mut state = 0;
+    loop {
+        visited[state] = true;
+        if a.is_accept(state) {
+            return prefix;
+        }
+
+        match a.transitions(state) {
+            [t] if t.min == t.max && !visited[t.dest] => {
+                prefix.push(t.min);
+                state = t.dest;
+            }
+            _ => return prefix,
+        }
+    }
+}
+
+/// Returns the longest string of labels that is a suffix of every string accepted by `a`.
+pub fn get_common_suffix(a: &Automaton, work_limit: usize) -> BoxResult<Vec<u32>> {
+    let reversed = determinize(&reverse(a), work_limit)?;
+    let mut suffix = get_common_prefix(&reversed);
+    suffix.reverse();
+    Ok(suffix)
+}
+
+/// Indicates whether the language of `a` is empty.
+pub fn is_empty(a: &Automaton) -> bool {
+    if a.num_states() == 0 {
+        return true;
+    }
+
+    let mut seen = vec![false; a.num_states()];
+    let mut stack = vec![0];
+    seen[0] = true;
+    while let Some(state) = stack.pop() {
+        if a.is_accept(state) {
+            return false;
+        }
+
+        for t in a.transitions(state) {
+            if !seen[t.dest] {
+                seen[t.dest] = true;
+                stack.push(t.dest);
+            }
+        }
+    }
+
+    true
+}
+
+/// Indicates whether `a` accepts the given string of code points.
+pub fn run(a: &Automaton, s: &str) -> bool {
+    let labels: Vec<u32> = s.chars().map(|c| c as u32).collect();
+    run_labels(a, &labels)
+}
+
+/// Indicates whether `a` accepts the given sequence of labels. This works for both deterministic and
+/// non-deterministic automata.
+pub fn run_labels(a: &Automaton, labels: &[u32]) -> bool {
+    if a.num_states() == 0 {
+        return false;
+    }
+
+    if a.is_deterministic() {
+        let mut state = 0;
+        for &label in labels {
+            match a.step(state, label) {
+                Some(next) => state = next,
+                None => return false,
+            }
+        }
+        return a.is_accept(state);
+    }
+
+    let mut current = vec![0];
+    let mut seen = vec![false; a.num_states()];
+    for &label in labels {
+        let mut next = Vec::new();
+        seen.iter_mut().for_each(|s| *s = false);
+        for &state in current.iter() {
+            for t in a.transitions(state) {
+                if t.min <= label && label <= t.max && !seen[t.dest] {
+                    seen[t.dest] = true;
+                    next.push(t.dest);
+                }
+            }
+        }
+
+        if next.is_empty() {
+            return false;
+        }
+        current = next;
+    }
+
+    current.iter().any(|&s| a.is_accept(s))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::util::automaton::{
+        complement, concatenate, determinize, intersection, is_empty, make_any_string, make_char_range, make_string,
+        minus, repeat, repeat_range, run, union, DEFAULT_DETERMINIZE_WORK_LIMIT,
+    };
+
+    #[test]
+    fn test_concatenate_and_union() {
+        let ab = concatenate(&make_string("a"), &make_string("b"));
+        let a = union(&[&ab, &make_string("c")]);
+        assert!(run(&a, "ab"));
+        assert!(run(&a, "c"));
+        assert!(!run(&a, "a"));
+        assert!(!run(&a, "abc"));
+
+        let d = determinize(&a, DEFAULT_DETERMINIZE_WORK_LIMIT).unwrap();
+        assert!(d.is_deterministic());
+        for s in ["ab", "c", "a", "abc", ""] {
+            assert_eq!(run(&a, s), run(&d, s), "{s}");
+        }
+    }
+
+    #[test]
+    fn test_repeat() {
+        let a = repeat(&make_string("ab"));
+        assert!(run(&a, ""));
+        assert!(run(&a, "abab"));
+        assert!(!run(&a, "aba"));
+
+        let a = repeat_range(&make_char_range('a' as u32, 'b' as u32), 2, 3);
+        assert!(!run(&a, "a"));
+        assert!(run(&a, "ab"));
+        assert!(run(&a, "bab"));
+        assert!(!run(&a, "abab"));
+    }
+
+    #[test]
+    fn test_complement_and_intersection() {
+        let not_foo = complement(&make_string("foo"), DEFAULT_DETERMINIZE_WORK_LIMIT).unwrap();
+        assert!(!run(&not_foo, "foo"));
+        assert!(run(&not_foo, "fo"));
+        assert!(run(&not_foo, "fooo"));
+        assert!(run(&not_foo, ""));
+
+        let starts_with_f = concatenate(&make_string("f"), &make_any_string());
+        let a = intersection(&starts_with_f, &not_foo);
+        assert!(run(&a, "fo"));
+        assert!(!run(&a, "foo"));
+        assert!(!run(&a, "bar"));
+
+        let nothing = minus(&make_string("foo"), &make_string("foo"), DEFAULT_DETERMINIZE_WORK_LIMIT).unwrap();
+        assert!(is_empty(&nothing));
+    }
+}
@@ -0,0 +1,732 @@
+use {
+    crate::{
+        util::automaton::{
+            case_insensitive_alternatives, complement, concatenate, determinize, intersection, make_any_char,
+            make_any_string, make_char, make_decimal_interval, make_empty, make_empty_string, make_string, optional,
+            remove_dead_states, repeat, repeat_min, repeat_range, union, Automaton, DEFAULT_DETERMINIZE_WORK_LIMIT,
+            MAX_CODE_POINT,
+        },
+        BoxResult, LuceneError,
+    },
+    std::{
+        collections::HashMap,
+        fmt::{Display, Formatter, Result as FmtResult},
+    },
+};
+
+/// Supplies named automata referenced from a regular expression using the `<identifier>` syntax.
+pub trait AutomatonProvider {
+    /// Returns the automaton with the given name, or `None` if it is not known.
+    fn get_automaton(&self, name: &str) -> BoxResult<Option<Automaton>>;
+}
+
+impl AutomatonProvider for HashMap<String, Automaton> {
+    fn get_automaton(&self, name: &str) -> BoxResult<Option<Automaton>> {
+        Ok(self.get(name).cloned())
+    }
+}
+
+/// A regular expression in Lucene's syntax, which can be compiled into an [Automaton].
+///
+/// The core syntax is:
+///
+/// ```text
+/// regexp     ::= unionexp
+/// unionexp   ::= interexp | unionexp              (union)
+///              | interexp
+/// interexp   ::= concatexp & interexp             (intersection)           [INTERSECTION]
+///              | concatexp
+/// concatexp  ::= repeatexp concatexp              (concatenation)
+///              | repeatexp
+/// repeatexp  ::= repeatexp ?                      (zero or one occurrence)
+///              | repeatexp *                      (zero or more occurrences)
+///              | repeatexp +                      (one or more occurrences)
+///              | repeatexp {n}                    (n occurrences)
+///              | repeatexp {n,}                   (n or more occurrences)
+///              | repeatexp {n,m}                  (n to m occurrences, including both)
+///              | complexp
+/// complexp   ::= ~ complexp                       (complement)             [COMPLEMENT]
+///              | charclassexp
+/// charclassexp ::= [ charclasses ]                (character class)
+///              | [^ charclasses ]                 (negated character class)
+///              | simpleexp
+/// charclasses ::= charclass charclasses
+///              | charclass
+/// charclass  ::= charexp - charexp                (character range, including end-points)
+///              | charexp
+/// simpleexp  ::= charexp
+///              | .                                (any single character)
+///              | #                                (the empty language)     [EMPTY]
+///              | @                                (any string)             [ANYSTRING]
+///              | " <Unicode string without double-quotes> "  (a string)
+///              | ( )                              (the empty string)
+///              | ( unionexp )                     (precedence override)
+///              | < <identifier> >                 (named automaton)        [AUTOMATON]
+///              | <n-m>                            (numerical interval)     [INTERVAL]
+/// charexp    ::= <Unicode character>              (a single non-reserved character)
+///              | \d | \D | \s | \S | \w | \W      (digit, space and word character classes)
+///              | \ <Unicode character>            (a single character)
+/// ```
+///
+/// Operators marked with a flag in brackets are only recognized when that flag is enabled; otherwise their
+/// characters are treated literally.
+#[derive(Clone, Debug)]
+pub struct RegExp {
+    original: String,
+    flags: u32,
+    node: Node,
+}
+
+/// The parsed form of a regular expression.
+#[derive(Clone, Debug)]
+enum Node {
+    Union(Box<Node>, Box<Node>),
+    Concatenation(Box<Node>, Box<Node>),
+    Intersection(Box<Node>, Box<Node>),
+    Optional(Box<Node>),
+    Repeat(Box<Node>),
+    RepeatMin(Box<Node>, usize),
+    RepeatMinMax(Box<Node>, usize, usize),
+    Complement(Box<Node>),
+    Char(u32),
+    CharClass(Vec<(u32, u32)>),
+    AnyChar,
+    Empty,
+    String(String),
+    AnyString,
+    Automaton(String),
+    Interval(u64, u64, usize),
+}
+
+impl RegExp {
+    /// Enables intersection (`&`).
+    pub const INTERSECTION: u32 = 0x0001;
+
+    /// Enables complement (`~`).
+    pub const COMPLEMENT: u32 = 0x0002;
+
+    /// Enables the empty language (`#`).
+    pub const EMPTY: u32 = 0x0004;
+
+    /// Enables any string (`@`).
+    pub const ANYSTRING: u32 = 0x0008;
+
+    /// Enables named automata (`<identifier>`).
+    pub const AUTOMATON: u32 = 0x0010;
+
+    /// Enables numerical intervals (`<n-m>`).
+    pub const INTERVAL: u32 = 0x0020;
+
+    /// Enables all optional regular expression syntax.
+    pub const ALL: u32 = 0x00ff;
+
+    /// Matches characters and strings regardless of case, by their Unicode simple case folding (see
+    /// [crate::util::automaton::case_insensitive_alternatives]). Character classes and ranges are matched as
+    /// written. This isn't part of [RegExp::ALL].
+    pub const CASE_INSENSITIVE: u32 = 0x0100;
+
+    /// Enables no optional regular expression syntax.
+    pub const NONE: u32 = 0x0000;
+
+    /// Parses the given regular expression with all optional syntax ([RegExp::ALL]) enabled.
+    pub fn new(s: &str) -> BoxResult<Self> {
+        Self::with_flags(s, Self::ALL)
+    }
+
+    /// Parses the given regular expression with only the optional syntax given by `flags` enabled.
+    pub fn with_flags(s: &str, flags: u32) -> BoxResult<Self> {
+        let mut parser = Parser {
+            chars: s.chars().collect(),
+            pos: 0,
+            flags,
+        };
+
+        let node = if parser.chars.is_empty() {
+            Node::String(String::new())
+        } else {
+            let node = parser.parse_union()?;
+            if parser.more() {
+                return Err(parser.error("end of string expected"));
+            }
+            node
+        };
+        let node = if flags & Self::CASE_INSENSITIVE != 0 {
+            fold_case(node)
+        } else {
+            node
+        };
+
+        Ok(Self {
+            original: s.to_string(),
+            flags,
+            node,
+        })
+    }
+
+    /// Returns the original string this regular expression was parsed from.
+    #[inline]
+    pub fn original(&self) -> &str {
+        &self.original
+    }
+
+    /// Returns the flags this regular expression was parsed with.
+    #[inline]
+    pub fn flags(&self) -> u32 {
+        self.flags
+    }
+
+    /// Returns the names of all automata referenced by this regular expression.
+    pub fn identifiers(&self) -> Vec<String> {
+        fn collect(node: &Node, names: &mut Vec<String>) {
+            match node {
+                Node::Union(a, b) | Node::Concatenation(a, b) | Node::Intersection(a, b) => {
+                    collect(a, names);
+                    collect(b, names);
+                }
+                Node::Optional(a)
+                | Node::Repeat(a)
+                | Node::RepeatMin(a, _)
+                | Node::RepeatMinMax(a, _, _)
+                | Node::Complement(a) => collect(a, names),
+                Node::Automaton(name) => names.push(name.clone()),
+                _ => (),
+            }
+        }
+
+        let mut names = Vec::new();
+        collect(&self.node, &mut names);
+        names
+    }
+
+    /// Compiles this regular expression into a deterministic automaton.
+    pub fn to_automaton(&self) -> BoxResult<Automaton> {
+        self.to_automaton_with_provider(None, DEFAULT_DETERMINIZE_WORK_LIMIT)
+    }
+
+    /// Compiles this regular expression into a deterministic automaton, resolving named automata with `provider`
+    /// and giving up with [LuceneError::TooComplexToDeterminize] if determinizing needs more than `work_limit`
+    /// effort.
+    pub fn to_automaton_with_provider(
+        &self,
+        provider: Option<&dyn AutomatonProvider>,
+        work_limit: usize,
+    ) -> BoxResult<Automaton> {
+        let a = to_automaton(&self.node, provider, work_limit)?;
+        Ok(remove_dead_states(&determinize(&a, work_limit)?))
+    }
+}
+
+impl Display for RegExp {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        f.write_str(&self.original)
+    }
+}
+
+/// Replaces the characters and strings of a parsed expression with classes of their case-insensitive alternatives.
+fn fold_case(node: Node) -> Node {
+    let fold_char = |c: u32| match case_insensitive_alternatives(c) {
+        alternatives if alternatives.len() == 1 => Node::Char(c),
+        alternatives => Node::CharClass(alternatives.into_iter().map(|c| (c, c)).collect()),
+    };
+
+    match node {
+        Node::Union(a, b) => Node::Union(Box::new(fold_case(*a)), Box::new(fold_case(*b))),
+        Node::Concatenation(a, b) => Node::Concatenation(Box::new(fold_case(*a)), Box::new(fold_case(*b))),
+        Node::Intersection(a, b) => Node::Intersection(Box::new(fold_case(*a)), Box::new(fold_case(*b))),
+        Node::Optional(a) => Node::Optional(Box::new(fold_case(*a))),
+        Node::Repeat(a) => Node::Repeat(Box::new(fold_case(*a))),
+        Node::RepeatMin(a, min) => Node::RepeatMin(Box::new(fold_case(*a)), min),
+        Node::RepeatMinMax(a, min, max) => Node::RepeatMinMax(Box::new(fold_case(*a)), min, max),
+        Node::Complement(a) => Node::Complement(Box::new(fold_case(*a))),
+        Node::Char(c) => fold_char(c),
+        Node::String(s) if s.chars().any(|c| case_insensitive_alternatives(c as u32).len() > 1) => {
+            s.chars().map(|c| fold_char(c as u32)).reduce(|a, b| Node::Concatenation(Box::new(a), Box::new(b))).unwrap()
+        }
+        node => node,
+    }
+}
+
+fn to_automaton(node: &Node, provider: Option<&dyn AutomatonProvider>, work_limit: usize) -> BoxResult<Automaton> {
+    let a = match node {
+        Node::Union(a, b) => union(&[&to_automaton(a, provider, work_limit)?, &to_automaton(b, provider, work_limit)?]),
+        Node::Concatenation(a, b) => {
+            concatenate(&to_automaton(a, provider, work_limit)?, &to_automaton(b, provider, work_limit)?)
+        }
+        Node::Intersection(a, b) => {
+            intersection(&to_automaton(a, provider, work_limit)?, &to_automaton(b, provider, work_limit)?)
+        }
+        Node::Optional(a) => optional(&to_automaton(a, provider, work_limit)?),
+        Node::Repeat(a) => repeat(&to_automaton(a, provider, work_limit)?),
+        Node::RepeatMin(a, min) => repeat_min(&to_automaton(a, provider, work_limit)?, *min),
+        Node::RepeatMinMax(a, min, max) => repeat_range(&to_automaton(a, provider, work_limit)?, *min, *max),
+        Node::Complement(a) => complement(&to_automaton(a, provider, work_limit)?, work_limit)?,
+        Node::Char(c) => make_char(*c),
+        Node::CharClass(ranges) => {
+            let mut a = Automaton::new();
+            let start = a.create_state();
+            let end = a.create_state();
+            a.set_accept(end, true);
+            for &(min, max) in ranges.iter() {
+                a.add_transition_range(start, end, min, max);
+            }
+            a.finish();
+            a
+        }
+        Node::AnyChar => make_any_char(),
+        Node::Empty => make_empty(),
+        Node::String(s) if s.is_empty() => make_empty_string(),
+        Node::String(s) => make_string(s),
+        Node::AnyString => make_any_string(),
+        Node::Automaton(name) => {
+            let found = match provider {
+                Some(provider) => provider.get_automaton(name)?,
+                None => None,
+            };
+
+            match found {
+                Some(a) => a,
+                None => return Err(LuceneError::InvalidRegExp(format!("'{name}' not found")).into()),
+            }
+        }
+        Node::Interval(min, max, digits) => match make_decimal_interval(*min, *max, *digits) {
+            Some(a) => a,
+            None => return Err(LuceneError::InvalidRegExp(format!("invalid interval <{min}-{max}>")).into()),
+        },
+    };
+
+    Ok(a)
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    flags: u32,
+}
+
+impl Parser {
+    #[inline]
+    fn check(&self, flag: u32) -> bool {
+        self.flags & flag != 0
+    }
+
+    #[inline]
+    fn more(&self) -> bool {
+        self.pos < self.chars.len()
+    }
+
+    #[inline]
+    fn peek(&self, set: &str) -> bool {
+        self.more() && set.contains(self.chars[self.pos])
+    }
+
+    fn matches(&mut self, c: char) -> bool {
+        if self.more() && self.chars[self.pos] == c {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn next(&mut self) -> BoxResult<char> {
+        if !self.more() {
+            return Err(self.error("unexpected end of string"));
+        }
+        self.pos += 1;
+        Ok(self.chars[self.pos - 1])
+    }
+
+    fn error(&self, message: &str) -> Box<dyn std::error::Error + Send + Sync> {
+        LuceneError::InvalidRegExp(format!("{message} at position {}", self.pos)).into()
+    }
+
+    fn parse_union(&mut self) -> BoxResult<Node> {
+        let e = self.parse_intersection()?;
+        if self.matches('|') {
+            Ok(Node::Union(Box::new(e), Box::new(self.parse_union()?)))
+        } else {
+            Ok(e)
+        }
+    }
+
+    fn parse_intersection(&mut self) -> BoxResult<Node> {
+        let e = self.parse_concatenation()?;
+        if self.check(RegExp::INTERSECTION) && self.matches('&') {
+            Ok(Node::Intersection(Box::new(e), Box::new(self.parse_intersection()?)))
+        } else {
+            Ok(e)
+        }
+    }
+
+    fn parse_concatenation(&mut self) -> BoxResult<Node> {
+        let e = self.parse_repeat()?;
+        if self.more() && !self.peek(")|") && (!self.check(RegExp::INTERSECTION) || !self.peek("&")) {
+            Ok(Node::Concatenation(Box::new(e), Box::new(self.parse_concatenation()?)))
+        } else {
+            Ok(e)
+        }
+    }
+
+    fn parse_repeat(&mut self) -> BoxResult<Node> {
+        let mut e = self.parse_complement()?;
+        while self.peek("?*+{") {
+            if self.matches('?') {
+                e = Node::Optional(Box::new(e));
+            } else if self.matches('*') {
+                e = Node::Repeat(Box::new(e));
+            } else if self.matches('+') {
+                e = Node::RepeatMin(Box::new(e), 1);
+            } else if self.matches('{') {
+                let min = self.parse_integer()?;
+                let max = if self.matches(',') {
+                    if self.peek("0123456789") {
+                        Some(self.parse_integer()?)
+                    } else {
+                        None
+                    }
+                } else {
+                    Some(min)
+                };
+
+                if !self.matches('}') {
+                    return Err(self.error("expected '}'"));
+                }
+
+                e = match max {
+                    None => Node::RepeatMin(Box::new(e), min),
+                    Some(max) => Node::RepeatMinMax(Box::new(e), min, max),
+                };
+            }
+        }
+
+        Ok(e)
+    }
+
+    fn parse_integer(&mut self) -> BoxResult<usize> {
+        let start = self.pos;
+        while self.peek("0123456789") {
+            self.pos += 1;
+        }
+
+        if start == self.pos {
+            return Err(self.error("integer expected"));
+        }
+
+        let digits: String = self.chars[start..self.pos].iter().collect();
+        digits.parse().map_err(|_| self.error("integer out of range"))
+    }
+
+    fn parse_complement(&mut self) -> BoxResult<Node> {
+        if self.check(RegExp::COMPLEMENT) && self.matches('~') {
+            Ok(Node::Complement(Box::new(self.parse_complement()?)))
+        } else {
+            self.parse_char_class_exp()
+        }
+    }
+
+    fn parse_char_class_exp(&mut self) -> BoxResult<Node> {
+        if !self.matches('[') {
+            return self.parse_simple();
+        }
+
+        let negate = self.matches('^');
+        let mut ranges = Vec::new();
+        while self.more() && !self.peek("]") {
+            self.parse_char_class(&mut ranges)?;
+        }
+
+        if !self.matches(']') {
+            return Err(self.error("expected ']'"));
+        }
+
+        let ranges = normalize_ranges(ranges);
+        Ok(Node::CharClass(if negate {
+            invert_ranges(&ranges)
+        } else {
+            ranges
+        }))
+    }
+
+    fn parse_char_class(&mut self, ranges: &mut Vec<(u32, u32)>) -> BoxResult<()> {
+        if let Some(class) = self.parse_shorthand() {
+            ranges.extend(class);
+            return Ok(());
+        }
+
+        let c = self.parse_char()?;
+        if self.matches('-') {
+            if self.peek("]") {
+                // A trailing '-' is a literal.
+                ranges.push((c, c));
+                ranges.push(('-' as u32, '-' as u32));
+            } else {
+                let end = self.parse_char()?;
+                if c > end {
+                    return Err(self.error("illegal character range"));
+                }
+                ranges.push((c, end));
+            }
+        } else {
+            ranges.push((c, c));
+        }
+
+        Ok(())
+    }
+
+    /// Parses one of the `\d`, `\D`, `\s`, `\S`, `\w`, or `\W` shorthand classes, if present.
+    fn parse_shorthand(&mut self) -> Option<Vec<(u32, u32)>> {
+        if self.pos + 1 >= self.chars.len() || self.chars[self.pos] != '\\' {
+            return None;
+        }
+
+        let ranges = match self.chars[self.pos + 1] {
+            'd' => digit_ranges(),
+            'D' => invert_ranges(&digit_ranges()),
+            's' => space_ranges(),
+            'S' => invert_ranges(&space_ranges()),
+            'w' => word_ranges(),
+            'W' => invert_ranges(&word_ranges()),
+            _ => return None,
+        };
+
+        self.pos += 2;
+        Some(ranges)
+    }
+
+    fn parse_simple(&mut self) -> BoxResult<Node> {
+        if let Some(ranges) = self.parse_shorthand() {
+            return Ok(Node::CharClass(ranges));
+        }
+
+        if self.matches('.') {
+            return Ok(Node::AnyChar);
+        }
+
+        if self.check(RegExp::EMPTY) && self.matches('#') {
+            return Ok(Node::Empty);
+        }
+
+        if self.check(RegExp::ANYSTRING) && self.matches('@') {
+            return Ok(Node::AnyString);
+        }
+
+        if self.matches('"') {
+            let start = self.pos;
+            while self.more() && !self.peek("\"") {
+                self.pos += 1;
+            }
+
+            if !self.matches('"') {
+                return Err(self.error("expected '\"'"));
+            }
+
+            return Ok(Node::String(self.chars[start..self.pos - 1].iter().collect()));
+        }
+
+        if self.matches('(') {
+            if self.matches(')') {
+                return Ok(Node::String(String::new()));
+            }
+
+            let e = self.parse_union()?;
+            if !self.matches(')') {
+                return Err(self.error("expected ')'"));
+            }
+
+            return Ok(e);
+        }
+
+        if (self.check(RegExp::AUTOMATON) || self.check(RegExp::INTERVAL)) && self.matches('<') {
+            let start = self.pos;
+            while self.more() && !self.peek(">") {
+                self.pos += 1;
+            }
+
+            if !self.matches('>') {
+                return Err(self.error("expected '>'"));
+            }
+
+            let s: String = self.chars[start..self.pos - 1].iter().collect();
+            return self.parse_angle_bracketed(s);
+        }
+
+        Ok(Node::Char(self.parse_char()?))
+    }
+
+    fn parse_angle_bracketed(&self, s: String) -> BoxResult<Node> {
+        let Some(dash) = s.find('-') else {
+            if !self.check(RegExp::AUTOMATON) {
+                return Err(self.error("interval syntax error"));
+            }
+            return Ok(Node::Automaton(s));
+        };
+
+        if !self.check(RegExp::INTERVAL) {
+            return Err(self.error("illegal identifier"));
+        }
+
+        let (smin, smax) = (&s[..dash], &s[dash + 1..]);
+        if smin.is_empty() || smax.is_empty() {
+            return Err(self.error("interval syntax error"));
+        }
+
+        let (Ok(mut min), Ok(mut max)) = (smin.parse::<u64>(), smax.parse::<u64>()) else {
+            return Err(self.error("interval syntax error"));
+        };
+
+        let digits = if smin.len() == smax.len() {
+            smin.len()
+        } else {
+            0
+        };
+
+        if min > max {
+            std::mem::swap(&mut min, &mut max);
+        }
+
+        Ok(Node::Interval(min, max, digits))
+    }
+
+    fn parse_char(&mut self) -> BoxResult<u32> {
+        self.matches('\\');
+        Ok(self.next()? as u32)
+    }
+}
+
+/// Sorts the given ranges and merges overlapping or adjacent ones.
+fn normalize_ranges(mut ranges: Vec<(u32, u32)>) -> Vec<(u32, u32)> {
+    ranges.sort_unstable();
+    let mut result: Vec<(u32, u32)> = Vec::with_capacity(ranges.len());
+    for (min, max) in ranges {
+        match result.last_mut() {
+            Some(last) if min <= last.1.saturating_add(1) => last.1 = last.1.max(max),
+            _ => result.push((min, max)),
+        }
+    }
+    result
+}
+
+/// Returns the complement of the given normalized ranges within the code point space.
+fn invert_ranges(ranges: &[(u32, u32)]) -> Vec<(u32, u32)> {
+    let mut result = Vec::new();
+    let mut next = 0;
+    for &(min, max) in ranges {
+        if min > next {
+            result.push((next, min - 1));
+        }
+        next = max + 1;
+    }
+
+    if next <= MAX_CODE_POINT {
+        result.push((next, MAX_CODE_POINT));
+    }
+    result
+}
+
+fn digit_ranges() -> Vec<(u32, u32)> {
+    vec![('0' as u32, '9' as u32)]
+}
+
+fn space_ranges() -> Vec<(u32, u32)> {
+    vec![(0x09, 0x0d), (' ' as u32, ' ' as u32)]
+}
+
+fn word_ranges() -> Vec<(u32, u32)> {
+    vec![('0' as u32, '9' as u32), ('A' as u32, 'Z' as u32), ('_' as u32, '_' as u32), ('a' as u32, 'z' as u32)]
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::util::automaton::{make_string, run, Automaton, RegExp},
+        std::collections::HashMap,
+    };
+
+    fn compile(s: &str) -> Automaton {
+        RegExp::new(s).unwrap().to_automaton().unwrap()
+    }
+
+    #[test]
+    fn test_basic_syntax() {
+        let a = compile("ab*c|d+e?");
+        assert!(a.is_deterministic());
+        assert!(run(&a, "ac"));
+        assert!(run(&a, "abbbc"));
+        assert!(run(&a, "d"));
+        assert!(run(&a, "dddde"));
+        assert!(!run(&a, "abd"));
+        assert!(!run(&a, "e"));
+
+        let a = compile("[a-c][^a-c]\\d{2,3}.");
+        assert!(run(&a, "bz12x"));
+        assert!(run(&a, "cx123y"));
+        assert!(!run(&a, "ba12x"));
+        assert!(!run(&a, "bz1x"));
+
+        let a = compile("\"a*b\"x?");
+        assert!(run(&a, "a*b"));
+        assert!(run(&a, "a*bx"));
+        assert!(!run(&a, "ab"));
+
+        let a = compile("");
+        assert!(run(&a, ""));
+        assert!(!run(&a, "a"));
+    }
+
+    #[test]
+    fn test_optional_syntax() {
+        let a = compile("@&~(foo.*)");
+        assert!(run(&a, "bar"));
+        assert!(!run(&a, "food"));
+
+        let a = compile("a#|b");
+        assert!(run(&a, "b"));
+        assert!(!run(&a, "a"));
+
+        let a = compile("x<1-100>");
+        assert!(run(&a, "x1"));
+        assert!(run(&a, "x0042"));
+        assert!(!run(&a, "x101"));
+
+        let a = compile("<10-20>");
+        assert!(run(&a, "15"));
+        assert!(!run(&a, "015"));
+
+        // With no optional syntax, the special characters are literals.
+        let a = RegExp::with_flags("a&b@", RegExp::NONE).unwrap().to_automaton().unwrap();
+        assert!(run(&a, "a&b@"));
+    }
+
+    #[test]
+    fn test_case_insensitive() {
+        let re = RegExp::with_flags("Straße\\.\"ΣΟΦΌΣ\"|[a-c]x", RegExp::ALL | RegExp::CASE_INSENSITIVE).unwrap();
+        let a = re.to_automaton().unwrap();
+        assert!(run(&a, "STRAẞE.σοφός"));
+        assert!(run(&a, "straße.ΣΟΦΌς"));
+        assert!(run(&a, "bX"));
+        assert!(!run(&a, "strasse.σοφός"));
+
+        // Character classes are matched as written.
+        assert!(!run(&a, "Bx"));
+        assert!(!run(&compile("Straße"), "STRAẞE"));
+    }
+
+    #[test]
+    fn test_named_automata() {
+        let mut provider = HashMap::new();
+        provider.insert("greeting".to_string(), make_string("hello"));
+
+        let re = RegExp::new("<greeting> world").unwrap();
+        assert_eq!(re.identifiers(), vec!["greeting".to_string()]);
+        let a = re.to_automaton_with_provider(Some(&provider), 10000).unwrap();
+        assert!(run(&a, "hello world"));
+        assert!(re.to_automaton().is_err());
+    }
+
+    #[test]
+    fn test_syntax_errors() {
+        for bad in ["(ab", "[ab", "a{x}", "a{2", "\"abc", "<1-", "ab)"] {
+            assert!(RegExp::new(bad).is_err(), "{bad} should fail to parse");
+        }
+    }
+}
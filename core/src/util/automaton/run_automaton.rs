@@ -0,0 +1,202 @@
+use {
+    crate::{
+        util::{
+            automaton::{determinize, utf32_to_utf8, Automaton, MAX_CODE_POINT},
+            size_of_vec, Accountable,
+        },
+        BoxResult,
+    },
+    std::ops::Deref,
+};
+
+/// A compact, table-driven representation of a deterministic automaton for fast matching.
+///
+/// The label alphabet is partitioned into classes (intervals on which every state behaves the same), and the
+/// transition function is stored as a dense `state × class` table. For small alphabets (bytes), a direct label to
+/// class map avoids the binary search.
+#[derive(Clone, Debug)]
+pub struct RunAutomaton {
+    alphabet_size: u32,
+    accept: Vec<bool>,
+    points: Vec<u32>,
+    transitions: Vec<Option<u32>>,
+    class_map: Option<Vec<u16>>,
+}
+
+impl RunAutomaton {
+    /// Builds a run automaton for `a` over labels `0..alphabet_size`, determinizing it first if necessary.
+    pub fn new(a: &Automaton, alphabet_size: u32, work_limit: usize) -> BoxResult<Self> {
+        let a = determinize(a, work_limit)?;
+        let points = a.start_points();
+        let num_states = a.num_states();
+
+        let mut transitions = vec![None; num_states * points.len()];
+        for state in 0..num_states {
+            for (class, &point) in points.iter().enumerate() {
+                transitions[state * points.len() + class] = a.step(state, point).map(|dest| dest as u32);
+            }
+        }
+
+        let class_map = (alphabet_size <= 256)
+            .then(|| (0..alphabet_size).map(|label| (points.partition_point(|&p| p <= label) - 1) as u16).collect());
+
+        Ok(Self {
+            alphabet_size,
+            accept: (0..num_states).map(|s| a.is_accept(s)).collect(),
+            points,
+            transitions,
+            class_map,
+        })
+    }
+
+    /// Returns the number of states.
+    #[inline]
+    pub fn size(&self) -> usize {
+        self.accept.len()
+    }
+
+    /// Returns the size of the label alphabet.
+    #[inline]
+    pub fn alphabet_size(&self) -> u32 {
+        self.alphabet_size
+    }
+
+    /// Indicates whether the given state is an accept state.
+    #[inline]
+    pub fn is_accept(&self, state: usize) -> bool {
+        self.accept[state]
+    }
+
+    /// Returns the sorted start points of the label classes.
+    #[inline]
+    pub fn char_intervals(&self) -> &[u32] {
+        &self.points
+    }
+
+    /// Returns the class the given label belongs to.
+    #[inline]
+    pub fn char_class(&self, label: u32) -> usize {
+        match &self.class_map {
+            Some(map) => map[label as usize] as usize,
+            None => self.points.partition_point(|&p| p <= label) - 1,
+        }
+    }
+
+    /// Returns the state reached from `state` on `label`, or `None` if there is no transition.
+    #[inline]
+    pub fn step(&self, state: usize, label: u32) -> Option<usize> {
+        if self.accept.is_empty() || label >= self.alphabet_size {
+            return None;
+        }
+
+        self.transitions[state * self.points.len() + self.char_class(label)].map(|dest| dest as usize)
+    }
+
+    /// Indicates whether the automaton accepts the given sequence of labels.
+    pub fn run_labels<I: IntoIterator<Item = u32>>(&self, labels: I) -> bool {
+        if self.accept.is_empty() {
+            return false;
+        }
+
+        let mut state = 0;
+        for label in labels {
+            match self.step(state, label) {
+                Some(next) => state = next,
+                None => return false,
+            }
+        }
+        self.accept[state]
+    }
+}
+
+impl Accountable for RunAutomaton {
+    fn ram_bytes_used(&self) -> usize {
+        size_of::<Self>()
+            + size_of_vec(&self.accept)
+            + size_of_vec(&self.points)
+            + size_of_vec(&self.transitions)
+            + self.class_map.as_ref().map_or(0, size_of_vec)
+    }
+}
+
+/// A [RunAutomaton] over bytes, for matching terms directly against their (UTF-8 or binary) encoding.
+#[derive(Clone, Debug)]
+pub struct ByteRunAutomaton {
+    inner: RunAutomaton,
+}
+
+impl ByteRunAutomaton {
+    /// Builds a byte run automaton. If `is_binary` is false, `a` is taken to be over Unicode code points and is
+    /// converted to match their UTF-8 encodings; otherwise its labels must already be bytes.
+    pub fn new(a: &Automaton, is_binary: bool, work_limit: usize) -> BoxResult<Self> {
+        let inner = if is_binary {
+            RunAutomaton::new(a, 256, work_limit)?
+        } else {
+            RunAutomaton::new(&utf32_to_utf8(a), 256, work_limit)?
+        };
+        Ok(Self {
+            inner,
+        })
+    }
+
+    /// Indicates whether the automaton accepts the given bytes.
+    pub fn run(&self, bytes: &[u8]) -> bool {
+        self.inner.run_labels(bytes.iter().map(|&b| b as u32))
+    }
+}
+
+impl Deref for ByteRunAutomaton {
+    type Target = RunAutomaton;
+
+    fn deref(&self) -> &RunAutomaton {
+        &self.inner
+    }
+}
+
+/// A [RunAutomaton] over Unicode code points.
+#[derive(Clone, Debug)]
+pub struct CharacterRunAutomaton {
+    inner: RunAutomaton,
+}
+
+impl CharacterRunAutomaton {
+    /// Builds a character run automaton for an automaton over Unicode code points.
+    pub fn new(a: &Automaton, work_limit: usize) -> BoxResult<Self> {
+        Ok(Self {
+            inner: RunAutomaton::new(a, MAX_CODE_POINT + 1, work_limit)?,
+        })
+    }
+
+    /// Indicates whether the automaton accepts the given string.
+    pub fn run(&self, s: &str) -> bool {
+        self.inner.run_labels(s.chars().map(|c| c as u32))
+    }
+}
+
+impl Deref for CharacterRunAutomaton {
+    type Target = RunAutomaton;
+
+    fn deref(&self) -> &RunAutomaton {
+        &self.inner
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::util::automaton::{ByteRunAutomaton, CharacterRunAutomaton, RegExp, DEFAULT_DETERMINIZE_WORK_LIMIT};
+
+    #[test]
+    fn test_run_automata() {
+        let a = RegExp::new("(caf[eé]|th[eé])s?").unwrap().to_automaton().unwrap();
+
+        let chars = CharacterRunAutomaton::new(&a, DEFAULT_DETERMINIZE_WORK_LIMIT).unwrap();
+        let bytes = ByteRunAutomaton::new(&a, false, DEFAULT_DETERMINIZE_WORK_LIMIT).unwrap();
+        for (s, expected) in [("cafe", true), ("cafés", true), ("thé", true), ("caf", false), ("cafex", false)] {
+            assert_eq!(chars.run(s), expected, "{s}");
+            assert_eq!(bytes.run(s.as_bytes()), expected, "{s}");
+        }
+
+        // Invalid UTF-8 is never accepted.
+        assert!(!bytes.run(b"caf\xc3"));
+    }
+}
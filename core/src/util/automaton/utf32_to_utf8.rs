@@ -0,0 +1,139 @@
+use crate::util::automaton::Automaton;
+
+/// The code point ranges encoded with 1, 2, 3 and 4 UTF-8 bytes, excluding the surrogate range (which is not valid in
+/// UTF-8).
+const UTF8_RANGES: [(u32, u32); 5] = [(0, 0x7f), (0x80, 0x7ff), (0x800, 0xd7ff), (0xe000, 0xffff), (0x10000, 0x10ffff)];
+
+const MIN_CONTINUATION: u8 = 0x80;
+const MAX_CONTINUATION: u8 = 0xbf;
+
+/// Converts an automaton over Unicode code points into an equivalent automaton over UTF-8 bytes.
+///
+/// Each code point transition is replaced by a small set of byte paths covering exactly the UTF-8 encodings of its
+/// range. The result accepts the UTF-8 encoding of every string accepted by `a`. It is generally not deterministic,
+/// because transitions on different code point ranges can share leading bytes.
+pub fn utf32_to_utf8(a: &Automaton) -> Automaton {
+    let mut result = Automaton::new();
+    for state in 0..a.num_states() {
+        let s = result.create_state();
+        result.set_accept(s, a.is_accept(state));
+    }
+
+    for state in 0..a.num_states() {
+        for t in a.transitions(state) {
+            for &(lo, hi) in UTF8_RANGES.iter() {
+                let min = t.min.max(lo);
+                let max = t.max.min(hi);
+                if min > max {
+                    continue;
+                }
+
+                let min_bytes = encode(min);
+                let max_bytes = encode(max);
+                add_byte_range(&mut result, state, t.dest, &min_bytes, &max_bytes);
+            }
+        }
+    }
+
+    result.finish();
+    result
+}
+
+fn encode(code_point: u32) -> Vec<u8> {
+    let c = char::from_u32(code_point).expect("code point is a valid scalar value");
+    let mut buf = [0u8; 4];
+    c.encode_utf8(&mut buf).as_bytes().to_vec()
+}
+
+/// Adds paths from `from` to `to` accepting every UTF-8 byte sequence between `lo` and `hi` (inclusive). Both must
+/// encode code points of the same length.
+fn add_byte_range(a: &mut Automaton, from: usize, to: usize, lo: &[u8], hi: &[u8]) {
+    debug_assert_eq!(lo.len(), hi.len());
+    let (lo0, hi0) = (lo[0] as u32, hi[0] as u32);
+    let rest = lo.len() - 1;
+
+    if rest == 0 {
+        a.add_transition_range(from, to, lo0, hi0);
+        return;
+    }
+
+    if lo0 == hi0 {
+        let next = a.create_state();
+        a.add_transition(from, next, lo0);
+        add_byte_range(a, next, to, &lo[1..], &hi[1..]);
+        return;
+    }
+
+    let min_tail = vec![MIN_CONTINUATION; rest];
+    let max_tail = vec![MAX_CONTINUATION; rest];
+
+    // If both tails are unconstrained, any continuation bytes may follow every leading byte in the range.
+    if lo[1..] == min_tail[..] && hi[1..] == max_tail[..] {
+        let next = a.create_state();
+        a.add_transition_range(from, next, lo0, hi0);
+        add_any_continuations(a, next, to, rest);
+        return;
+    }
+
+    let s_lo = a.create_state();
+    a.add_transition(from, s_lo, lo0);
+    add_byte_range(a, s_lo, to, &lo[1..], &max_tail);
+
+    if hi0 - lo0 > 1 {
+        let s_mid = a.create_state();
+        a.add_transition_range(from, s_mid, lo0 + 1, hi0 - 1);
+        add_any_continuations(a, s_mid, to, rest);
+    }
+
+    let s_hi = a.create_state();
+    a.add_transition(from, s_hi, hi0);
+    add_byte_range(a, s_hi, to, &min_tail, &hi[1..]);
+}
+
+/// Adds a path from `from` to `to` accepting any `count` continuation bytes.
+fn add_any_continuations(a: &mut Automaton, from: usize, to: usize, count: usize) {
+    let mut state = from;
+    for i in 0..count {
+        let next = if i + 1 == count {
+            to
+        } else {
+            a.create_state()
+        };
+        a.add_transition_range(state, next, MIN_CONTINUATION as u32, MAX_CONTINUATION as u32);
+        state = next;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::util::automaton::{
+        determinize, make_char_range, run, run_labels, utf32_to_utf8, RegExp, DEFAULT_DETERMINIZE_WORK_LIMIT,
+    };
+
+    fn run_bytes(a: &crate::util::automaton::Automaton, s: &str) -> bool {
+        let labels: Vec<u32> = s.bytes().map(|b| b as u32).collect();
+        run_labels(a, &labels)
+    }
+
+    #[test]
+    fn test_convert_ranges() {
+        // A range spanning every encoding length.
+        let a = make_char_range(0x70, 0x10100);
+        let utf8 = determinize(&utf32_to_utf8(&a), DEFAULT_DETERMINIZE_WORK_LIMIT).unwrap();
+        for c in ['p', 'z', '\u{80}', 'é', '\u{7ff}', '\u{800}', '€', '\u{ffff}', '\u{10000}', '\u{10100}'] {
+            assert!(run_bytes(&utf8, &c.to_string()), "{c:?} should be accepted");
+        }
+        for c in ['a', '\u{6f}', '\u{10101}', '\u{10ffff}'] {
+            assert!(!run_bytes(&utf8, &c.to_string()), "{c:?} should be rejected");
+        }
+    }
+
+    #[test]
+    fn test_convert_matches_code_points() {
+        let a = RegExp::new("[a-zé-ü]+(€|\u{1f600})?").unwrap().to_automaton().unwrap();
+        let utf8 = utf32_to_utf8(&a);
+        for s in ["abc", "éü", "zz€", "a\u{1f600}", "A", "a€€", "\u{1f600}"] {
+            assert_eq!(run(&a, s), run_bytes(&utf8, s), "{s}");
+        }
+    }
+}
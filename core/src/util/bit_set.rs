@@ -0,0 +1,48 @@
+use {
+    crate::{
+        search::{DocIdSetIterator, NO_MORE_DOCS},
+        util::{Accountable, FixedBitSet},
+        BoxResult,
+    },
+    std::fmt::Debug,
+};
+
+/// A set of bits addressed by document id, with random access and iteration over the set bits.
+pub trait BitSet: Accountable + Debug + Send + Sync {
+    /// Returns the number of bits in the set; valid indices are `0..num_bits()`.
+    fn num_bits(&self) -> u32;
+
+    /// Indicates whether the bit at `index` is set.
+    fn get(&self, index: u32) -> bool;
+
+    /// Sets the bit at `index`.
+    fn set(&mut self, index: u32);
+
+    /// Clears the bit at `index`.
+    fn clear(&mut self, index: u32);
+
+    /// Returns the number of set bits.
+    fn cardinality(&self) -> u32;
+
+    /// Returns the index of the first set bit at or after `index`, or [NO_MORE_DOCS] if there is none.
+    fn next_set_bit(&self, index: u32) -> u32;
+
+    /// Returns the index of the last set bit at or before `index`, if any.
+    fn prev_set_bit(&self, index: u32) -> Option<u32>;
+
+    /// Returns this set as a [FixedBitSet], if it is one, so that bulk operations can work on its words directly.
+    fn as_fixed_bit_set(&self) -> Option<&FixedBitSet> {
+        None
+    }
+
+    /// Sets the bits of every document of an iterator, which must be unpositioned.
+    fn or(&mut self, iterator: &mut dyn DocIdSetIterator) -> BoxResult<()> {
+        loop {
+            let doc = iterator.next_doc()?;
+            if doc == NO_MORE_DOCS {
+                return Ok(());
+            }
+            self.set(doc);
+        }
+    }
+}
@@ -0,0 +1,271 @@
+/// The base-2 logarithm of [BYTE_BLOCK_SIZE].
+pub const BYTE_BLOCK_SHIFT: usize = 15;
+
+/// The size of each block of a [ByteBlockPool], in bytes.
+pub const BYTE_BLOCK_SIZE: usize = 1 << BYTE_BLOCK_SHIFT;
+
+/// Masks an offset into a [ByteBlockPool] to an offset within its block.
+pub const BYTE_BLOCK_MASK: usize = BYTE_BLOCK_SIZE - 1;
+
+/// The sizes of the slices at each level. A stream starts in a slice of the first size, and each time it fills up,
+/// continues in a slice of the next level's size.
+pub const LEVEL_SIZE_ARRAY: [usize; 10] = [5, 14, 20, 30, 40, 40, 80, 80, 120, 200];
+
+/// The level following each level.
+pub const NEXT_LEVEL_ARRAY: [usize; 10] = [1, 2, 3, 4, 5, 6, 7, 8, 9, 9];
+
+/// The size of the first slice of a stream.
+pub const FIRST_LEVEL_SIZE: usize = LEVEL_SIZE_ARRAY[0];
+
+/// An append-only arena of bytes, allocated in fixed-size blocks so that growing it never moves existing data.
+///
+/// Besides plain byte strings (see [ByteBlockPool::append]), the pool holds streams of bytes written in linked
+/// slices of increasing size, as Lucene does for the postings of terms being indexed. Each slice ends with a non-zero
+/// level marker; when a writer reaches it, [ByteBlockPool::alloc_slice] replaces the last four bytes of the slice with
+/// the address of the next one. [ByteSliceReader] follows the links back.
+///
+/// Offsets are global across blocks, and a string or slice never spans two blocks.
+///
+/// Blocks can be recycled with [ByteBlockPool::reset], so that a pool reused for several segments doesn't reallocate.
+#[derive(Debug, Default)]
+pub struct ByteBlockPool {
+    blocks: Vec<Box<[u8]>>,
+
+    /// The offset of the first free byte within the last block.
+    byte_upto: usize,
+}
+
+impl ByteBlockPool {
+    /// Creates an empty pool. No memory is allocated until something is written.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the number of bytes allocated by the pool.
+    #[inline]
+    pub fn bytes_allocated(&self) -> usize {
+        self.blocks.len() * BYTE_BLOCK_SIZE
+    }
+
+    /// Returns the global offset just past the last byte written, or 0 if the pool is empty.
+    #[inline]
+    pub fn bytes_used(&self) -> usize {
+        match self.blocks.len() {
+            0 => 0,
+            n => (n - 1) * BYTE_BLOCK_SIZE + self.byte_upto,
+        }
+    }
+
+    /// Discards the contents of the pool. The first block is zeroed and kept for reuse; the others are freed.
+    pub fn reset(&mut self) {
+        let used = if self.blocks.len() > 1 {
+            BYTE_BLOCK_SIZE
+        } else {
+            self.byte_upto
+        };
+        self.blocks.truncate(1);
+        if let Some(block) = self.blocks.first_mut() {
+            block[..used].fill(0);
+        }
+        self.byte_upto = 0;
+    }
+
+    /// Ensures `size` bytes are available in the current block, starting a new block if needed, and returns the
+    /// global offset of the first of them.
+    fn reserve(&mut self, size: usize) -> usize {
+        assert!(size <= BYTE_BLOCK_SIZE, "{size} bytes don't fit in a block");
+        if self.blocks.is_empty() || self.byte_upto + size > BYTE_BLOCK_SIZE {
+            self.blocks.push(vec![0; BYTE_BLOCK_SIZE].into_boxed_slice());
+            self.byte_upto = 0;
+        }
+
+        let offset = (self.blocks.len() - 1) * BYTE_BLOCK_SIZE + self.byte_upto;
+        self.byte_upto += size;
+        offset
+    }
+
+    /// Copies bytes into the pool, returning their global offset. At most [BYTE_BLOCK_SIZE] bytes can be appended at
+    /// once.
+    pub fn append(&mut self, bytes: &[u8]) -> usize {
+        let offset = self.reserve(bytes.len());
+        let start = offset & BYTE_BLOCK_MASK;
+        self.blocks[offset >> BYTE_BLOCK_SHIFT][start..start + bytes.len()].copy_from_slice(bytes);
+        offset
+    }
+
+    /// Returns the `len` bytes at a global offset, which must not span two blocks.
+    #[inline]
+    pub fn bytes(&self, offset: usize, len: usize) -> &[u8] {
+        let start = offset & BYTE_BLOCK_MASK;
+        &self.blocks[offset >> BYTE_BLOCK_SHIFT][start..start + len]
+    }
+
+    /// Returns the byte at a global offset.
+    #[inline]
+    pub fn byte(&self, offset: usize) -> u8 {
+        self.blocks[offset >> BYTE_BLOCK_SHIFT][offset & BYTE_BLOCK_MASK]
+    }
+
+    #[inline]
+    fn set_byte(&mut self, offset: usize, value: u8) {
+        self.blocks[offset >> BYTE_BLOCK_SHIFT][offset & BYTE_BLOCK_MASK] = value;
+    }
+
+    /// Allocates the first slice of a new stream, returning its global offset.
+    pub fn new_slice(&mut self) -> usize {
+        let offset = self.reserve(FIRST_LEVEL_SIZE);
+        self.set_byte(offset + FIRST_LEVEL_SIZE - 1, 16);
+        offset
+    }
+
+    /// Continues a stream whose writer reached the level marker at `upto` in a new, larger slice, and returns the
+    /// global offset to continue writing at.
+    pub fn alloc_slice(&mut self, upto: usize) -> usize {
+        let level = (self.byte(upto) & 15) as usize;
+        let new_level = NEXT_LEVEL_ARRAY[level];
+        let new_size = LEVEL_SIZE_ARRAY[new_level];
+        let offset = self.reserve(new_size);
+
+        // The last three bytes of the full slice move to the new slice, and together with the marker, make room for
+        // the address of the new slice.
+        for i in 0..3 {
+            let value = self.byte(upto - 3 + i);
+            self.set_byte(offset + i, value);
+        }
+        for (i, value) in (offset as u32).to_le_bytes().into_iter().enumerate() {
+            self.set_byte(upto - 3 + i, value);
+        }
+        self.set_byte(offset + new_size - 1, 16 | new_level as u8);
+        offset + 3
+    }
+
+    /// Writes a byte to the stream whose writer is at `upto`, following on to a new slice if the current one is full.
+    pub fn write_byte(&mut self, upto: &mut usize, value: u8) {
+        if self.byte(*upto) != 0 {
+            *upto = self.alloc_slice(*upto);
+        }
+        self.set_byte(*upto, value);
+        *upto += 1;
+    }
+
+    /// Writes a variable-length integer to the stream whose writer is at `upto`: seven bits per byte, least
+    /// significant first, with the high bit set on all but the last byte.
+    pub fn write_vint(&mut self, upto: &mut usize, mut value: u32) {
+        while value >= 0x80 {
+            self.write_byte(upto, (value as u8 & 0x7f) | 0x80);
+            value >>= 7;
+        }
+        self.write_byte(upto, value as u8);
+    }
+}
+
+/// Reads a stream written to a [ByteBlockPool] in slices, from its first slice up to the writer's position.
+#[derive(Debug)]
+pub struct ByteSliceReader<'a> {
+    pool: &'a ByteBlockPool,
+    level: usize,
+    upto: usize,
+    limit: usize,
+    end: usize,
+}
+
+impl<'a> ByteSliceReader<'a> {
+    /// Creates a reader for the stream that starts at `start` and whose writer is at `end`.
+    pub fn new(pool: &'a ByteBlockPool, start: usize, end: usize) -> Self {
+        Self {
+            pool,
+            level: 0,
+            upto: start,
+            limit: Self::limit(start, FIRST_LEVEL_SIZE, end),
+            end,
+        }
+    }
+
+    /// Returns where the readable bytes of a slice stop: at the end of the stream, or before the address of the
+    /// next slice.
+    fn limit(start: usize, size: usize, end: usize) -> usize {
+        if start + size >= end {
+            end
+        } else {
+            start + size - 4
+        }
+    }
+
+    /// Indicates whether the whole stream has been read.
+    #[inline]
+    pub fn eof(&self) -> bool {
+        self.upto == self.end
+    }
+
+    /// Reads the next byte. The stream must not be at its end.
+    pub fn read_byte(&mut self) -> u8 {
+        debug_assert!(!self.eof());
+        if self.upto == self.limit {
+            let address: [u8; 4] = self.pool.bytes(self.limit, 4).try_into().unwrap();
+            let next = u32::from_le_bytes(address) as usize;
+            self.level = NEXT_LEVEL_ARRAY[self.level];
+            self.upto = next;
+            self.limit = Self::limit(next, LEVEL_SIZE_ARRAY[self.level], self.end);
+        }
+
+        let value = self.pool.byte(self.upto);
+        self.upto += 1;
+        value
+    }
+
+    /// Reads a variable-length integer written by [ByteBlockPool::write_vint].
+    pub fn read_vint(&mut self) -> u32 {
+        let mut value = 0;
+        let mut shift = 0;
+        loop {
+            let byte = self.read_byte();
+            value |= ((byte & 0x7f) as u32) << shift;
+            if byte & 0x80 == 0 {
+                return value;
+            }
+            shift += 7;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::util::{ByteBlockPool, ByteSliceReader, BYTE_BLOCK_SIZE},
+        pretty_assertions::assert_eq,
+    };
+
+    #[test]
+    fn test_interleaved_slices() {
+        let mut pool = ByteBlockPool::new();
+        assert_eq!(pool.bytes_allocated(), 0);
+
+        // Three streams written in turn, so that their slices interleave and span several blocks.
+        let starts: Vec<usize> = (0..3).map(|_| pool.new_slice()).collect();
+        let mut uptos = starts.clone();
+        for value in 0..20_000u32 {
+            for (stream, upto) in uptos.iter_mut().enumerate() {
+                pool.write_vint(upto, value * (stream as u32 + 1));
+            }
+        }
+        assert!(pool.bytes_allocated() > BYTE_BLOCK_SIZE);
+
+        for (stream, (&start, &end)) in starts.iter().zip(&uptos).enumerate() {
+            let mut reader = ByteSliceReader::new(&pool, start, end);
+            for value in 0..20_000u32 {
+                assert_eq!(reader.read_vint(), value * (stream as u32 + 1));
+            }
+            assert!(reader.eof());
+        }
+
+        let offset = pool.append(b"quick");
+        assert_eq!(pool.bytes(offset, 5), b"quick");
+
+        pool.reset();
+        assert_eq!(pool.bytes_allocated(), BYTE_BLOCK_SIZE);
+        assert_eq!(pool.bytes_used(), 0);
+        let start = pool.new_slice();
+        assert_eq!(start, 0);
+        assert_eq!(pool.bytes(0, 4), &[0, 0, 0, 0]);
+    }
+}
@@ -0,0 +1,176 @@
+use std::cmp::Ordering;
+
+/// Buckets no larger than this are sorted by comparison instead of being split further.
+const RADIX_SORT_THRESHOLD: usize = 32;
+
+/// The number of bytes the radix sort splits on before falling back to comparison sorting, to bound its recursion on
+/// strings with long common prefixes.
+const RADIX_SORT_MAX_DEPTH: usize = 8;
+
+/// An append-only list of byte strings, stored back to back in a single buffer rather than one allocation each.
+///
+/// Strings are addressed by their index, in the order they were appended; [BytesRefArray::sorted_indices] gives the
+/// indices ordered by the strings' bytes.
+#[derive(Clone, Debug, Default)]
+pub struct BytesRefArray {
+    bytes: Vec<u8>,
+    ends: Vec<usize>,
+}
+
+impl BytesRefArray {
+    /// Creates an empty array.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the number of strings appended.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.ends.len()
+    }
+
+    /// Indicates whether no strings have been appended.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.ends.is_empty()
+    }
+
+    /// Returns the memory used by the array, in bytes.
+    pub fn bytes_used(&self) -> usize {
+        self.bytes.capacity() + self.ends.capacity() * size_of::<usize>()
+    }
+
+    /// Appends a string, returning its index.
+    pub fn append(&mut self, bytes: &[u8]) -> usize {
+        self.bytes.extend_from_slice(bytes);
+        self.ends.push(self.bytes.len());
+        self.ends.len() - 1
+    }
+
+    /// Returns the string at the given index.
+    pub fn get(&self, index: usize) -> &[u8] {
+        let start = match index {
+            0 => 0,
+            _ => self.ends[index - 1],
+        };
+        &self.bytes[start..self.ends[index]]
+    }
+
+    /// Returns an iterator over the strings, in the order they were appended.
+    pub fn iter(&self) -> impl Iterator<Item = &[u8]> + '_ {
+        (0..self.len()).map(|index| self.get(index))
+    }
+
+    /// Removes all strings, keeping the allocated memory.
+    pub fn clear(&mut self) {
+        self.bytes.clear();
+        self.ends.clear();
+    }
+
+    /// Returns the indices of the strings, ordered by their bytes. Equal strings keep no particular order.
+    pub fn sorted_indices(&self) -> Vec<u32> {
+        let mut indices: Vec<u32> = (0..self.len() as u32).collect();
+        sort_by_bytes(&mut indices, |index| self.get(index as usize));
+        indices
+    }
+}
+
+impl<B: AsRef<[u8]>> FromIterator<B> for BytesRefArray {
+    fn from_iter<I: IntoIterator<Item = B>>(iter: I) -> Self {
+        let mut array = Self::new();
+        for bytes in iter {
+            array.append(bytes.as_ref());
+        }
+        array
+    }
+}
+
+/// Sorts ids by the byte strings they refer to, in unsigned lexicographic order.
+///
+/// This is a most-significant-byte-first radix sort, as Lucene uses to sort terms: ids are distributed into buckets
+/// by one byte at a time, and small buckets, or buckets left after several bytes, are finished with a comparison sort.
+pub fn sort_by_bytes<'a, F>(ids: &mut [u32], bytes: F)
+where
+    F: Fn(u32) -> &'a [u8],
+{
+    let mut scratch = vec![0; ids.len()];
+    radix_sort(ids, &mut scratch, 0, 0, &bytes);
+}
+
+fn radix_sort<'a, F>(ids: &mut [u32], scratch: &mut [u32], k: usize, depth: usize, bytes: &F)
+where
+    F: Fn(u32) -> &'a [u8],
+{
+    if ids.len() <= RADIX_SORT_THRESHOLD || depth >= RADIX_SORT_MAX_DEPTH {
+        ids.sort_unstable_by(|&a, &b| compare_suffixes(bytes(a), bytes(b), k));
+        return;
+    }
+
+    // Bucket 0 holds the strings that end before byte k; bucket b + 1 holds those whose byte k is b.
+    let bucket = |id: u32| bytes(id).get(k).map_or(0, |&b| b as usize + 1);
+    let mut counts = [0usize; 257];
+    for &id in ids.iter() {
+        counts[bucket(id)] += 1;
+    }
+
+    // When every string shares byte k, move on to the next byte without redistributing.
+    if let Some(only) = counts.iter().position(|&count| count == ids.len()) {
+        if only != 0 {
+            radix_sort(ids, scratch, k + 1, depth + 1, bytes);
+        }
+        return;
+    }
+
+    let mut starts = [0usize; 257];
+    for b in 1..257 {
+        starts[b] = starts[b - 1] + counts[b - 1];
+    }
+    let mut next = starts;
+    for &id in ids.iter() {
+        let b = bucket(id);
+        scratch[next[b]] = id;
+        next[b] += 1;
+    }
+    ids.copy_from_slice(&scratch[..ids.len()]);
+
+    for b in 1..257 {
+        if counts[b] > 1 {
+            let range = starts[b]..starts[b] + counts[b];
+            radix_sort(&mut ids[range.clone()], &mut scratch[range], k + 1, depth + 1, bytes);
+        }
+    }
+}
+
+#[inline]
+fn compare_suffixes(a: &[u8], b: &[u8], k: usize) -> Ordering {
+    a.get(k..).unwrap_or_default().cmp(b.get(k..).unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use {crate::util::BytesRefArray, pretty_assertions::assert_eq};
+
+    #[test]
+    fn test_bytes_ref_array() {
+        let mut words: Vec<Vec<u8>> = (0..2000u32)
+            .map(|i| format!("{}{}", ["", "pre", "prefix"][i as usize % 3], i * 7919 % 2000).into_bytes())
+            .collect();
+        words.extend([Vec::new(), vec![0xff], vec![0xff, 0], b"pre".to_vec()]);
+        words.extend((0..100).map(|i| [b"common-prefix-".repeat(4), vec![i as u8]].concat()));
+
+        let array: BytesRefArray = words.iter().collect();
+        assert_eq!(array.len(), words.len());
+        assert_eq!(array.get(3), b"1757");
+        assert_eq!(array.iter().collect::<Vec<_>>(), words.iter().map(|w| w.as_slice()).collect::<Vec<_>>());
+
+        let sorted: Vec<&[u8]> = array.sorted_indices().into_iter().map(|i| array.get(i as usize)).collect();
+        let mut expected: Vec<&[u8]> = words.iter().map(|w| w.as_slice()).collect();
+        expected.sort();
+        assert_eq!(sorted, expected);
+
+        let mut array = array;
+        array.clear();
+        assert!(array.is_empty());
+        assert_eq!(array.append(b"again"), 0);
+    }
+}
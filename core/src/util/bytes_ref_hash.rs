@@ -0,0 +1,185 @@
+use {
+    crate::{
+        util::{sort_by_bytes, ByteBlockPool},
+        BoxResult, LuceneError,
+    },
+    std::hash::{DefaultHasher, Hash, Hasher},
+};
+
+/// The maximum length of a term, in bytes. Longer terms are rejected when indexed.
+pub const MAX_TERM_LENGTH: usize = (1 << 15) - 2;
+
+/// Marks an empty slot of the hash table.
+const EMPTY: u32 = u32::MAX;
+
+/// The initial number of slots of the hash table.
+const INITIAL_CAPACITY: usize = 16;
+
+/// Assigns dense, sequential ids to distinct byte strings, such as the terms of a field being indexed.
+///
+/// The strings themselves are stored in a [ByteBlockPool], which can be shared by several hashes and is passed to
+/// each call, each prefixed by its length in one or two bytes. The hash only holds their offsets and an open-addressing
+/// table of ids.
+#[derive(Debug)]
+pub struct BytesRefHash {
+    starts: Vec<u32>,
+    ids: Vec<u32>,
+}
+
+impl Default for BytesRefHash {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BytesRefHash {
+    /// Creates an empty hash.
+    pub fn new() -> Self {
+        Self {
+            starts: Vec::new(),
+            ids: vec![EMPTY; INITIAL_CAPACITY],
+        }
+    }
+
+    /// Returns the number of distinct strings added.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.starts.len()
+    }
+
+    /// Indicates whether no strings have been added.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.starts.is_empty()
+    }
+
+    /// Returns the memory used by the hash, in bytes, excluding the pool.
+    pub fn bytes_used(&self) -> usize {
+        (self.starts.capacity() + self.ids.capacity()) * size_of::<u32>()
+    }
+
+    /// Returns the string with the given id.
+    pub fn get<'a>(&self, pool: &'a ByteBlockPool, id: u32) -> &'a [u8] {
+        let start = self.starts[id as usize] as usize;
+        let first = pool.byte(start) as usize;
+        if first & 0x80 == 0 {
+            pool.bytes(start + 1, first)
+        } else {
+            let len = (first & 0x7f) | (pool.byte(start + 1) as usize) << 7;
+            pool.bytes(start + 2, len)
+        }
+    }
+
+    /// Returns the id of a string, if it has been added.
+    pub fn find(&self, pool: &ByteBlockPool, bytes: &[u8]) -> Option<u32> {
+        match self.ids[self.slot(pool, bytes)] {
+            EMPTY => None,
+            id => Some(id),
+        }
+    }
+
+    /// Adds a string, returning its id and whether it is new. Ids are assigned in order from 0. This fails with
+    /// [LuceneError::InvalidArgument] if the string is longer than [MAX_TERM_LENGTH].
+    pub fn add(&mut self, pool: &mut ByteBlockPool, bytes: &[u8]) -> BoxResult<(u32, bool)> {
+        if bytes.len() > MAX_TERM_LENGTH {
+            return Err(LuceneError::InvalidArgument(format!(
+                "term of {} bytes is longer than the maximum of {MAX_TERM_LENGTH} bytes",
+                bytes.len()
+            ))
+            .into());
+        }
+
+        let slot = self.slot(pool, bytes);
+        if self.ids[slot] != EMPTY {
+            return Ok((self.ids[slot], false));
+        }
+
+        let mut entry = Vec::with_capacity(bytes.len() + 2);
+        if bytes.len() < 0x80 {
+            entry.push(bytes.len() as u8);
+        } else {
+            entry.extend([(bytes.len() & 0x7f) as u8 | 0x80, (bytes.len() >> 7) as u8]);
+        }
+        entry.extend_from_slice(bytes);
+
+        let id = self.starts.len() as u32;
+        self.starts.push(pool.append(&entry) as u32);
+        self.ids[slot] = id;
+        if self.starts.len() * 2 > self.ids.len() {
+            self.rehash(pool);
+        }
+        Ok((id, true))
+    }
+
+    /// Returns the ids of the strings, ordered by their bytes.
+    pub fn sorted_ids(&self, pool: &ByteBlockPool) -> Vec<u32> {
+        let mut ids: Vec<u32> = (0..self.starts.len() as u32).collect();
+        sort_by_bytes(&mut ids, |id| self.get(pool, id));
+        ids
+    }
+
+    /// Returns the slot holding a string, or the empty slot where it belongs.
+    fn slot(&self, pool: &ByteBlockPool, bytes: &[u8]) -> usize {
+        let mask = self.ids.len() - 1;
+        let mut slot = hash(bytes) & mask;
+        loop {
+            match self.ids[slot] {
+                EMPTY => return slot,
+                id if self.get(pool, id) == bytes => return slot,
+                _ => slot = (slot + 1) & mask,
+            }
+        }
+    }
+
+    fn rehash(&mut self, pool: &ByteBlockPool) {
+        let mask = self.ids.len() * 2 - 1;
+        let mut ids = vec![EMPTY; self.ids.len() * 2];
+        for id in 0..self.starts.len() as u32 {
+            let mut slot = hash(self.get(pool, id)) & mask;
+            while ids[slot] != EMPTY {
+                slot = (slot + 1) & mask;
+            }
+            ids[slot] = id;
+        }
+        self.ids = ids;
+    }
+}
+
+fn hash(bytes: &[u8]) -> usize {
+    let mut hasher = DefaultHasher::new();
+    bytes.hash(&mut hasher);
+    hasher.finish() as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::util::{ByteBlockPool, BytesRefHash, MAX_TERM_LENGTH},
+        pretty_assertions::assert_eq,
+    };
+
+    #[test]
+    fn test_bytes_ref_hash() {
+        let mut pool = ByteBlockPool::new();
+        let mut hash = BytesRefHash::new();
+        let long = vec![b'x'; 300];
+
+        let words: Vec<Vec<u8>> = (0..1000).map(|i| format!("term{}", (i * 7919) % 1000).into_bytes()).collect();
+        for (i, word) in words.iter().enumerate() {
+            assert_eq!(hash.add(&mut pool, word).unwrap(), (i as u32, true));
+        }
+        assert_eq!(hash.add(&mut pool, &long).unwrap(), (1000, true));
+        assert_eq!(hash.add(&mut pool, b"term7").unwrap().1, false);
+        assert_eq!(hash.len(), 1001);
+
+        assert_eq!(hash.get(&pool, 1000), &long[..]);
+        assert_eq!(hash.find(&pool, b"term1"), Some(words.iter().position(|w| w == b"term1").unwrap() as u32));
+        assert_eq!(hash.find(&pool, b"term1000"), None);
+
+        let sorted: Vec<&[u8]> = hash.sorted_ids(&pool).into_iter().map(|id| hash.get(&pool, id)).collect();
+        assert!(sorted.windows(2).all(|w| w[0] < w[1]));
+
+        assert!(hash.add(&mut pool, &vec![b'x'; MAX_TERM_LENGTH + 1]).is_err());
+        assert!(hash.add(&mut pool, &vec![b'x'; MAX_TERM_LENGTH]).is_ok());
+    }
+}
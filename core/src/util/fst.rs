@@ -0,0 +1,807 @@
+use {
+    crate::{
+        io::RandomAccessInput,
+        util::{size_of_vec, Accountable},
+        BoxError, BoxResult, LuceneError,
+    },
+    std::{collections::HashMap, sync::Arc},
+};
+
+/// The length of the header of a serialized [Fst]: the root node (u32), the number of inputs (u64), and the numbers
+/// of nodes and arcs (u32 each).
+const HEADER_LENGTH: u64 = 20;
+
+/// The length of a serialized node: the index of its first arc and its number of arcs (u32 each), whether it's final
+/// (u8), and its final output (u64).
+const NODE_LENGTH: u64 = 17;
+
+/// The length of a serialized arc: its label (u8), output (u64) and target node (u32).
+const ARC_LENGTH: u64 = 13;
+
+/// Where the nodes and arcs of an [Fst] are read from once it's loaded with [Fst::load], as with Lucene's
+/// `FSTLoadMode`.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum FstLoadMode {
+    /// The FST is decoded onto the heap, for the fastest lookups. This suits small and frequently searched fields.
+    #[default]
+    OnHeap,
+
+    /// The FST is left in its input, such as a memory-mapped file, and nodes and arcs are decoded as lookups reach
+    /// them. This suits fields with huge term dictionaries, whose FSTs would take too much memory.
+    OffHeap,
+}
+
+/// A minimal acyclic finite state transducer mapping byte strings to `u64` outputs.
+///
+/// Inputs that share prefixes share the arcs leading from the start node, and inputs that share suffixes share the
+/// arcs leading to the final nodes, so an FST over a sorted term dictionary is usually much smaller than the terms
+/// themselves. Outputs are pushed as close to the start node as possible: the output of an input is the sum of the
+/// outputs of the arcs it follows, plus the final output of the node it ends at.
+///
+/// An FST is built with an [FstBuilder] and is immutable. [Fst::get] looks up an input, and an [FstEnum] steps
+/// through the inputs in byte order. An FST can be serialized with [Fst::to_bytes] and loaded back, on or off the
+/// heap, with [Fst::load].
+#[derive(Clone, Debug, Default)]
+pub struct Fst {
+    storage: FstStorage,
+    root: u32,
+    len: usize,
+}
+
+/// The nodes and arcs of an [Fst].
+#[derive(Clone, Debug)]
+enum FstStorage {
+    OnHeap {
+        nodes: Vec<FstNode>,
+        arcs: Vec<FstArc>,
+    },
+
+    /// Serialized nodes and arcs, as written by [Fst::to_bytes].
+    OffHeap {
+        input: Arc<dyn RandomAccessInput>,
+        num_nodes: u32,
+        num_arcs: u32,
+    },
+}
+
+impl Default for FstStorage {
+    fn default() -> Self {
+        Self::OnHeap {
+            nodes: Vec::new(),
+            arcs: Vec::new(),
+        }
+    }
+}
+
+/// A node of an [Fst]: its arcs are `arcs[arcs_start..arcs_start + num_arcs]`, sorted by label.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+struct FstNode {
+    arcs_start: u32,
+    num_arcs: u32,
+    final_output: Option<u64>,
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+struct FstArc {
+    label: u8,
+    output: u64,
+    target: u32,
+}
+
+impl Fst {
+    /// Loads an FST serialized with [Fst::to_bytes] from `input`. With [FstLoadMode::OnHeap], the FST is decoded at
+    /// once; with [FstLoadMode::OffHeap], `input` is kept and read as lookups need. Either way, every node and arc
+    /// is checked once here, so that lookups can't run out of bounds or loop. This fails with
+    /// [LuceneError::CorruptIndex] if `input` is too short for the FST its header describes, or if a node's arcs are
+    /// out of bounds or unsorted, or an arc leads to a node that isn't compiled before its source.
+    pub fn load(input: Arc<dyn RandomAccessInput>, mode: FstLoadMode) -> BoxResult<Self> {
+        let length = input.length();
+        if length < HEADER_LENGTH {
+            return Err(corrupt(format!("an FST needs a header of {HEADER_LENGTH} bytes, but the input has {length}")));
+        }
+
+        let root = input.read_u32_le_at(0)?;
+        let len = usize::try_from(input.read_u64_le_at(4)?)?;
+        let num_nodes = input.read_u32_le_at(12)?;
+        let num_arcs = input.read_u32_le_at(16)?;
+        let expected = HEADER_LENGTH + num_nodes as u64 * NODE_LENGTH + num_arcs as u64 * ARC_LENGTH;
+        if length < expected {
+            return Err(corrupt(format!(
+                "an FST of {num_nodes} nodes and {num_arcs} arcs needs {expected} bytes, but the input has {length}"
+            )));
+        }
+        if root >= num_nodes {
+            return Err(corrupt(format!("the root node {root} of an FST is out of bounds (it has {num_nodes} nodes)")));
+        }
+
+        let fst = Self {
+            storage: FstStorage::OffHeap {
+                input,
+                num_nodes,
+                num_arcs,
+            },
+            root,
+            len,
+        };
+        fst.check_nodes()?;
+        Ok(match mode {
+            FstLoadMode::OnHeap => Self {
+                storage: FstStorage::OnHeap {
+                    nodes: (0..num_nodes).map(|node| fst.node(node)).collect(),
+                    arcs: (0..num_arcs).map(|arc| fst.arc(arc)).collect(),
+                },
+                ..fst
+            },
+            FstLoadMode::OffHeap => fst,
+        })
+    }
+
+    /// Checks the nodes and arcs of an FST read from an input. The builder compiles the targets of a node's arcs
+    /// before the node, so every arc must lead to a lower node, which also rules out cycles.
+    fn check_nodes(&self) -> BoxResult<()> {
+        let FstStorage::OffHeap {
+            input,
+            num_nodes,
+            num_arcs,
+        } = &self.storage
+        else {
+            return Ok(());
+        };
+
+        for index in 0..*num_nodes {
+            let flag = input.read_u8_at(HEADER_LENGTH + index as u64 * NODE_LENGTH + 8)?;
+            if flag > 1 {
+                return Err(corrupt(format!("node {index} of an FST has an invalid final flag {flag}")));
+            }
+            let node = self.node(index);
+            if node.arcs_start as u64 + node.num_arcs as u64 > *num_arcs as u64 {
+                return Err(corrupt(format!(
+                    "the {} arcs of node {index} of an FST start at {}, out of bounds (it has {num_arcs} arcs)",
+                    node.num_arcs, node.arcs_start
+                )));
+            }
+
+            let mut last_label = None;
+            for arc in (node.arcs_start..node.arcs_start + node.num_arcs).map(|arc| self.arc(arc)) {
+                if last_label.is_some_and(|last_label| arc.label <= last_label) {
+                    return Err(corrupt(format!("the arcs of node {index} of an FST aren't sorted by label")));
+                }
+                if arc.target >= index {
+                    return Err(corrupt(format!(
+                        "an arc of node {index} of an FST leads to node {}, which isn't compiled before it",
+                        arc.target
+                    )));
+                }
+                last_label = Some(arc.label);
+            }
+        }
+        Ok(())
+    }
+
+    /// Serializes the FST so that it can be loaded back with [Fst::load]. Nodes and arcs are written as fixed-length
+    /// little-endian records, so that they can be read at random when the FST is off the heap.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let (num_nodes, num_arcs) = (self.num_nodes() as u32, self.num_arcs() as u32);
+        let mut bytes = Vec::with_capacity(
+            (HEADER_LENGTH + num_nodes as u64 * NODE_LENGTH + num_arcs as u64 * ARC_LENGTH) as usize,
+        );
+        bytes.extend_from_slice(&self.root.to_le_bytes());
+        bytes.extend_from_slice(&(self.len as u64).to_le_bytes());
+        bytes.extend_from_slice(&num_nodes.to_le_bytes());
+        bytes.extend_from_slice(&num_arcs.to_le_bytes());
+        for node in (0..num_nodes).map(|node| self.node(node)) {
+            bytes.extend_from_slice(&node.arcs_start.to_le_bytes());
+            bytes.extend_from_slice(&node.num_arcs.to_le_bytes());
+            bytes.push(node.final_output.is_some() as u8);
+            bytes.extend_from_slice(&node.final_output.unwrap_or_default().to_le_bytes());
+        }
+        for arc in (0..num_arcs).map(|arc| self.arc(arc)) {
+            bytes.push(arc.label);
+            bytes.extend_from_slice(&arc.output.to_le_bytes());
+            bytes.extend_from_slice(&arc.target.to_le_bytes());
+        }
+        bytes
+    }
+
+    /// Returns where the nodes and arcs are read from.
+    #[inline]
+    pub fn load_mode(&self) -> FstLoadMode {
+        match self.storage {
+            FstStorage::OnHeap {
+                ..
+            } => FstLoadMode::OnHeap,
+            FstStorage::OffHeap {
+                ..
+            } => FstLoadMode::OffHeap,
+        }
+    }
+
+    /// Returns the number of bytes of the input an off-heap FST is read from, which aren't counted by
+    /// [Accountable::ram_bytes_used]. This is zero for an FST on the heap.
+    pub fn off_heap_bytes(&self) -> u64 {
+        match &self.storage {
+            FstStorage::OnHeap {
+                ..
+            } => 0,
+            FstStorage::OffHeap {
+                input,
+                ..
+            } => input.length(),
+        }
+    }
+
+    /// Returns the output of `input`, or `None` if it isn't in the FST.
+    pub fn get(&self, input: &[u8]) -> Option<u64> {
+        let mut node = self.root;
+        let mut output = 0;
+        for &label in input {
+            let arc = self.find_arc(node, label)?;
+            output += arc.output;
+            node = arc.target;
+        }
+        self.node(node).final_output.map(|final_output| output + final_output)
+    }
+
+    /// Returns the number of inputs.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Indicates whether the FST has no inputs.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the number of nodes, which measures how well the inputs were shared.
+    #[inline]
+    pub fn num_nodes(&self) -> usize {
+        match &self.storage {
+            FstStorage::OnHeap {
+                nodes,
+                ..
+            } => nodes.len(),
+            FstStorage::OffHeap {
+                num_nodes,
+                ..
+            } => *num_nodes as usize,
+        }
+    }
+
+    /// Returns the number of arcs.
+    #[inline]
+    pub fn num_arcs(&self) -> usize {
+        match &self.storage {
+            FstStorage::OnHeap {
+                arcs,
+                ..
+            } => arcs.len(),
+            FstStorage::OffHeap {
+                num_arcs,
+                ..
+            } => *num_arcs as usize,
+        }
+    }
+
+    fn node(&self, node: u32) -> FstNode {
+        match &self.storage {
+            FstStorage::OnHeap {
+                nodes,
+                ..
+            } => nodes[node as usize],
+            FstStorage::OffHeap {
+                input,
+                ..
+            } => {
+                let mut record = [0; NODE_LENGTH as usize];
+                input.read_bytes_at(HEADER_LENGTH + node as u64 * NODE_LENGTH, &mut record).expect(OFF_HEAP_READ);
+                FstNode {
+                    arcs_start: u32::from_le_bytes(record[0..4].try_into().unwrap()),
+                    num_arcs: u32::from_le_bytes(record[4..8].try_into().unwrap()),
+                    final_output: (record[8] != 0).then(|| u64::from_le_bytes(record[9..17].try_into().unwrap())),
+                }
+            }
+        }
+    }
+
+    /// Returns the arc at `index` among all the arcs of the FST.
+    fn arc(&self, index: u32) -> FstArc {
+        match &self.storage {
+            FstStorage::OnHeap {
+                arcs,
+                ..
+            } => arcs[index as usize],
+            FstStorage::OffHeap {
+                input,
+                num_nodes,
+                ..
+            } => {
+                let start = HEADER_LENGTH + *num_nodes as u64 * NODE_LENGTH;
+                let mut record = [0; ARC_LENGTH as usize];
+                input.read_bytes_at(start + index as u64 * ARC_LENGTH, &mut record).expect(OFF_HEAP_READ);
+                FstArc {
+                    label: record[0],
+                    output: u64::from_le_bytes(record[1..9].try_into().unwrap()),
+                    target: u32::from_le_bytes(record[9..13].try_into().unwrap()),
+                }
+            }
+        }
+    }
+
+    /// Returns the number of arcs leaving `node`.
+    #[inline]
+    fn num_node_arcs(&self, node: u32) -> usize {
+        self.node(node).num_arcs as usize
+    }
+
+    /// Returns the arc at `index` among the arcs leaving `node`.
+    #[inline]
+    fn node_arc(&self, node: u32, index: usize) -> FstArc {
+        self.arc(self.node(node).arcs_start + index as u32)
+    }
+
+    /// Returns the index of the first arc leaving `node` whose label isn't less than `label`.
+    fn arc_ceil(&self, node: u32, label: u8) -> usize {
+        let node = self.node(node);
+        let (mut low, mut high) = (0, node.num_arcs);
+        while low < high {
+            let mid = low + (high - low) / 2;
+            if self.arc(node.arcs_start + mid).label < label {
+                low = mid + 1;
+            } else {
+                high = mid;
+            }
+        }
+        low as usize
+    }
+
+    fn find_arc(&self, node: u32, label: u8) -> Option<FstArc> {
+        let index = self.arc_ceil(node, label);
+        (index < self.num_node_arcs(node)).then(|| self.node_arc(node, index)).filter(|arc| arc.label == label)
+    }
+}
+
+/// The reason for panicking when an off-heap FST can't be read, which [Fst::load] rules out by checking the length
+/// of its input and the bounds of every node and arc.
+const OFF_HEAP_READ: &str = "the FST's input was checked to hold every node and arc when it was loaded";
+
+fn corrupt(message: String) -> BoxError {
+    LuceneError::CorruptIndex(message.into()).into()
+}
+
+// FSTs are equal if they have the same nodes and arcs, wherever they're read from.
+impl PartialEq for Fst {
+    fn eq(&self, other: &Self) -> bool {
+        self.root == other.root
+            && self.len == other.len
+            && self.num_nodes() == other.num_nodes()
+            && self.num_arcs() == other.num_arcs()
+            && (0..self.num_nodes() as u32).all(|node| self.node(node) == other.node(node))
+            && (0..self.num_arcs() as u32).all(|arc| self.arc(arc) == other.arc(arc))
+    }
+}
+
+impl Eq for Fst {}
+
+/// Counts the nodes and arcs on the heap: those of an off-heap FST belong to its input (see [Fst::off_heap_bytes]).
+impl Accountable for Fst {
+    fn ram_bytes_used(&self) -> usize {
+        size_of::<Self>()
+            + match &self.storage {
+                FstStorage::OnHeap {
+                    nodes,
+                    arcs,
+                } => size_of_vec(nodes) + size_of_vec(arcs),
+                FstStorage::OffHeap {
+                    ..
+                } => 0,
+            }
+    }
+}
+
+/// A node of the path of the last input added to an [FstBuilder], which may still gain arcs.
+#[derive(Debug, Default)]
+struct UncompiledNode {
+    /// The arcs, sorted by label. The target of the last arc is the next node of the path until it is compiled.
+    arcs: Vec<(u8, u64, Option<u32>)>,
+    final_output: Option<u64>,
+}
+
+impl UncompiledNode {
+    /// Adds `output` to the front of the outputs of every input going through this node.
+    fn prepend_output(&mut self, output: u64) {
+        for arc in &mut self.arcs {
+            arc.1 += output;
+        }
+        if let Some(final_output) = &mut self.final_output {
+            *final_output += output;
+        }
+    }
+}
+
+/// Builds an [Fst] from inputs added in increasing byte order.
+///
+/// Nodes are compiled as soon as no later input can reach them, and equivalent nodes are compiled only once, so the
+/// builder only holds the path of the last input and the nodes compiled so far.
+#[derive(Debug, Default)]
+pub struct FstBuilder {
+    nodes: Vec<FstNode>,
+    arcs: Vec<FstArc>,
+    len: usize,
+    frontier: Vec<UncompiledNode>,
+    last_input: Option<Vec<u8>>,
+    registry: HashMap<(Option<u64>, Vec<FstArc>), u32>,
+}
+
+impl FstBuilder {
+    /// Creates a builder with no inputs.
+    pub fn new() -> Self {
+        Self {
+            frontier: vec![UncompiledNode::default()],
+            ..Default::default()
+        }
+    }
+
+    /// Adds `input`, mapped to `output`. This fails with [LuceneError::InvalidArgument] unless `input` is greater
+    /// than the previous input.
+    pub fn add(&mut self, input: &[u8], mut output: u64) -> BoxResult<()> {
+        let prefix_len = match &self.last_input {
+            Some(last) if last.as_slice() >= input => {
+                return Err(LuceneError::InvalidArgument(format!(
+                    "FST inputs must be added in increasing order: {:?} follows {:?}",
+                    String::from_utf8_lossy(input),
+                    String::from_utf8_lossy(last)
+                ))
+                .into());
+            }
+            Some(last) => last.iter().zip(input).take_while(|(a, b)| a == b).count(),
+            None => 0,
+        };
+
+        // The nodes past the shared prefix can't gain arcs anymore.
+        self.freeze_tail(prefix_len);
+
+        for &label in &input[prefix_len..] {
+            self.frontier.last_mut().unwrap().arcs.push((label, 0, None));
+            self.frontier.push(UncompiledNode::default());
+        }
+        self.frontier[input.len()].final_output = Some(0);
+
+        // Push the shared part of the outputs towards the start node.
+        for depth in 0..prefix_len {
+            let last_arc = self.frontier[depth].arcs.last_mut().unwrap();
+            let common = last_arc.1.min(output);
+            let suffix = last_arc.1 - common;
+            last_arc.1 = common;
+            self.frontier[depth + 1].prepend_output(suffix);
+            output -= common;
+        }
+
+        match self.frontier[prefix_len].arcs.last_mut() {
+            Some(arc) if prefix_len < input.len() => arc.1 = output,
+            _ => self.frontier[prefix_len].final_output = Some(output),
+        }
+
+        self.len += 1;
+        self.last_input = Some(input.to_vec());
+        Ok(())
+    }
+
+    /// Finishes building the FST.
+    pub fn finish(mut self) -> Fst {
+        self.freeze_tail(0);
+        let root = self.frontier.pop().unwrap();
+        let root = self.compile(root);
+        Fst {
+            storage: FstStorage::OnHeap {
+                nodes: self.nodes,
+                arcs: self.arcs,
+            },
+            root,
+            len: self.len,
+        }
+    }
+
+    /// Compiles the nodes of the last input's path that are deeper than `depth`.
+    fn freeze_tail(&mut self, depth: usize) {
+        while self.frontier.len() > depth + 1 {
+            let node = self.frontier.pop().unwrap();
+            let target = self.compile(node);
+            self.frontier.last_mut().unwrap().arcs.last_mut().unwrap().2 = Some(target);
+        }
+    }
+
+    /// Adds a node to the FST, unless an equivalent one was already added, and returns its index.
+    fn compile(&mut self, node: UncompiledNode) -> u32 {
+        let arcs: Vec<FstArc> = node
+            .arcs
+            .into_iter()
+            .map(|(label, output, target)| FstArc {
+                label,
+                output,
+                target: target.expect("arc targets are compiled first"),
+            })
+            .collect();
+
+        let (nodes, fst_arcs) = (&mut self.nodes, &mut self.arcs);
+        *self.registry.entry((node.final_output, arcs)).or_insert_with_key(|(final_output, arcs)| {
+            nodes.push(FstNode {
+                arcs_start: fst_arcs.len() as u32,
+                num_arcs: arcs.len() as u32,
+                final_output: *final_output,
+            });
+            fst_arcs.extend_from_slice(arcs);
+            nodes.len() as u32 - 1
+        })
+    }
+}
+
+/// Steps through the inputs of an [Fst] in byte order, or seeks to the smallest input not less than a target.
+///
+/// The enum is unpositioned when created; [FstEnum::next_input] moves to the first input.
+#[derive(Debug)]
+pub struct FstEnum<'a> {
+    fst: &'a Fst,
+    /// The arcs followed to reach the current node: for each, its source node, its index among the node's arcs, and
+    /// the output accumulated before it.
+    path: Vec<(u32, usize, u64)>,
+    input: Vec<u8>,
+    /// The current node and the output accumulated to reach it, or `None` if unpositioned.
+    current: Option<(u32, u64)>,
+    started: bool,
+}
+
+impl<'a> FstEnum<'a> {
+    /// Creates an unpositioned enum over `fst`.
+    pub fn new(fst: &'a Fst) -> Self {
+        Self {
+            fst,
+            path: Vec::new(),
+            input: Vec::new(),
+            current: None,
+            started: false,
+        }
+    }
+
+    /// Returns the current input. It is empty if the enum is unpositioned.
+    #[inline]
+    pub fn input(&self) -> &[u8] {
+        &self.input
+    }
+
+    /// Returns the output of the current input, or `None` if the enum is unpositioned.
+    pub fn output(&self) -> Option<u64> {
+        self.current.map(|(node, output)| output + self.fst.node(node).final_output.unwrap_or_default())
+    }
+
+    /// Moves to the next input, returning its output, or `None` if there are no more inputs.
+    pub fn next_input(&mut self) -> Option<u64> {
+        if !self.started {
+            self.started = true;
+            if self.fst.is_empty() {
+                return None;
+            }
+            return self.first_from(self.fst.root, 0);
+        }
+
+        let (node, output) = self.current?;
+        if self.fst.num_node_arcs(node) == 0 {
+            self.next_sibling()
+        } else {
+            self.follow(node, 0, output)
+        }
+    }
+
+    /// Moves to the smallest input not less than `target`, returning its output, or `None` if every input is less
+    /// than `target`.
+    pub fn seek_ceil(&mut self, target: &[u8]) -> Option<u64> {
+        self.started = true;
+        self.path.clear();
+        self.input.clear();
+        if self.fst.is_empty() {
+            self.current = None;
+            return None;
+        }
+
+        let (mut node, mut output) = (self.fst.root, 0);
+        for &label in target {
+            let index = self.fst.arc_ceil(node, label);
+            if index == self.fst.num_node_arcs(node) {
+                // Every input through this node is less than the target.
+                self.current = Some((node, output));
+                return self.next_sibling();
+            }
+
+            let arc = self.fst.node_arc(node, index);
+            self.path.push((node, index, output));
+            self.input.push(arc.label);
+            (node, output) = (arc.target, output + arc.output);
+            if arc.label > label {
+                return self.first_from(node, output);
+            }
+        }
+        self.first_from(node, output)
+    }
+
+    /// Moves to the smallest input at or below `node`, which is reached with `output`.
+    fn first_from(&mut self, mut node: u32, mut output: u64) -> Option<u64> {
+        loop {
+            let fst_node = self.fst.node(node);
+            if let Some(final_output) = fst_node.final_output {
+                self.current = Some((node, output));
+                return Some(output + final_output);
+            }
+
+            let arc = self.fst.node_arc(node, 0);
+            self.path.push((node, 0, output));
+            self.input.push(arc.label);
+            (node, output) = (arc.target, output + arc.output);
+        }
+    }
+
+    /// Follows the arc at `index` of `node`, reached with `output`, and moves to the smallest input below it.
+    fn follow(&mut self, node: u32, index: usize, output: u64) -> Option<u64> {
+        let arc = self.fst.node_arc(node, index);
+        self.path.push((node, index, output));
+        self.input.push(arc.label);
+        self.first_from(arc.target, output + arc.output)
+    }
+
+    /// Moves to the smallest input greater than every input at or below the current node.
+    fn next_sibling(&mut self) -> Option<u64> {
+        while let Some((node, index, output)) = self.path.pop() {
+            self.input.pop();
+            if index + 1 < self.fst.num_node_arcs(node) {
+                return self.follow(node, index + 1, output);
+            }
+        }
+        self.current = None;
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::{ARC_LENGTH, HEADER_LENGTH, NODE_LENGTH},
+        crate::{
+            util::{Accountable, Fst, FstBuilder, FstEnum, FstLoadMode},
+            LuceneError,
+        },
+        pretty_assertions::assert_eq,
+        std::sync::Arc,
+    };
+
+    #[test]
+    fn test_fst() {
+        let inputs = ["", "cat", "cats", "dog", "dogs", "mop", "mops", "top", "tops"];
+        let mut builder = FstBuilder::new();
+        for (ord, input) in inputs.iter().enumerate() {
+            builder.add(input.as_bytes(), ord as u64 * 10).unwrap();
+        }
+        assert!(builder.add(b"cat", 100).is_err());
+        let fst = builder.finish();
+
+        assert_eq!(fst.len(), inputs.len());
+        for (ord, input) in inputs.iter().enumerate() {
+            assert_eq!(fst.get(input.as_bytes()), Some(ord as u64 * 10), "{input}");
+        }
+        assert_eq!(fst.get(b"ca"), None);
+        assert_eq!(fst.get(b"catss"), None);
+        assert_eq!(fst.get(b"zoo"), None);
+
+        // Shared suffixes are stored once: "mop(s)" and "top(s)" end at the same nodes.
+        let total: usize = inputs.iter().map(|input| input.len()).sum();
+        assert!(fst.num_arcs() < total, "{} arcs for {total} bytes", fst.num_arcs());
+
+        let mut fst_enum = FstEnum::new(&fst);
+        let mut seen = Vec::new();
+        while let Some(output) = fst_enum.next_input() {
+            seen.push((String::from_utf8(fst_enum.input().to_vec()).unwrap(), output));
+        }
+        let expected: Vec<(String, u64)> =
+            inputs.iter().enumerate().map(|(ord, input)| (input.to_string(), ord as u64 * 10)).collect();
+        assert_eq!(seen, expected);
+        assert_eq!(fst_enum.next_input(), None);
+
+        assert_eq!(fst_enum.seek_ceil(b"cat"), Some(10));
+        assert_eq!(fst_enum.input(), b"cat");
+        assert_eq!(fst_enum.seek_ceil(b"d"), Some(30));
+        assert_eq!(fst_enum.input(), b"dog");
+        assert_eq!(fst_enum.seek_ceil(b"catz"), Some(30));
+        assert_eq!(fst_enum.next_input(), Some(40));
+        assert_eq!(fst_enum.input(), b"dogs");
+        assert_eq!(fst_enum.seek_ceil(b"mopz"), Some(70));
+        assert_eq!(fst_enum.output(), Some(70));
+        assert_eq!(fst_enum.seek_ceil(b"topsy"), None);
+        assert_eq!(fst_enum.output(), None);
+        assert_eq!(fst_enum.seek_ceil(b""), Some(0));
+
+        // Outputs that don't grow with the inputs are pushed and split across shared arcs.
+        let mut inputs: Vec<String> = (0..2000).map(|i| format!("{}", i * 7919 % 10007)).collect();
+        inputs.sort_unstable();
+        inputs.dedup();
+        let mut builder = FstBuilder::new();
+        for (i, input) in inputs.iter().enumerate() {
+            builder.add(input.as_bytes(), (i as u64 * 7919) % 1000).unwrap();
+        }
+        let fst = builder.finish();
+        let mut fst_enum = FstEnum::new(&fst);
+        for (i, input) in inputs.iter().enumerate() {
+            assert_eq!(fst.get(input.as_bytes()), Some((i as u64 * 7919) % 1000), "{input}");
+            assert_eq!(fst_enum.next_input(), Some((i as u64 * 7919) % 1000), "{input}");
+            assert_eq!(fst_enum.input(), input.as_bytes());
+        }
+        for target in ["0", "10", "5000", "99999", "5"] {
+            let expected = inputs.iter().find(|input| input.as_str() >= target);
+            fst_enum.seek_ceil(target.as_bytes());
+            assert_eq!(fst_enum.output().map(|_| fst_enum.input()), expected.map(|input| input.as_bytes()));
+        }
+
+        let empty = FstBuilder::new().finish();
+        assert_eq!(empty.get(b""), None);
+        assert_eq!(FstEnum::new(&empty).next_input(), None);
+        assert_eq!(FstEnum::new(&empty).seek_ceil(b"a"), None);
+    }
+
+    #[test]
+    fn test_load_modes() {
+        let inputs = ["band", "bands", "bend", "bends", "bond", "bonds"];
+        let mut builder = FstBuilder::new();
+        for (ord, input) in inputs.iter().enumerate() {
+            builder.add(input.as_bytes(), ord as u64).unwrap();
+        }
+        let fst = builder.finish();
+        let bytes: Arc<[u8]> = fst.to_bytes().into();
+
+        for mode in [FstLoadMode::OnHeap, FstLoadMode::OffHeap] {
+            let loaded = Fst::load(Arc::new(bytes.clone()), mode).unwrap();
+            assert_eq!(loaded.load_mode(), mode);
+            assert_eq!(loaded, fst);
+            for (ord, input) in inputs.iter().enumerate() {
+                assert_eq!(loaded.get(input.as_bytes()), Some(ord as u64), "{input}");
+            }
+            assert_eq!(loaded.get(b"bind"), None);
+
+            let mut fst_enum = FstEnum::new(&loaded);
+            assert_eq!(fst_enum.seek_ceil(b"bent"), Some(4));
+            assert_eq!(fst_enum.input(), b"bond");
+            assert_eq!(fst_enum.next_input(), Some(5));
+            assert_eq!(fst_enum.next_input(), None);
+        }
+
+        // Only the FST on the heap counts its nodes and arcs.
+        let off_heap = Fst::load(Arc::new(bytes.clone()), FstLoadMode::OffHeap).unwrap();
+        assert_eq!(off_heap.off_heap_bytes(), bytes.len() as u64);
+        assert_eq!(fst.off_heap_bytes(), 0);
+        assert!(off_heap.ram_bytes_used() < fst.ram_bytes_used());
+
+        // Truncated inputs are rejected.
+        let truncated: Arc<[u8]> = bytes[..bytes.len() - 1].into();
+        assert!(Fst::load(Arc::new(truncated), FstLoadMode::OffHeap).is_err());
+        let header: Arc<[u8]> = bytes[..10].into();
+        assert!(Fst::load(Arc::new(header), FstLoadMode::OnHeap).is_err());
+
+        // So are nodes whose arcs are out of bounds, unsorted, or lead to later nodes.
+        let corrupted = |offset: usize, patch: &[u8]| {
+            let mut bytes = bytes.to_vec();
+            bytes[offset..offset + patch.len()].copy_from_slice(patch);
+            let bytes: Arc<[u8]> = bytes.into();
+            let error = Fst::load(Arc::new(bytes), FstLoadMode::OffHeap).unwrap_err();
+            assert!(matches!(error.downcast_ref(), Some(LuceneError::CorruptIndex(_))), "{error}");
+        };
+        let root = fst.root as usize;
+        let root_node = HEADER_LENGTH as usize + root * NODE_LENGTH as usize;
+        let arcs = HEADER_LENGTH as usize + fst.num_nodes() * NODE_LENGTH as usize;
+        corrupted(root_node, &(fst.num_arcs() as u32).to_le_bytes());
+        corrupted(root_node + 4, &u32::MAX.to_le_bytes());
+        corrupted(root_node + 8, &[2]);
+        corrupted(arcs + 9, &(fst.num_nodes() as u32).to_le_bytes());
+        let source = (0..fst.num_nodes() as u32).find(|&node| fst.node(node).num_arcs > 0).unwrap();
+        corrupted(arcs + fst.node(source).arcs_start as usize * ARC_LENGTH as usize + 9, &source.to_le_bytes());
+        let node = (0..fst.num_nodes() as u32).map(|node| fst.node(node)).find(|node| node.num_arcs > 1).unwrap();
+        let second_arc = arcs + (node.arcs_start as usize + 1) * ARC_LENGTH as usize;
+        corrupted(second_arc, &[fst.arc(node.arcs_start).label]);
+    }
+}
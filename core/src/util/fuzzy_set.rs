@@ -0,0 +1,124 @@
+use {
+    crate::{
+        util::{Accountable, BitSet, FixedBitSet},
+        BoxResult, LuceneError,
+    },
+    std::{
+        collections::hash_map::DefaultHasher,
+        hash::{Hash, Hasher},
+    },
+};
+
+/// The largest number of bits a [FuzzySet] uses.
+const MAX_BITS: u64 = 1 << 30;
+
+/// A bloom filter over byte strings: [FuzzySet::may_contain] never misses a value that was added, and wrongly reports
+/// other values with a probability bounded when the set is created.
+///
+/// The set is sized for an expected number of values; adding more than that raises the false positive probability
+/// above the bound.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FuzzySet {
+    bits: FixedBitSet,
+    hash_count: u32,
+}
+
+impl FuzzySet {
+    /// Creates an empty set sized so that, once it holds `expected_values` values, its false positive probability is
+    /// at most `max_fpp`. This fails with [LuceneError::InvalidArgument] unless `max_fpp` is strictly between 0 and
+    /// 1.
+    pub fn new(expected_values: usize, max_fpp: f64) -> BoxResult<Self> {
+        if !(max_fpp > 0.0 && max_fpp < 1.0) {
+            return Err(LuceneError::InvalidArgument(format!(
+                "the false positive probability must be between 0 and 1, got {max_fpp}"
+            ))
+            .into());
+        }
+
+        // The optimal number of bits is -n ln(p) / ln(2)^2, rounded up to a power of two so that a hash maps to a
+        // bit with a mask; the optimal number of hashes for that many bits is (m / n) ln(2).
+        let expected_values = expected_values.max(1) as f64;
+        let optimal_bits = (-expected_values * max_fpp.ln() / (2f64.ln() * 2f64.ln())).ceil() as u64;
+        let num_bits = optimal_bits.clamp(64, MAX_BITS).next_power_of_two();
+        let hash_count = ((num_bits as f64 / expected_values) * 2f64.ln()).round().clamp(1.0, 16.0) as u32;
+
+        Ok(Self {
+            bits: FixedBitSet::new(num_bits as u32),
+            hash_count,
+        })
+    }
+
+    /// Adds a value to the set.
+    pub fn add(&mut self, value: &[u8]) {
+        let (hash, step) = Self::hashes(value);
+        let mask = self.bits.num_bits() - 1;
+        for i in 0..self.hash_count {
+            self.bits.set(hash.wrapping_add(i.wrapping_mul(step)) & mask);
+        }
+    }
+
+    /// Indicates whether the set may contain `value`. If this returns false, `value` was never added.
+    pub fn may_contain(&self, value: &[u8]) -> bool {
+        let (hash, step) = Self::hashes(value);
+        let mask = self.bits.num_bits() - 1;
+        (0..self.hash_count).all(|i| self.bits.get(hash.wrapping_add(i.wrapping_mul(step)) & mask))
+    }
+
+    /// Returns the number of bits of the set.
+    #[inline]
+    pub fn num_bits(&self) -> u32 {
+        self.bits.num_bits()
+    }
+
+    /// Returns the number of bits set for each value.
+    #[inline]
+    pub fn hash_count(&self) -> u32 {
+        self.hash_count
+    }
+
+    /// Returns the fraction of the bits that are set. The false positive probability is about this raised to the
+    /// power of [FuzzySet::hash_count].
+    pub fn saturation(&self) -> f64 {
+        self.bits.cardinality() as f64 / self.bits.num_bits() as f64
+    }
+
+    /// Splits the hash of a value into the two halves used for double hashing. The step is odd, so that it is
+    /// coprime with the power-of-two number of bits.
+    fn hashes(value: &[u8]) -> (u32, u32) {
+        let mut hasher = DefaultHasher::new();
+        value.hash(&mut hasher);
+        let hash = hasher.finish();
+        (hash as u32, (hash >> 32) as u32 | 1)
+    }
+}
+
+impl Accountable for FuzzySet {
+    fn ram_bytes_used(&self) -> usize {
+        size_of::<Self>() + self.bits.ram_bytes_used() - size_of::<FixedBitSet>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::util::FuzzySet;
+
+    #[test]
+    fn test_fuzzy_set() {
+        let mut set = FuzzySet::new(1000, 0.01).unwrap();
+        assert!(set.num_bits().is_power_of_two());
+        assert!(!set.may_contain(b"id0"));
+
+        for i in 0..1000 {
+            set.add(format!("id{i}").as_bytes());
+        }
+        assert!((0..1000).all(|i| set.may_contain(format!("id{i}").as_bytes())));
+
+        let false_positives = (1000..11000).filter(|i| set.may_contain(format!("id{i}").as_bytes())).count();
+        assert!(false_positives < 200, "{false_positives} false positives out of 10000");
+        assert!(set.saturation() > 0.0 && set.saturation() < 1.0);
+
+        assert!(FuzzySet::new(10, 0.0).is_err());
+        assert!(FuzzySet::new(10, 1.0).is_err());
+        assert!(FuzzySet::new(10, f64::NAN).is_err());
+    }
+}
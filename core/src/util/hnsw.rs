@@ -0,0 +1,5 @@
+mod hnsw_graph;
+mod hnsw_graph_builder;
+mod hnsw_graph_searcher;
+
+pub use {hnsw_graph::*, hnsw_graph_builder::*, hnsw_graph_searcher::*};
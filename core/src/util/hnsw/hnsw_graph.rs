@@ -0,0 +1,116 @@
+use crate::util::{size_of_vec, Accountable};
+
+/// A hierarchical navigable small world graph over the vectors of a field, held on the heap, as Lucene's
+/// `OnHeapHnswGraph` is. Nodes are vector ordinals; every node is on level 0, and each level above holds a sparser
+/// subset of the nodes of the level below it, with searches starting from the entry node on the top level.
+///
+/// Graphs are built with [crate::util::hnsw::HnswGraphBuilder] and searched with [crate::util::hnsw::search_graph].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct OnHeapHnswGraph {
+    // neighbors[node][level] lists the neighbors of a node on a level; it is empty for nodes not in the graph.
+    neighbors: Vec<Vec<Vec<u32>>>,
+    // upper_levels[level - 1] lists the nodes on each level above 0.
+    upper_levels: Vec<Vec<u32>>,
+    entry_node: Option<u32>,
+    size: usize,
+}
+
+impl OnHeapHnswGraph {
+    /// Creates an empty graph.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the number of nodes in the graph.
+    #[inline]
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Returns the number of levels of the graph, or 0 if it is empty.
+    #[inline]
+    pub fn num_levels(&self) -> usize {
+        if self.size == 0 {
+            0
+        } else {
+            self.upper_levels.len() + 1
+        }
+    }
+
+    /// Returns the node searches start from, on the top level, or `None` if the graph is empty.
+    #[inline]
+    pub fn entry_node(&self) -> Option<u32> {
+        self.entry_node
+    }
+
+    /// Indicates whether `node` is in the graph.
+    #[inline]
+    pub fn contains(&self, node: u32) -> bool {
+        self.neighbors.get(node as usize).is_some_and(|levels| !levels.is_empty())
+    }
+
+    /// Returns the top level `node` is on, or `None` if it isn't in the graph.
+    #[inline]
+    pub fn node_level(&self, node: u32) -> Option<usize> {
+        self.neighbors.get(node as usize).and_then(|levels| levels.len().checked_sub(1))
+    }
+
+    /// Returns the neighbors of `node` on `level`, which is empty if the node isn't on that level.
+    pub fn neighbors(&self, level: usize, node: u32) -> &[u32] {
+        self.neighbors.get(node as usize).and_then(|levels| levels.get(level)).map_or(&[], Vec::as_slice)
+    }
+
+    /// Returns the nodes on `level`, in increasing order.
+    pub fn nodes_on_level(&self, level: usize) -> Vec<u32> {
+        let mut nodes = if level == 0 {
+            (0..self.neighbors.len() as u32).filter(|&node| self.contains(node)).collect()
+        } else {
+            self.upper_levels.get(level - 1).cloned().unwrap_or_default()
+        };
+        nodes.sort_unstable();
+        nodes
+    }
+
+    /// Adds `node` to every level up to and including `level`, without neighbors. Levels above the current top are
+    /// created, but the entry node is left for the caller to move up to them.
+    pub(crate) fn add_node(&mut self, level: usize, node: u32) {
+        debug_assert!(!self.contains(node));
+        if self.neighbors.len() <= node as usize {
+            self.neighbors.resize(node as usize + 1, Vec::new());
+        }
+        self.neighbors[node as usize] = vec![Vec::new(); level + 1];
+        while self.upper_levels.len() < level {
+            self.upper_levels.push(Vec::new());
+        }
+        for nodes in &mut self.upper_levels[..level] {
+            nodes.push(node);
+        }
+        self.size += 1;
+    }
+
+    /// Returns the neighbors of `node` on `level` for changing. The node must be on that level.
+    #[inline]
+    pub(crate) fn neighbors_mut(&mut self, level: usize, node: u32) -> &mut Vec<u32> {
+        &mut self.neighbors[node as usize][level]
+    }
+
+    /// Makes `node` the node searches start from.
+    #[inline]
+    pub(crate) fn set_entry_node(&mut self, node: u32) {
+        self.entry_node = Some(node);
+    }
+}
+
+impl Accountable for OnHeapHnswGraph {
+    fn ram_bytes_used(&self) -> usize {
+        size_of::<Self>()
+            + size_of_vec(&self.neighbors)
+            + self
+                .neighbors
+                .iter()
+                .map(|levels| size_of_vec(levels) + levels.iter().map(size_of_vec).sum::<usize>())
+                .sum::<usize>()
+            + size_of_vec(&self.upper_levels)
+            + self.upper_levels.iter().map(size_of_vec).sum::<usize>()
+    }
+}
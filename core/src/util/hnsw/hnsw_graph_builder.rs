@@ -0,0 +1,356 @@
+use {
+    crate::{
+        index::VectorSimilarityFunction,
+        util::{
+            hnsw::{search_level, OnHeapHnswGraph},
+            BitSet, FixedBitSet,
+        },
+        BoxResult, LuceneError,
+    },
+    rand::{rngs::StdRng, Rng, SeedableRng},
+};
+
+/// The default number of neighbors each node is connected to on the levels above 0. Nodes have up to twice as many
+/// on level 0.
+pub const DEFAULT_MAX_CONN: usize = 16;
+
+/// The default number of candidates kept while looking for the neighbors of a new node.
+pub const DEFAULT_BEAM_WIDTH: usize = 100;
+
+/// The default seed of the random levels nodes are assigned to, fixed so that building a graph is repeatable.
+pub const DEFAULT_HNSW_SEED: u64 = 42;
+
+/// Builds an [OnHeapHnswGraph] over a set of vectors, as Lucene's `HnswGraphBuilder` does. Nodes are the ordinals of
+/// the vectors, which are held one after another in a single slice.
+///
+/// A builder may start from an existing graph (see [HnswGraphBuilder::initialize_from]), which is how merges reuse
+/// the graph of the largest segment rather than inserting every vector again.
+#[derive(Debug)]
+pub struct HnswGraphBuilder<'a> {
+    similarity: VectorSimilarityFunction,
+    dimension: usize,
+    vectors: &'a [f32],
+    max_conn: usize,
+    beam_width: usize,
+    ml: f64,
+    rng: StdRng,
+    graph: OnHeapHnswGraph,
+}
+
+impl<'a> HnswGraphBuilder<'a> {
+    /// Creates a builder over `vectors`, which holds vectors of `dimension` values each, compared with `similarity`.
+    /// Each node gets up to `max_conn` neighbors per level (twice as many on level 0), chosen among `beam_width`
+    /// candidates, and the levels nodes are assigned to are drawn from a generator seeded with `seed`.
+    pub fn new(
+        similarity: VectorSimilarityFunction,
+        dimension: usize,
+        vectors: &'a [f32],
+        max_conn: usize,
+        beam_width: usize,
+        seed: u64,
+    ) -> BoxResult<Self> {
+        if dimension == 0 || !vectors.len().is_multiple_of(dimension) {
+            return Err(LuceneError::InvalidArgument(format!(
+                "{} values can't hold vectors of {dimension} dimensions",
+                vectors.len()
+            ))
+            .into());
+        }
+        if max_conn == 0 || beam_width == 0 {
+            return Err(LuceneError::InvalidArgument(format!(
+                "max_conn and beam_width must be positive; got {max_conn} and {beam_width}"
+            ))
+            .into());
+        }
+
+        Ok(Self {
+            similarity,
+            dimension,
+            vectors,
+            max_conn,
+            beam_width,
+            ml: if max_conn == 1 {
+                1.0
+            } else {
+                1.0 / (max_conn as f64).ln()
+            },
+            rng: StdRng::seed_from_u64(seed),
+            graph: OnHeapHnswGraph::new(),
+        })
+    }
+
+    /// Returns the number of vectors the graph is built over.
+    #[inline]
+    pub fn size(&self) -> usize {
+        self.vectors.len() / self.dimension
+    }
+
+    /// Starts from `graph`, copying its nodes and connections with each node `node` renumbered to `ord_map[node]`.
+    /// The vectors of the copied nodes are not compared again, so they must be the same as those `graph` was built
+    /// over; [HnswGraphBuilder::build] then only inserts the remaining vectors.
+    ///
+    /// This fails if the builder already has nodes, or if `ord_map` doesn't map every node of `graph` to a distinct
+    /// ordinal of this builder.
+    pub fn initialize_from(&mut self, graph: &OnHeapHnswGraph, ord_map: &[u32]) -> BoxResult<()> {
+        if self.graph.size() > 0 {
+            return Err(LuceneError::IllegalState("the graph has already been initialized".to_string()).into());
+        }
+
+        let nodes = graph.nodes_on_level(0);
+        let mut mapped = FixedBitSet::new(self.size() as u32);
+        for &node in &nodes {
+            match ord_map.get(node as usize) {
+                Some(&ord) if (ord as usize) < self.size() && !mapped.get(ord) => mapped.set(ord),
+                _ => {
+                    return Err(LuceneError::InvalidArgument(format!(
+                        "node {node} isn't mapped to a distinct ordinal below {}",
+                        self.size()
+                    ))
+                    .into())
+                }
+            }
+        }
+
+        for &node in &nodes {
+            let level = graph.node_level(node).unwrap_or_default();
+            let ord = ord_map[node as usize];
+            self.graph.add_node(level, ord);
+            for l in 0..=level {
+                *self.graph.neighbors_mut(l, ord) =
+                    graph.neighbors(l, node).iter().map(|&neighbor| ord_map[neighbor as usize]).collect();
+            }
+        }
+        if let Some(entry_node) = graph.entry_node() {
+            self.graph.set_entry_node(ord_map[entry_node as usize]);
+        }
+        Ok(())
+    }
+
+    /// Inserts every vector not yet in the graph, in order, and returns the graph.
+    pub fn build(mut self) -> OnHeapHnswGraph {
+        for node in 0..self.size() as u32 {
+            if !self.graph.contains(node) {
+                self.add_graph_node(node);
+            }
+        }
+        self.graph
+    }
+
+    /// Returns the vector with ordinal `ord`.
+    #[inline]
+    fn vector(&self, ord: u32) -> &[f32] {
+        &self.vectors[ord as usize * self.dimension..(ord as usize + 1) * self.dimension]
+    }
+
+    /// Returns the similarity of the vectors with ordinals `a` and `b`.
+    #[inline]
+    fn compare(&self, a: u32, b: u32) -> f32 {
+        self.similarity.compare(self.vector(a), self.vector(b))
+    }
+
+    /// Draws the top level of a new node, so that each level holds about `1 / max_conn` of the nodes of the level
+    /// below it.
+    fn random_level(&mut self) -> usize {
+        // gen() draws from [0, 1), so this is in (0, 1] and its logarithm is finite.
+        let uniform = 1.0 - self.rng.gen::<f64>();
+        (-uniform.ln() * self.ml) as usize
+    }
+
+    /// Inserts a node, connecting it to its nearest diverse neighbors on each of its levels.
+    fn add_graph_node(&mut self, node: u32) {
+        let level = self.random_level();
+        let Some(entry_node) = self.graph.entry_node() else {
+            self.graph.add_node(level, node);
+            self.graph.set_entry_node(node);
+            return;
+        };
+
+        let top_level = self.graph.num_levels() - 1;
+        let score = |other: u32| self.compare(node, other);
+        let mut entry_points = vec![entry_node];
+        for l in (level + 1..=top_level).rev() {
+            entry_points = Self::nodes(search_level(&self.graph, &score, &entry_points, 1, l, &|_| true));
+        }
+
+        let mut candidates_by_level = Vec::with_capacity(level.min(top_level) + 1);
+        for l in (0..=level.min(top_level)).rev() {
+            let candidates = search_level(&self.graph, &score, &entry_points, self.beam_width, l, &|_| true);
+            entry_points = Self::nodes(candidates.clone());
+            candidates_by_level.push((l, candidates));
+        }
+
+        self.graph.add_node(level, node);
+        for (l, candidates) in candidates_by_level {
+            self.add_diverse_neighbors(l, node, &candidates);
+        }
+        if level > top_level {
+            self.graph.set_entry_node(node);
+        }
+    }
+
+    fn nodes(scored: Vec<(u32, f32)>) -> Vec<u32> {
+        scored.into_iter().map(|(node, _)| node).collect()
+    }
+
+    /// Returns the largest number of neighbors a node may have on `level`.
+    #[inline]
+    fn max_neighbors(&self, level: usize) -> usize {
+        if level == 0 {
+            self.max_conn * 2
+        } else {
+            self.max_conn
+        }
+    }
+
+    /// Connects `node` to the diverse candidates on `level`, and each of them back to it, dropping the least diverse
+    /// neighbor of any that then has too many.
+    fn add_diverse_neighbors(&mut self, level: usize, node: u32, candidates: &[(u32, f32)]) {
+        let max_neighbors = self.max_neighbors(level);
+        let selected = self.select_diverse(candidates, max_neighbors);
+        *self.graph.neighbors_mut(level, node) = selected.clone();
+
+        for neighbor in selected {
+            let mut neighbors = std::mem::take(self.graph.neighbors_mut(level, neighbor));
+            neighbors.push(node);
+            if neighbors.len() > max_neighbors {
+                let worst = self.worst_non_diverse(neighbor, &neighbors);
+                neighbors.remove(worst);
+            }
+            *self.graph.neighbors_mut(level, neighbor) = neighbors;
+        }
+    }
+
+    /// Selects up to `max` of `candidates`, which are sorted by decreasing score, keeping only those closer to the
+    /// node than to any candidate already selected, so that neighbors point in different directions.
+    fn select_diverse(&self, candidates: &[(u32, f32)], max: usize) -> Vec<u32> {
+        let mut selected: Vec<u32> = Vec::with_capacity(max);
+        for &(candidate, score) in candidates {
+            if selected.len() >= max {
+                break;
+            }
+            if selected.iter().all(|&other| self.compare(candidate, other) < score) {
+                selected.push(candidate);
+            }
+        }
+        selected
+    }
+
+    /// Returns the index in `neighbors` of the farthest neighbor of `node` that is closer to another, closer
+    /// neighbor than it is to `node`, or of the farthest neighbor if all are diverse.
+    fn worst_non_diverse(&self, node: u32, neighbors: &[u32]) -> usize {
+        let mut scored: Vec<(usize, f32)> =
+            neighbors.iter().enumerate().map(|(i, &neighbor)| (i, self.compare(node, neighbor))).collect();
+        scored.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+        for (position, &(i, score)) in scored.iter().enumerate().rev() {
+            let candidate = neighbors[i];
+            if scored[..position].iter().any(|&(closer, _)| self.compare(candidate, neighbors[closer]) >= score) {
+                return i;
+            }
+        }
+        scored.last().map_or(0, |&(i, _)| i)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{
+            index::VectorSimilarityFunction,
+            util::hnsw::{
+                search_graph, HnswGraphBuilder, OnHeapHnswGraph, DEFAULT_BEAM_WIDTH, DEFAULT_HNSW_SEED,
+                DEFAULT_MAX_CONN,
+            },
+        },
+        pretty_assertions::assert_eq,
+        rand::{rngs::StdRng, Rng, SeedableRng},
+    };
+
+    const DIMENSION: usize = 8;
+
+    fn random_vectors(count: usize, seed: u64) -> Vec<f32> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        (0..count * DIMENSION).map(|_| rng.gen_range(-1.0..1.0)).collect()
+    }
+
+    fn build(vectors: &[f32], seed_graph: Option<(&OnHeapHnswGraph, &[u32])>) -> OnHeapHnswGraph {
+        let mut builder = HnswGraphBuilder::new(
+            VectorSimilarityFunction::Euclidean,
+            DIMENSION,
+            vectors,
+            DEFAULT_MAX_CONN,
+            DEFAULT_BEAM_WIDTH,
+            DEFAULT_HNSW_SEED,
+        )
+        .unwrap();
+        if let Some((graph, ord_map)) = seed_graph {
+            builder.initialize_from(graph, ord_map).unwrap();
+        }
+        builder.build()
+    }
+
+    /// Returns the fraction of the true `k` nearest neighbors of a set of queries that the graph finds.
+    fn recall(graph: &OnHeapHnswGraph, vectors: &[f32], k: usize) -> f64 {
+        let vector = |ord: u32| &vectors[ord as usize * DIMENSION..(ord as usize + 1) * DIMENSION];
+        let num_vectors = (vectors.len() / DIMENSION) as u32;
+        let queries = random_vectors(20, 7);
+        let mut found = 0;
+        for query in queries.chunks(DIMENSION) {
+            let score = |ord: u32| VectorSimilarityFunction::Euclidean.compare(query, vector(ord));
+            let mut exact: Vec<(u32, f32)> = (0..num_vectors).map(|ord| (ord, score(ord))).collect();
+            exact.sort_by(|a, b| b.1.total_cmp(&a.1));
+            let approximate = search_graph(graph, &score, k, &|_| true);
+            found += approximate.iter().filter(|(ord, _)| exact[..k].iter().any(|(o, _)| o == ord)).count();
+        }
+        found as f64 / (20 * k) as f64
+    }
+
+    #[test]
+    fn test_build_and_search() {
+        let vectors = random_vectors(500, 1);
+        let graph = build(&vectors, None);
+        assert_eq!(graph.size(), 500);
+        assert!(graph.num_levels() > 1);
+        for node in graph.nodes_on_level(0) {
+            assert!(graph.neighbors(0, node).len() <= DEFAULT_MAX_CONN * 2);
+            assert!(!graph.neighbors(0, node).contains(&node));
+        }
+        assert!(recall(&graph, &vectors, 10) >= 0.9);
+
+        // Filtered searches only return accepted nodes.
+        let query = &vectors[..DIMENSION];
+        let score = |ord: u32| {
+            VectorSimilarityFunction::Euclidean.compare(query, &vectors[ord as usize * DIMENSION..][..DIMENSION])
+        };
+        let hits = search_graph(&graph, &score, 5, &|ord| ord % 2 == 1);
+        assert_eq!(hits.len(), 5);
+        assert!(hits.iter().all(|(ord, _)| ord % 2 == 1));
+        assert!(hits.windows(2).all(|pair| pair[0].1 >= pair[1].1));
+        assert_eq!(search_graph(&graph, &score, 1, &|_| true)[0], (0, 1.0));
+        assert!(search_graph(&OnHeapHnswGraph::new(), &score, 5, &|_| true).is_empty());
+    }
+
+    #[test]
+    fn test_initialize_from() {
+        // The first 300 vectors are the last 300 of the merged graph.
+        let vectors = random_vectors(500, 2);
+        let seed = build(&vectors[..300 * DIMENSION], None);
+        let mut merged_vectors = vectors[300 * DIMENSION..].to_vec();
+        merged_vectors.extend_from_slice(&vectors[..300 * DIMENSION]);
+        let ord_map: Vec<u32> = (200..500).collect();
+
+        let merged = build(&merged_vectors, Some((&seed, &ord_map)));
+        assert_eq!(merged.size(), 500);
+        assert_eq!(merged.entry_node(), seed.entry_node().map(|node| node + 200));
+        assert!(recall(&merged, &merged_vectors, 10) >= 0.9);
+
+        let mut builder =
+            HnswGraphBuilder::new(VectorSimilarityFunction::Euclidean, DIMENSION, &merged_vectors, 16, 100, 42)
+                .unwrap();
+        assert!(builder.initialize_from(&seed, &ord_map[..299]).is_err());
+        assert!(builder.initialize_from(&seed, &vec![0; 300]).is_err());
+        builder.initialize_from(&seed, &ord_map).unwrap();
+        assert!(builder.initialize_from(&seed, &ord_map).is_err());
+        assert!(HnswGraphBuilder::new(VectorSimilarityFunction::Euclidean, 3, &merged_vectors, 16, 100, 42).is_err());
+    }
+}
@@ -0,0 +1,131 @@
+use {
+    crate::util::hnsw::OnHeapHnswGraph,
+    std::{
+        cmp::{Ordering, Reverse},
+        collections::{BinaryHeap, HashSet},
+    },
+};
+
+/// A node along with its score against the target of a search. Higher scores order first, with ties broken by the
+/// lower node.
+#[derive(Clone, Copy, Debug)]
+struct ScoredNode {
+    node: u32,
+    score: f32,
+}
+
+impl PartialEq for ScoredNode {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for ScoredNode {}
+
+impl PartialOrd for ScoredNode {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for ScoredNode {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.score.total_cmp(&other.score).then_with(|| other.node.cmp(&self.node))
+    }
+}
+
+/// Returns up to `top_k` nodes of `graph` that `accept` accepts, with their scores, highest scores first, as Lucene's
+/// `HnswGraphSearcher` does. `score` returns the similarity of a node to the target.
+///
+/// The search descends greedily from the entry node to level 0, then explores level 0 keeping the best `top_k` nodes
+/// seen. Nodes that aren't accepted are still traversed, so filtering doesn't disconnect the graph, but the search is
+/// approximate: nodes far from the path it takes may be missed.
+pub fn search_graph(
+    graph: &OnHeapHnswGraph,
+    score: &dyn Fn(u32) -> f32,
+    top_k: usize,
+    accept: &dyn Fn(u32) -> bool,
+) -> Vec<(u32, f32)> {
+    let Some(entry_node) = graph.entry_node() else {
+        return Vec::new();
+    };
+    if top_k == 0 {
+        return Vec::new();
+    }
+
+    let mut entry_points = vec![entry_node];
+    for level in (1..graph.num_levels()).rev() {
+        entry_points =
+            search_level(graph, score, &entry_points, 1, level, &|_| true).into_iter().map(|(node, _)| node).collect();
+    }
+    search_level(graph, score, &entry_points, top_k, 0, accept)
+}
+
+/// Searches one level of `graph` from `entry_points`, returning up to `top_k` accepted nodes with their scores,
+/// highest scores first.
+pub(crate) fn search_level(
+    graph: &OnHeapHnswGraph,
+    score: &dyn Fn(u32) -> f32,
+    entry_points: &[u32],
+    top_k: usize,
+    level: usize,
+    accept: &dyn Fn(u32) -> bool,
+) -> Vec<(u32, f32)> {
+    let mut visited = HashSet::new();
+    let mut candidates = BinaryHeap::new();
+    let mut results: BinaryHeap<Reverse<ScoredNode>> = BinaryHeap::new();
+    let mut min_accepted = f32::NEG_INFINITY;
+
+    for &node in entry_points {
+        if visited.insert(node) {
+            let scored = ScoredNode {
+                node,
+                score: score(node),
+            };
+            candidates.push(scored);
+            collect(scored, top_k, accept, &mut results, &mut min_accepted);
+        }
+    }
+
+    while let Some(candidate) = candidates.pop() {
+        if candidate.score < min_accepted {
+            break;
+        }
+        for &neighbor in graph.neighbors(level, candidate.node) {
+            if !visited.insert(neighbor) {
+                continue;
+            }
+            let scored = ScoredNode {
+                node: neighbor,
+                score: score(neighbor),
+            };
+            if scored.score >= min_accepted {
+                candidates.push(scored);
+                collect(scored, top_k, accept, &mut results, &mut min_accepted);
+            }
+        }
+    }
+
+    results.into_sorted_vec().into_iter().map(|Reverse(scored)| (scored.node, scored.score)).collect()
+}
+
+/// Adds a node to the results if it is accepted, keeping the best `top_k`, and updates the lowest score a node must
+/// have to be added once the results are full.
+fn collect(
+    scored: ScoredNode,
+    top_k: usize,
+    accept: &dyn Fn(u32) -> bool,
+    results: &mut BinaryHeap<Reverse<ScoredNode>>,
+    min_accepted: &mut f32,
+) {
+    if !accept(scored.node) {
+        return;
+    }
+    results.push(Reverse(scored));
+    if results.len() > top_k {
+        results.pop();
+    }
+    if results.len() == top_k {
+        *min_accepted = results.peek().map_or(f32::NEG_INFINITY, |Reverse(worst)| worst.score);
+    }
+}
@@ -0,0 +1,249 @@
+/// The base-2 logarithm of [INT_BLOCK_SIZE].
+pub const INT_BLOCK_SHIFT: usize = 13;
+
+/// The number of integers in each block of an [IntBlockPool].
+pub const INT_BLOCK_SIZE: usize = 1 << INT_BLOCK_SHIFT;
+
+/// Masks an offset into an [IntBlockPool] to an offset within its block.
+pub const INT_BLOCK_MASK: usize = INT_BLOCK_SIZE - 1;
+
+/// The sizes of the integer slices at each level.
+const INT_LEVEL_SIZE_ARRAY: [usize; 10] = [2, 4, 8, 16, 16, 32, 32, 64, 64, 128];
+
+/// The level following each level.
+const INT_NEXT_LEVEL_ARRAY: [usize; 10] = [1, 2, 3, 4, 5, 6, 7, 8, 9, 9];
+
+/// The size of the first integer slice of a stream.
+const INT_FIRST_LEVEL_SIZE: usize = INT_LEVEL_SIZE_ARRAY[0];
+
+/// An append-only arena of `u32` values, allocated in fixed-size blocks; the integer counterpart of
+/// [ByteBlockPool](crate::util::ByteBlockPool).
+///
+/// The pool holds fixed-size records (see [IntBlockPool::alloc]), such as the write positions of each term's
+/// posting streams, and streams of integers written in linked slices through an [IntSliceWriter]. The last integer of
+/// each slice is a non-zero level marker, which is replaced by the address of the next slice once the slice is full.
+///
+/// Blocks can be recycled with [IntBlockPool::reset], so that a pool reused for several segments doesn't reallocate.
+#[derive(Debug, Default)]
+pub struct IntBlockPool {
+    blocks: Vec<Box<[u32]>>,
+
+    /// The offset of the first free integer within the last block.
+    int_upto: usize,
+}
+
+impl IntBlockPool {
+    /// Creates an empty pool. No memory is allocated until something is written.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the number of bytes allocated by the pool.
+    #[inline]
+    pub fn bytes_allocated(&self) -> usize {
+        self.blocks.len() * INT_BLOCK_SIZE * size_of::<u32>()
+    }
+
+    /// Discards the contents of the pool. The first block is zeroed and kept for reuse; the others are freed.
+    pub fn reset(&mut self) {
+        let used = if self.blocks.len() > 1 {
+            INT_BLOCK_SIZE
+        } else {
+            self.int_upto
+        };
+        self.blocks.truncate(1);
+        if let Some(block) = self.blocks.first_mut() {
+            block[..used].fill(0);
+        }
+        self.int_upto = 0;
+    }
+
+    /// Allocates `size` zeroed integers that don't span two blocks, and returns the global offset of the first.
+    pub fn alloc(&mut self, size: usize) -> usize {
+        assert!(size <= INT_BLOCK_SIZE, "{size} integers don't fit in a block");
+        if self.blocks.is_empty() || self.int_upto + size > INT_BLOCK_SIZE {
+            self.blocks.push(vec![0; INT_BLOCK_SIZE].into_boxed_slice());
+            self.int_upto = 0;
+        }
+
+        let offset = (self.blocks.len() - 1) * INT_BLOCK_SIZE + self.int_upto;
+        self.int_upto += size;
+        offset
+    }
+
+    /// Returns the integer at a global offset.
+    #[inline]
+    pub fn int(&self, offset: usize) -> u32 {
+        self.blocks[offset >> INT_BLOCK_SHIFT][offset & INT_BLOCK_MASK]
+    }
+
+    /// Sets the integer at a global offset.
+    #[inline]
+    pub fn set_int(&mut self, offset: usize, value: u32) {
+        self.blocks[offset >> INT_BLOCK_SHIFT][offset & INT_BLOCK_MASK] = value;
+    }
+
+    /// Allocates the first slice of a new stream, returning its global offset.
+    fn new_slice(&mut self) -> usize {
+        let offset = self.alloc(INT_FIRST_LEVEL_SIZE);
+        self.set_int(offset + INT_FIRST_LEVEL_SIZE - 1, 16);
+        offset
+    }
+
+    /// Continues a stream whose writer reached the level marker at `upto` in a new, larger slice, and returns the
+    /// global offset to continue writing at.
+    fn alloc_slice(&mut self, upto: usize) -> usize {
+        let level = (self.int(upto) & 15) as usize;
+        let new_level = INT_NEXT_LEVEL_ARRAY[level];
+        let new_size = INT_LEVEL_SIZE_ARRAY[new_level];
+        let offset = self.alloc(new_size);
+        self.set_int(upto, offset as u32);
+        self.set_int(offset + new_size - 1, 16 | new_level as u32);
+        offset
+    }
+}
+
+/// Writes a stream of integers to an [IntBlockPool] in linked slices.
+#[derive(Debug)]
+pub struct IntSliceWriter<'a> {
+    pool: &'a mut IntBlockPool,
+    offset: usize,
+}
+
+impl<'a> IntSliceWriter<'a> {
+    /// Creates a writer for the given pool. Call [IntSliceWriter::start_new_slice] or [IntSliceWriter::reset] before
+    /// writing.
+    pub fn new(pool: &'a mut IntBlockPool) -> Self {
+        Self {
+            pool,
+            offset: 0,
+        }
+    }
+
+    /// Starts a new stream, returning its global offset, which is later passed to [IntSliceReader::new].
+    pub fn start_new_slice(&mut self) -> usize {
+        self.offset = self.pool.new_slice();
+        self.offset
+    }
+
+    /// Continues the stream whose writer was at the given offset.
+    pub fn reset(&mut self, offset: usize) {
+        self.offset = offset;
+    }
+
+    /// Returns the offset just past the last integer written, to be passed to [IntSliceWriter::reset] or used as the
+    /// end of an [IntSliceReader].
+    #[inline]
+    pub fn current_offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Writes an integer, following on to a new slice if the current one is full.
+    pub fn write_int(&mut self, value: u32) {
+        if self.pool.int(self.offset) != 0 {
+            self.offset = self.pool.alloc_slice(self.offset);
+        }
+        self.pool.set_int(self.offset, value);
+        self.offset += 1;
+    }
+}
+
+/// Reads a stream written by an [IntSliceWriter], from its first slice up to the writer's position.
+#[derive(Debug)]
+pub struct IntSliceReader<'a> {
+    pool: &'a IntBlockPool,
+    level: usize,
+    upto: usize,
+    limit: usize,
+    end: usize,
+}
+
+impl<'a> IntSliceReader<'a> {
+    /// Creates a reader for the stream that starts at `start` and whose writer is at `end`.
+    pub fn new(pool: &'a IntBlockPool, start: usize, end: usize) -> Self {
+        Self {
+            pool,
+            level: 0,
+            upto: start,
+            limit: Self::limit(start, INT_FIRST_LEVEL_SIZE, end),
+            end,
+        }
+    }
+
+    /// Returns where the readable integers of a slice stop: at the end of the stream, or at the address of the next
+    /// slice.
+    fn limit(start: usize, size: usize, end: usize) -> usize {
+        if start + size > end {
+            end
+        } else {
+            start + size - 1
+        }
+    }
+
+    /// Indicates whether the whole stream has been read.
+    #[inline]
+    pub fn eof(&self) -> bool {
+        self.upto == self.end
+    }
+
+    /// Reads the next integer. The stream must not be at its end.
+    pub fn read_int(&mut self) -> u32 {
+        debug_assert!(!self.eof());
+        if self.upto == self.limit {
+            let next = self.pool.int(self.limit) as usize;
+            self.level = INT_NEXT_LEVEL_ARRAY[self.level];
+            self.upto = next;
+            self.limit = Self::limit(next, INT_LEVEL_SIZE_ARRAY[self.level], self.end);
+        }
+
+        let value = self.pool.int(self.upto);
+        self.upto += 1;
+        value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::util::{IntBlockPool, IntSliceReader, IntSliceWriter, INT_BLOCK_SIZE},
+        pretty_assertions::assert_eq,
+    };
+
+    #[test]
+    fn test_interleaved_int_slices() {
+        let mut pool = IntBlockPool::new();
+        assert_eq!(pool.bytes_allocated(), 0);
+
+        let mut starts = Vec::new();
+        let mut ends = Vec::new();
+        {
+            let mut writer = IntSliceWriter::new(&mut pool);
+            for _ in 0..3 {
+                starts.push(writer.start_new_slice());
+                ends.push(writer.current_offset());
+            }
+            for value in 0..5000u32 {
+                for (stream, end) in ends.iter_mut().enumerate() {
+                    writer.reset(*end);
+                    writer.write_int(value * (stream as u32 + 1));
+                    *end = writer.current_offset();
+                }
+            }
+        }
+        assert!(pool.bytes_allocated() > INT_BLOCK_SIZE * 4);
+
+        for (stream, (&start, &end)) in starts.iter().zip(&ends).enumerate() {
+            let mut reader = IntSliceReader::new(&pool, start, end);
+            for value in 0..5000u32 {
+                assert_eq!(reader.read_int(), value * (stream as u32 + 1));
+            }
+            assert!(reader.eof());
+        }
+
+        pool.reset();
+        assert_eq!(pool.bytes_allocated(), INT_BLOCK_SIZE * 4);
+        assert_eq!(pool.alloc(2), 0);
+        assert_eq!((pool.int(0), pool.int(1)), (0, 0));
+        assert_eq!(pool.bytes_allocated(), INT_BLOCK_SIZE * 4);
+    }
+}
@@ -0,0 +1,269 @@
+use crate::util::{pop_count, Accountable};
+
+/// A bit set of fixed length addressed by 64-bit indices, for sets too large for a
+/// [FixedBitSet](crate::util::FixedBitSet), such as the global ordinals of a high-cardinality field across an index.
+///
+/// Bits past [LongBitSet::num_bits] are always clear.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct LongBitSet {
+    words: Vec<u64>,
+    num_bits: u64,
+}
+
+impl LongBitSet {
+    /// Creates a set of `num_bits` bits, all clear.
+    pub fn new(num_bits: u64) -> Self {
+        Self {
+            words: vec![0; Self::num_words(num_bits)],
+            num_bits,
+        }
+    }
+
+    /// Returns the number of 64-bit words needed to hold `num_bits` bits.
+    #[inline]
+    pub fn num_words(num_bits: u64) -> usize {
+        num_bits.div_ceil(64) as usize
+    }
+
+    /// Returns the number of bits in the set; valid indices are `0..num_bits()`.
+    #[inline]
+    pub fn num_bits(&self) -> u64 {
+        self.num_bits
+    }
+
+    /// Returns the words backing the set.
+    #[inline]
+    pub fn words(&self) -> &[u64] {
+        &self.words
+    }
+
+    /// Grows the set to hold at least `num_bits` bits, keeping the bits already set. Like Lucene, this leaves some
+    /// room so that repeated growth is amortized.
+    pub fn ensure_capacity(&mut self, num_bits: u64) {
+        if num_bits <= self.num_bits {
+            return;
+        }
+        let num_words = Self::num_words(num_bits);
+        if num_words > self.words.len() {
+            self.words.resize(num_words + (num_words >> 3), 0);
+        }
+        self.num_bits = self.words.len() as u64 * 64;
+    }
+
+    /// Indicates whether the bit at `index` is set.
+    #[inline]
+    pub fn get(&self, index: u64) -> bool {
+        debug_assert!(index < self.num_bits, "index {index} out of bounds for {} bits", self.num_bits);
+        self.words[(index >> 6) as usize] & (1 << (index & 63)) != 0
+    }
+
+    /// Sets the bit at `index`.
+    #[inline]
+    pub fn set(&mut self, index: u64) {
+        debug_assert!(index < self.num_bits, "index {index} out of bounds for {} bits", self.num_bits);
+        self.words[(index >> 6) as usize] |= 1 << (index & 63);
+    }
+
+    /// Clears the bit at `index`.
+    #[inline]
+    pub fn clear(&mut self, index: u64) {
+        debug_assert!(index < self.num_bits, "index {index} out of bounds for {} bits", self.num_bits);
+        self.words[(index >> 6) as usize] &= !(1 << (index & 63));
+    }
+
+    /// Sets the bit at `index`, returning whether it was already set.
+    #[inline]
+    pub fn get_and_set(&mut self, index: u64) -> bool {
+        let was_set = self.get(index);
+        self.set(index);
+        was_set
+    }
+
+    /// Clears the bit at `index`, returning whether it was set.
+    #[inline]
+    pub fn get_and_clear(&mut self, index: u64) -> bool {
+        let was_set = self.get(index);
+        self.clear(index);
+        was_set
+    }
+
+    /// Applies `op` to the words covering `start..end`, with a mask of the bits of each word that are in the range.
+    fn apply_range(&mut self, start: u64, end: u64, op: impl Fn(&mut u64, u64)) {
+        debug_assert!(start <= end && end <= self.num_bits, "invalid range {start}..{end} for {} bits", self.num_bits);
+        if start >= end {
+            return;
+        }
+
+        let start_word = (start >> 6) as usize;
+        let end_word = ((end - 1) >> 6) as usize;
+        let start_mask = u64::MAX << (start & 63);
+        let end_mask = u64::MAX >> (63 - ((end - 1) & 63));
+        if start_word == end_word {
+            op(&mut self.words[start_word], start_mask & end_mask);
+            return;
+        }
+
+        op(&mut self.words[start_word], start_mask);
+        for word in &mut self.words[start_word + 1..end_word] {
+            op(word, u64::MAX);
+        }
+        op(&mut self.words[end_word], end_mask);
+    }
+
+    /// Sets every bit in `start..end`.
+    pub fn set_range(&mut self, start: u64, end: u64) {
+        self.apply_range(start, end, |word, mask| *word |= mask);
+    }
+
+    /// Clears every bit in `start..end`.
+    pub fn clear_range(&mut self, start: u64, end: u64) {
+        self.apply_range(start, end, |word, mask| *word &= !mask);
+    }
+
+    /// Flips every bit in `start..end`.
+    pub fn flip_range(&mut self, start: u64, end: u64) {
+        self.apply_range(start, end, |word, mask| *word ^= mask);
+    }
+
+    /// Returns the number of set bits.
+    pub fn cardinality(&self) -> u64 {
+        pop_count(&self.words)
+    }
+
+    /// Returns the index of the first set bit at or after `index`, if any.
+    pub fn next_set_bit(&self, index: u64) -> Option<u64> {
+        if index >= self.num_bits {
+            return None;
+        }
+
+        let i = (index >> 6) as usize;
+        let word = self.words[i] >> (index & 63);
+        if word != 0 {
+            return Some(index + word.trailing_zeros() as u64);
+        }
+        let i = i + 1 + self.words[i + 1..].iter().position(|&word| word != 0)?;
+        Some(i as u64 * 64 + self.words[i].trailing_zeros() as u64)
+    }
+
+    /// Returns the index of the last set bit at or before `index`, if any.
+    pub fn prev_set_bit(&self, index: u64) -> Option<u64> {
+        let index = index.min(self.num_bits.checked_sub(1)?);
+        let i = (index >> 6) as usize;
+        let word = self.words[i] << (63 - (index & 63));
+        if word != 0 {
+            return Some(index - word.leading_zeros() as u64);
+        }
+        let i = self.words[..i].iter().rposition(|&word| word != 0)?;
+        Some(i as u64 * 64 + 63 - self.words[i].leading_zeros() as u64)
+    }
+
+    /// Returns an iterator over the indices of the set bits, in increasing order.
+    pub fn iter(&self) -> impl Iterator<Item = u64> + '_ {
+        self.words.iter().enumerate().flat_map(|(i, &word)| {
+            let mut word = word;
+            std::iter::from_fn(move || {
+                if word == 0 {
+                    return None;
+                }
+                let bit = word.trailing_zeros() as u64;
+                word &= word - 1;
+                Some(i as u64 * 64 + bit)
+            })
+        })
+    }
+
+    /// Sets the bits that are set in `other`, which must not be longer than this set.
+    pub fn or(&mut self, other: &LongBitSet) {
+        debug_assert!(other.num_bits <= self.num_bits, "can't or {} bits into {}", other.num_bits, self.num_bits);
+        for (word, other) in self.words.iter_mut().zip(&other.words) {
+            *word |= other;
+        }
+    }
+
+    /// Flips the bits that are set in `other`, which must not be longer than this set.
+    pub fn xor(&mut self, other: &LongBitSet) {
+        debug_assert!(other.num_bits <= self.num_bits, "can't xor {} bits into {}", other.num_bits, self.num_bits);
+        for (word, other) in self.words.iter_mut().zip(&other.words) {
+            *word ^= other;
+        }
+    }
+
+    /// Clears the bits that aren't set in `other`.
+    pub fn and(&mut self, other: &LongBitSet) {
+        let common = self.words.len().min(other.words.len());
+        for (word, other) in self.words[..common].iter_mut().zip(&other.words) {
+            *word &= other;
+        }
+        self.words[common..].fill(0);
+    }
+
+    /// Clears the bits that are set in `other`.
+    pub fn and_not(&mut self, other: &LongBitSet) {
+        for (word, other) in self.words.iter_mut().zip(&other.words) {
+            *word &= !other;
+        }
+    }
+
+    /// Indicates whether this set and `other` have a set bit in common.
+    pub fn intersects(&self, other: &LongBitSet) -> bool {
+        self.words.iter().zip(&other.words).any(|(a, b)| a & b != 0)
+    }
+}
+
+impl Accountable for LongBitSet {
+    fn ram_bytes_used(&self) -> usize {
+        size_of::<Self>() + self.words.capacity() * size_of::<u64>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {crate::util::LongBitSet, pretty_assertions::assert_eq};
+
+    #[test]
+    fn test_long_bit_set() {
+        let mut bits = LongBitSet::new(300);
+        assert_eq!(bits.next_set_bit(0), None);
+        assert_eq!(bits.prev_set_bit(299), None);
+
+        bits.set(3);
+        bits.set_range(64, 70);
+        assert!(!bits.get_and_set(299));
+        assert!(bits.get_and_set(299));
+        assert_eq!(bits.cardinality(), 8);
+        assert_eq!(bits.iter().collect::<Vec<_>>(), vec![3, 64, 65, 66, 67, 68, 69, 299]);
+        assert_eq!(bits.next_set_bit(4), Some(64));
+        assert_eq!(bits.next_set_bit(70), Some(299));
+        assert_eq!(bits.prev_set_bit(63), Some(3));
+        assert_eq!(bits.prev_set_bit(1000), Some(299));
+        assert!(bits.get_and_clear(3));
+        assert_eq!(bits.prev_set_bit(63), None);
+
+        let mut other = LongBitSet::new(300);
+        other.set_range(66, 200);
+        assert!(bits.intersects(&other));
+        let mut union = bits.clone();
+        union.or(&other);
+        assert_eq!(union.cardinality(), 137);
+        let mut intersection = bits.clone();
+        intersection.and(&other);
+        assert_eq!(intersection.cardinality(), 4);
+        let mut difference = bits.clone();
+        difference.xor(&other);
+        assert_eq!(difference.cardinality(), 133);
+        bits.and_not(&other);
+        assert_eq!(bits.iter().collect::<Vec<_>>(), vec![64, 65, 299]);
+        bits.clear_range(0, 300);
+        assert_eq!(bits.cardinality(), 0);
+        bits.flip_range(10, 20);
+        assert_eq!(bits.cardinality(), 10);
+
+        bits.ensure_capacity(5000);
+        assert!(bits.num_bits() >= 5000);
+        bits.set(4999);
+        assert_eq!(bits.cardinality(), 11);
+
+        let wide = LongBitSet::new(1 << 20);
+        assert_eq!(wide.words().len(), 1 << 14);
+    }
+}
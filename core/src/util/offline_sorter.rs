@@ -0,0 +1,537 @@
+use {
+    crate::{
+        io::{Directory, EncodingReadExt, EncodingWriteExt, IoContext},
+        util::{sort_by_bytes, BytesRefArray},
+        BoxResult, LuceneError,
+    },
+    std::{
+        cmp::Ordering,
+        collections::HashSet,
+        fmt::{Debug, Formatter, Result as FmtResult},
+        io::Result as IoResult,
+        pin::Pin,
+        sync::Arc,
+    },
+    tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+};
+
+/// The default amount of memory used to sort each run, in bytes.
+pub const DEFAULT_SORT_RAM_BUFFER_SIZE: usize = 16 << 20;
+
+/// The default number of runs merged at once.
+pub const DEFAULT_MAX_TEMP_FILES: usize = 10;
+
+/// The amount of record data buffered before a block is written, and compressed if enabled.
+const SORT_BLOCK_SIZE: usize = 64 << 10;
+
+/// Marks a block stored as is.
+const BLOCK_RAW: u8 = 0;
+
+/// Marks a block compressed with LZ4.
+#[cfg(feature = "lz4")]
+const BLOCK_LZ4: u8 = 1;
+
+/// Orders the records sorted by an [OfflineSorter].
+pub type BytesComparator = Arc<dyn Fn(&[u8], &[u8]) -> Ordering + Send + Sync>;
+
+/// How the blocks of the files written by an [OfflineSorter] are compressed.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum SortCompression {
+    /// Blocks are stored as is.
+    #[default]
+    None,
+
+    /// Blocks are compressed with LZ4, trading CPU time for temporary disk space and I/O.
+    #[cfg(feature = "lz4")]
+    Lz4,
+}
+
+/// Statistics about a sort performed by [OfflineSorter::sort].
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct SortInfo {
+    /// The name of the file holding the sorted records.
+    pub output: String,
+
+    /// The number of records sorted.
+    pub lines: u64,
+
+    /// The number of runs sorted in memory and written to temporary files.
+    pub runs: usize,
+
+    /// The number of rounds of merging, including the final one.
+    pub merge_rounds: usize,
+
+    /// The number of temporary files written, including the output.
+    pub temp_files: usize,
+}
+
+/// Writes records to a file in the format read by [ByteSequencesReader] and [OfflineSorter].
+///
+/// Records are buffered in blocks, each optionally compressed, and prefixed with their lengths. The file must be
+/// completed with [ByteSequencesWriter::finish].
+pub struct ByteSequencesWriter {
+    output: Pin<Box<dyn AsyncWrite>>,
+    compression: SortCompression,
+    block: Vec<u8>,
+}
+
+impl ByteSequencesWriter {
+    /// Creates a writer that writes to the given output.
+    pub fn new(output: Pin<Box<dyn AsyncWrite>>, compression: SortCompression) -> Self {
+        Self {
+            output,
+            compression,
+            block: Vec::with_capacity(SORT_BLOCK_SIZE),
+        }
+    }
+
+    /// Writes a record.
+    pub async fn write(&mut self, bytes: &[u8]) -> BoxResult<()> {
+        let len = i32::try_from(bytes.len())
+            .map_err(|_| LuceneError::InvalidArgument(format!("a record of {} bytes is too long", bytes.len())))?;
+        self.block.write_vi32(len).await?;
+        self.block.extend_from_slice(bytes);
+        if self.block.len() >= SORT_BLOCK_SIZE {
+            self.write_block().await?;
+        }
+        Ok(())
+    }
+
+    /// Writes the remaining records and the end of the file, and closes the output.
+    pub async fn finish(mut self) -> BoxResult<()> {
+        self.write_block().await?;
+        self.output.write_vi32(0).await?;
+        self.output.shutdown().await?;
+        Ok(())
+    }
+
+    async fn write_block(&mut self) -> IoResult<()> {
+        if self.block.is_empty() {
+            return Ok(());
+        }
+
+        self.output.write_vi32(self.block.len() as i32).await?;
+        match self.compression {
+            SortCompression::None => {
+                self.output.write_u8(BLOCK_RAW).await?;
+                self.output.write_all(&self.block).await?;
+            }
+            #[cfg(feature = "lz4")]
+            SortCompression::Lz4 => {
+                let compressed = lz4_flex::block::compress(&self.block);
+                self.output.write_u8(BLOCK_LZ4).await?;
+                self.output.write_vi32(compressed.len() as i32).await?;
+                self.output.write_all(&compressed).await?;
+            }
+        }
+        self.block.clear();
+        Ok(())
+    }
+}
+
+impl Debug for ByteSequencesWriter {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        f.debug_struct("ByteSequencesWriter").field("compression", &self.compression).finish()
+    }
+}
+
+/// Reads records written by a [ByteSequencesWriter]. Compressed blocks are detected automatically.
+pub struct ByteSequencesReader {
+    input: Pin<Box<dyn AsyncRead>>,
+    block: Vec<u8>,
+    pos: usize,
+    done: bool,
+}
+
+impl ByteSequencesReader {
+    /// Creates a reader that reads from the given input.
+    pub fn new(input: Pin<Box<dyn AsyncRead>>) -> Self {
+        Self {
+            input,
+            block: Vec::new(),
+            pos: 0,
+            done: false,
+        }
+    }
+
+    /// Reads the next record, or returns `None` at the end of the file.
+    pub async fn next(&mut self) -> BoxResult<Option<Vec<u8>>> {
+        if self.pos == self.block.len() && !self.read_block().await? {
+            return Ok(None);
+        }
+
+        let mut remaining = &self.block[self.pos..];
+        let len = remaining.read_vi32().await?;
+        let record = usize::try_from(len)
+            .ok()
+            .and_then(|len| remaining.get(..len))
+            .ok_or_else(|| LuceneError::CorruptIndex(format!("invalid record length {len}").into()))?
+            .to_vec();
+        self.pos = self.block.len() - remaining.len() + record.len();
+        Ok(Some(record))
+    }
+
+    /// Reads the next block, returning false at the end of the file.
+    async fn read_block(&mut self) -> BoxResult<bool> {
+        if self.done {
+            return Ok(false);
+        }
+
+        let raw_len = self.input.read_vi32().await?;
+        if raw_len == 0 {
+            self.done = true;
+            return Ok(false);
+        }
+        let raw_len = usize::try_from(raw_len)
+            .map_err(|_| LuceneError::CorruptIndex(format!("invalid block length {raw_len}").into()))?;
+
+        match self.input.read_u8().await? {
+            BLOCK_RAW => {
+                self.block.resize(raw_len, 0);
+                self.input.read_exact(&mut self.block).await?;
+            }
+            #[cfg(feature = "lz4")]
+            BLOCK_LZ4 => {
+                let len = self.input.read_vi32().await?;
+                let len = usize::try_from(len)
+                    .map_err(|_| LuceneError::CorruptIndex(format!("invalid compressed block length {len}").into()))?;
+                let mut compressed = vec![0; len];
+                self.input.read_exact(&mut compressed).await?;
+                self.block = lz4_flex::block::decompress(&compressed, raw_len)
+                    .map_err(|e| LuceneError::CorruptIndex(format!("invalid compressed block: {e}").into()))?;
+            }
+            codec => return Err(LuceneError::CorruptIndex(format!("unknown block codec {codec}").into()).into()),
+        }
+        self.pos = 0;
+        Ok(true)
+    }
+}
+
+impl Debug for ByteSequencesReader {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        f.debug_struct("ByteSequencesReader").field("done", &self.done).finish()
+    }
+}
+
+/// Sorts a file of records that may not fit in memory, using temporary files in a [Directory].
+///
+/// As in Lucene, the input is read in runs that fill the RAM buffer; each run is sorted in memory and written to a
+/// temporary file, and the runs are then merged, up to [OfflineSorter::max_temp_files] at a time, until one sorted
+/// file remains. Input and output files are written with [ByteSequencesWriter] and read with [ByteSequencesReader].
+///
+/// Records are compared as unsigned bytes unless a comparator is set. Temporary files other than the output are
+/// removed once merged.
+#[derive(Clone)]
+pub struct OfflineSorter {
+    temp_prefix: String,
+    ram_buffer_size: usize,
+    max_temp_files: usize,
+    compression: SortCompression,
+    comparator: Option<BytesComparator>,
+}
+
+impl OfflineSorter {
+    /// Creates a sorter whose temporary files are named after `temp_prefix`.
+    pub fn new(temp_prefix: &str) -> Self {
+        Self {
+            temp_prefix: temp_prefix.to_string(),
+            ram_buffer_size: DEFAULT_SORT_RAM_BUFFER_SIZE,
+            max_temp_files: DEFAULT_MAX_TEMP_FILES,
+            compression: SortCompression::None,
+            comparator: None,
+        }
+    }
+
+    /// Returns the prefix of the names of temporary files.
+    #[inline]
+    pub fn temp_prefix(&self) -> &str {
+        &self.temp_prefix
+    }
+
+    /// Returns the amount of memory used to sort each run, in bytes.
+    #[inline]
+    pub fn ram_buffer_size(&self) -> usize {
+        self.ram_buffer_size
+    }
+
+    /// Sets the amount of memory used to sort each run, in bytes. This fails with [LuceneError::InvalidArgument] if
+    /// the size is zero.
+    pub fn set_ram_buffer_size(&mut self, ram_buffer_size: usize) -> BoxResult<&mut Self> {
+        if ram_buffer_size == 0 {
+            return Err(LuceneError::InvalidArgument("ram_buffer_size must be positive".to_string()).into());
+        }
+        self.ram_buffer_size = ram_buffer_size;
+        Ok(self)
+    }
+
+    /// Returns the number of runs merged at once.
+    #[inline]
+    pub fn max_temp_files(&self) -> usize {
+        self.max_temp_files
+    }
+
+    /// Sets the number of runs merged at once. This fails with [LuceneError::InvalidArgument] if it is less than 2.
+    pub fn set_max_temp_files(&mut self, max_temp_files: usize) -> BoxResult<&mut Self> {
+        if max_temp_files < 2 {
+            return Err(
+                LuceneError::InvalidArgument(format!("max_temp_files must be at least 2: {max_temp_files}")).into()
+            );
+        }
+        self.max_temp_files = max_temp_files;
+        Ok(self)
+    }
+
+    /// Returns how temporary and output files are compressed.
+    #[inline]
+    pub fn compression(&self) -> SortCompression {
+        self.compression
+    }
+
+    /// Sets how temporary and output files are compressed.
+    pub fn set_compression(&mut self, compression: SortCompression) -> &mut Self {
+        self.compression = compression;
+        self
+    }
+
+    /// Sets the comparator records are sorted by, or `None` to compare them as unsigned bytes.
+    pub fn set_comparator(&mut self, comparator: Option<BytesComparator>) -> &mut Self {
+        self.comparator = comparator;
+        self
+    }
+
+    /// Sorts the records of the `input` file into a new temporary file, whose name is returned in the [SortInfo].
+    /// The input file is left in place.
+    pub async fn sort(&self, directory: &mut dyn Directory, input: &str) -> BoxResult<SortInfo> {
+        let mut temp_names = TempNames {
+            prefix: &self.temp_prefix,
+            next: 0,
+            existing: directory.read_dir().await?.into_iter().collect(),
+        };
+        let mut info = SortInfo::default();
+
+        let mut reader = ByteSequencesReader::new(directory.open(input, &IoContext::ReadOnce).await?);
+        let mut runs = Vec::new();
+        let mut buffer = BytesRefArray::new();
+        let mut buffered = 0;
+        while let Some(record) = reader.next().await? {
+            buffered += record.len() + size_of::<usize>();
+            buffer.append(&record);
+            info.lines += 1;
+            if buffered >= self.ram_buffer_size {
+                runs.push(self.write_run(directory, &mut temp_names, &buffer).await?);
+                buffer.clear();
+                buffered = 0;
+            }
+        }
+        if !buffer.is_empty() || runs.is_empty() {
+            runs.push(self.write_run(directory, &mut temp_names, &buffer).await?);
+        }
+        drop(reader);
+        info.runs = runs.len();
+
+        while runs.len() > 1 {
+            let mut merged = Vec::with_capacity(runs.len().div_ceil(self.max_temp_files));
+            for group in runs.chunks(self.max_temp_files) {
+                match group {
+                    [run] => merged.push(run.clone()),
+                    _ => merged.push(self.merge(directory, &mut temp_names, group).await?),
+                }
+            }
+            runs = merged;
+            info.merge_rounds += 1;
+        }
+
+        info.output = runs.pop().unwrap();
+        info.temp_files = temp_names.next;
+        Ok(info)
+    }
+
+    fn compare(&self, a: &[u8], b: &[u8]) -> Ordering {
+        match &self.comparator {
+            Some(comparator) => comparator(a, b),
+            None => a.cmp(b),
+        }
+    }
+
+    /// Sorts the buffered records and writes them to a new temporary file.
+    async fn write_run(
+        &self,
+        directory: &mut dyn Directory,
+        temp_names: &mut TempNames<'_>,
+        buffer: &BytesRefArray,
+    ) -> BoxResult<String> {
+        let mut order: Vec<u32> = (0..buffer.len() as u32).collect();
+        match &self.comparator {
+            Some(comparator) => order.sort_by(|&a, &b| comparator(buffer.get(a as usize), buffer.get(b as usize))),
+            None => sort_by_bytes(&mut order, |i| buffer.get(i as usize)),
+        }
+
+        let name = temp_names.next_name();
+        let mut writer =
+            ByteSequencesWriter::new(directory.create(&name, &IoContext::Default).await?, self.compression);
+        for i in order {
+            writer.write(buffer.get(i as usize)).await?;
+        }
+        writer.finish().await?;
+        Ok(name)
+    }
+
+    /// Merges sorted runs into a new temporary file, and removes them.
+    async fn merge(
+        &self,
+        directory: &mut dyn Directory,
+        temp_names: &mut TempNames<'_>,
+        runs: &[String],
+    ) -> BoxResult<String> {
+        let mut readers = Vec::with_capacity(runs.len());
+        let mut heads = Vec::with_capacity(runs.len());
+        for run in runs {
+            let mut reader = ByteSequencesReader::new(directory.open(run, &IoContext::ReadOnce).await?);
+            heads.push(reader.next().await?);
+            readers.push(reader);
+        }
+
+        let name = temp_names.next_name();
+        let mut writer =
+            ByteSequencesWriter::new(directory.create(&name, &IoContext::Default).await?, self.compression);
+
+        // Few runs are merged at once, so the smallest head is found by a linear scan. Ties go to the earliest run,
+        // which keeps the merge stable.
+        loop {
+            let mut min: Option<usize> = None;
+            for (i, head) in heads.iter().enumerate() {
+                if let Some(head) = head {
+                    if min.is_none_or(|m| self.compare(head, heads[m].as_ref().unwrap()) == Ordering::Less) {
+                        min = Some(i);
+                    }
+                }
+            }
+            let Some(min) = min else {
+                break;
+            };
+
+            writer.write(heads[min].as_ref().unwrap()).await?;
+            heads[min] = readers[min].next().await?;
+        }
+        writer.finish().await?;
+
+        drop(readers);
+        for run in runs {
+            directory.remove(run).await?;
+        }
+        Ok(name)
+    }
+}
+
+impl Debug for OfflineSorter {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        f.debug_struct("OfflineSorter")
+            .field("temp_prefix", &self.temp_prefix)
+            .field("ram_buffer_size", &self.ram_buffer_size)
+            .field("max_temp_files", &self.max_temp_files)
+            .field("compression", &self.compression)
+            .field("comparator", &self.comparator.as_ref().map(|_| "<custom>"))
+            .finish()
+    }
+}
+
+/// Generates names of temporary files that don't clash with files already in the directory.
+struct TempNames<'a> {
+    prefix: &'a str,
+    next: usize,
+    existing: HashSet<String>,
+}
+
+impl TempNames<'_> {
+    fn next_name(&mut self) -> String {
+        loop {
+            let name = format!("{}_sort_{}.tmp", self.prefix, self.next);
+            self.next += 1;
+            if !self.existing.contains(&name) {
+                return name;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{
+            io::{block_on, ByteBuffersDirectory, Directory, IoContext},
+            util::{ByteSequencesReader, ByteSequencesWriter, OfflineSorter, SortCompression},
+        },
+        pretty_assertions::assert_eq,
+        std::sync::Arc,
+    };
+
+    fn write_records(dir: &mut ByteBuffersDirectory, name: &str, records: &[Vec<u8>]) {
+        block_on(async {
+            let mut writer =
+                ByteSequencesWriter::new(dir.create(name, &IoContext::Default).await.unwrap(), SortCompression::None);
+            for record in records {
+                writer.write(record).await.unwrap();
+            }
+            writer.finish().await.unwrap();
+        });
+    }
+
+    fn read_records(dir: &mut ByteBuffersDirectory, name: &str) -> Vec<Vec<u8>> {
+        block_on(async {
+            let mut reader = ByteSequencesReader::new(dir.open(name, &IoContext::Read).await.unwrap());
+            let mut records = Vec::new();
+            while let Some(record) = reader.next().await.unwrap() {
+                records.push(record);
+            }
+            records
+        })
+    }
+
+    fn check_sort(sorter: &OfflineSorter, records: &[Vec<u8>], expected: &[Vec<u8>]) -> usize {
+        let mut dir = ByteBuffersDirectory::new();
+        write_records(&mut dir, "input", records);
+        let info = block_on(sorter.sort(&mut dir, "input")).unwrap();
+        assert_eq!(info.lines, records.len() as u64);
+        assert_eq!(read_records(&mut dir, &info.output), expected);
+
+        // Only the input and output remain.
+        let mut files = block_on(dir.read_dir()).unwrap();
+        files.sort();
+        let mut expected_files = vec!["input".to_string(), info.output.clone()];
+        expected_files.sort();
+        assert_eq!(files, expected_files);
+        info.runs
+    }
+
+    #[test]
+    fn test_offline_sort() {
+        let records: Vec<Vec<u8>> =
+            (0..20_000u32).map(|i| format!("record-{}", i.wrapping_mul(2_654_435_761) % 50_000).into_bytes()).collect();
+        let mut expected = records.clone();
+        expected.sort();
+
+        // Everything fits in memory.
+        assert_eq!(check_sort(&OfflineSorter::new("t"), &records, &expected), 1);
+        assert_eq!(check_sort(&OfflineSorter::new("t"), &[], &[]), 1);
+
+        // Many runs, merged in several rounds.
+        let mut sorter = OfflineSorter::new("t");
+        sorter.set_ram_buffer_size(8 << 10).unwrap().set_max_temp_files(3).unwrap();
+        assert!(check_sort(&sorter, &records, &expected) > 9);
+
+        // A custom comparator: descending by length, then by bytes.
+        sorter.set_comparator(Some(Arc::new(|a: &[u8], b: &[u8]| b.len().cmp(&a.len()).then_with(|| a.cmp(b)))));
+        let mut by_length = records.clone();
+        by_length.sort_by(|a, b| b.len().cmp(&a.len()).then_with(|| a.cmp(b)));
+        check_sort(&sorter, &records, &by_length);
+
+        #[cfg(feature = "lz4")]
+        {
+            sorter.set_comparator(None).set_compression(SortCompression::Lz4);
+            check_sort(&sorter, &records, &expected);
+        }
+
+        assert!(sorter.set_max_temp_files(1).is_err());
+        assert!(sorter.set_ram_buffer_size(0).is_err());
+    }
+}
@@ -0,0 +1,14 @@
+mod bulk_operation_packed;
+mod direct_monotonic_reader;
+mod direct_monotonic_writer;
+mod direct_reader;
+mod direct_writer;
+mod growable_writer;
+mod packed_ints;
+mod paged_bytes;
+mod paged_mutable;
+
+pub use {
+    bulk_operation_packed::*, direct_monotonic_reader::*, direct_monotonic_writer::*, direct_reader::*,
+    direct_writer::*, growable_writer::*, packed_ints::*, paged_bytes::*, paged_mutable::*,
+};
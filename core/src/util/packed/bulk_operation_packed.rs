@@ -0,0 +1,407 @@
+use std::fmt::Debug;
+
+/// The `B` parameter of a [BulkOperationPacked] whose number of bits per value is only known at runtime.
+pub const ANY_BITS_PER_VALUE: u32 = 0;
+
+/// Encodes and decodes blocks of values packed with a fixed number of bits each, like Lucene's `BulkOperation`.
+///
+/// For every number of bits per value there is a minimum number of blocks that hold a whole number of values: 25
+/// longs hold 32 values of 50 bits, for instance, and 25 bytes hold 4. Values are encoded and decoded in
+/// `iterations` of those, so `blocks` and `values` must hold at least `iterations` times the block and value counts.
+pub trait BulkOperation: Debug + Send + Sync {
+    /// Returns the number of bits per value.
+    fn bits_per_value(&self) -> u32;
+
+    /// Returns the number of long blocks encoded in an iteration.
+    fn long_block_count(&self) -> u32;
+
+    /// Returns the number of values held by [BulkOperation::long_block_count] long blocks.
+    fn long_value_count(&self) -> u32;
+
+    /// Returns the number of byte blocks encoded in an iteration.
+    fn byte_block_count(&self) -> u32;
+
+    /// Returns the number of values held by [BulkOperation::byte_block_count] byte blocks.
+    fn byte_value_count(&self) -> u32;
+
+    /// Decodes `iterations` long block counts of `blocks` into `values`.
+    fn decode_longs(&self, blocks: &[u64], values: &mut [u64], iterations: u32);
+
+    /// Decodes `iterations` byte block counts of `blocks` into `values`.
+    fn decode_bytes(&self, blocks: &[u8], values: &mut [u64], iterations: u32);
+
+    /// Encodes `iterations` long value counts of `values` into `blocks`. Values must fit in the number of bits per
+    /// value.
+    fn encode_longs(&self, values: &[u64], blocks: &mut [u64], iterations: u32);
+
+    /// Encodes `iterations` byte value counts of `values` into `blocks`. Values must fit in the number of bits per
+    /// value.
+    fn encode_bytes(&self, values: &[u64], blocks: &mut [u8], iterations: u32);
+
+    /// Returns the number of iterations to decode `value_count` values from bytes with about `ram_budget` bytes of
+    /// buffers: at least 1, and no more than `value_count` needs.
+    fn compute_iterations(&self, value_count: u32, ram_budget: usize) -> u32 {
+        let byte_value_count = self.byte_value_count();
+        let iterations = (ram_budget / (self.byte_block_count() as usize + 8 * byte_value_count as usize)) as u32;
+        if iterations == 0 {
+            1
+        } else if (iterations - 1) * byte_value_count >= value_count {
+            value_count.div_ceil(byte_value_count)
+        } else {
+            iterations
+        }
+    }
+}
+
+/// Returns the [BulkOperationPacked] for `bits_per_value`, or `None` if it isn't between 1 and 64.
+///
+/// The byte-aligned widths, which are the most common, get their own copy of the implementation, so that the
+/// compiler can fold their shifts and masks into constants; the other widths share one that reads the width at
+/// runtime.
+pub fn new_bulk_operation_packed(bits_per_value: u32) -> Option<Box<dyn BulkOperation>> {
+    match bits_per_value {
+        8 => Some(Box::new(BulkOperationPacked::<8>::new(8))),
+        16 => Some(Box::new(BulkOperationPacked::<16>::new(16))),
+        24 => Some(Box::new(BulkOperationPacked::<24>::new(24))),
+        32 => Some(Box::new(BulkOperationPacked::<32>::new(32))),
+        40 => Some(Box::new(BulkOperationPacked::<40>::new(40))),
+        48 => Some(Box::new(BulkOperationPacked::<48>::new(48))),
+        56 => Some(Box::new(BulkOperationPacked::<56>::new(56))),
+        64 => Some(Box::new(BulkOperationPacked::<64>::new(64))),
+        1..=64 => Some(Box::new(BulkOperationPacked::<ANY_BITS_PER_VALUE>::new(bits_per_value))),
+        _ => None,
+    }
+}
+
+/// A [BulkOperation] for values packed contiguously, most significant bit first, with values spanning block
+/// boundaries. This is the layout of Lucene's `PackedInts.Format.PACKED`, and its `BulkOperationPacked`.
+///
+/// `B` fixes the number of bits per value at compile time, or is [ANY_BITS_PER_VALUE] to take it from
+/// [BulkOperationPacked::new]. Both share the same implementation.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct BulkOperationPacked<const B: u32> {
+    bits_per_value: u32,
+    long_block_count: u32,
+    long_value_count: u32,
+    byte_block_count: u32,
+    byte_value_count: u32,
+}
+
+impl<const B: u32> BulkOperationPacked<B> {
+    /// Creates the operation for `bits_per_value`, which must be between 1 and 64, and equal to `B` unless `B` is
+    /// [ANY_BITS_PER_VALUE].
+    pub fn new(bits_per_value: u32) -> Self {
+        assert!((1..=64).contains(&bits_per_value), "invalid bits per value {bits_per_value}");
+        assert!(B == ANY_BITS_PER_VALUE || B == bits_per_value, "bits per value must be {B}, not {bits_per_value}");
+
+        // The smallest number of longs holding a whole number of values is the odd part of the width.
+        let long_block_count = bits_per_value >> bits_per_value.trailing_zeros();
+        let long_value_count = 64 * long_block_count / bits_per_value;
+        let mut byte_block_count = 8 * long_block_count;
+        let mut byte_value_count = long_value_count;
+        while byte_block_count & 1 == 0 && byte_value_count & 1 == 0 {
+            byte_block_count >>= 1;
+            byte_value_count >>= 1;
+        }
+
+        Self {
+            bits_per_value,
+            long_block_count,
+            long_value_count,
+            byte_block_count,
+            byte_value_count,
+        }
+    }
+
+    /// Returns the number of bits per value, as a constant when `B` fixes it.
+    #[inline(always)]
+    fn width(&self) -> u32 {
+        if B == ANY_BITS_PER_VALUE {
+            self.bits_per_value
+        } else {
+            B
+        }
+    }
+}
+
+impl<const B: u32> BulkOperation for BulkOperationPacked<B> {
+    #[inline]
+    fn bits_per_value(&self) -> u32 {
+        self.width()
+    }
+
+    #[inline]
+    fn long_block_count(&self) -> u32 {
+        self.long_block_count
+    }
+
+    #[inline]
+    fn long_value_count(&self) -> u32 {
+        self.long_value_count
+    }
+
+    #[inline]
+    fn byte_block_count(&self) -> u32 {
+        self.byte_block_count
+    }
+
+    #[inline]
+    fn byte_value_count(&self) -> u32 {
+        self.byte_value_count
+    }
+
+    fn decode_longs(&self, blocks: &[u64], values: &mut [u64], iterations: u32) {
+        decode_longs(self.width(), blocks, &mut values[..(self.long_value_count * iterations) as usize]);
+    }
+
+    fn decode_bytes(&self, blocks: &[u8], values: &mut [u64], iterations: u32) {
+        decode_bytes(self.width(), blocks, &mut values[..(self.byte_value_count * iterations) as usize]);
+    }
+
+    fn encode_longs(&self, values: &[u64], blocks: &mut [u64], iterations: u32) {
+        encode_longs(self.width(), &values[..(self.long_value_count * iterations) as usize], blocks);
+    }
+
+    fn encode_bytes(&self, values: &[u64], blocks: &mut [u8], iterations: u32) {
+        encode_bytes(self.width(), &values[..(self.byte_value_count * iterations) as usize], blocks);
+    }
+}
+
+/// Returns a mask of the low `bits` bits, which must be between 1 and 64.
+#[inline(always)]
+fn mask(bits: u32) -> u64 {
+    u64::MAX >> (64 - bits)
+}
+
+#[inline(always)]
+fn decode_longs(bits_per_value: u32, blocks: &[u64], values: &mut [u64]) {
+    let value_mask = mask(bits_per_value);
+    if 64_u32.is_multiple_of(bits_per_value) {
+        // Values never span blocks.
+        let values_per_block = (64 / bits_per_value) as usize;
+        for (&block, values) in blocks.iter().zip(values.chunks_exact_mut(values_per_block)) {
+            let mut shift = 64;
+            for value in values {
+                shift -= bits_per_value;
+                *value = (block >> shift) & value_mask;
+            }
+        }
+        return;
+    }
+
+    let mut blocks = blocks.iter();
+    let mut block = 0;
+    let mut bits_left = 0;
+    for value in values {
+        if bits_left >= bits_per_value {
+            bits_left -= bits_per_value;
+            *value = (block >> bits_left) & value_mask;
+        } else {
+            // The value starts in this block, if any of it is left, and ends in the next one.
+            let spill = bits_per_value - bits_left;
+            let high = if bits_left == 0 {
+                0
+            } else {
+                (block & mask(bits_left)) << spill
+            };
+            block = *blocks.next().expect("not enough blocks");
+            bits_left = 64 - spill;
+            *value = high | (block >> bits_left);
+            if spill < 64 {
+                *value &= value_mask;
+            }
+        }
+    }
+}
+
+#[inline(always)]
+fn decode_bytes(bits_per_value: u32, blocks: &[u8], values: &mut [u64]) {
+    if bits_per_value.is_multiple_of(8) {
+        // Each value is a run of whole big-endian bytes.
+        let bytes_per_value = (bits_per_value / 8) as usize;
+        for (value, bytes) in values.iter_mut().zip(blocks.chunks_exact(bytes_per_value)) {
+            *value = bytes.iter().fold(0, |bits, &byte| (bits << 8) | byte as u64);
+        }
+        return;
+    }
+
+    // At most bits_per_value + 7 bits are ever buffered, which fits in a u128.
+    let value_mask = mask(bits_per_value);
+    let mut bytes = blocks.iter();
+    let mut buffer = 0_u128;
+    let mut buffered = 0;
+    for value in values {
+        while buffered < bits_per_value {
+            buffer = (buffer << 8) | *bytes.next().expect("not enough blocks") as u128;
+            buffered += 8;
+        }
+        buffered -= bits_per_value;
+        *value = (buffer >> buffered) as u64 & value_mask;
+    }
+}
+
+#[inline(always)]
+fn encode_longs(bits_per_value: u32, values: &[u64], blocks: &mut [u64]) {
+    let mut blocks = blocks.iter_mut();
+    let mut next_block = 0;
+    let mut bits_left = 64;
+    for &value in values {
+        debug_assert!(value & !mask(bits_per_value) == 0, "{value} doesn't fit in {bits_per_value} bits");
+        if bits_per_value < bits_left {
+            bits_left -= bits_per_value;
+            next_block |= value << bits_left;
+        } else {
+            // Flush the block, and start the next with whatever spills into it.
+            let spill = bits_per_value - bits_left;
+            *blocks.next().expect("not enough blocks") = next_block | (value >> spill);
+            bits_left = 64 - spill;
+            next_block = if spill == 0 {
+                0
+            } else {
+                value << bits_left
+            };
+        }
+    }
+    debug_assert_eq!(bits_left, 64, "values don't fill a whole number of blocks");
+}
+
+#[inline(always)]
+fn encode_bytes(bits_per_value: u32, values: &[u64], blocks: &mut [u8]) {
+    // At most bits_per_value + 7 bits are ever buffered, which fits in a u128.
+    let mut blocks = blocks.iter_mut();
+    let mut buffer = 0_u128;
+    let mut buffered = 0;
+    for &value in values {
+        debug_assert!(value & !mask(bits_per_value) == 0, "{value} doesn't fit in {bits_per_value} bits");
+        buffer = (buffer << bits_per_value) | value as u128;
+        buffered += bits_per_value;
+        while buffered >= 8 {
+            buffered -= 8;
+            *blocks.next().expect("not enough blocks") = (buffer >> buffered) as u8;
+        }
+    }
+    debug_assert_eq!(buffered, 0, "values don't fill a whole number of blocks");
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::util::packed::{new_bulk_operation_packed, BulkOperation, BulkOperationPacked, ANY_BITS_PER_VALUE},
+        pretty_assertions::assert_eq,
+    };
+
+    /// A xorshift generator, so that failures can be reproduced from the seed.
+    struct XorShift(u64);
+
+    impl XorShift {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+    }
+
+    /// Returns `count` values of `bits_per_value` bits, mixing random values with the extremes of the range.
+    fn random_values(rng: &mut XorShift, bits_per_value: u32, count: usize) -> Vec<u64> {
+        let mask = u64::MAX >> (64 - bits_per_value);
+        (0..count)
+            .map(|i| match i % 7 {
+                0 => 0,
+                1 => mask,
+                _ => rng.next() & mask,
+            })
+            .collect()
+    }
+
+    /// Packs values most significant bit first one bit at a time, as a reference for the encoders.
+    fn pack_bits(bits_per_value: u32, values: &[u64]) -> Vec<u8> {
+        let mut bytes = vec![0_u8; (values.len() * bits_per_value as usize).div_ceil(8)];
+        let mut bit = 0;
+        for &value in values {
+            for shift in (0..bits_per_value).rev() {
+                bytes[bit / 8] |= (((value >> shift) & 1) as u8) << (7 - bit % 8);
+                bit += 1;
+            }
+        }
+        bytes
+    }
+
+    /// Checks that `values` are encoded as [pack_bits] does, in longs and in bytes, and decode back to themselves.
+    fn check_round_trip(op: &dyn BulkOperation, values: &[u64]) {
+        let bits_per_value = op.bits_per_value();
+        let iterations = values.len() as u32 / op.long_value_count();
+        let byte_iterations = values.len() as u32 / op.byte_value_count();
+        let expected = pack_bits(bits_per_value, values);
+
+        let mut blocks = vec![0_u64; (op.long_block_count() * iterations) as usize];
+        op.encode_longs(values, &mut blocks, iterations);
+        let bytes: Vec<u8> = blocks.iter().flat_map(|block| block.to_be_bytes()).collect();
+        assert_eq!(bytes, expected, "bits_per_value={bits_per_value}");
+        let mut bytes = vec![0_u8; (op.byte_block_count() * byte_iterations) as usize];
+        op.encode_bytes(values, &mut bytes, byte_iterations);
+        assert_eq!(bytes, expected, "bits_per_value={bits_per_value}");
+
+        let mut decoded = vec![0; values.len()];
+        op.decode_longs(&blocks, &mut decoded, iterations);
+        assert_eq!(decoded, values, "bits_per_value={bits_per_value}");
+        let mut decoded = vec![0; values.len()];
+        op.decode_bytes(&bytes, &mut decoded, byte_iterations);
+        assert_eq!(decoded, values, "bits_per_value={bits_per_value}");
+    }
+
+    #[test]
+    fn test_bulk_operation_packed_every_width() {
+        let mut rng = XorShift(0x2545_f491_4f6c_dd1d);
+        for bits_per_value in 1..=64 {
+            let op = BulkOperationPacked::<ANY_BITS_PER_VALUE>::new(bits_per_value);
+            for iterations in [1, 2, 5] {
+                let values = random_values(&mut rng, bits_per_value, (op.long_value_count() * iterations) as usize);
+                check_round_trip(&op, &values);
+            }
+
+            // The factory's operation, specialized or not, encodes the same way.
+            let values = random_values(&mut rng, bits_per_value, (op.long_value_count() * 3) as usize);
+            check_round_trip(new_bulk_operation_packed(bits_per_value).unwrap().as_ref(), &values);
+        }
+    }
+
+    #[test]
+    fn test_bulk_operation_packed_specialized_widths() {
+        fn check<const B: u32>(rng: &mut XorShift) {
+            let op = BulkOperationPacked::<B>::new(B);
+            assert_eq!(op, BulkOperationPacked::<B>::new(B));
+            let values = random_values(rng, B, (op.long_value_count() * 3) as usize);
+            check_round_trip(&op, &values);
+        }
+
+        let mut rng = XorShift(0x9e37_79b9_7f4a_7c15);
+        check::<8>(&mut rng);
+        check::<16>(&mut rng);
+        check::<24>(&mut rng);
+        check::<32>(&mut rng);
+        check::<40>(&mut rng);
+        check::<48>(&mut rng);
+        check::<56>(&mut rng);
+        check::<64>(&mut rng);
+    }
+
+    #[test]
+    fn test_bulk_operation_packed_counts() {
+        // As in Lucene: 32 values of 50 bits fill 25 longs, and 4 of them fill 25 bytes.
+        let op = new_bulk_operation_packed(50).unwrap();
+        assert_eq!((op.long_block_count(), op.long_value_count()), (25, 32));
+        assert_eq!((op.byte_block_count(), op.byte_value_count()), (25, 4));
+        let op = new_bulk_operation_packed(16).unwrap();
+        assert_eq!((op.long_block_count(), op.long_value_count()), (1, 4));
+        assert_eq!((op.byte_block_count(), op.byte_value_count()), (2, 1));
+        assert!(new_bulk_operation_packed(0).is_none());
+        assert!(new_bulk_operation_packed(65).is_none());
+
+        // An iteration takes 25 bytes of blocks and 4 values of 8 bytes, so 1024 bytes buy 17, unless fewer are needed.
+        let op = new_bulk_operation_packed(50).unwrap();
+        assert_eq!(op.compute_iterations(1000, 1024), 17);
+        assert_eq!(op.compute_iterations(10, 1024), 3);
+        assert_eq!(op.compute_iterations(1000, 10), 1);
+    }
+}
@@ -0,0 +1,234 @@
+use {
+    crate::{
+        io::RandomAccessInput,
+        util::{
+            packed::{DirectReader, MAX_BLOCK_SHIFT, MIN_BLOCK_SHIFT},
+            size_of_vec, Accountable, NamedAccountable,
+        },
+        BoxResult, LuceneError,
+    },
+    std::{io::Result as IoResult, sync::Arc},
+    tokio::io::{AsyncRead, AsyncReadExt},
+};
+
+/// The per-block metadata written by a [DirectMonotonicWriter](crate::util::packed::DirectMonotonicWriter), which
+/// readers keep in memory.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DirectMonotonicMeta {
+    num_values: u64,
+    block_shift: u32,
+    mins: Vec<i64>,
+    avgs: Vec<f32>,
+    bits_per_values: Vec<u8>,
+    offsets: Vec<u64>,
+}
+
+impl DirectMonotonicMeta {
+    /// Reads the metadata of `num_values` values written in blocks of `1 << block_shift`. This fails with
+    /// [LuceneError::CorruptIndex] if the metadata is invalid.
+    pub async fn read_from<R: AsyncRead + Unpin + ?Sized>(
+        input: &mut R,
+        num_values: u64,
+        block_shift: u32,
+    ) -> BoxResult<Self> {
+        if !(MIN_BLOCK_SHIFT..=MAX_BLOCK_SHIFT).contains(&block_shift) {
+            return Err(
+                LuceneError::CorruptIndex(format!("Invalid monotonic block shift: {block_shift}").into()).into()
+            );
+        }
+
+        let num_blocks = num_values.div_ceil(1 << block_shift) as usize;
+        let mut meta = Self {
+            num_values,
+            block_shift,
+            mins: Vec::with_capacity(num_blocks),
+            avgs: Vec::with_capacity(num_blocks),
+            bits_per_values: Vec::with_capacity(num_blocks),
+            offsets: Vec::with_capacity(num_blocks),
+        };
+        for _ in 0..num_blocks {
+            meta.mins.push(input.read_i64_le().await?);
+            meta.avgs.push(f32::from_bits(input.read_u32_le().await?));
+            meta.offsets.push(input.read_u64_le().await?);
+            let bits_per_value = input.read_u8().await?;
+            if bits_per_value > 64 {
+                return Err(LuceneError::CorruptIndex(
+                    format!("Invalid monotonic bits per value: {bits_per_value}").into(),
+                )
+                .into());
+            }
+            meta.bits_per_values.push(bits_per_value);
+        }
+        Ok(meta)
+    }
+
+    /// Returns the number of values.
+    #[inline]
+    pub fn num_values(&self) -> u64 {
+        self.num_values
+    }
+}
+
+impl Accountable for DirectMonotonicMeta {
+    fn ram_bytes_used(&self) -> usize {
+        size_of::<Self>()
+            + self.mins.capacity() * size_of::<i64>()
+            + self.avgs.capacity() * size_of::<f32>()
+            + self.bits_per_values.capacity()
+            + self.offsets.capacity() * size_of::<u64>()
+    }
+}
+
+/// Looks up the values written by a [DirectMonotonicWriter](crate::util::packed::DirectMonotonicWriter) by index.
+#[derive(Clone, Debug)]
+pub struct DirectMonotonicReader {
+    meta: Arc<DirectMonotonicMeta>,
+    readers: Vec<Option<DirectReader>>,
+}
+
+impl DirectMonotonicReader {
+    /// Creates a reader of the values described by `meta`, whose data was written to `data` at `offset`. This fails
+    /// with [LuceneError::CorruptIndex] if a block has an unsupported number of bits per value.
+    pub fn new(meta: Arc<DirectMonotonicMeta>, data: Arc<dyn RandomAccessInput>, offset: u64) -> BoxResult<Self> {
+        let mut readers = Vec::with_capacity(meta.offsets.len());
+        for (&block_offset, &bits_per_value) in meta.offsets.iter().zip(&meta.bits_per_values) {
+            readers.push(match bits_per_value {
+                0 => None,
+                bits_per_value => {
+                    let reader = DirectReader::new(data.clone(), bits_per_value as u32, offset + block_offset)
+                        .map_err(|_| {
+                            LuceneError::CorruptIndex(
+                                format!("Invalid monotonic bits per value: {bits_per_value}").into(),
+                            )
+                        })?;
+                    Some(reader)
+                }
+            });
+        }
+        Ok(Self {
+            meta,
+            readers,
+        })
+    }
+
+    /// Returns the number of values.
+    #[inline]
+    pub fn len(&self) -> u64 {
+        self.meta.num_values
+    }
+
+    /// Indicates whether there are no values.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.meta.num_values == 0
+    }
+
+    /// Returns the value at `index`.
+    pub fn get(&self, index: u64) -> IoResult<i64> {
+        debug_assert!(index < self.meta.num_values, "index {index} out of bounds for {} values", self.meta.num_values);
+        let block = (index >> self.meta.block_shift) as usize;
+        let index_in_block = index & ((1 << self.meta.block_shift) - 1);
+        let delta = match &self.readers[block] {
+            Some(reader) => reader.get(index_in_block)? as i64,
+            None => 0,
+        };
+        Ok(self.meta.mins[block]
+            .wrapping_add((self.meta.avgs[block] * index_in_block as f32) as i64)
+            .wrapping_add(delta))
+    }
+
+    /// Searches `from..to` for `key`, as [slice::binary_search] does: this returns `Ok` with the index of a matching
+    /// value, or `Err` with the index where `key` would be inserted to keep the values in order.
+    pub fn binary_search(&self, from: u64, to: u64, key: i64) -> IoResult<Result<u64, u64>> {
+        let (mut low, mut high) = (from, to);
+        while low < high {
+            let mid = low + (high - low) / 2;
+            let value = self.get(mid)?;
+            if value < key {
+                low = mid + 1;
+            } else if value > key {
+                high = mid;
+            } else {
+                return Ok(Ok(mid));
+            }
+        }
+        Ok(Err(low))
+    }
+}
+
+impl Accountable for DirectMonotonicReader {
+    fn ram_bytes_used(&self) -> usize {
+        size_of::<Self>() + self.meta.ram_bytes_used() + size_of_vec(&self.readers)
+    }
+
+    fn child_resources(&self) -> Vec<NamedAccountable> {
+        vec![NamedAccountable::new("meta", self.meta.as_ref())]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{
+            io::block_on,
+            util::packed::{DirectMonotonicMeta, DirectMonotonicReader, DirectMonotonicWriter},
+        },
+        pretty_assertions::assert_eq,
+        std::sync::Arc,
+    };
+
+    fn round_trip(values: &[i64], block_shift: u32) -> DirectMonotonicReader {
+        let (mut meta, mut data) = (Vec::new(), Vec::new());
+        block_on(async {
+            let mut writer =
+                DirectMonotonicWriter::new(&mut meta, &mut data, values.len() as u64, block_shift).unwrap();
+            for &value in values {
+                writer.add(value).await.unwrap();
+            }
+            writer.finish().await.unwrap();
+        });
+
+        let meta =
+            block_on(DirectMonotonicMeta::read_from(&mut meta.as_slice(), values.len() as u64, block_shift)).unwrap();
+        DirectMonotonicReader::new(Arc::new(meta), Arc::new(data), 0).unwrap()
+    }
+
+    #[test]
+    fn test_direct_monotonic() {
+        // Offsets of values of varying lengths, a constant block, and a block with a large jump.
+        let mut values: Vec<i64> = Vec::new();
+        let mut offset = -50;
+        for i in 0..1000i64 {
+            offset += (i * 7919) % 13;
+            values.push(offset);
+        }
+        values.extend([offset; 64]);
+        values.extend((0..100).map(|i| offset + (i / 50) * (1 << 40)));
+
+        for block_shift in [2, 6, 10] {
+            let reader = round_trip(&values, block_shift);
+            assert_eq!(reader.len(), values.len() as u64);
+            for (i, &value) in values.iter().enumerate() {
+                assert_eq!(reader.get(i as u64).unwrap(), value, "block shift {block_shift}, index {i}");
+            }
+            assert_eq!(
+                reader.binary_search(0, 1000, values[500]).unwrap(),
+                values[..1000].binary_search(&values[500]).map(|i| i as u64).map_err(|i| i as u64)
+            );
+            assert_eq!(reader.binary_search(0, 1000, -100).unwrap(), Err(0));
+        }
+
+        // A single block of equal values takes no data.
+        let reader = round_trip(&[5; 10], 4);
+        assert_eq!(reader.get(9).unwrap(), 5);
+
+        block_on(async {
+            let (mut meta, mut data) = (Vec::new(), Vec::new());
+            assert!(DirectMonotonicWriter::new(&mut meta, &mut data, 2, 1).is_err());
+            let mut writer = DirectMonotonicWriter::new(&mut meta, &mut data, 2, 2).unwrap();
+            writer.add(10).await.unwrap();
+            assert!(writer.add(9).await.is_err());
+            assert!(writer.finish().await.is_err());
+        });
+    }
+}
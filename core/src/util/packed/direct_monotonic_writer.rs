@@ -0,0 +1,131 @@
+use {
+    crate::{
+        util::packed::{direct_bits_required, DirectWriter},
+        BoxResult, LuceneError,
+    },
+    tokio::io::{AsyncWrite, AsyncWriteExt},
+};
+
+/// The smallest block shift [DirectMonotonicWriter] accepts.
+pub const MIN_BLOCK_SHIFT: u32 = 2;
+
+/// The largest block shift [DirectMonotonicWriter] accepts.
+pub const MAX_BLOCK_SHIFT: u32 = 22;
+
+/// Writes a non-decreasing sequence of integers, such as the offsets of variable-length values, compactly enough to be
+/// looked up by index with a [DirectMonotonicReader](crate::util::packed::DirectMonotonicReader).
+///
+/// As in Lucene, values are split into blocks of `1 << block_shift`. Each block is modeled as a line from its first
+/// value with the block's average increment, and only each value's distance above that line is stored, with a
+/// [DirectWriter]. The line of each block goes to the metadata output and the distances to the data output.
+#[derive(Debug)]
+pub struct DirectMonotonicWriter<'a, M: ?Sized, D: ?Sized> {
+    meta: &'a mut M,
+    data: &'a mut D,
+    num_values: u64,
+    buffer: Vec<i64>,
+    block_size: usize,
+    count: u64,
+    previous: Option<i64>,
+    data_bytes: u64,
+}
+
+impl<'a, M, D> DirectMonotonicWriter<'a, M, D>
+where
+    M: AsyncWrite + Unpin + ?Sized,
+    D: AsyncWrite + Unpin + ?Sized,
+{
+    /// Creates a writer of `num_values` values, in blocks of `1 << block_shift`. This fails with
+    /// [LuceneError::InvalidArgument] unless `block_shift` is between [MIN_BLOCK_SHIFT] and [MAX_BLOCK_SHIFT].
+    pub fn new(meta: &'a mut M, data: &'a mut D, num_values: u64, block_shift: u32) -> BoxResult<Self> {
+        if !(MIN_BLOCK_SHIFT..=MAX_BLOCK_SHIFT).contains(&block_shift) {
+            return Err(LuceneError::InvalidArgument(format!(
+                "block shift must be between {MIN_BLOCK_SHIFT} and {MAX_BLOCK_SHIFT}, got {block_shift}"
+            ))
+            .into());
+        }
+        let block_size = 1 << block_shift;
+        Ok(Self {
+            meta,
+            data,
+            num_values,
+            buffer: Vec::with_capacity(num_values.min(block_size as u64) as usize),
+            block_size,
+            count: 0,
+            previous: None,
+            data_bytes: 0,
+        })
+    }
+
+    /// Adds the next value. This fails with [LuceneError::InvalidArgument] if it is less than the previous value, or
+    /// if all values have already been added.
+    pub async fn add(&mut self, value: i64) -> BoxResult<()> {
+        if self.previous.is_some_and(|previous| value < previous) {
+            return Err(LuceneError::InvalidArgument(format!(
+                "values must not decrease, but {value} follows {}",
+                self.previous.unwrap()
+            ))
+            .into());
+        }
+        if self.count >= self.num_values {
+            return Err(LuceneError::InvalidArgument(format!("more than {} values added", self.num_values)).into());
+        }
+
+        self.buffer.push(value);
+        self.previous = Some(value);
+        self.count += 1;
+        if self.buffer.len() == self.block_size {
+            self.flush().await?;
+        }
+        Ok(())
+    }
+
+    /// Writes the buffered block.
+    async fn flush(&mut self) -> BoxResult<()> {
+        let len = self.buffer.len();
+        let avg_inc = ((self.buffer[len - 1].wrapping_sub(self.buffer[0])) as f64 / (len.max(2) - 1) as f64) as f32;
+        for (i, value) in self.buffer.iter_mut().enumerate() {
+            *value = value.wrapping_sub((avg_inc * i as f32) as i64);
+        }
+        let min = self.buffer.iter().copied().min().unwrap();
+        let mut max_delta = 0u64;
+        for value in &mut self.buffer {
+            *value = value.wrapping_sub(min);
+            // Or-ing gives the same number of bits as the maximum, and copes with deltas that overflowed.
+            max_delta |= *value as u64;
+        }
+
+        self.meta.write_i64_le(min).await?;
+        self.meta.write_u32_le(avg_inc.to_bits()).await?;
+        self.meta.write_u64_le(self.data_bytes).await?;
+        if max_delta == 0 {
+            self.meta.write_u8(0).await?;
+        } else {
+            let bits_per_value = direct_bits_required(max_delta);
+            let mut writer = DirectWriter::new(&mut *self.data, len as u64, bits_per_value)?;
+            for &delta in &self.buffer {
+                writer.add(delta as u64).await?;
+            }
+            self.data_bytes += writer.finish().await?;
+            self.meta.write_u8(bits_per_value as u8).await?;
+        }
+
+        self.buffer.clear();
+        Ok(())
+    }
+
+    /// Writes the last block. This fails with [LuceneError::InvalidArgument] if fewer values were added than promised.
+    pub async fn finish(mut self) -> BoxResult<()> {
+        if self.count != self.num_values {
+            return Err(LuceneError::InvalidArgument(format!(
+                "expected {} values but only {} were added",
+                self.num_values, self.count
+            ))
+            .into());
+        }
+        if !self.buffer.is_empty() {
+            self.flush().await?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,109 @@
+use {
+    crate::{
+        io::RandomAccessInput,
+        util::{
+            packed::{max_value, SUPPORTED_BITS_PER_VALUE},
+            Accountable,
+        },
+        BoxResult, LuceneError,
+    },
+    std::{io::Result as IoResult, sync::Arc},
+};
+
+/// Looks up the values written by a [DirectWriter](crate::util::packed::DirectWriter) by index, straight from a
+/// [RandomAccessInput] and without decoding any other value.
+///
+/// Each value is read with a single little-endian read of the narrowest byte, short, int or long that holds it at any
+/// bit offset, then shifted and masked; the writer's padding guarantees such reads stay within the data.
+#[derive(Clone, Debug)]
+pub struct DirectReader {
+    input: Arc<dyn RandomAccessInput>,
+    offset: u64,
+    bits_per_value: u32,
+    mask: u64,
+}
+
+impl DirectReader {
+    /// Creates a reader of values of `bits_per_value` bits written to `input` at `offset`. This fails with
+    /// [LuceneError::InvalidArgument] unless `bits_per_value` is one of [SUPPORTED_BITS_PER_VALUE].
+    pub fn new(input: Arc<dyn RandomAccessInput>, bits_per_value: u32, offset: u64) -> BoxResult<Self> {
+        if !SUPPORTED_BITS_PER_VALUE.contains(&bits_per_value) {
+            return Err(LuceneError::InvalidArgument(format!(
+                "unsupported bits per value {bits_per_value}; must be one of {SUPPORTED_BITS_PER_VALUE:?}"
+            ))
+            .into());
+        }
+        Ok(Self {
+            input,
+            offset,
+            bits_per_value,
+            mask: max_value(bits_per_value),
+        })
+    }
+
+    /// Returns the number of bits per value.
+    #[inline]
+    pub fn bits_per_value(&self) -> u32 {
+        self.bits_per_value
+    }
+
+    /// Returns the value at `index`.
+    pub fn get(&self, index: u64) -> IoResult<u64> {
+        let bit = index * self.bits_per_value as u64;
+        let pos = self.offset + (bit >> 3);
+        let shift = (bit & 7) as u32;
+        let word = match self.bits_per_value {
+            33.. => self.input.read_u64_le_at(pos)?,
+            17.. => self.input.read_u32_le_at(pos)? as u64,
+            9.. => self.input.read_u16_le_at(pos)? as u64,
+            _ => self.input.read_u8_at(pos)? as u64,
+        };
+        Ok((word >> shift) & self.mask)
+    }
+}
+
+impl Accountable for DirectReader {
+    /// The data is read in place from the input, which isn't counted.
+    fn ram_bytes_used(&self) -> usize {
+        size_of::<Self>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{
+            io::block_on,
+            util::packed::{direct_byte_count, max_value, DirectReader, DirectWriter, SUPPORTED_BITS_PER_VALUE},
+        },
+        pretty_assertions::assert_eq,
+        std::sync::Arc,
+    };
+
+    #[test]
+    fn test_direct_reader() {
+        for bits_per_value in SUPPORTED_BITS_PER_VALUE {
+            // Enough values to cross a buffer flush, and an odd count so 4- and 12-bit values end mid-byte.
+            let num_values = 2001u64;
+            let value = |i: u64| i.wrapping_mul(0x9e37_79b9_7f4a_7c15) & max_value(bits_per_value);
+
+            // Write after a few unrelated bytes to check that offsets are honored.
+            let mut output = vec![0xff; 3];
+            block_on(async {
+                let mut writer = DirectWriter::new(&mut output, num_values, bits_per_value).unwrap();
+                for i in 0..num_values {
+                    writer.add(value(i)).await.unwrap();
+                }
+                writer.finish().await.unwrap();
+            });
+            assert_eq!(output.len() as u64, 3 + direct_byte_count(num_values, bits_per_value));
+
+            let reader = DirectReader::new(Arc::new(output), bits_per_value, 3).unwrap();
+            for i in 0..num_values {
+                assert_eq!(reader.get(i).unwrap(), value(i), "{bits_per_value} bits, index {i}");
+            }
+        }
+
+        assert!(DirectReader::new(Arc::new(Vec::new()), 7, 0).is_err());
+    }
+}
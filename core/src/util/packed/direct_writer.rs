@@ -0,0 +1,183 @@
+use {
+    crate::{util::packed::bits_required, BoxResult, LuceneError},
+    tokio::io::{AsyncWrite, AsyncWriteExt},
+};
+
+/// The numbers of bits per value that [DirectWriter] supports. Each lets a value be read with a single aligned read of
+/// a byte, short, int or long.
+pub const SUPPORTED_BITS_PER_VALUE: [u32; 14] = [1, 2, 4, 8, 12, 16, 20, 24, 28, 32, 40, 48, 56, 64];
+
+/// The number of values buffered before they are packed and written.
+const BUFFER_VALUES: usize = 1024;
+
+/// Rounds a number of bits per value up to the nearest one that [DirectWriter] supports.
+pub fn round_bits(bits_per_value: u32) -> u32 {
+    SUPPORTED_BITS_PER_VALUE.into_iter().find(|&supported| supported >= bits_per_value).unwrap_or(64)
+}
+
+/// Returns the number of bits per value [DirectWriter] needs to store values up to `max_value`.
+#[inline]
+pub fn direct_bits_required(max_value: u64) -> u32 {
+    round_bits(bits_required(max_value))
+}
+
+/// Returns the number of bytes written by [DirectWriter] for `num_values` values of `bits_per_value` bits, including
+/// the padding that lets the last value be read with a full-width read.
+pub fn direct_byte_count(num_values: u64, bits_per_value: u32) -> u64 {
+    (num_values * bits_per_value as u64).div_ceil(8) + padding_bytes(bits_per_value) as u64
+}
+
+/// Returns the number of zero bytes written after the values so that the last one can be read with a read of the
+/// width [DirectReader](crate::util::packed::DirectReader) uses for `bits_per_value`.
+fn padding_bytes(bits_per_value: u32) -> u32 {
+    let padding_bits = match bits_per_value {
+        33.. => 64 - bits_per_value,
+        17.. => 32 - bits_per_value,
+        9.. => 16 - bits_per_value,
+        _ => 0,
+    };
+    padding_bits.div_ceil(8)
+}
+
+/// Writes unsigned integers of a fixed number of bits as a little-endian bit stream that
+/// [DirectReader](crate::util::packed::DirectReader) can look up by index without decoding the rest, as Lucene's
+/// `DirectWriter` does for doc values and points.
+///
+/// The number of values must be known up front, and exactly that many must be [added](Self::add) before the writer is
+/// [finished](Self::finish).
+#[derive(Debug)]
+pub struct DirectWriter<'a, W: ?Sized> {
+    output: &'a mut W,
+    bits_per_value: u32,
+    num_values: u64,
+    count: u64,
+    buffer: Vec<u64>,
+    bytes_written: u64,
+}
+
+impl<'a, W: AsyncWrite + Unpin + ?Sized> DirectWriter<'a, W> {
+    /// Creates a writer of `num_values` values of `bits_per_value` bits to `output`. This fails with
+    /// [LuceneError::InvalidArgument] unless `bits_per_value` is one of [SUPPORTED_BITS_PER_VALUE].
+    pub fn new(output: &'a mut W, num_values: u64, bits_per_value: u32) -> BoxResult<Self> {
+        if !SUPPORTED_BITS_PER_VALUE.contains(&bits_per_value) {
+            return Err(LuceneError::InvalidArgument(format!(
+                "unsupported bits per value {bits_per_value}; must be one of {SUPPORTED_BITS_PER_VALUE:?}"
+            ))
+            .into());
+        }
+        Ok(Self {
+            output,
+            bits_per_value,
+            num_values,
+            count: 0,
+            buffer: Vec::with_capacity(num_values.min(BUFFER_VALUES as u64) as usize),
+            bytes_written: 0,
+        })
+    }
+
+    /// Adds the next value. This fails with [LuceneError::InvalidArgument] if the value doesn't fit in the number of
+    /// bits per value, or if all values have already been added.
+    pub async fn add(&mut self, value: u64) -> BoxResult<()> {
+        if self.bits_per_value < 64 && value >> self.bits_per_value != 0 {
+            return Err(LuceneError::InvalidArgument(format!(
+                "value {value} doesn't fit in {} bits",
+                self.bits_per_value
+            ))
+            .into());
+        }
+        if self.count >= self.num_values {
+            return Err(LuceneError::InvalidArgument(format!("more than {} values added", self.num_values)).into());
+        }
+
+        self.buffer.push(value);
+        self.count += 1;
+        if self.buffer.len() == BUFFER_VALUES {
+            self.flush().await?;
+        }
+        Ok(())
+    }
+
+    /// Packs the buffered values and writes them. Only the last flush can end partway through a byte, since the buffer
+    /// holds a multiple of 8 values.
+    async fn flush(&mut self) -> BoxResult<()> {
+        let mut bytes = Vec::with_capacity((self.buffer.len() * self.bits_per_value as usize).div_ceil(8));
+        let mut pending = 0u128;
+        let mut pending_bits = 0;
+        for &value in &self.buffer {
+            pending |= (value as u128) << pending_bits;
+            pending_bits += self.bits_per_value;
+            while pending_bits >= 8 {
+                bytes.push(pending as u8);
+                pending >>= 8;
+                pending_bits -= 8;
+            }
+        }
+        if pending_bits > 0 {
+            bytes.push(pending as u8);
+        }
+
+        self.output.write_all(&bytes).await?;
+        self.bytes_written += bytes.len() as u64;
+        self.buffer.clear();
+        Ok(())
+    }
+
+    /// Writes the remaining values and the trailing padding, returning the total number of bytes written. This fails
+    /// with [LuceneError::InvalidArgument] if fewer values were added than promised.
+    pub async fn finish(mut self) -> BoxResult<u64> {
+        if self.count != self.num_values {
+            return Err(LuceneError::InvalidArgument(format!(
+                "expected {} values but only {} were added",
+                self.num_values, self.count
+            ))
+            .into());
+        }
+
+        self.flush().await?;
+        let padding = vec![0; padding_bytes(self.bits_per_value) as usize];
+        self.output.write_all(&padding).await?;
+        self.bytes_written += padding.len() as u64;
+        Ok(self.bytes_written)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{
+            io::block_on,
+            util::packed::{direct_bits_required, direct_byte_count, round_bits, DirectWriter},
+        },
+        pretty_assertions::assert_eq,
+    };
+
+    #[test]
+    fn test_direct_writer() {
+        assert_eq!(round_bits(3), 4);
+        assert_eq!(round_bits(9), 12);
+        assert_eq!(round_bits(33), 40);
+        assert_eq!(direct_bits_required(0), 1);
+        assert_eq!(direct_bits_required(1000), 12);
+        assert_eq!(direct_bits_required(u64::MAX), 64);
+
+        block_on(async {
+            let mut output = Vec::new();
+            assert!(DirectWriter::new(&mut output, 3, 3).is_err());
+
+            // Three 12-bit values take 4.5 bytes, rounded up, plus 1 byte of padding for 16-bit reads.
+            let mut writer = DirectWriter::new(&mut output, 3, 12).unwrap();
+            writer.add(0xabc).await.unwrap();
+            assert!(writer.add(0x1000).await.is_err());
+            writer.add(0x123).await.unwrap();
+            writer.add(0xfff).await.unwrap();
+            assert!(writer.add(0).await.is_err());
+            assert_eq!(writer.finish().await.unwrap(), direct_byte_count(3, 12));
+            assert_eq!(output, vec![0xbc, 0x3a, 0x12, 0xff, 0x0f, 0x00]);
+
+            let mut output = Vec::new();
+            let mut writer = DirectWriter::new(&mut output, 2, 8).unwrap();
+            writer.add(1).await.unwrap();
+            assert!(writer.finish().await.is_err());
+        });
+    }
+}
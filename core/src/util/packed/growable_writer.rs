@@ -0,0 +1,123 @@
+use crate::util::{
+    packed::{bits_required, max_value, Mutable, Packed64},
+    Accountable,
+};
+
+/// A [Mutable] that starts with few bits per value and widens all of its values whenever one is set that doesn't fit,
+/// so that arrays can be filled without knowing the largest value up front.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GrowableWriter {
+    current: Packed64,
+    current_mask: u64,
+    acceptable_overhead_ratio: f32,
+}
+
+impl GrowableWriter {
+    /// Creates an array of `value_count` zeros, starting at `start_bits_per_value` bits per value.
+    pub fn new(start_bits_per_value: u32, value_count: usize, acceptable_overhead_ratio: f32) -> Self {
+        let current = Packed64::with_format(value_count, start_bits_per_value, acceptable_overhead_ratio);
+        Self {
+            current_mask: max_value(current.bits_per_value()),
+            current,
+            acceptable_overhead_ratio,
+        }
+    }
+
+    /// Returns the acceptable overhead ratio used when widening the values.
+    #[inline]
+    pub fn acceptable_overhead_ratio(&self) -> f32 {
+        self.acceptable_overhead_ratio
+    }
+
+    /// Widens the values if needed so that `value` fits.
+    fn ensure_capacity(&mut self, value: u64) {
+        if value & self.current_mask == value {
+            return;
+        }
+        let mut widened = Packed64::with_format(self.size(), bits_required(value), self.acceptable_overhead_ratio);
+        for index in 0..self.size() {
+            widened.set(index, self.current.get(index));
+        }
+        self.current_mask = max_value(widened.bits_per_value());
+        self.current = widened;
+    }
+}
+
+impl Mutable for GrowableWriter {
+    fn with_format(value_count: usize, bits_per_value: u32, acceptable_overhead_ratio: f32) -> Self {
+        Self::new(bits_per_value, value_count, acceptable_overhead_ratio)
+    }
+
+    #[inline]
+    fn size(&self) -> usize {
+        self.current.size()
+    }
+
+    #[inline]
+    fn bits_per_value(&self) -> u32 {
+        self.current.bits_per_value()
+    }
+
+    #[inline]
+    fn get(&self, index: usize) -> u64 {
+        self.current.get(index)
+    }
+
+    fn set(&mut self, index: usize, value: u64) {
+        self.ensure_capacity(value);
+        self.current.set(index, value);
+    }
+
+    fn fill(&mut self, from: usize, to: usize, value: u64) {
+        self.ensure_capacity(value);
+        self.current.fill(from, to, value);
+    }
+
+    fn resize(&self, value_count: usize) -> Self {
+        Self {
+            current: self.current.resize(value_count),
+            current_mask: self.current_mask,
+            acceptable_overhead_ratio: self.acceptable_overhead_ratio,
+        }
+    }
+}
+
+impl Accountable for GrowableWriter {
+    fn ram_bytes_used(&self) -> usize {
+        size_of::<Self>() - size_of::<Packed64>() + self.current.ram_bytes_used()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::util::packed::{GrowableWriter, Mutable, COMPACT, DEFAULT},
+        pretty_assertions::assert_eq,
+    };
+
+    #[test]
+    fn test_growable_writer() {
+        let mut writer = GrowableWriter::new(1, 100, COMPACT);
+        assert_eq!(writer.bits_per_value(), 1);
+        writer.set(3, 1);
+        assert_eq!(writer.bits_per_value(), 1);
+        writer.set(4, 5);
+        assert_eq!(writer.bits_per_value(), 3);
+        writer.set(99, 1 << 40);
+        assert_eq!(writer.bits_per_value(), 41);
+        assert_eq!((writer.get(3), writer.get(4), writer.get(5), writer.get(99)), (1, 5, 0, 1 << 40));
+
+        let resized = writer.resize(200);
+        assert_eq!(resized.size(), 200);
+        assert_eq!((resized.get(4), resized.get(99), resized.get(150)), (5, 1 << 40, 0));
+
+        // With some overhead allowed, widths round up to whole bytes.
+        let mut writer = GrowableWriter::new(1, 10, DEFAULT);
+        writer.fill(0, 10, 200);
+        assert_eq!(writer.bits_per_value(), 8);
+        assert!((0..10).all(|i| writer.get(i) == 200));
+        writer.set(0, u64::MAX);
+        assert_eq!(writer.bits_per_value(), 64);
+        assert_eq!((writer.get(0), writer.get(9)), (u64::MAX, 200));
+    }
+}
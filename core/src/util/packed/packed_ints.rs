@@ -0,0 +1,214 @@
+use {crate::util::Accountable, std::fmt::Debug};
+
+/// An acceptable overhead ratio that never rounds the number of bits per value up.
+pub const COMPACT: f32 = 0.0;
+
+/// An acceptable overhead ratio of 25%, the default.
+pub const DEFAULT: f32 = 0.25;
+
+/// An acceptable overhead ratio of 50%.
+pub const FAST: f32 = 0.5;
+
+/// An acceptable overhead ratio that always rounds the number of bits per value up to a whole byte, short, int or
+/// long, so that reads never mask.
+pub const FASTEST: f32 = 7.0;
+
+/// Returns the number of bits needed to store `value`, which is at least 1.
+#[inline]
+pub fn bits_required(value: u64) -> u32 {
+    (64 - value.leading_zeros()).max(1)
+}
+
+/// Returns the largest value that fits in `bits_per_value` bits, which must be between 1 and 64.
+#[inline]
+pub fn max_value(bits_per_value: u32) -> u64 {
+    debug_assert!((1..=64).contains(&bits_per_value), "invalid bits per value {bits_per_value}");
+    u64::MAX >> (64 - bits_per_value)
+}
+
+/// Returns the number of bits per value to use for values that need `bits_per_value` bits, given an acceptable
+/// overhead ratio: as in Lucene, the number is rounded up to 8, 16, 32 or 64 when the extra bits per value stay
+/// within the ratio, since aligned values are faster to read and write.
+pub fn fastest_bits_per_value(bits_per_value: u32, acceptable_overhead_ratio: f32) -> u32 {
+    let ratio = acceptable_overhead_ratio.clamp(COMPACT, FASTEST);
+    let max_bits_per_value = bits_per_value + (ratio * bits_per_value as f32) as u32;
+    [8, 16, 32, 64]
+        .into_iter()
+        .find(|&aligned| bits_per_value <= aligned && max_bits_per_value >= aligned)
+        .unwrap_or(bits_per_value)
+}
+
+/// A fixed-size array of unsigned integers of the same bit width, which can be updated in place.
+pub trait Mutable: Accountable + Clone + Debug + Send + Sync {
+    /// Creates an array of `value_count` zeros, each of `bits_per_value` bits, rounded up according to
+    /// `acceptable_overhead_ratio`.
+    fn with_format(value_count: usize, bits_per_value: u32, acceptable_overhead_ratio: f32) -> Self;
+
+    /// Returns the number of values.
+    fn size(&self) -> usize;
+
+    /// Returns the number of bits used to store each value.
+    fn bits_per_value(&self) -> u32;
+
+    /// Returns the value at `index`.
+    fn get(&self, index: usize) -> u64;
+
+    /// Sets the value at `index`, which must fit in the array's number of bits per value.
+    fn set(&mut self, index: usize, value: u64);
+
+    /// Sets every value in `from..to` to `value`.
+    fn fill(&mut self, from: usize, to: usize, value: u64) {
+        for index in from..to {
+            self.set(index, value);
+        }
+    }
+
+    /// Returns a copy of the array with `value_count` values, truncated or padded with zeros.
+    fn resize(&self, value_count: usize) -> Self;
+}
+
+/// A [Mutable] that packs its values into 64-bit blocks, with values spanning block boundaries where needed. This is
+/// the most compact layout, and Lucene's `Packed64`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Packed64 {
+    blocks: Vec<u64>,
+    value_count: usize,
+    bits_per_value: u32,
+    mask: u64,
+}
+
+impl Packed64 {
+    /// Creates an array of `value_count` zeros of `bits_per_value` bits each, which must be between 1 and 64.
+    pub fn new(value_count: usize, bits_per_value: u32) -> Self {
+        let mask = max_value(bits_per_value);
+        Self {
+            blocks: vec![0; (value_count as u64 * bits_per_value as u64).div_ceil(64) as usize],
+            value_count,
+            bits_per_value,
+            mask,
+        }
+    }
+}
+
+impl Mutable for Packed64 {
+    fn with_format(value_count: usize, bits_per_value: u32, acceptable_overhead_ratio: f32) -> Self {
+        Self::new(value_count, fastest_bits_per_value(bits_per_value, acceptable_overhead_ratio))
+    }
+
+    #[inline]
+    fn size(&self) -> usize {
+        self.value_count
+    }
+
+    #[inline]
+    fn bits_per_value(&self) -> u32 {
+        self.bits_per_value
+    }
+
+    #[inline]
+    fn get(&self, index: usize) -> u64 {
+        debug_assert!(index < self.value_count, "index {index} out of bounds for {} values", self.value_count);
+        let bit = index as u64 * self.bits_per_value as u64;
+        let block = (bit >> 6) as usize;
+        let shift = (bit & 63) as u32;
+        let mut value = self.blocks[block] >> shift;
+        if shift + self.bits_per_value > 64 {
+            value |= self.blocks[block + 1] << (64 - shift);
+        }
+        value & self.mask
+    }
+
+    #[inline]
+    fn set(&mut self, index: usize, value: u64) {
+        debug_assert!(index < self.value_count, "index {index} out of bounds for {} values", self.value_count);
+        debug_assert!(value <= self.mask, "value {value} doesn't fit in {} bits", self.bits_per_value);
+        let bit = index as u64 * self.bits_per_value as u64;
+        let block = (bit >> 6) as usize;
+        let shift = (bit & 63) as u32;
+        self.blocks[block] = (self.blocks[block] & !(self.mask << shift)) | (value << shift);
+        if shift + self.bits_per_value > 64 {
+            let high_bits = shift + self.bits_per_value - 64;
+            let high_mask = max_value(high_bits);
+            self.blocks[block + 1] = (self.blocks[block + 1] & !high_mask) | (value >> (64 - shift));
+        }
+    }
+
+    fn fill(&mut self, from: usize, to: usize, value: u64) {
+        if value == 0 && from == 0 && to == self.value_count {
+            self.blocks.fill(0);
+            return;
+        }
+        for index in from..to {
+            self.set(index, value);
+        }
+    }
+
+    fn resize(&self, value_count: usize) -> Self {
+        let mut resized = Self::new(value_count, self.bits_per_value);
+        let common = resized.blocks.len().min(self.blocks.len());
+        resized.blocks[..common].copy_from_slice(&self.blocks[..common]);
+        // Clear any bits of values past the new end that were copied along with the last block.
+        if value_count < self.value_count {
+            let used_bits = value_count as u64 * self.bits_per_value as u64;
+            if used_bits & 63 != 0 {
+                if let Some(last) = resized.blocks.last_mut() {
+                    *last &= max_value((used_bits & 63) as u32);
+                }
+            }
+        }
+        resized
+    }
+}
+
+impl Accountable for Packed64 {
+    fn ram_bytes_used(&self) -> usize {
+        size_of::<Self>() + self.blocks.capacity() * size_of::<u64>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::util::packed::{bits_required, fastest_bits_per_value, max_value, Mutable, Packed64, COMPACT, DEFAULT},
+        pretty_assertions::assert_eq,
+    };
+
+    #[test]
+    fn test_packed64() {
+        assert_eq!(bits_required(0), 1);
+        assert_eq!(bits_required(255), 8);
+        assert_eq!(bits_required(256), 9);
+        assert_eq!(bits_required(u64::MAX), 64);
+        assert_eq!(max_value(7), 127);
+        assert_eq!(max_value(64), u64::MAX);
+        assert_eq!(fastest_bits_per_value(7, DEFAULT), 8);
+        assert_eq!(fastest_bits_per_value(7, COMPACT), 7);
+        assert_eq!(fastest_bits_per_value(20, DEFAULT), 20);
+        assert_eq!(fastest_bits_per_value(27, DEFAULT), 32);
+
+        for bits_per_value in [1, 3, 7, 13, 31, 33, 63, 64] {
+            let count = 300;
+            let mut packed = Packed64::new(count, bits_per_value);
+            let value = |i: usize| (i as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15) & max_value(bits_per_value);
+            for i in 0..count {
+                packed.set(i, value(i));
+            }
+            for i in 0..count {
+                assert_eq!(packed.get(i), value(i), "{bits_per_value} bits, index {i}");
+            }
+
+            let shrunk = packed.resize(101);
+            assert_eq!(shrunk.size(), 101);
+            assert_eq!(shrunk.get(100), value(100));
+            let grown = shrunk.resize(count);
+            assert_eq!(grown.get(100), value(100));
+            assert_eq!(grown.get(101), 0);
+            assert_eq!(grown.get(count - 1), 0);
+
+            packed.fill(10, 20, 1);
+            assert_eq!((packed.get(9), packed.get(10), packed.get(19)), (value(9), 1, 1));
+            packed.fill(0, count, 0);
+            assert!((0..count).all(|i| packed.get(i) == 0));
+        }
+    }
+}
@@ -0,0 +1,233 @@
+use {
+    crate::{util::Accountable, BoxResult, LuceneError},
+    std::borrow::Cow,
+};
+
+/// The longest byte string that [PagedBytes::copy_using_length_prefix] can store.
+pub const MAX_LENGTH_PREFIXED: usize = (1 << 15) - 1;
+
+/// Stores many byte strings in a list of fixed-size blocks, addressed by 64-bit pointers, avoiding an allocation per
+/// string. Once filled, it is frozen into a [PagedBytesReader] to read them back.
+///
+/// Strings added with [copy](Self::copy) or [copy_using_length_prefix](Self::copy_using_length_prefix) never span
+/// blocks, so they can be read without copying; bytes added with [append](Self::append) fill every block.
+#[derive(Clone, Debug)]
+pub struct PagedBytes {
+    blocks: Vec<Vec<u8>>,
+    block_bits: u32,
+}
+
+impl PagedBytes {
+    /// Creates an empty store with blocks of `1 << block_bits` bytes. This fails with [LuceneError::InvalidArgument]
+    /// unless `block_bits` is between 1 and 31.
+    pub fn new(block_bits: u32) -> BoxResult<Self> {
+        if !(1..=31).contains(&block_bits) {
+            return Err(
+                LuceneError::InvalidArgument(format!("block bits must be between 1 and 31, got {block_bits}")).into()
+            );
+        }
+        Ok(Self {
+            blocks: Vec::new(),
+            block_bits,
+        })
+    }
+
+    /// Returns the number of bytes in each block.
+    #[inline]
+    pub fn block_size(&self) -> usize {
+        1 << self.block_bits
+    }
+
+    /// Returns the pointer that the next bytes will be written at.
+    pub fn pointer(&self) -> u64 {
+        match self.blocks.last() {
+            Some(block) => ((self.blocks.len() as u64 - 1) << self.block_bits) + block.len() as u64,
+            None => 0,
+        }
+    }
+
+    /// Starts a new block unless the current one has room for `length` more bytes.
+    fn ensure_room(&mut self, length: usize) {
+        if self.blocks.last().is_none_or(|block| block.len() + length > self.block_size()) {
+            self.blocks.push(Vec::with_capacity(self.block_size()));
+        }
+    }
+
+    /// Copies `bytes`, which must fit in a block, returning their pointer. If the current block doesn't have room,
+    /// the rest of it is left unused.
+    pub fn copy(&mut self, bytes: &[u8]) -> BoxResult<u64> {
+        if bytes.len() > self.block_size() {
+            return Err(LuceneError::InvalidArgument(format!(
+                "{} bytes don't fit in a block of {}",
+                bytes.len(),
+                self.block_size()
+            ))
+            .into());
+        }
+        self.ensure_room(bytes.len());
+        let pointer = self.pointer();
+        self.blocks.last_mut().unwrap().extend_from_slice(bytes);
+        Ok(pointer)
+    }
+
+    /// Copies `bytes` after a one- or two-byte length prefix, returning their pointer for
+    /// [PagedBytesReader::fill_using_length_prefix]. This fails with [LuceneError::InvalidArgument] if the bytes are
+    /// longer than [MAX_LENGTH_PREFIXED] or don't fit in a block along with their prefix.
+    pub fn copy_using_length_prefix(&mut self, bytes: &[u8]) -> BoxResult<u64> {
+        let length = bytes.len();
+        if length > MAX_LENGTH_PREFIXED {
+            return Err(LuceneError::InvalidArgument(format!(
+                "{length} bytes are longer than the maximum of {MAX_LENGTH_PREFIXED}"
+            ))
+            .into());
+        }
+
+        let prefix: &[u8] = if length < 0x80 {
+            &[length as u8]
+        } else {
+            &[0x80 | (length >> 8) as u8, length as u8]
+        };
+        if prefix.len() + length > self.block_size() {
+            return Err(LuceneError::InvalidArgument(format!(
+                "{length} bytes and their prefix don't fit in a block of {}",
+                self.block_size()
+            ))
+            .into());
+        }
+
+        self.ensure_room(prefix.len() + length);
+        let pointer = self.pointer();
+        let block = self.blocks.last_mut().unwrap();
+        block.extend_from_slice(prefix);
+        block.extend_from_slice(bytes);
+        Ok(pointer)
+    }
+
+    /// Appends `bytes`, filling the current block and spanning as many blocks as needed, and returns their pointer.
+    pub fn append(&mut self, mut bytes: &[u8]) -> u64 {
+        self.ensure_room(0);
+        let pointer = self.pointer();
+        while !bytes.is_empty() {
+            self.ensure_room(1);
+            let block_size = self.block_size();
+            let block = self.blocks.last_mut().unwrap();
+            let length = bytes.len().min(block_size - block.len());
+            block.extend_from_slice(&bytes[..length]);
+            bytes = &bytes[length..];
+        }
+        pointer
+    }
+
+    /// Freezes the store for reading. If `trim` is set, the unused part of the last block is released.
+    pub fn freeze(mut self, trim: bool) -> PagedBytesReader {
+        if trim {
+            if let Some(block) = self.blocks.last_mut() {
+                block.shrink_to_fit();
+            }
+        }
+        PagedBytesReader {
+            blocks: self.blocks,
+            block_bits: self.block_bits,
+        }
+    }
+}
+
+impl Accountable for PagedBytes {
+    fn ram_bytes_used(&self) -> usize {
+        size_of::<Self>() + self.blocks.iter().map(|block| size_of::<Vec<u8>>() + block.capacity()).sum::<usize>()
+    }
+}
+
+/// Reads back the bytes of a frozen [PagedBytes].
+#[derive(Clone, Debug)]
+pub struct PagedBytesReader {
+    blocks: Vec<Vec<u8>>,
+    block_bits: u32,
+}
+
+impl PagedBytesReader {
+    /// Splits a pointer into its block and its offset within the block.
+    #[inline]
+    fn locate(&self, pointer: u64) -> (usize, usize) {
+        ((pointer >> self.block_bits) as usize, (pointer & ((1 << self.block_bits) - 1)) as usize)
+    }
+
+    /// Returns the `length` bytes at `pointer`. They are borrowed unless they span blocks, which only bytes added with
+    /// [PagedBytes::append] can.
+    pub fn fill(&self, pointer: u64, length: usize) -> Cow<'_, [u8]> {
+        let (block, offset) = self.locate(pointer);
+        if offset + length <= self.blocks[block].len() {
+            return Cow::Borrowed(&self.blocks[block][offset..offset + length]);
+        }
+
+        let mut bytes = Vec::with_capacity(length);
+        bytes.extend_from_slice(&self.blocks[block][offset..]);
+        for block in &self.blocks[block + 1..] {
+            let needed = length - bytes.len();
+            bytes.extend_from_slice(&block[..needed.min(block.len())]);
+            if bytes.len() == length {
+                break;
+            }
+        }
+        debug_assert_eq!(bytes.len(), length, "bytes at {pointer} run past the end of the store");
+        Cow::Owned(bytes)
+    }
+
+    /// Returns the bytes at a pointer returned by [PagedBytes::copy_using_length_prefix].
+    pub fn fill_using_length_prefix(&self, pointer: u64) -> &[u8] {
+        let (block, offset) = self.locate(pointer);
+        let block = &self.blocks[block];
+        let (start, length) = if block[offset] & 0x80 == 0 {
+            (offset + 1, block[offset] as usize)
+        } else {
+            (offset + 2, ((block[offset] & 0x7f) as usize) << 8 | block[offset + 1] as usize)
+        };
+        &block[start..start + length]
+    }
+}
+
+impl Accountable for PagedBytesReader {
+    fn ram_bytes_used(&self) -> usize {
+        size_of::<Self>() + self.blocks.iter().map(|block| size_of::<Vec<u8>>() + block.capacity()).sum::<usize>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::util::packed::{PagedBytes, MAX_LENGTH_PREFIXED},
+        pretty_assertions::assert_eq,
+        std::borrow::Cow,
+    };
+
+    #[test]
+    fn test_paged_bytes() {
+        assert!(PagedBytes::new(0).is_err());
+
+        let mut bytes = PagedBytes::new(8).unwrap();
+        let strings: Vec<Vec<u8>> = (0..200).map(|i| vec![i as u8; i % 150]).collect();
+        let pointers: Vec<u64> = strings.iter().map(|s| bytes.copy_using_length_prefix(s).unwrap()).collect();
+        let raw = bytes.copy(b"lucene").unwrap();
+        let appended: Vec<u8> = (0..1000).map(|i| i as u8).collect();
+        let spanning = bytes.append(&appended);
+        assert!(bytes.copy(&[0; 257]).is_err());
+        assert!(bytes.copy_using_length_prefix(&[0; 255]).is_err());
+        assert!(PagedBytes::new(16).unwrap().copy_using_length_prefix(&[0; MAX_LENGTH_PREFIXED + 1]).is_err());
+
+        let reader = bytes.freeze(true);
+        for (string, &pointer) in strings.iter().zip(&pointers) {
+            assert_eq!(reader.fill_using_length_prefix(pointer), string.as_slice());
+        }
+        assert!(matches!(reader.fill(raw, 6), Cow::Borrowed(b"lucene")));
+        assert!(matches!(reader.fill(spanning, 1000), Cow::Owned(_)));
+        assert_eq!(reader.fill(spanning, 1000).as_ref(), appended.as_slice());
+        assert_eq!(reader.fill(spanning + 10, 5).as_ref(), &appended[10..15]);
+
+        // Two-byte length prefixes.
+        let mut bytes = PagedBytes::new(15).unwrap();
+        let long = vec![7; 3000];
+        let pointer = bytes.copy_using_length_prefix(&long).unwrap();
+        assert_eq!(bytes.pointer(), 3002);
+        assert_eq!(bytes.freeze(false).fill_using_length_prefix(pointer), long.as_slice());
+    }
+}
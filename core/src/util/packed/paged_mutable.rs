@@ -0,0 +1,203 @@
+use crate::{
+    util::{
+        packed::{GrowableWriter, Mutable, Packed64},
+        Accountable,
+    },
+    BoxResult, LuceneError,
+};
+
+/// The smallest page size allowed for paged arrays.
+pub const MIN_PAGE_SIZE: usize = 1 << 6;
+
+/// The largest page size allowed for paged arrays.
+pub const MAX_PAGE_SIZE: usize = 1 << 30;
+
+/// An array of unsigned integers addressed by 64-bit indices, stored as a list of fixed-size [Mutable] pages.
+///
+/// Paging lets arrays grow beyond what a single allocation allows, and lets [grow](Self::grow) and
+/// [resize](Self::resize) reuse all but the last page rather than copying every value. Use it through the
+/// [PagedMutable] and [PagedGrowableWriter] aliases.
+#[derive(Clone, Debug)]
+pub struct AbstractPagedMutable<T> {
+    pages: Vec<T>,
+    size: u64,
+    page_shift: u32,
+    bits_per_value: u32,
+    acceptable_overhead_ratio: f32,
+}
+
+/// A paged array whose pages all use the same number of bits per value, set up front.
+pub type PagedMutable = AbstractPagedMutable<Packed64>;
+
+/// A paged array whose pages each widen as larger values are set; see [GrowableWriter]. This suits arrays whose
+/// largest value isn't known up front, such as ordinal maps, where most pages hold small values.
+pub type PagedGrowableWriter = AbstractPagedMutable<GrowableWriter>;
+
+impl<T: Mutable> AbstractPagedMutable<T> {
+    /// Creates an array of `size` zeros, in pages of `page_size` values. `bits_per_value` is the number of bits of
+    /// each value of a [PagedMutable], or the starting number of bits of a [PagedGrowableWriter].
+    ///
+    /// This fails with [LuceneError::InvalidArgument] unless `page_size` is a power of two between [MIN_PAGE_SIZE] and
+    /// [MAX_PAGE_SIZE].
+    pub fn new(size: u64, page_size: usize, bits_per_value: u32, acceptable_overhead_ratio: f32) -> BoxResult<Self> {
+        if !page_size.is_power_of_two() || !(MIN_PAGE_SIZE..=MAX_PAGE_SIZE).contains(&page_size) {
+            return Err(LuceneError::InvalidArgument(format!(
+                "page size must be a power of two between {MIN_PAGE_SIZE} and {MAX_PAGE_SIZE}, got {page_size}"
+            ))
+            .into());
+        }
+
+        let mut array = Self {
+            pages: Vec::new(),
+            size: 0,
+            page_shift: page_size.trailing_zeros(),
+            bits_per_value,
+            acceptable_overhead_ratio,
+        };
+        array.set_size(size);
+        Ok(array)
+    }
+
+    /// Returns the number of values.
+    #[inline]
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// Returns the number of values per page.
+    #[inline]
+    pub fn page_size(&self) -> usize {
+        1 << self.page_shift
+    }
+
+    #[inline]
+    fn page_mask(&self) -> u64 {
+        (1 << self.page_shift) - 1
+    }
+
+    /// Returns the number of values of page `page` for an array of `size` values.
+    fn page_len(&self, size: u64, page: usize) -> usize {
+        (size - ((page as u64) << self.page_shift)).min(self.page_size() as u64) as usize
+    }
+
+    /// Returns the value at `index`.
+    #[inline]
+    pub fn get(&self, index: u64) -> u64 {
+        debug_assert!(index < self.size, "index {index} out of bounds for {} values", self.size);
+        self.pages[(index >> self.page_shift) as usize].get((index & self.page_mask()) as usize)
+    }
+
+    /// Sets the value at `index`.
+    #[inline]
+    pub fn set(&mut self, index: u64, value: u64) {
+        debug_assert!(index < self.size, "index {index} out of bounds for {} values", self.size);
+        let page_mask = self.page_mask();
+        self.pages[(index >> self.page_shift) as usize].set((index & page_mask) as usize, value);
+    }
+
+    /// Sets every value in `from..to` to `value`.
+    pub fn fill(&mut self, from: u64, to: u64, value: u64) {
+        debug_assert!(from <= to && to <= self.size, "invalid range {from}..{to} for {} values", self.size);
+        let mut start = from;
+        while start < to {
+            let page = (start >> self.page_shift) as usize;
+            let page_end = (((page as u64) + 1) << self.page_shift).min(to);
+            let page_start = (page as u64) << self.page_shift;
+            self.pages[page].fill((start - page_start) as usize, (page_end - page_start) as usize, value);
+            start = page_end;
+        }
+    }
+
+    /// Changes the number of values to `size`, keeping the existing pages and resizing only the last one.
+    fn set_size(&mut self, size: u64) {
+        let num_pages = size.div_ceil(self.page_size() as u64) as usize;
+        self.pages.truncate(num_pages);
+        if let Some(last) = self.pages.len().checked_sub(1) {
+            let len = self.page_len(size, last);
+            if self.pages[last].size() != len {
+                self.pages[last] = self.pages[last].resize(len);
+            }
+        }
+        while self.pages.len() < num_pages {
+            let len = self.page_len(size, self.pages.len());
+            self.pages.push(T::with_format(len, self.bits_per_value, self.acceptable_overhead_ratio));
+        }
+        self.size = size;
+    }
+
+    /// Returns the array with `size` values, truncated or padded with zeros.
+    pub fn resize(mut self, size: u64) -> Self {
+        self.set_size(size);
+        self
+    }
+
+    /// Returns the array grown to at least `min_size` values, with some room to spare so that repeated growth is
+    /// amortized. The array is returned unchanged if it is already large enough.
+    pub fn grow(self, min_size: u64) -> Self {
+        if min_size <= self.size {
+            return self;
+        }
+        let size = min_size + (min_size >> 3);
+        self.resize(size)
+    }
+}
+
+impl<T: Mutable> Accountable for AbstractPagedMutable<T> {
+    fn ram_bytes_used(&self) -> usize {
+        size_of::<Self>()
+            + self.pages.capacity() * size_of::<T>()
+            + self.pages.iter().map(|page| page.ram_bytes_used() - size_of::<T>()).sum::<usize>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::util::{
+            packed::{PagedGrowableWriter, PagedMutable, COMPACT, DEFAULT},
+            Accountable,
+        },
+        pretty_assertions::assert_eq,
+    };
+
+    #[test]
+    fn test_paged_mutable() {
+        assert!(PagedMutable::new(100, 100, 8, COMPACT).is_err());
+        assert!(PagedMutable::new(100, 32, 8, COMPACT).is_err());
+
+        let mut array = PagedMutable::new(1000, 64, 10, COMPACT).unwrap();
+        assert_eq!(array.page_size(), 64);
+        for i in 0..1000 {
+            array.set(i, i);
+        }
+        assert_eq!((array.get(0), array.get(63), array.get(64), array.get(999)), (0, 63, 64, 999));
+
+        array.fill(60, 70, 5);
+        assert_eq!((array.get(59), array.get(60), array.get(69), array.get(70)), (59, 5, 5, 70));
+
+        let array = array.resize(100);
+        assert_eq!(array.size(), 100);
+        assert_eq!(array.get(99), 99);
+        let array = array.grow(500);
+        assert!(array.size() >= 500);
+        assert_eq!((array.get(99), array.get(100), array.get(499)), (99, 0, 0));
+        let size = array.size();
+        assert_eq!(array.grow(10).size(), size);
+    }
+
+    #[test]
+    fn test_paged_growable_writer() {
+        let mut array = PagedGrowableWriter::new(10_000, 1024, 1, DEFAULT).unwrap();
+        array.set(5, 1);
+        array.set(9_999, 1 << 33);
+        assert_eq!((array.get(5), array.get(6), array.get(9_999)), (1, 0, 1 << 33));
+
+        // Only the page holding the large value widened.
+        let small = PagedGrowableWriter::new(10_000, 1024, 1, DEFAULT).unwrap();
+        assert!(array.ram_bytes_used() > small.ram_bytes_used());
+        assert!(array.ram_bytes_used() < small.ram_bytes_used() + 1024 * 8 + 1024);
+
+        let array = array.resize(20_000);
+        assert_eq!((array.get(9_999), array.get(19_999)), (1 << 33, 0));
+    }
+}
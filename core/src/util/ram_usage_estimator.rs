@@ -0,0 +1,50 @@
+/// The number of bytes in a kilobyte, as used by [human_readable_units].
+pub const ONE_KB: usize = 1024;
+
+/// The number of bytes in a megabyte, as used by [human_readable_units].
+pub const ONE_MB: usize = ONE_KB * ONE_KB;
+
+/// The number of bytes in a gigabyte, as used by [human_readable_units].
+pub const ONE_GB: usize = ONE_KB * ONE_MB;
+
+/// Returns the memory used by the heap allocation of a vector: its capacity, not just its length, times the size of
+/// its elements. The vector itself is not included, since it is usually counted as part of its owner.
+#[inline]
+pub fn size_of_vec<T>(vec: &Vec<T>) -> usize {
+    vec.capacity() * size_of::<T>()
+}
+
+/// Formats a number of bytes in the largest unit that keeps it at least 1, with at most one decimal place, as
+/// Lucene's `RamUsageEstimator.humanReadableUnits` does.
+pub fn human_readable_units(bytes: usize) -> String {
+    let (value, unit) = match bytes {
+        ONE_GB.. => (bytes as f64 / ONE_GB as f64, "GB"),
+        ONE_MB.. => (bytes as f64 / ONE_MB as f64, "MB"),
+        ONE_KB.. => (bytes as f64 / ONE_KB as f64, "KB"),
+        _ => return format!("{bytes} bytes"),
+    };
+    let formatted = format!("{value:.1}");
+    format!("{} {unit}", formatted.strip_suffix(".0").unwrap_or(&formatted))
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::util::{human_readable_units, size_of_vec, ONE_GB, ONE_MB},
+        pretty_assertions::assert_eq,
+    };
+
+    #[test]
+    fn test_ram_usage_estimator() {
+        assert_eq!(human_readable_units(0), "0 bytes");
+        assert_eq!(human_readable_units(1023), "1023 bytes");
+        assert_eq!(human_readable_units(1024), "1 KB");
+        assert_eq!(human_readable_units(1536), "1.5 KB");
+        assert_eq!(human_readable_units(ONE_MB * 3 / 2), "1.5 MB");
+        assert_eq!(human_readable_units(ONE_GB * 2), "2 GB");
+
+        let mut vec: Vec<u64> = Vec::with_capacity(10);
+        vec.push(1);
+        assert_eq!(size_of_vec(&vec), 80);
+    }
+}
@@ -0,0 +1,100 @@
+/// The largest value [long_to_int4] produces for an `i32`.
+const MAX_INT4: u32 = long_to_int4(i32::MAX as u64);
+
+/// The number of small values [int_to_byte4] encodes exactly.
+const NUM_FREE_VALUES: u32 = 255 - MAX_INT4;
+
+/// Encodes a value into a floating point format with a 4-bit mantissa (3 bits stored, the leading 1 being implicit)
+/// and the rest of the bits as the exponent. Values below 8 are encoded exactly; larger ones are rounded down,
+/// keeping their 4 most significant bits. Order is preserved.
+pub const fn long_to_int4(i: u64) -> u32 {
+    let num_bits = 64 - i.leading_zeros();
+    if num_bits < 4 {
+        // Subnormal value.
+        i as u32
+    } else {
+        // Keep the 4 most significant bits, then clear the leading one, which is implicit.
+        let shift = num_bits - 4;
+        let encoded = (i >> shift) as u32 & 0x07;
+
+        // Encode the shift, adding 1 because 0 is reserved for subnormal values.
+        encoded | ((shift + 1) << 3)
+    }
+}
+
+/// Decodes a value encoded with [long_to_int4], returning the smallest value with that encoding.
+pub const fn int4_to_long(i: u32) -> u64 {
+    let bits = (i & 0x07) as u64;
+    let shift = i >> 3;
+    if shift == 0 {
+        // Subnormal value.
+        bits
+    } else {
+        (bits | 0x08) << (shift - 1)
+    }
+}
+
+/// Encodes a non-negative integer into a byte, as Lucene does for field lengths in norms. Values below 24 are encoded
+/// exactly, and larger values with [long_to_int4], so precision decreases as values grow. Order is preserved. Values
+/// above `i32::MAX` are encoded as `i32::MAX`.
+pub const fn int_to_byte4(i: u32) -> u8 {
+    let i = if i > i32::MAX as u32 {
+        i32::MAX as u32
+    } else {
+        i
+    };
+
+    if i < NUM_FREE_VALUES {
+        i as u8
+    } else {
+        (NUM_FREE_VALUES + long_to_int4((i - NUM_FREE_VALUES) as u64)) as u8
+    }
+}
+
+/// Decodes a byte encoded with [int_to_byte4], returning the smallest value with that encoding.
+pub const fn byte4_to_int(b: u8) -> u32 {
+    let i = b as u32;
+    if i < NUM_FREE_VALUES {
+        i
+    } else {
+        NUM_FREE_VALUES + int4_to_long(i - NUM_FREE_VALUES) as u32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::util::{byte4_to_int, int4_to_long, int_to_byte4, long_to_int4},
+        pretty_assertions::assert_eq,
+    };
+
+    #[test]
+    fn test_small_float() {
+        for i in 0..24 {
+            assert_eq!(int_to_byte4(i), i as u8);
+            assert_eq!(byte4_to_int(i as u8), i);
+        }
+
+        // Every byte decodes to a value that encodes back to it, in increasing order.
+        let mut previous = None;
+        for b in 0..=255u8 {
+            let decoded = byte4_to_int(b);
+            assert_eq!(int_to_byte4(decoded), b);
+            assert!(previous.is_none_or(|p| p < decoded));
+            previous = Some(decoded);
+        }
+        assert_eq!(int_to_byte4(i32::MAX as u32), 255);
+        assert_eq!(int_to_byte4(u32::MAX), 255);
+
+        // Encoding rounds down to a representable value.
+        for i in [24, 25, 100, 1_000, 123_456, 1 << 30] {
+            let decoded = byte4_to_int(int_to_byte4(i));
+            assert!(decoded <= i && int_to_byte4(decoded) == int_to_byte4(i), "{i} decoded as {decoded}");
+        }
+
+        for i in [0, 7, 8, 9, 100, u32::MAX as u64] {
+            let decoded = int4_to_long(long_to_int4(i));
+            assert!(decoded <= i && long_to_int4(decoded) == long_to_int4(i));
+        }
+    }
+}
@@ -0,0 +1,209 @@
+use crate::{
+    search::NO_MORE_DOCS,
+    util::{Accountable, BitSet},
+};
+
+/// A [BitSet] of fixed length that only allocates memory for the 64-bit words that have bits set.
+///
+/// As in Lucene, the bits are split into blocks of 4096. For each block, a 64-bit index records which of its 64
+/// words are non-zero, and only those words are stored, in order. This makes the set much smaller than a
+/// [FixedBitSet](crate::util::FixedBitSet) when few documents match, while keeping random access cheap.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SparseFixedBitSet {
+    indices: Vec<u64>,
+    blocks: Vec<Vec<u64>>,
+    num_bits: u32,
+}
+
+impl SparseFixedBitSet {
+    /// Creates a set of `num_bits` bits, all clear.
+    pub fn new(num_bits: u32) -> Self {
+        let num_blocks = (num_bits as usize).div_ceil(4096);
+        Self {
+            indices: vec![0; num_blocks],
+            blocks: vec![Vec::new(); num_blocks],
+            num_bits,
+        }
+    }
+
+    /// Returns the position of word `i64` of a block within its stored words.
+    #[inline]
+    fn word_offset(index: u64, i64: u32) -> usize {
+        (index & ((1 << i64) - 1)).count_ones() as usize
+    }
+
+    /// Returns the first set bit at or after bit `i` of block `i4096`, if any.
+    fn first_set_bit_in_block(&self, i4096: usize, i: u32) -> Option<u32> {
+        let index = self.indices[i4096];
+        let i64 = (i >> 6) & 63;
+
+        // The word holding bit i, if it's stored.
+        if index & (1 << i64) != 0 {
+            let word = self.blocks[i4096][Self::word_offset(index, i64)] >> (i & 63);
+            if word != 0 {
+                return Some(i + word.trailing_zeros());
+            }
+        }
+
+        // The following stored words of the block.
+        let later = index.checked_shr(i64 + 1).unwrap_or(0);
+        if later != 0 {
+            let next_i64 = i64 + 1 + later.trailing_zeros();
+            let word = self.blocks[i4096][Self::word_offset(index, next_i64)];
+            return Some((i4096 as u32) << 12 | next_i64 << 6 | word.trailing_zeros());
+        }
+        None
+    }
+
+    /// Returns the last set bit at or before bit `i` of block `i4096`, if any.
+    fn last_set_bit_in_block(&self, i4096: usize, i: u32) -> Option<u32> {
+        let index = self.indices[i4096];
+        let i64 = (i >> 6) & 63;
+
+        if index & (1 << i64) != 0 {
+            let word = self.blocks[i4096][Self::word_offset(index, i64)] << (63 - (i & 63));
+            if word != 0 {
+                return Some(i - word.leading_zeros());
+            }
+        }
+
+        let earlier = index & ((1 << i64) - 1);
+        if earlier != 0 {
+            let prev_i64 = 63 - earlier.leading_zeros();
+            let word = self.blocks[i4096][Self::word_offset(index, prev_i64)];
+            return Some((i4096 as u32) << 12 | prev_i64 << 6 | (63 - word.leading_zeros()));
+        }
+        None
+    }
+}
+
+impl BitSet for SparseFixedBitSet {
+    #[inline]
+    fn num_bits(&self) -> u32 {
+        self.num_bits
+    }
+
+    fn get(&self, index: u32) -> bool {
+        debug_assert!(index < self.num_bits, "index {index} out of bounds for {} bits", self.num_bits);
+        let i4096 = (index >> 12) as usize;
+        let i64 = (index >> 6) & 63;
+        let block_index = self.indices[i4096];
+        if block_index & (1 << i64) == 0 {
+            return false;
+        }
+        self.blocks[i4096][Self::word_offset(block_index, i64)] & (1 << (index & 63)) != 0
+    }
+
+    fn set(&mut self, index: u32) {
+        debug_assert!(index < self.num_bits, "index {index} out of bounds for {} bits", self.num_bits);
+        let i4096 = (index >> 12) as usize;
+        let i64 = (index >> 6) & 63;
+        let block_index = self.indices[i4096];
+        let offset = Self::word_offset(block_index, i64);
+        if block_index & (1 << i64) != 0 {
+            self.blocks[i4096][offset] |= 1 << (index & 63);
+        } else {
+            self.blocks[i4096].insert(offset, 1 << (index & 63));
+            self.indices[i4096] |= 1 << i64;
+        }
+    }
+
+    fn clear(&mut self, index: u32) {
+        debug_assert!(index < self.num_bits, "index {index} out of bounds for {} bits", self.num_bits);
+        let i4096 = (index >> 12) as usize;
+        let i64 = (index >> 6) & 63;
+        let block_index = self.indices[i4096];
+        if block_index & (1 << i64) == 0 {
+            return;
+        }
+
+        let offset = Self::word_offset(block_index, i64);
+        let word = &mut self.blocks[i4096][offset];
+        *word &= !(1 << (index & 63));
+        if *word == 0 {
+            self.blocks[i4096].remove(offset);
+            self.indices[i4096] &= !(1 << i64);
+        }
+    }
+
+    fn cardinality(&self) -> u32 {
+        self.blocks.iter().flatten().map(|word| word.count_ones()).sum()
+    }
+
+    fn next_set_bit(&self, index: u32) -> u32 {
+        if index >= self.num_bits {
+            return NO_MORE_DOCS;
+        }
+
+        let i4096 = (index >> 12) as usize;
+        if let Some(doc) = self.first_set_bit_in_block(i4096, index) {
+            return doc;
+        }
+        match (i4096 + 1..self.indices.len()).find(|&i| self.indices[i] != 0) {
+            Some(i) => self.first_set_bit_in_block(i, (i as u32) << 12).unwrap(),
+            None => NO_MORE_DOCS,
+        }
+    }
+
+    fn prev_set_bit(&self, index: u32) -> Option<u32> {
+        let index = index.min(self.num_bits.checked_sub(1)?);
+        let i4096 = (index >> 12) as usize;
+        if let Some(doc) = self.last_set_bit_in_block(i4096, index) {
+            return Some(doc);
+        }
+        (0..i4096)
+            .rev()
+            .find(|&i| self.indices[i] != 0)
+            .and_then(|i| self.last_set_bit_in_block(i, (i as u32) << 12 | 4095))
+    }
+}
+
+impl Accountable for SparseFixedBitSet {
+    fn ram_bytes_used(&self) -> usize {
+        size_of::<Self>()
+            + self.indices.capacity() * size_of::<u64>()
+            + self.blocks.iter().map(|block| size_of::<Vec<u64>>() + block.capacity() * size_of::<u64>()).sum::<usize>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{
+            search::NO_MORE_DOCS,
+            util::{Accountable, BitSet, FixedBitSet, SparseFixedBitSet},
+        },
+        pretty_assertions::assert_eq,
+    };
+
+    #[test]
+    fn test_sparse_fixed_bit_set() {
+        let num_bits = 100_000;
+        let mut sparse = SparseFixedBitSet::new(num_bits);
+        let mut fixed = FixedBitSet::new(num_bits);
+        for i in 0..300u32 {
+            let doc = i.wrapping_mul(2_654_435_761) % num_bits;
+            sparse.set(doc);
+            fixed.set(doc);
+            if i % 5 == 0 {
+                let doc = (doc + 1) % num_bits;
+                sparse.clear(doc);
+                fixed.clear(doc);
+            }
+        }
+        sparse.set(num_bits - 1);
+        fixed.set(num_bits - 1);
+
+        assert_eq!(sparse.cardinality(), fixed.cardinality());
+        assert!(sparse.ram_bytes_used() < fixed.ram_bytes_used());
+        for index in (0..num_bits).step_by(7) {
+            assert_eq!(sparse.get(index), fixed.get(index));
+            assert_eq!(sparse.next_set_bit(index), fixed.next_set_bit(index));
+            assert_eq!(sparse.prev_set_bit(index), fixed.prev_set_bit(index));
+        }
+
+        let empty = SparseFixedBitSet::new(num_bits);
+        assert_eq!(empty.next_set_bit(0), NO_MORE_DOCS);
+        assert_eq!(empty.prev_set_bit(num_bits), None);
+    }
+}
@@ -0,0 +1,261 @@
+use {
+    crate::util::Accountable,
+    std::collections::{btree_map::Entry, BTreeMap},
+};
+
+/// The 4096 values of one block of a [SparseLongSet], stored as in a
+/// [SparseFixedBitSet](crate::util::SparseFixedBitSet): an index of which of its 64 words are non-zero, and those
+/// words in order.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+struct SparseBlock {
+    index: u64,
+    words: Vec<u64>,
+}
+
+impl SparseBlock {
+    /// Returns the position of word `i64` within the stored words.
+    #[inline]
+    fn word_offset(&self, i64: u32) -> usize {
+        (self.index & ((1 << i64) - 1)).count_ones() as usize
+    }
+
+    fn contains(&self, i: u32) -> bool {
+        let i64 = i >> 6;
+        self.index & (1 << i64) != 0 && self.words[self.word_offset(i64)] & (1 << (i & 63)) != 0
+    }
+
+    /// Sets bit `i`, returning whether it was clear.
+    fn insert(&mut self, i: u32) -> bool {
+        let i64 = i >> 6;
+        let offset = self.word_offset(i64);
+        if self.index & (1 << i64) == 0 {
+            self.words.insert(offset, 1 << (i & 63));
+            self.index |= 1 << i64;
+            return true;
+        }
+        let word = &mut self.words[offset];
+        let inserted = *word & (1 << (i & 63)) == 0;
+        *word |= 1 << (i & 63);
+        inserted
+    }
+
+    /// Clears bit `i`, returning whether it was set.
+    fn remove(&mut self, i: u32) -> bool {
+        let i64 = i >> 6;
+        if self.index & (1 << i64) == 0 {
+            return false;
+        }
+        let offset = self.word_offset(i64);
+        let word = &mut self.words[offset];
+        let removed = *word & (1 << (i & 63)) != 0;
+        *word &= !(1 << (i & 63));
+        if *word == 0 {
+            self.words.remove(offset);
+            self.index &= !(1 << i64);
+        }
+        removed
+    }
+
+    /// Returns the first set bit at or after bit `i`, if any.
+    fn next(&self, i: u32) -> Option<u32> {
+        let i64 = i >> 6;
+        if self.index & (1 << i64) != 0 {
+            let word = self.words[self.word_offset(i64)] >> (i & 63);
+            if word != 0 {
+                return Some(i + word.trailing_zeros());
+            }
+        }
+        let later = self.index.checked_shr(i64 + 1).unwrap_or(0);
+        if later == 0 {
+            return None;
+        }
+        let next_i64 = i64 + 1 + later.trailing_zeros();
+        Some(next_i64 << 6 | self.words[self.word_offset(next_i64)].trailing_zeros())
+    }
+
+    fn ram_bytes_used(&self) -> usize {
+        size_of::<u64>() + size_of::<Self>() + self.words.capacity() * size_of::<u64>()
+    }
+}
+
+/// A set of 64-bit values, such as global ordinals or the values of a long field, that only allocates memory for the
+/// ranges of values it holds.
+///
+/// Values are grouped into blocks of 4096, keyed by their upper bits in an ordered map; within a block, only the
+/// non-zero 64-bit words are stored. Unlike a [LongBitSet](crate::util::LongBitSet), the set needs no upper bound and
+/// its size depends only on the values it holds, which suits joins and faceting over high-cardinality fields where
+/// few ordinals match. Values are iterated in increasing order.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct SparseLongSet {
+    blocks: BTreeMap<u64, SparseBlock>,
+    len: u64,
+}
+
+impl SparseLongSet {
+    /// Creates an empty set.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the number of values in the set.
+    #[inline]
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Indicates whether the set has no values.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Indicates whether the set holds `value`.
+    pub fn contains(&self, value: u64) -> bool {
+        self.blocks.get(&(value >> 12)).is_some_and(|block| block.contains((value & 4095) as u32))
+    }
+
+    /// Adds `value`, returning whether it was not already in the set.
+    pub fn insert(&mut self, value: u64) -> bool {
+        let inserted = self.blocks.entry(value >> 12).or_default().insert((value & 4095) as u32);
+        self.len += inserted as u64;
+        inserted
+    }
+
+    /// Removes `value`, returning whether it was in the set.
+    pub fn remove(&mut self, value: u64) -> bool {
+        let Entry::Occupied(mut entry) = self.blocks.entry(value >> 12) else {
+            return false;
+        };
+        let removed = entry.get_mut().remove((value & 4095) as u32);
+        if entry.get().index == 0 {
+            entry.remove();
+        }
+        self.len -= removed as u64;
+        removed
+    }
+
+    /// Removes every value.
+    pub fn clear(&mut self) {
+        self.blocks.clear();
+        self.len = 0;
+    }
+
+    /// Returns the smallest value in the set at or after `value`, if any.
+    pub fn next(&self, value: u64) -> Option<u64> {
+        let key = value >> 12;
+        self.blocks.range(key..).find_map(|(&block_key, block)| {
+            let start = if block_key == key {
+                (value & 4095) as u32
+            } else {
+                0
+            };
+            block.next(start).map(|i| block_key << 12 | i as u64)
+        })
+    }
+
+    /// Adds every value of `other`.
+    pub fn union(&mut self, other: &SparseLongSet) {
+        for value in other.iter() {
+            self.insert(value);
+        }
+    }
+
+    /// Returns an iterator over the values, in increasing order.
+    pub fn iter(&self) -> impl Iterator<Item = u64> + '_ {
+        self.blocks.iter().flat_map(|(&key, block)| {
+            let mut next = block.next(0);
+            std::iter::from_fn(move || {
+                let i = next?;
+                next = if i < 4095 {
+                    block.next(i + 1)
+                } else {
+                    None
+                };
+                Some(key << 12 | i as u64)
+            })
+        })
+    }
+}
+
+impl Accountable for SparseLongSet {
+    fn ram_bytes_used(&self) -> usize {
+        // An estimate, since the map's node layout isn't known.
+        size_of::<Self>() + self.blocks.values().map(SparseBlock::ram_bytes_used).sum::<usize>()
+    }
+}
+
+impl FromIterator<u64> for SparseLongSet {
+    fn from_iter<I: IntoIterator<Item = u64>>(iter: I) -> Self {
+        let mut set = Self::new();
+        for value in iter {
+            set.insert(value);
+        }
+        set
+    }
+}
+
+impl Extend<u64> for SparseLongSet {
+    fn extend<I: IntoIterator<Item = u64>>(&mut self, iter: I) {
+        for value in iter {
+            self.insert(value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::util::{LongBitSet, SparseLongSet},
+        pretty_assertions::assert_eq,
+        std::collections::BTreeSet,
+    };
+
+    #[test]
+    fn test_sparse_long_set() {
+        let mut set = SparseLongSet::new();
+        let mut expected = BTreeSet::new();
+        for i in 0..2000u64 {
+            // Spread values across the whole 64-bit range, with some clustered runs.
+            let value = if i % 3 == 0 {
+                i.wrapping_mul(0x9e37_79b9_7f4a_7c15)
+            } else {
+                (1 << 40) + i * 7
+            };
+            assert_eq!(set.insert(value), expected.insert(value));
+            if i % 11 == 0 {
+                assert_eq!(set.remove(value ^ 1), expected.remove(&(value ^ 1)));
+            }
+        }
+        assert!(!set.insert((1 << 40) + 7));
+        set.insert(u64::MAX);
+        expected.insert(u64::MAX);
+
+        assert_eq!(set.len(), expected.len() as u64);
+        assert_eq!(set.iter().count() as u64, set.len());
+        assert_eq!(set.iter().collect::<Vec<_>>(), expected.iter().copied().collect::<Vec<_>>());
+        for &value in expected.iter().step_by(13) {
+            assert!(set.contains(value));
+            assert!(!set.contains(value.wrapping_add(1)) || expected.contains(&(value.wrapping_add(1))));
+            assert_eq!(set.next(value.wrapping_add(1)), expected.range(value.wrapping_add(1)..).next().copied());
+        }
+        assert_eq!(set.next(0), expected.first().copied());
+
+        // Removing every value of a block releases it.
+        let mut small: SparseLongSet = [5, 4100, 4101].into_iter().collect();
+        assert!(small.remove(5));
+        assert!(!small.remove(5));
+        assert_eq!(small.blocks.len(), 1);
+        assert_eq!(small.next(0), Some(4100));
+
+        let mut bits = LongBitSet::new(5000);
+        bits.set(4100);
+        bits.set(4999);
+        small.extend(bits.iter());
+        assert_eq!(small.iter().collect::<Vec<_>>(), vec![4100, 4101, 4999]);
+        small.union(&set);
+        assert_eq!(small.len(), set.len() + 3);
+        small.clear();
+        assert!(small.is_empty());
+        assert_eq!(small.next(0), None);
+    }
+}
@@ -1,11 +1,15 @@
 use {
-    crate::{io::EncodingReadExt, BoxError, LuceneError},
+    crate::{
+        io::{EncodingReadExt, EncodingWriteExt},
+        BoxError, LuceneError,
+    },
     log::error,
     std::{
         fmt::{Display, Formatter, Result as FmtResult},
+        io::Result as IoResult,
         str::FromStr,
     },
-    tokio::io::{AsyncRead, AsyncReadExt},
+    tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
 };
 
 /// Version numbers of Lucene. This is used to ensure compatibility across different releases.
@@ -77,6 +81,20 @@ impl Version {
         }
     }
 
+    /// Write the version to a stream as three vi32 values, as read by [Version::read_from_vi32].
+    pub async fn write_to_vi32<W: EncodingWriteExt + Unpin>(&self, w: &mut W) -> IoResult<()> {
+        w.write_vi32(self.major as i32).await?;
+        w.write_vi32(self.minor as i32).await?;
+        w.write_vi32(self.bugfix as i32).await
+    }
+
+    /// Write the version to a stream as three i32 little-endian values, as read by [Version::read_from_i32_le].
+    pub async fn write_to_i32_le<W: AsyncWrite + Unpin>(&self, w: &mut W) -> IoResult<()> {
+        w.write_i32_le(self.major as i32).await?;
+        w.write_i32_le(self.minor as i32).await?;
+        w.write_i32_le(self.bugfix as i32).await
+    }
+
     /// Read a version from a stream as three i32 little-endian values.
     pub async fn read_from_i32_le<R: AsyncRead + Unpin>(r: &mut R) -> Result<Self, BoxError> {
         let major = r.read_i32_le().await?;
@@ -1,3 +1,6 @@
+// The index is read from the filesystem, which needs the tokio runtime.
+#![cfg(feature = "tokio-runtime")]
+
 use {
     lucene_core::{fs::FilesystemDirectory, index::*},
     std::{collections::HashSet, path::PathBuf},
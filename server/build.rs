@@ -0,0 +1,42 @@
+//! Generates the gRPC service of the `grpc` feature. The service is described in Rust rather than compiled from
+//! `proto/lucene.proto`, so building it doesn't need `protoc`; the two must be kept in sync.
+
+fn main() {
+    #[cfg(feature = "grpc")]
+    {
+        use tonic_build::manual::{Builder, Method, Service};
+
+        let method = |name: &str, route_name: &str, message: &str, comment: &str| {
+            Method::builder()
+                .name(name)
+                .route_name(route_name)
+                .input_type(format!("crate::grpc::{message}Request"))
+                .output_type(format!("crate::grpc::{message}Response"))
+                .codec_path("tonic::codec::ProstCodec")
+                .comment(comment)
+                .build()
+        };
+
+        let service = Service::builder()
+            .name("Search")
+            .package("lucene")
+            .comment("Indexes, deletes, commits and searches the documents of a ServerIndex.")
+            .method(method(
+                "index_document",
+                "IndexDocument",
+                "IndexDocument",
+                "Adds or replaces a document, visible once committed.",
+            ))
+            .method(method(
+                "delete_document",
+                "DeleteDocument",
+                "DeleteDocument",
+                "Deletes a document, visible once committed.",
+            ))
+            .method(method("commit", "Commit", "Commit", "Makes the uncommitted changes visible to searches."))
+            .method(method("search", "Search", "SearchDocuments", "Searches the committed documents."))
+            .build();
+
+        Builder::new().build_client(false).build_transport(false).compile(&[service]);
+    }
+}
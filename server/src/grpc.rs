@@ -0,0 +1,311 @@
+use {
+    crate::{QueryDsl, SearchRequest, ServerIndex, SharedIndex, DEFAULT_SEARCH_SIZE},
+    lucene_core::{BoxError, BoxResult},
+    serde_json::{Map, Value},
+    tokio::{
+        runtime::Handle,
+        task::{self, JoinError},
+    },
+    tonic::{Request, Response, Status},
+};
+
+include!(concat!(env!("OUT_DIR"), "/lucene.Search.rs"));
+
+pub use search_server::{Search, SearchServer};
+
+/// A request to add or replace a document.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct IndexDocumentRequest {
+    /// The id of the document.
+    #[prost(string, tag = "1")]
+    pub id: String,
+
+    /// The document, a JSON object (see [crate::ServerIndex::index]).
+    #[prost(string, tag = "2")]
+    pub source: String,
+}
+
+/// The response to an [IndexDocumentRequest].
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct IndexDocumentResponse {
+    /// The id of the document.
+    #[prost(string, tag = "1")]
+    pub id: String,
+}
+
+/// A request to delete a document.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct DeleteDocumentRequest {
+    /// The id of the document.
+    #[prost(string, tag = "1")]
+    pub id: String,
+}
+
+/// The response to a [DeleteDocumentRequest].
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct DeleteDocumentResponse {
+    /// The id of the document.
+    #[prost(string, tag = "1")]
+    pub id: String,
+
+    /// Whether the document existed, counting uncommitted changes.
+    #[prost(bool, tag = "2")]
+    pub found: bool,
+}
+
+/// A request to make the uncommitted changes visible to searches.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct CommitRequest {}
+
+/// The response to a [CommitRequest].
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct CommitResponse {
+    /// The number of committed documents.
+    #[prost(uint64, tag = "1")]
+    pub num_docs: u64,
+}
+
+/// A search request, the gRPC form of [SearchRequest].
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct SearchDocumentsRequest {
+    /// The query to run, a [QueryDsl] in JSON.
+    #[prost(string, tag = "1")]
+    pub query: String,
+
+    /// The number of hits to return, [DEFAULT_SEARCH_SIZE] if unset.
+    #[prost(uint32, optional, tag = "2")]
+    pub size: Option<u32>,
+}
+
+/// The results of a [SearchDocumentsRequest], the gRPC form of [crate::SearchResponse].
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct SearchDocumentsResponse {
+    /// The total number of matching documents.
+    #[prost(uint64, tag = "1")]
+    pub total_hits: u64,
+
+    /// Whether [SearchDocumentsResponse::total_hits] is a lower bound rather than an exact count.
+    #[prost(bool, tag = "2")]
+    pub total_hits_is_lower_bound: bool,
+
+    /// The top hits, best first.
+    #[prost(message, repeated, tag = "3")]
+    pub hits: Vec<Hit>,
+}
+
+/// A document matching a [SearchDocumentsRequest], the gRPC form of [crate::SearchHit].
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Hit {
+    /// The id the document was indexed with.
+    #[prost(string, tag = "1")]
+    pub id: String,
+
+    /// The document's score.
+    #[prost(float, tag = "2")]
+    pub score: f32,
+
+    /// The document's stored fields, a JSON object whose fields with several values are arrays.
+    #[prost(string, tag = "3")]
+    pub fields: String,
+}
+
+/// The gRPC service of the server, `lucene.Search`, described by `proto/lucene.proto`. It serves the same operations
+/// as the HTTP routes, on the same index; documents and queries are passed as JSON strings.
+///
+/// Invalid requests fail with [tonic::Code::InvalidArgument], and requests to an index whose lock was poisoned by a
+/// panic with [tonic::Code::Internal]. As with the HTTP routes, the index is locked on tokio's blocking threads, so
+/// the service must be served by a multi-threaded runtime.
+#[derive(Clone, Debug)]
+pub struct SearchService {
+    index: SharedIndex,
+}
+
+impl SearchService {
+    /// Creates a service over the given index.
+    pub fn new(index: SharedIndex) -> Self {
+        Self {
+            index,
+        }
+    }
+
+    /// Wraps this service in a [SearchServer], which can be added to a tonic server or router.
+    pub fn into_server(self) -> SearchServer<Self> {
+        SearchServer::new(self)
+    }
+
+    /// Runs `f` with the index locked for reading, on a blocking thread.
+    async fn read<T, F>(&self, f: F) -> Result<T, Status>
+    where
+        T: Send + 'static,
+        F: FnOnce(&ServerIndex) -> BoxResult<T> + Send + 'static,
+    {
+        let index = self.index.clone();
+        let result = task::spawn_blocking(move || index.read().ok().map(|index| f(&index))).await;
+        result.map_err(failed)?.ok_or_else(poisoned)?.map_err(invalid_argument)
+    }
+
+    /// Runs `f` with the index locked for writing, on a blocking thread.
+    async fn write<T, F>(&self, f: F) -> Result<T, Status>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut ServerIndex) -> BoxResult<T> + Send + 'static,
+    {
+        let index = self.index.clone();
+        let result = task::spawn_blocking(move || index.write().ok().map(|mut index| f(&mut index))).await;
+        result.map_err(failed)?.ok_or_else(poisoned)?.map_err(invalid_argument)
+    }
+}
+
+#[tonic::async_trait]
+impl Search for SearchService {
+    async fn index_document(
+        &self,
+        request: Request<IndexDocumentRequest>,
+    ) -> Result<Response<IndexDocumentResponse>, Status> {
+        let request = request.into_inner();
+        let source: Map<String, Value> = serde_json::from_str(&request.source)
+            .map_err(|e| Status::invalid_argument(format!("invalid document: {e}")))?;
+        self.write({
+            let id = request.id.clone();
+            move |index| index.index(&id, &source)
+        })
+        .await?;
+        Ok(Response::new(IndexDocumentResponse {
+            id: request.id,
+        }))
+    }
+
+    async fn delete_document(
+        &self,
+        request: Request<DeleteDocumentRequest>,
+    ) -> Result<Response<DeleteDocumentResponse>, Status> {
+        let request = request.into_inner();
+        let found = self
+            .write({
+                let id = request.id.clone();
+                move |index| index.delete(&id)
+            })
+            .await?;
+        Ok(Response::new(DeleteDocumentResponse {
+            id: request.id,
+            found,
+        }))
+    }
+
+    async fn commit(&self, _request: Request<CommitRequest>) -> Result<Response<CommitResponse>, Status> {
+        let num_docs = self
+            .write(|index| {
+                Handle::current().block_on(index.commit())?;
+                Ok(index.num_docs())
+            })
+            .await?;
+        Ok(Response::new(CommitResponse {
+            num_docs: num_docs as u64,
+        }))
+    }
+
+    async fn search(
+        &self,
+        request: Request<SearchDocumentsRequest>,
+    ) -> Result<Response<SearchDocumentsResponse>, Status> {
+        let request = request.into_inner();
+        let query: QueryDsl = serde_json::from_str(&request.query)
+            .map_err(|e| Status::invalid_argument(format!("invalid query: {e}")))?;
+        let request = SearchRequest {
+            query,
+            size: request.size.map_or(DEFAULT_SEARCH_SIZE, |size| size as usize),
+        };
+
+        let response = self.read(move |index| index.search(&request)).await?;
+        Ok(Response::new(SearchDocumentsResponse {
+            total_hits: response.total_hits,
+            total_hits_is_lower_bound: response.total_hits_is_lower_bound,
+            hits: response
+                .hits
+                .into_iter()
+                .map(|hit| Hit {
+                    id: hit.id,
+                    score: hit.score,
+                    fields: Value::Object(hit.fields).to_string(),
+                })
+                .collect(),
+        }))
+    }
+}
+
+fn invalid_argument(error: BoxError) -> Status {
+    Status::invalid_argument(error.to_string())
+}
+
+fn failed(error: JoinError) -> Status {
+    Status::internal(format!("the request failed: {error}"))
+}
+
+fn poisoned() -> Status {
+    Status::internal("the index is unusable: a request panicked while holding its lock")
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::{CommitRequest, DeleteDocumentRequest, IndexDocumentRequest, Search, SearchDocumentsRequest},
+        crate::{SearchService, ServerIndex},
+        lucene_core::{
+            index::{IndexWriter, IndexWriterConfig},
+            io::ByteBuffersDirectory,
+        },
+        pretty_assertions::assert_eq,
+        std::{
+            sync::{Arc, RwLock},
+            thread,
+        },
+        tonic::{Code, Request},
+    };
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_search_service() {
+        let writer = IndexWriter::new(Box::new(ByteBuffersDirectory::new()), IndexWriterConfig::new());
+        let index = Arc::new(RwLock::new(ServerIndex::new(writer.await.unwrap()).unwrap()));
+        let service = SearchService::new(index.clone());
+
+        {
+            for (id, title) in [("1", "The quick fox"), ("2", "A lazy dog"), ("3", "A lazy fox")] {
+                let request = IndexDocumentRequest {
+                    id: id.to_string(),
+                    source: format!(r#"{{"title": "{title}"}}"#),
+                };
+                assert_eq!(service.index_document(Request::new(request)).await.unwrap().into_inner().id, id);
+            }
+            let request = DeleteDocumentRequest {
+                id: "2".to_string(),
+            };
+            assert!(service.delete_document(Request::new(request)).await.unwrap().into_inner().found);
+            let response = service.commit(Request::new(CommitRequest {})).await.unwrap().into_inner();
+            assert_eq!(response.num_docs, 2);
+
+            let request = SearchDocumentsRequest {
+                query: r#"{"match": {"field": "title", "text": "fox"}}"#.to_string(),
+                size: Some(1),
+            };
+            let response = service.search(Request::new(request)).await.unwrap().into_inner();
+            assert_eq!((response.total_hits, response.hits.len()), (2, 1));
+            assert_eq!(response.hits[0].fields, r#"{"title":"The quick fox"}"#);
+
+            let request = IndexDocumentRequest {
+                id: "4".to_string(),
+                source: "[]".to_string(),
+            };
+            assert_eq!(service.index_document(Request::new(request)).await.unwrap_err().code(), Code::InvalidArgument);
+        }
+
+        // A panic while the index is locked makes the service fail rather than panic.
+        thread::spawn(move || {
+            let _guard = index.write().unwrap();
+            panic!("poisoning the index lock");
+        })
+        .join()
+        .unwrap_err();
+        let err = service.commit(Request::new(CommitRequest {})).await.unwrap_err();
+        assert_eq!(err.code(), Code::Internal);
+    }
+}
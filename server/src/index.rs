@@ -0,0 +1,353 @@
+use {
+    crate::QueryDsl,
+    lucene_core::{
+        analysis::Analyzer,
+        document::{Document, Field, Store},
+        index::{IndexWriter, Term, Translog},
+        search::{IndexSearcher, TermQuery, TotalHitsRelation},
+        BoxResult, LuceneError,
+    },
+    serde::{Deserialize, Serialize},
+    serde_json::{Map, Value},
+    std::{
+        collections::BTreeMap,
+        sync::{Arc, Mutex, PoisonError, RwLock},
+    },
+};
+
+/// The stored field holding each document's id.
+pub const ID_FIELD: &str = "_id";
+
+/// The number of segments a commit merges the index down to.
+pub const MAX_SEGMENTS: usize = 8;
+
+/// The number of hits returned when a search request doesn't say.
+pub const DEFAULT_SEARCH_SIZE: usize = 10;
+
+/// An index shared between request handlers.
+pub type SharedIndex = Arc<RwLock<ServerIndex>>;
+
+/// A search request: a query and the number of hits to return.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct SearchRequest {
+    /// The query to run.
+    pub query: QueryDsl,
+
+    /// The number of hits to return.
+    #[serde(default = "default_search_size")]
+    pub size: usize,
+}
+
+fn default_search_size() -> usize {
+    DEFAULT_SEARCH_SIZE
+}
+
+/// The results of a [SearchRequest].
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct SearchResponse {
+    /// The total number of matching documents.
+    pub total_hits: u64,
+
+    /// Whether [SearchResponse::total_hits] is a lower bound rather than an exact count.
+    pub total_hits_is_lower_bound: bool,
+
+    /// The top hits, best first.
+    pub hits: Vec<SearchHit>,
+}
+
+/// A document matching a [SearchRequest].
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct SearchHit {
+    /// The id the document was indexed with.
+    pub id: String,
+
+    /// The document's score.
+    pub score: f32,
+
+    /// The document's stored fields. Fields with several values are returned as arrays.
+    pub fields: Map<String, Value>,
+}
+
+/// An index served by the server.
+///
+/// Documents are JSON objects, converted with [Document::from_json]: their values are strings (indexed as analyzed
+/// text), integers (kept as numeric doc values), or arrays of these, and every value is also stored and returned with
+/// search hits. Each document is identified
+/// by an id, and indexing a document with an existing id replaces it.
+///
+/// Documents are added to an [IndexWriter], and changes only become visible to searches once committed: a commit
+/// deletes the previous versions of the changed documents, adds their new versions, flushes them to a segment, and
+/// reopens the searcher over the writer's segments. These segments are held in memory (see [IndexWriter]), and are
+/// merged down to [MAX_SEGMENTS] as commits add more.
+///
+/// The writer can't write its segments to its directory yet, so [IndexWriter::commit] can't persist them. An index
+/// opened with [ServerIndex::open] is durable through a [Translog] instead: a commit logs its deletes and additions
+/// and syncs them before they're applied, and reopening the index replays them. An index created with
+/// [ServerIndex::new] has no translog, and loses its documents when dropped.
+#[derive(Debug)]
+pub struct ServerIndex {
+    writer: IndexWriter,
+    translog: Option<Mutex<Translog>>,
+    analyzer: Arc<dyn Analyzer>,
+    pending: BTreeMap<String, Option<Document>>,
+    searcher: IndexSearcher,
+}
+
+impl ServerIndex {
+    /// Creates an index that adds documents to `writer`, and analyzes `match` queries with its analyzer. Documents
+    /// the writer has already flushed are searchable. Nothing is logged, so committed documents are only kept in
+    /// memory.
+    pub fn new(writer: IndexWriter) -> BoxResult<Self> {
+        let searcher = IndexSearcher::new(Arc::new(writer.reader()?));
+        Ok(Self {
+            analyzer: writer.config().analyzer().clone(),
+            writer,
+            translog: None,
+            pending: BTreeMap::new(),
+            searcher,
+        })
+    }
+
+    /// Opens an index that adds documents to `writer` and logs its commits in `translog`, after replaying the
+    /// translog into the writer (see [Translog::recover]), so that the documents committed before a restart are
+    /// searchable again.
+    ///
+    /// Commits sync the translog themselves, so it can use an interval durability without losing committed changes.
+    pub async fn open(writer: IndexWriter, mut translog: Translog) -> BoxResult<Self> {
+        translog.recover(&writer).await?;
+        writer.flush()?;
+        writer.force_merge(MAX_SEGMENTS)?;
+
+        let mut index = Self::new(writer)?;
+        index.translog = Some(Mutex::new(translog));
+        Ok(index)
+    }
+
+    /// Returns the writer documents are added to.
+    #[inline]
+    pub fn writer(&self) -> &IndexWriter {
+        &self.writer
+    }
+
+    /// Returns the analyzer used for indexed text and `match` queries.
+    #[inline]
+    pub fn analyzer(&self) -> &Arc<dyn Analyzer> {
+        &self.analyzer
+    }
+
+    /// Returns the number of committed documents.
+    #[inline]
+    pub fn num_docs(&self) -> usize {
+        self.searcher.reader().num_docs() as usize
+    }
+
+    /// Returns the number of uncommitted changes.
+    #[inline]
+    pub fn num_pending(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Adds or replaces the document with the given id. The change is visible once committed.
+    pub fn index(&mut self, id: &str, source: &Map<String, Value>) -> BoxResult<()> {
+        if source.contains_key(ID_FIELD) {
+            return Err(LuceneError::InvalidArgument(format!("field name {ID_FIELD} is reserved")).into());
+        }
+
+        let mut document = Document::from_json(source)?;
+        document.add(Field::string(ID_FIELD, id, Store::Yes));
+        self.pending.insert(id.to_string(), Some(document));
+        Ok(())
+    }
+
+    /// Deletes the document with the given id, returning whether it exists, counting uncommitted changes. The change
+    /// is visible once committed.
+    pub fn delete(&mut self, id: &str) -> BoxResult<bool> {
+        let exists = match self.pending.get(id) {
+            Some(document) => document.is_some(),
+            None => self.searcher.count(&TermQuery::new(id_term(id)))? > 0,
+        };
+        self.pending.insert(id.to_string(), None);
+        Ok(exists)
+    }
+
+    /// Makes the uncommitted changes visible to searches and, if the index has a translog, durable: they're logged
+    /// and the translog is synced before this returns.
+    ///
+    /// If this fails, the changes stay uncommitted, and the next commit applies them again from the start: it deletes
+    /// the documents the failed commit added before adding them anew. Replaying the translog does the same.
+    pub async fn commit(&mut self) -> BoxResult<()> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+
+        let ids: Vec<Term> = self.pending.keys().map(|id| id_term(id)).collect();
+        match &mut self.translog {
+            Some(translog) => {
+                let translog = translog.get_mut().unwrap_or_else(PoisonError::into_inner);
+                translog.delete_documents(&self.writer, &ids).await?;
+                for document in self.pending.values().flatten() {
+                    translog.add_document(&self.writer, document).await?;
+                }
+                translog.sync().await?;
+            }
+            None => {
+                self.writer.delete_documents(&ids)?;
+                for document in self.pending.values().flatten() {
+                    self.writer.add_document(document)?;
+                }
+            }
+        }
+
+        self.writer.flush()?;
+        self.writer.force_merge(MAX_SEGMENTS)?;
+        self.searcher = IndexSearcher::new(Arc::new(self.writer.reader()?));
+        self.pending.clear();
+        Ok(())
+    }
+
+    /// Runs a search against the committed documents.
+    pub fn search(&self, request: &SearchRequest) -> BoxResult<SearchResponse> {
+        let query = request.query.to_query(self.analyzer.as_ref())?;
+        let top_docs = self.searcher.search(query.as_ref(), request.size)?;
+
+        let mut hits = Vec::with_capacity(top_docs.score_docs.len());
+        for score_doc in &top_docs.score_docs {
+            let mut fields = self.searcher.doc(score_doc.doc)?.to_json();
+            let id = match fields.remove(ID_FIELD) {
+                Some(Value::String(id)) => id,
+                _ => String::new(),
+            };
+            hits.push(SearchHit {
+                id,
+                score: score_doc.score,
+                fields,
+            });
+        }
+
+        Ok(SearchResponse {
+            total_hits: top_docs.total_hits.value,
+            total_hits_is_lower_bound: top_docs.total_hits.relation == TotalHitsRelation::GreaterThanOrEqualTo,
+            hits,
+        })
+    }
+}
+
+/// Returns the term identifying the document with the given id.
+fn id_term(id: &str) -> Term {
+    Term::new(ID_FIELD, id)
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{QueryDsl, SearchRequest, ServerIndex},
+        lucene_core::{
+            index::{IndexWriter, IndexWriterConfig, Translog, TranslogDurability},
+            io::{BlockingExecutor, ByteBuffersDirectory},
+        },
+        pretty_assertions::assert_eq,
+        serde_json::{json, Map, Value},
+        std::time::Duration,
+    };
+
+    fn open_index(executor: &BlockingExecutor) -> ServerIndex {
+        let writer = IndexWriter::new(Box::new(ByteBuffersDirectory::new()), IndexWriterConfig::new());
+        ServerIndex::new(executor.block_on(writer).unwrap()).unwrap()
+    }
+
+    /// Opens an index over `directory`, logging its commits in a translog kept there too.
+    fn open_durable_index(executor: &BlockingExecutor, directory: &ByteBuffersDirectory) -> ServerIndex {
+        executor.block_on(async {
+            let writer = IndexWriter::new(Box::new(directory.clone()), IndexWriterConfig::new()).await.unwrap();
+            let durability = TranslogDurability::Interval(Duration::MAX);
+            let translog = Translog::open(Box::new(directory.clone()), durability).await.unwrap();
+            ServerIndex::open(writer, translog).await.unwrap()
+        })
+    }
+
+    fn source(value: Value) -> Map<String, Value> {
+        value.as_object().unwrap().clone()
+    }
+
+    fn search(index: &ServerIndex, query: Value) -> Vec<String> {
+        let request: SearchRequest = serde_json::from_value(json!({ "query": query })).unwrap();
+        index.search(&request).unwrap().hits.into_iter().map(|hit| hit.id).collect()
+    }
+
+    #[test]
+    fn test_server_index() {
+        let executor = BlockingExecutor::new().unwrap();
+        let mut index = open_index(&executor);
+        index.index("1", &source(json!({"title": "The quick fox", "tags": ["animal", "fast"], "year": 2020}))).unwrap();
+        index.index("2", &source(json!({"title": "A lazy dog", "year": 2021}))).unwrap();
+
+        // Nothing is visible until committed.
+        let fox = json!({"match": {"field": "title", "text": "fox"}});
+        assert_eq!(search(&index, fox.clone()), Vec::<String>::new());
+        assert_eq!(index.num_pending(), 2);
+        executor.block_on(index.commit()).unwrap();
+        assert_eq!(index.num_docs(), 2);
+        assert_eq!(search(&index, fox.clone()), vec!["1"]);
+
+        let request = SearchRequest {
+            query: QueryDsl::MatchAll {},
+            size: 1,
+        };
+        let response = index.search(&request).unwrap();
+        assert_eq!(response.total_hits, 2);
+        assert_eq!(response.hits.len(), 1);
+        assert_eq!(
+            Value::Object(response.hits[0].fields.clone()),
+            json!({"title": "The quick fox", "tags": ["animal", "fast"], "year": 2020})
+        );
+
+        // Replacing and deleting documents.
+        index.index("2", &source(json!({"title": "A lazy fox"}))).unwrap();
+        assert!(index.delete("1").unwrap());
+        assert!(!index.delete("3").unwrap());
+        executor.block_on(index.commit()).unwrap();
+        assert_eq!(search(&index, fox.clone()), vec!["2"]);
+        assert_eq!(index.num_docs(), 1);
+
+        // Replaced documents are deleted from the writer's segments, which are dropped once empty.
+        index.index("4", &source(json!({"title": "A quick fox"}))).unwrap();
+        executor.block_on(index.commit()).unwrap();
+        index.index("2", &source(json!({"title": "A lazy dog"}))).unwrap();
+        executor.block_on(index.commit()).unwrap();
+        let segments = index.writer().segments();
+        let sizes: Vec<_> = segments.iter().map(|segment| (segment.max_doc(), segment.num_docs())).collect();
+        assert_eq!(sizes, [(1, 1), (1, 1)]);
+        assert_eq!(search(&index, fox), vec!["4"]);
+        assert!(index.delete("4").unwrap());
+        executor.block_on(index.commit()).unwrap();
+        assert_eq!(index.num_docs(), 1);
+
+        assert!(index.index("3", &source(json!({"_id": "4"}))).is_err());
+        assert!(index.index("3", &source(json!({"price": 1.5}))).is_err());
+        assert!(index.index("3", &source(json!({"nested": {"a": 1}}))).is_err());
+    }
+
+    #[test]
+    fn test_durable_commits() {
+        let executor = BlockingExecutor::new().unwrap();
+        let directory = ByteBuffersDirectory::new();
+        let mut index = open_durable_index(&executor, &directory);
+        index.index("1", &source(json!({"title": "The quick fox"}))).unwrap();
+        index.index("2", &source(json!({"title": "A lazy dog"}))).unwrap();
+        executor.block_on(index.commit()).unwrap();
+        index.index("2", &source(json!({"title": "A lazy fox"}))).unwrap();
+        assert!(index.delete("1").unwrap());
+        index.index("3", &source(json!({"title": "A quick dog"}))).unwrap();
+        executor.block_on(index.commit()).unwrap();
+
+        // Uncommitted changes aren't logged, so they're lost when the index is dropped.
+        index.index("4", &source(json!({"title": "Another fox"}))).unwrap();
+        drop(index);
+
+        // The committed changes are replayed, in order, when the index is reopened.
+        let index = open_durable_index(&executor, &directory);
+        assert_eq!(index.num_docs(), 2);
+        assert_eq!(search(&index, json!({"match": {"field": "title", "text": "fox"}})), vec!["2"]);
+        assert_eq!(search(&index, json!({"match": {"field": "title", "text": "quick"}})), vec!["3"]);
+    }
+}
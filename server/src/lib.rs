@@ -0,0 +1,25 @@
+//! A search server exposing a Lucene index over HTTP+JSON and gRPC.
+//!
+//! Documents are indexed, deleted and committed through a small REST API, and searched with the JSON query language
+//! of lucene-core ([QueryDsl]). See `router` for the endpoints, which are only built with the `http` feature, and
+//! `SearchService` for the gRPC service, which is only built with the `grpc` feature (both enabled by default).
+//! Without them, the crate provides [ServerIndex] for embedding elsewhere.
+
+#![warn(clippy::all)]
+#![warn(rustdoc::missing_crate_level_docs)]
+#![warn(rustdoc::broken_intra_doc_links)]
+#![warn(missing_docs)]
+
+#[cfg(feature = "grpc")]
+mod grpc;
+mod index;
+#[cfg(feature = "http")]
+mod routes;
+
+pub use {index::*, lucene_core::search::QueryDsl};
+
+#[cfg(feature = "grpc")]
+pub use grpc::*;
+
+#[cfg(feature = "http")]
+pub use routes::*;
@@ -0,0 +1,75 @@
+use {
+    log::{info, warn},
+    lucene_core::{
+        fs::FilesystemDirectory,
+        index::{IndexWriter, IndexWriterConfig, Translog, TranslogDurability},
+        io::ByteBuffersDirectory,
+        BoxResult, LuceneError,
+    },
+    lucene_server::{router, ServerIndex},
+    std::{
+        env,
+        sync::{Arc, RwLock},
+        time::Duration,
+    },
+    tokio::net::TcpListener,
+};
+
+/// The address listened on when none is given on the command line.
+const DEFAULT_ADDRESS: &str = "127.0.0.1:8080";
+
+/// The usage printed for invalid command lines.
+const USAGE: &str = "usage: lucene-server [--data-dir DIR] [ADDRESS]";
+
+#[tokio::main]
+async fn main() -> BoxResult<()> {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+
+    let mut address = DEFAULT_ADDRESS.to_string();
+    let mut data_dir = None;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--data-dir" => {
+                data_dir = Some(args.next().ok_or_else(|| LuceneError::InvalidArgument(USAGE.to_string()))?)
+            }
+            _ if arg.starts_with('-') => return Err(LuceneError::InvalidArgument(USAGE.to_string()).into()),
+            _ => address = arg,
+        }
+    }
+
+    let index = match data_dir {
+        Some(data_dir) => {
+            // Commits sync the translog themselves, so it never needs to sync on a timer.
+            let writer = IndexWriter::new(
+                Box::new(FilesystemDirectory::open_or_create(&data_dir).await?),
+                IndexWriterConfig::new(),
+            )
+            .await?;
+            let translog = Translog::open(
+                Box::new(FilesystemDirectory::open(&data_dir).await?),
+                TranslogDurability::Interval(Duration::MAX),
+            )
+            .await?;
+            let index = ServerIndex::open(writer, translog).await?;
+            info!("Opened the index in {data_dir} with {} documents", index.num_docs());
+            index
+        }
+        None => {
+            warn!("No --data-dir given: documents are kept in memory, and lost when the server stops");
+            ServerIndex::new(IndexWriter::new(Box::new(ByteBuffersDirectory::new()), IndexWriterConfig::new()).await?)?
+        }
+    };
+    let index = Arc::new(RwLock::new(index));
+
+    // gRPC requests are told apart from HTTP+JSON ones by their paths, so both are served on the same port.
+    let app = router(index.clone());
+    #[cfg(feature = "grpc")]
+    let app = app
+        .merge(tonic::service::Routes::new(lucene_server::SearchService::new(index).into_server()).into_axum_router());
+
+    let listener = TcpListener::bind(&address).await?;
+    info!("lucene-server listening on {}", listener.local_addr()?);
+    axum::serve(listener, app).await?;
+    Ok(())
+}
@@ -0,0 +1,212 @@
+use {
+    crate::{SearchRequest, SearchResponse, SharedIndex},
+    axum::{
+        extract::{Path, State},
+        http::StatusCode,
+        response::{IntoResponse, Response},
+        routing::{post, put},
+        Json, Router,
+    },
+    lucene_core::BoxError,
+    serde_json::{json, Map, Value},
+    std::sync::PoisonError,
+    tokio::{runtime::Handle, task},
+};
+
+/// Creates the HTTP routes of the server:
+///
+/// * `PUT /docs/{id}` indexes the JSON object in the body under the given id, replacing any existing document.
+/// * `DELETE /docs/{id}` deletes a document.
+/// * `POST /commit` makes indexed and deleted documents visible to searches.
+/// * `POST /search` runs the [SearchRequest] in the body and returns a [SearchResponse].
+///
+/// Errors are returned with a JSON body of the form `{"error": "..."}`, and status 400, or 500 if the index is
+/// unusable because a request panicked while holding its lock.
+///
+/// The index is locked, and commits and searches run, on tokio's blocking threads, so that a long commit or merge
+/// doesn't stall the runtime's workers. The routes must be served by a multi-threaded runtime.
+pub fn router(index: SharedIndex) -> Router {
+    Router::new()
+        .route("/docs/:id", put(index_document).delete(delete_document))
+        .route("/commit", post(commit))
+        .route("/search", post(search))
+        .with_state(index)
+}
+
+/// An error returned by a handler, with its HTTP status.
+#[derive(Debug)]
+struct ServerError {
+    status: StatusCode,
+    message: String,
+}
+
+impl From<BoxError> for ServerError {
+    fn from(error: BoxError) -> Self {
+        Self {
+            status: StatusCode::BAD_REQUEST,
+            message: error.to_string(),
+        }
+    }
+}
+
+impl<T> From<PoisonError<T>> for ServerError {
+    fn from(_: PoisonError<T>) -> Self {
+        Self {
+            status: StatusCode::INTERNAL_SERVER_ERROR,
+            message: "the index is unusable: a request panicked while holding its lock".to_string(),
+        }
+    }
+}
+
+impl From<task::JoinError> for ServerError {
+    fn from(error: task::JoinError) -> Self {
+        Self {
+            status: StatusCode::INTERNAL_SERVER_ERROR,
+            message: format!("the request failed: {error}"),
+        }
+    }
+}
+
+impl IntoResponse for ServerError {
+    fn into_response(self) -> Response {
+        (self.status, Json(json!({"error": self.message}))).into_response()
+    }
+}
+
+/// Runs `f` with the index on a blocking thread.
+async fn with_index<T, F>(index: SharedIndex, f: F) -> Result<T, ServerError>
+where
+    T: Send + 'static,
+    F: FnOnce(&SharedIndex) -> Result<T, ServerError> + Send + 'static,
+{
+    task::spawn_blocking(move || f(&index)).await?
+}
+
+async fn index_document(
+    State(index): State<SharedIndex>,
+    Path(id): Path<String>,
+    Json(source): Json<Map<String, Value>>,
+) -> Result<Json<Value>, ServerError> {
+    with_index(index, {
+        let id = id.clone();
+        move |index| Ok(index.write()?.index(&id, &source)?)
+    })
+    .await?;
+    Ok(Json(json!({"id": id})))
+}
+
+async fn delete_document(State(index): State<SharedIndex>, Path(id): Path<String>) -> Result<Json<Value>, ServerError> {
+    let found = with_index(index, {
+        let id = id.clone();
+        move |index| Ok(index.write()?.delete(&id)?)
+    })
+    .await?;
+    Ok(Json(json!({"id": id, "found": found})))
+}
+
+async fn commit(State(index): State<SharedIndex>) -> Result<Json<Value>, ServerError> {
+    let num_docs = with_index(index, |index| {
+        let mut index = index.write()?;
+        Handle::current().block_on(index.commit())?;
+        Ok(index.num_docs())
+    })
+    .await?;
+    Ok(Json(json!({"num_docs": num_docs})))
+}
+
+async fn search(
+    State(index): State<SharedIndex>,
+    Json(request): Json<SearchRequest>,
+) -> Result<Json<SearchResponse>, ServerError> {
+    Ok(Json(with_index(index, move |index| Ok(index.read()?.search(&request)?)).await?))
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{router, ServerIndex},
+        axum::{
+            body::Body,
+            http::{Method, Request, StatusCode},
+            Router,
+        },
+        http_body_util::BodyExt,
+        lucene_core::{
+            index::{IndexWriter, IndexWriterConfig},
+            io::ByteBuffersDirectory,
+        },
+        pretty_assertions::assert_eq,
+        serde_json::{json, Value},
+        std::{
+            sync::{Arc, RwLock},
+            thread,
+        },
+        tower::ServiceExt,
+    };
+
+    /// Sends a request to the routes and returns the status and JSON body of the response.
+    async fn send(app: &Router, method: Method, uri: &str, body: Option<Value>) -> (StatusCode, Value) {
+        let request = Request::builder().method(method).uri(uri);
+        let request = match body {
+            Some(body) => request.header("content-type", "application/json").body(Body::from(body.to_string())),
+            None => request.body(Body::empty()),
+        };
+        let response = app.clone().oneshot(request.unwrap()).await.unwrap();
+        let status = response.status();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_routes() {
+        let writer = IndexWriter::new(Box::new(ByteBuffersDirectory::new()), IndexWriterConfig::new()).await.unwrap();
+        let index = Arc::new(RwLock::new(ServerIndex::new(writer).unwrap()));
+        let app = router(index.clone());
+
+        let (status, body) = send(&app, Method::PUT, "/docs/1", Some(json!({"title": "The quick fox"}))).await;
+        assert_eq!((status, body), (StatusCode::OK, json!({"id": "1"})));
+        send(&app, Method::PUT, "/docs/2", Some(json!({"title": "A lazy fox", "year": 2021}))).await;
+        send(&app, Method::PUT, "/docs/3", Some(json!({"title": "A lazy dog"}))).await;
+        let (status, body) = send(&app, Method::DELETE, "/docs/3", None).await;
+        assert_eq!((status, body), (StatusCode::OK, json!({"id": "3", "found": true})));
+        let (_, body) = send(&app, Method::DELETE, "/docs/4", None).await;
+        assert_eq!(body, json!({"id": "4", "found": false}));
+
+        // Nothing is visible until committed.
+        let search = json!({"query": {"match": {"field": "title", "text": "fox"}}, "size": 1});
+        let (status, body) = send(&app, Method::POST, "/search", Some(search.clone())).await;
+        assert_eq!(
+            (status, body),
+            (StatusCode::OK, json!({"total_hits": 0, "total_hits_is_lower_bound": false, "hits": []}))
+        );
+        let (status, body) = send(&app, Method::POST, "/commit", None).await;
+        assert_eq!((status, body), (StatusCode::OK, json!({"num_docs": 2})));
+
+        let (status, body) = send(&app, Method::POST, "/search", Some(search)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!((&body["total_hits"], body["hits"].as_array().unwrap().len()), (&json!(2), 1));
+        assert_eq!(body["hits"][0]["id"], json!("1"));
+        assert_eq!(body["hits"][0]["fields"], json!({"title": "The quick fox"}));
+
+        // Invalid documents are rejected with their error.
+        let (status, body) = send(&app, Method::PUT, "/docs/5", Some(json!({"_id": "6"}))).await;
+        assert_eq!(
+            (status, body),
+            (StatusCode::BAD_REQUEST, json!({"error": "Invalid argument: field name _id is reserved"}))
+        );
+        let (status, body) = send(&app, Method::PUT, "/docs/5", Some(json!({"price": 1.5}))).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(body["error"].is_string());
+
+        // A panic while the index is locked makes the routes fail rather than panic.
+        thread::spawn(move || {
+            let _guard = index.write().unwrap();
+            panic!("poisoning the index lock");
+        })
+        .join()
+        .unwrap_err();
+        let (status, body) = send(&app, Method::POST, "/commit", None).await;
+        assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(body, json!({"error": "the index is unusable: a request panicked while holding its lock"}));
+    }
+}